@@ -2,7 +2,7 @@
 /// downloading, processing, and organizing papers from the arXiv repository.
 use std::process;
 use colored::Colorize;
-use log::{info, error};
+use log::{info, warn, error};
 use anyhow::Result;
 use std::path::{Path, PathBuf};
 use clap::Parser;
@@ -17,12 +17,16 @@ mod modules;
 mod error;
 mod utils;
 
-use modules::cli::{Cli, OutputFormat};
+use modules::cli::{Cli, Command, OutputFormat};
 use modules::config::Config;
 use modules::arxiv::ArxivClient;
 use modules::download::{PdfDownloader, DownloadInfo};
 use modules::parser::PdfParser;
+use modules::ar5iv::{Ar5ivClient, Ar5ivError};
 use modules::metadata::PaperMetadata;
+use modules::index::VectorIndex;
+use modules::bib::{BibLibrary, Upsert};
+use llama_moonlight_mlx::{EmbeddingModel, EmbeddingModelConfig, Mlx};
 use modules::Context;
 
 /// Application error types
@@ -43,6 +47,15 @@ enum AppError {
     #[error("Parser error: {0}")]
     Parser(#[from] modules::parser::ParserError),
     
+    #[error("Search index error: {0}")]
+    Index(#[from] modules::index::IndexError),
+    
+    #[error("HTML rendering error: {0}")]
+    Ar5iv(#[from] Ar5ivError),
+    
+    #[error("BibTeX library error: {0}")]
+    Bib(#[from] modules::bib::BibError),
+    
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
     
@@ -111,6 +124,26 @@ async fn process_id(id: &str, context: &Context) -> AppResult<()> {
     // Add metadata to parsed PDF
     parsed.metadata = Some(metadata.clone());
     
+    // Take the text from the HTML rendering if preferred or if the PDF text is poor
+    let quality = parsed.quality();
+    let html_config = &context.config.html;
+    if context.args.prefer_html || (html_config.fallback && quality < html_config.min_pdf_quality) {
+        if !context.args.prefer_html {
+            info!("PDF text quality of {} is {:.2}, trying the HTML rendering", metadata.id, quality);
+        }
+        
+        let ar5iv = Ar5ivClient::new(html_config.clone(), &context.config.user_agent)?;
+        match ar5iv.fetch(&metadata.id).await {
+            Ok(html) => {
+                info!("Using HTML rendering {}", html.url);
+                parsed.merge_html(html);
+            },
+            Err(e) => {
+                warn!("Keeping PDF text of {}: {}", metadata.id, e);
+            }
+        }
+    }
+    
     // Save output in the requested format
     let extension = match context.args.format {
         OutputFormat::Text => "txt",
@@ -137,13 +170,143 @@ async fn process_id(id: &str, context: &Context) -> AppResult<()> {
         context.args.format, 
         output_path.display().to_string().blue());
     
-    // Save citations if requested
+    // Add the citation to the BibTeX library if requested
     if context.args.citations {
-        let citation_path = output_path.with_extension("bib");
-        fs::write(&citation_path, metadata.to_bibtex())?;
-        println!("{} Saved BibTeX citation to {}", 
+        let mut library = BibLibrary::open(&context.config.library_path())?;
+        let key = match library.upsert(&metadata) {
+            Upsert::Added(key) | Upsert::Updated(key) | Upsert::Unchanged(key) => key,
+        };
+        library.save()?;
+        println!("{} Saved BibTeX citation {} to {}", 
+            "✓".green(), 
+            key, 
+            library.path().display().to_string().blue());
+    }
+    
+    // Add the paper to the search index if requested
+    if context.args.index {
+        let model = embedding_model(&context.config).await?;
+        let mut index = VectorIndex::open(&context.config.index_path(), &model)?;
+        let count = index.add_paper(&metadata, &parsed.text, &model, &context.config.search).await?;
+        index.save()?;
+        println!("{} Indexed {} passages of {}", 
             "✓".green(), 
-            citation_path.display().to_string().blue());
+            count, 
+            metadata.id.blue());
+    }
+    
+    Ok(())
+}
+
+/// Load the embedding model configured for semantic search
+async fn embedding_model(config: &Config) -> AppResult<EmbeddingModel> {
+    let name = match &config.search.model {
+        Some(name) => name,
+        None => return Ok(EmbeddingModel::hashing(config.search.dimensions)),
+    };
+    
+    let mut model_config = EmbeddingModelConfig {
+        dimensions: config.search.dimensions,
+        ..EmbeddingModelConfig::default()
+    };
+    model_config.base.name = name.clone();
+    
+    let mut mlx = Mlx::new(config.models_dir());
+    let model = mlx
+        .load_embedding_model(name, model_config)
+        .await
+        .map_err(modules::index::IndexError::from)?;
+    info!("Loaded embedding model {}", model.name);
+    
+    Ok((*model).clone())
+}
+
+/// Answer a question with the most relevant passages from indexed papers
+async fn ask(question: &str, top_k: Option<usize>, config: &Config) -> AppResult<()> {
+    let model = embedding_model(config).await?;
+    let index = VectorIndex::open(&config.index_path(), &model)?;
+    
+    if index.entries.is_empty() {
+        println!("No papers indexed yet. Process papers with --index first.");
+        return Ok(());
+    }
+    
+    let hits = index.search(question, &model, top_k.unwrap_or(config.search.top_k)).await?;
+    
+    for (rank, hit) in hits.iter().enumerate() {
+        println!("{} {}", 
+            format!("{}.", rank + 1).bold(), 
+            hit.entry.citation().blue());
+        println!("   {}", format!("score {:.3}, passage {}", hit.score, hit.entry.chunk + 1).dimmed());
+        println!("   {}\n", hit.entry.text);
+    }
+    
+    Ok(())
+}
+
+/// Add papers to the BibTeX library, refreshing unpublished entries if requested
+async fn bib(targets: &[String], refresh: bool, library: Option<&Path>, config: &Config) -> AppResult<()> {
+    let path = library.map(Path::to_path_buf).unwrap_or_else(|| config.library_path());
+    let mut library = BibLibrary::open(&path)?;
+    
+    let merged = library.dedup();
+    if merged > 0 {
+        println!("{} Merged {} duplicate entries", "✓".green(), merged);
+    }
+    
+    // Collect the papers to fetch, once each
+    let mut ids: Vec<String> = Vec::new();
+    for target in targets {
+        let id = if target.starts_with("http") {
+            parse_arxiv_url(target).ok_or_else(|| AppError::InvalidInput(target.clone()))?
+        } else {
+            target.clone()
+        };
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    if refresh {
+        for id in library.unpublished() {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    
+    let client = ArxivClient::new(config.api.clone())
+        .map_err(AppError::ArxivApi)?;
+    
+    let mut errors = false;
+    for id in &ids {
+        let metadata = match client.get_paper(id).await {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                error!("Failed to fetch {}: {}", id, e);
+                errors = true;
+                continue;
+            }
+        };
+        
+        match library.upsert(&metadata) {
+            Upsert::Added(key) => println!("{} Added {} as {}", "✓".green(), id, key.blue()),
+            Upsert::Updated(key) => println!("{} Updated {}", "✓".green(), key.blue()),
+            Upsert::Unchanged(key) => info!("{} is up to date", key),
+        }
+    }
+    
+    for (old, new) in library.normalize_keys() {
+        println!("{} Renamed {} to {}", "✓".green(), old, new.blue());
+    }
+    
+    library.save()?;
+    println!("{} Saved {} entries to {}", 
+        "✓".green(), 
+        library.entries.len(), 
+        library.path().display().to_string().blue());
+    
+    if errors {
+        return Err(AppError::Unknown("some papers could not be fetched".to_string()));
     }
     
     Ok(())
@@ -246,6 +409,24 @@ async fn main() {
         }
     };
     
+    // Answer questions from the search index
+    if let Some(Command::Ask { question, top_k }) = &cli.command {
+        if let Err(e) = ask(question, *top_k, &config).await {
+            error!("Failed to answer question: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    
+    // Maintain the BibTeX library
+    if let Some(Command::Bib { targets, refresh, library }) = &cli.command {
+        if let Err(e) = bib(targets, *refresh, library.as_deref(), &config).await {
+            error!("Failed to update BibTeX library: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    
     // Create application context
     let context = Context::new(cli, config);
     
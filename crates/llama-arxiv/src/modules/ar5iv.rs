@@ -0,0 +1,294 @@
+use std::time::Duration;
+use reqwest::{Client, StatusCode};
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+use scraper::{ElementRef, Html, Node, Selector};
+use thiserror::Error;
+use log::debug;
+use lazy_static::lazy_static;
+
+use crate::modules::config::HtmlConfig;
+use crate::modules::parser::PdfSection;
+
+/// Error types for ar5iv HTML operations
+#[derive(Error, Debug)]
+pub enum Ar5ivError {
+    #[error("Network error: {0}")]
+    Network(#[from] reqwest::Error),
+    
+    #[error("No HTML rendering available for {0}")]
+    NotAvailable(String),
+    
+    #[error("HTML parsing error: {0}")]
+    Parse(String),
+}
+
+/// Result type for ar5iv operations
+pub type Ar5ivResult<T> = Result<T, Ar5ivError>;
+
+lazy_static! {
+    static ref DOCUMENT: Selector = Selector::parse(".ltx_document").unwrap();
+    static ref DOCUMENT_TITLE: Selector = Selector::parse("h1.ltx_title_document").unwrap();
+    static ref ABSTRACT: Selector = Selector::parse("div.ltx_abstract p").unwrap();
+    static ref SECTIONS: Selector = Selector::parse(
+        "section.ltx_section, section.ltx_subsection, section.ltx_subsubsection, section.ltx_appendix"
+    ).unwrap();
+    static ref BIBITEMS: Selector = Selector::parse("li.ltx_bibitem").unwrap();
+    static ref BIBBLOCKS: Selector = Selector::parse(".ltx_bibblock").unwrap();
+    static ref PARAGRAPHS: Selector = Selector::parse("p").unwrap();
+    static ref CAPTION: Selector = Selector::parse("figcaption").unwrap();
+}
+
+/// Structure representing a paper parsed from its ar5iv HTML rendering
+#[derive(Debug, Clone)]
+pub struct ParsedHtml {
+    /// URL of the HTML rendering
+    pub url: String,
+    
+    /// Title of the paper
+    pub title: Option<String>,
+    
+    /// Sections of the paper, the abstract first if present
+    pub sections: Vec<PdfSection>,
+    
+    /// References from the bibliography
+    pub references: Vec<String>,
+}
+
+impl ParsedHtml {
+    /// Get the full text of the paper, section by section
+    pub fn text(&self) -> String {
+        self.sections
+            .iter()
+            .map(|section| format!("{}\n\n{}", section.heading, section.content))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// Client fetching the ar5iv HTML renderings of arXiv papers
+pub struct Ar5ivClient {
+    /// HTTP client for making requests
+    client: Client,
+    
+    /// HTML rendering configuration
+    config: HtmlConfig,
+}
+
+impl Ar5ivClient {
+    /// Create a new ar5iv client
+    pub fn new(config: HtmlConfig, user_agent: &str) -> Ar5ivResult<Self> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            USER_AGENT,
+            HeaderValue::from_str(user_agent).unwrap_or_else(|_| HeaderValue::from_static("llama-arxiv/0.1.0")),
+        );
+        
+        let client = Client::builder()
+            .default_headers(headers)
+            .timeout(Duration::from_secs(config.timeout))
+            .build()?;
+        
+        Ok(Self { client, config })
+    }
+    
+    /// Fetch and parse the HTML rendering of a paper
+    pub async fn fetch(&self, id: &str) -> Ar5ivResult<ParsedHtml> {
+        let url = format!("{}/{}", self.config.base_url.trim_end_matches('/'), id);
+        
+        debug!("Fetching HTML rendering: {}", url);
+        let response = self.client.get(&url).send().await?;
+        
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(Ar5ivError::NotAvailable(id.to_string()));
+        }
+        if !response.status().is_success() {
+            return Err(Ar5ivError::Parse(format!(
+                "HTML rendering returned status code: {}", response.status()
+            )));
+        }
+        
+        // Papers without a rendering redirect to their abstract page
+        let final_url = response.url().to_string();
+        let html = response.text().await?;
+        
+        parse_html(&html, &final_url).ok_or_else(|| Ar5ivError::NotAvailable(id.to_string()))
+    }
+}
+
+/// Parse an ar5iv HTML rendering into structured sections
+///
+/// Returns `None` if the page is not a LaTeXML rendering.
+pub fn parse_html(html: &str, url: &str) -> Option<ParsedHtml> {
+    let document = Html::parse_document(html);
+    document.select(&DOCUMENT).next()?;
+    
+    let title = document
+        .select(&DOCUMENT_TITLE)
+        .next()
+        .map(|title| element_text(title, true))
+        .filter(|title| !title.is_empty());
+    
+    let mut sections = Vec::new();
+    
+    // The abstract comes first
+    let abstract_text = join_paragraphs(document.select(&ABSTRACT).map(|p| element_text(p, false)));
+    if !abstract_text.is_empty() {
+        sections.push(PdfSection {
+            heading: "Abstract".to_string(),
+            content: abstract_text,
+            level: 1,
+        });
+    }
+    
+    // Sections in document order, each with only its own paragraphs
+    for section in document.select(&SECTIONS) {
+        let classes: Vec<&str> = section.value().classes().collect();
+        let level = if classes.contains(&"ltx_subsubsection") {
+            3
+        } else if classes.contains(&"ltx_subsection") {
+            2
+        } else {
+            1
+        };
+        
+        let mut heading = String::new();
+        let mut paragraphs = Vec::new();
+        
+        for child in section.children().filter_map(ElementRef::wrap) {
+            let name = child.value().name();
+            if name == "section" {
+                continue;
+            }
+            
+            if heading.is_empty() && child.value().classes().any(|class| class == "ltx_title") {
+                heading = element_text(child, true);
+            } else if name == "figure" {
+                // Keep captions, not the figure or table itself
+                paragraphs.extend(child.select(&CAPTION).map(|caption| element_text(caption, false)));
+            } else {
+                let texts: Vec<String> = child.select(&PARAGRAPHS).map(|p| element_text(p, false)).collect();
+                if texts.is_empty() {
+                    paragraphs.push(element_text(child, false));
+                } else {
+                    paragraphs.extend(texts);
+                }
+            }
+        }
+        
+        let content = join_paragraphs(paragraphs.into_iter());
+        if heading.is_empty() && content.is_empty() {
+            continue;
+        }
+        
+        sections.push(PdfSection {
+            heading,
+            content,
+            level,
+        });
+    }
+    
+    let references = document
+        .select(&BIBITEMS)
+        .map(|item| {
+            let blocks: Vec<String> = item.select(&BIBBLOCKS).map(|block| element_text(block, false)).collect();
+            blocks.join(" ")
+        })
+        .filter(|reference| !reference.is_empty())
+        .collect();
+    
+    Some(ParsedHtml {
+        url: url.to_string(),
+        title,
+        sections,
+        references,
+    })
+}
+
+/// Join the non-empty paragraphs of a section
+fn join_paragraphs(paragraphs: impl Iterator<Item = String>) -> String {
+    paragraphs
+        .filter(|paragraph| !paragraph.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Get the text of an element with math as LaTeX and whitespace collapsed
+///
+/// With `skip_tags`, numbering tags such as `1.2` in headings are dropped.
+fn element_text(element: ElementRef, skip_tags: bool) -> String {
+    let mut text = String::new();
+    collect_text(element, skip_tags, &mut text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Append the text below an element to a buffer
+fn collect_text(element: ElementRef, skip_tags: bool, text: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(node) => text.push_str(node),
+            Node::Element(child_element) => {
+                if child_element.name() == "math" {
+                    // MathML carries the LaTeX source in its alttext
+                    if let Some(latex) = child_element.attr("alttext") {
+                        text.push_str(&format!(" ${}$ ", latex));
+                    }
+                    continue;
+                }
+                if skip_tags && child_element.classes().any(|class| class == "ltx_tag") {
+                    continue;
+                }
+                if let Some(child) = ElementRef::wrap(child) {
+                    collect_text(child, skip_tags, text);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const SAMPLE: &str = r#"<html><body><div class="ltx_page_main"><article class="ltx_document">
+        <h1 class="ltx_title ltx_title_document">Attention Is All You Need</h1>
+        <div class="ltx_abstract"><h6 class="ltx_title ltx_title_abstract">Abstract</h6>
+            <p class="ltx_p">The dominant sequence transduction models are recurrent.</p></div>
+        <section id="S1" class="ltx_section">
+            <h2 class="ltx_title ltx_title_section"><span class="ltx_tag ltx_tag_section">1 </span>Introduction</h2>
+            <div class="ltx_para"><p class="ltx_p">Models scale as <math alttext="O(n^{2})"><mi>O</mi></math> in length.</p></div>
+            <figure class="ltx_figure"><img src="x.png"><figcaption>Figure 1: The Transformer.</figcaption></figure>
+            <section id="S1.SS1" class="ltx_subsection">
+                <h3 class="ltx_title ltx_title_subsection"><span class="ltx_tag">1.1 </span>Background</h3>
+                <div class="ltx_para"><p class="ltx_p">Self-attention relates positions.</p></div>
+            </section>
+        </section>
+        <section class="ltx_bibliography"><ul>
+            <li class="ltx_bibitem"><span class="ltx_tag">[1]</span><span class="ltx_bibblock">J. Ba et al.</span> <span class="ltx_bibblock">Layer normalization, 2016.</span></li>
+        </ul></section>
+    </article></div></body></html>"#;
+    
+    #[test]
+    fn test_parse_html() {
+        let parsed = parse_html(SAMPLE, "https://ar5iv.labs.arxiv.org/html/1706.03762").unwrap();
+        
+        assert_eq!(parsed.title.as_deref(), Some("Attention Is All You Need"));
+        assert_eq!(parsed.sections.len(), 3);
+        assert_eq!(parsed.sections[0].heading, "Abstract");
+        
+        let introduction = &parsed.sections[1];
+        assert_eq!(introduction.heading, "Introduction");
+        assert_eq!(introduction.level, 1);
+        assert_eq!(introduction.content, "Models scale as $O(n^{2})$ in length.\n\nFigure 1: The Transformer.");
+        
+        assert_eq!(parsed.sections[2].heading, "Background");
+        assert_eq!(parsed.sections[2].level, 2);
+        assert_eq!(parsed.references, vec!["J. Ba et al. Layer normalization, 2016."]);
+        assert!(parsed.text().starts_with("Abstract\n\nThe dominant"));
+    }
+    
+    #[test]
+    fn test_parse_non_rendering() {
+        assert!(parse_html("<html><body><h1>arXiv abstract page</h1></body></html>", "https://arxiv.org/abs/1").is_none());
+    }
+}
@@ -0,0 +1,701 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use log::{debug, info};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::modules::metadata::PaperMetadata;
+
+/// Error types for BibTeX library operations
+#[derive(Error, Debug)]
+pub enum BibError {
+    #[error("File system error: {0}")]
+    FileSystem(#[from] std::io::Error),
+    
+    #[error("BibTeX syntax error on line {line}: {message}")]
+    Syntax { line: usize, message: String },
+}
+
+/// Result type for BibTeX library operations
+pub type BibResult<T> = Result<T, BibError>;
+
+lazy_static! {
+    static ref ARXIV_REFERENCE: Regex = Regex::new(
+        r"(?i)arxiv(?:\.org/(?:abs|pdf)/|:\s*|\s+preprint\s+arxiv:\s*)([a-z\-]+(?:\.[a-z]{2})?/\d{7}|\d{4}\.\d{4,5})"
+    ).unwrap();
+    static ref LATEX_COMMAND: Regex = Regex::new(r"\\[a-zA-Z]+\s*|\\.").unwrap();
+}
+
+/// Title words that never make a citation key keyword
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "the", "on", "of", "for", "in", "to", "and", "with", "via", "by", "at", "as", "from",
+    "is", "are", "do", "does", "can", "what", "how", "why", "when", "towards", "toward", "using",
+];
+
+/// What adding a paper did to the library
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Upsert {
+    /// The paper was added under this key
+    Added(String),
+    
+    /// The entry with this key gained or changed fields
+    Updated(String),
+    
+    /// The entry with this key was already up to date
+    Unchanged(String),
+}
+
+/// An entry of a BibTeX file
+///
+/// Field values are kept as written, delimiters included, so entries the
+/// library does not touch are saved the way they were read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BibEntry {
+    /// Entry type, e.g. `article`
+    pub entry_type: String,
+    
+    /// Citation key
+    pub key: String,
+    
+    /// Fields in order, with their raw values
+    pub fields: Vec<(String, String)>,
+}
+
+impl BibEntry {
+    /// Create an entry from paper metadata
+    pub fn from_metadata(metadata: &PaperMetadata) -> Self {
+        let mut entry = Self {
+            entry_type: "article".to_string(),
+            key: metadata.citation_key(),
+            fields: Vec::new(),
+        };
+        
+        entry.set_field("author", &metadata.authors.join(" and "));
+        entry.set_field("title", &metadata.title);
+        if let Some(year) = metadata.year() {
+            entry.set_field("year", &year.to_string());
+        }
+        entry.set_field("archivePrefix", "arXiv");
+        entry.set_field("eprint", strip_version(&metadata.id));
+        
+        if let Some(ref doi) = metadata.doi {
+            entry.set_field("doi", doi);
+        }
+        
+        if let Some(ref journal) = metadata.journal_ref {
+            entry.set_field("journal", journal);
+        }
+        
+        if !metadata.primary_category.is_empty() {
+            entry.set_field("primaryClass", &metadata.primary_category);
+        }
+        
+        entry
+    }
+    
+    /// Get the value of a field, without its delimiters
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| unquote(value))
+    }
+    
+    /// Set the value of a field, returning whether it changed
+    pub fn set_field(&mut self, name: &str, value: &str) -> bool {
+        let raw = format!("{{{}}}", value);
+        match self.fields.iter_mut().find(|(field, _)| field.eq_ignore_ascii_case(name)) {
+            Some((_, current)) if unquote(current) == value => false,
+            Some((_, current)) => {
+                *current = raw;
+                true
+            },
+            None => {
+                self.fields.push((name.to_string(), raw));
+                true
+            }
+        }
+    }
+    
+    /// Get the arXiv ID of the paper, without version
+    pub fn arxiv_id(&self) -> Option<String> {
+        let prefix = self.field("archivePrefix").or_else(|| self.field("eprinttype"));
+        if let Some(eprint) = self.field("eprint") {
+            if prefix.map_or(true, |prefix| prefix.eq_ignore_ascii_case("arxiv")) {
+                let eprint = eprint.trim();
+                let eprint = eprint.strip_prefix("arXiv:").unwrap_or(eprint);
+                return Some(strip_version(eprint).to_lowercase());
+            }
+        }
+        
+        ["url", "journal", "note", "howpublished"]
+            .iter()
+            .filter_map(|name| self.field(name))
+            .find_map(|value| ARXIV_REFERENCE.captures(value))
+            .map(|captures| captures[1].to_lowercase())
+    }
+    
+    /// Get the DOI of the paper, lowercased and without resolver prefix
+    pub fn doi(&self) -> Option<String> {
+        let doi = self.field("doi")?.trim().to_lowercase();
+        let doi = ["https://doi.org/", "http://doi.org/", "https://dx.doi.org/", "http://dx.doi.org/", "doi:"]
+            .iter()
+            .find_map(|prefix| doi.strip_prefix(prefix))
+            .unwrap_or(&doi)
+            .trim()
+            .to_string();
+        
+        (!doi.is_empty()).then_some(doi)
+    }
+    
+    /// Whether two entries are about the same paper, by arXiv ID or DOI
+    pub fn same_paper(&self, other: &BibEntry) -> bool {
+        let same = |a: Option<String>, b: Option<String>| matches!((a, b), (Some(a), Some(b)) if a == b);
+        same(self.arxiv_id(), other.arxiv_id()) || same(self.doi(), other.doi())
+    }
+    
+    /// Generate the normalized citation key, `authorYEARkeyword`
+    ///
+    /// The author is the last name of the first author and the keyword the
+    /// first significant word of the title, both folded to lowercase ASCII.
+    /// Returns `None` for entries without author.
+    pub fn normalized_key(&self) -> Option<String> {
+        let first_author = self.field("author")?.split(" and ").next()?.trim();
+        let last_name = match first_author.split_once(',') {
+            Some((last, _)) => last,
+            None => first_author.split_whitespace().last()?,
+        };
+        
+        let author = fold(last_name);
+        if author.is_empty() {
+            return None;
+        }
+        
+        let year = self.field("year")
+            .map(|year| year.chars().filter(char::is_ascii_digit).take(4).collect::<String>())
+            .unwrap_or_default();
+        
+        let keyword = self.field("title")
+            .map(|title| {
+                title
+                    .split(|c: char| c.is_whitespace() || c == '-' || c == ':')
+                    .map(fold)
+                    .find(|word| !word.is_empty() && !STOP_WORDS.contains(&word.as_str()))
+                    .unwrap_or_default()
+            })
+            .unwrap_or_default();
+        
+        Some(format!("{}{}{}", author, year, keyword))
+    }
+    
+    /// Copy the fields of another entry that this one lacks
+    fn merge(&mut self, other: &BibEntry) {
+        for (name, value) in &other.fields {
+            if self.field(name).is_none() {
+                self.fields.push((name.clone(), value.clone()));
+            }
+        }
+    }
+    
+    /// Format the entry as BibTeX
+    pub fn to_bibtex(&self) -> String {
+        let mut entry = format!("@{}{{{},\n", self.entry_type, self.key);
+        for (name, value) in &self.fields {
+            entry.push_str(&format!("\t{} = {},\n", name, value));
+        }
+        entry.push_str("}\n");
+        entry
+    }
+}
+
+/// A BibTeX file maintained across runs
+///
+/// Papers are added with [`BibLibrary::upsert`], which updates the entry of a
+/// paper already in the library instead of adding a second one, so fetching
+/// a paper again after it was published adds its journal reference and DOI.
+#[derive(Debug, Clone)]
+pub struct BibLibrary {
+    /// `@comment`, `@preamble` and `@string` blocks, written before the entries
+    pub preamble: Vec<String>,
+    
+    /// Entries in file order
+    pub entries: Vec<BibEntry>,
+    
+    /// Path the library is stored at
+    path: PathBuf,
+}
+
+impl BibLibrary {
+    /// Create an empty library stored at the given path
+    pub fn new(path: &Path) -> Self {
+        Self {
+            preamble: Vec::new(),
+            entries: Vec::new(),
+            path: path.to_path_buf(),
+        }
+    }
+    
+    /// Load the library at the given path, or create an empty one if none exists
+    pub fn open(path: &Path) -> BibResult<Self> {
+        if !path.exists() {
+            debug!("No BibTeX library at {}, creating a new one", path.display());
+            return Ok(Self::new(path));
+        }
+        
+        let content = fs::read_to_string(path)?;
+        let (preamble, entries) = parse(&content)?;
+        
+        Ok(Self {
+            preamble,
+            entries,
+            path: path.to_path_buf(),
+        })
+    }
+    
+    /// Get the path the library is stored at
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    
+    /// Write the library to disk
+    pub fn save(&self) -> BibResult<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        
+        let blocks: Vec<String> = self.preamble
+            .iter()
+            .cloned()
+            .chain(self.entries.iter().map(BibEntry::to_bibtex))
+            .map(|block| block.trim_end().to_string())
+            .collect();
+        
+        fs::write(&self.path, format!("{}\n", blocks.join("\n\n")))?;
+        info!("Saved BibTeX library with {} entries to {}", self.entries.len(), self.path.display());
+        
+        Ok(())
+    }
+    
+    /// Get the arXiv IDs of the entries that have no journal reference yet
+    pub fn unpublished(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.field("journal").is_none())
+            .filter_map(BibEntry::arxiv_id)
+            .collect()
+    }
+    
+    /// Add a paper, or update its entry if the library already has one
+    ///
+    /// An existing entry gains the fields it lacks, and takes the journal
+    /// reference and DOI of the paper; other fields keep any manual edits.
+    pub fn upsert(&mut self, metadata: &PaperMetadata) -> Upsert {
+        let fresh = BibEntry::from_metadata(metadata);
+        
+        let Some(index) = self.entries.iter().position(|entry| entry.same_paper(&fresh)) else {
+            let mut entry = fresh;
+            if let Some(key) = entry.normalized_key() {
+                let taken = self.entries.iter().map(|entry| entry.key.clone()).collect();
+                entry.key = unique_key(&key, &taken);
+            }
+            let key = entry.key.clone();
+            self.entries.push(entry);
+            return Upsert::Added(key);
+        };
+        
+        let entry = &mut self.entries[index];
+        let mut changed = false;
+        for (name, _) in &fresh.fields {
+            let value = fresh.field(name).unwrap_or_default();
+            if matches!(name.as_str(), "journal" | "doi") || entry.field(name).is_none() {
+                changed |= entry.set_field(name, value);
+            }
+        }
+        
+        if changed {
+            Upsert::Updated(entry.key.clone())
+        } else {
+            Upsert::Unchanged(entry.key.clone())
+        }
+    }
+    
+    /// Merge entries about the same paper, by arXiv ID or DOI
+    ///
+    /// The first entry of a paper is kept and gains the fields the later
+    /// ones have and it lacks. Returns the number of entries removed.
+    pub fn dedup(&mut self) -> usize {
+        let mut kept: Vec<BibEntry> = Vec::with_capacity(self.entries.len());
+        let mut removed = 0;
+        
+        for entry in self.entries.drain(..) {
+            match kept.iter_mut().find(|kept| kept.same_paper(&entry)) {
+                Some(kept) => {
+                    debug!("Merging duplicate entry {} into {}", entry.key, kept.key);
+                    kept.merge(&entry);
+                    removed += 1;
+                },
+                None => kept.push(entry),
+            }
+        }
+        
+        self.entries = kept;
+        removed
+    }
+    
+    /// Rename entries to their normalized citation keys
+    ///
+    /// Entries whose keys collide get a letter suffix in file order (`b`,
+    /// `c`, ...), and entries without author keep their key. Returns the
+    /// renamed keys as `(old, new)` pairs.
+    pub fn normalize_keys(&mut self) -> Vec<(String, String)> {
+        let keys: Vec<Option<String>> = self.entries.iter().map(BibEntry::normalized_key).collect();
+        let mut taken: HashSet<String> = self.entries
+            .iter()
+            .zip(&keys)
+            .filter(|(_, key)| key.is_none())
+            .map(|(entry, _)| entry.key.clone())
+            .collect();
+        let mut renamed = Vec::new();
+        
+        for (entry, key) in self.entries.iter_mut().zip(keys) {
+            let Some(key) = key else {
+                continue;
+            };
+            
+            let key = unique_key(&key, &taken);
+            taken.insert(key.clone());
+            
+            if entry.key != key {
+                renamed.push((std::mem::replace(&mut entry.key, key.clone()), key));
+            }
+        }
+        
+        renamed
+    }
+}
+
+/// Parse BibTeX into its `@comment`, `@preamble` and `@string` blocks and its entries
+///
+/// Text outside of blocks is ignored, as BibTeX does.
+pub fn parse(text: &str) -> BibResult<(Vec<String>, Vec<BibEntry>)> {
+    let mut parser = Parser { chars: text.chars().collect(), pos: 0 };
+    let mut preamble = Vec::new();
+    let mut entries = Vec::new();
+    
+    while parser.skip_to('@') {
+        let start = parser.pos;
+        parser.pos += 1;
+        
+        let entry_type = parser.identifier();
+        parser.skip_whitespace();
+        let close = match parser.peek() {
+            Some('{') => '}',
+            Some('(') => ')',
+            _ => return Err(parser.error("expected '{' or '(' after entry type")),
+        };
+        
+        if matches!(entry_type.to_lowercase().as_str(), "comment" | "preamble" | "string") {
+            parser.delimited()?;
+            preamble.push(parser.chars[start..parser.pos].iter().collect());
+            continue;
+        }
+        
+        parser.pos += 1;
+        parser.skip_whitespace();
+        let key = parser.take_while(|c| c != ',' && c != close && !c.is_whitespace());
+        let mut entry = BibEntry { entry_type, key, fields: Vec::new() };
+        
+        loop {
+            parser.skip_whitespace();
+            match parser.peek() {
+                Some(',') => {
+                    parser.pos += 1;
+                    continue;
+                },
+                Some(c) if c == close => {
+                    parser.pos += 1;
+                    break;
+                },
+                None => return Err(parser.error(&format!("entry {} is not closed", entry.key))),
+                _ => {}
+            }
+            
+            let name = parser.identifier();
+            parser.skip_whitespace();
+            if name.is_empty() || parser.peek() != Some('=') {
+                return Err(parser.error(&format!("expected field in entry {}", entry.key)));
+            }
+            parser.pos += 1;
+            
+            let value = parser.value(close)?;
+            entry.fields.push((name, value));
+        }
+        
+        entries.push(entry);
+    }
+    
+    Ok((preamble, entries))
+}
+
+/// A cursor over BibTeX text
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+    
+    fn skip_to(&mut self, target: char) -> bool {
+        while let Some(c) = self.peek() {
+            if c == target {
+                return true;
+            }
+            self.pos += 1;
+        }
+        false
+    }
+    
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+    
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.peek().map_or(false, &predicate) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+    
+    fn identifier(&mut self) -> String {
+        self.take_while(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | ':' | '.'))
+    }
+    
+    /// Skip a block starting at the current `{`, `(` or `"`, up to its matching delimiter
+    fn delimited(&mut self) -> BibResult<()> {
+        let open = self.peek().unwrap_or_default();
+        let close = match open {
+            '{' => '}',
+            '(' => ')',
+            _ => '"',
+        };
+        let start = self.pos;
+        let mut depth = 0;
+        self.pos += 1;
+        
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '{' => depth += 1,
+                '}' if depth > 0 => depth -= 1,
+                c if c == close && depth == 0 => return Ok(()),
+                _ => {}
+            }
+        }
+        
+        self.pos = start;
+        Err(self.error(&format!("unbalanced '{}'", open)))
+    }
+    
+    /// Read a field value as written, including delimiters and `#` concatenations
+    fn value(&mut self, close: char) -> BibResult<String> {
+        let mut parts = Vec::new();
+        
+        loop {
+            self.skip_whitespace();
+            let start = self.pos;
+            match self.peek() {
+                Some('{') | Some('"') => self.delimited()?,
+                _ => {
+                    self.take_while(|c| c != ',' && c != close && c != '#' && !c.is_whitespace());
+                },
+            }
+            if self.pos == start {
+                return Err(self.error("expected field value"));
+            }
+            parts.push(self.chars[start..self.pos].iter().collect::<String>());
+            
+            self.skip_whitespace();
+            if self.peek() != Some('#') {
+                break;
+            }
+            self.pos += 1;
+        }
+        
+        Ok(parts.join(" # "))
+    }
+    
+    fn error(&self, message: &str) -> BibError {
+        let line = self.chars[..self.pos.min(self.chars.len())].iter().filter(|&&c| c == '\n').count() + 1;
+        BibError::Syntax { line, message: message.to_string() }
+    }
+}
+
+/// Remove the braces or quotes around a raw field value
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    if (value.starts_with('{') && value.ends_with('}')) || (value.len() > 1 && value.starts_with('"') && value.ends_with('"')) {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    }
+}
+
+/// Remove the version suffix of an arXiv ID, e.g. `v2` of `2101.12345v2`
+fn strip_version(id: &str) -> &str {
+    match id.rfind('v') {
+        Some(pos) if pos > 0 && pos + 1 < id.len() && id[pos + 1..].chars().all(|c| c.is_ascii_digit()) => &id[..pos],
+        _ => id,
+    }
+}
+
+/// Fold a name or word to lowercase ASCII letters and digits, dropping LaTeX markup
+fn fold(text: &str) -> String {
+    LATEX_COMMAND
+        .replace_all(text, "")
+        .chars()
+        .filter_map(|c| match c {
+            'á' | 'à' | 'ä' | 'â' | 'ã' | 'å' | 'Á' | 'À' | 'Ä' | 'Â' | 'Ã' | 'Å' => Some('a'),
+            'é' | 'è' | 'ë' | 'ê' | 'É' | 'È' | 'Ë' | 'Ê' => Some('e'),
+            'í' | 'ì' | 'ï' | 'î' | 'Í' | 'Ì' | 'Ï' | 'Î' => Some('i'),
+            'ó' | 'ò' | 'ö' | 'ô' | 'õ' | 'ø' | 'Ó' | 'Ò' | 'Ö' | 'Ô' | 'Õ' | 'Ø' => Some('o'),
+            'ú' | 'ù' | 'ü' | 'û' | 'Ú' | 'Ù' | 'Ü' | 'Û' => Some('u'),
+            'ñ' | 'Ñ' => Some('n'),
+            'ç' | 'Ç' => Some('c'),
+            'ß' => Some('s'),
+            c if c.is_ascii_alphanumeric() => Some(c.to_ascii_lowercase()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Make a citation key unique by appending a letter, starting at `b`
+fn unique_key(key: &str, taken: &HashSet<String>) -> String {
+    if !taken.contains(key) {
+        return key.to_string();
+    }
+    
+    ('b'..='z')
+        .map(|suffix| format!("{}{}", key, suffix))
+        .chain((2..).map(|n| format!("{}{}", key, n)))
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    
+    const LIBRARY: &str = r#"@comment{Maintained by llama-arxiv}
+
+@inproceedings{Vaswani:2017,
+  author = {Vaswani, Ashish and Shazeer, Noam},
+  title = "{Attention} Is All You Need",
+  booktitle = {NeurIPS},
+  year = 2017,
+  month = dec,
+  doi = {https://doi.org/10.5555/3295222.3295349},
+}
+
+Text between entries is ignored.
+
+@misc{vaswani_attention_preprint,
+  author = {Ashish Vaswani and Noam Shazeer},
+  title = {Attention Is All You Need},
+  year = {2017},
+  eprint = {1706.03762v5},
+  archivePrefix = {arXiv},
+  doi = {10.5555/3295222.3295349},
+}
+
+@article{muller,
+  author = {M{\"u}ller, Rafael},
+  title = {When Does Label Smoothing Help?},
+  year = {2019},
+  journal = {arXiv preprint arXiv:1906.02629},
+}
+"#;
+    
+    #[test]
+    fn test_parse_and_keys() {
+        let (preamble, entries) = parse(LIBRARY).unwrap();
+        assert_eq!(preamble, vec!["@comment{Maintained by llama-arxiv}"]);
+        assert_eq!(entries.len(), 3);
+        
+        let attention = &entries[0];
+        assert_eq!(attention.entry_type, "inproceedings");
+        assert_eq!(attention.field("title"), Some("{Attention} Is All You Need"));
+        assert_eq!(attention.field("YEAR"), Some("2017"));
+        assert_eq!(attention.doi().as_deref(), Some("10.5555/3295222.3295349"));
+        assert_eq!(attention.normalized_key().as_deref(), Some("vaswani2017attention"));
+        assert!(attention.to_bibtex().contains("\tmonth = dec,\n"));
+        
+        assert_eq!(entries[1].arxiv_id().as_deref(), Some("1706.03762"));
+        assert_eq!(entries[2].arxiv_id().as_deref(), Some("1906.02629"));
+        assert_eq!(entries[2].normalized_key().as_deref(), Some("muller2019label"));
+        
+        assert!(matches!(parse("@article{broken,\n  title = {Unclosed"), Err(BibError::Syntax { line: 2, .. })));
+    }
+    
+    #[test]
+    fn test_dedup_and_normalize_keys() {
+        let (preamble, entries) = parse(LIBRARY).unwrap();
+        let mut library = BibLibrary { preamble, entries, path: PathBuf::new() };
+        
+        assert_eq!(library.dedup(), 1);
+        assert_eq!(library.entries[0].key, "Vaswani:2017");
+        assert_eq!(library.entries[0].arxiv_id().as_deref(), Some("1706.03762"));
+        
+        let mut smoothing = library.entries[1].clone();
+        smoothing.key = "other".to_string();
+        smoothing.set_field("eprint", "1906.99999");
+        smoothing.set_field("journal", "Preprint");
+        library.entries.push(smoothing);
+        
+        let renamed = library.normalize_keys();
+        assert_eq!(renamed, vec![
+            ("Vaswani:2017".to_string(), "vaswani2017attention".to_string()),
+            ("muller".to_string(), "muller2019label".to_string()),
+            ("other".to_string(), "muller2019labelb".to_string()),
+        ]);
+        assert!(library.normalize_keys().is_empty());
+    }
+    
+    #[test]
+    fn test_upsert_and_save() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("library.bib");
+        
+        let mut metadata = PaperMetadata::new("2101.12345v1");
+        metadata.title = "On the Test of Papers".to_string();
+        metadata.authors = vec!["John Smith".to_string(), "Jane Doe".to_string()];
+        metadata.published = "2021-01-01".to_string();
+        
+        let mut library = BibLibrary::open(&path).unwrap();
+        assert_eq!(library.upsert(&metadata), Upsert::Added("smith2021test".to_string()));
+        library.entries[0].set_field("note", "Read twice");
+        library.save().unwrap();
+        
+        let mut library = BibLibrary::open(&path).unwrap();
+        assert_eq!(library.unpublished(), vec!["2101.12345"]);
+        
+        metadata.id = "2101.12345v2".to_string();
+        assert_eq!(library.upsert(&metadata), Upsert::Unchanged("smith2021test".to_string()));
+        
+        metadata.journal_ref = Some("J. Test. 12 (2022) 1-10".to_string());
+        metadata.doi = Some("10.1000/test.2022".to_string());
+        assert_eq!(library.upsert(&metadata), Upsert::Updated("smith2021test".to_string()));
+        
+        let entry = &library.entries[0];
+        assert_eq!(library.entries.len(), 1);
+        assert_eq!(entry.field("journal"), Some("J. Test. 12 (2022) 1-10"));
+        assert_eq!(entry.field("note"), Some("Read twice"));
+        assert!(library.unpublished().is_empty());
+    }
+}
@@ -1,5 +1,5 @@
 use std::path::PathBuf;
-use clap::{Parser, ValueEnum, Args};
+use clap::{Parser, Subcommand, ValueEnum, Args};
 use anyhow::Result;
 use directories::ProjectDirs;
 
@@ -32,7 +32,12 @@ impl std::fmt::Display for OutputFormat {
 #[command(author = "Llama Moonlight Team <info@llamamoonlight.com>")]
 #[command(version = env!("CARGO_PKG_VERSION"))]
 #[command(about = "Download, process, and organize papers from arXiv", long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Cli {
+    /// Command to run instead of processing papers
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    
     /// ArXiv IDs or URLs to process
     #[arg(required = true)]
     targets: Vec<String>,
@@ -45,7 +50,7 @@ pub struct Cli {
     #[arg(short, long, value_enum)]
     format: Option<OutputFormat>,
     
-    /// Extract and save BibTeX citations (requires --process-pdf)
+    /// Add BibTeX citations to the library (requires --process-pdf)
     #[arg(short, long)]
     citations: bool,
     
@@ -61,6 +66,14 @@ pub struct Cli {
     #[arg(short, long)]
     force: bool,
     
+    /// Add processed papers to the semantic search index
+    #[arg(short, long)]
+    pub index: bool,
+    
+    /// Use the ar5iv HTML rendering for the text whenever one exists
+    #[arg(long)]
+    pub prefer_html: bool,
+    
     /// Path to configuration file
     #[arg(short, long)]
     config: Option<PathBuf>,
@@ -74,6 +87,34 @@ pub struct Cli {
     quiet: bool,
 }
 
+/// Commands other than processing papers
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Answer a question with passages from indexed papers
+    Ask {
+        /// Question to answer
+        question: String,
+        
+        /// Number of passages to return
+        #[arg(short = 'k', long)]
+        top_k: Option<usize>,
+    },
+    
+    /// Add papers to the BibTeX library, merging duplicate entries and normalizing citation keys
+    Bib {
+        /// ArXiv IDs or URLs of papers to add
+        targets: Vec<String>,
+        
+        /// Re-fetch papers without journal reference to pick up publications
+        #[arg(short, long)]
+        refresh: bool,
+        
+        /// Path of the BibTeX library
+        #[arg(short, long)]
+        library: Option<PathBuf>,
+    },
+}
+
 /// Application configuration derived from command-line arguments
 #[derive(Debug, Clone)]
 pub struct AppConfig {
@@ -86,7 +127,7 @@ pub struct AppConfig {
     /// Format for extracted text
     pub output_format: Option<OutputFormat>,
     
-    /// Extract and save BibTeX citations
+    /// Add BibTeX citations to the library
     pub extract_citations: bool,
     
     /// Whether to download PDFs
@@ -98,6 +139,12 @@ pub struct AppConfig {
     /// Force re-download of existing files
     pub force: bool,
     
+    /// Add processed papers to the semantic search index
+    pub index_papers: bool,
+    
+    /// Use the ar5iv HTML rendering for the text whenever one exists
+    pub prefer_html: bool,
+    
     /// Path to configuration file
     pub config_path: PathBuf,
     
@@ -128,8 +175,10 @@ pub fn parse_args() -> Result<AppConfig> {
         output_format: cli.format,
         extract_citations: cli.citations,
         download: !cli.metadata_only,
-        process_pdf: !cli.download_only && cli.format.is_some() || cli.citations,
+        process_pdf: !cli.download_only && cli.format.is_some() || cli.citations || cli.index,
         force: cli.force,
+        index_papers: cli.index,
+        prefer_html: cli.prefer_html,
         config_path,
         verbose: cli.verbose,
         quiet: cli.quiet,
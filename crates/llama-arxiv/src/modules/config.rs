@@ -24,6 +24,14 @@ pub struct Config {
     /// Citation settings
     pub citation: CitationConfig,
     
+    /// Semantic search settings
+    #[serde(default)]
+    pub search: SearchConfig,
+    
+    /// HTML rendering settings
+    #[serde(default)]
+    pub html: HtmlConfig,
+    
     /// User agent for HTTP requests
     pub user_agent: String,
 }
@@ -96,6 +104,55 @@ pub struct CitationConfig {
     
     /// Default citation style
     pub style: String,
+    
+    /// Path of the BibTeX library (defaults to library.bib in the download directory)
+    #[serde(default)]
+    pub library_path: Option<PathBuf>,
+}
+
+/// Semantic search configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchConfig {
+    /// Path of the search index (defaults to index.json in the download directory)
+    pub index_path: Option<PathBuf>,
+    
+    /// Name of the MLX embedding model in `models_dir`; unset to embed with
+    /// feature hashing, which needs no weights but only matches words
+    #[serde(default)]
+    pub model: Option<String>,
+    
+    /// Directory of MLX models (defaults to models in the download directory)
+    #[serde(default)]
+    pub models_dir: Option<PathBuf>,
+    
+    /// Number of dimensions of the passage embeddings
+    pub dimensions: usize,
+    
+    /// Number of words per indexed passage
+    pub chunk_size: usize,
+    
+    /// Number of words shared by consecutive passages
+    pub chunk_overlap: usize,
+    
+    /// Number of passages returned per question
+    pub top_k: usize,
+}
+
+/// ar5iv HTML rendering configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HtmlConfig {
+    /// Base URL of the HTML renderings
+    pub base_url: String,
+    
+    /// Whether to use the HTML rendering when PDF text extraction is poor
+    pub fallback: bool,
+    
+    /// Share of readable words below which PDF text extraction is poor
+    pub min_pdf_quality: f32,
+    
+    /// Request timeout in seconds
+    pub timeout: u64,
 }
 
 impl Default for Config {
@@ -108,6 +165,8 @@ impl Default for Config {
             download: DownloadConfig::default(),
             pdf: PdfConfig::default(),
             citation: CitationConfig::default(),
+            search: SearchConfig::default(),
+            html: HtmlConfig::default(),
             user_agent: format!(
                 "llama-arxiv/{} (https://github.com/llamamoonlight/llama-arxiv)",
                 env!("CARGO_PKG_VERSION")
@@ -158,10 +217,62 @@ impl Default for CitationConfig {
             extract_from_pdf: true,
             generate_from_metadata: true,
             style: "bibtex".to_string(),
+            library_path: None,
+        }
+    }
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            index_path: None,
+            model: None,
+            models_dir: None,
+            dimensions: 384,
+            chunk_size: 200,
+            chunk_overlap: 40,
+            top_k: 5,
+        }
+    }
+}
+
+impl Default for HtmlConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "https://ar5iv.labs.arxiv.org/html".to_string(),
+            fallback: true,
+            min_pdf_quality: 0.6,
+            timeout: 60,
         }
     }
 }
 
+impl Config {
+    /// Get the path of the search index
+    pub fn index_path(&self) -> PathBuf {
+        self.search
+            .index_path
+            .clone()
+            .unwrap_or_else(|| self.download_dir.join("index.json"))
+    }
+    
+    /// Get the directory of MLX models
+    pub fn models_dir(&self) -> PathBuf {
+        self.search
+            .models_dir
+            .clone()
+            .unwrap_or_else(|| self.download_dir.join("models"))
+    }
+    
+    /// Get the path of the BibTeX library
+    pub fn library_path(&self) -> PathBuf {
+        self.citation
+            .library_path
+            .clone()
+            .unwrap_or_else(|| self.download_dir.join("library.bib"))
+    }
+}
+
 /// Load configuration from a file
 pub fn load(path: &Path) -> Result<Config, ArxivError> {
     if !path.exists() {
@@ -0,0 +1,282 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+use log::{debug, info};
+use llama_moonlight_mlx::{EmbeddingModel, MlxError, cosine_similarity};
+
+use crate::modules::config::SearchConfig;
+use crate::modules::metadata::PaperMetadata;
+
+/// Error types for search index operations
+#[derive(Error, Debug)]
+pub enum IndexError {
+    #[error("File system error: {0}")]
+    FileSystem(#[from] std::io::Error),
+    
+    #[error("Index format error: {0}")]
+    Format(#[from] serde_json::Error),
+    
+    #[error("Embedding error: {0}")]
+    Embedding(#[from] MlxError),
+    
+    #[error("Index was built with {expected} dimensions, model produces {actual}")]
+    DimensionMismatch { expected: usize, actual: usize },
+    
+    #[error("Index was built with embedding model {expected}, not {actual}; rebuild it or configure that model")]
+    ModelMismatch { expected: String, actual: String },
+}
+
+/// Result type for search index operations
+pub type IndexResult<T> = Result<T, IndexError>;
+
+/// An embedded passage of a paper
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// arXiv ID of the paper
+    pub paper_id: String,
+    
+    /// Paper title
+    pub title: String,
+    
+    /// Paper authors
+    pub authors: Vec<String>,
+    
+    /// Publication year, if known
+    pub year: Option<u32>,
+    
+    /// Position of the passage within the paper
+    pub chunk: usize,
+    
+    /// Passage text
+    pub text: String,
+    
+    /// Passage embedding
+    pub vector: Vec<f32>,
+}
+
+impl IndexEntry {
+    /// Format a citation for the paper of the passage
+    pub fn citation(&self) -> String {
+        let mut citation = format!("[{}] {}", self.paper_id, self.title);
+        
+        if let Some(author) = self.authors.first() {
+            citation.push_str(" — ");
+            citation.push_str(author);
+            if self.authors.len() > 1 {
+                citation.push_str(" et al.");
+            }
+        }
+        
+        if let Some(year) = self.year {
+            citation.push_str(&format!(" ({})", year));
+        }
+        
+        citation
+    }
+}
+
+/// A passage matching a search query
+#[derive(Debug, Clone)]
+pub struct SearchHit<'a> {
+    /// Matching passage
+    pub entry: &'a IndexEntry,
+    
+    /// Cosine similarity to the query
+    pub score: f32,
+}
+
+/// Local vector index of downloaded paper passages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorIndex {
+    /// Name of the embedding model that built the index
+    pub model: String,
+    
+    /// Number of dimensions of the embeddings
+    pub dimensions: usize,
+    
+    /// Indexed passages
+    pub entries: Vec<IndexEntry>,
+    
+    /// Path the index is stored at
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl VectorIndex {
+    /// Create an empty index stored at the given path
+    pub fn new(path: &Path, model: &EmbeddingModel) -> Self {
+        Self {
+            model: model.name.clone(),
+            dimensions: model.dimensions(),
+            entries: Vec::new(),
+            path: path.to_path_buf(),
+        }
+    }
+    
+    /// Load the index at the given path, or create an empty one if none exists
+    ///
+    /// Fails if the index was built with another model, whose vectors are not
+    /// comparable with the ones `model` produces.
+    pub fn open(path: &Path, model: &EmbeddingModel) -> IndexResult<Self> {
+        if !path.exists() {
+            debug!("No index at {}, creating a new one", path.display());
+            return Ok(Self::new(path, model));
+        }
+        
+        let content = fs::read_to_string(path)?;
+        let mut index: VectorIndex = serde_json::from_str(&content)?;
+        index.path = path.to_path_buf();
+        
+        if index.model != model.name {
+            return Err(IndexError::ModelMismatch {
+                expected: index.model,
+                actual: model.name.clone(),
+            });
+        }
+        
+        if index.dimensions != model.dimensions() {
+            return Err(IndexError::DimensionMismatch {
+                expected: index.dimensions,
+                actual: model.dimensions(),
+            });
+        }
+        
+        Ok(index)
+    }
+    
+    /// Write the index to disk
+    pub fn save(&self) -> IndexResult<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        
+        fs::write(&self.path, serde_json::to_string(self)?)?;
+        info!("Saved search index with {} passages to {}", self.entries.len(), self.path.display());
+        
+        Ok(())
+    }
+    
+    /// Embed the text of a paper, replacing any passages already indexed for it
+    ///
+    /// Returns the number of passages indexed.
+    pub async fn add_paper(
+        &mut self,
+        metadata: &PaperMetadata,
+        text: &str,
+        model: &EmbeddingModel,
+        config: &SearchConfig,
+    ) -> IndexResult<usize> {
+        let passages = chunk_text(text, config.chunk_size, config.chunk_overlap);
+        let vectors = model.embed_batch(&passages).await?;
+        
+        self.remove_paper(&metadata.id);
+        for (chunk, (text, vector)) in passages.into_iter().zip(vectors).enumerate() {
+            self.entries.push(IndexEntry {
+                paper_id: metadata.id.clone(),
+                title: metadata.title.clone(),
+                authors: metadata.authors.clone(),
+                year: metadata.year(),
+                chunk,
+                text,
+                vector,
+            });
+        }
+        
+        let count = self.entries.iter().filter(|entry| entry.paper_id == metadata.id).count();
+        debug!("Indexed {} passages of {}", count, metadata.id);
+        
+        Ok(count)
+    }
+    
+    /// Remove the passages of a paper
+    pub fn remove_paper(&mut self, paper_id: &str) {
+        self.entries.retain(|entry| entry.paper_id != paper_id);
+    }
+    
+    /// Find the passages most similar to a query
+    pub async fn search(&self, query: &str, model: &EmbeddingModel, limit: usize) -> IndexResult<Vec<SearchHit<'_>>> {
+        let query = model.embed(query).await?;
+        
+        let mut hits: Vec<SearchHit<'_>> = self
+            .entries
+            .iter()
+            .map(|entry| SearchHit {
+                entry,
+                score: cosine_similarity(&query, &entry.vector),
+            })
+            .collect();
+        
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(limit);
+        
+        Ok(hits)
+    }
+}
+
+/// Split text into passages of about `size` words, overlapping by `overlap` words
+fn chunk_text(text: &str, size: usize, overlap: usize) -> Vec<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let size = size.max(1);
+    let step = size.saturating_sub(overlap).max(1);
+    
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < words.len() {
+        let end = (start + size).min(words.len());
+        chunks.push(words[start..end].join(" "));
+        if end == words.len() {
+            break;
+        }
+        start += step;
+    }
+    
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    
+    #[test]
+    fn test_chunk_text() {
+        let chunks = chunk_text("a b c d e f g", 3, 1);
+        assert_eq!(chunks, vec!["a b c", "c d e", "e f g"]);
+        assert!(chunk_text("   ", 3, 1).is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_index_and_search() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("index.json");
+        let model = EmbeddingModel::hashing(256);
+        let config = SearchConfig {
+            chunk_size: 8,
+            chunk_overlap: 0,
+            ..SearchConfig::default()
+        };
+        
+        let mut pruning = PaperMetadata::new("1905.10650");
+        pruning.title = "Are Sixteen Heads Really Better than One?".to_string();
+        pruning.authors = vec!["Paul Michel".to_string(), "Omer Levy".to_string()];
+        pruning.published = "2019-05-25".to_string();
+        
+        let mut graphs = PaperMetadata::new("2101.00001");
+        graphs.title = "Greedy Graph Coloring".to_string();
+        
+        let mut index = VectorIndex::new(&path, &model);
+        index.add_paper(&pruning, "We prune attention heads and find most heads can be removed.", &model, &config).await.unwrap();
+        index.add_paper(&graphs, "Greedy heuristics color sparse graphs with few colors.", &model, &config).await.unwrap();
+        index.save().unwrap();
+        
+        // Vectors of another model are not comparable
+        let other = EmbeddingModel::hashing(128);
+        assert!(matches!(VectorIndex::open(&path, &other), Err(IndexError::ModelMismatch { .. })));
+        
+        let index = VectorIndex::open(&path, &model).unwrap();
+        let hits = index.search("what methods prune attention heads?", &model, 1).await.unwrap();
+        assert_eq!(hits[0].entry.paper_id, "1905.10650");
+        assert_eq!(hits[0].entry.citation(), "[1905.10650] Are Sixteen Heads Really Better than One? — Paul Michel et al. (2019)");
+    }
+}
@@ -4,6 +4,9 @@ pub mod download;
 pub mod parser;
 pub mod metadata;
 pub mod config;
+pub mod index;
+pub mod ar5iv;
+pub mod bib;
 
 // Context struct to hold application state
 #[derive(Debug)]
@@ -7,9 +7,13 @@ use log::{debug, info, warn, error};
 use regex::Regex;
 use lazy_static::lazy_static;
 
+use crate::modules::ar5iv::ParsedHtml;
 use crate::modules::config::PdfConfig;
 use crate::modules::metadata::PaperMetadata;
 
+/// Minimum number of words for extracted text to be judged at all
+const MIN_QUALITY_WORDS: usize = 200;
+
 /// Error types for PDF parsing operations
 #[derive(Error, Debug)]
 pub enum ParserError {
@@ -46,6 +50,9 @@ pub struct ParsedPdf {
     
     /// Associated metadata if available
     pub metadata: Option<PaperMetadata>,
+    
+    /// URL of the HTML rendering the text was taken from, if any
+    pub html_url: Option<String>,
 }
 
 /// Structure representing a section in a PDF
@@ -70,7 +77,51 @@ impl ParsedPdf {
             references: Vec::new(),
             source_path,
             metadata: None,
+            html_url: None,
+        }
+    }
+    
+    /// Estimate the quality of the extracted text
+    ///
+    /// Returns the share of words made only of letters, between 0 and 1.
+    /// Garbled extraction (glyph codes, broken encodings) scores low, and so
+    /// does text too short to be the full paper.
+    pub fn quality(&self) -> f32 {
+        let words: Vec<&str> = self.text.split_whitespace().collect();
+        if words.len() < MIN_QUALITY_WORDS {
+            return 0.0;
+        }
+        
+        let readable = words.iter().filter(|word| is_readable_word(word)).count();
+        readable as f32 / words.len() as f32
+    }
+    
+    /// Replace the extracted text with the structure of the HTML rendering
+    ///
+    /// The metadata and source path are kept, and so is the PDF's abstract
+    /// when the HTML rendering has none. References come from the HTML
+    /// bibliography unless it is empty.
+    pub fn merge_html(&mut self, html: ParsedHtml) {
+        let pdf_abstract = self.sections
+            .iter()
+            .find(|s| s.heading.to_lowercase().contains("abstract"))
+            .cloned();
+        
+        self.text = html.text();
+        self.sections = html.sections;
+        
+        if let Some(pdf_abstract) = pdf_abstract {
+            if !self.sections.iter().any(|s| s.heading.to_lowercase().contains("abstract")) {
+                self.text = format!("{}\n\n{}\n\n{}", pdf_abstract.heading, pdf_abstract.content, self.text);
+                self.sections.insert(0, pdf_abstract);
+            }
+        }
+        
+        if !html.references.is_empty() {
+            self.references = html.references;
         }
+        
+        self.html_url = Some(html.url);
     }
     
     /// Get the abstract section if it exists
@@ -261,6 +312,14 @@ impl ParsedPdf {
     }
 }
 
+/// Check whether a word of extracted text is made of letters
+fn is_readable_word(word: &str) -> bool {
+    let word = word.trim_matches(|c: char| c.is_ascii_punctuation());
+    !word.is_empty()
+        && word.chars().count() <= 24
+        && word.chars().all(|c| c.is_alphabetic() || c == '-' || c == '\'')
+}
+
 /// PDF parser for scientific papers
 pub struct PdfParser {
     /// Parser configuration
@@ -538,4 +597,39 @@ mod tests {
             extract_references: true,
         }
     }
+    
+    #[test]
+    fn test_quality_and_html_merge() {
+        let prose = "The transformer relies entirely on attention mechanisms. ".repeat(40);
+        let parsed = ParsedPdf::new(prose, PathBuf::from("paper.pdf"));
+        assert!(parsed.quality() > 0.9);
+        
+        let garbled = "\\x01\\x02 0x3F ##@ 12.5 q1w2e3 ".repeat(40);
+        let mut parsed = ParsedPdf::new(garbled, PathBuf::from("paper.pdf"));
+        assert!(parsed.quality() < 0.2);
+        assert_eq!(ParsedPdf::new("Too short".to_string(), PathBuf::new()).quality(), 0.0);
+        
+        parsed.sections = vec![PdfSection {
+            heading: "Abstract".to_string(),
+            content: "We propose a model.".to_string(),
+            level: 1,
+        }];
+        parsed.references = vec!["Garbled reference".to_string()];
+        parsed.merge_html(ParsedHtml {
+            url: "https://ar5iv.labs.arxiv.org/html/1706.03762".to_string(),
+            title: None,
+            sections: vec![PdfSection {
+                heading: "Introduction".to_string(),
+                content: "Attention relates positions.".to_string(),
+                level: 1,
+            }],
+            references: Vec::new(),
+        });
+        
+        assert_eq!(parsed.sections.len(), 2);
+        assert_eq!(parsed.sections[0].heading, "Abstract");
+        assert_eq!(parsed.text, "Abstract\n\nWe propose a model.\n\nIntroduction\n\nAttention relates positions.");
+        assert_eq!(parsed.references, vec!["Garbled reference"]);
+        assert_eq!(parsed.html_url.as_deref(), Some("https://ar5iv.labs.arxiv.org/html/1706.03762"));
+    }
 } 
\ No newline at end of file
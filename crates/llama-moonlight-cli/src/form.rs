@@ -0,0 +1,204 @@
+//! Form data for the `fill` command
+//!
+//! A form file is a JSON object mapping selectors to values, filled in the
+//! order they appear:
+//!
+//! ```json
+//! {
+//!     "#name": "Ada Lovelace",
+//!     "#newsletter": true,
+//!     "#languages": ["en", "fr"],
+//!     "#country": { "select": "GB" },
+//!     "#cv": { "file": "cv.pdf" }
+//! }
+//! ```
+//!
+//! Strings are typed, booleans check or uncheck a checkbox, and arrays select
+//! options. The explicit forms `{"text": ...}`, `{"check": ...}`,
+//! `{"select": ...}` and `{"file": ...}` take a single value or, for `select`
+//! and `file`, a list. Relative file paths are resolved against the directory
+//! of the form file.
+
+use anyhow::{Context, Result};
+use llama_moonlight_core::Page;
+use llama_moonlight_stealth::humanize::HumanizationManager;
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::Deserialize;
+use std::{fmt, path::Path};
+
+/// A value or a list of values
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany {
+    /// A single value
+    One(String),
+    /// A list of values
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    /// Get the values as a list
+    pub fn into_vec(self) -> Vec<String> {
+        match self {
+            Self::One(value) => vec![value],
+            Self::Many(values) => values,
+        }
+    }
+}
+
+/// Value of a form field, written with an explicit kind
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TypedValue {
+    /// Text typed into an input or textarea
+    Text(String),
+    /// State of a checkbox or radio button
+    Check(bool),
+    /// Options of a select element, by value or label
+    Select(OneOrMany),
+    /// Files of a file input
+    File(OneOrMany),
+}
+
+/// Value of a form field
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum FieldValue {
+    /// Text typed into an input or textarea
+    Text(String),
+    /// State of a checkbox or radio button
+    Check(bool),
+    /// Options of a select element, by value or label
+    Select(Vec<String>),
+    /// Value with an explicit kind
+    Typed(TypedValue),
+}
+
+impl FieldValue {
+    /// Get the value with its kind made explicit
+    fn into_typed(self) -> TypedValue {
+        match self {
+            Self::Text(text) => TypedValue::Text(text),
+            Self::Check(checked) => TypedValue::Check(checked),
+            Self::Select(values) => TypedValue::Select(OneOrMany::Many(values)),
+            Self::Typed(value) => value,
+        }
+    }
+}
+
+/// Form fields in the order of the form file
+#[derive(Debug, Clone, Default)]
+pub struct FormData {
+    /// Selectors and the values to fill them with
+    pub fields: Vec<(String, FieldValue)>,
+}
+
+impl FormData {
+    /// Read a form file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read form data from {}", path.display()))?;
+        serde_json::from_str(&json).with_context(|| format!("Invalid form data in {}", path.display()))
+    }
+}
+
+impl<'de> Deserialize<'de> for FormData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FormVisitor;
+
+        impl<'de> Visitor<'de> for FormVisitor {
+            type Value = FormData;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an object mapping selectors to values")
+            }
+
+            // Read the entries one by one, since maps would lose their order
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<FormData, A::Error> {
+                let mut fields = Vec::new();
+                while let Some(field) = map.next_entry()? {
+                    fields.push(field);
+                }
+                Ok(FormData { fields })
+            }
+        }
+
+        deserializer.deserialize_map(FormVisitor)
+    }
+}
+
+/// Fill a form field, typing text with human delays
+///
+/// Relative file paths are resolved against `base_dir`.
+pub async fn fill_field(
+    page: &Page,
+    selector: &str,
+    value: FieldValue,
+    humanizer: &HumanizationManager,
+    base_dir: &Path,
+) -> Result<()> {
+    match value.into_typed() {
+        TypedValue::Text(text) => {
+            let delays = humanizer.typing_delays(&text);
+            page.type_text_with_delays(selector, &text, &delays).await?;
+        }
+        TypedValue::Check(checked) => page.set_checked(selector, checked).await?,
+        TypedValue::Select(values) => {
+            page.select_option(selector, &values.into_vec()).await?;
+        }
+        TypedValue::File(paths) => {
+            let paths: Vec<_> = paths.into_vec().iter().map(|path| base_dir.join(path)).collect();
+            page.set_input_files(selector, &paths).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Vec<(String, TypedValue)> {
+        let form: FormData = serde_json::from_str(json).unwrap();
+        form.fields.into_iter().map(|(selector, value)| (selector, value.into_typed())).collect()
+    }
+
+    #[test]
+    fn test_fields_keep_file_order() {
+        let fields = parse(r##"{ "#z": "last name", "#a": "first name", "#m": "middle" }"##);
+        let selectors: Vec<_> = fields.iter().map(|(selector, _)| selector.as_str()).collect();
+        assert_eq!(selectors, ["#z", "#a", "#m"]);
+    }
+
+    #[test]
+    fn test_value_kinds() {
+        let fields = parse(
+            r##"{
+                "#name": "Ada Lovelace",
+                "#newsletter": true,
+                "#languages": ["en", "fr"],
+                "#country": { "select": "GB" },
+                "#bio": { "text": "true" },
+                "#cv": { "file": ["cv.pdf", "letter.pdf"] }
+            }"##,
+        );
+
+        assert!(matches!(&fields[0].1, TypedValue::Text(text) if text == "Ada Lovelace"));
+        assert!(matches!(fields[1].1, TypedValue::Check(true)));
+        assert!(matches!(&fields[2].1, TypedValue::Select(OneOrMany::Many(values)) if values == &["en", "fr"]));
+        assert!(matches!(&fields[3].1, TypedValue::Select(OneOrMany::One(value)) if value == "GB"));
+        assert!(matches!(&fields[4].1, TypedValue::Text(text) if text == "true"));
+        match fields[5].1.clone() {
+            TypedValue::File(paths) => assert_eq!(paths.into_vec(), ["cv.pdf", "letter.pdf"]),
+            other => panic!("expected files, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_forms() {
+        assert!(serde_json::from_str::<FormData>(r##"["#name", "Ada"]"##).is_err());
+        assert!(serde_json::from_str::<FormData>(r##"{ "#age": 36 }"##).is_err());
+        assert!(serde_json::from_str::<FormData>(r##"{ "#cv": { "upload": "cv.pdf" } }"##).is_err());
+        assert!(FormData::from_file(Path::new("/nonexistent/form.json")).is_err());
+    }
+}
@@ -1,16 +1,26 @@
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use clap::{Parser, Subcommand};
 use colored::*;
-use indicatif::{ProgressBar, ProgressStyle};
 use llama_moonlight_core::{
     options::{BrowserOptions, ContextOptions, PageOptions},
+    telemetry::{self, TelemetryConfig},
     BrowserType, Moonlight,
 };
+use llama_moonlight_crawler::{BrowserFetcher, CrawlOutcome, Crawler, CrawlerConfig, Error as CrawlError, Schema};
+use llama_moonlight_sinks::{BufferConfig, BufferedSink, CsvSink, JsonlSink};
+use llama_moonlight_stealth::humanize::HumanizationManager;
 use std::{
-    path::PathBuf,
-    time::{Duration, Instant},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
 };
 
+mod form;
+mod report;
+
+use form::FormData;
+use report::{Report, UsageError};
+
 /// Llama Moonlight - A browser automation CLI
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -37,6 +47,14 @@ struct Cli {
     /// Verbose output
     #[arg(short, long)]
     verbose: bool,
+
+    /// Print the outcome as a JSON envelope, with exit codes by failure class
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// OTLP collector to export traces to, e.g. http://localhost:4317 (needs the `otlp` feature)
+    #[arg(long, global = true, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    otlp_endpoint: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -99,16 +117,24 @@ enum Commands {
         url: String,
 
         /// The selector of the input to fill
-        #[arg(short, long)]
-        selector: String,
+        #[arg(short, long, required_unless_present = "data", requires = "text")]
+        selector: Option<String>,
 
         /// The text to fill the input with
-        #[arg(short, long)]
-        text: String,
+        #[arg(short, long, requires = "selector")]
+        text: Option<String>,
 
-        /// Submit the form after filling (clicks the first submit button)
-        #[arg(short, long)]
+        /// JSON file mapping selectors to values, filled in order with human typing
+        #[arg(long, conflicts_with = "selector")]
+        data: Option<PathBuf>,
+
+        /// Submit the form after filling and wait for the navigation
+        #[arg(long)]
         submit: bool,
+
+        /// The selector of the button submitting the form
+        #[arg(long, default_value = "[type=submit]")]
+        submit_selector: String,
     },
 
     /// Extract data from a webpage using selectors
@@ -117,8 +143,12 @@ enum Commands {
         url: String,
 
         /// The selector to extract data from
-        #[arg(short, long)]
-        selector: String,
+        #[arg(short, long, required_unless_present = "schema")]
+        selector: Option<String>,
+
+        /// JSON schema file describing the fields to extract
+        #[arg(long, conflicts_with = "selector")]
+        schema: Option<PathBuf>,
 
         /// The attribute to extract (default is innerText)
         #[arg(short, long, default_value = "innerText")]
@@ -150,40 +180,137 @@ enum Commands {
         #[arg(short, long, default_value = "30")]
         duration: u64,
     },
+
+    /// Show the request headers actually sent while loading a webpage
+    Headers {
+        /// The URL to navigate to
+        url: String,
+
+        /// Include every request of the page, not only documents
+        #[arg(short, long)]
+        all: bool,
+
+        /// Output format (json, text)
+        #[arg(short, long, default_value = "json")]
+        format: String,
+
+        /// Output file (if not specified, prints to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Show the cookies received while loading a webpage
+    Cookies {
+        /// The URL to navigate to
+        url: String,
+
+        /// Output format (json, text)
+        #[arg(short, long, default_value = "json")]
+        format: String,
+
+        /// Output file (if not specified, prints to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Crawl a site starting from a URL, following same-host links
+    Crawl {
+        /// The URL to start crawling from
+        url: String,
+
+        /// Maximum number of pages to visit
+        #[arg(short, long, default_value = "50")]
+        max_pages: usize,
+
+        /// Honor robots.txt rules and crawl-delay
+        #[arg(short, long)]
+        respect_robots: bool,
+
+        /// Seed the crawl with URLs from the site's sitemaps
+        #[arg(long)]
+        sitemap: bool,
+
+        /// Minimum delay between requests to the same host in milliseconds
+        #[arg(short, long, default_value = "1000")]
+        delay_ms: u64,
+
+        /// Output file for visited URLs (if not specified, prints to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Write one record per fetched page to this file (.csv for CSV, JSON Lines otherwise)
+        #[arg(long)]
+        results: Option<PathBuf>,
+    },
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize logger
-    env_logger::init();
+impl Commands {
+    /// Name of the command, as given on the command line
+    fn name(&self) -> &'static str {
+        match self {
+            Commands::Screenshot { .. } => "screenshot",
+            Commands::Content { .. } => "content",
+            Commands::Evaluate { .. } => "evaluate",
+            Commands::Click { .. } => "click",
+            Commands::Fill { .. } => "fill",
+            Commands::Extract { .. } => "extract",
+            Commands::Network { .. } => "network",
+            Commands::Headers { .. } => "headers",
+            Commands::Cookies { .. } => "cookies",
+            Commands::Crawl { .. } => "crawl",
+        }
+    }
+}
 
+#[tokio::main]
+async fn main() {
     // Parse command line arguments
     let cli = Cli::parse();
 
+    // Log to standard error, filtered by RUST_LOG
+    let telemetry_config = TelemetryConfig {
+        filter: Some(std::env::var("RUST_LOG").unwrap_or_else(|_| "error".to_string())),
+        otlp_endpoint: cli.otlp_endpoint.clone(),
+        service_name: "llama-moonlight-cli".to_string(),
+        ..TelemetryConfig::default()
+    };
+    if let Err(e) = telemetry::init(&telemetry_config) {
+        eprintln!("Failed to set up logging: {}", e);
+    }
+    #[cfg(not(feature = "otlp"))]
+    if cli.otlp_endpoint.is_some() {
+        eprintln!("Ignoring --otlp-endpoint: built without the `otlp` feature");
+    }
+
     // Print banner
-    print_banner();
-
-    // Initialize the spinner
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(
-        ProgressStyle::default_spinner()
-            .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
-            .template("{prefix:.bold.dim} {spinner} {wide_msg}")
-            .unwrap(),
-    );
-    pb.set_prefix("[llama-moonlight]");
+    if !cli.json {
+        print_banner();
+    }
+
+    let mut report = Report::new(cli.json);
+    let result = run(&cli, &mut report).await;
+    let exit_code = report.conclude(cli.command.name(), result);
+
+    // Exiting skips destructors, so pending spans are flushed first
+    telemetry::shutdown();
+    std::process::exit(exit_code);
+}
+
+/// Run the command, reporting its outcome
+async fn run(cli: &Cli, report: &mut Report) -> Result<()> {
+    let pb = report.progress();
+    // Progress notes would break the JSON envelope
+    let verbose = cli.verbose && !report.is_json();
     
     // Initialize the framework
     pb.set_message("Initializing Llama Moonlight...".to_string());
-    pb.enable_steady_tick(Duration::from_millis(100));
     
-    let start = Instant::now();
     let moonlight = Moonlight::new().await?;
     
     // Get browser type
     let browser_type = moonlight
         .browser_type(&cli.browser)
-        .ok_or_else(|| anyhow!("Browser type '{}' not found", cli.browser))?;
+        .ok_or_else(|| llama_moonlight_core::Error::BrowserTypeNotFound(cli.browser.clone()))?;
     
     // Configure browser options
     let mut options = BrowserOptions::default();
@@ -196,8 +323,8 @@ async fn main() -> Result<()> {
     
     // Configure context options
     let mut context_options = ContextOptions::default();
-    if let Some(user_agent) = cli.user_agent {
-        context_options.user_agent = Some(user_agent);
+    if let Some(user_agent) = &cli.user_agent {
+        context_options.user_agent = Some(user_agent.clone());
     }
     
     // Create a new context
@@ -207,6 +334,7 @@ async fn main() -> Result<()> {
     // Create a new page
     pb.set_message("Creating page...".to_string());
     let page = context.new_page().await?;
+    report.end_phase("launch");
     
     // Execute the command
     match &cli.command {
@@ -218,7 +346,8 @@ async fn main() -> Result<()> {
             // TODO: Implement full_page screenshot option
             page.screenshot(output.to_str().unwrap()).await?;
             
-            pb.finish_with_message(format!("Screenshot saved to {}", output.display()));
+            report.artifact(output);
+            report.finish(format!("Screenshot saved to {}", output.display()));
         }
         
         Commands::Content { url, output, format } => {
@@ -232,15 +361,15 @@ async fn main() -> Result<()> {
                     let script = "document.body.innerText";
                     page.evaluate::<String>(script).await?
                 }
-                _ => return Err(anyhow!("Unsupported format: {}", format)),
+                _ => return Err(UsageError(format!("Unsupported format: {}", format)).into()),
             };
             
             if let Some(path) = output {
                 std::fs::write(path, content)?;
-                pb.finish_with_message(format!("Content saved to {}", path.display()));
+                report.artifact(path);
+                report.finish(format!("Content saved to {}", path.display()));
             } else {
-                pb.finish();
-                println!("{}", content);
+                report.output(content.clone(), serde_json::json!({ "content": content }));
             }
         }
         
@@ -251,8 +380,7 @@ async fn main() -> Result<()> {
             pb.set_message("Evaluating JavaScript...".to_string());
             let result = page.evaluate::<serde_json::Value>(script).await?;
             
-            pb.finish();
-            println!("{}", serde_json::to_string_pretty(&result)?);
+            report.output(serde_json::to_string_pretty(&result)?, result);
         }
         
         Commands::Click { url, selector, screenshot } => {
@@ -265,53 +393,87 @@ async fn main() -> Result<()> {
             if let Some(path) = screenshot {
                 pb.set_message("Taking screenshot...".to_string());
                 page.screenshot(path.to_str().unwrap()).await?;
-                pb.finish_with_message(format!("Screenshot saved to {}", path.display()));
+                report.artifact(path);
+                report.finish(format!("Screenshot saved to {}", path.display()));
             } else {
-                pb.finish_with_message("Click completed successfully".to_string());
+                report.finish("Click completed successfully");
             }
         }
         
-        Commands::Fill { url, selector, text, submit } => {
+        Commands::Fill { url, selector, text, data, submit, submit_selector } => {
             pb.set_message(format!("Navigating to {}", url));
             page.goto(url).await?;
             
-            pb.set_message(format!("Filling in form field: {}", selector));
-            page.type_text(selector, text).await?;
+            let mut filled = 0;
+            if let Some(path) = data {
+                let form_data = FormData::from_file(path).map_err(|e| UsageError(format!("{:#}", e)))?;
+                let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+                let humanizer = HumanizationManager::new();
+                let total = form_data.fields.len();
+                
+                for (i, (selector, value)) in form_data.fields.into_iter().enumerate() {
+                    pb.set_message(format!("[{}/{}] Filling in form field: {}", i + 1, total, selector));
+                    form::fill_field(&page, &selector, value, &humanizer, base_dir)
+                        .await
+                        .map_err(|e| e.context(format!("Failed to fill in {}", selector)))?;
+                    filled += 1;
+                }
+            } else if let (Some(selector), Some(text)) = (selector, text) {
+                pb.set_message(format!("Filling in form field: {}", selector));
+                page.type_text(selector, text).await?;
+                filled += 1;
+            }
             
             if *submit {
                 pb.set_message("Submitting form...".to_string());
-                page.evaluate::<()>("document.querySelector('form').submit()").await?;
+                page.click_and_wait_for_navigation(submit_selector).await?;
             }
             
-            pb.finish_with_message("Form interaction completed successfully".to_string());
+            report.set_data(serde_json::json!({ "fields": filled, "submitted": submit }));
+            report.finish("Form interaction completed successfully");
         }
         
-        Commands::Extract { url, selector, attribute, format, output } => {
+        Commands::Extract { url, selector, schema, attribute, format, output } => {
             pb.set_message(format!("Navigating to {}", url));
             page.goto(url).await?;
             
-            pb.set_message(format!("Extracting data using selector: {}", selector));
-            let script = format!(
-                "Array.from(document.querySelectorAll('{}'))
-                    .map(el => el.{})",
-                selector, attribute
-            );
-            
-            let data = page.evaluate::<Vec<String>>(&script).await?;
-            
-            let formatted_data = match format.to_lowercase().as_str() {
-                "json" => serde_json::to_string_pretty(&data)?,
-                "csv" => data.join("\n"),
-                "text" => data.join("\n"),
-                _ => return Err(anyhow!("Unsupported format: {}", format)),
+            let (formatted_data, data) = if let Some(schema_path) = schema {
+                pb.set_message(format!("Extracting data using schema: {}", schema_path.display()));
+                let schema = Schema::from_json(&std::fs::read_to_string(schema_path)?)?;
+                let data = schema.extract_page(&page).await?;
+                
+                match format.to_lowercase().as_str() {
+                    "json" => (serde_json::to_string_pretty(&data)?, serde_json::to_value(&data)?),
+                    _ => return Err(UsageError(format!("Schema extraction only supports json output, got: {}", format)).into()),
+                }
+            } else {
+                let selector = selector
+                    .as_deref()
+                    .ok_or_else(|| UsageError("Either --selector or --schema is required".to_string()))?;
+                pb.set_message(format!("Extracting data using selector: {}", selector));
+                let script = format!(
+                    "Array.from(document.querySelectorAll('{}'))
+                        .map(el => el.{})",
+                    selector, attribute
+                );
+                
+                let data = page.evaluate::<Vec<String>>(&script).await?;
+                
+                let formatted_data = match format.to_lowercase().as_str() {
+                    "json" => serde_json::to_string_pretty(&data)?,
+                    "csv" => data.join("\n"),
+                    "text" => data.join("\n"),
+                    _ => return Err(UsageError(format!("Unsupported format: {}", format)).into()),
+                };
+                (formatted_data, serde_json::json!(data))
             };
             
             if let Some(path) = output {
                 std::fs::write(path, formatted_data)?;
-                pb.finish_with_message(format!("Data saved to {}", path.display()));
+                report.artifact(path);
+                report.finish(format!("Data saved to {}", path.display()));
             } else {
-                pb.finish();
-                println!("{}", formatted_data);
+                report.output(formatted_data, data);
             }
         }
         
@@ -329,24 +491,175 @@ async fn main() -> Result<()> {
             // Monitor for specified duration
             tokio::time::sleep(Duration::from_secs(*duration)).await;
             
-            pb.finish_with_message("Network monitoring completed".to_string());
+            report.finish("Network monitoring completed");
+        }
+        
+        Commands::Headers { url, all, format, output } => {
+            // Record before navigating, so the document request is included
+            let recorder = page.record_requests().await?;
+            
+            pb.set_message(format!("Navigating to {}", url));
+            page.goto(url).await?;
+            
+            let requests: Vec<_> = recorder
+                .requests()
+                .into_iter()
+                .filter(|request| *all || request.resource_type.as_deref() == Some("Document"))
+                .collect();
+            
+            let formatted = match format.to_lowercase().as_str() {
+                "json" => serde_json::to_string_pretty(&requests)?,
+                "text" => requests
+                    .iter()
+                    .map(|request| {
+                        let headers: Vec<String> = request
+                            .headers
+                            .iter()
+                            .map(|(name, value)| format!("{}: {}", name, value))
+                            .collect();
+                        format!("{} {}\n{}\n", request.method, request.url, headers.join("\n"))
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                _ => return Err(UsageError(format!("Unsupported format: {}", format)).into()),
+            };
+            
+            if let Some(path) = output {
+                std::fs::write(path, formatted)?;
+                report.artifact(path);
+                report.finish(format!("Headers of {} requests saved to {}", requests.len(), path.display()));
+            } else {
+                report.output(formatted, serde_json::to_value(&requests)?);
+            }
+        }
+        
+        Commands::Cookies { url, format, output } => {
+            pb.set_message(format!("Navigating to {}", url));
+            page.goto(url).await?;
+            
+            pb.set_message("Reading cookies...".to_string());
+            let cookies = context.storage_state().await?.cookies;
+            
+            let formatted = match format.to_lowercase().as_str() {
+                "json" => serde_json::to_string_pretty(&cookies)?,
+                "text" => cookies
+                    .iter()
+                    .map(|cookie| {
+                        let mut line = format!("{}={}; Domain={}; Path={}", cookie.name, cookie.value, cookie.domain, cookie.path);
+                        if cookie.http_only == Some(true) {
+                            line.push_str("; HttpOnly");
+                        }
+                        if cookie.secure == Some(true) {
+                            line.push_str("; Secure");
+                        }
+                        line
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                _ => return Err(UsageError(format!("Unsupported format: {}", format)).into()),
+            };
+            
+            if let Some(path) = output {
+                std::fs::write(path, formatted)?;
+                report.artifact(path);
+                report.finish(format!("{} cookies saved to {}", cookies.len(), path.display()));
+            } else {
+                report.output(formatted, serde_json::to_value(&cookies)?);
+            }
+        }
+        
+        Commands::Crawl { url, max_pages, respect_robots, sitemap, delay_ms, output, results } => {
+            let mut crawler_config = CrawlerConfig::default();
+            crawler_config.max_pages = *max_pages;
+            // A single page can only render one URL at a time
+            crawler_config.concurrency = 1;
+            crawler_config.respect_robots = *respect_robots;
+            crawler_config.seed_from_sitemaps = *sitemap;
+            crawler_config.frontier.default_host_delay = Duration::from_millis(*delay_ms);
+            
+            let crawl_page = Arc::new(context.new_page().await?);
+            let crawler = Crawler::new(crawler_config, BrowserFetcher::new(crawl_page));
+            
+            // Results go to the sink as pages complete, so a long crawl keeps
+            // little in memory and a failed one keeps what it wrote so far
+            let sink = match results {
+                Some(path) if path.extension().map(|ext| ext == "csv").unwrap_or(false) => {
+                    Some(BufferedSink::spawn(CsvSink::create(path).await?, BufferConfig::default()))
+                }
+                Some(path) => Some(BufferedSink::spawn(JsonlSink::create(path).await?, BufferConfig::default())),
+                None => None,
+            };
+            
+            let mut visited = Vec::new();
+            let crawled = crawler
+                .run_with(vec![url.clone()], |result| {
+                    let mut record = None;
+                    match result.outcome {
+                        CrawlOutcome::Fetched { page, extracted } => {
+                            pb.set_message(format!("[{}/{}] Crawled {}", visited.len() + 1, max_pages, result.entry.url));
+                            if sink.is_some() {
+                                record = Some(serde_json::json!({
+                                    "url": result.entry.url,
+                                    "final_url": page.final_url,
+                                    "status": page.status,
+                                    "depth": result.entry.depth,
+                                    "links": extracted.links.len(),
+                                    "data": extracted.data,
+                                }));
+                            }
+                            visited.push(result.entry.url);
+                        }
+                        CrawlOutcome::Disallowed if verbose => {
+                            println!("Skipping {} (disallowed by robots.txt)", result.entry.url);
+                        }
+                        CrawlOutcome::Failed(e) if verbose => {
+                            println!("Failed to load {}: {}", result.entry.url, e);
+                        }
+                        _ => {}
+                    }
+                    
+                    let sink = sink.clone();
+                    async move {
+                        if let (Some(sink), Some(record)) = (sink, record) {
+                            sink.send(record)
+                                .await
+                                .map_err(|e| CrawlError::Other(format!("Failed to write crawl result: {}", e)))?;
+                        }
+                        Ok::<(), CrawlError>(())
+                    }
+                })
+                .await;
+            
+            // Flush what was written even if the crawl failed
+            if let (Some(sink), Some(path)) = (&sink, results) {
+                let sink_stats = sink.shutdown().await?;
+                if verbose {
+                    println!("Wrote {} records to {}", sink_stats.written, path.display());
+                }
+                report.artifact(path);
+            }
+            let stats = crawled?;
+            
+            let listing = visited.join("\n");
+            if let Some(path) = output {
+                std::fs::write(path, listing)?;
+                report.artifact(path);
+                report.set_data(serde_json::json!({ "fetched": stats.fetched, "visited": visited }));
+                report.finish(format!("Crawled {} pages, URLs saved to {}", stats.fetched, path.display()));
+            } else {
+                let data = serde_json::json!({ "fetched": stats.fetched, "visited": visited });
+                report.output(listing, data);
+            }
         }
     }
+    report.end_phase("command");
     
     // Close the browser
-    if cli.verbose {
+    if verbose {
         println!("Closing browser...");
     }
     browser.close().await?;
     
-    // Print execution time
-    let duration = start.elapsed();
-    println!(
-        "{} Operation completed in {:.2} seconds",
-        "✓".green().bold(),
-        duration.as_secs_f64()
-    );
-    
     Ok(())
 }
 
@@ -0,0 +1,352 @@
+//! Command outcomes and exit codes
+//!
+//! Every command reports its outcome through a [`Report`]. For people, the
+//! report drives the spinner and prints results; with `--json`, it collects
+//! them into a single envelope printed when the command ends:
+//!
+//! ```json
+//! {
+//!   "status": "error",
+//!   "command": "screenshot",
+//!   "data": null,
+//!   "message": null,
+//!   "artifacts": [],
+//!   "timings": { "launch_ms": 812.4, "command_ms": 30004.1, "total_ms": 30816.5 },
+//!   "error": { "category": "timeout", "message": "Timeout: ...", "exit_code": 5 }
+//! }
+//! ```
+//!
+//! Failures exit with the code of their [`ErrorCategory`] in both modes.
+
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+/// Error in the arguments of a command that clap cannot catch
+#[derive(Debug, Error)]
+#[error("{0}")]
+pub struct UsageError(pub String);
+
+/// Class of a failure, deciding the exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// Anything not covered by another category
+    Other,
+    /// Invalid arguments or input files
+    Usage,
+    /// The browser could not be found, launched or talked to
+    Browser,
+    /// A page could not be loaded
+    Navigation,
+    /// An operation timed out
+    Timeout,
+    /// A selector matched no element or was invalid
+    ElementNotFound,
+    /// A script threw in the page
+    Script,
+    /// A file could not be read or written
+    Io,
+}
+
+impl ErrorCategory {
+    /// Classify an error by the first cause with a known type
+    pub fn of(error: &anyhow::Error) -> Self {
+        use llama_moonlight_core::Error as CoreError;
+
+        for cause in error.chain() {
+            if cause.is::<UsageError>() || cause.is::<serde_json::Error>() {
+                return Self::Usage;
+            }
+            if cause.is::<std::io::Error>() {
+                return Self::Io;
+            }
+            if let Some(error) = cause.downcast_ref::<CoreError>() {
+                return match error {
+                    CoreError::BrowserLaunchError(_)
+                    | CoreError::ContextCreationError(_)
+                    | CoreError::PageCreationError(_)
+                    | CoreError::BrowserTypeNotFound(_)
+                    | CoreError::ProtocolError(_)
+                    | CoreError::PageCrashed(_) => Self::Browser,
+                    CoreError::NavigationError(_) | CoreError::NavigationFailed { .. } => Self::Navigation,
+                    CoreError::TimeoutError(_) => Self::Timeout,
+                    CoreError::ElementNotFoundError(_) | CoreError::SelectorError(_) => Self::ElementNotFound,
+                    CoreError::JavaScriptError(_) => Self::Script,
+                    CoreError::FileError(_) => Self::Io,
+                    CoreError::JsonError(_) => Self::Usage,
+                    _ => Self::Other,
+                };
+            }
+        }
+
+        Self::Other
+    }
+
+    /// Exit code of the category (2 is left to clap's usage errors too)
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Other => 1,
+            Self::Usage => 2,
+            Self::Browser => 3,
+            Self::Navigation => 4,
+            Self::Timeout => 5,
+            Self::ElementNotFound => 6,
+            Self::Script => 7,
+            Self::Io => 8,
+        }
+    }
+}
+
+/// Error part of the JSON envelope
+#[derive(Debug, Serialize)]
+struct ErrorInfo {
+    category: ErrorCategory,
+    message: String,
+    exit_code: i32,
+}
+
+/// JSON envelope of a command outcome
+#[derive(Debug, Serialize)]
+struct Envelope<'a> {
+    status: &'static str,
+    command: &'a str,
+    data: &'a Value,
+    message: Option<&'a str>,
+    artifacts: &'a [PathBuf],
+    timings: BTreeMap<String, f64>,
+    error: Option<ErrorInfo>,
+}
+
+/// Outcome of a command, shown to people or collected for `--json`
+pub struct Report {
+    /// Whether to print a JSON envelope instead of human output
+    json: bool,
+    /// Spinner, hidden in JSON mode
+    pb: ProgressBar,
+    /// Start of the command
+    started: Instant,
+    /// End of the last phase
+    phase_started: Instant,
+    /// Duration of each phase
+    phases: Vec<(&'static str, Duration)>,
+    /// Structured result
+    data: Value,
+    /// Summary of the outcome
+    message: Option<String>,
+    /// Files written by the command
+    artifacts: Vec<PathBuf>,
+}
+
+impl Report {
+    /// Create a report, starting the spinner in human mode
+    pub fn new(json: bool) -> Self {
+        let pb = if json {
+            ProgressBar::hidden()
+        } else {
+            let pb = ProgressBar::new_spinner();
+            pb.set_style(
+                ProgressStyle::default_spinner()
+                    .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
+                    .template("{prefix:.bold.dim} {spinner} {wide_msg}")
+                    .unwrap(),
+            );
+            pb.set_prefix("[llama-moonlight]");
+            pb.enable_steady_tick(Duration::from_millis(100));
+            pb
+        };
+
+        let now = Instant::now();
+        Self {
+            json,
+            pb,
+            started: now,
+            phase_started: now,
+            phases: Vec::new(),
+            data: Value::Null,
+            message: None,
+            artifacts: Vec::new(),
+        }
+    }
+
+    /// Whether the report is printed as JSON
+    pub fn is_json(&self) -> bool {
+        self.json
+    }
+
+    /// Get the spinner, to show progress
+    pub fn progress(&self) -> ProgressBar {
+        self.pb.clone()
+    }
+
+    /// Record the time since the previous phase ended as the duration of `phase`
+    pub fn end_phase(&mut self, phase: &'static str) {
+        let now = Instant::now();
+        self.phases.push((phase, now - self.phase_started));
+        self.phase_started = now;
+    }
+
+    /// Output a result: `text` is printed for people, `data` goes into the envelope
+    pub fn output(&mut self, text: impl Into<String>, data: Value) {
+        if self.json {
+            self.data = data;
+        } else {
+            self.pb.finish();
+            println!("{}", text.into());
+        }
+    }
+
+    /// Set the structured result without printing anything for people
+    pub fn set_data(&mut self, data: Value) {
+        self.data = data;
+    }
+
+    /// Finish with a summary of the outcome
+    pub fn finish(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        if !self.json {
+            self.pb.finish_with_message(message.clone());
+        }
+        self.message = Some(message);
+    }
+
+    /// Record a file written by the command
+    pub fn artifact(&mut self, path: &Path) {
+        self.artifacts.push(path.to_path_buf());
+    }
+
+    /// Print the outcome of `command` and return the exit code
+    pub fn conclude(mut self, command: &str, result: anyhow::Result<()>) -> i32 {
+        let total = self.started.elapsed();
+        let error = result.err().map(|error| {
+            let category = ErrorCategory::of(&error);
+            ErrorInfo {
+                category,
+                message: format!("{:#}", error),
+                exit_code: category.exit_code(),
+            }
+        });
+        let exit_code = error.as_ref().map_or(0, |error| error.exit_code);
+
+        if !self.json {
+            if !self.pb.is_finished() {
+                self.pb.finish_and_clear();
+            }
+            match &error {
+                Some(error) => eprintln!("{} {}", "✗".red().bold(), error.message),
+                None => println!(
+                    "{} Operation completed in {:.2} seconds",
+                    "✓".green().bold(),
+                    total.as_secs_f64()
+                ),
+            }
+            return exit_code;
+        }
+
+        println!("{}", self.envelope_json(command, error, total));
+        exit_code
+    }
+
+    /// Render the JSON envelope of `command`
+    fn envelope_json(&mut self, command: &str, error: Option<ErrorInfo>, total: Duration) -> String {
+        let mut timings: BTreeMap<String, f64> = self
+            .phases
+            .drain(..)
+            .map(|(phase, duration)| (format!("{}_ms", phase), duration.as_secs_f64() * 1000.0))
+            .collect();
+        timings.insert("total_ms".to_string(), total.as_secs_f64() * 1000.0);
+
+        let envelope = Envelope {
+            status: if error.is_some() { "error" } else { "ok" },
+            command,
+            data: &self.data,
+            message: self.message.as_deref(),
+            artifacts: &self.artifacts,
+            timings,
+            error,
+        };
+        serde_json::to_string_pretty(&envelope).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Context};
+    use llama_moonlight_core::Error as CoreError;
+
+    #[test]
+    fn test_error_categories() {
+        let timeout = anyhow::Error::new(CoreError::TimeoutError("30000ms".to_string()));
+        assert_eq!(ErrorCategory::of(&timeout), ErrorCategory::Timeout);
+
+        // Context added on top does not hide the cause
+        let missing = Err::<(), _>(CoreError::ElementNotFoundError("#submit".to_string()))
+            .context("Failed to click")
+            .unwrap_err();
+        assert_eq!(ErrorCategory::of(&missing), ErrorCategory::ElementNotFound);
+
+        let usage = anyhow::Error::new(UsageError("--width needs --height".to_string()));
+        assert_eq!(ErrorCategory::of(&usage), ErrorCategory::Usage);
+
+        let io = anyhow::Error::new(std::io::Error::new(std::io::ErrorKind::NotFound, "form.json"));
+        assert_eq!(ErrorCategory::of(&io), ErrorCategory::Io);
+
+        assert_eq!(ErrorCategory::of(&anyhow!("something else")), ErrorCategory::Other);
+    }
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let categories = [
+            ErrorCategory::Other,
+            ErrorCategory::Usage,
+            ErrorCategory::Browser,
+            ErrorCategory::Navigation,
+            ErrorCategory::Timeout,
+            ErrorCategory::ElementNotFound,
+            ErrorCategory::Script,
+            ErrorCategory::Io,
+        ];
+        let mut codes: Vec<_> = categories.iter().map(|category| category.exit_code()).collect();
+        assert!(codes.iter().all(|code| *code != 0));
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), categories.len());
+    }
+
+    #[test]
+    fn test_envelope() {
+        let mut report = Report::new(true);
+        report.end_phase("launch");
+        report.output("ignored in JSON mode", serde_json::json!({ "title": "Example" }));
+        report.artifact(Path::new("shot.png"));
+        report.finish("Saved screenshot");
+
+        let envelope: Value = serde_json::from_str(&report.envelope_json("screenshot", None, Duration::from_secs(2))).unwrap();
+        assert_eq!(envelope["status"], "ok");
+        assert_eq!(envelope["command"], "screenshot");
+        assert_eq!(envelope["data"]["title"], "Example");
+        assert_eq!(envelope["message"], "Saved screenshot");
+        assert_eq!(envelope["artifacts"], serde_json::json!(["shot.png"]));
+        assert!(envelope["timings"]["launch_ms"].is_number());
+        assert_eq!(envelope["timings"]["total_ms"], 2000.0);
+        assert!(envelope["error"].is_null());
+
+        let error = ErrorInfo {
+            category: ErrorCategory::Navigation,
+            message: "Navigation failed".to_string(),
+            exit_code: ErrorCategory::Navigation.exit_code(),
+        };
+        let envelope: Value = serde_json::from_str(&Report::new(true).envelope_json("goto", Some(error), Duration::ZERO)).unwrap();
+        assert_eq!(envelope["status"], "error");
+        assert_eq!(envelope["error"]["category"], "navigation");
+        assert_eq!(envelope["error"]["exit_code"], 4);
+    }
+}
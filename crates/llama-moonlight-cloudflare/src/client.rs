@@ -1,10 +1,17 @@
 use crate::{Challenge, ChallengeSolution, ChallengeType, CloudflareConfig, CloudflareError, extract_challenge, is_cloudflare_challenge, is_cloudflare_captcha};
 use crate::challenge::solve_challenge;
-use crate::cookie::get_cookies_from_response;
+use crate::cookie::{self, get_cookies_from_response};
+use crate::identity::{ChallengeLoopStats, Identity, LoopCheck, LoopTracker};
+use crate::prewarm::{self, DomainRegistry, PrewarmHandle, CLEARANCE_COOKIE};
 use crate::proxy::ProxyManager;
-use crate::sessions::Session;
+use crate::sessions::{DomainSession, Session, SessionStore, CHALLENGE_LOCK_TTL, CLEARANCE_TTL};
+use crate::transport::{build_transport, Transport};
 use futures::future::BoxFuture;
-use log::{debug, error, info, warn};
+use llama_moonlight_core::telemetry::{self, FIELD_DOMAIN, FIELD_REQUEST_ID};
+use llama_moonlight_core::StorageState;
+use llama_moonlight_ratelimit::{RateLimit, RateLimitHeaders, RateLimiter};
+use tracing::{debug, error, info, instrument, warn};
+use reqwest::header::{HeaderValue, COOKIE, USER_AGENT};
 use reqwest::{Client, ClientBuilder, Method, Request, RequestBuilder, Response, StatusCode, Url};
 use serde::Serialize;
 use std::{
@@ -14,10 +21,15 @@ use std::{
 };
 use tokio::time::sleep;
 
+/// How often a worker waiting for another's challenge checks the lock
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 /// A client for handling Cloudflare protected websites
 pub struct CloudflareClient {
     /// HTTP client
     client: Client,
+    /// Transport sending the requests
+    transport: Arc<dyn Transport>,
     /// Configuration
     config: CloudflareConfig,
     /// Proxy manager
@@ -28,6 +40,16 @@ pub struct CloudflareClient {
     challenge_handler: Box<dyn Fn(Challenge, &str) -> BoxFuture<'static, Result<ChallengeSolution, CloudflareError>> + Send + Sync>,
     /// Session
     session: Option<Session>,
+    /// Rate limiter keyed by host
+    rate_limiter: Arc<RateLimiter>,
+    /// Known-protected domains to pre-warm
+    domain_registry: Arc<DomainRegistry>,
+    /// Challenge loops and the identity of each domain
+    challenge_loops: Arc<LoopTracker>,
+    /// Whether rotating the identity may replace the transport
+    rotate_transport: bool,
+    /// Store sharing clearance, pacing and challenge locks with other workers
+    session_store: Option<Arc<dyn SessionStore>>,
 }
 
 impl CloudflareClient {
@@ -58,6 +80,15 @@ impl CloudflareClient {
         let client = client_builder.build()
             .map_err(|e| CloudflareError::HttpError(e))?;
         
+        // Select the transport backend
+        let transport = build_transport(
+            &config.transport,
+            client.clone(),
+            config.user_agent.as_deref(),
+            Duration::from_secs(config.timeout_seconds),
+            config.proxy.as_deref(),
+        )?;
+        
         // Initialize cookies
         let cookies = config.cookies.clone().unwrap_or_default();
         
@@ -69,6 +100,12 @@ impl CloudflareClient {
             None
         };
         
+        // Pace requests per host
+        let rate_limits = config.requests_per_minute
+            .map(|limit| vec![RateLimit::per_minute(limit)])
+            .unwrap_or_default();
+        let rate_limiter = Arc::new(RateLimiter::new().with_default_limits(rate_limits));
+        
         // Default challenge handler
         let challenge_handler: Box<dyn Fn(Challenge, &str) -> BoxFuture<'static, Result<ChallengeSolution, CloudflareError>> + Send + Sync> = 
             Box::new(|challenge, domain| {
@@ -77,13 +114,22 @@ impl CloudflareClient {
                 })
             });
         
+        let domain_registry = Arc::new(DomainRegistry::new(&config.prewarm));
+        let challenge_loops = Arc::new(LoopTracker::new(&config.challenge_loops));
+        
         Ok(Self {
             client,
+            transport,
             config,
             proxy_manager,
             cookies: Arc::new(Mutex::new(cookies)),
             challenge_handler,
             session: None,
+            rate_limiter,
+            domain_registry,
+            challenge_loops,
+            rotate_transport: true,
+            session_store: None,
         })
     }
     
@@ -102,6 +148,74 @@ impl CloudflareClient {
         self
     }
     
+    /// Use a custom transport backend
+    ///
+    /// A custom transport is kept when the identity of a domain is rotated
+    /// after a challenge loop; only the headers change.
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = transport;
+        self.rotate_transport = false;
+        self
+    }
+    
+    /// Share a rate limiter with other clients
+    ///
+    /// Requests are paced per host, following the `x-ratelimit-*` and
+    /// `Retry-After` headers of the responses.
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+    
+    /// Share clearance cookies, pacing and challenge solving with other workers
+    ///
+    /// Requests to a host pick up the cookies other workers shared for it,
+    /// and are spaced by `requests_per_minute` across all workers. Only one
+    /// worker at a time solves the challenge of a host; the others wait for
+    /// its clearance. A worker that fails while solving keeps the lock until
+    /// it expires.
+    pub fn with_session_store(mut self, store: Arc<dyn SessionStore>) -> Self {
+        self.session_store = Some(store);
+        self
+    }
+    
+    /// Get the rate limiter
+    pub fn rate_limiter(&self) -> &Arc<RateLimiter> {
+        &self.rate_limiter
+    }
+    
+    /// Register a known-protected domain to pre-warm, by a URL to request on it
+    pub fn with_protected_domain(mut self, url: &str) -> Self {
+        self.config.prewarm.urls.push(url.to_string());
+        self.domain_registry = Arc::new(DomainRegistry::new(&self.config.prewarm));
+        self
+    }
+    
+    /// Get the registry of known-protected domains
+    pub fn domain_registry(&self) -> &Arc<DomainRegistry> {
+        &self.domain_registry
+    }
+    
+    /// Start solving the challenges of the known-protected domains in the background
+    ///
+    /// Each domain is requested right away, then again at the keep-alive
+    /// interval to keep its clearance fresh. Requests to a domain whose first
+    /// challenge is being solved wait for it. Dropping the handle stops the
+    /// background requests.
+    pub fn start_prewarm(self: &Arc<Self>) -> PrewarmHandle {
+        prewarm::start(self)
+    }
+    
+    /// Get the counts of challenge loops met and identity rotations made
+    pub fn challenge_loop_stats(&self) -> ChallengeLoopStats {
+        self.challenge_loops.stats()
+    }
+    
+    /// Get the identity a domain switched to after a challenge loop, if any
+    pub fn identity_for(&self, host: &str) -> Option<Identity> {
+        self.challenge_loops.identity(host)
+    }
+    
     /// Get the client configuration
     pub fn config(&self) -> &CloudflareConfig {
         &self.config
@@ -132,6 +246,25 @@ impl CloudflareClient {
         self.cookies.lock().unwrap().clear();
     }
     
+    /// Export the cookies as a browser storage state for a domain
+    ///
+    /// Loading the result into a browser context with
+    /// `BrowserContext::set_storage_state` carries a solved challenge over to
+    /// the browser.
+    pub fn export_storage_state(&self, domain: &str) -> StorageState {
+        cookie::to_storage_state(&self.cookies.lock().unwrap(), domain)
+    }
+    
+    /// Import cookies from a browser storage state
+    ///
+    /// With a host, only the cookies sent to that host are imported. Imported
+    /// cookies replace existing ones with the same name.
+    pub fn import_storage_state(&self, state: &StorageState, host: Option<&str>) {
+        let imported = cookie::from_storage_state(state, host);
+        debug!("Importing {} cookies from storage state", imported.len());
+        self.cookies.lock().unwrap().extend(imported);
+    }
+    
     /// Get a proxy URL
     fn get_proxy(&self) -> Option<String> {
         if let Some(ref proxy_manager) = self.proxy_manager {
@@ -141,6 +274,96 @@ impl CloudflareClient {
         }
     }
     
+    /// Switch a domain to a new identity after a challenge loop
+    ///
+    /// The clearance cookie was issued to the old identity, so it is dropped.
+    fn rotate_identity(&self, host: &str, identity: &Identity) -> Result<(), CloudflareError> {
+        self.remove_cookie(CLEARANCE_COOKIE);
+        
+        if !self.rotate_transport {
+            return Ok(());
+        }
+        if let Some(transport_config) = identity.transport_config(&self.config.transport) {
+            let transport = build_transport(
+                &transport_config,
+                self.client.clone(),
+                Some(&identity.user_agent),
+                Duration::from_secs(self.config.timeout_seconds),
+                self.config.proxy.as_deref(),
+            )?;
+            self.challenge_loops.set_transport(host, transport);
+        }
+        
+        Ok(())
+    }
+    
+    /// Get the spacing of requests to a host shared between workers
+    fn shared_interval(&self) -> Option<Duration> {
+        self.config.requests_per_minute
+            .filter(|limit| *limit > 0)
+            .map(|limit| Duration::from_secs(60) / limit)
+    }
+    
+    /// Merge the cookies shared for a host into the client and a request
+    async fn load_shared_session(&self, store: &dyn SessionStore, host: &str, request: &mut Request) -> Result<(), CloudflareError> {
+        let shared = match store.load(host).await? {
+            Some(shared) => shared,
+            None => return Ok(()),
+        };
+        
+        let cookie_header = {
+            let mut cookies = self.cookies.lock().unwrap();
+            cookies.extend(shared.cookies);
+            cookie::create_cookie_header(&cookies)
+        };
+        if let Ok(value) = HeaderValue::from_str(&cookie_header) {
+            request.headers_mut().insert(COOKIE, value);
+        }
+        
+        // The clearance is only honoured with the user agent it was issued to
+        if let Some(user_agent) = shared.user_agent.as_deref().and_then(|ua| HeaderValue::from_str(ua).ok()) {
+            request.headers_mut().insert(USER_AGENT, user_agent);
+        }
+        
+        Ok(())
+    }
+    
+    /// Share the cookies of a host with other workers
+    async fn save_shared_session(&self, store: &dyn SessionStore, host: &str, request: &Request) -> Result<(), CloudflareError> {
+        let session = DomainSession {
+            cookies: self.cookies(),
+            user_agent: request.headers().get(USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+        };
+        store.save(host, &session, CLEARANCE_TTL).await
+    }
+    
+    /// Wait for the right to solve the challenge of a host
+    ///
+    /// Returns the lock token, or `None` if another worker obtained a
+    /// clearance other than `stale` meanwhile.
+    async fn claim_challenge(&self, store: &dyn SessionStore, host: &str, stale: Option<String>) -> Result<Option<String>, CloudflareError> {
+        let token = uuid::Uuid::new_v4().to_string();
+        
+        loop {
+            if store.try_lock(host, &token, CHALLENGE_LOCK_TTL).await? {
+                return Ok(Some(token));
+            }
+            
+            debug!("Another worker is solving the challenge of {}, waiting", host);
+            while store.is_locked(host).await? {
+                sleep(LOCK_POLL_INTERVAL).await;
+            }
+            
+            let clearance = store.load(host).await?
+                .and_then(|shared| cookie::get_clearance_cookie(&shared.cookies));
+            if clearance.is_some() && clearance != stale {
+                return Ok(None);
+            }
+        }
+    }
+    
     /// Get default headers for a request
     async fn get_default_headers(&self, url: &str) -> HashMap<String, String> {
         let mut headers = HashMap::new();
@@ -224,23 +447,94 @@ impl CloudflareClient {
     }
     
     /// Send a request and handle Cloudflare challenges
+    ///
+    /// If the challenge of the request's domain is being pre-solved, waits
+    /// for it first.
+    ///
+    /// Retries, redirects and challenge round trips share one request ID,
+    /// that of the enclosing [`telemetry::in_request`] scope if any.
     pub async fn send(&self, request: RequestBuilder) -> Result<Response, CloudflareError> {
+        let request = request.build().map_err(|e| CloudflareError::HttpError(e))?;
+        telemetry::in_request(async move {
+            if let Some(host) = request.url().host_str() {
+                self.domain_registry.wait_until_settled(host).await;
+            }
+            
+            self.send_request(request).await
+        })
+        .await
+    }
+    
+    /// Request a known-protected domain to solve its challenge or keep its clearance fresh
+    pub(crate) async fn prewarm_request(&self, url: &str) -> Result<(), CloudflareError> {
+        let request = self.create_request(Method::GET, url).await?
+            .build()
+            .map_err(|e| CloudflareError::HttpError(e))?;
+        let response = telemetry::in_request(async { self.send_request(request).await }).await?;
+        
+        if response.status().is_success() || response.status().is_redirection() {
+            Ok(())
+        } else {
+            Err(CloudflareError::Other(format!("{} answered {}", url, response.status())))
+        }
+    }
+    
+    /// Send a built request, handling Cloudflare challenges
+    #[instrument(
+        skip(self, request),
+        fields(
+            { FIELD_REQUEST_ID } = %telemetry::current_request_id().unwrap_or_default(),
+            { FIELD_DOMAIN } = tracing::field::Empty,
+        )
+    )]
+    async fn send_request(&self, request: Request) -> Result<Response, CloudflareError> {
         let mut retries = 0;
         let max_retries = self.config.max_retries;
         
         // Get the URL from the request
-        let request = request.build().map_err(|e| CloudflareError::HttpError(e))?;
         let url = request.url().to_string();
         let host = request.url().host_str().unwrap_or("");
+        tracing::Span::current().record(FIELD_DOMAIN, host);
+        
+        // Keep a copy to send again with a new identity after a challenge loop
+        let original_request = request.try_clone();
         
         // Create a new request each time as we can't reuse the original
         let mut current_request = request;
         
+        // Pick up the clearance other workers shared for the host
+        if let Some(store) = &self.session_store {
+            let shared_host = current_request.url().host_str().unwrap_or_default().to_string();
+            self.load_shared_session(store.as_ref(), &shared_host, &mut current_request).await?;
+        }
+        
+        // Token of the challenge lock while this worker solves the challenge
+        let mut challenge_lock: Option<String> = None;
+        
         loop {
-            // Send the request
-            let response = self.client.execute(current_request.try_clone().unwrap())
-                .await
-                .map_err(|e| CloudflareError::HttpError(e))?;
+            // Pace the host across the workers sharing the session store
+            let rate_limit_key = current_request.url().host_str().unwrap_or_default().to_string();
+            if let (Some(store), Some(interval)) = (&self.session_store, self.shared_interval()) {
+                let wait = store.reserve_slot(&rate_limit_key, interval).await?;
+                if !wait.is_zero() {
+                    sleep(wait).await;
+                }
+            }
+            
+            // Wait for the rate limit of the host
+            self.rate_limiter.acquire(&rate_limit_key, 1).await
+                .map_err(|e| CloudflareError::RateLimited(e.to_string()))?;
+            
+            // Send the request with the identity of the host, if it switched to one
+            let transport = match self.challenge_loops.identity(&rate_limit_key) {
+                Some(identity) => {
+                    identity.apply(current_request.headers_mut());
+                    self.challenge_loops.transport(&rate_limit_key).unwrap_or_else(|| self.transport.clone())
+                }
+                None => self.transport.clone(),
+            };
+            let response = transport.execute(current_request.try_clone().unwrap()).await?;
+            self.rate_limiter.update_from_headers(&rate_limit_key, &RateLimitHeaders::from_header_map(response.headers()));
             
             // Check response status
             let status = response.status();
@@ -249,11 +543,53 @@ impl CloudflareClient {
             if is_cloudflare_challenge(&response) {
                 info!("Cloudflare challenge detected for {}", url);
                 
+                // A challenge re-issued right after solving one will not clear with this identity
+                let user_agent = current_request.headers().get(USER_AGENT).and_then(|value| value.to_str().ok());
+                match self.challenge_loops.challenged(&rate_limit_key, user_agent, Instant::now()) {
+                    LoopCheck::None => {}
+                    LoopCheck::Exhausted(rotations) => {
+                        error!(rotations, "Challenge loop for {} persists after rotating identities", url);
+                        return Err(CloudflareError::ChallengeLoop(format!(
+                            "Challenge for {} re-issued after {} identity rotations",
+                            url, rotations
+                        )));
+                    }
+                    LoopCheck::Rotate(identity) => {
+                        warn!(identity = %identity.name, "Challenge loop detected for {}, rotating identity", url);
+                        self.rotate_identity(&rate_limit_key, &identity)?;
+                        
+                        current_request = match original_request.as_ref().and_then(Request::try_clone) {
+                            Some(request) => request,
+                            None => self.create_request(Method::GET, &url).await?
+                                .build()
+                                .map_err(|e| CloudflareError::HttpError(e))?,
+                        };
+                        
+                        // The new identity gets its own attempts at the challenge
+                        retries = 0;
+                        continue;
+                    }
+                }
+                
                 if retries >= max_retries {
                     error!("Max retries reached for {}", url);
                     return Err(CloudflareError::ChallengeDetected(format!("Max retries reached for {}", url)));
                 }
                 
+                // Let one worker at a time solve the challenge, the others reuse its clearance
+                if let (Some(store), None) = (&self.session_store, &challenge_lock) {
+                    let stale = self.cookies.lock().unwrap().get(CLEARANCE_COOKIE).cloned();
+                    match self.claim_challenge(store.as_ref(), &rate_limit_key, stale).await? {
+                        Some(token) => challenge_lock = Some(token),
+                        None => {
+                            info!("Reusing the clearance another worker obtained for {}", url);
+                            self.load_shared_session(store.as_ref(), &rate_limit_key, &mut current_request).await?;
+                            retries += 1;
+                            continue;
+                        }
+                    }
+                }
+                
                 // Extract challenge
                 let challenge = extract_challenge(&response)?;
                 
@@ -271,6 +607,7 @@ impl CloudflareClient {
                 for (name, value) in &solution.cookies {
                     self.set_cookie(name, value);
                 }
+                self.challenge_loops.solved(&rate_limit_key, Instant::now());
                 
                 // Wait a bit to not trigger anti-bot measures
                 sleep(Duration::from_millis(1000 + rand::random::<u64>() % 1000)).await;
@@ -302,10 +639,24 @@ impl CloudflareClient {
             
             // Extract cookies from the response
             let response_cookies = get_cookies_from_response(&response);
+            let new_clearance = response_cookies.contains_key(CLEARANCE_COOKIE);
             for (name, value) in response_cookies {
                 self.set_cookie(&name, &value);
             }
             
+            // The host let the request through
+            self.challenge_loops.cleared(&rate_limit_key);
+            
+            // Share the clearance, then let other workers at the challenge again
+            if let Some(store) = &self.session_store {
+                if new_clearance || challenge_lock.is_some() {
+                    self.save_shared_session(store.as_ref(), &rate_limit_key, &current_request).await?;
+                }
+                if let Some(token) = challenge_lock.take() {
+                    store.unlock(&rate_limit_key, &token).await?;
+                }
+            }
+            
             // Check for success
             if status.is_success() {
                 return Ok(response);
@@ -360,4 +711,4 @@ impl CloudflareClient {
     pub fn create_session(&self) -> Session {
         Session::new(self.config.clone(), self.cookies.lock().unwrap().clone())
     }
-} 
\ No newline at end of file
+}
\ No newline at end of file
@@ -1,4 +1,5 @@
 use crate::CloudflareError;
+use llama_moonlight_core::{Cookie, SameSite, StorageState};
 use reqwest::Response;
 use regex::Regex;
 use std::collections::HashMap;
@@ -130,4 +131,73 @@ pub fn load_cookies_from_file(file_path: &str) -> Result<HashMap<String, String>
         .map_err(|e| CloudflareError::CookieError(format!("Failed to parse cookies: {}", e)))?;
     
     Ok(cookies)
-} 
\ No newline at end of file
+}
+
+/// Export cookies as a browser storage state for a domain
+///
+/// The result can be passed to `BrowserContext::set_storage_state` or saved
+/// with `StorageState::save`. Cookies are exported as session cookies valid
+/// for the whole domain and its subdomains.
+pub fn to_storage_state(cookies: &HashMap<String, String>, domain: &str) -> StorageState {
+    let domain = format!(".{}", domain.trim_start_matches('.'));
+    
+    let mut cookies: Vec<Cookie> = cookies.iter()
+        .map(|(name, value)| {
+            let cloudflare = name == "cf_clearance" || name.starts_with("__cf");
+            
+            Cookie {
+                name: name.clone(),
+                value: value.clone(),
+                domain: domain.clone(),
+                path: "/".to_string(),
+                expires: Some(-1.0),
+                http_only: Some(cloudflare),
+                secure: Some(true),
+                same_site: Some(if cloudflare { SameSite::None } else { SameSite::Lax }),
+            }
+        })
+        .collect();
+    cookies.sort_by(|a, b| a.name.cmp(&b.name));
+    
+    StorageState {
+        cookies,
+        origins: Vec::new(),
+    }
+}
+
+/// Import cookies from a browser storage state
+///
+/// With a host, only the unexpired cookies sent to that host are imported;
+/// without one, all unexpired cookies are.
+pub fn from_storage_state(state: &StorageState, host: Option<&str>) -> HashMap<String, String> {
+    let cookies = match host {
+        Some(host) => state.cookies_for_host(host),
+        None => state.unexpired_cookies(),
+    };
+    
+    cookies.into_iter()
+        .map(|cookie| (cookie.name.clone(), cookie.value.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_storage_state_round_trip() {
+        let mut cookies = HashMap::new();
+        cookies.insert("cf_clearance".to_string(), "abc".to_string());
+        cookies.insert("session".to_string(), "123".to_string());
+        
+        let state = to_storage_state(&cookies, "example.com");
+        assert_eq!(state.cookies.len(), 2);
+        assert_eq!(state.cookies[0].domain, ".example.com");
+        assert_eq!(state.cookies[0].http_only, Some(true));
+        assert_eq!(state.cookies[1].http_only, Some(false));
+        
+        assert_eq!(from_storage_state(&state, Some("www.example.com")), cookies);
+        assert!(from_storage_state(&state, Some("other.org")).is_empty());
+        assert_eq!(from_storage_state(&state, None), cookies);
+    }
+}
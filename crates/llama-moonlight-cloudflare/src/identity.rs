@@ -0,0 +1,417 @@
+//! Identity rotation on challenge loops
+//!
+//! Cloudflare binds a clearance to the client that solved the challenge: its
+//! user agent, client hints, languages and TLS fingerprint. When it has
+//! flagged that identity, a solved challenge is re-issued right away and
+//! solving it again never helps. [`CloudflareClient`](crate::CloudflareClient)
+//! treats a challenge arriving within
+//! [`ChallengeLoopConfig::window_seconds`] of a solved one as such a loop:
+//! it drops the clearance cookie, switches the domain to the next
+//! [`Identity`] (headers and, with the HTTP/2 or curl-impersonate transports,
+//! the matching TLS profile) and solves the challenge again, up to
+//! [`ChallengeLoopConfig::max_rotations`] times before giving up. Counts of
+//! detected loops and rotations are kept in [`ChallengeLoopStats`].
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT_LANGUAGE, USER_AGENT};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::transport::{H2Browser, Transport, TransportConfig};
+
+/// Configuration of the identity rotation on challenge loops
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChallengeLoopConfig {
+    /// Whether to rotate the identity when a challenge loop is detected
+    pub enabled: bool,
+    
+    /// Seconds after solving a challenge during which a new challenge counts as a loop
+    pub window_seconds: u64,
+    
+    /// Identities tried for a domain before giving up on it
+    pub max_rotations: u32,
+    
+    /// Identities to rotate through, the bundled ones if empty
+    pub identities: Vec<Identity>,
+}
+
+impl Default for ChallengeLoopConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_seconds: 30,
+            max_rotations: 3,
+            identities: Vec::new(),
+        }
+    }
+}
+
+/// A consistent browser identity: headers and TLS profile of one browser
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Identity {
+    /// Name of the identity, for logs
+    pub name: String,
+    
+    /// User-Agent header
+    pub user_agent: String,
+    
+    /// Accept-Language header
+    pub accept_language: String,
+    
+    /// Client hint headers (`Sec-CH-UA*`), empty for browsers that send none
+    #[serde(default)]
+    pub client_hints: BTreeMap<String, String>,
+    
+    /// Browser whose HTTP/2 settings the HTTP/2 transport uses
+    pub browser: H2Browser,
+    
+    /// curl-impersonate binary reproducing the browser's TLS fingerprint
+    #[serde(default)]
+    pub curl_binary: Option<String>,
+}
+
+impl Identity {
+    /// Get the bundled identities, each matching a curl-impersonate target
+    pub fn bundled() -> Vec<Identity> {
+        vec![
+            Self::chromium(
+                "chrome_116_windows",
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/116.0.0.0 Safari/537.36",
+                "en-US,en;q=0.9",
+                "\"Chromium\";v=\"116\", \"Not)A;Brand\";v=\"24\", \"Google Chrome\";v=\"116\"",
+                "Windows",
+                "curl_chrome116",
+            ),
+            Self::chromium(
+                "chrome_116_macos",
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/116.0.0.0 Safari/537.36",
+                "en-GB,en;q=0.9",
+                "\"Chromium\";v=\"116\", \"Not)A;Brand\";v=\"24\", \"Google Chrome\";v=\"116\"",
+                "macOS",
+                "curl_chrome116",
+            ),
+            Self::chromium(
+                "edge_101_windows",
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/101.0.4951.64 Safari/537.36 Edg/101.0.1210.53",
+                "en-US,en;q=0.9",
+                "\" Not A;Brand\";v=\"99\", \"Chromium\";v=\"101\", \"Microsoft Edge\";v=\"101\"",
+                "Windows",
+                "curl_edge101",
+            ),
+            Identity {
+                name: "firefox_117_windows".to_string(),
+                user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:109.0) Gecko/20100101 Firefox/117.0".to_string(),
+                accept_language: "en-US,en;q=0.5".to_string(),
+                client_hints: BTreeMap::new(),
+                browser: H2Browser::Firefox,
+                curl_binary: Some("curl_ff117".to_string()),
+            },
+            Identity {
+                name: "safari_15_5_macos".to_string(),
+                user_agent: "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/15.5 Safari/605.1.15".to_string(),
+                accept_language: "en-US,en;q=0.9".to_string(),
+                client_hints: BTreeMap::new(),
+                browser: H2Browser::Safari,
+                curl_binary: Some("curl_safari15_5".to_string()),
+            },
+        ]
+    }
+    
+    /// Create a desktop Chromium identity
+    fn chromium(name: &str, user_agent: &str, accept_language: &str, brands: &str, platform: &str, curl_binary: &str) -> Self {
+        let client_hints = [
+            ("sec-ch-ua", brands.to_string()),
+            ("sec-ch-ua-mobile", "?0".to_string()),
+            ("sec-ch-ua-platform", format!("\"{}\"", platform)),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect();
+        
+        Identity {
+            name: name.to_string(),
+            user_agent: user_agent.to_string(),
+            accept_language: accept_language.to_string(),
+            client_hints,
+            browser: H2Browser::Chrome,
+            curl_binary: Some(curl_binary.to_string()),
+        }
+    }
+    
+    /// Replace the identifying headers of a request with this identity's
+    ///
+    /// Client hints of the previous identity are removed, so switching to a
+    /// browser that sends none leaves none behind.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        let stale: Vec<HeaderName> = headers.keys()
+            .filter(|name| name.as_str().starts_with("sec-ch-ua"))
+            .cloned()
+            .collect();
+        for name in stale {
+            headers.remove(name);
+        }
+        
+        if let Ok(value) = HeaderValue::from_str(&self.user_agent) {
+            headers.insert(USER_AGENT, value);
+        }
+        if let Ok(value) = HeaderValue::from_str(&self.accept_language) {
+            headers.insert(ACCEPT_LANGUAGE, value);
+        }
+        for (name, value) in &self.client_hints {
+            if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) {
+                headers.insert(name, value);
+            }
+        }
+    }
+    
+    /// Get the transport matching this identity's TLS profile
+    ///
+    /// Returns `None` when the configured transport cannot change its
+    /// fingerprint: reqwest always uses its own TLS stack, and
+    /// curl-impersonate needs a binary for the browser.
+    pub fn transport_config(&self, base: &TransportConfig) -> Option<TransportConfig> {
+        match base {
+            TransportConfig::Reqwest => None,
+            TransportConfig::Http2 { .. } => Some(TransportConfig::Http2 { profile: Some(self.browser) }),
+            TransportConfig::CurlImpersonate { .. } => self.curl_binary.as_ref()
+                .map(|binary| TransportConfig::CurlImpersonate { binary: binary.into() }),
+        }
+    }
+}
+
+/// Counts of the challenge loops met by a client
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChallengeLoopStats {
+    /// Challenges re-issued right after being solved
+    pub loops_detected: u64,
+    
+    /// Identity switches made to break a loop
+    pub rotations: u64,
+    
+    /// Loops given up on after running out of rotations
+    pub exhausted: u64,
+}
+
+/// Outcome of a challenge seen by the loop tracker
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum LoopCheck {
+    /// The challenge is not a loop
+    None,
+    
+    /// The challenge is a loop; solve it again with this identity
+    Rotate(Identity),
+    
+    /// The challenge is a loop and the domain used up its rotations
+    Exhausted(u32),
+}
+
+/// Loop state of a domain
+#[derive(Debug, Default)]
+struct HostState {
+    /// When the latest challenge was solved
+    solved_at: Option<Instant>,
+    
+    /// Rotations since the domain last answered without a challenge
+    rotations: u32,
+    
+    /// Index of the identity in use
+    identity: Option<usize>,
+    
+    /// Transport matching the identity in use
+    transport: Option<Arc<dyn Transport>>,
+}
+
+/// Loop state of all domains
+#[derive(Debug, Default)]
+struct TrackerState {
+    hosts: HashMap<String, HostState>,
+    stats: ChallengeLoopStats,
+}
+
+/// Detects challenge loops and picks the identity of each domain
+#[derive(Debug)]
+pub(crate) struct LoopTracker {
+    enabled: bool,
+    window: Duration,
+    max_rotations: u32,
+    identities: Vec<Identity>,
+    state: Mutex<TrackerState>,
+}
+
+impl LoopTracker {
+    /// Create a tracker from a configuration
+    pub(crate) fn new(config: &ChallengeLoopConfig) -> Self {
+        let identities = if config.identities.is_empty() {
+            Identity::bundled()
+        } else {
+            config.identities.clone()
+        };
+        
+        Self {
+            enabled: config.enabled,
+            window: Duration::from_secs(config.window_seconds),
+            max_rotations: config.max_rotations,
+            identities,
+            state: Mutex::new(TrackerState::default()),
+        }
+    }
+    
+    /// Get the identity a domain switched to, if any
+    pub(crate) fn identity(&self, host: &str) -> Option<Identity> {
+        let state = self.state.lock().unwrap();
+        let index = state.hosts.get(host)?.identity?;
+        Some(self.identities[index].clone())
+    }
+    
+    /// Get the transport of the identity a domain switched to, if any
+    pub(crate) fn transport(&self, host: &str) -> Option<Arc<dyn Transport>> {
+        self.state.lock().unwrap().hosts.get(host)?.transport.clone()
+    }
+    
+    /// Set the transport of the identity a domain switched to
+    pub(crate) fn set_transport(&self, host: &str, transport: Arc<dyn Transport>) {
+        self.state.lock().unwrap().hosts.entry(host.to_string()).or_default().transport = Some(transport);
+    }
+    
+    /// Record that a challenge of a domain was solved
+    pub(crate) fn solved(&self, host: &str, now: Instant) {
+        if self.enabled {
+            self.state.lock().unwrap().hosts.entry(host.to_string()).or_default().solved_at = Some(now);
+        }
+    }
+    
+    /// Record that a domain answered without a challenge
+    pub(crate) fn cleared(&self, host: &str) {
+        if let Some(host_state) = self.state.lock().unwrap().hosts.get_mut(host) {
+            host_state.rotations = 0;
+        }
+    }
+    
+    /// Check whether a challenge of a domain is a loop, rotating its identity if so
+    ///
+    /// The next identity is one whose user agent differs from the one the
+    /// challenged request was sent with.
+    pub(crate) fn challenged(&self, host: &str, user_agent: Option<&str>, now: Instant) -> LoopCheck {
+        if !self.enabled || self.identities.is_empty() {
+            return LoopCheck::None;
+        }
+        
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        let host_state = state.hosts.entry(host.to_string()).or_default();
+        
+        let looped = host_state.solved_at.take()
+            .is_some_and(|solved_at| now.saturating_duration_since(solved_at) <= self.window);
+        if !looped {
+            return LoopCheck::None;
+        }
+        state.stats.loops_detected += 1;
+        
+        if host_state.rotations >= self.max_rotations {
+            state.stats.exhausted += 1;
+            return LoopCheck::Exhausted(host_state.rotations);
+        }
+        
+        let count = self.identities.len();
+        let start = host_state.identity.map_or(0, |index| index + 1);
+        let next = (0..count)
+            .map(|offset| (start + offset) % count)
+            .find(|&index| Some(self.identities[index].user_agent.as_str()) != user_agent)
+            .unwrap_or(start % count);
+        
+        host_state.identity = Some(next);
+        host_state.transport = None;
+        host_state.rotations += 1;
+        state.stats.rotations += 1;
+        
+        LoopCheck::Rotate(self.identities[next].clone())
+    }
+    
+    /// Get the counts of loops met so far
+    pub(crate) fn stats(&self) -> ChallengeLoopStats {
+        self.state.lock().unwrap().stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_apply_identity() {
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_static("curl/8.0"));
+        headers.insert("sec-ch-ua-platform", HeaderValue::from_static("\"Linux\""));
+        headers.insert("sec-ch-ua-arch", HeaderValue::from_static("\"x86\""));
+        
+        let identities = Identity::bundled();
+        identities[1].apply(&mut headers);
+        assert_eq!(headers[USER_AGENT], identities[1].user_agent.as_str());
+        assert_eq!(headers["sec-ch-ua-platform"], "\"macOS\"");
+        assert!(headers.get("sec-ch-ua-arch").is_none());
+        
+        let firefox = identities.iter().find(|identity| identity.browser == H2Browser::Firefox).unwrap();
+        firefox.apply(&mut headers);
+        assert_eq!(headers[ACCEPT_LANGUAGE], "en-US,en;q=0.5");
+        assert!(headers.keys().all(|name| !name.as_str().starts_with("sec-ch-ua")));
+        
+        assert_eq!(firefox.transport_config(&TransportConfig::Reqwest), None);
+        assert_eq!(
+            firefox.transport_config(&TransportConfig::Http2 { profile: None }),
+            Some(TransportConfig::Http2 { profile: Some(H2Browser::Firefox) }),
+        );
+        assert_eq!(
+            firefox.transport_config(&TransportConfig::CurlImpersonate { binary: "curl_chrome116".into() }),
+            Some(TransportConfig::CurlImpersonate { binary: "curl_ff117".into() }),
+        );
+    }
+    
+    #[test]
+    fn test_loop_rotation() {
+        let config = ChallengeLoopConfig {
+            max_rotations: 2,
+            ..Default::default()
+        };
+        let tracker = LoopTracker::new(&config);
+        let start = Instant::now();
+        let default_user_agent = Identity::bundled()[0].user_agent.clone();
+        
+        // A first challenge is not a loop
+        assert_eq!(tracker.challenged("example.com", None, start), LoopCheck::None);
+        
+        // A challenge right after solving one is, and skips the identity in use
+        tracker.solved("example.com", start);
+        let first = match tracker.challenged("example.com", Some(&default_user_agent), start + Duration::from_secs(2)) {
+            LoopCheck::Rotate(identity) => identity,
+            other => panic!("expected a rotation, got {:?}", other),
+        };
+        assert_ne!(first.user_agent, default_user_agent);
+        assert_eq!(tracker.identity("example.com"), Some(first.clone()));
+        assert_eq!(tracker.identity("other.org"), None);
+        
+        // Challenges long after solving are not loops
+        tracker.solved("example.com", start);
+        assert_eq!(tracker.challenged("example.com", None, start + Duration::from_secs(60)), LoopCheck::None);
+        
+        tracker.solved("example.com", start);
+        assert!(matches!(tracker.challenged("example.com", None, start + Duration::from_secs(1)), LoopCheck::Rotate(identity) if identity != first));
+        
+        tracker.solved("example.com", start);
+        assert_eq!(tracker.challenged("example.com", None, start + Duration::from_secs(1)), LoopCheck::Exhausted(2));
+        
+        // Answering without a challenge gives the domain its rotations back
+        tracker.cleared("example.com");
+        tracker.solved("example.com", start);
+        assert!(matches!(tracker.challenged("example.com", None, start + Duration::from_secs(1)), LoopCheck::Rotate(_)));
+        
+        assert_eq!(tracker.stats(), ChallengeLoopStats {
+            loops_detected: 4,
+            rotations: 3,
+            exhausted: 1,
+        });
+    }
+}
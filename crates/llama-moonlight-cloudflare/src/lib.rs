@@ -6,7 +6,7 @@
 
 use anyhow::Result;
 use lazy_static::lazy_static;
-use log::{debug, error, info, warn};
+use tracing::{debug, error, info, warn};
 use reqwest::{Client, Response, StatusCode};
 use serde::{Deserialize, Serialize};
 use std::{
@@ -15,25 +15,36 @@ use std::{
     time::{Duration, Instant},
 };
 use thiserror::Error;
+use llama_moonlight_config::{ConfigSection, Secret};
 use url::Url;
 
 pub mod challenge;
 pub mod cookie;
 pub mod fingerprint;
 pub mod headers;
+pub mod identity;
 pub mod javascript;
 pub mod proxy;
 pub mod tls;
+pub mod transport;
 pub mod useragent;
 pub mod captcha;
 pub mod util;
 pub mod solvers;
 pub mod client;
+pub mod prewarm;
 pub mod sessions;
+#[cfg(feature = "redis")]
+pub mod redis_sessions;
 
 pub use client::CloudflareClient;
 pub use challenge::{Challenge, ChallengeType, ChallengeSolution};
-pub use sessions::Session;
+pub use identity::{ChallengeLoopConfig, ChallengeLoopStats, Identity};
+pub use prewarm::{DomainRegistry, PrewarmConfig, PrewarmHandle, WarmStatus};
+pub use sessions::{DomainSession, MemorySessionStore, Session, SessionStore};
+#[cfg(feature = "redis")]
+pub use redis_sessions::{RedisSessionConfig, RedisSessionStore};
+pub use transport::{H2Browser, H2Profile, Transport, TransportConfig};
 
 /// Cloudflare bypass errors
 #[derive(Error, Debug)]
@@ -46,6 +57,10 @@ pub enum CloudflareError {
     #[error("Failed to solve Cloudflare challenge: {0}")]
     ChallengeSolvingFailed(String),
     
+    /// Error when solved challenges keep being re-issued
+    #[error("Cloudflare challenge loop: {0}")]
+    ChallengeLoop(String),
+    
     /// Error when handling cookies
     #[error("Cookie error: {0}")]
     CookieError(String),
@@ -87,8 +102,16 @@ pub enum CloudflareError {
     Other(String),
 }
 
+#[cfg(feature = "redis")]
+impl From<redis::RedisError> for CloudflareError {
+    fn from(e: redis::RedisError) -> Self {
+        CloudflareError::Other(format!("Redis error: {}", e))
+    }
+}
+
 /// Cloudflare bypass configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CloudflareConfig {
     /// User agent to use
     pub user_agent: Option<String>,
@@ -109,7 +132,7 @@ pub struct CloudflareConfig {
     pub solve_captchas: bool,
     
     /// CAPTCHA API key
-    pub captcha_api_key: Option<String>,
+    pub captcha_api_key: Option<Secret>,
     
     /// CAPTCHA provider
     pub captcha_provider: Option<String>,
@@ -131,6 +154,18 @@ pub struct CloudflareConfig {
     
     /// Whether to use Cloudflare clearance cookies
     pub use_clearance: bool,
+    
+    /// Transport backend sending the requests
+    pub transport: TransportConfig,
+    
+    /// Maximum number of requests per minute to each host
+    pub requests_per_minute: Option<u32>,
+    
+    /// Known-protected domains to solve challenges for ahead of time
+    pub prewarm: PrewarmConfig,
+    
+    /// Identity rotation when solved challenges keep being re-issued
+    pub challenge_loops: ChallengeLoopConfig,
 }
 
 impl Default for CloudflareConfig {
@@ -150,10 +185,18 @@ impl Default for CloudflareConfig {
             custom_headers: None,
             cookies: None,
             use_clearance: true,
+            transport: TransportConfig::Reqwest,
+            requests_per_minute: None,
+            prewarm: PrewarmConfig::default(),
+            challenge_loops: ChallengeLoopConfig::default(),
         }
     }
 }
 
+impl ConfigSection for CloudflareConfig {
+    const SECTION: &'static str = "cloudflare";
+}
+
 /// Check if a response is a Cloudflare challenge
 pub fn is_cloudflare_challenge(response: &Response) -> bool {
     // Check for common Cloudflare challenge signatures
@@ -0,0 +1,317 @@
+//! Proactive challenge solving for known-protected domains
+//!
+//! Domains listed in [`PrewarmConfig`] are known to sit behind Cloudflare.
+//! [`CloudflareClient::start_prewarm`](crate::CloudflareClient::start_prewarm)
+//! solves their challenges in the background as soon as the client starts,
+//! then keeps the clearance fresh with periodic keep-alive requests. Requests
+//! to a listed domain made while its first challenge is still being solved
+//! wait for it instead of solving a challenge of their own, so the first real
+//! request never pays the challenge latency twice.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::client::CloudflareClient;
+
+/// Name of the cookie Cloudflare sets once a challenge is solved
+pub const CLEARANCE_COOKIE: &str = "cf_clearance";
+
+/// Longest wait before retrying a domain whose challenge failed
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+/// Configuration of the domains to pre-warm
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrewarmConfig {
+    /// URLs of known-protected domains, requested to solve their challenges
+    pub urls: Vec<String>,
+    
+    /// Seconds between keep-alive requests to each domain
+    pub keepalive_seconds: u64,
+    
+    /// Longest time a request waits for the first challenge of its domain, in seconds
+    pub wait_seconds: u64,
+}
+
+impl Default for PrewarmConfig {
+    fn default() -> Self {
+        Self {
+            urls: Vec::new(),
+            keepalive_seconds: 900,
+            wait_seconds: 30,
+        }
+    }
+}
+
+/// State of a pre-warmed domain
+#[derive(Debug, Clone, PartialEq)]
+pub enum WarmStatus {
+    /// The first challenge is being solved
+    Pending,
+    
+    /// The domain answered the latest keep-alive request
+    Warm {
+        /// When the domain last answered
+        refreshed_at: Instant,
+        
+        /// Whether a clearance cookie is held
+        has_clearance: bool,
+    },
+    
+    /// The latest keep-alive request failed
+    Failed {
+        /// Error of the latest attempt
+        error: String,
+        
+        /// Attempts failed in a row
+        failures: u32,
+    },
+}
+
+/// Registry of the known-protected domains and their state
+#[derive(Debug)]
+pub struct DomainRegistry {
+    /// URL requested for each domain
+    urls: HashMap<String, String>,
+    
+    /// State of each domain, by host
+    states: Mutex<HashMap<String, watch::Sender<WarmStatus>>>,
+    
+    /// Seconds between keep-alive requests
+    keepalive: Duration,
+    
+    /// Longest time a request waits for the first challenge of its domain
+    wait: Duration,
+}
+
+impl DomainRegistry {
+    /// Create a registry of the domains of a configuration
+    pub fn new(config: &PrewarmConfig) -> Self {
+        let mut registry = Self {
+            urls: HashMap::new(),
+            states: Mutex::new(HashMap::new()),
+            keepalive: Duration::from_secs(config.keepalive_seconds.max(1)),
+            wait: Duration::from_secs(config.wait_seconds),
+        };
+        for url in &config.urls {
+            registry.register(url);
+        }
+        registry
+    }
+    
+    /// Register the domain of a URL as known-protected
+    ///
+    /// URLs without a host are ignored.
+    pub fn register(&mut self, url: &str) {
+        match host_of(url) {
+            Some(host) => {
+                self.urls.insert(host, url.to_string());
+            }
+            None => warn!("Ignoring protected domain URL without a host: {}", url),
+        }
+    }
+    
+    /// Get whether a host is known to be protected
+    pub fn is_protected(&self, host: &str) -> bool {
+        self.urls.contains_key(&host.to_lowercase())
+    }
+    
+    /// Get the state of a host, if it is being pre-warmed
+    pub fn status(&self, host: &str) -> Option<WarmStatus> {
+        let states = self.states.lock().unwrap();
+        states.get(&host.to_lowercase()).map(|state| state.borrow().clone())
+    }
+    
+    /// Get the hosts and URLs of the registered domains
+    pub fn domains(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.urls.iter().map(|(host, url)| (host.as_str(), url.as_str()))
+    }
+    
+    /// Mark a host as having its first challenge solved in the background
+    fn start(&self, host: &str) {
+        let (state, _) = watch::channel(WarmStatus::Pending);
+        self.states.lock().unwrap().insert(host.to_string(), state);
+    }
+    
+    /// Record the outcome of a request warming a host
+    fn update(&self, host: &str, status: WarmStatus) {
+        if let Some(state) = self.states.lock().unwrap().get(host) {
+            state.send_replace(status);
+        }
+    }
+    
+    /// Wait until the first challenge of a host is settled, if it is being solved
+    ///
+    /// Gives up after the configured wait, letting the request solve its own
+    /// challenge.
+    pub async fn wait_until_settled(&self, host: &str) {
+        let receiver = self.states.lock().unwrap().get(&host.to_lowercase()).map(|state| state.subscribe());
+        let mut receiver = match receiver {
+            Some(receiver) => receiver,
+            None => return,
+        };
+        
+        let settled = receiver.wait_for(|status| *status != WarmStatus::Pending);
+        if tokio::time::timeout(self.wait, settled).await.is_err() {
+            debug!("Stopped waiting for the challenge of {} after {:?}", host, self.wait);
+        }
+    }
+}
+
+/// Background tasks keeping the registered domains warm
+///
+/// Dropping the handle stops the tasks.
+#[derive(Debug)]
+pub struct PrewarmHandle {
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl PrewarmHandle {
+    /// Get the number of domains kept warm
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+    
+    /// Get whether no domain is kept warm
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+    
+    /// Stop keeping the domains warm
+    pub fn stop(self) {}
+}
+
+impl Drop for PrewarmHandle {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Start a task per registered domain, solving its challenge and keeping it warm
+pub(crate) fn start(client: &Arc<CloudflareClient>) -> PrewarmHandle {
+    let registry = client.domain_registry();
+    let tasks = registry
+        .domains()
+        .map(|(host, url)| {
+            registry.start(host);
+            tokio::spawn(keep_warm(client.clone(), host.to_string(), url.to_string()))
+        })
+        .collect::<Vec<_>>();
+    
+    info!("Pre-warming {} protected domains", tasks.len());
+    PrewarmHandle { tasks }
+}
+
+/// Request a domain periodically, solving challenges as they come
+async fn keep_warm(client: Arc<CloudflareClient>, host: String, url: String) {
+    let registry = client.domain_registry();
+    let mut failures = 0;
+    
+    loop {
+        let status = match client.prewarm_request(&url).await {
+            Ok(()) => {
+                failures = 0;
+                let has_clearance = client.cookies().contains_key(CLEARANCE_COOKIE);
+                debug!("Refreshed {} (clearance: {})", host, has_clearance);
+                WarmStatus::Warm {
+                    refreshed_at: Instant::now(),
+                    has_clearance,
+                }
+            }
+            Err(e) => {
+                failures += 1;
+                warn!("Failed to pre-warm {} ({} in a row): {}", host, failures, e);
+                WarmStatus::Failed {
+                    error: e.to_string(),
+                    failures,
+                }
+            }
+        };
+        registry.update(&host, status);
+        
+        tokio::time::sleep(next_delay(registry.keepalive, failures)).await;
+    }
+}
+
+/// Get the delay before the next request to a domain
+///
+/// Keep-alive requests are spread by up to a tenth of the interval so they do
+/// not line up; failed domains are retried sooner, backing off exponentially.
+fn next_delay(keepalive: Duration, failures: u32) -> Duration {
+    if failures > 0 {
+        let backoff = Duration::from_secs(5).saturating_mul(1 << (failures - 1).min(16));
+        return backoff.min(MAX_RETRY_DELAY).min(keepalive);
+    }
+    
+    let jitter = keepalive.mul_f64(rand::random::<f64>() * 0.1);
+    keepalive - keepalive / 20 + jitter
+}
+
+/// Get the lowercase host of a URL
+fn host_of(url: &str) -> Option<String> {
+    Url::parse(url).ok()?.host_str().map(str::to_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_registry() {
+        let config = PrewarmConfig {
+            urls: vec!["https://Example.com/".to_string(), "not a url".to_string()],
+            ..Default::default()
+        };
+        let registry = DomainRegistry::new(&config);
+        assert!(registry.is_protected("example.com"));
+        assert_eq!(registry.domains().count(), 1);
+        assert_eq!(registry.status("example.com"), None);
+    }
+    
+    #[tokio::test]
+    async fn test_wait_until_settled() {
+        let registry = Arc::new(DomainRegistry::new(&PrewarmConfig {
+            urls: vec!["https://example.com/".to_string()],
+            ..Default::default()
+        }));
+        
+        // Hosts not being pre-warmed never wait
+        registry.wait_until_settled("other.org").await;
+        
+        registry.start("example.com");
+        assert_eq!(registry.status("example.com"), Some(WarmStatus::Pending));
+        
+        let waiter = registry.clone();
+        let wait = tokio::spawn(async move { waiter.wait_until_settled("example.com").await });
+        let status = WarmStatus::Warm {
+            refreshed_at: Instant::now(),
+            has_clearance: true,
+        };
+        registry.update("example.com", status.clone());
+        wait.await.unwrap();
+        assert_eq!(registry.status("example.com"), Some(status));
+    }
+    
+    #[test]
+    fn test_next_delay() {
+        let keepalive = Duration::from_secs(900);
+        for _ in 0..20 {
+            let delay = next_delay(keepalive, 0);
+            assert!(delay >= Duration::from_secs(855) && delay <= Duration::from_secs(945));
+        }
+        assert_eq!(next_delay(keepalive, 1), Duration::from_secs(5));
+        assert_eq!(next_delay(keepalive, 3), Duration::from_secs(20));
+        assert_eq!(next_delay(keepalive, 30), MAX_RETRY_DELAY);
+        assert_eq!(next_delay(Duration::from_secs(60), 30), Duration::from_secs(60));
+    }
+}
@@ -0,0 +1,154 @@
+//! Redis session store
+//!
+//! Shares clearance cookies, request pacing and challenge locks between the
+//! workers of a fleet. Keys live under a configurable prefix:
+//!
+//! - `<prefix>:session:<domain>` holds the shared cookies as JSON, expiring
+//!   with the clearance
+//! - `<prefix>:pace:<domain>` holds the next free request slot, in
+//!   milliseconds of the Redis server clock so that workers on hosts with
+//!   skewed clocks agree
+//! - `<prefix>:lock:<domain>` holds the token of the worker solving the
+//!   domain's challenge, taken with `SET NX PX` so it expires if the worker dies
+
+use crate::sessions::{DomainSession, SessionStore};
+use crate::CloudflareError;
+use async_trait::async_trait;
+use redis::aio::MultiplexedConnection;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::info;
+
+/// Reserves the next request slot, returning the milliseconds to wait for it
+const RESERVE_SLOT_SCRIPT: &str = r#"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local slot = math.max(now, tonumber(redis.call('GET', KEYS[1]) or '0'))
+local interval = tonumber(ARGV[1])
+redis.call('SET', KEYS[1], slot + interval, 'PX', slot - now + interval + 60000)
+return slot - now
+"#;
+
+/// Deletes a lock only if the token still holds it
+const UNLOCK_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+/// Configuration of the Redis session store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedisSessionConfig {
+    /// Redis URL, e.g. `redis://localhost:6379`
+    pub url: String,
+
+    /// Prefix of every key used by the store
+    pub key_prefix: String,
+}
+
+impl Default for RedisSessionConfig {
+    fn default() -> Self {
+        Self {
+            url: "redis://localhost:6379".to_string(),
+            key_prefix: "llama:cloudflare".to_string(),
+        }
+    }
+}
+
+/// Session store shared through Redis
+pub struct RedisSessionStore {
+    conn: MultiplexedConnection,
+    config: RedisSessionConfig,
+}
+
+impl RedisSessionStore {
+    /// Connect to Redis
+    pub async fn connect(config: RedisSessionConfig) -> Result<Self, CloudflareError> {
+        let client = redis::Client::open(config.url.as_str())?;
+        let conn = client.get_multiplexed_tokio_connection().await?;
+        info!("Connected to Redis session store {} at {}", config.key_prefix, config.url);
+        Ok(Self { conn, config })
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &RedisSessionConfig {
+        &self.config
+    }
+
+    fn session_key(&self, domain: &str) -> String {
+        format!("{}:session:{}", self.config.key_prefix, domain)
+    }
+
+    fn pace_key(&self, domain: &str) -> String {
+        format!("{}:pace:{}", self.config.key_prefix, domain)
+    }
+
+    fn lock_key(&self, domain: &str) -> String {
+        format!("{}:lock:{}", self.config.key_prefix, domain)
+    }
+}
+
+/// Convert a duration to milliseconds for Redis, at least 1
+fn millis(duration: Duration) -> u64 {
+    (duration.as_millis() as u64).max(1)
+}
+
+#[async_trait]
+impl SessionStore for RedisSessionStore {
+    async fn load(&self, domain: &str) -> Result<Option<DomainSession>, CloudflareError> {
+        let payload: Option<String> = self.conn.clone().get(self.session_key(domain)).await?;
+        payload
+            .map(|payload| serde_json::from_str(&payload))
+            .transpose()
+            .map_err(|e| CloudflareError::Other(format!("Failed to deserialize shared session: {}", e)))
+    }
+
+    async fn save(&self, domain: &str, session: &DomainSession, ttl: Duration) -> Result<(), CloudflareError> {
+        let payload = serde_json::to_string(session)
+            .map_err(|e| CloudflareError::Other(format!("Failed to serialize shared session: {}", e)))?;
+        let _: () = redis::cmd("SET")
+            .arg(self.session_key(domain))
+            .arg(payload)
+            .arg("PX")
+            .arg(millis(ttl))
+            .query_async(&mut self.conn.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn reserve_slot(&self, domain: &str, interval: Duration) -> Result<Duration, CloudflareError> {
+        let wait: u64 = redis::Script::new(RESERVE_SLOT_SCRIPT)
+            .key(self.pace_key(domain))
+            .arg(interval.as_millis() as u64)
+            .invoke_async(&mut self.conn.clone())
+            .await?;
+        Ok(Duration::from_millis(wait))
+    }
+
+    async fn try_lock(&self, domain: &str, token: &str, ttl: Duration) -> Result<bool, CloudflareError> {
+        let reply: Option<String> = redis::cmd("SET")
+            .arg(self.lock_key(domain))
+            .arg(token)
+            .arg("NX")
+            .arg("PX")
+            .arg(millis(ttl))
+            .query_async(&mut self.conn.clone())
+            .await?;
+        Ok(reply.is_some())
+    }
+
+    async fn unlock(&self, domain: &str, token: &str) -> Result<(), CloudflareError> {
+        let _: i64 = redis::Script::new(UNLOCK_SCRIPT)
+            .key(self.lock_key(domain))
+            .arg(token)
+            .invoke_async(&mut self.conn.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn is_locked(&self, domain: &str) -> Result<bool, CloudflareError> {
+        Ok(self.conn.clone().exists(self.lock_key(domain)).await?)
+    }
+}
@@ -1,4 +1,15 @@
+//! Sessions and the state shared between scraper processes
+//!
+//! A [`Session`] carries the cookies and headers of one client. A
+//! [`SessionStore`] shares state per domain between clients, possibly in
+//! different processes: the clearance cookies of a solved challenge, the
+//! pacing of requests, and a lock so that only one worker at a time solves a
+//! domain's challenge while the others wait for its cookies.
+//! [`MemorySessionStore`] shares within a process; the `redis` feature adds a
+//! store shared by a whole worker fleet.
+
 use crate::{CloudflareConfig, CloudflareError};
+use async_trait::async_trait;
 use reqwest::{Client, ClientBuilder, Method, RequestBuilder, Response};
 use serde::{Deserialize, Serialize};
 use std::{
@@ -8,6 +19,112 @@ use std::{
     time::{Duration, Instant},
 };
 
+/// How long shared clearance cookies are kept
+pub const CLEARANCE_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// How long a challenge lock is held if its worker never releases it
+pub const CHALLENGE_LOCK_TTL: Duration = Duration::from_secs(60);
+
+/// Cookies of a domain shared between workers
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DomainSession {
+    /// Cookies set by the domain, including its clearance cookie
+    pub cookies: HashMap<String, String>,
+    
+    /// User agent the clearance was issued to
+    pub user_agent: Option<String>,
+}
+
+/// State shared per domain between clients
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    /// Load the shared cookies of a domain
+    async fn load(&self, domain: &str) -> Result<Option<DomainSession>, CloudflareError>;
+    
+    /// Share the cookies of a domain for `ttl`
+    async fn save(&self, domain: &str, session: &DomainSession, ttl: Duration) -> Result<(), CloudflareError>;
+    
+    /// Reserve the next request slot of a domain, `interval` after the previous one
+    ///
+    /// Returns how long to wait before sending the request.
+    async fn reserve_slot(&self, domain: &str, interval: Duration) -> Result<Duration, CloudflareError>;
+    
+    /// Try to take the challenge lock of a domain, held by `token` for at most `ttl`
+    async fn try_lock(&self, domain: &str, token: &str, ttl: Duration) -> Result<bool, CloudflareError>;
+    
+    /// Release the challenge lock of a domain, if `token` still holds it
+    async fn unlock(&self, domain: &str, token: &str) -> Result<(), CloudflareError>;
+    
+    /// Check whether the challenge of a domain is locked
+    async fn is_locked(&self, domain: &str) -> Result<bool, CloudflareError>;
+}
+
+/// Session store shared by the clients of one process
+#[derive(Debug, Default)]
+pub struct MemorySessionStore {
+    /// Cookies by domain, with their expiry
+    sessions: Mutex<HashMap<String, (DomainSession, Instant)>>,
+    
+    /// Next free request slot by domain
+    slots: Mutex<HashMap<String, Instant>>,
+    
+    /// Challenge lock holders by domain, with their expiry
+    locks: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl MemorySessionStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SessionStore for MemorySessionStore {
+    async fn load(&self, domain: &str) -> Result<Option<DomainSession>, CloudflareError> {
+        let sessions = self.sessions.lock().unwrap();
+        Ok(sessions.get(domain)
+            .filter(|(_, expires)| *expires > Instant::now())
+            .map(|(session, _)| session.clone()))
+    }
+    
+    async fn save(&self, domain: &str, session: &DomainSession, ttl: Duration) -> Result<(), CloudflareError> {
+        self.sessions.lock().unwrap().insert(domain.to_string(), (session.clone(), Instant::now() + ttl));
+        Ok(())
+    }
+    
+    async fn reserve_slot(&self, domain: &str, interval: Duration) -> Result<Duration, CloudflareError> {
+        let now = Instant::now();
+        let mut slots = self.slots.lock().unwrap();
+        let slot = slots.get(domain).copied().unwrap_or(now).max(now);
+        slots.insert(domain.to_string(), slot + interval);
+        Ok(slot - now)
+    }
+    
+    async fn try_lock(&self, domain: &str, token: &str, ttl: Duration) -> Result<bool, CloudflareError> {
+        let now = Instant::now();
+        let mut locks = self.locks.lock().unwrap();
+        if locks.get(domain).map_or(false, |(_, expires)| *expires > now) {
+            return Ok(false);
+        }
+        locks.insert(domain.to_string(), (token.to_string(), now + ttl));
+        Ok(true)
+    }
+    
+    async fn unlock(&self, domain: &str, token: &str) -> Result<(), CloudflareError> {
+        let mut locks = self.locks.lock().unwrap();
+        if locks.get(domain).map_or(false, |(holder, _)| holder == token) {
+            locks.remove(domain);
+        }
+        Ok(())
+    }
+    
+    async fn is_locked(&self, domain: &str) -> Result<bool, CloudflareError> {
+        let locks = self.locks.lock().unwrap();
+        Ok(locks.get(domain).map_or(false, |(_, expires)| *expires > Instant::now()))
+    }
+}
+
 /// A persistent session for making requests
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
@@ -393,4 +510,47 @@ fn parse_cookie(cookie_str: &str) -> Option<(String, String)> {
     }
     
     Some((name_value[0].trim().to_string(), name_value[1..].join("=").to_string()))
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_memory_store_lock() {
+        let store = MemorySessionStore::new();
+        
+        assert!(store.try_lock("example.com", "a", CHALLENGE_LOCK_TTL).await.unwrap());
+        assert!(!store.try_lock("example.com", "b", CHALLENGE_LOCK_TTL).await.unwrap());
+        assert!(store.try_lock("other.com", "b", CHALLENGE_LOCK_TTL).await.unwrap());
+        
+        // Only the holder releases the lock
+        store.unlock("example.com", "b").await.unwrap();
+        assert!(store.is_locked("example.com").await.unwrap());
+        store.unlock("example.com", "a").await.unwrap();
+        assert!(!store.is_locked("example.com").await.unwrap());
+        
+        // An abandoned lock expires
+        assert!(store.try_lock("example.com", "c", Duration::ZERO).await.unwrap());
+        assert!(store.try_lock("example.com", "d", CHALLENGE_LOCK_TTL).await.unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_memory_store_sessions_and_slots() {
+        let store = MemorySessionStore::new();
+        let session = DomainSession {
+            cookies: HashMap::from([("cf_clearance".to_string(), "token".to_string())]),
+            user_agent: Some("agent".to_string()),
+        };
+        
+        store.save("example.com", &session, CLEARANCE_TTL).await.unwrap();
+        assert_eq!(store.load("example.com").await.unwrap(), Some(session));
+        assert_eq!(store.load("other.com").await.unwrap(), None);
+        
+        let interval = Duration::from_secs(10);
+        assert!(store.reserve_slot("example.com", interval).await.unwrap().is_zero());
+        let wait = store.reserve_slot("example.com", interval).await.unwrap();
+        assert!(wait > Duration::from_secs(9) && wait <= interval);
+        assert!(store.reserve_slot("other.com", interval).await.unwrap().is_zero());
+    }
+}
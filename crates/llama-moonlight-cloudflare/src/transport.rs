@@ -0,0 +1,490 @@
+//! HTTP transport backends
+//!
+//! Cloudflare fingerprints clients below the headers: the ALPN protocols
+//! offered in the TLS handshake, the HTTP/2 SETTINGS frame, the initial
+//! connection window and the order of pseudo-headers all differ between
+//! browsers, and reqwest's defaults match none of them. The transport used by
+//! [`CloudflareClient`](crate::CloudflareClient) is chosen with
+//! [`CloudflareConfig::transport`](crate::CloudflareConfig::transport):
+//!
+//! - [`TransportConfig::Reqwest`]: the default reqwest client
+//! - [`TransportConfig::Http2`]: hyper over rustls, negotiating HTTP/2 with
+//!   the window and frame sizes of an [`H2Profile`] (requires the `h2`
+//!   feature). hyper does not expose SETTINGS order or pseudo-header order, so
+//!   this narrows the gap rather than closing it
+//! - [`TransportConfig::CurlImpersonate`]: runs a
+//!   [curl-impersonate](https://github.com/lwthiker/curl-impersonate) binary,
+//!   which reproduces a browser's TLS and HTTP/2 fingerprint exactly
+
+use crate::CloudflareError;
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Transport backend configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TransportConfig {
+    /// reqwest with its default TLS and HTTP/2 settings
+    #[default]
+    Reqwest,
+    
+    /// hyper with browser-like HTTP/2 settings
+    Http2 {
+        /// Browser whose HTTP/2 settings to use, guessed from the user agent if unset
+        #[serde(default)]
+        profile: Option<H2Browser>,
+    },
+    
+    /// curl-impersonate binary
+    CurlImpersonate {
+        /// Path or name of the binary, e.g. `curl_chrome116`
+        binary: PathBuf,
+    },
+}
+
+/// Browser families with known HTTP/2 fingerprints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum H2Browser {
+    /// Chrome and other Chromium-based browsers
+    Chrome,
+    
+    /// Firefox
+    Firefox,
+    
+    /// Safari
+    Safari,
+}
+
+/// HTTP/2 connection parameters of a browser
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct H2Profile {
+    /// SETTINGS_HEADER_TABLE_SIZE
+    pub header_table_size: Option<u32>,
+    
+    /// SETTINGS_ENABLE_PUSH
+    pub enable_push: Option<bool>,
+    
+    /// SETTINGS_MAX_CONCURRENT_STREAMS
+    pub max_concurrent_streams: Option<u32>,
+    
+    /// SETTINGS_INITIAL_WINDOW_SIZE
+    pub initial_window_size: Option<u32>,
+    
+    /// SETTINGS_MAX_FRAME_SIZE
+    pub max_frame_size: Option<u32>,
+    
+    /// SETTINGS_MAX_HEADER_LIST_SIZE
+    pub max_header_list_size: Option<u32>,
+    
+    /// Increment of the WINDOW_UPDATE frame sent after SETTINGS
+    pub connection_window_update: u32,
+    
+    /// Order of the `:method`, `:authority`, `:scheme` and `:path` pseudo-headers
+    pub pseudo_header_order: String,
+    
+    /// ALPN protocols offered in the TLS handshake
+    pub alpn_protocols: Vec<String>,
+}
+
+/// Initial window of an HTTP/2 connection before any WINDOW_UPDATE
+#[cfg(feature = "h2")]
+const DEFAULT_CONNECTION_WINDOW: u32 = 65_535;
+
+impl H2Profile {
+    /// HTTP/2 parameters of Chrome
+    pub fn chrome() -> Self {
+        Self {
+            header_table_size: Some(65_536),
+            enable_push: Some(false),
+            max_concurrent_streams: None,
+            initial_window_size: Some(6_291_456),
+            max_frame_size: None,
+            max_header_list_size: Some(262_144),
+            connection_window_update: 15_663_105,
+            pseudo_header_order: "m,a,s,p".to_string(),
+            alpn_protocols: vec!["h2".to_string(), "http/1.1".to_string()],
+        }
+    }
+    
+    /// HTTP/2 parameters of Firefox
+    pub fn firefox() -> Self {
+        Self {
+            header_table_size: Some(65_536),
+            enable_push: None,
+            max_concurrent_streams: None,
+            initial_window_size: Some(131_072),
+            max_frame_size: Some(16_384),
+            max_header_list_size: None,
+            connection_window_update: 12_517_377,
+            pseudo_header_order: "m,p,a,s".to_string(),
+            alpn_protocols: vec!["h2".to_string(), "http/1.1".to_string()],
+        }
+    }
+    
+    /// HTTP/2 parameters of Safari
+    pub fn safari() -> Self {
+        Self {
+            header_table_size: None,
+            enable_push: Some(false),
+            max_concurrent_streams: Some(100),
+            initial_window_size: Some(2_097_152),
+            max_frame_size: None,
+            max_header_list_size: None,
+            connection_window_update: 10_485_760,
+            pseudo_header_order: "m,s,p,a".to_string(),
+            alpn_protocols: vec!["h2".to_string(), "http/1.1".to_string()],
+        }
+    }
+    
+    /// Get the parameters of a browser family
+    pub fn for_browser(browser: H2Browser) -> Self {
+        match browser {
+            H2Browser::Chrome => Self::chrome(),
+            H2Browser::Firefox => Self::firefox(),
+            H2Browser::Safari => Self::safari(),
+        }
+    }
+    
+    /// Get the parameters matching a user agent, defaulting to Chrome
+    pub fn for_user_agent(user_agent: &str) -> Self {
+        Self::for_browser(browser_of(user_agent))
+    }
+    
+    /// Get the Akamai HTTP/2 fingerprint string of these parameters
+    ///
+    /// Format: `SETTINGS|WINDOW_UPDATE|PRIORITY|PSEUDO_HEADER_ORDER`, as
+    /// reported by fingerprinting services.
+    pub fn akamai_fingerprint(&self) -> String {
+        let settings: Vec<String> = [
+            (1, self.header_table_size),
+            (2, self.enable_push.map(u32::from)),
+            (3, self.max_concurrent_streams),
+            (4, self.initial_window_size),
+            (5, self.max_frame_size),
+            (6, self.max_header_list_size),
+        ]
+        .iter()
+        .filter_map(|(id, value)| value.map(|value| format!("{}:{}", id, value)))
+        .collect();
+        
+        format!("{}|{}|0|{}", settings.join(";"), self.connection_window_update, self.pseudo_header_order)
+    }
+}
+
+/// Guess the browser family of a user agent
+fn browser_of(user_agent: &str) -> H2Browser {
+    if user_agent.contains("Firefox/") {
+        H2Browser::Firefox
+    } else if user_agent.contains("Safari/") && !user_agent.contains("Chrome/") && !user_agent.contains("Chromium/") {
+        H2Browser::Safari
+    } else {
+        H2Browser::Chrome
+    }
+}
+
+/// A backend sending HTTP requests
+#[async_trait]
+pub trait Transport: Send + Sync + std::fmt::Debug {
+    /// Send a request without following redirects or handling challenges
+    async fn execute(&self, request: Request) -> Result<Response, CloudflareError>;
+}
+
+/// Transport using a reqwest client
+#[derive(Debug, Clone)]
+pub struct ReqwestTransport {
+    client: Client,
+}
+
+impl ReqwestTransport {
+    /// Create a transport using a client
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl Transport for ReqwestTransport {
+    async fn execute(&self, request: Request) -> Result<Response, CloudflareError> {
+        Ok(self.client.execute(request).await?)
+    }
+}
+
+/// Transport using hyper with browser-like HTTP/2 settings
+#[cfg(feature = "h2")]
+#[derive(Debug, Clone)]
+pub struct Http2Transport {
+    client: hyper::Client<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>, hyper::Body>,
+    profile: H2Profile,
+    timeout: Duration,
+}
+
+#[cfg(feature = "h2")]
+impl Http2Transport {
+    /// Create a transport with the parameters of a profile
+    pub fn new(profile: H2Profile, timeout: Duration) -> Self {
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+            rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(anchor.subject, anchor.spki, anchor.name_constraints)
+        }));
+        
+        let mut tls = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        tls.alpn_protocols = profile.alpn_protocols.iter().map(|p| p.as_bytes().to_vec()).collect();
+        
+        let connector = hyper_rustls::HttpsConnectorBuilder::new()
+            .with_tls_config(tls)
+            .https_or_http()
+            .enable_http1()
+            .enable_http2()
+            .build();
+        
+        let client = hyper::Client::builder()
+            .http2_initial_stream_window_size(profile.initial_window_size)
+            .http2_initial_connection_window_size(DEFAULT_CONNECTION_WINDOW + profile.connection_window_update)
+            .http2_max_frame_size(profile.max_frame_size)
+            .build(connector);
+        
+        Self { client, profile, timeout }
+    }
+    
+    /// Get the HTTP/2 parameters of this transport
+    pub fn profile(&self) -> &H2Profile {
+        &self.profile
+    }
+}
+
+#[cfg(feature = "h2")]
+#[async_trait]
+impl Transport for Http2Transport {
+    async fn execute(&self, request: Request) -> Result<Response, CloudflareError> {
+        let mut builder = hyper::Request::builder()
+            .method(request.method().clone())
+            .uri(request.url().as_str());
+        for (name, value) in request.headers() {
+            builder = builder.header(name, value);
+        }
+        
+        let body = request.body().and_then(|body| body.as_bytes()).map(<[u8]>::to_vec).unwrap_or_default();
+        let hyper_request = builder
+            .body(hyper::Body::from(body))
+            .map_err(|e| CloudflareError::Other(format!("Invalid request: {}", e)))?;
+        
+        let response = tokio::time::timeout(self.timeout, self.client.request(hyper_request))
+            .await
+            .map_err(|_| CloudflareError::Other(format!("Request to {} timed out", request.url())))?
+            .map_err(|e| CloudflareError::Other(format!("HTTP/2 request failed: {}", e)))?;
+        
+        tracing::debug!("{} {} over {:?}", request.method(), request.url(), response.version());
+        
+        let (parts, body) = response.into_parts();
+        let body = hyper::body::to_bytes(body)
+            .await
+            .map_err(|e| CloudflareError::Other(format!("Failed to read response body: {}", e)))?;
+        
+        Ok(Response::from(http::Response::from_parts(parts, body)))
+    }
+}
+
+/// Transport running a curl-impersonate binary
+#[derive(Debug, Clone)]
+pub struct CurlImpersonateTransport {
+    binary: PathBuf,
+    timeout: Duration,
+    proxy: Option<String>,
+}
+
+impl CurlImpersonateTransport {
+    /// Create a transport running a binary, e.g. `curl_chrome116`
+    pub fn new(binary: impl Into<PathBuf>, timeout: Duration) -> Self {
+        Self {
+            binary: binary.into(),
+            timeout,
+            proxy: None,
+        }
+    }
+    
+    /// Send requests through a proxy
+    pub fn with_proxy(mut self, proxy: &str) -> Self {
+        self.proxy = Some(proxy.to_string());
+        self
+    }
+}
+
+#[async_trait]
+impl Transport for CurlImpersonateTransport {
+    async fn execute(&self, request: Request) -> Result<Response, CloudflareError> {
+        let body = request.body().and_then(|body| body.as_bytes()).map(<[u8]>::to_vec);
+        
+        let mut command = Command::new(&self.binary);
+        command
+            .args(["--silent", "--show-error", "--include", "--compressed"])
+            .args(["--max-time", &self.timeout.as_secs().max(1).to_string()])
+            .args(["--request", request.method().as_str()]);
+        for (name, value) in request.headers() {
+            if let Ok(value) = value.to_str() {
+                command.args(["--header", &format!("{}: {}", name, value)]);
+            }
+        }
+        if let Some(proxy) = &self.proxy {
+            command.args(["--proxy", proxy]);
+        }
+        if body.is_some() {
+            command.args(["--data-binary", "@-"]);
+        }
+        command
+            .arg(request.url().as_str())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        
+        let mut child = command
+            .spawn()
+            .map_err(|e| CloudflareError::Other(format!("Failed to run {}: {}", self.binary.display(), e)))?;
+        
+        if let Some(mut stdin) = child.stdin.take() {
+            if let Some(body) = body {
+                stdin.write_all(&body).await.map_err(|e| CloudflareError::Other(e.to_string()))?;
+            }
+        }
+        
+        let output = child
+            .wait_with_output()
+            .await
+            .map_err(|e| CloudflareError::Other(format!("Failed to run {}: {}", self.binary.display(), e)))?;
+        
+        if !output.status.success() {
+            return Err(CloudflareError::Other(format!(
+                "{} failed: {}",
+                self.binary.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        
+        parse_curl_output(&output.stdout)
+    }
+}
+
+/// Parse the output of `curl --include` into a response
+fn parse_curl_output(output: &[u8]) -> Result<Response, CloudflareError> {
+    let invalid = || CloudflareError::Other("Invalid response from curl".to_string());
+    let mut rest = output;
+    
+    // Skip interim responses (100 Continue) and proxy CONNECT responses
+    loop {
+        let end = find_subsequence(rest, b"\r\n\r\n").ok_or_else(invalid)?;
+        let head = std::str::from_utf8(&rest[..end]).map_err(|_| invalid())?;
+        rest = &rest[end + 4..];
+        
+        let mut lines = head.split("\r\n");
+        let status_line = lines.next().ok_or_else(invalid)?;
+        let status: u16 = status_line.split_whitespace().nth(1).and_then(|code| code.parse().ok()).ok_or_else(invalid)?;
+        
+        let connect_established = status_line.to_lowercase().contains("connection established");
+        if (100..200).contains(&status) || connect_established {
+            continue;
+        }
+        
+        let mut headers = HeaderMap::new();
+        for line in lines {
+            let Some((name, value)) = line.split_once(':') else { continue };
+            if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.trim().as_bytes()), HeaderValue::from_str(value.trim())) {
+                headers.append(name, value);
+            }
+        }
+        
+        // curl already decompressed the body
+        headers.remove(reqwest::header::CONTENT_ENCODING);
+        headers.remove(reqwest::header::CONTENT_LENGTH);
+        
+        let mut response = http::Response::new(rest.to_vec());
+        *response.status_mut() = StatusCode::from_u16(status).map_err(|_| invalid())?;
+        *response.headers_mut() = headers;
+        return Ok(Response::from(response));
+    }
+}
+
+/// Find the first position of a byte sequence
+fn find_subsequence(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Build the transport described by a configuration
+///
+/// `client` is used by the reqwest transport; `user_agent` picks the HTTP/2
+/// profile when none is configured.
+pub fn build_transport(
+    config: &TransportConfig,
+    client: Client,
+    user_agent: Option<&str>,
+    timeout: Duration,
+    proxy: Option<&str>,
+) -> Result<Arc<dyn Transport>, CloudflareError> {
+    match config {
+        TransportConfig::Reqwest => Ok(Arc::new(ReqwestTransport::new(client))),
+        #[cfg(feature = "h2")]
+        TransportConfig::Http2 { profile } => {
+            if proxy.is_some() {
+                return Err(CloudflareError::ProxyError("The HTTP/2 transport does not support proxies".to_string()));
+            }
+            let profile = match profile {
+                Some(browser) => H2Profile::for_browser(*browser),
+                None => H2Profile::for_user_agent(user_agent.unwrap_or_default()),
+            };
+            Ok(Arc::new(Http2Transport::new(profile, timeout)))
+        },
+        #[cfg(not(feature = "h2"))]
+        TransportConfig::Http2 { .. } => {
+            let _ = user_agent;
+            Err(CloudflareError::Other("The HTTP/2 transport requires the `h2` feature".to_string()))
+        },
+        TransportConfig::CurlImpersonate { binary } => {
+            let transport = CurlImpersonateTransport::new(binary.clone(), timeout);
+            Ok(Arc::new(match proxy {
+                Some(proxy) => transport.with_proxy(proxy),
+                None => transport,
+            }))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_h2_profiles() {
+        assert_eq!(H2Profile::chrome().akamai_fingerprint(), "1:65536;2:0;4:6291456;6:262144|15663105|0|m,a,s,p");
+        assert_eq!(H2Profile::firefox().akamai_fingerprint(), "1:65536;4:131072;5:16384|12517377|0|m,p,a,s");
+        
+        let safari = "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_4) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Safari/605.1.15";
+        let chrome = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36";
+        assert_eq!(H2Profile::for_user_agent(safari), H2Profile::safari());
+        assert_eq!(H2Profile::for_user_agent(chrome), H2Profile::chrome());
+        
+        let config: TransportConfig = serde_json::from_str(r#"{"kind": "http2", "profile": "firefox"}"#).unwrap();
+        assert_eq!(config, TransportConfig::Http2 { profile: Some(H2Browser::Firefox) });
+    }
+    
+    #[tokio::test]
+    async fn test_parse_curl_output() {
+        let output = b"HTTP/1.1 100 Continue\r\n\r\nHTTP/2 403\r\ncf-ray: 8a1b2c3d4e5f-AMS\r\nset-cookie: a=1\r\nset-cookie: b=2\r\ncontent-encoding: br\r\n\r\n<html>blocked</html>";
+        let response = parse_curl_output(output).unwrap();
+        
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(response.headers().get_all("set-cookie").iter().count(), 2);
+        assert!(response.headers().get("content-encoding").is_none());
+        assert_eq!(response.text().await.unwrap(), "<html>blocked</html>");
+    }
+}
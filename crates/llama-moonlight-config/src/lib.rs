@@ -0,0 +1,110 @@
+//! # llama-moonlight-config
+//!
+//! Layered configuration for the Llama Moonlight ecosystem.
+//!
+//! A [`ConfigLoader`] builds a typed configuration from four layers, each
+//! overriding the one before it:
+//!
+//! 1. The type's `Default` values
+//! 2. Configuration files (TOML, or JSON by extension)
+//! 3. Environment variables, e.g. `LLAMA_MOONLIGHT__TOR__SOCKS_PORT=9150`
+//! 4. CLI overrides, e.g. `--set tor.socks_port=9150`
+//!
+//! Crates expose their configuration types as [`ConfigSection`]s, so one file
+//! can hold the settings of every component:
+//!
+//! ```toml
+//! [tor]
+//! socks_port = 9150
+//! control_password = "hunter2"
+//!
+//! [cloudflare]
+//! max_retries = 5
+//!
+//! [proxy_pool]
+//! strategy = "Fastest"
+//! ```
+//!
+//! | Section         | Type                                        |
+//! |-----------------|---------------------------------------------|
+//! | `tor`           | `llama_moonlight_tor::TorConfig`            |
+//! | `stealth`       | `llama_moonlight_stealth::StealthConfig`    |
+//! | `cloudflare`    | `llama_moonlight_cloudflare::CloudflareConfig` |
+//! | `proxy_pool`    | `llama_moonlight_proxymaster::PoolConfig`   |
+//! | `proxy_scraper` | `llama_moonlight_proxymaster::ScraperConfig` |
+//! | `proxy_gateway` | `llama_moonlight_proxymaster::GatewayConfig` |
+//!
+//! Credentials are held in [`Secret`]s, which are redacted from `Debug`
+//! output so configurations can be logged safely.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use llama_moonlight_config::{ConfigLoader, ConfigSection, Result};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, Default, Serialize, Deserialize)]
+//! struct CrawlConfig {
+//!     max_depth: u32,
+//!     user_agent: Option<String>,
+//! }
+//!
+//! impl ConfigSection for CrawlConfig {
+//!     const SECTION: &'static str = "crawl";
+//! }
+//!
+//! fn main() -> Result<()> {
+//!     let loader = ConfigLoader::new()
+//!         .with_optional_file("moonlight.toml")
+//!         .with_overrides(std::env::args().skip(1))?;
+//!
+//!     let config: CrawlConfig = loader.section()?;
+//!     println!("{:?}", config);
+//!     Ok(())
+//! }
+//! ```
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs)]
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+
+pub mod loader;
+pub mod secret;
+
+pub use loader::{ConfigLoader, DEFAULT_ENV_PREFIX};
+pub use secret::Secret;
+
+/// Custom result type for configuration operations
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Error type for configuration operations
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A configuration file could not be read
+    #[error("Failed to read {0}: {1}")]
+    ReadError(String, std::io::Error),
+
+    /// A configuration file is not valid TOML or JSON
+    #[error("Failed to parse {0}: {1}")]
+    ParseError(String, String),
+
+    /// A CLI override is not of the form `key=value`
+    #[error("Invalid override '{0}', expected key=value")]
+    InvalidOverride(String),
+
+    /// The merged configuration does not match the target type
+    #[error("Invalid configuration{0}: {1}")]
+    InvalidConfig(String, serde_json::Error),
+}
+
+/// A configuration type stored under its own section of a configuration file
+///
+/// The section name is also the first segment of the type's environment
+/// variables and CLI override keys.
+pub trait ConfigSection: Serialize + DeserializeOwned + Default {
+    /// Name of the section, e.g. `"tor"`
+    const SECTION: &'static str;
+}
@@ -0,0 +1,390 @@
+//! Layered configuration loading.
+//!
+//! Every layer is merged into one JSON document before it is deserialized.
+//! The type's defaults come first, so files, environment variables and CLI
+//! overrides only need to name the fields they change.
+//!
+//! Environment variable names are lowercased and split on `__` into a key
+//! path, so `LLAMA_MOONLIGHT__TOR__SOCKS_PORT` sets `tor.socks_port`.
+//!
+//! Environment variables and overrides carry plain strings. They are
+//! converted to the type of the value they replace: a string field keeps the
+//! raw text, a list field accepts a JSON array or comma-separated items, and
+//! anything else is parsed as JSON when possible (`9150`, `true`, `null`).
+
+use crate::{ConfigSection, Error, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+/// Default prefix of configuration environment variables
+pub const DEFAULT_ENV_PREFIX: &str = "LLAMA_MOONLIGHT";
+
+/// Separator between key segments in environment variable names
+const ENV_SEPARATOR: &str = "__";
+
+/// A configuration file layer
+#[derive(Debug, Clone)]
+struct FileLayer {
+    /// Path of the file
+    path: PathBuf,
+
+    /// Whether a missing file is an error
+    required: bool,
+}
+
+/// Builds configurations from defaults, files, env vars and CLI overrides
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    /// Files in the order they are applied
+    files: Vec<FileLayer>,
+
+    /// Prefix of environment variables, or `None` to ignore the environment
+    env_prefix: Option<String>,
+
+    /// Environment variables to use instead of the process environment
+    env_vars: Option<Vec<(String, String)>>,
+
+    /// CLI overrides as dotted key paths and raw values
+    overrides: Vec<(String, String)>,
+}
+
+impl Default for ConfigLoader {
+    fn default() -> Self {
+        Self {
+            files: Vec::new(),
+            env_prefix: Some(DEFAULT_ENV_PREFIX.to_string()),
+            env_vars: None,
+            overrides: Vec::new(),
+        }
+    }
+}
+
+impl ConfigLoader {
+    /// Create a loader reading `LLAMA_MOONLIGHT__*` environment variables
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a configuration file that must exist
+    pub fn with_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.files.push(FileLayer { path: path.as_ref().to_path_buf(), required: true });
+        self
+    }
+
+    /// Add a configuration file that is skipped if missing
+    pub fn with_optional_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.files.push(FileLayer { path: path.as_ref().to_path_buf(), required: false });
+        self
+    }
+
+    /// Set the prefix of configuration environment variables
+    pub fn with_env_prefix(mut self, prefix: &str) -> Self {
+        self.env_prefix = Some(prefix.trim_end_matches('_').to_string());
+        self
+    }
+
+    /// Ignore environment variables
+    pub fn without_env(mut self) -> Self {
+        self.env_prefix = None;
+        self
+    }
+
+    /// Read environment variables from a list instead of the process environment
+    pub fn with_env_vars<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.env_vars = Some(vars.into_iter().map(|(k, v)| (k.into(), v.into())).collect());
+        self
+    }
+
+    /// Override a value by its dotted key path, e.g. `tor.socks_port`
+    pub fn with_override(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.overrides.push((key.to_string(), value.into()));
+        self
+    }
+
+    /// Add CLI overrides of the form `key=value`
+    ///
+    /// Accepts the values of repeated `--set key=value` flags.
+    pub fn with_overrides<I, S>(mut self, overrides: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for item in overrides {
+            let item = item.as_ref();
+            match item.split_once('=') {
+                Some((key, value)) if !key.trim().is_empty() => {
+                    self.overrides.push((key.trim().to_string(), value.to_string()));
+                }
+                _ => return Err(Error::InvalidOverride(item.to_string())),
+            }
+        }
+        Ok(self)
+    }
+
+    /// Load a whole configuration document
+    pub fn load<T: Serialize + DeserializeOwned + Default>(&self) -> Result<T> {
+        let value = self.build(None, T::default())?;
+        serde_json::from_value(value).map_err(|e| Error::InvalidConfig(String::new(), e))
+    }
+
+    /// Load one section of the configuration
+    pub fn section<T: ConfigSection>(&self) -> Result<T> {
+        let value = self.build(Some(T::SECTION), T::default())?;
+        serde_json::from_value(value).map_err(|e| Error::InvalidConfig(format!(" of [{}]", T::SECTION), e))
+    }
+
+    /// Merge all layers and return the value at `section`
+    fn build<T: Serialize>(&self, section: Option<&str>, defaults: T) -> Result<Value> {
+        let defaults = serde_json::to_value(defaults)
+            .map_err(|e| Error::InvalidConfig(String::new(), e))?;
+
+        let mut document = match section {
+            Some(section) => {
+                let mut root = Map::new();
+                root.insert(section.to_string(), defaults);
+                Value::Object(root)
+            }
+            None => defaults,
+        };
+
+        for file in &self.files {
+            if let Some(value) = read_file(file)? {
+                merge(&mut document, value);
+            }
+        }
+
+        for (key, raw) in self.env_entries() {
+            set_path(&mut document, &key, &raw);
+        }
+
+        for (key, raw) in &self.overrides {
+            let key: Vec<String> = key.split('.').map(str::to_string).collect();
+            set_path(&mut document, &key, raw);
+        }
+
+        Ok(match section {
+            Some(section) => document.get_mut(section).map(Value::take).unwrap_or(Value::Null),
+            None => document,
+        })
+    }
+
+    /// Get the configuration environment variables as key paths and raw values
+    fn env_entries(&self) -> Vec<(Vec<String>, String)> {
+        let prefix = match &self.env_prefix {
+            Some(prefix) => format!("{}{}", prefix, ENV_SEPARATOR),
+            None => return Vec::new(),
+        };
+
+        let vars = match &self.env_vars {
+            Some(vars) => vars.clone(),
+            None => std::env::vars().collect(),
+        };
+
+        let mut entries: Vec<_> = vars.into_iter()
+            .filter_map(|(name, value)| {
+                let key = name.strip_prefix(&prefix)?;
+                let key: Vec<String> = key.split(ENV_SEPARATOR).map(str::to_lowercase).collect();
+                Some((key, value))
+            })
+            .collect();
+
+        // Apply parents before children so nested variables are not replaced
+        entries.sort_by_key(|(key, _)| key.len());
+        entries
+    }
+}
+
+/// Read a configuration file, or `None` if an optional file is missing
+fn read_file(file: &FileLayer) -> Result<Option<Value>> {
+    let display = file.path.display().to_string();
+    let content = match std::fs::read_to_string(&file.path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && !file.required => return Ok(None),
+        Err(e) => return Err(Error::ReadError(display, e)),
+    };
+
+    let is_json = file.path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let value = if is_json {
+        serde_json::from_str(&content).map_err(|e| Error::ParseError(display, e.to_string()))?
+    } else {
+        let value: toml::Value = toml::from_str(&content).map_err(|e| Error::ParseError(display.clone(), e.to_string()))?;
+        serde_json::to_value(value).map_err(|e| Error::ParseError(display, e.to_string()))?
+    };
+
+    Ok(Some(value))
+}
+
+/// Deep-merge `overlay` into `base`, replacing everything but tables
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Set the value at a key path from a raw string
+fn set_path(document: &mut Value, key: &[String], raw: &str) {
+    let (last, parents) = match key.split_last() {
+        Some(split) => split,
+        None => return,
+    };
+
+    let mut node = document;
+    for segment in parents {
+        node = as_object(node).entry(segment.clone()).or_insert(Value::Null);
+    }
+
+    let map = as_object(node);
+    let value = coerce(map.get(last), raw);
+    map.insert(last.clone(), value);
+}
+
+/// Get a value as a table, replacing it with an empty one if it is not
+fn as_object(value: &mut Value) -> &mut Map<String, Value> {
+    if !value.is_object() {
+        *value = Value::Object(Map::new());
+    }
+    match value {
+        Value::Object(map) => map,
+        _ => unreachable!("value was replaced with an object"),
+    }
+}
+
+/// Convert a raw string to the type of the value it replaces
+fn coerce(existing: Option<&Value>, raw: &str) -> Value {
+    match existing {
+        Some(Value::String(_)) => Value::String(raw.to_string()),
+        Some(Value::Array(_)) => match serde_json::from_str(raw) {
+            Ok(Value::Array(items)) => Value::Array(items),
+            _ => Value::Array(
+                raw.split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(|item| serde_json::from_str(item).unwrap_or_else(|_| Value::String(item.to_string())))
+                    .collect(),
+            ),
+        },
+        _ => serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Secret;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+    use std::io::Write;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct TestConfig {
+        host: String,
+        port: u16,
+        enabled: bool,
+        bridges: Vec<String>,
+        password: Option<Secret>,
+        options: HashMap<String, String>,
+    }
+
+    impl Default for TestConfig {
+        fn default() -> Self {
+            Self {
+                host: "127.0.0.1".to_string(),
+                port: 9050,
+                enabled: false,
+                bridges: Vec::new(),
+                password: None,
+                options: HashMap::new(),
+            }
+        }
+    }
+
+    impl ConfigSection for TestConfig {
+        const SECTION: &'static str = "test";
+    }
+
+    fn config_file(extension: &str, content: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::Builder::new().suffix(extension).tempfile().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_layers_override_in_order() {
+        let file = config_file(".toml", "[test]\nport = 9150\nhost = \"10.0.0.1\"\n\n[test.options]\nStrictNodes = \"1\"\n");
+
+        let config: TestConfig = ConfigLoader::new()
+            .with_file(file.path())
+            .with_env_vars([
+                ("LLAMA_MOONLIGHT__TEST__PORT", "9250"),
+                ("LLAMA_MOONLIGHT__TEST__ENABLED", "true"),
+                ("LLAMA_MOONLIGHT__TEST__BRIDGES", "obfs4 a, obfs4 b"),
+                ("LLAMA_MOONLIGHT__TEST__PASSWORD", "1234"),
+                ("OTHER__TEST__PORT", "1"),
+            ])
+            .with_overrides(["test.port=9350"])
+            .unwrap()
+            .section()
+            .unwrap();
+
+        assert_eq!(config.host, "10.0.0.1");
+        assert_eq!(config.port, 9350);
+        assert!(config.enabled);
+        assert_eq!(config.bridges, vec!["obfs4 a", "obfs4 b"]);
+        assert_eq!(config.password.as_ref().map(Secret::expose), Some("1234"));
+        assert_eq!(config.options.get("StrictNodes").map(String::as_str), Some("1"));
+        assert!(!format!("{:?}", config).contains("1234"));
+    }
+
+    #[test]
+    fn test_defaults_and_json_files() {
+        let loader = ConfigLoader::new().without_env().with_optional_file("/nonexistent/moonlight.toml");
+        assert_eq!(loader.section::<TestConfig>().unwrap(), TestConfig::default());
+
+        let file = config_file(".json", r#"{ "host": "tor.local", "bridges": ["a"] }"#);
+        let config: TestConfig = ConfigLoader::new()
+            .with_env_prefix("APP_")
+            .with_env_vars([("APP__HOST", "env.local")])
+            .with_file(file.path())
+            .load()
+            .unwrap();
+        assert_eq!(config.host, "env.local");
+        assert_eq!(config.bridges, vec!["a"]);
+        assert_eq!(config.port, 9050);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            ConfigLoader::new().with_overrides(["test.port"]),
+            Err(Error::InvalidOverride(_))
+        ));
+        assert!(matches!(
+            ConfigLoader::new().with_file("/nonexistent/moonlight.toml").section::<TestConfig>(),
+            Err(Error::ReadError(..))
+        ));
+
+        let error = ConfigLoader::new()
+            .without_env()
+            .with_override("test.port", "not a port")
+            .section::<TestConfig>()
+            .unwrap_err();
+        assert!(error.to_string().contains("[test]"));
+    }
+}
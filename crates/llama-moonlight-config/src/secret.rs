@@ -0,0 +1,125 @@
+//! Redacted secret values.
+//!
+//! [`Secret`] wraps credentials such as API keys and passwords. Its `Debug`
+//! output never shows the value, and it deliberately has no `Display`
+//! implementation, so a secret can only end up in a log or request through an
+//! explicit call to [`Secret::expose`].
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+
+/// A configuration value that must not appear in logs
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    /// Wrap a secret value
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// Get the secret value
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Check whether the secret is empty
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+/// Secrets serialize as their plain value so configurations can be saved
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+/// Accepts strings as well as numbers and booleans, so that an all-digit
+/// password read from an environment variable or written unquoted still
+/// deserializes.
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SecretVisitor;
+
+        impl Visitor<'_> for SecretVisitor {
+            type Value = Secret;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a secret string")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Secret, E> {
+                Ok(Secret::from(value))
+            }
+
+            fn visit_string<E: de::Error>(self, value: String) -> Result<Secret, E> {
+                Ok(Secret(value))
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Secret, E> {
+                Ok(Secret(value.to_string()))
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Secret, E> {
+                Ok(Secret(value.to_string()))
+            }
+
+            fn visit_f64<E: de::Error>(self, value: f64) -> Result<Secret, E> {
+                Ok(Secret(value.to_string()))
+            }
+
+            fn visit_bool<E: de::Error>(self, value: bool) -> Result<Secret, E> {
+                Ok(Secret(value.to_string()))
+            }
+        }
+
+        deserializer.deserialize_any(SecretVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_is_redacted() {
+        #[derive(Debug, Deserialize)]
+        struct Credentials {
+            user: String,
+            password: Secret,
+        }
+
+        let credentials: Credentials =
+            serde_json::from_str(r#"{ "user": "llama", "password": "hunter2" }"#).unwrap();
+        let debug = format!("{:?}", credentials);
+        assert_eq!(credentials.user, "llama");
+        assert!(debug.contains("llama"));
+        assert!(!debug.contains("hunter2"));
+        assert_eq!(credentials.password.expose(), "hunter2");
+
+        let numeric: Secret = serde_json::from_str("123456").unwrap();
+        assert_eq!(numeric.expose(), "123456");
+        assert_eq!(serde_json::to_string(&numeric).unwrap(), "\"123456\"");
+    }
+}
@@ -0,0 +1,208 @@
+//! Resource blocking.
+//!
+//! Pages of a context created with blocked resource types or domains (see
+//! [`ContextOptions::block_resources`](crate::ContextOptions::block_resources))
+//! intercept their requests and fail the matching ones before they reach the
+//! network. Blocked requests are reported to the page as blocked by the client.
+//!
+//! The same interception aborts the requests of contexts over their transfer
+//! quota (see [`crate::quota`]), since a page can only have one set of
+//! interception patterns.
+
+use crate::errors::Result;
+use crate::options::{ContextOptions, ResourceType};
+use crate::page::{send_to_session, Page};
+use crate::protocol::Connection;
+use crate::quota::BandwidthQuota;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+/// Decides which requests of a page are blocked.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ResourceBlocker {
+    resource_types: Vec<ResourceType>,
+    domains: Vec<String>,
+}
+
+impl ResourceBlocker {
+    /// Creates a blocker from the context options, if anything is blocked.
+    pub(crate) fn from_options(options: &ContextOptions) -> Option<Self> {
+        let resource_types = options.blocked_resource_types.clone().unwrap_or_default();
+        let domains = options.blocked_domains.clone().unwrap_or_default();
+
+        if resource_types.is_empty() && domains.is_empty() {
+            None
+        } else {
+            Some(Self { resource_types, domains })
+        }
+    }
+
+    /// Returns whether a request should be blocked.
+    pub(crate) fn should_block(&self, url: &str, resource_type: Option<ResourceType>) -> bool {
+        if let Some(resource_type) = resource_type {
+            if self.resource_types.contains(&resource_type) {
+                return true;
+            }
+        }
+
+        let host = match reqwest::Url::parse(url) {
+            Ok(url) => match url.host_str() {
+                Some(host) => host.to_ascii_lowercase(),
+                None => return false,
+            },
+            Err(_) => return false,
+        };
+
+        self.domains.iter().any(|domain| {
+            host == *domain || (host.ends_with(domain.as_str()) && host[..host.len() - domain.len()].ends_with('.'))
+        })
+    }
+
+    /// Returns the interception patterns for the blocker.
+    ///
+    /// Domain rules need to see every request; type rules only need the
+    /// requests of the blocked types.
+    fn patterns(&self) -> Vec<serde_json::Value> {
+        if !self.domains.is_empty() {
+            return vec![serde_json::json!({ "urlPattern": "*" })];
+        }
+
+        self.resource_types
+            .iter()
+            .map(|resource_type| serde_json::json!({ "urlPattern": "*", "resourceType": resource_type }))
+            .collect()
+    }
+}
+
+/// Starts intercepting requests of a page for resource blocking and bandwidth quotas.
+///
+/// Interception runs in a background task until the connection closes. With a
+/// quota every request is intercepted, since any of them may need aborting,
+/// and the page's network events are enabled to count received bytes.
+pub(crate) async fn install_interception(
+    page: &Page,
+    blocker: Option<ResourceBlocker>,
+    quota: Option<Arc<BandwidthQuota>>,
+) -> Result<()> {
+    info!("Installing request interception for page {}", page.target_id);
+
+    // Subscribe before enabling interception so no paused request is missed
+    let mut events = page.connection.subscribe("Target.receivedMessageFromTarget".to_string()).await?;
+
+    let patterns = match (&blocker, &quota) {
+        (Some(blocker), None) => blocker.patterns(),
+        _ => vec![serde_json::json!({ "urlPattern": "*" })],
+    };
+    if quota.is_some() {
+        page.send_session_command("Network.enable", None).await?;
+    }
+    let params = serde_json::json!({ "patterns": patterns });
+    page.send_session_command("Fetch.enable", Some(params)).await?;
+
+    let connection = page.connection.clone();
+    let session_id = page.session_id.clone();
+    tokio::spawn(async move {
+        while let Some(event) = events.recv().await {
+            let params = match event.params {
+                Some(params) if params["sessionId"] == session_id.as_str() => params,
+                _ => continue,
+            };
+            let message: serde_json::Value = match params["message"].as_str().map(serde_json::from_str) {
+                Some(Ok(message)) => message,
+                _ => continue,
+            };
+
+            match message["method"].as_str() {
+                Some("Fetch.requestPaused") => {
+                    let result = handle_paused(
+                        &connection,
+                        &session_id,
+                        &message["params"],
+                        blocker.as_ref(),
+                        quota.as_deref(),
+                    )
+                    .await;
+                    if let Err(e) = result {
+                        warn!("Failed to resolve intercepted request: {}", e);
+                    }
+                }
+                Some("Network.loadingFinished") => {
+                    if let Some(quota) = &quota {
+                        quota.record(message["params"]["encodedDataLength"].as_f64().unwrap_or(0.0) as u64);
+                    }
+                }
+                _ => {}
+            }
+        }
+        debug!("Request interception stopped for session {}", session_id);
+    });
+
+    Ok(())
+}
+
+/// Fails or continues a paused request.
+async fn handle_paused(
+    connection: &Arc<Connection>,
+    session_id: &str,
+    paused: &serde_json::Value,
+    blocker: Option<&ResourceBlocker>,
+    quota: Option<&BandwidthQuota>,
+) -> Result<()> {
+    let request_id = paused["requestId"].clone();
+    let url = paused["request"]["url"].as_str().unwrap_or_default();
+    let resource_type = serde_json::from_value(paused["resourceType"].clone()).ok();
+
+    let blocked = if quota.is_some_and(BandwidthQuota::is_exceeded) {
+        debug!("Aborting request to {} over the transfer quota", url);
+        true
+    } else if blocker.is_some_and(|blocker| blocker.should_block(url, resource_type)) {
+        debug!("Blocking request to {}", url);
+        true
+    } else {
+        false
+    };
+
+    if blocked {
+        let params = serde_json::json!({ "requestId": request_id, "errorReason": "BlockedByClient" });
+        send_to_session(connection, session_id, "Fetch.failRequest", Some(params)).await?;
+    } else {
+        let params = serde_json::json!({ "requestId": request_id });
+        send_to_session(connection, session_id, "Fetch.continueRequest", Some(params)).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::BlockList;
+
+    #[test]
+    fn test_from_options() {
+        assert!(ResourceBlocker::from_options(&ContextOptions::default()).is_none());
+
+        let options = ContextOptions::default()
+            .block_resources(&[ResourceType::Image, ResourceType::Font, ResourceType::Image]);
+        let blocker = ResourceBlocker::from_options(&options).unwrap();
+        assert_eq!(blocker.resource_types, vec![ResourceType::Image, ResourceType::Font]);
+        assert_eq!(blocker.patterns().len(), 2);
+    }
+
+    #[test]
+    fn test_should_block() {
+        let options = ContextOptions::default()
+            .block_resources(&[ResourceType::Media])
+            .block_domains(&[".Tracker.example"])
+            .block_list(BlockList::Ads);
+        let blocker = ResourceBlocker::from_options(&options).unwrap();
+
+        assert!(blocker.should_block("https://example.com/video.mp4", Some(ResourceType::Media)));
+        assert!(blocker.should_block("https://tracker.example/pixel.gif", Some(ResourceType::Image)));
+        assert!(blocker.should_block("https://cdn.tracker.example/t.js", None));
+        assert!(blocker.should_block("https://securepubads.g.doubleclick.net/tag.js", Some(ResourceType::Script)));
+        assert!(!blocker.should_block("https://nottracker.example/app.js", Some(ResourceType::Script)));
+        assert!(!blocker.should_block("https://example.com/", Some(ResourceType::Document)));
+        assert_eq!(blocker.patterns().len(), 1);
+    }
+}
@@ -4,17 +4,21 @@
 
 use crate::context::{BrowserContext, ContextOptions};
 use crate::errors::{Error, Result};
+use crate::event::{BrowserEvent, EventEmitter};
+use crate::launcher::BrowserHandle;
 use crate::protocol::Connection;
+use crate::quota::BandwidthQuota;
 use crate::options::BrowserOptions;
-use std::process::{Child, Command};
+use crate::telemetry::FIELD_BROWSER_ID;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use log::{debug, info, warn};
+use tracing::{debug, info, instrument, warn};
 use std::path::{Path, PathBuf};
 use tokio::time::{timeout, Duration};
 use uuid::Uuid;
 use std::fs;
-use tempfile::TempDir;
 use llama_headers_rs;
 
 /// Represents a type of browser (chromium, firefox, webkit).
@@ -52,49 +56,35 @@ impl BrowserType {
     }
     
     /// Launches a browser instance with the specified options.
+    #[instrument(skip(self, options), fields(browser = %self.name, { FIELD_BROWSER_ID } = tracing::field::Empty))]
     pub async fn launch_with_options(&self, options: BrowserOptions) -> Result<Browser> {
+        let browser_id = Uuid::new_v4().to_string();
+        tracing::Span::current().record(FIELD_BROWSER_ID, browser_id.as_str());
         info!("Launching {} browser", self.name);
         
-        // Create a temporary user data directory if none is specified
-        let user_data_dir = if let Some(dir) = options.user_data_dir.clone() {
-            PathBuf::from(dir)
-        } else {
-            let temp_dir = TempDir::new()
-                .map_err(|e| Error::BrowserLaunchError(format!("Failed to create temp directory: {}", e)))?;
-            temp_dir.into_path()
-        };
-        
-        // Prepare browser executable and arguments
-        let (executable, args) = self.prepare_launch_command(&user_data_dir, &options)?;
-        
-        // Launch browser process
-        let mut cmd = Command::new(executable);
-        cmd.args(args);
-        
-        // Add environment variables
-        if let Some(env_vars) = &options.env {
-            for (key, value) in env_vars {
-                cmd.env(key, value);
-            }
-        }
-        
-        // Start the process
-        let child = cmd
-            .spawn()
-            .map_err(|e| Error::BrowserLaunchError(format!("Failed to spawn browser process: {}", e)))?;
-        
-        // Wait for the WebSocket endpoint to be available
-        let ws_endpoint = self.wait_for_websocket_endpoint(&user_data_dir, options.timeout_ms.unwrap_or(30000)).await?;
+        // Start the browser wherever the options point to
+        let target = options.launch_target.clone().unwrap_or_default();
+        let launched = target.launcher().launch(self, &options).await?;
         
         // Connect to the browser
-        let connection = Connection::connect(&ws_endpoint).await?;
+        let connection = match Connection::connect(&launched.ws_endpoint).await {
+            Ok(connection) => connection,
+            Err(e) => {
+                launched.handle.terminate();
+                return Err(e.into());
+            }
+        };
         
         // Create browser object
         let browser = Browser {
+            id: browser_id,
             connection: Arc::new(connection),
-            process: Arc::new(Mutex::new(Some(child))),
+            handle: Arc::new(Mutex::new(Some(launched.handle))),
             browser_type: self.clone(),
-            user_data_dir,
+            user_data_dir: launched.user_data_dir,
+            stderr_log: launched.stderr_log,
+            events: EventEmitter::new(),
+            closing: AtomicBool::new(false),
         };
         
         info!("Successfully launched {} browser", self.name);
@@ -102,7 +92,7 @@ impl BrowserType {
     }
     
     /// Prepares the launch command for the specific browser type.
-    fn prepare_launch_command(&self, user_data_dir: &Path, options: &BrowserOptions) -> Result<(String, Vec<String>)> {
+    pub(crate) fn prepare_launch_command(&self, user_data_dir: &Path, options: &BrowserOptions) -> Result<(String, Vec<String>)> {
         match self.name.as_str() {
             "chromium" => {
                 let executable = if let Some(path) = &self.executable_path {
@@ -257,7 +247,7 @@ impl BrowserType {
     }
     
     /// Wait for the WebSocket endpoint to be available after browser launch.
-    async fn wait_for_websocket_endpoint(&self, user_data_dir: &Path, timeout_ms: u64) -> Result<String> {
+    pub(crate) async fn wait_for_websocket_endpoint(&self, user_data_dir: &Path, timeout_ms: u64) -> Result<String> {
         let start_time = std::time::Instant::now();
         let timeout_duration = Duration::from_millis(timeout_ms);
         
@@ -300,13 +290,20 @@ impl BrowserType {
     }
 }
 
+/// How often [`Browser::wait_for_exit`] checks whether the browser process exited.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 /// Represents a browser instance.
 #[derive(Debug)]
 pub struct Browser {
+    id: String,
     connection: Arc<Connection>,
-    process: Arc<Mutex<Option<Child>>>,
+    handle: Arc<Mutex<Option<BrowserHandle>>>,
     browser_type: BrowserType,
-    user_data_dir: PathBuf,
+    user_data_dir: Option<PathBuf>,
+    stderr_log: Option<PathBuf>,
+    events: EventEmitter<BrowserEvent>,
+    closing: AtomicBool,
 }
 
 impl Browser {
@@ -316,9 +313,12 @@ impl Browser {
     }
     
     /// Creates a new browser context with the specified options.
+    #[instrument(skip(self, options), fields({ FIELD_BROWSER_ID } = %self.id))]
     pub async fn new_context_with_options(&self, options: ContextOptions) -> Result<BrowserContext> {
         info!("Creating new browser context");
         
+        let ca_certificates = options.load_ca_certificates()?;
+        
         // Call the protocol method to create a new context
         let params = match self.browser_type.name() {
             "chromium" => {
@@ -329,6 +329,8 @@ impl Browser {
                     "viewport": options.viewport,
                     "ignoreHTTPSErrors": options.ignore_https_errors,
                     "acceptDownloads": options.accept_downloads,
+                    "httpCredentials": options.http_credentials,
+                    "caCertificates": ca_certificates,
                 })
             },
             "firefox" => {
@@ -339,6 +341,8 @@ impl Browser {
                     "viewport": options.viewport,
                     "ignoreHTTPSErrors": options.ignore_https_errors,
                     "acceptDownloads": options.accept_downloads,
+                    "httpCredentials": options.http_credentials,
+                    "caCertificates": ca_certificates,
                 })
             },
             "webkit" => {
@@ -349,6 +353,8 @@ impl Browser {
                     "viewport": options.viewport,
                     "ignoreHTTPSErrors": options.ignore_https_errors,
                     "acceptDownloads": options.accept_downloads,
+                    "httpCredentials": options.http_credentials,
+                    "caCertificates": ca_certificates,
                 })
             },
             _ => return Err(Error::BrowserTypeNotFound(self.browser_type.name().to_string())),
@@ -365,28 +371,92 @@ impl Browser {
         
         let context = BrowserContext {
             connection: self.connection.clone(),
+            quota: BandwidthQuota::from_options(&context_id, &options),
+            events: EventEmitter::new(),
             id: context_id,
             browser_type: self.browser_type.name().to_string(),
             options,
         };
+        context.forward_quota_events();
         
+        if let Some(permissions) = &context.options.permissions {
+            let permissions: Vec<&str> = permissions.iter().map(String::as_str).collect();
+            context.grant_permissions(&permissions, None).await?;
+        }
+        
+        self.events.emit(BrowserEvent::Context { context_id: context.id.clone() });
         info!("Successfully created browser context");
         Ok(context)
     }
     
+    /// Returns the unique ID assigned to this browser at launch.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    
     /// Returns the browser type.
     pub fn browser_type(&self) -> &BrowserType {
         &self.browser_type
     }
     
+    /// Returns the emitter of the browser's events.
+    pub fn events(&self) -> &EventEmitter<BrowserEvent> {
+        &self.events
+    }
+    
     /// Returns the WebSocket connection.
     pub fn connection(&self) -> Arc<Connection> {
         self.connection.clone()
     }
     
+    /// Returns the last lines the browser wrote to standard error.
+    ///
+    /// Available for local and Docker browsers until the browser is closed.
+    pub async fn stderr_tail(&self, max_lines: usize) -> Option<String> {
+        if let Some(path) = &self.stderr_log {
+            let content = tokio::fs::read(path).await.ok()?;
+            let content = String::from_utf8_lossy(&content);
+            let lines: Vec<&str> = content.lines().collect();
+            return Some(lines[lines.len().saturating_sub(max_lines)..].join("\n"));
+        }
+        
+        let handle = self.handle.lock().await;
+        handle.as_ref().and_then(|handle| handle.container_logs(max_lines))
+    }
+    
+    /// Waits until the browser goes away without being closed.
+    ///
+    /// Completes with a description of what happened when the browser
+    /// process exits or the connection to the browser is lost, or with
+    /// `None` once the browser is closed with [`close`](Self::close).
+    pub async fn wait_for_exit(&self) -> Option<String> {
+        let mut poll = tokio::time::interval(EXIT_POLL_INTERVAL);
+        
+        let reason = loop {
+            tokio::select! {
+                _ = self.connection.closed() => break "connection to the browser was lost".to_string(),
+                _ = poll.tick() => {
+                    let mut handle = self.handle.lock().await;
+                    if let Some(status) = handle.as_mut().and_then(|handle| handle.try_exit_status()) {
+                        break format!("browser process exited ({})", status);
+                    }
+                },
+            }
+        };
+        
+        if self.closing.load(Ordering::SeqCst) {
+            return None;
+        }
+        
+        warn!(browser_id = %self.id, "Browser exited unexpectedly: {}", reason);
+        Some(reason)
+    }
+    
     /// Closes the browser.
+    #[instrument(skip(self), fields({ FIELD_BROWSER_ID } = %self.id))]
     pub async fn close(&self) -> Result<()> {
         info!("Closing browser");
+        self.closing.store(true, Ordering::SeqCst);
         
         // Send browser close command via protocol
         let _ = self.connection.send_request(
@@ -396,18 +466,17 @@ impl Browser {
         
         // Close the connection
         let _ = self.connection.close().await;
+        self.events.emit(BrowserEvent::Disconnected);
         
-        // Kill the process if it's still running
-        let mut process = self.process.lock().await;
-        if let Some(child) = process.take() {
-            if let Err(e) = child.kill() {
-                warn!("Failed to kill browser process: {}", e);
-            }
+        // Stop the process or container if it's still running
+        let mut handle = self.handle.lock().await;
+        if let Some(handle) = handle.take() {
+            handle.terminate();
         }
         
         // Clean up the user data directory if it's temporary
-        if self.user_data_dir.exists() {
-            if let Err(e) = fs::remove_dir_all(&self.user_data_dir) {
+        if let Some(user_data_dir) = self.user_data_dir.as_ref().filter(|dir| dir.exists()) {
+            if let Err(e) = fs::remove_dir_all(user_data_dir) {
                 warn!("Failed to remove user data directory: {}", e);
             }
         }
@@ -436,14 +505,14 @@ impl Browser {
 
 impl Drop for Browser {
     fn drop(&mut self) {
-        if let Ok(mut process) = self.process.try_lock() {
-            if let Some(mut child) = process.take() {
-                let _ = child.kill();
+        if let Ok(mut handle) = self.handle.try_lock() {
+            if let Some(handle) = handle.take() {
+                handle.terminate();
             }
         }
         
-        if self.user_data_dir.exists() {
-            let _ = fs::remove_dir_all(&self.user_data_dir);
+        if let Some(user_data_dir) = self.user_data_dir.as_ref().filter(|dir| dir.exists()) {
+            let _ = fs::remove_dir_all(user_data_dir);
         }
     }
 }
@@ -0,0 +1,180 @@
+//! Clock and timer control inside pages.
+//!
+//! This module provides a fake clock that replaces `Date`, `performance.now`,
+//! `setTimeout`/`setInterval`, and `requestAnimationFrame` inside a page, so
+//! time-dependent UI (countdowns, token expiry flows) can be driven
+//! deterministically from tests.
+
+use crate::errors::{Error, Result};
+use crate::page::Page;
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+use tracing::info;
+
+/// The fake clock implementation injected into the page.
+///
+/// `__LLAMA_CLOCK_START__` is replaced with the start time in milliseconds.
+const CLOCK_SCRIPT: &str = r#"
+(() => {
+  if (window.__llamaClock) { return; }
+  const NativeDate = Date;
+  const nativePerformanceNow = performance.now.bind(performance);
+  const clock = {
+    now: __LLAMA_CLOCK_START__,
+    perfOrigin: 0,
+    nextId: 1,
+    timers: new Map(),
+  };
+
+  function schedule(callback, delay, args, interval) {
+    const id = clock.nextId++;
+    delay = Math.max(0, Number(delay) || 0);
+    clock.timers.set(id, { id, callback, args, interval: interval ? delay : null, at: clock.now + delay });
+    return id;
+  }
+
+  function runDue(until) {
+    for (;;) {
+      let next = null;
+      for (const timer of clock.timers.values()) {
+        if (timer.at <= until && (next === null || timer.at < next.at || (timer.at === next.at && timer.id < next.id))) {
+          next = timer;
+        }
+      }
+      if (next === null) { break; }
+      clock.now = Math.max(clock.now, next.at);
+      if (next.interval !== null) {
+        next.at += Math.max(1, next.interval);
+      } else {
+        clock.timers.delete(next.id);
+      }
+      if (typeof next.callback === 'function') {
+        next.callback.apply(window, next.args);
+      } else {
+        (0, eval)(String(next.callback));
+      }
+    }
+    clock.now = until;
+  }
+
+  function FakeDate(...args) {
+    if (!new.target) { return new NativeDate(clock.now).toString(); }
+    return args.length === 0 ? new NativeDate(clock.now) : new NativeDate(...args);
+  }
+  FakeDate.prototype = NativeDate.prototype;
+  FakeDate.now = () => clock.now;
+  FakeDate.parse = NativeDate.parse;
+  FakeDate.UTC = NativeDate.UTC;
+
+  window.Date = FakeDate;
+  performance.now = () => clock.now - clock.perfOrigin;
+  window.setTimeout = (callback, delay, ...args) => schedule(callback, delay, args, false);
+  window.setInterval = (callback, delay, ...args) => schedule(callback, delay, args, true);
+  window.clearTimeout = (id) => { clock.timers.delete(id); };
+  window.clearInterval = (id) => { clock.timers.delete(id); };
+  window.requestAnimationFrame = (callback) => schedule(() => callback(performance.now()), 16, [], false);
+  window.cancelAnimationFrame = (id) => { clock.timers.delete(id); };
+
+  clock.perfOrigin = clock.now - nativePerformanceNow();
+  window.__llamaClock = {
+    tick: (ms) => runDue(clock.now + ms),
+    setSystemTime: (ms) => { clock.now = ms; },
+    runAll: () => {
+      let guard = 0;
+      while (clock.timers.size > 0 && guard++ < 10000) {
+        const next = Math.min(...Array.from(clock.timers.values(), (t) => t.at));
+        runDue(Math.max(clock.now, next));
+      }
+    },
+    now: () => clock.now,
+    pending: () => clock.timers.size,
+  };
+})();
+"#;
+
+/// Expression referring to the installed clock, throwing in the page if missing.
+const CLOCK_HANDLE: &str =
+    "(window.__llamaClock || (() => { throw new Error('Clock is not installed; call clock().install() first'); })())";
+
+/// Controls the fake clock of a page.
+///
+/// Obtained through [`Page::clock`]. Nothing is patched until
+/// [`Clock::install`] is called.
+#[derive(Debug)]
+pub struct Clock<'a> {
+    page: &'a Page,
+}
+
+impl<'a> Clock<'a> {
+    pub(crate) fn new(page: &'a Page) -> Self {
+        Self { page }
+    }
+
+    /// Replaces the page's time sources with a fake clock starting at `start_time`.
+    ///
+    /// The clock is installed in the current document and in every document
+    /// loaded afterwards. Time only moves when [`Clock::tick`] or
+    /// [`Clock::set_system_time`] is called.
+    pub async fn install(&self, start_time: DateTime<Utc>) -> Result<()> {
+        info!("Installing fake clock at {}", start_time);
+
+        let script = install_script(start_time);
+        let params = serde_json::json!({ "source": script });
+        self.page
+            .send_session_command("Page.addScriptToEvaluateOnNewDocument", Some(params))
+            .await?;
+
+        let _: serde_json::Value = self.page.evaluate(&script).await?;
+        Ok(())
+    }
+
+    /// Advances the clock, firing every timer that comes due in order.
+    pub async fn tick(&self, duration: Duration) -> Result<()> {
+        let _: serde_json::Value = self
+            .page
+            .evaluate(&format!("{}.tick({})", CLOCK_HANDLE, duration.as_millis()))
+            .await?;
+        Ok(())
+    }
+
+    /// Jumps the clock to `time` without firing timers.
+    pub async fn set_system_time(&self, time: DateTime<Utc>) -> Result<()> {
+        let _: serde_json::Value = self
+            .page
+            .evaluate(&format!("{}.setSystemTime({})", CLOCK_HANDLE, time.timestamp_millis()))
+            .await?;
+        Ok(())
+    }
+
+    /// Fires every pending timer, advancing the clock as needed.
+    pub async fn run_all(&self) -> Result<()> {
+        let _: serde_json::Value = self.page.evaluate(&format!("{}.runAll()", CLOCK_HANDLE)).await?;
+        Ok(())
+    }
+
+    /// Returns the current time of the fake clock.
+    pub async fn now(&self) -> Result<DateTime<Utc>> {
+        let millis: i64 = self.page.evaluate(&format!("{}.now()", CLOCK_HANDLE)).await?;
+        DateTime::from_timestamp_millis(millis)
+            .ok_or_else(|| Error::JavaScriptError(format!("Invalid clock time: {}", millis)))
+    }
+}
+
+/// Builds the install script for the given start time.
+fn install_script(start_time: DateTime<Utc>) -> String {
+    CLOCK_SCRIPT.replace("__LLAMA_CLOCK_START__", &start_time.timestamp_millis().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_install_script_embeds_start_time() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let script = install_script(start);
+        assert!(script.contains("now: 1704067200000,"));
+        assert!(!script.contains("__LLAMA_CLOCK_START__"));
+    }
+}
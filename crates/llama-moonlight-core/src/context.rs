@@ -2,12 +2,17 @@
 //!
 //! This module provides functionality for browser contexts, which are similar to incognito windows.
 
+use crate::blocking::{install_interception, ResourceBlocker};
 use crate::errors::{Error, Result};
+use crate::event::{ContextEvent, EventEmitter};
 use crate::page::Page;
 use crate::protocol::Connection;
+use crate::quota::{BandwidthQuota, QuotaExceeded};
 use crate::options::{ContextOptions, PageOptions};
+use crate::storage_state::StorageState;
 use std::sync::Arc;
-use log::{debug, info};
+use tokio::sync::{broadcast, watch};
+use tracing::{debug, info, instrument};
 
 /// Represents a browser context (similar to an incognito window).
 #[derive(Debug)]
@@ -23,10 +28,17 @@ pub struct BrowserContext {
     
     /// Context options.
     pub(crate) options: ContextOptions,
+    
+    /// Transfer quota shared by the pages of the context.
+    pub(crate) quota: Option<Arc<BandwidthQuota>>,
+    
+    /// Emitter of the context's events.
+    pub(crate) events: EventEmitter<ContextEvent>,
 }
 
 impl BrowserContext {
     /// Creates a new page in the context.
+    #[instrument(skip(self), fields(context_id = %self.id))]
     pub async fn new_page(&self) -> Result<Page> {
         info!("Creating new page in context {}", self.id);
         
@@ -64,13 +76,22 @@ impl BrowserContext {
             context_id: self.id.clone(),
             browser_type: self.browser_type.clone(),
             options: PageOptions::default(),
+            llama_model: None,
+            events: EventEmitter::new(),
+            crash: Arc::new(watch::channel(None).0),
+            coverage_state: Default::default(),
         };
         
+        self.install_interception(&page).await?;
+        page.forward_protocol_events().await?;
+        self.events.emit(ContextEvent::Page { target_id: page.target_id.clone() });
+        
         info!("Successfully created page in context {}", self.id);
         Ok(page)
     }
     
     /// Creates a new page with the specified options.
+    #[instrument(skip(self, options), fields(context_id = %self.id))]
     pub async fn new_page_with_options(&self, options: PageOptions) -> Result<Page> {
         info!("Creating new page with options in context {}", self.id);
         
@@ -108,12 +129,63 @@ impl BrowserContext {
             context_id: self.id.clone(),
             browser_type: self.browser_type.clone(),
             options,
+            llama_model: None,
+            events: EventEmitter::new(),
+            crash: Arc::new(watch::channel(None).0),
+            coverage_state: Default::default(),
         };
         
+        self.install_interception(&page).await?;
+        page.forward_protocol_events().await?;
+        self.events.emit(ContextEvent::Page { target_id: page.target_id.clone() });
+        
         info!("Successfully created page with options in context {}", self.id);
         Ok(page)
     }
     
+    /// Starts request interception in a page, if blocking or a quota needs it.
+    async fn install_interception(&self, page: &Page) -> Result<()> {
+        let blocker = ResourceBlocker::from_options(&self.options);
+        if blocker.is_none() && self.quota.is_none() {
+            return Ok(());
+        }
+        
+        install_interception(page, blocker, self.quota.clone()).await
+    }
+    
+    /// Returns the bytes received by the pages of the context, if it has a transfer quota.
+    pub fn bytes_received(&self) -> Option<u64> {
+        self.quota.as_ref().map(|quota| quota.used_bytes())
+    }
+    
+    /// Subscribes to the event sent when the context uses up its transfer quota.
+    ///
+    /// Returns `None` if the context has no transfer quota.
+    pub fn on_quota_exceeded(&self) -> Option<broadcast::Receiver<QuotaExceeded>> {
+        self.quota.as_ref().map(|quota| quota.subscribe())
+    }
+    
+    /// Emits a [`ContextEvent::QuotaExceeded`] event when the transfer quota is used up.
+    pub(crate) fn forward_quota_events(&self) {
+        let mut receiver = match self.on_quota_exceeded() {
+            Some(receiver) => receiver,
+            None => return,
+        };
+        
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            // The quota is exceeded at most once
+            if let Ok(exceeded) = receiver.recv().await {
+                events.emit(ContextEvent::QuotaExceeded(exceeded));
+            }
+        });
+    }
+    
+    /// Returns the emitter of the context's events.
+    pub fn events(&self) -> &EventEmitter<ContextEvent> {
+        &self.events
+    }
+    
     /// Closes the context.
     pub async fn close(&self) -> Result<()> {
         info!("Closing context {}", self.id);
@@ -127,6 +199,7 @@ impl BrowserContext {
             Some(params),
         ).await?;
         
+        self.events.emit(ContextEvent::Close);
         info!("Context {} closed", self.id);
         Ok(())
     }
@@ -154,6 +227,32 @@ impl BrowserContext {
         Ok(())
     }
     
+    /// Returns the cookies of the context as a storage state.
+    ///
+    /// Local storage lives in pages, so `origins` is left empty.
+    pub async fn storage_state(&self) -> Result<StorageState> {
+        let params = serde_json::json!({
+            "browserContextId": self.id,
+        });
+        
+        let result = self.connection.send_request(
+            "Storage.getCookies".to_string(),
+            Some(params),
+        ).await?;
+        
+        let cookies = serde_json::from_value(result["cookies"].clone())?;
+        Ok(StorageState { cookies, origins: Vec::new() })
+    }
+    
+    /// Sets the cookies of a storage state, e.g. one saved from another context.
+    pub async fn set_storage_state(&self, state: &StorageState) -> Result<()> {
+        if !state.origins.is_empty() {
+            debug!("Local storage of {} origins is not applied to context {}", state.origins.len(), self.id);
+        }
+        
+        self.set_cookies(state.cookies.clone()).await
+    }
+    
     /// Clears cookies for the context.
     pub async fn clear_cookies(&self) -> Result<()> {
         info!("Clearing cookies for context {}", self.id);
@@ -171,14 +270,29 @@ impl BrowserContext {
         Ok(())
     }
     
-    /// Grants permissions to the context.
-    pub async fn grant_permissions(&self, permissions: Vec<String>) -> Result<()> {
-        info!("Granting permissions for context {}", self.id);
-        
-        let params = serde_json::json!({
-            "contextId": self.id,
-            "permissions": permissions,
+    /// Grants permissions to the context, for all origins or only `origin`.
+    ///
+    /// Accepts web permission names such as `"geolocation"`, `"notifications"`,
+    /// `"clipboard-read"`, and `"clipboard-write"`. Granted permissions never
+    /// show a prompt, so flows that request them don't stall in headless runs.
+    pub async fn grant_permissions(&self, permissions: &[&str], origin: Option<&str>) -> Result<()> {
+        info!("Granting permissions {:?} for context {}", permissions, self.id);
+        
+        let mut protocol_permissions = Vec::new();
+        for permission in permissions {
+            let mapped = permission_to_protocol(permission)?;
+            if !protocol_permissions.contains(&mapped) {
+                protocol_permissions.push(mapped);
+            }
+        }
+        
+        let mut params = serde_json::json!({
+            "browserContextId": self.id,
+            "permissions": protocol_permissions,
         });
+        if let Some(origin) = origin {
+            params["origin"] = serde_json::Value::String(origin.to_string());
+        }
         
         let _ = self.connection.send_request(
             "Browser.grantPermissions".to_string(),
@@ -189,6 +303,23 @@ impl BrowserContext {
         Ok(())
     }
     
+    /// Revokes every permission granted to the context.
+    pub async fn clear_permissions(&self) -> Result<()> {
+        info!("Clearing permissions for context {}", self.id);
+        
+        let params = serde_json::json!({
+            "browserContextId": self.id,
+        });
+        
+        let _ = self.connection.send_request(
+            "Browser.resetPermissions".to_string(),
+            Some(params),
+        ).await?;
+        
+        info!("Permissions cleared for context {}", self.id);
+        Ok(())
+    }
+    
     /// Sets geolocation for the context.
     pub async fn set_geolocation(&self, geolocation: crate::options::Geolocation) -> Result<()> {
         info!("Setting geolocation for context {}", self.id);
@@ -248,4 +379,39 @@ impl BrowserContext {
         info!("HAR exported for context {}", self.id);
         Ok(())
     }
-} 
\ No newline at end of file
+}
+
+/// Maps a web permission name to its protocol permission type.
+fn permission_to_protocol(permission: &str) -> Result<&'static str> {
+    let mapped = match permission {
+        "geolocation" => "geolocation",
+        "notifications" => "notifications",
+        "camera" => "videoCapture",
+        "microphone" => "audioCapture",
+        "midi" => "midi",
+        "midi-sysex" => "midiSysex",
+        "clipboard-read" => "clipboardReadWrite",
+        "clipboard-write" => "clipboardSanitizedWrite",
+        "background-sync" => "backgroundSync",
+        "persistent-storage" => "durableStorage",
+        "payment-handler" => "paymentHandler",
+        "accelerometer" | "gyroscope" | "magnetometer" | "ambient-light-sensor" => "sensors",
+        "idle-detection" => "idleDetection",
+        "storage-access" => "storageAccess",
+        _ => return Err(Error::Generic(format!("Unknown permission: {}", permission))),
+    };
+    Ok(mapped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_permission_to_protocol() {
+        assert_eq!(permission_to_protocol("clipboard-read").unwrap(), "clipboardReadWrite");
+        assert_eq!(permission_to_protocol("camera").unwrap(), "videoCapture");
+        assert_eq!(permission_to_protocol("gyroscope").unwrap(), "sensors");
+        assert!(permission_to_protocol("teleportation").is_err());
+    }
+}
@@ -0,0 +1,489 @@
+//! JavaScript and CSS coverage.
+//!
+//! Coverage tells which parts of each script and stylesheet a page actually
+//! used while it was collected. Resources that are fetched but barely used are
+//! candidates for [`ContextOptions::block_domains`](crate::ContextOptions::block_domains);
+//! heavily used ones are probably needed for the page to work.
+//!
+//! Offsets count characters of the resource's source text, which are bytes
+//! for ASCII sources.
+
+use crate::errors::{Error, Result};
+use crate::page::Page;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::info;
+
+/// Kind of resource covered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoverageKind {
+    /// A script.
+    Js,
+
+    /// A stylesheet.
+    Css,
+}
+
+/// A half-open range of offsets in a resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ByteRange {
+    /// First offset of the range
+    pub start: u64,
+
+    /// Offset just past the range
+    pub end: u64,
+}
+
+impl ByteRange {
+    /// Returns the length of the range.
+    pub fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    /// Returns whether the range is empty.
+    pub fn is_empty(&self) -> bool {
+        self.end <= self.start
+    }
+}
+
+/// Coverage of one script or stylesheet.
+///
+/// Inline scripts and stylesheets report the URL of their document, so
+/// several entries may share a URL.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageEntry {
+    /// URL the resource was loaded from
+    pub url: String,
+
+    /// Kind of resource
+    pub kind: CoverageKind,
+
+    /// Length of the resource's source text
+    pub total_bytes: u64,
+
+    /// Ranges that were used, in order
+    pub used: Vec<ByteRange>,
+
+    /// Ranges that were not used, in order
+    pub unused: Vec<ByteRange>,
+}
+
+impl CoverageEntry {
+    /// Builds an entry from the used ranges of a resource.
+    fn new(url: &str, kind: CoverageKind, total_bytes: u64, used: Vec<ByteRange>) -> Self {
+        let used = merge_ranges(used);
+        let unused = complement(&used, total_bytes);
+        Self {
+            url: url.to_string(),
+            kind,
+            total_bytes,
+            used,
+            unused,
+        }
+    }
+
+    /// Returns the number of used bytes.
+    pub fn used_bytes(&self) -> u64 {
+        self.used.iter().map(ByteRange::len).sum()
+    }
+
+    /// Returns the number of unused bytes.
+    pub fn unused_bytes(&self) -> u64 {
+        self.unused.iter().map(ByteRange::len).sum()
+    }
+}
+
+/// Used and total bytes of all the resources loaded from one URL.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UrlCoverage {
+    /// Number of scripts and stylesheets loaded from the URL
+    pub resources: usize,
+
+    /// Length of their source texts
+    pub total_bytes: u64,
+
+    /// Bytes that were used
+    pub used_bytes: u64,
+}
+
+impl UrlCoverage {
+    /// Returns the fraction of bytes used, 0 for empty resources.
+    pub fn used_ratio(&self) -> f64 {
+        if self.total_bytes == 0 {
+            0.0
+        } else {
+            self.used_bytes as f64 / self.total_bytes as f64
+        }
+    }
+}
+
+/// Coverage collected between starting and stopping.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CoverageReport {
+    /// Coverage of every script and stylesheet
+    pub entries: Vec<CoverageEntry>,
+}
+
+impl CoverageReport {
+    /// Returns the entries of one kind of resource.
+    pub fn of_kind(&self, kind: CoverageKind) -> impl Iterator<Item = &CoverageEntry> {
+        self.entries.iter().filter(move |entry| entry.kind == kind)
+    }
+
+    /// Sums the coverage of the resources loaded from each URL.
+    pub fn by_url(&self) -> BTreeMap<String, UrlCoverage> {
+        let mut by_url: BTreeMap<String, UrlCoverage> = BTreeMap::new();
+        for entry in &self.entries {
+            let summary = by_url.entry(entry.url.clone()).or_default();
+            summary.resources += 1;
+            summary.total_bytes += entry.total_bytes;
+            summary.used_bytes += entry.used_bytes();
+        }
+        by_url
+    }
+}
+
+/// Coverage being collected for a page.
+#[derive(Debug, Default)]
+pub(crate) struct CoverageState {
+    /// Whether script coverage was started
+    js: bool,
+
+    /// Collector of the stylesheets added since CSS coverage was started
+    css: Option<StyleSheetRecorder>,
+}
+
+/// Header of a stylesheet, from `CSS.styleSheetAdded`.
+#[derive(Debug, Clone, PartialEq)]
+struct StyleSheet {
+    url: String,
+    length: u64,
+}
+
+/// Collects the stylesheets of a page while CSS coverage runs.
+#[derive(Debug)]
+struct StyleSheetRecorder {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<HashMap<String, StyleSheet>>,
+}
+
+impl StyleSheetRecorder {
+    /// Starts collecting, before `CSS.enable` reports the existing stylesheets.
+    async fn start(page: &Page) -> Result<Self> {
+        let events = page.connection.subscribe("Target.receivedMessageFromTarget".to_string()).await?;
+
+        let (stop, stopped) = oneshot::channel();
+        let task = tokio::spawn(collect(events, page.session_id.clone(), stopped));
+        Ok(Self { stop, task })
+    }
+
+    /// Stops collecting and returns the stylesheets by ID.
+    async fn finish(self) -> HashMap<String, StyleSheet> {
+        let _ = self.stop.send(());
+        self.task.await.unwrap_or_default()
+    }
+}
+
+/// Records the stylesheets added in a session until told to stop.
+async fn collect(
+    mut events: mpsc::Receiver<crate::protocol::Event>,
+    session_id: String,
+    mut stopped: oneshot::Receiver<()>,
+) -> HashMap<String, StyleSheet> {
+    let mut sheets = HashMap::new();
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => match event {
+                Some(event) => event,
+                None => break,
+            },
+            _ = &mut stopped => break,
+        };
+        record_style_sheet(&mut sheets, &session_id, event);
+    }
+    while let Ok(event) = events.try_recv() {
+        record_style_sheet(&mut sheets, &session_id, event);
+    }
+    sheets
+}
+
+/// Records the stylesheet of a `CSS.styleSheetAdded` message of a session.
+fn record_style_sheet(sheets: &mut HashMap<String, StyleSheet>, session_id: &str, event: crate::protocol::Event) {
+    let params = match event.params {
+        Some(params) if params["sessionId"] == session_id => params,
+        _ => return,
+    };
+    let message: serde_json::Value = match params["message"].as_str().map(serde_json::from_str) {
+        Some(Ok(message)) => message,
+        _ => return,
+    };
+    if message["method"] != "CSS.styleSheetAdded" {
+        return;
+    }
+
+    let header = &message["params"]["header"];
+    if let Some(id) = header["styleSheetId"].as_str() {
+        sheets.insert(id.to_string(), StyleSheet {
+            url: header["sourceURL"].as_str().unwrap_or_default().to_string(),
+            length: header["length"].as_f64().unwrap_or(0.0) as u64,
+        });
+    }
+}
+
+/// Collects JavaScript and CSS coverage of a page.
+///
+/// Obtained through [`Page::coverage`]. Coverage runs from the `start_*`
+/// calls until [`Coverage::stop`], across navigations of the page.
+#[derive(Debug)]
+pub struct Coverage<'a> {
+    page: &'a Page,
+}
+
+impl<'a> Coverage<'a> {
+    pub(crate) fn new(page: &'a Page) -> Self {
+        Self { page }
+    }
+
+    /// Starts collecting which parts of each script run.
+    pub async fn start_js(&self) -> Result<()> {
+        info!("Starting JavaScript coverage for page {}", self.page.target_id);
+
+        self.page.send_session_command("Profiler.enable", None).await?;
+        let params = serde_json::json!({
+            "callCount": false,
+            "detailed": true,
+        });
+        self.page.send_session_command("Profiler.startPreciseCoverage", Some(params)).await?;
+
+        self.page.coverage_state.lock().unwrap().js = true;
+        Ok(())
+    }
+
+    /// Starts collecting which rules of each stylesheet match.
+    pub async fn start_css(&self) -> Result<()> {
+        info!("Starting CSS coverage for page {}", self.page.target_id);
+
+        let recorder = StyleSheetRecorder::start(self.page).await?;
+        self.page.send_session_command("DOM.enable", None).await?;
+        self.page.send_session_command("CSS.enable", None).await?;
+        self.page.send_session_command("CSS.startRuleUsageTracking", None).await?;
+
+        self.page.coverage_state.lock().unwrap().css = Some(recorder);
+        Ok(())
+    }
+
+    /// Stops all coverage collection and returns what was collected.
+    pub async fn stop(&self) -> Result<CoverageReport> {
+        let (js, css) = {
+            let mut state = self.page.coverage_state.lock().unwrap();
+            (std::mem::take(&mut state.js), state.css.take())
+        };
+        if !js && css.is_none() {
+            return Err(Error::Generic("Coverage was not started".to_string()));
+        }
+
+        let mut report = CoverageReport::default();
+
+        if js {
+            let result = self.page.send_session_command("Profiler.takePreciseCoverage", None).await?;
+            self.page.send_session_command("Profiler.stopPreciseCoverage", None).await?;
+            self.page.send_session_command("Profiler.disable", None).await?;
+            report.entries.extend(js_entries(&result["result"]));
+        }
+
+        if let Some(recorder) = css {
+            let result = self.page.send_session_command("CSS.stopRuleUsageTracking", None).await?;
+            let sheets = recorder.finish().await;
+            report.entries.extend(css_entries(&result["ruleUsage"], &sheets));
+        }
+
+        info!("Stopped coverage for page {} with {} entries", self.page.target_id, report.entries.len());
+        Ok(report)
+    }
+}
+
+/// Builds the entries of a `Profiler.takePreciseCoverage` result.
+///
+/// Scripts without a URL, such as the ones evaluated through the protocol,
+/// are skipped.
+fn js_entries(scripts: &serde_json::Value) -> Vec<CoverageEntry> {
+    let mut entries = Vec::new();
+
+    for script in scripts.as_array().into_iter().flatten() {
+        let url = script["url"].as_str().unwrap_or_default();
+        if url.is_empty() {
+            continue;
+        }
+
+        // Block ranges nest, an inner range overriding the count of the outer ones
+        let mut ranges = Vec::new();
+        for function in script["functions"].as_array().into_iter().flatten() {
+            for range in function["ranges"].as_array().into_iter().flatten() {
+                ranges.push((
+                    range["startOffset"].as_u64().unwrap_or(0),
+                    range["endOffset"].as_u64().unwrap_or(0),
+                    range["count"].as_u64().unwrap_or(0),
+                ));
+            }
+        }
+
+        // The first function is the whole script
+        let total = ranges.iter().map(|(_, end, _)| *end).max().unwrap_or(0);
+        entries.push(CoverageEntry::new(url, CoverageKind::Js, total, used_block_ranges(&ranges)));
+    }
+
+    entries
+}
+
+/// Returns the ranges covered by a block with a non-zero count, innermost blocks winning.
+fn used_block_ranges(ranges: &[(u64, u64, u64)]) -> Vec<ByteRange> {
+    // Ends sort before starts at the same offset, longer ranges opening first and closing last
+    let mut points: Vec<(u64, u8, u64, u64)> = Vec::with_capacity(ranges.len() * 2);
+    for &(start, end, count) in ranges.iter().filter(|(start, end, _)| start < end) {
+        points.push((start, 1, u64::MAX - (end - start), count));
+        points.push((end, 0, end - start, count));
+    }
+    points.sort();
+
+    let mut used = Vec::new();
+    let mut counts: Vec<u64> = Vec::new();
+    let mut last = 0;
+    for (offset, kind, _, count) in points {
+        if let Some(&current) = counts.last() {
+            if current > 0 && last < offset {
+                used.push(ByteRange { start: last, end: offset });
+            }
+        }
+        last = offset;
+        if kind == 0 {
+            counts.pop();
+        } else {
+            counts.push(count);
+        }
+    }
+
+    used
+}
+
+/// Builds the entries of a `CSS.stopRuleUsageTracking` result.
+fn css_entries(rules: &serde_json::Value, sheets: &HashMap<String, StyleSheet>) -> Vec<CoverageEntry> {
+    let mut used: HashMap<&str, Vec<ByteRange>> = HashMap::new();
+    for rule in rules.as_array().into_iter().flatten() {
+        let Some(id) = rule["styleSheetId"].as_str() else { continue };
+        let ranges = used.entry(id).or_default();
+        if rule["used"].as_bool().unwrap_or(false) {
+            ranges.push(ByteRange {
+                start: rule["startOffset"].as_f64().unwrap_or(0.0) as u64,
+                end: rule["endOffset"].as_f64().unwrap_or(0.0) as u64,
+            });
+        }
+    }
+
+    // Stylesheets without any rule usage were not used at all
+    let mut ids: Vec<&String> = sheets.keys().collect();
+    ids.sort();
+    ids.into_iter()
+        .map(|id| {
+            let sheet = &sheets[id];
+            let ranges = used.remove(id.as_str()).unwrap_or_default();
+            CoverageEntry::new(&sheet.url, CoverageKind::Css, sheet.length, ranges)
+        })
+        .collect()
+}
+
+/// Sorts ranges and joins the ones that overlap or touch.
+fn merge_ranges(mut ranges: Vec<ByteRange>) -> Vec<ByteRange> {
+    ranges.retain(|range| !range.is_empty());
+    ranges.sort_by_key(|range| range.start);
+
+    let mut merged: Vec<ByteRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Returns the parts of `0..total` not covered by sorted, disjoint ranges.
+fn complement(used: &[ByteRange], total: u64) -> Vec<ByteRange> {
+    let mut unused = Vec::new();
+    let mut start = 0;
+    for range in used {
+        if range.start > start {
+            unused.push(ByteRange { start, end: range.start.min(total) });
+        }
+        start = start.max(range.end);
+    }
+    if start < total {
+        unused.push(ByteRange { start, end: total });
+    }
+    unused.retain(|range| !range.is_empty());
+    unused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: u64, end: u64) -> ByteRange {
+        ByteRange { start, end }
+    }
+
+    #[test]
+    fn test_js_entries() {
+        let scripts = serde_json::json!([
+            {
+                "scriptId": "1",
+                "url": "https://example.com/app.js",
+                "functions": [
+                    { "functionName": "", "isBlockCoverage": true, "ranges": [
+                        { "startOffset": 0, "endOffset": 100, "count": 1 },
+                        { "startOffset": 20, "endOffset": 50, "count": 0 },
+                    ] },
+                    { "functionName": "handler", "isBlockCoverage": true, "ranges": [
+                        { "startOffset": 30, "endOffset": 40, "count": 1 },
+                        { "startOffset": 40, "endOffset": 45, "count": 0 },
+                    ] },
+                ],
+            },
+            { "scriptId": "2", "url": "", "functions": [] },
+        ]);
+
+        let entries = js_entries(&scripts);
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry.total_bytes, 100);
+        assert_eq!(entry.used, vec![range(0, 20), range(30, 40), range(50, 100)]);
+        assert_eq!(entry.unused, vec![range(20, 30), range(40, 50)]);
+        assert_eq!(entry.used_bytes(), 80);
+        assert_eq!(entry.unused_bytes(), 20);
+    }
+
+    #[test]
+    fn test_css_entries() {
+        let mut sheets = HashMap::new();
+        sheets.insert("1".to_string(), StyleSheet { url: "https://example.com/site.css".to_string(), length: 50 });
+        sheets.insert("2".to_string(), StyleSheet { url: "https://example.com/print.css".to_string(), length: 30 });
+        let rules = serde_json::json!([
+            { "styleSheetId": "1", "startOffset": 0.0, "endOffset": 10.0, "used": true },
+            { "styleSheetId": "1", "startOffset": 10.0, "endOffset": 20.0, "used": true },
+            { "styleSheetId": "1", "startOffset": 25.0, "endOffset": 40.0, "used": false },
+        ]);
+
+        let report = CoverageReport { entries: css_entries(&rules, &sheets) };
+        assert_eq!(report.entries.len(), 2);
+        assert_eq!(report.entries[0].used, vec![range(0, 20)]);
+        assert_eq!(report.entries[0].unused, vec![range(20, 50)]);
+        assert_eq!(report.entries[1].unused, vec![range(0, 30)]);
+
+        let by_url = report.by_url();
+        assert_eq!(by_url["https://example.com/site.css"].used_ratio(), 0.4);
+        assert_eq!(by_url["https://example.com/print.css"].used_bytes, 0);
+        assert_eq!(report.of_kind(CoverageKind::Js).count(), 0);
+    }
+}
@@ -0,0 +1,110 @@
+//! Page crashes.
+//!
+//! When the renderer of a page crashes or is killed, the page emits a
+//! [`PageEvent::Crash`](crate::PageEvent::Crash) carrying a [`PageCrash`], and
+//! navigations in progress or started afterwards fail with
+//! [`Error::PageCrashed`](crate::Error::PageCrashed) rather than running into
+//! their timeout. A crashed page cannot be used again: close it and open a
+//! new one, or recycle its browser.
+//!
+//! The page's session reports the crash (`Inspector.targetCrashed`), and the
+//! browser how the renderer ended (`Target.targetCrashed`), which tells
+//! renderers that ran out of memory or were killed apart from crashes.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+/// How long a crash reported by the page's session waits for the browser to
+/// report how the renderer ended.
+pub(crate) const TERMINATION_STATUS_GRACE: Duration = Duration::from_millis(200);
+
+/// How the renderer of a page ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrashReason {
+    /// The renderer crashed
+    Crashed,
+
+    /// The renderer ran out of memory
+    OutOfMemory,
+
+    /// The renderer was killed, e.g. by the operating system
+    Killed,
+}
+
+impl CrashReason {
+    /// Classifies the termination status the browser reports for a renderer,
+    /// such as `crashed`, `killed` or `oom`.
+    pub fn from_termination_status(status: &str) -> Self {
+        let status = status.to_ascii_lowercase();
+        if status.contains("oom") || status.contains("memory") {
+            Self::OutOfMemory
+        } else if status.contains("killed") {
+            Self::Killed
+        } else {
+            Self::Crashed
+        }
+    }
+}
+
+/// Crash of the renderer of a page.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageCrash {
+    /// How the renderer ended
+    pub reason: CrashReason,
+
+    /// Last URL the main frame navigated to, if any
+    pub url: Option<String>,
+}
+
+impl fmt::Display for PageCrash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reason {
+            CrashReason::Crashed => write!(f, "renderer crashed")?,
+            CrashReason::OutOfMemory => write!(f, "renderer ran out of memory")?,
+            CrashReason::Killed => write!(f, "renderer was killed")?,
+        }
+        match &self.url {
+            Some(url) => write!(f, " at {}", url),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns how the renderer of a target ended, from the params of a
+/// `Target.targetCrashed` event, if the event is about the target.
+pub(crate) fn termination_reason(params: &serde_json::Value, target_id: &str) -> Option<CrashReason> {
+    if params["targetId"].as_str() != Some(target_id) {
+        return None;
+    }
+    Some(CrashReason::from_termination_status(params["status"].as_str().unwrap_or_default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_termination_reason() {
+        assert_eq!(CrashReason::from_termination_status("oom"), CrashReason::OutOfMemory);
+        assert_eq!(CrashReason::from_termination_status("killed"), CrashReason::Killed);
+        assert_eq!(CrashReason::from_termination_status("crashed"), CrashReason::Crashed);
+        assert_eq!(CrashReason::from_termination_status(""), CrashReason::Crashed);
+
+        let params = json!({ "targetId": "A1", "status": "oom", "errorCode": -536870904 });
+        assert_eq!(termination_reason(&params, "A1"), Some(CrashReason::OutOfMemory));
+        assert_eq!(termination_reason(&params, "B2"), None);
+    }
+
+    #[test]
+    fn test_display() {
+        let crash = PageCrash {
+            reason: CrashReason::OutOfMemory,
+            url: Some("https://example.com/feed".to_string()),
+        };
+        assert_eq!(crash.to_string(), "renderer ran out of memory at https://example.com/feed");
+        assert_eq!(PageCrash { reason: CrashReason::Crashed, url: None }.to_string(), "renderer crashed");
+    }
+}
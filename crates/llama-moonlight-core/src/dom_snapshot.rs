@@ -0,0 +1,683 @@
+//! Normalized DOM snapshots and their diffs.
+//!
+//! A snapshot keeps the element tree of a document with sorted attributes and
+//! class names, collapsed whitespace, and without the attributes and elements
+//! that change on every load (nonces, framework bookkeeping, scripts). Two
+//! snapshots of the same page taken before and after a site deploy therefore
+//! only differ where the structure changed, and the diff names the changed
+//! elements by CSS paths that can be checked against extraction selectors.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+
+/// Collects the elements of the current document.
+///
+/// Elements are listed in document order with the index of their parent
+/// rather than nested, so deep documents do not exceed the recursion limits
+/// of the page or of the JSON parser.
+pub(crate) const DOM_SNAPSHOT_SCRIPT: &str = r#"
+(() => {
+  const nodes = [];
+  const stack = [[document.documentElement, null]];
+  while (stack.length > 0) {
+    const [element, parent] = stack.pop();
+    const index = nodes.length;
+    nodes.push({
+      tag: element.localName,
+      attributes: Array.from(element.attributes, (attribute) => [attribute.name, attribute.value]),
+      text: Array.from(element.childNodes)
+        .filter((node) => node.nodeType === Node.TEXT_NODE)
+        .map((node) => node.textContent)
+        .join(' '),
+      parent,
+    });
+    for (let i = element.children.length - 1; i >= 0; i--) {
+      stack.push([element.children[i], index]);
+    }
+  }
+  return { url: location.href, nodes };
+})()
+"#;
+
+/// Elements as reported by the page, in document order.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct RawSnapshot {
+    pub url: String,
+    pub nodes: Vec<RawNode>,
+}
+
+/// Element as reported by the page.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct RawNode {
+    pub tag: String,
+    pub attributes: Vec<(String, String)>,
+    pub text: String,
+    /// Index of the parent element, `None` for the root
+    pub parent: Option<usize>,
+}
+
+/// Siblings compared by longest common subsequence at most; longer lists
+/// are aligned greedily, as the table grows with the product of their lengths.
+const MAX_LCS_CELLS: usize = 250_000;
+
+/// What a DOM snapshot keeps.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnapshotOptions {
+    /// Attributes left out of the snapshot; a trailing `*` matches a prefix
+    pub volatile_attributes: Vec<String>,
+
+    /// Elements left out of the snapshot, with their subtrees
+    pub ignored_tags: Vec<String>,
+
+    /// Whether to keep the text of elements, so that content changes show up in diffs
+    pub include_text: bool,
+}
+
+impl Default for SnapshotOptions {
+    fn default() -> Self {
+        Self {
+            volatile_attributes: [
+                "nonce", "integrity", "style", "data-reactid", "data-reactroot", "data-v-*", "jsaction",
+                "data-ved", "csrf-token",
+            ]
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+            ignored_tags: ["script", "style", "noscript", "link", "meta"]
+                .iter()
+                .map(|tag| tag.to_string())
+                .collect(),
+            include_text: false,
+        }
+    }
+}
+
+impl SnapshotOptions {
+    /// Whether an attribute is left out of snapshots.
+    pub fn is_volatile(&self, name: &str) -> bool {
+        self.volatile_attributes.iter().any(|volatile| match volatile.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == volatile,
+        })
+    }
+}
+
+/// Element of a normalized DOM snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DomNode {
+    /// Lowercase tag name
+    pub tag: String,
+
+    /// Attributes by name, with class names sorted
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, String>,
+
+    /// Whitespace-collapsed text of the element's own text nodes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+
+    /// Child elements
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<DomNode>,
+}
+
+impl DomNode {
+    /// Normalizes an element reported by the page, without its children.
+    pub(crate) fn from_raw(raw: RawNode, options: &SnapshotOptions) -> Self {
+        let attributes = raw.attributes.into_iter()
+            .filter(|(name, _)| !options.is_volatile(name))
+            .map(|(name, value)| {
+                let value = if name == "class" { normalize_classes(&value) } else { value };
+                (name, value)
+            })
+            .collect();
+
+        let text = Some(collapse_whitespace(&raw.text))
+            .filter(|text| options.include_text && !text.is_empty());
+
+        Self {
+            tag: raw.tag.to_ascii_lowercase(),
+            attributes,
+            text,
+            children: Vec::new(),
+        }
+    }
+
+    /// Returns the CSS selector of the element by tag, id and classes.
+    pub fn selector(&self) -> String {
+        let mut selector = self.tag.clone();
+        if let Some(id) = self.attributes.get("id").filter(|id| !id.is_empty()) {
+            selector.push('#');
+            selector.push_str(id);
+        }
+        for class in self.attributes.get("class").into_iter().flat_map(|c| c.split(' ')).filter(|c| !c.is_empty()) {
+            selector.push('.');
+            selector.push_str(class);
+        }
+        selector
+    }
+
+    /// Returns the number of elements in the subtree, including this one.
+    pub fn element_count(&self) -> usize {
+        1 + self.children.iter().map(DomNode::element_count).sum::<usize>()
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let indent = "  ".repeat(depth);
+        write!(f, "{}<{}", indent, self.tag)?;
+        for (name, value) in &self.attributes {
+            write!(f, " {}=\"{}\"", name, value.replace('"', "&quot;"))?;
+        }
+        writeln!(f, ">")?;
+        if let Some(text) = &self.text {
+            writeln!(f, "{}  {:?}", indent, text)?;
+        }
+        for child in &self.children {
+            child.write(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Normalized DOM of a page at one point in time.
+///
+/// Snapshots serialize to JSON so monitoring jobs can store them and diff
+/// later runs against them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DomSnapshot {
+    /// URL of the document
+    pub url: String,
+
+    /// Root element, normally `html`
+    pub root: DomNode,
+}
+
+impl DomSnapshot {
+    /// Normalizes the elements reported by the page into a tree.
+    ///
+    /// Parents come before their children in the list, so the tree is built
+    /// from the end without recursing.
+    pub(crate) fn from_raw(raw: RawSnapshot, options: &SnapshotOptions) -> Self {
+        let is_ignored = |tag: &str| options.ignored_tags.iter().any(|ignored| ignored.eq_ignore_ascii_case(tag));
+
+        // Ignored elements are left out with their subtrees
+        let mut ignored = vec![false; raw.nodes.len()];
+        for (index, node) in raw.nodes.iter().enumerate() {
+            let parent_ignored = node.parent.is_some_and(|parent| ignored.get(parent).copied().unwrap_or(true));
+            ignored[index] = parent_ignored || (node.parent.is_some() && is_ignored(&node.tag));
+        }
+
+        let mut parents = Vec::with_capacity(raw.nodes.len());
+        let mut nodes: Vec<Option<DomNode>> = raw.nodes.into_iter()
+            .zip(&ignored)
+            .map(|(node, ignored)| {
+                parents.push(node.parent);
+                Some(DomNode::from_raw(node, options)).filter(|_| !ignored)
+            })
+            .collect();
+
+        for index in (1..nodes.len()).rev() {
+            let (mut node, parent) = match (nodes[index].take(), parents[index]) {
+                (Some(node), Some(parent)) if parent < index => (node, parent),
+                _ => continue,
+            };
+            // Children were attached last to first
+            node.children.reverse();
+            if let Some(parent) = nodes[parent].as_mut() {
+                parent.children.push(node);
+            }
+        }
+
+        let mut root = nodes.into_iter().next().flatten().unwrap_or_else(|| DomNode {
+            tag: "html".to_string(),
+            attributes: BTreeMap::new(),
+            text: None,
+            children: Vec::new(),
+        });
+        root.children.reverse();
+
+        Self { url: raw.url, root }
+    }
+
+    /// Returns the changes from this snapshot to a later one.
+    pub fn diff(&self, later: &DomSnapshot) -> DomDiff {
+        let mut changes = Vec::new();
+        let path = self.root.selector();
+        if self.root.tag == later.root.tag {
+            diff_nodes(&self.root, &later.root, &path, &mut changes);
+        } else {
+            changes.push(DomChange::Removed { path: path.clone(), element: self.root.selector() });
+            changes.push(DomChange::Added { path: later.root.selector(), element: later.root.selector() });
+        }
+        DomDiff { changes }
+    }
+}
+
+/// Indented one element per line, stable across loads of an unchanged page.
+impl fmt::Display for DomSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.root.write(f, 0)
+    }
+}
+
+/// One difference between two DOM snapshots.
+///
+/// Paths are CSS selectors from the root, with `:nth-child` positions among
+/// the elements kept in the snapshot the element belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DomChange {
+    /// An element appeared
+    Added {
+        /// Path of the element in the later snapshot
+        path: String,
+        /// Selector of the element by tag, id and classes
+        element: String,
+    },
+
+    /// An element disappeared
+    Removed {
+        /// Path of the element, with its position in the earlier snapshot
+        path: String,
+        /// Selector of the element by tag, id and classes
+        element: String,
+    },
+
+    /// An attribute was added, removed or changed
+    AttributeChanged {
+        /// Path of the element in the later snapshot
+        path: String,
+        /// Attribute name
+        name: String,
+        /// Earlier value, if the attribute was set
+        before: Option<String>,
+        /// Later value, if the attribute is set
+        after: Option<String>,
+    },
+
+    /// The text of an element changed
+    TextChanged {
+        /// Path of the element in the later snapshot
+        path: String,
+        /// Earlier text
+        before: Option<String>,
+        /// Later text
+        after: Option<String>,
+    },
+}
+
+impl DomChange {
+    /// Returns the path of the changed element.
+    pub fn path(&self) -> &str {
+        match self {
+            DomChange::Added { path, .. }
+            | DomChange::Removed { path, .. }
+            | DomChange::AttributeChanged { path, .. }
+            | DomChange::TextChanged { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for DomChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DomChange::Added { path, .. } => write!(f, "+ {}", path),
+            DomChange::Removed { path, .. } => write!(f, "- {}", path),
+            DomChange::AttributeChanged { path, name, before, after } => {
+                write!(f, "~ {} [{}] {:?} -> {:?}", path, name, before, after)
+            }
+            DomChange::TextChanged { path, before, after } => {
+                write!(f, "~ {} text {:?} -> {:?}", path, before, after)
+            }
+        }
+    }
+}
+
+/// Changes between two DOM snapshots.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DomDiff {
+    /// Changes in document order
+    pub changes: Vec<DomChange>,
+}
+
+impl DomDiff {
+    /// Whether the snapshots have the same structure.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Returns the changes whose path mentions any of the given fragments.
+    ///
+    /// Fragments are parts of extraction selectors such as `#price` or
+    /// `.product-title`, so a monitoring job can check the changes that
+    /// touch the elements it extracts.
+    pub fn touching<'a>(&'a self, fragments: &'a [&'a str]) -> impl Iterator<Item = &'a DomChange> + 'a {
+        self.changes.iter().filter(move |change| {
+            let path = change.path();
+            fragments.iter().any(|fragment| path.contains(fragment))
+        })
+    }
+}
+
+impl fmt::Display for DomDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// Records the changes between two elements with the same tag.
+fn diff_nodes(before: &DomNode, after: &DomNode, path: &str, changes: &mut Vec<DomChange>) {
+    let names: std::collections::BTreeSet<&String> = before.attributes.keys().chain(after.attributes.keys()).collect();
+    for name in names {
+        let (old, new) = (before.attributes.get(name), after.attributes.get(name));
+        if old != new {
+            changes.push(DomChange::AttributeChanged {
+                path: path.to_string(),
+                name: name.clone(),
+                before: old.cloned(),
+                after: new.cloned(),
+            });
+        }
+    }
+
+    if before.text != after.text {
+        changes.push(DomChange::TextChanged {
+            path: path.to_string(),
+            before: before.text.clone(),
+            after: after.text.clone(),
+        });
+    }
+
+    // Pair up children by selector so an insertion doesn't mark every later sibling as changed
+    let old_keys: Vec<String> = before.children.iter().map(DomNode::selector).collect();
+    let new_keys: Vec<String> = after.children.iter().map(DomNode::selector).collect();
+    let pairs = match_siblings(&old_keys, &new_keys);
+
+    let child_path = |node: &DomNode, index: usize| format!("{} > {}:nth-child({})", path, node.selector(), index + 1);
+    let (mut i, mut j) = (0, 0);
+    for (old_index, new_index) in pairs.into_iter().chain(std::iter::once((old_keys.len(), new_keys.len()))) {
+        for removed in &before.children[i..old_index] {
+            changes.push(DomChange::Removed { path: child_path(removed, i), element: removed.selector() });
+            i += 1;
+        }
+        for added in &after.children[j..new_index] {
+            changes.push(DomChange::Added { path: child_path(added, j), element: added.selector() });
+            j += 1;
+        }
+        if old_index < old_keys.len() {
+            let (old, new) = (&before.children[old_index], &after.children[new_index]);
+            diff_nodes(old, new, &child_path(new, new_index), changes);
+            i += 1;
+            j += 1;
+        }
+    }
+}
+
+/// Returns the index pairs of siblings with the same key, in order.
+///
+/// Unchanged runs at the start and end are paired first, which covers most
+/// page updates. The rest is aligned by longest common subsequence when it
+/// is small enough, and greedily by key otherwise.
+fn match_siblings(a: &[String], b: &[String]) -> Vec<(usize, usize)> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_middle, b_middle) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let middle = if a_middle.len().saturating_mul(b_middle.len()) <= MAX_LCS_CELLS {
+        longest_common_subsequence(a_middle, b_middle)
+    } else {
+        greedy_matches(a_middle, b_middle)
+    };
+
+    (0..prefix).map(|i| (i, i))
+        .chain(middle.into_iter().map(|(i, j)| (prefix + i, prefix + j)))
+        .chain((0..suffix).map(|k| (a.len() - suffix + k, b.len() - suffix + k)))
+        .collect()
+}
+
+/// Pairs each key with the next unpaired occurrence in `b`, in linear time.
+fn greedy_matches(a: &[String], b: &[String]) -> Vec<(usize, usize)> {
+    let mut positions: HashMap<&str, VecDeque<usize>> = HashMap::new();
+    for (j, key) in b.iter().enumerate() {
+        positions.entry(key.as_str()).or_default().push_back(j);
+    }
+
+    let mut pairs = Vec::new();
+    let mut next = 0;
+    for (i, key) in a.iter().enumerate() {
+        let queue = match positions.get_mut(key.as_str()) {
+            Some(queue) => queue,
+            None => continue,
+        };
+        while queue.front().is_some_and(|&j| j < next) {
+            queue.pop_front();
+        }
+        if let Some(j) = queue.pop_front() {
+            pairs.push((i, j));
+            next = j + 1;
+        }
+    }
+    pairs
+}
+
+/// Returns the index pairs of a longest common subsequence of two lists.
+fn longest_common_subsequence(a: &[String], b: &[String]) -> Vec<(usize, usize)> {
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// Sorts and deduplicates the names of a `class` attribute.
+fn normalize_classes(value: &str) -> String {
+    let mut classes: Vec<&str> = value.split_whitespace().collect();
+    classes.sort_unstable();
+    classes.dedup();
+    classes.join(" ")
+}
+
+/// Trims text and collapses runs of whitespace to single spaces.
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Element with children, flattened like the page reports it by [`flatten`].
+    struct Tree {
+        node: RawNode,
+        children: Vec<Tree>,
+    }
+
+    fn raw(tag: &str, attributes: &[(&str, &str)], children: Vec<Tree>) -> Tree {
+        Tree {
+            node: RawNode {
+                tag: tag.to_string(),
+                attributes: attributes.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect(),
+                text: String::new(),
+                parent: None,
+            },
+            children,
+        }
+    }
+
+    fn flatten(tree: Tree, parent: Option<usize>, nodes: &mut Vec<RawNode>) {
+        let index = nodes.len();
+        nodes.push(RawNode { parent, ..tree.node });
+        for child in tree.children {
+            flatten(child, Some(index), nodes);
+        }
+    }
+
+    fn snapshot(root: Tree) -> DomSnapshot {
+        let mut nodes = Vec::new();
+        flatten(root, None, &mut nodes);
+        DomSnapshot::from_raw(
+            RawSnapshot { url: "https://example.com/".to_string(), nodes },
+            &SnapshotOptions::default(),
+        )
+    }
+
+    fn page(items: Vec<Tree>) -> DomSnapshot {
+        snapshot(raw("html", &[], vec![
+            raw("head", &[], vec![raw("script", &[("nonce", "a1")], vec![])]),
+            raw("body", &[], vec![raw("ul", &[("id", "items")], items)]),
+        ]))
+    }
+
+    #[test]
+    fn test_normalization_is_stable() {
+        let first = snapshot(raw("HTML", &[("lang", "en")], vec![raw(
+            "div",
+            &[("class", "b  a b"), ("data-v-3f2a", ""), ("nonce", "x"), ("id", "main")],
+            vec![raw("script", &[], vec![])],
+        )]));
+        let second = snapshot(raw("html", &[("lang", "en")], vec![raw(
+            "div",
+            &[("id", "main"), ("nonce", "y"), ("class", "a b"), ("data-v-9c1d", "")],
+            vec![],
+        )]));
+
+        assert_eq!(first, second);
+        assert_eq!(first.to_string(), "<html lang=\"en\">\n  <div class=\"a b\" id=\"main\">\n");
+        assert_eq!(first.root.children[0].selector(), "div#main.a.b");
+        assert!(first.diff(&second).is_empty());
+    }
+
+    #[test]
+    fn test_text_is_optional() {
+        let mut node = raw("p", &[], vec![]).node;
+        node.text = "  Price:\n  $10 ".to_string();
+
+        assert_eq!(DomNode::from_raw(node.clone(), &SnapshotOptions::default()).text, None);
+
+        let options = SnapshotOptions { include_text: true, ..Default::default() };
+        assert_eq!(DomNode::from_raw(node, &options).text.as_deref(), Some("Price: $10"));
+    }
+
+    #[test]
+    fn test_diff_aligns_siblings() {
+        let item = |class: &str| raw("li", &[("class", class)], vec![raw("span", &[("class", "price")], vec![])]);
+        let before = page(vec![item("first"), item("second")]);
+        let after = page(vec![
+            raw("li", &[("class", "ad")], vec![]),
+            item("first"),
+            raw("li", &[("class", "second")], vec![raw("span", &[("class", "cost")], vec![])]),
+        ]);
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.changes, vec![
+            DomChange::Added {
+                path: "html > body:nth-child(2) > ul#items:nth-child(1) > li.ad:nth-child(1)".to_string(),
+                element: "li.ad".to_string(),
+            },
+            DomChange::Removed {
+                path: "html > body:nth-child(2) > ul#items:nth-child(1) > li.second:nth-child(3) > span.price:nth-child(1)".to_string(),
+                element: "span.price".to_string(),
+            },
+            DomChange::Added {
+                path: "html > body:nth-child(2) > ul#items:nth-child(1) > li.second:nth-child(3) > span.cost:nth-child(1)".to_string(),
+                element: "span.cost".to_string(),
+            },
+        ]);
+
+        assert_eq!(diff.touching(&[".price"]).count(), 1);
+        assert_eq!(diff.touching(&["#reviews"]).count(), 0);
+    }
+
+    #[test]
+    fn test_diff_attributes() {
+        let before = page(vec![raw("li", &[("data-sku", "1")], vec![])]);
+        let after = page(vec![raw("li", &[("data-sku", "2"), ("hidden", "")], vec![])]);
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.changes.len(), 2);
+        assert!(matches!(
+            &diff.changes[0],
+            DomChange::AttributeChanged { name, before: Some(_), after: Some(_), .. } if name == "data-sku"
+        ));
+        assert!(matches!(
+            &diff.changes[1],
+            DomChange::AttributeChanged { name, before: None, .. } if name == "hidden"
+        ));
+
+        let json = serde_json::to_string(&before).unwrap();
+        assert_eq!(serde_json::from_str::<DomSnapshot>(&json).unwrap(), before);
+    }
+
+    #[test]
+    fn test_deep_documents() {
+        // Reported flat, so depth is not limited by the JSON parser
+        let depth = 5_000;
+        let nodes: Vec<serde_json::Value> = (0..depth)
+            .map(|i| serde_json::json!({
+                "tag": if i == 0 { "html" } else { "div" },
+                "attributes": [],
+                "text": "",
+                "parent": if i == 0 { None } else { Some(i - 1) },
+            }))
+            .collect();
+        let json = serde_json::json!({ "url": "https://example.com/", "nodes": nodes }).to_string();
+        let raw: RawSnapshot = serde_json::from_str(&json).unwrap();
+
+        let snapshot = DomSnapshot::from_raw(raw, &SnapshotOptions::default());
+        let mut node = &snapshot.root;
+        let mut count = 1;
+        while let Some(child) = node.children.first() {
+            node = child;
+            count += 1;
+        }
+        assert_eq!(count, depth);
+    }
+
+    #[test]
+    fn test_long_sibling_lists() {
+        let keys = |count: usize| (0..count).map(|i| format!("li.item-{}", i % 7)).collect::<Vec<_>>();
+        let before = keys(20_000);
+        let mut after = before.clone();
+        after.insert(10_000, "li.ad".to_string());
+        after[15_000] = "li.changed".to_string();
+
+        let pairs = match_siblings(&before, &after);
+        assert_eq!(pairs.len(), before.len() - 1);
+        assert!(pairs.windows(2).all(|pair| pair[0].0 < pair[1].0 && pair[0].1 < pair[1].1));
+        assert!(pairs.iter().all(|&(i, j)| before[i] == after[j]));
+
+        // Large unaligned middles fall back to greedy matching
+        let shuffled: Vec<String> = (0..1_000).map(|i| format!("li.item-{}", (i * 7919) % 1_000)).collect();
+        let sorted: Vec<String> = (0..1_000).map(|i| format!("li.item-{}", i)).collect();
+        let pairs = match_siblings(&shuffled, &sorted);
+        assert!(pairs.windows(2).all(|pair| pair[0].0 < pair[1].0 && pair[0].1 < pair[1].1));
+    }
+}
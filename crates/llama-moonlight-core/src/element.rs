@@ -3,12 +3,121 @@
 //! This module provides functionality for interacting with DOM elements.
 
 use crate::errors::{Error, Result};
+use crate::js_handle;
 use crate::protocol::Connection;
 use crate::page::Page;
-use std::sync::Arc;
-use log::{debug, info, warn};
+use crate::selectors::Selectors;
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// A step on the way from the page's document to an element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveStep {
+    /// The match of a selector at `index` below the previous node.
+    Query {
+        /// Selector evaluated below the previous node.
+        selector: String,
+        
+        /// Index of the element among the selector's matches.
+        index: usize,
+    },
+    
+    /// The open shadow root of the previous node.
+    ShadowRoot,
+}
+
+/// How an element handle was obtained, replayed to find its element again.
+///
+/// When a single-page application re-renders, the nodes behind existing
+/// handles are detached and replaced by new ones. Following the same steps
+/// from the document then leads to the replacement.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ElementOrigin {
+    /// Steps from the document to the element.
+    steps: Vec<ResolveStep>,
+}
+
+impl ElementOrigin {
+    /// Returns the origin of the document itself.
+    pub(crate) fn document() -> Self {
+        Self::default()
+    }
+    
+    /// Returns the origin of a match of a selector evaluated against the document.
+    pub(crate) fn query(selector: &str, index: usize) -> Self {
+        Self::document().then_query(selector, index)
+    }
+    
+    /// Returns the origin of a match of a selector evaluated below this node.
+    pub(crate) fn then_query(&self, selector: &str, index: usize) -> Self {
+        self.then(ResolveStep::Query {
+            selector: selector.to_string(),
+            index,
+        })
+    }
+    
+    /// Returns the origin of a node reached from this one.
+    pub(crate) fn then(&self, step: ResolveStep) -> Self {
+        let mut steps = self.steps.clone();
+        steps.push(step);
+        Self { steps }
+    }
+    
+    /// Returns the steps from the document to the element.
+    pub fn steps(&self) -> &[ResolveStep] {
+        &self.steps
+    }
+    
+    /// Returns a JavaScript expression evaluating to the element, or `null` if it is gone.
+    pub(crate) fn resolve_expression(&self) -> Result<String> {
+        let mut body = String::from("let node = document;\n");
+        for step in &self.steps {
+            match step {
+                ResolveStep::Query { selector, index: 0 } => {
+                    body.push_str(&format!("node = ({})(node);\n", Selectors::query_function(selector, false)?));
+                },
+                ResolveStep::Query { selector, index } => {
+                    body.push_str(&format!("node = ({})(node)[{}];\n", Selectors::query_function(selector, true)?, index));
+                },
+                ResolveStep::ShadowRoot => body.push_str("node = node.shadowRoot;\n"),
+            }
+            body.push_str("if (!node) { return null; }\n");
+        }
+        body.push_str("return node;");
+        
+        Ok(format!("(() => {{\n{}\n}})()", body))
+    }
+}
+
+impl fmt::Display for ElementOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.steps.is_empty() {
+            return write!(f, "document");
+        }
+        
+        for (i, step) in self.steps.iter().enumerate() {
+            if i > 0 {
+                write!(f, " > ")?;
+            }
+            match step {
+                ResolveStep::Query { selector, index: 0 } => write!(f, "'{}'", selector)?,
+                ResolveStep::Query { selector, index } => write!(f, "'{}' [{}]", selector, index)?,
+                ResolveStep::ShadowRoot => write!(f, "shadow root")?,
+            }
+        }
+        Ok(())
+    }
+}
 
 /// Represents a handle to a DOM element.
+///
+/// Handles remember the selectors they were found with. With
+/// `PageOptions::reresolve_detached_elements` enabled, commands on a handle
+/// whose node was detached, e.g. by a re-render, find the element again from
+/// the document.
 #[derive(Debug)]
 pub struct ElementHandle<'a> {
     /// Connection to the browser.
@@ -22,9 +131,32 @@ pub struct ElementHandle<'a> {
     
     /// Reference to the page.
     pub(crate) page: &'a Page,
+    
+    /// How the element was found.
+    pub(crate) origin: ElementOrigin,
+    
+    /// Object ID of the node the element was found again as, after its own was detached.
+    pub(crate) current_id: Mutex<Option<String>>,
 }
 
 impl<'a> ElementHandle<'a> {
+    /// Creates a handle for an element found on the page.
+    pub(crate) fn new(page: &'a Page, object_id: &str, origin: ElementOrigin) -> Self {
+        Self {
+            connection: page.connection.clone(),
+            session_id: page.session_id.clone(),
+            object_id: object_id.to_string(),
+            page,
+            origin,
+            current_id: Mutex::new(None),
+        }
+    }
+    
+    /// Returns how the element was found.
+    pub fn origin(&self) -> &ElementOrigin {
+        &self.origin
+    }
+    
     /// Clicks the element.
     pub async fn click(&self) -> Result<()> {
         info!("Clicking element with object ID {}", self.object_id);
@@ -69,6 +201,105 @@ impl<'a> ElementHandle<'a> {
         Ok(())
     }
     
+    /// Types text into the element, pausing after each character.
+    ///
+    /// `delays` holds the pause after each character, e.g. human typing
+    /// delays; characters beyond its end are typed without pausing.
+    pub async fn type_text_with_delays(&self, text: &str, delays: &[Duration]) -> Result<()> {
+        info!("Typing text with delays into element with object ID {}", self.object_id);
+        
+        let _ = self.focus().await?;
+        
+        for (i, c) in text.chars().enumerate() {
+            let params = serde_json::json!({
+                "text": c.to_string(),
+            });
+            
+            let _ = self.send_session_command("Input.dispatchKeyEvent", Some(params)).await?;
+            
+            if let Some(delay) = delays.get(i) {
+                tokio::time::sleep(*delay).await;
+            }
+        }
+        
+        info!("Typed text with delays into element with object ID {}", self.object_id);
+        Ok(())
+    }
+    
+    /// Selects the options of a `<select>` element matching the given values or labels.
+    ///
+    /// Other options are deselected. Returns the values of the selected options.
+    pub async fn select_option(&self, values: &[String]) -> Result<Vec<String>> {
+        info!("Selecting options {:?} of element with object ID {}", values, self.object_id);
+        
+        let declaration = r#"function(values) {
+            if (!(this instanceof HTMLSelectElement)) {
+                throw new Error('Element is not a <select>');
+            }
+            const selected = [];
+            for (const option of this.options) {
+                option.selected = (this.multiple || selected.length === 0)
+                    && (values.includes(option.value) || values.includes(option.label));
+                if (option.selected) {
+                    selected.push(option.value);
+                }
+            }
+            this.dispatchEvent(new Event('input', { bubbles: true }));
+            this.dispatchEvent(new Event('change', { bubbles: true }));
+            return selected;
+        }"#;
+        let value = self.call_function(declaration, vec![serde_json::json!(values)]).await?;
+        let selected: Vec<String> = serde_json::from_value(value)?;
+        
+        if selected.is_empty() && !values.is_empty() {
+            return Err(Error::ElementNotFoundError(format!("No option matches {:?}", values)));
+        }
+        
+        info!("Selected options {:?} of element with object ID {}", selected, self.object_id);
+        Ok(selected)
+    }
+    
+    /// Checks or unchecks a checkbox or radio button, clicking it if its state differs.
+    pub async fn set_checked(&self, checked: bool) -> Result<()> {
+        info!("Setting checked state of element with object ID {} to {}", self.object_id, checked);
+        
+        let declaration = r#"function() {
+            if (!('checked' in this)) {
+                throw new Error('Element is not a checkbox or radio button');
+            }
+            return this.checked;
+        }"#;
+        let current = self.call_function(declaration, Vec::new()).await?.as_bool().unwrap_or(false);
+        
+        if current != checked {
+            self.click().await?;
+        }
+        
+        info!("Set checked state of element with object ID {} to {}", self.object_id, checked);
+        Ok(())
+    }
+    
+    /// Sets the files of a file input element.
+    pub async fn set_input_files<P: AsRef<Path>>(&self, paths: &[P]) -> Result<()> {
+        info!("Setting {} files of element with object ID {}", paths.len(), self.object_id);
+        
+        // The browser reads the files itself, so it needs absolute paths
+        let files = paths
+            .iter()
+            .map(|path| std::fs::canonicalize(path).map(|path| path.to_string_lossy().into_owned()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        
+        let params = serde_json::json!({
+            "objectId": self.object_id,
+            "files": files,
+        });
+        
+        let _ = self.send_session_command("DOM.setFileInputFiles", Some(params)).await?;
+        
+        info!("Set {} files of element with object ID {}", files.len(), self.object_id);
+        Ok(())
+    }
+    
     /// Gets the text content of the element.
     pub async fn text_content(&self) -> Result<String> {
         info!("Getting text content of element with object ID {}", self.object_id);
@@ -285,6 +516,185 @@ impl<'a> ElementHandle<'a> {
         Ok(())
     }
     
+    /// Returns the element's open shadow root, if it has one.
+    ///
+    /// The returned handle refers to the shadow root itself, so
+    /// [`ElementHandle::query_selector`] on it searches inside the component.
+    /// Closed shadow roots are not accessible and yield `None`.
+    pub async fn shadow_root(&self) -> Result<Option<ElementHandle<'a>>> {
+        info!("Getting shadow root of element with object ID {}", self.object_id);
+        
+        let params = serde_json::json!({
+            "objectId": self.object_id,
+            "functionDeclaration": "function() { return this.shadowRoot; }",
+            "returnByValue": false,
+        });
+        
+        let result = self.send_session_command("Runtime.callFunctionOn", Some(params)).await?;
+        
+        // Check if there was an error
+        if let Some(error) = result["exceptionDetails"].as_object() {
+            let error_message = error["exception"]["description"].as_str()
+                .unwrap_or("Unknown error getting shadow root");
+            
+            return Err(Error::JavaScriptError(error_message.to_string()));
+        }
+        
+        match result["result"]["objectId"].as_str() {
+            Some(id) => Ok(Some(self.handle(id, self.origin.then(ResolveStep::ShadowRoot)))),
+            None => {
+                debug!("Element with object ID {} has no open shadow root", self.object_id);
+                Ok(None)
+            }
+        }
+    }
+    
+    /// Finds the first element matching the selector below this element.
+    pub async fn query_selector(&self, selector: &str) -> Result<Option<ElementHandle<'a>>> {
+        info!("Finding element with selector '{}' below element with object ID {}", selector, self.object_id);
+        
+        let result = self.call_query(selector, false).await?;
+        
+        Ok(result["result"]["objectId"].as_str().map(|id| self.handle(id, self.origin.then_query(selector, 0))))
+    }
+    
+    /// Finds all elements matching the selector below this element.
+    pub async fn query_selector_all(&self, selector: &str) -> Result<Vec<ElementHandle<'a>>> {
+        info!("Finding all elements with selector '{}' below element with object ID {}", selector, self.object_id);
+        
+        let result = self.call_query(selector, true).await?;
+        
+        let array_id = result["result"]["objectId"].as_str()
+            .ok_or_else(|| Error::ElementNotFoundError("Failed to get array object ID".to_string()))?;
+        
+        let elements = self.page.element_handles(array_id, &self.origin, selector).await?;
+        
+        info!("Found {} elements with selector '{}'", elements.len(), selector);
+        Ok(elements)
+    }
+    
+    /// Calls a JavaScript function with this element as its first argument, followed by `args`.
+    ///
+    /// `args` must serialize to an array, such as a tuple, see
+    /// [`Page::evaluate_with_args`].
+    pub async fn evaluate<T: serde::de::DeserializeOwned, A: serde::Serialize>(&self, function: &str, args: A) -> Result<T> {
+        let object_id = self.live_object_id().await?;
+        let mut arguments = vec![serde_json::json!({ "objectId": object_id })];
+        arguments.extend(js_handle::call_arguments(args)?);
+        let params = serde_json::json!({
+            "objectId": object_id,
+            "functionDeclaration": function,
+            "arguments": arguments,
+            "returnByValue": true,
+            "awaitPromise": true,
+        });
+        
+        let result = self.page.send_session_command("Runtime.callFunctionOn", Some(params)).await?;
+        js_handle::check_exception(&result, "Unknown error calling function on element")?;
+        js_handle::deserialize_result(result["result"]["value"].clone())
+    }
+    
+    /// Calls a function with this element as `this`, returning its result by value.
+    async fn call_function(&self, declaration: &str, arguments: Vec<serde_json::Value>) -> Result<serde_json::Value> {
+        let arguments: Vec<serde_json::Value> = arguments
+            .into_iter()
+            .map(|value| serde_json::json!({ "value": value }))
+            .collect();
+        let params = serde_json::json!({
+            "objectId": self.object_id,
+            "functionDeclaration": declaration,
+            "arguments": arguments,
+            "returnByValue": true,
+        });
+        
+        let result = self.send_session_command("Runtime.callFunctionOn", Some(params)).await?;
+        
+        // Check if there was an error
+        if let Some(error) = result["exceptionDetails"].as_object() {
+            let error_message = error["exception"]["description"].as_str()
+                .unwrap_or("Unknown error calling function on element");
+            
+            return Err(Error::JavaScriptError(error_message.to_string()));
+        }
+        
+        Ok(result["result"]["value"].clone())
+    }
+    
+    /// Evaluates a selector with this element as the scope.
+    async fn call_query(&self, selector: &str, all: bool) -> Result<serde_json::Value> {
+        let params = serde_json::json!({
+            "objectId": self.object_id,
+            "functionDeclaration": format!("function() {{ return ({})(this); }}", Selectors::query_function(selector, all)?),
+            "returnByValue": false,
+        });
+        
+        let result = self.send_session_command("Runtime.callFunctionOn", Some(params)).await?;
+        
+        // Check if there was an error
+        if let Some(error) = result["exceptionDetails"].as_object() {
+            let error_message = error["exception"]["description"].as_str()
+                .unwrap_or("Unknown error during element selection");
+            
+            return Err(Error::JavaScriptError(error_message.to_string()));
+        }
+        
+        Ok(result)
+    }
+    
+    /// Creates a handle for another object on the same page.
+    fn handle(&self, object_id: &str, origin: ElementOrigin) -> ElementHandle<'a> {
+        ElementHandle::new(self.page, object_id, origin)
+    }
+    
+    /// Returns the object ID of the element's node, finding the element again if its node was detached.
+    async fn live_object_id(&self) -> Result<String> {
+        let current = self.current_id.lock().unwrap().clone().unwrap_or_else(|| self.object_id.clone());
+        
+        // The document itself is never detached
+        if self.origin.steps().is_empty() || !self.page.options.reresolve_detached_elements.unwrap_or(false) {
+            return Ok(current);
+        }
+        
+        let params = serde_json::json!({
+            "objectId": current,
+            "functionDeclaration": "function() { return this.isConnected; }",
+            "returnByValue": true,
+        });
+        
+        // The object may also have been released along with its node
+        let connected = match self.page.send_session_command("Runtime.callFunctionOn", Some(params)).await {
+            Ok(result) => result["result"]["value"].as_bool().unwrap_or(false),
+            Err(_) => false,
+        };
+        if connected {
+            return Ok(current);
+        }
+        
+        debug!("Element {} was detached, finding it again", self.origin);
+        
+        let params = serde_json::json!({
+            "expression": self.origin.resolve_expression()?,
+            "returnByValue": false,
+        });
+        
+        let result = self.page.send_session_command("Runtime.evaluate", Some(params)).await?;
+        
+        // Check if there was an error
+        if let Some(error) = result["exceptionDetails"].as_object() {
+            let error_message = error["exception"]["description"].as_str()
+                .unwrap_or("Unknown error finding detached element");
+            
+            return Err(Error::JavaScriptError(error_message.to_string()));
+        }
+        
+        let id = result["result"]["objectId"].as_str()
+            .ok_or_else(|| Error::ElementNotFoundError(format!("Element {} was detached and no longer matches", self.origin)))?;
+        
+        info!("Found detached element {} again as object ID {}", self.origin, id);
+        *self.current_id.lock().unwrap() = Some(id.to_string());
+        Ok(id.to_string())
+    }
+    
     /// Gets the box model of the element.
     async fn get_box_model(&self) -> Result<serde_json::Value> {
         // Call function to get the bounding client rect
@@ -322,7 +732,50 @@ impl<'a> ElementHandle<'a> {
     }
     
     /// Sends a protocol command to the page session.
-    async fn send_session_command(&self, method: &str, params: Option<serde_json::Value>) -> Result<serde_json::Value> {
+    ///
+    /// Commands on this element are sent to its live node.
+    async fn send_session_command(&self, method: &str, mut params: Option<serde_json::Value>) -> Result<serde_json::Value> {
+        if let Some(params) = params.as_mut() {
+            if params.get("objectId").and_then(|id| id.as_str()) == Some(self.object_id.as_str()) {
+                params["objectId"] = serde_json::json!(self.live_object_id().await?);
+            }
+        }
+        
         self.page.send_session_command(method, params).await
     }
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_origin_display() {
+        assert_eq!(ElementOrigin::document().to_string(), "document");
+        
+        let origin = ElementOrigin::query("my-app", 0)
+            .then(ResolveStep::ShadowRoot)
+            .then_query("li.item", 2);
+        assert_eq!(origin.to_string(), "'my-app' > shadow root > 'li.item' [2]");
+        assert_eq!(origin.steps().len(), 3);
+    }
+    
+    #[test]
+    fn test_resolve_expression() {
+        let origin = ElementOrigin::query("my-app", 0)
+            .then(ResolveStep::ShadowRoot)
+            .then_query("li.item", 2);
+        let expression = origin.resolve_expression().unwrap();
+        
+        let first = format!("node = ({})(node);", Selectors::query_function("my-app", false).unwrap());
+        let nth = format!("node = ({})(node)[2];", Selectors::query_function("li.item", true).unwrap());
+        assert!(expression.starts_with("(() => {\nlet node = document;\n"));
+        assert!(expression.contains(&first));
+        assert!(expression.contains("node = node.shadowRoot;"));
+        assert!(expression.contains(&nth));
+        assert_eq!(expression.matches("if (!node) { return null; }").count(), 3);
+        assert!(expression.ends_with("return node;\n})()"));
+        
+        assert!(ElementOrigin::query("nope=div", 0).resolve_expression().is_err());
+    }
+}
@@ -30,6 +30,19 @@ pub enum Error {
     #[error("Timeout: {0}")]
     TimeoutError(String),
     
+    /// Error when a navigation failed after all of its attempts
+    #[error("Navigation failed after {attempts} attempts: {failure}")]
+    NavigationFailed {
+        /// Failure of the last attempt
+        failure: crate::retry::NavigationFailure,
+        /// Number of attempts made
+        attempts: u32,
+    },
+    
+    /// Error when the renderer of a page crashed or was killed
+    #[error("Page crashed: {0}")]
+    PageCrashed(crate::crash::PageCrash),
+    
     /// Error when the specified browser type is not found
     #[error("Browser type not found: {0}")]
     BrowserTypeNotFound(String),
@@ -46,6 +59,10 @@ pub enum Error {
     #[error("JavaScript evaluation error: {0}")]
     JavaScriptError(String),
     
+    /// Error when a selector cannot be parsed
+    #[error("Invalid selector: {0}")]
+    SelectorError(String),
+    
     /// Error when screenshot fails
     #[error("Screenshot error: {0}")]
     ScreenshotError(String),
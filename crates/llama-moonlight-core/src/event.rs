@@ -0,0 +1,491 @@
+//! Events.
+//!
+//! Browsers, contexts and pages emit typed events ([`BrowserEvent`],
+//! [`ContextEvent`] and [`PageEvent`]) through an [`EventEmitter`]:
+//!
+//! - [`on`](EventEmitter::on) registers a handler for every event of a kind,
+//!   [`once`](EventEmitter::once) one for the next event only, and
+//!   [`off`](EventEmitter::off) removes a handler.
+//! - [`subscribe`](EventEmitter::subscribe) returns a receiver of every event.
+//!   Its channel is bounded: a subscriber that falls behind misses events
+//!   rather than holding up the emitter or growing without limit.
+//! - [`wait_for`](EventEmitter::wait_for) waits for the next event of a kind.
+//!
+//! Handlers run synchronously on the emitting task, so they should be quick;
+//! anything slow belongs in a subscriber.
+
+use crate::crash::PageCrash;
+use crate::quota::QuotaExceeded;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, oneshot};
+use tracing::debug;
+
+/// Default capacity of the channel of each subscriber.
+pub const DEFAULT_SUBSCRIBER_CAPACITY: usize = 64;
+
+/// An event with a kind that listeners can filter on.
+pub trait Event: Clone + Send + 'static {
+    /// Kind of the event, without its data.
+    type Kind: Copy + Eq + fmt::Debug + Send + Sync + 'static;
+
+    /// Returns the kind of the event.
+    fn kind(&self) -> Self::Kind;
+}
+
+/// Event of a browser.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BrowserEvent {
+    /// A context was created
+    Context {
+        /// ID of the context
+        context_id: String,
+    },
+
+    /// The connection to the browser was closed
+    Disconnected,
+}
+
+/// Kind of a [`BrowserEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BrowserEventKind {
+    /// See [`BrowserEvent::Context`]
+    Context,
+
+    /// See [`BrowserEvent::Disconnected`]
+    Disconnected,
+}
+
+impl Event for BrowserEvent {
+    type Kind = BrowserEventKind;
+
+    fn kind(&self) -> Self::Kind {
+        match self {
+            Self::Context { .. } => BrowserEventKind::Context,
+            Self::Disconnected => BrowserEventKind::Disconnected,
+        }
+    }
+}
+
+/// Event of a browser context.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContextEvent {
+    /// A page was created
+    Page {
+        /// Target ID of the page
+        target_id: String,
+    },
+
+    /// The context used up its transfer quota
+    QuotaExceeded(QuotaExceeded),
+
+    /// The context was closed
+    Close,
+}
+
+/// Kind of a [`ContextEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContextEventKind {
+    /// See [`ContextEvent::Page`]
+    Page,
+
+    /// See [`ContextEvent::QuotaExceeded`]
+    QuotaExceeded,
+
+    /// See [`ContextEvent::Close`]
+    Close,
+}
+
+impl Event for ContextEvent {
+    type Kind = ContextEventKind;
+
+    fn kind(&self) -> Self::Kind {
+        match self {
+            Self::Page { .. } => ContextEventKind::Page,
+            Self::QuotaExceeded(_) => ContextEventKind::QuotaExceeded,
+            Self::Close => ContextEventKind::Close,
+        }
+    }
+}
+
+/// Event of a page.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PageEvent {
+    /// The `DOMContentLoaded` event fired
+    DomContentLoaded,
+
+    /// The `load` event fired
+    Load,
+
+    /// The main frame navigated
+    Navigated {
+        /// URL navigated to
+        url: String,
+    },
+
+    /// The page logged to the console
+    Console {
+        /// Console method, e.g. `log` or `error`
+        level: String,
+
+        /// Logged values, joined with spaces
+        text: String,
+    },
+
+    /// A JavaScript dialog opened
+    Dialog {
+        /// Dialog type, e.g. `alert` or `confirm`
+        dialog_type: String,
+
+        /// Message of the dialog
+        message: String,
+    },
+
+    /// The renderer of the page crashed or was killed
+    Crash(PageCrash),
+
+    /// The page was closed
+    Close,
+}
+
+/// Kind of a [`PageEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PageEventKind {
+    /// See [`PageEvent::DomContentLoaded`]
+    DomContentLoaded,
+
+    /// See [`PageEvent::Load`]
+    Load,
+
+    /// See [`PageEvent::Navigated`]
+    Navigated,
+
+    /// See [`PageEvent::Console`]
+    Console,
+
+    /// See [`PageEvent::Dialog`]
+    Dialog,
+
+    /// See [`PageEvent::Crash`]
+    Crash,
+
+    /// See [`PageEvent::Close`]
+    Close,
+}
+
+impl Event for PageEvent {
+    type Kind = PageEventKind;
+
+    fn kind(&self) -> Self::Kind {
+        match self {
+            Self::DomContentLoaded => PageEventKind::DomContentLoaded,
+            Self::Load => PageEventKind::Load,
+            Self::Navigated { .. } => PageEventKind::Navigated,
+            Self::Console { .. } => PageEventKind::Console,
+            Self::Dialog { .. } => PageEventKind::Dialog,
+            Self::Crash(_) => PageEventKind::Crash,
+            Self::Close => PageEventKind::Close,
+        }
+    }
+}
+
+impl PageEvent {
+    /// Converts a protocol event of a page, if it is one the page emits.
+    ///
+    /// Crashes are reported by the page itself, which knows its last URL.
+    pub(crate) fn from_protocol(method: &str, params: &serde_json::Value) -> Option<Self> {
+        match method {
+            "Page.domContentEventFired" => Some(Self::DomContentLoaded),
+            "Page.loadEventFired" => Some(Self::Load),
+            // Only the main frame has no parent
+            "Page.frameNavigated" if params["frame"]["parentId"].is_null() => Some(Self::Navigated {
+                url: params["frame"]["url"].as_str().unwrap_or_default().to_string(),
+            }),
+            "Runtime.consoleAPICalled" => {
+                let text = params["args"]
+                    .as_array()
+                    .map(|args| {
+                        args.iter()
+                            .map(|arg| match arg.get("value").or_else(|| arg.get("description")) {
+                                Some(serde_json::Value::String(value)) => value.clone(),
+                                Some(value) => value.to_string(),
+                                None => String::new(),
+                            })
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    })
+                    .unwrap_or_default();
+                Some(Self::Console {
+                    level: params["type"].as_str().unwrap_or("log").to_string(),
+                    text,
+                })
+            }
+            "Page.javascriptDialogOpening" => Some(Self::Dialog {
+                dialog_type: params["type"].as_str().unwrap_or_default().to_string(),
+                message: params["message"].as_str().unwrap_or_default().to_string(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// ID of a handler registered on an [`EventEmitter`], used to remove it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListenerId(u64);
+
+/// Handler registered on an emitter.
+struct Listener<E: Event> {
+    id: ListenerId,
+    kind: E::Kind,
+    once: bool,
+    handler: Arc<dyn Fn(&E) + Send + Sync>,
+}
+
+/// Listeners and subscribers of an emitter.
+struct Registry<E: Event> {
+    listeners: Vec<Listener<E>>,
+    subscribers: Vec<mpsc::Sender<E>>,
+}
+
+/// Emits events of type `E` to handlers and subscribers.
+///
+/// Clones share their handlers and subscribers.
+pub struct EventEmitter<E: Event> {
+    registry: Arc<Mutex<Registry<E>>>,
+    next_id: Arc<AtomicU64>,
+    dropped: Arc<AtomicU64>,
+    capacity: usize,
+}
+
+impl<E: Event> Clone for EventEmitter<E> {
+    fn clone(&self) -> Self {
+        Self {
+            registry: self.registry.clone(),
+            next_id: self.next_id.clone(),
+            dropped: self.dropped.clone(),
+            capacity: self.capacity,
+        }
+    }
+}
+
+impl<E: Event> Default for EventEmitter<E> {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_SUBSCRIBER_CAPACITY)
+    }
+}
+
+impl<E: Event> fmt::Debug for EventEmitter<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let registry = self.registry.lock().unwrap();
+        f.debug_struct("EventEmitter")
+            .field("listeners", &registry.listeners.len())
+            .field("subscribers", &registry.subscribers.len())
+            .field("capacity", &self.capacity)
+            .field("dropped", &self.dropped())
+            .finish()
+    }
+}
+
+impl<E: Event> EventEmitter<E> {
+    /// Creates an emitter whose subscribers buffer up to
+    /// [`DEFAULT_SUBSCRIBER_CAPACITY`] events.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an emitter whose subscribers buffer up to `capacity` events.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            registry: Arc::new(Mutex::new(Registry {
+                listeners: Vec::new(),
+                subscribers: Vec::new(),
+            })),
+            next_id: Arc::new(AtomicU64::new(0)),
+            dropped: Arc::new(AtomicU64::new(0)),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Registers a handler for every event of `kind`.
+    pub fn on<F>(&self, kind: E::Kind, handler: F) -> ListenerId
+    where
+        F: Fn(&E) + Send + Sync + 'static,
+    {
+        self.add_listener(kind, false, Arc::new(handler))
+    }
+
+    /// Registers a handler for the next event of `kind` only.
+    pub fn once<F>(&self, kind: E::Kind, handler: F) -> ListenerId
+    where
+        F: Fn(&E) + Send + Sync + 'static,
+    {
+        self.add_listener(kind, true, Arc::new(handler))
+    }
+
+    /// Removes a handler, returning whether it was still registered.
+    pub fn off(&self, id: ListenerId) -> bool {
+        let mut registry = self.registry.lock().unwrap();
+        let count = registry.listeners.len();
+        registry.listeners.retain(|listener| listener.id != id);
+        registry.listeners.len() != count
+    }
+
+    /// Returns a receiver of every event emitted from now on.
+    ///
+    /// Events that do not fit in the receiver's buffer are dropped; dropping
+    /// the receiver unsubscribes it.
+    pub fn subscribe(&self) -> mpsc::Receiver<E> {
+        let (sender, receiver) = mpsc::channel(self.capacity);
+        self.registry.lock().unwrap().subscribers.push(sender);
+        receiver
+    }
+
+    /// Waits for the next event of `kind`.
+    ///
+    /// Returns `None` if the handlers are removed with
+    /// [`remove_all_listeners`](Self::remove_all_listeners) first.
+    pub async fn wait_for(&self, kind: E::Kind) -> Option<E> {
+        let (sender, receiver) = oneshot::channel();
+        let sender = Mutex::new(Some(sender));
+        let id = self.once(kind, move |event| {
+            if let Some(sender) = sender.lock().unwrap().take() {
+                let _ = sender.send(event.clone());
+            }
+        });
+
+        let event = receiver.await.ok();
+        self.off(id);
+        event
+    }
+
+    /// Emits an event to the handlers of its kind and to every subscriber.
+    pub fn emit(&self, event: E) {
+        let kind = event.kind();
+
+        // Call handlers outside the lock so they can register and remove handlers
+        let handlers: Vec<_> = {
+            let mut registry = self.registry.lock().unwrap();
+            let handlers = registry
+                .listeners
+                .iter()
+                .filter(|listener| listener.kind == kind)
+                .map(|listener| listener.handler.clone())
+                .collect();
+            registry.listeners.retain(|listener| !(listener.once && listener.kind == kind));
+            registry.subscribers.retain(|subscriber| match subscriber.try_send(event.clone()) {
+                Ok(()) => true,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    true
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => false,
+            });
+            handlers
+        };
+
+        debug!("Emitting {:?} event to {} handlers", kind, handlers.len());
+        for handler in handlers {
+            handler(&event);
+        }
+    }
+
+    /// Removes every handler.
+    pub fn remove_all_listeners(&self) {
+        self.registry.lock().unwrap().listeners.clear();
+    }
+
+    /// Returns the number of events dropped because a subscriber's buffer was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of handlers registered.
+    pub fn listener_count(&self) -> usize {
+        self.registry.lock().unwrap().listeners.len()
+    }
+
+    fn add_listener(&self, kind: E::Kind, once: bool, handler: Arc<dyn Fn(&E) + Send + Sync>) -> ListenerId {
+        let id = ListenerId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        self.registry.lock().unwrap().listeners.push(Listener { id, kind, once, handler });
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn test_on_once_off() {
+        let emitter = EventEmitter::<PageEvent>::new();
+        let loads = Arc::new(AtomicUsize::new(0));
+        let once_loads = Arc::new(AtomicUsize::new(0));
+
+        let counter = loads.clone();
+        let id = emitter.on(PageEventKind::Load, move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let counter = once_loads.clone();
+        emitter.once(PageEventKind::Load, move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        emitter.emit(PageEvent::Load);
+        emitter.emit(PageEvent::Close);
+        emitter.emit(PageEvent::Load);
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+        assert_eq!(once_loads.load(Ordering::SeqCst), 1);
+
+        assert!(emitter.off(id));
+        assert!(!emitter.off(id));
+        emitter.emit(PageEvent::Load);
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+        assert_eq!(emitter.listener_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_bounded_subscribers() {
+        let emitter = EventEmitter::<BrowserEvent>::with_capacity(2);
+        let mut receiver = emitter.subscribe();
+        for _ in 0..3 {
+            emitter.emit(BrowserEvent::Disconnected);
+        }
+        assert_eq!(emitter.dropped(), 1);
+        assert_eq!(receiver.recv().await, Some(BrowserEvent::Disconnected));
+
+        // Dropped receivers are unsubscribed on the next event
+        drop(receiver);
+        emitter.emit(BrowserEvent::Disconnected);
+        assert_eq!(emitter.registry.lock().unwrap().subscribers.len(), 0);
+
+        let waiter = emitter.clone();
+        let wait = tokio::spawn(async move { waiter.wait_for(BrowserEventKind::Context).await });
+        while emitter.listener_count() == 0 {
+            tokio::task::yield_now().await;
+        }
+        emitter.emit(BrowserEvent::Context { context_id: "1".to_string() });
+        assert_eq!(wait.await.unwrap(), Some(BrowserEvent::Context { context_id: "1".to_string() }));
+    }
+
+    #[test]
+    fn test_from_protocol() {
+        assert_eq!(
+            PageEvent::from_protocol("Page.frameNavigated", &json!({ "frame": { "url": "https://example.com/" } })),
+            Some(PageEvent::Navigated { url: "https://example.com/".to_string() })
+        );
+        assert_eq!(
+            PageEvent::from_protocol("Page.frameNavigated", &json!({ "frame": { "parentId": "1", "url": "about:blank" } })),
+            None
+        );
+        assert_eq!(
+            PageEvent::from_protocol("Runtime.consoleAPICalled", &json!({
+                "type": "warning",
+                "args": [{ "type": "string", "value": "count" }, { "type": "number", "value": 3 }],
+            })),
+            Some(PageEvent::Console { level: "warning".to_string(), text: "count 3".to_string() })
+        );
+    }
+}
@@ -0,0 +1,229 @@
+//! Handles to JavaScript values in a page.
+//!
+//! Scripts take their arguments as serialized values rather than as text
+//! formatted into their source, so values containing quotes or newlines
+//! reach them intact. Results that cannot be serialized, such as DOM nodes,
+//! functions or `window`, are kept in the page and returned as [`JsHandle`]s.
+
+use crate::element::{ElementHandle, ElementOrigin};
+use crate::errors::{Error, Result};
+use crate::page::Page;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::debug;
+
+/// Handle to a JavaScript value living in a page.
+///
+/// Objects stay alive in the page until the handle is
+/// [disposed](Self::dispose) or the page navigates away. Primitive values
+/// are held by the handle itself.
+#[derive(Debug)]
+pub struct JsHandle<'a> {
+    /// Reference to the page.
+    page: &'a Page,
+
+    /// Object ID of the value, if it is an object.
+    object_id: Option<String>,
+
+    /// Remote object describing the value, as returned by the browser.
+    remote: serde_json::Value,
+}
+
+impl<'a> JsHandle<'a> {
+    /// Creates a handle from a remote object returned by the browser.
+    pub(crate) fn new(page: &'a Page, remote: serde_json::Value) -> Self {
+        Self {
+            page,
+            object_id: remote["objectId"].as_str().map(str::to_string),
+            remote,
+        }
+    }
+
+    /// Returns the object ID of the value, if it is an object.
+    pub fn object_id(&self) -> Option<&str> {
+        self.object_id.as_deref()
+    }
+
+    /// Returns the `typeof` of the value, such as `"object"` or `"function"`.
+    pub fn type_name(&self) -> &str {
+        self.remote["type"].as_str().unwrap_or("undefined")
+    }
+
+    /// Returns the subtype of an object, such as `"node"`, `"array"` or `"null"`.
+    pub fn subtype(&self) -> Option<&str> {
+        self.remote["subtype"].as_str()
+    }
+
+    /// Returns the browser's description of the value, such as `"HTMLDivElement"`.
+    pub fn description(&self) -> Option<&str> {
+        self.remote["description"].as_str()
+    }
+
+    /// Returns the value, serialized to JSON and deserialized into `T`.
+    pub async fn json_value<T: DeserializeOwned>(&self) -> Result<T> {
+        match &self.object_id {
+            Some(_) => self.evaluate("value => value", ()).await,
+            None => {
+                // Values such as NaN and -0 have no JSON form
+                if let Some(unserializable) = self.remote["unserializableValue"].as_str() {
+                    return Err(Error::JavaScriptError(format!("Value {} cannot be serialized", unserializable)));
+                }
+                deserialize_result(self.remote["value"].clone())
+            }
+        }
+    }
+
+    /// Calls a function with the value as its first argument, followed by `args`.
+    ///
+    /// `args` must serialize to an array, such as a tuple, see
+    /// [`Page::evaluate_with_args`].
+    pub async fn evaluate<T: DeserializeOwned, A: Serialize>(&self, function: &str, args: A) -> Result<T> {
+        let result = self.call(function, args, true).await?;
+        deserialize_result(result["result"]["value"].clone())
+    }
+
+    /// Calls a function with the value as its first argument, followed by
+    /// `args`, returning a handle to its result.
+    pub async fn evaluate_handle<A: Serialize>(&self, function: &str, args: A) -> Result<JsHandle<'a>> {
+        let result = self.call(function, args, false).await?;
+        Ok(JsHandle::new(self.page, result["result"].clone()))
+    }
+
+    /// Returns a handle to a property of the value.
+    pub async fn get_property(&self, name: &str) -> Result<JsHandle<'a>> {
+        self.evaluate_handle("(value, name) => value[name]", (name,)).await
+    }
+
+    /// Returns the value as an element handle, if it is a DOM element.
+    ///
+    /// Unlike elements found with selectors, the element cannot be found
+    /// again once its node is detached.
+    pub async fn as_element(&self) -> Result<Option<ElementHandle<'a>>> {
+        let object_id = match (&self.object_id, self.subtype()) {
+            (Some(object_id), Some("node")) => object_id,
+            _ => return Ok(None),
+        };
+
+        let is_element: bool = self.evaluate("value => value instanceof Element", ()).await?;
+        Ok(is_element.then(|| ElementHandle::new(self.page, object_id, ElementOrigin::document())))
+    }
+
+    /// Releases the value in the page.
+    ///
+    /// Using the handle afterwards fails.
+    pub async fn dispose(self) -> Result<()> {
+        if let Some(object_id) = &self.object_id {
+            let params = serde_json::json!({ "objectId": object_id });
+            self.page.send_session_command("Runtime.releaseObject", Some(params)).await?;
+            debug!("Released JavaScript object {}", object_id);
+        }
+        Ok(())
+    }
+
+    /// Calls a function with the value as its first argument.
+    async fn call<A: Serialize>(&self, function: &str, args: A, by_value: bool) -> Result<serde_json::Value> {
+        let (method, params) = match &self.object_id {
+            Some(object_id) => {
+                let mut arguments = vec![serde_json::json!({ "objectId": object_id })];
+                arguments.extend(call_arguments(args)?);
+                ("Runtime.callFunctionOn", serde_json::json!({
+                    "objectId": object_id,
+                    "functionDeclaration": function,
+                    "arguments": arguments,
+                    "returnByValue": by_value,
+                    "awaitPromise": true,
+                }))
+            },
+            // Primitives have no object to call the function on, so they are passed as literals
+            None => {
+                let literal = match (self.type_name(), self.remote["unserializableValue"].as_str()) {
+                    ("undefined", _) => "undefined".to_string(),
+                    (_, Some(unserializable)) => unserializable.to_string(),
+                    _ => self.remote["value"].to_string(),
+                };
+                let function = format!("(...args) => ({})({}, ...args)", function, literal);
+                ("Runtime.evaluate", serde_json::json!({
+                    "expression": call_expression(&function, args)?,
+                    "returnByValue": by_value,
+                    "awaitPromise": true,
+                }))
+            },
+        };
+
+        let result = self.page.send_session_command(method, Some(params)).await?;
+        check_exception(&result, "Unknown error calling function on JavaScript value")?;
+        Ok(result)
+    }
+}
+
+/// Serializes arguments into call arguments, one per item of the array they serialize to.
+pub(crate) fn call_arguments<A: Serialize>(args: A) -> Result<Vec<serde_json::Value>> {
+    match serde_json::to_value(args)? {
+        serde_json::Value::Array(values) => Ok(values
+            .into_iter()
+            .map(|value| serde_json::json!({ "value": value }))
+            .collect()),
+        // `()` serializes to null
+        serde_json::Value::Null => Ok(Vec::new()),
+        other => Err(Error::JavaScriptError(format!(
+            "Arguments must serialize to an array, such as a tuple, got {}",
+            other
+        ))),
+    }
+}
+
+/// Returns an expression calling a function with serialized arguments.
+///
+/// JSON is valid JavaScript, so the arguments are passed as literals without
+/// any escaping of their own.
+pub(crate) fn call_expression<A: Serialize>(function: &str, args: A) -> Result<String> {
+    let values: Vec<serde_json::Value> = call_arguments(args)?
+        .into_iter()
+        .map(|argument| argument["value"].clone())
+        .collect();
+    Ok(format!("({})(...{})", function, serde_json::Value::Array(values)))
+}
+
+/// Fails with the exception thrown by a script, if any.
+pub(crate) fn check_exception(result: &serde_json::Value, fallback: &str) -> Result<()> {
+    if let Some(error) = result["exceptionDetails"].as_object() {
+        let error_message = error["exception"]["description"].as_str()
+            .or_else(|| error["text"].as_str())
+            .unwrap_or(fallback);
+
+        return Err(Error::JavaScriptError(error_message.to_string()));
+    }
+    Ok(())
+}
+
+/// Deserializes the value a script returned.
+pub(crate) fn deserialize_result<T: DeserializeOwned>(value: serde_json::Value) -> Result<T> {
+    serde_json::from_value(value)
+        .map_err(|e| Error::JavaScriptError(format!("Failed to deserialize result: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_arguments() {
+        let arguments = call_arguments(("it's \"quoted\"\n", 3, vec![true])).unwrap();
+        assert_eq!(arguments, vec![
+            serde_json::json!({ "value": "it's \"quoted\"\n" }),
+            serde_json::json!({ "value": 3 }),
+            serde_json::json!({ "value": [true] }),
+        ]);
+
+        assert!(call_arguments(()).unwrap().is_empty());
+        assert!(call_arguments("single").is_err());
+    }
+
+    #[test]
+    fn test_call_expression() {
+        let expression = call_expression("(a, b) => a + b", ("x'y", "</script>")).unwrap();
+        assert_eq!(expression, r#"((a, b) => a + b)(...["x'y","</script>"])"#);
+
+        assert_eq!(call_expression("() => 1", ()).unwrap(), "(() => 1)(...[])");
+    }
+}
@@ -0,0 +1,499 @@
+//! Browser launchers.
+//!
+//! A launcher starts (or locates) a browser and returns the DevTools
+//! WebSocket endpoint to connect to. [`BrowserType::launch_with_options`]
+//! picks the launcher from [`BrowserOptions::launch_target`]:
+//!
+//! - [`LaunchTarget::Local`]: spawns the browser executable on this machine
+//!   (the default)
+//! - [`LaunchTarget::Docker`]: runs the browser in a container, publishing its
+//!   debugging port on a random local port
+//! - [`LaunchTarget::Remote`]: connects to a browser that is already running
+//!   elsewhere; closing the [`Browser`](crate::Browser) only disconnects
+
+use crate::browser::BrowserType;
+use crate::errors::{Error, Result};
+use crate::options::BrowserOptions;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::{Child, Command};
+use tempfile::TempDir;
+use tokio::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// Where a browser is launched.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub enum LaunchTarget {
+    /// A local browser process.
+    #[default]
+    Local,
+
+    /// A browser in a Docker container.
+    Docker(DockerTarget),
+
+    /// An already running browser, reached through its WebSocket endpoint.
+    ///
+    /// An `http(s)://` URL is resolved through its `/json/version` endpoint.
+    Remote {
+        /// DevTools endpoint URL.
+        endpoint: String,
+    },
+}
+
+impl LaunchTarget {
+    /// Returns the launcher for the target.
+    pub fn launcher(&self) -> Box<dyn Launcher> {
+        match self {
+            LaunchTarget::Local => Box::new(LocalLauncher),
+            LaunchTarget::Docker(target) => Box::new(DockerLauncher::new(target.clone())),
+            LaunchTarget::Remote { endpoint } => Box::new(RemoteLauncher::new(endpoint)),
+        }
+    }
+}
+
+/// Docker container settings.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct DockerTarget {
+    /// Image to run. Defaults to a headless Chromium image when not set.
+    pub image: Option<String>,
+
+    /// Debugging port inside the container.
+    pub port: u16,
+
+    /// Container name.
+    pub name: Option<String>,
+
+    /// Extra `docker run` arguments, e.g. `--shm-size=2g`.
+    pub docker_args: Vec<String>,
+
+    /// Path or name of the docker CLI.
+    pub docker_path: String,
+}
+
+impl Default for DockerTarget {
+    fn default() -> Self {
+        Self {
+            image: None,
+            port: 9222,
+            name: None,
+            docker_args: Vec::new(),
+            docker_path: "docker".to_string(),
+        }
+    }
+}
+
+impl DockerTarget {
+    /// Creates settings for the given image.
+    pub fn new(image: &str) -> Self {
+        Self {
+            image: Some(image.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the image to run for a browser type.
+    fn image_for(&self, browser_type: &str) -> Result<String> {
+        if let Some(image) = &self.image {
+            return Ok(image.clone());
+        }
+
+        match browser_type {
+            "chromium" => Ok("chromedp/headless-shell:latest".to_string()),
+            other => Err(Error::BrowserLaunchError(format!(
+                "No default Docker image for {}; set DockerTarget::image", other
+            ))),
+        }
+    }
+}
+
+/// A started browser, ready to connect to.
+#[derive(Debug)]
+pub struct LaunchedBrowser {
+    /// DevTools WebSocket endpoint.
+    pub ws_endpoint: String,
+
+    /// What to shut down when the browser is closed.
+    pub handle: BrowserHandle,
+
+    /// Local user data directory, removed when the browser is closed.
+    pub user_data_dir: Option<PathBuf>,
+
+    /// File receiving the browser's standard error, if it is captured.
+    pub stderr_log: Option<PathBuf>,
+}
+
+/// Name of the file in the user data directory receiving a local browser's standard error.
+const STDERR_LOG_FILE: &str = "browser-stderr.log";
+
+/// The resource backing a launched browser.
+#[derive(Debug)]
+pub enum BrowserHandle {
+    /// A local browser process.
+    Process(Child),
+
+    /// A Docker container.
+    Container {
+        /// Container ID.
+        id: String,
+
+        /// Path or name of the docker CLI.
+        docker_path: String,
+    },
+
+    /// A browser owned by someone else, which is left running.
+    Remote,
+}
+
+impl BrowserHandle {
+    /// Stops the process or container.
+    pub fn terminate(self) {
+        match self {
+            BrowserHandle::Process(mut child) => {
+                if let Err(e) = child.kill() {
+                    warn!("Failed to kill browser process: {}", e);
+                }
+            },
+            BrowserHandle::Container { id, docker_path } => {
+                match Command::new(&docker_path).args(["rm", "-f", &id]).output() {
+                    Ok(output) if output.status.success() => debug!("Removed browser container {}", id),
+                    Ok(output) => warn!(
+                        "Failed to remove browser container {}: {}",
+                        id,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ),
+                    Err(e) => warn!("Failed to remove browser container {}: {}", id, e),
+                }
+            },
+            BrowserHandle::Remote => {},
+        }
+    }
+
+    /// Returns the exit status of a local browser process that has exited.
+    ///
+    /// Containers and remote browsers always report `None`; their exit shows
+    /// as the connection closing instead.
+    pub fn try_exit_status(&mut self) -> Option<std::process::ExitStatus> {
+        match self {
+            BrowserHandle::Process(child) => child.try_wait().ok().flatten(),
+            _ => None,
+        }
+    }
+
+    /// Returns the last lines a container logged, if the browser runs in one.
+    pub fn container_logs(&self, max_lines: usize) -> Option<String> {
+        match self {
+            BrowserHandle::Container { id, docker_path } => {
+                let tail = max_lines.to_string();
+                let output = Command::new(docker_path)
+                    .args(["logs", "--tail", &tail, id])
+                    .output()
+                    .ok()?;
+
+                // Browsers log to stderr, which docker replays on stderr
+                let mut logs = String::from_utf8_lossy(&output.stdout).into_owned();
+                logs.push_str(&String::from_utf8_lossy(&output.stderr));
+                Some(logs)
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Starts browsers somewhere and returns how to reach them.
+#[async_trait]
+pub trait Launcher: Send + Sync {
+    /// Starts a browser of the given type.
+    async fn launch(&self, browser_type: &BrowserType, options: &BrowserOptions) -> Result<LaunchedBrowser>;
+}
+
+/// Spawns the browser executable on this machine.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LocalLauncher;
+
+#[async_trait]
+impl Launcher for LocalLauncher {
+    async fn launch(&self, browser_type: &BrowserType, options: &BrowserOptions) -> Result<LaunchedBrowser> {
+        // Create a temporary user data directory if none is specified
+        let user_data_dir = if let Some(dir) = options.user_data_dir.clone() {
+            PathBuf::from(dir)
+        } else {
+            let temp_dir = TempDir::new()
+                .map_err(|e| Error::BrowserLaunchError(format!("Failed to create temp directory: {}", e)))?;
+            temp_dir.into_path()
+        };
+
+        // Prepare browser executable and arguments
+        let (executable, args) = browser_type.prepare_launch_command(&user_data_dir, options)?;
+
+        // Launch browser process, keeping its standard error for diagnostics
+        let stderr_log = user_data_dir.join(STDERR_LOG_FILE);
+        let stderr = std::fs::File::create(&stderr_log)
+            .map_err(|e| Error::BrowserLaunchError(format!("Failed to create stderr log: {}", e)))?;
+
+        let mut cmd = Command::new(executable);
+        cmd.args(args).stderr(stderr);
+
+        // Add environment variables
+        if let Some(env_vars) = &options.env {
+            for (key, value) in env_vars {
+                cmd.env(key, value);
+            }
+        }
+
+        // Start the process
+        let child = cmd
+            .spawn()
+            .map_err(|e| Error::BrowserLaunchError(format!("Failed to spawn browser process: {}", e)))?;
+
+        // Wait for the WebSocket endpoint to be available
+        let ws_endpoint = match browser_type
+            .wait_for_websocket_endpoint(&user_data_dir, options.timeout_ms.unwrap_or(30000))
+            .await
+        {
+            Ok(ws_endpoint) => ws_endpoint,
+            Err(e) => {
+                BrowserHandle::Process(child).terminate();
+                return Err(e);
+            }
+        };
+
+        Ok(LaunchedBrowser {
+            ws_endpoint,
+            handle: BrowserHandle::Process(child),
+            user_data_dir: Some(user_data_dir),
+            stderr_log: Some(stderr_log),
+        })
+    }
+}
+
+/// Runs the browser in a Docker container.
+#[derive(Debug, Clone)]
+pub struct DockerLauncher {
+    target: DockerTarget,
+}
+
+impl DockerLauncher {
+    /// Creates a launcher with the given container settings.
+    pub fn new(target: DockerTarget) -> Self {
+        Self { target }
+    }
+
+    /// Runs a docker CLI command and returns its trimmed stdout.
+    async fn docker(&self, args: &[String]) -> Result<String> {
+        let output = tokio::process::Command::new(&self.target.docker_path)
+            .args(args)
+            .output()
+            .await
+            .map_err(|e| Error::BrowserLaunchError(format!("Failed to run {}: {}", self.target.docker_path, e)))?;
+
+        if !output.status.success() {
+            return Err(Error::BrowserLaunchError(format!(
+                "docker {} failed: {}",
+                args.first().map(String::as_str).unwrap_or_default(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Builds the `docker run` arguments.
+    fn run_args(&self, image: &str, options: &BrowserOptions) -> Vec<String> {
+        let mut args = vec![
+            "run".to_string(),
+            "-d".to_string(),
+            "--rm".to_string(),
+            "-p".to_string(),
+            format!("127.0.0.1::{}", self.target.port),
+        ];
+
+        if let Some(name) = &self.target.name {
+            args.push("--name".to_string());
+            args.push(name.clone());
+        }
+
+        if let Some(env_vars) = &options.env {
+            for (key, value) in env_vars {
+                args.push("-e".to_string());
+                args.push(format!("{}={}", key, value));
+            }
+        }
+
+        args.extend(self.target.docker_args.iter().cloned());
+        args.push(image.to_string());
+
+        // Browser arguments are passed to the image's entrypoint
+        if let Some(browser_args) = &options.args {
+            args.extend(browser_args.iter().cloned());
+        }
+
+        args
+    }
+}
+
+#[async_trait]
+impl Launcher for DockerLauncher {
+    async fn launch(&self, browser_type: &BrowserType, options: &BrowserOptions) -> Result<LaunchedBrowser> {
+        let image = self.target.image_for(browser_type.name())?;
+        info!("Starting {} in Docker image {}", browser_type.name(), image);
+
+        let id = self.docker(&self.run_args(&image, options)).await?;
+        let handle = BrowserHandle::Container {
+            id: id.clone(),
+            docker_path: self.target.docker_path.clone(),
+        };
+
+        let started = async {
+            let mapping = self.docker(&["port".to_string(), id.clone(), format!("{}/tcp", self.target.port)]).await?;
+            let address = parse_port_mapping(&mapping).ok_or_else(|| {
+                Error::BrowserLaunchError(format!("Unexpected docker port output: {}", mapping))
+            })?;
+
+            debug!("Browser container {} is published on {}", id, address);
+            let timeout_ms = options.timeout_ms.unwrap_or(30000);
+            resolve_endpoint(&format!("http://{}", address), Some(&address), timeout_ms).await
+        }
+        .await;
+
+        match started {
+            Ok(ws_endpoint) => Ok(LaunchedBrowser {
+                ws_endpoint,
+                handle,
+                user_data_dir: None,
+                stderr_log: None,
+            }),
+            Err(e) => {
+                handle.terminate();
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Connects to a browser that is already running.
+#[derive(Debug, Clone)]
+pub struct RemoteLauncher {
+    endpoint: String,
+}
+
+impl RemoteLauncher {
+    /// Creates a launcher for the given endpoint.
+    pub fn new(endpoint: &str) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl Launcher for RemoteLauncher {
+    async fn launch(&self, _browser_type: &BrowserType, options: &BrowserOptions) -> Result<LaunchedBrowser> {
+        info!("Connecting to remote browser at {}", self.endpoint);
+
+        let ws_endpoint = if self.endpoint.starts_with("ws://") || self.endpoint.starts_with("wss://") {
+            self.endpoint.clone()
+        } else {
+            resolve_endpoint(&self.endpoint, None, options.timeout_ms.unwrap_or(30000)).await?
+        };
+
+        Ok(LaunchedBrowser {
+            ws_endpoint,
+            handle: BrowserHandle::Remote,
+            user_data_dir: None,
+            stderr_log: None,
+        })
+    }
+}
+
+/// Parses `docker port` output such as `127.0.0.1:49153` into an address.
+fn parse_port_mapping(output: &str) -> Option<String> {
+    let line = output.lines().find(|line| !line.contains("::"))?.trim();
+    let (host, port) = line.rsplit_once(':')?;
+    port.parse::<u16>().ok()?;
+
+    let host = if host == "0.0.0.0" { "127.0.0.1" } else { host };
+    Some(format!("{}:{}", host, port))
+}
+
+/// Polls `/json/version` until the browser reports its WebSocket endpoint.
+///
+/// When `public_address` is set, the host and port of the reported endpoint
+/// are replaced with it, since the browser only knows its own address.
+async fn resolve_endpoint(base_url: &str, public_address: Option<&str>, timeout_ms: u64) -> Result<String> {
+    let url = format!("{}/json/version", base_url.trim_end_matches('/'));
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+    let client = reqwest::Client::new();
+
+    loop {
+        match client.get(&url).send().await {
+            Ok(response) => {
+                let data: serde_json::Value = response
+                    .json()
+                    .await
+                    .map_err(|e| Error::BrowserLaunchError(format!("Invalid response from {}: {}", url, e)))?;
+
+                let endpoint = data["webSocketDebuggerUrl"].as_str().ok_or_else(|| {
+                    Error::BrowserLaunchError(format!("No webSocketDebuggerUrl in response from {}", url))
+                })?;
+
+                return Ok(match public_address {
+                    Some(address) => rewrite_endpoint(endpoint, address),
+                    None => endpoint.to_string(),
+                });
+            },
+            Err(e) if Instant::now() < deadline => {
+                debug!("Browser not ready at {}: {}", url, e);
+                tokio::time::sleep(Duration::from_millis(250)).await;
+            },
+            Err(e) => {
+                return Err(Error::TimeoutError(format!("Timed out waiting for browser at {}: {}", url, e)));
+            },
+        }
+    }
+}
+
+/// Replaces the host and port of a WebSocket URL.
+fn rewrite_endpoint(endpoint: &str, address: &str) -> String {
+    match endpoint.split_once("://") {
+        Some((scheme, rest)) => {
+            let path = rest.find('/').map(|i| &rest[i..]).unwrap_or("");
+            format!("{}://{}{}", scheme, address, path)
+        },
+        None => endpoint.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_port_mapping() {
+        assert_eq!(parse_port_mapping("127.0.0.1:49153"), Some("127.0.0.1:49153".to_string()));
+        assert_eq!(parse_port_mapping("0.0.0.0:32768\n[::]:32768"), Some("127.0.0.1:32768".to_string()));
+        assert_eq!(parse_port_mapping("garbage"), None);
+    }
+
+    #[test]
+    fn test_rewrite_endpoint() {
+        assert_eq!(
+            rewrite_endpoint("ws://0.0.0.0:9222/devtools/browser/abc", "127.0.0.1:49153"),
+            "ws://127.0.0.1:49153/devtools/browser/abc"
+        );
+    }
+
+    #[test]
+    fn test_docker_run_args() {
+        let mut target = DockerTarget::new("chromedp/headless-shell:latest");
+        target.docker_args = vec!["--shm-size=2g".to_string()];
+        let launcher = DockerLauncher::new(target);
+
+        let mut options = BrowserOptions::default();
+        options.args = Some(vec!["--disable-gpu".to_string()]);
+
+        let args = launcher.run_args("chromedp/headless-shell:latest", &options);
+        assert_eq!(args[..5], ["run", "-d", "--rm", "-p", "127.0.0.1::9222"]);
+        assert_eq!(args[5..], ["--shm-size=2g", "chromedp/headless-shell:latest", "--disable-gpu"]);
+    }
+}
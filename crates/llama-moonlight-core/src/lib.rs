@@ -7,11 +7,23 @@
 //!
 //! - Browser automation (Chrome, Firefox, Safari support)
 //! - Headless and headed modes
+//! - Local, Docker, or remote browser launch targets
 //! - Network interception and mocking
+//! - Resource type and domain blocking (images, fonts, ads, analytics)
+//! - Per-context bandwidth quotas that abort requests once exceeded
+//! - Navigation retries classified by failure kind, with backoff and proxy rotation hooks
+//! - Shadow DOM piercing selectors (`>>>` and `pierce=`)
 //! - Screenshot and video capture
+//! - Per-page request size and timing metrics
+//! - Normalized DOM snapshots and diffs to detect site structure changes
+//! - Deterministic clock and timer control inside pages
+//! - JavaScript and CSS coverage to tune resource blocking
 //! - WebSocket protocol support
 //! - Integration with llama-headers-rs for stealth browsing
 //! - Support for MLX integration for AI-powered automation
+//! - Optional model-suggested replacements for selectors that match nothing
+//! - Storage state export and import in Playwright's `storageState` format
+//! - Structured `tracing` spans with optional OTLP export (`otlp` feature)
 //!
 //! ## Example
 //!
@@ -44,12 +56,21 @@
 //! }
 //! ```
 
+mod blocking;
 mod browser;
+mod clock;
+mod coverage;
 mod context;
+mod crash;
+mod dom_snapshot;
 mod page;
 mod frame;
 mod element;
 mod input;
+mod js_handle;
+mod load_state;
+pub mod launcher;
+mod navigation;
 mod network;
 mod selectors;
 mod dialog;
@@ -62,37 +83,61 @@ mod cdp;
 mod accessibility;
 mod worker;
 mod protocol;
+mod quota;
+mod request_log;
+mod request_metrics;
+mod retry;
 mod options;
 mod utils;
 mod chromium;
 mod firefox;
 mod webkit;
 mod llama_integration;
+mod storage_state;
+pub mod telemetry;
 
 // Re-exports
 pub use browser::{Browser, BrowserType};
+pub use clock::Clock;
+pub use coverage::{ByteRange, Coverage, CoverageEntry, CoverageKind, CoverageReport, UrlCoverage};
 pub use context::BrowserContext;
+pub use crash::{CrashReason, PageCrash};
+pub use dom_snapshot::{DomChange, DomDiff, DomNode, DomSnapshot, SnapshotOptions};
 pub use page::Page;
 pub use frame::Frame;
-pub use element::ElementHandle;
+pub use element::{ElementHandle, ElementOrigin, ResolveStep};
 pub use input::{Keyboard, Mouse, Touchscreen};
+pub use js_handle::JsHandle;
+pub use launcher::{DockerTarget, LaunchTarget};
+pub use navigation::{NavigationResult, NavigationTiming, Redirect};
 pub use network::{Request, Response, Route, WebSocket};
-pub use selectors::Selectors;
+pub use selectors::{SelectorEngine, SelectorStep, Selectors};
 pub use dialog::Dialog;
 pub use download::Download;
 pub use video::VideoRecorder;
 pub use errors::Error;
-pub use event::EventEmitter;
+pub use event::{
+    BrowserEvent, BrowserEventKind, ContextEvent, ContextEventKind, Event, EventEmitter, ListenerId, PageEvent,
+    PageEventKind, DEFAULT_SUBSCRIBER_CAPACITY,
+};
 pub use har::Har;
 pub use cdp::CDPSession;
 pub use accessibility::Accessibility;
 pub use worker::Worker;
-pub use options::{BrowserOptions, ContextOptions, PageOptions};
-pub use llama_integration::LlamaModel;
+pub use options::{
+    BlockList, BrowserOptions, ColorScheme, ContextOptions, Cookie, ForcedColors, HttpCredentials, Media, MediaOptions,
+    PageOptions, ReducedMotion, ResourceType, SameSite, WaitUntilState,
+};
+pub use storage_state::{domain_matches, OriginState, StorageEntry, StorageState};
+pub use quota::QuotaExceeded;
+pub use request_log::{RequestRecorder, SentRequest};
+pub use request_metrics::{RequestMetrics, ResourceEntry, ResourceTypeMetrics, TimingPercentiles};
+pub use retry::{Backoff, NavigationFailure, NavigationFailureKind, RetryHook, RetryPolicy};
+pub use llama_integration::{CompletionBackend, HttpBackend, LlamaModel, SelectorSubstitution};
 
 use crate::protocol::Connection;
 use std::sync::Arc;
-use log::{debug, info};
+use tracing::{debug, info};
 
 /// The main entry point for the Llama-Moonlight API.
 pub struct Moonlight {
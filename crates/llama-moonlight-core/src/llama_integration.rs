@@ -0,0 +1,239 @@
+//! Llama model integration.
+//!
+//! This module connects pages to a Llama language model. When an action
+//! fails because its selector matches nothing, and the page was created with
+//! `PageOptions::ai_selector_recovery`, the page sends the model a summary of
+//! its interactive elements and retries the action with the selector the
+//! model suggests. Every substitution is kept in an audit log.
+
+use crate::errors::{Error, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
+
+/// Script summarizing the interactive elements of a page, one per line.
+pub(crate) const DOM_SUMMARY_SCRIPT: &str = r#"(() => {
+    const attrs = ['id', 'name', 'type', 'role', 'aria-label', 'placeholder', 'href', 'data-testid'];
+    const nodes = document.querySelectorAll('a, button, input, select, textarea, label, [role], [onclick], [data-testid]');
+    return Array.from(nodes).slice(0, 150).map(el => {
+        let tag = el.tagName.toLowerCase();
+        let parts = attrs.filter(a => el.hasAttribute(a)).map(a => `${a}="${el.getAttribute(a).slice(0, 60)}"`);
+        if (el.classList.length) parts.push(`class="${Array.from(el.classList).slice(0, 4).join(' ')}"`);
+        let text = (el.innerText || el.value || '').trim().replace(/\s+/g, ' ').slice(0, 60);
+        return `<${tag} ${parts.join(' ')}>${text}</${tag}>`;
+    }).join('\n');
+})()"#;
+
+/// Maximum length of a suggested selector.
+const MAX_SELECTOR_LENGTH: usize = 200;
+
+/// A backend generating text completions.
+#[async_trait]
+pub trait CompletionBackend: Send + Sync + fmt::Debug {
+    /// Completes a prompt.
+    async fn complete(&self, prompt: &str) -> Result<String>;
+}
+
+/// Backend for servers speaking the Ollama `/api/generate` API.
+#[derive(Debug, Clone)]
+pub struct HttpBackend {
+    /// HTTP client.
+    client: reqwest::Client,
+    
+    /// Base URL of the server, e.g. `http://localhost:11434`.
+    endpoint: String,
+    
+    /// Model name, e.g. `llama3`.
+    model: String,
+}
+
+impl HttpBackend {
+    /// Creates a backend for a model served at an endpoint.
+    pub fn new(endpoint: &str, model: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl CompletionBackend for HttpBackend {
+    async fn complete(&self, prompt: &str) -> Result<String> {
+        let body = serde_json::json!({
+            "model": self.model,
+            "prompt": prompt,
+            "stream": false,
+        });
+        
+        let response = self.client
+            .post(format!("{}/api/generate", self.endpoint))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| Error::Generic(format!("Model request failed: {}", e)))?;
+        
+        let value: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| Error::Generic(format!("Invalid model response: {}", e)))?;
+        
+        value["response"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| Error::Generic("Model response has no text".to_string()))
+    }
+}
+
+/// A selector replaced on the model's suggestion.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelectorSubstitution {
+    /// When the substitution was made.
+    pub timestamp: DateTime<Utc>,
+    
+    /// URL of the page.
+    pub page_url: String,
+    
+    /// Action that failed, e.g. `click`.
+    pub action: String,
+    
+    /// Selector that matched nothing.
+    pub original: String,
+    
+    /// Selector used instead.
+    pub replacement: String,
+}
+
+/// A Llama language model used to assist automation.
+///
+/// Clones share the same backend and audit log.
+#[derive(Debug, Clone)]
+pub struct LlamaModel {
+    /// Completion backend.
+    backend: Arc<dyn CompletionBackend>,
+    
+    /// Selector substitutions made with this model.
+    audit_log: Arc<Mutex<Vec<SelectorSubstitution>>>,
+}
+
+impl LlamaModel {
+    /// Creates a model using a completion backend.
+    pub fn new(backend: impl CompletionBackend + 'static) -> Self {
+        Self {
+            backend: Arc::new(backend),
+            audit_log: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+    
+    /// Creates a model served over HTTP by Ollama or a compatible server.
+    pub fn http(endpoint: &str, model: &str) -> Self {
+        Self::new(HttpBackend::new(endpoint, model))
+    }
+    
+    /// Completes a prompt.
+    pub async fn complete(&self, prompt: &str) -> Result<String> {
+        self.backend.complete(prompt).await
+    }
+    
+    /// Suggests a selector replacing one that matched nothing.
+    ///
+    /// Returns `None` if the model gives no usable selector.
+    pub async fn suggest_selector(&self, action: &str, selector: &str, dom_summary: &str) -> Result<Option<String>> {
+        let prompt = format!(
+            "A browser automation script tried to {action} the element matching the CSS selector `{selector}`, \
+             but no element matches it. These are the interactive elements of the page:\n\n{dom_summary}\n\n\
+             Reply with a single CSS selector for the element the script most likely meant, and nothing else. \
+             Reply NONE if no element fits."
+        );
+        
+        let completion = self.complete(&prompt).await?;
+        let suggestion = parse_selector(&completion).filter(|s| s != selector);
+        debug!("Model suggested {:?} for selector '{}'", suggestion, selector);
+        
+        Ok(suggestion)
+    }
+    
+    /// Records a selector substitution in the audit log.
+    pub fn record_substitution(&self, page_url: &str, action: &str, original: &str, replacement: &str) {
+        warn!(
+            page_url,
+            action,
+            original,
+            replacement,
+            "Substituted selector suggested by model"
+        );
+        
+        self.audit_log.lock().unwrap().push(SelectorSubstitution {
+            timestamp: Utc::now(),
+            page_url: page_url.to_string(),
+            action: action.to_string(),
+            original: original.to_string(),
+            replacement: replacement.to_string(),
+        });
+    }
+    
+    /// Returns the selector substitutions made so far.
+    pub fn substitutions(&self) -> Vec<SelectorSubstitution> {
+        self.audit_log.lock().unwrap().clone()
+    }
+}
+
+/// Extracts a selector from a model completion.
+fn parse_selector(completion: &str) -> Option<String> {
+    let line = completion
+        .lines()
+        .map(|line| line.trim().trim_matches('`').trim())
+        .find(|line| !line.is_empty() && !line.starts_with("css"))?;
+    
+    let selector = line.trim_matches(|c| c == '"' || c == '\'').trim();
+    if selector.is_empty() || selector.eq_ignore_ascii_case("none") || selector.len() > MAX_SELECTOR_LENGTH {
+        return None;
+    }
+    
+    Some(selector.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[derive(Debug)]
+    struct StaticBackend(&'static str);
+    
+    #[async_trait]
+    impl CompletionBackend for StaticBackend {
+        async fn complete(&self, prompt: &str) -> Result<String> {
+            assert!(prompt.contains("#login"));
+            Ok(self.0.to_string())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_suggest_selector() {
+        let dom = r#"<button id="sign-in">Sign in</button>"#;
+        
+        let model = LlamaModel::new(StaticBackend("```css\nbutton#sign-in\n```"));
+        assert_eq!(model.suggest_selector("click", "#login", dom).await.unwrap(), Some("button#sign-in".to_string()));
+        
+        let model = LlamaModel::new(StaticBackend("NONE"));
+        assert_eq!(model.suggest_selector("click", "#login", dom).await.unwrap(), None);
+        
+        let model = LlamaModel::new(StaticBackend("#login"));
+        assert_eq!(model.suggest_selector("click", "#login", dom).await.unwrap(), None);
+    }
+    
+    #[test]
+    fn test_audit_log() {
+        let model = LlamaModel::new(StaticBackend(""));
+        model.clone().record_substitution("https://example.com/", "click", "#login", "#sign-in");
+        
+        let log = model.substitutions();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].original, "#login");
+        assert_eq!(log[0].replacement, "#sign-in");
+    }
+}
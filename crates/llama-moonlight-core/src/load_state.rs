@@ -0,0 +1,335 @@
+//! Waiting for page load states.
+//!
+//! Load states are reached from protocol events rather than by polling the
+//! page: the `DOMContentLoaded` and `load` lifecycle events of the main
+//! frame, and for the network idle states the requests in flight, tracked
+//! from the page's network events. The network counts as idle once it has
+//! stayed at or below the allowed number of requests for
+//! [`NETWORK_IDLE_DURATION`], which is timed rather than sampled, so a wait
+//! resolves as soon as that period ends.
+
+use crate::errors::{Error, Result};
+use crate::options::WaitUntilState;
+use crate::page::Page;
+use std::collections::HashSet;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// How long the network must stay quiet to count as idle.
+pub(crate) const NETWORK_IDLE_DURATION: Duration = Duration::from_millis(500);
+
+/// Progress of the main frame's document towards a load state.
+#[derive(Debug)]
+pub(crate) struct LoadStateTracker {
+    /// Load state waited for.
+    state: WaitUntilState,
+
+    /// Frame ID of the main frame.
+    main_frame_id: String,
+
+    /// Loader of the document followed, once known.
+    loader_id: Option<String>,
+
+    /// Whether `DOMContentLoaded` fired.
+    dom_content_loaded: bool,
+
+    /// Whether `load` fired.
+    loaded: bool,
+
+    /// Whether the browser itself reported the network as idle enough.
+    browser_idle: bool,
+
+    /// Requests in flight, by request ID.
+    inflight: HashSet<String>,
+
+    /// Since when the requests in flight are within the allowed number.
+    quiet_since: Option<Instant>,
+}
+
+impl LoadStateTracker {
+    /// Creates a tracker for a document that has not started loading yet.
+    pub(crate) fn new(state: WaitUntilState, main_frame_id: &str, now: Instant) -> Self {
+        Self {
+            state,
+            main_frame_id: main_frame_id.to_string(),
+            loader_id: None,
+            dom_content_loaded: false,
+            loaded: false,
+            browser_idle: false,
+            inflight: HashSet::new(),
+            quiet_since: Some(now),
+        }
+    }
+
+    /// Takes the progress of the current document from its `document.readyState`.
+    pub(crate) fn with_ready_state(mut self, ready_state: &str) -> Self {
+        self.dom_content_loaded = ready_state != "loading";
+        self.loaded = ready_state == "complete";
+        self
+    }
+
+    /// Only follows the document of a loader, such as the one a navigation started.
+    pub(crate) fn follow_loader(&mut self, loader_id: &str) {
+        self.loader_id = Some(loader_id.to_string());
+    }
+
+    /// Applies a protocol event of the page.
+    pub(crate) fn apply(&mut self, method: &str, params: &serde_json::Value, now: Instant) {
+        match method {
+            "Page.lifecycleEvent" => {
+                if params["frameId"] != self.main_frame_id.as_str() {
+                    return;
+                }
+                let loader_id = params["loaderId"].as_str().unwrap_or_default();
+                if self.loader_id.as_deref().is_some_and(|followed| followed != loader_id) {
+                    return;
+                }
+
+                match params["name"].as_str().unwrap_or_default() {
+                    // A new document starts loading
+                    "init" => {
+                        self.loader_id = Some(loader_id.to_string());
+                        self.dom_content_loaded = false;
+                        self.loaded = false;
+                        self.browser_idle = false;
+                    },
+                    "DOMContentLoaded" => self.dom_content_loaded = true,
+                    "load" => self.loaded = true,
+                    "networkIdle" => self.browser_idle = true,
+                    "networkAlmostIdle" => self.browser_idle |= self.max_inflight() > 0,
+                    _ => {},
+                }
+            },
+            "Network.requestWillBeSent" => {
+                // Redirects are sent again under the same request ID
+                if let Some(request_id) = params["requestId"].as_str() {
+                    self.inflight.insert(request_id.to_string());
+                    self.update_quiet(now);
+                }
+            },
+            "Network.loadingFinished" | "Network.loadingFailed" => {
+                if let Some(request_id) = params["requestId"].as_str() {
+                    self.inflight.remove(request_id);
+                    self.update_quiet(now);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Returns whether the load state is reached.
+    pub(crate) fn reached(&self, now: Instant) -> bool {
+        match self.state {
+            WaitUntilState::DomContentLoaded => self.dom_content_loaded || self.loaded,
+            WaitUntilState::Load => self.loaded,
+            WaitUntilState::NetworkIdle | WaitUntilState::NetworkIdle2 => {
+                self.loaded && (self.browser_idle || self.idle_at().is_some_and(|idle_at| now >= idle_at))
+            },
+        }
+    }
+
+    /// Returns when the load state will be reached if no other event arrives.
+    pub(crate) fn idle_at(&self) -> Option<Instant> {
+        match self.state {
+            WaitUntilState::NetworkIdle | WaitUntilState::NetworkIdle2 if self.loaded => {
+                self.quiet_since.map(|since| since + NETWORK_IDLE_DURATION)
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns whether the network events are needed to reach the load state.
+    pub(crate) fn tracks_network(&self) -> bool {
+        matches!(self.state, WaitUntilState::NetworkIdle | WaitUntilState::NetworkIdle2)
+    }
+
+    /// Returns how many requests may be in flight while the network counts as idle.
+    fn max_inflight(&self) -> usize {
+        match self.state {
+            WaitUntilState::NetworkIdle2 => 2,
+            _ => 0,
+        }
+    }
+
+    /// Starts or stops the quiet period after the requests in flight changed.
+    fn update_quiet(&mut self, now: Instant) {
+        if self.inflight.len() > self.max_inflight() {
+            self.quiet_since = None;
+        } else if self.quiet_since.is_none() {
+            self.quiet_since = Some(now);
+        }
+    }
+}
+
+/// Waits for a load state of a page from its protocol events.
+///
+/// Start the waiter before whatever triggers the navigation, so no event
+/// is missed.
+pub(crate) struct LoadStateWaiter {
+    events: mpsc::Receiver<crate::protocol::Event>,
+    session_id: String,
+    tracker: LoadStateTracker,
+}
+
+impl LoadStateWaiter {
+    /// Starts following the next document the page loads.
+    pub(crate) async fn start(page: &Page, state: WaitUntilState) -> Result<Self> {
+        let tracker = LoadStateTracker::new(state, &page.target_id, Instant::now());
+        Self::subscribe(page, tracker).await
+    }
+
+    /// Starts following the document currently in the page.
+    pub(crate) async fn current(page: &Page, state: WaitUntilState) -> Result<Self> {
+        let ready_state: String = page.evaluate("document.readyState").await?;
+        let tracker = LoadStateTracker::new(state, &page.target_id, Instant::now()).with_ready_state(&ready_state);
+        Self::subscribe(page, tracker).await
+    }
+
+    /// Subscribes to the events a tracker needs.
+    async fn subscribe(page: &Page, tracker: LoadStateTracker) -> Result<Self> {
+        let events = page.connection.subscribe("Target.receivedMessageFromTarget".to_string()).await?;
+        page.send_session_command("Page.enable", None).await?;
+        page.send_session_command("Page.setLifecycleEventsEnabled", Some(serde_json::json!({ "enabled": true }))).await?;
+        if tracker.tracks_network() {
+            page.send_session_command("Network.enable", None).await?;
+        }
+
+        Ok(Self {
+            events,
+            session_id: page.session_id.clone(),
+            tracker,
+        })
+    }
+
+    /// Only follows the document of a loader, such as the one a navigation started.
+    pub(crate) fn follow_loader(&mut self, loader_id: &str) {
+        self.tracker.follow_loader(loader_id);
+    }
+
+    /// Waits until the load state is reached, failing after `timeout_ms`.
+    pub(crate) async fn wait(mut self, timeout_ms: u64) -> Result<()> {
+        let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+
+        loop {
+            let now = Instant::now();
+            if self.tracker.reached(now) {
+                debug!("Load state {:?} reached", self.tracker.state);
+                return Ok(());
+            }
+            if now >= deadline {
+                warn!("Waiting for load state {:?} timed out after {}ms", self.tracker.state, timeout_ms);
+                return Err(Error::TimeoutError(format!(
+                    "Waiting for load state {:?} timed out after {}ms",
+                    self.tracker.state, timeout_ms
+                )));
+            }
+
+            // Wake up for the next event, or when the network turns idle without one
+            let wake_at = self.tracker.idle_at().map_or(deadline, |idle_at| idle_at.min(deadline));
+            tokio::select! {
+                event = self.events.recv() => match event {
+                    Some(event) => self.apply(event),
+                    None => {
+                        warn!("Event channel closed before load state {:?}", self.tracker.state);
+                        return Err(Error::NavigationError("Event channel closed".to_string()));
+                    },
+                },
+                _ = tokio::time::sleep_until(wake_at) => {},
+            }
+        }
+    }
+
+    /// Applies a message of the page's session.
+    fn apply(&mut self, event: crate::protocol::Event) {
+        let params = match event.params {
+            Some(params) if params["sessionId"] == self.session_id.as_str() => params,
+            _ => return,
+        };
+        let message: serde_json::Value = match params["message"].as_str().map(serde_json::from_str) {
+            Some(Ok(message)) => message,
+            _ => return,
+        };
+
+        if let Some(method) = message["method"].as_str() {
+            self.tracker.apply(method, &message["params"], Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn lifecycle(name: &str, frame_id: &str, loader_id: &str) -> serde_json::Value {
+        json!({ "frameId": frame_id, "loaderId": loader_id, "name": name })
+    }
+
+    #[test]
+    fn test_lifecycle_states() {
+        let now = Instant::now();
+        let mut tracker = LoadStateTracker::new(WaitUntilState::Load, "MAIN", now);
+        tracker.follow_loader("L2");
+
+        // Other frames and loaders are ignored
+        tracker.apply("Page.lifecycleEvent", &lifecycle("load", "IFRAME", "L2"), now);
+        tracker.apply("Page.lifecycleEvent", &lifecycle("load", "MAIN", "L1"), now);
+        assert!(!tracker.reached(now));
+
+        tracker.apply("Page.lifecycleEvent", &lifecycle("init", "MAIN", "L2"), now);
+        tracker.apply("Page.lifecycleEvent", &lifecycle("DOMContentLoaded", "MAIN", "L2"), now);
+        assert!(!tracker.reached(now));
+        tracker.apply("Page.lifecycleEvent", &lifecycle("load", "MAIN", "L2"), now);
+        assert!(tracker.reached(now));
+
+        let tracker = LoadStateTracker::new(WaitUntilState::DomContentLoaded, "MAIN", now).with_ready_state("interactive");
+        assert!(tracker.reached(now));
+        assert!(!LoadStateTracker::new(WaitUntilState::Load, "MAIN", now).with_ready_state("interactive").reached(now));
+    }
+
+    #[test]
+    fn test_network_idle() {
+        let start = Instant::now();
+        let mut tracker = LoadStateTracker::new(WaitUntilState::NetworkIdle, "MAIN", start).with_ready_state("complete");
+        assert_eq!(tracker.idle_at(), Some(start + NETWORK_IDLE_DURATION));
+
+        // A request in flight holds off idleness until it finishes
+        let t1 = start + Duration::from_millis(100);
+        tracker.apply("Network.requestWillBeSent", &json!({ "requestId": "R1" }), t1);
+        tracker.apply("Network.requestWillBeSent", &json!({ "requestId": "R1" }), t1);
+        assert_eq!(tracker.idle_at(), None);
+        assert!(!tracker.reached(start + Duration::from_secs(5)));
+
+        let t2 = start + Duration::from_millis(300);
+        tracker.apply("Network.loadingFinished", &json!({ "requestId": "R1" }), t2);
+        assert_eq!(tracker.idle_at(), Some(t2 + NETWORK_IDLE_DURATION));
+        assert!(!tracker.reached(t2 + Duration::from_millis(499)));
+        assert!(tracker.reached(t2 + NETWORK_IDLE_DURATION));
+
+        // Up to two requests may stay in flight for networkidle2
+        let mut tracker = LoadStateTracker::new(WaitUntilState::NetworkIdle2, "MAIN", start).with_ready_state("complete");
+        for id in ["R1", "R2"] {
+            tracker.apply("Network.requestWillBeSent", &json!({ "requestId": id }), t1);
+        }
+        assert!(tracker.reached(start + NETWORK_IDLE_DURATION));
+        tracker.apply("Network.requestWillBeSent", &json!({ "requestId": "R3" }), t1);
+        assert_eq!(tracker.idle_at(), None);
+        tracker.apply("Network.loadingFailed", &json!({ "requestId": "R3" }), t2);
+        assert!(tracker.reached(t2 + NETWORK_IDLE_DURATION));
+    }
+
+    #[test]
+    fn test_network_idle_waits_for_load() {
+        let now = Instant::now();
+        let mut tracker = LoadStateTracker::new(WaitUntilState::NetworkIdle, "MAIN", now);
+        assert_eq!(tracker.idle_at(), None);
+        assert!(!tracker.reached(now + Duration::from_secs(5)));
+
+        // The browser's own report counts once the document has loaded
+        tracker.apply("Page.lifecycleEvent", &lifecycle("networkIdle", "MAIN", "L1"), now);
+        assert!(!tracker.reached(now));
+        tracker.apply("Page.lifecycleEvent", &lifecycle("load", "MAIN", "L1"), now);
+        assert!(tracker.reached(now));
+    }
+}
@@ -0,0 +1,284 @@
+//! Navigation results.
+//!
+//! [`Page::goto`](crate::Page::goto) reports where a navigation ended up: the
+//! final URL and HTTP status, every redirect on the way, and how long the
+//! phases of the final request took. Soft blocks rarely fail a navigation;
+//! they answer 200 after redirecting to a consent or captcha page, so the
+//! redirect chain is often the only sign of them.
+//!
+//! The chain and status come from the network events of the document
+//! request, the timings from the page's Navigation Timing entry.
+
+use crate::errors::Result;
+use crate::page::Page;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// Reads the timings and status of the current document.
+pub(crate) const NAVIGATION_ENTRY_SCRIPT: &str = r#"
+(() => {
+  const entry = performance.getEntriesByType('navigation')[0];
+  if (!entry) return null;
+  return {
+    dnsMs: Math.max(entry.domainLookupEnd - entry.domainLookupStart, 0),
+    connectMs: Math.max(entry.connectEnd - entry.connectStart, 0),
+    ttfbMs: Math.max(entry.responseStart - entry.requestStart, 0),
+    loadMs: entry.loadEventEnd > 0 ? entry.loadEventEnd - entry.startTime : null,
+    status: entry.responseStatus || null,
+  };
+})()
+"#;
+
+/// A redirect followed during a navigation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Redirect {
+    /// URL that answered with the redirect
+    pub url: String,
+
+    /// HTTP status of the redirect, e.g. 301 or 302
+    pub status: u16,
+}
+
+/// Timing breakdown of the final request of a navigation, in milliseconds.
+///
+/// Phases skipped by the browser, such as DNS and connecting on a reused
+/// connection, take 0.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NavigationTiming {
+    /// Time resolving the host name
+    pub dns_ms: f64,
+
+    /// Time connecting, including the TLS handshake
+    pub connect_ms: f64,
+
+    /// Time from sending the request to the first byte of the response
+    pub ttfb_ms: f64,
+
+    /// Time from the start of the navigation to the end of the `load` event,
+    /// if the page has finished loading
+    pub load_ms: Option<f64>,
+}
+
+/// Outcome of a navigation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NavigationResult {
+    /// URL the navigation ended at, after redirects
+    pub url: String,
+
+    /// HTTP status of the final response, if known
+    pub status: Option<u16>,
+
+    /// Redirects followed, in order
+    pub redirects: Vec<Redirect>,
+
+    /// Timing breakdown of the final request
+    pub timing: NavigationTiming,
+}
+
+impl NavigationResult {
+    /// Returns whether the navigation was redirected.
+    pub fn was_redirected(&self) -> bool {
+        !self.redirects.is_empty()
+    }
+}
+
+/// Navigation Timing entry as read by [`NAVIGATION_ENTRY_SCRIPT`].
+#[derive(Debug, Deserialize)]
+pub(crate) struct NavigationEntry {
+    #[serde(flatten)]
+    pub(crate) timing: NavigationTiming,
+    pub(crate) status: Option<u16>,
+}
+
+/// Document request of a navigation, as reported by the network events.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct DocumentLoad {
+    /// URL of the latest request of the chain
+    pub(crate) url: String,
+
+    /// Status of the final response, once received
+    pub(crate) status: Option<u16>,
+
+    /// Redirects followed so far
+    pub(crate) redirects: Vec<Redirect>,
+}
+
+/// Document requests seen during a navigation, by loader ID.
+#[derive(Debug, Default)]
+pub(crate) struct NavigationLog {
+    loads: HashMap<String, DocumentLoad>,
+}
+
+impl NavigationLog {
+    /// Applies a `Network.requestWillBeSent` or `Network.responseReceived` event.
+    fn apply(&mut self, method: &str, params: &serde_json::Value) {
+        if params["type"] != "Document" {
+            return;
+        }
+        let loader_id = match params["loaderId"].as_str() {
+            Some(loader_id) => loader_id.to_string(),
+            None => return,
+        };
+        let load = self.loads.entry(loader_id).or_default();
+
+        match method {
+            "Network.requestWillBeSent" => {
+                // Each hop of a redirect chain is sent again with the response that redirected it
+                let redirect = &params["redirectResponse"];
+                if let Some(status) = redirect["status"].as_u64() {
+                    load.redirects.push(Redirect {
+                        url: redirect["url"].as_str().unwrap_or(&load.url).to_string(),
+                        status: status as u16,
+                    });
+                }
+                load.url = params["request"]["url"].as_str().unwrap_or_default().to_string();
+            }
+            "Network.responseReceived" => {
+                let response = &params["response"];
+                load.status = response["status"].as_u64().map(|status| status as u16);
+                if let Some(url) = response["url"].as_str() {
+                    load.url = url.to_string();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Takes the document request of a loader.
+    pub(crate) fn take(&mut self, loader_id: &str) -> Option<DocumentLoad> {
+        self.loads.remove(loader_id)
+    }
+}
+
+/// Collects the network events of a page while it navigates.
+pub(crate) struct NavigationRecorder {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<NavigationLog>,
+}
+
+impl NavigationRecorder {
+    /// Starts collecting, before the navigation is sent so no event is missed.
+    pub(crate) async fn start(page: &Page) -> Result<Self> {
+        let events = page.connection.subscribe("Target.receivedMessageFromTarget".to_string()).await?;
+        page.send_session_command("Network.enable", None).await?;
+
+        let (stop, stopped) = oneshot::channel();
+        let task = tokio::spawn(collect(events, page.session_id.clone(), stopped));
+        Ok(Self { stop, task })
+    }
+
+    /// Stops collecting and returns the events collected.
+    pub(crate) async fn finish(self) -> NavigationLog {
+        let _ = self.stop.send(());
+        self.task.await.unwrap_or_default()
+    }
+}
+
+/// Applies the events of a session to a log until told to stop.
+async fn collect(
+    mut events: mpsc::Receiver<crate::protocol::Event>,
+    session_id: String,
+    mut stopped: oneshot::Receiver<()>,
+) -> NavigationLog {
+    let mut log = NavigationLog::default();
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => match event {
+                Some(event) => event,
+                None => break,
+            },
+            // Events already received are applied below
+            _ = &mut stopped => break,
+        };
+        apply_event(&mut log, &session_id, event);
+    }
+    while let Ok(event) = events.try_recv() {
+        apply_event(&mut log, &session_id, event);
+    }
+    log
+}
+
+/// Applies a message of a session to a log.
+fn apply_event(log: &mut NavigationLog, session_id: &str, event: crate::protocol::Event) {
+    let params = match event.params {
+        Some(params) if params["sessionId"] == session_id => params,
+        _ => return,
+    };
+    let message: serde_json::Value = match params["message"].as_str().map(serde_json::from_str) {
+        Some(Ok(message)) => message,
+        _ => return,
+    };
+
+    if let Some(method) = message["method"].as_str() {
+        log.apply(method, &message["params"]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redirect_chain() {
+        let mut log = NavigationLog::default();
+        log.apply("Network.requestWillBeSent", &json!({
+            "loaderId": "L1",
+            "type": "Document",
+            "request": { "url": "http://example.com/" },
+        }));
+        log.apply("Network.requestWillBeSent", &json!({
+            "loaderId": "L1",
+            "type": "Document",
+            "request": { "url": "https://example.com/" },
+            "redirectResponse": { "url": "http://example.com/", "status": 301 },
+        }));
+        log.apply("Network.requestWillBeSent", &json!({
+            "loaderId": "L1",
+            "type": "Document",
+            "request": { "url": "https://consent.example.com/?continue=%2F" },
+            "redirectResponse": { "url": "https://example.com/", "status": 302 },
+        }));
+        // Subresources and other loaders are ignored
+        log.apply("Network.responseReceived", &json!({
+            "loaderId": "L1",
+            "type": "Script",
+            "response": { "url": "https://consent.example.com/app.js", "status": 404 },
+        }));
+        log.apply("Network.responseReceived", &json!({
+            "loaderId": "L1",
+            "type": "Document",
+            "response": { "url": "https://consent.example.com/?continue=%2F", "status": 200 },
+        }));
+
+        let load = log.take("L1").unwrap();
+        assert_eq!(load.url, "https://consent.example.com/?continue=%2F");
+        assert_eq!(load.status, Some(200));
+        assert_eq!(
+            load.redirects,
+            vec![
+                Redirect { url: "http://example.com/".to_string(), status: 301 },
+                Redirect { url: "https://example.com/".to_string(), status: 302 },
+            ]
+        );
+        assert!(log.take("L2").is_none());
+    }
+
+    #[test]
+    fn test_navigation_entry() {
+        let entry: NavigationEntry = serde_json::from_value(json!({
+            "dnsMs": 12.5,
+            "connectMs": 30.0,
+            "ttfbMs": 80.2,
+            "loadMs": null,
+            "status": 200,
+        }))
+        .unwrap();
+        assert_eq!(entry.status, Some(200));
+        assert_eq!(entry.timing.dns_ms, 12.5);
+        assert_eq!(entry.timing.load_ms, None);
+    }
+}
@@ -5,6 +5,11 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
+use crate::errors::{Error, Result};
+use crate::launcher::LaunchTarget;
+
+/// Header line opening a certificate in a PEM file.
+const PEM_CERTIFICATE_HEADER: &str = "-----BEGIN CERTIFICATE-----";
 
 /// Configuration options for launching a browser.
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -44,6 +49,9 @@ pub struct BrowserOptions {
     
     /// Download path.
     pub downloads_path: Option<PathBuf>,
+    
+    /// Where to launch the browser. Defaults to a local process.
+    pub launch_target: Option<LaunchTarget>,
 }
 
 impl Default for BrowserOptions {
@@ -61,6 +69,7 @@ impl Default for BrowserOptions {
             slow_mo: None,
             devtools: Some(false),
             downloads_path: None,
+            launch_target: None,
         }
     }
 }
@@ -113,6 +122,9 @@ pub struct ContextOptions {
     /// HTTP credentials.
     pub http_credentials: Option<HttpCredentials>,
     
+    /// PEM files of additional certificate authorities trusted by the context.
+    pub ca_certificates: Option<Vec<PathBuf>>,
+    
     /// Offline mode.
     pub offline: Option<bool>,
     
@@ -121,6 +133,15 @@ pub struct ContextOptions {
     
     /// Whether to record videos.
     pub record_video: Option<RecordVideo>,
+    
+    /// Resource types whose requests are blocked.
+    pub blocked_resource_types: Option<Vec<ResourceType>>,
+    
+    /// Domains whose requests are blocked, including their subdomains.
+    pub blocked_domains: Option<Vec<String>>,
+    
+    /// Bytes pages of the context may receive before further requests are aborted.
+    pub max_bytes: Option<u64>,
 }
 
 impl Default for ContextOptions {
@@ -144,13 +165,91 @@ impl Default for ContextOptions {
             proxy: None,
             cookies: None,
             http_credentials: None,
+            ca_certificates: None,
             offline: Some(false),
             color_scheme: Some(ColorScheme::Light),
             record_video: None,
+            blocked_resource_types: None,
+            blocked_domains: None,
+            max_bytes: None,
         }
     }
 }
 
+impl ContextOptions {
+    /// Blocks requests for the given resource types in every page of the context.
+    pub fn block_resources(mut self, resource_types: &[ResourceType]) -> Self {
+        let blocked = self.blocked_resource_types.get_or_insert_with(Vec::new);
+        for resource_type in resource_types {
+            if !blocked.contains(resource_type) {
+                blocked.push(*resource_type);
+            }
+        }
+        self
+    }
+    
+    /// Blocks requests to the given domains and their subdomains.
+    pub fn block_domains(mut self, domains: &[&str]) -> Self {
+        let blocked = self.blocked_domains.get_or_insert_with(Vec::new);
+        for domain in domains {
+            let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+            if !blocked.contains(&domain) {
+                blocked.push(domain);
+            }
+        }
+        self
+    }
+    
+    /// Blocks requests to every domain on a built-in block list.
+    pub fn block_list(self, list: BlockList) -> Self {
+        self.block_domains(list.domains())
+    }
+    
+    /// Aborts every request of the context once its pages have received `max_bytes` bytes.
+    pub fn limit_transfer(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+    
+    /// Answers HTTP authentication prompts (Basic, Digest, or NTLM) with the given credentials.
+    pub fn http_credentials(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.http_credentials = Some(HttpCredentials::new(username, password));
+        self
+    }
+    
+    /// Sets whether certificate errors are ignored, e.g. behind TLS-inspecting proxies.
+    pub fn ignore_https_errors(mut self, ignore: bool) -> Self {
+        self.ignore_https_errors = Some(ignore);
+        self
+    }
+    
+    /// Trusts the certificate authorities of a PEM file in addition to the system ones.
+    pub fn ca_certificate(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let certificates = self.ca_certificates.get_or_insert_with(Vec::new);
+        if !certificates.contains(&path) {
+            certificates.push(path);
+        }
+        self
+    }
+    
+    /// Reads the PEM contents of the additional certificate authorities.
+    pub(crate) fn load_ca_certificates(&self) -> Result<Vec<String>> {
+        let mut certificates = Vec::new();
+        for path in self.ca_certificates.iter().flatten() {
+            let pem = std::fs::read_to_string(path)?;
+            if !pem.contains(PEM_CERTIFICATE_HEADER) {
+                return Err(Error::ContextCreationError(format!(
+                    "No PEM certificate found in {}",
+                    path.display()
+                )));
+            }
+            certificates.push(pem);
+        }
+        Ok(certificates)
+    }
+}
+
 /// Configuration options for a page.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PageOptions {
@@ -180,6 +279,19 @@ pub struct PageOptions {
     
     /// User agent to use for this page.
     pub user_agent: Option<String>,
+    
+    /// Whether to ask the page's Llama model for a replacement when a selector matches nothing.
+    #[serde(default)]
+    pub ai_selector_recovery: Option<bool>,
+    
+    /// Whether element handles whose node was detached, e.g. by a re-render,
+    /// find their element again from the selectors that produced them.
+    ///
+    /// Off by default: a re-resolved handle may point at a different element
+    /// than the one first found, and checking whether the node is still
+    /// attached costs one extra protocol round trip per element command.
+    #[serde(default)]
+    pub reresolve_detached_elements: Option<bool>,
 }
 
 impl Default for PageOptions {
@@ -194,6 +306,105 @@ impl Default for PageOptions {
             javascript_enabled: Some(true),
             bypass_csp: Some(false),
             user_agent: None,
+            ai_selector_recovery: Some(false),
+            reresolve_detached_elements: Some(false),
+        }
+    }
+}
+
+/// Types of resources a page can request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum ResourceType {
+    /// HTML documents, including frames.
+    Document,
+    
+    /// CSS stylesheets.
+    Stylesheet,
+    
+    /// Images.
+    Image,
+    
+    /// Audio and video.
+    Media,
+    
+    /// Web fonts.
+    Font,
+    
+    /// Scripts.
+    Script,
+    
+    /// Subtitle and caption tracks.
+    TextTrack,
+    
+    /// XMLHttpRequest calls.
+    #[serde(rename = "XHR")]
+    Xhr,
+    
+    /// Fetch API calls.
+    Fetch,
+    
+    /// Server-sent event streams.
+    EventSource,
+    
+    /// WebSocket connections.
+    WebSocket,
+    
+    /// Web app manifests.
+    Manifest,
+    
+    /// Anything else.
+    Other,
+}
+
+/// Built-in domain block lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum BlockList {
+    /// Analytics, session recording, and tracking pixels.
+    Analytics,
+    
+    /// Ad networks and ad servers.
+    Ads,
+}
+
+impl BlockList {
+    /// Returns the domains on the list.
+    pub fn domains(&self) -> &'static [&'static str] {
+        match self {
+            BlockList::Analytics => &[
+                "google-analytics.com",
+                "analytics.google.com",
+                "googletagmanager.com",
+                "segment.io",
+                "segment.com",
+                "mixpanel.com",
+                "amplitude.com",
+                "heapanalytics.com",
+                "hotjar.com",
+                "fullstory.com",
+                "clarity.ms",
+                "nr-data.net",
+                "plausible.io",
+                "connect.facebook.net",
+                "bat.bing.com",
+                "stats.wp.com",
+            ],
+            BlockList::Ads => &[
+                "doubleclick.net",
+                "googlesyndication.com",
+                "googleadservices.com",
+                "adservice.google.com",
+                "amazon-adsystem.com",
+                "adnxs.com",
+                "criteo.com",
+                "criteo.net",
+                "taboola.com",
+                "outbrain.com",
+                "pubmatic.com",
+                "rubiconproject.com",
+                "openx.net",
+                "moatads.com",
+                "scorecardresearch.com",
+            ],
         }
     }
 }
@@ -245,10 +456,36 @@ pub struct HttpCredentials {
     
     /// Password.
     pub password: String,
+    
+    /// Origin the credentials are restricted to, e.g. `https://intranet.example.com`.
+    /// Any origin asking for authentication receives them if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+}
+
+impl HttpCredentials {
+    /// Creates credentials sent to any origin asking for authentication.
+    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            username: username.into(),
+            password: password.into(),
+            origin: None,
+        }
+    }
+    
+    /// Restricts the credentials to an origin.
+    pub fn with_origin(mut self, origin: impl Into<String>) -> Self {
+        self.origin = Some(origin.into());
+        self
+    }
 }
 
 /// Cookie data.
+///
+/// Serialized with the protocol's camelCase field names; snake_case names
+/// are accepted when deserializing.
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Cookie {
     /// Cookie name.
     pub name: String,
@@ -266,12 +503,14 @@ pub struct Cookie {
     pub expires: Option<f64>,
     
     /// Whether the cookie is HTTP only.
+    #[serde(alias = "http_only")]
     pub http_only: Option<bool>,
     
     /// Whether the cookie is secure.
     pub secure: Option<bool>,
     
     /// Same site policy.
+    #[serde(alias = "same_site")]
     pub same_site: Option<SameSite>,
 }
 
@@ -327,6 +566,82 @@ pub enum ColorScheme {
     NoPreference,
 }
 
+/// Media types for emulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Media {
+    /// Screen media.
+    #[serde(rename = "screen")]
+    Screen,
+    
+    /// Print media, applying print stylesheets.
+    #[serde(rename = "print")]
+    Print,
+}
+
+/// Reduced motion preferences for emulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ReducedMotion {
+    /// Prefer reduced motion.
+    #[serde(rename = "reduce")]
+    Reduce,
+    
+    /// No reduced motion preference.
+    #[serde(rename = "no-preference")]
+    NoPreference,
+}
+
+/// Forced colors modes for emulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ForcedColors {
+    /// Forced colors active, as with high contrast themes.
+    #[serde(rename = "active")]
+    Active,
+    
+    /// Forced colors inactive.
+    #[serde(rename = "none")]
+    None,
+}
+
+/// Media emulation options for a page.
+///
+/// Unset options are not emulated, leaving the browser's own value.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct MediaOptions {
+    /// Media type.
+    pub media: Option<Media>,
+    
+    /// Value of the `prefers-color-scheme` media feature.
+    pub color_scheme: Option<ColorScheme>,
+    
+    /// Value of the `prefers-reduced-motion` media feature.
+    pub reduced_motion: Option<ReducedMotion>,
+    
+    /// Value of the `forced-colors` media feature.
+    pub forced_colors: Option<ForcedColors>,
+}
+
+impl MediaOptions {
+    /// Returns the parameters of the protocol's `Emulation.setEmulatedMedia` command.
+    pub(crate) fn to_params(&self) -> serde_json::Value {
+        let mut features = Vec::new();
+        if let Some(color_scheme) = &self.color_scheme {
+            features.push(serde_json::json!({ "name": "prefers-color-scheme", "value": color_scheme }));
+        }
+        if let Some(reduced_motion) = self.reduced_motion {
+            features.push(serde_json::json!({ "name": "prefers-reduced-motion", "value": reduced_motion }));
+        }
+        if let Some(forced_colors) = self.forced_colors {
+            features.push(serde_json::json!({ "name": "forced-colors", "value": forced_colors }));
+        }
+        
+        serde_json::json!({
+            // An empty media type disables media type emulation
+            "media": self.media.map_or(serde_json::json!(""), |media| serde_json::json!(media)),
+            "features": features,
+        })
+    }
+}
+
 /// Video recording options.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct RecordVideo {
@@ -345,4 +660,60 @@ pub struct VideoSize {
     
     /// Height in pixels.
     pub height: i32,
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    
+    #[test]
+    fn test_credentials_and_certificates() {
+        let options = ContextOptions::default()
+            .http_credentials("alice", "secret")
+            .ignore_https_errors(true);
+        let credentials = options.http_credentials.as_ref().unwrap();
+        assert_eq!(credentials.username, "alice");
+        assert_eq!(credentials.password, "secret");
+        assert_eq!(options.ignore_https_errors, Some(true));
+        assert!(options.load_ca_certificates().unwrap().is_empty());
+        
+        let json = serde_json::to_value(HttpCredentials::new("alice", "secret")).unwrap();
+        assert!(json.get("origin").is_none());
+        
+        let mut pem = tempfile::NamedTempFile::new().unwrap();
+        writeln!(pem, "{}\nMIIB\n-----END CERTIFICATE-----", PEM_CERTIFICATE_HEADER).unwrap();
+        let options = options.ca_certificate(pem.path()).ca_certificate(pem.path());
+        assert_eq!(options.ca_certificates.as_ref().unwrap().len(), 1);
+        assert_eq!(options.load_ca_certificates().unwrap().len(), 1);
+        
+        let not_pem = tempfile::NamedTempFile::new().unwrap();
+        assert!(ContextOptions::default().ca_certificate(not_pem.path()).load_ca_certificates().is_err());
+    }
+    
+    #[test]
+    fn test_media_options_params() {
+        assert_eq!(
+            MediaOptions::default().to_params(),
+            serde_json::json!({ "media": "", "features": [] })
+        );
+        
+        let options = MediaOptions {
+            media: Some(Media::Print),
+            color_scheme: Some(ColorScheme::Dark),
+            reduced_motion: Some(ReducedMotion::Reduce),
+            forced_colors: Some(ForcedColors::Active),
+        };
+        assert_eq!(
+            options.to_params(),
+            serde_json::json!({
+                "media": "print",
+                "features": [
+                    { "name": "prefers-color-scheme", "value": "dark" },
+                    { "name": "prefers-reduced-motion", "value": "reduce" },
+                    { "name": "forced-colors", "value": "active" },
+                ],
+            })
+        );
+    }
+}
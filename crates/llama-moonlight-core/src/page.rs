@@ -3,11 +3,29 @@
 //! This module provides functionality for interacting with pages.
 
 use crate::errors::{Error, Result};
-use crate::element::ElementHandle;
+use crate::clock::Clock;
+use crate::coverage::{Coverage, CoverageState};
+use crate::crash::{self, CrashReason, PageCrash};
+use crate::dom_snapshot::{DomSnapshot, RawSnapshot, SnapshotOptions, DOM_SNAPSHOT_SCRIPT};
+use crate::element::{ElementHandle, ElementOrigin};
+use crate::event::{EventEmitter, PageEvent};
+use crate::js_handle::{self, JsHandle};
+use crate::llama_integration::{LlamaModel, DOM_SUMMARY_SCRIPT};
+use crate::load_state::LoadStateWaiter;
+use crate::navigation::{NavigationEntry, NavigationRecorder, NavigationResult, NAVIGATION_ENTRY_SCRIPT};
 use crate::protocol::Connection;
-use crate::options::PageOptions;
+use crate::options::{MediaOptions, PageOptions, WaitUntilState};
+use crate::request_log::RequestRecorder;
+use crate::request_metrics::{RequestMetrics, ResourceEntry, RESOURCE_ENTRIES_SCRIPT};
+use crate::retry::{NavigationFailure, NavigationFailureKind, RetryPolicy};
+use crate::selectors::Selectors;
+use crate::telemetry::FIELD_DOMAIN;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::future::Future;
 use std::sync::Arc;
-use log::{debug, info, warn};
+use tracing::{debug, info, instrument, warn};
+use tokio::sync::watch;
 use tokio::time::{timeout, Duration};
 use std::path::Path;
 
@@ -31,19 +49,49 @@ pub struct Page {
     
     /// Page options.
     pub(crate) options: PageOptions,
+    
+    /// Model suggesting replacements for selectors that match nothing.
+    pub(crate) llama_model: Option<LlamaModel>,
+    
+    /// Emitter of the page's events.
+    pub(crate) events: EventEmitter<PageEvent>,
+    
+    /// Crash of the page's renderer, once it crashed.
+    pub(crate) crash: Arc<watch::Sender<Option<PageCrash>>>,
+    
+    /// Coverage being collected.
+    pub(crate) coverage_state: std::sync::Mutex<CoverageState>,
 }
 
 impl Page {
     /// Navigates to the specified URL.
-    pub async fn goto(&self, url: &str) -> Result<()> {
+    ///
+    /// Returns where the navigation ended up: the final URL and status, the
+    /// redirects followed and the timings of the final request. Without a
+    /// `wait_until` state in the page options, the navigation may still be in
+    /// progress when this returns, leaving the status and timings unknown.
+    ///
+    /// Fails with [`Error::PageCrashed`] as soon as the page crashes, rather
+    /// than with a timeout.
+    #[instrument(skip(self), fields({ FIELD_DOMAIN } = %crate::telemetry::domain_of(url), target_id = %self.target_id))]
+    pub async fn goto(&self, url: &str) -> Result<NavigationResult> {
         info!("Navigating to {}", url);
-        
+        self.unless_crashed(self.navigate(url)).await
+    }
+    
+    /// Navigates to the specified URL, failing on errors and timeouts.
+    async fn navigate(&self, url: &str) -> Result<NavigationResult> {
         let timeout_ms = self.options.navigation_timeout_ms.unwrap_or(30000);
         
         let params = serde_json::json!({
             "url": url,
         });
         
+        let recorder = NavigationRecorder::start(self).await?;
+        let waiter = match &self.options.wait_until {
+            Some(wait_until) => Some(LoadStateWaiter::start(self, wait_until.clone()).await?),
+            None => None,
+        };
         let timeout_future = timeout(
             Duration::from_millis(timeout_ms),
             self.send_session_command("Page.navigate", Some(params)),
@@ -52,15 +100,15 @@ impl Page {
         match timeout_future.await {
             Ok(result) => {
                 match result {
-                    Ok(_) => {
+                    Ok(result) => {
                         info!("Successfully navigated to {}", url);
                         
                         // Wait for the page to be loaded
-                        if let Some(wait_until) = &self.options.wait_until {
-                            self.wait_for_navigation(wait_until).await?;
+                        if let Some(waiter) = waiter {
+                            self.wait_for_navigation(waiter, result["loaderId"].as_str()).await?;
                         }
                         
-                        Ok(())
+                        Ok(self.navigation_result(url, recorder, result["loaderId"].as_str()).await)
                     },
                     Err(e) => {
                         warn!("Failed to navigate to {}: {}", url, e);
@@ -75,38 +123,142 @@ impl Page {
         }
     }
     
-    /// Waits for navigation to complete.
-    async fn wait_for_navigation(&self, wait_until: &crate::options::WaitUntilState) -> Result<()> {
-        let event = match wait_until {
-            crate::options::WaitUntilState::Load => "Page.loadEventFired",
-            crate::options::WaitUntilState::DomContentLoaded => "Page.domContentEventFired",
-            _ => "Page.loadEventFired", // Default to load for other states
+    /// Navigates to the specified URL, retrying failures as given by the policy.
+    ///
+    /// Unlike [`goto`](Self::goto), a navigation answered with a 5xx status
+    /// fails, as a server error. Failures of kinds the policy does not retry
+    /// are returned after the first attempt, and a crash of the page right
+    /// away, as [`Error::PageCrashed`].
+    #[instrument(skip(self, policy), fields({ FIELD_DOMAIN } = %crate::telemetry::domain_of(url), target_id = %self.target_id))]
+    pub async fn goto_with_retry(&self, url: &str, policy: &RetryPolicy) -> Result<NavigationResult> {
+        let mut attempt = 1;
+        loop {
+            // A crashed page fails every attempt, so crashes are never retried
+            let failure = match self.unless_crashed(async { Ok(self.navigate_classified(url).await) }).await? {
+                Ok(navigation) => return Ok(navigation),
+                Err(failure) => failure,
+            };
+            
+            if !policy.should_retry(&failure, attempt) {
+                warn!("Giving up on {} after {} attempts: {}", url, attempt, failure);
+                return Err(Error::NavigationFailed { failure, attempts: attempt });
+            }
+            
+            let delay = policy.backoff.delay(attempt);
+            warn!("Attempt {} failed ({}), retrying in {:?}", attempt, failure, delay);
+            tokio::time::sleep(delay).await;
+            
+            if let Some(hook) = &policy.hook {
+                hook.before_retry(self, &failure, attempt).await?;
+            }
+            attempt += 1;
+        }
+    }
+    
+    /// Navigates once, classifying any failure.
+    async fn navigate_classified(&self, url: &str) -> std::result::Result<NavigationResult, NavigationFailure> {
+        let failure = |kind, status, message: String| NavigationFailure {
+            kind,
+            url: url.to_string(),
+            status,
+            message,
         };
         
-        // Enable page events
-        let _ = self.send_session_command("Page.enable", None).await?;
-        
-        // Subscribe to the event
-        let mut event_receiver = self.connection.subscribe(event.to_string()).await?;
-        
-        // Wait for the event
         let timeout_ms = self.options.navigation_timeout_ms.unwrap_or(30000);
-        match timeout(Duration::from_millis(timeout_ms), event_receiver.recv()).await {
-            Ok(Some(_)) => {
-                debug!("Navigation completed: event {} received", event);
-                Ok(())
-            },
-            Ok(None) => {
-                warn!("Event channel closed before receiving {}", event);
-                Err(Error::NavigationError("Event channel closed".to_string()))
-            },
+        let params = serde_json::json!({
+            "url": url,
+        });
+        
+        let recorder = NavigationRecorder::start(self)
+            .await
+            .map_err(|e| failure(NavigationFailureKind::Other, None, e.to_string()))?;
+        let waiter = match &self.options.wait_until {
+            Some(wait_until) => Some(
+                LoadStateWaiter::start(self, wait_until.clone())
+                    .await
+                    .map_err(|e| failure(NavigationFailureKind::Other, None, e.to_string()))?,
+            ),
+            None => None,
+        };
+        let result = match timeout(Duration::from_millis(timeout_ms), self.send_session_command("Page.navigate", Some(params))).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(e)) => {
+                let message = e.to_string();
+                return Err(failure(NavigationFailureKind::from_error_text(&message), None, message));
+            }
             Err(_) => {
-                warn!("Waiting for {} timed out after {}ms", event, timeout_ms);
-                Err(Error::TimeoutError(format!("Waiting for {} timed out after {}ms", event, timeout_ms)))
+                let message = format!("timed out after {}ms", timeout_ms);
+                return Err(failure(NavigationFailureKind::Timeout, None, message));
             }
+        };
+        
+        // Network errors are reported in the result rather than as a protocol error
+        if let Some(error_text) = result["errorText"].as_str().filter(|text| !text.is_empty()) {
+            return Err(failure(NavigationFailureKind::from_error_text(error_text), None, error_text.to_string()));
+        }
+        
+        if let Some(waiter) = waiter {
+            match self.wait_for_navigation(waiter, result["loaderId"].as_str()).await {
+                Ok(()) => {}
+                Err(Error::TimeoutError(message)) => return Err(failure(NavigationFailureKind::Timeout, None, message)),
+                Err(e) => return Err(failure(NavigationFailureKind::Other, None, e.to_string())),
+            }
+        }
+        
+        // An unknown status is never retried
+        let navigation = self.navigation_result(url, recorder, result["loaderId"].as_str()).await;
+        if let Some(status) = navigation.status.filter(|status| *status >= 500) {
+            return Err(failure(NavigationFailureKind::ServerError, Some(status), format!("HTTP {}", status)));
+        }
+        
+        debug!("Navigated to {} ({} redirects)", navigation.url, navigation.redirects.len());
+        Ok(navigation)
+    }
+    
+    /// Builds the result of a navigation from its network events and timing entry.
+    async fn navigation_result(&self, url: &str, recorder: NavigationRecorder, loader_id: Option<&str>) -> NavigationResult {
+        // Same-document navigations have no loader, and no document request
+        let mut log = recorder.finish().await;
+        let load = loader_id.and_then(|loader_id| log.take(loader_id)).unwrap_or_default();
+        
+        let entry: Option<NavigationEntry> = self.evaluate(NAVIGATION_ENTRY_SCRIPT).await.unwrap_or(None);
+        let (timing, entry_status) = entry.map(|entry| (entry.timing, entry.status)).unwrap_or_default();
+        
+        NavigationResult {
+            url: if load.url.is_empty() { url.to_string() } else { load.url },
+            status: load.status.or(entry_status),
+            redirects: load.redirects,
+            timing,
         }
     }
     
+    /// Waits for a navigation to reach its load state.
+    ///
+    /// Follows the document of the navigation's loader if known, so events
+    /// of the previous document do not count.
+    async fn wait_for_navigation(&self, mut waiter: LoadStateWaiter, loader_id: Option<&str>) -> Result<()> {
+        if let Some(loader_id) = loader_id {
+            waiter.follow_loader(loader_id);
+        }
+        waiter.wait(self.options.navigation_timeout_ms.unwrap_or(30000)).await
+    }
+    
+    /// Waits until the current document reaches a load state.
+    ///
+    /// Resolves right away if the state is already reached. The network idle
+    /// states are reached once the document has loaded and at most 0 or 2
+    /// requests have been in flight for 500 ms; the wait follows the page's
+    /// lifecycle and network events rather than polling it.
+    #[instrument(skip(self), fields(target_id = %self.target_id))]
+    pub async fn wait_for_load_state(&self, state: WaitUntilState) -> Result<()> {
+        info!("Waiting for load state {:?}", state);
+        self.unless_crashed(async {
+            let waiter = LoadStateWaiter::current(self, state).await?;
+            waiter.wait(self.options.navigation_timeout_ms.unwrap_or(30000)).await
+        })
+        .await
+    }
+    
     /// Takes a screenshot of the page.
     pub async fn screenshot(&self, path: &str) -> Result<()> {
         info!("Taking screenshot and saving to {}", path);
@@ -146,16 +298,25 @@ impl Page {
             Some(params),
         ).await?;
         
+        self.events.emit(PageEvent::Close);
         info!("Page {} closed", self.target_id);
         Ok(())
     }
     
+    /// Returns the cookies sent with requests to the page's URL.
+    ///
+    /// Unlike `document.cookie`, this includes HTTP-only cookies.
+    pub async fn cookies(&self) -> Result<Vec<crate::options::Cookie>> {
+        let result = self.send_session_command("Network.getCookies", None).await?;
+        Ok(serde_json::from_value(result["cookies"].clone())?)
+    }
+    
     /// Finds an element matching the specified selector.
     pub async fn query_selector(&self, selector: &str) -> Result<Option<ElementHandle>> {
         info!("Finding element with selector '{}'", selector);
         
-        // First we need to evaluate the document.querySelector in the page
-        let expression = format!(r#"document.querySelector("{}")"#, escape_string(selector));
+        // Evaluate the selector against the document in the page
+        let expression = format!("({})(document)", Selectors::query_function(selector, false)?);
         
         let params = serde_json::json!({
             "expression": expression,
@@ -177,12 +338,7 @@ impl Page {
         
         if let Some(id) = object_id {
             // Create an ElementHandle with the object ID
-            let element = ElementHandle {
-                connection: self.connection.clone(),
-                session_id: self.session_id.clone(),
-                object_id: id.to_string(),
-                page: self,
-            };
+            let element = ElementHandle::new(self, id, ElementOrigin::query(selector, 0));
             
             Ok(Some(element))
         } else {
@@ -195,8 +351,8 @@ impl Page {
     pub async fn query_selector_all(&self, selector: &str) -> Result<Vec<ElementHandle>> {
         info!("Finding all elements with selector '{}'", selector);
         
-        // Evaluate document.querySelectorAll in the page
-        let expression = format!(r#"Array.from(document.querySelectorAll("{}"))"#, escape_string(selector));
+        // Evaluate the selector against the document in the page
+        let expression = format!("({})(document)", Selectors::query_function(selector, true)?);
         
         let params = serde_json::json!({
             "expression": expression,
@@ -218,52 +374,14 @@ impl Page {
         let array_id = result["result"]["objectId"].as_str()
             .ok_or_else(|| Error::ElementNotFoundError("Failed to get array object ID".to_string()))?;
         
-        // Get the properties of the array
-        let params = serde_json::json!({
-            "objectId": array_id,
-            "ownProperties": true,
-        });
-        
-        let result = self.send_session_command("Runtime.getProperties", Some(params)).await?;
-        
-        // Process the properties to find elements
-        let properties = result["result"].as_array()
-            .ok_or_else(|| Error::ElementNotFoundError("Failed to get array properties".to_string()))?;
-        
-        let mut elements = Vec::new();
-        
-        for property in properties {
-            if let Some(object_id) = property["value"]["objectId"].as_str() {
-                // Check if this is an element
-                let params = serde_json::json!({
-                    "objectId": object_id,
-                    "expression": "this instanceof Element",
-                    "returnByValue": true,
-                });
-                
-                let result = self.send_session_command("Runtime.callFunctionOn", Some(params)).await?;
-                
-                if let Some(is_element) = result["result"]["value"].as_bool() {
-                    if is_element {
-                        // Create an ElementHandle
-                        let element = ElementHandle {
-                            connection: self.connection.clone(),
-                            session_id: self.session_id.clone(),
-                            object_id: object_id.to_string(),
-                            page: self,
-                        };
-                        
-                        elements.push(element);
-                    }
-                }
-            }
-        }
+        let elements = self.element_handles(array_id, &ElementOrigin::document(), selector).await?;
         
         info!("Found {} elements with selector '{}'", elements.len(), selector);
         Ok(elements)
     }
     
     /// Evaluates JavaScript code in the page context.
+    #[instrument(skip(self, expression), fields(target_id = %self.target_id))]
     pub async fn evaluate<T: serde::de::DeserializeOwned>(&self, expression: &str) -> Result<T> {
         info!("Evaluating JavaScript in page: {}", expression);
         
@@ -290,6 +408,82 @@ impl Page {
             .map_err(|e| Error::JavaScriptError(format!("Failed to deserialize result: {}", e)))
     }
     
+    /// Calls a JavaScript function in the page context with serialized arguments.
+    ///
+    /// `args` must serialize to an array, such as a tuple, whose items are
+    /// passed to the function in order: `(value,)` for a single argument and
+    /// `()` for none. Arguments reach the function as values, so strings with
+    /// quotes or newlines need no escaping.
+    ///
+    /// ```rust,no_run
+    /// # async fn example(page: &llama_moonlight_core::Page) -> Result<(), llama_moonlight_core::Error> {
+    /// let text: String = page
+    ///     .evaluate_with_args("(sel, suffix) => document.querySelector(sel).textContent + suffix", ("h1", "'s"))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(skip(self, function, args), fields(target_id = %self.target_id))]
+    pub async fn evaluate_with_args<T: DeserializeOwned, A: Serialize>(&self, function: &str, args: A) -> Result<T> {
+        info!("Evaluating JavaScript function in page: {}", function);
+        
+        let params = serde_json::json!({
+            "expression": js_handle::call_expression(function, args)?,
+            "returnByValue": true,
+            "awaitPromise": true,
+        });
+        
+        let result = self.send_session_command("Runtime.evaluate", Some(params)).await?;
+        js_handle::check_exception(&result, "Unknown error during JavaScript evaluation")?;
+        js_handle::deserialize_result(result["result"]["value"].clone())
+    }
+    
+    /// Evaluates JavaScript code in the page context, returning a handle to its result.
+    ///
+    /// Unlike [`evaluate`](Self::evaluate), the result may be anything,
+    /// including values that cannot be serialized such as DOM nodes.
+    pub async fn evaluate_handle(&self, expression: &str) -> Result<JsHandle<'_>> {
+        let params = serde_json::json!({
+            "expression": expression,
+            "returnByValue": false,
+            "awaitPromise": true,
+        });
+        
+        let result = self.send_session_command("Runtime.evaluate", Some(params)).await?;
+        js_handle::check_exception(&result, "Unknown error during JavaScript evaluation")?;
+        Ok(JsHandle::new(self, result["result"].clone()))
+    }
+    
+    /// Calls a JavaScript function in the page context with serialized
+    /// arguments, returning a handle to its result.
+    ///
+    /// See [`evaluate_with_args`](Self::evaluate_with_args) for how arguments are passed.
+    pub async fn evaluate_handle_with_args<A: Serialize>(&self, function: &str, args: A) -> Result<JsHandle<'_>> {
+        self.evaluate_handle(&js_handle::call_expression(function, args)?).await
+    }
+    
+    /// Calls a JavaScript function with the first element matching a selector
+    /// as its first argument, followed by `args`.
+    ///
+    /// Fails with [`Error::ElementNotFoundError`] if nothing matches.
+    pub async fn evaluate_on_selector<T: DeserializeOwned, A: Serialize>(
+        &self,
+        selector: &str,
+        function: &str,
+        args: A,
+    ) -> Result<T> {
+        let element = self.query_selector(selector).await?
+            .ok_or_else(|| Error::ElementNotFoundError(format!("No element matches selector '{}'", selector)))?;
+        element.evaluate(function, args).await
+    }
+    
+    /// Adds a script evaluated in every document loaded afterwards, before its own scripts.
+    pub async fn add_init_script(&self, script: &str) -> Result<()> {
+        let params = serde_json::json!({ "source": script });
+        self.send_session_command("Page.addScriptToEvaluateOnNewDocument", Some(params)).await?;
+        Ok(())
+    }
+    
     /// Returns the page title.
     pub async fn title(&self) -> Result<String> {
         info!("Getting page title");
@@ -380,8 +574,7 @@ impl Page {
         info!("Clicking on element with selector '{}'", selector);
         
         // Find the element
-        let element = self.query_selector(selector).await?
-            .ok_or_else(|| Error::ElementNotFoundError(format!("Element with selector '{}' not found", selector)))?;
+        let element = self.find_element("click", selector).await?;
         
         // Click the element
         element.click().await?;
@@ -390,13 +583,80 @@ impl Page {
         Ok(())
     }
     
+    /// Clicks on an element matching the selector and waits for the navigation it starts.
+    ///
+    /// Waits for the `load` event unless `PageOptions::wait_until` says otherwise.
+    pub async fn click_and_wait_for_navigation(&self, selector: &str) -> Result<()> {
+        info!("Clicking on element with selector '{}' and waiting for navigation", selector);
+        
+        let element = self.find_element("click", selector).await?;
+        
+        // Start waiting before clicking so a fast navigation is not missed
+        let wait_until = self.options.wait_until.clone().unwrap_or(WaitUntilState::Load);
+        let waiter = LoadStateWaiter::start(self, wait_until).await?;
+        element.click().await?;
+        self.wait_for_navigation(waiter, None).await?;
+        
+        info!("Navigation after clicking '{}' completed", selector);
+        Ok(())
+    }
+    
+    /// Sets the model used for selector recovery.
+    ///
+    /// The model is only consulted if `PageOptions::ai_selector_recovery` is enabled.
+    pub fn with_llama_model(mut self, model: LlamaModel) -> Self {
+        self.llama_model = Some(model);
+        self
+    }
+    
+    /// Returns the model used for selector recovery, if any.
+    pub fn llama_model(&self) -> Option<&LlamaModel> {
+        self.llama_model.as_ref()
+    }
+    
+    /// Finds the element an action targets, recovering from stale selectors if enabled.
+    async fn find_element(&self, action: &str, selector: &str) -> Result<ElementHandle<'_>> {
+        if let Some(element) = self.query_selector(selector).await? {
+            return Ok(element);
+        }
+        
+        let not_found = || Error::ElementNotFoundError(format!("Element with selector '{}' not found", selector));
+        
+        let model = match &self.llama_model {
+            Some(model) if self.options.ai_selector_recovery.unwrap_or(false) => model,
+            _ => return Err(not_found()),
+        };
+        
+        info!("Asking model for a replacement of selector '{}'", selector);
+        
+        let dom_summary: String = self.evaluate(DOM_SUMMARY_SCRIPT).await?;
+        let replacement = match model.suggest_selector(action, selector, &dom_summary).await {
+            Ok(Some(replacement)) => replacement,
+            Ok(None) => return Err(not_found()),
+            Err(e) => {
+                warn!("Selector recovery for '{}' failed: {}", selector, e);
+                return Err(not_found());
+            }
+        };
+        
+        // Invalid suggestions are treated like suggestions that match nothing
+        let element = match self.query_selector(&replacement).await {
+            Ok(Some(element)) => element,
+            _ => return Err(not_found()),
+        };
+        
+        let page_url = self.url().await.unwrap_or_default();
+        model.record_substitution(&page_url, action, selector, &replacement);
+        
+        Ok(element)
+    }
+    
     /// Types text into an element matching the selector.
     pub async fn type_text(&self, selector: &str, text: &str) -> Result<()> {
         info!("Typing text into element with selector '{}'", selector);
         
         // Find the element
-        let element = self.query_selector(selector).await?
-            .ok_or_else(|| Error::ElementNotFoundError(format!("Element with selector '{}' not found", selector)))?;
+        let element = self.find_element("type into", selector).await?;
         
         // Type text into the element
         element.type_text(text).await?;
@@ -405,25 +665,324 @@ impl Page {
         Ok(())
     }
     
-    /// Sends a protocol command to the page session.
-    async fn send_session_command(&self, method: &str, params: Option<serde_json::Value>) -> Result<serde_json::Value> {
-        // For session commands, we need to wrap the method and params in a Target.sendMessageToTarget command
-        let session_params = serde_json::json!({
-            "sessionId": self.session_id,
-            "message": serde_json::to_string(&serde_json::json!({
-                "id": 1,
-                "method": method,
-                "params": params.unwrap_or(serde_json::json!({})),
-            })).unwrap(),
+    /// Types text into an element matching the selector, pausing after each character.
+    ///
+    /// See [`ElementHandle::type_text_with_delays`].
+    pub async fn type_text_with_delays(&self, selector: &str, text: &str, delays: &[Duration]) -> Result<()> {
+        let element = self.find_element("type into", selector).await?;
+        element.type_text_with_delays(text, delays).await
+    }
+    
+    /// Selects options of a `<select>` element matching the selector by value or label.
+    ///
+    /// Returns the values of the selected options.
+    pub async fn select_option(&self, selector: &str, values: &[String]) -> Result<Vec<String>> {
+        let element = self.find_element("select options of", selector).await?;
+        element.select_option(values).await
+    }
+    
+    /// Checks or unchecks a checkbox or radio button matching the selector.
+    pub async fn set_checked(&self, selector: &str, checked: bool) -> Result<()> {
+        let element = self.find_element("check", selector).await?;
+        element.set_checked(checked).await
+    }
+    
+    /// Sets the files of a file input matching the selector.
+    pub async fn set_input_files<P: AsRef<Path>>(&self, selector: &str, paths: &[P]) -> Result<()> {
+        let element = self.find_element("set files of", selector).await?;
+        element.set_input_files(paths).await
+    }
+    
+    /// Returns request count, size, and timing metrics for the current navigation.
+    ///
+    /// The metrics are also published as `page.*` gauges through the
+    /// `metrics` facade.
+    pub async fn request_metrics(&self) -> Result<RequestMetrics> {
+        info!("Collecting request metrics for page {}", self.target_id);
+        
+        let entries: Vec<ResourceEntry> = self.evaluate(RESOURCE_ENTRIES_SCRIPT).await?;
+        let metrics = RequestMetrics::from_entries(&entries);
+        metrics.record_gauges();
+        
+        info!("Page {} made {} requests ({} bytes)", self.target_id, metrics.request_count, metrics.transfer_bytes);
+        Ok(metrics)
+    }
+    
+    /// Returns a normalized snapshot of the page's DOM.
+    ///
+    /// Attributes are sorted and volatile ones such as nonces are dropped, so
+    /// snapshots of an unchanged page are equal. Diff a stored snapshot
+    /// against a fresh one with [`DomSnapshot::diff`] to notice structure
+    /// changes that would break extraction selectors.
+    pub async fn dom_snapshot(&self) -> Result<DomSnapshot> {
+        self.dom_snapshot_with_options(&SnapshotOptions::default()).await
+    }
+    
+    /// Returns a snapshot of the page's DOM, normalized with the given options.
+    pub async fn dom_snapshot_with_options(&self, options: &SnapshotOptions) -> Result<DomSnapshot> {
+        info!("Taking DOM snapshot of page {}", self.target_id);
+        
+        let raw: RawSnapshot = self.evaluate(DOM_SNAPSHOT_SCRIPT).await?;
+        let snapshot = DomSnapshot::from_raw(raw, options);
+        
+        debug!("DOM snapshot of page {} has {} elements", self.target_id, snapshot.root.element_count());
+        Ok(snapshot)
+    }
+    
+    /// Starts recording the requests the page sends, with their wire headers.
+    ///
+    /// Only requests sent after the call are recorded, so start recording
+    /// before navigating.
+    pub async fn record_requests(&self) -> Result<RequestRecorder> {
+        crate::request_log::record_requests(self).await
+    }
+    
+    /// Returns the emitter of the page's events.
+    pub fn events(&self) -> &EventEmitter<PageEvent> {
+        &self.events
+    }
+    
+    /// Returns the crash of the page's renderer, if it crashed.
+    pub fn crash(&self) -> Option<PageCrash> {
+        self.crash.borrow().clone()
+    }
+    
+    /// Returns whether the page's renderer crashed.
+    pub fn is_crashed(&self) -> bool {
+        self.crash.borrow().is_some()
+    }
+    
+    /// Runs an operation on the page, failing with [`Error::PageCrashed`] as
+    /// soon as the page crashes, or right away if it already did.
+    async fn unless_crashed<T>(&self, operation: impl Future<Output = Result<T>>) -> Result<T> {
+        let mut crashes = self.crash.subscribe();
+        tokio::pin!(operation);
+        
+        tokio::select! {
+            crash = crashes.wait_for(Option::is_some) => match crash.ok().and_then(|crash| (*crash).clone()) {
+                Some(crash) => Err(Error::PageCrashed(crash)),
+                None => operation.await,
+            },
+            result = &mut operation => result,
+        }
+    }
+    
+    /// Emits the page's protocol events through its emitter until the connection closes.
+    pub(crate) async fn forward_protocol_events(&self) -> Result<()> {
+        // Subscribe before enabling the domains so no event is missed
+        let mut messages = self.connection.subscribe("Target.receivedMessageFromTarget".to_string()).await?;
+        let mut terminations = self.connection.subscribe("Target.targetCrashed".to_string()).await?;
+        self.send_session_command("Page.enable", None).await?;
+        self.send_session_command("Runtime.enable", None).await?;
+        self.send_session_command("Inspector.enable", None).await?;
+        
+        // The browser only reports how renderers end to clients discovering targets
+        let discover = serde_json::json!({ "discover": true });
+        if let Err(e) = self.connection.send_request("Target.setDiscoverTargets".to_string(), Some(discover)).await {
+            debug!("Failed to discover targets, crashes will not tell out-of-memory kills apart: {}", e);
+        }
+        
+        let events = self.events.clone();
+        let crash = self.crash.clone();
+        let session_id = self.session_id.clone();
+        let target_id = self.target_id.clone();
+        tokio::spawn(async move {
+            let mut last_url = None;
+            loop {
+                let reason = tokio::select! {
+                    message = messages.recv() => {
+                        let params = match message {
+                            Some(message) => match message.params {
+                                Some(params) if params["sessionId"] == session_id.as_str() => params,
+                                _ => continue,
+                            },
+                            None => break,
+                        };
+                        let message: serde_json::Value = match params["message"].as_str().map(serde_json::from_str) {
+                            Some(Ok(message)) => message,
+                            _ => continue,
+                        };
+                        
+                        let method = message["method"].as_str().unwrap_or_default();
+                        if method != "Inspector.targetCrashed" {
+                            if let Some(event) = PageEvent::from_protocol(method, &message["params"]) {
+                                if let PageEvent::Navigated { url } = &event {
+                                    last_url = Some(url.clone()).filter(|url| !url.is_empty());
+                                }
+                                events.emit(event);
+                            }
+                            continue;
+                        }
+                        if crash.borrow().is_some() {
+                            continue;
+                        }
+                        
+                        // The browser may report how the renderer ended right after the session
+                        let deadline = tokio::time::Instant::now() + crash::TERMINATION_STATUS_GRACE;
+                        let mut reason = CrashReason::Crashed;
+                        while let Ok(Some(termination)) = tokio::time::timeout_at(deadline, terminations.recv()).await {
+                            if let Some(ended) = termination.params.and_then(|params| crash::termination_reason(&params, &target_id)) {
+                                reason = ended;
+                                break;
+                            }
+                        }
+                        reason
+                    },
+                    Some(termination) = terminations.recv() => {
+                        match termination.params.and_then(|params| crash::termination_reason(&params, &target_id)) {
+                            Some(reason) => reason,
+                            None => continue,
+                        }
+                    },
+                };
+                
+                // The session and the browser both report the crash
+                if crash.borrow().is_some() {
+                    continue;
+                }
+                let page_crash = PageCrash { reason, url: last_url.clone() };
+                warn!("Page {} crashed: {}", target_id, page_crash);
+                crash.send_replace(Some(page_crash.clone()));
+                events.emit(PageEvent::Crash(page_crash));
+            }
+            debug!("Event forwarding stopped for session {}", session_id);
         });
         
-        self.connection.send_request(
-            "Target.sendMessageToTarget".to_string(),
-            Some(session_params),
-        ).await.map_err(Error::ProtocolError)
+        Ok(())
+    }
+    
+    /// Reads text from the clipboard.
+    ///
+    /// Requires the `clipboard-read` permission (see
+    /// [`BrowserContext::grant_permissions`](crate::BrowserContext::grant_permissions)).
+    pub async fn clipboard_read_text(&self) -> Result<String> {
+        info!("Reading clipboard text");
+        
+        self.evaluate("navigator.clipboard.readText()").await
+    }
+    
+    /// Writes text to the clipboard.
+    ///
+    /// Uses the async clipboard API when the `clipboard-write` permission is
+    /// granted, falling back to a copy command on a temporary text area.
+    pub async fn clipboard_write_text(&self, text: &str) -> Result<()> {
+        info!("Writing {} characters to the clipboard", text.len());
+        
+        let expression = format!(
+            r#"(async (text) => {{
+                try {{
+                    await navigator.clipboard.writeText(text);
+                    return true;
+                }} catch (e) {{
+                    const area = document.createElement('textarea');
+                    area.value = text;
+                    document.body.appendChild(area);
+                    area.select();
+                    const copied = document.execCommand('copy');
+                    area.remove();
+                    return copied;
+                }}
+            }})("{}")"#,
+            escape_string(text)
+        );
+        
+        let copied: bool = self.evaluate(&expression).await?;
+        if copied {
+            Ok(())
+        } else {
+            Err(Error::JavaScriptError("Failed to write to the clipboard".to_string()))
+        }
+    }
+    
+    /// Emulates a media type and media features such as `prefers-reduced-motion`.
+    ///
+    /// Each call replaces the previous emulation, so options left unset
+    /// return to the browser's own values.
+    pub async fn emulate_media(&self, options: MediaOptions) -> Result<()> {
+        info!("Emulating media {:?} for page {}", options, self.target_id);
+        
+        self.send_session_command("Emulation.setEmulatedMedia", Some(options.to_params())).await?;
+        Ok(())
+    }
+    
+    /// Returns the fake clock controller for this page.
+    pub fn clock(&self) -> Clock<'_> {
+        Clock::new(self)
+    }
+    
+    /// Returns the JavaScript and CSS coverage controller for this page.
+    pub fn coverage(&self) -> Coverage<'_> {
+        Coverage::new(self)
+    }
+    
+    /// Creates element handles for the elements of an in-page array.
+    ///
+    /// The array holds the matches of `selector` below the node `parent` leads to.
+    pub(crate) async fn element_handles(&self, array_id: &str, parent: &ElementOrigin, selector: &str) -> Result<Vec<ElementHandle<'_>>> {
+        // Get the properties of the array
+        let params = serde_json::json!({
+            "objectId": array_id,
+            "ownProperties": true,
+        });
+        
+        let result = self.send_session_command("Runtime.getProperties", Some(params)).await?;
+        
+        // Process the properties to find elements
+        let properties = result["result"].as_array()
+            .ok_or_else(|| Error::ElementNotFoundError("Failed to get array properties".to_string()))?;
+        
+        let mut elements = Vec::new();
+        
+        for property in properties {
+            if let Some(object_id) = property["value"]["objectId"].as_str() {
+                // Check if this is an element
+                let params = serde_json::json!({
+                    "objectId": object_id,
+                    "functionDeclaration": "function() { return this instanceof Element; }",
+                    "returnByValue": true,
+                });
+                
+                let result = self.send_session_command("Runtime.callFunctionOn", Some(params)).await?;
+                
+                if let Some(is_element) = result["result"]["value"].as_bool() {
+                    if is_element {
+                        // Create an ElementHandle, the property name being its index in the array
+                        let index = property["name"].as_str().and_then(|name| name.parse().ok()).unwrap_or(elements.len());
+                        let element = ElementHandle::new(self, object_id, parent.then_query(selector, index));
+                        
+                        elements.push(element);
+                    }
+                }
+            }
+        }
+        
+        Ok(elements)
+    }
+    
+    /// Sends a protocol command to the page session.
+    pub(crate) async fn send_session_command(&self, method: &str, params: Option<serde_json::Value>) -> Result<serde_json::Value> {
+        send_to_session(&self.connection, &self.session_id, method, params).await
     }
 }
 
+/// Sends a protocol command to a page session.
+pub(crate) async fn send_to_session(connection: &Connection, session_id: &str, method: &str, params: Option<serde_json::Value>) -> Result<serde_json::Value> {
+    // For session commands, we need to wrap the method and params in a Target.sendMessageToTarget command
+    let session_params = serde_json::json!({
+        "sessionId": session_id,
+        "message": serde_json::to_string(&serde_json::json!({
+            "id": 1,
+            "method": method,
+            "params": params.unwrap_or(serde_json::json!({})),
+        })).unwrap(),
+    });
+    
+    connection.send_request(
+        "Target.sendMessageToTarget".to_string(),
+        Some(session_params),
+    ).await.map_err(Error::ProtocolError)
+}
+
 /// Escapes a string for JavaScript.
 fn escape_string(s: &str) -> String {
     s.replace('\\', "\\\\")
@@ -8,14 +8,14 @@ use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use tokio::net::TcpStream;
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{mpsc, oneshot, watch};
 use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
 use tokio_tungstenite::tungstenite::Message;
 use futures::{SinkExt, StreamExt};
 use std::time::Duration;
 use tokio::time::timeout;
 use uuid::Uuid;
-use log::{debug, error, info, warn};
+use tracing::{debug, error, info, warn};
 
 /// Errors that can occur during protocol communication.
 #[derive(Error, Debug)]
@@ -96,6 +96,7 @@ type EventSubscribers = HashMap<String, Vec<(usize, mpsc::Sender<Event>)>>;
 pub struct Connection {
     command_sender: mpsc::Sender<ConnectionCommand>,
     next_subscriber_id: Arc<Mutex<usize>>,
+    closed: watch::Receiver<bool>,
 }
 
 impl Connection {
@@ -105,16 +106,32 @@ impl Connection {
         
         let (command_sender, command_receiver) = mpsc::channel(100);
         let next_subscriber_id = Arc::new(Mutex::new(0));
+        let (closed_sender, closed) = watch::channel(false);
         
         // Spawn a worker task to handle the connection
-        tokio::spawn(Self::connection_worker(ws_stream, command_receiver));
+        tokio::spawn(Self::connection_worker(ws_stream, command_receiver, closed_sender));
         
         Ok(Self {
             command_sender,
             next_subscriber_id,
+            closed,
         })
     }
     
+    /// Returns whether the connection is closed, by either side.
+    pub fn is_closed(&self) -> bool {
+        *self.closed.borrow()
+    }
+    
+    /// Waits until the connection is closed, by either side.
+    ///
+    /// Completes when the browser drops the WebSocket, for instance because
+    /// its process died, as well as after [`close`](Self::close).
+    pub async fn closed(&self) {
+        let mut closed = self.closed.clone();
+        let _ = closed.wait_for(|closed| *closed).await;
+    }
+    
     /// Sends a request to the browser and waits for a response.
     pub async fn send_request(
         &self,
@@ -138,7 +155,11 @@ impl Connection {
             .await
             .map_err(|_| ProtocolError::ChannelClosed)?;
         
-        let response = response_receiver.await.map_err(|_| ProtocolError::ChannelClosed)?;
+        // Requests still pending when the browser goes away are never answered
+        let response = tokio::select! {
+            response = response_receiver => response.map_err(|_| ProtocolError::ChannelClosed)?,
+            _ = self.closed() => return Err(ProtocolError::ChannelClosed),
+        };
         let response = response?;
         
         if let Some(error) = response.error {
@@ -186,7 +207,9 @@ impl Connection {
     async fn connection_worker(
         ws_stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
         command_receiver: mpsc::Receiver<ConnectionCommand>,
+        closed_sender: watch::Sender<bool>,
     ) {
+        let closed_sender = Arc::new(closed_sender);
         let (mut ws_sender, mut ws_receiver) = ws_stream.split();
         let pending_requests = Arc::new(Mutex::new(HashMap::new()));
         let event_subscribers = Arc::new(Mutex::new(HashMap::<String, Vec<(usize, mpsc::Sender<Event>)>>::new()));
@@ -214,6 +237,7 @@ impl Connection {
         // Task for receiving messages
         let receiver_pending_requests = Arc::clone(&pending_requests);
         let receiver_event_subscribers = Arc::clone(&event_subscribers);
+        let receiver_closed_sender = Arc::clone(&closed_sender);
         tokio::spawn(async move {
             while let Some(message_result) = ws_receiver.next().await {
                 match message_result {
@@ -252,6 +276,9 @@ impl Connection {
                     }
                 }
             }
+            
+            // The browser is gone once its side of the socket ends
+            receiver_closed_sender.send_replace(true);
         });
         
         // Main command processing loop
@@ -323,6 +350,7 @@ impl Connection {
         
         // Cleanup
         let _ = ws_send_queue_tx.send(Message::Close(None)).await;
+        closed_sender.send_replace(true);
         info!("Connection closed");
     }
 }
@@ -412,4 +440,21 @@ mod tests {
         // Close the connection
         conn.close().await.unwrap();
     }
-} 
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_connection_closed_by_peer() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        
+        // A browser that hangs up right after the handshake
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws_stream = accept_async(stream).await.unwrap();
+            let _ = ws_stream.close(None).await;
+        });
+        
+        let conn = Connection::connect(&url).await.unwrap();
+        timeout(Duration::from_secs(1), conn.closed()).await.unwrap();
+        assert!(conn.is_closed());
+    }
+}
@@ -0,0 +1,115 @@
+//! Bandwidth quotas.
+//!
+//! Contexts created with a transfer limit (see
+//! [`ContextOptions::limit_transfer`](crate::ContextOptions::limit_transfer))
+//! count the bytes every page receives over the network, as reported when each
+//! response finishes loading. Once the limit is reached, a [`QuotaExceeded`]
+//! event is sent and every further request of the context is aborted, so a
+//! runaway page cannot burn through metered proxy bandwidth. Responses already
+//! in flight when the limit is reached still complete.
+
+use crate::options::ContextOptions;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::warn;
+
+/// Sent once when a context uses up its transfer quota.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuotaExceeded {
+    /// ID of the context
+    pub context_id: String,
+
+    /// Transfer limit of the context, in bytes
+    pub max_bytes: u64,
+
+    /// Bytes received when the limit was reached
+    pub used_bytes: u64,
+}
+
+/// Tracks the bytes received by the pages of a context.
+#[derive(Debug)]
+pub(crate) struct BandwidthQuota {
+    context_id: String,
+    max_bytes: u64,
+    used_bytes: AtomicU64,
+    exceeded: AtomicBool,
+    events: broadcast::Sender<QuotaExceeded>,
+}
+
+impl BandwidthQuota {
+    /// Creates a quota from the context options, if a transfer limit is set.
+    pub(crate) fn from_options(context_id: &str, options: &ContextOptions) -> Option<Arc<Self>> {
+        let max_bytes = options.max_bytes?;
+        let (events, _) = broadcast::channel(16);
+
+        Some(Arc::new(Self {
+            context_id: context_id.to_string(),
+            max_bytes,
+            used_bytes: AtomicU64::new(0),
+            exceeded: AtomicBool::new(false),
+            events,
+        }))
+    }
+
+    /// Adds received bytes, sending the event if they use up the quota.
+    pub(crate) fn record(&self, bytes: u64) {
+        let used_bytes = self.used_bytes.fetch_add(bytes, Ordering::SeqCst) + bytes;
+
+        if used_bytes >= self.max_bytes && !self.exceeded.swap(true, Ordering::SeqCst) {
+            warn!(
+                "Context {} exceeded its transfer quota ({} of {} bytes), aborting further requests",
+                self.context_id, used_bytes, self.max_bytes
+            );
+            // Nobody may be listening
+            let _ = self.events.send(QuotaExceeded {
+                context_id: self.context_id.clone(),
+                max_bytes: self.max_bytes,
+                used_bytes,
+            });
+        }
+    }
+
+    /// Returns whether the quota is used up.
+    pub(crate) fn is_exceeded(&self) -> bool {
+        self.exceeded.load(Ordering::SeqCst)
+    }
+
+    /// Returns the bytes received so far.
+    pub(crate) fn used_bytes(&self) -> u64 {
+        self.used_bytes.load(Ordering::SeqCst)
+    }
+
+    /// Subscribes to the quota exceeded event.
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<QuotaExceeded> {
+        self.events.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        assert!(BandwidthQuota::from_options("ctx", &ContextOptions::default()).is_none());
+
+        let quota = BandwidthQuota::from_options("ctx", &ContextOptions::default().limit_transfer(1000)).unwrap();
+        let mut events = quota.subscribe();
+
+        quota.record(600);
+        assert!(!quota.is_exceeded());
+        assert!(events.try_recv().is_err());
+
+        quota.record(500);
+        quota.record(100);
+        assert!(quota.is_exceeded());
+        assert_eq!(quota.used_bytes(), 1200);
+        assert_eq!(
+            events.try_recv().unwrap(),
+            QuotaExceeded { context_id: "ctx".to_string(), max_bytes: 1000, used_bytes: 1100 }
+        );
+        assert!(events.try_recv().is_err());
+    }
+}
@@ -0,0 +1,214 @@
+//! Request logs.
+//!
+//! A page can record the requests it sends (see
+//! [`Page::record_requests`](crate::Page::record_requests)). Each request
+//! carries the headers that went over the wire, as reported by the network
+//! stack: besides the headers set by the page, these include the ones the
+//! browser adds itself, such as `Cookie`, `Sec-CH-UA` and `Accept-Encoding`.
+//! This is what a site sees of a stealth profile, so it is the first thing to
+//! check when the profile gets challenged.
+
+use crate::errors::Result;
+use crate::page::Page;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info};
+
+/// A request sent by a page.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SentRequest {
+    /// Protocol ID of the request, shared by the requests of a redirect chain
+    pub request_id: String,
+
+    /// Requested URL
+    pub url: String,
+
+    /// HTTP method
+    pub method: String,
+
+    /// Resource type, e.g. `Document` or `Script`
+    pub resource_type: Option<String>,
+
+    /// Headers sent, by name as sent
+    pub headers: BTreeMap<String, String>,
+
+    /// Whether the headers are the ones sent over the wire, rather than only
+    /// those set by the page
+    pub headers_complete: bool,
+}
+
+/// Requests recorded for a page.
+#[derive(Debug, Default)]
+struct Log {
+    /// Requests in the order they were sent
+    requests: Vec<SentRequest>,
+
+    /// Index of the latest request with each ID
+    latest: HashMap<String, usize>,
+
+    /// Wire headers reported before their request
+    pending: HashMap<String, BTreeMap<String, String>>,
+}
+
+impl Log {
+    /// Applies a `Network.requestWillBeSent` or
+    /// `Network.requestWillBeSentExtraInfo` event.
+    fn apply(&mut self, method: &str, params: &serde_json::Value) {
+        let request_id = match params["requestId"].as_str() {
+            Some(request_id) => request_id.to_string(),
+            None => return,
+        };
+
+        match method {
+            "Network.requestWillBeSent" => {
+                let request = &params["request"];
+                let mut sent = SentRequest {
+                    request_id: request_id.clone(),
+                    url: request["url"].as_str().unwrap_or_default().to_string(),
+                    method: request["method"].as_str().unwrap_or("GET").to_string(),
+                    resource_type: params["type"].as_str().map(str::to_string),
+                    headers: headers_of(&request["headers"]),
+                    headers_complete: false,
+                };
+                if let Some(headers) = self.pending.remove(&request_id) {
+                    sent.headers = headers;
+                    sent.headers_complete = true;
+                }
+
+                self.latest.insert(request_id, self.requests.len());
+                self.requests.push(sent);
+            }
+            "Network.requestWillBeSentExtraInfo" => {
+                let headers = headers_of(&params["headers"]);
+                let index = self.latest.get(&request_id).copied();
+
+                match index.map(|index| &mut self.requests[index]) {
+                    Some(sent) if !sent.headers_complete => {
+                        sent.headers = headers;
+                        sent.headers_complete = true;
+                    }
+                    // A redirect reports the headers of the next request first
+                    _ => {
+                        self.pending.insert(request_id, headers);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Converts a protocol header object to a map.
+fn headers_of(headers: &serde_json::Value) -> BTreeMap<String, String> {
+    headers
+        .as_object()
+        .map(|headers| {
+            headers
+                .iter()
+                .map(|(name, value)| {
+                    let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+                    (name.clone(), value)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Records the requests of a page from the moment it is created.
+///
+/// Recording runs in a background task until the connection closes.
+#[derive(Debug, Clone, Default)]
+pub struct RequestRecorder {
+    log: Arc<Mutex<Log>>,
+}
+
+impl RequestRecorder {
+    /// Returns the requests recorded so far, in the order they were sent.
+    pub fn requests(&self) -> Vec<SentRequest> {
+        self.log.lock().unwrap().requests.clone()
+    }
+
+    /// Forgets the requests recorded so far.
+    pub fn clear(&self) {
+        let mut log = self.log.lock().unwrap();
+        log.requests.clear();
+        log.latest.clear();
+    }
+}
+
+/// Starts recording the requests of a page.
+pub(crate) async fn record_requests(page: &Page) -> Result<RequestRecorder> {
+    info!("Recording requests of page {}", page.target_id);
+
+    // Subscribe before enabling network events so no request is missed
+    let mut events = page.connection.subscribe("Target.receivedMessageFromTarget".to_string()).await?;
+    page.send_session_command("Network.enable", None).await?;
+
+    let recorder = RequestRecorder::default();
+    let log = recorder.log.clone();
+    let session_id = page.session_id.clone();
+    tokio::spawn(async move {
+        while let Some(event) = events.recv().await {
+            let params = match event.params {
+                Some(params) if params["sessionId"] == session_id.as_str() => params,
+                _ => continue,
+            };
+            let message: serde_json::Value = match params["message"].as_str().map(serde_json::from_str) {
+                Some(Ok(message)) => message,
+                _ => continue,
+            };
+
+            if let Some(method) = message["method"].as_str() {
+                log.lock().unwrap().apply(method, &message["params"]);
+            }
+        }
+        debug!("Request recording stopped for session {}", session_id);
+    });
+
+    Ok(recorder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_apply() {
+        let mut log = Log::default();
+
+        // Wire headers after the request
+        log.apply("Network.requestWillBeSent", &json!({
+            "requestId": "1",
+            "type": "Document",
+            "request": { "url": "https://example.com/", "method": "GET", "headers": { "User-Agent": "test" } },
+        }));
+        assert!(!log.requests[0].headers_complete);
+        log.apply("Network.requestWillBeSentExtraInfo", &json!({
+            "requestId": "1",
+            "headers": { "User-Agent": "test", "Cookie": "a=1", "sec-ch-ua": "\"Chromium\";v=\"120\"" },
+        }));
+
+        // Wire headers before the request
+        log.apply("Network.requestWillBeSentExtraInfo", &json!({
+            "requestId": "2",
+            "headers": { "Accept": "*/*" },
+        }));
+        log.apply("Network.requestWillBeSent", &json!({
+            "requestId": "2",
+            "type": "Script",
+            "request": { "url": "https://example.com/app.js", "method": "GET", "headers": {} },
+        }));
+        log.apply("Network.responseReceived", &json!({ "requestId": "2" }));
+
+        assert_eq!(log.requests.len(), 2);
+        let document = &log.requests[0];
+        assert!(document.headers_complete);
+        assert_eq!(document.headers.get("Cookie").map(String::as_str), Some("a=1"));
+        assert_eq!(document.resource_type.as_deref(), Some("Document"));
+        let script = &log.requests[1];
+        assert!(script.headers_complete);
+        assert_eq!(script.headers.get("Accept").map(String::as_str), Some("*/*"));
+    }
+}
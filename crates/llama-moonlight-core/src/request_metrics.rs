@@ -0,0 +1,216 @@
+//! Request size and timing metrics.
+//!
+//! Metrics are read from the page's Resource Timing entries, so they cover the
+//! document that is currently loaded: the navigation request itself plus every
+//! subresource fetched since. Cross-origin responses served without a
+//! `Timing-Allow-Origin` header report zero sizes, so byte totals are a lower
+//! bound on those pages.
+
+use metrics::gauge;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Collects the navigation and resource entries of the current document.
+pub(crate) const RESOURCE_ENTRIES_SCRIPT: &str = r#"
+[...performance.getEntriesByType('navigation'), ...performance.getEntriesByType('resource')].map((entry) => ({
+  name: entry.name,
+  initiatorType: entry.initiatorType,
+  transferSize: entry.transferSize || 0,
+  encodedBodySize: entry.encodedBodySize || 0,
+  decodedBodySize: entry.decodedBodySize || 0,
+  duration: entry.duration || 0,
+}))
+"#;
+
+/// One Resource Timing entry as reported by the page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceEntry {
+    /// URL of the resource
+    pub name: String,
+
+    /// What fetched the resource (`navigation`, `script`, `img`, `fetch`, ...)
+    pub initiator_type: String,
+
+    /// Bytes transferred over the network, including headers (0 if cached)
+    pub transfer_size: u64,
+
+    /// Size of the response body before decoding
+    pub encoded_body_size: u64,
+
+    /// Size of the response body after decoding
+    pub decoded_body_size: u64,
+
+    /// Time from start of the fetch to the end of the response, in milliseconds
+    pub duration: f64,
+}
+
+/// Totals for one resource type.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceTypeMetrics {
+    /// Number of requests
+    pub count: usize,
+
+    /// Bytes transferred over the network
+    pub transfer_bytes: u64,
+
+    /// Decoded body bytes
+    pub decoded_body_bytes: u64,
+}
+
+/// Request duration percentiles, in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TimingPercentiles {
+    /// Median duration
+    pub p50_ms: f64,
+
+    /// 90th percentile duration
+    pub p90_ms: f64,
+
+    /// 99th percentile duration
+    pub p99_ms: f64,
+
+    /// Longest duration
+    pub max_ms: f64,
+}
+
+/// Request metrics for the current navigation of a page.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RequestMetrics {
+    /// Number of requests
+    pub request_count: usize,
+
+    /// Bytes transferred over the network, including headers
+    pub transfer_bytes: u64,
+
+    /// Response body bytes before decoding
+    pub encoded_body_bytes: u64,
+
+    /// Response body bytes after decoding
+    pub decoded_body_bytes: u64,
+
+    /// Totals keyed by resource type
+    pub by_resource_type: BTreeMap<String, ResourceTypeMetrics>,
+
+    /// Duration percentiles across all requests
+    pub timing: TimingPercentiles,
+}
+
+impl RequestMetrics {
+    /// Aggregates Resource Timing entries.
+    pub fn from_entries(entries: &[ResourceEntry]) -> Self {
+        let mut metrics = RequestMetrics {
+            request_count: entries.len(),
+            ..Default::default()
+        };
+
+        for entry in entries {
+            metrics.transfer_bytes += entry.transfer_size;
+            metrics.encoded_body_bytes += entry.encoded_body_size;
+            metrics.decoded_body_bytes += entry.decoded_body_size;
+
+            let resource_type = metrics
+                .by_resource_type
+                .entry(resource_type(&entry.initiator_type).to_string())
+                .or_default();
+            resource_type.count += 1;
+            resource_type.transfer_bytes += entry.transfer_size;
+            resource_type.decoded_body_bytes += entry.decoded_body_size;
+        }
+
+        let mut durations: Vec<f64> = entries.iter().map(|e| e.duration).collect();
+        durations.sort_by(|a, b| a.total_cmp(b));
+        metrics.timing = TimingPercentiles {
+            p50_ms: percentile(&durations, 50.0),
+            p90_ms: percentile(&durations, 90.0),
+            p99_ms: percentile(&durations, 99.0),
+            max_ms: durations.last().copied().unwrap_or(0.0),
+        };
+
+        metrics
+    }
+
+    /// Publishes the metrics as gauges through the `metrics` facade.
+    pub fn record_gauges(&self) {
+        gauge!("page.requests", self.request_count as f64);
+        gauge!("page.transfer_bytes", self.transfer_bytes as f64);
+        gauge!("page.decoded_body_bytes", self.decoded_body_bytes as f64);
+
+        for (resource_type, totals) in &self.by_resource_type {
+            gauge!("page.requests_by_type", totals.count as f64, "resource_type" => resource_type.clone());
+            gauge!("page.transfer_bytes_by_type", totals.transfer_bytes as f64, "resource_type" => resource_type.clone());
+        }
+
+        gauge!("page.request_duration_ms", self.timing.p50_ms, "quantile" => "0.5");
+        gauge!("page.request_duration_ms", self.timing.p90_ms, "quantile" => "0.9");
+        gauge!("page.request_duration_ms", self.timing.p99_ms, "quantile" => "0.99");
+        gauge!("page.request_duration_ms", self.timing.max_ms, "quantile" => "1");
+    }
+}
+
+/// Maps a Resource Timing initiator type to a coarse resource type.
+fn resource_type(initiator_type: &str) -> &str {
+    match initiator_type {
+        "navigation" | "iframe" | "frame" => "document",
+        "script" => "script",
+        "css" | "link" => "stylesheet",
+        "img" | "image" | "imageset" => "image",
+        "fetch" | "xmlhttprequest" | "beacon" => "xhr",
+        "video" | "audio" | "track" => "media",
+        "" | "other" => "other",
+        other => other,
+    }
+}
+
+/// Nearest-rank percentile of sorted values.
+fn percentile(sorted: &[f64], percent: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((percent / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(initiator_type: &str, transfer_size: u64, duration: f64) -> ResourceEntry {
+        ResourceEntry {
+            name: format!("https://example.com/{}", initiator_type),
+            initiator_type: initiator_type.to_string(),
+            transfer_size,
+            encoded_body_size: transfer_size / 2,
+            decoded_body_size: transfer_size,
+            duration,
+        }
+    }
+
+    #[test]
+    fn test_from_entries() {
+        let entries = vec![
+            entry("navigation", 1000, 120.0),
+            entry("script", 500, 40.0),
+            entry("img", 300, 10.0),
+            entry("img", 200, 80.0),
+        ];
+        let metrics = RequestMetrics::from_entries(&entries);
+
+        assert_eq!(metrics.request_count, 4);
+        assert_eq!(metrics.transfer_bytes, 2000);
+        assert_eq!(metrics.encoded_body_bytes, 1000);
+        assert_eq!(metrics.by_resource_type["image"].count, 2);
+        assert_eq!(metrics.by_resource_type["image"].transfer_bytes, 500);
+        assert_eq!(metrics.by_resource_type["document"].count, 1);
+        assert_eq!(metrics.timing.p50_ms, 40.0);
+        assert_eq!(metrics.timing.p90_ms, 120.0);
+        assert_eq!(metrics.timing.max_ms, 120.0);
+    }
+
+    #[test]
+    fn test_empty_entries() {
+        let metrics = RequestMetrics::from_entries(&[]);
+        assert_eq!(metrics.request_count, 0);
+        assert_eq!(metrics.timing, TimingPercentiles::default());
+    }
+}
@@ -0,0 +1,243 @@
+//! Retrying failed navigations.
+//!
+//! [`Page::goto_with_retry`](crate::Page::goto_with_retry) classifies each
+//! failed navigation as a DNS failure, a connection reset, a server error, or
+//! a timeout, and retries the kinds selected in its [`RetryPolicy`], waiting
+//! between attempts as given by the policy's [`Backoff`]. A [`RetryHook`] can
+//! rotate the identity or proxy of the session before each retry.
+
+use crate::errors::Result;
+use crate::page::Page;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Exponential backoff between retries.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Backoff {
+    /// Delay before the first retry.
+    pub initial: Duration,
+    
+    /// Longest delay between retries.
+    pub max: Duration,
+    
+    /// Factor the delay grows by with each retry.
+    pub multiplier: f64,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(500),
+            max: Duration::from_secs(30),
+            multiplier: 2.0,
+        }
+    }
+}
+
+impl Backoff {
+    /// Creates a backoff doubling from `initial` up to `max`.
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self { initial, max, multiplier: 2.0 }
+    }
+    
+    /// Sets the factor the delay grows by with each retry.
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+    
+    /// Returns the delay before a retry, counting retries from 1.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = self.initial.as_secs_f64() * self.multiplier.powi(exponent);
+        
+        if delay.is_finite() && delay < self.max.as_secs_f64() {
+            Duration::from_secs_f64(delay)
+        } else {
+            self.max
+        }
+    }
+}
+
+/// Kind of navigation failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NavigationFailureKind {
+    /// The host name could not be resolved.
+    Dns,
+    
+    /// The connection was reset, closed, or refused.
+    ConnectionReset,
+    
+    /// The server answered with a 5xx status.
+    ServerError,
+    
+    /// The navigation or the connection timed out.
+    Timeout,
+    
+    /// Any other failure.
+    Other,
+}
+
+impl NavigationFailureKind {
+    /// Classifies a browser network error such as `net::ERR_NAME_NOT_RESOLVED`.
+    pub fn from_error_text(text: &str) -> Self {
+        let text = text.to_ascii_uppercase();
+        let any = |codes: &[&str]| codes.iter().any(|code| text.contains(code));
+        
+        if any(&["NAME_NOT_RESOLVED", "NAME_RESOLUTION_FAILED", "UNKNOWN_HOST", "DNS_"]) {
+            Self::Dns
+        } else if any(&["TIMED_OUT", "TIMEOUT"]) {
+            Self::Timeout
+        } else if any(&[
+            "CONNECTION_RESET",
+            "CONNECTION_CLOSED",
+            "CONNECTION_ABORTED",
+            "CONNECTION_REFUSED",
+            "CONNECTION_FAILED",
+            "EMPTY_RESPONSE",
+            "NET_RESET",
+            "NET_INTERRUPT",
+        ]) {
+            Self::ConnectionReset
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// A failed navigation attempt.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NavigationFailure {
+    /// Kind of failure.
+    pub kind: NavigationFailureKind,
+    
+    /// URL navigated to.
+    pub url: String,
+    
+    /// HTTP status of the response, for server errors.
+    pub status: Option<u16>,
+    
+    /// Error reported by the browser.
+    pub message: String,
+}
+
+impl fmt::Display for NavigationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} navigating to {}: {}", self.kind, self.url, self.message)
+    }
+}
+
+/// Called before each retry of a navigation.
+#[async_trait]
+pub trait RetryHook: Send + Sync + fmt::Debug {
+    /// Prepares the page for retry number `retry` (counted from 1), e.g. by
+    /// rotating its proxy or identity. An error aborts the navigation.
+    async fn before_retry(&self, page: &Page, failure: &NavigationFailure, retry: u32) -> Result<()>;
+}
+
+/// How failed navigations are retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Number of attempts, including the first.
+    pub max_attempts: u32,
+    
+    /// Delay between attempts.
+    pub backoff: Backoff,
+    
+    /// Kinds of failure that are retried.
+    pub retry_on: Vec<NavigationFailureKind>,
+    
+    /// Hook called before each retry.
+    pub hook: Option<Arc<dyn RetryHook>>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: Backoff::default(),
+            retry_on: vec![
+                NavigationFailureKind::Dns,
+                NavigationFailureKind::ConnectionReset,
+                NavigationFailureKind::ServerError,
+                NavigationFailureKind::Timeout,
+            ],
+            hook: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Creates the default policy with `max_attempts` attempts.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            ..Self::default()
+        }
+    }
+    
+    /// Sets the delay between attempts.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+    
+    /// Sets the kinds of failure that are retried.
+    pub fn retry_on(mut self, kinds: &[NavigationFailureKind]) -> Self {
+        self.retry_on = kinds.to_vec();
+        self
+    }
+    
+    /// Sets the hook called before each retry.
+    pub fn with_hook(mut self, hook: Arc<dyn RetryHook>) -> Self {
+        self.hook = Some(hook);
+        self
+    }
+    
+    /// Returns whether a failure is retried after `attempt` attempts.
+    pub fn should_retry(&self, failure: &NavigationFailure, attempt: u32) -> bool {
+        attempt < self.max_attempts && self.retry_on.contains(&failure.kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_backoff() {
+        let backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(1));
+        assert_eq!(backoff.delay(1), Duration::from_millis(100));
+        assert_eq!(backoff.delay(3), Duration::from_millis(400));
+        assert_eq!(backoff.delay(5), Duration::from_secs(1));
+        assert_eq!(backoff.delay(u32::MAX), Duration::from_secs(1));
+    }
+    
+    #[test]
+    fn test_classification() {
+        assert_eq!(NavigationFailureKind::from_error_text("net::ERR_NAME_NOT_RESOLVED"), NavigationFailureKind::Dns);
+        assert_eq!(NavigationFailureKind::from_error_text("NS_ERROR_UNKNOWN_HOST"), NavigationFailureKind::Dns);
+        assert_eq!(NavigationFailureKind::from_error_text("net::ERR_CONNECTION_RESET"), NavigationFailureKind::ConnectionReset);
+        assert_eq!(NavigationFailureKind::from_error_text("net::ERR_CONNECTION_TIMED_OUT"), NavigationFailureKind::Timeout);
+        assert_eq!(NavigationFailureKind::from_error_text("net::ERR_ABORTED"), NavigationFailureKind::Other);
+    }
+    
+    #[test]
+    fn test_should_retry() {
+        let policy = RetryPolicy::new(2).retry_on(&[NavigationFailureKind::Timeout]);
+        let failure = |kind| NavigationFailure {
+            kind,
+            url: "https://example.com".to_string(),
+            status: None,
+            message: String::new(),
+        };
+        
+        assert!(policy.should_retry(&failure(NavigationFailureKind::Timeout), 1));
+        assert!(!policy.should_retry(&failure(NavigationFailureKind::Timeout), 2));
+        assert!(!policy.should_retry(&failure(NavigationFailureKind::Dns), 1));
+    }
+}
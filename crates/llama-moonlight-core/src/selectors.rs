@@ -0,0 +1,419 @@
+//! Selector parsing and evaluation.
+//!
+//! Selectors are plain CSS by default. A selector may name its engine with a
+//! prefix and may be split into steps with the `>>>` deep combinator, which
+//! matches the next step anywhere below the previous match, including inside
+//! open shadow roots:
+//!
+//! ```text
+//! my-app >>> settings-panel >>> button.save
+//! pierce=input[name="email"]
+//! xpath=//table//tr[2]
+//! text=Sign in
+//! ```
+//!
+//! The built-in engines are:
+//!
+//! - `css=`: standard CSS (the default)
+//! - `pierce=`: CSS matched inside every open shadow root as well
+//! - `xpath=`: XPath; selectors starting with `//` or `..` use it implicitly
+//! - `text=`: the smallest elements whose text contains the value, ignoring
+//!   case and collapsing whitespace; a quoted value (`text="Sign in"`) must
+//!   match the whole text exactly. Quoted selectors use it implicitly.
+//!
+//! Further engines can be written in JavaScript and added with
+//! [`Selectors::register`].
+//!
+//! Closed shadow roots are never reachable. A single CSS step is matched
+//! within one tree, so a selector such as `my-app button` can't cross a shadow
+//! boundary; use `>>>` between the two parts instead. XPath does not see into
+//! shadow roots.
+
+use crate::errors::{Error, Result};
+use lazy_static::lazy_static;
+use serde::{Serialize, Serializer};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use tracing::info;
+
+/// The `>>>` deep combinator.
+const DEEP_COMBINATOR: &str = ">>>";
+
+/// Names of the built-in engines, which custom engines can't replace.
+const BUILTIN_ENGINES: &[&str] = &["css", "pierce", "xpath", "text"];
+
+lazy_static! {
+    /// Custom engines by name, as JavaScript expressions.
+    static ref CUSTOM_ENGINES: RwLock<HashMap<String, String>> = RwLock::new(HashMap::new());
+}
+
+/// The in-page query function.
+///
+/// `__LLAMA_SELECTOR_STEPS__` is replaced with the JSON-encoded steps,
+/// `__LLAMA_SELECTOR_ENGINES__` with the custom engines they use, and
+/// `__LLAMA_SELECTOR_ALL__` with whether every match is returned.
+const QUERY_SCRIPT: &str = r#"(scope) => {
+  const steps = __LLAMA_SELECTOR_STEPS__;
+  const engines = __LLAMA_SELECTOR_ENGINES__;
+  const rootsOf = (root, pierce) => {
+    const roots = [root];
+    if (!pierce) { return roots; }
+    for (let i = 0; i < roots.length; i++) {
+      const walker = document.createTreeWalker(roots[i], NodeFilter.SHOW_ELEMENT);
+      for (let node = walker.currentNode; node; node = walker.nextNode()) {
+        if (node.shadowRoot) { roots.push(node.shadowRoot); }
+      }
+    }
+    return roots;
+  };
+  const normalize = (text) => text.replace(/\s+/g, ' ').trim();
+  const textMatcher = (body) => {
+    const quoted = body.match(/^(["'])([\s\S]*)\1$/);
+    if (quoted) {
+      const expected = normalize(quoted[2]);
+      return (text) => text === expected;
+    }
+    const needle = normalize(body).toLowerCase();
+    return (text) => text.toLowerCase().includes(needle);
+  };
+  const byText = (root, body) => {
+    const matches = textMatcher(body);
+    const skip = new Set(['SCRIPT', 'STYLE', 'NOSCRIPT', 'TEMPLATE', 'HEAD']);
+    const test = (element) => !skip.has(element.nodeName) && matches(normalize(element.textContent || ''));
+    const found = [];
+    const walker = document.createTreeWalker(root, NodeFilter.SHOW_ELEMENT);
+    for (let node = walker.nextNode(); node; node = walker.nextNode()) {
+      if (test(node) && !Array.from(node.children).some(test)) { found.push(node); }
+    }
+    return found;
+  };
+  const byXPath = (root, body) => {
+    const path = root.nodeType !== Node.DOCUMENT_NODE && body.startsWith('/') ? '.' + body : body;
+    const result = document.evaluate(path, root, null, XPathResult.ORDERED_NODE_SNAPSHOT_TYPE, null);
+    const found = [];
+    for (let i = 0; i < result.snapshotLength; i++) {
+      const node = result.snapshotItem(i);
+      if (node.nodeType === Node.ELEMENT_NODE) { found.push(node); }
+    }
+    return found;
+  };
+  const byCustom = (name, root, body) => {
+    const engine = engines[name];
+    if (!engine) { throw new Error('Unknown selector engine: ' + name); }
+    if (typeof engine.queryAll === 'function') { return Array.from(engine.queryAll(root, body) || []); }
+    const element = engine.query(root, body);
+    return element ? [element] : [];
+  };
+  const run = (step, root) => {
+    const found = [];
+    for (const current of rootsOf(root, step.pierce || step.engine === 'pierce')) {
+      switch (step.engine) {
+        case 'css':
+        case 'pierce': found.push(...current.querySelectorAll(step.body)); break;
+        case 'xpath': found.push(...byXPath(current, step.body)); break;
+        case 'text': found.push(...byText(current, step.body)); break;
+        default: found.push(...byCustom(step.engine, current, step.body));
+      }
+    }
+    return found;
+  };
+  let current = [scope];
+  for (const step of steps) {
+    const next = new Set();
+    for (const root of current) {
+      for (const element of run(step, root)) { next.add(element); }
+    }
+    current = Array.from(next);
+  }
+  return __LLAMA_SELECTOR_ALL__ ? current : (current[0] || null);
+}"#;
+
+/// A selector engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectorEngine {
+    /// Standard CSS selectors (the default)
+    Css,
+
+    /// CSS matched inside every open shadow root below the scope
+    Pierce,
+
+    /// XPath expressions
+    XPath,
+
+    /// Matching on element text
+    Text,
+
+    /// A custom engine added with [`Selectors::register`]
+    Custom(String),
+}
+
+impl SelectorEngine {
+    /// Returns the engine's prefix name.
+    pub fn name(&self) -> &str {
+        match self {
+            SelectorEngine::Css => "css",
+            SelectorEngine::Pierce => "pierce",
+            SelectorEngine::XPath => "xpath",
+            SelectorEngine::Text => "text",
+            SelectorEngine::Custom(name) => name,
+        }
+    }
+
+    /// Looks up an engine by its prefix name.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "css" => Some(SelectorEngine::Css),
+            "pierce" => Some(SelectorEngine::Pierce),
+            "xpath" => Some(SelectorEngine::XPath),
+            "text" => Some(SelectorEngine::Text),
+            _ => {
+                let engines = CUSTOM_ENGINES.read().unwrap();
+                engines.contains_key(name).then(|| SelectorEngine::Custom(name.to_string()))
+            }
+        }
+    }
+}
+
+impl Serialize for SelectorEngine {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+/// One step of a parsed selector.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SelectorStep {
+    /// Engine used to match the step
+    pub engine: SelectorEngine,
+
+    /// Selector text passed to the engine
+    pub body: String,
+
+    /// Whether the step also matches inside shadow roots (set after `>>>`)
+    pub pierce: bool,
+}
+
+/// Parses selectors and builds the in-page functions that evaluate them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Selectors;
+
+impl Selectors {
+    /// Registers a custom selector engine, usable as `name=...` in every page.
+    ///
+    /// `script` is a JavaScript expression evaluating to an object with a
+    /// `queryAll(root, selector)` method returning the matching elements, or a
+    /// `query(root, selector)` method returning the first one:
+    ///
+    /// ```text
+    /// ({ queryAll: (root, selector) => root.querySelectorAll(`[data-testid="${selector}"]`) })
+    /// ```
+    pub fn register(name: &str, script: &str) -> Result<()> {
+        if !is_engine_name(name) {
+            return Err(Error::SelectorError(format!("Invalid selector engine name '{}'", name)));
+        }
+        if BUILTIN_ENGINES.contains(&name) {
+            return Err(Error::SelectorError(format!("Selector engine '{}' is built in", name)));
+        }
+
+        let mut engines = CUSTOM_ENGINES.write().unwrap();
+        if engines.contains_key(name) {
+            return Err(Error::SelectorError(format!("Selector engine '{}' is already registered", name)));
+        }
+        engines.insert(name.to_string(), script.to_string());
+
+        info!("Registered selector engine '{}'", name);
+        Ok(())
+    }
+
+    /// Parses a selector into its steps.
+    pub fn parse(selector: &str) -> Result<Vec<SelectorStep>> {
+        let mut steps = Vec::new();
+
+        for (index, part) in split_deep(selector).into_iter().enumerate() {
+            let part = part.trim();
+            if part.is_empty() {
+                return Err(Error::SelectorError(format!("Empty step in selector '{}'", selector)));
+            }
+
+            let (engine, body) = match engine_prefix(part) {
+                Some((name, body)) => {
+                    let engine = SelectorEngine::from_name(name).ok_or_else(|| {
+                        Error::SelectorError(format!("Unknown selector engine '{}' in '{}'", name, selector))
+                    })?;
+                    (engine, body.trim())
+                },
+                None if part.starts_with("//") || part.starts_with("..") => (SelectorEngine::XPath, part),
+                None if part.starts_with('"') || part.starts_with('\'') => (SelectorEngine::Text, part),
+                None => (SelectorEngine::Css, part),
+            };
+
+            if body.is_empty() {
+                return Err(Error::SelectorError(format!("Empty step in selector '{}'", selector)));
+            }
+
+            steps.push(SelectorStep {
+                engine,
+                body: body.to_string(),
+                pierce: index > 0,
+            });
+        }
+
+        Ok(steps)
+    }
+
+    /// Builds a JavaScript function `(scope) => ...` evaluating the selector.
+    ///
+    /// The function returns the first match (or `null`), or an array of every
+    /// match when `all` is set.
+    pub fn query_function(selector: &str, all: bool) -> Result<String> {
+        let steps = Self::parse(selector)?;
+
+        let mut engines = Vec::new();
+        {
+            let custom = CUSTOM_ENGINES.read().unwrap();
+            for step in &steps {
+                if let SelectorEngine::Custom(name) = &step.engine {
+                    let script = custom.get(name).ok_or_else(|| {
+                        Error::SelectorError(format!("Unknown selector engine '{}' in '{}'", name, selector))
+                    })?;
+                    let entry = format!("{}: ({})", serde_json::to_string(name)?, script);
+                    if !engines.contains(&entry) {
+                        engines.push(entry);
+                    }
+                }
+            }
+        }
+
+        let steps = serde_json::to_string(&steps)?;
+
+        Ok(QUERY_SCRIPT
+            .replace("__LLAMA_SELECTOR_STEPS__", &steps)
+            .replace("__LLAMA_SELECTOR_ENGINES__", &format!("{{{}}}", engines.join(", ")))
+            .replace("__LLAMA_SELECTOR_ALL__", if all { "true" } else { "false" }))
+    }
+}
+
+/// Returns whether `name` can be used as an engine prefix.
+fn is_engine_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Returns the engine name and body if the selector starts with `name=`.
+fn engine_prefix(part: &str) -> Option<(&str, &str)> {
+    let (name, body) = part.split_once('=')?;
+    if is_engine_name(name) {
+        Some((name, body))
+    } else {
+        None
+    }
+}
+
+/// Splits a selector on `>>>`, ignoring combinators inside quotes, brackets, or parentheses.
+fn split_deep(selector: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut depth = 0usize;
+    let mut escaped = false;
+    let mut start = 0;
+    let mut skip_until = 0;
+
+    for (i, c) in selector.char_indices() {
+        if i < skip_until {
+            continue;
+        }
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' => escaped = true,
+            '"' | '\'' if quote == Some(c) => quote = None,
+            '"' | '\'' if quote.is_none() => quote = Some(c),
+            _ if quote.is_some() => {},
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth = depth.saturating_sub(1),
+            '>' if depth == 0 && selector[i..].starts_with(DEEP_COMBINATOR) => {
+                parts.push(&selector[start..i]);
+                start = i + DEEP_COMBINATOR.len();
+                skip_until = start;
+            },
+            _ => {},
+        }
+    }
+
+    parts.push(&selector[start..]);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plain_css() {
+        let steps = Selectors::parse("div.item > a").unwrap();
+        assert_eq!(steps, vec![SelectorStep {
+            engine: SelectorEngine::Css,
+            body: "div.item > a".to_string(),
+            pierce: false,
+        }]);
+    }
+
+    #[test]
+    fn test_parse_deep_combinator() {
+        let steps = Selectors::parse("my-app >>> pierce=button[title=\">>>\"] >>> span").unwrap();
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[0].body, "my-app");
+        assert!(!steps[0].pierce);
+        assert_eq!(steps[1].engine, SelectorEngine::Pierce);
+        assert_eq!(steps[1].body, "button[title=\">>>\"]");
+        assert!(steps[2].pierce);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(Selectors::parse("div >>> ").is_err());
+        assert!(Selectors::parse("pierce=").is_err());
+        assert!(Selectors::parse("nope=div").is_err());
+        // An attribute selector is not an engine prefix
+        assert!(Selectors::parse("[name=email]").is_ok());
+    }
+
+    #[test]
+    fn test_parse_xpath_and_text() {
+        let steps = Selectors::parse("xpath=//ul/li[2] >>> text=Buy now").unwrap();
+        assert_eq!(steps[0].engine, SelectorEngine::XPath);
+        assert_eq!(steps[0].body, "//ul/li[2]");
+        assert_eq!(steps[1].engine, SelectorEngine::Text);
+        assert_eq!(steps[1].body, "Buy now");
+
+        // Implicit engines
+        assert_eq!(Selectors::parse("//a[@href='x=1']").unwrap()[0].engine, SelectorEngine::XPath);
+        assert_eq!(Selectors::parse("\"Sign in\"").unwrap()[0].engine, SelectorEngine::Text);
+    }
+
+    #[test]
+    fn test_register_custom_engine() {
+        let script = "({ queryAll: (root, s) => root.querySelectorAll(`[data-testid=\"${s}\"]`) })";
+        Selectors::register("test-id", script).unwrap();
+        assert!(Selectors::register("test-id", script).is_err());
+        assert!(Selectors::register("xpath", script).is_err());
+        assert!(Selectors::register("1bad", script).is_err());
+
+        let steps = Selectors::parse("test-id=submit").unwrap();
+        assert_eq!(steps[0].engine, SelectorEngine::Custom("test-id".to_string()));
+
+        let function = Selectors::query_function("test-id=submit", false).unwrap();
+        assert!(function.contains(&format!("\"test-id\": ({})", script)));
+        assert!(function.contains(r#"[{"engine":"test-id","body":"submit","pierce":false}]"#));
+    }
+
+    #[test]
+    fn test_query_function_embeds_steps() {
+        let function = Selectors::query_function("pierce=input", true).unwrap();
+        assert!(function.contains(r#"[{"engine":"pierce","body":"input","pierce":false}]"#));
+        assert!(function.contains("return true ?"));
+    }
+}
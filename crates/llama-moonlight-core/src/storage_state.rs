@@ -0,0 +1,127 @@
+//! Storage state of browser contexts.
+//!
+//! A [`StorageState`] is a snapshot of the cookies and local storage of a
+//! [`BrowserContext`](crate::BrowserContext), in the JSON format used by
+//! Playwright's `storageState`. It can be saved to a file, loaded into another
+//! context, or shared with HTTP clients that speak the same format, so a
+//! session established in a browser can continue outside of it and back.
+
+use crate::errors::Result;
+use crate::options::Cookie;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Cookies and local storage of a browser context.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct StorageState {
+    /// Cookies of all domains.
+    #[serde(default)]
+    pub cookies: Vec<Cookie>,
+    
+    /// Local storage by origin.
+    #[serde(default)]
+    pub origins: Vec<OriginState>,
+}
+
+/// Local storage of an origin.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OriginState {
+    /// Origin, e.g. `https://example.com`.
+    pub origin: String,
+    
+    /// Local storage entries.
+    #[serde(default)]
+    pub local_storage: Vec<StorageEntry>,
+}
+
+/// A local storage entry.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StorageEntry {
+    /// Key.
+    pub name: String,
+    
+    /// Value.
+    pub value: String,
+}
+
+impl StorageState {
+    /// Loads a storage state from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+    
+    /// Saves the storage state to a JSON file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+    
+    /// Returns the unexpired cookies.
+    ///
+    /// Cookies without an expiration date, or with a negative one, are
+    /// session cookies and never expire.
+    pub fn unexpired_cookies(&self) -> Vec<&Cookie> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or_default();
+        
+        self.cookies
+            .iter()
+            .filter(|cookie| cookie.expires.map_or(true, |expires| expires < 0.0 || expires > now))
+            .collect()
+    }
+    
+    /// Returns the unexpired cookies sent to a host.
+    pub fn cookies_for_host(&self, host: &str) -> Vec<&Cookie> {
+        self.unexpired_cookies()
+            .into_iter()
+            .filter(|cookie| domain_matches(&cookie.domain, host))
+            .collect()
+    }
+}
+
+/// Checks whether a cookie domain matches a host.
+///
+/// A leading dot (`.example.com`) matches the domain and its subdomains, as
+/// do domains set without one by the `Domain` attribute.
+pub fn domain_matches(cookie_domain: &str, host: &str) -> bool {
+    let domain = cookie_domain.trim_start_matches('.').to_ascii_lowercase();
+    let host = host.to_ascii_lowercase();
+    
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_storage_state_format() {
+        let json = r#"{
+            "cookies": [
+                {"name": "cf_clearance", "value": "abc", "domain": ".example.com", "path": "/", "expires": -1, "httpOnly": true, "secure": true, "sameSite": "None"},
+                {"name": "old", "value": "x", "domain": "example.com", "path": "/", "expires": 1, "httpOnly": false, "secure": false, "sameSite": "Lax"},
+                {"name": "other", "value": "y", "domain": "other.org", "path": "/"}
+            ],
+            "origins": [{"origin": "https://example.com", "localStorage": [{"name": "token", "value": "t"}]}]
+        }"#;
+        
+        let state: StorageState = serde_json::from_str(json).unwrap();
+        assert_eq!(state.origins[0].local_storage[0].name, "token");
+        assert_eq!(state.unexpired_cookies().len(), 2);
+        
+        let cookies = state.cookies_for_host("www.example.com");
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies[0].name, "cf_clearance");
+        assert_eq!(cookies[0].http_only, Some(true));
+        
+        let value = serde_json::to_value(&state).unwrap();
+        assert_eq!(value["cookies"][0]["httpOnly"], true);
+        assert_eq!(value["origins"][0]["localStorage"][0]["value"], "t");
+    }
+}
@@ -0,0 +1,190 @@
+//! Tracing and telemetry setup.
+//!
+//! Every layer of the workspace (core, pool, stealth, cloudflare, proxymaster)
+//! emits `tracing` spans carrying a common set of field names, so a single
+//! scrape can be followed end to end. This module installs a subscriber for
+//! those spans and, with the `otlp` feature, exports them over OTLP.
+
+use crate::errors::{Error, Result};
+use std::future::Future;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+/// Span field carrying the request ID.
+pub const FIELD_REQUEST_ID: &str = "request_id";
+
+/// Span field carrying the browser ID.
+pub const FIELD_BROWSER_ID: &str = "browser_id";
+
+/// Span field carrying the proxy ID.
+pub const FIELD_PROXY_ID: &str = "proxy_id";
+
+/// Span field carrying the target domain.
+pub const FIELD_DOMAIN: &str = "domain";
+
+tokio::task_local! {
+    /// ID of the logical request the current task serves.
+    static REQUEST_ID: String;
+}
+
+/// Returns the ID of the logical request the current task serves, if any.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Runs a future as one logical request.
+///
+/// Inside it, [`current_request_id`] returns the ID of the enclosing request
+/// if there is one, or a new ID otherwise, so the retries, redirects and
+/// challenge round trips of a request carry one ID across layers.
+pub async fn in_request<F: Future>(future: F) -> F::Output {
+    match current_request_id() {
+        Some(_) => future.await,
+        None => REQUEST_ID.scope(uuid::Uuid::new_v4().to_string(), future).await,
+    }
+}
+
+/// Configuration for the tracing subscriber.
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    /// Filter directive (same syntax as `RUST_LOG`). Falls back to `RUST_LOG`, then `info`.
+    pub filter: Option<String>,
+
+    /// Whether to emit JSON-formatted log lines instead of the human-readable format.
+    pub json: bool,
+
+    /// OTLP collector endpoint (e.g. `http://localhost:4317`). Only used with the `otlp` feature.
+    pub otlp_endpoint: Option<String>,
+
+    /// Service name reported to the collector.
+    pub service_name: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            filter: None,
+            json: false,
+            otlp_endpoint: None,
+            service_name: "llama-moonlight".to_string(),
+        }
+    }
+}
+
+impl TelemetryConfig {
+    /// Builds the environment filter for this configuration.
+    fn env_filter(&self) -> EnvFilter {
+        match &self.filter {
+            Some(directive) => EnvFilter::new(directive),
+            None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        }
+    }
+}
+
+/// Installs the global tracing subscriber.
+///
+/// Returns an error if a global subscriber has already been installed or the
+/// OTLP pipeline cannot be built.
+pub fn init(config: &TelemetryConfig) -> Result<()> {
+    let registry = tracing_subscriber::registry().with(config.env_filter());
+
+    // Standard output is left to the program's own results
+    let fmt_layer = if config.json {
+        tracing_subscriber::fmt::layer().json().with_writer(std::io::stderr).boxed()
+    } else {
+        tracing_subscriber::fmt::layer().with_writer(std::io::stderr).boxed()
+    };
+
+    #[cfg(feature = "otlp")]
+    {
+        if let Some(endpoint) = &config.otlp_endpoint {
+            let tracer = otlp_tracer(endpoint, &config.service_name)?;
+            return registry
+                .with(fmt_layer)
+                .with(tracing_opentelemetry::layer().with_tracer(tracer))
+                .try_init()
+                .map_err(|e| Error::Generic(format!("Failed to install tracing subscriber: {}", e)));
+        }
+    }
+
+    registry
+        .with(fmt_layer)
+        .try_init()
+        .map_err(|e| Error::Generic(format!("Failed to install tracing subscriber: {}", e)))
+}
+
+/// Flushes pending spans and shuts down the OTLP exporter, if any.
+pub fn shutdown() {
+    #[cfg(feature = "otlp")]
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+/// Builds an OTLP tracer pipeline exporting to the given endpoint.
+#[cfg(feature = "otlp")]
+fn otlp_tracer(endpoint: &str, service_name: &str) -> Result<opentelemetry::sdk::trace::Tracer> {
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::WithExportConfig;
+
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(opentelemetry::sdk::trace::config().with_resource(
+            opentelemetry::sdk::Resource::new(vec![KeyValue::new(
+                "service.name",
+                service_name.to_string(),
+            )]),
+        ))
+        .install_batch(opentelemetry::runtime::Tokio)
+        .map_err(|e| Error::Generic(format!("Failed to build OTLP pipeline: {}", e)))
+}
+
+/// Extracts the host portion of a URL for use as the `domain` span field.
+pub fn domain_of(url: &str) -> String {
+    let without_scheme = url.split("://").nth(1).unwrap_or(url);
+    let authority = without_scheme.split(['/', '?', '#']).next().unwrap_or("");
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    host.split(':').next().unwrap_or(host).to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_domain_of() {
+        assert_eq!(domain_of("https://Example.com/path?q=1"), "example.com");
+        assert_eq!(domain_of("http://user:pw@host.test:8080/"), "host.test");
+        assert_eq!(domain_of("example.org"), "example.org");
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_shared_by_nested_requests() {
+        assert_eq!(current_request_id(), None);
+        
+        let (outer, inner) = in_request(async {
+            let outer = current_request_id();
+            let inner = in_request(async { current_request_id() }).await;
+            (outer, inner)
+        })
+        .await;
+        assert!(outer.is_some());
+        assert_eq!(outer, inner);
+        
+        // Separate requests get separate IDs
+        let other = in_request(async { current_request_id() }).await;
+        assert_ne!(other, outer);
+    }
+
+    #[test]
+    fn test_default_config() {
+        let config = TelemetryConfig::default();
+        assert!(!config.json);
+        assert!(config.otlp_endpoint.is_none());
+        assert_eq!(config.service_name, "llama-moonlight");
+    }
+}
@@ -0,0 +1,466 @@
+//! Crawl engine.
+//!
+//! [`Crawler`] ties the pieces together: it seeds a [`Frontier`], pulls ready
+//! URLs, gates them through the [`PolitenessPolicy`], fetches them with the
+//! configured [`Fetcher`], runs every [`Extractor`], and feeds discovered links
+//! back into the frontier.
+
+use crate::extractor::{Extracted, Extractor, LinkExtractor};
+use crate::fetcher::{FetchedPage, Fetcher};
+use crate::frontier::{Frontier, FrontierConfig, FrontierEntry};
+use crate::robots::PolitenessPolicy;
+use crate::sitemap::{SitemapConfig, SitemapFetcher};
+use crate::Result;
+use futures::future;
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::Arc;
+use tracing::{debug, info, instrument, warn};
+use url::Url;
+
+/// Configuration for a crawl.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrawlerConfig {
+    /// Maximum number of pages fetched
+    pub max_pages: usize,
+
+    /// Maximum number of concurrent fetches
+    pub concurrency: usize,
+
+    /// Only follow links on the seeds' hosts
+    pub same_host_only: bool,
+
+    /// Check robots.txt before fetching and honor its crawl-delay
+    pub respect_robots: bool,
+
+    /// Add URLs from the seeds' sitemaps to the frontier before crawling
+    pub seed_from_sitemaps: bool,
+
+    /// Frontier configuration
+    pub frontier: FrontierConfig,
+}
+
+impl Default for CrawlerConfig {
+    fn default() -> Self {
+        Self {
+            max_pages: 1_000,
+            concurrency: 4,
+            same_host_only: true,
+            respect_robots: true,
+            seed_from_sitemaps: false,
+            frontier: FrontierConfig::default(),
+        }
+    }
+}
+
+/// Outcome of crawling one URL.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CrawlOutcome {
+    /// The page was fetched and extracted
+    Fetched {
+        /// The fetched page
+        page: FetchedPage,
+        /// Merged output of all extractors
+        extracted: Extracted,
+    },
+    /// robots.txt disallowed the URL
+    Disallowed,
+    /// Fetching or extraction failed
+    Failed(String),
+}
+
+/// Result of crawling one URL.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrawlResult {
+    /// The frontier entry that was crawled
+    pub entry: FrontierEntry,
+    /// What happened
+    pub outcome: CrawlOutcome,
+}
+
+/// Counters for a finished crawl.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrawlStats {
+    /// Pages fetched successfully
+    pub fetched: usize,
+    /// URLs that failed
+    pub failed: usize,
+    /// URLs skipped because of robots.txt
+    pub disallowed: usize,
+    /// New URLs added to the frontier (including seeds)
+    pub discovered: usize,
+}
+
+/// A crawl engine with a pluggable fetcher and extractors.
+pub struct Crawler {
+    config: CrawlerConfig,
+    fetcher: Arc<dyn Fetcher>,
+    extractors: Vec<Arc<dyn Extractor>>,
+    policy: PolitenessPolicy,
+}
+
+impl Crawler {
+    /// Creates a crawler using the given fetcher and a [`LinkExtractor`].
+    pub fn new(config: CrawlerConfig, fetcher: impl Fetcher + 'static) -> Self {
+        let policy = PolitenessPolicy::new(crate::PolitenessConfig {
+            default_delay: config.frontier.default_host_delay,
+            ..crate::PolitenessConfig::default()
+        });
+
+        Self {
+            config,
+            fetcher: Arc::new(fetcher),
+            extractors: vec![Arc::new(LinkExtractor::default())],
+            policy,
+        }
+    }
+
+    /// Adds an extractor.
+    pub fn with_extractor(mut self, extractor: impl Extractor + 'static) -> Self {
+        self.extractors.push(Arc::new(extractor));
+        self
+    }
+
+    /// Replaces all extractors (including the default link extractor).
+    pub fn with_extractors(mut self, extractors: Vec<Arc<dyn Extractor>>) -> Self {
+        self.extractors = extractors;
+        self
+    }
+
+    /// Uses the given politeness policy (e.g. to share a robots.txt cache).
+    pub fn with_policy(mut self, policy: PolitenessPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Returns the crawler configuration.
+    pub fn config(&self) -> &CrawlerConfig {
+        &self.config
+    }
+
+    /// Crawls from the given seeds, calling `on_result` for every crawled URL.
+    pub async fn run<F>(&self, seeds: Vec<String>, mut on_result: F) -> Result<CrawlStats>
+    where
+        F: FnMut(CrawlResult),
+    {
+        self.run_with(seeds, |result| {
+            on_result(result);
+            future::ready(Ok(()))
+        })
+        .await
+    }
+
+    /// Crawls from the given seeds, awaiting `on_result` for every crawled URL.
+    ///
+    /// The crawl waits for the callback before it takes the next result, so a
+    /// callback writing results out (to a sink, say) slows the crawl down to
+    /// its own speed instead of letting results pile up. The crawl stops with
+    /// the first error the callback returns.
+    #[instrument(skip(self, seeds, on_result), fields(fetcher = self.fetcher.name()))]
+    pub async fn run_with<F, Fut>(&self, seeds: Vec<String>, mut on_result: F) -> Result<CrawlStats>
+    where
+        F: FnMut(CrawlResult) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let mut frontier = Frontier::new(self.config.frontier.clone());
+        let mut stats = CrawlStats::default();
+        let mut allowed_hosts = HashSet::new();
+        let mut configured_hosts = HashSet::new();
+
+        for seed in &seeds {
+            if let Some(host) = Url::parse(seed)?.host_str() {
+                allowed_hosts.insert(host.to_string());
+            }
+            if frontier.push(seed, 0, None)? {
+                stats.discovered += 1;
+            }
+        }
+
+        if self.config.seed_from_sitemaps {
+            stats.discovered += self.seed_sitemaps(&seeds, &mut frontier).await;
+        }
+
+        let mut in_flight = FuturesUnordered::new();
+        let mut dispatched = 0;
+
+        loop {
+            while in_flight.len() < self.config.concurrency.max(1) && dispatched < self.config.max_pages {
+                let Some(entry) = frontier.pop_ready() else {
+                    break;
+                };
+
+                if self.config.respect_robots {
+                    if let Some(host) = Url::parse(&entry.url).ok().and_then(|u| u.host_str().map(str::to_string)) {
+                        if configured_hosts.insert(host.clone()) {
+                            let delay = self.policy.delay_for(&entry.url).await?;
+                            frontier.set_host_delay(&host, delay);
+                        }
+                    }
+                }
+
+                dispatched += 1;
+                in_flight.push(self.crawl_one(entry));
+            }
+
+            if in_flight.is_empty() {
+                if dispatched >= self.config.max_pages {
+                    break;
+                }
+                match frontier.next_ready_in() {
+                    Some(wait) => {
+                        tokio::time::sleep(wait).await;
+                        continue;
+                    }
+                    None => break,
+                }
+            }
+
+            // Wake up when a fetch completes or, if there is spare capacity, when
+            // another host becomes ready.
+            let can_dispatch = in_flight.len() < self.config.concurrency.max(1) && dispatched < self.config.max_pages;
+            let completed = match frontier.next_ready_in().filter(|_| can_dispatch) {
+                Some(wait) => match tokio::time::timeout(wait, in_flight.next()).await {
+                    Ok(Some(result)) => result,
+                    Ok(None) | Err(_) => continue,
+                },
+                None => match in_flight.next().await {
+                    Some(result) => result,
+                    None => continue,
+                },
+            };
+
+            match &completed.outcome {
+                CrawlOutcome::Fetched { extracted, .. } => {
+                    stats.fetched += 1;
+                    for link in &extracted.links {
+                        if self.config.same_host_only && !on_allowed_host(link, &allowed_hosts) {
+                            continue;
+                        }
+                        match frontier.push(link, completed.entry.depth + 1, Some(&completed.entry.url)) {
+                            Ok(true) => stats.discovered += 1,
+                            Ok(false) => {}
+                            Err(e) => debug!("Skipping link {}: {}", link, e),
+                        }
+                    }
+                }
+                CrawlOutcome::Disallowed => stats.disallowed += 1,
+                CrawlOutcome::Failed(_) => stats.failed += 1,
+            }
+
+            on_result(completed).await?;
+        }
+
+        info!(
+            "Crawl finished: {} fetched, {} failed, {} disallowed, {} discovered",
+            stats.fetched, stats.failed, stats.disallowed, stats.discovered
+        );
+        Ok(stats)
+    }
+
+    /// Crawls a single frontier entry.
+    async fn crawl_one(&self, entry: FrontierEntry) -> CrawlResult {
+        if self.config.respect_robots {
+            match self.policy.is_allowed(&entry.url).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    return CrawlResult {
+                        entry,
+                        outcome: CrawlOutcome::Disallowed,
+                    }
+                }
+                Err(e) => {
+                    return CrawlResult {
+                        entry,
+                        outcome: CrawlOutcome::Failed(e.to_string()),
+                    }
+                }
+            }
+        }
+
+        let outcome = match self.fetcher.fetch(&entry.url).await {
+            Ok(page) => {
+                let mut extracted = Extracted::default();
+                if page.is_success() {
+                    for extractor in &self.extractors {
+                        match extractor.extract(&page) {
+                            Ok(output) => extracted.merge(output),
+                            Err(e) => warn!("Extractor {} failed on {}: {}", extractor.name(), entry.url, e),
+                        }
+                    }
+                }
+                CrawlOutcome::Fetched { page, extracted }
+            }
+            Err(e) => CrawlOutcome::Failed(e.to_string()),
+        };
+
+        CrawlResult { entry, outcome }
+    }
+
+    /// Pushes sitemap URLs for each seed's origin into the frontier.
+    async fn seed_sitemaps(&self, seeds: &[String], frontier: &mut Frontier) -> usize {
+        let fetcher = SitemapFetcher::new(SitemapConfig {
+            max_urls: self.config.max_pages,
+            ..SitemapConfig::default()
+        });
+
+        let origins: HashMap<String, &String> = seeds
+            .iter()
+            .filter_map(|seed| Url::parse(seed).ok().map(|u| (u.origin().ascii_serialization(), seed)))
+            .collect();
+
+        let mut added = 0;
+        for seed in origins.values() {
+            let sitemaps = match fetcher.discover(seed, &self.policy).await {
+                Ok(sitemaps) => sitemaps,
+                Err(e) => {
+                    warn!("Sitemap discovery failed for {}: {}", seed, e);
+                    continue;
+                }
+            };
+
+            let mut entries = Box::pin(fetcher.stream(sitemaps));
+            while let Some(entry) = entries.next().await {
+                if let Ok(true) = frontier.push(&entry.loc, 1, None) {
+                    added += 1;
+                }
+            }
+        }
+
+        added
+    }
+}
+
+/// Checks whether a URL is on one of the allowed hosts.
+fn on_allowed_host(url: &str, hosts: &HashSet<String>) -> bool {
+    Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| hosts.contains(h)))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::time::Duration;
+
+    /// Serves a tiny in-memory site.
+    struct StaticFetcher {
+        pages: HashMap<String, String>,
+    }
+
+    #[async_trait]
+    impl Fetcher for StaticFetcher {
+        async fn fetch(&self, url: &str) -> Result<FetchedPage> {
+            let body = self
+                .pages
+                .get(url)
+                .cloned()
+                .ok_or_else(|| crate::Error::Other(format!("404 {}", url)))?;
+            Ok(FetchedPage {
+                url: url.to_string(),
+                final_url: url.to_string(),
+                status: 200,
+                headers: HashMap::from([("content-type".to_string(), "text/html".to_string())]),
+                body,
+            })
+        }
+
+        fn name(&self) -> &str {
+            "static"
+        }
+    }
+
+    fn site() -> StaticFetcher {
+        let pages = [
+            ("https://site.test/", r#"<a href="/a">a</a><a href="/b">b</a><a href="https://elsewhere.test/">x</a>"#),
+            ("https://site.test/a", r#"<a href="/">home</a><a href="/c">c</a>"#),
+            ("https://site.test/b", r#"<a href="/missing">missing</a>"#),
+            ("https://site.test/c", "<p>leaf</p>"),
+        ];
+        StaticFetcher {
+            pages: pages.iter().map(|(u, b)| (u.to_string(), b.to_string())).collect(),
+        }
+    }
+
+    fn config() -> CrawlerConfig {
+        CrawlerConfig {
+            respect_robots: false,
+            frontier: FrontierConfig {
+                expected_urls: 100,
+                default_host_delay: Duration::ZERO,
+                ..FrontierConfig::default()
+            },
+            ..CrawlerConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_crawls_same_host_site() {
+        let crawler = Crawler::new(config(), site());
+        let mut urls = Vec::new();
+
+        let stats = crawler
+            .run(vec!["https://site.test/".to_string()], |result| urls.push(result.entry.url))
+            .await
+            .unwrap();
+
+        urls.sort();
+        assert_eq!(
+            urls,
+            vec![
+                "https://site.test/",
+                "https://site.test/a",
+                "https://site.test/b",
+                "https://site.test/c",
+                "https://site.test/missing",
+            ]
+        );
+        assert_eq!(stats.fetched, 4);
+        assert_eq!(stats.failed, 1);
+        assert_eq!(stats.discovered, 5);
+    }
+
+    #[tokio::test]
+    async fn test_max_pages() {
+        let crawler = Crawler::new(CrawlerConfig { max_pages: 2, ..config() }, site());
+        let stats = crawler.run(vec!["https://site.test/".to_string()], |_| {}).await.unwrap();
+        assert_eq!(stats.fetched + stats.failed, 2);
+    }
+
+    #[tokio::test]
+    async fn test_run_with_stops_on_callback_error() {
+        let crawler = Crawler::new(CrawlerConfig { concurrency: 1, ..config() }, site());
+        let mut seen = 0;
+        let err = crawler
+            .run_with(vec!["https://site.test/".to_string()], |_| {
+                seen += 1;
+                let result = if seen == 2 {
+                    Err(crate::Error::Other("sink closed".to_string()))
+                } else {
+                    Ok(())
+                };
+                async move { result }
+            })
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("sink closed"));
+        assert_eq!(seen, 2);
+    }
+
+    #[tokio::test]
+    async fn test_robots_disallow() {
+        let crawler = Crawler::new(CrawlerConfig { respect_robots: true, ..config() }, site());
+        crawler
+            .policy
+            .insert("https://site.test", crate::RobotsTxt::parse("User-agent: *\nDisallow: /b\n"))
+            .await;
+
+        let stats = crawler.run(vec!["https://site.test/".to_string()], |_| {}).await.unwrap();
+        assert_eq!(stats.disallowed, 1);
+        assert_eq!(stats.fetched, 3);
+    }
+}
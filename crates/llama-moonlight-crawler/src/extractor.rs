@@ -0,0 +1,210 @@
+//! Pluggable extractors.
+//!
+//! An [`Extractor`] inspects a [`FetchedPage`] and returns the links to follow
+//! and any structured data worth keeping. The crawler runs every registered
+//! extractor on every successfully fetched page and merges their output.
+
+use crate::fetcher::FetchedPage;
+use crate::Result;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use url::Url;
+
+/// Output of one extractor for one page.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Extracted {
+    /// Absolute URLs discovered on the page
+    pub links: Vec<String>,
+    /// Structured data keyed by field name
+    pub data: HashMap<String, serde_json::Value>,
+}
+
+impl Extracted {
+    /// Merges another extraction into this one.
+    pub fn merge(&mut self, other: Extracted) {
+        self.links.extend(other.links);
+        self.data.extend(other.data);
+    }
+}
+
+/// Extracts links and data from fetched pages.
+pub trait Extractor: Send + Sync {
+    /// Extracts from a page.
+    fn extract(&self, page: &FetchedPage) -> Result<Extracted>;
+
+    /// Short name used in logs.
+    fn name(&self) -> &str;
+}
+
+/// Extracts `<a href>` links, resolved against the page URL and `<base href>`.
+#[derive(Debug, Clone)]
+pub struct LinkExtractor {
+    /// Skip links marked `rel="nofollow"`
+    pub respect_nofollow: bool,
+}
+
+impl Default for LinkExtractor {
+    fn default() -> Self {
+        Self { respect_nofollow: true }
+    }
+}
+
+impl Extractor for LinkExtractor {
+    fn extract(&self, page: &FetchedPage) -> Result<Extracted> {
+        if !page.is_html() {
+            return Ok(Extracted::default());
+        }
+
+        let document = Html::parse_document(&page.body);
+        let mut base = Url::parse(&page.final_url)?;
+
+        if let Some(href) = document
+            .select(&selector("base[href]"))
+            .next()
+            .and_then(|el| el.value().attr("href"))
+        {
+            if let Ok(resolved) = base.join(href) {
+                base = resolved;
+            }
+        }
+
+        let links = document
+            .select(&selector("a[href]"))
+            .filter(|el| {
+                !self.respect_nofollow
+                    || !el
+                        .value()
+                        .attr("rel")
+                        .map(|rel| rel.split_whitespace().any(|r| r.eq_ignore_ascii_case("nofollow")))
+                        .unwrap_or(false)
+            })
+            .filter_map(|el| el.value().attr("href"))
+            .filter_map(|href| base.join(href.trim()).ok())
+            .filter(|url| url.scheme() == "http" || url.scheme() == "https")
+            .map(|mut url| {
+                url.set_fragment(None);
+                url.to_string()
+            })
+            .collect();
+
+        Ok(Extracted {
+            links,
+            data: HashMap::new(),
+        })
+    }
+
+    fn name(&self) -> &str {
+        "links"
+    }
+}
+
+/// Extracts the page title and meta description.
+#[derive(Debug, Clone, Default)]
+pub struct MetadataExtractor;
+
+impl Extractor for MetadataExtractor {
+    fn extract(&self, page: &FetchedPage) -> Result<Extracted> {
+        if !page.is_html() {
+            return Ok(Extracted::default());
+        }
+
+        let document = Html::parse_document(&page.body);
+        let mut data = HashMap::new();
+
+        if let Some(title) = document.select(&selector("title")).next() {
+            let title = title.text().collect::<String>().trim().to_string();
+            data.insert("title".to_string(), serde_json::Value::String(title));
+        }
+
+        if let Some(description) = document
+            .select(&selector(r#"meta[name="description"]"#))
+            .next()
+            .and_then(|el| el.value().attr("content"))
+        {
+            data.insert(
+                "description".to_string(),
+                serde_json::Value::String(description.trim().to_string()),
+            );
+        }
+
+        Ok(Extracted {
+            links: Vec::new(),
+            data,
+        })
+    }
+
+    fn name(&self) -> &str {
+        "metadata"
+    }
+}
+
+/// Parses a static CSS selector.
+fn selector(css: &str) -> Selector {
+    Selector::parse(css).expect("static selector is valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn html_page(body: &str) -> FetchedPage {
+        FetchedPage {
+            url: "https://example.com/dir/page".to_string(),
+            final_url: "https://example.com/dir/page".to_string(),
+            status: 200,
+            headers: HashMap::from([("content-type".to_string(), "text/html".to_string())]),
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_link_extraction() {
+        let page = html_page(
+            r##"<html><body>
+                <a href="/abs">abs</a>
+                <a href="rel#frag">rel</a>
+                <a href="https://other.test/x">other</a>
+                <a href="mailto:me@example.com">mail</a>
+                <a href="/hidden" rel="nofollow">nofollow</a>
+            </body></html>"##,
+        );
+
+        let links = LinkExtractor::default().extract(&page).unwrap().links;
+        assert_eq!(
+            links,
+            vec![
+                "https://example.com/abs",
+                "https://example.com/dir/rel",
+                "https://other.test/x",
+            ]
+        );
+
+        let all = LinkExtractor { respect_nofollow: false }.extract(&page).unwrap().links;
+        assert!(all.contains(&"https://example.com/hidden".to_string()));
+    }
+
+    #[test]
+    fn test_base_href() {
+        let page = html_page(r#"<html><head><base href="https://cdn.test/root/"></head><body><a href="a">a</a></body></html>"#);
+        let links = LinkExtractor::default().extract(&page).unwrap().links;
+        assert_eq!(links, vec!["https://cdn.test/root/a"]);
+    }
+
+    #[test]
+    fn test_metadata_extraction() {
+        let page = html_page(
+            r#"<html><head><title> Hello </title><meta name="description" content="A page"></head></html>"#,
+        );
+        let data = MetadataExtractor.extract(&page).unwrap().data;
+        assert_eq!(data["title"], "Hello");
+        assert_eq!(data["description"], "A page");
+    }
+
+    #[test]
+    fn test_non_html_is_skipped() {
+        let mut page = html_page("<a href='/x'>x</a>");
+        page.headers.insert("content-type".to_string(), "application/json".to_string());
+        assert_eq!(LinkExtractor::default().extract(&page).unwrap(), Extracted::default());
+    }
+}
@@ -0,0 +1,256 @@
+//! Pluggable page fetchers.
+//!
+//! A [`Fetcher`] turns a URL into a [`FetchedPage`]. Plain HTTP is always
+//! available; the stealth HTTP fetcher and the browser fetcher are behind the
+//! `stealth` and `browser` features respectively.
+
+use crate::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::instrument;
+
+/// A fetched page.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FetchedPage {
+    /// URL that was requested
+    pub url: String,
+    /// URL after redirects
+    pub final_url: String,
+    /// HTTP status code
+    pub status: u16,
+    /// Response headers (lowercased names)
+    pub headers: HashMap<String, String>,
+    /// Response body as text
+    pub body: String,
+}
+
+impl FetchedPage {
+    /// Returns the response content type, if known.
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get("content-type").map(String::as_str)
+    }
+
+    /// Whether the page looks like HTML.
+    pub fn is_html(&self) -> bool {
+        self.content_type()
+            .map(|ct| ct.contains("html"))
+            .unwrap_or_else(|| self.body.trim_start().starts_with('<'))
+    }
+
+    /// Whether the status code is 2xx.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Fetches pages for the crawler.
+#[async_trait]
+pub trait Fetcher: Send + Sync {
+    /// Fetches a URL.
+    async fn fetch(&self, url: &str) -> Result<FetchedPage>;
+
+    /// Short name used in logs.
+    fn name(&self) -> &str;
+}
+
+/// Plain HTTP fetcher backed by reqwest.
+#[derive(Debug, Clone)]
+pub struct HttpFetcher {
+    client: Client,
+}
+
+impl HttpFetcher {
+    /// Creates a fetcher sending the given user agent.
+    pub fn new(user_agent: &str) -> Result<Self> {
+        let client = Client::builder()
+            .user_agent(user_agent)
+            .timeout(Duration::from_secs(30))
+            .gzip(true)
+            .build()?;
+
+        Ok(Self { client })
+    }
+
+    /// Creates a fetcher reusing an existing HTTP client.
+    pub fn with_client(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl Fetcher for HttpFetcher {
+    #[instrument(skip(self))]
+    async fn fetch(&self, url: &str) -> Result<FetchedPage> {
+        let response = self.client.get(url).send().await?;
+        page_from_response(url, response).await
+    }
+
+    fn name(&self) -> &str {
+        "http"
+    }
+}
+
+/// Converts a reqwest response into a [`FetchedPage`].
+async fn page_from_response(url: &str, response: reqwest::Response) -> Result<FetchedPage> {
+    let final_url = response.url().to_string();
+    let status = response.status().as_u16();
+    let headers = response
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| {
+            value
+                .to_str()
+                .ok()
+                .map(|v| (name.as_str().to_ascii_lowercase(), v.to_string()))
+        })
+        .collect();
+    let body = response.text().await?;
+
+    Ok(FetchedPage {
+        url: url.to_string(),
+        final_url,
+        status,
+        headers,
+        body,
+    })
+}
+
+/// HTTP fetcher that sends browser-consistent headers from a stealth client.
+#[cfg(feature = "stealth")]
+pub struct StealthHttpFetcher {
+    client: Client,
+    stealth: std::sync::Mutex<llama_moonlight_stealth::StealthClient>,
+}
+
+#[cfg(feature = "stealth")]
+impl StealthHttpFetcher {
+    /// Creates a fetcher using the stealth client's headers and active proxy.
+    pub fn new(stealth: llama_moonlight_stealth::StealthClient) -> Result<Self> {
+        let mut builder = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .gzip(true);
+
+        if let Some(proxy) = stealth.active_proxy() {
+            builder = builder.proxy(reqwest::Proxy::all(proxy.to_url())?);
+        }
+
+        Ok(Self {
+            client: builder.build()?,
+            stealth: std::sync::Mutex::new(stealth),
+        })
+    }
+}
+
+#[cfg(feature = "stealth")]
+#[async_trait]
+impl Fetcher for StealthHttpFetcher {
+    #[instrument(skip(self))]
+    async fn fetch(&self, url: &str) -> Result<FetchedPage> {
+        let headers = {
+            let mut stealth = self
+                .stealth
+                .lock()
+                .map_err(|_| crate::Error::Other("Stealth client lock poisoned".to_string()))?;
+            let _ = stealth.record_visit(url);
+            stealth.generate_headers(url)
+        };
+
+        let mut request = self.client.get(url);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+
+        let response = request.send().await?;
+        page_from_response(url, response).await
+    }
+
+    fn name(&self) -> &str {
+        "stealth-http"
+    }
+}
+
+/// Fetcher that renders pages in a browser page.
+///
+/// A single page navigates one URL at a time, so crawls using this fetcher
+/// should run with a concurrency of 1 per fetcher.
+#[cfg(feature = "browser")]
+pub struct BrowserFetcher {
+    page: std::sync::Arc<llama_moonlight_core::Page>,
+    lock: tokio::sync::Mutex<()>,
+}
+
+#[cfg(feature = "browser")]
+impl BrowserFetcher {
+    /// Creates a fetcher driving the given page.
+    pub fn new(page: std::sync::Arc<llama_moonlight_core::Page>) -> Self {
+        Self {
+            page,
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+}
+
+#[cfg(feature = "browser")]
+#[async_trait]
+impl Fetcher for BrowserFetcher {
+    #[instrument(skip(self))]
+    async fn fetch(&self, url: &str) -> Result<FetchedPage> {
+        let _guard = self.lock.lock().await;
+        let to_crawl_error = |e: llama_moonlight_core::Error| crate::Error::Other(e.to_string());
+
+        let navigation = self.page.goto(url).await.map_err(to_crawl_error)?;
+        let body = self.page.content().await.map_err(to_crawl_error)?;
+
+        let mut headers = HashMap::new();
+        headers.insert("content-type".to_string(), "text/html".to_string());
+
+        Ok(FetchedPage {
+            url: url.to_string(),
+            final_url: navigation.url,
+            // Pages loaded without a network response, such as `about:` and
+            // `data:` URLs, have no status
+            status: navigation.status.unwrap_or(200),
+            headers,
+            body,
+        })
+    }
+
+    fn name(&self) -> &str {
+        "browser"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(content_type: Option<&str>, body: &str, status: u16) -> FetchedPage {
+        let mut headers = HashMap::new();
+        if let Some(ct) = content_type {
+            headers.insert("content-type".to_string(), ct.to_string());
+        }
+        FetchedPage {
+            url: "https://example.com/".to_string(),
+            final_url: "https://example.com/".to_string(),
+            status,
+            headers,
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_is_html() {
+        assert!(page(Some("text/html; charset=utf-8"), "", 200).is_html());
+        assert!(!page(Some("application/json"), "{}", 200).is_html());
+        assert!(page(None, "  <!doctype html>", 200).is_html());
+    }
+
+    #[test]
+    fn test_is_success() {
+        assert!(page(None, "", 204).is_success());
+        assert!(!page(None, "", 404).is_success());
+    }
+}
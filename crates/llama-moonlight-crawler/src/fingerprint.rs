@@ -0,0 +1,536 @@
+//! Content fingerprinting and change detection.
+//!
+//! A [`Fingerprint`] summarizes the visible text of a page with a simhash and a
+//! minhash signature. Fingerprints are kept per URL in a sqlite-backed
+//! [`FingerprintStore`], which reports whether a page changed materially since
+//! the last crawl so that unchanged pages can be skipped by incremental
+//! re-crawls and change-monitoring pipelines.
+
+use crate::Result;
+use scraper::{Html, Node};
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Row, SqlitePool};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+/// Number of words per shingle.
+const SHINGLE_SIZE: usize = 3;
+
+/// Default number of minhash permutations.
+pub const DEFAULT_MINHASH_PERMUTATIONS: usize = 64;
+
+/// Elements whose text is not part of the visible content.
+const SKIPPED_ELEMENTS: &[&str] = &["script", "style", "noscript", "template", "head"];
+
+/// 64-bit FNV-1a hash.
+///
+/// Fingerprints are persisted, so the hash must be stable across builds and
+/// Rust versions, which `DefaultHasher` does not guarantee.
+fn fnv1a(bytes: &[u8], seed: u64) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325 ^ seed;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// Final mixing step (splitmix64) so that seeded hashes are independent.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Extracts the visible text of an HTML document, whitespace-normalized.
+pub fn visible_text(html: &str) -> String {
+    let document = Html::parse_document(html);
+    let mut words = Vec::new();
+
+    for node in document.tree.root().descendants() {
+        let Node::Text(text) = node.value() else {
+            continue;
+        };
+        let hidden = node.ancestors().any(|ancestor| {
+            ancestor
+                .value()
+                .as_element()
+                .map(|el| SKIPPED_ELEMENTS.contains(&el.name()))
+                .unwrap_or(false)
+        });
+        if !hidden {
+            words.extend(text.split_whitespace());
+        }
+    }
+
+    words.join(" ")
+}
+
+/// Lowercased word tokens of a text.
+fn tokens(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Hashes of the word shingles of a text.
+fn shingle_hashes(text: &str) -> Vec<u64> {
+    let tokens = tokens(text);
+    if tokens.is_empty() {
+        return Vec::new();
+    }
+    if tokens.len() < SHINGLE_SIZE {
+        return vec![fnv1a(tokens.join(" ").as_bytes(), 0)];
+    }
+    tokens
+        .windows(SHINGLE_SIZE)
+        .map(|window| fnv1a(window.join(" ").as_bytes(), 0))
+        .collect()
+}
+
+/// Computes the 64-bit simhash of a text.
+pub fn simhash(text: &str) -> u64 {
+    let mut weights = [0i64; 64];
+    for hash in shingle_hashes(text) {
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0, |acc, (bit, _)| acc | (1 << bit))
+}
+
+/// Number of differing bits between two simhashes.
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// A minhash signature estimating Jaccard similarity between shingle sets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MinHash {
+    /// Minimum hash per permutation
+    pub signature: Vec<u64>,
+}
+
+impl MinHash {
+    /// Computes a signature with `permutations` hash functions.
+    pub fn compute(text: &str, permutations: usize) -> Self {
+        let shingles = shingle_hashes(text);
+        let signature = (0..permutations as u64)
+            .map(|seed| {
+                let seed = mix(seed.wrapping_add(1));
+                shingles
+                    .iter()
+                    .map(|hash| mix(hash ^ seed))
+                    .min()
+                    .unwrap_or(u64::MAX)
+            })
+            .collect();
+
+        Self { signature }
+    }
+
+    /// Estimated Jaccard similarity in `[0, 1]`.
+    ///
+    /// Signatures of different lengths are compared over their common prefix.
+    pub fn similarity(&self, other: &MinHash) -> f64 {
+        let len = self.signature.len().min(other.signature.len());
+        if len == 0 {
+            return 0.0;
+        }
+        let equal = self
+            .signature
+            .iter()
+            .zip(&other.signature)
+            .filter(|(a, b)| a == b)
+            .count();
+        equal as f64 / len as f64
+    }
+}
+
+/// Fingerprint of a page's content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    /// Simhash of the text
+    pub simhash: u64,
+    /// Minhash signature of the text
+    pub minhash: MinHash,
+    /// Exact hash of the normalized text
+    pub content_hash: u64,
+    /// Number of characters in the normalized text
+    pub text_len: usize,
+}
+
+impl Fingerprint {
+    /// Fingerprints plain text.
+    pub fn of_text(text: &str) -> Self {
+        let normalized = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        Self {
+            simhash: simhash(&normalized),
+            minhash: MinHash::compute(&normalized, DEFAULT_MINHASH_PERMUTATIONS),
+            content_hash: fnv1a(normalized.as_bytes(), 0),
+            text_len: normalized.chars().count(),
+        }
+    }
+
+    /// Fingerprints the visible text of an HTML document.
+    pub fn of_html(html: &str) -> Self {
+        Self::of_text(&visible_text(html))
+    }
+}
+
+/// Thresholds deciding whether a change is material.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeConfig {
+    /// Simhash distance above which a page counts as changed
+    pub max_hamming_distance: u32,
+
+    /// Minhash similarity below which a page counts as changed
+    pub min_similarity: f64,
+}
+
+impl Default for ChangeConfig {
+    fn default() -> Self {
+        Self {
+            max_hamming_distance: 3,
+            min_similarity: 0.9,
+        }
+    }
+}
+
+/// How a page compares to its previous fingerprint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ChangeStatus {
+    /// No previous fingerprint exists for the URL
+    New,
+    /// The normalized text is byte-for-byte the same
+    Identical,
+    /// The text differs, but below the change thresholds
+    Unchanged {
+        /// Simhash distance to the previous fingerprint
+        distance: u32,
+        /// Minhash similarity to the previous fingerprint
+        similarity: f64,
+    },
+    /// The text changed materially
+    Changed {
+        /// Simhash distance to the previous fingerprint
+        distance: u32,
+        /// Minhash similarity to the previous fingerprint
+        similarity: f64,
+    },
+}
+
+impl ChangeStatus {
+    /// Compares a fingerprint to the previous one.
+    pub fn compare(previous: &Fingerprint, current: &Fingerprint, config: &ChangeConfig) -> Self {
+        if previous.content_hash == current.content_hash && previous.text_len == current.text_len {
+            return Self::Identical;
+        }
+
+        let distance = hamming_distance(previous.simhash, current.simhash);
+        let similarity = previous.minhash.similarity(&current.minhash);
+
+        if distance > config.max_hamming_distance || similarity < config.min_similarity {
+            Self::Changed { distance, similarity }
+        } else {
+            Self::Unchanged { distance, similarity }
+        }
+    }
+
+    /// Whether the page is new or changed materially, i.e. worth re-processing.
+    pub fn is_material(&self) -> bool {
+        matches!(self, Self::New | Self::Changed { .. })
+    }
+}
+
+/// A stored fingerprint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredFingerprint {
+    /// The fingerprinted URL
+    pub url: String,
+    /// The fingerprint
+    pub fingerprint: Fingerprint,
+    /// When the fingerprint was first recorded (seconds since the Unix epoch)
+    pub first_seen: i64,
+    /// When the content last changed materially (seconds since the Unix epoch)
+    pub last_changed: i64,
+    /// When the fingerprint was last checked (seconds since the Unix epoch)
+    pub last_checked: i64,
+}
+
+/// Sqlite-backed store of the latest fingerprint per URL.
+#[derive(Debug, Clone)]
+pub struct FingerprintStore {
+    pool: SqlitePool,
+    config: ChangeConfig,
+}
+
+impl FingerprintStore {
+    /// Opens (creating if needed) a store at the given sqlite URL, e.g. `sqlite://fingerprints.db`.
+    pub async fn open(database_url: &str) -> Result<Self> {
+        let options = SqliteConnectOptions::from_str(database_url)?.create_if_missing(true);
+        let pool = SqlitePoolOptions::new().max_connections(5).connect_with(options).await?;
+        Self::with_pool(pool).await
+    }
+
+    /// Opens a store that lives only in memory.
+    pub async fn in_memory() -> Result<Self> {
+        // Every in-memory connection is its own database, so keep exactly one.
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await?;
+        Self::with_pool(pool).await
+    }
+
+    /// Uses an existing pool, creating the table if needed.
+    pub async fn with_pool(pool: SqlitePool) -> Result<Self> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS fingerprints (
+                url TEXT PRIMARY KEY,
+                simhash INTEGER NOT NULL,
+                minhash BLOB NOT NULL,
+                content_hash INTEGER NOT NULL,
+                text_len INTEGER NOT NULL,
+                first_seen INTEGER NOT NULL,
+                last_changed INTEGER NOT NULL,
+                last_checked INTEGER NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self {
+            pool,
+            config: ChangeConfig::default(),
+        })
+    }
+
+    /// Uses the given change thresholds.
+    pub fn with_config(mut self, config: ChangeConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Returns the stored fingerprint for a URL.
+    pub async fn get(&self, url: &str) -> Result<Option<StoredFingerprint>> {
+        let row = sqlx::query(
+            r#"
+            SELECT url, simhash, minhash, content_hash, text_len, first_seen, last_changed, last_checked
+            FROM fingerprints WHERE url = ?
+            "#,
+        )
+        .bind(url)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let minhash: Vec<u8> = row.try_get("minhash")?;
+        Ok(Some(StoredFingerprint {
+            url: row.try_get("url")?,
+            fingerprint: Fingerprint {
+                simhash: row.try_get::<i64, _>("simhash")? as u64,
+                minhash: MinHash {
+                    signature: decode_signature(&minhash),
+                },
+                content_hash: row.try_get::<i64, _>("content_hash")? as u64,
+                text_len: row.try_get::<i64, _>("text_len")? as usize,
+            },
+            first_seen: row.try_get("first_seen")?,
+            last_changed: row.try_get("last_changed")?,
+            last_checked: row.try_get("last_checked")?,
+        }))
+    }
+
+    /// Compares a fingerprint to the stored one without recording it.
+    pub async fn check(&self, url: &str, fingerprint: &Fingerprint) -> Result<ChangeStatus> {
+        Ok(match self.get(url).await? {
+            Some(stored) => ChangeStatus::compare(&stored.fingerprint, fingerprint, &self.config),
+            None => ChangeStatus::New,
+        })
+    }
+
+    /// Compares a fingerprint to the stored one and records it.
+    ///
+    /// The stored fingerprint is only replaced when the change is material, so
+    /// that many small edits still add up to a reported change.
+    pub async fn record(&self, url: &str, fingerprint: &Fingerprint) -> Result<ChangeStatus> {
+        let status = self.check(url, fingerprint).await?;
+        let now = unix_now();
+
+        if status.is_material() {
+            sqlx::query(
+                r#"
+                INSERT INTO fingerprints (
+                    url, simhash, minhash, content_hash, text_len, first_seen, last_changed, last_checked
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT(url) DO UPDATE SET
+                    simhash = excluded.simhash,
+                    minhash = excluded.minhash,
+                    content_hash = excluded.content_hash,
+                    text_len = excluded.text_len,
+                    last_changed = excluded.last_changed,
+                    last_checked = excluded.last_checked
+                "#,
+            )
+            .bind(url)
+            .bind(fingerprint.simhash as i64)
+            .bind(encode_signature(&fingerprint.minhash.signature))
+            .bind(fingerprint.content_hash as i64)
+            .bind(fingerprint.text_len as i64)
+            .bind(now)
+            .bind(now)
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        } else {
+            sqlx::query("UPDATE fingerprints SET last_checked = ? WHERE url = ?")
+                .bind(now)
+                .bind(url)
+                .execute(&self.pool)
+                .await?;
+        }
+
+        debug!(url, ?status, "Recorded fingerprint");
+        Ok(status)
+    }
+
+    /// Fingerprints an HTML document and records it.
+    pub async fn record_html(&self, url: &str, html: &str) -> Result<ChangeStatus> {
+        self.record(url, &Fingerprint::of_html(html)).await
+    }
+
+    /// Removes the stored fingerprint for a URL.
+    pub async fn remove(&self, url: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM fingerprints WHERE url = ?")
+            .bind(url)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// URLs not checked since the given time (seconds since the Unix epoch), oldest first.
+    pub async fn stale_urls(&self, checked_before: i64, limit: usize) -> Result<Vec<String>> {
+        let rows = sqlx::query("SELECT url FROM fingerprints WHERE last_checked < ? ORDER BY last_checked LIMIT ?")
+            .bind(checked_before)
+            .bind(limit as i64)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(|row| Ok(row.try_get("url")?)).collect()
+    }
+}
+
+/// Encodes a minhash signature as little-endian bytes.
+fn encode_signature(signature: &[u64]) -> Vec<u8> {
+    signature.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+/// Decodes a minhash signature from little-endian bytes.
+fn decode_signature(bytes: &[u8]) -> Vec<u64> {
+    bytes
+        .chunks_exact(8)
+        .map(|chunk| u64::from_le_bytes(chunk.try_into().expect("chunk is 8 bytes")))
+        .collect()
+}
+
+/// Current time in seconds since the Unix epoch.
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTICLE: &str = "The quick brown fox jumps over the lazy dog while the farmer watches \
+        from the porch and the cat sleeps in the warm afternoon sun near the old red barn \
+        where the chickens scratch at the dusty ground looking for seeds and small insects";
+
+    #[test]
+    fn test_visible_text_skips_scripts() {
+        let html = "<html><head><title>T</title><script>var x = 1;</script></head>\
+            <body><p>Hello <b>world</b></p><style>p{}</style></body></html>";
+        assert_eq!(visible_text(html), "Hello world");
+    }
+
+    #[test]
+    fn test_simhash_similar_texts_are_close() {
+        let edited = ARTICLE.replace("afternoon", "morning");
+        let unrelated = "Quarterly revenue grew eleven percent on strong demand for cloud services \
+            and the company raised its full year guidance despite currency headwinds";
+
+        let near = hamming_distance(simhash(ARTICLE), simhash(&edited));
+        let far = hamming_distance(simhash(ARTICLE), simhash(unrelated));
+        assert!(near < far, "near={} far={}", near, far);
+        assert_eq!(simhash(ARTICLE), simhash(ARTICLE));
+    }
+
+    #[test]
+    fn test_minhash_similarity() {
+        let a = MinHash::compute(ARTICLE, 128);
+        let b = MinHash::compute(&ARTICLE.replace("afternoon", "morning"), 128);
+        let c = MinHash::compute("completely different words about something else entirely here", 128);
+
+        assert_eq!(a.similarity(&a), 1.0);
+        assert!(a.similarity(&b) > 0.6);
+        assert!(a.similarity(&c) < 0.2);
+    }
+
+    #[test]
+    fn test_compare() {
+        let config = ChangeConfig::default();
+        let original = Fingerprint::of_text(ARTICLE);
+
+        assert_eq!(
+            ChangeStatus::compare(&original, &Fingerprint::of_text(&format!("  {}  ", ARTICLE)), &config),
+            ChangeStatus::Identical
+        );
+        assert!(ChangeStatus::compare(&original, &Fingerprint::of_text("Page not found"), &config).is_material());
+    }
+
+    #[test]
+    fn test_signature_roundtrip() {
+        let signature = vec![0, 1, u64::MAX, 0xdead_beef];
+        assert_eq!(decode_signature(&encode_signature(&signature)), signature);
+    }
+
+    #[tokio::test]
+    async fn test_store_records_changes() {
+        let store = FingerprintStore::in_memory().await.unwrap();
+        let url = "https://example.com/article";
+
+        let original = Fingerprint::of_text(ARTICLE);
+        assert_eq!(store.record(url, &original).await.unwrap(), ChangeStatus::New);
+        assert_eq!(store.record(url, &original).await.unwrap(), ChangeStatus::Identical);
+
+        let rewritten = Fingerprint::of_text("An entirely rewritten article about a different topic");
+        assert!(matches!(store.record(url, &rewritten).await.unwrap(), ChangeStatus::Changed { .. }));
+
+        let stored = store.get(url).await.unwrap().unwrap();
+        assert_eq!(stored.fingerprint, rewritten);
+        assert!(stored.first_seen <= stored.last_changed);
+
+        assert!(store.remove(url).await.unwrap());
+        assert!(store.get(url).await.unwrap().is_none());
+    }
+}
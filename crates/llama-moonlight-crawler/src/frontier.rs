@@ -0,0 +1,454 @@
+//! URL frontier.
+//!
+//! The frontier holds every URL still to be crawled. URLs are normalized and
+//! deduplicated through a bloom filter, queued per host, and handed out by
+//! priority only once their host's politeness delay has elapsed.
+
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// Query parameters dropped during normalization because they only track visitors.
+const TRACKING_PARAMS: &[&str] = &["gclid", "fbclid", "msclkid", "mc_cid", "mc_eid"];
+
+/// Normalizes a URL so that trivially different spellings deduplicate.
+///
+/// Lowercases scheme and host, drops default ports and fragments, removes
+/// tracking parameters (`utm_*`, `gclid`, ...), and sorts the remaining query
+/// parameters.
+pub fn normalize_url(url: &str) -> Result<String> {
+    let mut parsed = Url::parse(url.trim())?;
+    parsed.set_fragment(None);
+
+    let mut params: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(key, _)| {
+            let key = key.to_ascii_lowercase();
+            !key.starts_with("utm_") && !TRACKING_PARAMS.contains(&key.as_str())
+        })
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+
+    if params.is_empty() {
+        parsed.set_query(None);
+    } else {
+        params.sort();
+        parsed.query_pairs_mut().clear().extend_pairs(params);
+    }
+
+    Ok(parsed.to_string())
+}
+
+/// A fixed-size bloom filter over strings.
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    /// Creates a filter sized for `expected_items` at the given false-positive rate.
+    pub fn with_capacity(expected_items: usize, false_positive_rate: f64) -> Self {
+        let n = expected_items.max(1) as f64;
+        let p = false_positive_rate.clamp(1e-9, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+
+        let num_bits = (-(n * p.ln()) / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / n) * ln2).round().clamp(1.0, 32.0) as u32;
+
+        Self {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+        }
+    }
+
+    /// Inserts an item. Returns `true` if it was (probably) not present before.
+    pub fn insert(&mut self, item: &str) -> bool {
+        let mut newly_set = false;
+        let indexes: Vec<u64> = self.indexes(item).collect();
+        for index in indexes {
+            let (word, mask) = ((index / 64) as usize, 1u64 << (index % 64));
+            if self.bits[word] & mask == 0 {
+                self.bits[word] |= mask;
+                newly_set = true;
+            }
+        }
+        newly_set
+    }
+
+    /// Checks whether an item is (probably) present.
+    pub fn contains(&self, item: &str) -> bool {
+        self.indexes(item)
+            .all(|index| self.bits[(index / 64) as usize] & (1u64 << (index % 64)) != 0)
+    }
+
+    /// Bit indexes for an item using double hashing.
+    fn indexes(&self, item: &str) -> impl Iterator<Item = u64> + '_ {
+        let h1 = hash_with_seed(item, 0x51_7c_c1_b7);
+        let h2 = hash_with_seed(item, 0x27_22_0a_95) | 1;
+        (0..self.num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits)
+    }
+}
+
+/// Hashes a string together with a seed.
+fn hash_with_seed(item: &str, seed: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    item.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Scores URLs so that higher scores are crawled first.
+pub trait PriorityScorer: Send + Sync {
+    /// Returns the priority for a URL discovered at `depth`.
+    fn score(&self, url: &Url, depth: u32) -> f64;
+}
+
+/// Default scorer: shallower pages and shorter, parameter-free URLs first.
+#[derive(Debug, Clone, Default)]
+pub struct DepthScorer;
+
+impl PriorityScorer for DepthScorer {
+    fn score(&self, url: &Url, depth: u32) -> f64 {
+        let depth_score = 1.0 / (1.0 + depth as f64);
+        let segments = url.path_segments().map(|s| s.filter(|p| !p.is_empty()).count()).unwrap_or(0);
+        let query_penalty = if url.query().is_some() { 0.1 } else { 0.0 };
+        depth_score - 0.01 * segments as f64 - query_penalty
+    }
+}
+
+/// A URL handed out by the frontier.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrontierEntry {
+    /// Normalized URL
+    pub url: String,
+    /// Link distance from the seeds
+    pub depth: u32,
+    /// Priority assigned when queued
+    pub priority: f64,
+    /// The page this URL was discovered on, if any
+    pub discovered_from: Option<String>,
+}
+
+/// Configuration for the frontier.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrontierConfig {
+    /// Number of URLs the dedup filter is sized for
+    pub expected_urls: usize,
+
+    /// Acceptable false-positive rate of the dedup filter
+    pub false_positive_rate: f64,
+
+    /// URLs deeper than this are dropped
+    pub max_depth: u32,
+
+    /// Maximum URLs queued per host (further URLs are dropped)
+    pub max_queue_per_host: usize,
+
+    /// Delay between two URLs handed out for the same host
+    pub default_host_delay: Duration,
+}
+
+impl Default for FrontierConfig {
+    fn default() -> Self {
+        Self {
+            expected_urls: 1_000_000,
+            false_positive_rate: 0.001,
+            max_depth: 10,
+            max_queue_per_host: 100_000,
+            default_host_delay: Duration::from_millis(1000),
+        }
+    }
+}
+
+/// A queued URL ordered by priority, then insertion order.
+#[derive(Debug)]
+struct Queued {
+    entry: FrontierEntry,
+    seq: u64,
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.entry
+            .priority
+            .total_cmp(&other.entry.priority)
+            // Earlier insertions win ties (BinaryHeap is a max-heap).
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+/// Per-host queue and politeness state.
+#[derive(Debug)]
+struct HostQueue {
+    heap: BinaryHeap<Queued>,
+    next_ready: Instant,
+    delay: Duration,
+}
+
+/// The crawl frontier.
+pub struct Frontier {
+    config: FrontierConfig,
+    seen: BloomFilter,
+    hosts: HashMap<String, HostQueue>,
+    scorer: Box<dyn PriorityScorer>,
+    seq: u64,
+    len: usize,
+}
+
+impl std::fmt::Debug for Frontier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Frontier")
+            .field("config", &self.config)
+            .field("hosts", &self.hosts.len())
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl Frontier {
+    /// Creates a frontier with the default priority scorer.
+    pub fn new(config: FrontierConfig) -> Self {
+        Self::with_scorer(config, DepthScorer)
+    }
+
+    /// Creates a frontier with a custom priority scorer.
+    pub fn with_scorer(config: FrontierConfig, scorer: impl PriorityScorer + 'static) -> Self {
+        Self {
+            seen: BloomFilter::with_capacity(config.expected_urls, config.false_positive_rate),
+            config,
+            hosts: HashMap::new(),
+            scorer: Box::new(scorer),
+            seq: 0,
+            len: 0,
+        }
+    }
+
+    /// Queues a URL. Returns `Ok(false)` if it was a duplicate, too deep, or its host queue is full.
+    pub fn push(&mut self, url: &str, depth: u32, discovered_from: Option<&str>) -> Result<bool> {
+        if depth > self.config.max_depth {
+            return Ok(false);
+        }
+
+        let normalized = normalize_url(url)?;
+        let parsed = Url::parse(&normalized)?;
+        let Some(host) = parsed.host_str().map(str::to_string) else {
+            return Ok(false);
+        };
+
+        if self.seen.contains(&normalized) {
+            return Ok(false);
+        }
+
+        let default_delay = self.config.default_host_delay;
+        let queue = self.hosts.entry(host).or_insert_with(|| HostQueue {
+            heap: BinaryHeap::new(),
+            next_ready: Instant::now(),
+            delay: default_delay,
+        });
+        if queue.heap.len() >= self.config.max_queue_per_host {
+            return Ok(false);
+        }
+
+        self.seen.insert(&normalized);
+        let priority = self.scorer.score(&parsed, depth);
+        self.seq += 1;
+        queue.heap.push(Queued {
+            entry: FrontierEntry {
+                url: normalized,
+                depth,
+                priority,
+                discovered_from: discovered_from.map(str::to_string),
+            },
+            seq: self.seq,
+        });
+        self.len += 1;
+
+        Ok(true)
+    }
+
+    /// Marks a URL as already seen without queueing it.
+    pub fn mark_seen(&mut self, url: &str) -> Result<()> {
+        self.seen.insert(&normalize_url(url)?);
+        Ok(())
+    }
+
+    /// Checks whether a URL has (probably) been seen.
+    pub fn is_seen(&self, url: &str) -> bool {
+        normalize_url(url).map(|u| self.seen.contains(&u)).unwrap_or(false)
+    }
+
+    /// Sets the politeness delay for a host.
+    pub fn set_host_delay(&mut self, host: &str, delay: Duration) {
+        if let Some(queue) = self.hosts.get_mut(host) {
+            queue.delay = delay;
+        }
+    }
+
+    /// Pops the highest-priority URL among hosts whose delay has elapsed.
+    pub fn pop_ready(&mut self) -> Option<FrontierEntry> {
+        let now = Instant::now();
+
+        let host = self
+            .hosts
+            .iter()
+            .filter(|(_, queue)| queue.next_ready <= now)
+            .filter_map(|(host, queue)| queue.heap.peek().map(|head| (host, head)))
+            .max_by(|(_, a), (_, b)| a.cmp(b))
+            .map(|(host, _)| host.clone())?;
+
+        let queue = self.hosts.get_mut(&host)?;
+        let queued = queue.heap.pop()?;
+        queue.next_ready = now + queue.delay;
+        self.len -= 1;
+
+        if queue.heap.is_empty() && queue.delay.is_zero() {
+            self.hosts.remove(&host);
+        }
+
+        Some(queued.entry)
+    }
+
+    /// Time until the next host becomes ready, or `None` if the frontier is empty.
+    pub fn next_ready_in(&self) -> Option<Duration> {
+        let now = Instant::now();
+        self.hosts
+            .values()
+            .filter(|queue| !queue.heap.is_empty())
+            .map(|queue| queue.next_ready.saturating_duration_since(now))
+            .min()
+    }
+
+    /// Number of queued URLs.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the frontier has no queued URLs.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of hosts with queued URLs.
+    pub fn host_count(&self) -> usize {
+        self.hosts.values().filter(|queue| !queue.heap.is_empty()).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> FrontierConfig {
+        FrontierConfig {
+            expected_urls: 1_000,
+            default_host_delay: Duration::ZERO,
+            ..FrontierConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        assert_eq!(
+            normalize_url("HTTPS://Example.COM:443/a?b=2&a=1&utm_source=x#frag").unwrap(),
+            "https://example.com/a?a=1&b=2"
+        );
+        assert_eq!(normalize_url("http://example.com").unwrap(), "http://example.com/");
+        assert_eq!(normalize_url("http://example.com/?gclid=abc").unwrap(), "http://example.com/");
+        assert!(normalize_url("not a url").is_err());
+    }
+
+    #[test]
+    fn test_bloom_filter() {
+        let mut filter = BloomFilter::with_capacity(1_000, 0.01);
+        assert!(filter.insert("https://example.com/"));
+        assert!(!filter.insert("https://example.com/"));
+        assert!(filter.contains("https://example.com/"));
+
+        let false_positives = (0..1_000)
+            .filter(|i| filter.contains(&format!("https://other.test/{}", i)))
+            .count();
+        assert!(false_positives < 50);
+    }
+
+    #[test]
+    fn test_push_dedups_normalized_urls() {
+        let mut frontier = Frontier::new(config());
+        assert!(frontier.push("https://example.com/a#x", 0, None).unwrap());
+        assert!(!frontier.push("https://EXAMPLE.com/a", 0, None).unwrap());
+        assert_eq!(frontier.len(), 1);
+        assert!(frontier.is_seen("https://example.com/a"));
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let mut frontier = Frontier::new(FrontierConfig { max_depth: 1, ..config() });
+        assert!(frontier.push("https://example.com/a", 1, None).unwrap());
+        assert!(!frontier.push("https://example.com/b", 2, None).unwrap());
+    }
+
+    #[test]
+    fn test_pop_by_priority() {
+        let mut frontier = Frontier::new(config());
+        frontier.push("https://example.com/deep/page", 3, None).unwrap();
+        frontier.push("https://example.com/", 0, None).unwrap();
+        frontier.push("https://example.com/shallow", 1, Some("https://example.com/")).unwrap();
+
+        assert_eq!(frontier.pop_ready().unwrap().url, "https://example.com/");
+        let next = frontier.pop_ready().unwrap();
+        assert_eq!(next.url, "https://example.com/shallow");
+        assert_eq!(next.discovered_from.as_deref(), Some("https://example.com/"));
+        assert_eq!(frontier.pop_ready().unwrap().url, "https://example.com/deep/page");
+        assert!(frontier.pop_ready().is_none());
+        assert!(frontier.is_empty());
+    }
+
+    #[test]
+    fn test_host_politeness() {
+        let mut frontier = Frontier::new(FrontierConfig {
+            default_host_delay: Duration::from_secs(60),
+            ..config()
+        });
+        frontier.push("https://a.test/1", 0, None).unwrap();
+        frontier.push("https://a.test/2", 0, None).unwrap();
+        frontier.push("https://b.test/1", 0, None).unwrap();
+        assert_eq!(frontier.host_count(), 2);
+
+        let first = frontier.pop_ready().unwrap();
+        let second = frontier.pop_ready().unwrap();
+        assert_ne!(Url::parse(&first.url).unwrap().host(), Url::parse(&second.url).unwrap().host());
+
+        // Both hosts are now cooling down
+        assert!(frontier.pop_ready().is_none());
+        assert!(frontier.next_ready_in().unwrap() > Duration::from_secs(50));
+    }
+
+    #[test]
+    fn test_max_queue_per_host() {
+        let mut frontier = Frontier::new(FrontierConfig { max_queue_per_host: 1, ..config() });
+        assert!(frontier.push("https://a.test/1", 0, None).unwrap());
+        assert!(!frontier.push("https://a.test/2", 0, None).unwrap());
+        assert!(frontier.push("https://b.test/1", 0, None).unwrap());
+    }
+}
@@ -0,0 +1,100 @@
+//! # llama-moonlight-crawler
+//!
+//! Crawl subsystem for the Llama Moonlight ecosystem.
+//!
+//! This crate collects the pieces every large crawl needs so that consumers of the
+//! workspace don't have to reinvent them.
+//!
+//! ## Features
+//!
+//! - robots.txt fetching, caching, and allow/deny evaluation per user agent
+//! - Politeness policy with crawl-delay support and per-host request pacing
+//! - sitemap.xml discovery and streaming (sitemap indexes, urlsets, gzip)
+//! - URL frontier with bloom-filter dedup, per-host queues, and priority scoring
+//! - Pluggable fetchers (plain HTTP, stealth HTTP, browser) and extractors
+//! - Declarative schema extraction with CSS and XPath selectors
+//! - Simhash/minhash content fingerprints with sqlite-backed change detection
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use llama_moonlight_crawler::{PolitenessConfig, PolitenessPolicy, Result};
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<()> {
+//!     let policy = PolitenessPolicy::new(PolitenessConfig::default());
+//!
+//!     let url = "https://example.com/some/page";
+//!     if policy.is_allowed(url).await? {
+//!         // Waits until the host may be hit again
+//!         policy.wait_turn(url).await?;
+//!         println!("Fetching {}", url);
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs)]
+
+use thiserror::Error;
+
+pub mod crawler;
+pub mod extractor;
+pub mod fetcher;
+pub mod fingerprint;
+pub mod frontier;
+pub mod robots;
+pub mod schema;
+pub mod sitemap;
+
+pub use crawler::{CrawlOutcome, CrawlResult, CrawlStats, Crawler, CrawlerConfig};
+pub use extractor::{Extracted, Extractor, LinkExtractor, MetadataExtractor};
+pub use fetcher::{FetchedPage, Fetcher, HttpFetcher};
+pub use fingerprint::{ChangeConfig, ChangeStatus, Fingerprint, FingerprintStore, MinHash};
+pub use frontier::{normalize_url, BloomFilter, Frontier, FrontierConfig, FrontierEntry, PriorityScorer};
+pub use robots::{PolitenessConfig, PolitenessPolicy, RobotsTxt};
+pub use schema::{FieldSpec, FieldType, Schema, SchemaExtractor, SelectorType};
+pub use sitemap::{Sitemap, SitemapConfig, SitemapFetcher, SitemapUrl};
+
+#[cfg(feature = "stealth")]
+pub use fetcher::StealthHttpFetcher;
+
+#[cfg(feature = "browser")]
+pub use fetcher::BrowserFetcher;
+
+/// Custom result type for crawl operations
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Error type for crawl operations
+#[derive(Debug, Error)]
+pub enum Error {
+    /// HTTP errors from reqwest
+    #[error("HTTP error: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// URL parsing errors
+    #[error("URL error: {0}")]
+    UrlError(#[from] url::ParseError),
+
+    /// The URL is disallowed by the host's robots.txt
+    #[error("Disallowed by robots.txt: {0}")]
+    Disallowed(String),
+
+    /// Error parsing fetched content
+    #[error("Parse error: {0}")]
+    ParseError(String),
+
+    /// Database errors
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    /// IO errors
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// Other errors
+    #[error("Other error: {0}")]
+    Other(String),
+}
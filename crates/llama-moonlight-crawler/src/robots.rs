@@ -0,0 +1,577 @@
+//! robots.txt parsing and politeness policy.
+//!
+//! [`RobotsTxt`] parses a robots.txt body and answers allow/deny questions for a
+//! user agent. [`PolitenessPolicy`] fetches and caches robots.txt per origin and
+//! paces requests per host, honoring `Crawl-delay` where present.
+
+use crate::{Error, Result};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{debug, instrument, warn};
+use url::Url;
+
+/// A single allow/disallow rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    /// Path pattern (may contain `*` and a trailing `$`)
+    pattern: String,
+    /// Whether the rule allows (true) or disallows (false) matching paths
+    allow: bool,
+}
+
+/// A group of rules applying to one or more user agents.
+#[derive(Debug, Clone, Default)]
+struct Group {
+    /// Lowercased user-agent tokens this group applies to
+    user_agents: Vec<String>,
+    /// Rules in the group
+    rules: Vec<Rule>,
+    /// Crawl delay requested for this group
+    crawl_delay: Option<Duration>,
+}
+
+/// A parsed robots.txt file.
+#[derive(Debug, Clone, Default)]
+pub struct RobotsTxt {
+    groups: Vec<Group>,
+    sitemaps: Vec<String>,
+}
+
+impl RobotsTxt {
+    /// Parses a robots.txt body. Unknown directives and malformed lines are ignored.
+    pub fn parse(body: &str) -> Self {
+        let mut robots = RobotsTxt::default();
+        let mut current: Option<Group> = None;
+        // Consecutive user-agent lines share a group; a rule ends the user-agent run.
+        let mut collecting_agents = false;
+
+        for raw_line in body.lines() {
+            let line = raw_line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim();
+
+            match key.as_str() {
+                "user-agent" => {
+                    if !collecting_agents {
+                        if let Some(group) = current.take() {
+                            robots.groups.push(group);
+                        }
+                        current = Some(Group::default());
+                    }
+                    collecting_agents = true;
+                    if let Some(group) = current.as_mut() {
+                        group.user_agents.push(value.to_ascii_lowercase());
+                    }
+                }
+                "allow" | "disallow" => {
+                    collecting_agents = false;
+                    if let Some(group) = current.as_mut() {
+                        // An empty Disallow means "allow everything" and adds no rule.
+                        if !value.is_empty() {
+                            group.rules.push(Rule {
+                                pattern: value.to_string(),
+                                allow: key == "allow",
+                            });
+                        }
+                    }
+                }
+                "crawl-delay" => {
+                    collecting_agents = false;
+                    if let Some(group) = current.as_mut() {
+                        // Negative, non-finite and out-of-range delays are ignored.
+                        if let Some(delay) = value
+                            .parse::<f64>()
+                            .ok()
+                            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                        {
+                            group.crawl_delay = Some(delay);
+                        }
+                    }
+                }
+                // Sitemap lines are global and do not end a group.
+                "sitemap" if !value.is_empty() => {
+                    robots.sitemaps.push(value.to_string());
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(group) = current.take() {
+            robots.groups.push(group);
+        }
+
+        robots
+    }
+
+    /// A robots.txt that allows everything (used when none exists).
+    pub fn allow_all() -> Self {
+        RobotsTxt::default()
+    }
+
+    /// A robots.txt that disallows everything (used when the server errors).
+    pub fn disallow_all() -> Self {
+        RobotsTxt {
+            groups: vec![Group {
+                user_agents: vec!["*".to_string()],
+                rules: vec![Rule { pattern: "/".to_string(), allow: false }],
+                crawl_delay: None,
+            }],
+            sitemaps: Vec::new(),
+        }
+    }
+
+    /// Returns the sitemap URLs declared in the file.
+    pub fn sitemaps(&self) -> &[String] {
+        &self.sitemaps
+    }
+
+    /// Checks whether `path` (path plus optional query) may be fetched by `user_agent`.
+    pub fn is_allowed(&self, user_agent: &str, path: &str) -> bool {
+        if path == "/robots.txt" {
+            return true;
+        }
+
+        let rules = self.rules_for(user_agent);
+        let mut best: Option<&Rule> = None;
+
+        for rule in rules {
+            if !pattern_matches(&rule.pattern, path) {
+                continue;
+            }
+            best = match best {
+                None => Some(rule),
+                Some(current) => {
+                    let (len, current_len) = (rule.pattern.len(), current.pattern.len());
+                    // Longest match wins; on a tie the least restrictive (allow) rule wins.
+                    if len > current_len || (len == current_len && rule.allow && !current.allow) {
+                        Some(rule)
+                    } else {
+                        Some(current)
+                    }
+                }
+            };
+        }
+
+        best.map(|rule| rule.allow).unwrap_or(true)
+    }
+
+    /// Returns the crawl delay requested for `user_agent`, if any.
+    pub fn crawl_delay(&self, user_agent: &str) -> Option<Duration> {
+        self.matching_groups(user_agent)
+            .iter()
+            .filter_map(|group| group.crawl_delay)
+            .max()
+    }
+
+    /// Collects the rules of every group that applies to `user_agent`.
+    fn rules_for(&self, user_agent: &str) -> Vec<&Rule> {
+        self.matching_groups(user_agent)
+            .into_iter()
+            .flat_map(|group| group.rules.iter())
+            .collect()
+    }
+
+    /// Finds the groups for the most specific user-agent token matching `user_agent`,
+    /// falling back to the `*` groups.
+    fn matching_groups(&self, user_agent: &str) -> Vec<&Group> {
+        let product = product_token(user_agent);
+
+        let best_token = self
+            .groups
+            .iter()
+            .flat_map(|group| group.user_agents.iter())
+            .filter(|token| token.as_str() != "*" && product.contains(token.as_str()))
+            .max_by_key(|token| token.len());
+
+        match best_token {
+            Some(token) => self
+                .groups
+                .iter()
+                .filter(|group| group.user_agents.iter().any(|ua| ua == token))
+                .collect(),
+            None => self
+                .groups
+                .iter()
+                .filter(|group| group.user_agents.iter().any(|ua| ua == "*"))
+                .collect(),
+        }
+    }
+}
+
+/// Extracts the lowercased product token from a user-agent string
+/// (`"LlamaBot/1.0 (+https://...)"` becomes `"llamabot"`).
+fn product_token(user_agent: &str) -> String {
+    user_agent
+        .split(|c: char| c == '/' || c.is_whitespace())
+        .next()
+        .unwrap_or("")
+        .to_ascii_lowercase()
+}
+
+/// Matches a robots.txt path pattern supporting `*` wildcards and a trailing `$` anchor.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(stripped) => (stripped, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    if !path.starts_with(first) {
+        return false;
+    }
+
+    let mut pos = first.len();
+    let rest: Vec<&str> = parts.collect();
+
+    for (i, part) in rest.iter().enumerate() {
+        let is_last = i == rest.len() - 1;
+        if is_last && anchored {
+            // The final literal must sit at the very end of the path.
+            return path.len() >= pos + part.len() && path.ends_with(part);
+        }
+        match path[pos..].find(part) {
+            Some(idx) => pos += idx + part.len(),
+            None => return false,
+        }
+    }
+
+    !anchored || pos == path.len()
+}
+
+/// Configuration for the politeness policy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolitenessConfig {
+    /// User agent used both for fetching robots.txt and for rule matching
+    pub user_agent: String,
+
+    /// Minimum delay between two requests to the same host
+    pub default_delay: Duration,
+
+    /// Whether to honor `Crawl-delay` directives
+    pub respect_crawl_delay: bool,
+
+    /// Upper bound applied to `Crawl-delay` values
+    pub max_crawl_delay: Duration,
+
+    /// How long a fetched robots.txt stays cached
+    pub cache_ttl: Duration,
+
+    /// Timeout for robots.txt requests
+    pub fetch_timeout: Duration,
+}
+
+impl Default for PolitenessConfig {
+    fn default() -> Self {
+        Self {
+            user_agent: format!("LlamaMoonlight/{}", env!("CARGO_PKG_VERSION")),
+            default_delay: Duration::from_millis(1000),
+            respect_crawl_delay: true,
+            max_crawl_delay: Duration::from_secs(60),
+            cache_ttl: Duration::from_secs(24 * 60 * 60),
+            fetch_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// A cached robots.txt entry.
+#[derive(Debug, Clone)]
+struct CachedRobots {
+    robots: Arc<RobotsTxt>,
+    fetched_at: Instant,
+}
+
+/// Fetches and caches robots.txt per origin and paces requests per host.
+#[derive(Debug, Clone)]
+pub struct PolitenessPolicy {
+    config: PolitenessConfig,
+    client: Client,
+    cache: Arc<RwLock<HashMap<String, CachedRobots>>>,
+    next_slot: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl PolitenessPolicy {
+    /// Creates a new policy with the given configuration.
+    pub fn new(config: PolitenessConfig) -> Self {
+        let client = Client::builder()
+            .user_agent(config.user_agent.clone())
+            .timeout(config.fetch_timeout)
+            .build()
+            .unwrap_or_default();
+
+        Self::with_client(config, client)
+    }
+
+    /// Creates a new policy reusing an existing HTTP client.
+    pub fn with_client(config: PolitenessConfig, client: Client) -> Self {
+        Self {
+            config,
+            client,
+            cache: Arc::new(RwLock::new(HashMap::new())),
+            next_slot: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the policy configuration.
+    pub fn config(&self) -> &PolitenessConfig {
+        &self.config
+    }
+
+    /// Inserts a robots.txt for an origin (e.g. `https://example.com`) without fetching it.
+    pub async fn insert(&self, origin: &str, robots: RobotsTxt) {
+        self.cache.write().await.insert(
+            origin.to_string(),
+            CachedRobots {
+                robots: Arc::new(robots),
+                fetched_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Returns the robots.txt for the URL's origin, fetching it if not cached or expired.
+    #[instrument(skip(self), fields(domain = tracing::field::Empty))]
+    pub async fn robots_for(&self, url: &str) -> Result<Arc<RobotsTxt>> {
+        let parsed = Url::parse(url)?;
+        let origin = parsed.origin().ascii_serialization();
+        tracing::Span::current().record("domain", parsed.host_str().unwrap_or(""));
+
+        if let Some(cached) = self.cache.read().await.get(&origin) {
+            if cached.fetched_at.elapsed() < self.config.cache_ttl {
+                return Ok(cached.robots.clone());
+            }
+        }
+
+        let robots = self.fetch(&origin).await;
+        let robots = Arc::new(robots);
+        self.cache.write().await.insert(
+            origin,
+            CachedRobots {
+                robots: robots.clone(),
+                fetched_at: Instant::now(),
+            },
+        );
+
+        Ok(robots)
+    }
+
+    /// Fetches robots.txt for an origin. Missing files allow everything; server
+    /// errors and network failures disallow everything until the entry expires.
+    async fn fetch(&self, origin: &str) -> RobotsTxt {
+        let robots_url = format!("{}/robots.txt", origin);
+        debug!("Fetching {}", robots_url);
+
+        match self.client.get(&robots_url).send().await {
+            Ok(response) => {
+                let status = response.status();
+                if status.is_success() {
+                    match response.text().await {
+                        Ok(body) => RobotsTxt::parse(&body),
+                        Err(e) => {
+                            warn!("Failed to read {}: {}", robots_url, e);
+                            RobotsTxt::disallow_all()
+                        }
+                    }
+                } else if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+                    RobotsTxt::allow_all()
+                } else {
+                    warn!("robots.txt at {} returned {}", robots_url, status);
+                    RobotsTxt::disallow_all()
+                }
+            }
+            Err(e) => {
+                warn!("Failed to fetch {}: {}", robots_url, e);
+                RobotsTxt::disallow_all()
+            }
+        }
+    }
+
+    /// Checks whether the configured user agent may fetch `url`.
+    pub async fn is_allowed(&self, url: &str) -> Result<bool> {
+        let parsed = Url::parse(url)?;
+        let robots = self.robots_for(url).await?;
+        Ok(robots.is_allowed(&self.config.user_agent, &path_and_query(&parsed)))
+    }
+
+    /// Returns an error if `url` is disallowed by robots.txt.
+    pub async fn check(&self, url: &str) -> Result<()> {
+        if self.is_allowed(url).await? {
+            Ok(())
+        } else {
+            Err(Error::Disallowed(url.to_string()))
+        }
+    }
+
+    /// Returns the delay enforced between requests to the URL's host.
+    pub async fn delay_for(&self, url: &str) -> Result<Duration> {
+        if !self.config.respect_crawl_delay {
+            return Ok(self.config.default_delay);
+        }
+
+        let robots = self.robots_for(url).await?;
+        let delay = robots
+            .crawl_delay(&self.config.user_agent)
+            .map(|d| d.min(self.config.max_crawl_delay))
+            .unwrap_or(self.config.default_delay);
+
+        Ok(delay.max(self.config.default_delay))
+    }
+
+    /// Waits until the URL's host may be requested again and reserves the next slot.
+    #[instrument(skip(self))]
+    pub async fn wait_turn(&self, url: &str) -> Result<()> {
+        let host = Url::parse(url)?
+            .host_str()
+            .ok_or_else(|| Error::Other(format!("URL has no host: {}", url)))?
+            .to_string();
+        let delay = self.delay_for(url).await?;
+
+        let wait = {
+            let mut slots = self.next_slot.lock().await;
+            let now = Instant::now();
+            let slot = slots.get(&host).copied().unwrap_or(now).max(now);
+            slots.insert(host, slot + delay);
+            slot - now
+        };
+
+        if !wait.is_zero() {
+            debug!("Pacing request for {:?}", wait);
+            tokio::time::sleep(wait).await;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the path and query of a URL as matched against robots.txt rules.
+fn path_and_query(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "
+# Example robots.txt
+User-agent: *
+Disallow: /private/
+Allow: /private/public.html
+Crawl-delay: 2
+
+User-agent: LlamaBot
+User-agent: OtherBot
+Disallow: /
+Allow: /open/
+
+Sitemap: https://example.com/sitemap.xml
+";
+
+    #[test]
+    fn test_parse_groups_and_sitemaps() {
+        let robots = RobotsTxt::parse(SAMPLE);
+        assert_eq!(robots.groups.len(), 2);
+        assert_eq!(robots.groups[1].user_agents, vec!["llamabot", "otherbot"]);
+        assert_eq!(robots.sitemaps(), &["https://example.com/sitemap.xml".to_string()]);
+    }
+
+    #[test]
+    fn test_wildcard_group() {
+        let robots = RobotsTxt::parse(SAMPLE);
+        assert!(robots.is_allowed("SomeBot/1.0", "/index.html"));
+        assert!(!robots.is_allowed("SomeBot/1.0", "/private/secret.html"));
+        assert!(robots.is_allowed("SomeBot/1.0", "/private/public.html"));
+        assert_eq!(robots.crawl_delay("SomeBot/1.0"), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_specific_group_overrides_wildcard() {
+        let robots = RobotsTxt::parse(SAMPLE);
+        assert!(!robots.is_allowed("LlamaBot/2.0 (+https://llama.ai)", "/index.html"));
+        assert!(robots.is_allowed("LlamaBot/2.0", "/open/page"));
+        assert_eq!(robots.crawl_delay("LlamaBot/2.0"), None);
+        assert!(robots.is_allowed("LlamaBot/2.0", "/robots.txt"));
+    }
+
+    #[test]
+    fn test_invalid_crawl_delays_are_ignored() {
+        for delay in ["1e30", "-1", "NaN", "inf", "soon"] {
+            let robots = RobotsTxt::parse(&format!("User-agent: *\nCrawl-delay: {}\n", delay));
+            assert_eq!(robots.crawl_delay("AnyBot"), None, "Crawl-delay: {}", delay);
+        }
+
+        let robots = RobotsTxt::parse("User-agent: *\nCrawl-delay: 0.5\n");
+        assert_eq!(robots.crawl_delay("AnyBot"), Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn test_pattern_matching() {
+        assert!(pattern_matches("/fish", "/fish.html"));
+        assert!(pattern_matches("/*.php", "/index.php?x=1"));
+        assert!(pattern_matches("/*.php$", "/folder/index.php"));
+        assert!(!pattern_matches("/*.php$", "/index.php?x=1"));
+        assert!(pattern_matches("/fish*shark", "/fish-and-shark"));
+        assert!(!pattern_matches("/fish", "/Fish"));
+        assert!(pattern_matches("/exact$", "/exact"));
+        assert!(!pattern_matches("/exact$", "/exactly"));
+    }
+
+    #[test]
+    fn test_empty_disallow_allows_everything() {
+        let robots = RobotsTxt::parse("User-agent: *\nDisallow:\n");
+        assert!(robots.is_allowed("AnyBot", "/anything"));
+    }
+
+    #[test]
+    fn test_allow_and_disallow_all() {
+        assert!(RobotsTxt::allow_all().is_allowed("AnyBot", "/x"));
+        assert!(!RobotsTxt::disallow_all().is_allowed("AnyBot", "/x"));
+    }
+
+    #[tokio::test]
+    async fn test_policy_uses_cached_robots() {
+        let config = PolitenessConfig {
+            user_agent: "LlamaBot/1.0".to_string(),
+            default_delay: Duration::from_millis(0),
+            ..PolitenessConfig::default()
+        };
+        let policy = PolitenessPolicy::new(config);
+        policy.insert("https://example.com", RobotsTxt::parse(SAMPLE)).await;
+
+        assert!(!policy.is_allowed("https://example.com/index.html").await.unwrap());
+        assert!(policy.is_allowed("https://example.com/open/a?b=c").await.unwrap());
+        assert!(matches!(
+            policy.check("https://example.com/closed").await,
+            Err(Error::Disallowed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_crawl_delay_is_capped_and_floored() {
+        let config = PolitenessConfig {
+            user_agent: "SomeBot".to_string(),
+            default_delay: Duration::from_millis(500),
+            max_crawl_delay: Duration::from_secs(1),
+            ..PolitenessConfig::default()
+        };
+        let policy = PolitenessPolicy::new(config);
+        policy
+            .insert("https://slow.test", RobotsTxt::parse("User-agent: *\nCrawl-delay: 30\n"))
+            .await;
+        policy
+            .insert("https://fast.test", RobotsTxt::parse("User-agent: *\nCrawl-delay: 0.1\n"))
+            .await;
+
+        assert_eq!(policy.delay_for("https://slow.test/").await.unwrap(), Duration::from_secs(1));
+        assert_eq!(policy.delay_for("https://fast.test/").await.unwrap(), Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,638 @@
+//! Declarative HTML-to-structured-data extraction.
+//!
+//! A [`Schema`] maps field names to selectors (CSS or a practical XPath subset),
+//! optionally picks an attribute, applies a regex, coerces the result to a type,
+//! and can nest further schemas for lists of records. Extraction yields a
+//! `serde_json::Value` or, through [`Schema::extract_as`], any deserializable type.
+//!
+//! ```rust
+//! use llama_moonlight_crawler::schema::Schema;
+//!
+//! let schema = Schema::from_json(r#"{
+//!     "fields": [
+//!         { "name": "title", "selector": "h1" },
+//!         { "name": "price", "selector": "//span[@class='price']", "regex": "([0-9.]+)", "type": "float" }
+//!     ]
+//! }"#).unwrap();
+//!
+//! let data = schema.extract("<h1>Lamp</h1><span class='price'>$12.50</span>").unwrap();
+//! assert_eq!(data["title"], "Lamp");
+//! assert_eq!(data["price"], 12.5);
+//! ```
+
+use crate::extractor::{Extracted, Extractor};
+use crate::fetcher::FetchedPage;
+use crate::{Error, Result};
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// How a field's selector is interpreted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SelectorType {
+    /// Detect from the selector: XPath if it starts with `/`, `./` or `(`, CSS otherwise
+    #[default]
+    Auto,
+    /// CSS selector
+    Css,
+    /// XPath expression (subset, see [`xpath_to_css`])
+    XPath,
+}
+
+/// Type a field value is coerced to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    /// Text, whitespace-trimmed
+    #[default]
+    String,
+    /// Integer (thousands separators are stripped)
+    Integer,
+    /// Floating point number (thousands separators are stripped)
+    Float,
+    /// Boolean (`true`/`yes`/`1`/`on` are true)
+    Boolean,
+}
+
+/// Specification of one extracted field.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldSpec {
+    /// Output key
+    pub name: String,
+
+    /// Selector, relative to the parent element for nested fields; `:scope`,
+    /// `.` or an empty selector reads the parent element itself
+    pub selector: String,
+
+    /// How to interpret `selector`
+    #[serde(default)]
+    pub selector_type: SelectorType,
+
+    /// What to read from a matched element: `text` (default), `html`, `inner_html`, or an attribute name
+    #[serde(default)]
+    pub attr: Option<String>,
+
+    /// Regex applied to the value; the first capture group (or whole match) is kept
+    #[serde(default)]
+    pub regex: Option<String>,
+
+    /// Type the value is coerced to
+    #[serde(default, rename = "type")]
+    pub field_type: FieldType,
+
+    /// Collect every match into a list instead of taking the first
+    #[serde(default)]
+    pub multiple: bool,
+
+    /// Nested fields; when present each match becomes an object
+    #[serde(default)]
+    pub fields: Vec<FieldSpec>,
+
+    /// Fail extraction when the field has no value
+    #[serde(default)]
+    pub required: bool,
+
+    /// Value used when nothing matches
+    #[serde(default)]
+    pub default: Option<Value>,
+}
+
+impl FieldSpec {
+    /// Creates a text field with a CSS or XPath selector.
+    pub fn new(name: &str, selector: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            selector: selector.to_string(),
+            selector_type: SelectorType::Auto,
+            attr: None,
+            regex: None,
+            field_type: FieldType::String,
+            multiple: false,
+            fields: Vec::new(),
+            required: false,
+            default: None,
+        }
+    }
+
+    /// Reads the given attribute instead of the text.
+    pub fn attr(mut self, attr: &str) -> Self {
+        self.attr = Some(attr.to_string());
+        self
+    }
+
+    /// Applies a regex to the value.
+    pub fn regex(mut self, regex: &str) -> Self {
+        self.regex = Some(regex.to_string());
+        self
+    }
+
+    /// Coerces the value to the given type.
+    pub fn field_type(mut self, field_type: FieldType) -> Self {
+        self.field_type = field_type;
+        self
+    }
+
+    /// Collects all matches into a list.
+    pub fn multiple(mut self) -> Self {
+        self.multiple = true;
+        self
+    }
+
+    /// Marks the field as required.
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Adds a nested field.
+    pub fn field(mut self, field: FieldSpec) -> Self {
+        self.fields.push(field);
+        self
+    }
+}
+
+/// A declarative extraction schema.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Schema {
+    /// Top-level fields
+    pub fields: Vec<FieldSpec>,
+}
+
+impl Schema {
+    /// Creates an empty schema.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a field.
+    pub fn field(mut self, field: FieldSpec) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    /// Parses a schema from JSON.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let schema: Schema = serde_json::from_str(json)
+            .map_err(|e| Error::ParseError(format!("Invalid schema: {}", e)))?;
+        schema.compile()?;
+        Ok(schema)
+    }
+
+    /// Validates every selector and regex so errors surface before extraction.
+    pub fn compile(&self) -> Result<()> {
+        fn check(fields: &[FieldSpec]) -> Result<()> {
+            for field in fields {
+                resolve_selector(field)?;
+                if let Some(pattern) = &field.regex {
+                    compile_regex(pattern)?;
+                }
+                check(&field.fields)?;
+            }
+            Ok(())
+        }
+        check(&self.fields)
+    }
+
+    /// Extracts a JSON object from an HTML document.
+    pub fn extract(&self, html: &str) -> Result<Value> {
+        let document = Html::parse_document(html);
+        extract_fields(&self.fields, document.root_element())
+    }
+
+    /// Extracts and deserializes into a typed struct.
+    pub fn extract_as<T: DeserializeOwned>(&self, html: &str) -> Result<T> {
+        serde_json::from_value(self.extract(html)?)
+            .map_err(|e| Error::ParseError(format!("Extracted data does not match type: {}", e)))
+    }
+
+    /// Extracts from the current content of a browser page.
+    #[cfg(feature = "browser")]
+    pub async fn extract_page(&self, page: &llama_moonlight_core::Page) -> Result<Value> {
+        let html = page.content().await.map_err(|e| Error::Other(e.to_string()))?;
+        self.extract(&html)
+    }
+}
+
+/// Extracts every field relative to `scope` into a JSON object.
+fn extract_fields(fields: &[FieldSpec], scope: ElementRef<'_>) -> Result<Value> {
+    let mut object = Map::new();
+    for field in fields {
+        object.insert(field.name.clone(), extract_field(field, scope)?);
+    }
+    Ok(Value::Object(object))
+}
+
+/// Extracts a single field relative to `scope`.
+fn extract_field(field: &FieldSpec, scope: ElementRef<'_>) -> Result<Value> {
+    let (selector, xpath_attr) = resolve_selector(field)?;
+    let attr = field.attr.as_deref().or(xpath_attr.as_deref());
+    let regex = field.regex.as_deref().map(compile_regex).transpose()?;
+
+    let elements: Vec<ElementRef<'_>> = match &selector {
+        Some(selector) => scope.select(selector).collect(),
+        None => vec![scope],
+    };
+
+    let mut values = Vec::new();
+    for element in elements {
+        let value = if field.fields.is_empty() {
+            let raw = read_value(element, attr);
+            let raw = match &regex {
+                Some(re) => match apply_regex(re, &raw) {
+                    Some(matched) => matched,
+                    None => continue,
+                },
+                None => raw,
+            };
+            match coerce(&raw, field.field_type) {
+                Some(value) => value,
+                None => continue,
+            }
+        } else {
+            extract_fields(&field.fields, element)?
+        };
+
+        values.push(value);
+        if !field.multiple {
+            break;
+        }
+    }
+
+    let value = if field.multiple {
+        if values.is_empty() {
+            field.default.clone().unwrap_or(Value::Array(Vec::new()))
+        } else {
+            Value::Array(values)
+        }
+    } else {
+        values
+            .into_iter()
+            .next()
+            .or_else(|| field.default.clone())
+            .unwrap_or(Value::Null)
+    };
+
+    let missing = value.is_null() || value.as_array().map(|a| a.is_empty()).unwrap_or(false);
+    if field.required && missing {
+        return Err(Error::ParseError(format!("Required field '{}' not found", field.name)));
+    }
+
+    Ok(value)
+}
+
+/// Reads the requested part of an element.
+fn read_value(element: ElementRef<'_>, attr: Option<&str>) -> String {
+    match attr {
+        None | Some("text") => normalize_whitespace(&element.text().collect::<String>()),
+        Some("html") => element.html(),
+        Some("inner_html") => element.inner_html(),
+        Some(name) => element.value().attr(name).unwrap_or("").trim().to_string(),
+    }
+}
+
+/// Collapses runs of whitespace into single spaces.
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Applies a regex, returning the first capture group or the whole match.
+fn apply_regex(re: &Regex, value: &str) -> Option<String> {
+    let captures = re.captures(value)?;
+    captures
+        .get(1)
+        .or_else(|| captures.get(0))
+        .map(|m| m.as_str().to_string())
+}
+
+/// Coerces a raw string to the requested type, or `None` if it cannot be.
+fn coerce(raw: &str, field_type: FieldType) -> Option<Value> {
+    let trimmed = raw.trim();
+    match field_type {
+        FieldType::String => Some(Value::String(trimmed.to_string())),
+        FieldType::Integer => trimmed.replace([',', '_', ' '], "").parse::<i64>().ok().map(Value::from),
+        FieldType::Float => trimmed
+            .replace([',', '_', ' '], "")
+            .parse::<f64>()
+            .ok()
+            .and_then(|f| serde_json::Number::from_f64(f).map(Value::Number)),
+        FieldType::Boolean => match trimmed.to_ascii_lowercase().as_str() {
+            "true" | "yes" | "1" | "on" => Some(Value::Bool(true)),
+            "false" | "no" | "0" | "off" => Some(Value::Bool(false)),
+            _ => None,
+        },
+    }
+}
+
+/// Compiles a regex, mapping errors.
+fn compile_regex(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).map_err(|e| Error::ParseError(format!("Invalid regex '{}': {}", pattern, e)))
+}
+
+/// Resolves a field's selector to CSS, returning any attribute selected by an XPath `@attr` step.
+///
+/// The selector is `None` when the field reads the scope element itself.
+fn resolve_selector(field: &FieldSpec) -> Result<(Option<Selector>, Option<String>)> {
+    if matches!(field.selector.trim(), "" | ":scope" | ".") {
+        return Ok((None, None));
+    }
+
+    let is_xpath = match field.selector_type {
+        SelectorType::Css => false,
+        SelectorType::XPath => true,
+        SelectorType::Auto => {
+            let s = field.selector.trim_start();
+            s.starts_with('/') || s.starts_with("./") || s.starts_with('(')
+        }
+    };
+
+    let (css, attr) = if is_xpath {
+        xpath_to_css(&field.selector)?
+    } else {
+        (field.selector.clone(), None)
+    };
+
+    let selector = Selector::parse(&css)
+        .map_err(|e| Error::ParseError(format!("Invalid selector '{}': {:?}", field.selector, e)))?;
+    Ok((Some(selector), attr))
+}
+
+/// Translates a practical XPath subset to a CSS selector.
+///
+/// Supported: `/` and `//` steps (leading `.` for relative paths), element names
+/// and `*`, predicates `[@a]`, `[@a='v']`, `[contains(@a,'v')]`,
+/// `[starts-with(@a,'v')]` and `[n]`, and a trailing `/@attr` or `/text()` step.
+/// The returned attribute is `Some` for a trailing `/@attr` step.
+pub fn xpath_to_css(xpath: &str) -> Result<(String, Option<String>)> {
+    let unsupported = || Error::ParseError(format!("Unsupported XPath expression: {}", xpath));
+    let mut rest = xpath.trim().trim_start_matches('.');
+    let mut css = String::new();
+    let mut attr = None;
+    let mut first = true;
+
+    while !rest.is_empty() {
+        let descendant = rest.starts_with("//");
+        rest = if descendant {
+            &rest[2..]
+        } else {
+            rest.strip_prefix('/').ok_or_else(unsupported)?
+        };
+
+        // Step runs until the next '/' outside of a predicate.
+        let mut depth = 0;
+        let end = rest
+            .char_indices()
+            .find(|&(_, c)| {
+                match c {
+                    '[' => depth += 1,
+                    ']' => depth -= 1,
+                    _ => {}
+                }
+                c == '/' && depth == 0
+            })
+            .map(|(i, _)| i)
+            .unwrap_or(rest.len());
+        let step = &rest[..end];
+        rest = &rest[end..];
+
+        if let Some(name) = step.strip_prefix('@') {
+            if !rest.is_empty() {
+                return Err(unsupported());
+            }
+            attr = Some(name.to_string());
+            break;
+        }
+        if step == "text()" {
+            if !rest.is_empty() {
+                return Err(unsupported());
+            }
+            attr = Some("text".to_string());
+            break;
+        }
+
+        let (tag, predicates) = match step.find('[') {
+            Some(i) => (&step[..i], &step[i..]),
+            None => (step, ""),
+        };
+        if tag.is_empty() || !tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '*' || c == '-') {
+            return Err(unsupported());
+        }
+
+        if !first {
+            css.push_str(if descendant { " " } else { " > " });
+        } else if !descendant && !css.is_empty() {
+            css.push_str(" > ");
+        }
+        first = false;
+        css.push_str(tag);
+
+        let mut predicates = predicates;
+        while let Some(stripped) = predicates.strip_prefix('[') {
+            let close = stripped.find(']').ok_or_else(unsupported)?;
+            css.push_str(&predicate_to_css(&stripped[..close]).ok_or_else(unsupported)?);
+            predicates = &stripped[close + 1..];
+        }
+        if !predicates.is_empty() {
+            return Err(unsupported());
+        }
+    }
+
+    if css.is_empty() {
+        return Err(unsupported());
+    }
+
+    Ok((css, attr))
+}
+
+/// Translates a single XPath predicate body to a CSS fragment.
+fn predicate_to_css(predicate: &str) -> Option<String> {
+    let predicate = predicate.trim();
+
+    if let Ok(n) = predicate.parse::<usize>() {
+        return Some(format!(":nth-of-type({})", n));
+    }
+    if predicate == "last()" {
+        return Some(":last-of-type".to_string());
+    }
+
+    let function = |name: &str, op: &str| -> Option<String> {
+        let args = predicate.strip_prefix(name)?.strip_prefix('(')?.strip_suffix(')')?;
+        let (attr, value) = args.split_once(',')?;
+        let attr = attr.trim().strip_prefix('@')?;
+        let value = unquote(value.trim())?;
+        Some(format!("[{}{}=\"{}\"]", attr, op, value))
+    };
+    if let Some(css) = function("contains", "*").or_else(|| function("starts-with", "^")) {
+        return Some(css);
+    }
+
+    let attr_expr = predicate.strip_prefix('@')?;
+    match attr_expr.split_once('=') {
+        Some((name, value)) => Some(format!("[{}=\"{}\"]", name.trim(), unquote(value.trim())?)),
+        None => Some(format!("[{}]", attr_expr.trim())),
+    }
+}
+
+/// Strips matching single or double quotes.
+fn unquote(value: &str) -> Option<&str> {
+    value
+        .strip_prefix('\'')
+        .and_then(|v| v.strip_suffix('\''))
+        .or_else(|| value.strip_prefix('"').and_then(|v| v.strip_suffix('"')))
+}
+
+/// Runs a [`Schema`] on every fetched HTML page and stores the result under one key.
+#[derive(Debug, Clone)]
+pub struct SchemaExtractor {
+    schema: Schema,
+    key: String,
+}
+
+impl SchemaExtractor {
+    /// Creates an extractor storing results under `key`.
+    pub fn new(key: &str, schema: Schema) -> Self {
+        Self {
+            schema,
+            key: key.to_string(),
+        }
+    }
+}
+
+impl Extractor for SchemaExtractor {
+    fn extract(&self, page: &FetchedPage) -> Result<Extracted> {
+        let mut extracted = Extracted::default();
+        if page.is_html() {
+            extracted.data.insert(self.key.clone(), self.schema.extract(&page.body)?);
+        }
+        Ok(extracted)
+    }
+
+    fn name(&self) -> &str {
+        "schema"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const HTML: &str = r#"
+        <html><body>
+          <h1 class="title"> Product   List </h1>
+          <ul id="items">
+            <li class="item" data-id="1"><a href="/p/1">Lamp</a><span class="price">$1,204.50</span><i>in stock</i></li>
+            <li class="item" data-id="2"><a href="/p/2">Desk</a><span class="price">$99</span><i>yes</i></li>
+            <li class="item featured" data-id="3"><a href="/p/3">Chair</a><span class="price">n/a</span></li>
+          </ul>
+        </body></html>
+    "#;
+
+    #[test]
+    fn test_css_fields_and_nested_lists() {
+        let schema = Schema::new()
+            .field(FieldSpec::new("title", "h1.title"))
+            .field(
+                FieldSpec::new("items", "li.item")
+                    .multiple()
+                    .field(FieldSpec::new("id", ":scope").attr("data-id").field_type(FieldType::Integer))
+                    .field(FieldSpec::new("name", "a"))
+                    .field(FieldSpec::new("url", "a").attr("href"))
+                    .field(FieldSpec::new("price", "span.price").regex(r"([\d,.]+)").field_type(FieldType::Float)),
+            );
+
+        let data = schema.extract(HTML).unwrap();
+        assert_eq!(data["title"], "Product List");
+        assert_eq!(data["items"].as_array().unwrap().len(), 3);
+        assert_eq!(data["items"][0]["id"], 1);
+        assert_eq!(data["items"][0]["name"], "Lamp");
+        assert_eq!(data["items"][0]["url"], "/p/1");
+        assert_eq!(data["items"][0]["price"], 1204.5);
+        assert_eq!(data["items"][1]["price"], 99.0);
+        assert_eq!(data["items"][2]["price"], Value::Null);
+    }
+
+    #[test]
+    fn test_scope_selector_reads_scope_element() {
+        // Other selectors are matched against descendants of the scope element only
+        let schema = Schema::new().field(
+            FieldSpec::new("ids", "li.item")
+                .multiple()
+                .field(FieldSpec::new("nested", "li").attr("data-id"))
+                .field(FieldSpec::new("id", ":scope").attr("data-id").field_type(FieldType::Integer))
+                .field(FieldSpec::new("class", "").attr("class")),
+        );
+        let data = schema.extract(HTML).unwrap();
+        assert_eq!(data["ids"][0]["nested"], Value::Null);
+        assert_eq!(data["ids"][0]["id"], 1);
+        assert_eq!(data["ids"][2]["class"], "item featured");
+    }
+
+    #[test]
+    fn test_xpath_fields() {
+        let schema = Schema::from_json(
+            r#"{"fields": [
+                {"name": "names", "selector": "//ul[@id='items']/li/a/text()", "multiple": true},
+                {"name": "links", "selector": "//li[contains(@class,'featured')]/a/@href"},
+                {"name": "second", "selector": "//li[2]/a"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let data = schema.extract(HTML).unwrap();
+        assert_eq!(data["names"], json!(["Lamp", "Desk", "Chair"]));
+        assert_eq!(data["links"], "/p/3");
+        assert_eq!(data["second"], "Desk");
+    }
+
+    #[test]
+    fn test_xpath_to_css() {
+        assert_eq!(xpath_to_css("//div[@class='a']/p").unwrap(), ("div[class=\"a\"] > p".to_string(), None));
+        assert_eq!(xpath_to_css("//a/@href").unwrap(), ("a".to_string(), Some("href".to_string())));
+        assert_eq!(xpath_to_css("//ul//li[1]").unwrap().0, "ul li:nth-of-type(1)");
+        assert_eq!(xpath_to_css("//a[starts-with(@href,'/p')]").unwrap().0, "a[href^=\"/p\"]");
+        assert!(xpath_to_css("//a[@x=1 and @y=2]").is_err());
+        assert!(xpath_to_css("//a/../b").is_err());
+    }
+
+    #[test]
+    fn test_boolean_default_and_required() {
+        let schema = Schema::new()
+            .field(FieldSpec::new("stock", "li i").field_type(FieldType::Boolean))
+            .field(FieldSpec {
+                default: Some(json!("none")),
+                ..FieldSpec::new("missing", "table")
+            });
+        let data = schema.extract(HTML).unwrap();
+        // "in stock" is not a boolean so the first coercible match ("yes") wins
+        assert_eq!(data["stock"], true);
+        assert_eq!(data["missing"], "none");
+
+        let required = Schema::new().field(FieldSpec::new("missing", "table").required());
+        assert!(required.extract(HTML).is_err());
+    }
+
+    #[test]
+    fn test_extract_as_typed_struct() {
+        #[derive(Deserialize)]
+        struct Page {
+            title: String,
+            prices: Vec<String>,
+        }
+
+        let schema = Schema::new()
+            .field(FieldSpec::new("title", "h1"))
+            .field(FieldSpec::new("prices", ".price").multiple());
+        let page: Page = schema.extract_as(HTML).unwrap();
+        assert_eq!(page.title, "Product List");
+        assert_eq!(page.prices, vec!["$1,204.50", "$99", "n/a"]);
+    }
+
+    #[test]
+    fn test_invalid_schema_is_rejected() {
+        assert!(Schema::from_json(r#"{"fields": [{"name": "x", "selector": "a[", "type": "string"}]}"#).is_err());
+        assert!(Schema::from_json(r#"{"fields": [{"name": "x", "selector": "a", "regex": "("}]}"#).is_err());
+    }
+}
@@ -0,0 +1,360 @@
+//! sitemap.xml discovery and parsing.
+//!
+//! Sitemaps are discovered from robots.txt `Sitemap:` lines (falling back to
+//! `/sitemap.xml`), fetched (gzip-compressed or not), and expanded recursively
+//! through sitemap indexes. URLs are yielded as an async stream so that very large
+//! sites never need to be held in memory at once.
+
+use crate::robots::PolitenessPolicy;
+use crate::{Error, Result};
+use flate2::read::GzDecoder;
+use futures::stream::{self, Stream};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::io::Read;
+use std::time::Duration;
+use tracing::{debug, instrument, warn};
+use url::Url;
+
+/// Magic bytes at the start of a gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// A URL entry from a `<urlset>` sitemap.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SitemapUrl {
+    /// Page location
+    pub loc: String,
+    /// Last modification date as written in the sitemap (W3C datetime)
+    pub lastmod: Option<String>,
+    /// Change frequency hint (`daily`, `weekly`, ...)
+    pub changefreq: Option<String>,
+    /// Priority hint between 0.0 and 1.0
+    pub priority: Option<f32>,
+}
+
+/// The parsed contents of a single sitemap document.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Sitemap {
+    /// A `<sitemapindex>` pointing at further sitemaps
+    Index(Vec<String>),
+    /// A `<urlset>` listing page URLs
+    UrlSet(Vec<SitemapUrl>),
+    /// A document that is neither (empty or unrecognized root element)
+    #[default]
+    Empty,
+}
+
+impl Sitemap {
+    /// Parses a sitemap document from raw bytes, decompressing gzip if needed.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.starts_with(&GZIP_MAGIC) {
+            let mut decoded = String::new();
+            GzDecoder::new(bytes).read_to_string(&mut decoded)?;
+            Self::parse(&decoded)
+        } else {
+            let text = std::str::from_utf8(bytes)
+                .map_err(|e| Error::ParseError(format!("Sitemap is not valid UTF-8: {}", e)))?;
+            Self::parse(text)
+        }
+    }
+
+    /// Parses a sitemap document from XML text.
+    pub fn parse(xml: &str) -> Result<Self> {
+        let mut reader = Reader::from_str(xml);
+        reader.trim_text(true);
+
+        let mut root: Option<String> = None;
+        let mut element: Option<String> = None;
+        let mut current = SitemapUrl::default();
+        let mut urls = Vec::new();
+        let mut sitemaps = Vec::new();
+
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(e)) => {
+                    let name = String::from_utf8_lossy(e.local_name().as_ref()).to_ascii_lowercase();
+                    if root.is_none() {
+                        root = Some(name.clone());
+                    }
+                    if name == "url" || name == "sitemap" {
+                        current = SitemapUrl::default();
+                    }
+                    element = Some(name);
+                }
+                Ok(Event::Text(text)) => {
+                    let value = text
+                        .unescape()
+                        .map_err(|e| Error::ParseError(format!("Invalid sitemap text: {}", e)))?
+                        .trim()
+                        .to_string();
+                    current.set_field(element.as_deref(), value);
+                }
+                Ok(Event::CData(data)) => {
+                    let value = String::from_utf8_lossy(&data).trim().to_string();
+                    current.set_field(element.as_deref(), value);
+                }
+                Ok(Event::End(e)) => {
+                    let name = String::from_utf8_lossy(e.local_name().as_ref()).to_ascii_lowercase();
+                    match name.as_str() {
+                        "url" if !current.loc.is_empty() => urls.push(std::mem::take(&mut current)),
+                        "sitemap" if !current.loc.is_empty() => {
+                            sitemaps.push(std::mem::take(&mut current).loc)
+                        }
+                        _ => {}
+                    }
+                    element = None;
+                }
+                Ok(Event::Eof) => break,
+                Ok(_) => {}
+                Err(e) => {
+                    return Err(Error::ParseError(format!(
+                        "Invalid sitemap XML at position {}: {}",
+                        reader.buffer_position(),
+                        e
+                    )))
+                }
+            }
+        }
+
+        Ok(match root.as_deref() {
+            Some("sitemapindex") => Sitemap::Index(sitemaps),
+            Some("urlset") => Sitemap::UrlSet(urls),
+            _ => Sitemap::Empty,
+        })
+    }
+}
+
+impl SitemapUrl {
+    /// Assigns a child element's text to the matching field.
+    fn set_field(&mut self, element: Option<&str>, value: String) {
+        match element {
+            Some("loc") => self.loc = value,
+            Some("lastmod") => self.lastmod = Some(value),
+            Some("changefreq") => self.changefreq = Some(value),
+            Some("priority") => self.priority = value.parse().ok(),
+            _ => {}
+        }
+    }
+}
+
+/// Configuration for the sitemap fetcher.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SitemapConfig {
+    /// Maximum number of sitemap documents fetched per stream (guards against index loops)
+    pub max_sitemaps: usize,
+
+    /// Maximum number of URLs yielded per stream
+    pub max_urls: usize,
+
+    /// Timeout for each sitemap request
+    pub fetch_timeout: Duration,
+
+    /// Only yield URLs on the same host as the sitemap that listed them
+    pub same_host_only: bool,
+}
+
+impl Default for SitemapConfig {
+    fn default() -> Self {
+        Self {
+            max_sitemaps: 1_000,
+            max_urls: 1_000_000,
+            fetch_timeout: Duration::from_secs(30),
+            same_host_only: true,
+        }
+    }
+}
+
+/// Fetches sitemaps and streams the URLs they contain.
+#[derive(Debug, Clone)]
+pub struct SitemapFetcher {
+    config: SitemapConfig,
+    client: Client,
+}
+
+impl SitemapFetcher {
+    /// Creates a new fetcher with the given configuration.
+    pub fn new(config: SitemapConfig) -> Self {
+        let client = Client::builder()
+            .timeout(config.fetch_timeout)
+            .build()
+            .unwrap_or_default();
+
+        Self::with_client(config, client)
+    }
+
+    /// Creates a new fetcher reusing an existing HTTP client.
+    pub fn with_client(config: SitemapConfig, client: Client) -> Self {
+        Self { config, client }
+    }
+
+    /// Discovers sitemap URLs for the URL's origin from robots.txt, falling back
+    /// to `/sitemap.xml` when robots.txt declares none.
+    #[instrument(skip(self, policy))]
+    pub async fn discover(&self, url: &str, policy: &PolitenessPolicy) -> Result<Vec<String>> {
+        let robots = policy.robots_for(url).await?;
+        if !robots.sitemaps().is_empty() {
+            return Ok(robots.sitemaps().to_vec());
+        }
+
+        let origin = Url::parse(url)?.origin().ascii_serialization();
+        Ok(vec![format!("{}/sitemap.xml", origin)])
+    }
+
+    /// Fetches and parses a single sitemap document.
+    #[instrument(skip(self))]
+    pub async fn fetch(&self, sitemap_url: &str) -> Result<Sitemap> {
+        let response = self.client.get(sitemap_url).send().await?.error_for_status()?;
+        let bytes = response.bytes().await?;
+        Sitemap::from_bytes(&bytes)
+    }
+
+    /// Streams every URL reachable from the given sitemaps, expanding indexes.
+    ///
+    /// Sitemaps that fail to fetch or parse are logged and skipped so that one
+    /// broken child doesn't end the whole stream.
+    pub fn stream(&self, sitemap_urls: Vec<String>) -> impl Stream<Item = SitemapUrl> + '_ {
+        let state = StreamState {
+            pending: sitemap_urls.into_iter().collect(),
+            buffered: VecDeque::new(),
+            seen: HashSet::new(),
+            fetched: 0,
+            yielded: 0,
+        };
+
+        stream::unfold(state, move |mut state| async move {
+            loop {
+                if state.yielded >= self.config.max_urls {
+                    return None;
+                }
+
+                if let Some(entry) = state.buffered.pop_front() {
+                    state.yielded += 1;
+                    return Some((entry, state));
+                }
+
+                let sitemap_url = state.pending.pop_front()?;
+                if !state.seen.insert(sitemap_url.clone()) {
+                    continue;
+                }
+                if state.fetched >= self.config.max_sitemaps {
+                    warn!("Sitemap limit of {} reached", self.config.max_sitemaps);
+                    return None;
+                }
+                state.fetched += 1;
+
+                match self.fetch(&sitemap_url).await {
+                    Ok(Sitemap::Index(children)) => {
+                        debug!("Sitemap index {} lists {} sitemaps", sitemap_url, children.len());
+                        state.pending.extend(children);
+                    }
+                    Ok(Sitemap::UrlSet(urls)) => {
+                        debug!("Sitemap {} lists {} URLs", sitemap_url, urls.len());
+                        let host = Url::parse(&sitemap_url).ok().and_then(|u| u.host_str().map(str::to_string));
+                        state.buffered.extend(urls.into_iter().filter(|entry| {
+                            !self.config.same_host_only || same_host(&entry.loc, host.as_deref())
+                        }));
+                    }
+                    Ok(Sitemap::Empty) => debug!("Sitemap {} is empty", sitemap_url),
+                    Err(e) => warn!("Skipping sitemap {}: {}", sitemap_url, e),
+                }
+            }
+        })
+    }
+}
+
+/// Internal state threaded through [`SitemapFetcher::stream`].
+struct StreamState {
+    pending: VecDeque<String>,
+    buffered: VecDeque<SitemapUrl>,
+    seen: HashSet<String>,
+    fetched: usize,
+    yielded: usize,
+}
+
+/// Checks whether `url` is on `host`.
+fn same_host(url: &str, host: Option<&str>) -> bool {
+    match (Url::parse(url).ok(), host) {
+        (Some(parsed), Some(host)) => parsed.host_str() == Some(host),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    const URLSET: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url>
+    <loc>https://example.com/</loc>
+    <lastmod>2024-01-15</lastmod>
+    <changefreq>daily</changefreq>
+    <priority>1.0</priority>
+  </url>
+  <url>
+    <loc>https://example.com/about?a=1&amp;b=2</loc>
+  </url>
+</urlset>"#;
+
+    const INDEX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <sitemap><loc>https://example.com/sitemap-1.xml</loc><lastmod>2024-01-01</lastmod></sitemap>
+  <sitemap><loc><![CDATA[https://example.com/sitemap-2.xml.gz]]></loc></sitemap>
+</sitemapindex>"#;
+
+    #[test]
+    fn test_parse_urlset() {
+        let Sitemap::UrlSet(urls) = Sitemap::parse(URLSET).unwrap() else {
+            panic!("Expected a urlset");
+        };
+        assert_eq!(urls.len(), 2);
+        assert_eq!(urls[0].loc, "https://example.com/");
+        assert_eq!(urls[0].lastmod.as_deref(), Some("2024-01-15"));
+        assert_eq!(urls[0].changefreq.as_deref(), Some("daily"));
+        assert_eq!(urls[0].priority, Some(1.0));
+        assert_eq!(urls[1].loc, "https://example.com/about?a=1&b=2");
+        assert_eq!(urls[1].priority, None);
+    }
+
+    #[test]
+    fn test_parse_index() {
+        let sitemap = Sitemap::parse(INDEX).unwrap();
+        assert_eq!(
+            sitemap,
+            Sitemap::Index(vec![
+                "https://example.com/sitemap-1.xml".to_string(),
+                "https://example.com/sitemap-2.xml.gz".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_gzip() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(URLSET.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        match Sitemap::from_bytes(&compressed).unwrap() {
+            Sitemap::UrlSet(urls) => assert_eq!(urls.len(), 2),
+            other => panic!("Unexpected sitemap: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_unknown_root() {
+        assert_eq!(Sitemap::parse("<html><body/></html>").unwrap(), Sitemap::Empty);
+    }
+
+    #[test]
+    fn test_same_host() {
+        assert!(same_host("https://example.com/a", Some("example.com")));
+        assert!(!same_host("https://other.com/a", Some("example.com")));
+        assert!(!same_host("not a url", Some("example.com")));
+    }
+}
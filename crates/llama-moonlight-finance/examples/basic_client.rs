@@ -236,6 +236,8 @@ fn create_mock_client(config: ClientConfig) -> FinanceClient {
                 end_time: end_date,
                 timezone: "UTC".to_string(),
                 currency: "USD".to_string(),
+                corporate_actions: Default::default(),
+                adjustment: Default::default(),
             })
         }
         
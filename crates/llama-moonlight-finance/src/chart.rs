@@ -0,0 +1,403 @@
+//! Candlestick chart rendering
+//!
+//! This module renders a price series as a candlestick chart, with optional
+//! indicator overlays and a volume pane, to PNG or SVG bytes that can be
+//! attached to alerts or embedded in reports.
+//!
+//! Bars are laid out by index rather than by time, so gaps such as weekends
+//! and market closures do not leave holes in the chart.
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::io::Cursor;
+
+use crate::{Error, Result};
+use crate::data::{Price, TimeInterval, TimeSeries};
+
+/// Output format of a chart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartFormat {
+    /// PNG image
+    Png,
+    
+    /// SVG document
+    Svg,
+}
+
+/// An indicator drawn over the candles
+#[derive(Debug, Clone, PartialEq)]
+pub enum Overlay {
+    /// Simple moving average of the close over a period
+    Sma(usize),
+    
+    /// Exponential moving average of the close over a period
+    Ema(usize),
+    
+    /// Precomputed values, aligned to the end of the series
+    Line {
+        /// Legend label
+        label: String,
+        
+        /// Values; if fewer than the bars, they belong to the last bars
+        values: Vec<f64>,
+    },
+}
+
+impl Overlay {
+    /// Get the legend label of the overlay
+    pub fn label(&self) -> String {
+        match self {
+            Overlay::Sma(period) => format!("SMA({})", period),
+            Overlay::Ema(period) => format!("EMA({})", period),
+            Overlay::Line { label, .. } => label.clone(),
+        }
+    }
+    
+    /// Compute the points of the overlay as (bar index, value)
+    fn points(&self, series: &TimeSeries<Price>) -> Vec<(f64, f64)> {
+        let values = match self {
+            Overlay::Sma(period) => series.sma(*period),
+            Overlay::Ema(period) => series.ema(*period),
+            Overlay::Line { values, .. } => values.clone(),
+        };
+        
+        let offset = series.len().saturating_sub(values.len());
+        values
+            .into_iter()
+            .take(series.len())
+            .enumerate()
+            .filter(|(_, value)| value.is_finite())
+            .map(|(i, value)| ((offset + i) as f64, value))
+            .collect()
+    }
+}
+
+/// A candlestick chart of a price series
+#[derive(Debug, Clone)]
+pub struct CandlestickChart<'a> {
+    /// Price series
+    series: &'a TimeSeries<Price>,
+    
+    /// Width and height in pixels
+    size: (u32, u32),
+    
+    /// Chart title, defaults to the symbol and interval
+    title: Option<String>,
+    
+    /// Indicator overlays
+    overlays: Vec<Overlay>,
+    
+    /// Whether to draw the volume pane
+    volume: bool,
+}
+
+impl<'a> CandlestickChart<'a> {
+    /// Create a new chart of a price series
+    pub fn new(series: &'a TimeSeries<Price>) -> Self {
+        Self {
+            series,
+            size: (1024, 640),
+            title: None,
+            overlays: Vec::new(),
+            volume: true,
+        }
+    }
+    
+    /// Set the size in pixels
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.size = (width, height);
+        self
+    }
+    
+    /// Set the title
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+    
+    /// Add an indicator overlay
+    pub fn with_overlay(mut self, overlay: Overlay) -> Self {
+        self.overlays.push(overlay);
+        self
+    }
+    
+    /// Set whether to draw the volume pane
+    pub fn with_volume(mut self, volume: bool) -> Self {
+        self.volume = volume;
+        self
+    }
+    
+    /// Render the chart in a format
+    pub fn render(&self, format: ChartFormat) -> Result<Vec<u8>> {
+        match format {
+            ChartFormat::Png => self.to_png(),
+            ChartFormat::Svg => self.to_svg(),
+        }
+    }
+    
+    /// Render the chart as PNG bytes
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        let (width, height) = self.size;
+        let mut buffer = vec![0u8; width as usize * height as usize * 3];
+        
+        {
+            let root = BitMapBackend::with_buffer(&mut buffer, self.size).into_drawing_area();
+            self.draw(&root)?;
+            root.present().map_err(chart_error)?;
+        }
+        
+        let image = image::RgbImage::from_raw(width, height, buffer)
+            .ok_or_else(|| Error::ChartError("Invalid image buffer".to_string()))?;
+        
+        let mut png = Cursor::new(Vec::new());
+        image
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .map_err(|e| Error::ChartError(format!("Failed to encode PNG: {}", e)))?;
+        
+        Ok(png.into_inner())
+    }
+    
+    /// Render the chart as SVG bytes
+    pub fn to_svg(&self) -> Result<Vec<u8>> {
+        let mut svg = String::new();
+        
+        {
+            let root = SVGBackend::with_string(&mut svg, self.size).into_drawing_area();
+            self.draw(&root)?;
+            root.present().map_err(chart_error)?;
+        }
+        
+        Ok(svg.into_bytes())
+    }
+    
+    /// Draw the chart on a drawing area
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<()> {
+        let prices = &self.series.prices;
+        if prices.is_empty() {
+            return Err(Error::ChartError("No prices to chart".to_string()));
+        }
+        
+        root.fill(&WHITE).map_err(chart_error)?;
+        
+        let title = self.title.clone().unwrap_or_else(|| {
+            format!("{} ({})", self.series.symbol, self.series.interval.to_string_representation())
+        });
+        
+        let (price_area, volume_area) = if self.volume {
+            let (upper, lower) = root.split_vertically(self.size.1 * 3 / 4);
+            (upper, Some(lower))
+        } else {
+            (root.clone(), None)
+        };
+        
+        let overlays: Vec<(String, Vec<(f64, f64)>)> = self.overlays
+            .iter()
+            .map(|overlay| (overlay.label(), overlay.points(self.series)))
+            .collect();
+        
+        let (mut low, mut high) = prices
+            .iter()
+            .fold((f64::MAX, f64::MIN), |(low, high), price| (low.min(price.low), high.max(price.high)));
+        for (_, points) in &overlays {
+            for (_, value) in points {
+                low = low.min(*value);
+                high = high.max(*value);
+            }
+        }
+        let padding = ((high - low) * 0.05).max(high.abs() * 0.001).max(f64::EPSILON);
+        
+        let x_range = -0.5..prices.len() as f64 - 0.5;
+        let label_format = date_format(self.series.interval);
+        let x_label = |x: &f64| {
+            let index = x.round();
+            if index < 0.0 {
+                return String::new();
+            }
+            prices
+                .get(index as usize)
+                .map(|price| price.timestamp.format(label_format).to_string())
+                .unwrap_or_default()
+        };
+        
+        let mut chart = ChartBuilder::on(&price_area)
+            .caption(title, ("sans-serif", 22))
+            .margin(10)
+            .x_label_area_size(if volume_area.is_some() { 0 } else { 30 })
+            .y_label_area_size(60)
+            .build_cartesian_2d(x_range.clone(), (low - padding)..(high + padding))
+            .map_err(chart_error)?;
+        
+        chart
+            .configure_mesh()
+            .light_line_style(WHITE.mix(0.0))
+            .x_labels(8)
+            .x_label_formatter(&x_label)
+            .y_label_formatter(&|y| format!("{:.2}", y))
+            .draw()
+            .map_err(chart_error)?;
+        
+        let plot_width = self.size.0.saturating_sub(80);
+        let candle_width = ((plot_width as f64 / prices.len() as f64) * 0.7).clamp(1.0, 15.0) as u32;
+        
+        chart
+            .draw_series(prices.iter().enumerate().map(|(i, price)| {
+                CandleStick::new(
+                    i as f64,
+                    price.open,
+                    price.high,
+                    price.low,
+                    price.close,
+                    GAIN.filled(),
+                    LOSS.filled(),
+                    candle_width,
+                )
+            }))
+            .map_err(chart_error)?;
+        
+        for (i, (label, points)) in overlays.into_iter().enumerate() {
+            let color = Palette99::pick(i).to_rgba();
+            chart
+                .draw_series(LineSeries::new(points, color.stroke_width(2)))
+                .map_err(chart_error)?
+                .label(label)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+        }
+        
+        if !self.overlays.is_empty() {
+            chart
+                .configure_series_labels()
+                .background_style(WHITE.mix(0.8))
+                .border_style(BLACK.mix(0.3))
+                .position(SeriesLabelPosition::UpperLeft)
+                .draw()
+                .map_err(chart_error)?;
+        }
+        
+        if let Some(volume_area) = volume_area {
+            let max_volume = prices.iter().map(|price| price.volume).max().unwrap_or(0).max(1);
+            
+            let mut chart = ChartBuilder::on(&volume_area)
+                .margin(10)
+                .margin_top(0)
+                .x_label_area_size(30)
+                .y_label_area_size(60)
+                .build_cartesian_2d(x_range, 0u64..max_volume + max_volume / 10)
+                .map_err(chart_error)?;
+            
+            chart
+                .configure_mesh()
+                .light_line_style(WHITE.mix(0.0))
+                .x_labels(8)
+                .x_label_formatter(&x_label)
+                .y_labels(3)
+                .y_label_formatter(&|volume| compact_volume(*volume))
+                .draw()
+                .map_err(chart_error)?;
+            
+            chart
+                .draw_series(prices.iter().enumerate().map(|(i, price)| {
+                    let color = if price.close >= price.open { GAIN } else { LOSS };
+                    let x = i as f64;
+                    Rectangle::new([(x - 0.35, 0), (x + 0.35, price.volume)], color.mix(0.6).filled())
+                }))
+                .map_err(chart_error)?;
+        }
+        
+        Ok(())
+    }
+}
+
+/// Color of rising candles
+const GAIN: RGBColor = RGBColor(38, 166, 154);
+
+/// Color of falling candles
+const LOSS: RGBColor = RGBColor(239, 83, 80);
+
+/// Render a candlestick chart of a price series with default settings
+pub fn render_candlestick(series: &TimeSeries<Price>, overlays: &[Overlay], format: ChartFormat) -> Result<Vec<u8>> {
+    overlays
+        .iter()
+        .cloned()
+        .fold(CandlestickChart::new(series), CandlestickChart::with_overlay)
+        .render(format)
+}
+
+/// Get the axis date format of an interval
+fn date_format(interval: TimeInterval) -> &'static str {
+    match interval {
+        TimeInterval::Minute1
+        | TimeInterval::Minute5
+        | TimeInterval::Minute15
+        | TimeInterval::Minute30
+        | TimeInterval::Hourly => "%m-%d %H:%M",
+        TimeInterval::Custom(minutes) if minutes < 24 * 60 => "%m-%d %H:%M",
+        TimeInterval::Monthly | TimeInterval::Quarterly | TimeInterval::Yearly => "%Y-%m",
+        _ => "%Y-%m-%d",
+    }
+}
+
+/// Format a volume compactly, e.g. `1.2M`
+fn compact_volume(volume: u64) -> String {
+    match volume {
+        v if v >= 1_000_000_000 => format!("{:.1}B", v as f64 / 1e9),
+        v if v >= 1_000_000 => format!("{:.1}M", v as f64 / 1e6),
+        v if v >= 1_000 => format!("{:.1}K", v as f64 / 1e3),
+        v => v.to_string(),
+    }
+}
+
+/// Convert a drawing error
+fn chart_error<E: std::error::Error + Send + Sync>(error: DrawingAreaErrorKind<E>) -> Error {
+    Error::ChartError(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+    
+    fn series(len: usize) -> TimeSeries<Price> {
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+        let prices = (0..len)
+            .map(|i| {
+                let open = 100.0 + (i as f64 * 0.7).sin() * 5.0;
+                let close = open + if i % 3 == 0 { -1.5 } else { 1.0 };
+                Price::new(open, open.max(close) + 1.0, open.min(close) - 1.0, close, 1_000_000 + i as u64 * 10_000, start + Duration::days(i as i64))
+            })
+            .collect();
+        
+        TimeSeries::new("AAPL".to_string(), TimeInterval::Daily, prices, start, start + Duration::days(len as i64), "UTC".to_string(), "USD".to_string())
+    }
+    
+    #[test]
+    fn test_render_formats() {
+        let series = series(40);
+        let chart = CandlestickChart::new(&series)
+            .with_size(640, 400)
+            .with_overlay(Overlay::Sma(10))
+            .with_overlay(Overlay::Ema(5));
+        
+        let png = chart.to_png().unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        
+        let svg = String::from_utf8(chart.render(ChartFormat::Svg).unwrap()).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("SMA(10)"));
+        
+        let empty = series.clone();
+        let empty = TimeSeries { prices: Vec::new(), ..empty };
+        assert!(matches!(CandlestickChart::new(&empty).to_svg(), Err(Error::ChartError(_))));
+    }
+    
+    #[test]
+    fn test_overlay_alignment() {
+        let series = series(10);
+        let points = Overlay::Sma(3).points(&series);
+        assert_eq!(points.len(), 8);
+        assert_eq!(points[0].0, 2.0);
+        
+        let line = Overlay::Line { label: "signal".to_string(), values: vec![1.0, f64::NAN, 3.0] };
+        assert_eq!(line.points(&series), vec![(7.0, 1.0), (9.0, 3.0)]);
+        assert_eq!(compact_volume(1_250_000), "1.2M");
+    }
+}
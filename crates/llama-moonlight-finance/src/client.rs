@@ -7,11 +7,15 @@ use chrono::{DateTime, Utc};
 
 use crate::{Error, Result, AssetClass};
 use crate::config::ClientConfig;
-use crate::provider::{Provider, DataProvider, TradingProvider};
-use crate::data::{TimeInterval, TimeRange, TimeSeries, Price, Quote, MarketData};
+use crate::provider::{Capability, Provider, DataProvider, TradingProvider, EconomicDataProvider};
+use crate::data::{TimeInterval, TimeRange, TimeSeries, Price, Quote, MarketData, Observation, EconomicIndicator};
 use crate::market::{OrderBook, TradeHistory};
 use crate::trading::{Order, OrderStatus, Position, TradeExecution};
 use crate::portfolio::{Portfolio, Transaction};
+use crate::corporate_actions::{AdjustmentPolicy, CorporateAction};
+use crate::scheduler::{RequestKind, RequestScheduler};
+use std::future::Future;
+use std::time::Duration;
 
 /// The main client for accessing financial data and trading functionality
 pub struct FinanceClient {
@@ -27,15 +31,24 @@ pub struct FinanceClient {
     /// Registered trading providers
     trading_providers: HashMap<String, Arc<dyn TradingProvider>>,
     
+    /// Registered economic data providers
+    economic_providers: HashMap<String, Arc<dyn EconomicDataProvider>>,
+    
     /// Default data provider
     default_data_provider: Option<String>,
     
     /// Default trading provider
     default_trading_provider: Option<String>,
     
+    /// Default economic data provider
+    default_economic_provider: Option<String>,
+    
     /// User portfolio
     portfolio: Arc<RwLock<Option<Portfolio>>>,
     
+    /// Paces requests according to provider rate limits
+    scheduler: Arc<RequestScheduler>,
+    
     /// API request counter
     request_count: Arc<Mutex<u64>>,
 }
@@ -59,17 +72,26 @@ impl FinanceClient {
             config,
             data_providers: HashMap::new(),
             trading_providers: HashMap::new(),
+            economic_providers: HashMap::new(),
             default_data_provider: None,
             default_trading_provider: None,
+            default_economic_provider: None,
             portfolio: Arc::new(RwLock::new(None)),
+            scheduler: Arc::new(RequestScheduler::new()),
             request_count: Arc::new(Mutex::new(0)),
         }
     }
     
     /// Add a provider to the client
-    pub fn with_provider<P: Provider + 'static>(mut self, provider: P) -> Self {
+    pub fn with_provider<P: Provider + 'static>(mut self, mut provider: P) -> Self {
         let provider_name = provider.name().to_string();
         
+        // Pace requests to the provider unless rate limiting is disabled
+        if self.config.rate_limiting {
+            self.scheduler.register(&provider_name, provider.rate_limits());
+            provider.set_scheduler(self.scheduler.clone());
+        }
+        
         // Register as data provider if it implements DataProvider
         if let Some(data_provider) = provider.as_data_provider() {
             self.data_providers.insert(provider_name.clone(), data_provider);
@@ -82,7 +104,15 @@ impl FinanceClient {
         if let Some(trading_provider) = provider.as_trading_provider() {
             self.trading_providers.insert(provider_name.clone(), trading_provider);
             if self.default_trading_provider.is_none() {
-                self.default_trading_provider = Some(provider_name);
+                self.default_trading_provider = Some(provider_name.clone());
+            }
+        }
+        
+        // Register as economic data provider if it implements EconomicDataProvider
+        if let Some(economic_provider) = provider.as_economic_provider() {
+            self.economic_providers.insert(provider_name.clone(), economic_provider);
+            if self.default_economic_provider.is_none() {
+                self.default_economic_provider = Some(provider_name);
             }
         }
         
@@ -105,6 +135,14 @@ impl FinanceClient {
         self
     }
     
+    /// Set the default economic data provider
+    pub fn with_default_economic_provider(mut self, provider_name: &str) -> Self {
+        if self.economic_providers.contains_key(provider_name) {
+            self.default_economic_provider = Some(provider_name.to_string());
+        }
+        self
+    }
+    
     /// Build the client
     pub fn build(self) -> Self {
         self
@@ -138,6 +176,66 @@ impl FinanceClient {
         }
     }
     
+    /// Get an economic data provider by name
+    pub fn economic_provider(&self, name: &str) -> Option<Arc<dyn EconomicDataProvider>> {
+        self.economic_providers.get(name).cloned()
+    }
+    
+    /// Get the default economic data provider
+    pub fn default_economic_provider(&self) -> Result<Arc<dyn EconomicDataProvider>> {
+        match &self.default_economic_provider {
+            Some(name) => self.economic_provider(name)
+                .ok_or_else(|| Error::ProviderError(format!("Default economic provider '{}' not found", name))),
+            None => Err(Error::ProviderError("No default economic provider set".to_string())),
+        }
+    }
+    
+    /// Get the request scheduler
+    pub fn scheduler(&self) -> &Arc<RequestScheduler> {
+        &self.scheduler
+    }
+    
+    /// Estimate how long a request to a provider would wait for its rate limits
+    pub fn estimated_wait(&self, provider_name: &str, request: RequestKind) -> Duration {
+        let weight = if let Some(provider) = self.data_providers.get(provider_name) {
+            provider.request_weight(request)
+        } else if let Some(provider) = self.trading_providers.get(provider_name) {
+            provider.request_weight(request)
+        } else if let Some(provider) = self.economic_providers.get(provider_name) {
+            provider.request_weight(request)
+        } else {
+            return Duration::ZERO;
+        };
+        
+        self.scheduler.estimated_wait(provider_name, weight)
+    }
+    
+    /// Send a provider request once its rate limits allow
+    ///
+    /// If the provider reports a rate limit error anyway, further requests to
+    /// it are held back for its longest limit window, as any of them may be
+    /// the one that ran out.
+    async fn scheduled<P, T, F>(&self, provider: &P, request: RequestKind, send: F) -> Result<T>
+    where
+        P: Provider + ?Sized,
+        F: Future<Output = Result<T>>,
+    {
+        let name = provider.name();
+        self.scheduler.acquire(name, provider.request_weight(request)).await?;
+        *self.request_count.lock().await += 1;
+        
+        let result = send.await;
+        if let Err(Error::RateLimitError(_)) = &result {
+            let backoff = self.scheduler.limits(name).iter()
+                .map(|limit| limit.window)
+                .max()
+                .unwrap_or(Duration::from_millis(self.config.retry_delay as u64));
+            self.scheduler.pause(name, backoff);
+        }
+        
+        result
+    }
+    
     /// Get the client configuration
     pub fn config(&self) -> &ClientConfig {
         &self.config
@@ -148,49 +246,74 @@ impl FinanceClient {
         HistoricalPriceBuilder::new(self, symbol.to_string())
     }
     
+    /// Create an economic series request builder for a provider series ID
+    pub fn economic_series(&self, series_id: &str) -> EconomicSeriesBuilder {
+        EconomicSeriesBuilder::new(self, SeriesKey::Id(series_id.to_string()))
+    }
+    
+    /// Create an economic series request builder for a common indicator
+    pub fn economic_indicator(&self, indicator: EconomicIndicator) -> EconomicSeriesBuilder {
+        EconomicSeriesBuilder::new(self, SeriesKey::Indicator(indicator))
+    }
+    
     /// Get the latest quote for a symbol
     pub async fn quote(&self, symbol: &str) -> Result<Quote> {
-        self.default_data_provider()?.quote(symbol).await
+        let provider = self.default_data_provider()?;
+        self.scheduled(&*provider, RequestKind::Quote, provider.quote(symbol)).await
     }
     
     /// Get quotes for multiple symbols
     pub async fn quotes(&self, symbols: &[&str]) -> Result<HashMap<String, Quote>> {
-        self.default_data_provider()?.quotes(symbols).await
+        let provider = self.default_data_provider()?;
+        self.scheduled(&*provider, RequestKind::Quotes(symbols.len()), provider.quotes(symbols)).await
+    }
+    
+    /// Get the splits and dividends of a symbol
+    pub async fn corporate_actions(&self, symbol: &str, range: TimeRange) -> Result<Vec<CorporateAction>> {
+        let provider = self.default_data_provider()?;
+        self.scheduled(&*provider, RequestKind::CorporateActions, provider.corporate_actions(symbol, range)).await
     }
     
     /// Search for symbols by query
     pub async fn search(&self, query: &str, asset_class: Option<AssetClass>) -> Result<Vec<MarketData>> {
-        self.default_data_provider()?.search(query, asset_class).await
+        let provider = self.default_data_provider()?;
+        self.scheduled(&*provider, RequestKind::Search, provider.search(query, asset_class)).await
     }
     
     /// Get an order book for a symbol
     pub async fn order_book(&self, symbol: &str, depth: Option<u32>) -> Result<OrderBook> {
-        self.default_trading_provider()?.order_book(symbol, depth).await
+        let provider = self.default_trading_provider()?;
+        self.scheduled(&*provider, RequestKind::OrderBook(depth), provider.order_book(symbol, depth)).await
     }
     
     /// Get recent trades for a symbol
     pub async fn recent_trades(&self, symbol: &str, limit: Option<u32>) -> Result<TradeHistory> {
-        self.default_trading_provider()?.recent_trades(symbol, limit).await
+        let provider = self.default_trading_provider()?;
+        self.scheduled(&*provider, RequestKind::RecentTrades, provider.recent_trades(symbol, limit)).await
     }
     
     /// Place a trading order
     pub async fn place_order(&self, order: Order) -> Result<OrderStatus> {
-        self.default_trading_provider()?.place_order(order).await
+        let provider = self.default_trading_provider()?;
+        self.scheduled(&*provider, RequestKind::Order, provider.place_order(order)).await
     }
     
     /// Get the status of an order
     pub async fn order_status(&self, order_id: &str) -> Result<OrderStatus> {
-        self.default_trading_provider()?.order_status(order_id).await
+        let provider = self.default_trading_provider()?;
+        self.scheduled(&*provider, RequestKind::Order, provider.order_status(order_id)).await
     }
     
     /// Cancel an order
     pub async fn cancel_order(&self, order_id: &str) -> Result<bool> {
-        self.default_trading_provider()?.cancel_order(order_id).await
+        let provider = self.default_trading_provider()?;
+        self.scheduled(&*provider, RequestKind::Order, provider.cancel_order(order_id)).await
     }
     
     /// Get open positions
     pub async fn positions(&self) -> Result<Vec<Position>> {
-        self.default_trading_provider()?.positions().await
+        let provider = self.default_trading_provider()?;
+        self.scheduled(&*provider, RequestKind::Account, provider.positions()).await
     }
     
     /// Get portfolio data
@@ -222,6 +345,7 @@ impl FinanceClient {
             request_count: *self.request_count.lock().await,
             data_providers: self.data_providers.keys().cloned().collect(),
             trading_providers: self.trading_providers.keys().cloned().collect(),
+            economic_providers: self.economic_providers.keys().cloned().collect(),
         }
     }
 }
@@ -246,6 +370,9 @@ pub struct HistoricalPriceBuilder<'a> {
     /// Whether to adjust for splits and dividends
     adjust: bool,
     
+    /// Adjustment to apply locally from corporate actions
+    adjustment: Option<AdjustmentPolicy>,
+    
     /// Maximum number of data points to return
     limit: Option<u32>,
     
@@ -263,6 +390,7 @@ impl<'a> HistoricalPriceBuilder<'a> {
             range: None,
             include_extended: false,
             adjust: true,
+            adjustment: None,
             limit: None,
             provider: None,
         }
@@ -292,6 +420,16 @@ impl<'a> HistoricalPriceBuilder<'a> {
         self
     }
     
+    /// Adjust prices locally from the provider's corporate actions
+    ///
+    /// Raw prices and corporate actions are fetched separately and the
+    /// returned series carries its actions, so it can be re-adjusted with
+    /// [`TimeSeries::adjust`]. This overrides [`adjust`](Self::adjust).
+    pub fn adjustment(mut self, policy: AdjustmentPolicy) -> Self {
+        self.adjustment = Some(policy);
+        self
+    }
+    
     /// Set the maximum number of data points
     pub fn limit(mut self, limit: u32) -> Self {
         self.limit = Some(limit);
@@ -317,15 +455,116 @@ impl<'a> HistoricalPriceBuilder<'a> {
         let interval = self.interval.unwrap_or(TimeInterval::Daily);
         let range = self.range.unwrap_or(TimeRange::Month(1));
         
-        // Fetch the historical prices
-        provider.historical_prices(
+        let policy = match self.adjustment {
+            Some(policy) => policy,
+            None => {
+                // Fetch the historical prices
+                let request = provider.historical_prices(
+                    &self.symbol,
+                    interval,
+                    range,
+                    self.include_extended,
+                    self.adjust,
+                    self.limit,
+                );
+                return self.client.scheduled(&*provider, RequestKind::HistoricalPrices, request).await;
+            }
+        };
+        
+        // Fetch raw prices and adjust them locally
+        if !provider.supports(Capability::CorporateActions) {
+            return Err(Error::ProviderError(format!(
+                "Provider '{}' does not serve corporate actions, so prices cannot be adjusted with {:?}",
+                provider.name(),
+                policy
+            )));
+        }
+        
+        let request = provider.historical_prices(
             &self.symbol,
             interval,
             range,
             self.include_extended,
-            self.adjust,
+            false,
             self.limit,
-        ).await
+        );
+        let prices = self.client.scheduled(&*provider, RequestKind::HistoricalPrices, request).await?;
+        let request = provider.corporate_actions(&self.symbol, range);
+        let actions = self.client.scheduled(&*provider, RequestKind::CorporateActions, request).await?;
+        
+        prices.with_corporate_actions(actions).adjust(policy)
+    }
+}
+
+/// Series requested by an economic series builder
+enum SeriesKey {
+    /// Provider-specific series ID
+    Id(String),
+    
+    /// Common indicator
+    Indicator(EconomicIndicator),
+}
+
+/// Builder for economic series requests
+pub struct EconomicSeriesBuilder<'a> {
+    /// Reference to the finance client
+    client: &'a FinanceClient,
+    
+    /// Series to fetch
+    key: SeriesKey,
+    
+    /// Time range for the series
+    range: Option<TimeRange>,
+    
+    /// Economic data provider to use
+    provider: Option<String>,
+}
+
+impl<'a> EconomicSeriesBuilder<'a> {
+    /// Create a new economic series builder
+    fn new(client: &'a FinanceClient, key: SeriesKey) -> Self {
+        Self {
+            client,
+            key,
+            range: None,
+            provider: None,
+        }
+    }
+    
+    /// Set the time range
+    pub fn range(mut self, range: TimeRange) -> Self {
+        self.range = Some(range);
+        self
+    }
+    
+    /// Set the economic data provider to use
+    pub fn provider(mut self, provider: &str) -> Self {
+        self.provider = Some(provider.to_string());
+        self
+    }
+    
+    /// Execute the request and fetch the series
+    pub async fn fetch(self) -> Result<TimeSeries<Observation>> {
+        // Determine which provider to use
+        let provider = match self.provider {
+            Some(ref name) => self.client.economic_provider(name)
+                .ok_or_else(|| Error::ProviderError(format!("Provider '{}' not found", name)))?,
+            None => self.client.default_economic_provider()?,
+        };
+        
+        // Macro series are sparse, so default to a longer range than prices
+        let range = self.range.unwrap_or(TimeRange::Year(10));
+        
+        match self.key {
+            SeriesKey::Id(ref series_id) => {
+                let request = provider.series(series_id, range);
+                self.client.scheduled(&*provider, RequestKind::EconomicSeries, request).await
+            }
+            SeriesKey::Indicator(indicator) => {
+                let request = provider.indicator(indicator, range);
+                self.client.scheduled(&*provider, RequestKind::EconomicSeries, request).await
+            }
+        }
     }
 }
 
@@ -340,12 +579,16 @@ pub struct ClientStats {
     
     /// List of registered trading providers
     pub trading_providers: Vec<String>,
+    
+    /// List of registered economic data providers
+    pub economic_providers: Vec<String>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::data::{TimeInterval, TimeRange, Price, Quote, MarketData};
+    use crate::scheduler::RateLimit;
     use chrono::Utc;
     use std::sync::Arc;
     
@@ -408,6 +651,8 @@ mod tests {
                 end_time: Utc::now(),
                 timezone: "UTC".to_string(),
                 currency: "USD".to_string(),
+                corporate_actions: Default::default(),
+                adjustment: Default::default(),
             })
         }
         
@@ -472,6 +717,10 @@ mod tests {
             Ok(true)
         }
         
+        async fn order_by_client_id(&self, _client_order_id: &str) -> Result<Option<OrderStatus>> {
+            Ok(None)
+        }
+        
         async fn positions(&self) -> Result<Vec<Position>> {
             Ok(vec![])
         }
@@ -490,4 +739,22 @@ mod tests {
         assert!(client.default_data_provider().is_ok());
         assert!(client.default_trading_provider().is_ok());
     }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limit_error_pauses_for_longest_window() {
+        let client = FinanceClient::new().with_provider(MockProvider).build();
+        client.scheduler().register("mock", vec![RateLimit::per_second(10), RateLimit::per_minute(100)]);
+        
+        let result: Result<()> = client
+            .scheduled(&MockProvider, RequestKind::Quote, async { Err(Error::RateLimitError("slow down".to_string())) })
+            .await;
+        assert!(result.is_err());
+        
+        let wait = client.estimated_wait("mock", RequestKind::Quote);
+        assert!(wait > Duration::from_secs(59) && wait <= Duration::from_secs(60));
+        
+        let start = tokio::time::Instant::now();
+        client.scheduled(&MockProvider, RequestKind::Quote, async { Ok(()) }).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_secs(59));
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,310 @@
+//! Corporate actions and price adjustment
+//!
+//! Splits and cash dividends make raw price history discontinuous: a 4:1 split
+//! looks like a 75% crash. This module describes corporate actions and
+//! back-adjusts price series so returns computed across them are correct.
+//!
+//! Adjustment works backwards from the most recent bar, which keeps its raw
+//! prices. Bars before a split are divided by the split ratio (and their
+//! volume multiplied by it); bars before a dividend's ex-date are multiplied
+//! by `1 - dividend / previous close`, the factor used for total-return
+//! series such as Yahoo's adjusted close.
+
+use std::collections::BTreeMap;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+
+use crate::{Error, Result};
+use crate::data::{Price, TimeSeries};
+
+/// A corporate action affecting the price of a security
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CorporateAction {
+    /// Stock split (or reverse split when `to < from`)
+    Split {
+        /// First trading day on the new share basis
+        ex_date: DateTime<Utc>,
+        
+        /// Shares held before the split
+        from: f64,
+        
+        /// Shares held after the split
+        to: f64,
+    },
+    
+    /// Cash dividend
+    Dividend {
+        /// First trading day without the dividend
+        ex_date: DateTime<Utc>,
+        
+        /// Amount per share, on the share basis in effect at the ex-date
+        amount: f64,
+    },
+}
+
+impl CorporateAction {
+    /// Create a split, e.g. `split(date, 1.0, 4.0)` for a 4-for-1 split
+    pub fn split(ex_date: DateTime<Utc>, from: f64, to: f64) -> Self {
+        CorporateAction::Split { ex_date, from, to }
+    }
+    
+    /// Create a cash dividend
+    pub fn dividend(ex_date: DateTime<Utc>, amount: f64) -> Self {
+        CorporateAction::Dividend { ex_date, amount }
+    }
+    
+    /// Get the ex-date of the action
+    pub fn ex_date(&self) -> DateTime<Utc> {
+        match self {
+            CorporateAction::Split { ex_date, .. } | CorporateAction::Dividend { ex_date, .. } => *ex_date,
+        }
+    }
+    
+    /// Check that the action describes a valid adjustment
+    fn validate(&self) -> Result<()> {
+        match self {
+            CorporateAction::Split { from, to, .. } if !(*from > 0.0 && *to > 0.0) => {
+                Err(Error::ValidationError(format!("Invalid split ratio {}:{}", to, from)))
+            }
+            CorporateAction::Dividend { amount, .. } if !amount.is_finite() || *amount < 0.0 => {
+                Err(Error::ValidationError(format!("Invalid dividend amount {}", amount)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Which corporate actions a price series is adjusted for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum AdjustmentPolicy {
+    /// Raw traded prices
+    #[default]
+    Unadjusted,
+    
+    /// Adjusted for splits only
+    Splits,
+    
+    /// Adjusted for splits and dividends (total return)
+    SplitsAndDividends,
+}
+
+impl AdjustmentPolicy {
+    /// Check whether dividends are adjusted for
+    fn includes_dividends(&self) -> bool {
+        matches!(self, AdjustmentPolicy::SplitsAndDividends)
+    }
+}
+
+/// Actions taking effect between two consecutive bars
+#[derive(Debug)]
+struct Boundary {
+    /// Product of split ratios (`to / from`)
+    split_ratio: f64,
+    
+    /// Sum of dividends per share
+    dividends: f64,
+}
+
+impl TimeSeries<Price> {
+    /// Attach corporate actions to the series
+    pub fn with_corporate_actions(mut self, actions: Vec<CorporateAction>) -> Self {
+        self.corporate_actions = actions;
+        self
+    }
+    
+    /// Mark which corporate actions the series' prices are already adjusted for
+    pub fn with_adjustment(mut self, adjustment: AdjustmentPolicy) -> Self {
+        self.adjustment = adjustment;
+        self
+    }
+    
+    /// Produce the series adjusted according to a policy
+    ///
+    /// Uses the series' attached corporate actions. A series that is already
+    /// adjusted is first restored to raw prices, so any policy can be reached
+    /// from any other, including [`AdjustmentPolicy::Unadjusted`].
+    pub fn adjust(&self, policy: AdjustmentPolicy) -> Result<TimeSeries<Price>> {
+        for action in &self.corporate_actions {
+            action.validate()?;
+        }
+        
+        let mut prices = self.prices.clone();
+        let boundaries = self.boundaries();
+        
+        if self.adjustment != AdjustmentPolicy::Unadjusted {
+            unadjust_prices(&mut prices, &boundaries, self.adjustment.includes_dividends());
+        }
+        if policy != AdjustmentPolicy::Unadjusted {
+            adjust_prices(&mut prices, &boundaries, policy.includes_dividends())?;
+        }
+        
+        let mut series = self.clone();
+        series.prices = prices;
+        series.adjustment = policy;
+        Ok(series)
+    }
+    
+    /// Group the corporate actions by the bar they precede
+    ///
+    /// The key is the index of the first bar on or after the ex-date. Actions
+    /// before the first bar or after the last one do not affect the series.
+    fn boundaries(&self) -> BTreeMap<usize, Boundary> {
+        let mut boundaries: BTreeMap<usize, Boundary> = BTreeMap::new();
+        
+        for action in &self.corporate_actions {
+            let index = self.prices.partition_point(|p| p.timestamp < action.ex_date());
+            if index == 0 || index == self.prices.len() {
+                continue;
+            }
+            
+            let boundary = boundaries.entry(index).or_insert(Boundary { split_ratio: 1.0, dividends: 0.0 });
+            match action {
+                CorporateAction::Split { from, to, .. } => boundary.split_ratio *= to / from,
+                CorporateAction::Dividend { amount, .. } => boundary.dividends += amount,
+            }
+        }
+        
+        boundaries
+    }
+}
+
+/// Back-adjust raw prices in place
+fn adjust_prices(prices: &mut [Price], boundaries: &BTreeMap<usize, Boundary>, dividends: bool) -> Result<()> {
+    let mut price_factor = 1.0;
+    let mut volume_factor = 1.0;
+    
+    for i in (0..prices.len()).rev() {
+        if let Some(boundary) = boundaries.get(&(i + 1)) {
+            price_factor /= boundary.split_ratio;
+            volume_factor *= boundary.split_ratio;
+            
+            if dividends && boundary.dividends > 0.0 {
+                // prices[i] is still raw here; express its close on the ex-date share basis
+                let close = prices[i].close / boundary.split_ratio;
+                if boundary.dividends >= close {
+                    return Err(Error::ValidationError(format!(
+                        "Dividend {} is not less than the previous close {}", boundary.dividends, close
+                    )));
+                }
+                price_factor *= 1.0 - boundary.dividends / close;
+            }
+        }
+        
+        scale(&mut prices[i], price_factor, volume_factor);
+    }
+    
+    Ok(())
+}
+
+/// Restore raw prices from back-adjusted prices in place
+fn unadjust_prices(prices: &mut [Price], boundaries: &BTreeMap<usize, Boundary>, dividends: bool) {
+    let mut price_factor = 1.0;
+    let mut volume_factor = 1.0;
+    
+    for i in (0..prices.len()).rev() {
+        if let Some(boundary) = boundaries.get(&(i + 1)) {
+            price_factor /= boundary.split_ratio;
+            volume_factor *= boundary.split_ratio;
+            
+            if dividends && boundary.dividends > 0.0 {
+                // adjusted close = factor * (raw close - dividends), solved for the raw close
+                let close = prices[i].close / price_factor + boundary.dividends;
+                price_factor *= 1.0 - boundary.dividends / close;
+            }
+        }
+        
+        scale(&mut prices[i], 1.0 / price_factor, 1.0 / volume_factor);
+    }
+}
+
+/// Scale the prices and volume of a bar
+fn scale(price: &mut Price, price_factor: f64, volume_factor: f64) {
+    price.open *= price_factor;
+    price.high *= price_factor;
+    price.low *= price_factor;
+    price.close *= price_factor;
+    price.volume = (price.volume as f64 * volume_factor).round() as u64;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use crate::data::TimeInterval;
+    
+    fn series(closes: &[f64]) -> TimeSeries<Price> {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let prices = closes.iter().enumerate()
+            .map(|(i, &close)| Price::new(close, close, close, close, 1000, start + Duration::days(i as i64)))
+            .collect();
+        
+        TimeSeries::new(
+            "TEST".to_string(),
+            TimeInterval::Daily,
+            prices,
+            start,
+            start + Duration::days(closes.len() as i64),
+            "UTC".to_string(),
+            "USD".to_string(),
+        )
+    }
+    
+    fn day(n: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap() + Duration::days(n)
+    }
+    
+    fn closes(series: &TimeSeries<Price>) -> Vec<f64> {
+        series.prices.iter().map(|p| (p.close * 1e6).round() / 1e6).collect()
+    }
+    
+    #[test]
+    fn test_split_adjustment() {
+        let raw = series(&[400.0, 404.0, 101.0, 102.0])
+            .with_corporate_actions(vec![CorporateAction::split(day(2), 1.0, 4.0)]);
+        
+        let adjusted = raw.adjust(AdjustmentPolicy::Splits).unwrap();
+        assert_eq!(closes(&adjusted), vec![100.0, 101.0, 101.0, 102.0]);
+        assert_eq!(adjusted.prices[0].volume, 4000);
+        assert_eq!(adjusted.prices[3].volume, 1000);
+        assert_eq!(adjusted.adjustment, AdjustmentPolicy::Splits);
+        
+        // Dividends are ignored without a dividend policy
+        let restored = adjusted.adjust(AdjustmentPolicy::Unadjusted).unwrap();
+        assert_eq!(closes(&restored), closes(&raw));
+        assert_eq!(restored.prices[0].volume, 1000);
+    }
+    
+    #[test]
+    fn test_dividend_adjustment_round_trip() {
+        let raw = series(&[100.0, 98.0, 50.0, 51.0, 52.0])
+            .with_corporate_actions(vec![
+                CorporateAction::split(day(2), 1.0, 2.0),
+                CorporateAction::dividend(day(4), 1.04),
+                CorporateAction::dividend(day(10), 5.0),
+            ]);
+        
+        let total_return = raw.adjust(AdjustmentPolicy::SplitsAndDividends).unwrap();
+        let dividend_factor = 1.0 - 1.04 / 51.0;
+        assert_eq!(total_return.prices[4].close, 52.0);
+        assert!((total_return.prices[3].close - 51.0 * dividend_factor).abs() < 1e-9);
+        assert!((total_return.prices[0].close - 50.0 * dividend_factor).abs() < 1e-9);
+        
+        let restored = total_return.adjust(AdjustmentPolicy::Unadjusted).unwrap();
+        assert_eq!(closes(&restored), closes(&raw));
+        
+        let splits = total_return.adjust(AdjustmentPolicy::Splits).unwrap();
+        assert_eq!(closes(&splits), vec![50.0, 49.0, 50.0, 51.0, 52.0]);
+    }
+    
+    #[test]
+    fn test_invalid_actions() {
+        let raw = series(&[10.0, 10.0])
+            .with_corporate_actions(vec![CorporateAction::dividend(day(1), 12.0)]);
+        assert!(raw.adjust(AdjustmentPolicy::SplitsAndDividends).is_err());
+        
+        let raw = series(&[10.0, 10.0])
+            .with_corporate_actions(vec![CorporateAction::split(day(1), 0.0, 2.0)]);
+        assert!(raw.adjust(AdjustmentPolicy::Splits).is_err());
+    }
+}
@@ -3,6 +3,8 @@ use chrono::{DateTime, Utc, Duration};
 use serde::{Serialize, Deserialize};
 use rust_decimal::Decimal;
 
+use crate::corporate_actions::{AdjustmentPolicy, CorporateAction};
+
 /// A single price data point
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Price {
@@ -95,6 +97,14 @@ pub struct TimeSeries<T> {
     
     /// Currency of the price data
     pub currency: String,
+    
+    /// Corporate actions affecting the series
+    #[serde(default)]
+    pub corporate_actions: Vec<CorporateAction>,
+    
+    /// Corporate actions the prices are adjusted for
+    #[serde(default)]
+    pub adjustment: AdjustmentPolicy,
 }
 
 impl<T> TimeSeries<T> {
@@ -116,6 +126,8 @@ impl<T> TimeSeries<T> {
             end_time,
             timezone,
             currency,
+            corporate_actions: Vec::new(),
+            adjustment: AdjustmentPolicy::Unadjusted,
         }
     }
     
@@ -285,6 +297,66 @@ impl TimeSeries<Price> {
     }
 }
 
+/// A single observation of an economic series
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Observation {
+    /// Start of the period the observation covers
+    pub timestamp: DateTime<Utc>,
+    
+    /// Observed value
+    pub value: f64,
+}
+
+impl Observation {
+    /// Create a new observation
+    pub fn new(timestamp: DateTime<Utc>, value: f64) -> Self {
+        Self { timestamp, value }
+    }
+}
+
+impl TimeSeries<Observation> {
+    /// Get the most recent observation
+    pub fn latest(&self) -> Option<&Observation> {
+        self.prices.last()
+    }
+    
+    /// Get the observed values in order
+    pub fn values(&self) -> Vec<f64> {
+        self.prices.iter().map(|o| o.value).collect()
+    }
+    
+    /// Get the observation in effect at a point in time
+    ///
+    /// Macro data is published at a lower frequency than prices, so this
+    /// returns the latest observation starting at or before `timestamp`.
+    pub fn value_at(&self, timestamp: DateTime<Utc>) -> Option<f64> {
+        let index = self.prices.partition_point(|o| o.timestamp <= timestamp);
+        index.checked_sub(1).map(|i| self.prices[i].value)
+    }
+}
+
+/// Common economic indicators
+///
+/// Each economic data provider maps these to its own series identifiers, so
+/// strategies can ask for e.g. the policy rate without knowing provider IDs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EconomicIndicator {
+    /// Central bank policy rate
+    PolicyRate,
+    
+    /// Consumer price index
+    Cpi,
+    
+    /// Unemployment rate
+    Unemployment,
+    
+    /// 10-year government bond yield
+    TenYearYield,
+    
+    /// Real gross domestic product
+    Gdp,
+}
+
 /// Realtime market quote
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Quote {
@@ -639,6 +711,28 @@ mod tests {
         assert_eq!(sma3[2], (102.0 + 103.0 + 104.0) / 3.0);
     }
     
+    #[test]
+    fn test_observation_value_at() {
+        let series = TimeSeries::new(
+            "UNRATE".to_string(),
+            TimeInterval::Monthly,
+            vec![
+                Observation::new(Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap(), 3.4),
+                Observation::new(Utc.with_ymd_and_hms(2023, 2, 1, 0, 0, 0).unwrap(), 3.6),
+            ],
+            Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2023, 2, 1, 0, 0, 0).unwrap(),
+            "UTC".to_string(),
+            "Percent".to_string(),
+        );
+        
+        assert_eq!(series.value_at(Utc.with_ymd_and_hms(2022, 12, 31, 0, 0, 0).unwrap()), None);
+        assert_eq!(series.value_at(Utc.with_ymd_and_hms(2023, 1, 20, 0, 0, 0).unwrap()), Some(3.4));
+        assert_eq!(series.value_at(Utc.with_ymd_and_hms(2023, 3, 1, 0, 0, 0).unwrap()), Some(3.6));
+        assert_eq!(series.latest().map(|o| o.value), Some(3.6));
+        assert_eq!(series.values(), vec![3.4, 3.6]);
+    }
+    
     #[test]
     fn test_time_interval_to_duration() {
         assert_eq!(TimeInterval::Minute1.to_duration(), Duration::minutes(1));
@@ -6,8 +6,10 @@
 //! ## Features
 //!
 //! - **Market Data**: Access financial data from multiple providers (Yahoo Finance, Alpha Vantage, etc.)
+//! - **Economic Data**: Macro series such as rates, CPI, and unemployment from FRED and the ECB
 //! - **Trading**: Integration with various trading platforms (Binance, FTX, TradingView, etc.)
 //! - **Analysis**: Technical analysis indicators, charting, and statistical tools
+//! - **Charts**: Candlestick charts with indicator overlays and volume, rendered to PNG or SVG
 //! - **Portfolio**: Portfolio tracking, performance analysis, and risk assessment
 //! - **Stealth**: Optional integration with llama-moonlight-stealth for avoiding rate limits and detection
 //! - **Anonymity**: Optional integration with llama-moonlight-tor for enhanced privacy
@@ -45,15 +47,18 @@
 #![warn(missing_docs)]
 
 use thiserror::Error;
+use serde::{Serialize, Deserialize};
 use std::fmt;
 use std::str::FromStr;
 
 // Core modules
 pub mod client;
 pub mod config;
+pub mod corporate_actions;
 pub mod data;
 pub mod market;
 pub mod provider;
+pub mod scheduler;
 pub mod portfolio;
 pub mod trading;
 pub mod analysis;
@@ -75,6 +80,10 @@ pub mod stealth;
 #[cfg(feature = "tor")]
 pub mod tor;
 
+// Chart rendering
+#[cfg(feature = "visualization")]
+pub mod chart;
+
 // Provider-specific modules
 pub mod providers {
     //! Financial data provider implementations
@@ -102,13 +111,24 @@ pub mod providers {
     /// TradingView API provider
     #[cfg(feature = "tradingview")]
     pub mod tradingview;
+    
+    /// FRED economic data provider
+    #[cfg(feature = "fred")]
+    pub mod fred;
+    
+    /// ECB SDMX economic data provider
+    #[cfg(feature = "ecb")]
+    pub mod ecb;
 }
 
 // Re-exports for convenience
 pub use crate::client::FinanceClient;
 pub use crate::config::ClientConfig;
-pub use crate::data::{Price, TimeSeries, MarketData, Quote};
-pub use crate::provider::{Provider, DataProvider, TradingProvider};
+pub use crate::data::{Price, TimeSeries, MarketData, Quote, Observation, EconomicIndicator};
+pub use crate::corporate_actions::{AdjustmentPolicy, CorporateAction};
+pub use crate::scheduler::{RateLimit, RequestKind, RequestScheduler};
+pub use crate::provider::{Provider, DataProvider, TradingProvider, EconomicDataProvider};
+pub use crate::portfolio::{Portfolio, Transaction};
 
 #[cfg(feature = "yahoo")]
 pub use crate::providers::yahoo::YahooProvider;
@@ -116,6 +136,12 @@ pub use crate::providers::yahoo::YahooProvider;
 #[cfg(feature = "alphavantage")]
 pub use crate::providers::alpha_vantage::AlphaVantageProvider;
 
+#[cfg(feature = "fred")]
+pub use crate::providers::fred::FredProvider;
+
+#[cfg(feature = "ecb")]
+pub use crate::providers::ecb::EcbProvider;
+
 /// Result type used throughout the crate
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -197,13 +223,18 @@ pub enum Error {
     #[error("Portfolio error: {0}")]
     PortfolioError(String),
     
+    /// Chart rendering errors
+    #[cfg(feature = "visualization")]
+    #[error("Chart error: {0}")]
+    ChartError(String),
+    
     /// Other errors
     #[error("Other error: {0}")]
     Other(String),
 }
 
 /// Asset class types
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum AssetClass {
     /// Stocks/Equities
     Stock,
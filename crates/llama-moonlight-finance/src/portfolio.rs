@@ -0,0 +1,240 @@
+//! Portfolio tracking
+//!
+//! A [`Portfolio`] records transactions and keeps the resulting holdings, each
+//! classified by asset class and, optionally, sector. The [`risk`] module
+//! computes risk metrics over a portfolio from historical prices.
+
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+
+use crate::AssetClass;
+
+pub mod risk;
+
+pub use risk::{CorrelationMatrix, Exposure, RiskAnalyzer, RiskReport};
+
+/// Type of a portfolio transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionType {
+    /// Buy shares
+    Buy,
+    
+    /// Sell shares
+    Sell,
+    
+    /// Cash dividend received
+    Dividend,
+    
+    /// Cash deposited
+    Deposit,
+    
+    /// Cash withdrawn
+    Withdrawal,
+}
+
+/// A portfolio transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transaction {
+    /// Transaction type
+    pub kind: TransactionType,
+    
+    /// Symbol, for trades and dividends
+    pub symbol: Option<String>,
+    
+    /// Number of shares, for trades
+    pub quantity: f64,
+    
+    /// Price per share for trades, or the cash amount otherwise
+    pub price: f64,
+    
+    /// Fees paid
+    #[serde(default)]
+    pub fee: f64,
+    
+    /// Time of the transaction
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Transaction {
+    /// Create a buy transaction
+    pub fn buy(symbol: &str, quantity: f64, price: f64, timestamp: DateTime<Utc>) -> Self {
+        Self::trade(TransactionType::Buy, symbol, quantity, price, timestamp)
+    }
+    
+    /// Create a sell transaction
+    pub fn sell(symbol: &str, quantity: f64, price: f64, timestamp: DateTime<Utc>) -> Self {
+        Self::trade(TransactionType::Sell, symbol, quantity, price, timestamp)
+    }
+    
+    /// Create a cash transaction
+    pub fn cash(kind: TransactionType, amount: f64, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            kind,
+            symbol: None,
+            quantity: 0.0,
+            price: amount,
+            fee: 0.0,
+            timestamp,
+        }
+    }
+    
+    /// Set the fee
+    pub fn with_fee(mut self, fee: f64) -> Self {
+        self.fee = fee;
+        self
+    }
+    
+    /// Create a trade transaction
+    fn trade(kind: TransactionType, symbol: &str, quantity: f64, price: f64, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            kind,
+            symbol: Some(symbol.to_string()),
+            quantity,
+            price,
+            fee: 0.0,
+            timestamp,
+        }
+    }
+}
+
+/// A position held in a portfolio
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Holding {
+    /// Symbol
+    pub symbol: String,
+    
+    /// Number of shares
+    pub quantity: f64,
+    
+    /// Total cost of the shares, including fees
+    pub cost_basis: f64,
+    
+    /// Asset class
+    pub asset_class: AssetClass,
+    
+    /// Sector, if known
+    pub sector: Option<String>,
+}
+
+impl Holding {
+    /// Get the average cost per share
+    pub fn average_cost(&self) -> f64 {
+        if self.quantity == 0.0 {
+            0.0
+        } else {
+            self.cost_basis / self.quantity
+        }
+    }
+}
+
+/// A portfolio of holdings and cash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Portfolio {
+    /// Portfolio name
+    pub name: String,
+    
+    /// Currency of the cash and cost basis
+    pub currency: String,
+    
+    /// Cash balance
+    pub cash: f64,
+    
+    /// Holdings by symbol
+    pub holdings: HashMap<String, Holding>,
+    
+    /// Transactions in the order they were added
+    pub transactions: Vec<Transaction>,
+}
+
+impl Portfolio {
+    /// Create an empty portfolio
+    pub fn new(name: &str, currency: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            currency: currency.to_string(),
+            cash: 0.0,
+            holdings: HashMap::new(),
+            transactions: Vec::new(),
+        }
+    }
+    
+    /// Set the asset class and sector of a symbol
+    ///
+    /// The classification applies to the current holding and is kept if the
+    /// position is closed and reopened.
+    pub fn classify(&mut self, symbol: &str, asset_class: AssetClass, sector: Option<&str>) {
+        let holding = self.holdings.entry(symbol.to_string()).or_insert_with(|| empty_holding(symbol));
+        holding.asset_class = asset_class;
+        holding.sector = sector.map(str::to_string);
+    }
+    
+    /// Record a transaction and update the holdings and cash
+    pub fn add_transaction(&mut self, transaction: Transaction) {
+        match (transaction.kind, &transaction.symbol) {
+            (TransactionType::Buy, Some(symbol)) => {
+                let holding = self.holdings.entry(symbol.clone()).or_insert_with(|| empty_holding(symbol));
+                holding.quantity += transaction.quantity;
+                holding.cost_basis += transaction.quantity * transaction.price + transaction.fee;
+                self.cash -= transaction.quantity * transaction.price + transaction.fee;
+            }
+            (TransactionType::Sell, Some(symbol)) => {
+                if let Some(holding) = self.holdings.get_mut(symbol) {
+                    let sold = transaction.quantity.min(holding.quantity);
+                    holding.cost_basis -= holding.average_cost() * sold;
+                    holding.quantity -= sold;
+                }
+                self.cash += transaction.quantity * transaction.price - transaction.fee;
+            }
+            (TransactionType::Withdrawal, _) => self.cash -= transaction.price + transaction.fee,
+            _ => self.cash += transaction.price - transaction.fee,
+        }
+        
+        self.transactions.push(transaction);
+    }
+    
+    /// Get the holdings with shares
+    pub fn open_holdings(&self) -> impl Iterator<Item = &Holding> {
+        self.holdings.values().filter(|holding| holding.quantity > 0.0)
+    }
+    
+    /// Get the total cost basis of the holdings
+    pub fn cost_basis(&self) -> f64 {
+        self.open_holdings().map(|holding| holding.cost_basis).sum()
+    }
+}
+
+/// Holding of a symbol without shares
+fn empty_holding(symbol: &str) -> Holding {
+    Holding {
+        symbol: symbol.to_string(),
+        quantity: 0.0,
+        cost_basis: 0.0,
+        asset_class: AssetClass::Stock,
+        sector: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_transactions() {
+        let now = Utc::now();
+        let mut portfolio = Portfolio::new("test", "USD");
+        portfolio.add_transaction(Transaction::cash(TransactionType::Deposit, 10_000.0, now));
+        portfolio.add_transaction(Transaction::buy("AAPL", 10.0, 150.0, now).with_fee(1.0));
+        portfolio.add_transaction(Transaction::buy("AAPL", 10.0, 170.0, now).with_fee(1.0));
+        portfolio.add_transaction(Transaction::sell("AAPL", 5.0, 180.0, now));
+        
+        let holding = &portfolio.holdings["AAPL"];
+        assert_eq!(holding.quantity, 15.0);
+        assert!((holding.average_cost() - 160.1).abs() < 1e-9);
+        assert!((portfolio.cash - (10_000.0 - 3_202.0 + 900.0)).abs() < 1e-9);
+        
+        portfolio.classify("AAPL", AssetClass::Stock, Some("Technology"));
+        assert_eq!(portfolio.holdings["AAPL"].sector.as_deref(), Some("Technology"));
+    }
+}
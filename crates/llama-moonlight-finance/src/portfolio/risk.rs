@@ -0,0 +1,467 @@
+//! Portfolio risk metrics
+//!
+//! The functions in this module work on series of periodic simple returns:
+//! beta against a benchmark, historical and parametric (variance-covariance)
+//! value-at-risk, and correlations. [`RiskAnalyzer`] fetches historical prices
+//! for the holdings of a [`Portfolio`] and a benchmark, caches them, and
+//! combines these metrics with exposure breakdowns into a [`RiskReport`].
+//!
+//! Value-at-risk is reported as a positive fraction of the portfolio value
+//! that is not expected to be lost over one period with the given confidence.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use tokio::sync::Mutex;
+
+use crate::{AssetClass, Error, Result};
+use crate::client::FinanceClient;
+use crate::data::{TimeInterval, TimeRange};
+use crate::portfolio::Portfolio;
+
+/// Compute simple returns from a series of prices
+pub fn returns(prices: &[f64]) -> Vec<f64> {
+    prices
+        .windows(2)
+        .map(|window| window[1] / window[0] - 1.0)
+        .collect()
+}
+
+/// Compute the beta of an asset against a benchmark
+///
+/// Returns `None` if the series differ in length, have fewer than two
+/// returns, or the benchmark does not vary.
+pub fn beta(asset: &[f64], benchmark: &[f64]) -> Option<f64> {
+    let variance = covariance(benchmark, benchmark)?;
+    if variance == 0.0 {
+        return None;
+    }
+    
+    Some(covariance(asset, benchmark)? / variance)
+}
+
+/// Compute the Pearson correlation of two return series
+pub fn correlation(a: &[f64], b: &[f64]) -> Option<f64> {
+    let deviation = (covariance(a, a)? * covariance(b, b)?).sqrt();
+    if deviation == 0.0 {
+        return None;
+    }
+    
+    Some(covariance(a, b)? / deviation)
+}
+
+/// Compute historical value-at-risk from the empirical return distribution
+pub fn historical_var(returns: &[f64], confidence: f64) -> Option<f64> {
+    if returns.is_empty() || !(0.0..1.0).contains(&confidence) {
+        return None;
+    }
+    
+    let mut sorted = returns.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    
+    // Number of returns in the tail, tolerating rounding in the product
+    let tail = ((1.0 - confidence) * sorted.len() as f64 + 1e-9).floor() as usize;
+    let index = tail.clamp(1, sorted.len()) - 1;
+    Some((-sorted[index]).max(0.0))
+}
+
+/// Compute parametric value-at-risk assuming normally distributed returns
+pub fn parametric_var(returns: &[f64], confidence: f64) -> Option<f64> {
+    if !(0.0..1.0).contains(&confidence) {
+        return None;
+    }
+    
+    let deviation = covariance(returns, returns)?.sqrt();
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    
+    Some((-(mean + normal_quantile(1.0 - confidence) * deviation)).max(0.0))
+}
+
+/// Sample covariance of two series of equal length
+fn covariance(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.len() != b.len() || a.len() < 2 {
+        return None;
+    }
+    
+    let n = a.len() as f64;
+    let mean_a = a.iter().sum::<f64>() / n;
+    let mean_b = b.iter().sum::<f64>() / n;
+    
+    Some(a.iter().zip(b).map(|(x, y)| (x - mean_a) * (y - mean_b)).sum::<f64>() / (n - 1.0))
+}
+
+/// Quantile of the standard normal distribution (Acklam's approximation)
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [-3.969683028665376e1, 2.209460984245205e2, -2.759285104469687e2, 1.38357751867269e2, -3.066479806614716e1, 2.506628277459239];
+    const B: [f64; 5] = [-5.447609879822406e1, 1.615858368580409e2, -1.556989798598866e2, 6.680131188771972e1, -1.328068155288572e1];
+    const C: [f64; 6] = [-7.784894002430293e-3, -3.223964580411365e-1, -2.400758277161838, -2.549732539343734, 4.374664141464968, 2.938163982698783];
+    const D: [f64; 4] = [7.784695709041462e-3, 3.224671290700398e-1, 2.445134137142996, 3.754408661907416];
+    const LOW: f64 = 0.02425;
+    
+    if p < LOW {
+        let q = (-2.0 * p.ln()).sqrt();
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    } else if p <= 1.0 - LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        -normal_quantile(1.0 - p)
+    }
+}
+
+/// Pairwise correlations of return series
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorrelationMatrix {
+    /// Symbols, in row and column order
+    pub symbols: Vec<String>,
+    
+    /// Correlations; `None` where a series does not vary
+    pub values: Vec<Vec<Option<f64>>>,
+}
+
+impl CorrelationMatrix {
+    /// Compute the correlation matrix of return series by symbol
+    pub fn from_returns(returns: &BTreeMap<String, Vec<f64>>) -> Self {
+        let symbols: Vec<String> = returns.keys().cloned().collect();
+        let values = returns
+            .values()
+            .map(|a| returns.values().map(|b| correlation(a, b)).collect())
+            .collect();
+        
+        Self { symbols, values }
+    }
+    
+    /// Get the correlation of two symbols
+    pub fn get(&self, a: &str, b: &str) -> Option<f64> {
+        let i = self.symbols.iter().position(|symbol| symbol == a)?;
+        let j = self.symbols.iter().position(|symbol| symbol == b)?;
+        self.values[i][j]
+    }
+}
+
+/// Share of the portfolio value by asset class and sector
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Exposure {
+    /// Weight by asset class
+    pub by_asset_class: HashMap<AssetClass, f64>,
+    
+    /// Weight by sector; holdings without a sector are under `"Unclassified"`
+    pub by_sector: HashMap<String, f64>,
+    
+    /// Weight of cash
+    pub cash: f64,
+}
+
+impl Exposure {
+    /// Compute the exposure of a portfolio from the market values of its holdings
+    pub fn from_values(portfolio: &Portfolio, values: &HashMap<String, f64>) -> Self {
+        let invested: f64 = values.values().sum();
+        let total = invested + portfolio.cash.max(0.0);
+        
+        let mut exposure = Exposure::default();
+        if total <= 0.0 {
+            return exposure;
+        }
+        
+        for holding in portfolio.open_holdings() {
+            let weight = values.get(&holding.symbol).copied().unwrap_or(0.0) / total;
+            *exposure.by_asset_class.entry(holding.asset_class).or_default() += weight;
+            
+            let sector = holding.sector.clone().unwrap_or_else(|| "Unclassified".to_string());
+            *exposure.by_sector.entry(sector).or_default() += weight;
+        }
+        exposure.cash = portfolio.cash.max(0.0) / total;
+        
+        exposure
+    }
+}
+
+/// Risk metrics of a portfolio
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskReport {
+    /// Benchmark symbol
+    pub benchmark: String,
+    
+    /// Market value of the holdings and cash
+    pub total_value: f64,
+    
+    /// Number of returns the metrics are computed from
+    pub observations: usize,
+    
+    /// Beta of the invested part of the portfolio
+    pub beta: Option<f64>,
+    
+    /// Beta of each holding
+    pub holding_betas: HashMap<String, f64>,
+    
+    /// Confidence level of the value-at-risk
+    pub confidence: f64,
+    
+    /// Historical value-at-risk of the invested part, as a fraction
+    pub historical_var: Option<f64>,
+    
+    /// Parametric value-at-risk of the invested part, as a fraction
+    pub parametric_var: Option<f64>,
+    
+    /// Exposure by asset class and sector
+    pub exposure: Exposure,
+    
+    /// Correlations of the holdings' returns
+    pub correlations: CorrelationMatrix,
+}
+
+impl RiskReport {
+    /// Get the historical value-at-risk in the portfolio currency
+    pub fn historical_var_amount(&self) -> Option<f64> {
+        self.historical_var.map(|var| var * self.invested_value())
+    }
+    
+    /// Get the parametric value-at-risk in the portfolio currency
+    pub fn parametric_var_amount(&self) -> Option<f64> {
+        self.parametric_var.map(|var| var * self.invested_value())
+    }
+    
+    fn invested_value(&self) -> f64 {
+        self.total_value * (1.0 - self.exposure.cash)
+    }
+}
+
+/// Cached closing prices of a symbol
+struct CachedCloses {
+    /// Time the prices were fetched
+    fetched_at: Instant,
+    
+    /// Closing price by date
+    closes: BTreeMap<NaiveDate, f64>,
+}
+
+/// Computes portfolio risk from provider historical data
+pub struct RiskAnalyzer<'a> {
+    /// Finance client
+    client: &'a FinanceClient,
+    
+    /// Benchmark symbol
+    benchmark: String,
+    
+    /// Interval of the returns
+    interval: TimeInterval,
+    
+    /// Range of the history
+    range: TimeRange,
+    
+    /// Value-at-risk confidence level
+    confidence: f64,
+    
+    /// How long fetched prices are reused
+    cache_ttl: Duration,
+    
+    /// Closing prices by symbol
+    cache: Mutex<HashMap<String, CachedCloses>>,
+}
+
+impl<'a> RiskAnalyzer<'a> {
+    /// Create a new risk analyzer with daily returns over one year against SPY
+    pub fn new(client: &'a FinanceClient) -> Self {
+        Self {
+            client,
+            benchmark: "SPY".to_string(),
+            interval: TimeInterval::Daily,
+            range: TimeRange::Year(1),
+            confidence: 0.95,
+            cache_ttl: Duration::from_secs(3600),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    /// Set the benchmark symbol
+    pub fn with_benchmark(mut self, benchmark: &str) -> Self {
+        self.benchmark = benchmark.to_string();
+        self
+    }
+    
+    /// Set the interval of the returns
+    pub fn with_interval(mut self, interval: TimeInterval) -> Self {
+        self.interval = interval;
+        self
+    }
+    
+    /// Set the range of the history
+    pub fn with_range(mut self, range: TimeRange) -> Self {
+        self.range = range;
+        self
+    }
+    
+    /// Set the value-at-risk confidence level, e.g. `0.99`
+    pub fn with_confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence;
+        self
+    }
+    
+    /// Set how long fetched prices are reused
+    pub fn with_cache_ttl(mut self, cache_ttl: Duration) -> Self {
+        self.cache_ttl = cache_ttl;
+        self
+    }
+    
+    /// Drop all cached prices
+    pub async fn clear_cache(&self) {
+        self.cache.lock().await.clear();
+    }
+    
+    /// Compute the risk report of a portfolio
+    pub async fn report(&self, portfolio: &Portfolio) -> Result<RiskReport> {
+        if !(0.0..1.0).contains(&self.confidence) {
+            return Err(Error::AnalysisError(format!("Invalid confidence level: {}", self.confidence)));
+        }
+        
+        let symbols: Vec<String> = portfolio.open_holdings().map(|holding| holding.symbol.clone()).collect();
+        if symbols.is_empty() {
+            return Err(Error::PortfolioError("Portfolio has no holdings".to_string()));
+        }
+        
+        let benchmark = self.closes(&self.benchmark).await?;
+        let mut closes = BTreeMap::new();
+        for symbol in &symbols {
+            closes.insert(symbol.clone(), self.closes(symbol).await?);
+        }
+        
+        // Only dates with a close for every series are used
+        let dates: Vec<NaiveDate> = benchmark
+            .keys()
+            .filter(|date| closes.values().all(|series| series.contains_key(*date)))
+            .copied()
+            .collect();
+        if dates.len() < 3 {
+            return Err(Error::AnalysisError("Not enough overlapping price history".to_string()));
+        }
+        
+        let aligned = |series: &BTreeMap<NaiveDate, f64>| -> Vec<f64> {
+            dates.iter().map(|date| series[date]).collect()
+        };
+        
+        let benchmark_returns = returns(&aligned(&benchmark));
+        let holding_returns: BTreeMap<String, Vec<f64>> = closes
+            .iter()
+            .map(|(symbol, series)| (symbol.clone(), returns(&aligned(series))))
+            .collect();
+        
+        let values: HashMap<String, f64> = portfolio
+            .open_holdings()
+            .map(|holding| (holding.symbol.clone(), holding.quantity * closes[&holding.symbol][dates.last().unwrap()]))
+            .collect();
+        let invested: f64 = values.values().sum();
+        
+        let mut portfolio_returns = vec![0.0; benchmark_returns.len()];
+        if invested > 0.0 {
+            for (symbol, series) in &holding_returns {
+                let weight = values[symbol] / invested;
+                for (total, value) in portfolio_returns.iter_mut().zip(series) {
+                    *total += weight * value;
+                }
+            }
+        }
+        
+        let holding_betas = holding_returns
+            .iter()
+            .filter_map(|(symbol, series)| Some((symbol.clone(), beta(series, &benchmark_returns)?)))
+            .collect();
+        
+        Ok(RiskReport {
+            benchmark: self.benchmark.clone(),
+            total_value: invested + portfolio.cash.max(0.0),
+            observations: portfolio_returns.len(),
+            beta: beta(&portfolio_returns, &benchmark_returns),
+            holding_betas,
+            confidence: self.confidence,
+            historical_var: historical_var(&portfolio_returns, self.confidence),
+            parametric_var: parametric_var(&portfolio_returns, self.confidence),
+            exposure: Exposure::from_values(portfolio, &values),
+            correlations: CorrelationMatrix::from_returns(&holding_returns),
+        })
+    }
+    
+    /// Get the closing prices of a symbol, from the cache if fresh
+    async fn closes(&self, symbol: &str) -> Result<BTreeMap<NaiveDate, f64>> {
+        if let Some(cached) = self.cache.lock().await.get(symbol) {
+            if cached.fetched_at.elapsed() < self.cache_ttl {
+                return Ok(cached.closes.clone());
+            }
+        }
+        
+        let series = self.client
+            .historical_prices(symbol)
+            .interval(self.interval)
+            .range(self.range)
+            .fetch()
+            .await?;
+        
+        let closes: BTreeMap<NaiveDate, f64> = series.prices
+            .iter()
+            .filter(|price| price.close > 0.0)
+            .map(|price| (price.timestamp.date_naive(), price.close))
+            .collect();
+        
+        self.cache.lock().await.insert(symbol.to_string(), CachedCloses {
+            fetched_at: Instant::now(),
+            closes: closes.clone(),
+        });
+        
+        Ok(closes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_beta_and_correlation() {
+        let benchmark = [0.01, -0.02, 0.015, 0.005, -0.01];
+        let levered: Vec<f64> = benchmark.iter().map(|r| r * 2.0).collect();
+        let inverse: Vec<f64> = benchmark.iter().map(|r| -r).collect();
+        
+        assert!((beta(&levered, &benchmark).unwrap() - 2.0).abs() < 1e-12);
+        assert!((correlation(&inverse, &benchmark).unwrap() + 1.0).abs() < 1e-12);
+        assert_eq!(beta(&levered, &[0.0; 5]), None);
+        
+        let mut matrix_input = BTreeMap::new();
+        matrix_input.insert("A".to_string(), benchmark.to_vec());
+        matrix_input.insert("B".to_string(), inverse);
+        let matrix = CorrelationMatrix::from_returns(&matrix_input);
+        assert!((matrix.get("A", "B").unwrap() + 1.0).abs() < 1e-12);
+        assert!((matrix.get("A", "A").unwrap() - 1.0).abs() < 1e-12);
+    }
+    
+    #[test]
+    fn test_value_at_risk() {
+        let returns: Vec<f64> = (1..=100).map(|i| (i as f64 - 50.0) / 1000.0).collect();
+        assert!((historical_var(&returns, 0.95).unwrap() - 0.045).abs() < 1e-12);
+        
+        assert!((normal_quantile(0.05) + 1.644854).abs() < 1e-6);
+        assert!((normal_quantile(0.99) - 2.326348).abs() < 1e-6);
+        
+        let var = parametric_var(&returns, 0.95).unwrap();
+        assert!(var > 0.04 && var < 0.05);
+        assert_eq!(historical_var(&[], 0.95), None);
+    }
+    
+    #[test]
+    fn test_exposure() {
+        let mut portfolio = Portfolio::new("test", "USD");
+        portfolio.cash = 200.0;
+        portfolio.add_transaction(crate::portfolio::Transaction::buy("AAPL", 1.0, 100.0, chrono::Utc::now()));
+        portfolio.add_transaction(crate::portfolio::Transaction::buy("TLT", 1.0, 100.0, chrono::Utc::now()));
+        portfolio.classify("AAPL", AssetClass::Stock, Some("Technology"));
+        portfolio.classify("TLT", AssetClass::Bond, None);
+        
+        let values = HashMap::from([("AAPL".to_string(), 300.0), ("TLT".to_string(), 100.0)]);
+        let exposure = Exposure::from_values(&portfolio, &values);
+        assert_eq!(exposure.by_asset_class[&AssetClass::Stock], 0.75);
+        assert_eq!(exposure.by_sector["Unclassified"], 0.25);
+        assert_eq!(exposure.cash, 0.0);
+    }
+}
@@ -3,7 +3,9 @@ use std::sync::Arc;
 use async_trait::async_trait;
 
 use crate::{Result, AssetClass};
-use crate::data::{TimeInterval, TimeRange, TimeSeries, Price, Quote, MarketData};
+use crate::data::{TimeInterval, TimeRange, TimeSeries, Price, Quote, MarketData, Observation, EconomicIndicator};
+use crate::corporate_actions::CorporateAction;
+use crate::scheduler::{self, RateLimit, RequestKind, RequestScheduler};
 use crate::market::{OrderBook, TradeHistory};
 use crate::trading::{Order, OrderStatus, Position, TradeExecution};
 
@@ -27,6 +29,28 @@ pub trait Provider {
         self.capabilities().contains(&capability)
     }
     
+    /// Get the provider's documented rate limits
+    ///
+    /// Defaults to the known limits of built-in providers, matched by name.
+    fn rate_limits(&self) -> Vec<RateLimit> {
+        scheduler::known_limits(self.name())
+    }
+    
+    /// Get the weight a request counts against the rate limits
+    fn request_weight(&self, request: RequestKind) -> u32 {
+        match request {
+            RequestKind::Quotes(count) => count.max(1) as u32,
+            _ => 1,
+        }
+    }
+    
+    /// Attach the scheduler pacing requests to the provider
+    ///
+    /// Providers reading rate limit headers from their responses pass them
+    /// on with [`RequestScheduler::update_from_headers`]. Does nothing by
+    /// default.
+    fn set_scheduler(&mut self, _scheduler: Arc<RequestScheduler>) {}
+    
     /// Convert to a data provider if supported
     fn as_data_provider(&self) -> Option<Arc<dyn DataProvider>> {
         None
@@ -36,6 +60,11 @@ pub trait Provider {
     fn as_trading_provider(&self) -> Option<Arc<dyn TradingProvider>> {
         None
     }
+    
+    /// Convert to an economic data provider if supported
+    fn as_economic_provider(&self) -> Option<Arc<dyn EconomicDataProvider>> {
+        None
+    }
 }
 
 /// Provider type categories
@@ -62,6 +91,9 @@ pub enum ProviderType {
     /// Alternative data provider
     Alternative,
     
+    /// Macroeconomic data provider
+    Economic,
+    
     /// Other provider type
     Other,
 }
@@ -111,6 +143,9 @@ pub enum Capability {
     /// Economic data
     EconomicData,
     
+    /// Corporate actions (splits and dividends)
+    CorporateActions,
+    
     /// Alternative data
     AlternativeData,
     
@@ -148,6 +183,13 @@ pub trait DataProvider: Provider + Send + Sync {
         limit: Option<u32>,
     ) -> Result<TimeSeries<Price>>;
     
+    /// Get the splits and dividends of a symbol
+    ///
+    /// Providers serving them also report [`Capability::CorporateActions`].
+    async fn corporate_actions(&self, _symbol: &str, _range: TimeRange) -> Result<Vec<CorporateAction>> {
+        Err(crate::Error::ProviderError(format!("Corporate actions not supported by {}", self.name())))
+    }
+    
     /// Search for symbols
     async fn search(&self, query: &str, asset_class: Option<AssetClass>) -> Result<Vec<MarketData>>;
     
@@ -172,6 +214,27 @@ pub trait DataProvider: Provider + Send + Sync {
     }
 }
 
+/// Trait for macroeconomic data providers
+///
+/// Economic series are returned as `TimeSeries<Observation>`. The series'
+/// `currency` field holds the unit of measure reported by the provider
+/// (e.g. "Percent"), since most macro series are not denominated in a currency.
+#[async_trait]
+pub trait EconomicDataProvider: Provider + Send + Sync {
+    /// Get an economic series by the provider's series identifier
+    async fn series(&self, series_id: &str, range: TimeRange) -> Result<TimeSeries<Observation>>;
+    
+    /// Get the provider's series identifier for a common indicator
+    fn indicator_id(&self, indicator: EconomicIndicator) -> Option<&'static str>;
+    
+    /// Get the series for a common indicator
+    async fn indicator(&self, indicator: EconomicIndicator, range: TimeRange) -> Result<TimeSeries<Observation>> {
+        let series_id = self.indicator_id(indicator)
+            .ok_or_else(|| crate::Error::ProviderError(format!("{:?} is not available from {}", indicator, self.name())))?;
+        self.series(series_id, range).await
+    }
+}
+
 /// Trait for trading providers
 #[async_trait]
 pub trait TradingProvider: Provider + Send + Sync {
@@ -190,6 +253,15 @@ pub trait TradingProvider: Provider + Send + Sync {
     /// Cancel an order
     async fn cancel_order(&self, order_id: &str) -> Result<bool>;
     
+    /// Find an order by the client order ID it was placed with
+    ///
+    /// Returns `None` if the provider never received such an order.
+    async fn order_by_client_id(&self, client_order_id: &str) -> Result<Option<OrderStatus>> {
+        Err(crate::Error::ProviderError(format!(
+            "Client order ID lookup not supported by this provider (order {})", client_order_id
+        )))
+    }
+    
     /// Get current positions
     async fn positions(&self) -> Result<Vec<Position>>;
     
@@ -0,0 +1,351 @@
+//! European Central Bank (ECB) data portal provider
+//!
+//! Serves euro area series through the ECB's SDMX REST API. No API key is
+//! needed. Series IDs are SDMX keys prefixed with their dataflow, e.g.
+//! `ICP/M.U2.N.000000.4.ANR` for euro area HICP inflation.
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use reqwest::Client as HttpClient;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{Error, Result};
+use crate::data::{EconomicIndicator, Observation, TimeInterval, TimeRange, TimeSeries};
+use crate::provider::{Capability, EconomicDataProvider, Provider, ProviderType};
+use crate::scheduler::RequestScheduler;
+
+/// Default ECB data portal API base URL
+const DEFAULT_BASE_URL: &str = "https://data-api.ecb.europa.eu/service";
+
+/// ECB SDMX economic data provider
+#[derive(Debug, Clone)]
+pub struct EcbProvider {
+    /// API base URL
+    base_url: String,
+    
+    /// HTTP client for API requests
+    http_client: HttpClient,
+    
+    /// Scheduler told about the rate limit headers of responses
+    scheduler: Option<Arc<RequestScheduler>>,
+}
+
+impl Default for EcbProvider {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            http_client: HttpClient::new(),
+            scheduler: None,
+        }
+    }
+}
+
+impl EcbProvider {
+    /// Create a new provider
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Set the API base URL
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+    
+    /// Set the HTTP client used for requests
+    pub fn with_http_client(mut self, http_client: HttpClient) -> Self {
+        self.http_client = http_client;
+        self
+    }
+}
+
+/// An SDMX-JSON data message
+#[derive(Debug, Deserialize)]
+struct DataMessage {
+    #[serde(rename = "dataSets")]
+    data_sets: Vec<DataSet>,
+    structure: Structure,
+}
+
+#[derive(Debug, Deserialize)]
+struct DataSet {
+    #[serde(default)]
+    series: serde_json::Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Structure {
+    dimensions: Dimensions,
+    #[serde(default)]
+    attributes: Attributes,
+}
+
+#[derive(Debug, Deserialize)]
+struct Dimensions {
+    #[serde(default)]
+    series: Vec<Component>,
+    observation: Vec<Component>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Attributes {
+    #[serde(default)]
+    series: Vec<Component>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Component {
+    id: String,
+    values: Vec<ComponentValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ComponentValue {
+    #[serde(default)]
+    id: String,
+    name: Option<String>,
+}
+
+/// A series parsed from an SDMX-JSON message
+#[derive(Debug)]
+struct ParsedSeries {
+    observations: Vec<Observation>,
+    interval: TimeInterval,
+    unit: String,
+}
+
+impl Provider for EcbProvider {
+    fn name(&self) -> &str {
+        "ecb"
+    }
+    
+    fn provider_type(&self) -> ProviderType {
+        ProviderType::Economic
+    }
+    
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![Capability::EconomicData]
+    }
+    
+    fn set_scheduler(&mut self, scheduler: Arc<RequestScheduler>) {
+        self.scheduler = Some(scheduler);
+    }
+    
+    fn as_economic_provider(&self) -> Option<Arc<dyn EconomicDataProvider>> {
+        Some(Arc::new(self.clone()))
+    }
+}
+
+#[async_trait]
+impl EconomicDataProvider for EcbProvider {
+    async fn series(&self, series_id: &str, range: TimeRange) -> Result<TimeSeries<Observation>> {
+        let (flow, key) = series_id.split_once('/')
+            .ok_or_else(|| Error::ValidationError(format!("ECB series ID must be 'FLOW/KEY': {}", series_id)))?;
+        
+        let (start, end) = range.to_date_range();
+        let response = self.http_client
+            .get(format!("{}/data/{}/{}", self.base_url, flow, key))
+            .query(&[
+                ("format", "jsondata".to_string()),
+                ("startPeriod", start.format("%Y-%m-%d").to_string()),
+                ("endPeriod", end.format("%Y-%m-%d").to_string()),
+            ])
+            .send()
+            .await?;
+        
+        if let Some(scheduler) = &self.scheduler {
+            scheduler.update_from_headers(self.name(), response.headers());
+        }
+        
+        let status = response.status();
+        if status.as_u16() == 404 {
+            // The data portal answers 404 when no observations match
+            return Err(Error::MarketDataError(format!("No ECB data for {}", series_id)));
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(match status.as_u16() {
+                429 => Error::RateLimitError(body),
+                _ => Error::ProviderError(format!("ECB returned {}: {}", status, body)),
+            });
+        }
+        
+        let message: DataMessage = response.json().await?;
+        let parsed = parse_message(message, series_id)?;
+        
+        Ok(TimeSeries::new(
+            series_id.to_string(),
+            parsed.interval,
+            parsed.observations,
+            start,
+            end,
+            "CET".to_string(),
+            parsed.unit,
+        ))
+    }
+    
+    fn indicator_id(&self, indicator: EconomicIndicator) -> Option<&'static str> {
+        match indicator {
+            EconomicIndicator::PolicyRate => Some("FM/D.U2.EUR.4F.KR.DFR.LEV"),
+            EconomicIndicator::Cpi => Some("ICP/M.U2.N.000000.4.INX"),
+            EconomicIndicator::Unemployment => Some("LFSI/M.I9.S.UNEHRT.TOTAL0.15_74.T"),
+            EconomicIndicator::TenYearYield => Some("YC/B.U2.EUR.4F.G_N_A.SV_C_YM.SR_10Y"),
+            EconomicIndicator::Gdp => None,
+        }
+    }
+}
+
+/// Extract the single series of an SDMX-JSON data message
+fn parse_message(message: DataMessage, series_id: &str) -> Result<ParsedSeries> {
+    let data_set = message.data_sets.into_iter().next()
+        .ok_or_else(|| Error::MarketDataError(format!("No ECB data for {}", series_id)))?;
+    if data_set.series.len() > 1 {
+        return Err(Error::ValidationError(format!("ECB key matches more than one series: {}", series_id)));
+    }
+    let series = data_set.series.into_iter().next().map(|(_, series)| series)
+        .ok_or_else(|| Error::MarketDataError(format!("No ECB data for {}", series_id)))?;
+    
+    let periods = message.structure.dimensions.observation.iter()
+        .find(|d| d.id == "TIME_PERIOD")
+        .ok_or_else(|| Error::ParseError("SDMX message has no TIME_PERIOD dimension".to_string()))?;
+    
+    // Observations are keyed by their index into the TIME_PERIOD values
+    let mut observations = Vec::new();
+    if let Some(raw) = series["observations"].as_object() {
+        for (index, values) in raw {
+            let value = match values.get(0).and_then(Value::as_f64) {
+                Some(value) => value,
+                None => continue,
+            };
+            let period = index.parse::<usize>().ok()
+                .and_then(|i| periods.values.get(i))
+                .ok_or_else(|| Error::ParseError(format!("Unknown SDMX observation index: {}", index)))?;
+            observations.push(Observation::new(parse_period(&period.id)?, value));
+        }
+    }
+    observations.sort_by_key(|o| o.timestamp);
+    
+    let interval = message.structure.dimensions.series.iter()
+        .find(|d| d.id == "FREQ")
+        .and_then(|d| d.values.first())
+        .map(|v| frequency_interval(&v.id))
+        .unwrap_or(TimeInterval::Daily);
+    
+    // Series attribute values are indices into the attribute's value list
+    let unit = message.structure.attributes.series.iter()
+        .position(|a| a.id == "UNIT")
+        .and_then(|position| {
+            let index = series["attributes"].get(position)?.as_u64()? as usize;
+            let value = message.structure.attributes.series[position].values.get(index)?;
+            Some(value.name.clone().unwrap_or_else(|| value.id.clone()))
+        })
+        .unwrap_or_default();
+    
+    Ok(ParsedSeries { observations, interval, unit })
+}
+
+/// Parse an SDMX time period into the start of the period
+///
+/// Supports daily (`2024-01-31`), monthly (`2024-01`), quarterly (`2024-Q1`),
+/// half-yearly (`2024-S1`), weekly (`2024-W05`) and annual (`2024`) periods.
+fn parse_period(period: &str) -> Result<DateTime<Utc>> {
+    let invalid = || Error::ParseError(format!("Invalid SDMX period: {}", period));
+    
+    let date = match period.split_once('-') {
+        None => NaiveDate::from_ymd_opt(period.parse().map_err(|_| invalid())?, 1, 1),
+        Some((year, rest)) => {
+            let year: i32 = year.parse().map_err(|_| invalid())?;
+            let number = |s: &str| s.parse::<u32>().map_err(|_| invalid());
+            
+            if let Some(quarter) = rest.strip_prefix('Q') {
+                NaiveDate::from_ymd_opt(year, number(quarter)?.checked_sub(1).ok_or_else(invalid)? * 3 + 1, 1)
+            } else if let Some(half) = rest.strip_prefix('S') {
+                NaiveDate::from_ymd_opt(year, number(half)?.checked_sub(1).ok_or_else(invalid)? * 6 + 1, 1)
+            } else if let Some(week) = rest.strip_prefix('W') {
+                NaiveDate::from_isoywd_opt(year, number(week)?, chrono::Weekday::Mon)
+            } else if rest.contains('-') {
+                NaiveDate::parse_from_str(period, "%Y-%m-%d").ok()
+            } else {
+                NaiveDate::from_ymd_opt(year, number(rest)?, 1)
+            }
+        }
+    };
+    
+    let date = date.ok_or_else(invalid)?;
+    Ok(DateTime::<Utc>::from_naive_utc_and_offset(date.and_hms_opt(0, 0, 0).unwrap_or_default(), Utc))
+}
+
+/// Map an SDMX frequency code to a time interval
+fn frequency_interval(frequency: &str) -> TimeInterval {
+    match frequency {
+        "B" | "D" => TimeInterval::Daily,
+        "W" => TimeInterval::Weekly,
+        "M" => TimeInterval::Monthly,
+        "Q" => TimeInterval::Quarterly,
+        "H" => TimeInterval::Custom(60 * 24 * 182),
+        _ => TimeInterval::Yearly,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_period() {
+        let date = |period: &str| parse_period(period).unwrap().format("%Y-%m-%d").to_string();
+        
+        assert_eq!(date("2024-03-15"), "2024-03-15");
+        assert_eq!(date("2024-03"), "2024-03-01");
+        assert_eq!(date("2024-Q3"), "2024-07-01");
+        assert_eq!(date("2024-S2"), "2024-07-01");
+        assert_eq!(date("2024-W01"), "2024-01-01");
+        assert_eq!(date("2024"), "2024-01-01");
+        assert!(parse_period("2024-Q0").is_err());
+        assert!(parse_period("soon").is_err());
+    }
+    
+    #[test]
+    fn test_parse_message() {
+        let message: DataMessage = serde_json::from_value(serde_json::json!({
+            "dataSets": [{
+                "action": "Replace",
+                "series": {
+                    "0:0:0:0:0:0": {
+                        "attributes": [0, null, 1],
+                        "observations": {
+                            "1": [2.9, 0, null],
+                            "0": [2.4, 0, null],
+                            "2": [null, 1, null],
+                        }
+                    }
+                }
+            }],
+            "structure": {
+                "dimensions": {
+                    "series": [{ "id": "FREQ", "values": [{ "id": "M", "name": "Monthly" }] }],
+                    "observation": [{
+                        "id": "TIME_PERIOD",
+                        "values": [{ "id": "2023-11" }, { "id": "2023-12" }, { "id": "2024-01" }],
+                    }],
+                },
+                "attributes": {
+                    "series": [
+                        { "id": "TITLE", "values": [{ "name": "HICP - Overall index" }] },
+                        { "id": "COMPILATION", "values": [] },
+                        { "id": "UNIT", "values": [{ "id": "EUR" }, { "id": "PCCH", "name": "Percentage change" }] },
+                    ]
+                }
+            }
+        })).unwrap();
+        
+        let parsed = parse_message(message, "ICP/M.U2.N.000000.4.ANR").unwrap();
+        assert_eq!(parsed.interval, TimeInterval::Monthly);
+        assert_eq!(parsed.unit, "Percentage change");
+        assert_eq!(parsed.observations.len(), 2);
+        assert_eq!(parsed.observations[0].value, 2.4);
+        assert_eq!(parsed.observations[1].timestamp.format("%Y-%m").to_string(), "2023-12");
+    }
+}
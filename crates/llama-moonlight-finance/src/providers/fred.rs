@@ -0,0 +1,250 @@
+//! Federal Reserve Economic Data (FRED) provider
+//!
+//! Serves US and international macro series from the St. Louis Fed. Requests
+//! need a free API key, read from `FRED_API_KEY` by [`FredProvider::from_env`].
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use reqwest::Client as HttpClient;
+use serde::Deserialize;
+
+use crate::{Error, Result};
+use crate::data::{EconomicIndicator, Observation, TimeInterval, TimeRange, TimeSeries};
+use crate::provider::{Capability, EconomicDataProvider, Provider, ProviderType};
+use crate::scheduler::RequestScheduler;
+
+/// Default FRED API base URL
+const DEFAULT_BASE_URL: &str = "https://api.stlouisfed.org/fred";
+
+/// FRED economic data provider
+#[derive(Debug, Clone)]
+pub struct FredProvider {
+    /// FRED API key
+    api_key: String,
+    
+    /// API base URL
+    base_url: String,
+    
+    /// HTTP client for API requests
+    http_client: HttpClient,
+    
+    /// Scheduler told about the rate limit headers of responses
+    scheduler: Option<Arc<RequestScheduler>>,
+}
+
+impl FredProvider {
+    /// Create a provider with an API key
+    pub fn new(api_key: &str) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            http_client: HttpClient::new(),
+            scheduler: None,
+        }
+    }
+    
+    /// Create a provider with the API key from the `FRED_API_KEY` environment variable
+    pub fn from_env() -> Result<Self> {
+        let api_key = std::env::var("FRED_API_KEY")
+            .map_err(|_| Error::AuthError("FRED_API_KEY is not set".to_string()))?;
+        Ok(Self::new(&api_key))
+    }
+    
+    /// Set the API base URL
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+    
+    /// Set the HTTP client used for requests
+    pub fn with_http_client(mut self, http_client: HttpClient) -> Self {
+        self.http_client = http_client;
+        self
+    }
+    
+    /// Make a GET request to the FRED API
+    async fn get<T: for<'de> Deserialize<'de>>(&self, path: &str, params: &[(&str, String)]) -> Result<T> {
+        let response = self.http_client
+            .get(format!("{}/{}", self.base_url, path))
+            .query(&[("api_key", self.api_key.as_str()), ("file_type", "json")])
+            .query(params)
+            .send()
+            .await?;
+        
+        if let Some(scheduler) = &self.scheduler {
+            scheduler.update_from_headers(self.name(), response.headers());
+        }
+        
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(match status.as_u16() {
+                429 => Error::RateLimitError(body),
+                400 | 401 | 403 => Error::ProviderError(format!("FRED rejected the request: {}", body)),
+                _ => Error::NetworkError(format!("FRED returned {}: {}", status, body)),
+            });
+        }
+        
+        Ok(response.json().await?)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SeriesResponse {
+    seriess: Vec<SeriesInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeriesInfo {
+    frequency_short: String,
+    units: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ObservationsResponse {
+    observations: Vec<RawObservation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawObservation {
+    date: String,
+    value: String,
+}
+
+impl Provider for FredProvider {
+    fn name(&self) -> &str {
+        "fred"
+    }
+    
+    fn provider_type(&self) -> ProviderType {
+        ProviderType::Economic
+    }
+    
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![Capability::EconomicData]
+    }
+    
+    fn set_scheduler(&mut self, scheduler: Arc<RequestScheduler>) {
+        self.scheduler = Some(scheduler);
+    }
+    
+    fn as_economic_provider(&self) -> Option<Arc<dyn EconomicDataProvider>> {
+        Some(Arc::new(self.clone()))
+    }
+}
+
+#[async_trait]
+impl EconomicDataProvider for FredProvider {
+    async fn series(&self, series_id: &str, range: TimeRange) -> Result<TimeSeries<Observation>> {
+        let info: SeriesResponse = self.get("series", &[("series_id", series_id.to_string())]).await?;
+        let info = info.seriess.into_iter().next()
+            .ok_or_else(|| Error::MarketDataError(format!("FRED series not found: {}", series_id)))?;
+        
+        let (start, end) = range.to_date_range();
+        let params = [
+            ("series_id", series_id.to_string()),
+            ("observation_start", start.format("%Y-%m-%d").to_string()),
+            ("observation_end", end.format("%Y-%m-%d").to_string()),
+        ];
+        let response: ObservationsResponse = self.get("series/observations", &params).await?;
+        let observations = parse_observations(response.observations)?;
+        
+        Ok(TimeSeries::new(
+            series_id.to_string(),
+            frequency_interval(&info.frequency_short),
+            observations,
+            start,
+            end,
+            "UTC".to_string(),
+            info.units,
+        ))
+    }
+    
+    fn indicator_id(&self, indicator: EconomicIndicator) -> Option<&'static str> {
+        Some(match indicator {
+            EconomicIndicator::PolicyRate => "FEDFUNDS",
+            EconomicIndicator::Cpi => "CPIAUCSL",
+            EconomicIndicator::Unemployment => "UNRATE",
+            EconomicIndicator::TenYearYield => "DGS10",
+            EconomicIndicator::Gdp => "GDPC1",
+        })
+    }
+}
+
+/// Convert raw FRED observations, skipping missing values
+///
+/// FRED reports missing values (e.g. bank holidays in daily series) as ".".
+fn parse_observations(raw: Vec<RawObservation>) -> Result<Vec<Observation>> {
+    raw.into_iter()
+        .filter(|o| o.value != ".")
+        .map(|o| {
+            let date = NaiveDate::parse_from_str(&o.date, "%Y-%m-%d")
+                .map_err(|e| Error::ParseError(format!("Invalid FRED date '{}': {}", o.date, e)))?;
+            let value = o.value.parse::<f64>()
+                .map_err(|e| Error::ParseError(format!("Invalid FRED value '{}': {}", o.value, e)))?;
+            let timestamp = DateTime::<Utc>::from_naive_utc_and_offset(date.and_hms_opt(0, 0, 0).unwrap_or_default(), Utc);
+            Ok(Observation::new(timestamp, value))
+        })
+        .collect()
+}
+
+/// Map a FRED frequency code to a time interval
+fn frequency_interval(frequency: &str) -> TimeInterval {
+    match frequency {
+        "D" => TimeInterval::Daily,
+        "W" | "BW" => TimeInterval::Weekly,
+        "M" => TimeInterval::Monthly,
+        "Q" => TimeInterval::Quarterly,
+        "SA" => TimeInterval::Custom(60 * 24 * 182),
+        _ => TimeInterval::Yearly,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_observations() {
+        let raw: ObservationsResponse = serde_json::from_value(serde_json::json!({
+            "observations": [
+                { "realtime_start": "2024-01-05", "realtime_end": "2024-01-05", "date": "2023-12-29", "value": "3.88" },
+                { "realtime_start": "2024-01-05", "realtime_end": "2024-01-05", "date": "2024-01-01", "value": "." },
+                { "realtime_start": "2024-01-05", "realtime_end": "2024-01-05", "date": "2024-01-02", "value": "3.95" },
+            ]
+        })).unwrap();
+        
+        let observations = parse_observations(raw.observations).unwrap();
+        assert_eq!(observations.len(), 2);
+        assert_eq!(observations[1].value, 3.95);
+        assert_eq!(observations[1].timestamp.format("%Y-%m-%d").to_string(), "2024-01-02");
+        
+        assert_eq!(frequency_interval("M"), TimeInterval::Monthly);
+        assert_eq!(frequency_interval("A"), TimeInterval::Yearly);
+    }
+    
+    #[tokio::test]
+    async fn test_rate_limit_headers_reach_scheduler() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/series"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "30"))
+            .mount(&server)
+            .await;
+        
+        let scheduler = Arc::new(RequestScheduler::new());
+        scheduler.register("fred", crate::scheduler::known_limits("fred"));
+        let mut provider = FredProvider::new("key").with_base_url(&server.uri());
+        provider.set_scheduler(scheduler.clone());
+        
+        let result = provider.series("UNRATE", TimeRange::Day(30)).await;
+        assert!(matches!(result, Err(Error::RateLimitError(_))));
+        
+        // The provider's Retry-After holds back further requests
+        assert!(scheduler.estimated_wait("fred", 1) > std::time::Duration::from_secs(25));
+    }
+}
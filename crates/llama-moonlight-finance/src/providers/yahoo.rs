@@ -0,0 +1,485 @@
+//! Yahoo Finance provider
+//!
+//! Serves quotes, price history, splits and dividends from Yahoo's public
+//! chart API, and symbol search. No API key is needed.
+//!
+//! Yahoo's chart prices are already split-adjusted, so the series returned by
+//! [`DataProvider::historical_prices`] are restored to raw prices (or fully
+//! adjusted when asked to) from the splits and dividends sent along with them.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Client as HttpClient;
+use serde::Deserialize;
+
+use crate::{AssetClass, Error, Result};
+use crate::corporate_actions::{AdjustmentPolicy, CorporateAction};
+use crate::data::{MarketData, Price, Quote, TimeInterval, TimeRange, TimeSeries};
+use crate::provider::{Capability, DataProvider, Provider, ProviderType};
+use crate::scheduler::RequestScheduler;
+
+/// Default Yahoo Finance API base URL
+const DEFAULT_BASE_URL: &str = "https://query1.finance.yahoo.com";
+
+/// Yahoo Finance market data provider
+#[derive(Debug, Clone)]
+pub struct YahooProvider {
+    /// API base URL
+    base_url: String,
+    
+    /// HTTP client for API requests
+    http_client: HttpClient,
+    
+    /// Scheduler told about the rate limit headers of responses
+    scheduler: Option<Arc<RequestScheduler>>,
+}
+
+impl Default for YahooProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl YahooProvider {
+    /// Create a provider
+    pub fn new() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            http_client: HttpClient::new(),
+            scheduler: None,
+        }
+    }
+    
+    /// Set the API base URL
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+    
+    /// Set the HTTP client used for requests
+    pub fn with_http_client(mut self, http_client: HttpClient) -> Self {
+        self.http_client = http_client;
+        self
+    }
+    
+    /// Make a GET request to the Yahoo Finance API
+    async fn get<T: for<'de> Deserialize<'de>>(&self, path: &str, params: &[(&str, String)]) -> Result<T> {
+        let response = self.http_client
+            .get(format!("{}/{}", self.base_url, path))
+            .query(params)
+            .send()
+            .await?;
+        
+        if let Some(scheduler) = &self.scheduler {
+            scheduler.update_from_headers(self.name(), response.headers());
+        }
+        
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(match status.as_u16() {
+                429 => Error::RateLimitError(body),
+                404 => Error::MarketDataError(format!("Yahoo Finance has no data: {}", body)),
+                400 | 401 | 403 => Error::ProviderError(format!("Yahoo Finance rejected the request: {}", body)),
+                _ => Error::NetworkError(format!("Yahoo Finance returned {}: {}", status, body)),
+            });
+        }
+        
+        Ok(response.json().await?)
+    }
+    
+    /// Fetch the chart of a symbol, with its splits and dividends
+    async fn chart(&self, symbol: &str, interval: TimeInterval, range: TimeRange, include_extended: bool) -> Result<Chart> {
+        let mut params = vec![
+            ("interval", chart_interval(interval)?.to_string()),
+            ("events", "div,splits".to_string()),
+            ("includePrePost", include_extended.to_string()),
+        ];
+        match range {
+            TimeRange::Max => params.push(("range", "max".to_string())),
+            _ => {
+                let (start, end) = range.to_date_range();
+                params.push(("period1", start.timestamp().to_string()));
+                params.push(("period2", end.timestamp().to_string()));
+            }
+        }
+        
+        let response: ChartResponse = self.get(&format!("v8/finance/chart/{}", symbol), &params).await?;
+        if let Some(error) = response.chart.error {
+            return Err(Error::MarketDataError(format!("Yahoo Finance error for {}: {}", symbol, error.description)));
+        }
+        
+        response.chart.result
+            .and_then(|result| result.into_iter().next())
+            .ok_or_else(|| Error::MarketDataError(format!("Symbol not found: {}", symbol)))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChartResponse {
+    chart: ChartEnvelope,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChartEnvelope {
+    result: Option<Vec<Chart>>,
+    error: Option<ChartError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChartError {
+    description: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Chart {
+    meta: ChartMeta,
+    #[serde(default)]
+    timestamp: Vec<i64>,
+    #[serde(default)]
+    events: ChartEvents,
+    indicators: Indicators,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChartMeta {
+    symbol: String,
+    currency: Option<String>,
+    exchange_name: Option<String>,
+    exchange_timezone_name: Option<String>,
+    regular_market_price: Option<f64>,
+    regular_market_time: Option<i64>,
+    regular_market_volume: Option<u64>,
+    chart_previous_close: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ChartEvents {
+    #[serde(default)]
+    dividends: HashMap<String, RawDividend>,
+    #[serde(default)]
+    splits: HashMap<String, RawSplit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawDividend {
+    amount: f64,
+    date: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawSplit {
+    date: i64,
+    numerator: f64,
+    denominator: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Indicators {
+    #[serde(default)]
+    quote: Vec<Bars>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Bars {
+    #[serde(default)]
+    open: Vec<Option<f64>>,
+    #[serde(default)]
+    high: Vec<Option<f64>>,
+    #[serde(default)]
+    low: Vec<Option<f64>>,
+    #[serde(default)]
+    close: Vec<Option<f64>>,
+    #[serde(default)]
+    volume: Vec<Option<u64>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    quotes: Vec<SearchQuote>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchQuote {
+    symbol: String,
+    #[serde(rename = "shortname")]
+    short_name: Option<String>,
+    #[serde(rename = "longname")]
+    long_name: Option<String>,
+    quote_type: Option<String>,
+    #[serde(rename = "exchDisp")]
+    exchange_display: Option<String>,
+    exchange: Option<String>,
+    sector: Option<String>,
+    industry: Option<String>,
+}
+
+impl Provider for YahooProvider {
+    fn name(&self) -> &str {
+        "yahoo"
+    }
+    
+    fn provider_type(&self) -> ProviderType {
+        ProviderType::MarketData
+    }
+    
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![Capability::RealTimeQuotes, Capability::HistoricalPrices, Capability::CorporateActions]
+    }
+    
+    fn set_scheduler(&mut self, scheduler: Arc<RequestScheduler>) {
+        self.scheduler = Some(scheduler);
+    }
+    
+    fn as_data_provider(&self) -> Option<Arc<dyn DataProvider>> {
+        Some(Arc::new(self.clone()))
+    }
+}
+
+#[async_trait]
+impl DataProvider for YahooProvider {
+    async fn quote(&self, symbol: &str) -> Result<Quote> {
+        let chart = self.chart(symbol, TimeInterval::Daily, TimeRange::Day(5), false).await?;
+        parse_quote(chart)
+    }
+    
+    async fn historical_prices(
+        &self,
+        symbol: &str,
+        interval: TimeInterval,
+        range: TimeRange,
+        include_extended: bool,
+        adjust: bool,
+        limit: Option<u32>,
+    ) -> Result<TimeSeries<Price>> {
+        let chart = self.chart(symbol, interval, range, include_extended).await?;
+        let actions = parse_actions(&chart.events);
+        let mut prices = parse_prices(&chart)?;
+        if let Some(limit) = limit {
+            let skip = prices.len().saturating_sub(limit as usize);
+            prices.drain(..skip);
+        }
+        
+        let (start, end) = range.to_date_range();
+        let series = TimeSeries::new(
+            chart.meta.symbol,
+            interval,
+            prices,
+            start,
+            end,
+            chart.meta.exchange_timezone_name.unwrap_or_else(|| "UTC".to_string()),
+            chart.meta.currency.unwrap_or_default(),
+        )
+        .with_corporate_actions(actions)
+        .with_adjustment(AdjustmentPolicy::Splits);
+        
+        series.adjust(if adjust { AdjustmentPolicy::SplitsAndDividends } else { AdjustmentPolicy::Unadjusted })
+    }
+    
+    async fn corporate_actions(&self, symbol: &str, range: TimeRange) -> Result<Vec<CorporateAction>> {
+        let chart = self.chart(symbol, TimeInterval::Daily, range, false).await?;
+        Ok(parse_actions(&chart.events))
+    }
+    
+    async fn search(&self, query: &str, asset_class: Option<AssetClass>) -> Result<Vec<MarketData>> {
+        let params = [("q", query.to_string()), ("newsCount", "0".to_string())];
+        let response: SearchResponse = self.get("v1/finance/search", &params).await?;
+        
+        Ok(response.quotes
+            .into_iter()
+            .filter(|quote| asset_class.map_or(true, |class| quote_asset_class(quote.quote_type.as_deref()) == class))
+            .map(parse_market_data)
+            .collect())
+    }
+}
+
+/// Map an interval to a Yahoo chart interval
+fn chart_interval(interval: TimeInterval) -> Result<&'static str> {
+    Ok(match interval {
+        TimeInterval::Minute1 => "1m",
+        TimeInterval::Minute5 => "5m",
+        TimeInterval::Minute15 => "15m",
+        TimeInterval::Minute30 => "30m",
+        TimeInterval::Hourly => "60m",
+        TimeInterval::Daily => "1d",
+        TimeInterval::Weekly => "1wk",
+        TimeInterval::Monthly => "1mo",
+        TimeInterval::Quarterly => "3mo",
+        other => return Err(Error::ValidationError(format!("Yahoo Finance does not serve {:?} bars", other))),
+    })
+}
+
+/// Convert a Unix timestamp
+fn timestamp(seconds: i64) -> Result<DateTime<Utc>> {
+    DateTime::<Utc>::from_timestamp(seconds, 0)
+        .ok_or_else(|| Error::ParseError(format!("Invalid Yahoo Finance timestamp {}", seconds)))
+}
+
+/// Convert the bars of a chart, skipping bars without prices
+///
+/// Yahoo sends nulls for intervals without trades, e.g. holidays.
+fn parse_prices(chart: &Chart) -> Result<Vec<Price>> {
+    let bars = match chart.indicators.quote.first() {
+        Some(bars) => bars,
+        None => return Ok(Vec::new()),
+    };
+    let at = |values: &[Option<f64>], i: usize| values.get(i).copied().flatten();
+    
+    let mut prices = Vec::with_capacity(chart.timestamp.len());
+    for (i, &seconds) in chart.timestamp.iter().enumerate() {
+        let (open, high, low, close) = match (at(&bars.open, i), at(&bars.high, i), at(&bars.low, i), at(&bars.close, i)) {
+            (Some(open), Some(high), Some(low), Some(close)) => (open, high, low, close),
+            _ => continue,
+        };
+        let volume = bars.volume.get(i).copied().flatten().unwrap_or(0);
+        prices.push(Price::new(open, high, low, close, volume, timestamp(seconds)?));
+    }
+    
+    Ok(prices)
+}
+
+/// Convert the splits and dividends of a chart, oldest first
+fn parse_actions(events: &ChartEvents) -> Vec<CorporateAction> {
+    let splits = events.splits.values().filter_map(|split| {
+        let ex_date = timestamp(split.date).ok()?;
+        Some(CorporateAction::split(ex_date, split.denominator, split.numerator))
+    });
+    let dividends = events.dividends.values().filter_map(|dividend| {
+        let ex_date = timestamp(dividend.date).ok()?;
+        Some(CorporateAction::dividend(ex_date, dividend.amount))
+    });
+    
+    let mut actions: Vec<CorporateAction> = splits.chain(dividends).collect();
+    actions.sort_by_key(|action| action.ex_date());
+    actions
+}
+
+/// Build a quote from the metadata of a chart
+fn parse_quote(chart: Chart) -> Result<Quote> {
+    let meta = chart.meta;
+    let price = meta.regular_market_price
+        .ok_or_else(|| Error::MarketDataError(format!("No market price for {}", meta.symbol)))?;
+    let previous = meta.chart_previous_close.unwrap_or(price);
+    let change = price - previous;
+    
+    Ok(Quote {
+        symbol: meta.symbol,
+        price,
+        change,
+        change_percent: if previous != 0.0 { change / previous * 100.0 } else { 0.0 },
+        volume: meta.regular_market_volume.unwrap_or(0),
+        market_cap: None,
+        timestamp: match meta.regular_market_time {
+            Some(seconds) => timestamp(seconds)?,
+            None => Utc::now(),
+        },
+        exchange: meta.exchange_name,
+        currency: meta.currency,
+        additional_data: HashMap::new(),
+    })
+}
+
+/// Map a Yahoo quote type to an asset class
+fn quote_asset_class(quote_type: Option<&str>) -> AssetClass {
+    match quote_type {
+        Some("EQUITY") => AssetClass::Stock,
+        Some("ETF") => AssetClass::ETF,
+        Some("CRYPTOCURRENCY") => AssetClass::Crypto,
+        Some("CURRENCY") => AssetClass::Forex,
+        Some("FUTURE") => AssetClass::Future,
+        Some("OPTION") => AssetClass::Option,
+        _ => AssetClass::Other,
+    }
+}
+
+/// Convert a search result
+fn parse_market_data(quote: SearchQuote) -> MarketData {
+    let instrument_type = quote.quote_type.clone().unwrap_or_default();
+    MarketData {
+        name: quote.long_name.or(quote.short_name).unwrap_or_else(|| quote.symbol.clone()),
+        symbol: quote.symbol,
+        is_etf: instrument_type == "ETF",
+        instrument_type,
+        exchange: quote.exchange_display.or(quote.exchange).unwrap_or_default(),
+        currency: String::new(),
+        country: None,
+        index_membership: None,
+        sector: quote.sector,
+        industry: quote.industry,
+        market_cap_category: None,
+        additional_data: HashMap::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_chart() {
+        let response: ChartResponse = serde_json::from_value(serde_json::json!({
+            "chart": {
+                "result": [{
+                    "meta": {
+                        "symbol": "AAPL",
+                        "currency": "USD",
+                        "exchangeName": "NMS",
+                        "exchangeTimezoneName": "America/New_York",
+                        "regularMarketPrice": 132.0,
+                        "regularMarketTime": 1598990400,
+                        "chartPreviousClose": 120.0
+                    },
+                    "timestamp": [1598621400, 1598880600, 1598967000],
+                    "events": {
+                        "splits": { "1598880600": { "date": 1598880600, "numerator": 4.0, "denominator": 1.0, "splitRatio": "4:1" } },
+                        "dividends": { "1596807000": { "amount": 0.205, "date": 1596807000 } }
+                    },
+                    "indicators": {
+                        "quote": [{
+                            "open": [126.0, 127.0, null],
+                            "high": [126.5, 131.0, null],
+                            "low": [125.0, 126.0, null],
+                            "close": [125.0, 129.0, null],
+                            "volume": [100, 200, null]
+                        }]
+                    }
+                }],
+                "error": null
+            }
+        })).unwrap();
+        let chart = response.chart.result.unwrap().pop().unwrap();
+        
+        let prices = parse_prices(&chart).unwrap();
+        assert_eq!(prices.len(), 2);
+        assert_eq!(prices[1].close, 129.0);
+        
+        let actions = parse_actions(&chart.events);
+        assert_eq!(actions.len(), 2);
+        assert!(matches!(actions[0], CorporateAction::Dividend { amount, .. } if amount == 0.205));
+        assert!(matches!(actions[1], CorporateAction::Split { from, to, .. } if from == 1.0 && to == 4.0));
+        
+        // Yahoo's split-adjusted closes are restored to the raw pre-split prices
+        let series = TimeSeries::new("AAPL".to_string(), TimeInterval::Daily, prices, Utc::now(), Utc::now(), "UTC".to_string(), "USD".to_string())
+            .with_corporate_actions(actions)
+            .with_adjustment(AdjustmentPolicy::Splits);
+        let raw = series.adjust(AdjustmentPolicy::Unadjusted).unwrap();
+        assert_eq!(raw.prices[0].close, 500.0);
+        assert_eq!(raw.prices[1].close, 129.0);
+        
+        let quote = parse_quote(chart).unwrap();
+        assert_eq!(quote.change, 12.0);
+        assert_eq!(quote.change_percent, 10.0);
+    }
+    
+    #[test]
+    fn test_chart_interval() {
+        assert_eq!(chart_interval(TimeInterval::Weekly).unwrap(), "1wk");
+        assert!(chart_interval(TimeInterval::Yearly).is_err());
+        assert_eq!(quote_asset_class(Some("ETF")), AssetClass::ETF);
+    }
+}
@@ -0,0 +1,190 @@
+//! Rate-limit-aware request scheduling
+//!
+//! Every provider registered with a [`FinanceClient`](crate::FinanceClient)
+//! gets a limiter built from its documented limits. Requests wait their turn
+//! in FIFO order until each of the provider's windows has room for their
+//! weight, so bulk fetches from many tasks are paced instead of banned.
+//!
+//! Limits are sliding windows of request weight. Plain request-count limits
+//! use a weight of 1 per request; weight systems such as Binance's charge
+//! more for expensive endpoints (see [`Provider::request_weight`](crate::Provider::request_weight)).
+//! The pacing itself is done by the shared `llama-moonlight-ratelimit` crate.
+
+use std::time::Duration;
+use llama_moonlight_ratelimit::{RateLimitHeaders, RateLimiter};
+
+use crate::{Error, Result};
+
+pub use llama_moonlight_ratelimit::RateLimit;
+
+/// The kind of request made to a provider, used to weigh it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestKind {
+    /// Single quote
+    Quote,
+    
+    /// Batch of quotes
+    Quotes(usize),
+    
+    /// Historical prices
+    HistoricalPrices,
+    
+    /// Symbol search
+    Search,
+    
+    /// Corporate actions
+    CorporateActions,
+    
+    /// Economic series
+    EconomicSeries,
+    
+    /// Order book of the given depth
+    OrderBook(Option<u32>),
+    
+    /// Recent trades
+    RecentTrades,
+    
+    /// Order placement, status, or cancellation
+    Order,
+    
+    /// Account positions or balances
+    Account,
+}
+
+/// Documented limits of the built-in providers
+///
+/// Free-tier limits are used where a provider has several plans.
+pub fn known_limits(provider: &str) -> Vec<RateLimit> {
+    match provider {
+        "yahoo" => vec![RateLimit::per_hour(2000)],
+        "alphavantage" | "alpha_vantage" => vec![RateLimit::per_minute(5), RateLimit::per_day(25)],
+        "coinmarketcap" => vec![RateLimit::per_minute(30)],
+        "binance" => vec![RateLimit::per_minute(6000)],
+        "fred" => vec![RateLimit::per_minute(120)],
+        "ecb" => vec![RateLimit::per_minute(60)],
+        _ => Vec::new(),
+    }
+}
+
+/// Paces requests to each provider according to its rate limits
+///
+/// The scheduler is shared by all clones of a client, so concurrent tasks
+/// draw from the same limits.
+#[derive(Debug, Default)]
+pub struct RequestScheduler {
+    /// Limiter keyed by provider name
+    limiter: RateLimiter,
+}
+
+impl RequestScheduler {
+    /// Create a scheduler without any limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Set the limits of a provider, replacing earlier limits
+    ///
+    /// Providers without limits are not paced.
+    pub fn register(&self, provider: &str, limits: Vec<RateLimit>) {
+        self.limiter.register(provider, limits);
+    }
+    
+    /// Get the limits of a provider
+    pub fn limits(&self, provider: &str) -> Vec<RateLimit> {
+        self.limiter.limits(provider)
+    }
+    
+    /// Wait until a request of `weight` may be sent to a provider
+    ///
+    /// The request's weight counts against the provider's limits from the
+    /// moment this returns.
+    pub async fn acquire(&self, provider: &str, weight: u32) -> Result<()> {
+        self.limiter.acquire(provider, weight).await
+            .map_err(|e| Error::RateLimitError(e.to_string()))
+    }
+    
+    /// Estimate how long a request of `weight` would wait if made now
+    ///
+    /// Accounts for requests already queued for the provider.
+    pub fn estimated_wait(&self, provider: &str, weight: u32) -> Duration {
+        self.limiter.estimated_wait(provider, weight)
+    }
+    
+    /// Hold all requests to a provider for a while
+    ///
+    /// Use this when a provider reports that a limit was hit anyway, e.g.
+    /// with the `Retry-After` of a 429 response.
+    pub fn pause(&self, provider: &str, duration: Duration) {
+        self.limiter.pause(provider, duration);
+    }
+    
+    /// Apply the `x-ratelimit-*` and `Retry-After` headers of a provider's response
+    pub fn update_from_headers(&self, provider: &str, headers: &reqwest::header::HeaderMap) {
+        let headers = RateLimitHeaders::from_header_map(headers);
+        self.limiter.update_from_headers(provider, &headers);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::time::Instant;
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_acquire_paces_requests() {
+        let scheduler = Arc::new(RequestScheduler::new());
+        scheduler.register("test", vec![RateLimit::new(2, Duration::from_millis(200))]);
+        
+        let start = Instant::now();
+        let tasks: Vec<_> = (0..5)
+            .map(|_| {
+                let scheduler = scheduler.clone();
+                tokio::spawn(async move { scheduler.acquire("test", 1).await })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+        
+        // 5 requests at 2 per 200ms need two full windows
+        assert!(start.elapsed() >= Duration::from_millis(400));
+        assert!(start.elapsed() < Duration::from_millis(1000));
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_weights_and_estimates() {
+        let scheduler = RequestScheduler::new();
+        scheduler.register("binance", vec![RateLimit::new(10, Duration::from_secs(60))]);
+        
+        scheduler.acquire("binance", 6).await.unwrap();
+        assert_eq!(scheduler.estimated_wait("binance", 4), Duration::ZERO);
+        
+        let wait = scheduler.estimated_wait("binance", 5);
+        assert!(wait > Duration::from_secs(59) && wait <= Duration::from_secs(60));
+        
+        assert!(scheduler.acquire("binance", 11).await.is_err());
+        assert_eq!(scheduler.estimated_wait("unlimited", 100), Duration::ZERO);
+        scheduler.acquire("unlimited", 100).await.unwrap();
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_pause() {
+        let scheduler = RequestScheduler::new();
+        scheduler.register("test", vec![RateLimit::per_second(100)]);
+        scheduler.pause("test", Duration::from_millis(100));
+        
+        assert!(scheduler.estimated_wait("test", 1) > Duration::from_millis(50));
+        
+        let start = Instant::now();
+        scheduler.acquire("test", 1).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+    
+    #[test]
+    fn test_known_limits() {
+        assert_eq!(known_limits("alphavantage").len(), 2);
+        assert_eq!(known_limits("fred"), vec![RateLimit::per_minute(120)]);
+        assert!(known_limits("mock").is_empty());
+    }
+}
@@ -2,6 +2,10 @@ use std::collections::HashMap;
 use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
 
+pub mod oms;
+
+pub use oms::{FileJournal, ManagedOrder, MemoryJournal, OrderJournal, OrderManager, OrderState, Reconciliation};
+
 /// Represents a trading order
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Order {
@@ -0,0 +1,724 @@
+//! Order management
+//!
+//! An [`OrderManager`] sits between strategies and a [`TradingProvider`] and
+//! keeps live trading safe over connections that drop:
+//!
+//! - Every order carries a client order ID. Submitting an ID again returns
+//!   the order already known for it instead of placing a duplicate.
+//! - Orders move through a state machine (new, acknowledged, partially
+//!   filled, then filled, canceled or rejected). Updates that would move an
+//!   order backwards, such as a late "open" report after a fill, are ignored.
+//! - Every change is written to an [`OrderJournal`] before anything else
+//!   happens, so a restarted process knows which orders it may have placed.
+//! - [`reconcile`](OrderManager::reconcile) queries the provider for every
+//!   order that is not finished, including orders whose submission never got
+//!   an answer, and should be called after reconnecting.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use serde::{Serialize, Deserialize};
+
+use crate::{Error, Result};
+use crate::provider::TradingProvider;
+use crate::trading::{Order, OrderStatus};
+
+/// State of a managed order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderState {
+    /// Recorded and sent, but not yet acknowledged by the provider
+    New,
+
+    /// Accepted by the provider and working
+    Acknowledged,
+
+    /// Partly executed and still working
+    PartiallyFilled,
+
+    /// Fully executed
+    Filled,
+
+    /// Canceled before being fully executed
+    Canceled,
+
+    /// Refused by the provider, or never received by it
+    Rejected,
+}
+
+impl OrderState {
+    /// Check if the order can no longer change
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, OrderState::Filled | OrderState::Canceled | OrderState::Rejected)
+    }
+
+    /// Check if an order in this state can move to `next`
+    ///
+    /// Staying in the same state is allowed, so repeated reports are harmless.
+    /// Orders may skip states, as a market order can be filled before its
+    /// acknowledgement arrives.
+    pub fn can_transition_to(&self, next: OrderState) -> bool {
+        use OrderState::*;
+
+        match (self, next) {
+            (current, next) if *current == next => true,
+            (New, _) => true,
+            (Acknowledged, PartiallyFilled | Filled | Canceled | Rejected) => true,
+            (PartiallyFilled, Filled | Canceled) => true,
+            _ => false,
+        }
+    }
+
+    /// Get the state a provider's order status describes
+    pub fn from_status(status: &OrderStatus) -> Self {
+        if status.is_rejected() {
+            OrderState::Rejected
+        } else if status.is_cancelled() || status.status == "expired" {
+            OrderState::Canceled
+        } else if status.is_filled() {
+            OrderState::Filled
+        } else if status.filled_quantity > 0.0 {
+            OrderState::PartiallyFilled
+        } else {
+            OrderState::Acknowledged
+        }
+    }
+}
+
+/// An order tracked by an [`OrderManager`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManagedOrder {
+    /// Client order ID, unique per order
+    pub client_order_id: String,
+
+    /// Order as submitted
+    pub order: Order,
+
+    /// Current state
+    pub state: OrderState,
+
+    /// Order ID assigned by the provider, once known
+    pub provider_order_id: Option<String>,
+
+    /// Quantity executed so far
+    pub filled_quantity: f64,
+
+    /// Average execution price
+    pub average_price: Option<f64>,
+
+    /// Why the order was rejected
+    pub reason: Option<String>,
+
+    /// When the order was recorded
+    pub created_at: DateTime<Utc>,
+
+    /// When the order last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ManagedOrder {
+    /// Create a record for an order about to be submitted
+    fn new(client_order_id: String, order: Order) -> Self {
+        let now = Utc::now();
+        Self {
+            client_order_id,
+            order,
+            state: OrderState::New,
+            provider_order_id: None,
+            filled_quantity: 0.0,
+            average_price: None,
+            reason: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Apply a provider's order status, returning whether the order changed
+    ///
+    /// Statuses that would move the order backwards are ignored.
+    pub fn apply(&mut self, status: &OrderStatus) -> bool {
+        let next = OrderState::from_status(status);
+        if !self.state.can_transition_to(next) {
+            debug!(
+                "Ignoring stale {} status for order {} in state {:?}",
+                status.status, self.client_order_id, self.state
+            );
+            return false;
+        }
+
+        // Executions never shrink, whatever order reports arrive in
+        let filled_quantity = self.filled_quantity.max(status.filled_quantity);
+        let average_price = status.average_price.or(self.average_price);
+        let provider_order_id = Some(status.order_id.clone());
+
+        let changed = next != self.state
+            || filled_quantity != self.filled_quantity
+            || average_price != self.average_price
+            || provider_order_id != self.provider_order_id;
+        if changed {
+            self.state = next;
+            self.filled_quantity = filled_quantity;
+            self.average_price = average_price;
+            self.provider_order_id = provider_order_id;
+            self.updated_at = Utc::now();
+        }
+        changed
+    }
+
+    /// Mark the order as rejected
+    fn reject(&mut self, reason: String) {
+        self.state = OrderState::Rejected;
+        self.reason = Some(reason);
+        self.updated_at = Utc::now();
+    }
+}
+
+/// Durable record of order changes
+///
+/// Each entry is a snapshot of an order after a change; replaying the
+/// journal keeps the last snapshot of each order.
+pub trait OrderJournal: Send + Sync {
+    /// Durably append a snapshot
+    fn append(&self, order: &ManagedOrder) -> Result<()>;
+
+    /// Load every snapshot, oldest first
+    fn load(&self) -> Result<Vec<ManagedOrder>>;
+}
+
+/// Journal kept in memory, for tests and paper trading
+#[derive(Debug, Default)]
+pub struct MemoryJournal {
+    entries: Mutex<Vec<ManagedOrder>>,
+}
+
+impl MemoryJournal {
+    /// Create an empty journal
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl OrderJournal for MemoryJournal {
+    fn append(&self, order: &ManagedOrder) -> Result<()> {
+        self.entries.lock().unwrap().push(order.clone());
+        Ok(())
+    }
+
+    fn load(&self) -> Result<Vec<ManagedOrder>> {
+        Ok(self.entries.lock().unwrap().clone())
+    }
+}
+
+/// Journal stored as JSON lines in a file
+///
+/// Each snapshot is flushed to disk before [`append`](OrderJournal::append)
+/// returns. A line cut short by a crash is skipped when loading.
+#[derive(Debug)]
+pub struct FileJournal {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl FileJournal {
+    /// Open a journal, creating the file if it does not exist
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new().create(true).read(true).append(true).open(&path)?;
+
+        // End a line cut short by a crash, so the next snapshot starts on its own line
+        let len = file.metadata()?.len();
+        if len > 0 {
+            let mut last = [0u8; 1];
+            file.seek(SeekFrom::Start(len - 1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                file.write_all(b"\n")?;
+            }
+        }
+
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Get the path of the journal file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl OrderJournal for FileJournal {
+    fn append(&self, order: &ManagedOrder) -> Result<()> {
+        let mut line = serde_json::to_vec(order)?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&line)?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    fn load(&self) -> Result<Vec<ManagedOrder>> {
+        let reader = BufReader::new(File::open(&self.path)?);
+        let mut orders = Vec::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(order) => orders.push(order),
+                Err(e) => warn!("Skipping unreadable order journal entry in {}: {}", self.path.display(), e),
+            }
+        }
+
+        Ok(orders)
+    }
+}
+
+/// Manages orders placed through a trading provider
+pub struct OrderManager {
+    /// Provider orders are placed with
+    provider: Arc<dyn TradingProvider>,
+
+    /// Journal every change is written to
+    journal: Arc<dyn OrderJournal>,
+
+    /// Orders by client order ID
+    orders: Mutex<HashMap<String, ManagedOrder>>,
+}
+
+impl OrderManager {
+    /// Create a manager, restoring the orders recorded in `journal`
+    ///
+    /// Restored orders that are not finished should be
+    /// [reconciled](Self::reconcile) before trading resumes.
+    pub fn new(provider: Arc<dyn TradingProvider>, journal: Arc<dyn OrderJournal>) -> Result<Self> {
+        let orders = journal
+            .load()?
+            .into_iter()
+            .map(|order| (order.client_order_id.clone(), order))
+            .collect();
+
+        Ok(Self {
+            provider,
+            journal,
+            orders: Mutex::new(orders),
+        })
+    }
+
+    /// Submit an order
+    ///
+    /// Orders without a client order ID are given one. If an order with the
+    /// same client order ID is already known, it is returned as is and
+    /// nothing is placed, so a submission can be retried safely.
+    ///
+    /// If the provider cannot be reached, the order stays
+    /// [`New`](OrderState::New), as it may or may not have been received,
+    /// and the error is returned; [`reconcile`](Self::reconcile) settles it.
+    pub async fn submit(&self, mut order: Order) -> Result<ManagedOrder> {
+        order.validate().map_err(Error::ValidationError)?;
+
+        let client_order_id = order
+            .client_order_id
+            .clone()
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        order.client_order_id = Some(client_order_id.clone());
+
+        // Record the order before placing it, so a concurrent or later
+        // submission with the same ID finds it
+        {
+            let mut orders = self.orders.lock().unwrap();
+            if let Some(existing) = orders.get(&client_order_id) {
+                debug!("Order {} already submitted, not placing it again", client_order_id);
+                return Ok(existing.clone());
+            }
+
+            let managed = ManagedOrder::new(client_order_id.clone(), order.clone());
+            self.journal.append(&managed)?;
+            orders.insert(client_order_id.clone(), managed);
+        }
+
+        match self.provider.place_order(order).await {
+            Ok(status) => self.update(&client_order_id, |managed| managed.apply(&status)),
+            Err(e) if outcome_unknown(&e) => {
+                warn!("Submission of order {} failed with unknown outcome: {}", client_order_id, e);
+                Err(e)
+            },
+            Err(e) => {
+                self.update(&client_order_id, |managed| {
+                    managed.reject(e.to_string());
+                    true
+                })?;
+                Err(e)
+            },
+        }
+    }
+
+    /// Cancel an order
+    ///
+    /// Finished orders are returned unchanged.
+    pub async fn cancel(&self, client_order_id: &str) -> Result<ManagedOrder> {
+        let managed = self.get(client_order_id)
+            .ok_or_else(|| Error::TradingError(format!("Unknown order: {}", client_order_id)))?;
+        if managed.state.is_terminal() {
+            return Ok(managed);
+        }
+
+        let provider_order_id = managed.provider_order_id.ok_or_else(|| Error::TradingError(format!(
+            "Order {} has not been acknowledged yet, reconcile it before canceling", client_order_id
+        )))?;
+
+        if !self.provider.cancel_order(&provider_order_id).await? {
+            return Err(Error::TradingError(format!("Provider refused to cancel order {}", client_order_id)));
+        }
+
+        let status = self.provider.order_status(&provider_order_id).await?;
+        self.update(client_order_id, |managed| managed.apply(&status))
+    }
+
+    /// Apply an order status reported by the provider, such as an execution report
+    ///
+    /// Returns `None` if the status is for an order this manager did not place.
+    pub fn apply_status(&self, status: &OrderStatus) -> Result<Option<ManagedOrder>> {
+        let client_order_id = {
+            let orders = self.orders.lock().unwrap();
+            orders
+                .values()
+                .find(|managed| managed.provider_order_id.as_deref() == Some(status.order_id.as_str()))
+                .map(|managed| managed.client_order_id.clone())
+        };
+
+        match client_order_id {
+            Some(client_order_id) => self.update(&client_order_id, |managed| managed.apply(status)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Bring every unfinished order up to date with the provider
+    ///
+    /// Orders whose submission never got an answer are looked up by client
+    /// order ID; if the provider never received one, it is rejected and may
+    /// be submitted again under a new ID. An order that cannot be looked up
+    /// is left as it is and reported in [`Reconciliation::failed`], so one
+    /// order does not hold up the others.
+    pub async fn reconcile(&self) -> Reconciliation {
+        let pending: Vec<ManagedOrder> = self.orders.lock().unwrap()
+            .values()
+            .filter(|managed| !managed.state.is_terminal())
+            .cloned()
+            .collect();
+
+        let mut reconciliation = Reconciliation::default();
+        for managed in pending {
+            let client_order_id = managed.client_order_id.clone();
+            match self.reconcile_order(managed).await {
+                Ok(Some(order)) => reconciliation.changed.push(order),
+                Ok(None) => {},
+                Err(e) => {
+                    warn!("Could not reconcile order {}: {}", client_order_id, e);
+                    reconciliation.failed.push((client_order_id, e));
+                },
+            }
+        }
+
+        debug!(
+            "Reconciled orders, {} changed, {} failed",
+            reconciliation.changed.len(),
+            reconciliation.failed.len()
+        );
+        reconciliation
+    }
+
+    /// Bring one order up to date with the provider, returning it if it changed
+    async fn reconcile_order(&self, managed: ManagedOrder) -> Result<Option<ManagedOrder>> {
+        let status = match &managed.provider_order_id {
+            Some(provider_order_id) => Some(self.provider.order_status(provider_order_id).await?),
+            None => self.provider.order_by_client_id(&managed.client_order_id).await?,
+        };
+
+        let mut updated = false;
+        let order = self.update(&managed.client_order_id, |managed| {
+            updated = match &status {
+                Some(status) => managed.apply(status),
+                None => {
+                    managed.reject("Order was not received by the provider".to_string());
+                    true
+                },
+            };
+            updated
+        })?;
+
+        Ok(updated.then_some(order))
+    }
+
+    /// Get an order by client order ID
+    pub fn get(&self, client_order_id: &str) -> Option<ManagedOrder> {
+        self.orders.lock().unwrap().get(client_order_id).cloned()
+    }
+
+    /// Get all orders
+    pub fn orders(&self) -> Vec<ManagedOrder> {
+        self.orders.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Get the orders that are not finished
+    pub fn open_orders(&self) -> Vec<ManagedOrder> {
+        self.orders.lock().unwrap()
+            .values()
+            .filter(|managed| !managed.state.is_terminal())
+            .cloned()
+            .collect()
+    }
+
+    /// Change an order, journaling it if `change` reports a change
+    fn update<F>(&self, client_order_id: &str, change: F) -> Result<ManagedOrder>
+    where
+        F: FnOnce(&mut ManagedOrder) -> bool,
+    {
+        let mut orders = self.orders.lock().unwrap();
+        let managed = orders.get_mut(client_order_id)
+            .ok_or_else(|| Error::TradingError(format!("Unknown order: {}", client_order_id)))?;
+
+        // Journal the change before keeping it
+        let mut updated = managed.clone();
+        if change(&mut updated) {
+            self.journal.append(&updated)?;
+            *managed = updated;
+        }
+        Ok(managed.clone())
+    }
+}
+
+/// Outcome of [`OrderManager::reconcile`]
+#[derive(Debug, Default)]
+pub struct Reconciliation {
+    /// Orders that changed
+    pub changed: Vec<ManagedOrder>,
+
+    /// Orders that could not be brought up to date, by client order ID
+    pub failed: Vec<(String, Error)>,
+}
+
+/// Check if a failed submission may still have reached the provider
+fn outcome_unknown(error: &Error) -> bool {
+    matches!(error, Error::NetworkError(_) | Error::HttpError(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use crate::market::{OrderBook, TradeHistory};
+    use crate::provider::Provider;
+    use crate::trading::Position;
+
+    /// Provider that can drop submissions and remembers what it received
+    #[derive(Default)]
+    struct FlakyProvider {
+        /// Whether the next submission fails after being received
+        drop_response: Mutex<bool>,
+
+        /// Whether the next submission fails before being received
+        drop_request: Mutex<bool>,
+
+        /// Orders received, by client order ID
+        received: Mutex<HashMap<String, OrderStatus>>,
+
+        /// Client order IDs whose lookup fails
+        failing_lookups: Mutex<Vec<String>>,
+    }
+
+    impl Provider for FlakyProvider {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+    }
+
+    #[async_trait]
+    impl TradingProvider for FlakyProvider {
+        async fn order_book(&self, symbol: &str, _depth: Option<u32>) -> Result<OrderBook> {
+            Ok(OrderBook {
+                symbol: symbol.to_string(),
+                timestamp: Utc::now(),
+                bids: vec![],
+                asks: vec![],
+            })
+        }
+
+        async fn recent_trades(&self, symbol: &str, _limit: Option<u32>) -> Result<TradeHistory> {
+            Ok(TradeHistory {
+                symbol: symbol.to_string(),
+                trades: vec![],
+            })
+        }
+
+        async fn place_order(&self, order: Order) -> Result<OrderStatus> {
+            if std::mem::take(&mut *self.drop_request.lock().unwrap()) {
+                return Err(Error::NetworkError("connection reset".to_string()));
+            }
+
+            let mut received = self.received.lock().unwrap();
+            let order_id = format!("p{}", received.len() + 1);
+            let status = OrderStatus::new(&order_id, &order.symbol, "new", &order.order_type, &order.side, order.quantity, Utc::now());
+            received.insert(order.client_order_id.clone().unwrap(), status.clone());
+
+            if std::mem::take(&mut *self.drop_response.lock().unwrap()) {
+                return Err(Error::NetworkError("connection reset".to_string()));
+            }
+            Ok(status)
+        }
+
+        async fn order_status(&self, order_id: &str) -> Result<OrderStatus> {
+            self.received.lock().unwrap()
+                .values()
+                .find(|status| status.order_id == order_id)
+                .cloned()
+                .ok_or_else(|| Error::TradingError(format!("No order {}", order_id)))
+        }
+
+        async fn cancel_order(&self, _order_id: &str) -> Result<bool> {
+            Ok(true)
+        }
+
+        async fn positions(&self) -> Result<Vec<Position>> {
+            Ok(vec![])
+        }
+
+        async fn order_by_client_id(&self, client_order_id: &str) -> Result<Option<OrderStatus>> {
+            if self.failing_lookups.lock().unwrap().iter().any(|id| id == client_order_id) {
+                return Err(Error::NetworkError("connection reset".to_string()));
+            }
+            Ok(self.received.lock().unwrap().get(client_order_id).cloned())
+        }
+    }
+
+    fn status(state: &str, filled: f64) -> OrderStatus {
+        let mut status = OrderStatus::new("p1", "AAPL", state, "limit", "buy", 10.0, Utc::now());
+        status.filled_quantity = filled;
+        status
+    }
+
+    #[test]
+    fn test_state_machine() {
+        let mut managed = ManagedOrder::new("c1".to_string(), Order::new("AAPL", "buy", 10.0));
+
+        assert!(managed.apply(&status("new", 0.0)));
+        assert_eq!(managed.state, OrderState::Acknowledged);
+        assert_eq!(managed.provider_order_id.as_deref(), Some("p1"));
+
+        assert!(managed.apply(&status("partially_filled", 4.0)));
+        assert_eq!(managed.state, OrderState::PartiallyFilled);
+
+        // A late acknowledgement does not undo the fill
+        assert!(!managed.apply(&status("open", 0.0)));
+        assert_eq!(managed.state, OrderState::PartiallyFilled);
+        assert_eq!(managed.filled_quantity, 4.0);
+
+        assert!(managed.apply(&status("filled", 10.0)));
+        assert_eq!(managed.state, OrderState::Filled);
+        assert!(!managed.apply(&status("cancelled", 10.0)));
+
+        assert!(OrderState::New.can_transition_to(OrderState::Filled));
+        assert!(!OrderState::PartiallyFilled.can_transition_to(OrderState::Rejected));
+        assert!(!OrderState::Rejected.can_transition_to(OrderState::Acknowledged));
+    }
+
+    #[tokio::test]
+    async fn test_submit_is_idempotent() {
+        let provider = Arc::new(FlakyProvider::default());
+        let manager = OrderManager::new(provider.clone(), Arc::new(MemoryJournal::new())).unwrap();
+
+        let order = Order::new("AAPL", "buy", 10.0).client_order_id("c1");
+        let first = manager.submit(order.clone()).await.unwrap();
+        let second = manager.submit(order).await.unwrap();
+
+        assert_eq!(first.state, OrderState::Acknowledged);
+        assert_eq!(second.provider_order_id, first.provider_order_id);
+        assert_eq!(provider.received.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_after_lost_submissions() {
+        let provider = Arc::new(FlakyProvider::default());
+        let journal = Arc::new(MemoryJournal::new());
+        let manager = OrderManager::new(provider.clone(), journal.clone()).unwrap();
+
+        // Received, but the answer was lost
+        *provider.drop_response.lock().unwrap() = true;
+        assert!(manager.submit(Order::new("AAPL", "buy", 10.0).client_order_id("received")).await.is_err());
+
+        // Never received
+        *provider.drop_request.lock().unwrap() = true;
+        assert!(manager.submit(Order::new("AAPL", "buy", 5.0).client_order_id("lost")).await.is_err());
+
+        assert_eq!(manager.open_orders().len(), 2);
+
+        // A restarted process picks the orders up from the journal
+        let restarted = OrderManager::new(provider.clone(), journal).unwrap();
+        let reconciliation = restarted.reconcile().await;
+        assert_eq!(reconciliation.changed.len(), 2);
+        assert!(reconciliation.failed.is_empty());
+
+        let received = restarted.get("received").unwrap();
+        assert_eq!(received.state, OrderState::Acknowledged);
+        assert_eq!(received.provider_order_id.as_deref(), Some("p1"));
+        assert_eq!(restarted.get("lost").unwrap().state, OrderState::Rejected);
+        assert_eq!(restarted.open_orders().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_skips_failed_lookups() {
+        let provider = Arc::new(FlakyProvider::default());
+        let manager = OrderManager::new(provider.clone(), Arc::new(MemoryJournal::new())).unwrap();
+
+        for client_order_id in ["a", "b", "c"] {
+            *provider.drop_response.lock().unwrap() = true;
+            let order = Order::new("AAPL", "buy", 1.0).client_order_id(client_order_id);
+            assert!(manager.submit(order).await.is_err());
+        }
+        provider.failing_lookups.lock().unwrap().push("b".to_string());
+
+        let reconciliation = manager.reconcile().await;
+        assert_eq!(reconciliation.changed.len(), 2);
+        assert_eq!(reconciliation.failed.len(), 1);
+        assert_eq!(reconciliation.failed[0].0, "b");
+        assert_eq!(manager.get("a").unwrap().state, OrderState::Acknowledged);
+        assert_eq!(manager.get("b").unwrap().state, OrderState::New);
+        assert_eq!(manager.get("c").unwrap().state, OrderState::Acknowledged);
+
+        // The order is picked up once the lookup works again
+        provider.failing_lookups.lock().unwrap().clear();
+        let reconciliation = manager.reconcile().await;
+        assert_eq!(reconciliation.changed.len(), 1);
+        assert_eq!(manager.get("b").unwrap().state, OrderState::Acknowledged);
+    }
+
+    #[test]
+    fn test_file_journal() {
+        let path = std::env::temp_dir().join(format!("oms-journal-{}.jsonl", uuid::Uuid::new_v4()));
+        let journal = FileJournal::open(&path).unwrap();
+
+        let mut managed = ManagedOrder::new("c1".to_string(), Order::new("AAPL", "buy", 10.0));
+        journal.append(&managed).unwrap();
+        managed.apply(&status("filled", 10.0));
+        journal.append(&managed).unwrap();
+
+        // A torn last line is skipped
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"client_order_id\":").unwrap();
+
+        let reopened = FileJournal::open(&path).unwrap();
+        reopened.append(&managed).unwrap();
+        let loaded = reopened.load().unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded[2].state, OrderState::Filled);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -97,7 +97,7 @@ impl BrowserType {
     pub fn random() -> BrowserType {
         use rand::seq::SliceRandom;
         let browsers = BrowserType::all();
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::random::rng();
         browsers.choose(&mut rng).unwrap().clone()
     }
 }
@@ -0,0 +1,207 @@
+//! Browser cache simulation
+//!
+//! A real browser does not send identical requests when it returns to a site:
+//! it revalidates pages it has cached with `If-None-Match` and
+//! `If-Modified-Since`, and plain link navigations carry no cache directives
+//! at all. [`BrowserCache`] remembers the validators of earlier responses and
+//! the pages visited in a session, and adjusts generated headers accordingly.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// Cache validators of a response
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheEntry {
+    /// Value of the ETag header
+    pub etag: Option<String>,
+    
+    /// Value of the Last-Modified header
+    pub last_modified: Option<String>,
+}
+
+/// Cache state of a browsing session
+#[derive(Debug, Default)]
+struct CacheState {
+    /// Validators by page URL
+    entries: HashMap<String, CacheEntry>,
+    
+    /// Pages requested in the session
+    visited: HashSet<String>,
+    
+    /// Number of requests per domain
+    requests_per_domain: HashMap<String, u32>,
+}
+
+/// Simulated HTTP cache of a browsing session
+///
+/// Clones share the same state, so a cache can be handed to several
+/// generators that represent the same browser.
+#[derive(Debug, Clone, Default)]
+pub struct BrowserCache {
+    state: Arc<Mutex<CacheState>>,
+}
+
+impl BrowserCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Store the validators of a response
+    ///
+    /// Header names are matched case-insensitively. Responses marked
+    /// `Cache-Control: no-store` are not cached.
+    pub fn record_response(&self, url: &str, response_headers: &HashMap<String, String>) {
+        let header = |name: &str| {
+            response_headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.trim().to_string())
+        };
+        
+        let no_store = header("Cache-Control").is_some_and(|value| value.to_lowercase().contains("no-store"));
+        let entry = CacheEntry {
+            etag: header("ETag"),
+            last_modified: header("Last-Modified"),
+        };
+        
+        let key = cache_key(url);
+        let mut state = self.state.lock().unwrap();
+        if no_store || (entry.etag.is_none() && entry.last_modified.is_none()) {
+            state.entries.remove(&key);
+        } else {
+            state.entries.insert(key, entry);
+        }
+    }
+    
+    /// Get the stored validators of a page
+    pub fn entry(&self, url: &str) -> Option<CacheEntry> {
+        self.state.lock().unwrap().entries.get(&cache_key(url)).cloned()
+    }
+    
+    /// Get the number of requests made to a domain
+    pub fn requests_to(&self, domain: &str) -> u32 {
+        self.state.lock().unwrap().requests_per_domain.get(domain).copied().unwrap_or(0)
+    }
+    
+    /// Forget all cached responses and visits
+    pub fn clear(&self) {
+        *self.state.lock().unwrap() = CacheState::default();
+    }
+    
+    /// Adjust the headers of a request and record it
+    ///
+    /// - First request to a domain: headers are left untouched.
+    /// - New page on a known domain: cache directives are dropped, as on a
+    ///   plain link navigation.
+    /// - Repeat request for a page: sent as a revalidation with
+    ///   `Cache-Control: max-age=0` and the stored validators.
+    pub fn apply(&self, url: &str, headers: &mut HashMap<String, String>) {
+        let key = cache_key(url);
+        let domain = domain_of(url);
+        let mut state = self.state.lock().unwrap();
+        
+        let domain_requests = state.requests_per_domain.entry(domain).or_insert(0);
+        let known_domain = *domain_requests > 0;
+        *domain_requests += 1;
+        
+        let repeat = !state.visited.insert(key.clone());
+        
+        if repeat {
+            headers.remove("Pragma");
+            headers.insert("Cache-Control".to_string(), "max-age=0".to_string());
+            
+            if let Some(entry) = state.entries.get(&key) {
+                if let Some(etag) = &entry.etag {
+                    headers.insert("If-None-Match".to_string(), etag.clone());
+                }
+                if let Some(last_modified) = &entry.last_modified {
+                    headers.insert("If-Modified-Since".to_string(), last_modified.clone());
+                }
+            }
+        } else if known_domain {
+            headers.remove("Pragma");
+            headers.remove("Cache-Control");
+        }
+    }
+}
+
+/// Get the cache key of a URL (the URL without its fragment)
+fn cache_key(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(mut parsed) => {
+            parsed.set_fragment(None);
+            parsed.to_string()
+        },
+        Err(_) => url.to_string(),
+    }
+}
+
+/// Get the domain of a URL
+fn domain_of(url: &str) -> String {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|parsed| parsed.host_str().map(str::to_lowercase))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn pristine() -> HashMap<String, String> {
+        let mut headers = HashMap::new();
+        headers.insert("Cache-Control".to_string(), "no-cache".to_string());
+        headers.insert("Pragma".to_string(), "no-cache".to_string());
+        headers
+    }
+    
+    #[test]
+    fn test_repeat_visits_drift() {
+        let cache = BrowserCache::new();
+        
+        // First visit is untouched
+        let mut headers = pristine();
+        cache.apply("https://example.com/", &mut headers);
+        assert_eq!(headers, pristine());
+        
+        let mut response = HashMap::new();
+        response.insert("etag".to_string(), "\"abc123\"".to_string());
+        response.insert("Last-Modified".to_string(), "Wed, 21 Oct 2015 07:28:00 GMT".to_string());
+        cache.record_response("https://example.com/", &response);
+        
+        // New page on the same site drops cache directives
+        let mut headers = pristine();
+        cache.apply("https://example.com/about", &mut headers);
+        assert!(!headers.contains_key("Cache-Control"));
+        assert!(!headers.contains_key("Pragma"));
+        
+        // Revisit revalidates with the stored validators
+        let mut headers = pristine();
+        cache.apply("https://example.com/#top", &mut headers);
+        assert_eq!(headers["Cache-Control"], "max-age=0");
+        assert_eq!(headers["If-None-Match"], "\"abc123\"");
+        assert_eq!(headers["If-Modified-Since"], "Wed, 21 Oct 2015 07:28:00 GMT");
+        assert!(!headers.contains_key("Pragma"));
+        
+        assert_eq!(cache.requests_to("example.com"), 3);
+    }
+    
+    #[test]
+    fn test_no_store_is_not_cached() {
+        let cache = BrowserCache::new();
+        let mut response = HashMap::new();
+        response.insert("ETag".to_string(), "\"v1\"".to_string());
+        cache.record_response("https://example.com/", &response);
+        assert!(cache.entry("https://example.com/").is_some());
+        
+        response.insert("Cache-Control".to_string(), "private, no-store".to_string());
+        cache.record_response("https://example.com/", &response);
+        assert!(cache.entry("https://example.com/").is_none());
+        
+        // Clones share state
+        let clone = cache.clone();
+        clone.clear();
+        assert_eq!(cache.requests_to("example.com"), 0);
+    }
+}
@@ -100,7 +100,7 @@ impl DeviceType {
     pub fn random() -> DeviceType {
         use rand::seq::SliceRandom;
         let devices = DeviceType::all();
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::random::rng();
         devices.choose(&mut rng).unwrap().clone()
     }
     
@@ -111,7 +111,7 @@ impl DeviceType {
             DeviceType::Mobile,
             DeviceType::Tablet,
         ];
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::random::rng();
         devices.choose(&mut rng).unwrap().clone()
     }
     
@@ -123,7 +123,7 @@ impl DeviceType {
             DeviceType::Console,
             DeviceType::TV,
         ];
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::random::rng();
         devices.choose(&mut rng).unwrap().clone()
     }
 }
@@ -243,7 +243,7 @@ impl DeviceBrand {
             DeviceBrand::Acer,
             DeviceBrand::Asus,
         ];
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::random::rng();
         brands.choose(&mut rng).unwrap().clone()
     }
     
@@ -262,7 +262,7 @@ impl DeviceBrand {
             DeviceBrand::Motorola,
             DeviceBrand::Nokia,
         ];
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::random::rng();
         brands.choose(&mut rng).unwrap().clone()
     }
     
@@ -278,7 +278,7 @@ impl DeviceBrand {
             DeviceBrand::Acer,
             DeviceBrand::Asus,
         ];
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::random::rng();
         brands.choose(&mut rng).unwrap().clone()
     }
     
@@ -289,25 +289,25 @@ impl DeviceBrand {
             (DeviceBrand::Apple, DeviceType::Mobile) => {
                 use rand::seq::SliceRandom;
                 let models = ["iPhone 13", "iPhone 14", "iPhone 14 Pro", "iPhone 15", "iPhone 15 Pro"];
-                let mut rng = rand::thread_rng();
+                let mut rng = crate::random::rng();
                 models.choose(&mut rng).unwrap().to_string()
             },
             (DeviceBrand::Apple, DeviceType::Tablet) => {
                 use rand::seq::SliceRandom;
                 let models = ["iPad", "iPad Pro", "iPad Air", "iPad Mini"];
-                let mut rng = rand::thread_rng();
+                let mut rng = crate::random::rng();
                 models.choose(&mut rng).unwrap().to_string()
             },
             (DeviceBrand::Apple, DeviceType::Desktop) => {
                 use rand::seq::SliceRandom;
                 let models = ["MacBook Air", "MacBook Pro", "iMac", "Mac Mini", "Mac Pro"];
-                let mut rng = rand::thread_rng();
+                let mut rng = crate::random::rng();
                 models.choose(&mut rng).unwrap().to_string()
             },
             (DeviceBrand::Samsung, DeviceType::Mobile) => {
                 use rand::seq::SliceRandom;
                 let models = ["Galaxy S22", "Galaxy S23", "Galaxy S23 Ultra", "Galaxy A54", "Galaxy Z Fold4"];
-                let mut rng = rand::thread_rng();
+                let mut rng = crate::random::rng();
                 models.choose(&mut rng).unwrap().to_string()
             },
             // Default to a generic model based on brand and device type
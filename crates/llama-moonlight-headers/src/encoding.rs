@@ -0,0 +1,212 @@
+//! Content encoding negotiation
+//!
+//! Browsers advertise different compression algorithms depending on their
+//! version: Chromium added `zstd` in version 123 and Firefox in version 126.
+//! This module builds `Accept-Encoding` values that match a browser version,
+//! and (with the `decode` feature) decodes response bodies according to their
+//! `Content-Encoding`, so that HTTP clients sending these headers can read the
+//! responses.
+
+use std::fmt;
+use crate::{BrowserType, HeaderError, Result};
+
+/// First Chromium version advertising zstd
+pub const CHROMIUM_ZSTD_VERSION: u32 = 123;
+
+/// First Firefox version advertising zstd
+pub const FIREFOX_ZSTD_VERSION: u32 = 126;
+
+/// Content codings understood by browsers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentEncoding {
+    /// No encoding
+    Identity,
+    
+    /// gzip
+    Gzip,
+    
+    /// zlib-wrapped deflate
+    Deflate,
+    
+    /// Brotli
+    Brotli,
+    
+    /// Zstandard
+    Zstd,
+}
+
+impl ContentEncoding {
+    /// Get the token used in HTTP headers
+    pub fn token(&self) -> &'static str {
+        match self {
+            ContentEncoding::Identity => "identity",
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+            ContentEncoding::Brotli => "br",
+            ContentEncoding::Zstd => "zstd",
+        }
+    }
+    
+    /// Parse a content coding token
+    pub fn from_token(token: &str) -> Option<Self> {
+        match token.trim().to_lowercase().as_str() {
+            "identity" | "" => Some(ContentEncoding::Identity),
+            "gzip" | "x-gzip" => Some(ContentEncoding::Gzip),
+            "deflate" => Some(ContentEncoding::Deflate),
+            "br" => Some(ContentEncoding::Brotli),
+            "zstd" => Some(ContentEncoding::Zstd),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ContentEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.token())
+    }
+}
+
+/// Get the encodings a browser version advertises, in header order
+pub fn supported_encodings(browser_type: &BrowserType, major_version: Option<u32>) -> Vec<ContentEncoding> {
+    let mut encodings = vec![ContentEncoding::Gzip, ContentEncoding::Deflate, ContentEncoding::Brotli];
+    
+    let zstd_version = match browser_type {
+        BrowserType::Chrome | BrowserType::Edge | BrowserType::Opera => Some(CHROMIUM_ZSTD_VERSION),
+        BrowserType::Firefox => Some(FIREFOX_ZSTD_VERSION),
+        BrowserType::Safari | BrowserType::Custom(_) => None,
+    };
+    
+    if let (Some(zstd_version), Some(version)) = (zstd_version, major_version) {
+        if version >= zstd_version {
+            encodings.push(ContentEncoding::Zstd);
+        }
+    }
+    
+    encodings
+}
+
+/// Get the Accept-Encoding header a browser version sends
+pub fn accept_encoding(browser_type: &BrowserType, major_version: Option<u32>) -> String {
+    supported_encodings(browser_type, major_version)
+        .iter()
+        .map(ContentEncoding::token)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Parse a Content-Encoding header into the codings in the order they were applied
+pub fn parse_content_encoding(header: &str) -> Result<Vec<ContentEncoding>> {
+    header
+        .split(',')
+        .filter(|token| !token.trim().is_empty())
+        .map(|token| {
+            ContentEncoding::from_token(token)
+                .ok_or_else(|| HeaderError::UnsupportedEncoding(token.trim().to_string()))
+        })
+        .filter(|encoding| !matches!(encoding, Ok(ContentEncoding::Identity)))
+        .collect()
+}
+
+/// Decode a response body according to its Content-Encoding header
+///
+/// Codings are undone in reverse order of application. A missing or empty
+/// header returns the body unchanged.
+#[cfg(feature = "decode")]
+pub fn decode_body(content_encoding: Option<&str>, body: &[u8]) -> Result<Vec<u8>> {
+    let encodings = match content_encoding {
+        Some(header) => parse_content_encoding(header)?,
+        None => Vec::new(),
+    };
+    
+    let mut body = body.to_vec();
+    for encoding in encodings.iter().rev() {
+        body = decode(*encoding, &body)?;
+    }
+    
+    Ok(body)
+}
+
+/// Undo a single content coding
+#[cfg(feature = "decode")]
+pub fn decode(encoding: ContentEncoding, body: &[u8]) -> Result<Vec<u8>> {
+    use std::io::Read;
+    
+    let mut decoded = Vec::new();
+    let result = match encoding {
+        ContentEncoding::Identity => {
+            decoded.extend_from_slice(body);
+            Ok(0)
+        },
+        ContentEncoding::Gzip => flate2::read::MultiGzDecoder::new(body).read_to_end(&mut decoded),
+        ContentEncoding::Deflate => {
+            // Some servers send raw deflate despite the zlib wrapper the spec requires
+            match flate2::read::ZlibDecoder::new(body).read_to_end(&mut decoded) {
+                Ok(n) => Ok(n),
+                Err(_) => {
+                    decoded.clear();
+                    flate2::read::DeflateDecoder::new(body).read_to_end(&mut decoded)
+                },
+            }
+        },
+        ContentEncoding::Brotli => brotli::Decompressor::new(body, 4096).read_to_end(&mut decoded),
+        ContentEncoding::Zstd => zstd::stream::read::Decoder::new(body).and_then(|mut d| d.read_to_end(&mut decoded)),
+    };
+    
+    result
+        .map(|_| decoded)
+        .map_err(|e| HeaderError::DecodeError(format!("{}: {}", encoding, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_accept_encoding_by_version() {
+        assert_eq!(accept_encoding(&BrowserType::Chrome, Some(117)), "gzip, deflate, br");
+        assert_eq!(accept_encoding(&BrowserType::Chrome, Some(124)), "gzip, deflate, br, zstd");
+        assert_eq!(accept_encoding(&BrowserType::Firefox, Some(125)), "gzip, deflate, br");
+        assert_eq!(accept_encoding(&BrowserType::Firefox, Some(128)), "gzip, deflate, br, zstd");
+        assert_eq!(accept_encoding(&BrowserType::Safari, Some(17)), "gzip, deflate, br");
+        assert_eq!(accept_encoding(&BrowserType::Edge, None), "gzip, deflate, br");
+    }
+    
+    #[test]
+    fn test_parse_content_encoding() {
+        assert_eq!(
+            parse_content_encoding("gzip, BR").unwrap(),
+            vec![ContentEncoding::Gzip, ContentEncoding::Brotli]
+        );
+        assert!(parse_content_encoding("identity").unwrap().is_empty());
+        assert!(parse_content_encoding("compress").is_err());
+    }
+    
+    #[cfg(feature = "decode")]
+    #[test]
+    fn test_decode_body() {
+        use std::io::Write;
+        
+        let text = b"<html>llama moonlight</html>";
+        
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(text).unwrap();
+        let gzip = gzip.finish().unwrap();
+        assert_eq!(decode_body(Some("gzip"), &gzip).unwrap(), text);
+        
+        let mut br = Vec::new();
+        brotli::CompressorWriter::new(&mut br, 4096, 5, 22).write_all(text).unwrap();
+        assert_eq!(decode_body(Some("br"), &br).unwrap(), text);
+        
+        let zstd = zstd::encode_all(&text[..], 3).unwrap();
+        assert_eq!(decode_body(Some("zstd"), &zstd).unwrap(), text);
+        
+        // Codings are undone in reverse order
+        let mut raw = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        raw.write_all(&zstd).unwrap();
+        let layered = raw.finish().unwrap();
+        assert_eq!(decode_body(Some("zstd, deflate"), &layered).unwrap(), text);
+        
+        assert_eq!(decode_body(None, text).unwrap(), text);
+        assert!(decode_body(Some("br"), text).is_err());
+    }
+}
@@ -0,0 +1,575 @@
+//! Fingerprint entropy reports
+//!
+//! An identity blends in when many real browsers share it. [`EntropyAnalyzer`]
+//! looks up each attribute of a fingerprint (browser, platform, screen, pixel
+//! ratio, hardware, timezone, language) in a distribution of real-world
+//! traffic and adds up how many bits of identifying information they carry,
+//! which estimates how rare the exact combination is. It also checks the
+//! fingerprint against the headers sent with it, warning about combinations
+//! real browsers never produce, such as client hints from Firefox or a
+//! Safari user agent on Windows.
+//!
+//! A distribution is bundled with the crate; others can be loaded with
+//! [`Distribution::from_json`] in the same format.
+
+use std::collections::HashMap;
+use std::fmt;
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use crate::fingerprint::BrowserFingerprint;
+use crate::useragent::{
+    is_mobile_user_agent, parse_browser_from_user_agent, parse_major_version_from_user_agent,
+    parse_platform_from_user_agent,
+};
+use crate::{BrowserType, PlatformType, Result};
+
+/// Bundled distribution of fingerprint attribute values
+const BUNDLED_DISTRIBUTION: &str = include_str!("../data/fingerprint_distribution.json");
+
+/// Frequency below which an attribute value is reported as rare
+pub const DEFAULT_RARE_THRESHOLD: f64 = 0.01;
+
+lazy_static! {
+    static ref BUNDLED: Distribution = Distribution::from_json(BUNDLED_DISTRIBUTION)
+        .expect("bundled fingerprint distribution is valid JSON");
+}
+
+/// Share of browsers having each value of fingerprint attributes
+#[derive(Debug, Clone, Deserialize)]
+pub struct Distribution {
+    /// Frequencies by attribute name, then segment (`desktop`, `mobile` or `all`), then value
+    pub attributes: HashMap<String, HashMap<String, HashMap<String, f64>>>,
+    
+    /// Frequency assumed for values that are not listed
+    pub unlisted_frequency: f64,
+}
+
+impl Distribution {
+    /// Get the distribution bundled with the crate
+    pub fn bundled() -> &'static Distribution {
+        &BUNDLED
+    }
+    
+    /// Parse a distribution in the bundled JSON format
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+    
+    /// Get the frequency of an attribute value, if the attribute is known
+    ///
+    /// Frequencies of the device segment are used when listed, falling back
+    /// to those of all devices.
+    pub fn frequency(&self, attribute: &str, segment: &str, value: &str) -> Option<f64> {
+        let segments = self.attributes.get(attribute)?;
+        let values = segments.get(segment).or_else(|| segments.get("all"))?;
+        
+        Some(values.get(value).copied().unwrap_or(self.unlisted_frequency))
+    }
+}
+
+/// Identifying information carried by one attribute of a fingerprint
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeEntropy {
+    /// Attribute name, e.g. `screen`
+    pub name: &'static str,
+    
+    /// Value of the attribute, e.g. `1920x1080`
+    pub value: String,
+    
+    /// Share of browsers having this value
+    pub frequency: f64,
+    
+    /// Bits of identifying information (`-log2(frequency)`)
+    pub bits: f64,
+}
+
+/// Kinds of entropy warnings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// The value is shared by few browsers
+    RareValue,
+    
+    /// The attributes contradict each other, which real browsers never do
+    Inconsistent,
+}
+
+/// Problem found in an identity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntropyWarning {
+    /// Kind of problem
+    pub kind: WarningKind,
+    
+    /// Names of the attributes or headers involved
+    pub attributes: Vec<String>,
+    
+    /// Description of the problem
+    pub message: String,
+}
+
+impl EntropyWarning {
+    /// Create a warning about contradicting attributes
+    fn inconsistent(attributes: &[&str], message: String) -> Self {
+        Self {
+            kind: WarningKind::Inconsistent,
+            attributes: attributes.iter().map(|attribute| attribute.to_string()).collect(),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for EntropyWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.attributes.join(", "))
+    }
+}
+
+/// Entropy and plausibility of an identity
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntropyReport {
+    /// Entropy of each attribute found in the distribution
+    pub attributes: Vec<AttributeEntropy>,
+    
+    /// Total bits of identifying information, assuming independent attributes
+    pub total_bits: f64,
+    
+    /// Rare values and contradictions found
+    pub warnings: Vec<EntropyWarning>,
+}
+
+impl EntropyReport {
+    /// Get the estimated share of browsers with this exact combination
+    pub fn combination_frequency(&self) -> f64 {
+        2f64.powf(-self.total_bits)
+    }
+    
+    /// Get the estimated number of browsers among which one has this combination
+    pub fn one_in(&self) -> f64 {
+        2f64.powf(self.total_bits)
+    }
+    
+    /// Check whether no attributes contradict each other
+    pub fn is_plausible(&self) -> bool {
+        !self.warnings.iter().any(|warning| warning.kind == WarningKind::Inconsistent)
+    }
+    
+    /// Get the attribute carrying the most identifying information
+    pub fn most_identifying(&self) -> Option<&AttributeEntropy> {
+        self.attributes.iter().max_by(|a, b| a.bits.total_cmp(&b.bits))
+    }
+}
+
+/// Scores generated identities against a distribution of real browsers
+#[derive(Debug, Clone)]
+pub struct EntropyAnalyzer {
+    /// Distribution of attribute values
+    distribution: Distribution,
+    
+    /// Frequency below which values are reported as rare
+    rare_threshold: f64,
+}
+
+impl Default for EntropyAnalyzer {
+    fn default() -> Self {
+        Self {
+            distribution: Distribution::bundled().clone(),
+            rare_threshold: DEFAULT_RARE_THRESHOLD,
+        }
+    }
+}
+
+impl EntropyAnalyzer {
+    /// Create an analyzer using the bundled distribution
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Use another distribution
+    pub fn with_distribution(mut self, distribution: Distribution) -> Self {
+        self.distribution = distribution;
+        self
+    }
+    
+    /// Set the frequency below which values are reported as rare
+    pub fn with_rare_threshold(mut self, threshold: f64) -> Self {
+        self.rare_threshold = threshold;
+        self
+    }
+    
+    /// Score a fingerprint and the headers sent with it
+    pub fn analyze(&self, headers: &HashMap<String, String>, fingerprint: &BrowserFingerprint) -> EntropyReport {
+        let segment = if is_mobile_user_agent(&fingerprint.user_agent) { "mobile" } else { "desktop" };
+        
+        let mut attributes = Vec::new();
+        let mut warnings = Vec::new();
+        for (name, value) in attribute_values(fingerprint) {
+            let frequency = match self.distribution.frequency(name, segment, &value) {
+                Some(frequency) => frequency.clamp(f64::MIN_POSITIVE, 1.0),
+                None => continue,
+            };
+            
+            if frequency < self.rare_threshold {
+                warnings.push(EntropyWarning {
+                    kind: WarningKind::RareValue,
+                    attributes: vec![name.to_string()],
+                    message: format!("{} {} is shared by about {:.2}% of {} browsers", name, value, frequency * 100.0, segment),
+                });
+            }
+            
+            attributes.push(AttributeEntropy {
+                name,
+                bits: -frequency.log2(),
+                value,
+                frequency,
+            });
+        }
+        
+        warnings.extend(check_fingerprint(fingerprint));
+        warnings.extend(check_headers(headers, fingerprint));
+        
+        EntropyReport {
+            total_bits: attributes.iter().map(|attribute| attribute.bits).sum(),
+            attributes,
+            warnings,
+        }
+    }
+    
+    /// Score a fingerprint on its own
+    pub fn analyze_fingerprint(&self, fingerprint: &BrowserFingerprint) -> EntropyReport {
+        self.analyze(&HashMap::new(), fingerprint)
+    }
+}
+
+/// Score a fingerprint and its headers against the bundled distribution
+pub fn entropy_report(headers: &HashMap<String, String>, fingerprint: &BrowserFingerprint) -> EntropyReport {
+    EntropyAnalyzer::new().analyze(headers, fingerprint)
+}
+
+/// Get the values of the attributes listed in distributions
+fn attribute_values(fingerprint: &BrowserFingerprint) -> Vec<(&'static str, String)> {
+    let browser = parse_browser_from_user_agent(&fingerprint.user_agent)
+        .map(|browser| browser.name())
+        .unwrap_or_else(|| "other".to_string());
+    
+    let mut values = vec![
+        ("browser", browser),
+        ("platform", fingerprint.platform.clone()),
+        ("screen", format!("{}x{}", fingerprint.screen_width, fingerprint.screen_height)),
+        ("pixel_ratio", format_ratio(fingerprint.pixel_ratio)),
+        ("color_depth", fingerprint.color_depth.to_string()),
+        ("hardware_concurrency", fingerprint.hardware_concurrency.to_string()),
+        ("timezone", fingerprint.timezone.clone()),
+        ("language", fingerprint.language.clone()),
+    ];
+    
+    // Only Chromium browsers expose navigator.deviceMemory
+    if is_chromium(&fingerprint.user_agent) {
+        values.push(("device_memory", fingerprint.device_memory.to_string()));
+    }
+    
+    values
+}
+
+/// Find contradictions between the attributes of a fingerprint
+fn check_fingerprint(fingerprint: &BrowserFingerprint) -> Vec<EntropyWarning> {
+    let mut warnings = Vec::new();
+    let browser = parse_browser_from_user_agent(&fingerprint.user_agent);
+    let ua_platform = parse_platform_from_user_agent(&fingerprint.user_agent);
+    let mobile = is_mobile_user_agent(&fingerprint.user_agent);
+    
+    if let Some(ua_platform) = &ua_platform {
+        let expected = navigator_platforms(ua_platform);
+        if !expected.is_empty() && !expected.iter().any(|prefix| fingerprint.platform.starts_with(prefix)) {
+            warnings.push(EntropyWarning::inconsistent(
+                &["user_agent", "platform"],
+                format!("navigator.platform {:?} does not match a {} user agent", fingerprint.platform, ua_platform.name()),
+            ));
+        }
+    }
+    
+    if browser == Some(BrowserType::Safari) && !matches!(ua_platform, Some(PlatformType::MacOS) | Some(PlatformType::IOS)) {
+        warnings.push(EntropyWarning::inconsistent(
+            &["user_agent"],
+            "Safari only runs on Apple platforms".to_string(),
+        ));
+    }
+    
+    if let Some(browser) = &browser {
+        let expected = navigator_vendor(browser);
+        if fingerprint.vendor != expected {
+            warnings.push(EntropyWarning::inconsistent(
+                &["user_agent", "vendor"],
+                format!("{} reports navigator.vendor {:?}, not {:?}", browser.name(), expected, fingerprint.vendor),
+            ));
+        }
+    }
+    
+    if is_chromium(&fingerprint.user_agent) {
+        // Chromium rounds deviceMemory to a power of two and caps it at 8
+        if ![1, 2, 4, 8].contains(&fingerprint.device_memory) {
+            warnings.push(EntropyWarning::inconsistent(
+                &["device_memory"],
+                format!("navigator.deviceMemory is one of 0.25, 0.5, 1, 2, 4 or 8 in Chromium, not {}", fingerprint.device_memory),
+            ));
+        }
+    } else if browser.is_some() {
+        warnings.push(EntropyWarning::inconsistent(
+            &["user_agent", "device_memory"],
+            "navigator.deviceMemory is only exposed by Chromium browsers".to_string(),
+        ));
+    }
+    
+    if mobile && (!fingerprint.touch_supported || fingerprint.max_touch_points == 0) {
+        warnings.push(EntropyWarning::inconsistent(
+            &["user_agent", "max_touch_points"],
+            "Mobile user agent without touch support".to_string(),
+        ));
+    }
+    
+    if fingerprint.available_width > fingerprint.screen_width || fingerprint.available_height > fingerprint.screen_height {
+        warnings.push(EntropyWarning::inconsistent(
+            &["screen", "available_screen"],
+            "Available screen area is larger than the screen".to_string(),
+        ));
+    }
+    
+    if let (Some(renderer), Some(ua_platform)) = (&fingerprint.webgl_renderer, &ua_platform) {
+        let windows_renderer = renderer.contains("Direct3D");
+        let apple_renderer = renderer.contains("Apple");
+        let apple_platform = matches!(ua_platform, PlatformType::MacOS | PlatformType::IOS);
+        if (windows_renderer && *ua_platform != PlatformType::Windows) || (apple_renderer && !apple_platform) {
+            warnings.push(EntropyWarning::inconsistent(
+                &["user_agent", "webgl_renderer"],
+                format!("WebGL renderer {:?} does not exist on {}", renderer, ua_platform.name()),
+            ));
+        }
+    }
+    
+    if fingerprint.languages.first().is_some_and(|first| !first.eq_ignore_ascii_case(&fingerprint.language)) {
+        warnings.push(EntropyWarning::inconsistent(
+            &["language", "languages"],
+            "navigator.language is not the first of navigator.languages".to_string(),
+        ));
+    }
+    
+    warnings
+}
+
+/// Find contradictions between the headers and the fingerprint they are sent with
+fn check_headers(headers: &HashMap<String, String>, fingerprint: &BrowserFingerprint) -> Vec<EntropyWarning> {
+    let mut warnings = Vec::new();
+    
+    if let Some(user_agent) = header(headers, "User-Agent") {
+        if user_agent != fingerprint.user_agent {
+            warnings.push(EntropyWarning::inconsistent(
+                &["User-Agent", "user_agent"],
+                "User-Agent header differs from navigator.userAgent".to_string(),
+            ));
+        }
+    }
+    
+    let user_agent = &fingerprint.user_agent;
+    let browser = parse_browser_from_user_agent(user_agent);
+    let ua_platform = parse_platform_from_user_agent(user_agent);
+    
+    if let Some(sec_ch_ua) = header(headers, "Sec-Ch-Ua") {
+        if !is_chromium(user_agent) && browser.is_some() {
+            warnings.push(EntropyWarning::inconsistent(
+                &["Sec-Ch-Ua", "user_agent"],
+                "Client hints are only sent by Chromium browsers".to_string(),
+            ));
+        } else if let (Some(hinted), Some(actual)) = (
+            chromium_version_from_hint(sec_ch_ua),
+            parse_major_version_from_user_agent(user_agent, &BrowserType::Chrome),
+        ) {
+            if hinted != actual {
+                warnings.push(EntropyWarning::inconsistent(
+                    &["Sec-Ch-Ua", "user_agent"],
+                    format!("Sec-Ch-Ua announces Chromium {} but the user agent is Chromium {}", hinted, actual),
+                ));
+            }
+        }
+    }
+    
+    if let (Some(hinted), Some(ua_platform)) = (header(headers, "Sec-Ch-Ua-Platform"), &ua_platform) {
+        let hinted = hinted.trim_matches('"');
+        if !hinted.eq_ignore_ascii_case(&ua_platform.name()) {
+            warnings.push(EntropyWarning::inconsistent(
+                &["Sec-Ch-Ua-Platform", "user_agent"],
+                format!("Sec-Ch-Ua-Platform {:?} does not match a {} user agent", hinted, ua_platform.name()),
+            ));
+        }
+    }
+    
+    if let Some(hinted) = header(headers, "Sec-Ch-Ua-Mobile") {
+        if (hinted == "?1") != is_mobile_user_agent(user_agent) {
+            warnings.push(EntropyWarning::inconsistent(
+                &["Sec-Ch-Ua-Mobile", "user_agent"],
+                format!("Sec-Ch-Ua-Mobile {} contradicts the user agent", hinted),
+            ));
+        }
+    }
+    
+    if let Some(accept_language) = header(headers, "Accept-Language") {
+        let primary = accept_language.split([',', ';']).next().unwrap_or("").trim();
+        if !primary.eq_ignore_ascii_case(&fingerprint.language) {
+            warnings.push(EntropyWarning::inconsistent(
+                &["Accept-Language", "language"],
+                format!("Accept-Language starts with {:?} but navigator.language is {:?}", primary, fingerprint.language),
+            ));
+        }
+    }
+    
+    warnings
+}
+
+/// Get a header by case-insensitive name
+fn header<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Check whether a user agent belongs to a Chromium-based browser
+fn is_chromium(user_agent: &str) -> bool {
+    matches!(
+        parse_browser_from_user_agent(user_agent),
+        Some(BrowserType::Chrome) | Some(BrowserType::Edge) | Some(BrowserType::Opera)
+    )
+}
+
+/// Get the Chromium major version announced by a Sec-Ch-Ua header
+fn chromium_version_from_hint(sec_ch_ua: &str) -> Option<u32> {
+    let start = sec_ch_ua.find("\"Chromium\";v=\"")? + "\"Chromium\";v=\"".len();
+    let digits: String = sec_ch_ua[start..].chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+/// Get the navigator.platform prefixes of a platform
+fn navigator_platforms(platform: &PlatformType) -> &'static [&'static str] {
+    match platform {
+        PlatformType::Windows => &["Win"],
+        PlatformType::MacOS => &["MacIntel"],
+        PlatformType::Linux => &["Linux"],
+        PlatformType::Android => &["Linux arm", "Linux aarch64", "Linux armv"],
+        PlatformType::IOS => &["iPhone", "iPad", "iPod"],
+        PlatformType::ChromeOS => &["Linux", "CrOS"],
+        PlatformType::Custom(_) => &[],
+    }
+}
+
+/// Get the navigator.vendor of a browser
+fn navigator_vendor(browser: &BrowserType) -> &'static str {
+    match browser {
+        BrowserType::Chrome | BrowserType::Edge | BrowserType::Opera => "Google Inc.",
+        BrowserType::Safari => "Apple Computer, Inc.",
+        BrowserType::Firefox | BrowserType::Custom(_) => "",
+    }
+}
+
+/// Format a pixel ratio the way distributions list it, e.g. `1.25` or `2`
+fn format_ratio(ratio: f32) -> String {
+    let formatted = format!("{:.3}", ratio);
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const CHROME_WINDOWS: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36";
+    
+    fn common_fingerprint() -> BrowserFingerprint {
+        BrowserFingerprint {
+            user_agent: CHROME_WINDOWS.to_string(),
+            available_height: 1032,
+            vendor: "Google Inc.".to_string(),
+            webgl_renderer: Some("ANGLE (Intel, Intel(R) UHD Graphics 620 Direct3D11 vs_5_0 ps_5_0)".to_string()),
+            ..BrowserFingerprint::default()
+        }
+    }
+    
+    #[test]
+    fn test_common_identity_is_plausible() {
+        let mut headers = HashMap::new();
+        headers.insert("User-Agent".to_string(), CHROME_WINDOWS.to_string());
+        headers.insert("sec-ch-ua".to_string(), "\"Google Chrome\";v=\"124\", \"Not;A=Brand\";v=\"8\", \"Chromium\";v=\"124\"".to_string());
+        headers.insert("Sec-Ch-Ua-Platform".to_string(), "\"Windows\"".to_string());
+        headers.insert("Sec-Ch-Ua-Mobile".to_string(), "?0".to_string());
+        headers.insert("Accept-Language".to_string(), "en-US,en;q=0.9".to_string());
+        
+        let report = entropy_report(&headers, &common_fingerprint());
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        assert!(report.is_plausible());
+        assert_eq!(report.attributes.len(), 9);
+        assert!(report.total_bits > 0.0);
+        assert!((report.one_in() * report.combination_frequency() - 1.0).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn test_rare_values_add_entropy() {
+        let common = EntropyAnalyzer::new().analyze_fingerprint(&common_fingerprint());
+        
+        let mut fingerprint = common_fingerprint();
+        fingerprint.screen_width = 1437;
+        fingerprint.screen_height = 911;
+        fingerprint.available_width = 1437;
+        fingerprint.available_height = 863;
+        let rare = EntropyAnalyzer::new().analyze_fingerprint(&fingerprint);
+        
+        assert!(rare.total_bits > common.total_bits);
+        assert_eq!(rare.most_identifying().unwrap().value, "1437x911");
+        assert!(rare.is_plausible());
+        assert!(rare.warnings.iter().any(|warning| warning.kind == WarningKind::RareValue && warning.attributes == ["screen"]));
+    }
+    
+    #[test]
+    fn test_implausible_combinations() {
+        let mut fingerprint = common_fingerprint();
+        fingerprint.user_agent = "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:125.0) Gecko/20100101 Firefox/125.0".to_string();
+        fingerprint.vendor = "".to_string();
+        
+        let mut headers = HashMap::new();
+        headers.insert("Sec-Ch-Ua".to_string(), "\"Chromium\";v=\"124\"".to_string());
+        headers.insert("Accept-Language".to_string(), "de-DE,de;q=0.9".to_string());
+        
+        let report = entropy_report(&headers, &fingerprint);
+        assert!(!report.is_plausible());
+        let involved: Vec<&str> = report.warnings.iter().flat_map(|warning| warning.attributes.iter().map(String::as_str)).collect();
+        assert!(involved.contains(&"Sec-Ch-Ua"));
+        assert!(involved.contains(&"Accept-Language"));
+        assert!(involved.contains(&"device_memory"));
+        assert!(!report.attributes.iter().any(|attribute| attribute.name == "device_memory"));
+    }
+    
+    #[test]
+    fn test_chromium_version_mismatch() {
+        let mut headers = HashMap::new();
+        headers.insert("Sec-Ch-Ua".to_string(), "\"Google Chrome\";v=\"117\", \"Chromium\";v=\"117\"".to_string());
+        
+        let report = entropy_report(&headers, &common_fingerprint());
+        assert_eq!(report.warnings.len(), 1);
+        assert!(report.warnings[0].message.contains("Chromium 117"));
+    }
+    
+    #[test]
+    fn test_custom_distribution() {
+        let distribution = Distribution::from_json(r#"{
+            "unlisted_frequency": 0.25,
+            "attributes": { "timezone": { "all": { "Europe/Paris": 0.5 } } }
+        }"#).unwrap();
+        
+        let report = EntropyAnalyzer::new()
+            .with_distribution(distribution)
+            .analyze_fingerprint(&common_fingerprint());
+        assert_eq!(report.attributes.len(), 1);
+        assert_eq!(report.total_bits, 2.0);
+    }
+    
+    #[test]
+    fn test_format_ratio() {
+        assert_eq!(format_ratio(1.0), "1");
+        assert_eq!(format_ratio(1.25), "1.25");
+        assert_eq!(format_ratio(2.625), "2.625");
+    }
+}
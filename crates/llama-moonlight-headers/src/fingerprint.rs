@@ -2,6 +2,98 @@ use crate::{BrowserType, DeviceType, PlatformType};
 use rand::prelude::*;
 use std::collections::HashMap;
 
+/// Script adding seeded noise to canvas and WebGL pixel readback
+///
+/// Called with the canvas and WebGL seeds. Noise depends only on the seed
+/// and the pixel position, so the same identity reads the same pixels on
+/// every page load.
+const NOISE_JS: &str = r#"
+(canvasSeed, webglSeed) => {
+    // Wrapping twice with the same seed would flip the noise back off
+    const applied = Symbol.for('canvasNoise');
+    if (HTMLCanvasElement.prototype[applied]) return;
+    Object.defineProperty(HTMLCanvasElement.prototype, applied, { value: true });
+    
+    // Stateless hash of a pixel position
+    const noiseAt = (seed, position) => {
+        let h = Math.imul(seed ^ Math.imul(position + 1, 0x9e3779b1), 0x85ebca6b);
+        h = Math.imul(h ^ (h >>> 13), 0xc2b2ae35);
+        return (h ^ (h >>> 16)) >>> 0;
+    };
+    
+    // Flip the low bit of about 1% of the pixels, keyed by their position in the canvas
+    const addNoise = (seed, data, x, y, width, height, canvasWidth) => {
+        const end = Math.min(data.length, width * height * 4);
+        for (let i = 0; i < end; i += 4) {
+            const pixel = i >> 2;
+            const position = (y + Math.floor(pixel / width)) * canvasWidth + x + pixel % width;
+            if (noiseAt(seed, position) % 100 === 0) {
+                data[i] ^= 1;     // Red
+                data[i + 1] ^= 1; // Green
+                data[i + 2] ^= 1; // Blue
+                // Don't modify alpha
+            }
+        }
+    };
+    
+    // Remember which kind of context each canvas draws with
+    const contextTypes = new WeakMap();
+    const originalGetContext = HTMLCanvasElement.prototype.getContext;
+    HTMLCanvasElement.prototype.getContext = function(type, ...args) {
+        const context = originalGetContext.call(this, type, ...args);
+        if (context && !contextTypes.has(this)) {
+            contextTypes.set(this, String(type));
+        }
+        return context;
+    };
+    const seedFor = (canvas) => /webgl/i.test(contextTypes.get(canvas) || '') ? webglSeed : canvasSeed;
+    
+    const originalGetImageData = CanvasRenderingContext2D.prototype.getImageData;
+    CanvasRenderingContext2D.prototype.getImageData = function(x, y, width, height, ...args) {
+        const imageData = originalGetImageData.call(this, x, y, width, height, ...args);
+        addNoise(canvasSeed, imageData.data, x, y, imageData.width, imageData.height, this.canvas.width);
+        return imageData;
+    };
+    
+    // Serialize a noised copy, so encoded images match the noised pixels
+    const noisedCopy = (canvas) => {
+        if (!canvas.width || !canvas.height || !contextTypes.has(canvas)) return canvas;
+        const copy = document.createElement('canvas');
+        copy.width = canvas.width;
+        copy.height = canvas.height;
+        const ctx = originalGetContext.call(copy, '2d');
+        ctx.drawImage(canvas, 0, 0);
+        const imageData = originalGetImageData.call(ctx, 0, 0, copy.width, copy.height);
+        addNoise(seedFor(canvas), imageData.data, 0, 0, copy.width, copy.height, copy.width);
+        ctx.putImageData(imageData, 0, 0);
+        return copy;
+    };
+    
+    const originalToDataURL = HTMLCanvasElement.prototype.toDataURL;
+    HTMLCanvasElement.prototype.toDataURL = function(...args) {
+        return originalToDataURL.apply(noisedCopy(this), args);
+    };
+    
+    const originalToBlob = HTMLCanvasElement.prototype.toBlob;
+    HTMLCanvasElement.prototype.toBlob = function(...args) {
+        return originalToBlob.apply(noisedCopy(this), args);
+    };
+    
+    const noiseReadPixels = (proto) => {
+        if (!proto) return;
+        const originalReadPixels = proto.readPixels;
+        proto.readPixels = function(x, y, width, height, format, type, pixels, ...args) {
+            originalReadPixels.call(this, x, y, width, height, format, type, pixels, ...args);
+            if (pixels instanceof Uint8Array && format === this.RGBA) {
+                addNoise(webglSeed, pixels, x, y, width, height, this.drawingBufferWidth);
+            }
+        };
+    };
+    noiseReadPixels(window.WebGLRenderingContext && WebGLRenderingContext.prototype);
+    noiseReadPixels(window.WebGL2RenderingContext && WebGL2RenderingContext.prototype);
+}
+"#;
+
 /// Browser fingerprint data
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct BrowserFingerprint {
@@ -172,7 +264,7 @@ impl BrowserFingerprint {
         device_type: &DeviceType,
         platform_type: &PlatformType,
     ) -> Self {
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::random::rng();
         
         // Generate a user agent for this configuration
         let user_agent = crate::useragent::generate_user_agent(browser_type, device_type, platform_type);
@@ -436,23 +528,7 @@ impl BrowserFingerprint {
                 }}
                 
                 // Canvas fingerprinting protection
-                if ({canvas_blocked}) {{
-                    const canvasProto = CanvasRenderingContext2D.prototype;
-                    const originalGetImageData = canvasProto.getImageData;
-                    canvasProto.getImageData = function(x, y, width, height) {{
-                        const imageData = originalGetImageData.call(this, x, y, width, height);
-                        const pixels = imageData.data;
-                        
-                        // Add some noise to the canvas data
-                        for (let i = 0; i < pixels.length; i += 4) {{
-                            pixels[i] = pixels[i] + Math.floor(Math.random() * 10) - 5;     // red
-                            pixels[i+1] = pixels[i+1] + Math.floor(Math.random() * 10) - 5; // green
-                            pixels[i+2] = pixels[i+2] + Math.floor(Math.random() * 10) - 5; // blue
-                        }}
-                        
-                        return imageData;
-                    }};
-                }}
+                {canvas_noise}
                 
                 // WebGL fingerprinting protection
                 try {{
@@ -485,7 +561,11 @@ impl BrowserFingerprint {
             available_height = self.available_height,
             color_depth = self.color_depth,
             pixel_ratio = self.pixel_ratio,
-            canvas_blocked = self.canvas_blocked,
+            canvas_noise = if self.canvas_blocked {
+                noise_script(self.canvas_noise_seed(), self.webgl_noise_seed())
+            } else {
+                String::new()
+            },
             webgl_vendor = self.webgl_vendor.clone().unwrap_or_else(|| "Google Inc.".to_string()),
             webgl_renderer = self.webgl_renderer.clone().unwrap_or_else(|| "ANGLE (Intel HD Graphics)".to_string()),
         );
@@ -493,6 +573,21 @@ impl BrowserFingerprint {
         js
     }
     
+    /// Get the seed of the canvas noise presented by this fingerprint
+    ///
+    /// The seed is derived from `canvas_hash`, so the same fingerprint adds
+    /// the same noise on every page load while different fingerprints differ.
+    pub fn canvas_noise_seed(&self) -> u32 {
+        noise_seed(self.canvas_hash.as_deref(), &self.user_agent)
+    }
+    
+    /// Get the seed of the WebGL noise presented by this fingerprint
+    ///
+    /// The seed is derived from `webgl_hash`, see [`Self::canvas_noise_seed`].
+    pub fn webgl_noise_seed(&self) -> u32 {
+        noise_seed(self.webgl_hash.as_deref(), &self.platform)
+    }
+    
     /// Convert the fingerprint to a JSON string
     pub fn to_json(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string_pretty(self)
@@ -513,9 +608,27 @@ pub fn generate_random_fingerprint() -> BrowserFingerprint {
     BrowserFingerprint::new(&browser_type, &device_type, &platform_type)
 }
 
+/// Get the script adding seeded noise to canvas and WebGL readback
+///
+/// Used for fingerprints blocking canvas reads and by the stealth crate, so
+/// every layer derives the same noise from the same seeds.
+pub fn noise_script(canvas_seed: u32, webgl_seed: u32) -> String {
+    format!("({})({}, {});", NOISE_JS.trim(), canvas_seed, webgl_seed)
+}
+
+/// Derive a 32-bit noise seed from a fingerprint hash with FNV-1a
+///
+/// Fingerprints without the hash fall back to `fallback`, which keeps the
+/// seed stable for the fingerprint even though it is no longer unique.
+fn noise_seed(hash: Option<&str>, fallback: &str) -> u32 {
+    hash.unwrap_or(fallback)
+        .bytes()
+        .fold(0x811c9dc5u32, |h, b| (h ^ b as u32).wrapping_mul(0x01000193))
+}
+
 /// Generate a random hex string of the given length (in bytes)
 fn generate_random_hash(byte_length: usize) -> String {
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     let mut bytes = vec![0u8; byte_length];
     rng.fill_bytes(&mut bytes);
     
@@ -624,6 +737,32 @@ mod tests {
         assert!(js.contains("override_getter(screen, 'width'"));
     }
     
+    #[test]
+    fn test_browser_fingerprint_noise_seed() {
+        let consistent = |seed| BrowserFingerprint::consistent(
+            &BrowserType::Chrome,
+            &DeviceType::Desktop,
+            &PlatformType::Windows,
+            seed,
+        );
+        
+        // The same identity seeds the same noise, different identities differ
+        assert_eq!(consistent(1).canvas_noise_seed(), consistent(1).canvas_noise_seed());
+        assert_ne!(consistent(1).canvas_noise_seed(), consistent(2).canvas_noise_seed());
+        assert_ne!(consistent(1).canvas_noise_seed(), consistent(1).webgl_noise_seed());
+        
+        let mut fp = consistent(1);
+        fp.canvas_blocked = true;
+        let js = fp.to_js();
+        assert_eq!(js, fp.to_js());
+        assert!(js.contains(&noise_script(fp.canvas_noise_seed(), fp.webgl_noise_seed())));
+        assert!(!js.contains("Math.random"));
+        
+        // Fingerprints allowing canvas reads leave the noise to other layers
+        fp.canvas_blocked = false;
+        assert!(!fp.to_js().contains("canvasNoise"));
+    }
+    
     #[test]
     fn test_browser_fingerprint_json() {
         let fp = BrowserFingerprint::new(
@@ -134,7 +134,7 @@ pub fn all_languages() -> Vec<&'static str> {
 
 /// Generate a random Accept-Language header
 pub fn random_language() -> String {
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     
     // Choose a primary language
     let primary_lang = common_languages()[rng.gen_range(0..common_languages().len())];
@@ -156,7 +156,7 @@ pub fn random_language() -> String {
 
 /// Generate a random Accept-Language header for Safari (includes more variants)
 pub fn random_safari_language() -> String {
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     
     // Choose a primary language
     let primary_lang = common_languages()[rng.gen_range(0..common_languages().len())];
@@ -170,7 +170,7 @@ pub fn random_safari_language() -> String {
 
 /// Generate a realistic Accept-Language header for the given primary language
 pub fn generate_accept_language(primary_lang: &str) -> String {
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     
     // Extract language code
     let primary_parts: Vec<&str> = primary_lang.split('-').collect();
@@ -10,7 +10,10 @@
 //! - Customizable user agents
 //! - Stealth mode for avoiding bot detection
 //! - Fingerprinting protection
-//! - Session persistence
+//! - Session persistence, with cache revalidation on repeat visits
+//! - Version-aware Accept-Encoding and response decoding (gzip, br, zstd)
+//! - Entropy reports flagging rare or implausible identities
+//! - Realistic identities drawn from browser market share, by country
 //!
 //! ## Example
 //!
@@ -24,23 +27,34 @@
 //! ```
 
 use std::collections::HashMap;
-use rand::Rng;
+use std::sync::{Arc, Mutex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use lazy_static::lazy_static;
 use thiserror::Error;
 use log::{debug, warn};
 use chrono::Utc;
 
 pub mod browser;
+pub mod cache;
 pub mod device;
+pub mod encoding;
+pub mod entropy;
 pub mod fingerprint;
 pub mod stealth;
 pub mod useragent;
 pub mod platform;
 pub mod language;
+pub mod market;
+pub mod random;
 pub mod utils;
 
 pub use browser::BrowserType;
+pub use cache::BrowserCache;
 pub use device::DeviceType;
+pub use encoding::ContentEncoding;
+pub use entropy::{EntropyAnalyzer, EntropyReport};
+pub use market::{MarketProfile, MarketShare};
 pub use platform::PlatformType;
 
 /// Errors that can occur when generating headers
@@ -62,6 +76,14 @@ pub enum HeaderError {
     #[error("Invalid header value: {0}")]
     InvalidHeaderValue(String),
     
+    /// Error when a content encoding is not supported
+    #[error("Unsupported content encoding: {0}")]
+    UnsupportedEncoding(String),
+    
+    /// Error when a response body cannot be decoded
+    #[error("Failed to decode body: {0}")]
+    DecodeError(String),
+    
     /// Error when JSON serialization/deserialization fails
     #[error("JSON error: {0}")]
     JsonError(#[from] serde_json::Error),
@@ -86,6 +108,9 @@ pub struct HeaderGenerator {
     /// The type of platform to generate headers for
     platform_type: PlatformType,
     
+    /// Major browser version, random for each user agent if unset
+    browser_version: Option<u32>,
+    
     /// Custom user agent, if any
     custom_user_agent: Option<String>,
     
@@ -103,6 +128,12 @@ pub struct HeaderGenerator {
     
     /// Whether to include Sec-* headers
     include_sec_headers: bool,
+    
+    /// Simulated browser cache, if repeat visits should drift
+    cache: Option<BrowserCache>,
+    
+    /// Seeded random number generator, if output should be reproducible
+    rng: Option<Arc<Mutex<StdRng>>>,
 }
 
 impl Default for HeaderGenerator {
@@ -111,12 +142,15 @@ impl Default for HeaderGenerator {
             browser_type: BrowserType::Chrome,
             device_type: DeviceType::Desktop,
             platform_type: PlatformType::Windows,
+            browser_version: None,
             custom_user_agent: None,
             stealth_mode: false,
             custom_headers: HashMap::new(),
             language: "en-US".to_string(),
             randomize_language: false,
             include_sec_headers: true,
+            cache: None,
+            rng: None,
         }
     }
 }
@@ -142,6 +176,44 @@ impl HeaderGenerator {
         self
     }
     
+    /// Set the major browser version
+    pub fn with_browser_version(mut self, version: u32) -> Self {
+        self.browser_version = Some(version);
+        self
+    }
+    
+    /// Create a generator for a browser identity drawn from worldwide market share
+    ///
+    /// Browser, version, platform and device are picked together in the
+    /// proportions they are seen in real-world traffic, so that a fleet of
+    /// generators does not all claim to be the same browser.
+    pub fn random_realistic() -> Self {
+        let profile = MarketShare::bundled()
+            .sample(None)
+            .expect("bundled market share has profiles");
+        
+        Self::from_market_profile(profile)
+    }
+    
+    /// Create a generator for a browser identity drawn from the market share of a country
+    ///
+    /// The country is given by its ISO 3166-1 alpha-2 code, e.g. `DE`.
+    pub fn random_realistic_for_country(country: &str) -> Result<Self> {
+        let profile = MarketShare::bundled()
+            .sample(Some(country))
+            .ok_or_else(|| HeaderError::Other(format!("No market share data for country {}", country)))?;
+        
+        Ok(Self::from_market_profile(profile))
+    }
+    
+    /// Create a generator for a market share profile
+    pub fn from_market_profile(profile: &MarketProfile) -> Self {
+        Self::new(profile.browser.clone())
+            .with_device(profile.device.clone())
+            .with_platform(profile.platform.clone())
+            .with_browser_version(profile.version)
+    }
+    
     /// Set a custom user agent
     pub fn with_user_agent(mut self, user_agent: &str) -> Self {
         self.custom_user_agent = Some(user_agent.to_string());
@@ -178,25 +250,76 @@ impl HeaderGenerator {
         self
     }
     
+    /// Enable or disable browser cache simulation
+    ///
+    /// With a cache, repeated requests for the same site revalidate cached
+    /// pages instead of repeating the first request. Clones of the generator
+    /// share the cache.
+    pub fn with_cache_simulation(mut self, enabled: bool) -> Self {
+        self.cache = enabled.then(BrowserCache::new);
+        self
+    }
+    
+    /// Use an existing browser cache
+    pub fn with_cache(mut self, cache: BrowserCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+    
+    /// Get the simulated browser cache, if enabled
+    pub fn cache(&self) -> Option<&BrowserCache> {
+        self.cache.as_ref()
+    }
+    
+    /// Record the headers of a response so that later requests can revalidate it
+    pub fn record_response(&self, url: &str, response_headers: &HashMap<String, String>) {
+        if let Some(cache) = &self.cache {
+            cache.record_response(url, response_headers);
+        }
+    }
+    
+    /// Seed the random choices of this generator
+    ///
+    /// Two generators created with the same settings and seed produce the
+    /// same sequence of headers. Clones share the random number generator.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Some(Arc::new(Mutex::new(StdRng::seed_from_u64(seed))));
+        self
+    }
+    
     /// Get the user agent string
     pub fn get_user_agent(&self) -> String {
         if let Some(ref ua) = self.custom_user_agent {
             return ua.clone();
         }
         
-        useragent::generate_user_agent(&self.browser_type, &self.device_type, &self.platform_type)
+        useragent::generate_user_agent_with_version(
+            &self.browser_type,
+            &self.device_type,
+            &self.platform_type,
+            self.browser_version,
+        )
     }
     
     /// Generate headers for a specific URL
     pub fn generate(&self, url: &str) -> HashMap<String, String> {
+        match &self.rng {
+            Some(rng) => random::scoped(rng, || self.build_headers(url)),
+            None => self.build_headers(url),
+        }
+    }
+    
+    /// Build the headers for a URL with the current random number generator
+    fn build_headers(&self, url: &str) -> HashMap<String, String> {
         let mut headers = HashMap::new();
         let user_agent = self.get_user_agent();
+        let accept_encoding = self.get_accept_encoding(&user_agent);
         
         // Basic headers
         headers.insert("User-Agent".to_string(), user_agent);
         headers.insert("Accept".to_string(), self.get_accept_header());
         headers.insert("Accept-Language".to_string(), self.get_accept_language());
-        headers.insert("Accept-Encoding".to_string(), "gzip, deflate, br".to_string());
+        headers.insert("Accept-Encoding".to_string(), accept_encoding);
         headers.insert("Connection".to_string(), "keep-alive".to_string());
         
         // Include Referer if stealth mode is enabled and URL is not empty
@@ -275,6 +398,11 @@ impl HeaderGenerator {
             stealth::add_stealth_headers(&mut headers, url, &self.browser_type, &self.device_type);
         }
         
+        // Drift from the first request on repeat visits
+        if let Some(cache) = &self.cache {
+            cache.apply(url, &mut headers);
+        }
+        
         // Add custom headers
         for (name, value) in &self.custom_headers {
             headers.insert(name.clone(), value.clone());
@@ -308,7 +436,7 @@ impl HeaderGenerator {
             let scheme = parsed_url.scheme();
             
             // Generate a referer from a common site or the same domain
-            let mut rng = rand::thread_rng();
+            let mut rng = crate::random::rng();
             if rng.gen_bool(0.7) {
                 // 70% chance to use a search engine as referer
                 let search_engines = [
@@ -368,6 +496,12 @@ impl HeaderGenerator {
         }
     }
     
+    /// Get the Accept-Encoding header matching the browser version of the user agent
+    fn get_accept_encoding(&self, user_agent: &str) -> String {
+        let version = useragent::parse_major_version_from_user_agent(user_agent, &self.browser_type);
+        encoding::accept_encoding(&self.browser_type, version)
+    }
+    
     /// Get the Accept-Language header
     fn get_accept_language(&self) -> String {
         if self.randomize_language {
@@ -381,13 +515,13 @@ impl HeaderGenerator {
     fn get_sec_ch_ua(&self) -> String {
         match self.browser_type {
             BrowserType::Chrome => {
-                "\"Google Chrome\";v=\"117\", \"Not;A=Brand\";v=\"8\", \"Chromium\";v=\"117\"".to_string()
+                format!("\"Google Chrome\";v=\"{0}\", \"Not;A=Brand\";v=\"8\", \"Chromium\";v=\"{0}\"", self.browser_version.unwrap_or(117))
             },
             BrowserType::Edge => {
-                "\"Microsoft Edge\";v=\"117\", \"Not;A=Brand\";v=\"8\", \"Chromium\";v=\"117\"".to_string()
+                format!("\"Microsoft Edge\";v=\"{0}\", \"Not;A=Brand\";v=\"8\", \"Chromium\";v=\"{0}\"", self.browser_version.unwrap_or(117))
             },
             BrowserType::Opera => {
-                "\"Opera\";v=\"101\", \"Not;A=Brand\";v=\"8\", \"Chromium\";v=\"117\"".to_string()
+                format!("\"Opera\";v=\"{}\", \"Not;A=Brand\";v=\"8\", \"Chromium\";v=\"117\"", self.browser_version.unwrap_or(101))
             },
             _ => "".to_string(),
         }
@@ -464,6 +598,63 @@ mod tests {
         assert!(headers.contains_key("TE"));
     }
     
+    #[test]
+    fn test_seeded_generator_is_reproducible() {
+        let generator = || HeaderGenerator::new(BrowserType::Chrome).with_stealth(true).with_randomize_language(true);
+        
+        let first = generator().with_seed(7);
+        let second = generator().with_seed(7);
+        for _ in 0..3 {
+            assert_eq!(first.generate("https://example.com/page"), second.generate("https://example.com/page"));
+        }
+    }
+    
+    #[test]
+    fn test_random_realistic() {
+        let generator = HeaderGenerator::new(BrowserType::Chrome)
+            .with_platform(PlatformType::Android)
+            .with_device(DeviceType::Mobile)
+            .with_browser_version(116);
+        let headers = generator.generate("https://example.com");
+        assert!(headers["User-Agent"].contains("Chrome/116."));
+        assert!(headers["Sec-Ch-Ua"].contains("v=\"116\""));
+        
+        for _ in 0..20 {
+            let generator = HeaderGenerator::random_realistic_for_country("IN").unwrap();
+            let version = generator.browser_version.unwrap();
+            assert!(generator.get_user_agent().contains(&format!("/{}.", version)));
+        }
+        
+        assert!(HeaderGenerator::random_realistic_for_country("ZZ").is_err());
+        assert!(HeaderGenerator::random_realistic().generate("https://example.com").contains_key("User-Agent"));
+    }
+    
+    #[test]
+    fn test_accept_encoding_follows_user_agent() {
+        let generator = HeaderGenerator::new(BrowserType::Chrome)
+            .with_user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36");
+        assert_eq!(generator.generate("https://example.com")["Accept-Encoding"], "gzip, deflate, br, zstd");
+        
+        let generator = HeaderGenerator::new(BrowserType::Safari);
+        assert_eq!(generator.generate("https://example.com")["Accept-Encoding"], "gzip, deflate, br");
+    }
+    
+    #[test]
+    fn test_cache_simulation() {
+        let generator = HeaderGenerator::new(BrowserType::Firefox).with_cache_simulation(true);
+        let first = generator.generate("https://example.com/");
+        assert!(!first.contains_key("If-None-Match"));
+        
+        let mut response = HashMap::new();
+        response.insert("ETag".to_string(), "W/\"42\"".to_string());
+        generator.record_response("https://example.com/", &response);
+        
+        let repeat = generator.clone().generate("https://example.com/");
+        assert_eq!(repeat["If-None-Match"], "W/\"42\"");
+        assert_eq!(repeat["Cache-Control"], "max-age=0");
+        assert!(!repeat.contains_key("Pragma"));
+    }
+    
     #[test]
     fn test_custom_user_agent() {
         let generator = HeaderGenerator::default()
@@ -0,0 +1,156 @@
+//! Browser market share
+//!
+//! A fleet of generators left at their defaults all claim to be desktop
+//! Chrome on Windows, which is easy to single out. [`MarketShare`] holds the
+//! share of real-world traffic of each browser version, platform and device
+//! combination, so that identities can be drawn in the proportions they are
+//! actually seen, optionally within the traffic of a given country.
+//!
+//! Market share data is bundled with the crate; other data can be loaded
+//! with [`MarketShare::from_json`] in the same format.
+
+use std::collections::HashMap;
+use lazy_static::lazy_static;
+use rand::prelude::*;
+use serde::Deserialize;
+use crate::{BrowserType, DeviceType, PlatformType, Result};
+
+/// Bundled market share data
+const BUNDLED_MARKET_SHARE: &str = include_str!("../data/market_share.json");
+
+lazy_static! {
+    static ref BUNDLED: MarketShare = MarketShare::from_json(BUNDLED_MARKET_SHARE)
+        .expect("bundled market share data is valid JSON");
+}
+
+/// A browser identity seen in real-world traffic
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MarketProfile {
+    /// Browser type
+    pub browser: BrowserType,
+    
+    /// Major version of the browser
+    pub version: u32,
+    
+    /// Platform the browser runs on
+    pub platform: PlatformType,
+    
+    /// Type of device the browser runs on
+    pub device: DeviceType,
+    
+    /// Worldwide share of traffic
+    pub share: f64,
+}
+
+/// Share of traffic of browser identities, worldwide and by country
+#[derive(Debug, Clone, Deserialize)]
+pub struct MarketShare {
+    /// Browser identities with their worldwide share
+    pub profiles: Vec<MarketProfile>,
+    
+    /// Share of each platform by country code (e.g. `US`)
+    #[serde(default)]
+    pub countries: HashMap<String, HashMap<PlatformType, f64>>,
+}
+
+impl MarketShare {
+    /// Get the market share data bundled with the crate
+    pub fn bundled() -> &'static MarketShare {
+        &BUNDLED
+    }
+    
+    /// Parse market share data in the bundled JSON format
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+    
+    /// Get the country codes with platform shares
+    pub fn countries(&self) -> Vec<&str> {
+        let mut countries: Vec<&str> = self.countries.keys().map(String::as_str).collect();
+        countries.sort_unstable();
+        countries
+    }
+    
+    /// Get the share of traffic of each profile, worldwide or in a country
+    ///
+    /// Within a country, profiles keep their worldwide share among the
+    /// profiles of the same platform, scaled to the country's share of that
+    /// platform. Returns `None` for countries without data.
+    pub fn weights(&self, country: Option<&str>) -> Option<Vec<f64>> {
+        let country = match country {
+            Some(code) => Some(self.countries.get(&code.to_ascii_uppercase())?),
+            None => None,
+        };
+        
+        let mut platform_totals: HashMap<&PlatformType, f64> = HashMap::new();
+        for profile in &self.profiles {
+            *platform_totals.entry(&profile.platform).or_default() += profile.share;
+        }
+        
+        Some(self.profiles.iter().map(|profile| match country {
+            Some(platforms) => {
+                let platform_share = platforms.get(&profile.platform).copied().unwrap_or(0.0);
+                platform_share * profile.share / platform_totals[&profile.platform]
+            },
+            None => profile.share,
+        }).collect())
+    }
+    
+    /// Draw a profile in proportion to its share of traffic
+    ///
+    /// Returns `None` for countries without data or when no profile has a
+    /// positive share.
+    pub fn sample(&self, country: Option<&str>) -> Option<&MarketProfile> {
+        let weights = self.weights(country)?;
+        let index = rand::distributions::WeightedIndex::new(&weights).ok()?;
+        let mut rng = crate::random::rng();
+        
+        self.profiles.get(index.sample(&mut rng))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_bundled_market_share() {
+        let market = MarketShare::bundled();
+        assert!(!market.profiles.is_empty());
+        assert!(market.countries().contains(&"US"));
+        
+        let mut browsers = std::collections::HashSet::new();
+        let mut platforms = std::collections::HashSet::new();
+        for _ in 0..500 {
+            let profile = market.sample(None).unwrap();
+            browsers.insert(profile.browser.clone());
+            platforms.insert(profile.platform.clone());
+        }
+        
+        // Draws should not collapse onto a single identity
+        assert!(browsers.len() > 1);
+        assert!(platforms.len() > 2);
+    }
+    
+    #[test]
+    fn test_country_weights() {
+        let market = MarketShare::from_json(r#"{
+            "profiles": [
+                { "browser": "Chrome", "version": 117, "platform": "Windows", "device": "Desktop", "share": 0.6 },
+                { "browser": "Edge", "version": 117, "platform": "Windows", "device": "Desktop", "share": 0.2 },
+                { "browser": "Safari", "version": 17, "platform": "IOS", "device": "Mobile", "share": 0.2 }
+            ],
+            "countries": {
+                "XX": { "IOS": 1.0 }
+            }
+        }"#).unwrap();
+        
+        assert_eq!(market.weights(None).unwrap(), vec![0.6, 0.2, 0.2]);
+        assert_eq!(market.weights(Some("xx")).unwrap(), vec![0.0, 0.0, 1.0]);
+        assert!(market.weights(Some("YY")).is_none());
+        
+        for _ in 0..20 {
+            assert_eq!(market.sample(Some("XX")).unwrap().browser, BrowserType::Safari);
+        }
+    }
+}
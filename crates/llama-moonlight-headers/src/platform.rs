@@ -75,7 +75,7 @@ impl PlatformType {
     pub fn random_version(&self) -> String {
         use rand::prelude::*;
         
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::random::rng();
         match self {
             PlatformType::Windows => {
                 // Select a Windows version
@@ -162,7 +162,7 @@ impl PlatformType {
     pub fn random() -> PlatformType {
         use rand::seq::SliceRandom;
         let platforms = PlatformType::all();
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::random::rng();
         platforms.choose(&mut rng).unwrap().clone()
     }
     
@@ -175,7 +175,7 @@ impl PlatformType {
             PlatformType::Linux,
             PlatformType::ChromeOS,
         ];
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::random::rng();
         platforms.choose(&mut rng).unwrap().clone()
     }
     
@@ -186,7 +186,7 @@ impl PlatformType {
             PlatformType::Android,
             PlatformType::IOS,
         ];
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::random::rng();
         platforms.choose(&mut rng).unwrap().clone()
     }
 }
@@ -216,7 +216,7 @@ pub fn platform_for_device(device_type: &crate::device::DeviceType) -> PlatformT
     use crate::device::DeviceType;
     use rand::seq::SliceRandom;
     
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     
     match device_type {
         DeviceType::Desktop => {
@@ -0,0 +1,97 @@
+//! Random number source for header generation
+//!
+//! All randomized choices of this crate (user agents, referers, languages,
+//! fingerprints) draw from [`rng`]. By default it is the thread's entropy
+//! seeded generator; tests can call [`seed`] to make the generated headers of
+//! the current thread reproducible, and [`HeaderGenerator::with_seed`] gives a
+//! single generator its own deterministic sequence.
+//!
+//! [`HeaderGenerator::with_seed`]: crate::HeaderGenerator::with_seed
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use std::cell::RefCell;
+use std::sync::Mutex;
+
+thread_local! {
+    /// Seeded generator replacing the thread generator, if any
+    static SEEDED: RefCell<Option<StdRng>> = const { RefCell::new(None) };
+}
+
+/// Seed the generator of the current thread
+pub fn seed(seed: u64) {
+    SEEDED.with(|seeded| *seeded.borrow_mut() = Some(StdRng::seed_from_u64(seed)));
+}
+
+/// Go back to the entropy seeded generator on the current thread
+pub fn unseed() {
+    SEEDED.with(|seeded| *seeded.borrow_mut() = None);
+}
+
+/// Get the random number generator used by this crate
+pub fn rng() -> HeaderRng {
+    HeaderRng
+}
+
+/// Handle to the current thread's generator
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeaderRng;
+
+impl HeaderRng {
+    fn with<T>(&mut self, f: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+        SEEDED.with(|seeded| match seeded.borrow_mut().as_mut() {
+            Some(rng) => f(rng),
+            None => f(&mut rand::thread_rng()),
+        })
+    }
+}
+
+impl RngCore for HeaderRng {
+    fn next_u32(&mut self) -> u32 {
+        self.with(|rng| rng.next_u32())
+    }
+    
+    fn next_u64(&mut self) -> u64 {
+        self.with(|rng| rng.next_u64())
+    }
+    
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.with(|rng| rng.fill_bytes(dest))
+    }
+    
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.with(|rng| rng.try_fill_bytes(dest))
+    }
+}
+
+/// Run `f` with the thread's generator replaced by `rng`
+///
+/// The state of `rng` advances by the values `f` draws.
+pub(crate) fn scoped<T>(rng: &Mutex<StdRng>, f: impl FnOnce() -> T) -> T {
+    let mut rng = rng.lock().unwrap();
+    let previous = SEEDED.with(|seeded| seeded.replace(Some(rng.clone())));
+    let result = f();
+    
+    if let Some(advanced) = SEEDED.with(|seeded| seeded.replace(previous)) {
+        *rng = advanced;
+    }
+    
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    
+    #[test]
+    fn test_seeded_thread_generator() {
+        seed(42);
+        let first: Vec<u32> = (0..4).map(|_| rng().gen_range(0..1000)).collect();
+        seed(42);
+        let second: Vec<u32> = (0..4).map(|_| rng().gen_range(0..1000)).collect();
+        unseed();
+        
+        assert_eq!(first, second);
+    }
+}
@@ -29,7 +29,7 @@ pub fn add_stealth_headers(
 
 /// Add cache-related headers
 fn add_cache_headers(headers: &mut HashMap<String, String>) {
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     
     // Random cache behavior to appear more natural
     if rng.gen_bool(0.8) {
@@ -48,7 +48,7 @@ fn add_cache_headers(headers: &mut HashMap<String, String>) {
 
 /// Add Referrer-Policy header
 fn add_referrer_policy_headers(headers: &mut HashMap<String, String>) {
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     
     // Random referrer policy
     let policies = [
@@ -66,7 +66,7 @@ fn add_referrer_policy_headers(headers: &mut HashMap<String, String>) {
 
 /// Add Do-Not-Track header
 fn add_do_not_track_header(headers: &mut HashMap<String, String>) {
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     
     // 30% chance of adding DNT header
     if rng.gen_bool(0.3) {
@@ -76,7 +76,7 @@ fn add_do_not_track_header(headers: &mut HashMap<String, String>) {
 
 /// Add random Client Hints headers
 fn add_random_client_hints(headers: &mut HashMap<String, String>, device_type: &DeviceType) {
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     
     // 70% chance of adding client hints
     if rng.gen_bool(0.7) {
@@ -124,7 +124,7 @@ fn add_sec_fetch_headers(headers: &mut HashMap<String, String>, url: &str) {
     headers.insert("Sec-Fetch-Site".to_string(), "cross-site".to_string());
     
     // If it's the first navigation, add Sec-Fetch-User
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     if rng.gen_bool(0.7) {
         headers.insert("Sec-Fetch-User".to_string(), "?1".to_string());
     }
@@ -135,7 +135,7 @@ fn add_chrome_stealth_headers(headers: &mut HashMap<String, String>) {
     // Priority header used by Chrome
     headers.insert("Priority".to_string(), "u=0, i".to_string());
     
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     if rng.gen_bool(0.3) {
         // Add Chrome extension-related headers to make the request look even more realistic
         headers.insert("X-Client-Data".to_string(), generate_chrome_client_data());
@@ -146,11 +146,6 @@ fn add_chrome_stealth_headers(headers: &mut HashMap<String, String>) {
 fn add_firefox_stealth_headers(headers: &mut HashMap<String, String>) {
     // Firefox-specific headers
     headers.insert("TE".to_string(), "trailers".to_string());
-    
-    let mut rng = rand::thread_rng();
-    if rng.gen_bool(0.6) {
-        headers.insert("Accept-Encoding".to_string(), "gzip, deflate, br".to_string());
-    }
 }
 
 /// Add Safari-specific stealth headers
@@ -178,14 +173,14 @@ fn generate_chrome_client_data() -> String {
     // Real X-Client-Data is more complex and encodes Chrome feature flags
     
     let random_bytes = [
-        rand::random::<u8>(),
-        rand::random::<u8>(),
-        rand::random::<u8>(),
-        rand::random::<u8>(),
-        rand::random::<u8>(),
-        rand::random::<u8>(),
-        rand::random::<u8>(),
-        rand::random::<u8>(),
+        crate::random::rng().gen::<u8>(),
+        crate::random::rng().gen::<u8>(),
+        crate::random::rng().gen::<u8>(),
+        crate::random::rng().gen::<u8>(),
+        crate::random::rng().gen::<u8>(),
+        crate::random::rng().gen::<u8>(),
+        crate::random::rng().gen::<u8>(),
+        crate::random::rng().gen::<u8>(),
     ];
     
     let base64_str = base64::encode(&random_bytes);
@@ -195,7 +190,7 @@ fn generate_chrome_client_data() -> String {
 
 /// Generate a realistic browser fingerprint to avoid detection
 pub fn generate_fingerprint(browser_type: &BrowserType, device_type: &DeviceType) -> serde_json::Value {
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     
     // Common browser features that are checked by fingerprinting systems
     let java_enabled = false; // Modern browsers rarely have Java
@@ -257,7 +252,7 @@ pub fn generate_fingerprint(browser_type: &BrowserType, device_type: &DeviceType
 
 /// Generate a random canvas fingerprint hash
 fn generate_random_canvas_hash() -> String {
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     let random_bytes = [
         rng.gen::<u8>(), rng.gen::<u8>(), rng.gen::<u8>(), rng.gen::<u8>(),
         rng.gen::<u8>(), rng.gen::<u8>(), rng.gen::<u8>(), rng.gen::<u8>(),
@@ -270,7 +265,7 @@ fn generate_random_canvas_hash() -> String {
 
 /// Generate a random WebGL fingerprint hash
 fn generate_random_webgl_hash() -> String {
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     let random_bytes = [
         rng.gen::<u8>(), rng.gen::<u8>(), rng.gen::<u8>(), rng.gen::<u8>(),
         rng.gen::<u8>(), rng.gen::<u8>(), rng.gen::<u8>(), rng.gen::<u8>(),
@@ -283,7 +278,7 @@ fn generate_random_webgl_hash() -> String {
 
 /// Generate a random audio fingerprint hash
 fn generate_random_audio_hash() -> String {
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     let random_bytes = [
         rng.gen::<u8>(), rng.gen::<u8>(), rng.gen::<u8>(), rng.gen::<u8>(),
         rng.gen::<u8>(), rng.gen::<u8>(), rng.gen::<u8>(), rng.gen::<u8>(),
@@ -6,13 +6,23 @@ pub fn generate_user_agent(
     browser_type: &BrowserType,
     device_type: &DeviceType,
     platform_type: &PlatformType,
+) -> String {
+    generate_user_agent_with_version(browser_type, device_type, platform_type, None)
+}
+
+/// Generate a user agent string with the given major browser version, or a random one
+pub fn generate_user_agent_with_version(
+    browser_type: &BrowserType,
+    device_type: &DeviceType,
+    platform_type: &PlatformType,
+    major: Option<u32>,
 ) -> String {
     match browser_type {
-        BrowserType::Chrome => generate_chrome_user_agent(device_type, platform_type),
-        BrowserType::Firefox => generate_firefox_user_agent(device_type, platform_type),
-        BrowserType::Safari => generate_safari_user_agent(device_type, platform_type),
-        BrowserType::Edge => generate_edge_user_agent(device_type, platform_type),
-        BrowserType::Opera => generate_opera_user_agent(device_type, platform_type),
+        BrowserType::Chrome => generate_chrome_user_agent(device_type, platform_type, major),
+        BrowserType::Firefox => generate_firefox_user_agent(device_type, platform_type, major),
+        BrowserType::Safari => generate_safari_user_agent(device_type, platform_type, major),
+        BrowserType::Edge => generate_edge_user_agent(device_type, platform_type, major),
+        BrowserType::Opera => generate_opera_user_agent(device_type, platform_type, major),
         BrowserType::Custom(name) => format!("{}/{}", name, "1.0.0"),
     }
 }
@@ -45,10 +55,10 @@ pub fn random_desktop_user_agent() -> String {
 }
 
 /// Generate a Chrome user agent
-fn generate_chrome_user_agent(device_type: &DeviceType, platform_type: &PlatformType) -> String {
-    let mut rng = rand::thread_rng();
+fn generate_chrome_user_agent(device_type: &DeviceType, platform_type: &PlatformType, major: Option<u32>) -> String {
+    let mut rng = crate::random::rng();
     let chrome_version = format!("{}.0.{}.{}",
-        rng.gen_range(90..118),
+        major.unwrap_or_else(|| rng.gen_range(90..118)),
         rng.gen_range(4000..5000),
         rng.gen_range(80..200)
     );
@@ -113,10 +123,10 @@ fn generate_chrome_user_agent(device_type: &DeviceType, platform_type: &Platform
 }
 
 /// Generate a Firefox user agent
-fn generate_firefox_user_agent(device_type: &DeviceType, platform_type: &PlatformType) -> String {
-    let mut rng = rand::thread_rng();
+fn generate_firefox_user_agent(device_type: &DeviceType, platform_type: &PlatformType, major: Option<u32>) -> String {
+    let mut rng = crate::random::rng();
     let firefox_version = format!("{}",
-        rng.gen_range(90..118),
+        major.unwrap_or_else(|| rng.gen_range(90..118)),
     );
     
     match (device_type, platform_type) {
@@ -172,10 +182,10 @@ fn generate_firefox_user_agent(device_type: &DeviceType, platform_type: &Platfor
 }
 
 /// Generate a Safari user agent
-fn generate_safari_user_agent(device_type: &DeviceType, platform_type: &PlatformType) -> String {
-    let mut rng = rand::thread_rng();
+fn generate_safari_user_agent(device_type: &DeviceType, platform_type: &PlatformType, major: Option<u32>) -> String {
+    let mut rng = crate::random::rng();
     let safari_version = format!("{}.{}.{}",
-        rng.gen_range(12..17),
+        major.unwrap_or_else(|| rng.gen_range(12..17)),
         rng.gen_range(0..5),
         rng.gen_range(0..20)
     );
@@ -217,16 +227,18 @@ fn generate_safari_user_agent(device_type: &DeviceType, platform_type: &Platform
 }
 
 /// Generate an Edge user agent
-fn generate_edge_user_agent(device_type: &DeviceType, platform_type: &PlatformType) -> String {
-    let mut rng = rand::thread_rng();
+fn generate_edge_user_agent(device_type: &DeviceType, platform_type: &PlatformType, major: Option<u32>) -> String {
+    let mut rng = crate::random::rng();
+    let edge_major = major.unwrap_or_else(|| rng.gen_range(90..118));
     let edge_version = format!("{}.0.{}.{}",
-        rng.gen_range(90..118),
+        edge_major,
         rng.gen_range(1000..2000),
         rng.gen_range(0..200)
     );
     
+    // Edge ships the Chromium release of the same major version
     let chrome_version = format!("{}.0.{}.{}",
-        rng.gen_range(90..118),
+        if major.is_some() { edge_major } else { rng.gen_range(90..118) },
         rng.gen_range(4000..5000),
         rng.gen_range(80..200)
     );
@@ -283,10 +295,10 @@ fn generate_edge_user_agent(device_type: &DeviceType, platform_type: &PlatformTy
 }
 
 /// Generate an Opera user agent
-fn generate_opera_user_agent(device_type: &DeviceType, platform_type: &PlatformType) -> String {
-    let mut rng = rand::thread_rng();
+fn generate_opera_user_agent(device_type: &DeviceType, platform_type: &PlatformType, major: Option<u32>) -> String {
+    let mut rng = crate::random::rng();
     let opera_version = format!("{}.0.{}.{}",
-        rng.gen_range(80..103),
+        major.unwrap_or_else(|| rng.gen_range(80..103)),
         rng.gen_range(0..5),
         rng.gen_range(0..200)
     );
@@ -380,6 +392,25 @@ pub fn parse_browser_from_user_agent(user_agent: &str) -> Option<BrowserType> {
     }
 }
 
+/// Parse a user agent string to extract the major version of a browser
+///
+/// For Chromium-based browsers this is the Chromium version, which decides
+/// the supported web platform features.
+pub fn parse_major_version_from_user_agent(user_agent: &str, browser_type: &BrowserType) -> Option<u32> {
+    let tokens: &[&str] = match browser_type {
+        BrowserType::Chrome | BrowserType::Edge | BrowserType::Opera => &["Chrome/", "CriOS/"],
+        BrowserType::Firefox => &["Firefox/", "FxiOS/"],
+        BrowserType::Safari => &["Version/"],
+        BrowserType::Custom(_) => return None,
+    };
+    
+    tokens.iter().find_map(|token| {
+        let start = user_agent.find(token)? + token.len();
+        let digits: String = user_agent[start..].chars().take_while(|c| c.is_ascii_digit()).collect();
+        digits.parse().ok()
+    })
+}
+
 /// Parse a user agent string to extract the platform type
 pub fn parse_platform_from_user_agent(user_agent: &str) -> Option<PlatformType> {
     let ua = user_agent.to_lowercase();
@@ -424,6 +455,16 @@ pub fn parse_device_from_user_agent(user_agent: &str) -> Option<DeviceType> {
 mod tests {
     use super::*;
     
+    #[test]
+    fn test_parse_major_version() {
+        let chrome = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36";
+        assert_eq!(parse_major_version_from_user_agent(chrome, &BrowserType::Chrome), Some(124));
+        
+        let firefox = "Mozilla/5.0 (X11; Linux x86_64; rv:126.0) Gecko/20100101 Firefox/126.0";
+        assert_eq!(parse_major_version_from_user_agent(firefox, &BrowserType::Firefox), Some(126));
+        assert_eq!(parse_major_version_from_user_agent(firefox, &BrowserType::Safari), None);
+    }
+    
     #[test]
     fn test_generate_user_agent() {
         // Test Chrome on Windows
@@ -10,7 +10,7 @@ pub fn random_uuid() -> String {
 /// Generate a random alphanumeric string of the given length
 pub fn random_string(length: usize) -> String {
     const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     let mut result = String::with_capacity(length);
     
     for _ in 0..length {
@@ -24,7 +24,7 @@ pub fn random_string(length: usize) -> String {
 /// Generate a random hexadecimal string of the given length
 pub fn random_hex(length: usize) -> String {
     const CHARSET: &[u8] = b"0123456789abcdef";
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     let mut result = String::with_capacity(length);
     
     for _ in 0..length {
@@ -184,7 +184,7 @@ pub fn random_element<T: Copy>(slice: &[T]) -> Option<T> {
         return None;
     }
     
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     let idx = rng.gen_range(0..slice.len());
     Some(slice[idx])
 }
@@ -202,7 +202,7 @@ pub fn seeded_random_element<T: Copy>(slice: &[T], seed: u64) -> Option<T> {
 
 /// Generate a random IP address
 pub fn random_ip() -> String {
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     format!(
         "{}.{}.{}.{}",
         rng.gen_range(1..255),
@@ -214,7 +214,7 @@ pub fn random_ip() -> String {
 
 /// Generate a random localhost port
 pub fn random_port() -> u16 {
-    let mut rng = rand::thread_rng();
+    let mut rng = crate::random::rng();
     rng.gen_range(1025..65535)
 }
 
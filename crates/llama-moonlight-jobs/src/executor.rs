@@ -0,0 +1,226 @@
+//! Job executors.
+//!
+//! A [`JobExecutor`] runs one [`JobSpec`] and returns the extracted data. The
+//! [`FetchExecutor`] covers plain and stealth HTTP through the crawler's
+//! fetchers, the [`PoolExecutor`] renders pages in browsers from a
+//! `BrowserPool`, and the [`RoutingExecutor`] dispatches on the job's mode.
+
+use crate::job::{ExecutionMode, JobSpec};
+use crate::{Error, Result};
+use async_trait::async_trait;
+use llama_moonlight_crawler::{Fetcher, HttpFetcher};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::instrument;
+
+/// Runs jobs.
+#[async_trait]
+pub trait JobExecutor: Send + Sync {
+    /// Executes a job and returns the extracted data.
+    async fn execute(&self, spec: &JobSpec) -> Result<Value>;
+
+    /// Whether this executor can run jobs in the given mode.
+    fn supports(&self, mode: ExecutionMode) -> bool;
+
+    /// Short name used in logs.
+    fn name(&self) -> &str;
+}
+
+/// Builds the output of a job from the fetched HTML.
+fn output(spec: &JobSpec, final_url: &str, status: u16, html: &str) -> Result<Value> {
+    match &spec.schema {
+        Some(schema) => Ok(schema.extract(html)?),
+        None => Ok(json!({
+            "url": spec.url,
+            "final_url": final_url,
+            "status": status,
+            "html": html,
+        })),
+    }
+}
+
+/// Executes jobs with a crawler [`Fetcher`].
+pub struct FetchExecutor {
+    fetcher: Arc<dyn Fetcher>,
+    mode: ExecutionMode,
+}
+
+impl FetchExecutor {
+    /// Creates an executor for the given mode using a fetcher.
+    pub fn new(fetcher: impl Fetcher + 'static, mode: ExecutionMode) -> Self {
+        Self {
+            fetcher: Arc::new(fetcher),
+            mode,
+        }
+    }
+
+    /// Creates a plain HTTP executor sending the given user agent.
+    pub fn http(user_agent: &str) -> Result<Self> {
+        Ok(Self::new(HttpFetcher::new(user_agent)?, ExecutionMode::Http))
+    }
+
+    /// Creates an executor sending requests through a stealth client.
+    #[cfg(feature = "stealth")]
+    pub fn stealth(client: llama_moonlight_stealth::StealthClient) -> Result<Self> {
+        Ok(Self::new(
+            llama_moonlight_crawler::StealthHttpFetcher::new(client)?,
+            ExecutionMode::Stealth,
+        ))
+    }
+}
+
+#[async_trait]
+impl JobExecutor for FetchExecutor {
+    #[instrument(skip(self, spec), fields(job_id = %spec.id, url = %spec.url))]
+    async fn execute(&self, spec: &JobSpec) -> Result<Value> {
+        let page = self.fetcher.fetch(&spec.url).await?;
+        if !page.is_success() {
+            return Err(Error::ExecutionError(format!("HTTP status {}", page.status)));
+        }
+        output(spec, &page.final_url, page.status, &page.body)
+    }
+
+    fn supports(&self, mode: ExecutionMode) -> bool {
+        mode == self.mode
+    }
+
+    fn name(&self) -> &str {
+        self.fetcher.name()
+    }
+}
+
+/// Executes jobs in browsers borrowed from a pool.
+#[cfg(feature = "browser")]
+pub struct PoolExecutor {
+    pool: Arc<llama_moonlight_pool::BrowserPool>,
+}
+
+#[cfg(feature = "browser")]
+impl PoolExecutor {
+    /// Creates an executor borrowing browsers from the pool.
+    pub fn new(pool: Arc<llama_moonlight_pool::BrowserPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[cfg(feature = "browser")]
+#[async_trait]
+impl JobExecutor for PoolExecutor {
+    #[instrument(skip(self, spec), fields(job_id = %spec.id, url = %spec.url))]
+    async fn execute(&self, spec: &JobSpec) -> Result<Value> {
+        let to_exec_error = |e: &dyn std::fmt::Display| Error::ExecutionError(e.to_string());
+
+        let browser = self.pool.get_browser().await.map_err(|e| to_exec_error(&e))?;
+        let context = browser.new_context().await.map_err(|e| to_exec_error(&e))?;
+        let page = context.new_page().await.map_err(|e| to_exec_error(&e))?;
+
+        let result = async {
+            page.goto(&spec.url).await.map_err(|e| to_exec_error(&e))?;
+            if let Some(selector) = &spec.wait_for {
+                page.wait_for_selector(selector, Some(spec.timeout_secs * 1000))
+                    .await
+                    .map_err(|e| to_exec_error(&e))?;
+            }
+            let final_url = page.url().await.map_err(|e| to_exec_error(&e))?;
+            let html = page.content().await.map_err(|e| to_exec_error(&e))?;
+            output(spec, &final_url, 200, &html)
+        }
+        .await;
+
+        let _ = page.close().await;
+        let _ = context.close().await;
+        result
+    }
+
+    fn supports(&self, mode: ExecutionMode) -> bool {
+        mode == ExecutionMode::Browser
+    }
+
+    fn name(&self) -> &str {
+        "browser-pool"
+    }
+}
+
+/// Dispatches jobs to an executor per execution mode.
+#[derive(Default)]
+pub struct RoutingExecutor {
+    executors: HashMap<ExecutionMode, Arc<dyn JobExecutor>>,
+}
+
+impl RoutingExecutor {
+    /// Creates a router without executors.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Routes jobs in the given mode to an executor.
+    pub fn with_executor(mut self, mode: ExecutionMode, executor: Arc<dyn JobExecutor>) -> Self {
+        self.executors.insert(mode, executor);
+        self
+    }
+}
+
+#[async_trait]
+impl JobExecutor for RoutingExecutor {
+    async fn execute(&self, spec: &JobSpec) -> Result<Value> {
+        match self.executors.get(&spec.mode) {
+            Some(executor) => executor.execute(spec).await,
+            None => Err(Error::ExecutionError(format!("No executor for mode {:?}", spec.mode))),
+        }
+    }
+
+    fn supports(&self, mode: ExecutionMode) -> bool {
+        self.executors.contains_key(&mode)
+    }
+
+    fn name(&self) -> &str {
+        "router"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use llama_moonlight_crawler::{FetchedPage, FieldSpec, Schema};
+
+    struct StaticFetcher(&'static str);
+
+    #[async_trait]
+    impl Fetcher for StaticFetcher {
+        async fn fetch(&self, url: &str) -> llama_moonlight_crawler::Result<FetchedPage> {
+            Ok(FetchedPage {
+                url: url.to_string(),
+                final_url: url.to_string(),
+                status: 200,
+                headers: HashMap::from([("content-type".to_string(), "text/html".to_string())]),
+                body: self.0.to_string(),
+            })
+        }
+
+        fn name(&self) -> &str {
+            "static"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_executor_applies_schema() {
+        let executor = FetchExecutor::new(StaticFetcher("<h1>Hello</h1>"), ExecutionMode::Http);
+        let spec = JobSpec::new("https://example.com").with_schema(Schema::new().field(FieldSpec::new("title", "h1")));
+
+        let data = executor.execute(&spec).await.unwrap();
+        assert_eq!(data["title"], "Hello");
+    }
+
+    #[tokio::test]
+    async fn test_router_rejects_unknown_mode() {
+        let router = RoutingExecutor::new().with_executor(
+            ExecutionMode::Http,
+            Arc::new(FetchExecutor::new(StaticFetcher("<p>x</p>"), ExecutionMode::Http)),
+        );
+
+        assert!(router.supports(ExecutionMode::Http));
+        let spec = JobSpec::new("https://example.com").with_mode(ExecutionMode::Browser);
+        assert!(router.execute(&spec).await.is_err());
+    }
+}
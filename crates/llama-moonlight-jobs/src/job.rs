@@ -0,0 +1,244 @@
+//! Job, result, and heartbeat types.
+//!
+//! Everything in this module travels over the queue as JSON, so workers and
+//! producers only need to agree on these types, not on a language or runtime.
+
+use chrono::{DateTime, Utc};
+use llama_moonlight_crawler::Schema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// How a job fetches its page.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionMode {
+    /// Plain HTTP request
+    #[default]
+    Http,
+    /// HTTP request through the stealth client
+    Stealth,
+    /// Rendered in a browser from the pool
+    Browser,
+}
+
+/// A scraping task: navigate to a URL and extract data from it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobSpec {
+    /// Unique job ID
+    pub id: String,
+
+    /// URL to navigate to
+    pub url: String,
+
+    /// How to fetch the page
+    #[serde(default)]
+    pub mode: ExecutionMode,
+
+    /// Schema describing what to extract (the raw HTML is returned if absent)
+    #[serde(default)]
+    pub schema: Option<Schema>,
+
+    /// CSS selector to wait for before extracting (browser mode only)
+    #[serde(default)]
+    pub wait_for: Option<String>,
+
+    /// Maximum execution time per attempt in seconds
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// Maximum number of attempts before the job is reported as failed
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+
+    /// Arbitrary metadata passed through to the result
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+fn default_timeout_secs() -> u64 {
+    60
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+impl JobSpec {
+    /// Creates a spec fetching a URL over plain HTTP.
+    pub fn new(url: &str) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            url: url.to_string(),
+            mode: ExecutionMode::default(),
+            schema: None,
+            wait_for: None,
+            timeout_secs: default_timeout_secs(),
+            max_attempts: default_max_attempts(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Sets the execution mode.
+    pub fn with_mode(mut self, mode: ExecutionMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets the extraction schema.
+    pub fn with_schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Waits for a selector before extracting.
+    pub fn with_wait_for(mut self, selector: &str) -> Self {
+        self.wait_for = Some(selector.to_string());
+        self
+    }
+
+    /// Sets the per-attempt timeout.
+    pub fn with_timeout_secs(mut self, timeout_secs: u64) -> Self {
+        self.timeout_secs = timeout_secs;
+        self
+    }
+
+    /// Sets the maximum number of attempts.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Adds a metadata entry.
+    pub fn with_metadata(mut self, key: &str, value: &str) -> Self {
+        self.metadata.insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+/// A job as it sits on the queue.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    /// What to do
+    pub spec: JobSpec,
+
+    /// Number of attempts already made
+    pub attempt: u32,
+
+    /// When the job was first enqueued
+    pub enqueued_at: DateTime<Utc>,
+}
+
+impl Job {
+    /// Whether another attempt is allowed after the current one fails.
+    pub fn can_retry(&self) -> bool {
+        self.attempt + 1 < self.spec.max_attempts
+    }
+
+    /// Returns the job for its next attempt.
+    pub fn next_attempt(&self) -> Self {
+        Self {
+            attempt: self.attempt + 1,
+            ..self.clone()
+        }
+    }
+}
+
+impl From<JobSpec> for Job {
+    fn from(spec: JobSpec) -> Self {
+        Self {
+            spec,
+            attempt: 0,
+            enqueued_at: Utc::now(),
+        }
+    }
+}
+
+/// Final state of a job attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    /// The job succeeded
+    Succeeded,
+    /// The attempt failed and the job was requeued
+    Retrying,
+    /// The job failed and will not be retried
+    Failed,
+}
+
+/// Result of one job attempt, published back to the queue.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobResult {
+    /// ID of the job
+    pub job_id: String,
+
+    /// URL of the job
+    pub url: String,
+
+    /// ID of the worker that ran the attempt
+    pub worker_id: String,
+
+    /// Outcome
+    pub status: JobStatus,
+
+    /// Extracted data (on success)
+    pub data: Option<serde_json::Value>,
+
+    /// Error message (on failure)
+    pub error: Option<String>,
+
+    /// Zero-based attempt number
+    pub attempt: u32,
+
+    /// How long the attempt took in milliseconds
+    pub duration_ms: u64,
+
+    /// When the attempt finished
+    pub finished_at: DateTime<Utc>,
+
+    /// Metadata copied from the job spec
+    pub metadata: HashMap<String, String>,
+}
+
+/// Liveness report periodically published by every worker.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkerHeartbeat {
+    /// Worker ID
+    pub worker_id: String,
+
+    /// When the worker started
+    pub started_at: DateTime<Utc>,
+
+    /// When this heartbeat was sent
+    pub sent_at: DateTime<Utc>,
+
+    /// Jobs currently being executed
+    pub in_flight: usize,
+
+    /// Jobs completed successfully since start
+    pub succeeded: u64,
+
+    /// Job attempts that failed since start
+    pub failed: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_defaults_from_json() {
+        let spec: JobSpec = serde_json::from_str(r#"{"id": "j1", "url": "https://example.com"}"#).unwrap();
+        assert_eq!(spec.mode, ExecutionMode::Http);
+        assert_eq!(spec.timeout_secs, 60);
+        assert_eq!(spec.max_attempts, 3);
+        assert!(spec.schema.is_none());
+    }
+
+    #[test]
+    fn test_retry_accounting() {
+        let job: Job = JobSpec::new("https://example.com").with_max_attempts(2).into();
+        assert!(job.can_retry());
+        assert!(!job.next_attempt().can_retry());
+    }
+}
@@ -0,0 +1,104 @@
+//! # llama-moonlight-jobs
+//!
+//! Queue-backed distributed job execution for the Llama Moonlight ecosystem.
+//!
+//! Scraping tasks ([`JobSpec`]: navigate to a URL and extract with a schema) are
+//! pushed to a shared queue. Any number of [`Worker`]s, on any number of
+//! machines, pull jobs, execute them through a [`JobExecutor`] (plain HTTP, the
+//! stealth client, or a browser pool), and publish results and errors back.
+//!
+//! ## Features
+//!
+//! - Redis backend (`redis` feature) built on reliable list queues
+//! - NATS JetStream backend (`nats` feature) built on a work-queue stream
+//! - In-memory backend for tests and single-process use
+//! - Worker heartbeats and requeueing of jobs held by dead workers
+//! - At-least-once delivery: jobs are acknowledged only after their result is published
+//! - Retries with a per-job attempt limit
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use llama_moonlight_jobs::{FetchExecutor, JobQueue, JobSpec, MemoryQueue, Result, Worker, WorkerConfig};
+//! use std::sync::Arc;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<()> {
+//!     let queue = Arc::new(MemoryQueue::new());
+//!     queue.push(JobSpec::new("https://example.com").into()).await?;
+//!
+//!     let executor = Arc::new(FetchExecutor::http("llama-moonlight-jobs")?);
+//!     let worker = Worker::new(WorkerConfig::default(), queue.clone(), executor);
+//!     worker.run_until_idle().await?;
+//!
+//!     for result in queue.results().await {
+//!         println!("{}: {:?}", result.job_id, result.status);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs)]
+
+use thiserror::Error;
+
+pub mod executor;
+pub mod job;
+pub mod queue;
+pub mod worker;
+
+#[cfg(feature = "nats")]
+pub mod nats_queue;
+#[cfg(feature = "redis")]
+pub mod redis_queue;
+
+pub use executor::{FetchExecutor, JobExecutor, RoutingExecutor};
+pub use job::{ExecutionMode, Job, JobResult, JobSpec, JobStatus, WorkerHeartbeat};
+pub use queue::{Delivery, JobQueue, MemoryQueue};
+pub use worker::{Worker, WorkerConfig};
+
+#[cfg(feature = "browser")]
+pub use executor::PoolExecutor;
+#[cfg(feature = "nats")]
+pub use nats_queue::{NatsConfig, NatsQueue};
+#[cfg(feature = "redis")]
+pub use redis_queue::{RedisConfig, RedisQueue};
+
+/// Custom result type for job operations
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Error type for job operations
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Errors from the queue backend
+    #[error("Queue error: {0}")]
+    QueueError(String),
+
+    /// Errors while executing a job
+    #[error("Execution error: {0}")]
+    ExecutionError(String),
+
+    /// The job did not finish within its timeout
+    #[error("Job timed out after {0} seconds")]
+    Timeout(u64),
+
+    /// Errors from the crawler (fetching, extraction)
+    #[error("Crawler error: {0}")]
+    CrawlerError(#[from] llama_moonlight_crawler::Error),
+
+    /// Serialization errors
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+
+    /// Other errors
+    #[error("Other error: {0}")]
+    Other(String),
+}
+
+#[cfg(feature = "redis")]
+impl From<redis::RedisError> for Error {
+    fn from(e: redis::RedisError) -> Self {
+        Error::QueueError(e.to_string())
+    }
+}
@@ -0,0 +1,191 @@
+//! NATS JetStream queue backend.
+//!
+//! Jobs are published to a work-queue stream and pulled through a shared
+//! durable consumer with explicit acks. JetStream redelivers any message not
+//! acknowledged within `ack_wait`, which covers crashed workers, so
+//! `ack_wait` must be longer than the longest job timeout. Results and
+//! heartbeats are published on plain NATS subjects.
+
+use crate::job::{Job, JobResult, WorkerHeartbeat};
+use crate::queue::{Delivery, JobQueue, Receipt};
+use crate::{Error, Result};
+use async_nats::jetstream::{self, consumer::pull, consumer::AckPolicy, stream};
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{debug, info};
+
+/// Configuration for the NATS backend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NatsConfig {
+    /// Server URL, e.g. `nats://localhost:4222`
+    pub url: String,
+
+    /// JetStream stream holding pending jobs
+    pub stream: String,
+
+    /// Subject jobs are published on
+    pub jobs_subject: String,
+
+    /// Subject results are published on
+    pub results_subject: String,
+
+    /// Subject heartbeats are published on
+    pub heartbeat_subject: String,
+
+    /// Durable consumer shared by all workers
+    pub consumer: String,
+
+    /// How long a pulled job may stay unacknowledged before redelivery
+    pub ack_wait: Duration,
+}
+
+impl Default for NatsConfig {
+    fn default() -> Self {
+        Self {
+            url: "nats://localhost:4222".to_string(),
+            stream: "LLAMA_JOBS".to_string(),
+            jobs_subject: "llama.jobs.pending".to_string(),
+            results_subject: "llama.jobs.results".to_string(),
+            heartbeat_subject: "llama.jobs.heartbeats".to_string(),
+            consumer: "llama-workers".to_string(),
+            ack_wait: Duration::from_secs(120),
+        }
+    }
+}
+
+/// Job queue backed by a NATS JetStream work queue.
+pub struct NatsQueue {
+    client: async_nats::Client,
+    jetstream: jetstream::Context,
+    consumer: jetstream::consumer::Consumer<pull::Config>,
+    config: NatsConfig,
+}
+
+/// Converts any NATS error into a queue error.
+fn queue_error(e: impl std::fmt::Display) -> Error {
+    Error::QueueError(e.to_string())
+}
+
+impl NatsQueue {
+    /// Connects and creates the stream and consumer if needed.
+    pub async fn connect(config: NatsConfig) -> Result<Self> {
+        let client = async_nats::connect(&config.url).await.map_err(queue_error)?;
+        let jetstream = jetstream::new(client.clone());
+
+        let stream = jetstream
+            .get_or_create_stream(stream::Config {
+                name: config.stream.clone(),
+                subjects: vec![config.jobs_subject.clone()],
+                retention: stream::RetentionPolicy::WorkQueue,
+                ..Default::default()
+            })
+            .await
+            .map_err(queue_error)?;
+
+        let consumer = stream
+            .get_or_create_consumer(
+                &config.consumer,
+                pull::Config {
+                    durable_name: Some(config.consumer.clone()),
+                    ack_policy: AckPolicy::Explicit,
+                    ack_wait: config.ack_wait,
+                    ..Default::default()
+                },
+            )
+            .await
+            .map_err(queue_error)?;
+
+        info!("Connected to NATS job stream {} at {}", config.stream, config.url);
+        Ok(Self {
+            client,
+            jetstream,
+            consumer,
+            config,
+        })
+    }
+
+    /// Returns the configuration.
+    pub fn config(&self) -> &NatsConfig {
+        &self.config
+    }
+
+    fn message(delivery: &Delivery) -> Result<&jetstream::Message> {
+        match &delivery.receipt {
+            Receipt::Nats(message) => Ok(message),
+            #[allow(unreachable_patterns)]
+            _ => Err(Error::QueueError("Delivery does not belong to the NATS queue".to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl JobQueue for NatsQueue {
+    async fn push(&self, job: Job) -> Result<()> {
+        let payload = serde_json::to_vec(&job)?;
+        // The second await waits for the stream to persist the message
+        self.jetstream
+            .publish(self.config.jobs_subject.clone(), payload.into())
+            .await
+            .map_err(queue_error)?
+            .await
+            .map_err(queue_error)?;
+        Ok(())
+    }
+
+    async fn pull(&self, _worker_id: &str, wait: Duration) -> Result<Option<Delivery>> {
+        let mut batch = self
+            .consumer
+            .fetch()
+            .max_messages(1)
+            .expires(wait.max(Duration::from_millis(100)))
+            .messages()
+            .await
+            .map_err(queue_error)?;
+
+        let Some(message) = batch.next().await else {
+            return Ok(None);
+        };
+        let message = message.map_err(queue_error)?;
+
+        let mut job: Job = serde_json::from_slice(&message.payload)?;
+        // Redeliveries after a crashed worker count as attempts too
+        if let Ok(info) = message.info() {
+            job.attempt += (info.delivered.max(1) - 1) as u32;
+        }
+
+        debug!(job_id = %job.spec.id, attempt = job.attempt, "Pulled job");
+        Ok(Some(Delivery::new(job, Receipt::Nats(Box::new(message)))))
+    }
+
+    async fn ack(&self, delivery: Delivery) -> Result<()> {
+        Self::message(&delivery)?.ack().await.map_err(queue_error)
+    }
+
+    async fn retry(&self, delivery: Delivery) -> Result<()> {
+        // Publish the next attempt before acking so the job can't be lost
+        self.push(delivery.job.next_attempt()).await?;
+        self.ack(delivery).await
+    }
+
+    async fn publish_result(&self, result: &JobResult) -> Result<()> {
+        let payload = serde_json::to_vec(result)?;
+        self.client
+            .publish(self.config.results_subject.clone(), payload.into())
+            .await
+            .map_err(queue_error)
+    }
+
+    async fn heartbeat(&self, heartbeat: &WorkerHeartbeat) -> Result<()> {
+        let payload = serde_json::to_vec(heartbeat)?;
+        self.client
+            .publish(self.config.heartbeat_subject.clone(), payload.into())
+            .await
+            .map_err(queue_error)
+    }
+
+    fn name(&self) -> &str {
+        "nats"
+    }
+}
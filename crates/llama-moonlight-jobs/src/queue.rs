@@ -0,0 +1,311 @@
+//! Queue abstraction.
+//!
+//! A [`JobQueue`] hands jobs to workers and collects their results. Delivery is
+//! at-least-once: a pulled job stays owned by the worker until it is
+//! acknowledged, and jobs held by workers that stop heartbeating are handed out
+//! again. Workers acknowledge only after the result has been published, so a
+//! crash between the two produces a duplicate result rather than a lost one.
+
+use crate::job::{Job, JobResult, WorkerHeartbeat};
+use crate::{Error, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// A job handed to a worker, to be acknowledged or retried.
+#[derive(Debug)]
+pub struct Delivery {
+    /// The delivered job
+    pub job: Job,
+    pub(crate) receipt: Receipt,
+}
+
+impl Delivery {
+    pub(crate) fn new(job: Job, receipt: Receipt) -> Self {
+        Self { job, receipt }
+    }
+}
+
+/// Backend-specific handle used to acknowledge a delivery.
+#[derive(Debug)]
+pub(crate) enum Receipt {
+    /// Key into the in-memory in-flight table
+    Memory(u64),
+    /// Raw payload and processing list it was moved to
+    #[cfg(feature = "redis")]
+    Redis {
+        payload: String,
+        processing: String,
+    },
+    /// The JetStream message to acknowledge
+    #[cfg(feature = "nats")]
+    Nats(Box<async_nats::jetstream::Message>),
+}
+
+/// A shared queue of jobs and results.
+#[async_trait]
+pub trait JobQueue: Send + Sync {
+    /// Enqueues a job.
+    async fn push(&self, job: Job) -> Result<()>;
+
+    /// Waits up to `wait` for a job and hands it to the worker.
+    async fn pull(&self, worker_id: &str, wait: Duration) -> Result<Option<Delivery>>;
+
+    /// Acknowledges a delivery so it is never handed out again.
+    async fn ack(&self, delivery: Delivery) -> Result<()>;
+
+    /// Re-enqueues a delivery for its next attempt.
+    async fn retry(&self, delivery: Delivery) -> Result<()>;
+
+    /// Publishes the result of a job attempt.
+    async fn publish_result(&self, result: &JobResult) -> Result<()>;
+
+    /// Records a worker heartbeat.
+    async fn heartbeat(&self, heartbeat: &WorkerHeartbeat) -> Result<()>;
+
+    /// Re-enqueues jobs held by workers without a heartbeat for `timeout`.
+    ///
+    /// Returns the number of jobs requeued. Backends whose broker redelivers
+    /// unacknowledged messages by itself need not do anything here.
+    async fn requeue_dead(&self, _timeout: Duration) -> Result<usize> {
+        Ok(0)
+    }
+
+    /// Short name used in logs.
+    fn name(&self) -> &str;
+}
+
+/// A job held by a worker.
+#[derive(Debug)]
+struct InFlight {
+    job: Job,
+    worker_id: String,
+    pulled_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    pending: VecDeque<Job>,
+    in_flight: HashMap<u64, InFlight>,
+    results: Vec<JobResult>,
+    heartbeats: HashMap<String, (WorkerHeartbeat, Instant)>,
+    next_receipt: u64,
+}
+
+/// In-process queue, for tests and single-machine use.
+#[derive(Debug, Default)]
+pub struct MemoryQueue {
+    state: Mutex<MemoryState>,
+    notify: Notify,
+}
+
+impl MemoryQueue {
+    /// Creates an empty queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of jobs waiting to be pulled.
+    pub fn pending_len(&self) -> usize {
+        self.lock().map(|s| s.pending.len()).unwrap_or(0)
+    }
+
+    /// Number of jobs pulled but not yet acknowledged.
+    pub fn in_flight_len(&self) -> usize {
+        self.lock().map(|s| s.in_flight.len()).unwrap_or(0)
+    }
+
+    /// All results published so far.
+    pub async fn results(&self) -> Vec<JobResult> {
+        self.lock().map(|s| s.results.clone()).unwrap_or_default()
+    }
+
+    /// The latest heartbeat of every worker.
+    pub fn heartbeats(&self) -> Vec<WorkerHeartbeat> {
+        self.lock()
+            .map(|s| s.heartbeats.values().map(|(hb, _)| hb.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, MemoryState>> {
+        self.state
+            .lock()
+            .map_err(|_| Error::QueueError("Memory queue lock poisoned".to_string()))
+    }
+
+    fn try_take(&self, worker_id: &str) -> Result<Option<Delivery>> {
+        let mut state = self.lock()?;
+        let Some(job) = state.pending.pop_front() else {
+            return Ok(None);
+        };
+
+        let receipt = state.next_receipt;
+        state.next_receipt += 1;
+        state.in_flight.insert(
+            receipt,
+            InFlight {
+                job: job.clone(),
+                worker_id: worker_id.to_string(),
+                pulled_at: Instant::now(),
+            },
+        );
+
+        Ok(Some(Delivery::new(job, Receipt::Memory(receipt))))
+    }
+
+    fn take_in_flight(&self, delivery: &Delivery) -> Result<Option<InFlight>> {
+        match delivery.receipt {
+            Receipt::Memory(receipt) => Ok(self.lock()?.in_flight.remove(&receipt)),
+            #[allow(unreachable_patterns)]
+            _ => Err(Error::QueueError("Delivery does not belong to the memory queue".to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl JobQueue for MemoryQueue {
+    async fn push(&self, job: Job) -> Result<()> {
+        self.lock()?.pending.push_back(job);
+        self.notify.notify_one();
+        Ok(())
+    }
+
+    async fn pull(&self, worker_id: &str, wait: Duration) -> Result<Option<Delivery>> {
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            // Register interest before checking so a concurrent push isn't missed.
+            let notified = self.notify.notified();
+            if let Some(delivery) = self.try_take(worker_id)? {
+                return Ok(Some(delivery));
+            }
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                return Ok(None);
+            }
+        }
+    }
+
+    async fn ack(&self, delivery: Delivery) -> Result<()> {
+        self.take_in_flight(&delivery)?;
+        Ok(())
+    }
+
+    async fn retry(&self, delivery: Delivery) -> Result<()> {
+        if self.take_in_flight(&delivery)?.is_some() {
+            self.push(delivery.job.next_attempt()).await?;
+        }
+        Ok(())
+    }
+
+    async fn publish_result(&self, result: &JobResult) -> Result<()> {
+        self.lock()?.results.push(result.clone());
+        Ok(())
+    }
+
+    async fn heartbeat(&self, heartbeat: &WorkerHeartbeat) -> Result<()> {
+        self.lock()?
+            .heartbeats
+            .insert(heartbeat.worker_id.clone(), (heartbeat.clone(), Instant::now()));
+        Ok(())
+    }
+
+    async fn requeue_dead(&self, timeout: Duration) -> Result<usize> {
+        let requeued = {
+            let mut state = self.lock()?;
+            let alive = |worker_id: &str, pulled_at: Instant, state: &MemoryState| {
+                match state.heartbeats.get(worker_id) {
+                    Some((_, seen)) => seen.elapsed() < timeout,
+                    // Never heartbeated: give it the same grace period from the pull
+                    None => pulled_at.elapsed() < timeout,
+                }
+            };
+
+            let dead: Vec<u64> = state
+                .in_flight
+                .iter()
+                .filter(|(_, held)| !alive(&held.worker_id, held.pulled_at, &state))
+                .map(|(receipt, _)| *receipt)
+                .collect();
+
+            for receipt in &dead {
+                if let Some(held) = state.in_flight.remove(receipt) {
+                    let mut job = held.job;
+                    job.enqueued_at = Utc::now();
+                    state.pending.push_back(job);
+                }
+            }
+            dead.len()
+        };
+
+        for _ in 0..requeued {
+            self.notify.notify_one();
+        }
+        Ok(requeued)
+    }
+
+    fn name(&self) -> &str {
+        "memory"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::JobSpec;
+
+    fn job(url: &str) -> Job {
+        JobSpec::new(url).into()
+    }
+
+    #[tokio::test]
+    async fn test_pull_ack() {
+        let queue = MemoryQueue::new();
+        queue.push(job("https://example.com/a")).await.unwrap();
+
+        let delivery = queue.pull("w1", Duration::from_millis(10)).await.unwrap().unwrap();
+        assert_eq!(delivery.job.spec.url, "https://example.com/a");
+        assert_eq!(queue.in_flight_len(), 1);
+
+        queue.ack(delivery).await.unwrap();
+        assert_eq!(queue.in_flight_len(), 0);
+        assert!(queue.pull("w1", Duration::from_millis(10)).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pull_waits_for_push() {
+        let queue = std::sync::Arc::new(MemoryQueue::new());
+        let producer = queue.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            producer.push(job("https://example.com/late")).await.unwrap();
+        });
+
+        let delivery = queue.pull("w1", Duration::from_secs(2)).await.unwrap();
+        assert!(delivery.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_retry_increments_attempt() {
+        let queue = MemoryQueue::new();
+        queue.push(job("https://example.com/a")).await.unwrap();
+
+        let delivery = queue.pull("w1", Duration::ZERO).await.unwrap().unwrap();
+        queue.retry(delivery).await.unwrap();
+
+        let again = queue.pull("w1", Duration::ZERO).await.unwrap().unwrap();
+        assert_eq!(again.job.attempt, 1);
+    }
+
+    #[tokio::test]
+    async fn test_requeue_dead_worker() {
+        let queue = MemoryQueue::new();
+        queue.push(job("https://example.com/a")).await.unwrap();
+
+        let _lost = queue.pull("dead-worker", Duration::ZERO).await.unwrap().unwrap();
+        assert_eq!(queue.requeue_dead(Duration::from_secs(60)).await.unwrap(), 0);
+        assert_eq!(queue.requeue_dead(Duration::ZERO).await.unwrap(), 1);
+        assert_eq!(queue.pending_len(), 1);
+    }
+}
@@ -0,0 +1,249 @@
+//! Redis queue backend.
+//!
+//! Implements the reliable-queue pattern on Redis lists. Producers `LPUSH`
+//! jobs onto `<prefix>:pending`; a worker atomically moves one job into its own
+//! `<prefix>:processing:<worker>` list with `BLMOVE` and removes it from there
+//! once the result is published. Heartbeats live in the `<prefix>:heartbeats`
+//! hash; any worker can move the processing list of a worker whose heartbeat
+//! went stale back onto the pending list. Results are appended to
+//! `<prefix>:results` and also published on the channel of the same name.
+//!
+//! Requires Redis 6.2 or newer for `BLMOVE`/`LMOVE`.
+
+use crate::job::{Job, JobResult, WorkerHeartbeat};
+use crate::queue::{Delivery, JobQueue, Receipt};
+use crate::{Error, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{debug, info};
+
+/// Configuration for the Redis backend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedisConfig {
+    /// Redis URL, e.g. `redis://localhost:6379`
+    pub url: String,
+
+    /// Prefix of every key used by the queue
+    pub key_prefix: String,
+
+    /// Maximum number of results kept in the results list (0 keeps all)
+    pub max_results: usize,
+}
+
+impl Default for RedisConfig {
+    fn default() -> Self {
+        Self {
+            url: "redis://localhost:6379".to_string(),
+            key_prefix: "llama:jobs".to_string(),
+            max_results: 100_000,
+        }
+    }
+}
+
+/// Job queue backed by Redis lists.
+pub struct RedisQueue {
+    client: redis::Client,
+    conn: ConnectionManager,
+    /// Idle connections for blocking commands, reused across pulls
+    blocking: Mutex<Vec<ConnectionManager>>,
+    config: RedisConfig,
+}
+
+impl RedisQueue {
+    /// Connects to Redis.
+    pub async fn connect(config: RedisConfig) -> Result<Self> {
+        let client = redis::Client::open(config.url.as_str())?;
+        let conn = ConnectionManager::new(client.clone()).await?;
+        info!("Connected to Redis job queue {} at {}", config.key_prefix, config.url);
+        Ok(Self { client, conn, blocking: Mutex::new(Vec::new()), config })
+    }
+
+    /// Returns the configuration.
+    pub fn config(&self) -> &RedisConfig {
+        &self.config
+    }
+
+    fn pending_key(&self) -> String {
+        format!("{}:pending", self.config.key_prefix)
+    }
+
+    fn processing_key(&self, worker_id: &str) -> String {
+        format!("{}:processing:{}", self.config.key_prefix, worker_id)
+    }
+
+    fn heartbeats_key(&self) -> String {
+        format!("{}:heartbeats", self.config.key_prefix)
+    }
+
+    fn workers_key(&self) -> String {
+        format!("{}:workers", self.config.key_prefix)
+    }
+
+    fn results_key(&self) -> String {
+        format!("{}:results", self.config.key_prefix)
+    }
+
+    /// Number of jobs waiting to be pulled.
+    pub async fn pending_len(&self) -> Result<usize> {
+        Ok(self.conn.clone().llen(self.pending_key()).await?)
+    }
+
+    fn receipt(delivery: &Delivery) -> Result<(&str, &str)> {
+        match &delivery.receipt {
+            Receipt::Redis { payload, processing } => Ok((payload, processing)),
+            #[allow(unreachable_patterns)]
+            _ => Err(Error::QueueError("Delivery does not belong to the Redis queue".to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl JobQueue for RedisQueue {
+    async fn push(&self, job: Job) -> Result<()> {
+        let payload = serde_json::to_string(&job)?;
+        let _: () = self.conn.clone().lpush(self.pending_key(), payload).await?;
+        Ok(())
+    }
+
+    async fn pull(&self, worker_id: &str, wait: Duration) -> Result<Option<Delivery>> {
+        let processing = self.processing_key(worker_id);
+        let _: () = self.conn.clone().sadd(self.workers_key(), worker_id).await?;
+
+        // Blocking commands would stall every other request on the shared
+        // multiplexed connection, so block on a dedicated one. It only goes
+        // back to the idle list once the command completed; a pull cancelled
+        // mid-command drops it.
+        let idle = self.blocking.lock().unwrap().pop();
+        let mut blocking = match idle {
+            Some(conn) => conn,
+            None => ConnectionManager::new(self.client.clone()).await?,
+        };
+        let payload: Option<String> = redis::cmd("BLMOVE")
+            .arg(self.pending_key())
+            .arg(&processing)
+            .arg("RIGHT")
+            .arg("LEFT")
+            .arg(wait.as_secs_f64().max(0.01))
+            .query_async(&mut blocking)
+            .await?;
+        self.blocking.lock().unwrap().push(blocking);
+
+        let Some(payload) = payload else {
+            return Ok(None);
+        };
+
+        let job: Job = match serde_json::from_str(&payload) {
+            Ok(job) => job,
+            Err(e) => {
+                // Drop poison messages rather than redelivering them forever
+                let _: () = self.conn.clone().lrem(&processing, 1, &payload).await?;
+                return Err(e.into());
+            }
+        };
+
+        debug!(job_id = %job.spec.id, attempt = job.attempt, "Pulled job");
+        Ok(Some(Delivery::new(job, Receipt::Redis { payload, processing })))
+    }
+
+    async fn ack(&self, delivery: Delivery) -> Result<()> {
+        let (payload, processing) = Self::receipt(&delivery)?;
+        let _: () = self.conn.clone().lrem(processing, 1, payload).await?;
+        Ok(())
+    }
+
+    async fn retry(&self, delivery: Delivery) -> Result<()> {
+        let (payload, processing) = Self::receipt(&delivery)?;
+        let next = serde_json::to_string(&delivery.job.next_attempt())?;
+
+        let _: () = redis::pipe()
+            .atomic()
+            .lrem(processing, 1, payload)
+            .ignore()
+            .lpush(self.pending_key(), next)
+            .ignore()
+            .query_async(&mut self.conn.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn publish_result(&self, result: &JobResult) -> Result<()> {
+        let payload = serde_json::to_string(result)?;
+        let key = self.results_key();
+
+        let mut pipe = redis::pipe();
+        pipe.rpush(&key, &payload).ignore();
+        if self.config.max_results > 0 {
+            pipe.ltrim(&key, -(self.config.max_results as isize), -1).ignore();
+        }
+        pipe.publish(&key, &payload).ignore();
+
+        let _: () = pipe.query_async(&mut self.conn.clone()).await?;
+        Ok(())
+    }
+
+    async fn heartbeat(&self, heartbeat: &WorkerHeartbeat) -> Result<()> {
+        let payload = serde_json::to_string(heartbeat)?;
+        let _: () = redis::pipe()
+            .hset(self.heartbeats_key(), &heartbeat.worker_id, payload)
+            .ignore()
+            .sadd(self.workers_key(), &heartbeat.worker_id)
+            .ignore()
+            .query_async(&mut self.conn.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn requeue_dead(&self, timeout: Duration) -> Result<usize> {
+        let mut conn = self.conn.clone();
+        let workers: Vec<String> = conn.smembers(self.workers_key()).await?;
+        let cutoff = Utc::now() - chrono::Duration::from_std(timeout).unwrap_or_else(|_| chrono::Duration::zero());
+        let mut requeued = 0;
+
+        for worker_id in workers {
+            let heartbeat: Option<String> = conn.hget(self.heartbeats_key(), &worker_id).await?;
+            let alive = heartbeat
+                .and_then(|hb| serde_json::from_str::<WorkerHeartbeat>(&hb).ok())
+                .map(|hb| hb.sent_at > cutoff)
+                .unwrap_or(false);
+            if alive {
+                continue;
+            }
+
+            // Move held jobs to the consuming end so they are picked up next
+            let processing = self.processing_key(&worker_id);
+            loop {
+                let moved: Option<String> = redis::cmd("LMOVE")
+                    .arg(&processing)
+                    .arg(self.pending_key())
+                    .arg("RIGHT")
+                    .arg("RIGHT")
+                    .query_async(&mut conn)
+                    .await?;
+                if moved.is_none() {
+                    break;
+                }
+                requeued += 1;
+            }
+
+            let _: () = redis::pipe()
+                .srem(self.workers_key(), &worker_id)
+                .ignore()
+                .hdel(self.heartbeats_key(), &worker_id)
+                .ignore()
+                .query_async(&mut conn)
+                .await?;
+            info!(worker_id = %worker_id, "Removed dead worker");
+        }
+
+        Ok(requeued)
+    }
+
+    fn name(&self) -> &str {
+        "redis"
+    }
+}
@@ -0,0 +1,371 @@
+//! Job workers.
+//!
+//! A [`Worker`] runs a fixed number of slots, each pulling one job at a time
+//! from the queue, while a background loop publishes heartbeats and requeues
+//! jobs held by workers that stopped heartbeating.
+
+use crate::executor::JobExecutor;
+use crate::job::{JobResult, JobStatus, WorkerHeartbeat};
+use crate::queue::{Delivery, JobQueue};
+use crate::{Error, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tracing::{debug, info, instrument, warn};
+use uuid::Uuid;
+
+/// Configuration for a worker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkerConfig {
+    /// Unique worker ID (reported in heartbeats and results)
+    pub worker_id: String,
+
+    /// Number of jobs executed concurrently
+    pub concurrency: usize,
+
+    /// How long one pull waits for a job
+    pub pull_timeout: Duration,
+
+    /// Interval between heartbeats
+    pub heartbeat_interval: Duration,
+
+    /// Workers silent for longer than this are considered dead
+    pub dead_worker_timeout: Duration,
+
+    /// Delay before pulling again after a queue error
+    pub error_backoff: Duration,
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        Self {
+            worker_id: Uuid::new_v4().to_string(),
+            concurrency: 4,
+            pull_timeout: Duration::from_secs(5),
+            heartbeat_interval: Duration::from_secs(10),
+            dead_worker_timeout: Duration::from_secs(60),
+            error_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Live counters of a worker.
+#[derive(Debug)]
+struct WorkerStats {
+    started_at: DateTime<Utc>,
+    in_flight: AtomicUsize,
+    succeeded: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// Pulls jobs from a queue and executes them.
+pub struct Worker {
+    config: WorkerConfig,
+    queue: Arc<dyn JobQueue>,
+    executor: Arc<dyn JobExecutor>,
+    stats: WorkerStats,
+}
+
+impl Worker {
+    /// Creates a worker.
+    pub fn new(config: WorkerConfig, queue: Arc<dyn JobQueue>, executor: Arc<dyn JobExecutor>) -> Self {
+        Self {
+            config,
+            queue,
+            executor,
+            stats: WorkerStats {
+                started_at: Utc::now(),
+                in_flight: AtomicUsize::new(0),
+                succeeded: AtomicU64::new(0),
+                failed: AtomicU64::new(0),
+            },
+        }
+    }
+
+    /// Returns the worker ID.
+    pub fn id(&self) -> &str {
+        &self.config.worker_id
+    }
+
+    /// Returns the current heartbeat.
+    pub fn heartbeat(&self) -> WorkerHeartbeat {
+        WorkerHeartbeat {
+            worker_id: self.config.worker_id.clone(),
+            started_at: self.stats.started_at,
+            sent_at: Utc::now(),
+            in_flight: self.stats.in_flight.load(Ordering::Relaxed),
+            succeeded: self.stats.succeeded.load(Ordering::Relaxed),
+            failed: self.stats.failed.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Runs until `shutdown` completes, then finishes the jobs in flight.
+    pub async fn run<F>(&self, shutdown: F) -> Result<()>
+    where
+        F: Future<Output = ()>,
+    {
+        self.run_slots(shutdown, false).await
+    }
+
+    /// Runs until the queue has no more jobs.
+    pub async fn run_until_idle(&self) -> Result<()> {
+        self.run_slots(std::future::pending(), true).await
+    }
+
+    #[instrument(skip(self, shutdown), fields(worker_id = %self.config.worker_id, queue = self.queue.name()))]
+    async fn run_slots<F>(&self, shutdown: F, stop_when_idle: bool) -> Result<()>
+    where
+        F: Future<Output = ()>,
+    {
+        info!(concurrency = self.config.concurrency, "Worker started");
+        let (stop_tx, stop_rx) = watch::channel(false);
+
+        let slots = async {
+            let slots = (0..self.config.concurrency.max(1)).map(|_| self.slot(stop_rx.clone(), stop_when_idle));
+            futures::future::join_all(slots).await;
+            // Stop the heartbeat loop once every slot is done
+            let _ = stop_tx.send(true);
+        };
+        let work = async {
+            tokio::join!(slots, self.heartbeat_loop(stop_rx.clone()));
+        };
+
+        tokio::pin!(work);
+        tokio::pin!(shutdown);
+        tokio::select! {
+            _ = &mut work => {}
+            _ = &mut shutdown => {
+                info!("Shutdown requested, finishing jobs in flight");
+                let _ = stop_tx.send(true);
+                work.await;
+            }
+        }
+
+        // Final heartbeat so monitors see the last counters
+        let _ = self.queue.heartbeat(&self.heartbeat()).await;
+        info!("Worker stopped");
+        Ok(())
+    }
+
+    /// One execution slot: pull, execute, report, repeat.
+    async fn slot(&self, mut stop: watch::Receiver<bool>, stop_when_idle: bool) {
+        loop {
+            if *stop.borrow() {
+                break;
+            }
+
+            let pulled = tokio::select! {
+                _ = stop.changed() => break,
+                pulled = self.queue.pull(&self.config.worker_id, self.config.pull_timeout) => pulled,
+            };
+
+            match pulled {
+                Ok(Some(delivery)) => self.process(delivery).await,
+                Ok(None) if stop_when_idle => break,
+                Ok(None) => {}
+                Err(e) => {
+                    warn!("Failed to pull job: {}", e);
+                    tokio::select! {
+                        _ = stop.changed() => break,
+                        _ = tokio::time::sleep(self.config.error_backoff) => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Publishes heartbeats and requeues jobs of dead workers until stopped.
+    async fn heartbeat_loop(&self, mut stop: watch::Receiver<bool>) {
+        let mut interval = tokio::time::interval(self.config.heartbeat_interval);
+        loop {
+            tokio::select! {
+                _ = stop.changed() => break,
+                _ = interval.tick() => {}
+            }
+
+            if let Err(e) = self.queue.heartbeat(&self.heartbeat()).await {
+                warn!("Failed to send heartbeat: {}", e);
+            }
+            match self.queue.requeue_dead(self.config.dead_worker_timeout).await {
+                Ok(0) => {}
+                Ok(n) => info!("Requeued {} jobs from dead workers", n),
+                Err(e) => warn!("Failed to requeue jobs from dead workers: {}", e),
+            }
+        }
+    }
+
+    /// Executes one job and reports its result.
+    #[instrument(skip(self, delivery), fields(job_id = %delivery.job.spec.id, attempt = delivery.job.attempt))]
+    async fn process(&self, delivery: Delivery) {
+        let job = delivery.job.clone();
+        let spec = &job.spec;
+        self.stats.in_flight.fetch_add(1, Ordering::Relaxed);
+        let started = Instant::now();
+
+        let outcome = if !self.executor.supports(spec.mode) {
+            Err(Error::ExecutionError(format!(
+                "Executor {} does not support mode {:?}",
+                self.executor.name(),
+                spec.mode
+            )))
+        } else {
+            match tokio::time::timeout(Duration::from_secs(spec.timeout_secs), self.executor.execute(spec)).await {
+                Ok(outcome) => outcome,
+                Err(_) => Err(Error::Timeout(spec.timeout_secs)),
+            }
+        };
+
+        let status = match &outcome {
+            Ok(_) => JobStatus::Succeeded,
+            Err(_) if job.can_retry() => JobStatus::Retrying,
+            Err(_) => JobStatus::Failed,
+        };
+        let (data, error) = match outcome {
+            Ok(data) => (Some(data), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+        let result = JobResult {
+            job_id: spec.id.clone(),
+            url: spec.url.clone(),
+            worker_id: self.config.worker_id.clone(),
+            status,
+            data,
+            error,
+            attempt: job.attempt,
+            duration_ms: started.elapsed().as_millis() as u64,
+            finished_at: Utc::now(),
+            metadata: spec.metadata.clone(),
+        };
+
+        match status {
+            JobStatus::Succeeded => self.stats.succeeded.fetch_add(1, Ordering::Relaxed),
+            _ => self.stats.failed.fetch_add(1, Ordering::Relaxed),
+        };
+
+        // Publish before acknowledging: a crash in between yields a duplicate
+        // result, never a lost job.
+        let settled = match self.queue.publish_result(&result).await {
+            Ok(()) if status == JobStatus::Retrying => self.queue.retry(delivery).await,
+            Ok(()) => self.queue.ack(delivery).await,
+            Err(e) => {
+                warn!("Failed to publish result, requeueing job: {}", e);
+                self.queue.retry(delivery).await
+            }
+        };
+        if let Err(e) = settled {
+            warn!("Failed to settle job (it will be redelivered): {}", e);
+        }
+
+        debug!(?status, duration_ms = result.duration_ms, "Job finished");
+        self.stats.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::{ExecutionMode, JobSpec};
+    use crate::queue::MemoryQueue;
+    use async_trait::async_trait;
+    use serde_json::{json, Value};
+
+    /// Fails every URL containing "fail", succeeds otherwise.
+    struct TestExecutor;
+
+    #[async_trait]
+    impl JobExecutor for TestExecutor {
+        async fn execute(&self, spec: &JobSpec) -> Result<Value> {
+            if spec.url.contains("fail") {
+                Err(Error::ExecutionError("boom".to_string()))
+            } else {
+                Ok(json!({ "url": spec.url }))
+            }
+        }
+
+        fn supports(&self, mode: ExecutionMode) -> bool {
+            mode == ExecutionMode::Http
+        }
+
+        fn name(&self) -> &str {
+            "test"
+        }
+    }
+
+    fn config() -> WorkerConfig {
+        WorkerConfig {
+            worker_id: "w1".to_string(),
+            concurrency: 2,
+            pull_timeout: Duration::from_millis(20),
+            ..WorkerConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_until_idle() {
+        let queue = Arc::new(MemoryQueue::new());
+        queue.push(JobSpec::new("https://example.com/ok").into()).await.unwrap();
+        queue
+            .push(JobSpec::new("https://example.com/fail").with_max_attempts(2).into())
+            .await
+            .unwrap();
+
+        let worker = Worker::new(config(), queue.clone(), Arc::new(TestExecutor));
+        worker.run_until_idle().await.unwrap();
+
+        let results = queue.results().await;
+        let statuses = |url: &str| -> Vec<JobStatus> {
+            results.iter().filter(|r| r.url.ends_with(url)).map(|r| r.status).collect()
+        };
+        assert_eq!(statuses("/ok"), vec![JobStatus::Succeeded]);
+        assert_eq!(statuses("/fail"), vec![JobStatus::Retrying, JobStatus::Failed]);
+        assert_eq!(queue.in_flight_len(), 0);
+        assert_eq!(queue.pending_len(), 0);
+
+        let heartbeat = worker.heartbeat();
+        assert_eq!((heartbeat.succeeded, heartbeat.failed), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_mode_fails() {
+        let queue = Arc::new(MemoryQueue::new());
+        queue
+            .push(
+                JobSpec::new("https://example.com")
+                    .with_mode(ExecutionMode::Browser)
+                    .with_max_attempts(1)
+                    .into(),
+            )
+            .await
+            .unwrap();
+
+        Worker::new(config(), queue.clone(), Arc::new(TestExecutor))
+            .run_until_idle()
+            .await
+            .unwrap();
+
+        let results = queue.results().await;
+        assert_eq!(results[0].status, JobStatus::Failed);
+        assert!(results[0].error.as_deref().unwrap().contains("does not support"));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_worker() {
+        let queue = Arc::new(MemoryQueue::new());
+        let worker = Worker::new(config(), queue.clone(), Arc::new(TestExecutor));
+
+        tokio::time::timeout(
+            Duration::from_secs(2),
+            worker.run(tokio::time::sleep(Duration::from_millis(50))),
+        )
+        .await
+        .expect("worker stops after shutdown")
+        .unwrap();
+
+        assert_eq!(queue.heartbeats().len(), 1);
+    }
+}
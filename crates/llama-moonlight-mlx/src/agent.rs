@@ -1,5 +1,8 @@
+use crate::critique::{ActionCritic, Failure, FailureKind, HeuristicCritic};
+use crate::text::{TextGenerationParams, TextModel};
 use crate::MlxError;
 use llama_moonlight_core::Page;
+use log::{debug, warn};
 use serde::{Deserialize, Serialize};
 use std::{path::PathBuf, sync::Arc};
 
@@ -36,6 +39,9 @@ pub struct AgentConfig {
     /// Memory capacity (number of past interactions to remember)
     pub memory_capacity: Option<usize>,
     
+    /// Number of failed actions the agent may try to correct before giving up
+    pub max_self_corrections: Option<usize>,
+    
     /// Custom parameters
     #[serde(flatten)]
     pub custom_params: std::collections::HashMap<String, serde_json::Value>,
@@ -60,6 +66,7 @@ impl Default for AgentConfig {
                 "extract".to_string(),
             ]),
             memory_capacity: Some(5),
+            max_self_corrections: Some(2),
             custom_params: std::collections::HashMap::new(),
         }
     }
@@ -178,6 +185,18 @@ pub struct Agent {
     
     /// Number of actions taken
     action_count: usize,
+    
+    /// Goal the agent is currently pursuing, set by a planner
+    goal: Option<String>,
+    
+    /// Critique of the last action, for the prompt of the next one
+    feedback: Option<String>,
+    
+    /// Failures found by critiquing actions
+    failures: Vec<Failure>,
+    
+    /// Model choosing the next action
+    model: Option<Arc<TextModel>>,
 }
 
 impl Agent {
@@ -189,9 +208,19 @@ impl Agent {
             memory: Vec::new(),
             current_observation: None,
             action_count: 0,
+            goal: None,
+            feedback: None,
+            failures: Vec::new(),
+            model: None,
         }
     }
     
+    /// Set the text model used to plan actions
+    pub fn with_text_model(mut self, model: Arc<TextModel>) -> Self {
+        self.model = Some(model);
+        self
+    }
+    
     /// Get the agent's configuration
     pub fn config(&self) -> &AgentConfig {
         &self.config
@@ -207,6 +236,26 @@ impl Agent {
         &self.memory
     }
     
+    /// Get the goal the agent is currently pursuing
+    pub fn goal(&self) -> Option<&str> {
+        self.goal.as_deref()
+    }
+    
+    /// Set the goal the agent is currently pursuing
+    pub fn set_goal(&mut self, goal: Option<String>) {
+        self.goal = goal;
+    }
+    
+    /// Get the critique of the last action, included in the prompt for the next one
+    pub fn feedback(&self) -> Option<&str> {
+        self.feedback.as_deref()
+    }
+    
+    /// Get the failures found by critiquing actions, oldest first
+    pub fn failures(&self) -> &[Failure] {
+        &self.failures
+    }
+    
     /// Get the current observation
     pub fn current_observation(&self) -> Option<&AgentObservation> {
         self.current_observation.as_ref()
@@ -271,23 +320,57 @@ impl Agent {
         Ok(observation)
     }
     
-    /// Plan the next action based on the current observation
+    /// Build the prompt asking for the next action
+    ///
+    /// Combines the goal, the current observation, the recent actions and the
+    /// critique of the last action. A `prompt_template` in the config can
+    /// rearrange these with the `{goal}`, `{observation}`, `{history}`,
+    /// `{feedback}` and `{actions}` placeholders.
+    pub fn planning_prompt(&self) -> String {
+        build_prompt(
+            &self.config,
+            self.goal.as_deref(),
+            self.current_observation.as_ref(),
+            &self.memory,
+            self.feedback.as_deref(),
+        )
+    }
+    
+    /// Plan the next action from the goal, the current observation and the feedback on the last action
     pub async fn plan_action(&self) -> Result<AgentAction, MlxError> {
-        // In a real implementation, this would use the text model to generate an action
-        // For now, we'll just create a placeholder action
-        let action = AgentAction {
-            action_type: ActionType::Click,
-            parameters: serde_json::json!({
-                "selector": "#main"
-            }),
-            reason: Some("Clicking on the main content".to_string()),
-            success: None,
-            error: None,
+        let model = self.model.as_ref().ok_or_else(|| {
+            MlxError::Agent("No text model configured to plan actions".to_string())
+        })?;
+        
+        let params = TextGenerationParams {
+            max_tokens: 256,
+            temperature: 0.2,
+            ..Default::default()
         };
+        let generation = model.generate(&self.planning_prompt(), params).await?;
+        
+        let action = parse_action(&generation.text).ok_or_else(|| {
+            MlxError::Agent(format!("Model did not return a valid action: {}", generation.text))
+        })?;
+        
+        if !self.is_available(&action.action_type) {
+            return Err(MlxError::Agent(format!(
+                "Model chose unavailable action {:?}",
+                action.action_type
+            )));
+        }
         
         Ok(action)
     }
     
+    /// Check whether an action type is allowed by the config
+    fn is_available(&self, action_type: &ActionType) -> bool {
+        match &self.config.available_actions {
+            Some(actions) => actions.iter().any(|action| action == action_name(action_type)),
+            None => true,
+        }
+    }
+    
     /// Execute an action
     pub async fn execute_action(&mut self, action: AgentAction) -> Result<AgentAction, MlxError> {
         let mut result = action.clone();
@@ -382,26 +465,237 @@ impl Agent {
         Ok(result)
     }
     
-    /// Run the agent autonomously
+    /// Run the agent autonomously, critiquing actions with a [`HeuristicCritic`]
     pub async fn run(&mut self) -> Result<Vec<(AgentAction, AgentObservation)>, MlxError> {
+        self.run_with_critic(&HeuristicCritic::new()).await
+    }
+    
+    /// Run the agent autonomously, critiquing every action with `critic`
+    ///
+    /// When an action misses, the critique is fed into the next prompt and
+    /// the agent tries again, up to `max_self_corrections` times. Being
+    /// blocked ends the run at once, as retrying would only deepen the block.
+    pub async fn run_with_critic(&mut self, critic: &dyn ActionCritic) -> Result<Vec<(AgentAction, AgentObservation)>, MlxError> {
         let max_actions = self.config.max_actions.unwrap_or(10);
+        let budget = self.config.max_self_corrections.unwrap_or(0);
+        let mut corrections = 0;
         
+        let mut before = self.observe().await?;
         for _ in 0..max_actions {
-            // Observe
-            let observation = self.observe().await?;
-            
-            // Plan action
+            // Plan and execute an action
             let action = self.plan_action().await?;
-            
-            // Execute action
             let result = self.execute_action(action).await?;
             
-            // Stop if action failed
-            if let Some(false) = result.success {
+            // Check that it had the expected effect
+            let after = self.observe().await?;
+            let failure = match critic.evaluate(&result, &before, &after).await? {
+                Some(failure) => failure,
+                None => {
+                    self.feedback = None;
+                    before = after;
+                    continue;
+                }
+            };
+            
+            let give_up = failure.kind == FailureKind::Blocked || corrections >= budget;
+            self.feedback = Some(failure.to_prompt(&result));
+            self.failures.push(failure);
+            
+            if give_up {
+                warn!("Stopping after {} self-corrections: {}", corrections, self.feedback.as_deref().unwrap_or_default());
                 break;
             }
+            corrections += 1;
+            debug!("Self-correcting ({}/{}): {}", corrections, budget, self.feedback.as_deref().unwrap_or_default());
+            before = after;
         }
         
         Ok(self.memory.clone())
     }
-} 
\ No newline at end of file
+} 
+
+/// Output format requested from text models
+const ACTION_FORMAT: &str = r#"Reply with the next browser action as JSON only, e.g.
+{"action_type": "click", "parameters": {"selector": "#login"}, "reason": "open the login form"}
+Parameters: click {selector}, type {selector, text}, navigate {url}, wait {milliseconds}, extract {selector}."#;
+
+/// Default prompt layout, used when the config has no template
+const DEFAULT_TEMPLATE: &str = "{format}\nAvailable actions: {actions}\n\nGoal: {goal}\n\n{observation}\n\nRecent actions:\n{history}\n\nFeedback: {feedback}";
+
+/// Longest page content included in a prompt, in characters
+const MAX_PROMPT_CONTENT: usize = 2000;
+
+/// Most elements listed in a prompt
+const MAX_PROMPT_ELEMENTS: usize = 30;
+
+/// Name of a built-in action type, as used in `available_actions`
+fn action_name(action_type: &ActionType) -> &str {
+    match action_type {
+        ActionType::Click => "click",
+        ActionType::Type => "type",
+        ActionType::Navigate => "navigate",
+        ActionType::Wait => "wait",
+        ActionType::Extract => "extract",
+        ActionType::Custom(name) => name,
+    }
+}
+
+/// Build a planning prompt from the agent state
+fn build_prompt(
+    config: &AgentConfig,
+    goal: Option<&str>,
+    observation: Option<&AgentObservation>,
+    memory: &[(AgentAction, AgentObservation)],
+    feedback: Option<&str>,
+) -> String {
+    let actions = config
+        .available_actions
+        .as_ref()
+        .map(|actions| actions.join(", "))
+        .unwrap_or_else(|| "click, type, navigate, wait, extract".to_string());
+    
+    let history = if memory.is_empty() {
+        "none".to_string()
+    } else {
+        memory
+            .iter()
+            .map(|(action, observation)| format!(
+                "- {:?} {} on {} ({})",
+                action.action_type,
+                action.parameters,
+                observation.url,
+                match action.success {
+                    Some(true) => "succeeded".to_string(),
+                    Some(false) => format!("failed: {}", action.error.as_deref().unwrap_or("unknown error")),
+                    None => "not run".to_string(),
+                }
+            ))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    
+    config
+        .prompt_template
+        .as_deref()
+        .unwrap_or(DEFAULT_TEMPLATE)
+        .replace("{format}", ACTION_FORMAT)
+        .replace("{actions}", &actions)
+        .replace("{goal}", goal.or(config.description.as_deref()).unwrap_or("explore the page"))
+        .replace("{observation}", &describe_observation(observation))
+        .replace("{history}", &history)
+        .replace("{feedback}", feedback.unwrap_or("none"))
+}
+
+/// Describe the page for a planning prompt
+fn describe_observation(observation: Option<&AgentObservation>) -> String {
+    let observation = match observation {
+        Some(observation) => observation,
+        None => return "Current page: unknown".to_string(),
+    };
+    
+    let mut description = format!("Current page: {} ({})", observation.title, observation.url);
+    
+    if let Some(elements) = &observation.elements {
+        description.push_str("\nElements:");
+        for element in elements.iter().filter(|element| element.visible).take(MAX_PROMPT_ELEMENTS) {
+            description.push_str(&format!("\n- <{}", element.tag));
+            if let Some(id) = &element.id {
+                description.push_str(&format!(" id=\"{}\"", id));
+            }
+            if let Some(classes) = element.classes.as_ref().filter(|classes| !classes.is_empty()) {
+                description.push_str(&format!(" class=\"{}\"", classes.join(" ")));
+            }
+            description.push('>');
+            if let Some(text) = &element.text {
+                description.push_str(&format!(" {}", text.trim()));
+            }
+        }
+    }
+    
+    if let Some(content) = &observation.content {
+        let content: String = content.chars().take(MAX_PROMPT_CONTENT).collect();
+        description.push_str(&format!("\nContent:\n{}", content));
+    }
+    
+    description
+}
+
+/// Parse the action in a model completion
+fn parse_action(text: &str) -> Option<AgentAction> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    let mut action: AgentAction = serde_json::from_str(text.get(start..=end)?).ok()?;
+    
+    // The model reports intent, not outcome
+    action.success = None;
+    action.error = None;
+    Some(action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn observation(url: &str) -> AgentObservation {
+        AgentObservation {
+            title: "Checkout".to_string(),
+            url: url.to_string(),
+            content: Some("Pay now".to_string()),
+            screenshot: None,
+            elements: Some(vec![ElementInfo {
+                tag: "button".to_string(),
+                id: Some("pay".to_string()),
+                classes: None,
+                text: Some("Pay now".to_string()),
+                visible: true,
+                bounding_box: None,
+                attributes: std::collections::HashMap::new(),
+            }]),
+            timestamp: chrono::Utc::now(),
+        }
+    }
+    
+    #[test]
+    fn test_planning_prompt() {
+        let config = AgentConfig::default();
+        let failed = AgentAction {
+            action_type: ActionType::Click,
+            parameters: serde_json::json!({"selector": "#buy"}),
+            reason: None,
+            success: Some(false),
+            error: Some("No element found for '#buy'".to_string()),
+        };
+        let memory = vec![(failed, observation("https://shop.test/cart"))];
+        
+        let prompt = build_prompt(
+            &config,
+            Some("pay for the order"),
+            Some(&observation("https://shop.test/checkout")),
+            &memory,
+            Some("Your previous action did not work"),
+        );
+        
+        assert!(prompt.contains("Goal: pay for the order"));
+        assert!(prompt.contains("Checkout (https://shop.test/checkout)"));
+        assert!(prompt.contains("<button id=\"pay\"> Pay now"));
+        assert!(prompt.contains("failed: No element found for '#buy'"));
+        assert!(prompt.contains("Feedback: Your previous action did not work"));
+        assert!(prompt.contains("Available actions: click, type, navigate, wait, extract"));
+        
+        let config = AgentConfig {
+            prompt_template: Some("{goal} | {feedback}".to_string()),
+            ..AgentConfig::default()
+        };
+        assert_eq!(build_prompt(&config, Some("log in"), None, &[], None), "log in | none");
+    }
+    
+    #[test]
+    fn test_parse_action() {
+        let action = parse_action(r#"Next: {"action_type": "type", "parameters": {"selector": "#q", "text": "rust"}, "reason": "search", "success": true}"#).unwrap();
+        assert!(matches!(action.action_type, ActionType::Type));
+        assert_eq!(action.parameters["text"], "rust");
+        assert_eq!(action.success, None);
+        
+        assert!(parse_action("click the button").is_none());
+    }
+}
@@ -0,0 +1,440 @@
+//! Self-critique of agent actions
+//!
+//! An action that "succeeds" does not always do what the agent meant: a click
+//! can land on an inert element, a navigation can end on a bot wall, and a
+//! page can re-render between observing and acting. After each action an
+//! [`ActionCritic`] compares the page before and after against the change the
+//! action was expected to cause. A mismatch is classified into a
+//! [`FailureKind`] and handed back to the agent, which includes it in the
+//! prompt for its next action, until its self-correction budget runs out.
+//!
+//! Actions can state what they expect with an `expect` parameter holding
+//! planner [`Criterion`]s, for example
+//! `{"selector": "#next", "expect": [{"type": "url_contains", "value": "page=2"}]}`.
+//! Without one, the expected change is derived from the action type.
+
+use crate::agent::{ActionType, AgentAction, AgentObservation};
+use crate::planner::Criterion;
+use crate::MlxError;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "text")]
+use crate::text::{TextGenerationParams, TextModel};
+#[cfg(feature = "text")]
+use log::warn;
+
+/// Text that marks a page as a bot wall or an access error
+const BLOCKED_MARKERS: &[&str] = &[
+    "captcha",
+    "are you a robot",
+    "verify you are human",
+    "unusual traffic",
+    "access denied",
+    "403 forbidden",
+    "too many requests",
+    "checking your browser",
+];
+
+/// Why an action did not have the expected effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    /// The action targeted the wrong element, or one that does nothing
+    WrongElement,
+
+    /// The page had not finished changing, or the action timed out
+    Timing,
+
+    /// The site refused the agent: a captcha, bot wall or access error
+    Blocked,
+
+    /// The page is laid out differently than the agent assumed
+    LayoutChange,
+
+    /// The failure could not be classified
+    Unknown,
+}
+
+impl FailureKind {
+    /// Get advice for the next action after this kind of failure
+    pub fn advice(&self) -> &'static str {
+        match self {
+            FailureKind::WrongElement => "Pick a different element; check its text and attributes against the goal.",
+            FailureKind::Timing => "Wait for the page to settle before acting again.",
+            FailureKind::Blocked => "The site is blocking automation; do not repeat the same request.",
+            FailureKind::LayoutChange => "Observe the page again and locate the element anew instead of reusing the old selector.",
+            FailureKind::Unknown => "Try a different approach.",
+        }
+    }
+}
+
+/// A classified mismatch between an action and its effect
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Failure {
+    /// Kind of failure
+    pub kind: FailureKind,
+
+    /// What went wrong
+    pub detail: String,
+}
+
+impl Failure {
+    /// Create a failure
+    pub fn new(kind: FailureKind, detail: impl Into<String>) -> Self {
+        Self {
+            kind,
+            detail: detail.into(),
+        }
+    }
+
+    /// Describe the failure for the prompt of the next action
+    pub fn to_prompt(&self, action: &AgentAction) -> String {
+        format!(
+            "Your previous action ({:?} {}) did not work: {} [{:?}]. {}",
+            action.action_type,
+            action.parameters,
+            self.detail,
+            self.kind,
+            self.kind.advice()
+        )
+    }
+}
+
+/// Checks whether actions had their expected effect
+#[async_trait]
+pub trait ActionCritic: Send + Sync {
+    /// Evaluate an executed action, returning the failure if it missed
+    async fn evaluate(
+        &self,
+        action: &AgentAction,
+        before: &AgentObservation,
+        after: &AgentObservation,
+    ) -> Result<Option<Failure>, MlxError>;
+}
+
+/// Critic comparing observations with rules, without a model
+///
+/// Failures are classified from the action's error message and from how the
+/// page changed: a bot wall after the action means [`Blocked`](FailureKind::Blocked),
+/// a click or typing that left the page untouched means
+/// [`WrongElement`](FailureKind::WrongElement), and a navigation or wait that
+/// left it untouched means [`Timing`](FailureKind::Timing).
+#[derive(Debug, Clone, Default)]
+pub struct HeuristicCritic;
+
+impl HeuristicCritic {
+    /// Create a heuristic critic
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Get the criteria an action is expected to meet
+    ///
+    /// Uses the action's `expect` parameter if it has one.
+    pub fn expectations(action: &AgentAction) -> Vec<Criterion> {
+        if let Some(expect) = action.parameters.get("expect") {
+            if let Ok(criteria) = serde_json::from_value::<Vec<Criterion>>(expect.clone()) {
+                return criteria;
+            }
+        }
+
+        match &action.action_type {
+            ActionType::Navigate => action.parameters["url"]
+                .as_str()
+                .and_then(|url| url::Url::parse(url).ok())
+                .and_then(|url| url.host_str().map(|host| vec![Criterion::UrlContains(host.to_string())]))
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Classify a failed action from its error message
+    ///
+    /// The agent's "Failed to click on selector '…': " prefix is skipped, so
+    /// only the underlying page error is classified.
+    pub fn classify_error(error: &str) -> FailureKind {
+        let error = underlying_error(error).to_lowercase();
+
+        if error.contains("403") || error.contains("429") || error.contains("blocked") {
+            FailureKind::Blocked
+        } else if error.contains("timeout") || error.contains("timed out") {
+            FailureKind::Timing
+        } else if ["detached", "not visible", "intercept", "obscured", "covered", "outside of the viewport"]
+            .iter()
+            .any(|marker| error.contains(marker))
+        {
+            FailureKind::LayoutChange
+        } else if ["not found", "no element", "no node", "selector", "not an element"]
+            .iter()
+            .any(|marker| error.contains(marker))
+        {
+            FailureKind::WrongElement
+        } else {
+            FailureKind::Unknown
+        }
+    }
+
+    /// Check whether a page shows a bot wall or an access error
+    pub fn is_blocked(observation: &AgentObservation) -> bool {
+        let title = observation.title.to_lowercase();
+        let content = observation.content.as_deref().unwrap_or_default().to_lowercase();
+
+        BLOCKED_MARKERS
+            .iter()
+            .any(|marker| title.contains(marker) || content.contains(marker))
+    }
+
+    /// Check whether the page changed between two observations
+    fn page_changed(before: &AgentObservation, after: &AgentObservation) -> bool {
+        before.url != after.url || before.title != after.title || before.content != after.content
+    }
+
+    /// Evaluate an action without a model
+    pub fn check(action: &AgentAction, before: &AgentObservation, after: &AgentObservation) -> Option<Failure> {
+        if Self::is_blocked(after) && !Self::is_blocked(before) {
+            return Some(Failure::new(FailureKind::Blocked, format!("{} shows a bot wall or access error", after.url)));
+        }
+
+        if action.success == Some(false) {
+            let error = action.error.as_deref().unwrap_or("Action failed");
+            return Some(Failure::new(Self::classify_error(error), error));
+        }
+
+        let unmet: Vec<Criterion> = Self::expectations(action)
+            .into_iter()
+            .filter(|criterion| !criterion.is_met(after, Some(action)))
+            .collect();
+        let changed = Self::page_changed(before, after);
+
+        match (&action.action_type, unmet.is_empty(), changed) {
+            (_, false, _) if Self::is_blocked(after) => Some(Failure::new(
+                FailureKind::Blocked,
+                format!("{} shows a bot wall or access error", after.url),
+            )),
+            (ActionType::Navigate | ActionType::Wait, false, false) => Some(Failure::new(
+                FailureKind::Timing,
+                format!("The page did not change yet, expected {:?}", unmet),
+            )),
+            (_, false, false) => Some(Failure::new(
+                FailureKind::WrongElement,
+                format!("The page did not change, expected {:?}", unmet),
+            )),
+            (_, false, true) => Some(Failure::new(
+                FailureKind::LayoutChange,
+                format!("The page changed, but not as expected: {:?}", unmet),
+            )),
+            // A click is meant to do something
+            (ActionType::Click, true, false) => Some(Failure::new(
+                FailureKind::WrongElement,
+                "The click had no visible effect",
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[async_trait]
+impl ActionCritic for HeuristicCritic {
+    async fn evaluate(
+        &self,
+        action: &AgentAction,
+        before: &AgentObservation,
+        after: &AgentObservation,
+    ) -> Result<Option<Failure>, MlxError> {
+        Ok(Self::check(action, before, after))
+    }
+}
+
+/// Critic asking a text model whether an action worked
+///
+/// The heuristic checks run first, so obvious failures do not cost a
+/// generation. The model is consulted for actions the heuristics accept, and
+/// its answer is ignored when it cannot be parsed.
+#[cfg(feature = "text")]
+pub struct TextModelCritic {
+    /// Model judging actions
+    model: std::sync::Arc<TextModel>,
+}
+
+#[cfg(feature = "text")]
+impl TextModelCritic {
+    /// Create a critic using a text model
+    pub fn new(model: std::sync::Arc<TextModel>) -> Self {
+        Self { model }
+    }
+}
+
+#[cfg(feature = "text")]
+#[async_trait]
+impl ActionCritic for TextModelCritic {
+    async fn evaluate(
+        &self,
+        action: &AgentAction,
+        before: &AgentObservation,
+        after: &AgentObservation,
+    ) -> Result<Option<Failure>, MlxError> {
+        if let Some(failure) = HeuristicCritic::check(action, before, after) {
+            return Ok(Some(failure));
+        }
+
+        let prompt = format!(
+            "{}\nAction: {:?} {}\nReason: {}\nBefore: {} ({})\nAfter: {} ({})",
+            VERDICT_FORMAT,
+            action.action_type,
+            action.parameters,
+            action.reason.as_deref().unwrap_or("none given"),
+            before.title,
+            before.url,
+            after.title,
+            after.url,
+        );
+        let params = TextGenerationParams {
+            max_tokens: 128,
+            temperature: 0.0,
+            ..Default::default()
+        };
+        let generation = self.model.generate(&prompt, params).await?;
+
+        match parse_verdict(&generation.text) {
+            Some(verdict) => Ok(verdict),
+            None => {
+                warn!("Model did not return a valid verdict, accepting the action");
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Strip the agent's "Failed to … '<target>': " prefix from an error
+fn underlying_error(error: &str) -> &str {
+    if error.starts_with("Failed to ") {
+        if let Some(index) = error.find("': ") {
+            return &error[index + 3..];
+        }
+    }
+    error
+}
+
+/// Output format requested from text models
+#[cfg(feature = "text")]
+const VERDICT_FORMAT: &str = r#"Did the browser action below have the effect it was taken for?
+Reply with JSON only, e.g. {"ok": false, "kind": "wrong_element", "detail": "clicked the logo instead of the login button"}
+Kinds: wrong_element, timing, blocked, layout_change, unknown."#;
+
+/// Verdict returned by a text model
+#[derive(Debug, Deserialize)]
+struct Verdict {
+    ok: bool,
+    #[serde(default)]
+    kind: Option<FailureKind>,
+    #[serde(default)]
+    detail: Option<String>,
+}
+
+/// Parse the verdict in a model completion
+#[cfg_attr(not(feature = "text"), allow(dead_code))]
+fn parse_verdict(text: &str) -> Option<Option<Failure>> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    let verdict: Verdict = serde_json::from_str(text.get(start..=end)?).ok()?;
+
+    if verdict.ok {
+        return Some(None);
+    }
+    Some(Some(Failure::new(
+        verdict.kind.unwrap_or(FailureKind::Unknown),
+        verdict.detail.unwrap_or_else(|| "The action did not have the expected effect".to_string()),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observation(url: &str, content: &str) -> AgentObservation {
+        AgentObservation {
+            title: "Shop".to_string(),
+            url: url.to_string(),
+            content: Some(content.to_string()),
+            screenshot: None,
+            elements: None,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    fn action(action_type: ActionType, parameters: serde_json::Value, error: Option<&str>) -> AgentAction {
+        AgentAction {
+            action_type,
+            parameters,
+            reason: None,
+            success: Some(error.is_none()),
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_classify_error() {
+        assert_eq!(HeuristicCritic::classify_error("Timed out waiting for selector"), FailureKind::Timing);
+        assert_eq!(HeuristicCritic::classify_error("Element is detached from the document"), FailureKind::LayoutChange);
+        assert_eq!(HeuristicCritic::classify_error("No element found for '#buy'"), FailureKind::WrongElement);
+        assert_eq!(HeuristicCritic::classify_error("something odd"), FailureKind::Unknown);
+
+        // The selector in the agent's wrapper does not make every failure a wrong element
+        assert_eq!(
+            HeuristicCritic::classify_error("Failed to click on selector '#buy': navigation answered 403 Forbidden"),
+            FailureKind::Blocked
+        );
+        assert_eq!(
+            HeuristicCritic::classify_error("Failed to type text into selector '#q': Timed out after 30000ms"),
+            FailureKind::Timing
+        );
+        assert_eq!(
+            HeuristicCritic::classify_error("Failed to click on selector '#buy': No node found"),
+            FailureKind::WrongElement
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let before = observation("https://shop.example.com/", "<button>Next</button>");
+
+        // A click with no effect hit the wrong element
+        let click = action(ActionType::Click, serde_json::json!({ "selector": "#logo" }), None);
+        assert_eq!(HeuristicCritic::check(&click, &before, &before).unwrap().kind, FailureKind::WrongElement);
+
+        // Expectations met
+        let next = action(ActionType::Click, serde_json::json!({
+            "selector": "#next",
+            "expect": [{ "type": "url_contains", "value": "page=2" }],
+        }), None);
+        let after = observation("https://shop.example.com/?page=2", "<button>Next</button>");
+        assert!(HeuristicCritic::check(&next, &before, &after).is_none());
+
+        // The page changed, but elsewhere
+        let elsewhere = observation("https://shop.example.com/cart", "");
+        assert_eq!(HeuristicCritic::check(&next, &before, &elsewhere).unwrap().kind, FailureKind::LayoutChange);
+
+        // A bot wall trumps everything
+        let wall = observation("https://shop.example.com/?page=2", "Please complete the CAPTCHA");
+        assert_eq!(HeuristicCritic::check(&next, &before, &wall).unwrap().kind, FailureKind::Blocked);
+
+        // Errors are classified from their message
+        let failed = action(ActionType::Click, serde_json::json!({ "selector": "#next" }), Some("Timeout after 30000ms"));
+        assert_eq!(HeuristicCritic::check(&failed, &before, &before).unwrap().kind, FailureKind::Timing);
+
+        // Navigation expects to end up on the target host
+        let navigate = action(ActionType::Navigate, serde_json::json!({ "url": "https://other.example.org/" }), None);
+        assert_eq!(HeuristicCritic::check(&navigate, &before, &before).unwrap().kind, FailureKind::Timing);
+    }
+
+    #[test]
+    fn test_parse_verdict() {
+        assert_eq!(parse_verdict("Sure: {\"ok\": true}"), Some(None));
+
+        let failure = parse_verdict("{\"ok\": false, \"kind\": \"layout_change\", \"detail\": \"menu moved\"}").unwrap().unwrap();
+        assert_eq!(failure, Failure::new(FailureKind::LayoutChange, "menu moved"));
+
+        assert!(parse_verdict("no idea").is_none());
+    }
+}
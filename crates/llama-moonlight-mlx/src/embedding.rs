@@ -0,0 +1,208 @@
+use crate::{MlxError, ModelTrait, config::{BaseModelConfig, ModelConfig}};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Configuration for text embedding models
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingModelConfig {
+    /// Base model configuration
+    #[serde(flatten)]
+    pub base: BaseModelConfig,
+    
+    /// Number of dimensions of the embeddings
+    pub dimensions: usize,
+    
+    /// Maximum number of tokens embedded per text; longer texts are truncated
+    pub max_tokens: usize,
+    
+    /// Whether to scale embeddings to unit length
+    pub normalize: bool,
+}
+
+impl Default for EmbeddingModelConfig {
+    fn default() -> Self {
+        Self {
+            base: BaseModelConfig::default(),
+            dimensions: 384,
+            max_tokens: 512,
+            normalize: true,
+        }
+    }
+}
+
+impl ModelConfig for EmbeddingModelConfig {
+    fn model_type(&self) -> &str {
+        "embedding"
+    }
+    
+    fn validate(&self) -> Result<(), MlxError> {
+        self.base.validate()?;
+        
+        if self.dimensions == 0 {
+            return Err(MlxError::ModelConfiguration("Embedding dimensions must be positive".to_string()));
+        }
+        
+        Ok(())
+    }
+}
+
+/// Text embedding model for semantic search
+///
+/// Until MLX inference is wired in, embeddings are computed with signed
+/// feature hashing of word unigrams and bigrams. They capture lexical rather
+/// than semantic similarity, but have the same shape and properties as model
+/// embeddings. Vectors are only comparable between models of the same
+/// [`name`](Self::name), so indexes should record it and check it on load.
+#[derive(Debug, Clone)]
+pub struct EmbeddingModel {
+    /// Model configuration
+    pub config: EmbeddingModelConfig,
+    /// Model path, if loaded from disk
+    pub model_path: Option<PathBuf>,
+    /// Model name
+    pub name: String,
+}
+
+impl EmbeddingModel {
+    /// Load an embedding model from the specified path
+    pub async fn load_from_path(path: &Path, config: EmbeddingModelConfig) -> Result<Self, MlxError> {
+        config.validate()?;
+        
+        if !path.exists() {
+            return Err(MlxError::ModelLoading(format!(
+                "Model path does not exist: {}",
+                path.display()
+            )));
+        }
+        
+        Ok(Self {
+            name: config.base.name.clone(),
+            config,
+            model_path: Some(path.to_path_buf()),
+        })
+    }
+    
+    /// Create an embedding model that needs no weights
+    pub fn hashing(dimensions: usize) -> Self {
+        let mut config = EmbeddingModelConfig {
+            dimensions: dimensions.max(1),
+            ..EmbeddingModelConfig::default()
+        };
+        config.base.name = format!("hashing-{}", config.dimensions);
+        
+        Self {
+            name: config.base.name.clone(),
+            config,
+            model_path: None,
+        }
+    }
+    
+    /// Get the number of dimensions of the embeddings
+    pub fn dimensions(&self) -> usize {
+        self.config.dimensions
+    }
+    
+    /// Embed a text
+    pub async fn embed(&self, text: &str) -> Result<Vec<f32>, MlxError> {
+        let tokens: Vec<String> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|token| !token.is_empty())
+            .take(self.config.max_tokens)
+            .map(|token| token.to_lowercase())
+            .collect();
+        
+        let mut counts = vec![0f32; self.config.dimensions];
+        for (i, token) in tokens.iter().enumerate() {
+            self.add_feature(&mut counts, token, 1.0);
+            if let Some(next) = tokens.get(i + 1) {
+                self.add_feature(&mut counts, &format!("{} {}", token, next), 0.5);
+            }
+        }
+        
+        // Dampen frequent terms
+        let mut embedding: Vec<f32> = counts
+            .into_iter()
+            .map(|count| count.signum() * count.abs().ln_1p())
+            .collect();
+        
+        if self.config.normalize {
+            let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+            if norm > 0.0 {
+                embedding.iter_mut().for_each(|x| *x /= norm);
+            }
+        }
+        
+        Ok(embedding)
+    }
+    
+    /// Embed several texts
+    pub async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, MlxError> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for text in texts {
+            embeddings.push(self.embed(text).await?);
+        }
+        Ok(embeddings)
+    }
+    
+    /// Add a hashed feature to the counts
+    fn add_feature(&self, counts: &mut [f32], feature: &str, weight: f32) {
+        let hash = fnv1a(feature.as_bytes());
+        let index = (hash % counts.len() as u64) as usize;
+        let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        counts[index] += sign * weight;
+    }
+}
+
+impl ModelTrait for EmbeddingModel {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn model_type(&self) -> &str {
+        "embedding"
+    }
+}
+
+/// Cosine similarity of two embeddings
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    
+    if norm == 0.0 {
+        0.0
+    } else {
+        dot / norm
+    }
+}
+
+/// 64-bit FNV-1a hash, stable across platforms and releases
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_hashing_embeddings() {
+        let model = EmbeddingModel::hashing(256);
+        let query = model.embed("pruning attention heads").await.unwrap();
+        let related = model.embed("We prune attention heads in transformers").await.unwrap();
+        let unrelated = model.embed("Graph coloring with greedy heuristics").await.unwrap();
+        
+        assert_eq!(query.len(), 256);
+        assert!((query.iter().map(|x| x * x).sum::<f32>() - 1.0).abs() < 1e-5);
+        assert!(cosine_similarity(&query, &related) > cosine_similarity(&query, &unrelated));
+    }
+}
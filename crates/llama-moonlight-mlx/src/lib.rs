@@ -22,8 +22,13 @@ use thiserror::Error;
 pub mod text;
 pub mod vision;
 pub mod agent;
+pub mod planner;
+pub mod critique;
 pub mod config;
 pub mod utils;
+pub mod embedding;
+pub mod skills;
+pub mod triage;
 
 #[cfg(feature = "text")]
 pub use text::{TextModel, TextModelConfig, TextGeneration, ChatMessage};
@@ -32,7 +37,12 @@ pub use text::{TextModel, TextModelConfig, TextGeneration, ChatMessage};
 pub use vision::{VisionModel, VisionModelConfig, ImageClassification, ObjectDetection};
 
 pub use agent::{Agent, AgentConfig, AgentAction, AgentObservation};
+pub use critique::{ActionCritic, Failure, FailureKind, HeuristicCritic};
+pub use planner::{Criterion, Plan, PlannerConfig, PlanningAgent, RuleBasedPlanner, Subgoal, TaskPlanner, Trajectory};
 pub use config::ModelConfig;
+pub use embedding::{EmbeddingModel, EmbeddingModelConfig, cosine_similarity};
+pub use skills::{fill_form, FormField, FormFillReport, FormFiller};
+pub use triage::{Classification, LabelScore, PageSummary, ReadableText, TriageConfig};
 
 /// MLX-related errors
 #[derive(Error, Debug)]
@@ -55,6 +65,9 @@ pub enum MlxError {
     #[error("Agent error: {0}")]
     Agent(String),
     
+    #[error("Skill error: {0}")]
+    Skill(String),
+    
     #[error("Tokenization error: {0}")]
     Tokenization(String),
     
@@ -80,6 +93,9 @@ pub struct Mlx {
     models_dir: PathBuf,
     /// Models cache
     models: std::collections::HashMap<String, Arc<dyn ModelTrait>>,
+    /// Most recently loaded text model, used to triage pages
+    #[cfg(feature = "text")]
+    text_model: Option<Arc<TextModel>>,
 }
 
 impl Mlx {
@@ -88,6 +104,8 @@ impl Mlx {
         Self {
             models_dir: models_dir.as_ref().to_path_buf(),
             models: std::collections::HashMap::new(),
+            #[cfg(feature = "text")]
+            text_model: None,
         }
     }
     
@@ -102,7 +120,9 @@ impl Mlx {
         if let Some(model) = self.models.get(model_name) {
             // Check if it's a TextModel
             if let Some(text_model) = model.as_any().downcast_ref::<TextModel>() {
-                return Ok(Arc::new(text_model.clone()));
+                let text_model = Arc::new(text_model.clone());
+                self.text_model = Some(text_model.clone());
+                return Ok(text_model);
             } else {
                 return Err(MlxError::ModelLoading(format!(
                     "Model '{}' exists but is not a text model",
@@ -115,6 +135,7 @@ impl Mlx {
         let model = TextModel::load_from_path(&model_path, config).await?;
         let model_arc = Arc::new(model);
         self.models.insert(model_name.to_string(), model_arc.clone() as Arc<dyn ModelTrait>);
+        self.text_model = Some(model_arc.clone());
         
         Ok(model_arc)
     }
@@ -142,9 +163,59 @@ impl Mlx {
         Ok(model_arc)
     }
     
+    /// Load an embedding model for semantic search
+    pub async fn load_embedding_model(&mut self, model_name: &str, config: EmbeddingModelConfig) -> Result<Arc<EmbeddingModel>, MlxError> {
+        if let Some(model) = self.models.get(model_name) {
+            // Check if it's an EmbeddingModel
+            if let Some(embedding_model) = model.as_any().downcast_ref::<EmbeddingModel>() {
+                return Ok(Arc::new(embedding_model.clone()));
+            } else {
+                return Err(MlxError::ModelLoading(format!(
+                    "Model '{}' exists but is not an embedding model",
+                    model_name
+                )));
+            }
+        }
+        
+        let model_path = self.models_dir.join(model_name);
+        let model = EmbeddingModel::load_from_path(&model_path, config).await?;
+        let model_arc = Arc::new(model);
+        self.models.insert(model_name.to_string(), model_arc.clone() as Arc<dyn ModelTrait>);
+        
+        Ok(model_arc)
+    }
+    
+    /// Summarize the readable text of a page with the most recently loaded text model
+    ///
+    /// Long pages are summarized in chunks fitting the model context window,
+    /// then the partial summaries are combined.
+    #[cfg(feature = "text")]
+    pub async fn summarize(&self, page: &Page) -> Result<PageSummary, MlxError> {
+        triage::summarize_page(self.triage_model()?, page, &TriageConfig::default()).await
+    }
+    
+    /// Score the readable text of a page against labels with the most recently loaded text model
+    #[cfg(feature = "text")]
+    pub async fn classify(&self, page: &Page, labels: &[&str]) -> Result<Classification, MlxError> {
+        triage::classify_page(self.triage_model()?, page, labels, &TriageConfig::default()).await
+    }
+    
+    /// Get the text model used to triage pages
+    #[cfg(feature = "text")]
+    fn triage_model(&self) -> Result<&TextModel, MlxError> {
+        self.text_model
+            .as_deref()
+            .ok_or_else(|| MlxError::ModelLoading("No text model loaded to triage pages".to_string()))
+    }
+    
     /// Create an agent for autonomous browser automation
+    ///
+    /// The agent plans its actions with the most recently loaded text model.
     pub async fn create_agent(&self, config: AgentConfig, page: Arc<Page>) -> Result<Agent, MlxError> {
-        let agent = Agent::new(config, page);
+        let mut agent = Agent::new(config, page);
+        if let Some(model) = &self.text_model {
+            agent = agent.with_text_model(model.clone());
+        }
         Ok(agent)
     }
 }
@@ -171,4 +242,4 @@ mod tests {
         assert_eq!(mlx.models_dir(), Path::new("/tmp/models"));
         assert_eq!(mlx.models.len(), 0);
     }
-} 
\ No newline at end of file
+}
\ No newline at end of file
@@ -0,0 +1,706 @@
+//! Multi-step task planning for agents
+//!
+//! A single observe-act loop tends to get stuck on longer tasks such as
+//! "find and export the last 3 invoices". This module adds a planning layer on
+//! top of [`Agent`]: a [`TaskPlanner`] breaks the task into subgoals, each with
+//! its own completion criteria, the [`PlanningAgent`] pursues them one at a
+//! time and asks the planner for a new plan when a subgoal fails. Every plan
+//! revision is kept in the [`Trajectory`] along with the actions taken.
+
+use crate::agent::{Agent, AgentAction, AgentObservation};
+use crate::MlxError;
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Condition that marks a subgoal as completed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum Criterion {
+    /// The page URL contains the given text
+    UrlContains(String),
+    
+    /// The page title contains the given text (case-insensitive)
+    TitleContains(String),
+    
+    /// The page content contains the given text (case-insensitive)
+    ContentContains(String),
+    
+    /// The last action succeeded
+    ActionSucceeded,
+}
+
+impl Criterion {
+    /// Check whether the criterion is met after an action
+    pub fn is_met(&self, observation: &AgentObservation, last_action: Option<&AgentAction>) -> bool {
+        match self {
+            Criterion::UrlContains(text) => observation.url.contains(text.as_str()),
+            Criterion::TitleContains(text) => observation.title.to_lowercase().contains(&text.to_lowercase()),
+            Criterion::ContentContains(text) => observation
+                .content
+                .as_ref()
+                .map(|content| content.to_lowercase().contains(&text.to_lowercase()))
+                .unwrap_or(false),
+            Criterion::ActionSucceeded => last_action.and_then(|action| action.success).unwrap_or(false),
+        }
+    }
+}
+
+/// Status of a subgoal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubgoalStatus {
+    /// Not started yet
+    Pending,
+    /// Currently pursued
+    Active,
+    /// All completion criteria were met
+    Completed,
+    /// Abandoned after a failure
+    Failed,
+}
+
+/// A step of a plan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Subgoal {
+    /// What the agent should achieve
+    pub description: String,
+    
+    /// Conditions that must all hold for the subgoal to be completed
+    #[serde(default)]
+    pub criteria: Vec<Criterion>,
+    
+    /// Current status
+    #[serde(default = "default_status")]
+    pub status: SubgoalStatus,
+    
+    /// Number of times the subgoal was pursued
+    #[serde(default)]
+    pub attempts: usize,
+    
+    /// Reason of the last failure
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+fn default_status() -> SubgoalStatus {
+    SubgoalStatus::Pending
+}
+
+impl Subgoal {
+    /// Create a pending subgoal without criteria
+    pub fn new(description: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+            criteria: Vec::new(),
+            status: SubgoalStatus::Pending,
+            attempts: 0,
+            error: None,
+        }
+    }
+    
+    /// Add a completion criterion
+    pub fn with_criterion(mut self, criterion: Criterion) -> Self {
+        self.criteria.push(criterion);
+        self
+    }
+    
+    /// Check whether the subgoal is completed after an action
+    ///
+    /// A subgoal without criteria is completed by any successful action.
+    pub fn is_met(&self, observation: &AgentObservation, last_action: Option<&AgentAction>) -> bool {
+        if self.criteria.is_empty() {
+            return Criterion::ActionSucceeded.is_met(observation, last_action);
+        }
+        
+        self.criteria.iter().all(|criterion| criterion.is_met(observation, last_action))
+    }
+}
+
+/// Ordered subgoals for a task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    /// Revision number, starting at 0 and incremented on each replan
+    pub revision: usize,
+    
+    /// Subgoals in execution order
+    pub subgoals: Vec<Subgoal>,
+}
+
+impl Plan {
+    /// Create the first revision of a plan
+    pub fn new(subgoals: Vec<Subgoal>) -> Self {
+        Self { revision: 0, subgoals }
+    }
+    
+    /// Get the index of the next subgoal to pursue
+    pub fn next_index(&self) -> Option<usize> {
+        self.subgoals.iter().position(|subgoal| subgoal.status != SubgoalStatus::Completed)
+    }
+    
+    /// Get the next subgoal to pursue
+    pub fn next_subgoal(&self) -> Option<&Subgoal> {
+        self.next_index().map(|index| &self.subgoals[index])
+    }
+    
+    /// Check whether all subgoals are completed
+    pub fn is_complete(&self) -> bool {
+        self.next_index().is_none()
+    }
+    
+    /// Build the next revision, replacing the subgoals from `index` onwards
+    pub fn revise(&self, index: usize, subgoals: Vec<Subgoal>) -> Plan {
+        let mut revised = self.subgoals[..index.min(self.subgoals.len())].to_vec();
+        revised.extend(subgoals);
+        
+        Plan {
+            revision: self.revision + 1,
+            subgoals: revised,
+        }
+    }
+}
+
+/// Action taken while pursuing a subgoal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrajectoryStep {
+    /// Revision of the plan the action belongs to
+    pub plan_revision: usize,
+    
+    /// Index of the subgoal in that revision
+    pub subgoal: usize,
+    
+    /// Action taken, with its outcome
+    pub action: AgentAction,
+    
+    /// Page state after the action
+    pub observation: AgentObservation,
+}
+
+/// Record of a planned task: every plan revision and every action
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trajectory {
+    /// Task in natural language
+    pub task: String,
+    
+    /// Plan revisions, oldest first
+    pub plans: Vec<Plan>,
+    
+    /// Actions taken, oldest first
+    pub steps: Vec<TrajectoryStep>,
+}
+
+impl Trajectory {
+    /// Create a trajectory with an initial plan
+    pub fn new(task: impl Into<String>, plan: Plan) -> Self {
+        Self {
+            task: task.into(),
+            plans: vec![plan],
+            steps: Vec::new(),
+        }
+    }
+    
+    /// Get the latest plan
+    pub fn plan(&self) -> &Plan {
+        self.plans.last().expect("trajectory has at least one plan")
+    }
+    
+    /// Get the latest plan mutably
+    pub fn plan_mut(&mut self) -> &mut Plan {
+        self.plans.last_mut().expect("trajectory has at least one plan")
+    }
+    
+    /// Check whether the task was completed
+    pub fn is_complete(&self) -> bool {
+        self.plan().is_complete()
+    }
+    
+    /// Record an action taken for a subgoal of the latest plan
+    pub fn record(&mut self, subgoal: usize, action: AgentAction, observation: AgentObservation) {
+        self.steps.push(TrajectoryStep {
+            plan_revision: self.plan().revision,
+            subgoal,
+            action,
+            observation,
+        });
+    }
+    
+    /// Start a new plan revision, replacing the subgoals from `index` onwards
+    pub fn replan(&mut self, index: usize, subgoals: Vec<Subgoal>) {
+        let revised = self.plan().revise(index, subgoals);
+        self.plans.push(revised);
+    }
+    
+    /// Save the trajectory as JSON
+    pub fn save(&self, path: &Path) -> Result<(), MlxError> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+    
+    /// Load a trajectory saved as JSON
+    pub fn load(path: &Path) -> Result<Self, MlxError> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// Breaks tasks into subgoals and revises plans after failures
+#[async_trait]
+pub trait TaskPlanner: Send + Sync {
+    /// Break a task into subgoals
+    async fn decompose(&self, task: &str, observation: Option<&AgentObservation>) -> Result<Vec<Subgoal>, MlxError>;
+    
+    /// Get the subgoals replacing a failed subgoal and the ones after it
+    async fn replan(&self, task: &str, plan: &Plan, failed: usize, observation: Option<&AgentObservation>) -> Result<Vec<Subgoal>, MlxError>;
+}
+
+/// Planner splitting a task at conjunctions, without a model
+///
+/// "find and export the last 3 invoices" becomes "find the last 3 invoices"
+/// and "export the last 3 invoices". Quoted text becomes a
+/// [`Criterion::ContentContains`] and URLs a [`Criterion::UrlContains`]. On
+/// failure the failed subgoal is retried as is.
+#[derive(Debug, Clone, Default)]
+pub struct RuleBasedPlanner;
+
+impl RuleBasedPlanner {
+    /// Create a rule-based planner
+    pub fn new() -> Self {
+        Self
+    }
+    
+    /// Split a task into subgoal descriptions
+    pub fn split(task: &str) -> Vec<String> {
+        let mut parts = vec![task.trim().to_string()];
+        for separator in [";", ", and then ", " and then ", ", then ", " then ", ", and ", " and ", ", "] {
+            parts = parts
+                .iter()
+                .flat_map(|part| part.split(separator).map(|piece| piece.trim().to_string()).collect::<Vec<_>>())
+                .filter(|part| !part.is_empty())
+                .collect();
+        }
+        
+        // A bare verb shares the object of the next part: "find and export X"
+        let mut descriptions = vec![String::new(); parts.len()];
+        let mut object = String::new();
+        for (i, part) in parts.iter().enumerate().rev() {
+            descriptions[i] = match part.split_once(' ') {
+                Some((_, rest)) => {
+                    object = rest.to_string();
+                    part.clone()
+                },
+                None if !object.is_empty() => format!("{} {}", part, object),
+                None => part.clone(),
+            };
+        }
+        
+        descriptions
+    }
+    
+    /// Derive completion criteria from a subgoal description
+    pub fn criteria(description: &str) -> Vec<Criterion> {
+        let mut criteria = Vec::new();
+        
+        for word in description.split_whitespace() {
+            if let Ok(url) = url::Url::parse(word.trim_end_matches(|c: char| ",.;".contains(c))) {
+                if let Some(host) = url.host_str() {
+                    criteria.push(Criterion::UrlContains(host.to_string()));
+                }
+            }
+        }
+        
+        for (i, quoted) in description.split('"').enumerate() {
+            if i % 2 == 1 && !quoted.is_empty() {
+                criteria.push(Criterion::ContentContains(quoted.to_string()));
+            }
+        }
+        
+        criteria
+    }
+}
+
+#[async_trait]
+impl TaskPlanner for RuleBasedPlanner {
+    async fn decompose(&self, task: &str, _observation: Option<&AgentObservation>) -> Result<Vec<Subgoal>, MlxError> {
+        Ok(Self::split(task)
+            .into_iter()
+            .map(|description| {
+                let criteria = Self::criteria(&description);
+                Subgoal {
+                    criteria,
+                    ..Subgoal::new(description)
+                }
+            })
+            .collect())
+    }
+    
+    async fn replan(&self, _task: &str, plan: &Plan, failed: usize, _observation: Option<&AgentObservation>) -> Result<Vec<Subgoal>, MlxError> {
+        Ok(plan.subgoals[failed..]
+            .iter()
+            .map(|subgoal| Subgoal {
+                status: SubgoalStatus::Pending,
+                error: None,
+                ..subgoal.clone()
+            })
+            .collect())
+    }
+}
+
+/// Planner asking a text model for subgoals as JSON
+///
+/// Falls back to a [`RuleBasedPlanner`] when the model output cannot be parsed.
+#[cfg(feature = "text")]
+pub struct TextModelPlanner {
+    /// Model generating plans
+    model: std::sync::Arc<crate::text::TextModel>,
+    
+    /// Planner used when the model output is not a valid plan
+    fallback: RuleBasedPlanner,
+}
+
+#[cfg(feature = "text")]
+impl TextModelPlanner {
+    /// Create a planner using a text model
+    pub fn new(model: std::sync::Arc<crate::text::TextModel>) -> Self {
+        Self {
+            model,
+            fallback: RuleBasedPlanner::new(),
+        }
+    }
+    
+    async fn generate(&self, prompt: &str) -> Result<Option<Vec<Subgoal>>, MlxError> {
+        let params = crate::text::TextGenerationParams {
+            max_tokens: 512,
+            temperature: 0.2,
+            ..Default::default()
+        };
+        let generation = self.model.generate(prompt, params).await?;
+        
+        Ok(parse_subgoals(&generation.text))
+    }
+}
+
+#[cfg(feature = "text")]
+#[async_trait]
+impl TaskPlanner for TextModelPlanner {
+    async fn decompose(&self, task: &str, observation: Option<&AgentObservation>) -> Result<Vec<Subgoal>, MlxError> {
+        let prompt = format!(
+            "{}\nBreak the browser task below into short subgoals.\n\nTask: {}\n{}",
+            PLAN_FORMAT,
+            task,
+            describe_page(observation)
+        );
+        
+        match self.generate(&prompt).await? {
+            Some(subgoals) => Ok(subgoals),
+            None => {
+                warn!("Model did not return a valid plan, splitting the task instead");
+                self.fallback.decompose(task, observation).await
+            }
+        }
+    }
+    
+    async fn replan(&self, task: &str, plan: &Plan, failed: usize, observation: Option<&AgentObservation>) -> Result<Vec<Subgoal>, MlxError> {
+        let subgoal = &plan.subgoals[failed];
+        let done: Vec<&str> = plan.subgoals[..failed].iter().map(|s| s.description.as_str()).collect();
+        let prompt = format!(
+            "{}\nThe browser task below is partially done. Plan the remaining subgoals.\n\nTask: {}\nCompleted: {}\nFailed: {} ({})\n{}",
+            PLAN_FORMAT,
+            task,
+            done.join("; "),
+            subgoal.description,
+            subgoal.error.as_deref().unwrap_or("unknown error"),
+            describe_page(observation)
+        );
+        
+        match self.generate(&prompt).await? {
+            Some(subgoals) => Ok(subgoals),
+            None => self.fallback.replan(task, plan, failed, observation).await,
+        }
+    }
+}
+
+/// Output format requested from text models
+#[cfg(feature = "text")]
+const PLAN_FORMAT: &str = r#"Reply with a JSON array of subgoals only, e.g.
+[{"description": "open the invoices page", "criteria": [{"type": "url_contains", "value": "/invoices"}]}]
+Criterion types: url_contains, title_contains, content_contains, action_succeeded."#;
+
+#[cfg(feature = "text")]
+fn describe_page(observation: Option<&AgentObservation>) -> String {
+    match observation {
+        Some(observation) => format!("Current page: {} ({})", observation.title, observation.url),
+        None => String::new(),
+    }
+}
+
+/// Parse the subgoals in a model completion
+#[cfg_attr(not(feature = "text"), allow(dead_code))]
+fn parse_subgoals(text: &str) -> Option<Vec<Subgoal>> {
+    let start = text.find('[')?;
+    let end = text.rfind(']')?;
+    let subgoals: Vec<Subgoal> = serde_json::from_str(text.get(start..=end)?).ok()?;
+    
+    if subgoals.is_empty() {
+        None
+    } else {
+        Some(subgoals)
+    }
+}
+
+/// Limits of a planning agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannerConfig {
+    /// Maximum number of actions taken for a subgoal before it fails
+    pub max_actions_per_subgoal: usize,
+    
+    /// Maximum number of plan revisions before the task is abandoned
+    pub max_replans: usize,
+}
+
+impl Default for PlannerConfig {
+    fn default() -> Self {
+        Self {
+            max_actions_per_subgoal: 5,
+            max_replans: 3,
+        }
+    }
+}
+
+/// Agent pursuing a task as a plan of subgoals
+pub struct PlanningAgent {
+    /// Agent taking the actions
+    agent: Agent,
+    
+    /// Planner producing the subgoals
+    planner: Box<dyn TaskPlanner>,
+    
+    /// Planning limits
+    config: PlannerConfig,
+}
+
+impl PlanningAgent {
+    /// Create a planning agent
+    pub fn new(agent: Agent, planner: impl TaskPlanner + 'static) -> Self {
+        Self {
+            agent,
+            planner: Box::new(planner),
+            config: PlannerConfig::default(),
+        }
+    }
+    
+    /// Set the planning limits
+    pub fn with_config(mut self, config: PlannerConfig) -> Self {
+        self.config = config;
+        self
+    }
+    
+    /// Get the underlying agent
+    pub fn agent(&self) -> &Agent {
+        &self.agent
+    }
+    
+    /// Get the underlying agent mutably
+    pub fn agent_mut(&mut self) -> &mut Agent {
+        &mut self.agent
+    }
+    
+    /// Pursue a task until its plan is completed or too many replans fail
+    ///
+    /// The returned trajectory tells whether the task was completed.
+    pub async fn run(&mut self, task: &str) -> Result<Trajectory, MlxError> {
+        let observation = self.agent.observe().await?;
+        let subgoals = self.planner.decompose(task, Some(&observation)).await?;
+        if subgoals.is_empty() {
+            return Err(MlxError::Agent(format!("Planner returned no subgoals for task '{}'", task)));
+        }
+        
+        info!("Planned {} subgoals for task '{}'", subgoals.len(), task);
+        let mut trajectory = Trajectory::new(task, Plan::new(subgoals));
+        let mut replans = 0;
+        
+        while let Some(index) = trajectory.plan().next_index() {
+            let subgoal = &mut trajectory.plan_mut().subgoals[index];
+            subgoal.status = SubgoalStatus::Active;
+            subgoal.attempts += 1;
+            debug!("Pursuing subgoal {}: {}", index, subgoal.description);
+            self.agent.set_goal(Some(subgoal.description.clone()));
+            
+            let failure = self.pursue(index, &mut trajectory).await?;
+            let subgoal = &mut trajectory.plan_mut().subgoals[index];
+            
+            match failure {
+                None => subgoal.status = SubgoalStatus::Completed,
+                Some(error) => {
+                    warn!("Subgoal '{}' failed: {}", subgoal.description, error);
+                    subgoal.status = SubgoalStatus::Failed;
+                    subgoal.error = Some(error);
+                    
+                    if replans >= self.config.max_replans {
+                        warn!("Giving up on task '{}' after {} replans", task, replans);
+                        break;
+                    }
+                    replans += 1;
+                    
+                    let observation = self.agent.current_observation();
+                    if !replan_after_failure(&*self.planner, &mut trajectory, index, observation).await? {
+                        warn!("Planner gave up on task '{}'", task);
+                        break;
+                    }
+                },
+            }
+        }
+        
+        self.agent.set_goal(None);
+        Ok(trajectory)
+    }
+    
+    /// Take actions for a subgoal until its criteria are met
+    ///
+    /// Returns the failure reason if the subgoal could not be completed.
+    async fn pursue(&mut self, index: usize, trajectory: &mut Trajectory) -> Result<Option<String>, MlxError> {
+        for _ in 0..self.config.max_actions_per_subgoal {
+            let action = self.agent.plan_action().await?;
+            let result = self.agent.execute_action(action).await?;
+            let observation = self.agent.observe().await?;
+            trajectory.record(index, result.clone(), observation.clone());
+            
+            if result.success == Some(false) {
+                return Ok(Some(result.error.unwrap_or_else(|| "Action failed".to_string())));
+            }
+            
+            if trajectory.plan().subgoals[index].is_met(&observation, Some(&result)) {
+                return Ok(None);
+            }
+        }
+        
+        Ok(Some(format!(
+            "Criteria not met after {} actions",
+            self.config.max_actions_per_subgoal
+        )))
+    }
+}
+
+/// Revise the plan of a trajectory after the subgoal at `index` failed
+///
+/// A planner returning no subgoals gives up on the task: the plan is left as
+/// is, with the failed subgoal in it, and `false` is returned.
+async fn replan_after_failure(
+    planner: &dyn TaskPlanner,
+    trajectory: &mut Trajectory,
+    index: usize,
+    observation: Option<&AgentObservation>,
+) -> Result<bool, MlxError> {
+    let subgoals = planner.replan(&trajectory.task, trajectory.plan(), index, observation).await?;
+    if subgoals.is_empty() {
+        return Ok(false);
+    }
+    
+    trajectory.replan(index, subgoals);
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::ActionType;
+    
+    fn observation(url: &str, content: &str) -> AgentObservation {
+        AgentObservation {
+            title: "Invoices".to_string(),
+            url: url.to_string(),
+            content: Some(content.to_string()),
+            screenshot: None,
+            elements: None,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_rule_based_decomposition() {
+        let planner = RuleBasedPlanner::new();
+        let subgoals = planner.decompose("find and export the last 3 invoices", None).await.unwrap();
+        let descriptions: Vec<&str> = subgoals.iter().map(|s| s.description.as_str()).collect();
+        assert_eq!(descriptions, vec!["find the last 3 invoices", "export the last 3 invoices"]);
+        
+        let subgoals = planner
+            .decompose("open https://billing.example.com/login, then search for \"INV-42\"", None)
+            .await
+            .unwrap();
+        assert_eq!(subgoals.len(), 2);
+        assert_eq!(subgoals[0].criteria, vec![Criterion::UrlContains("billing.example.com".to_string())]);
+        assert_eq!(subgoals[1].criteria, vec![Criterion::ContentContains("INV-42".to_string())]);
+    }
+    
+    #[test]
+    fn test_criteria() {
+        let page = observation("https://billing.example.com/invoices", "<td>INV-42</td>");
+        let succeeded = AgentAction {
+            action_type: ActionType::Click,
+            parameters: serde_json::json!({}),
+            reason: None,
+            success: Some(true),
+            error: None,
+        };
+        
+        let subgoal = Subgoal::new("open the invoices")
+            .with_criterion(Criterion::UrlContains("/invoices".to_string()))
+            .with_criterion(Criterion::ContentContains("inv-42".to_string()));
+        assert!(subgoal.is_met(&page, None));
+        assert!(!Subgoal::new("click").is_met(&page, None));
+        assert!(Subgoal::new("click").is_met(&page, Some(&succeeded)));
+    }
+    
+    #[test]
+    fn test_replan_keeps_completed_subgoals() {
+        let mut plan = Plan::new(vec![Subgoal::new("a"), Subgoal::new("b"), Subgoal::new("c")]);
+        plan.subgoals[0].status = SubgoalStatus::Completed;
+        plan.subgoals[1].status = SubgoalStatus::Failed;
+        
+        let mut trajectory = Trajectory::new("task", plan);
+        assert_eq!(trajectory.plan().next_index(), Some(1));
+        trajectory.replan(1, vec![Subgoal::new("b2")]);
+        
+        let plan = trajectory.plan();
+        assert_eq!(plan.revision, 1);
+        assert_eq!(plan.subgoals.iter().map(|s| s.description.as_str()).collect::<Vec<_>>(), vec!["a", "b2"]);
+        assert_eq!(trajectory.plans.len(), 2);
+        assert_eq!(trajectory.plans[0].subgoals[1].status, SubgoalStatus::Failed);
+        
+        let parsed = parse_subgoals("Plan:\n[{\"description\": \"open\", \"criteria\": [{\"type\": \"action_succeeded\"}]}]").unwrap();
+        assert_eq!(parsed[0].criteria, vec![Criterion::ActionSucceeded]);
+        assert_eq!(parsed[0].status, SubgoalStatus::Pending);
+    }
+    
+    /// Planner that cannot think of anything after a failure
+    struct GivingUpPlanner;
+    
+    #[async_trait]
+    impl TaskPlanner for GivingUpPlanner {
+        async fn decompose(&self, task: &str, _observation: Option<&AgentObservation>) -> Result<Vec<Subgoal>, MlxError> {
+            Ok(vec![Subgoal::new(task)])
+        }
+        
+        async fn replan(&self, _task: &str, _plan: &Plan, _failed: usize, _observation: Option<&AgentObservation>) -> Result<Vec<Subgoal>, MlxError> {
+            Ok(Vec::new())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_empty_replan_gives_up() {
+        let mut plan = Plan::new(vec![Subgoal::new("a"), Subgoal::new("b")]);
+        plan.subgoals[0].status = SubgoalStatus::Completed;
+        plan.subgoals[1].status = SubgoalStatus::Failed;
+        let mut trajectory = Trajectory::new("task", plan);
+        
+        assert!(!replan_after_failure(&GivingUpPlanner, &mut trajectory, 1, None).await.unwrap());
+        
+        // The failed subgoal stays, so the task is not reported as completed
+        assert_eq!(trajectory.plans.len(), 1);
+        assert_eq!(trajectory.plan().subgoals[1].status, SubgoalStatus::Failed);
+        assert!(!trajectory.is_complete());
+        
+        // Planners with new subgoals revise the plan
+        assert!(replan_after_failure(&RuleBasedPlanner::new(), &mut trajectory, 1, None).await.unwrap());
+        assert_eq!(trajectory.plan().revision, 1);
+    }
+}
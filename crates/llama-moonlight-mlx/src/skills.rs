@@ -0,0 +1,763 @@
+//! Reusable high-level skills
+//!
+//! Skills turn common agent tasks into single calls. [`fill_form`] inspects
+//! the fields of a form (labels, placeholders, `autocomplete` attributes),
+//! maps them to the keys of a JSON object, fills them with human-paced input
+//! and reports what could not be mapped. Keys are matched against the field
+//! descriptions first; a [`FormFiller`] with a text model asks the model to
+//! map the keys that are left.
+
+use crate::MlxError;
+use llama_moonlight_core::Page;
+use log::{debug, info, warn};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+/// Kind of a form field, deciding how it is filled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldKind {
+    /// Text-like `<input>`, e.g. text, email, tel or password
+    Text,
+    /// `<textarea>`
+    Textarea,
+    /// `<select>`
+    Select,
+    /// Checkbox
+    Checkbox,
+    /// Group of radio buttons sharing a name
+    Radio,
+    /// File input
+    File,
+}
+
+/// Option of a select or radio group
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldOption {
+    /// Value submitted with the form
+    pub value: String,
+    
+    /// Text shown to the user
+    pub label: String,
+    
+    /// Selector of the radio button, for radio groups
+    pub selector: Option<String>,
+}
+
+/// Form field as inspected on the page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormField {
+    /// Selector matching the field
+    pub selector: String,
+    
+    /// Kind of field
+    pub kind: FieldKind,
+    
+    /// `type` attribute of an `<input>`
+    pub input_type: Option<String>,
+    
+    /// `name` attribute
+    pub name: Option<String>,
+    
+    /// `id` attribute
+    pub id: Option<String>,
+    
+    /// Text of the field's label, `aria-label` or fieldset legend
+    pub label: Option<String>,
+    
+    /// `placeholder` attribute
+    pub placeholder: Option<String>,
+    
+    /// `autocomplete` attribute
+    pub autocomplete: Option<String>,
+    
+    /// Whether the field is required
+    #[serde(default)]
+    pub required: bool,
+    
+    /// Current value
+    #[serde(default)]
+    pub value: String,
+    
+    /// Options of a select or radio group
+    #[serde(default)]
+    pub options: Vec<FieldOption>,
+}
+
+impl FormField {
+    /// Describe the field in one line, for reports and prompts
+    pub fn describe(&self) -> String {
+        let mut parts = vec![match self.input_type.as_deref() {
+            Some(input_type) if self.kind == FieldKind::Text => format!("{} input", input_type),
+            _ => format!("{:?}", self.kind).to_lowercase(),
+        }];
+        
+        for (attribute, value) in [
+            ("label", &self.label),
+            ("name", &self.name),
+            ("id", &self.id),
+            ("placeholder", &self.placeholder),
+            ("autocomplete", &self.autocomplete),
+        ] {
+            if let Some(value) = value.as_deref().filter(|v| !v.is_empty()) {
+                parts.push(format!("{} \"{}\"", attribute, value));
+            }
+        }
+        if !self.options.is_empty() {
+            let labels: Vec<&str> = self.options.iter().map(|o| o.label.as_str()).collect();
+            parts.push(format!("options [{}]", labels.join(", ")));
+        }
+        
+        parts.join(", ")
+    }
+}
+
+/// How a data key was mapped to a field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchSource {
+    /// The key matches the field's name, label, placeholder or autocomplete attribute
+    Heuristic,
+    /// The text model mapped the key to the field
+    Model,
+}
+
+/// Field filled with a data value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilledField {
+    /// Data key
+    pub key: String,
+    
+    /// Selector of the field
+    pub selector: String,
+    
+    /// How the key was mapped to the field
+    pub matched_by: MatchSource,
+}
+
+/// Field that could not be filled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedField {
+    /// Data key
+    pub key: String,
+    
+    /// Selector of the field
+    pub selector: String,
+    
+    /// Why the field could not be filled
+    pub error: String,
+}
+
+/// Outcome of filling a form
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FormFillReport {
+    /// Fields filled
+    pub filled: Vec<FilledField>,
+    
+    /// Fields mapped to a key but not filled
+    pub failed: Vec<FailedField>,
+    
+    /// Fields no data key was mapped to
+    pub unmapped_fields: Vec<FormField>,
+    
+    /// Data keys not mapped to any field
+    pub unused_keys: Vec<String>,
+}
+
+impl FormFillReport {
+    /// Get whether every data key was filled into a field
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty() && self.unused_keys.is_empty()
+    }
+    
+    /// Get the required fields left empty
+    pub fn missing_required(&self) -> impl Iterator<Item = &FormField> {
+        self.unmapped_fields
+            .iter()
+            .filter(|field| field.required && field.value.is_empty())
+    }
+}
+
+/// Fills forms from JSON data
+pub struct FormFiller {
+    /// Selector of the form, the whole page if none
+    form_selector: Option<String>,
+    
+    /// Shortest and longest pause after typing a character
+    key_delay: (Duration, Duration),
+    
+    /// Shortest and longest pause between fields
+    field_pause: (Duration, Duration),
+    
+    /// Model mapping the keys the heuristics leave
+    #[cfg(feature = "text")]
+    model: Option<std::sync::Arc<crate::text::TextModel>>,
+}
+
+impl Default for FormFiller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FormFiller {
+    /// Create a form filler using heuristics only
+    pub fn new() -> Self {
+        Self {
+            form_selector: None,
+            key_delay: (Duration::from_millis(50), Duration::from_millis(180)),
+            field_pause: (Duration::from_millis(300), Duration::from_millis(900)),
+            #[cfg(feature = "text")]
+            model: None,
+        }
+    }
+    
+    /// Use a text model to map the keys the heuristics leave
+    #[cfg(feature = "text")]
+    pub fn with_model(mut self, model: std::sync::Arc<crate::text::TextModel>) -> Self {
+        self.model = Some(model);
+        self
+    }
+    
+    /// Only fill the fields of the form matching a selector
+    pub fn with_form_selector(mut self, selector: impl Into<String>) -> Self {
+        self.form_selector = Some(selector.into());
+        self
+    }
+    
+    /// Set the shortest and longest pause after typing a character
+    pub fn with_key_delay(mut self, min: Duration, max: Duration) -> Self {
+        self.key_delay = (min, max.max(min));
+        self
+    }
+    
+    /// Set the shortest and longest pause between fields
+    pub fn with_field_pause(mut self, min: Duration, max: Duration) -> Self {
+        self.field_pause = (min, max.max(min));
+        self
+    }
+    
+    /// Inspect the fields of the form
+    pub async fn inspect(&self, page: &Page) -> Result<Vec<FormField>, MlxError> {
+        let root = serde_json::to_string(&self.form_selector)?;
+        let script = INSPECT_FIELDS_SCRIPT.replace("__ROOT__", &root);
+        
+        page.evaluate(&script)
+            .await
+            .map_err(|e| MlxError::Skill(format!("Failed to inspect form fields: {}", e)))
+    }
+    
+    /// Fill the form with the values of a JSON object
+    pub async fn fill(&self, page: &Page, data: &serde_json::Value) -> Result<FormFillReport, MlxError> {
+        let data = data.as_object().ok_or_else(|| {
+            MlxError::Skill("Form data must be a JSON object".to_string())
+        })?;
+        let fields = self.inspect(page).await?;
+        // Null values are treated as absent
+        let keys: Vec<String> = data
+            .iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, _)| key.clone())
+            .collect();
+        
+        let mut mapping: Vec<(String, usize, MatchSource)> = match_heuristically(&keys, &fields)
+            .into_iter()
+            .map(|(key, index)| (key, index, MatchSource::Heuristic))
+            .collect();
+        debug!("Mapped {} of {} keys to fields heuristically", mapping.len(), keys.len());
+        
+        #[cfg(feature = "text")]
+        if let Some(ref model) = self.model {
+            let mapped = mapping.iter().map(|(key, _, _)| key.as_str()).collect::<HashSet<_>>();
+            let used = mapping.iter().map(|(_, index, _)| *index).collect::<HashSet<_>>();
+            let keys: Vec<String> = keys.iter().filter(|key| !mapped.contains(key.as_str())).cloned().collect();
+            let candidates: Vec<usize> = (0..fields.len()).filter(|index| !used.contains(index)).collect();
+            
+            if !keys.is_empty() && !candidates.is_empty() {
+                let matched = match_with_model(model, &keys, &fields, &candidates).await?;
+                debug!("Model mapped {} of {} remaining keys", matched.len(), keys.len());
+                mapping.extend(matched.into_iter().map(|(key, index)| (key, index, MatchSource::Model)));
+            }
+        }
+        
+        // Fill in page order, as a person would
+        mapping.sort_by_key(|(_, index, _)| *index);
+        
+        let mut report = FormFillReport::default();
+        for (i, (key, index, matched_by)) in mapping.iter().enumerate() {
+            let field = &fields[*index];
+            if i > 0 {
+                tokio::time::sleep(random_between(self.field_pause)).await;
+            }
+            
+            match self.fill_field(page, field, &data[key]).await {
+                Ok(()) => report.filled.push(FilledField {
+                    key: key.clone(),
+                    selector: field.selector.clone(),
+                    matched_by: *matched_by,
+                }),
+                Err(e) => {
+                    warn!("Failed to fill '{}' into {}: {}", key, field.selector, e);
+                    report.failed.push(FailedField {
+                        key: key.clone(),
+                        selector: field.selector.clone(),
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+        
+        let mapped_keys: HashSet<&str> = mapping.iter().map(|(key, _, _)| key.as_str()).collect();
+        let mapped_fields: HashSet<usize> = mapping.iter().map(|(_, index, _)| *index).collect();
+        report.unused_keys = keys.iter().filter(|key| !mapped_keys.contains(key.as_str())).cloned().collect();
+        report.unmapped_fields = fields
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !mapped_fields.contains(index))
+            .map(|(_, field)| field.clone())
+            .collect();
+        
+        info!(
+            "Filled {} form fields ({} failed, {} fields unmapped, {} keys unused)",
+            report.filled.len(),
+            report.failed.len(),
+            report.unmapped_fields.len(),
+            report.unused_keys.len()
+        );
+        Ok(report)
+    }
+    
+    /// Fill a field with a value
+    async fn fill_field(&self, page: &Page, field: &FormField, value: &serde_json::Value) -> Result<(), MlxError> {
+        let error = |e: llama_moonlight_core::Error| MlxError::Skill(e.to_string());
+        
+        match field.kind {
+            FieldKind::Text | FieldKind::Textarea => {
+                let text = value_to_text(value);
+                if field.value == text {
+                    return Ok(());
+                }
+                if !field.value.is_empty() {
+                    let selector = serde_json::to_string(&field.selector)?;
+                    page.evaluate::<serde_json::Value>(&CLEAR_FIELD_SCRIPT.replace("__SELECTOR__", &selector))
+                        .await
+                        .map_err(error)?;
+                }
+                
+                let delays = typing_delays(&text, self.key_delay);
+                page.type_text_with_delays(&field.selector, &text, &delays).await.map_err(error)
+            }
+            FieldKind::Select => {
+                let values = match value {
+                    serde_json::Value::Array(values) => values.iter().map(value_to_text).collect(),
+                    value => vec![value_to_text(value)],
+                };
+                page.select_option(&field.selector, &values).await.map(|_| ()).map_err(error)
+            }
+            FieldKind::Checkbox => page.set_checked(&field.selector, is_truthy(value)).await.map_err(error),
+            FieldKind::Radio => {
+                let wanted = value_to_text(value);
+                let option = choose_option(&field.options, &wanted).ok_or_else(|| {
+                    MlxError::Skill(format!("No option matches '{}'", wanted))
+                })?;
+                let selector = option.selector.as_deref().unwrap_or(&field.selector);
+                page.set_checked(selector, true).await.map_err(error)
+            }
+            FieldKind::File => {
+                let paths: Vec<String> = match value {
+                    serde_json::Value::Array(paths) => paths.iter().map(value_to_text).collect(),
+                    value => vec![value_to_text(value)],
+                };
+                page.set_input_files(&field.selector, &paths).await.map_err(error)
+            }
+        }
+    }
+}
+
+/// Fill a form with the values of a JSON object, mapping keys heuristically
+///
+/// Use a [`FormFiller`] to restrict filling to one form, tune the typing pace
+/// or map keys with a text model.
+pub async fn fill_form(page: &Page, data: &serde_json::Value) -> Result<FormFillReport, MlxError> {
+    FormFiller::new().fill(page, data).await
+}
+
+/// Lists the fillable fields under the root element, in page order.
+///
+/// Fields without a unique id or name get a marker attribute so they can be
+/// selected later.
+const INSPECT_FIELDS_SCRIPT: &str = r#"
+(() => {
+  const rootSelector = __ROOT__;
+  const root = rootSelector ? document.querySelector(rootSelector) : document;
+  if (!root) throw new Error('No form matches ' + rootSelector);
+  
+  const text = (value) => (value || '').replace(/\s+/g, ' ').trim() || null;
+  const unique = (selector) => document.querySelectorAll(selector).length === 1 ? selector : null;
+  let marker = 0;
+  const selectorOf = (el) => {
+    const selector = (el.id && unique('#' + CSS.escape(el.id)))
+      || (el.name && el.type !== 'radio' && unique(el.tagName.toLowerCase() + '[name="' + CSS.escape(el.name) + '"]'));
+    if (selector) return selector;
+    if (!el.dataset.moonlightField) el.dataset.moonlightField = String(marker++) + '-' + Date.now();
+    return '[data-moonlight-field="' + el.dataset.moonlightField + '"]';
+  };
+  const labelOf = (el) => {
+    if (el.labels && el.labels.length) return text(Array.from(el.labels).map((l) => l.innerText).join(' '));
+    const labelledBy = el.getAttribute('aria-labelledby');
+    if (labelledBy) {
+      return text(labelledBy.split(/\s+/).map((id) => (document.getElementById(id) || {}).innerText).join(' '));
+    }
+    return text(el.getAttribute('aria-label'));
+  };
+  const visible = (el) => el.type === 'file' || !!(el.offsetWidth || el.offsetHeight || el.getClientRects().length);
+  
+  const fields = [];
+  const radios = {};
+  for (const el of root.querySelectorAll('input, select, textarea')) {
+    const type = (el.getAttribute('type') || 'text').toLowerCase();
+    if (el.disabled || el.readOnly || !visible(el)) continue;
+    if (['hidden', 'submit', 'button', 'reset', 'image'].includes(type) && el.tagName === 'INPUT') continue;
+    
+    const tag = el.tagName.toLowerCase();
+    const kind = tag === 'select' ? 'select'
+      : tag === 'textarea' ? 'textarea'
+      : ['checkbox', 'radio', 'file'].includes(type) ? type
+      : 'text';
+    const option = { value: el.value, label: labelOf(el) || el.value, selector: selectorOf(el) };
+    
+    if (kind === 'radio' && el.name && radios[el.name]) {
+      radios[el.name].options.push(option);
+      if (el.checked) radios[el.name].value = el.value;
+      continue;
+    }
+    
+    const legend = el.closest('fieldset') && el.closest('fieldset').querySelector('legend');
+    const field = {
+      selector: kind === 'radio' ? option.selector : selectorOf(el),
+      kind,
+      inputType: tag === 'input' ? type : null,
+      name: text(el.name),
+      id: text(el.id),
+      label: kind === 'radio' ? text(legend && legend.innerText) : labelOf(el),
+      placeholder: text(el.getAttribute('placeholder')),
+      autocomplete: text(el.getAttribute('autocomplete')),
+      required: el.required,
+      value: kind === 'radio' ? (el.checked ? el.value : '') : kind === 'checkbox' ? (el.checked ? el.value : '') : (el.value || ''),
+      options: [],
+    };
+    if (kind === 'select') {
+      field.options = Array.from(el.options).map((o) => ({ value: o.value, label: text(o.label) || o.value, selector: null }));
+    }
+    if (kind === 'radio') {
+      field.options.push(option);
+      if (el.name) radios[el.name] = field;
+    }
+    fields.push(field);
+  }
+  return fields;
+})()
+"#;
+
+/// Clears the value of a text field, notifying the page's listeners.
+const CLEAR_FIELD_SCRIPT: &str = r#"
+(() => {
+  const el = document.querySelector(__SELECTOR__);
+  if (!el) return false;
+  el.value = '';
+  el.dispatchEvent(new Event('input', { bubbles: true }));
+  return true;
+})()
+"#;
+
+/// Data keys each `autocomplete` token stands for, normalized
+const AUTOCOMPLETE_KEYS: &[(&str, &[&str])] = &[
+    ("name", &["name", "fullname"]),
+    ("given-name", &["firstname", "givenname", "fname", "first"]),
+    ("family-name", &["lastname", "familyname", "surname", "lname", "last"]),
+    ("email", &["email", "emailaddress", "mail"]),
+    ("tel", &["phone", "phonenumber", "telephone", "tel", "mobile"]),
+    ("username", &["username", "login", "user"]),
+    ("new-password", &["password", "newpassword"]),
+    ("current-password", &["password", "currentpassword"]),
+    ("organization", &["company", "organization", "organisation", "employer"]),
+    ("street-address", &["address", "streetaddress", "street"]),
+    ("address-line1", &["address", "address1", "addressline1", "street"]),
+    ("address-line2", &["address2", "addressline2", "apartment", "suite"]),
+    ("address-level2", &["city", "town"]),
+    ("address-level1", &["state", "province", "region"]),
+    ("postal-code", &["zip", "zipcode", "postalcode", "postcode"]),
+    ("country", &["country", "countrycode"]),
+    ("country-name", &["country", "countryname"]),
+    ("bday", &["birthday", "birthdate", "dateofbirth", "dob"]),
+    ("url", &["url", "website", "homepage"]),
+];
+
+/// Lowercase a string, keeping letters and digits only
+fn normalize(text: &str) -> String {
+    text.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// Score how well a data key matches a field, 0 meaning no match
+fn match_score(key: &str, field: &FormField) -> u8 {
+    let key = normalize(key);
+    if key.is_empty() {
+        return 0;
+    }
+    let name = field.name.as_deref().map(normalize).unwrap_or_default();
+    let id = field.id.as_deref().map(normalize).unwrap_or_default();
+    let label = field.label.as_deref().map(normalize).unwrap_or_default();
+    let placeholder = field.placeholder.as_deref().map(normalize).unwrap_or_default();
+    
+    // Only the last token names the field, e.g. "shipping postal-code"
+    let autocomplete = field
+        .autocomplete
+        .as_deref()
+        .and_then(|autocomplete| autocomplete.split_whitespace().last())
+        .unwrap_or_default()
+        .to_lowercase();
+    let autocomplete_matches = AUTOCOMPLETE_KEYS
+        .iter()
+        .any(|(token, keys)| *token == autocomplete && keys.contains(&key.as_str()))
+        || (!autocomplete.is_empty() && normalize(&autocomplete) == key);
+    
+    if name == key || id == key || autocomplete_matches {
+        4
+    } else if label == key || placeholder == key {
+        3
+    } else if key.len() >= 3
+        && [&name, &id, &label, &placeholder].iter().any(|text| text.contains(&key))
+    {
+        2
+    } else if [&name, &id].iter().any(|text| text.len() >= 3 && key.contains(text.as_str())) {
+        1
+    } else {
+        0
+    }
+}
+
+/// Map data keys to fields by their descriptions
+///
+/// Best matches are assigned first; every key and field is used at most once.
+fn match_heuristically(keys: &[String], fields: &[FormField]) -> Vec<(String, usize)> {
+    let mut candidates: Vec<(u8, usize, usize)> = keys
+        .iter()
+        .enumerate()
+        .flat_map(|(k, key)| {
+            fields
+                .iter()
+                .enumerate()
+                .map(move |(f, field)| (match_score(key, field), k, f))
+        })
+        .filter(|(score, _, _)| *score > 0)
+        .collect();
+    candidates.sort_by(|a, b| b.0.cmp(&a.0).then(a.2.cmp(&b.2)));
+    
+    let mut used_keys = HashSet::new();
+    let mut used_fields = HashSet::new();
+    let mut mapping = Vec::new();
+    for (_, k, f) in candidates {
+        if used_keys.contains(&k) || used_fields.contains(&f) {
+            continue;
+        }
+        used_keys.insert(k);
+        used_fields.insert(f);
+        mapping.push((keys[k].clone(), f));
+    }
+    mapping
+}
+
+/// Ask a text model to map data keys to candidate fields
+#[cfg(feature = "text")]
+async fn match_with_model(
+    model: &crate::text::TextModel,
+    keys: &[String],
+    fields: &[FormField],
+    candidates: &[usize],
+) -> Result<Vec<(String, usize)>, MlxError> {
+    let field_list: Vec<String> = candidates
+        .iter()
+        .map(|index| format!("{}: {}", index, fields[*index].describe()))
+        .collect();
+    let prompt = format!(
+        "Map each data key to the form field it should be filled into.\n\
+         Reply with a JSON object only, mapping keys to field numbers, or to null when no field fits, e.g. {{\"email\": 2, \"fax\": null}}.\n\n\
+         Fields:\n{}\n\nKeys: {}",
+        field_list.join("\n"),
+        keys.join(", ")
+    );
+    let params = crate::text::TextGenerationParams {
+        max_tokens: 256,
+        temperature: 0.0,
+        ..Default::default()
+    };
+    let generation = model.generate(&prompt, params).await?;
+    
+    match parse_mapping(&generation.text, keys, candidates) {
+        Some(mapping) => Ok(mapping),
+        None => {
+            warn!("Model did not return a valid field mapping");
+            Ok(Vec::new())
+        }
+    }
+}
+
+/// Parse the key to field mapping in a model completion
+///
+/// Keys not asked for, fields not offered and fields mapped twice are dropped.
+#[cfg_attr(not(feature = "text"), allow(dead_code))]
+fn parse_mapping(text: &str, keys: &[String], candidates: &[usize]) -> Option<Vec<(String, usize)>> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    let object: HashMap<String, Option<usize>> = serde_json::from_str(text.get(start..=end)?).ok()?;
+    
+    let mut used = HashSet::new();
+    let mut mapping = Vec::new();
+    for key in keys {
+        if let Some(Some(index)) = object.get(key) {
+            if candidates.contains(index) && used.insert(*index) {
+                mapping.push((key.clone(), *index));
+            }
+        }
+    }
+    Some(mapping)
+}
+
+/// Choose the option of a select or radio group matching a value
+fn choose_option<'a>(options: &'a [FieldOption], value: &str) -> Option<&'a FieldOption> {
+    let wanted = normalize(value);
+    options
+        .iter()
+        .find(|option| option.value == value || normalize(&option.value) == wanted || normalize(&option.label) == wanted)
+        .or_else(|| {
+            options
+                .iter()
+                .find(|option| !wanted.is_empty() && normalize(&option.label).contains(&wanted))
+        })
+}
+
+/// Convert a data value to the text typed or selected
+fn value_to_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::Array(values) => values.iter().map(value_to_text).collect::<Vec<_>>().join(", "),
+        value => value.to_string(),
+    }
+}
+
+/// Get whether a data value checks a checkbox
+fn is_truthy(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Bool(checked) => *checked,
+        serde_json::Value::Number(number) => number.as_f64().is_some_and(|n| n != 0.0),
+        serde_json::Value::String(text) => {
+            matches!(text.trim().to_lowercase().as_str(), "true" | "yes" | "y" | "on" | "1" | "checked")
+        }
+        _ => false,
+    }
+}
+
+/// Get a random duration within a range
+fn random_between((min, max): (Duration, Duration)) -> Duration {
+    if max <= min {
+        return min;
+    }
+    rand::thread_rng().gen_range(min..=max)
+}
+
+/// Get the pause after each character of a text, as a person types
+///
+/// Word boundaries and punctuation are sometimes followed by a longer pause.
+fn typing_delays(text: &str, key_delay: (Duration, Duration)) -> Vec<Duration> {
+    let mut rng = rand::thread_rng();
+    text.chars()
+        .map(|c| {
+            let delay = random_between(key_delay);
+            if (c.is_whitespace() || c.is_ascii_punctuation()) && rng.gen_bool(0.15) {
+                delay + Duration::from_millis(rng.gen_range(150..450))
+            } else {
+                delay
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn field(kind: FieldKind, name: &str, label: &str, autocomplete: Option<&str>) -> FormField {
+        FormField {
+            selector: format!("[name=\"{}\"]", name),
+            kind,
+            input_type: None,
+            name: Some(name.to_string()),
+            id: None,
+            label: Some(label.to_string()),
+            placeholder: None,
+            autocomplete: autocomplete.map(String::from),
+            required: false,
+            value: String::new(),
+            options: Vec::new(),
+        }
+    }
+    
+    #[test]
+    fn test_match_heuristically() {
+        let fields = vec![
+            field(FieldKind::Text, "fn", "First name", Some("given-name")),
+            field(FieldKind::Text, "ln", "Last name", Some("family-name")),
+            field(FieldKind::Text, "em", "E-mail address", None),
+            field(FieldKind::Text, "zip", "Code", Some("shipping postal-code")),
+            field(FieldKind::Textarea, "notes", "Anything else?", None),
+        ];
+        let keys: Vec<String> = ["first_name", "Last Name", "email", "postal_code", "fax"]
+            .iter()
+            .map(|key| key.to_string())
+            .collect();
+        
+        let mut mapping = match_heuristically(&keys, &fields);
+        mapping.sort_by_key(|(_, index)| *index);
+        assert_eq!(
+            mapping,
+            vec![
+                ("first_name".to_string(), 0),
+                ("Last Name".to_string(), 1),
+                ("email".to_string(), 2),
+                ("postal_code".to_string(), 3),
+            ]
+        );
+    }
+    
+    #[test]
+    fn test_parse_mapping() {
+        let keys = vec!["email".to_string(), "phone".to_string(), "fax".to_string()];
+        let text = "Mapping: {\"email\": 2, \"phone\": 2, \"fax\": null, \"other\": 3}";
+        assert_eq!(parse_mapping(text, &keys, &[2, 3]), Some(vec![("email".to_string(), 2)]));
+        assert_eq!(parse_mapping("no idea", &keys, &[2]), None);
+    }
+    
+    #[test]
+    fn test_values() {
+        let options = vec![
+            FieldOption { value: "us".to_string(), label: "United States".to_string(), selector: None },
+            FieldOption { value: "fr".to_string(), label: "France".to_string(), selector: None },
+        ];
+        assert_eq!(choose_option(&options, "FR").unwrap().value, "fr");
+        assert_eq!(choose_option(&options, "united states").unwrap().value, "us");
+        assert_eq!(choose_option(&options, "States").unwrap().value, "us");
+        assert!(choose_option(&options, "Spain").is_none());
+        
+        assert!(is_truthy(&serde_json::json!("Yes")));
+        assert!(!is_truthy(&serde_json::json!(0)));
+        assert_eq!(value_to_text(&serde_json::json!(42)), "42");
+        
+        let delays = typing_delays("hi there", (Duration::from_millis(10), Duration::from_millis(20)));
+        assert_eq!(delays.len(), 8);
+        assert!(delays.iter().all(|d| *d >= Duration::from_millis(10) && *d < Duration::from_millis(470)));
+    }
+}
@@ -0,0 +1,421 @@
+//! Page summarization and classification
+//!
+//! Crawls collect far more pages than are worth processing in depth. These
+//! utilities triage them cheaply: the readable text of a page is extracted
+//! (navigation, headers, footers and other boilerplate dropped, in the spirit
+//! of Readability), split into chunks that fit the model context window, and
+//! either summarized map-reduce style or scored against a set of labels.
+
+use crate::MlxError;
+use llama_moonlight_core::Page;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "text")]
+use crate::embedding::{cosine_similarity, EmbeddingModel};
+#[cfg(feature = "text")]
+use crate::text::{TextGenerationParams, TextModel};
+#[cfg(feature = "text")]
+use log::{debug, warn};
+#[cfg(feature = "text")]
+use std::collections::HashMap;
+
+/// Context size assumed for models that do not declare one
+#[cfg(feature = "text")]
+const DEFAULT_CONTEXT_TOKENS: usize = 4096;
+
+/// Smallest chunk, in words, whatever the context size
+const MIN_CHUNK_WORDS: usize = 64;
+
+/// Configuration of page triage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriageConfig {
+    /// Tokens of the context window kept for the prompt and the answer
+    pub reserved_tokens: usize,
+    
+    /// Maximum number of chunks processed per page; later text is ignored
+    pub max_chunks: usize,
+    
+    /// Maximum number of tokens of a summary
+    pub summary_tokens: usize,
+}
+
+impl Default for TriageConfig {
+    fn default() -> Self {
+        Self {
+            reserved_tokens: 512,
+            max_chunks: 16,
+            summary_tokens: 256,
+        }
+    }
+}
+
+impl TriageConfig {
+    /// Get the number of words per chunk for a context window
+    pub fn chunk_words(&self, context_tokens: usize) -> usize {
+        // About 4 tokens for every 3 words of English text
+        (context_tokens.saturating_sub(self.reserved_tokens) * 3 / 4).max(MIN_CHUNK_WORDS)
+    }
+}
+
+/// Readable text of a page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadableText {
+    /// Page title
+    pub title: String,
+    
+    /// Page URL
+    pub url: String,
+    
+    /// Main content, one block per paragraph, heading or list item
+    pub text: String,
+}
+
+/// Summary of a page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageSummary {
+    /// Page title
+    pub title: String,
+    
+    /// Page URL
+    pub url: String,
+    
+    /// Summary of the main content
+    pub summary: String,
+    
+    /// Number of chunks summarized
+    pub chunks: usize,
+    
+    /// Whether text beyond the maximum number of chunks was ignored
+    pub truncated: bool,
+}
+
+/// Score of a label
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabelScore {
+    /// Label
+    pub label: String,
+    
+    /// How well the page fits the label, from 0 to 1
+    pub score: f32,
+}
+
+/// Scores of a page against a set of labels
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Classification {
+    /// Page URL
+    pub url: String,
+    
+    /// Label scores, best first
+    pub scores: Vec<LabelScore>,
+}
+
+impl Classification {
+    /// Get the best scoring label
+    pub fn best(&self) -> Option<&LabelScore> {
+        self.scores.first()
+    }
+    
+    /// Get the score of a label
+    pub fn score(&self, label: &str) -> Option<f32> {
+        self.scores.iter().find(|s| s.label == label).map(|s| s.score)
+    }
+}
+
+/// Extracts the main content of the page from a copy of its body.
+///
+/// Paragraphs score their parent, and half as much their grandparent; the
+/// best scoring element, penalized by its link density, holds the content.
+const READABLE_TEXT_SCRIPT: &str = r#"
+(() => {
+  const result = { title: document.title || '', url: location.href, text: '' };
+  if (!document.body) return result;
+  
+  const body = document.body.cloneNode(true);
+  body.querySelectorAll(
+    'script, style, noscript, template, svg, canvas, iframe, nav, header, footer, aside, form, ' +
+    '[role="navigation"], [role="banner"], [role="contentinfo"], [role="complementary"], [aria-hidden="true"]'
+  ).forEach((el) => el.remove());
+  
+  const clean = (text) => (text || '').replace(/\s+/g, ' ').trim();
+  const scores = new Map();
+  for (const p of body.querySelectorAll('p, pre, td, blockquote')) {
+    const text = clean(p.textContent);
+    if (text.length < 25) continue;
+    const points = 1 + text.split(',').length + Math.min(Math.floor(text.length / 100), 3);
+    const parent = p.parentElement;
+    if (parent) scores.set(parent, (scores.get(parent) || 0) + points);
+    if (parent && parent.parentElement) scores.set(parent.parentElement, (scores.get(parent.parentElement) || 0) + points / 2);
+  }
+  
+  let best = body.querySelector('article, main, [role="main"]') || body;
+  let bestScore = 0;
+  for (const [el, score] of scores) {
+    const length = clean(el.textContent).length || 1;
+    const links = Array.from(el.querySelectorAll('a')).reduce((sum, a) => sum + clean(a.textContent).length, 0);
+    const adjusted = score * (1 - Math.min(links / length, 1));
+    if (adjusted > bestScore) {
+      best = el;
+      bestScore = adjusted;
+    }
+  }
+  
+  const blocks = 'h1, h2, h3, h4, h5, h6, p, li, pre, blockquote, td';
+  const texts = Array.from(best.querySelectorAll(blocks))
+    .filter((el) => !el.querySelector(blocks))
+    .map((el) => clean(el.textContent))
+    .filter((text) => text.length > 0);
+  result.text = texts.length ? texts.join('\n\n') : clean(best.textContent);
+  return result;
+})()
+"#;
+
+/// Extract the readable text of a page
+pub async fn extract_readable_text(page: &Page) -> Result<ReadableText, MlxError> {
+    page.evaluate(READABLE_TEXT_SCRIPT)
+        .await
+        .map_err(|e| MlxError::Other(format!("Failed to extract page text: {}", e)))
+}
+
+/// Split text into chunks of at most `max_words` words
+///
+/// Chunks end at paragraph boundaries where possible; paragraphs longer than
+/// a chunk are split between words.
+pub fn chunk_text(text: &str, max_words: usize) -> Vec<String> {
+    let max_words = max_words.max(1);
+    let mut chunks = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    let mut current_words = 0;
+    
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        let words = paragraph.split_whitespace().count();
+        
+        if current_words + words > max_words && !current.is_empty() {
+            chunks.push(current.join("\n\n"));
+            current.clear();
+            current_words = 0;
+        }
+        
+        if words > max_words {
+            // The tail of a long paragraph starts the next chunk
+            let words: Vec<&str> = paragraph.split_whitespace().collect();
+            let mut pieces: Vec<&[&str]> = words.chunks(max_words).collect();
+            let tail = pieces.pop().unwrap_or_default();
+            chunks.extend(pieces.iter().map(|piece| piece.join(" ")));
+            current.push(tail.join(" "));
+            current_words = tail.len();
+        } else {
+            current.push(paragraph.to_string());
+            current_words += words;
+        }
+    }
+    
+    if !current.is_empty() {
+        chunks.push(current.join("\n\n"));
+    }
+    chunks
+}
+
+/// Summarize the readable text of a page with a text model
+#[cfg(feature = "text")]
+pub async fn summarize_page(model: &TextModel, page: &Page, config: &TriageConfig) -> Result<PageSummary, MlxError> {
+    let readable = extract_readable_text(page).await?;
+    let max_words = config.chunk_words(context_tokens(model));
+    
+    let mut chunks = chunk_text(&readable.text, max_words);
+    let truncated = chunks.len() > config.max_chunks;
+    chunks.truncate(config.max_chunks);
+    let chunk_count = chunks.len();
+    
+    if chunks.is_empty() {
+        return Ok(PageSummary {
+            title: readable.title,
+            url: readable.url,
+            summary: String::new(),
+            chunks: 0,
+            truncated: false,
+        });
+    }
+    
+    // Summarize the chunks, then the summaries, until a single one is left
+    let mut summaries = Vec::with_capacity(chunks.len());
+    for chunk in &chunks {
+        summaries.push(summarize_chunk(model, &readable.title, chunk, config).await?);
+    }
+    while summaries.len() > 1 {
+        debug!("Combining {} partial summaries of {}", summaries.len(), readable.url);
+        let combined = chunk_text(&summaries.join("\n\n"), max_words);
+        let mut next = Vec::with_capacity(combined.len());
+        for chunk in &combined {
+            next.push(summarize_chunk(model, &readable.title, chunk, config).await?);
+        }
+        // Stop if the summaries no longer shrink
+        if next.len() >= summaries.len() {
+            summaries = vec![next.join("\n\n")];
+            break;
+        }
+        summaries = next;
+    }
+    
+    Ok(PageSummary {
+        title: readable.title,
+        url: readable.url,
+        summary: summaries.remove(0),
+        chunks: chunk_count,
+        truncated,
+    })
+}
+
+/// Score the readable text of a page against labels with a text model
+///
+/// Each chunk is scored separately and the scores are averaged. Chunks the
+/// model does not score properly are scored by lexical similarity instead.
+#[cfg(feature = "text")]
+pub async fn classify_page(
+    model: &TextModel,
+    page: &Page,
+    labels: &[&str],
+    config: &TriageConfig,
+) -> Result<Classification, MlxError> {
+    if labels.is_empty() {
+        return Err(MlxError::Other("At least one label is needed to classify a page".to_string()));
+    }
+    
+    let readable = extract_readable_text(page).await?;
+    let text = format!("{}\n\n{}", readable.title, readable.text);
+    let mut chunks = chunk_text(&text, config.chunk_words(context_tokens(model)));
+    chunks.truncate(config.max_chunks);
+    
+    let mut totals = vec![0f32; labels.len()];
+    for chunk in &chunks {
+        let scores = match score_chunk(model, chunk, labels).await? {
+            Some(scores) => scores,
+            None => {
+                warn!("Model did not return valid label scores, using lexical similarity");
+                lexical_scores(chunk, labels).await?
+            }
+        };
+        totals.iter_mut().zip(scores).for_each(|(total, score)| *total += score);
+    }
+    
+    let count = chunks.len().max(1) as f32;
+    let mut scores: Vec<LabelScore> = labels
+        .iter()
+        .zip(totals)
+        .map(|(label, total)| LabelScore {
+            label: label.to_string(),
+            score: total / count,
+        })
+        .collect();
+    scores.sort_by(|a, b| b.score.total_cmp(&a.score));
+    
+    Ok(Classification {
+        url: readable.url,
+        scores,
+    })
+}
+
+/// Get the context window of a model, in tokens
+#[cfg(feature = "text")]
+fn context_tokens(model: &TextModel) -> usize {
+    model.config.context_size.unwrap_or(DEFAULT_CONTEXT_TOKENS)
+}
+
+/// Summarize a chunk of a page
+#[cfg(feature = "text")]
+async fn summarize_chunk(model: &TextModel, title: &str, chunk: &str, config: &TriageConfig) -> Result<String, MlxError> {
+    let prompt = format!(
+        "Summarize the following content of the web page \"{}\" in a few sentences, keeping names, numbers and dates.\n\n{}\n\nSummary:",
+        title,
+        chunk
+    );
+    let params = TextGenerationParams {
+        max_tokens: config.summary_tokens,
+        temperature: 0.2,
+        ..Default::default()
+    };
+    
+    Ok(model.generate(&prompt, params).await?.text.trim().to_string())
+}
+
+/// Ask a model to score a chunk against labels
+#[cfg(feature = "text")]
+async fn score_chunk(model: &TextModel, chunk: &str, labels: &[&str]) -> Result<Option<Vec<f32>>, MlxError> {
+    let prompt = format!(
+        "Score how well the web page content below fits each label, from 0 (not at all) to 1 (perfectly).\n\
+         Reply with a JSON object only, mapping each label to its score.\n\n\
+         Labels: {}\n\nContent:\n{}\n\nScores:",
+        labels.join(", "),
+        chunk
+    );
+    let params = TextGenerationParams {
+        max_tokens: 32 + 16 * labels.len(),
+        temperature: 0.0,
+        ..Default::default()
+    };
+    let generation = model.generate(&prompt, params).await?;
+    
+    Ok(parse_scores(&generation.text, labels))
+}
+
+/// Score a chunk against labels by the similarity of their hashed embeddings
+#[cfg(feature = "text")]
+async fn lexical_scores(chunk: &str, labels: &[&str]) -> Result<Vec<f32>, MlxError> {
+    let embeddings = EmbeddingModel::hashing(384);
+    let text = embeddings.embed(chunk).await?;
+    
+    let mut scores = Vec::with_capacity(labels.len());
+    for label in labels {
+        let label = embeddings.embed(label).await?;
+        scores.push(cosine_similarity(&text, &label).clamp(0.0, 1.0));
+    }
+    Ok(scores)
+}
+
+/// Parse label scores in a model completion
+///
+/// Labels are matched case-insensitively and scores clamped to 0..=1. Returns
+/// `None` unless every label is scored.
+#[cfg(feature = "text")]
+fn parse_scores(text: &str, labels: &[&str]) -> Option<Vec<f32>> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    let object: HashMap<String, f32> = serde_json::from_str(text.get(start..=end)?).ok()?;
+    let object: HashMap<String, f32> = object
+        .into_iter()
+        .map(|(label, score)| (label.trim().to_lowercase(), score))
+        .collect();
+    
+    labels
+        .iter()
+        .map(|label| object.get(&label.to_lowercase()).map(|score| score.clamp(0.0, 1.0)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_chunk_text() {
+        let text = "one two three\n\nfour five\n\nsix seven eight nine ten eleven\n\ntwelve";
+        assert_eq!(
+            chunk_text(text, 5),
+            vec!["one two three\n\nfour five", "six seven eight nine ten", "eleven\n\ntwelve"]
+        );
+        assert_eq!(chunk_text(text, 100).len(), 1);
+        assert!(chunk_text("  \n\n ", 10).is_empty());
+        
+        let config = TriageConfig::default();
+        assert_eq!(config.chunk_words(4096), 2688);
+        assert_eq!(config.chunk_words(256), MIN_CHUNK_WORDS);
+    }
+    
+    #[cfg(feature = "text")]
+    #[test]
+    fn test_parse_scores() {
+        let labels = ["News", "shop"];
+        assert_eq!(parse_scores("{\"news\": 0.9, \"Shop\": 1.5}", &labels), Some(vec![0.9, 1.0]));
+        assert_eq!(parse_scores("{\"news\": 0.9}", &labels), None);
+        assert_eq!(parse_scores("no scores", &labels), None);
+    }
+}
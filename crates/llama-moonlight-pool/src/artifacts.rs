@@ -0,0 +1,137 @@
+//! Diagnostics captured when a pooled browser session fails.
+//!
+//! When a browser is marked failed, the pool writes what is needed to debug
+//! the failure into a directory of its own under
+//! [`PoolConfig::artifacts_dir`](crate::PoolConfig::artifacts_dir):
+//!
+//! - `report.json`: browser ID and type, error, last page URL, and the names
+//!   of the other files
+//! - `screenshot.png`: screenshot of the last page
+//! - `console.log`: last console messages and uncaught errors of the page
+//! - `stderr.log`: last lines the browser wrote to standard error
+//!
+//! Every item is best effort: a crashed browser still gets a report.
+//!
+//! Nothing is captured unless `artifacts_dir` is set, as the pool does not
+//! clean the directory up; screenshots of busy pools add up quickly.
+
+use llama_moonlight_core::{Browser, Page};
+use serde::Serialize;
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, warn};
+
+/// Script recording console messages and uncaught errors of a page.
+pub(crate) const CONSOLE_CAPTURE_SCRIPT: &str = r#"(() => {
+    if (window.__moonlightConsole) return;
+    const buffer = window.__moonlightConsole = [];
+    const push = (level, args) => {
+        const text = Array.from(args).map(arg => {
+            if (arg instanceof Error) return arg.stack || String(arg);
+            if (typeof arg === 'object') { try { return JSON.stringify(arg); } catch (e) {} }
+            return String(arg);
+        }).join(' ');
+        buffer.push(`[${new Date().toISOString()}] ${level}: ${text}`);
+        if (buffer.length > 500) buffer.shift();
+    };
+    for (const level of ['log', 'info', 'warn', 'error', 'debug']) {
+        const original = console[level];
+        console[level] = function () { push(level, arguments); return original.apply(this, arguments); };
+    }
+    window.addEventListener('error', e => push('uncaught', [e.error || e.message]));
+    window.addEventListener('unhandledrejection', e => push('unhandledrejection', [e.reason]));
+})()"#;
+
+/// Summary of a failed session, written as `report.json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct FailureReport {
+    /// ID of the browser in the pool
+    pub browser_id: String,
+    /// Browser type name
+    pub browser_type: String,
+    /// Error that ended the session
+    pub error: String,
+    /// Time of the failure (milliseconds since the Unix epoch)
+    pub timestamp_ms: u64,
+    /// URL of the last page, if it could be read
+    pub page_url: Option<String>,
+    /// File name of the screenshot, if one was taken
+    pub screenshot: Option<String>,
+    /// File name of the console tail, if one was captured
+    pub console_log: Option<String>,
+    /// File name of the browser's stderr tail, if one was captured
+    pub stderr_log: Option<String>,
+}
+
+/// Install console capture on a page, for this and later documents.
+pub(crate) async fn install_console_capture(page: &Page) -> llama_moonlight_core::Result<()> {
+    page.add_init_script(CONSOLE_CAPTURE_SCRIPT).await?;
+    let _: serde_json::Value = page.evaluate(CONSOLE_CAPTURE_SCRIPT).await?;
+    Ok(())
+}
+
+/// Capture the diagnostics of a failed session and return their directory.
+pub(crate) async fn capture(
+    artifacts_dir: &Path,
+    browser_id: &str,
+    browser_type: &str,
+    error: &str,
+    browser: &Browser,
+    page: Option<&Page>,
+    tail_lines: usize,
+) -> std::io::Result<PathBuf> {
+    let timestamp_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+
+    let dir = artifacts_dir.join(format!("{}-{}", timestamp_ms, browser_id));
+    tokio::fs::create_dir_all(&dir).await?;
+
+    let mut report = FailureReport {
+        browser_id: browser_id.to_string(),
+        browser_type: browser_type.to_string(),
+        error: error.to_string(),
+        timestamp_ms,
+        page_url: None,
+        screenshot: None,
+        console_log: None,
+        stderr_log: None,
+    };
+
+    if let Some(page) = page {
+        match page.url().await {
+            Ok(url) => report.page_url = Some(url),
+            Err(e) => debug!("Could not read page URL for failure report: {}", e),
+        }
+
+        let screenshot = dir.join("screenshot.png");
+        match page.screenshot(&screenshot.to_string_lossy()).await {
+            Ok(()) => report.screenshot = Some("screenshot.png".to_string()),
+            Err(e) => debug!("Could not take screenshot for failure report: {}", e),
+        }
+
+        let script = format!("(window.__moonlightConsole || []).slice(-{})", tail_lines);
+        match page.evaluate::<Vec<String>>(&script).await {
+            Ok(lines) if !lines.is_empty() => {
+                tokio::fs::write(dir.join("console.log"), lines.join("\n") + "\n").await?;
+                report.console_log = Some("console.log".to_string());
+            }
+            Ok(_) => {}
+            Err(e) => debug!("Could not read console messages for failure report: {}", e),
+        }
+    }
+
+    if let Some(stderr) = browser.stderr_tail(tail_lines).await.filter(|s| !s.trim().is_empty()) {
+        tokio::fs::write(dir.join("stderr.log"), stderr + "\n").await?;
+        report.stderr_log = Some("stderr.log".to_string());
+    }
+
+    let json = serde_json::to_vec_pretty(&report)?;
+    tokio::fs::write(dir.join("report.json"), json).await?;
+
+    warn!("Captured failure artifacts of browser {} in {}", browser_id, dir.display());
+    Ok(dir)
+}
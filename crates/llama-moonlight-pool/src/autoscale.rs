@@ -0,0 +1,324 @@
+//! Adaptive sizing of the pool from claim wait times.
+//!
+//! With [`PoolConfig::autoscale`](crate::PoolConfig::autoscale) set, every
+//! maintenance run compares the average time claims waited for a browser
+//! since the previous run with a target. Above the target, or when claims
+//! failed for lack of a browser, the pool grows, so later claims find a warm
+//! browser instead of waiting for one to launch;
+//! after sustained idleness it shrinks back. The size stays between
+//! `min_size` and `max_size`, and within the memory budget if one is set.
+//!
+//! Every change is published as a [`ScaleEvent`] (see
+//! [`BrowserPool::scale_events`](crate::BrowserPool::scale_events)) and as
+//! `browser_pool.autoscale.*` metrics.
+
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Configuration of the pool's autoscaler
+#[derive(Debug, Clone)]
+pub struct AutoscaleConfig {
+    /// Average claim wait above which the pool grows (in milliseconds)
+    pub target_wait_ms: u64,
+    /// Number of browsers added when the pool grows
+    pub grow_step: usize,
+    /// Number of browsers removed when the pool shrinks
+    pub shrink_step: usize,
+    /// Time the pool must stay idle before it shrinks (in seconds)
+    pub idle_secs_before_shrink: u64,
+    /// Estimated memory used by one browser (in megabytes)
+    pub browser_memory_mb: u64,
+    /// Memory all browsers of the pool may use together (unbounded if `None`)
+    pub memory_budget_mb: Option<u64>,
+}
+
+impl Default for AutoscaleConfig {
+    fn default() -> Self {
+        Self {
+            target_wait_ms: 500,
+            grow_step: 1,
+            shrink_step: 1,
+            idle_secs_before_shrink: 120,
+            browser_memory_mb: 300,
+            memory_budget_mb: None,
+        }
+    }
+}
+
+impl AutoscaleConfig {
+    /// Get the largest number of browsers the memory budget allows
+    pub fn budget_size(&self) -> Option<usize> {
+        self.memory_budget_mb
+            .map(|budget| (budget / self.browser_memory_mb.max(1)) as usize)
+    }
+}
+
+/// Change of the pool's size decided by the autoscaler
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScaleEvent {
+    /// The pool grew because claims waited longer than the target
+    Grow {
+        /// Size before growing
+        from: usize,
+        /// Size after growing
+        to: usize,
+        /// Average claim wait since the previous maintenance run (in milliseconds)
+        average_wait_ms: u64,
+        /// Claims that failed since the previous maintenance run
+        failed_claims: u32,
+    },
+    /// The pool shrank after staying idle
+    Shrink {
+        /// Size before shrinking
+        from: usize,
+        /// Size after shrinking
+        to: usize,
+        /// Time the pool stayed idle (in seconds)
+        idle_secs: u64,
+    },
+}
+
+impl ScaleEvent {
+    /// Get the size the pool scales to
+    pub fn target(&self) -> usize {
+        match self {
+            ScaleEvent::Grow { to, .. } | ScaleEvent::Shrink { to, .. } => *to,
+        }
+    }
+}
+
+/// Claim wait times recorded between maintenance runs
+#[derive(Debug, Default)]
+pub(crate) struct WaitStats {
+    total: Duration,
+    claims: u32,
+    failures: u32,
+}
+
+impl WaitStats {
+    /// Record how long a claim waited for a browser
+    pub(crate) fn record(&mut self, wait: Duration) {
+        self.total += wait;
+        self.claims += 1;
+    }
+
+    /// Record how long a claim waited before failing to get a browser
+    pub(crate) fn record_failure(&mut self, wait: Duration) {
+        self.record(wait);
+        self.failures += 1;
+    }
+
+    /// Take the average wait and the number of failed claims recorded since
+    /// the last call
+    ///
+    /// The average covers failed claims too, and is `None` without claims.
+    pub(crate) fn take(&mut self) -> (Option<Duration>, u32) {
+        let stats = std::mem::take(self);
+        ((stats.claims > 0).then(|| stats.total / stats.claims), stats.failures)
+    }
+}
+
+/// State of the pool observed by a maintenance run
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PoolSample {
+    /// Number of browsers in the pool
+    pub size: usize,
+    /// Number of idle browsers
+    pub idle: usize,
+    /// Average claim wait since the previous run, if there were claims
+    pub average_wait: Option<Duration>,
+    /// Claims that failed since the previous run
+    pub failed_claims: u32,
+    /// Time of the observation
+    pub now: Instant,
+}
+
+/// Decides the size the pool keeps warm
+#[derive(Debug)]
+pub(crate) struct Autoscaler {
+    config: AutoscaleConfig,
+    min_size: usize,
+    max_size: usize,
+    target: usize,
+    idle_since: Option<Instant>,
+}
+
+impl Autoscaler {
+    /// Create an autoscaler starting at the pool's minimum size
+    pub(crate) fn new(config: AutoscaleConfig, min_size: usize, max_size: usize) -> Self {
+        let max_size = config
+            .budget_size()
+            .map_or(max_size, |budget| budget.min(max_size))
+            .max(min_size);
+
+        Self {
+            config,
+            min_size,
+            max_size,
+            target: min_size,
+            idle_since: None,
+        }
+    }
+
+    /// Get the number of browsers the pool should keep
+    pub(crate) fn target(&self) -> usize {
+        self.target
+    }
+
+    /// Update the target size from an observation of the pool
+    pub(crate) fn decide(&mut self, sample: PoolSample) -> Option<ScaleEvent> {
+        let target_wait = Duration::from_millis(self.config.target_wait_ms);
+
+        let slow = sample.average_wait.filter(|wait| *wait > target_wait);
+        if slow.is_some() || sample.failed_claims > 0 {
+            self.idle_since = None;
+
+            let from = self.target.max(sample.size);
+            let to = (from + self.config.grow_step.max(1)).min(self.max_size);
+            if to <= from {
+                return None;
+            }
+
+            self.target = to;
+            return Some(ScaleEvent::Grow {
+                from,
+                to,
+                average_wait_ms: sample.average_wait.unwrap_or_default().as_millis() as u64,
+                failed_claims: sample.failed_claims,
+            });
+        }
+
+        // The pool is idle while some of its browsers are unused
+        if sample.idle == 0 {
+            self.idle_since = None;
+            return None;
+        }
+
+        let idle_since = *self.idle_since.get_or_insert(sample.now);
+        let idle_for = sample.now.duration_since(idle_since);
+        if idle_for < Duration::from_secs(self.config.idle_secs_before_shrink) || self.target <= self.min_size {
+            return None;
+        }
+
+        // Each further step needs another full period of idleness
+        self.idle_since = Some(sample.now);
+
+        let from = self.target;
+        let to = from.saturating_sub(self.config.shrink_step.max(1)).max(self.min_size);
+        self.target = to;
+        Some(ScaleEvent::Shrink {
+            from,
+            to,
+            idle_secs: idle_for.as_secs(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(size: usize, idle: usize, wait_ms: Option<u64>, now: Instant) -> PoolSample {
+        PoolSample {
+            size,
+            idle,
+            average_wait: wait_ms.map(Duration::from_millis),
+            failed_claims: 0,
+            now,
+        }
+    }
+
+    #[test]
+    fn test_grows_on_slow_or_failed_claims() {
+        let config = AutoscaleConfig {
+            target_wait_ms: 500,
+            grow_step: 2,
+            ..AutoscaleConfig::default()
+        };
+        let mut autoscaler = Autoscaler::new(config, 1, 4);
+        let now = Instant::now();
+
+        // Claims within the target leave the size alone
+        assert_eq!(autoscaler.decide(sample(1, 0, Some(200), now)), None);
+
+        assert_eq!(
+            autoscaler.decide(sample(1, 0, Some(800), now)),
+            Some(ScaleEvent::Grow { from: 1, to: 3, average_wait_ms: 800, failed_claims: 0 })
+        );
+
+        // Failed claims grow the pool even if they failed fast
+        let failed = PoolSample { failed_claims: 2, ..sample(3, 0, Some(10), now) };
+        assert_eq!(
+            autoscaler.decide(failed),
+            Some(ScaleEvent::Grow { from: 3, to: 4, average_wait_ms: 10, failed_claims: 2 })
+        );
+
+        // Never beyond the maximum size
+        assert_eq!(autoscaler.decide(sample(4, 0, Some(900), now)), None);
+        assert_eq!(autoscaler.target(), 4);
+    }
+
+    #[test]
+    fn test_memory_budget_caps_size() {
+        let config = AutoscaleConfig {
+            browser_memory_mb: 300,
+            memory_budget_mb: Some(1000),
+            ..AutoscaleConfig::default()
+        };
+        let mut autoscaler = Autoscaler::new(config, 1, 10);
+        let now = Instant::now();
+
+        for _ in 0..5 {
+            autoscaler.decide(sample(autoscaler.target(), 0, Some(1000), now));
+        }
+        assert_eq!(autoscaler.target(), 3);
+    }
+
+    #[test]
+    fn test_shrinks_after_sustained_idleness() {
+        let config = AutoscaleConfig {
+            idle_secs_before_shrink: 60,
+            ..AutoscaleConfig::default()
+        };
+        let mut autoscaler = Autoscaler::new(config, 1, 4);
+        let start = Instant::now();
+        autoscaler.decide(sample(1, 0, Some(1000), start));
+        autoscaler.decide(sample(2, 0, Some(1000), start));
+        assert_eq!(autoscaler.target(), 3);
+
+        assert_eq!(autoscaler.decide(sample(3, 2, None, start)), None);
+        assert_eq!(autoscaler.decide(sample(3, 2, None, start + Duration::from_secs(30))), None);
+
+        // A busy run restarts the idle period
+        assert_eq!(autoscaler.decide(sample(3, 0, None, start + Duration::from_secs(40))), None);
+        assert_eq!(autoscaler.decide(sample(3, 2, None, start + Duration::from_secs(50))), None);
+        assert_eq!(autoscaler.decide(sample(3, 2, None, start + Duration::from_secs(100))), None);
+        assert_eq!(
+            autoscaler.decide(sample(3, 2, None, start + Duration::from_secs(110))),
+            Some(ScaleEvent::Shrink { from: 3, to: 2, idle_secs: 60 })
+        );
+
+        // Each further step needs another full period
+        assert_eq!(autoscaler.decide(sample(2, 1, None, start + Duration::from_secs(150))), None);
+        assert_eq!(
+            autoscaler.decide(sample(2, 1, None, start + Duration::from_secs(170))),
+            Some(ScaleEvent::Shrink { from: 2, to: 1, idle_secs: 60 })
+        );
+
+        // Never below the minimum size
+        assert_eq!(autoscaler.decide(sample(1, 1, None, start + Duration::from_secs(1000))), None);
+    }
+
+    #[test]
+    fn test_wait_stats_count_failures() {
+        let mut stats = WaitStats::default();
+        assert_eq!(stats.take(), (None, 0));
+
+        stats.record(Duration::from_millis(100));
+        stats.record_failure(Duration::from_millis(500));
+        assert_eq!(stats.take(), (Some(Duration::from_millis(300)), 1));
+
+        // Taking resets the stats
+        assert_eq!(stats.take(), (None, 0));
+    }
+}
@@ -0,0 +1,33 @@
+//! Fresh identities for the sessions of claimed browsers.
+//!
+//! Browsers are reused across claims, so whatever a site can observe of a
+//! browser — user agent, locale, screen, navigator properties — would stay
+//! the same for every logically distinct session run on it. An
+//! [`IdentityProvider`] installed with
+//! [`BrowserPool::set_identity_provider`](crate::BrowserPool::set_identity_provider)
+//! creates a new [`ContextIdentity`] for each claim, which the
+//! [`PooledBrowser`](crate::PooledBrowser) applies to every context and page
+//! it opens.
+//!
+//! `llama-moonlight-stealth` provides a provider generating a fresh stealth
+//! fingerprint and header identity per claim (with its `pool` feature).
+
+use async_trait::async_trait;
+use llama_moonlight_core::{options::ContextOptions, Error, Page};
+use std::sync::Arc;
+
+/// Identity applied to the contexts opened during one claim of a browser
+#[async_trait]
+pub trait ContextIdentity: Send + Sync {
+    /// Adjust the options of a new context, e.g. its user agent and locale
+    fn apply_to_options(&self, options: &mut ContextOptions);
+
+    /// Prepare a new page before it is handed out, e.g. by installing scripts
+    async fn prepare_page(&self, page: &Page) -> Result<(), Error>;
+}
+
+/// Source of a fresh identity for each claim of a browser
+pub trait IdentityProvider: Send + Sync {
+    /// Create the identity of a new claim
+    fn new_identity(&self) -> Arc<dyn ContextIdentity>;
+}
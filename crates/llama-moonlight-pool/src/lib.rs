@@ -3,19 +3,44 @@ use async_semaphore::Semaphore;
 use dashmap::DashMap;
 use futures::{future, StreamExt};
 use llama_moonlight_core::{
-    options::{BrowserOptions, ContextOptions},
-    Browser, BrowserType, Moonlight,
+    options::{BrowserOptions, ContextOptions, PageOptions},
+    telemetry::FIELD_BROWSER_ID,
+    Browser, BrowserContext, BrowserType, Moonlight, Page,
 };
-use log::{debug, error, info, warn};
+use tracing::{debug, error, info, instrument, warn};
 use metrics::{counter, gauge};
 use std::{
-    sync::Arc,
-    time::{Duration, Instant},
+    collections::HashMap,
+    fmt,
+    future::Future,
+    ops::Deref,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 use thiserror::Error;
-use tokio::sync::Mutex;
+use tokio::{
+    sync::{broadcast, Mutex, OwnedSemaphorePermit},
+    time::Instant,
+};
 use uuid::Uuid;
 
+use autoscale::{Autoscaler, PoolSample, WaitStats};
+use quota::ConsumerQuotas;
+
+pub mod artifacts;
+pub mod autoscale;
+pub mod identity;
+pub mod quota;
+
+pub use artifacts::FailureReport;
+pub use autoscale::{AutoscaleConfig, ScaleEvent};
+pub use identity::{ContextIdentity, IdentityProvider};
+pub use quota::QuotaPolicy;
+
 /// Errors specific to the browser pool
 #[derive(Error, Debug)]
 pub enum PoolError {
@@ -35,6 +60,25 @@ pub enum PoolError {
     #[error("Browser is not idle")]
     BrowserNotIdle,
 
+    /// Error when using a browser whose lease expired and which the pool took back
+    #[error("Lease of browser {0} expired")]
+    LeaseExpired(String),
+
+    /// Error when a consumer already holds as many browsers as its quota allows
+    #[error("Consumer '{0}' reached its quota of concurrent browsers")]
+    QuotaExceeded(String),
+
+    /// Error ending a browser session, with the directory of its failure artifacts
+    #[error("Browser {browser_id} failed: {message}{}", .artifacts.as_ref().map(|dir| format!(" (artifacts: {})", dir.display())).unwrap_or_default())]
+    SessionFailed {
+        /// ID of the failed browser
+        browser_id: String,
+        /// Error message
+        message: String,
+        /// Directory of the captured artifacts, if capture succeeded
+        artifacts: Option<PathBuf>,
+    },
+
     /// Error from the core library
     #[error("Core error: {0}")]
     CoreError(#[from] llama_moonlight_core::Error),
@@ -59,6 +103,54 @@ pub enum BrowserStatus {
     Failed,
 }
 
+/// Browser of the pool that exited or lost its connection unexpectedly
+///
+/// The browser is marked [`BrowserStatus::Failed`] as soon as the crash is
+/// detected. An idle browser is replaced right away; one in use is replaced
+/// once its handle is dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrowserCrash {
+    /// ID of the crashed browser
+    pub browser_id: String,
+    /// What happened, such as the exit status of the browser process
+    pub reason: String,
+    /// Status of the browser when it crashed
+    pub status: BrowserStatus,
+}
+
+/// Lease is running
+const LEASE_ACTIVE: u8 = 0;
+/// Handle was dropped before the lease expired
+const LEASE_RETURNED: u8 = 1;
+/// Lease expired and the pool took the browser back
+const LEASE_EXPIRED: u8 = 2;
+
+/// Time slice a browser is claimed for
+#[derive(Debug)]
+struct Lease {
+    /// Time at which the pool takes the browser back
+    deadline: Instant,
+    /// Whether the lease is running, returned or expired
+    state: AtomicU8,
+}
+
+impl Lease {
+    /// End the lease as returned or expired, unless it already ended
+    ///
+    /// Returns whether this call ended it, so that either the handle or the
+    /// pool deals with the browser, never both.
+    fn end(&self, state: u8) -> bool {
+        self.state
+            .compare_exchange(LEASE_ACTIVE, state, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
+    /// Check whether the lease expired
+    fn is_expired(&self) -> bool {
+        self.state.load(Ordering::Acquire) == LEASE_EXPIRED
+    }
+}
+
 /// Information about a browser in the pool
 #[derive(Debug)]
 struct BrowserInfo {
@@ -89,6 +181,8 @@ pub struct PoolConfig {
     pub max_uses: u32,
     /// Maximum idle time before recycling a browser (in seconds)
     pub max_idle_time_secs: u64,
+    /// Time between maintenance runs (in seconds)
+    pub maintenance_interval_secs: u64,
     /// Browser type to use (chromium, firefox, webkit)
     pub browser_type: String,
     /// Browser launch options
@@ -103,6 +197,18 @@ pub struct PoolConfig {
     pub max_creation_retries: u32,
     /// Enable metrics collection
     pub enable_metrics: bool,
+    /// Directory receiving diagnostics of failed sessions (disabled if `None`, the default)
+    pub artifacts_dir: Option<PathBuf>,
+    /// Number of console and stderr lines kept in failure artifacts
+    pub artifact_tail_lines: usize,
+    /// Default maximum number of browsers a consumer can hold at once, at least 1 (unlimited if `None`)
+    pub consumer_quota: Option<usize>,
+    /// Per-consumer quotas overriding `consumer_quota`, at least 1, keyed by consumer name or API key
+    pub consumer_quotas: HashMap<String, usize>,
+    /// What happens to claims beyond a consumer's quota
+    pub quota_policy: QuotaPolicy,
+    /// Grow and shrink the pool from claim wait times (fixed at `min_size` if `None`)
+    pub autoscale: Option<AutoscaleConfig>,
 }
 
 impl Default for PoolConfig {
@@ -112,6 +218,7 @@ impl Default for PoolConfig {
             max_size: 10,
             max_uses: 100,
             max_idle_time_secs: 300, // 5 minutes
+            maintenance_interval_secs: 30,
             browser_type: "chromium".to_string(),
             browser_options: BrowserOptions {
                 headless: Some(true),
@@ -123,6 +230,12 @@ impl Default for PoolConfig {
             creation_retry_delay_ms: 1000,
             max_creation_retries: 3,
             enable_metrics: true,
+            artifacts_dir: None,
+            artifact_tail_lines: 100,
+            consumer_quota: None,
+            consumer_quotas: HashMap::new(),
+            quota_policy: QuotaPolicy::Queue,
+            autoscale: None,
         }
     }
 }
@@ -135,22 +248,77 @@ pub struct PooledBrowser {
     id: String,
     /// Pool that owns this browser
     pool: Arc<BrowserPool>,
+    /// Last page opened through this handle, used for failure artifacts
+    last_page: std::sync::Mutex<Option<Arc<Page>>>,
+    /// Quota permit of the consumer holding this browser, released on drop
+    quota_permit: Option<OwnedSemaphorePermit>,
+    /// Identity of this claim, applied to new contexts and pages
+    identity: Option<Arc<dyn ContextIdentity>>,
+    /// Lease of this claim, if it was claimed for a limited time
+    lease: Option<Arc<Lease>>,
+    /// Task taking the browser back when the lease expires
+    lease_task: Option<tokio::task::JoinHandle<()>>,
 }
 
 impl PooledBrowser {
     /// Create a new browser context
-    pub async fn new_context(&self) -> Result<Arc<llama_moonlight_core::BrowserContext>> {
-        let context = self.browser.new_context().await?;
-        Ok(Arc::new(context))
+    ///
+    /// The context takes on the identity of this claim, if the pool has an
+    /// identity provider, and so do the pages opened in it.
+    pub async fn new_context(&self) -> Result<PooledContext> {
+        self.check_lease()?;
+        let context = if self.identity.is_some() {
+            let options = self.with_identity(ContextOptions::default());
+            self.browser.new_context_with_options(options).await?
+        } else {
+            self.browser.new_context().await?
+        };
+        Ok(self.wrap_context(context))
     }
 
     /// Create a new browser context with custom options
-    pub async fn new_context_with_options(
-        &self,
-        options: ContextOptions,
-    ) -> Result<Arc<llama_moonlight_core::BrowserContext>> {
-        let context = self.browser.new_context_with_options(options).await?;
-        Ok(Arc::new(context))
+    ///
+    /// The identity of this claim, if any, overrides the options it covers
+    /// and prepares the pages opened in the context.
+    pub async fn new_context_with_options(&self, options: ContextOptions) -> Result<PooledContext> {
+        self.check_lease()?;
+        let context = self.browser.new_context_with_options(self.with_identity(options)).await?;
+        Ok(self.wrap_context(context))
+    }
+
+    /// Open a page in a new context with the pool's context options
+    ///
+    /// The page records console messages, and is the one captured in the
+    /// artifacts if the session fails. It is prepared with the identity of
+    /// this claim, if any.
+    pub async fn new_page(&self) -> Result<Arc<Page>> {
+        self.check_lease()?;
+        let context = self
+            .new_context_with_options(self.pool.config.context_options.clone())
+            .await?;
+        let page = Arc::new(context.new_page().await?);
+
+        if let Err(e) = artifacts::install_console_capture(&page).await {
+            debug!("Failed to install console capture on page: {}", e);
+        }
+
+        *self.last_page.lock().unwrap() = Some(page.clone());
+        Ok(page)
+    }
+
+    /// Mark the browser as failed, capturing diagnostics
+    ///
+    /// The browser is recycled instead of returning to the pool when the
+    /// handle is dropped. The returned error carries the artifact directory.
+    pub async fn fail(&self, error: impl fmt::Display) -> PoolError {
+        if let Err(e) = self.check_lease() {
+            return e;
+        }
+
+        let page = self.last_page.lock().unwrap().clone();
+        self.pool
+            .mark_failed(&self.id, &error.to_string(), page.as_deref())
+            .await
     }
 
     /// Get the browser instance
@@ -162,10 +330,83 @@ impl PooledBrowser {
     pub fn id(&self) -> &str {
         &self.id
     }
+
+    /// Check whether this browser counts against a consumer's quota
+    pub fn has_quota_permit(&self) -> bool {
+        self.quota_permit.is_some()
+    }
+
+    /// Check that the pool has not taken the browser back
+    ///
+    /// Fails with [`PoolError::LeaseExpired`] once the lease of the claim
+    /// expired. Claims without a lease never expire.
+    pub fn check_lease(&self) -> Result<(), PoolError> {
+        match &self.lease {
+            Some(lease) if lease.is_expired() => Err(PoolError::LeaseExpired(self.id.clone())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Get the time left on the lease of this claim (`None` without a lease)
+    pub fn lease_remaining(&self) -> Option<Duration> {
+        self.lease
+            .as_ref()
+            .map(|lease| lease.deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Get the identity of this claim, if the pool has an identity provider
+    pub fn identity(&self) -> Option<Arc<dyn ContextIdentity>> {
+        self.identity.clone()
+    }
+
+    /// Wrap a context opened for this claim
+    fn wrap_context(&self, context: BrowserContext) -> PooledContext {
+        PooledContext {
+            context: Arc::new(context),
+            identity: self.identity.clone(),
+        }
+    }
+
+    /// Apply the identity of this claim to context options
+    fn with_identity(&self, mut options: ContextOptions) -> ContextOptions {
+        if let Some(identity) = &self.identity {
+            identity.apply_to_options(&mut options);
+        }
+        options
+    }
+
+    /// Limit this claim to `duration`, after which the pool takes the browser back
+    fn start_lease(&mut self, duration: Duration) {
+        let lease = Arc::new(Lease {
+            deadline: Instant::now() + duration,
+            state: AtomicU8::new(LEASE_ACTIVE),
+        });
+        let expiring = lease.clone();
+        let pool = self.pool.clone();
+        let id = self.id.clone();
+
+        self.lease_task = Some(tokio::spawn(async move {
+            tokio::time::sleep_until(expiring.deadline).await;
+            if expiring.end(LEASE_EXPIRED) {
+                pool.expire_lease(&id, duration).await;
+            }
+        }));
+        self.lease = Some(lease);
+    }
 }
 
 impl Drop for PooledBrowser {
     fn drop(&mut self) {
+        if let Some(lease) = &self.lease {
+            // An expired lease already took the browser back
+            if !lease.end(LEASE_RETURNED) {
+                return;
+            }
+            if let Some(task) = &self.lease_task {
+                task.abort();
+            }
+        }
+
         let id = self.id.clone();
         let pool = self.pool.clone();
 
@@ -178,6 +419,68 @@ impl Drop for PooledBrowser {
     }
 }
 
+/// A browser context opened through a [`PooledBrowser`]
+///
+/// Pages opened with [`new_page`](Self::new_page) or
+/// [`new_page_with_options`](Self::new_page_with_options) are prepared with
+/// the identity of the claim before they are returned. Everything else is
+/// reached through the wrapped [`BrowserContext`], which it dereferences to.
+#[derive(Clone)]
+pub struct PooledContext {
+    /// Wrapped context
+    context: Arc<BrowserContext>,
+    /// Identity of the claim the context was opened for
+    identity: Option<Arc<dyn ContextIdentity>>,
+}
+
+impl PooledContext {
+    /// Create a new page, prepared with the identity of the claim
+    pub async fn new_page(&self) -> Result<Page> {
+        let page = self.context.new_page().await?;
+        self.prepare(&page).await?;
+        Ok(page)
+    }
+
+    /// Create a new page with options, prepared with the identity of the claim
+    pub async fn new_page_with_options(&self, options: PageOptions) -> Result<Page> {
+        let page = self.context.new_page_with_options(options).await?;
+        self.prepare(&page).await?;
+        Ok(page)
+    }
+
+    /// Get the wrapped context
+    ///
+    /// Pages opened on it directly are not prepared with the identity.
+    pub fn context(&self) -> Arc<BrowserContext> {
+        self.context.clone()
+    }
+
+    /// Prepare a new page with the identity of the claim, if any
+    async fn prepare(&self, page: &Page) -> Result<()> {
+        if let Some(identity) = &self.identity {
+            identity.prepare_page(page).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Deref for PooledContext {
+    type Target = BrowserContext;
+
+    fn deref(&self) -> &BrowserContext {
+        &self.context
+    }
+}
+
+impl fmt::Debug for PooledContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PooledContext")
+            .field("context", &self.context.id())
+            .field("identity", &self.identity.is_some())
+            .finish()
+    }
+}
+
 /// A pool of browser instances for parallel automation
 pub struct BrowserPool {
     /// Map of browser ID to browser info
@@ -190,6 +493,18 @@ pub struct BrowserPool {
     config: PoolConfig,
     /// Maintenance task handle
     maintenance_task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    /// Quotas of consumers, shared between clones of the pool
+    consumer_quotas: Arc<ConsumerQuotas>,
+    /// Provider of a fresh identity per claim, shared between clones of the pool
+    identity_provider: Arc<std::sync::RwLock<Option<Arc<dyn IdentityProvider>>>>,
+    /// Claim wait times since the last maintenance run, shared between clones of the pool
+    claim_waits: Arc<std::sync::Mutex<WaitStats>>,
+    /// Autoscaler deciding the size kept warm, if autoscaling is enabled
+    autoscaler: Option<Arc<std::sync::Mutex<Autoscaler>>>,
+    /// Sender of the autoscaler's decisions
+    scale_events: broadcast::Sender<ScaleEvent>,
+    /// Sender of detected browser crashes
+    crash_events: broadcast::Sender<BrowserCrash>,
 }
 
 impl BrowserPool {
@@ -201,7 +516,19 @@ impl BrowserPool {
     /// Create a new browser pool with custom configuration
     pub async fn with_config(config: PoolConfig) -> Result<Arc<Self>> {
         // Initialize Moonlight
+        let consumer_quotas = ConsumerQuotas::new(
+            config.consumer_quota,
+            config.consumer_quotas.clone(),
+            config.quota_policy,
+        )?;
         let moonlight = Moonlight::new().await?;
+        let autoscaler = config.autoscale.clone().map(|autoscale| {
+            Arc::new(std::sync::Mutex::new(Autoscaler::new(
+                autoscale,
+                config.min_size,
+                config.max_size,
+            )))
+        });
 
         let pool = Arc::new(Self {
             browsers: DashMap::new(),
@@ -209,6 +536,12 @@ impl BrowserPool {
             moonlight: Arc::new(Mutex::new(moonlight)),
             config,
             maintenance_task: Mutex::new(None),
+            consumer_quotas: Arc::new(consumer_quotas),
+            identity_provider: Arc::new(std::sync::RwLock::new(None)),
+            claim_waits: Arc::new(std::sync::Mutex::new(WaitStats::default())),
+            autoscaler,
+            scale_events: broadcast::channel(16).0,
+            crash_events: broadcast::channel(16).0,
         });
 
         // Start maintenance task
@@ -268,8 +601,54 @@ impl BrowserPool {
             .count()
     }
 
+    /// Subscribe to the autoscaler's decisions
+    ///
+    /// Nothing is received unless `PoolConfig::autoscale` is set.
+    pub fn scale_events(&self) -> broadcast::Receiver<ScaleEvent> {
+        self.scale_events.subscribe()
+    }
+
+    /// Subscribe to browsers crashing
+    ///
+    /// Every browser is watched from its launch, so a crash is reported when
+    /// the process exits or its connection drops rather than when the next
+    /// claim tries to use it.
+    pub fn crash_events(&self) -> broadcast::Receiver<BrowserCrash> {
+        self.crash_events.subscribe()
+    }
+
     /// Get a browser from the pool
+    #[instrument(skip(self), fields({ FIELD_BROWSER_ID } = tracing::field::Empty))]
     pub async fn get_browser(&self) -> Result<PooledBrowser, PoolError> {
+        let started = Instant::now();
+        let result = self.claim_idle_or_new_browser().await;
+
+        // Failed claims tell the autoscaler the pool is too small
+        let mut claim_waits = self.claim_waits.lock().unwrap();
+        match &result {
+            Ok(_) => claim_waits.record(started.elapsed()),
+            Err(_) => claim_waits.record_failure(started.elapsed()),
+        }
+        drop(claim_waits);
+
+        result
+    }
+
+    /// Get a browser from the pool for at most `lease`
+    ///
+    /// If the handle is still held when the lease expires, the pool marks the
+    /// browser failed, captures its diagnostics and recycles it, and calls
+    /// through the handle fail with [`PoolError::LeaseExpired`]. This keeps
+    /// consumer tasks that hang or never drop their handle from holding on
+    /// to browsers forever.
+    pub async fn get_browser_leased(&self, lease: Duration) -> Result<PooledBrowser, PoolError> {
+        let mut browser = self.get_browser().await?;
+        browser.start_lease(lease);
+        Ok(browser)
+    }
+
+    /// Claim an idle browser, or a new one if there is none and room is left
+    async fn claim_idle_or_new_browser(&self) -> Result<PooledBrowser, PoolError> {
         // Try to find an idle browser
         let mut browser_id = None;
 
@@ -303,7 +682,144 @@ impl BrowserPool {
         Err(PoolError::NoBrowsersAvailable)
     }
 
+    /// Get a browser from the pool on behalf of a consumer
+    ///
+    /// The claim counts against the consumer's quota until the browser is
+    /// dropped. Beyond the quota, the claim waits or fails with
+    /// [`PoolError::QuotaExceeded`] depending on `PoolConfig::quota_policy`.
+    #[instrument(skip(self))]
+    pub async fn get_browser_for(&self, consumer: &str) -> Result<PooledBrowser, PoolError> {
+        let permit = self.consumer_quotas.acquire(consumer).await.map_err(|e| {
+            if self.config.enable_metrics {
+                counter!("browser_pool.quota_rejections", 1);
+            }
+            e
+        })?;
+
+        let mut browser = self.get_browser().await?;
+        browser.quota_permit = permit;
+        Ok(browser)
+    }
+
+    /// Give each claim of a browser a fresh identity from `provider`
+    ///
+    /// Contexts and pages opened through a [`PooledBrowser`] take on the
+    /// identity of its claim, so sessions reusing a browser do not share a
+    /// fingerprint. Browsers claimed before the call keep their identity.
+    pub fn set_identity_provider(&self, provider: impl IdentityProvider + 'static) {
+        *self.identity_provider.write().unwrap() = Some(Arc::new(provider));
+    }
+
+    /// Stop giving claims a fresh identity
+    pub fn clear_identity_provider(&self) {
+        *self.identity_provider.write().unwrap() = None;
+    }
+
+    /// Get the number of browsers a consumer currently holds
+    ///
+    /// Only consumers with a quota are tracked; others always report 0.
+    pub fn consumer_in_use(&self, consumer: &str) -> usize {
+        self.consumer_quotas.in_use(consumer)
+    }
+
+    /// Run a session on a pooled browser
+    ///
+    /// If the session returns an error, the browser is marked failed, its
+    /// diagnostics are captured, and the error is returned as
+    /// [`PoolError::SessionFailed`].
+    pub async fn with_browser<F, Fut, T>(&self, session: F) -> Result<T, PoolError>
+    where
+        F: FnOnce(Arc<PooledBrowser>) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let browser = self.get_browser().await?;
+        Self::run_session(browser, session).await
+    }
+
+    /// Run a session on a pooled browser on behalf of a consumer
+    ///
+    /// Like [`BrowserPool::with_browser`], with the claim counted against the
+    /// consumer's quota.
+    pub async fn with_browser_for<F, Fut, T>(&self, consumer: &str, session: F) -> Result<T, PoolError>
+    where
+        F: FnOnce(Arc<PooledBrowser>) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let browser = self.get_browser_for(consumer).await?;
+        Self::run_session(browser, session).await
+    }
+
+    /// Run a session, marking the browser failed if it returns an error
+    async fn run_session<F, Fut, T>(browser: PooledBrowser, session: F) -> Result<T, PoolError>
+    where
+        F: FnOnce(Arc<PooledBrowser>) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let browser = Arc::new(browser);
+
+        match session(browser.clone()).await {
+            Ok(value) => Ok(value),
+            Err(e) => Err(browser.fail(format!("{:#}", e)).await),
+        }
+    }
+
+    /// Mark a browser as failed and capture its diagnostics
+    async fn mark_failed(&self, browser_id: &str, message: &str, page: Option<&Page>) -> PoolError {
+        let (browser, browser_type) = match self.browsers.get_mut(browser_id) {
+            Some(mut entry) => {
+                let info = entry.value_mut();
+                info.status = BrowserStatus::Failed;
+                (info.browser.clone(), info.browser_type.clone())
+            }
+            None => return PoolError::InvalidBrowserId(browser_id.to_string()),
+        };
+
+        error!("Browser {} failed: {}", browser_id, message);
+
+        if self.config.enable_metrics {
+            counter!("browser_pool.failures", 1);
+        }
+
+        let artifacts = match &self.config.artifacts_dir {
+            Some(dir) => artifacts::capture(
+                dir,
+                browser_id,
+                &browser_type,
+                message,
+                &browser,
+                page,
+                self.config.artifact_tail_lines,
+            )
+            .await
+            .map_err(|e| warn!("Failed to capture artifacts of browser {}: {}", browser_id, e))
+            .ok(),
+            None => None,
+        };
+
+        PoolError::SessionFailed {
+            browser_id: browser_id.to_string(),
+            message: message.to_string(),
+            artifacts,
+        }
+    }
+
+    /// Take back a browser whose lease expired, recycling it
+    async fn expire_lease(&self, browser_id: &str, lease: Duration) {
+        warn!("Lease of browser {} expired after {:?}, recycling it", browser_id, lease);
+
+        if self.config.enable_metrics {
+            counter!("browser_pool.lease_expirations", 1);
+        }
+
+        self.mark_failed(browser_id, &format!("Lease expired after {:?}", lease), None)
+            .await;
+        if let Err(e) = self.recycle_browser(browser_id).await {
+            error!("Failed to recycle browser {} after its lease expired: {}", browser_id, e);
+        }
+    }
+
     /// Return a browser to the pool
+    #[instrument(skip(self))]
     async fn return_browser(&self, browser_id: &str) -> Result<(), PoolError> {
         let mut entry = match self.browsers.get_mut(browser_id) {
             Some(entry) => entry,
@@ -312,6 +828,22 @@ impl BrowserPool {
             }
         };
 
+        // Failed browsers are replaced rather than reused
+        if entry.value().status == BrowserStatus::Failed {
+            drop(entry);
+            debug!("Browser {} failed, scheduling recycling", browser_id);
+
+            let pool = Arc::new(self.clone());
+            let browser_id = browser_id.to_string();
+            tokio::spawn(async move {
+                if let Err(e) = pool.recycle_browser(&browser_id).await {
+                    error!("Failed to recycle browser {}: {}", browser_id, e);
+                }
+            });
+
+            return Ok(());
+        }
+
         // Update browser info
         let browser_info = entry.value_mut();
         browser_info.status = BrowserStatus::Idle;
@@ -350,6 +882,7 @@ impl BrowserPool {
 
     /// Claim a browser from the pool
     async fn claim_browser(&self, browser_id: &str) -> Result<PooledBrowser, PoolError> {
+        tracing::Span::current().record(FIELD_BROWSER_ID, browser_id);
         let mut entry = match self.browsers.get_mut(browser_id) {
             Some(entry) => entry,
             None => {
@@ -379,6 +912,16 @@ impl BrowserPool {
             browser: browser_info.browser.clone(),
             id: browser_id.to_string(),
             pool: Arc::new(self.clone()),
+            last_page: std::sync::Mutex::new(None),
+            quota_permit: None,
+            identity: self
+                .identity_provider
+                .read()
+                .unwrap()
+                .as_ref()
+                .map(|provider| provider.new_identity()),
+            lease: None,
+            lease_task: None,
         })
     }
 
@@ -450,15 +993,17 @@ impl BrowserPool {
         };
 
         // Update browser info with actual browser
+        let browser = Arc::new(browser);
         if let Some(mut entry) = self.browsers.get_mut(&browser_id) {
             let browser_info = entry.value_mut();
-            browser_info.browser = Arc::new(browser);
+            browser_info.browser = browser.clone();
             browser_info.status = BrowserStatus::Idle;
         } else {
             // This shouldn't happen, but just in case
             return Err(anyhow!("Browser ID {} not found in pool", browser_id));
         }
 
+        self.watch_for_crash(&browser_id, browser);
         info!("Browser {} created successfully", browser_id);
 
         if self.config.enable_metrics {
@@ -470,7 +1015,55 @@ impl BrowserPool {
         Ok(browser_id)
     }
 
+    /// Mark a browser failed as soon as its process exits or its connection drops
+    fn watch_for_crash(&self, browser_id: &str, browser: Arc<Browser>) {
+        let pool = Arc::new(self.clone());
+        let browser_id = browser_id.to_string();
+
+        tokio::spawn(async move {
+            // Browsers closed by the pool are not crashes
+            if let Some(reason) = browser.wait_for_exit().await {
+                pool.handle_crash(&browser_id, &reason).await;
+            }
+        });
+    }
+
+    /// Mark a crashed browser failed, publish the crash and replace the browser if idle
+    async fn handle_crash(&self, browser_id: &str, reason: &str) {
+        let status = match self.browsers.get(browser_id) {
+            Some(entry) => entry.value().status,
+            None => return,
+        };
+
+        // Browsers already failed or being closed are taken care of
+        if matches!(status, BrowserStatus::Failed | BrowserStatus::CleaningUp) {
+            return;
+        }
+
+        self.mark_failed(browser_id, &format!("Browser crashed: {}", reason), None)
+            .await;
+
+        if self.config.enable_metrics {
+            counter!("browser_pool.crashes", 1);
+        }
+
+        // Sending only fails without subscribers
+        let _ = self.crash_events.send(BrowserCrash {
+            browser_id: browser_id.to_string(),
+            reason: reason.to_string(),
+            status,
+        });
+
+        // Browsers in use are recycled when their handle is dropped
+        if status == BrowserStatus::Idle {
+            if let Err(e) = self.recycle_browser(browser_id).await {
+                error!("Failed to recycle crashed browser {}: {}", browser_id, e);
+            }
+        }
+    }
+
     /// Recycle a browser (close and create a new one)
+    #[instrument(skip(self))]
     async fn recycle_browser(&self, browser_id: &str) -> Result<()> {
         debug!("Recycling browser {}", browser_id);
 
@@ -516,9 +1109,10 @@ impl BrowserPool {
     /// Start the maintenance task
     fn start_maintenance_task(&self) {
         let pool = Arc::new(self.clone());
+        let period = Duration::from_secs(self.config.maintenance_interval_secs.max(1));
         
         let handle = tokio::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_secs(30));
+            let mut interval = tokio::time::interval(period);
             
             loop {
                 interval.tick().await;
@@ -537,6 +1131,9 @@ impl BrowserPool {
     async fn perform_maintenance(&self) -> Result<()> {
         debug!("Performing pool maintenance");
 
+        // Resize the pool before recycling so idle browsers above the new floor go first
+        let floor = self.autoscale().await;
+
         // Check for idle browsers that have been unused for too long
         let now = Instant::now();
         let max_idle_duration = Duration::from_secs(self.config.max_idle_time_secs);
@@ -548,7 +1145,7 @@ impl BrowserPool {
                 let info = pair.value();
                 if info.status == BrowserStatus::Idle
                     && now.duration_since(info.last_used) > max_idle_duration
-                    && self.browsers.len() > self.config.min_size
+                    && self.browsers.len() > floor
                 {
                     Some(info.id.clone())
                 } else {
@@ -568,10 +1165,10 @@ impl BrowserPool {
             }
         }
 
-        // Ensure we have at least min_size browsers
-        let shortfall = self.config.min_size.saturating_sub(self.browsers.len());
+        // Ensure we have at least min_size browsers, or the autoscaler's target
+        let shortfall = floor.saturating_sub(self.browsers.len());
         if shortfall > 0 {
-            info!("Creating {} browsers to maintain minimum pool size", shortfall);
+            info!("Creating {} browsers to maintain pool size of {}", shortfall, floor);
             
             let futures: Vec<_> = (0..shortfall).map(|_| self.create_browser()).collect();
             let results = future::join_all(futures).await;
@@ -579,12 +1176,18 @@ impl BrowserPool {
             let success_count = results.iter().filter(|r| r.is_ok()).count();
             if success_count < shortfall {
                 warn!(
-                    "Only created {}/{} browsers to maintain minimum pool size",
-                    success_count, shortfall
+                    "Only created {}/{} browsers to maintain pool size of {}",
+                    success_count, shortfall, floor
                 );
             }
         }
 
+        // Forget consumers that hold and wait for nothing
+        let evicted = self.consumer_quotas.evict_idle();
+        if evicted > 0 {
+            debug!("Stopped tracking {} idle consumers", evicted);
+        }
+
         if self.config.enable_metrics {
             gauge!("browser_pool.size", self.browsers.len() as f64);
             gauge!("browser_pool.available", self.available_count() as f64);
@@ -593,6 +1196,87 @@ impl BrowserPool {
         Ok(())
     }
 
+    /// Let the autoscaler resize the pool from the claim waits since the last run
+    ///
+    /// Returns the number of browsers to keep: the autoscaler's target, or
+    /// `min_size` without autoscaling.
+    async fn autoscale(&self) -> usize {
+        let (average_wait, failed_claims) = self.claim_waits.lock().unwrap().take();
+        if self.config.enable_metrics {
+            if let Some(wait) = average_wait {
+                gauge!("browser_pool.claim_wait_ms", wait.as_secs_f64() * 1000.0);
+            }
+        }
+
+        let autoscaler = match &self.autoscaler {
+            Some(autoscaler) => autoscaler,
+            None => return self.config.min_size,
+        };
+
+        let sample = PoolSample {
+            size: self.browsers.len(),
+            idle: self.available_count(),
+            average_wait,
+            failed_claims,
+            now: Instant::now(),
+        };
+        let (event, target) = {
+            let mut autoscaler = autoscaler.lock().unwrap();
+            (autoscaler.decide(sample), autoscaler.target())
+        };
+
+        if self.config.enable_metrics {
+            gauge!("browser_pool.autoscale.target_size", target as f64);
+        }
+
+        let event = match event {
+            Some(event) => event,
+            None => return target,
+        };
+
+        match &event {
+            ScaleEvent::Grow { from, to, average_wait_ms, failed_claims } => {
+                info!(
+                    "Growing pool from {} to {} browsers (average claim wait {} ms, {} failed claims)",
+                    from, to, average_wait_ms, failed_claims
+                );
+                if self.config.enable_metrics {
+                    counter!("browser_pool.autoscale.grow", 1);
+                }
+            }
+            ScaleEvent::Shrink { from, to, idle_secs } => {
+                info!("Shrinking pool from {} to {} browsers (idle for {} seconds)", from, to, idle_secs);
+                if self.config.enable_metrics {
+                    counter!("browser_pool.autoscale.shrink", 1);
+                }
+                self.close_idle_browsers_above(*to).await;
+            }
+        }
+
+        // Sending only fails without subscribers
+        let _ = self.scale_events.send(event);
+
+        target
+    }
+
+    /// Recycle the least recently used idle browsers until the pool is at most `size`
+    async fn close_idle_browsers_above(&self, size: usize) {
+        let mut idle: Vec<(Instant, String)> = self
+            .browsers
+            .iter()
+            .filter(|pair| pair.value().status == BrowserStatus::Idle)
+            .map(|pair| (pair.value().last_used, pair.key().clone()))
+            .collect();
+        idle.sort();
+
+        let excess = self.browsers.len().saturating_sub(size);
+        for (_, browser_id) in idle.into_iter().take(excess) {
+            if let Err(e) = self.recycle_browser(&browser_id).await {
+                warn!("Failed to recycle browser {} while shrinking: {}", browser_id, e);
+            }
+        }
+    }
+
     /// Close all browsers and shut down the pool
     pub async fn shutdown(&self) -> Result<()> {
         info!("Shutting down browser pool");
@@ -636,6 +1320,12 @@ impl Clone for BrowserPool {
             moonlight: self.moonlight.clone(),
             config: self.config.clone(),
             maintenance_task: Mutex::new(None),
+            consumer_quotas: self.consumer_quotas.clone(),
+            identity_provider: self.identity_provider.clone(),
+            claim_waits: self.claim_waits.clone(),
+            autoscaler: self.autoscaler.clone(),
+            scale_events: self.scale_events.clone(),
+            crash_events: self.crash_events.clone(),
         }
     }
 }
@@ -660,4 +1350,87 @@ impl Default for Browser {
     fn default() -> Self {
         unimplemented!("This is a placeholder and should never be called")
     }
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::SinkExt;
+    use llama_moonlight_core::launcher::LaunchTarget;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+    /// Start a DevTools endpoint answering every request with an empty result
+    ///
+    /// Sending on the returned channel drops the connections open at the
+    /// time, as a crashed browser would.
+    async fn fake_browser_endpoint() -> (String, broadcast::Sender<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+        let (drop_connections, _) = broadcast::channel(1);
+
+        let crash = drop_connections.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let mut crashed = crash.subscribe();
+                tokio::spawn(async move {
+                    let mut ws = accept_async(stream).await.unwrap();
+                    loop {
+                        tokio::select! {
+                            _ = crashed.recv() => return,
+                            message = ws.next() => match message {
+                                Some(Ok(Message::Text(text))) => {
+                                    let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+                                    let response = serde_json::json!({ "id": request["id"], "result": {} });
+                                    let _ = ws.send(Message::Text(response.to_string())).await;
+                                }
+                                Some(Ok(_)) => {}
+                                _ => return,
+                            },
+                        }
+                    }
+                });
+            }
+        });
+
+        (endpoint, drop_connections)
+    }
+
+    #[tokio::test]
+    async fn test_crashed_idle_browser_is_reported_and_recycled() {
+        let (endpoint, drop_connections) = fake_browser_endpoint().await;
+        let mut config = PoolConfig {
+            min_size: 1,
+            max_size: 2,
+            max_creation_retries: 1,
+            enable_metrics: false,
+            ..PoolConfig::default()
+        };
+        config.browser_options.launch_target = Some(LaunchTarget::Remote { endpoint });
+
+        let pool = BrowserPool::with_config(config).await.unwrap();
+        assert_eq!(pool.available_count(), 1);
+        let crashed_id = pool.browsers.iter().next().unwrap().key().clone();
+        let mut crashes = pool.crash_events();
+
+        drop_connections.send(()).unwrap();
+
+        let crash = tokio::time::timeout(Duration::from_secs(5), crashes.recv())
+            .await
+            .expect("no crash was reported")
+            .unwrap();
+        assert_eq!(crash.browser_id, crashed_id);
+        assert_eq!(crash.status, BrowserStatus::Idle);
+        assert!(crash.reason.contains("connection"));
+
+        // The crashed browser is replaced to keep the pool at its minimum size
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while pool.browsers.contains_key(&crashed_id) || pool.available_count() < 1 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("the crashed browser was not recycled");
+        assert_eq!(pool.size(), 1);
+    }
+}
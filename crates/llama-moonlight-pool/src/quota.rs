@@ -0,0 +1,190 @@
+//! Per-consumer quotas of concurrently held browsers.
+//!
+//! With [`PoolConfig::consumer_quota`](crate::PoolConfig::consumer_quota) or
+//! [`PoolConfig::consumer_quotas`](crate::PoolConfig::consumer_quotas) set,
+//! claims made through [`BrowserPool::get_browser_for`](crate::BrowserPool::get_browser_for)
+//! take a permit from their consumer's quota and hold it until the browser is
+//! dropped. Consumers are tracked from their first claim until a maintenance
+//! run finds them holding and waiting for nothing.
+
+use anyhow::{anyhow, Result};
+use dashmap::DashMap;
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::debug;
+
+use crate::PoolError;
+
+/// What happens to claims beyond a consumer's quota
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuotaPolicy {
+    /// Wait until one of the consumer's browsers is returned
+    #[default]
+    Queue,
+    /// Fail immediately with `PoolError::QuotaExceeded`
+    FailFast,
+}
+
+/// Quotas of the pool's consumers and the permits they hold
+#[derive(Debug)]
+pub(crate) struct ConsumerQuotas {
+    /// Quota of consumers without one of their own
+    default_quota: Option<usize>,
+    /// Quotas by consumer
+    quotas: HashMap<String, usize>,
+    /// What happens to claims beyond a quota
+    policy: QuotaPolicy,
+    /// Semaphores of the consumers currently tracked
+    semaphores: DashMap<String, Arc<Semaphore>>,
+}
+
+impl ConsumerQuotas {
+    /// Create the quotas, rejecting quotas of 0 which no claim could ever satisfy
+    pub(crate) fn new(default_quota: Option<usize>, quotas: HashMap<String, usize>, policy: QuotaPolicy) -> Result<Self> {
+        if default_quota == Some(0) {
+            return Err(anyhow!("The default consumer quota must be at least 1"));
+        }
+        if let Some((consumer, _)) = quotas.iter().find(|(_, quota)| **quota == 0) {
+            return Err(anyhow!("The quota of consumer '{}' must be at least 1", consumer));
+        }
+
+        Ok(Self {
+            default_quota,
+            quotas,
+            policy,
+            semaphores: DashMap::new(),
+        })
+    }
+
+    /// Get the quota of a consumer
+    pub(crate) fn quota_for(&self, consumer: &str) -> Option<usize> {
+        self.quotas.get(consumer).copied().or(self.default_quota)
+    }
+
+    /// Take a permit from a consumer's quota according to the quota policy
+    ///
+    /// Consumers without a quota get no permit.
+    pub(crate) async fn acquire(&self, consumer: &str) -> Result<Option<OwnedSemaphorePermit>, PoolError> {
+        let Some(quota) = self.quota_for(consumer) else {
+            return Ok(None);
+        };
+        let semaphore = self
+            .semaphores
+            .entry(consumer.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(quota)))
+            .clone();
+
+        let permit = match self.policy {
+            QuotaPolicy::FailFast => semaphore.try_acquire_owned().ok(),
+            QuotaPolicy::Queue => {
+                if semaphore.available_permits() == 0 {
+                    debug!("Consumer '{}' reached its quota, waiting for a browser to be returned", consumer);
+                }
+                semaphore.acquire_owned().await.ok()
+            }
+        };
+
+        permit
+            .map(Some)
+            .ok_or_else(|| PoolError::QuotaExceeded(consumer.to_string()))
+    }
+
+    /// Get the number of browsers a consumer currently holds
+    pub(crate) fn in_use(&self, consumer: &str) -> usize {
+        match (self.quota_for(consumer), self.semaphores.get(consumer)) {
+            (Some(quota), Some(semaphore)) => quota.saturating_sub(semaphore.available_permits()),
+            _ => 0,
+        }
+    }
+
+    /// Stop tracking consumers that hold no permit and have no claim waiting
+    ///
+    /// Permits and waiting claims each keep a reference to their consumer's
+    /// semaphore, so a semaphore referenced only by the map is idle.
+    /// Returns the number of consumers evicted.
+    pub(crate) fn evict_idle(&self) -> usize {
+        let before = self.semaphores.len();
+        self.semaphores.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+        before - self.semaphores.len()
+    }
+
+    /// Get the number of consumers currently tracked
+    pub(crate) fn tracked(&self) -> usize {
+        self.semaphores.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn quotas(policy: QuotaPolicy) -> ConsumerQuotas {
+        ConsumerQuotas::new(Some(2), HashMap::from([("batch".to_string(), 1)]), policy).unwrap()
+    }
+
+    #[test]
+    fn test_rejects_zero_quotas() {
+        assert!(ConsumerQuotas::new(Some(0), HashMap::new(), QuotaPolicy::Queue).is_err());
+        assert!(ConsumerQuotas::new(None, HashMap::from([("batch".to_string(), 0)]), QuotaPolicy::Queue).is_err());
+        assert!(ConsumerQuotas::new(None, HashMap::new(), QuotaPolicy::Queue).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_fail_fast_beyond_quota() {
+        let quotas = quotas(QuotaPolicy::FailFast);
+        assert_eq!(quotas.quota_for("batch"), Some(1));
+        assert_eq!(quotas.quota_for("anyone"), Some(2));
+
+        let first = quotas.acquire("batch").await.unwrap();
+        assert!(first.is_some());
+        assert_eq!(quotas.in_use("batch"), 1);
+        assert!(matches!(quotas.acquire("batch").await, Err(PoolError::QuotaExceeded(consumer)) if consumer == "batch"));
+
+        // Other consumers draw from their own quota
+        let _other = quotas.acquire("interactive").await.unwrap();
+        assert_eq!(quotas.in_use("interactive"), 1);
+
+        // Returning the browser frees the quota
+        drop(first);
+        assert_eq!(quotas.in_use("batch"), 0);
+        assert!(quotas.acquire("batch").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_queue_waits_for_returned_browser() {
+        let quotas = Arc::new(quotas(QuotaPolicy::Queue));
+        let first = quotas.acquire("batch").await.unwrap();
+
+        let waiting = {
+            let quotas = quotas.clone();
+            tokio::spawn(async move { quotas.acquire("batch").await.map(|permit| permit.is_some()) })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+
+        drop(first);
+        assert!(tokio::time::timeout(Duration::from_secs(1), waiting).await.unwrap().unwrap().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_evicts_idle_consumers() {
+        let quotas = ConsumerQuotas::new(None, HashMap::from([("batch".to_string(), 1)]), QuotaPolicy::FailFast).unwrap();
+
+        // Consumers without a quota are never tracked
+        assert!(quotas.acquire("anyone").await.unwrap().is_none());
+        assert_eq!(quotas.tracked(), 0);
+
+        let permit = quotas.acquire("batch").await.unwrap();
+        assert_eq!(quotas.tracked(), 1);
+
+        // A consumer holding a browser is kept
+        assert_eq!(quotas.evict_idle(), 0);
+        assert_eq!(quotas.in_use("batch"), 1);
+
+        drop(permit);
+        assert_eq!(quotas.evict_idle(), 1);
+        assert_eq!(quotas.tracked(), 0);
+        assert_eq!(quotas.in_use("batch"), 0);
+    }
+}
@@ -0,0 +1,40 @@
+-- Schema of the proxy database before versioned migrations.
+-- Tables are created only if missing so databases created before
+-- migrations existed are adopted as they are; later columns are added
+-- by the migrations that follow.
+
+CREATE TABLE IF NOT EXISTS proxies (
+    id TEXT PRIMARY KEY,
+    ip TEXT NOT NULL,
+    port INTEGER NOT NULL,
+    country TEXT,
+    anonymity TEXT,
+    https INTEGER NOT NULL,
+    last_checked TEXT,
+    response_time INTEGER,
+    weight REAL NOT NULL,
+    success_rate REAL NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS proxy_protocols (
+    proxy_id TEXT NOT NULL,
+    protocol TEXT NOT NULL,
+    PRIMARY KEY (proxy_id, protocol),
+    FOREIGN KEY (proxy_id) REFERENCES proxies(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS proxy_domain_stats (
+    proxy_id TEXT NOT NULL,
+    domain TEXT NOT NULL,
+    successes INTEGER NOT NULL,
+    blocks INTEGER NOT NULL,
+    captchas INTEGER NOT NULL,
+    consecutive_failures INTEGER NOT NULL,
+    last_outcome TEXT NOT NULL,
+    last_failure TEXT,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (proxy_id, domain),
+    FOREIGN KEY (proxy_id) REFERENCES proxies(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_proxies_ip_port ON proxies(ip, port);
@@ -0,0 +1,7 @@
+-- Network type of proxies, classified by the ASN of their exit IP.
+
+ALTER TABLE proxies ADD COLUMN class TEXT NOT NULL DEFAULT 'unknown';
+
+ALTER TABLE proxies ADD COLUMN asn INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_proxies_class ON proxies(class);
@@ -0,0 +1,7 @@
+-- HTTPS CONNECT support, TLS versions and certificate tampering of proxies.
+
+ALTER TABLE proxies ADD COLUMN tls_connect INTEGER;
+
+ALTER TABLE proxies ADD COLUMN tls_versions TEXT;
+
+ALTER TABLE proxies ADD COLUMN tls_tampered INTEGER;
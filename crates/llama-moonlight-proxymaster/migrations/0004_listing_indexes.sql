@@ -0,0 +1,9 @@
+-- Indexes for filtered and sorted proxy listings.
+
+CREATE INDEX IF NOT EXISTS idx_proxies_country ON proxies(country COLLATE NOCASE);
+
+CREATE INDEX IF NOT EXISTS idx_proxies_success_rate ON proxies(success_rate, id);
+
+CREATE INDEX IF NOT EXISTS idx_proxies_weight ON proxies(weight, id);
+
+CREATE INDEX IF NOT EXISTS idx_proxies_protocol ON proxy_protocols(protocol, proxy_id);
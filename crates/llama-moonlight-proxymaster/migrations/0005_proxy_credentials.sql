@@ -0,0 +1,5 @@
+-- Credentials of proxies that require authentication.
+
+ALTER TABLE proxies ADD COLUMN username TEXT;
+
+ALTER TABLE proxies ADD COLUMN password TEXT;
@@ -0,0 +1,5 @@
+-- City of the exit IP of proxies, next to their country and ASN.
+
+ALTER TABLE proxies ADD COLUMN city TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_proxies_asn ON proxies(asn);
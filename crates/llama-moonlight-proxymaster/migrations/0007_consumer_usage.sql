@@ -0,0 +1,13 @@
+-- Daily usage of proxies by consumers (API keys or caller tags), for
+-- chargeback and quotas.
+
+CREATE TABLE IF NOT EXISTS proxy_consumer_usage (
+    consumer TEXT NOT NULL,
+    proxy_id TEXT NOT NULL,
+    day TEXT NOT NULL,
+    requests INTEGER NOT NULL DEFAULT 0,
+    bytes INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (consumer, proxy_id, day)
+);
+
+CREATE INDEX IF NOT EXISTS idx_proxy_consumer_usage_day ON proxy_consumer_usage(day);
@@ -0,0 +1,178 @@
+//! API module.
+//! REST API over the proxy database.
+//!
+//! `GET /proxies` lists proxies one page at a time. Filters, sort and page
+//! come from the query string (see [`ProxyQuery`]), e.g.
+//! `/proxies?country=DE&protocol=socks5&sort=response_time&order=asc&limit=50`;
+//! the response is a [`ProxyPage`](crate::models::ProxyPage), whose
+//! `next_cursor` is passed as `cursor` to get the following page.
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use tracing::{error, info};
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+use crate::database;
+use crate::models::ProxyQuery;
+
+/// Body of an error response.
+#[derive(Debug, Clone, Serialize)]
+struct ApiError {
+    /// What went wrong.
+    error: String,
+}
+
+/// Returns the API routes, backed by the given database.
+pub fn routes(db: SqlitePool) -> impl Filter<Extract = impl Reply, Error = Infallible> + Clone {
+    let list = warp::path!("proxies")
+        .and(warp::get())
+        .and(warp::query::<ProxyQuery>())
+        .and(with_db(db))
+        .and_then(list_proxies);
+    
+    list.recover(handle_rejection)
+}
+
+/// Serves the API on an address until the task is cancelled.
+pub async fn serve(db: SqlitePool, addr: SocketAddr) {
+    info!("Serving the proxy API on {}", addr);
+    warp::serve(routes(db)).run(addr).await;
+}
+
+/// Passes the database to handlers.
+fn with_db(db: SqlitePool) -> impl Filter<Extract = (SqlitePool,), Error = Infallible> + Clone {
+    warp::any().map(move || db.clone())
+}
+
+/// Lists one page of proxies.
+async fn list_proxies(query: ProxyQuery, db: SqlitePool) -> Result<impl Reply, Rejection> {
+    if let Err(message) = query.validate() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, message));
+    }
+    
+    match database::list_proxies(&db, &query).await {
+        Ok(page) => Ok(warp::reply::with_status(warp::reply::json(&page), StatusCode::OK)),
+        Err(e) => {
+            error!("Failed to list proxies: {}", e);
+            Ok(error_reply(StatusCode::INTERNAL_SERVER_ERROR, "Failed to list proxies".to_string()))
+        },
+    }
+}
+
+/// Turns rejections into JSON errors.
+async fn handle_rejection(rejection: Rejection) -> Result<impl Reply, Infallible> {
+    let reply = if rejection.is_not_found() {
+        error_reply(StatusCode::NOT_FOUND, "Not found".to_string())
+    } else if let Some(e) = rejection.find::<warp::reject::InvalidQuery>() {
+        error_reply(StatusCode::BAD_REQUEST, e.to_string())
+    } else if rejection.find::<warp::reject::MethodNotAllowed>().is_some() {
+        error_reply(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed".to_string())
+    } else {
+        error!("Unhandled rejection: {:?}", rejection);
+        error_reply(StatusCode::INTERNAL_SERVER_ERROR, "Internal error".to_string())
+    };
+    
+    Ok(reply)
+}
+
+/// Builds a JSON error response.
+fn error_reply(status: StatusCode, error: String) -> warp::reply::WithStatus<warp::reply::Json> {
+    warp::reply::with_status(warp::reply::json(&ApiError { error }), status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{init_db, save_proxy};
+    use crate::models::Proxy;
+    use serde_json::Value;
+    use tempfile::tempdir;
+    
+    async fn get(db: &SqlitePool, path: &str) -> (StatusCode, Value) {
+        let response = warp::test::request().method("GET").path(path).reply(&routes(db.clone())).await;
+        (response.status(), serde_json::from_slice(response.body()).unwrap())
+    }
+    
+    #[tokio::test]
+    async fn test_list_proxies_route() {
+        let temp_dir = tempdir().unwrap();
+        let db_url = format!("sqlite:{}", temp_dir.path().join("test_db.sqlite").display());
+        let db = init_db(&db_url).await.unwrap();
+        
+        for i in 0..5u16 {
+            let mut proxy = Proxy::new(format!("10.0.0.{}", i), 8000 + i, false);
+            proxy.success_rate = i as f32 / 10.0;
+            if i >= 3 {
+                proxy.protocols = vec!["socks5".to_string()];
+            }
+            save_proxy(&db, &proxy).await.unwrap();
+        }
+        
+        let (status, page) = get(&db, "/proxies?limit=2").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(page["total"], 5);
+        assert_eq!(page["proxies"].as_array().unwrap().len(), 2);
+        assert_eq!(page["proxies"][0]["port"], 8004);
+        
+        // The cursor continues where the previous page ended
+        let cursor = page["next_cursor"].as_str().unwrap();
+        let (_, next) = get(&db, &format!("/proxies?limit=2&cursor={}", cursor)).await;
+        assert_eq!(next["proxies"][0]["port"], 8002);
+        
+        let (_, page) = get(&db, "/proxies?protocol=socks5&sort=success_rate&order=asc").await;
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["proxies"][0]["port"], 8003);
+        assert!(page.get("next_cursor").is_none());
+        
+        // Bad query strings are rejected with a JSON error
+        let (status, body) = get(&db, "/proxies?limit=many").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].is_string());
+        
+        let (status, body) = get(&db, &format!("/proxies?cursor={}&order=asc", cursor)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].as_str().unwrap().contains("cursor was issued for"));
+        
+        let (status, _) = get(&db, "/unknown").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+    
+    #[tokio::test]
+    async fn test_list_proxies_by_location() {
+        let temp_dir = tempdir().unwrap();
+        let db_url = format!("sqlite:{}", temp_dir.path().join("test_db.sqlite").display());
+        let db = init_db(&db_url).await.unwrap();
+        
+        let locations = [("DE", "Berlin", 3320), ("DE", "Munich", 3209), ("US", "Boston", 7922)];
+        for (i, (country, city, asn)) in locations.iter().enumerate() {
+            let mut proxy = Proxy::new(format!("10.0.1.{}", i), 8080, false);
+            proxy.country = Some(country.to_string());
+            proxy.city = Some(city.to_string());
+            proxy.asn = Some(*asn);
+            save_proxy(&db, &proxy).await.unwrap();
+        }
+        
+        let (status, page) = get(&db, "/proxies?country=DE").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(page["total"], 2);
+        assert!(page["proxies"].as_array().unwrap().iter().all(|proxy| proxy["country"] == "DE"));
+        
+        // Country and city match regardless of case
+        let (_, page) = get(&db, "/proxies?country=de&city=munich").await;
+        assert_eq!(page["total"], 1);
+        assert_eq!(page["proxies"][0]["city"], "Munich");
+        
+        let (_, page) = get(&db, "/proxies?asn=7922").await;
+        assert_eq!(page["total"], 1);
+        assert_eq!(page["proxies"][0]["country"], "US");
+        
+        let (_, page) = get(&db, "/proxies?country=FR").await;
+        assert_eq!(page["total"], 0);
+        
+        let (status, _) = get(&db, "/proxies?asn=AS3320").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,236 @@
+//! ASN module.
+//! Classifies proxies as datacenter, residential or mobile by the autonomous
+//! system their exit IP belongs to.
+//!
+//! The ASN of an IP comes from a lookup service such as ipinfo.io. The ASN is
+//! then matched against a bundled dataset of well-known networks, falling back
+//! to keywords in the network name for networks that are not listed.
+
+use crate::models::ProxyClass;
+use lazy_static::lazy_static;
+use reqwest::Client;
+use serde_json::Value;
+use std::collections::HashMap;
+use tracing::debug;
+
+/// Bundled dataset of network types by ASN.
+const BUNDLED_ASN_TYPES: &str = include_str!("../data/asn_types.csv");
+
+/// Network name keywords of hosting providers.
+const DATACENTER_KEYWORDS: &[&str] = &[
+    "hosting", "cloud", "data center", "datacenter", "server", "vps", "colo", "dedicated",
+];
+
+/// Network name keywords of cellular carriers.
+const MOBILE_KEYWORDS: &[&str] = &["mobile", "wireless", "cellular", "gsm", "lte"];
+
+/// Network name keywords of consumer ISPs.
+const RESIDENTIAL_KEYWORDS: &[&str] = &[
+    "broadband", "cable", "telecom", "dsl", "fiber", "fibre", "internet service",
+];
+
+lazy_static! {
+    static ref BUNDLED: AsnDatabase = AsnDatabase::parse(BUNDLED_ASN_TYPES);
+}
+
+/// Network types of autonomous systems.
+#[derive(Debug, Clone, Default)]
+pub struct AsnDatabase {
+    /// Class of each listed ASN.
+    classes: HashMap<u32, ProxyClass>,
+}
+
+impl AsnDatabase {
+    /// Returns the dataset bundled with the crate.
+    pub fn bundled() -> &'static AsnDatabase {
+        &BUNDLED
+    }
+    
+    /// Parses a dataset in the bundled CSV format.
+    ///
+    /// Each line is `asn,type,name` with type `hosting`, `isp` or `mobile`.
+    /// Comment lines starting with `#`, the header and malformed lines are skipped.
+    pub fn parse(csv: &str) -> Self {
+        let classes = csv
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, ',');
+                let asn = fields.next()?.trim().parse::<u32>().ok()?;
+                let class = ProxyClass::parse(fields.next()?);
+                (class != ProxyClass::Unknown).then_some((asn, class))
+            })
+            .collect();
+        
+        Self { classes }
+    }
+    
+    /// Returns the number of listed networks.
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+    
+    /// Returns whether the dataset is empty.
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+    
+    /// Returns the class of a listed ASN.
+    pub fn get(&self, asn: u32) -> Option<ProxyClass> {
+        self.classes.get(&asn).copied()
+    }
+    
+    /// Classifies a network by its ASN, or by its name if the ASN is not listed.
+    pub fn classify(&self, asn: u32, name: Option<&str>) -> ProxyClass {
+        if let Some(class) = self.get(asn) {
+            return class;
+        }
+        
+        let name = match name {
+            Some(name) => name.to_lowercase(),
+            None => return ProxyClass::Unknown,
+        };
+        let matches = |keywords: &[&str]| keywords.iter().any(|k| name.contains(k));
+        
+        if matches(DATACENTER_KEYWORDS) {
+            ProxyClass::Datacenter
+        } else if matches(MOBILE_KEYWORDS) {
+            ProxyClass::Mobile
+        } else if matches(RESIDENTIAL_KEYWORDS) {
+            ProxyClass::Residential
+        } else {
+            ProxyClass::Unknown
+        }
+    }
+}
+
+/// Autonomous system of an IP address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsnInfo {
+    /// Autonomous system number.
+    pub asn: u32,
+    
+    /// Name of the network (if reported).
+    pub name: Option<String>,
+}
+
+/// Looks up the autonomous system of an IP address.
+///
+/// `url_template` is the lookup URL with `{ip}` in place of the address. The
+/// response formats of ipinfo.io, ip-api.com and similar services are
+/// understood. Lookups are best-effort: failures are logged and yield `None`.
+pub async fn lookup_asn(client: &Client, url_template: &str, ip: &str) -> Option<AsnInfo> {
+    parse_asn_response(&fetch_lookup(client, url_template, ip).await?)
+}
+
+/// Fetches the JSON response of a lookup service for an IP address.
+pub(crate) async fn fetch_lookup(client: &Client, url_template: &str, ip: &str) -> Option<Value> {
+    let url = url_template.replace("{ip}", ip);
+    
+    let response = match client.get(&url).send().await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => {
+            debug!("IP lookup for {} returned {}", ip, response.status());
+            return None;
+        }
+        Err(e) => {
+            debug!("IP lookup for {} failed: {}", ip, e);
+            return None;
+        }
+    };
+    
+    match response.json::<Value>().await {
+        Ok(body) => Some(body),
+        Err(e) => {
+            debug!("Invalid IP lookup response for {}: {}", ip, e);
+            None
+        }
+    }
+}
+
+/// Extracts the autonomous system from a lookup service response.
+///
+/// Accepts `"org"` or `"as"` fields of the form `"AS15169 Google LLC"`, and
+/// `"asn"` fields holding a number, a string like that, or an object with `asn`
+/// and `name` fields.
+pub fn parse_asn_response(body: &Value) -> Option<AsnInfo> {
+    match body.get("asn") {
+        Some(Value::Number(asn)) => {
+            let name = ["as_name", "org", "isp"]
+                .iter()
+                .find_map(|field| body.get(*field).and_then(Value::as_str))
+                .map(str::to_string);
+            return Some(AsnInfo { asn: u32::try_from(asn.as_u64()?).ok()?, name });
+        }
+        Some(Value::String(asn)) => return parse_as_field(asn),
+        Some(Value::Object(asn)) => {
+            let mut info = parse_as_field(asn.get("asn")?.as_str()?)?;
+            info.name = asn.get("name").and_then(Value::as_str).map(str::to_string).or(info.name);
+            return Some(info);
+        }
+        _ => {}
+    }
+    
+    ["as", "org"]
+        .iter()
+        .find_map(|field| body.get(*field).and_then(Value::as_str).and_then(parse_as_field))
+}
+
+/// Parses an `"AS15169 Google LLC"` style field.
+fn parse_as_field(field: &str) -> Option<AsnInfo> {
+    let field = field.trim();
+    let rest = field.strip_prefix("AS").or_else(|| field.strip_prefix("as"))?;
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    let asn = rest[..digits].parse().ok()?;
+    let name = rest[digits..].trim();
+    
+    Some(AsnInfo {
+        asn,
+        name: (!name.is_empty()).then(|| name.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    #[test]
+    fn test_bundled_database() {
+        let db = AsnDatabase::bundled();
+        assert!(db.len() > 50);
+        assert_eq!(db.get(16509), Some(ProxyClass::Datacenter));
+        assert_eq!(db.get(7922), Some(ProxyClass::Residential));
+        assert_eq!(db.get(21928), Some(ProxyClass::Mobile));
+        assert_eq!(db.get(1), None);
+    }
+    
+    #[test]
+    fn test_classify_by_name() {
+        let db = AsnDatabase::parse("asn,type,name\n64500,isp,Example ISP\n");
+        assert_eq!(db.classify(64500, Some("Example Cloud Hosting")), ProxyClass::Residential);
+        assert_eq!(db.classify(64501, Some("Example Cloud Hosting")), ProxyClass::Datacenter);
+        assert_eq!(db.classify(64502, Some("Example Wireless")), ProxyClass::Mobile);
+        assert_eq!(db.classify(64503, Some("Example Broadband")), ProxyClass::Residential);
+        assert_eq!(db.classify(64504, Some("Example")), ProxyClass::Unknown);
+        assert_eq!(db.classify(64505, None), ProxyClass::Unknown);
+    }
+    
+    #[test]
+    fn test_parse_asn_response() {
+        let google = AsnInfo { asn: 15169, name: Some("Google LLC".to_string()) };
+        
+        // ipinfo.io
+        assert_eq!(parse_asn_response(&json!({ "ip": "8.8.8.8", "org": "AS15169 Google LLC" })), Some(google.clone()));
+        // ip-api.com
+        assert_eq!(parse_asn_response(&json!({ "as": "AS15169 Google LLC", "hosting": true })), Some(google.clone()));
+        // ipinfo.io with the ASN object
+        assert_eq!(parse_asn_response(&json!({ "asn": { "asn": "AS15169", "name": "Google LLC" } })), Some(google.clone()));
+        // Numeric ASN
+        assert_eq!(parse_asn_response(&json!({ "asn": 15169, "as_name": "Google LLC" })), Some(google));
+        
+        assert_eq!(parse_asn_response(&json!({ "org": "Google LLC" })), None);
+        assert_eq!(parse_asn_response(&json!({ "bogon": true })), None);
+    }
+}
@@ -0,0 +1,27 @@
+//! Self-hosted proxy judge.
+//!
+//! Usage: `proxymaster-judge [ADDR]`, where the address defaults to the
+//! `PROXYMASTER_JUDGE_ADDR` environment variable or `0.0.0.0:8899`.
+
+use llama_moonlight_proxymaster::judge;
+use std::net::SocketAddr;
+use tracing_subscriber::EnvFilter;
+
+/// Address the judge listens on by default.
+const DEFAULT_ADDR: &str = "0.0.0.0:8899";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .init();
+    
+    let addr: SocketAddr = std::env::args()
+        .nth(1)
+        .or_else(|| std::env::var("PROXYMASTER_JUDGE_ADDR").ok())
+        .unwrap_or_else(|| DEFAULT_ADDR.to_string())
+        .parse()?;
+    
+    judge::serve(addr).await;
+    Ok(())
+}
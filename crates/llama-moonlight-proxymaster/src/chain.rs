@@ -0,0 +1,489 @@
+//! Chain module.
+//! Composes proxies into a chain served as a single local proxy.
+//!
+//! A [`ProxyChain`] opens tunnels hop by hop: it connects to the first
+//! proxy, asks it for a tunnel to the second, asks the second through that
+//! tunnel for one to the third, and so on, until the last proxy tunnels to
+//! the target. Requests thus enter through the first proxy, e.g. a fast
+//! datacenter one, and leave through the last, e.g. a residential one. Each
+//! hop speaks its own protocol, so SOCKS and HTTP proxies can be mixed.
+//!
+//! HTTP clients and browsers take a single proxy URL, so
+//! [`ProxyChain::serve`] exposes the chain as a local HTTP proxy that
+//! tunnels CONNECT requests and forwards plain HTTP requests through it.
+
+use crate::models::{Proxy, ProxyProtocol};
+use crate::socks::{socks4_connect, socks5_connect};
+use crate::tls::http_connect;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+use tracing::{debug, info, warn};
+
+/// Longest request header accepted by the local endpoint.
+const MAX_REQUEST_HEAD: usize = 64 * 1024;
+
+/// Default time allowed to open a tunnel through the whole chain.
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Errors that can occur when building or serving a proxy chain.
+#[derive(Error, Debug)]
+pub enum ChainError {
+    /// Invalid chain.
+    #[error("Invalid proxy chain: {0}")]
+    InvalidChain(String),
+    
+    /// Failed to bind or serve the local endpoint.
+    #[error("Proxy chain I/O error: {0}")]
+    Io(#[from] io::Error),
+    
+    /// Failed to build a proxy for an HTTP client.
+    #[error("Failed to build proxy: {0}")]
+    Proxy(#[from] reqwest::Error),
+}
+
+/// A proxy of a chain, with the protocol it is spoken to in.
+#[derive(Debug, Clone)]
+pub struct ChainHop {
+    /// Proxy of the hop.
+    pub proxy: Proxy,
+    
+    /// Protocol the hop is asked for a tunnel in.
+    pub protocol: ProxyProtocol,
+}
+
+impl ChainHop {
+    /// Creates a hop using the preferred protocol of the proxy.
+    pub fn new(proxy: Proxy) -> Self {
+        let protocol = proxy.protocol();
+        Self { proxy, protocol }
+    }
+    
+    /// Creates a hop speaking a given protocol to the proxy.
+    pub fn with_protocol(proxy: Proxy, protocol: ProxyProtocol) -> Self {
+        Self { proxy, protocol }
+    }
+    
+    /// Asks the hop, connected to over `stream`, for a tunnel to `host:port`.
+    async fn open(&self, stream: &mut TcpStream, host: &str, port: u16) -> io::Result<()> {
+        match self.protocol {
+            ProxyProtocol::Http => http_connect(stream, &self.proxy, host, port).await,
+            ProxyProtocol::Socks4 => socks4_connect(stream, &self.proxy, host, port).await,
+            ProxyProtocol::Socks5 => socks5_connect(stream, &self.proxy, host, port).await,
+        }
+    }
+}
+
+/// Two or more proxies that requests pass through in order.
+#[derive(Debug, Clone)]
+pub struct ProxyChain {
+    /// Hops from the entry to the exit proxy.
+    hops: Vec<ChainHop>,
+    
+    /// Time allowed to open a tunnel through the whole chain.
+    connect_timeout: Duration,
+}
+
+impl ProxyChain {
+    /// Creates a chain of proxies, from the entry to the exit proxy, each
+    /// spoken to in its preferred protocol.
+    pub fn new(proxies: Vec<Proxy>) -> Result<Self, ChainError> {
+        Self::from_hops(proxies.into_iter().map(ChainHop::new).collect())
+    }
+    
+    /// Creates a chain of hops, from the entry to the exit hop.
+    pub fn from_hops(hops: Vec<ChainHop>) -> Result<Self, ChainError> {
+        if hops.len() < 2 {
+            return Err(ChainError::InvalidChain(format!(
+                "a chain needs at least 2 proxies, got {}",
+                hops.len()
+            )));
+        }
+        
+        Ok(Self {
+            hops,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+        })
+    }
+    
+    /// Sets the time allowed to open a tunnel through the whole chain.
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+    
+    /// Returns the hops of the chain, from the entry to the exit hop.
+    pub fn hops(&self) -> &[ChainHop] {
+        &self.hops
+    }
+    
+    /// Returns the proxy requests enter the chain through.
+    pub fn entry(&self) -> &Proxy {
+        &self.hops[0].proxy
+    }
+    
+    /// Returns the proxy requests leave the chain through.
+    pub fn exit(&self) -> &Proxy {
+        &self.hops[self.hops.len() - 1].proxy
+    }
+    
+    /// Opens a tunnel to `host:port` through every hop of the chain.
+    pub async fn connect(&self, host: &str, port: u16) -> io::Result<TcpStream> {
+        match timeout(self.connect_timeout, self.open_tunnel(host, port)).await {
+            Ok(result) => result,
+            Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "Tunnel through proxy chain timed out")),
+        }
+    }
+    
+    /// Opens a tunnel through every hop, without a time limit.
+    async fn open_tunnel(&self, host: &str, port: u16) -> io::Result<TcpStream> {
+        let entry = self.entry();
+        let mut stream = TcpStream::connect((entry.ip.as_str(), entry.port)).await?;
+        
+        for (index, hop) in self.hops.iter().enumerate() {
+            let (next_host, next_port) = match self.hops.get(index + 1) {
+                Some(next) => (next.proxy.ip.as_str(), next.proxy.port),
+                None => (host, port),
+            };
+            hop.open(&mut stream, next_host, next_port).await.map_err(|e| {
+                io::Error::new(e.kind(), format!("Hop {} ({} {}) failed: {}", index + 1, hop.protocol, hop.proxy, e))
+            })?;
+        }
+        
+        Ok(stream)
+    }
+    
+    /// Serves the chain as an HTTP proxy on a local port chosen by the system.
+    pub async fn serve_local(self) -> Result<ChainEndpoint, ChainError> {
+        self.serve(SocketAddr::from(([127, 0, 0, 1], 0))).await
+    }
+    
+    /// Serves the chain as an HTTP proxy on an address.
+    ///
+    /// CONNECT requests are tunneled through the chain, and plain HTTP
+    /// requests forwarded through it, one request per connection. The
+    /// endpoint has no authentication, so bind it to a loopback address.
+    pub async fn serve(self, addr: SocketAddr) -> Result<ChainEndpoint, ChainError> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        info!("Serving proxy chain {} on {}", self, addr);
+        
+        let chain = Arc::new(self);
+        let task = tokio::spawn(async move {
+            loop {
+                let (client, peer) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!("Failed to accept connection to proxy chain: {}", e);
+                        continue;
+                    }
+                };
+                
+                let chain = chain.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_client(&chain, client).await {
+                        debug!("Proxy chain connection from {} failed: {}", peer, e);
+                    }
+                });
+            }
+        });
+        
+        Ok(ChainEndpoint { addr, task })
+    }
+}
+
+impl fmt::Display for ProxyChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hops: Vec<String> = self.hops.iter().map(|hop| format!("{}://{}", hop.protocol, hop.proxy)).collect();
+        f.write_str(&hops.join(" -> "))
+    }
+}
+
+/// Local HTTP proxy serving a chain.
+///
+/// Dropping the endpoint stops accepting connections; tunnels already open
+/// stay open.
+#[derive(Debug)]
+pub struct ChainEndpoint {
+    /// Address the endpoint listens on.
+    addr: SocketAddr,
+    
+    /// Task accepting connections.
+    task: JoinHandle<()>,
+}
+
+impl ChainEndpoint {
+    /// Returns the address the endpoint listens on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+    
+    /// Returns the URL of the endpoint, e.g. for a browser's `--proxy-server`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+    
+    /// Returns the endpoint as a proxy, for code that takes pool proxies.
+    pub fn as_proxy(&self) -> Proxy {
+        let mut proxy = Proxy::new(self.addr.ip().to_string(), self.addr.port(), true);
+        proxy.protocols = vec![ProxyProtocol::Http.as_str().to_string()];
+        proxy
+    }
+    
+    /// Builds a proxy for a reqwest client sending all requests through the chain.
+    pub fn to_reqwest(&self) -> Result<reqwest::Proxy, ChainError> {
+        Ok(reqwest::Proxy::all(self.url())?)
+    }
+}
+
+impl Drop for ChainEndpoint {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Serves one client connection of the local endpoint.
+async fn handle_client(chain: &ProxyChain, mut client: TcpStream) -> io::Result<()> {
+    let (head, body) = match read_head(&mut client).await? {
+        Some(request) => request,
+        None => return Ok(()),
+    };
+    let request = match ProxyRequest::parse(&head) {
+        Some(request) => request,
+        None => return respond(&mut client, "400 Bad Request").await,
+    };
+    
+    let mut upstream = match chain.connect(&request.host, request.port).await {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            warn!("Failed to open tunnel to {}:{} through {}: {}", request.host, request.port, chain, e);
+            return respond(&mut client, "502 Bad Gateway").await;
+        }
+    };
+    
+    match &request.forward {
+        None => client.write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n").await?,
+        Some(forward) => upstream.write_all(forward.as_bytes()).await?,
+    }
+    upstream.write_all(&body).await?;
+    
+    tokio::io::copy_bidirectional(&mut client, &mut upstream).await?;
+    Ok(())
+}
+
+/// Reads a request header, returning it and the bytes read past it.
+///
+/// Returns `None` if the client closes the connection first.
+async fn read_head(client: &mut TcpStream) -> io::Result<Option<(String, Vec<u8>)>> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        if let Some(end) = buf.windows(4).position(|window| window == b"\r\n\r\n") {
+            let body = buf.split_off(end + 4);
+            return Ok(Some((String::from_utf8_lossy(&buf).into_owned(), body)));
+        }
+        if buf.len() > MAX_REQUEST_HEAD {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Request header too long"));
+        }
+        
+        let n = client.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+}
+
+/// Writes a response without a body and closes the connection.
+async fn respond(client: &mut TcpStream, status: &str) -> io::Result<()> {
+    client
+        .write_all(format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).as_bytes())
+        .await
+}
+
+/// Request received by the local endpoint.
+#[derive(Debug, PartialEq, Eq)]
+struct ProxyRequest {
+    /// Host to open a tunnel to.
+    host: String,
+    
+    /// Port to open a tunnel to.
+    port: u16,
+    
+    /// Request header to send through the tunnel, for plain HTTP requests.
+    forward: Option<String>,
+}
+
+impl ProxyRequest {
+    /// Parses the header of a CONNECT request or of a plain HTTP request
+    /// with an absolute URL.
+    fn parse(head: &str) -> Option<Self> {
+        let mut lines = head.split("\r\n");
+        let mut request_line = lines.next()?.split_whitespace();
+        let (method, target, version) = (request_line.next()?, request_line.next()?, request_line.next()?);
+        
+        if method.eq_ignore_ascii_case("CONNECT") {
+            let (host, port) = target.rsplit_once(':')?;
+            return Some(Self {
+                host: host.trim_matches(|c| c == '[' || c == ']').to_string(),
+                port: port.parse().ok()?,
+                forward: None,
+            });
+        }
+        
+        let url = reqwest::Url::parse(target).ok()?;
+        if url.scheme() != "http" {
+            return None;
+        }
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        
+        // One request per connection, as later ones could be for other hosts
+        let mut forward = format!("{} {} {}\r\n", method, path, version);
+        for line in lines.filter(|line| !line.is_empty()) {
+            let name = line.split(':').next().unwrap_or_default().trim();
+            if ["proxy-connection", "proxy-authorization", "connection", "keep-alive"]
+                .iter()
+                .any(|hop_header| name.eq_ignore_ascii_case(hop_header))
+            {
+                continue;
+            }
+            forward.push_str(line);
+            forward.push_str("\r\n");
+        }
+        forward.push_str("Connection: close\r\n\r\n");
+        
+        Some(Self {
+            host: url.host_str()?.trim_matches(|c| c == '[' || c == ']').to_string(),
+            port: url.port_or_known_default()?,
+            forward: Some(forward),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Opens a connection to the target a proxy was asked for, and relays the client to it.
+    async fn relay(mut client: TcpStream, host: String, port: u16) {
+        if let Ok(mut target) = TcpStream::connect((host.as_str(), port)).await {
+            let _ = tokio::io::copy_bidirectional(&mut client, &mut target).await;
+        }
+    }
+    
+    /// Starts a fake HTTP proxy granting every CONNECT request.
+    async fn http_hop() -> Proxy {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut client, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let (head, _) = read_head(&mut client).await.unwrap().unwrap();
+                    let request = ProxyRequest::parse(&head).unwrap();
+                    client.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n").await.unwrap();
+                    relay(client, request.host, request.port).await;
+                });
+            }
+        });
+        let mut proxy = Proxy::new("127.0.0.1".to_string(), port, true);
+        proxy.protocols = vec!["http".to_string()];
+        proxy
+    }
+    
+    /// Starts a fake SOCKS5 proxy granting every IPv4 CONNECT request.
+    async fn socks5_hop() -> Proxy {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut client, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut greeting = [0u8; 3];
+                    client.read_exact(&mut greeting).await.unwrap();
+                    client.write_all(&[5, 0]).await.unwrap();
+                    
+                    let mut request = [0u8; 10];
+                    client.read_exact(&mut request).await.unwrap();
+                    assert_eq!(request[..4], [5, 1, 0, 1]);
+                    let host = std::net::Ipv4Addr::new(request[4], request[5], request[6], request[7]).to_string();
+                    let port = u16::from_be_bytes([request[8], request[9]]);
+                    client.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]).await.unwrap();
+                    relay(client, host, port).await;
+                });
+            }
+        });
+        let mut proxy = Proxy::new("127.0.0.1".to_string(), port, false);
+        proxy.protocols = vec!["socks5".to_string()];
+        proxy
+    }
+    
+    /// Starts a server answering every request with `ok`.
+    async fn origin() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut client, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let _ = read_head(&mut client).await;
+                    let _ = client.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok").await;
+                });
+            }
+        });
+        port
+    }
+    
+    #[test]
+    fn test_parse_request() {
+        let connect = ProxyRequest::parse("CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\r\n").unwrap();
+        assert_eq!((connect.host.as_str(), connect.port, connect.forward), ("example.com", 443, None));
+        
+        let get = ProxyRequest::parse(
+            "GET http://example.com/a?b=1 HTTP/1.1\r\nHost: example.com\r\nProxy-Connection: keep-alive\r\nAccept: */*\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!((get.host.as_str(), get.port), ("example.com", 80));
+        assert_eq!(
+            get.forward.unwrap(),
+            "GET /a?b=1 HTTP/1.1\r\nHost: example.com\r\nAccept: */*\r\nConnection: close\r\n\r\n"
+        );
+        
+        assert!(ProxyRequest::parse("GET /relative HTTP/1.1\r\n\r\n").is_none());
+        assert!(ProxyChain::new(vec![Proxy::new("10.0.0.1".to_string(), 8080, false)]).is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_chain_endpoint() {
+        let chain = ProxyChain::new(vec![socks5_hop().await, http_hop().await]).unwrap();
+        assert!(chain.to_string().starts_with("socks5://127.0.0.1:"));
+        let endpoint = chain.serve_local().await.unwrap();
+        let port = origin().await;
+        
+        // Plain HTTP requests are forwarded through both hops
+        let client = reqwest::Client::builder().proxy(endpoint.to_reqwest().unwrap()).build().unwrap();
+        let body = client.get(format!("http://127.0.0.1:{}/", port)).send().await.unwrap().text().await.unwrap();
+        assert_eq!(body, "ok");
+        
+        // CONNECT requests are tunneled through both hops
+        let mut stream = TcpStream::connect(endpoint.addr()).await.unwrap();
+        stream.write_all(format!("CONNECT 127.0.0.1:{} HTTP/1.1\r\n\r\n", port).as_bytes()).await.unwrap();
+        let (head, _) = read_head(&mut stream).await.unwrap().unwrap();
+        assert!(head.starts_with("HTTP/1.1 200"));
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.ends_with("\r\n\r\nok"));
+        
+        // A dead exit hop fails the tunnel rather than the endpoint
+        let dead = ProxyChain::new(vec![socks5_hop().await, Proxy::new("127.0.0.1".to_string(), 1, true)]).unwrap();
+        assert!(dead.connect("127.0.0.1", port).await.is_err());
+    }
+}
@@ -1,13 +1,28 @@
 //! Database module.
 //! Handles database initialization and operations.
 
-use crate::models::Proxy;
-use log::{debug, error, info};
-use sqlx::{migrate::MigrateDatabase, sqlite::SqlitePoolOptions, Pool, Sqlite, SqlitePool};
+use crate::models::{
+    ConsumerUsage, DomainStats, Outcome, Proxy, ProxyClass, ProxyCursor, ProxyPage, ProxyQuery, ProxySort, SortOrder,
+    TlsCapabilities,
+};
+use tracing::{debug, error, info};
+use sqlx::{
+    migrate::{MigrateDatabase, Migrator},
+    sqlite::{SqlitePoolOptions, SqliteRow},
+    Pool, QueryBuilder, Row, Sqlite, SqlitePool,
+};
+use std::collections::HashMap;
 use std::time::Duration;
 use uuid::Uuid;
 
-/// Initializes the database and returns a connection pool.
+/// Versioned schema migrations, embedded from the `migrations` directory.
+///
+/// New columns and indexes go in a new migration file rather than in an
+/// existing one, which databases that already applied it would never run.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Initializes the database, applying pending migrations, and returns a
+/// connection pool.
 pub async fn init_db(database_url: &str) -> Result<SqlitePool, sqlx::Error> {
     // Create database if it doesn't exist
     if !Sqlite::database_exists(database_url).await.unwrap_or(false) {
@@ -22,47 +37,8 @@ pub async fn init_db(database_url: &str) -> Result<SqlitePool, sqlx::Error> {
         .connect(database_url)
         .await?;
     
-    // Create tables if they don't exist
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS proxies (
-            id TEXT PRIMARY KEY,
-            ip TEXT NOT NULL,
-            port INTEGER NOT NULL,
-            country TEXT,
-            anonymity TEXT,
-            https INTEGER NOT NULL,
-            last_checked TEXT,
-            response_time INTEGER,
-            weight REAL NOT NULL,
-            success_rate REAL NOT NULL
-        )
-        "#,
-    )
-    .execute(&pool)
-    .await?;
-    
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS proxy_protocols (
-            proxy_id TEXT NOT NULL,
-            protocol TEXT NOT NULL,
-            PRIMARY KEY (proxy_id, protocol),
-            FOREIGN KEY (proxy_id) REFERENCES proxies(id) ON DELETE CASCADE
-        )
-        "#,
-    )
-    .execute(&pool)
-    .await?;
-    
-    // Create index on ip and port
-    sqlx::query(
-        r#"
-        CREATE INDEX IF NOT EXISTS idx_proxies_ip_port ON proxies(ip, port)
-        "#,
-    )
-    .execute(&pool)
-    .await?;
+    // Apply any migrations the database has not seen yet
+    MIGRATOR.run(&pool).await?;
     
     Ok(pool)
 }
@@ -76,31 +52,48 @@ pub async fn save_proxy(pool: &SqlitePool, proxy: &Proxy) -> Result<(), sqlx::Er
     sqlx::query(
         r#"
         INSERT INTO proxies (
-            id, ip, port, country, anonymity, https, last_checked, 
-            response_time, weight, success_rate
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            id, ip, port, country, city, anonymity, https, last_checked, 
+            response_time, weight, success_rate, class, asn,
+            tls_connect, tls_versions, tls_tampered, username, password
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         ON CONFLICT(id) DO UPDATE SET
             ip = excluded.ip,
             port = excluded.port,
             country = excluded.country,
+            city = excluded.city,
             anonymity = excluded.anonymity,
             https = excluded.https,
             last_checked = excluded.last_checked,
             response_time = excluded.response_time,
             weight = excluded.weight,
-            success_rate = excluded.success_rate
+            success_rate = excluded.success_rate,
+            class = excluded.class,
+            asn = excluded.asn,
+            tls_connect = excluded.tls_connect,
+            tls_versions = excluded.tls_versions,
+            tls_tampered = excluded.tls_tampered,
+            username = excluded.username,
+            password = excluded.password
         "#,
     )
     .bind(&proxy.id.to_string())
     .bind(&proxy.ip)
     .bind(proxy.port as i64)
     .bind(&proxy.country)
+    .bind(&proxy.city)
     .bind(&proxy.anonymity)
     .bind(proxy.https as i64)
     .bind(proxy.last_checked.as_ref().map(|d| d.to_rfc3339()))
     .bind(proxy.response_time)
     .bind(proxy.weight)
     .bind(proxy.success_rate)
+    .bind(proxy.class.as_str())
+    .bind(proxy.asn.map(|asn| asn as i64))
+    .bind(proxy.tls.as_ref().map(|tls| tls.connect as i64))
+    .bind(proxy.tls.as_ref().map(|tls| tls.versions_str()))
+    .bind(proxy.tls.as_ref().and_then(|tls| tls.tampered).map(|tampered| tampered as i64))
+    .bind(&proxy.username)
+    .bind(&proxy.password)
     .execute(&mut *tx)
     .await?;
     
@@ -140,8 +133,9 @@ pub async fn load_proxies(pool: &SqlitePool) -> Result<Vec<Proxy>, sqlx::Error>
     let proxy_rows = sqlx::query!(
         r#"
         SELECT 
-            id, ip, port, country, anonymity, https, last_checked, 
-            response_time, weight, success_rate
+            id, ip, port, country, city, anonymity, https, last_checked, 
+            response_time, weight, success_rate, class, asn,
+            tls_connect, tls_versions, tls_tampered, username, password
         FROM proxies
         "#
     )
@@ -178,6 +172,7 @@ pub async fn load_proxies(pool: &SqlitePool) -> Result<Vec<Proxy>, sqlx::Error>
             ip: row.ip.clone(),
             port: row.port as u16,
             country: row.country.clone(),
+            city: row.city.clone(),
             anonymity: row.anonymity.clone(),
             https: row.https != 0,
             protocols,
@@ -185,6 +180,15 @@ pub async fn load_proxies(pool: &SqlitePool) -> Result<Vec<Proxy>, sqlx::Error>
             response_time: row.response_time,
             weight: row.weight,
             success_rate: row.success_rate,
+            class: ProxyClass::parse(&row.class),
+            asn: row.asn.and_then(|asn| u32::try_from(asn).ok()),
+            tls: row.tls_connect.map(|connect| TlsCapabilities {
+                connect: connect != 0,
+                versions: row.tls_versions.as_deref().map(TlsCapabilities::parse_versions).unwrap_or_default(),
+                tampered: row.tls_tampered.map(|tampered| tampered != 0),
+            }),
+            username: row.username.clone(),
+            password: row.password.clone(),
         };
         
         proxies.push(proxy);
@@ -193,6 +197,206 @@ pub async fn load_proxies(pool: &SqlitePool) -> Result<Vec<Proxy>, sqlx::Error>
     Ok(proxies)
 }
 
+/// Lists one page of the proxies matching a query.
+///
+/// The query is expected to be valid, see [`ProxyQuery::validate`]. Pages are
+/// read with keyset pagination on the sort key and id, so every page costs
+/// the same however far into the listing it is.
+pub async fn list_proxies(pool: &SqlitePool, query: &ProxyQuery) -> Result<ProxyPage, sqlx::Error> {
+    let now = chrono::Utc::now();
+    let sort_key = sort_key_expr(query.sort, query.order);
+    let page_size = query.page_size();
+    
+    let mut count = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM proxies WHERE 1 = 1");
+    push_filters(&mut count, query, now);
+    let total: i64 = count.build().fetch_one(pool).await?.try_get(0)?;
+    
+    let mut select = QueryBuilder::<Sqlite>::new(format!(
+        r#"
+        SELECT 
+            id, ip, port, country, city, anonymity, https, last_checked, 
+            response_time, weight, success_rate, class, asn,
+            tls_connect, tls_versions, tls_tampered, username, password, {} AS sort_key
+        FROM proxies
+        WHERE 1 = 1
+        "#,
+        sort_key,
+    ));
+    push_filters(&mut select, query, now);
+    
+    // Continue after the last proxy of the previous page
+    if let Some(cursor) = &query.cursor {
+        let comparison = match query.order {
+            SortOrder::Asc => ">",
+            SortOrder::Desc => "<",
+        };
+        select.push(format!(" AND ({}, id) {} (", sort_key, comparison));
+        push_cursor_key(&mut select, cursor);
+        select.push(", ").push_bind(cursor.id.to_string()).push(")");
+    }
+    
+    let order = query.order.as_str().to_ascii_uppercase();
+    select.push(format!(" ORDER BY sort_key {}, id {} LIMIT ", order, order));
+    select.push_bind(page_size as i64 + 1);
+    
+    let mut rows = select.build().fetch_all(pool).await?;
+    let has_more = rows.len() > page_size as usize;
+    rows.truncate(page_size as usize);
+    
+    // Load the protocols of the whole page at once
+    let mut protocols: HashMap<String, Vec<String>> = HashMap::new();
+    if !rows.is_empty() {
+        let mut select_protocols =
+            QueryBuilder::<Sqlite>::new("SELECT proxy_id, protocol FROM proxy_protocols WHERE proxy_id IN (");
+        let mut ids = select_protocols.separated(", ");
+        for row in &rows {
+            ids.push_bind(row.try_get::<String, _>("id")?);
+        }
+        select_protocols.push(")");
+        
+        for row in select_protocols.build().fetch_all(pool).await? {
+            protocols
+                .entry(row.try_get("proxy_id")?)
+                .or_default()
+                .push(row.try_get("protocol")?);
+        }
+    }
+    
+    let next_cursor = match rows.last() {
+        Some(row) if has_more => Some(ProxyCursor {
+            sort: query.sort,
+            order: query.order,
+            id: Uuid::parse_str(&row.try_get::<String, _>("id")?).unwrap_or_default(),
+            key: cursor_key(row, query.sort)?,
+        }),
+        _ => None,
+    };
+    
+    let proxies = rows
+        .iter()
+        .map(|row| {
+            let id: String = row.try_get("id")?;
+            proxy_from_row(row, protocols.remove(&id).unwrap_or_default())
+        })
+        .collect::<Result<Vec<_>, sqlx::Error>>()?;
+    
+    Ok(ProxyPage {
+        proxies,
+        total,
+        next_cursor,
+    })
+}
+
+/// Returns the SQL expression proxies are sorted by, placing proxies
+/// without a value last in either order.
+fn sort_key_expr(sort: ProxySort, order: SortOrder) -> &'static str {
+    match (sort, order) {
+        (ProxySort::SuccessRate, _) => "success_rate",
+        (ProxySort::Weight, _) => "weight",
+        (ProxySort::ResponseTime, SortOrder::Asc) => "COALESCE(response_time, 9223372036854775807)",
+        (ProxySort::ResponseTime, SortOrder::Desc) => "COALESCE(response_time, -1)",
+        (ProxySort::LastChecked, SortOrder::Asc) => "COALESCE(last_checked, '9999')",
+        (ProxySort::LastChecked, SortOrder::Desc) => "COALESCE(last_checked, '')",
+    }
+}
+
+/// Appends the filters of a query as `AND` conditions.
+fn push_filters(builder: &mut QueryBuilder<'_, Sqlite>, query: &ProxyQuery, now: chrono::DateTime<chrono::Utc>) {
+    if let Some(protocol) = &query.protocol {
+        builder
+            .push(" AND EXISTS (SELECT 1 FROM proxy_protocols WHERE proxy_id = proxies.id AND protocol = ")
+            .push_bind(protocol.to_ascii_lowercase())
+            .push(")");
+    }
+    
+    if let Some(country) = &query.country {
+        builder.push(" AND country = ").push_bind(country.clone()).push(" COLLATE NOCASE");
+    }
+    
+    if let Some(city) = &query.city {
+        builder.push(" AND city = ").push_bind(city.clone()).push(" COLLATE NOCASE");
+    }
+    
+    if let Some(asn) = query.asn {
+        builder.push(" AND asn = ").push_bind(asn as i64);
+    }
+    
+    if let Some(anonymity) = &query.anonymity {
+        builder.push(" AND anonymity = ").push_bind(anonymity.clone()).push(" COLLATE NOCASE");
+    }
+    
+    if let Some(rate) = query.min_success_rate {
+        builder.push(" AND success_rate >= ").push_bind(rate);
+    }
+    
+    if let Some(seconds) = query.checked_within {
+        let since = now - chrono::Duration::seconds(seconds.min(i64::MAX as u64 / 1000) as i64);
+        builder.push(" AND last_checked >= ").push_bind(since.to_rfc3339());
+    }
+}
+
+/// Binds the sort key of a cursor with the type of its column.
+fn push_cursor_key(builder: &mut QueryBuilder<'_, Sqlite>, cursor: &ProxyCursor) {
+    match cursor.sort {
+        ProxySort::SuccessRate | ProxySort::Weight => {
+            builder.push_bind(cursor.key.parse::<f64>().unwrap_or_default());
+        },
+        ProxySort::ResponseTime => {
+            builder.push_bind(cursor.key.parse::<i64>().unwrap_or_default());
+        },
+        ProxySort::LastChecked => {
+            builder.push_bind(cursor.key.clone());
+        },
+    }
+}
+
+/// Reads the sort key of a listed proxy for its cursor.
+fn cursor_key(row: &SqliteRow, sort: ProxySort) -> Result<String, sqlx::Error> {
+    Ok(match sort {
+        ProxySort::SuccessRate | ProxySort::Weight => row.try_get::<f64, _>("sort_key")?.to_string(),
+        ProxySort::ResponseTime => row.try_get::<i64, _>("sort_key")?.to_string(),
+        ProxySort::LastChecked => row.try_get::<String, _>("sort_key")?,
+    })
+}
+
+/// Assembles a proxy from a row of the proxies table and its protocols.
+fn proxy_from_row(row: &SqliteRow, protocols: Vec<String>) -> Result<Proxy, sqlx::Error> {
+    let id: String = row.try_get("id")?;
+    let last_checked: Option<String> = row.try_get("last_checked")?;
+    let tls_connect: Option<i64> = row.try_get("tls_connect")?;
+    let tls_versions: Option<String> = row.try_get("tls_versions")?;
+    let tls_tampered: Option<i64> = row.try_get("tls_tampered")?;
+    let class: String = row.try_get("class")?;
+    
+    Ok(Proxy {
+        id: Uuid::parse_str(&id).unwrap_or_else(|_| Uuid::new_v4()),
+        ip: row.try_get("ip")?,
+        port: row.try_get::<i64, _>("port")? as u16,
+        country: row.try_get("country")?,
+        city: row.try_get("city")?,
+        anonymity: row.try_get("anonymity")?,
+        https: row.try_get::<i64, _>("https")? != 0,
+        protocols,
+        last_checked: last_checked.and_then(|date_str| {
+            chrono::DateTime::parse_from_rfc3339(&date_str)
+                .ok()
+                .map(|dt| dt.with_timezone(&chrono::Utc))
+        }),
+        response_time: row.try_get("response_time")?,
+        weight: row.try_get("weight")?,
+        success_rate: row.try_get("success_rate")?,
+        class: ProxyClass::parse(&class),
+        asn: row.try_get::<Option<i64>, _>("asn")?.and_then(|asn| u32::try_from(asn).ok()),
+        tls: tls_connect.map(|connect| TlsCapabilities {
+            connect: connect != 0,
+            versions: tls_versions.as_deref().map(TlsCapabilities::parse_versions).unwrap_or_default(),
+            tampered: tls_tampered.map(|tampered| tampered != 0),
+        }),
+        username: row.try_get("username")?,
+        password: row.try_get("password")?,
+    })
+}
+
 /// Deletes a proxy from the database.
 pub async fn delete_proxy(pool: &SqlitePool, id: &Uuid) -> Result<bool, sqlx::Error> {
     // Start a transaction
@@ -208,6 +412,16 @@ pub async fn delete_proxy(pool: &SqlitePool, id: &Uuid) -> Result<bool, sqlx::Er
     .execute(&mut *tx)
     .await?;
     
+    // Delete domain stats
+    sqlx::query(
+        r#"
+        DELETE FROM proxy_domain_stats WHERE proxy_id = ?
+        "#,
+    )
+    .bind(id.to_string())
+    .execute(&mut *tx)
+    .await?;
+    
     // Delete proxy
     let result = sqlx::query(
         r#"
@@ -225,6 +439,144 @@ pub async fn delete_proxy(pool: &SqlitePool, id: &Uuid) -> Result<bool, sqlx::Er
     Ok(result.rows_affected() > 0)
 }
 
+/// Saves the outcome stats of a proxy for a domain, updating if they already exist.
+pub async fn save_domain_stats(pool: &SqlitePool, stats: &DomainStats) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO proxy_domain_stats (
+            proxy_id, domain, successes, blocks, captchas,
+            consecutive_failures, last_outcome, last_failure, updated_at
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(proxy_id, domain) DO UPDATE SET
+            successes = excluded.successes,
+            blocks = excluded.blocks,
+            captchas = excluded.captchas,
+            consecutive_failures = excluded.consecutive_failures,
+            last_outcome = excluded.last_outcome,
+            last_failure = excluded.last_failure,
+            updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(stats.proxy_id.to_string())
+    .bind(&stats.domain)
+    .bind(stats.successes as i64)
+    .bind(stats.blocks as i64)
+    .bind(stats.captchas as i64)
+    .bind(stats.consecutive_failures as i64)
+    .bind(stats.last_outcome.as_str())
+    .bind(stats.last_failure.as_ref().map(|d| d.to_rfc3339()))
+    .bind(stats.updated_at.to_rfc3339())
+    .execute(pool)
+    .await?;
+    
+    Ok(())
+}
+
+/// Loads the outcome stats of all proxies for all domains.
+pub async fn load_domain_stats(pool: &SqlitePool) -> Result<Vec<DomainStats>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT
+            proxy_id, domain, successes, blocks, captchas,
+            consecutive_failures, last_outcome, last_failure, updated_at
+        FROM proxy_domain_stats
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    
+    let parse_date = |date_str: String| {
+        chrono::DateTime::parse_from_rfc3339(&date_str)
+            .ok()
+            .map(|dt| dt.with_timezone(&chrono::Utc))
+    };
+    
+    let mut stats = Vec::with_capacity(rows.len());
+    for row in rows {
+        let proxy_id: String = row.try_get("proxy_id")?;
+        let proxy_id = match Uuid::parse_str(&proxy_id) {
+            Ok(id) => id,
+            Err(_) => continue,
+        };
+        let last_outcome: String = row.try_get("last_outcome")?;
+        let updated_at: String = row.try_get("updated_at")?;
+        
+        stats.push(DomainStats {
+            proxy_id,
+            domain: row.try_get("domain")?,
+            successes: row.try_get::<i64, _>("successes")? as u32,
+            blocks: row.try_get::<i64, _>("blocks")? as u32,
+            captchas: row.try_get::<i64, _>("captchas")? as u32,
+            consecutive_failures: row.try_get::<i64, _>("consecutive_failures")? as u32,
+            last_outcome: Outcome::parse(&last_outcome).unwrap_or(Outcome::Ok),
+            last_failure: row.try_get::<Option<String>, _>("last_failure")?.and_then(parse_date),
+            updated_at: parse_date(updated_at).unwrap_or_else(chrono::Utc::now),
+        });
+    }
+    
+    Ok(stats)
+}
+
+/// Adds requests and bytes to the usage of a proxy by a consumer on a day.
+pub async fn record_consumer_usage(pool: &SqlitePool, usage: &ConsumerUsage) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO proxy_consumer_usage (consumer, proxy_id, day, requests, bytes)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT(consumer, proxy_id, day) DO UPDATE SET
+            requests = requests + excluded.requests,
+            bytes = bytes + excluded.bytes
+        "#,
+    )
+    .bind(&usage.consumer)
+    .bind(usage.proxy_id.to_string())
+    .bind(usage.day.to_string())
+    .bind(usage.requests as i64)
+    .bind(usage.bytes as i64)
+    .execute(pool)
+    .await?;
+    
+    Ok(())
+}
+
+/// Loads the usage of proxies by consumers since a day, of one consumer or all.
+pub async fn load_consumer_usage(
+    pool: &SqlitePool,
+    consumer: Option<&str>,
+    since: chrono::NaiveDate,
+) -> Result<Vec<ConsumerUsage>, sqlx::Error> {
+    let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+        "SELECT consumer, proxy_id, day, requests, bytes FROM proxy_consumer_usage WHERE day >= ",
+    );
+    builder.push_bind(since.to_string());
+    if let Some(consumer) = consumer {
+        builder.push(" AND consumer = ").push_bind(consumer);
+    }
+    builder.push(" ORDER BY day, consumer, proxy_id");
+    
+    let rows = builder.build().fetch_all(pool).await?;
+    
+    let mut usage = Vec::with_capacity(rows.len());
+    for row in rows {
+        let proxy_id: String = row.try_get("proxy_id")?;
+        let day: String = row.try_get("day")?;
+        let (proxy_id, day) = match (Uuid::parse_str(&proxy_id), day.parse()) {
+            (Ok(proxy_id), Ok(day)) => (proxy_id, day),
+            _ => continue,
+        };
+        
+        usage.push(ConsumerUsage {
+            consumer: row.try_get("consumer")?,
+            proxy_id,
+            day,
+            requests: row.try_get::<i64, _>("requests")? as u64,
+            bytes: row.try_get::<i64, _>("bytes")? as u64,
+        });
+    }
+    
+    Ok(usage)
+}
+
 /// Gets a count of proxies in the database.
 pub async fn count_proxies(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
     let row = sqlx::query!(
@@ -241,6 +593,7 @@ pub async fn count_proxies(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::TlsVersion;
     use tempfile::tempdir;
     
     #[tokio::test]
@@ -256,6 +609,13 @@ mod tests {
         // Create a test proxy
         let mut proxy = Proxy::new("192.168.1.1".to_string(), 8080, true);
         proxy.protocols = vec!["http".to_string(), "socks5".to_string()];
+        proxy.class = ProxyClass::Residential;
+        proxy.asn = Some(7922);
+        proxy.tls = Some(TlsCapabilities {
+            connect: true,
+            versions: vec![TlsVersion::Tls12, TlsVersion::Tls13],
+            tampered: Some(false),
+        });
         
         // Save proxy
         save_proxy(&pool, &proxy).await.unwrap();
@@ -269,11 +629,27 @@ mod tests {
         assert_eq!(loaded_proxy.port, proxy.port);
         assert_eq!(loaded_proxy.https, proxy.https);
         assert_eq!(loaded_proxy.protocols.len(), 2);
+        assert_eq!(loaded_proxy.class, ProxyClass::Residential);
+        assert_eq!(loaded_proxy.asn, Some(7922));
+        assert_eq!(loaded_proxy.tls, proxy.tls);
         
         // Count proxies
         let count = count_proxies(&pool).await.unwrap();
         assert_eq!(count, 1);
         
+        // Save and load domain stats
+        let mut stats = DomainStats::new(proxy.id, "example.com".to_string());
+        stats.record(Outcome::Blocked, chrono::Utc::now());
+        save_domain_stats(&pool, &stats).await.unwrap();
+        stats.record(Outcome::Captcha, chrono::Utc::now());
+        save_domain_stats(&pool, &stats).await.unwrap();
+        
+        let loaded_stats = load_domain_stats(&pool).await.unwrap();
+        assert_eq!(loaded_stats.len(), 1);
+        assert_eq!(loaded_stats[0].consecutive_failures, 2);
+        assert_eq!(loaded_stats[0].last_outcome, Outcome::Captcha);
+        assert!(loaded_stats[0].last_failure.is_some());
+        
         // Delete proxy
         let deleted = delete_proxy(&pool, &proxy.id).await.unwrap();
         assert!(deleted);
@@ -281,5 +657,144 @@ mod tests {
         // Verify deletion
         let count_after = count_proxies(&pool).await.unwrap();
         assert_eq!(count_after, 0);
+        assert!(load_domain_stats(&pool).await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_list_proxies() {
+        let temp_dir = tempdir().unwrap();
+        let db_url = format!("sqlite:{}", temp_dir.path().join("test_db.sqlite").display());
+        let pool = init_db(&db_url).await.unwrap();
+        
+        let now = chrono::Utc::now();
+        for i in 0..7u16 {
+            let mut proxy = Proxy::new(format!("10.0.0.{}", i), 8000 + i, false);
+            proxy.country = Some(if i % 2 == 0 { "US" } else { "DE" }.to_string());
+            proxy.city = Some(if i % 2 == 0 { "New York" } else { "Berlin" }.to_string());
+            proxy.asn = Some(if i < 2 { 3320 } else { 7922 });
+            proxy.anonymity = Some("elite".to_string());
+            proxy.success_rate = i as f32 / 10.0;
+            proxy.response_time = (i != 3).then_some(100 * i as i64);
+            proxy.last_checked = (i != 5).then(|| now - chrono::Duration::minutes(i as i64));
+            if i >= 4 {
+                proxy.protocols = vec!["socks5".to_string()];
+            }
+            save_proxy(&pool, &proxy).await.unwrap();
+        }
+        
+        // Pages follow each other without gaps or repeats
+        let mut query = ProxyQuery { limit: Some(3), ..ProxyQuery::default() };
+        let mut rates = Vec::new();
+        loop {
+            let page = list_proxies(&pool, &query).await.unwrap();
+            assert_eq!(page.total, 7);
+            rates.extend(page.proxies.iter().map(|proxy| proxy.success_rate));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(rates, vec![0.6, 0.5, 0.4, 0.3, 0.2, 0.1, 0.0]);
+        
+        // Filters apply to the page and the total
+        let query = ProxyQuery {
+            protocol: Some("SOCKS5".to_string()),
+            country: Some("us".to_string()),
+            anonymity: Some("Elite".to_string()),
+            min_success_rate: Some(0.5),
+            ..ProxyQuery::default()
+        };
+        let page = list_proxies(&pool, &query).await.unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.proxies[0].port, 8006);
+        assert_eq!(page.proxies[0].protocols, vec!["socks5".to_string()]);
+        assert!(page.next_cursor.is_none());
+        
+        let query = ProxyQuery { checked_within: Some(150), ..ProxyQuery::default() };
+        assert_eq!(list_proxies(&pool, &query).await.unwrap().total, 3);
+        
+        let query = ProxyQuery {
+            country: Some("de".to_string()),
+            city: Some("BERLIN".to_string()),
+            asn: Some(7922),
+            ..ProxyQuery::default()
+        };
+        let page = list_proxies(&pool, &query).await.unwrap();
+        assert_eq!(page.total, 2);
+        assert!(page.proxies.iter().all(|proxy| proxy.city.as_deref() == Some("Berlin")));
+        
+        // Proxies without a value come last in either order
+        for order in [SortOrder::Asc, SortOrder::Desc] {
+            let mut query = ProxyQuery { sort: ProxySort::ResponseTime, order, limit: Some(4), ..ProxyQuery::default() };
+            let first = list_proxies(&pool, &query).await.unwrap();
+            query.cursor = first.next_cursor;
+            let second = list_proxies(&pool, &query).await.unwrap();
+            assert_eq!(second.proxies.len(), 3);
+            assert_eq!(second.proxies.last().unwrap().response_time, None);
+            
+            let query = ProxyQuery { sort: ProxySort::LastChecked, order, ..ProxyQuery::default() };
+            let page = list_proxies(&pool, &query).await.unwrap();
+            assert_eq!(page.proxies.last().unwrap().last_checked, None);
+            assert_eq!(page.proxies.first().unwrap().port, if order == SortOrder::Asc { 8006 } else { 8000 });
+        }
     }
-} 
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_migrate_legacy_database() {
+        let temp_dir = tempdir().unwrap();
+        let db_url = format!("sqlite:{}", temp_dir.path().join("test_db.sqlite").display());
+        
+        // A proxies table as created before classification, TLS and migrations
+        Sqlite::create_database(&db_url).await.unwrap();
+        let legacy = SqlitePool::connect(&db_url).await.unwrap();
+        sqlx::query(
+            r#"
+            CREATE TABLE proxies (
+                id TEXT PRIMARY KEY,
+                ip TEXT NOT NULL,
+                port INTEGER NOT NULL,
+                country TEXT,
+                anonymity TEXT,
+                https INTEGER NOT NULL,
+                last_checked TEXT,
+                response_time INTEGER,
+                weight REAL NOT NULL,
+                success_rate REAL NOT NULL
+            )
+            "#,
+        )
+        .execute(&legacy)
+        .await
+        .unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO proxies VALUES (
+                '6c1a8f5e-2a3c-4b1e-9d7f-0e5b8a4c3d21', '10.0.0.1', 8080, NULL, NULL, 0, NULL, NULL, 1.0, 0.5
+            )
+            "#,
+        )
+        .execute(&legacy)
+        .await
+        .unwrap();
+        legacy.close().await;
+        
+        // Upgrading keeps the proxies and adds the new columns
+        let pool = init_db(&db_url).await.unwrap();
+        let proxies = load_proxies(&pool).await.unwrap();
+        assert_eq!(proxies.len(), 1);
+        assert_eq!(proxies[0].class, ProxyClass::Unknown);
+        assert!(proxies[0].tls.is_none());
+        
+        let applied: Vec<i64> = sqlx::query_scalar("SELECT version FROM _sqlx_migrations ORDER BY version")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        let expected: Vec<i64> = MIGRATOR.iter().map(|migration| migration.version).collect();
+        assert_eq!(applied, expected);
+        pool.close().await;
+        
+        // Reopening an up-to-date database applies nothing
+        let pool = init_db(&db_url).await.unwrap();
+        assert_eq!(count_proxies(&pool).await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,95 @@
+//! Geo module.
+//! Locates the exit IPs of proxies, for geo-targeted scraping.
+//!
+//! Location comes from the same lookup services as the ASN (see the `asn`
+//! module), so that a single request per proxy finds out its country, city
+//! and network. The response formats of ipinfo.io, ip-api.com, ipapi.co and
+//! similar services are understood.
+
+use crate::asn::{fetch_lookup, parse_asn_response, AsnInfo};
+use reqwest::Client;
+use serde_json::Value;
+
+/// Location and network of an IP address.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GeoInfo {
+    /// ISO 3166-1 alpha-2 country code, uppercase (if reported).
+    pub country: Option<String>,
+    
+    /// City name (if reported).
+    pub city: Option<String>,
+    
+    /// Autonomous system (if reported).
+    pub asn: Option<AsnInfo>,
+}
+
+/// Looks up the location and autonomous system of an IP address.
+///
+/// `url_template` is the lookup URL with `{ip}` in place of the address.
+/// Lookups are best-effort: failures are logged and yield `None`.
+pub async fn lookup_geo(client: &Client, url_template: &str, ip: &str) -> Option<GeoInfo> {
+    parse_geo_response(&fetch_lookup(client, url_template, ip).await?)
+}
+
+/// Extracts the location and autonomous system from a lookup service response.
+///
+/// The country code is read from `countryCode` (ip-api.com), `country_code`
+/// (ipapi.co) or a two-letter `country` field (ipinfo.io). Returns `None`
+/// for failed lookups and responses without any of the three.
+pub fn parse_geo_response(body: &Value) -> Option<GeoInfo> {
+    if body.get("status").and_then(Value::as_str) == Some("fail") || body.get("bogon").is_some() {
+        return None;
+    }
+    
+    let country = ["countryCode", "country_code", "country"]
+        .iter()
+        .filter_map(|field| body.get(*field).and_then(Value::as_str))
+        .map(str::trim)
+        .find(|code| code.len() == 2 && code.chars().all(|c| c.is_ascii_alphabetic()))
+        .map(str::to_ascii_uppercase);
+    let city = body
+        .get("city")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|city| !city.is_empty())
+        .map(str::to_string);
+    
+    let info = GeoInfo {
+        country,
+        city,
+        asn: parse_asn_response(body),
+    };
+    (info != GeoInfo::default()).then_some(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    #[test]
+    fn test_parse_geo_response() {
+        let telekom = Some(AsnInfo { asn: 3320, name: Some("Deutsche Telekom AG".to_string()) });
+        
+        // ipinfo.io
+        let info = parse_geo_response(&json!({
+            "ip": "80.133.0.1", "city": "Berlin", "country": "DE", "org": "AS3320 Deutsche Telekom AG"
+        })).unwrap();
+        assert_eq!(info, GeoInfo { country: Some("DE".to_string()), city: Some("Berlin".to_string()), asn: telekom.clone() });
+        
+        // ip-api.com reports the country name next to its code
+        let info = parse_geo_response(&json!({
+            "status": "success", "country": "Germany", "countryCode": "de", "city": "Berlin", "as": "AS3320 Deutsche Telekom AG"
+        })).unwrap();
+        assert_eq!(info.country.as_deref(), Some("DE"));
+        assert_eq!(info.asn, telekom);
+        
+        // Location without network
+        let info = parse_geo_response(&json!({ "country_code": "US", "city": "" })).unwrap();
+        assert_eq!((info.country.as_deref(), info.city, info.asn), (Some("US"), None, None));
+        
+        assert_eq!(parse_geo_response(&json!({ "status": "fail", "message": "private range" })), None);
+        assert_eq!(parse_geo_response(&json!({ "ip": "10.0.0.1", "bogon": true })), None);
+        assert_eq!(parse_geo_response(&json!({ "country": "Germany" })), None);
+    }
+}
@@ -0,0 +1,162 @@
+//! Judge module.
+//! A self-hostable proxy judge.
+//!
+//! A judge answers every GET request with the address the request came from
+//! and the headers it arrived with, in the format of httpbin's `/get`
+//! endpoint. Requested through a proxy, this shows the proxy's exit IP and
+//! whether the proxy revealed itself or the client, e.g. with `Via` or
+//! `X-Forwarded-For` headers.
+//!
+//! Free public judges frequently go down or rewrite their answers, so it is
+//! worth running one or more of your own with the `proxymaster-judge` binary
+//! and listing them in [`ValidatorConfig::judges`](crate::ValidatorConfig::judges).
+
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use tracing::info;
+use warp::{Filter, Rejection, Reply};
+
+/// Headers that reveal a proxy or the client behind it.
+pub const REVEALING_HEADERS: &[&str] = &[
+    "via",
+    "forwarded",
+    "x-forwarded-for",
+    "x-real-ip",
+    "x-client-ip",
+    "client-ip",
+    "x-proxy-id",
+    "x-proxy-connection",
+];
+
+/// What a judge saw of a request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JudgeResponse {
+    /// Address the request came from.
+    pub origin: String,
+    
+    /// Headers of the request.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl JudgeResponse {
+    /// Describes a request from its remote address and headers.
+    pub fn from_request(remote: Option<SocketAddr>, headers: &HeaderMap) -> Self {
+        let mut seen = BTreeMap::new();
+        for (name, value) in headers {
+            let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+            seen.entry(name.as_str().to_string())
+                .and_modify(|existing: &mut String| {
+                    existing.push_str(", ");
+                    existing.push_str(&value);
+                })
+                .or_insert(value);
+        }
+        
+        Self {
+            origin: remote.map(|addr| addr.ip().to_string()).unwrap_or_default(),
+            headers: seen,
+        }
+    }
+    
+    /// Returns whether the request revealed a proxy, or `real_ip` if given.
+    pub fn reveals_proxy(&self, real_ip: Option<&str>) -> bool {
+        let revealing = self.headers
+            .keys()
+            .any(|name| REVEALING_HEADERS.contains(&name.to_ascii_lowercase().as_str()));
+        let leaked = real_ip.and_then(|ip| ip.trim().parse::<IpAddr>().ok()).is_some_and(|ip| {
+            std::iter::once(&self.origin)
+                .chain(self.headers.values())
+                .any(|value| addresses(value).any(|address| address == ip))
+        });
+        
+        revealing || leaked
+    }
+}
+
+/// Parses the addresses of a comma-separated list, as in `origin` or `X-Forwarded-For`.
+///
+/// Items may carry a port or be `Forwarded` parameters like `for="[2001:db8::1]:80"`;
+/// anything that is not an address is skipped.
+fn addresses(value: &str) -> impl Iterator<Item = IpAddr> + '_ {
+    value.split([',', ';']).filter_map(|item| {
+        let item = item.trim();
+        let item = match item.split_once('=') {
+            Some((name, value)) if name.trim().eq_ignore_ascii_case("for") => value.trim(),
+            Some(_) => return None,
+            None => item,
+        };
+        let item = item.trim_matches('"');
+        
+        item.parse::<IpAddr>().ok()
+            .or_else(|| item.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+            .or_else(|| item.trim_start_matches('[').split(']').next()?.parse().ok())
+    })
+}
+
+/// Creates the routes of the judge, answering GET requests on any path.
+pub fn routes() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::get()
+        .and(warp::addr::remote())
+        .and(warp::header::headers_cloned())
+        .map(|remote: Option<SocketAddr>, headers: HeaderMap| {
+            warp::reply::json(&JudgeResponse::from_request(remote, &headers))
+        })
+}
+
+/// Runs a judge on the given address until the task is cancelled.
+pub async fn serve(addr: SocketAddr) {
+    info!("Proxy judge listening on {}", addr);
+    warp::serve(routes()).run(addr).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_routes() {
+        let response = warp::test::request()
+            .path("/judge")
+            .remote_addr("203.0.113.7:40000".parse().unwrap())
+            .header("Via", "1.1 squid")
+            .header("Accept", "text/html")
+            .reply(&routes())
+            .await;
+        assert_eq!(response.status(), 200);
+        
+        let judged: JudgeResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(judged.origin, "203.0.113.7");
+        assert_eq!(judged.headers.get("via").map(String::as_str), Some("1.1 squid"));
+        assert!(judged.reveals_proxy(None));
+        
+        let clean = JudgeResponse {
+            origin: "203.0.113.7".to_string(),
+            headers: BTreeMap::from([("Accept".to_string(), "*/*".to_string())]),
+        };
+        assert!(!clean.reveals_proxy(Some("198.51.100.1")));
+        assert!(clean.reveals_proxy(Some("203.0.113.7")));
+    }
+    
+    #[test]
+    fn test_leaks_compare_whole_addresses() {
+        let judged = |origin: &str, name: &str, value: &str| JudgeResponse {
+            origin: origin.to_string(),
+            headers: BTreeMap::from([(name.to_string(), value.to_string())]),
+        };
+        
+        // A prefix of another address is not a leak
+        assert!(!judged("10.0.0.10", "Accept", "*/*").reveals_proxy(Some("10.0.0.1")));
+        assert!(!judged("203.0.113.7", "User-Agent", "agent/10.0.0.1").reveals_proxy(Some("10.0.0.1")));
+        
+        assert!(judged("203.0.113.7, 10.0.0.1", "Accept", "*/*").reveals_proxy(Some("10.0.0.1")));
+        assert!(judged("203.0.113.7", "Accept", "198.51.100.4,10.0.0.1").reveals_proxy(Some(" 10.0.0.1")));
+        assert!(judged("203.0.113.7", "Accept", "for=10.0.0.1:4711;proto=http").reveals_proxy(Some("10.0.0.1")));
+        assert!(judged("203.0.113.7", "Accept", "for=\"[2001:db8::1]:80\"").reveals_proxy(Some("2001:db8::1")));
+        
+        // Something that is not an address never matches
+        assert!(!judged("203.0.113.7", "Accept", "*/*").reveals_proxy(Some("unknown")));
+    }
+}
@@ -5,12 +5,22 @@
 //! 
 //! ## Features
 //! 
-//! - Proxy Discovery: Scrapes free proxies from multiple online sources
+//! - Proxy Discovery: Scrapes free proxies from multiple online sources, with
+//!   pluggable sources for paid APIs, internal lists and files
+//! - Gateways: Generates session proxies for commercial rotating-proxy gateways
+//! - Chaining: Chains SOCKS and HTTP proxies behind a single local endpoint
 //! - Validation: Tests proxies for functionality, speed, and anonymity
+//! - SOCKS: Detects and validates SOCKS4 and SOCKS5 proxies, with credentials
+//! - Judges: Requires several judges to agree, including self-hosted ones
+//! - Classification: Tells datacenter, residential and mobile proxies apart by ASN
+//! - Geolocation: Looks up the country and city of exit IPs, for geo-targeted selection
+//! - TLS Probing: Detects HTTPS CONNECT support, TLS versions and interception
 //! - Rotation: Smart proxy rotation with multiple selection strategies
 //! - Persistence: Stores proxies in SQLite database
+//! - Usage Accounting: Tracks proxy usage per consumer, with daily quotas
 //! - REST API: Complete API for proxy management
-//! - Integration: Seamless integration with the Llama Moonlight ecosystem
+//! - Integration: Seamless integration with the Llama Moonlight ecosystem,
+//!   including stealth proxy managers backed by the pool (`stealth` feature)
 //! 
 //! ## Example
 //! 
@@ -41,25 +51,41 @@
 
 // Re-export all modules
 pub mod api;
+pub mod asn;
+pub mod chain;
 pub mod database;
+pub mod gateway;
+pub mod geo;
+pub mod judge;
 pub mod models;
 pub mod pool;
 pub mod scraper;
+pub mod socks;
+#[cfg(feature = "stealth")]
+pub mod stealth;
+pub mod tls;
+pub mod usage;
 pub mod utils;
 pub mod validator;
 
 // Re-export commonly used types
-pub use crate::models::{Proxy, SelectionStrategy};
+pub use crate::chain::{ChainEndpoint, ProxyChain};
+pub use crate::gateway::{GatewayConfig, GatewayProxy, GatewayProxySource};
+pub use crate::models::{Proxy, ProxyClass, ProxyProtocol, SelectionStrategy, TlsCapabilities, TlsVersion};
 pub use crate::pool::{PoolConfig, ProxyPool};
-pub use crate::scraper::{ScraperConfig, scrape_proxies};
-pub use crate::validator::ValidatorConfig;
+pub use crate::scraper::{FileSource, ProxySource, ScraperConfig, SourceRegistry, UrlListSource, scrape_proxies};
+pub use crate::usage::{ConsumerQuota, QuotaExceeded};
+pub use crate::validator::{Consensus, JudgeVerdict, ValidatorConfig};
+#[cfg(feature = "stealth")]
+pub use crate::stealth::{PoolFeedback, StealthBridge};
 
 /// Version of the crate
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Module containing public types for use in library consumers
 pub mod types {
-    pub use crate::models::{Proxy, SelectionStrategy};
+    pub use crate::gateway::GatewayConfig;
+    pub use crate::models::{Proxy, ProxyClass, ProxyProtocol, SelectionStrategy, TlsCapabilities, TlsVersion};
     pub use crate::pool::PoolConfig;
     pub use crate::scraper::ScraperConfig;
     pub use crate::validator::ValidatorConfig;
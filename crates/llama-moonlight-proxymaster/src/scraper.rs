@@ -1,13 +1,22 @@
 //! Scraper module.
 //! Provides functionality for scraping proxies from various sources.
+//!
+//! Each source implements [`ProxySource`]. The URLs of [`ScraperConfig`] are
+//! fetched by the built-in [`UrlListSource`], and local lists by
+//! [`FileSource`]; paid APIs or internal lists can be added by implementing
+//! the trait and registering the source in a [`SourceRegistry`].
 
 use crate::models::Proxy;
+use llama_moonlight_config::ConfigSection;
+use async_trait::async_trait;
 use futures::stream::{self, StreamExt};
-use log::{debug, error, info, warn};
+use tracing::{debug, error, info, warn};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
 
@@ -25,10 +34,27 @@ pub enum ScraperError {
     
     #[error("No proxies found in response")]
     NoProxies,
+    
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    
+    #[error("Source error: {0}")]
+    Source(String),
+}
+
+/// A source of proxies, such as a free list, a paid API or a local file.
+#[async_trait]
+pub trait ProxySource: Send + Sync {
+    /// Returns the name of the source, used in logs.
+    fn name(&self) -> &str;
+    
+    /// Fetches the proxies currently offered by the source.
+    async fn fetch(&self) -> Result<Vec<Proxy>, ScraperError>;
 }
 
 /// Configuration for the scraper.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ScraperConfig {
     /// List of free proxy sources to scrape.
     pub sources: Vec<String>,
@@ -56,6 +82,10 @@ impl Default for ScraperConfig {
     }
 }
 
+impl ConfigSection for ScraperConfig {
+    const SECTION: &'static str = "proxy_scraper";
+}
+
 /// Scrapes proxies from a single source URL.
 async fn scrape_source(client: &Client, url: &str) -> Result<Vec<Proxy>, ScraperError> {
     info!("Scraping proxies from {}", url);
@@ -72,6 +102,15 @@ async fn scrape_source(client: &Client, url: &str) -> Result<Vec<Proxy>, Scraper
     
     // Parse response text
     let text = response.text().await?;
+    let proxies = parse_proxy_list(&text)?;
+    
+    info!("Found {} proxies from {}", proxies.len(), url);
+    Ok(proxies)
+}
+
+/// Parses a list of proxies, one per line, keeping at most
+/// [`MAX_PROXIES_PER_SOURCE`].
+fn parse_proxy_list(text: &str) -> Result<Vec<Proxy>, ScraperError> {
     let lines: Vec<&str> = text.lines().collect();
     
     if lines.is_empty() {
@@ -104,64 +143,179 @@ async fn scrape_source(client: &Client, url: &str) -> Result<Vec<Proxy>, Scraper
     }
     
     if proxies.is_empty() {
-        warn!("No valid proxies found in response from {}", url);
+        warn!("No valid proxies found in proxy list");
         return Err(ScraperError::NoProxies);
     }
     
-    info!("Found {} proxies from {}", proxies.len(), url);
     Ok(proxies)
 }
 
-/// Scrapes proxies from multiple sources in parallel.
-pub async fn scrape_proxies(config: &ScraperConfig) -> Result<Vec<Proxy>, Box<dyn Error + Send + Sync>> {
-    let client = Client::builder()
-        .timeout(Duration::from_secs(config.timeout))
-        .build()?;
-    
-    // Scrape from all sources in parallel
-    let results = stream::iter(config.sources.iter())
-        .map(|url| {
-            let client = client.clone();
-            async move {
-                match scrape_source(&client, url).await {
-                    Ok(proxies) => (url.clone(), Ok(proxies)),
-                    Err(e) => (url.clone(), Err(e)),
-                }
-            }
-        })
-        .buffer_unordered(config.max_concurrency)
-        .collect::<Vec<_>>()
-        .await;
+/// A built-in source fetching a list of proxies, one per line, from a URL.
+#[derive(Debug, Clone)]
+pub struct UrlListSource {
+    /// URL of the list.
+    url: String,
+    
+    /// HTTP client used to fetch the list.
+    client: Client,
+}
+
+impl UrlListSource {
+    /// Creates a source fetching a URL with the given client.
+    pub fn new(url: &str, client: Client) -> Self {
+        Self { url: url.to_string(), client }
+    }
+}
+
+#[async_trait]
+impl ProxySource for UrlListSource {
+    fn name(&self) -> &str {
+        &self.url
+    }
     
-    // Collect all proxies, removing duplicates
-    let mut unique_proxies = HashSet::new();
-    let mut all_proxies = Vec::new();
+    async fn fetch(&self) -> Result<Vec<Proxy>, ScraperError> {
+        scrape_source(&self.client, &self.url).await
+    }
+}
+
+/// A built-in source reading a list of proxies, one per line, from a file.
+///
+/// The file is read again on every fetch, so it can be updated while the
+/// scraper runs.
+#[derive(Debug, Clone)]
+pub struct FileSource {
+    /// Path of the file.
+    path: PathBuf,
     
-    for (url, result) in results {
-        match result {
-            Ok(proxies) => {
-                info!("Successfully scraped {} proxies from {}", proxies.len(), url);
-                
-                for proxy in proxies {
-                    let key = format!("{}:{}", proxy.ip, proxy.port);
-                    if unique_proxies.insert(key) {
-                        all_proxies.push(proxy);
-                    }
-                }
-            },
-            Err(e) => {
-                error!("Failed to scrape from {}: {}", url, e);
-            }
+    /// Path as displayed in logs.
+    name: String,
+}
+
+impl FileSource {
+    /// Creates a source reading a file.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let name = path.display().to_string();
+        Self { path, name }
+    }
+}
+
+#[async_trait]
+impl ProxySource for FileSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    async fn fetch(&self) -> Result<Vec<Proxy>, ScraperError> {
+        let text = tokio::fs::read_to_string(&self.path).await?;
+        parse_proxy_list(&text)
+    }
+}
+
+/// A set of proxy sources scraped together.
+#[derive(Clone, Default)]
+pub struct SourceRegistry {
+    /// Registered sources.
+    sources: Vec<Arc<dyn ProxySource>>,
+}
+
+impl SourceRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Creates a registry of the URL sources of a scraper configuration.
+    pub fn from_config(config: &ScraperConfig) -> Result<Self, ScraperError> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout))
+            .build()?;
+        
+        let mut registry = Self::new();
+        for url in &config.sources {
+            registry.register(UrlListSource::new(url, client.clone()));
         }
+        Ok(registry)
     }
     
-    info!("Scraped a total of {} unique proxies", all_proxies.len());
+    /// Registers a source.
+    pub fn register(&mut self, source: impl ProxySource + 'static) {
+        debug!("Registered proxy source {}", source.name());
+        self.sources.push(Arc::new(source));
+    }
+    
+    /// Returns the registry with a source added.
+    pub fn with_source(mut self, source: impl ProxySource + 'static) -> Self {
+        self.register(source);
+        self
+    }
     
-    if all_proxies.is_empty() {
-        error!("Failed to scrape any proxies from all sources");
+    /// Returns the names of the registered sources.
+    pub fn names(&self) -> Vec<&str> {
+        self.sources.iter().map(|source| source.name()).collect()
     }
     
-    Ok(all_proxies)
+    /// Returns the number of registered sources.
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+    
+    /// Returns whether no source is registered.
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+    
+    /// Fetches proxies from all sources in parallel, removing duplicates.
+    ///
+    /// Sources that fail are logged and skipped.
+    pub async fn scrape(&self, max_concurrency: usize) -> Vec<Proxy> {
+        let results = stream::iter(self.sources.iter())
+            .map(|source| async move {
+                debug!("Scraping proxies from {}", source.name());
+                (source.name(), source.fetch().await)
+            })
+            .buffer_unordered(max_concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await;
+        
+        // Collect all proxies, removing duplicates
+        let mut unique_proxies = HashSet::new();
+        let mut all_proxies = Vec::new();
+        
+        for (name, result) in results {
+            match result {
+                Ok(proxies) => {
+                    info!("Successfully scraped {} proxies from {}", proxies.len(), name);
+                    
+                    for proxy in proxies {
+                        let key = format!("{}:{}", proxy.ip, proxy.port);
+                        if unique_proxies.insert(key) {
+                            all_proxies.push(proxy);
+                        }
+                    }
+                },
+                Err(e) => {
+                    error!("Failed to scrape from {}: {}", name, e);
+                }
+            }
+        }
+        
+        info!("Scraped a total of {} unique proxies", all_proxies.len());
+        
+        if all_proxies.is_empty() {
+            error!("Failed to scrape any proxies from all sources");
+        }
+        
+        all_proxies
+    }
+}
+
+/// Scrapes proxies from the sources of a configuration in parallel.
+///
+/// Use a [`SourceRegistry`] to scrape custom sources as well.
+pub async fn scrape_proxies(config: &ScraperConfig) -> Result<Vec<Proxy>, Box<dyn Error + Send + Sync>> {
+    let registry = SourceRegistry::from_config(config)?;
+    Ok(registry.scrape(config.max_concurrency).await)
 }
 
 /// Scrapes HTTPS proxies specifically.
@@ -220,4 +374,52 @@ mod tests {
             _ => panic!("Expected NoProxies error"),
         }
     }
+    
+    struct StaticSource(Vec<&'static str>);
+    
+    #[async_trait]
+    impl ProxySource for StaticSource {
+        fn name(&self) -> &str {
+            "static"
+        }
+        
+        async fn fetch(&self) -> Result<Vec<Proxy>, ScraperError> {
+            Ok(self.0.iter().filter_map(|line| Proxy::from_str(line)).collect())
+        }
+    }
+    
+    struct FailingSource;
+    
+    #[async_trait]
+    impl ProxySource for FailingSource {
+        fn name(&self) -> &str {
+            "failing"
+        }
+        
+        async fn fetch(&self) -> Result<Vec<Proxy>, ScraperError> {
+            Err(ScraperError::Source("quota exceeded".to_string()))
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_source_registry() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("proxies.txt");
+        std::fs::write(&path, "10.0.0.1:8080\n\nnot a proxy\n10.0.0.3:3128\n").unwrap();
+        
+        let registry = SourceRegistry::new()
+            .with_source(StaticSource(vec!["10.0.0.1:8080", "10.0.0.2:8080"]))
+            .with_source(FileSource::new(&path))
+            .with_source(FailingSource);
+        assert_eq!(registry.len(), 3);
+        assert_eq!(registry.names()[0], "static");
+        
+        // Duplicates across sources are dropped and failing sources skipped
+        let mut found: Vec<String> = registry.scrape(2).await.iter().map(|p| p.as_str()).collect();
+        found.sort();
+        assert_eq!(found, vec!["10.0.0.1:8080", "10.0.0.2:8080", "10.0.0.3:3128"]);
+        
+        let missing = FileSource::new(temp_dir.path().join("missing.txt"));
+        assert!(matches!(missing.fetch().await, Err(ScraperError::Io(_))));
+    }
 } 
\ No newline at end of file
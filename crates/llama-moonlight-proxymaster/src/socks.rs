@@ -0,0 +1,355 @@
+//! SOCKS module.
+//! Detects SOCKS4 and SOCKS5 proxies and speaks their handshakes.
+//!
+//! Detection greets the proxy as SOCKS5 first, then asks it for a SOCKS4a
+//! tunnel; a proxy that accepts connections but answers neither is taken for
+//! an HTTP proxy. SOCKS5 proxies are validated through reqwest like HTTP
+//! ones. reqwest does not speak SOCKS4, so judges are asked through SOCKS4
+//! proxies with a plain HTTP/1.0 request over a tunnel opened here.
+
+use crate::models::{Proxy, ProxyProtocol};
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tracing::debug;
+
+/// SOCKS5 version byte.
+const SOCKS5_VERSION: u8 = 5;
+
+/// SOCKS5 method without authentication.
+const SOCKS5_NO_AUTH: u8 = 0x00;
+
+/// SOCKS5 username and password method (RFC 1929).
+const SOCKS5_USER_PASS: u8 = 0x02;
+
+/// SOCKS5 answer when none of the offered methods is acceptable.
+const SOCKS5_NO_ACCEPTABLE: u8 = 0xff;
+
+/// SOCKS5 CONNECT command.
+const SOCKS5_CONNECT: u8 = 1;
+
+/// SOCKS5 address type of IPv4 addresses.
+const SOCKS5_ATYP_IPV4: u8 = 1;
+
+/// SOCKS5 address type of domain names.
+const SOCKS5_ATYP_DOMAIN: u8 = 3;
+
+/// SOCKS5 address type of IPv6 addresses.
+const SOCKS5_ATYP_IPV6: u8 = 4;
+
+/// SOCKS4 version byte.
+const SOCKS4_VERSION: u8 = 4;
+
+/// SOCKS4 CONNECT command.
+const SOCKS4_CONNECT: u8 = 1;
+
+/// SOCKS4 status of a granted request.
+const SOCKS4_GRANTED: u8 = 0x5a;
+
+/// Longest judge response read through a SOCKS4 tunnel.
+const MAX_JUDGE_RESPONSE: u64 = 64 * 1024;
+
+/// Greets a SOCKS5 proxy, authenticating with the proxy's credentials if it asks.
+///
+/// Fails with [`io::ErrorKind::PermissionDenied`] if the proxy speaks SOCKS5
+/// but does not accept the credentials.
+pub async fn socks5_handshake(stream: &mut TcpStream, proxy: &Proxy) -> io::Result<()> {
+    let methods: &[u8] = if proxy.username.is_some() {
+        &[SOCKS5_NO_AUTH, SOCKS5_USER_PASS]
+    } else {
+        &[SOCKS5_NO_AUTH]
+    };
+    let mut greeting = vec![SOCKS5_VERSION, methods.len() as u8];
+    greeting.extend_from_slice(methods);
+    stream.write_all(&greeting).await?;
+    
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != SOCKS5_VERSION {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Not a SOCKS5 proxy"));
+    }
+    
+    match reply[1] {
+        SOCKS5_NO_AUTH => Ok(()),
+        SOCKS5_USER_PASS if proxy.username.is_some() => socks5_authenticate(stream, proxy).await,
+        SOCKS5_NO_ACCEPTABLE => Err(io::Error::new(io::ErrorKind::PermissionDenied, "SOCKS5 proxy requires credentials")),
+        method => Err(io::Error::other(format!("SOCKS5 proxy chose unsupported method {:#04x}", method))),
+    }
+}
+
+/// Sends the proxy's username and password to a SOCKS5 proxy.
+async fn socks5_authenticate(stream: &mut TcpStream, proxy: &Proxy) -> io::Result<()> {
+    let username = proxy.username.as_deref().unwrap_or_default().as_bytes();
+    let password = proxy.password.as_deref().unwrap_or_default().as_bytes();
+    if username.len() > 255 || password.len() > 255 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "SOCKS5 credentials longer than 255 bytes"));
+    }
+    
+    let mut request = vec![1, username.len() as u8];
+    request.extend_from_slice(username);
+    request.push(password.len() as u8);
+    request.extend_from_slice(password);
+    stream.write_all(&request).await?;
+    
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0 {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, "SOCKS5 proxy rejected the credentials"));
+    }
+    
+    Ok(())
+}
+
+/// Opens a SOCKS5 tunnel to `host:port` through a proxy, greeting it first.
+///
+/// Host names are resolved by the proxy.
+pub async fn socks5_connect(stream: &mut TcpStream, proxy: &Proxy, host: &str, port: u16) -> io::Result<()> {
+    socks5_handshake(stream, proxy).await?;
+    
+    let mut request = vec![SOCKS5_VERSION, SOCKS5_CONNECT, 0];
+    if let Ok(ip) = host.parse::<Ipv4Addr>() {
+        request.push(SOCKS5_ATYP_IPV4);
+        request.extend_from_slice(&ip.octets());
+    } else if let Ok(ip) = host.trim_matches(|c| c == '[' || c == ']').parse::<Ipv6Addr>() {
+        request.push(SOCKS5_ATYP_IPV6);
+        request.extend_from_slice(&ip.octets());
+    } else {
+        if host.len() > 255 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Host name longer than 255 bytes"));
+        }
+        request.push(SOCKS5_ATYP_DOMAIN);
+        request.push(host.len() as u8);
+        request.extend_from_slice(host.as_bytes());
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+    
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != SOCKS5_VERSION {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Not a SOCKS5 proxy"));
+    }
+    if reply[1] != 0 {
+        return Err(io::Error::other(format!("SOCKS5 proxy refused the tunnel with status {:#04x}", reply[1])));
+    }
+    
+    // Skip the address the proxy bound, and its port
+    let address_len = match reply[3] {
+        SOCKS5_ATYP_IPV4 => 4,
+        SOCKS5_ATYP_IPV6 => 16,
+        SOCKS5_ATYP_DOMAIN => stream.read_u8().await? as usize,
+        atyp => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unknown SOCKS5 address type {}", atyp))),
+    };
+    let mut bound = vec![0u8; address_len + 2];
+    stream.read_exact(&mut bound).await?;
+    
+    Ok(())
+}
+
+/// Opens a SOCKS4 tunnel to `host:port` through a proxy.
+///
+/// Host names are resolved by the proxy (SOCKS4a). The proxy's username is
+/// sent as the SOCKS4 user id.
+pub async fn socks4_connect(stream: &mut TcpStream, proxy: &Proxy, host: &str, port: u16) -> io::Result<()> {
+    let mut request = vec![SOCKS4_VERSION, SOCKS4_CONNECT];
+    request.extend_from_slice(&port.to_be_bytes());
+    
+    let ip = host.parse::<Ipv4Addr>().ok();
+    // An address of 0.0.0.x asks the proxy to resolve the host name that follows the user id
+    request.extend_from_slice(&ip.unwrap_or(Ipv4Addr::new(0, 0, 0, 1)).octets());
+    request.extend_from_slice(proxy.username.as_deref().unwrap_or_default().as_bytes());
+    request.push(0);
+    if ip.is_none() {
+        request.extend_from_slice(host.as_bytes());
+        request.push(0);
+    }
+    stream.write_all(&request).await?;
+    
+    let mut reply = [0u8; 8];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Not a SOCKS4 proxy"));
+    }
+    if reply[1] != SOCKS4_GRANTED {
+        return Err(io::Error::other(format!("SOCKS4 proxy refused the tunnel with status {:#04x}", reply[1])));
+    }
+    
+    Ok(())
+}
+
+/// Detects the protocol a proxy speaks.
+///
+/// `target` is the `host:port` SOCKS4 proxies are asked to tunnel to. Each
+/// step is bounded by `step_timeout`. Fails if the proxy does not accept
+/// connections, or if it speaks SOCKS5 but rejects the credentials.
+pub async fn detect_protocol(proxy: &Proxy, target: (&str, u16), step_timeout: Duration) -> io::Result<ProxyProtocol> {
+    let mut stream = timed(timeout(step_timeout, connect(proxy)).await)?;
+    match timeout(step_timeout, socks5_handshake(&mut stream, proxy)).await {
+        Ok(Ok(())) => return Ok(ProxyProtocol::Socks5),
+        Ok(Err(e)) if e.kind() == io::ErrorKind::PermissionDenied => return Err(e),
+        Ok(Err(e)) => debug!("Proxy {} does not speak SOCKS5: {}", proxy, e),
+        Err(_) => debug!("Proxy {} did not answer the SOCKS5 greeting", proxy),
+    }
+    
+    let mut stream = timed(timeout(step_timeout, connect(proxy)).await)?;
+    match timeout(step_timeout, socks4_connect(&mut stream, proxy, target.0, target.1)).await {
+        Ok(Ok(())) => return Ok(ProxyProtocol::Socks4),
+        Ok(Err(e)) => debug!("Proxy {} does not speak SOCKS4: {}", proxy, e),
+        Err(_) => debug!("Proxy {} did not answer the SOCKS4 request", proxy),
+    }
+    
+    Ok(ProxyProtocol::Http)
+}
+
+/// Requests a plain HTTP URL through a SOCKS4 proxy, returning the status and body.
+pub async fn socks4_get(proxy: &Proxy, url: &str) -> io::Result<(u16, String)> {
+    let url = reqwest::Url::parse(url).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    if url.scheme() != "http" {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "SOCKS4 proxies can only be checked against plain HTTP judges",
+        ));
+    }
+    let host = url.host_str()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Judge URL without host"))?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    
+    let mut stream = connect(proxy).await?;
+    socks4_connect(&mut stream, proxy, host, port).await?;
+    
+    // HTTP/1.0 keeps the body unchunked and the connection closing after it
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    let host_header = match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    };
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nAccept: application/json\r\nConnection: close\r\n\r\n",
+        path, host_header,
+    );
+    stream.write_all(request.as_bytes()).await?;
+    
+    let mut response = Vec::new();
+    stream.take(MAX_JUDGE_RESPONSE).read_to_end(&mut response).await?;
+    parse_response(&response).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid HTTP response"))
+}
+
+/// Splits an HTTP response into its status code and body.
+fn parse_response(response: &[u8]) -> Option<(u16, String)> {
+    let split = response.windows(4).position(|window| window == b"\r\n\r\n")?;
+    let head = std::str::from_utf8(&response[..split]).ok()?;
+    let status_line = head.lines().next()?;
+    if !status_line.starts_with("HTTP/") {
+        return None;
+    }
+    let status = status_line.split_whitespace().nth(1)?.parse().ok()?;
+    Some((status, String::from_utf8_lossy(&response[split + 4..]).into_owned()))
+}
+
+/// Connects to a proxy.
+async fn connect(proxy: &Proxy) -> io::Result<TcpStream> {
+    TcpStream::connect((proxy.ip.as_str(), proxy.port)).await
+}
+
+/// Turns an elapsed timeout into an I/O error.
+fn timed<T>(result: Result<io::Result<T>, tokio::time::error::Elapsed>) -> io::Result<T> {
+    result.unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::TimedOut, "Connection to proxy timed out")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    
+    /// Starts a fake proxy answering every connection with `reply` after its first read.
+    async fn fake_proxy(reply: &'static [u8]) -> Proxy {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream.write_all(reply).await;
+            }
+        });
+        Proxy::new("127.0.0.1".to_string(), port, false)
+    }
+    
+    /// Starts a fake SOCKS5 proxy requiring `user`/`secret`.
+    async fn authenticating_proxy() -> Proxy {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut greeting = [0u8; 2];
+                    stream.read_exact(&mut greeting).await.unwrap();
+                    let mut methods = vec![0u8; greeting[1] as usize];
+                    stream.read_exact(&mut methods).await.unwrap();
+                    if !methods.contains(&SOCKS5_USER_PASS) {
+                        stream.write_all(&[SOCKS5_VERSION, SOCKS5_NO_ACCEPTABLE]).await.unwrap();
+                        return;
+                    }
+                    stream.write_all(&[SOCKS5_VERSION, SOCKS5_USER_PASS]).await.unwrap();
+                    
+                    let mut buf = [0u8; 512];
+                    let n = stream.read(&mut buf).await.unwrap();
+                    let status = if buf[..n] == *b"\x01\x04user\x06secret" { 0 } else { 1 };
+                    stream.write_all(&[1, status]).await.unwrap();
+                });
+            }
+        });
+        Proxy::new("127.0.0.1".to_string(), port, false)
+    }
+    
+    #[tokio::test]
+    async fn test_detect_protocol() {
+        let step = Duration::from_secs(2);
+        let target = ("example.com", 80);
+        
+        let socks5 = fake_proxy(&[SOCKS5_VERSION, SOCKS5_NO_AUTH]).await;
+        assert_eq!(detect_protocol(&socks5, target, step).await.unwrap(), ProxyProtocol::Socks5);
+        
+        let socks4 = fake_proxy(&[0, SOCKS4_GRANTED, 0, 0, 0, 0, 0, 0]).await;
+        assert_eq!(detect_protocol(&socks4, target, step).await.unwrap(), ProxyProtocol::Socks4);
+        
+        let http = fake_proxy(b"HTTP/1.1 400 Bad Request\r\n\r\n").await;
+        assert_eq!(detect_protocol(&http, target, step).await.unwrap(), ProxyProtocol::Http);
+        
+        let closed = Proxy::new("127.0.0.1".to_string(), 1, false);
+        assert!(detect_protocol(&closed, target, step).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_socks5_authentication() {
+        let step = Duration::from_secs(2);
+        let target = ("example.com", 80);
+        let mut proxy = authenticating_proxy().await;
+        
+        let e = detect_protocol(&proxy, target, step).await.unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+        
+        proxy.username = Some("user".to_string());
+        proxy.password = Some("wrong".to_string());
+        let e = detect_protocol(&proxy, target, step).await.unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+        
+        proxy.password = Some("secret".to_string());
+        assert_eq!(detect_protocol(&proxy, target, step).await.unwrap(), ProxyProtocol::Socks5);
+    }
+    
+    #[test]
+    fn test_parse_response() {
+        let (status, body) = parse_response(b"HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{\"origin\":\"1.2.3.4\"}").unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(body, "{\"origin\":\"1.2.3.4\"}");
+        assert!(parse_response(b"SSH-2.0-OpenSSH\r\n\r\n").is_none());
+        assert!(parse_response(b"HTTP/1.0 200 OK").is_none());
+    }
+}
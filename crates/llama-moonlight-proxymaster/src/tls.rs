@@ -0,0 +1,272 @@
+//! TLS module.
+//! Probes the HTTPS capabilities of proxies.
+//!
+//! A probe opens a CONNECT tunnel through the proxy to a test endpoint and
+//! completes a TLS handshake with each [`TlsVersion`] through it. The
+//! certificate presented in the tunnel is checked against the trusted roots
+//! and, if configured, against pinned fingerprints: a proxy that intercepts
+//! TLS has to present a certificate of its own, which fails these checks.
+
+use crate::models::{Proxy, TlsCapabilities, TlsVersion};
+use crate::validator::ValidatorConfig;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use lazy_static::lazy_static;
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName};
+use sha2::{Digest, Sha256};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tokio_rustls::TlsConnector;
+use tracing::debug;
+
+/// Longest CONNECT response header accepted.
+const MAX_CONNECT_RESPONSE: usize = 8 * 1024;
+
+lazy_static! {
+    /// Trusted root certificates.
+    static ref ROOTS: Arc<RootCertStore> = {
+        let mut roots = RootCertStore::empty();
+        roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+            OwnedTrustAnchor::from_subject_spki_name_constraints(
+                anchor.subject,
+                anchor.spki,
+                anchor.name_constraints,
+            )
+        }));
+        Arc::new(roots)
+    };
+}
+
+/// Accepts any certificate, recording whether it chains to a trusted root.
+struct RecordingVerifier {
+    inner: WebPkiVerifier,
+    trusted: AtomicBool,
+}
+
+impl ServerCertVerifier for RecordingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let trusted = self.inner
+            .verify_server_cert(end_entity, intermediates, server_name, scts, ocsp_response, now)
+            .is_ok();
+        self.trusted.store(trusted, Ordering::SeqCst);
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+/// Certificate presented in a tunnel.
+struct ProbedCertificate {
+    /// SHA-256 fingerprint, in lowercase hex.
+    fingerprint: String,
+    
+    /// Whether it chains to a trusted root.
+    trusted: bool,
+}
+
+/// Probes whether a proxy tunnels HTTPS, with which TLS versions, and whether
+/// it tampers with certificates.
+pub async fn probe_tls(proxy: &Proxy, config: &ValidatorConfig) -> TlsCapabilities {
+    let (host, port) = split_target(&config.tls_probe_target);
+    let step_timeout = Duration::from_secs(config.request_timeout);
+    let pins: Vec<String> = config.tls_pinned_fingerprints.iter().map(|pin| normalize_fingerprint(pin)).collect();
+    let mut capabilities = TlsCapabilities::default();
+    
+    for version in TlsVersion::ALL {
+        let tunnel = match timeout(step_timeout, open_tunnel(proxy, host, port)).await {
+            Ok(Ok(tunnel)) => tunnel,
+            Ok(Err(e)) => {
+                debug!("CONNECT to {}:{} through {} failed: {}", host, port, proxy, e);
+                continue;
+            }
+            Err(_) => {
+                debug!("CONNECT to {}:{} through {} timed out", host, port, proxy);
+                continue;
+            }
+        };
+        capabilities.connect = true;
+        
+        match timeout(step_timeout, handshake(tunnel, host, version)).await {
+            Ok(Ok(certificate)) => {
+                let tampered = !certificate.trusted || (!pins.is_empty() && !pins.contains(&certificate.fingerprint));
+                if tampered {
+                    debug!("Proxy {} presented certificate {} for {}", proxy, certificate.fingerprint, host);
+                }
+                capabilities.versions.push(version);
+                capabilities.tampered = Some(capabilities.tampered.unwrap_or(false) || tampered);
+            }
+            Ok(Err(e)) => debug!("{} handshake through {} failed: {}", version, proxy, e),
+            Err(_) => debug!("{} handshake through {} timed out", version, proxy),
+        }
+    }
+    
+    capabilities
+}
+
+/// Opens a CONNECT tunnel to `host:port` through a proxy.
+async fn open_tunnel(proxy: &Proxy, host: &str, port: u16) -> io::Result<TcpStream> {
+    let mut stream = TcpStream::connect((proxy.ip.as_str(), proxy.port)).await?;
+    http_connect(&mut stream, proxy, host, port).await?;
+    Ok(stream)
+}
+
+/// Asks an HTTP proxy connected to over `stream` for a CONNECT tunnel to
+/// `host:port`, authenticating with the proxy's credentials if it has any.
+pub(crate) async fn http_connect(stream: &mut TcpStream, proxy: &Proxy, host: &str, port: u16) -> io::Result<()> {
+    let target = match host.contains(':') {
+        true => format!("[{}]:{}", host, port),
+        false => format!("{}:{}", host, port),
+    };
+    let mut request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n", target, target);
+    if let Some(username) = &proxy.username {
+        let credentials = format!("{}:{}", username, proxy.password.as_deref().unwrap_or_default());
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", BASE64.encode(credentials)));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+    
+    // Read the response header byte by byte, so nothing of the tunnel is consumed
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_CONNECT_RESPONSE {
+            return Err(io::Error::other("CONNECT response too long"));
+        }
+        let byte = stream.read_u8().await?;
+        response.push(byte);
+    }
+    
+    match parse_connect_status(&response) {
+        Some(status) if (200..300).contains(&status) => Ok(()),
+        Some(status) => Err(io::Error::other(format!("CONNECT refused with status {}", status))),
+        None => Err(io::Error::other("Invalid CONNECT response")),
+    }
+}
+
+/// Completes a TLS handshake restricted to one version over a tunnel.
+async fn handshake(stream: TcpStream, host: &str, version: TlsVersion) -> io::Result<ProbedCertificate> {
+    let verifier = Arc::new(RecordingVerifier {
+        inner: WebPkiVerifier::new(ROOTS.clone(), None),
+        trusted: AtomicBool::new(false),
+    });
+    let protocol = match version {
+        TlsVersion::Tls12 => &rustls::version::TLS12,
+        TlsVersion::Tls13 => &rustls::version::TLS13,
+    };
+    
+    let tls_config = ClientConfig::builder()
+        .with_safe_default_cipher_suites()
+        .with_safe_default_kx_groups()
+        .with_protocol_versions(&[protocol])
+        .map_err(io::Error::other)?
+        .with_custom_certificate_verifier(verifier.clone())
+        .with_no_client_auth();
+    let server_name = ServerName::try_from(host).map_err(io::Error::other)?;
+    
+    let stream = TlsConnector::from(Arc::new(tls_config)).connect(server_name, stream).await?;
+    let certificate = stream.get_ref().1
+        .peer_certificates()
+        .and_then(|certificates| certificates.first())
+        .ok_or_else(|| io::Error::other("No certificate presented"))?;
+    
+    Ok(ProbedCertificate {
+        fingerprint: fingerprint(&certificate.0),
+        trusted: verifier.trusted.load(Ordering::SeqCst),
+    })
+}
+
+/// Returns the status code of a CONNECT response.
+fn parse_connect_status(response: &[u8]) -> Option<u16> {
+    let response = std::str::from_utf8(response).ok()?;
+    let status_line = response.lines().next()?;
+    if !status_line.starts_with("HTTP/") {
+        return None;
+    }
+    status_line.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Splits a `host:port` target, defaulting to port 443.
+fn split_target(target: &str) -> (&str, u16) {
+    match target.rsplit_once(':') {
+        Some((host, port)) => match port.parse() {
+            Ok(port) => (host, port),
+            Err(_) => (target, 443),
+        },
+        None => (target, 443),
+    }
+}
+
+/// Returns the SHA-256 fingerprint of a DER certificate, in lowercase hex.
+pub fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Normalizes a fingerprint written with colons or in uppercase.
+fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint
+        .chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    
+    #[test]
+    fn test_parsing() {
+        assert_eq!(parse_connect_status(b"HTTP/1.1 200 Connection established\r\n\r\n"), Some(200));
+        assert_eq!(parse_connect_status(b"HTTP/1.0 403 Forbidden\r\nVia: squid\r\n\r\n"), Some(403));
+        assert_eq!(parse_connect_status(b"SSH-2.0-OpenSSH\r\n\r\n"), None);
+        
+        assert_eq!(split_target("example.com:8443"), ("example.com", 8443));
+        assert_eq!(split_target("example.com"), ("example.com", 443));
+        assert_eq!(normalize_fingerprint("AB:cd:01"), "abcd01");
+        assert_eq!(fingerprint(b"").len(), 64);
+    }
+    
+    /// Starts a fake proxy answering CONNECT with `response`, then closing.
+    async fn fake_proxy(response: &'static str) -> Proxy {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        Proxy::new("127.0.0.1".to_string(), port, true)
+    }
+    
+    #[tokio::test]
+    async fn test_probe_tls() {
+        let config = ValidatorConfig { request_timeout: 2, ..Default::default() };
+        
+        let refusing = fake_proxy("HTTP/1.1 405 Method Not Allowed\r\n\r\n").await;
+        let capabilities = probe_tls(&refusing, &config).await;
+        assert_eq!(capabilities, TlsCapabilities::default());
+        assert!(!capabilities.is_clean());
+        
+        // The tunnel opens, but nothing answers the handshake
+        let broken = fake_proxy("HTTP/1.1 200 Connection established\r\n\r\n").await;
+        let capabilities = probe_tls(&broken, &config).await;
+        assert!(capabilities.connect);
+        assert!(capabilities.versions.is_empty());
+        assert_eq!(capabilities.tampered, None);
+        assert!(!capabilities.is_clean());
+    }
+}
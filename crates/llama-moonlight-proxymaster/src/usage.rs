@@ -0,0 +1,136 @@
+//! Usage module.
+//! Daily quotas on the proxy usage of consumers.
+//!
+//! A consumer is whoever the pool hands proxies out to: an API key in shared
+//! deployments, or a tag a library caller picks for itself. The pool counts
+//! the requests and bytes of every consumer per proxy and UTC day (see
+//! `ProxyPool::get_proxy_for_consumer`), which is persisted for chargeback,
+//! and refuses proxies to consumers that used up their daily quota.
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
+
+/// Daily limits on the usage of a consumer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsumerQuota {
+    /// Maximum number of requests per UTC day (unlimited if not set).
+    pub daily_requests: Option<u64>,
+    
+    /// Maximum number of bytes per UTC day, as reported by the consumer
+    /// (unlimited if not set).
+    pub daily_bytes: Option<u64>,
+}
+
+impl ConsumerQuota {
+    /// Creates a quota on the number of requests per day.
+    pub fn requests(limit: u64) -> Self {
+        Self { daily_requests: Some(limit), ..Default::default() }
+    }
+    
+    /// Creates a quota on the number of bytes per day.
+    pub fn bytes(limit: u64) -> Self {
+        Self { daily_bytes: Some(limit), ..Default::default() }
+    }
+    
+    /// Returns whether the quota limits anything.
+    pub fn is_unlimited(&self) -> bool {
+        self.daily_requests.is_none() && self.daily_bytes.is_none()
+    }
+    
+    /// Checks whether a consumer that made `requests` requests and
+    /// transferred `bytes` bytes today may make another request.
+    pub fn check(&self, consumer: &str, requests: u64, bytes: u64, now: DateTime<Utc>) -> Result<(), QuotaExceeded> {
+        let exceeded = [
+            (QuotaKind::Requests, requests, self.daily_requests),
+            (QuotaKind::Bytes, bytes, self.daily_bytes),
+        ]
+        .into_iter()
+        .find_map(|(kind, used, limit)| limit.filter(|limit| used >= *limit).map(|limit| (kind, used, limit)));
+        
+        match exceeded {
+            Some((kind, used, limit)) => Err(QuotaExceeded {
+                consumer: consumer.to_string(),
+                kind,
+                used,
+                limit,
+                resets_at: next_day(now),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// What a quota limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuotaKind {
+    /// Number of requests.
+    Requests,
+    
+    /// Number of bytes transferred.
+    Bytes,
+}
+
+impl fmt::Display for QuotaKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotaKind::Requests => write!(f, "requests"),
+            QuotaKind::Bytes => write!(f, "bytes"),
+        }
+    }
+}
+
+/// Error returned when a consumer used up its daily quota.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Consumer '{consumer}' used {used} of its {limit} daily {kind}; the quota resets at {resets_at}")]
+pub struct QuotaExceeded {
+    /// Consumer that used up its quota.
+    pub consumer: String,
+    
+    /// What the consumer used up.
+    pub kind: QuotaKind,
+    
+    /// Amount used today.
+    pub used: u64,
+    
+    /// Daily limit.
+    pub limit: u64,
+    
+    /// Time at which the quota resets (the next UTC midnight).
+    pub resets_at: DateTime<Utc>,
+}
+
+/// Returns the UTC midnight after a time.
+fn next_day(now: DateTime<Utc>) -> DateTime<Utc> {
+    let midnight = (now.date_naive() + chrono::Duration::days(1))
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight is a valid time");
+    Utc.from_utc_datetime(&midnight)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_quota_check() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 15, 30, 0).unwrap();
+        let quota = ConsumerQuota { daily_requests: Some(100), daily_bytes: Some(1_000_000) };
+        
+        assert!(quota.check("team-a", 99, 999_999, now).is_ok());
+        assert!(ConsumerQuota::default().is_unlimited());
+        assert!(ConsumerQuota::default().check("team-a", u64::MAX, u64::MAX, now).is_ok());
+        
+        let err = quota.check("team-a", 100, 0, now).unwrap_err();
+        assert_eq!(err.kind, QuotaKind::Requests);
+        assert_eq!((err.used, err.limit), (100, 100));
+        assert_eq!(err.resets_at, Utc.with_ymd_and_hms(2024, 3, 2, 0, 0, 0).unwrap());
+        assert!(err.to_string().contains("'team-a' used 100 of its 100 daily requests"));
+        
+        let err = quota.check("team-a", 5, 2_000_000, now).unwrap_err();
+        assert_eq!(err.kind, QuotaKind::Bytes);
+    }
+}
@@ -1,9 +1,20 @@
 //! Validator module.
 //! Provides functionality for checking if proxies are working.
+//!
+//! Each proxy is checked against one or more judges, which report the exit IP
+//! and headers they saw (see the [`judge`](crate::judge) module). Free judges
+//! frequently go down or answer wrongly, so with several judges a measurement
+//! is only accepted when [`ValidatorConfig::min_agreement`] of them agree.
 
-use crate::models::Proxy;
+use crate::asn::AsnDatabase;
+use crate::geo::lookup_geo;
+use crate::judge::JudgeResponse;
+use crate::models::{Proxy, ProxyClass, ProxyProtocol, TlsCapabilities};
+use crate::socks::{detect_protocol, socks4_get};
+use crate::tls::probe_tls;
 use chrono::Utc;
-use log::{debug, error, info};
+use futures::future::join_all;
+use tracing::{debug, error, info, instrument, warn};
 use reqwest::{Client, Proxy as ReqwestProxy};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -11,13 +22,31 @@ use tokio::time::timeout;
 
 /// Configuration for the validator.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ValidatorConfig {
-    /// URL to test HTTP proxies.
+    /// URL to test HTTP proxies, used when no judges are configured.
     pub http_test_url: String,
     
-    /// URL to test HTTPS proxies.
+    /// URL to test HTTPS proxies, used when no judges are configured.
     pub https_test_url: String,
     
+    /// Judge URLs every proxy is checked against, e.g. self-hosted
+    /// `proxymaster-judge` instances or httpbin's `/get` endpoint.
+    pub judges: Vec<String>,
+    
+    /// Number of judges that must agree before a measurement is accepted.
+    ///
+    /// A proxy only counts as working when this many judges answered
+    /// through it, and its anonymity is only recorded when this many of
+    /// them agree on it. Capped at the number of judges.
+    pub min_agreement: usize,
+    
+    /// Public IP of this machine, to detect proxies that leak it.
+    ///
+    /// Looked up through the first judge when validating a batch of proxies
+    /// if not set.
+    pub real_ip: Option<String>,
+    
     /// Connect timeout in seconds.
     pub connect_timeout: u64,
     
@@ -27,8 +56,38 @@ pub struct ValidatorConfig {
     /// Whether to check for anonymity.
     pub check_anonymity: bool,
     
-    /// Whether to check for country.
+    /// Whether to look up the country and city of the exit IP the first
+    /// time a proxy without a country is validated.
+    ///
+    /// Off by default: every lookup is a request to `asn_lookup_url`, and
+    /// free lookup services ban clients that validate large lists.
     pub check_country: bool,
+    
+    /// Whether to classify proxies as datacenter, residential or mobile,
+    /// which takes the same lookup as `check_country` (off by default).
+    pub classify: bool,
+    
+    /// Whether to detect if a proxy speaks SOCKS5, SOCKS4 or HTTP the first
+    /// time it is validated. Otherwise proxies are validated with the
+    /// protocol they are listed with.
+    pub detect_protocols: bool,
+    
+    /// Lookup URL of the ASN and location of exit IPs, with `{ip}` in place
+    /// of the exit IP, e.g. `http://ip-api.com/json/{ip}`.
+    pub asn_lookup_url: String,
+    
+    /// Whether to probe HTTPS CONNECT support, TLS versions and certificate
+    /// tampering of working HTTP proxies.
+    pub probe_tls: bool,
+    
+    /// HTTPS endpoint (`host:port`) tunneled to when probing TLS.
+    pub tls_probe_target: String,
+    
+    /// SHA-256 fingerprints of the probe endpoint's certificates, in hex.
+    ///
+    /// When set, any other certificate counts as tampered with. Otherwise
+    /// only certificates that do not chain to a trusted root do.
+    pub tls_pinned_fingerprints: Vec<String>,
 }
 
 impl Default for ValidatorConfig {
@@ -36,10 +95,128 @@ impl Default for ValidatorConfig {
         Self {
             http_test_url: "http://httpbin.org/ip".to_string(),
             https_test_url: "https://httpbin.org/ip".to_string(),
+            judges: Vec::new(),
+            min_agreement: 1,
+            real_ip: None,
             connect_timeout: 5,
             request_timeout: 10,
             check_anonymity: true,
-            check_country: true,
+            check_country: false,
+            classify: false,
+            detect_protocols: true,
+            asn_lookup_url: "https://ipinfo.io/{ip}/json".to_string(),
+            probe_tls: false,
+            tls_probe_target: "example.com:443".to_string(),
+            tls_pinned_fingerprints: Vec::new(),
+        }
+    }
+}
+
+impl ValidatorConfig {
+    /// Returns the judges to check a proxy against.
+    pub fn judge_urls(&self, https: bool) -> Vec<&str> {
+        if !self.judges.is_empty() {
+            return self.judges.iter().map(String::as_str).collect();
+        }
+        
+        if https {
+            vec![&self.https_test_url]
+        } else {
+            vec![&self.http_test_url]
+        }
+    }
+    
+    /// Returns the number of judges that must agree, out of `judges`.
+    pub fn required_agreement(&self, judges: usize) -> usize {
+        self.min_agreement.clamp(1, judges.max(1))
+    }
+}
+
+/// What one judge reported about a proxy.
+#[derive(Debug, Clone)]
+pub struct JudgeVerdict {
+    /// URL of the judge.
+    pub judge: String,
+    
+    /// Response time in milliseconds (if the judge answered).
+    pub response_time: Option<i64>,
+    
+    /// Exit IP seen by the judge (if reported).
+    pub origin: Option<String>,
+    
+    /// True if the proxy hid itself and the client (if checked).
+    pub is_anonymous: Option<bool>,
+    
+    /// Error message (if the judge did not answer).
+    pub error: Option<String>,
+}
+
+impl JudgeVerdict {
+    /// Creates the verdict of a judge that did not answer.
+    fn failed(judge: &str, response_time: Option<i64>, error: String) -> Self {
+        Self {
+            judge: judge.to_string(),
+            response_time,
+            origin: None,
+            is_anonymous: None,
+            error: Some(error),
+        }
+    }
+    
+    /// Returns whether the judge answered.
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Measurements the judges agreed on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Consensus {
+    /// Whether enough judges answered.
+    pub is_working: bool,
+    
+    /// Median response time of the judges that answered.
+    pub response_time: Option<i64>,
+    
+    /// Anonymity, if enough judges agree on it.
+    pub is_anonymous: Option<bool>,
+    
+    /// Exit IP reported by most judges.
+    pub origin: Option<String>,
+}
+
+impl Consensus {
+    /// Combines the verdicts of the judges, requiring `required` to agree.
+    pub fn from_verdicts(verdicts: &[JudgeVerdict], required: usize) -> Self {
+        let answered: Vec<&JudgeVerdict> = verdicts.iter().filter(|v| v.is_success()).collect();
+        let is_working = !answered.is_empty() && answered.len() >= required;
+        
+        let mut times: Vec<i64> = answered.iter().filter_map(|v| v.response_time).collect();
+        times.sort_unstable();
+        let response_time = times.get(times.len().saturating_sub(1) / 2).copied();
+        
+        let votes = |anonymous: bool| answered.iter().filter(|v| v.is_anonymous == Some(anonymous)).count();
+        let (anonymous, transparent) = (votes(true), votes(false));
+        let is_anonymous = if anonymous >= required && anonymous > transparent {
+            Some(true)
+        } else if transparent >= required && transparent > anonymous {
+            Some(false)
+        } else {
+            None
+        };
+        
+        let mut origins: Vec<&str> = answered.iter().filter_map(|v| v.origin.as_deref()).collect();
+        origins.sort_unstable();
+        let origin = origins
+            .chunk_by(|a, b| a == b)
+            .max_by_key(|group| group.len())
+            .map(|group| group[0].to_string());
+        
+        Self {
+            is_working,
+            response_time: response_time.filter(|_| is_working),
+            is_anonymous: is_anonymous.filter(|_| is_working),
+            origin: origin.filter(|_| is_working),
         }
     }
 }
@@ -59,137 +236,305 @@ pub struct ValidationResult {
     /// Country code (if detected).
     pub country: Option<String>,
     
+    /// City (if detected).
+    pub city: Option<String>,
+    
+    /// Network class (if classified).
+    pub class: Option<ProxyClass>,
+    
     /// Error message (if not working).
     pub error: Option<String>,
+    
+    /// Verdicts of the individual judges.
+    pub judges: Vec<JudgeVerdict>,
+    
+    /// TLS capabilities (if probed).
+    pub tls: Option<TlsCapabilities>,
+}
+
+impl ValidationResult {
+    /// Creates the result of a proxy that is not working.
+    fn failed(error: String, response_time: Option<i64>, judges: Vec<JudgeVerdict>) -> Self {
+        Self {
+            is_working: false,
+            response_time,
+            is_anonymous: None,
+            country: None,
+            city: None,
+            class: None,
+            error: Some(error),
+            judges,
+            tls: None,
+        }
+    }
 }
 
 /// Validates a proxy.
+///
+/// HTTP and SOCKS5 proxies are checked through reqwest, SOCKS4 proxies
+/// through a tunnel of their own, which only reaches plain HTTP judges.
+#[instrument(skip_all, fields(proxy_id = %proxy.id, proxy = %proxy))]
 pub async fn validate_proxy(proxy: &mut Proxy, config: &ValidatorConfig) -> ValidationResult {
-    let start_time = std::time::Instant::now();
+    // Find out which protocol the proxy speaks the first time it is checked
+    if config.detect_protocols && proxy.last_checked.is_none() {
+        let target = config.judge_urls(false).first().and_then(|judge| judge_target(judge));
+        let target = target.as_ref().map_or(("example.com", 80), |(host, port)| (host.as_str(), *port));
+        match detect_protocol(proxy, target, Duration::from_secs(config.connect_timeout)).await {
+            Ok(protocol) => {
+                debug!("Proxy {} speaks {}", proxy, protocol);
+                proxy.protocols = vec![protocol.as_str().to_string()];
+            }
+            Err(e) => {
+                proxy.success_rate *= 0.8;
+                return ValidationResult::failed(format!("Failed to detect protocol: {}", e), None, Vec::new());
+            }
+        }
+    }
+    let protocol = proxy.protocol();
+    
+    // Ask every judge at once
+    let judges = config.judge_urls(proxy.https && protocol == ProxyProtocol::Http);
+    let verdicts = match protocol {
+        ProxyProtocol::Socks4 => {
+            join_all(judges.iter().map(|judge| ask_judge_through_socks4(proxy, judge, config))).await
+        }
+        ProxyProtocol::Http | ProxyProtocol::Socks5 => {
+            let client = match build_client(proxy, protocol, config) {
+                Ok(client) => client,
+                Err(e) => {
+                    return ValidationResult::failed(format!("Failed to build client: {}", e), None, Vec::new());
+                }
+            };
+            join_all(judges.iter().map(|judge| ask_judge(&client, judge, config))).await
+        }
+    };
+    let required = config.required_agreement(judges.len());
+    let consensus = Consensus::from_verdicts(&verdicts, required);
+    
+    if !consensus.is_working {
+        // Decrement success rate
+        proxy.success_rate *= 0.8;
+        
+        let answered = verdicts.iter().filter(|v| v.is_success()).count();
+        let error = match verdicts.iter().find_map(|v| v.error.as_deref()) {
+            Some(error) if judges.len() == 1 => error.to_string(),
+            Some(error) => format!("Only {} of {} judges answered ({} required): {}", answered, judges.len(), required, error),
+            None => "No judge answered".to_string(),
+        };
+        let response_time = verdicts.iter().find_map(|v| v.response_time);
+        return ValidationResult::failed(error, response_time, verdicts);
+    }
+    
+    let response_time = consensus.response_time.unwrap_or_default();
     
-    // Build a reqwest client with the proxy
+    // Update proxy fields
+    proxy.last_checked = Some(Utc::now());
+    proxy.response_time = Some(response_time);
+    
+    // Increment success rate
+    proxy.success_rate = proxy.success_rate * 0.8 + 0.2;
+    
+    // Adjust weight based on response time
+    // Lower response time = higher weight (max 10)
+    if response_time < 100 {
+        proxy.weight = 10.0;
+    } else if response_time < 200 {
+        proxy.weight = 8.0;
+    } else if response_time < 500 {
+        proxy.weight = 5.0;
+    } else if response_time < 1000 {
+        proxy.weight = 3.0;
+    } else {
+        proxy.weight = 1.0;
+    }
+    
+    if let Some(anonymous) = consensus.is_anonymous {
+        proxy.anonymity = Some(if anonymous { "anonymous" } else { "transparent" }.to_string());
+    } else if config.check_anonymity && judges.len() > 1 {
+        warn!("Judges disagree on the anonymity of proxy {}", proxy);
+    }
+    
+    // Classify and locate the exit network once
+    let classify = config.classify && proxy.class == ProxyClass::Unknown;
+    let locate = config.check_country && proxy.country.is_none();
+    if classify || locate {
+        enrich_proxy(proxy, consensus.origin.as_deref(), classify, locate, config).await;
+    }
+    
+    // Find out whether HTTPS passes through untouched
+    if config.probe_tls && protocol == ProxyProtocol::Http {
+        proxy.tls = Some(probe_tls(proxy, config).await);
+    }
+    
+    ValidationResult {
+        is_working: true,
+        response_time: Some(response_time),
+        is_anonymous: consensus.is_anonymous,
+        country: proxy.country.clone(),
+        city: proxy.city.clone(),
+        class: Some(proxy.class),
+        error: None,
+        judges: verdicts,
+        tls: proxy.tls.clone(),
+    }
+}
+
+/// Builds a reqwest client sending requests through a proxy.
+fn build_client(proxy: &Proxy, protocol: ProxyProtocol, config: &ValidatorConfig) -> reqwest::Result<Client> {
     let client_builder = Client::builder()
         .connect_timeout(Duration::from_secs(config.connect_timeout))
         .timeout(Duration::from_secs(config.request_timeout));
     
-    // HTTP proxy
-    let reqwest_proxy = if proxy.https {
-        ReqwestProxy::https(&proxy.as_str())
-    } else {
-        ReqwestProxy::http(&proxy.as_str())
+    let reqwest_proxy = match protocol {
+        // Let the proxy resolve host names, like SOCKS5 clients in the wild do
+        ProxyProtocol::Socks5 => ReqwestProxy::all(proxy.url(protocol).replacen("socks5://", "socks5h://", 1))?,
+        _ if proxy.https => ReqwestProxy::https(&proxy.as_str())?,
+        _ => ReqwestProxy::http(&proxy.as_str())?,
     };
     
-    let client = match reqwest_proxy.and_then(|p| client_builder.proxy(p).build()) {
-        Ok(client) => client,
-        Err(e) => {
-            return ValidationResult {
-                is_working: false,
-                response_time: None,
-                is_anonymous: None,
-                country: None,
-                error: Some(format!("Failed to build client: {}", e)),
-            };
+    // SOCKS5 credentials travel in the URL, HTTP ones in a header
+    let reqwest_proxy = match (&proxy.username, protocol) {
+        (Some(username), ProxyProtocol::Http) => {
+            reqwest_proxy.basic_auth(username, proxy.password.as_deref().unwrap_or_default())
         }
+        _ => reqwest_proxy,
     };
     
-    // Test the proxy
-    let test_url = if proxy.https {
-        &config.https_test_url
-    } else {
-        &config.http_test_url
-    };
+    client_builder.proxy(reqwest_proxy).build()
+}
+
+/// Returns the host and port of a judge URL.
+fn judge_target(judge: &str) -> Option<(String, u16)> {
+    let url = reqwest::Url::parse(judge).ok()?;
+    Some((url.host_str()?.to_string(), url.port_or_known_default()?))
+}
+
+/// Requests a judge through the proxy client.
+async fn ask_judge(client: &Client, judge: &str, config: &ValidatorConfig) -> JudgeVerdict {
+    let start_time = std::time::Instant::now();
     
     // Set a timeout for the request
     let request_timeout = Duration::from_secs(config.request_timeout);
-    let response = match timeout(request_timeout, client.get(test_url).send()).await {
-        Ok(result) => result,
-        Err(_) => {
-            return ValidationResult {
-                is_working: false,
-                response_time: None,
-                is_anonymous: None,
-                country: None,
-                error: Some("Request timed out".to_string()),
-            };
-        }
+    let response = match timeout(request_timeout, client.get(judge).send()).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => return JudgeVerdict::failed(judge, None, format!("Request failed: {}", e)),
+        Err(_) => return JudgeVerdict::failed(judge, None, "Request timed out".to_string()),
     };
     
-    // Check if the request was successful
-    match response {
-        Ok(resp) => {
-            // Calculate response time
-            let elapsed = start_time.elapsed();
-            let response_time = elapsed.as_millis() as i64;
-            
-            // Check status code
-            if !resp.status().is_success() {
-                return ValidationResult {
-                    is_working: false,
-                    response_time: Some(response_time),
-                    is_anonymous: None,
-                    country: None,
-                    error: Some(format!("HTTP error: {}", resp.status())),
-                };
-            }
-            
-            // Try to parse the response
-            match resp.text().await {
-                Ok(_) => {
-                    // Update proxy fields
-                    proxy.last_checked = Some(Utc::now());
-                    proxy.response_time = Some(response_time);
-                    
-                    // Increment success rate
-                    proxy.success_rate = proxy.success_rate * 0.8 + 0.2;
-                    
-                    // Adjust weight based on response time
-                    // Lower response time = higher weight (max 10)
-                    if response_time < 100 {
-                        proxy.weight = 10.0;
-                    } else if response_time < 200 {
-                        proxy.weight = 8.0;
-                    } else if response_time < 500 {
-                        proxy.weight = 5.0;
-                    } else if response_time < 1000 {
-                        proxy.weight = 3.0;
-                    } else {
-                        proxy.weight = 1.0;
-                    }
-                    
-                    ValidationResult {
-                        is_working: true,
-                        response_time: Some(response_time),
-                        is_anonymous: None,
-                        country: None,
-                        error: None,
-                    }
-                },
-                Err(e) => {
-                    // Decrement success rate
-                    proxy.success_rate = proxy.success_rate * 0.8;
-                    
-                    ValidationResult {
-                        is_working: false,
-                        response_time: Some(response_time),
-                        is_anonymous: None,
-                        country: None,
-                        error: Some(format!("Failed to parse response: {}", e)),
-                    }
-                }
-            }
-        },
+    // Calculate response time
+    let response_time = start_time.elapsed().as_millis() as i64;
+    
+    // Check status code
+    if !response.status().is_success() {
+        return JudgeVerdict::failed(judge, Some(response_time), format!("HTTP error: {}", response.status()));
+    }
+    
+    match response.text().await {
+        Ok(body) => judge_verdict(judge, response_time, &body, config),
+        Err(e) => JudgeVerdict::failed(judge, Some(response_time), format!("Failed to parse response: {}", e)),
+    }
+}
+
+/// Requests a judge through a SOCKS4 proxy.
+async fn ask_judge_through_socks4(proxy: &Proxy, judge: &str, config: &ValidatorConfig) -> JudgeVerdict {
+    let start_time = std::time::Instant::now();
+    
+    let request_timeout = Duration::from_secs(config.request_timeout);
+    let (status, body) = match timeout(request_timeout, socks4_get(proxy, judge)).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => return JudgeVerdict::failed(judge, None, format!("Request failed: {}", e)),
+        Err(_) => return JudgeVerdict::failed(judge, None, "Request timed out".to_string()),
+    };
+    
+    let response_time = start_time.elapsed().as_millis() as i64;
+    
+    if !(200..300).contains(&status) {
+        return JudgeVerdict::failed(judge, Some(response_time), format!("HTTP error: {}", status));
+    }
+    
+    judge_verdict(judge, response_time, &body, config)
+}
+
+/// Reads the verdict of a judge from its answer.
+fn judge_verdict(judge: &str, response_time: i64, body: &str, config: &ValidatorConfig) -> JudgeVerdict {
+    // Judges without headers in their answer (like httpbin's /ip) only report the exit IP
+    let judged = serde_json::from_str::<JudgeResponse>(body).ok();
+    let origin = judged.as_ref()
+        .and_then(|judged| judged.origin.split(',').next())
+        .map(|ip| ip.trim().to_string())
+        .filter(|ip| !ip.is_empty());
+    let is_anonymous = judged
+        .filter(|judged| config.check_anonymity && (!judged.headers.is_empty() || config.real_ip.is_some()))
+        .map(|judged| !judged.reveals_proxy(config.real_ip.as_deref()));
+    
+    JudgeVerdict {
+        judge: judge.to_string(),
+        response_time: Some(response_time),
+        origin,
+        is_anonymous,
+        error: None,
+    }
+}
+
+/// Looks up the public IP of this machine through the first judge.
+async fn lookup_real_ip(config: &ValidatorConfig) -> Option<String> {
+    let judge = config.judge_urls(false).first()?.to_string();
+    let client = Client::builder()
+        .timeout(Duration::from_secs(config.request_timeout))
+        .build()
+        .ok()?;
+    
+    let judged: JudgeResponse = client.get(&judge).send().await.ok()?.json().await.ok()?;
+    let ip = judged.origin.split(',').next()?.trim().to_string();
+    debug!("Public IP according to {} is {}", judge, ip);
+    Some(ip).filter(|ip| !ip.is_empty())
+}
+
+/// Classifies a proxy by the ASN of its exit IP and locates it.
+///
+/// The exit IP is the one reported by the judges, falling back to the
+/// proxy's own IP. The lookup is made without the proxy.
+async fn enrich_proxy(
+    proxy: &mut Proxy,
+    exit_ip: Option<&str>,
+    classify: bool,
+    locate: bool,
+    config: &ValidatorConfig,
+) {
+    let exit_ip = exit_ip.map(str::to_string).unwrap_or_else(|| proxy.ip.clone());
+    
+    let client = match Client::builder()
+        .connect_timeout(Duration::from_secs(config.connect_timeout))
+        .timeout(Duration::from_secs(config.request_timeout))
+        .build()
+    {
+        Ok(client) => client,
         Err(e) => {
-            // Decrement success rate
-            proxy.success_rate = proxy.success_rate * 0.8;
-            
-            ValidationResult {
-                is_working: false,
-                response_time: None,
-                is_anonymous: None,
-                country: None,
-                error: Some(format!("Request failed: {}", e)),
-            }
+            error!("Failed to build IP lookup client: {}", e);
+            return;
         }
+    };
+    
+    let geo = match lookup_geo(&client, &config.asn_lookup_url, &exit_ip).await {
+        Some(geo) => geo,
+        None => return,
+    };
+    
+    if let Some(info) = geo.asn.filter(|_| classify) {
+        proxy.asn = Some(info.asn);
+        proxy.class = AsnDatabase::bundled().classify(info.asn, info.name.as_deref());
+        debug!("Classified proxy {} (AS{}) as {}", proxy, info.asn, proxy.class);
+    }
+    
+    if locate && geo.country.is_some() {
+        proxy.country = geo.country;
+        proxy.city = geo.city;
+        debug!(
+            "Located proxy {} in {}{}",
+            proxy,
+            proxy.city.as_deref().map(|city| format!("{}, ", city)).unwrap_or_default(),
+            proxy.country.as_deref().unwrap_or_default(),
+        );
     }
 }
 
@@ -203,6 +548,13 @@ pub async fn validate_proxies(
     
     info!("Validating {} proxies with concurrency {}", proxies.len(), concurrency);
     
+    // Learn the public IP once, so that every proxy leaking it is caught
+    let mut config = config.clone();
+    if config.check_anonymity && config.real_ip.is_none() {
+        config.real_ip = lookup_real_ip(&config).await;
+    }
+    let config = &config;
+    
     let results = stream::iter(proxies.iter_mut())
         .map(|proxy| async move {
             let result = validate_proxy(proxy, config).await;
@@ -224,13 +576,7 @@ pub async fn validate_proxies(
         .map(|proxy| {
             result_map
                 .remove(&proxy.id)
-                .unwrap_or_else(|| ValidationResult {
-                    is_working: false,
-                    response_time: None,
-                    is_anonymous: None,
-                    country: None,
-                    error: Some("Result not found".to_string()),
-                })
+                .unwrap_or_else(|| ValidationResult::failed("Result not found".to_string(), None, Vec::new()))
         })
         .collect()
 }
@@ -248,4 +594,37 @@ mod tests {
         assert!(!result.is_working);
         assert!(result.error.is_some());
     }
-} 
\ No newline at end of file
+    
+    #[test]
+    fn test_consensus() {
+        let verdict = |time: i64, anonymous: bool| JudgeVerdict {
+            judge: "http://judge".to_string(),
+            response_time: Some(time),
+            origin: Some("203.0.113.7".to_string()),
+            is_anonymous: Some(anonymous),
+            error: None,
+        };
+        let down = JudgeVerdict::failed("http://down", None, "Request timed out".to_string());
+        
+        // Two honest judges outvote one that claims the proxy is transparent
+        let verdicts = vec![verdict(300, true), verdict(100, false), verdict(200, true), down.clone()];
+        let consensus = Consensus::from_verdicts(&verdicts, 2);
+        assert!(consensus.is_working);
+        assert_eq!(consensus.response_time, Some(200));
+        assert_eq!(consensus.is_anonymous, Some(true));
+        assert_eq!(consensus.origin.as_deref(), Some("203.0.113.7"));
+        
+        // Without enough agreement the anonymity stays unknown
+        let consensus = Consensus::from_verdicts(&verdicts[..2], 2);
+        assert!(consensus.is_working);
+        assert_eq!(consensus.is_anonymous, None);
+        
+        let consensus = Consensus::from_verdicts(&[verdict(100, true), down], 2);
+        assert!(!consensus.is_working);
+        assert_eq!(consensus.response_time, None);
+        
+        let config = ValidatorConfig { min_agreement: 5, ..Default::default() };
+        assert_eq!(config.judge_urls(true), vec!["https://httpbin.org/ip"]);
+        assert_eq!(config.required_agreement(3), 3);
+    }
+}
@@ -0,0 +1,141 @@
+//! Rate limit state reported by servers
+//!
+//! Many APIs report how much of their limit is left with `x-ratelimit-*`
+//! headers (Reddit, GitHub, most REST APIs) or the `ratelimit-*` headers of
+//! the IETF draft. Passing them to
+//! [`RateLimiter::update_from_headers`](crate::RateLimiter::update_from_headers)
+//! keeps the limiter in step with the server's own accounting.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Values of reset headers above this are Unix timestamps, not delays
+const EPOCH_THRESHOLD: f64 = 1_000_000_000.0;
+
+/// Longest delay taken from a header, so broken or hostile values cannot
+/// hold requests back indefinitely
+const MAX_DELAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Rate limit state parsed from response headers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RateLimitHeaders {
+    /// Total weight allowed in the current window (`x-ratelimit-limit`)
+    pub limit: Option<u32>,
+
+    /// Weight left in the current window (`x-ratelimit-remaining`)
+    pub remaining: Option<u32>,
+
+    /// Weight used in the current window (`x-ratelimit-used`)
+    pub used: Option<u32>,
+
+    /// Time until the window resets (`x-ratelimit-reset`)
+    pub reset: Option<Duration>,
+
+    /// Time to wait before the next request (`retry-after`, in seconds)
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimitHeaders {
+    /// Parse the headers returned by `get`, which looks up a header by its
+    /// lowercase name
+    ///
+    /// Counts may be fractional, as Reddit's are. Reset values are read as
+    /// seconds from now, or as a Unix timestamp when they are that large.
+    /// Delays are capped at a day.
+    pub fn parse<'a>(get: impl Fn(&str) -> Option<&'a str>) -> Self {
+        let header = |names: &[&str]| {
+            names.iter()
+                .find_map(|name| get(name))
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .filter(|value| value.is_finite() && *value >= 0.0)
+        };
+        let count = |names: &[&str]| header(names).map(|value| value.min(u32::MAX as f64) as u32);
+
+        Self {
+            limit: count(&["x-ratelimit-limit", "ratelimit-limit"]),
+            remaining: count(&["x-ratelimit-remaining", "ratelimit-remaining"]),
+            used: count(&["x-ratelimit-used", "ratelimit-used"]),
+            reset: header(&["x-ratelimit-reset", "ratelimit-reset"]).map(reset_delay),
+            retry_after: header(&["retry-after"]).map(delay),
+        }
+    }
+
+    /// Parse the headers of an `http` response
+    #[cfg(feature = "http")]
+    pub fn from_header_map(headers: &http::HeaderMap) -> Self {
+        Self::parse(|name| headers.get(name).and_then(|value| value.to_str().ok()))
+    }
+
+    /// Weight left in the current window, derived from the used weight if
+    /// the server does not report it directly
+    pub fn remaining(&self) -> Option<u32> {
+        self.remaining.or_else(|| Some(self.limit?.saturating_sub(self.used?)))
+    }
+
+    /// Whether any rate limit header was present
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Convert a reset header value to the time until the reset
+fn reset_delay(value: f64) -> Duration {
+    if value < EPOCH_THRESHOLD {
+        return delay(value);
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    delay((value - now).max(0.0))
+}
+
+/// Convert a non-negative number of seconds to a delay of at most [`MAX_DELAY`]
+fn delay(secs: f64) -> Duration {
+    Duration::from_secs_f64(secs.min(MAX_DELAY.as_secs_f64()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn parse(headers: &[(&str, &str)]) -> RateLimitHeaders {
+        let headers: HashMap<&str, &str> = headers.iter().copied().collect();
+        RateLimitHeaders::parse(|name| headers.get(name).copied())
+    }
+
+    #[test]
+    fn test_parse() {
+        let headers = parse(&[
+            ("x-ratelimit-remaining", "598.0"),
+            ("x-ratelimit-used", "2"),
+            ("x-ratelimit-reset", "347"),
+        ]);
+        assert_eq!(headers.remaining, Some(598));
+        assert_eq!(headers.used, Some(2));
+        assert_eq!(headers.reset, Some(Duration::from_secs(347)));
+        assert_eq!(headers.retry_after, None);
+
+        let headers = parse(&[("ratelimit-limit", "100"), ("ratelimit-used", "40"), ("retry-after", "5")]);
+        assert_eq!(headers.remaining(), Some(60));
+        assert_eq!(headers.retry_after, Some(Duration::from_secs(5)));
+
+        let reset = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + 60;
+        let headers = parse(&[("x-ratelimit-reset", &reset.to_string())]);
+        assert!(headers.reset.unwrap() > Duration::from_secs(55));
+        assert!(headers.reset.unwrap() <= Duration::from_secs(60));
+
+        assert!(parse(&[("x-ratelimit-remaining", "lots")]).is_empty());
+    }
+
+    #[test]
+    fn test_oversized_delays_are_capped() {
+        let headers = parse(&[("retry-after", "1e30"), ("x-ratelimit-reset", "1e300")]);
+        assert_eq!(headers.retry_after, Some(MAX_DELAY));
+        assert_eq!(headers.reset, Some(MAX_DELAY));
+
+        let headers = parse(&[("ratelimit-reset", "999999999")]);
+        assert_eq!(headers.reset, Some(MAX_DELAY));
+    }
+}
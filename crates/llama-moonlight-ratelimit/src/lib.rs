@@ -0,0 +1,80 @@
+//! # llama-moonlight-ratelimit
+//!
+//! Shared rate limiting for the HTTP clients of the Llama Moonlight ecosystem.
+//!
+//! A [`RateLimiter`] paces requests per key — a provider, an endpoint, or a
+//! host — so that concurrent tasks sharing it stay within each key's limits
+//! instead of getting throttled or banned.
+//!
+//! ## Features
+//!
+//! - Sliding window and token bucket limits, several per key
+//! - Weighted requests for APIs that charge more for expensive endpoints
+//! - Updates from `x-ratelimit-*` and `Retry-After` response headers
+//! - FIFO waiting, with an optional deadline
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use llama_moonlight_ratelimit::{RateLimit, RateLimitHeaders, RateLimiter, Result};
+//! use std::time::Duration;
+//! use tokio::time::Instant;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<()> {
+//!     let limiter = RateLimiter::new().with_default_limits(vec![RateLimit::per_minute(60)]);
+//!
+//!     // Give up if the request cannot be sent within 5 seconds
+//!     limiter.acquire_with_deadline("api.example.com", 1, Instant::now() + Duration::from_secs(5)).await?;
+//!
+//!     // ... send the request, then apply what the server reported
+//!     let headers = RateLimitHeaders::parse(|name| match name {
+//!         "x-ratelimit-remaining" => Some("10"),
+//!         "x-ratelimit-reset" => Some("30"),
+//!         _ => None,
+//!     });
+//!     limiter.update_from_headers("api.example.com", &headers);
+//!     Ok(())
+//! }
+//! ```
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs)]
+
+use std::time::Duration;
+use thiserror::Error;
+
+pub mod headers;
+pub mod limit;
+pub mod limiter;
+
+pub use headers::RateLimitHeaders;
+pub use limit::{Algorithm, RateLimit};
+pub use limiter::RateLimiter;
+
+/// Custom result type for rate limiting
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Error type for rate limiting
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A request can never fit a limit of its key
+    #[error("Request weight {weight} exceeds the {key} limit of {max_weight} per {window:?}")]
+    WeightExceedsLimit {
+        /// Key of the limit
+        key: String,
+        /// Weight of the request
+        weight: u32,
+        /// Maximum weight of the limit
+        max_weight: u32,
+        /// Window of the limit
+        window: Duration,
+    },
+
+    /// A request could not be sent before its deadline
+    #[error("The {key} rate limit allows no request before the deadline")]
+    DeadlineExceeded {
+        /// Key of the limit
+        key: String,
+    },
+}
@@ -0,0 +1,70 @@
+//! Rate limit definitions
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How a limit counts request weight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Algorithm {
+    /// At most `max_weight` within any window of the given length
+    #[default]
+    SlidingWindow,
+
+    /// Bursts of up to `max_weight`, refilled evenly over the window
+    TokenBucket,
+}
+
+/// A limit on request weight within a window
+///
+/// Plain request-count limits use a weight of 1 per request; weight systems
+/// such as Binance's charge more for expensive endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// Maximum total weight within the window
+    pub max_weight: u32,
+
+    /// Length of the window
+    pub window: Duration,
+
+    /// How the weight is counted
+    #[serde(default)]
+    pub algorithm: Algorithm,
+}
+
+impl RateLimit {
+    /// Create a sliding window limit of `max_weight` per `window`
+    pub fn new(max_weight: u32, window: Duration) -> Self {
+        Self {
+            max_weight,
+            window,
+            algorithm: Algorithm::SlidingWindow,
+        }
+    }
+
+    /// Create a per-second limit
+    pub fn per_second(max_weight: u32) -> Self {
+        Self::new(max_weight, Duration::from_secs(1))
+    }
+
+    /// Create a per-minute limit
+    pub fn per_minute(max_weight: u32) -> Self {
+        Self::new(max_weight, Duration::from_secs(60))
+    }
+
+    /// Create a per-hour limit
+    pub fn per_hour(max_weight: u32) -> Self {
+        Self::new(max_weight, Duration::from_secs(60 * 60))
+    }
+
+    /// Create a per-day limit
+    pub fn per_day(max_weight: u32) -> Self {
+        Self::new(max_weight, Duration::from_secs(24 * 60 * 60))
+    }
+
+    /// Count the weight with a token bucket instead of a sliding window
+    pub fn token_bucket(mut self) -> Self {
+        self.algorithm = Algorithm::TokenBucket;
+        self
+    }
+}
@@ -0,0 +1,528 @@
+//! Per-key request pacing
+//!
+//! A [`RateLimiter`] keeps independent limits per key, typically a provider,
+//! endpoint, or host. Requests for a key wait their turn in FIFO order until
+//! each of its limits has room for their weight, and until the server's own
+//! reported limit, if any, has reset.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::debug;
+
+use crate::{Algorithm, Error, RateLimit, RateLimitHeaders, Result};
+
+/// Weight counted against one limit
+#[derive(Debug)]
+enum Meter {
+    /// Weight of recent requests, oldest first, and their total
+    Window { log: VecDeque<(Instant, u32)>, used: u32 },
+
+    /// Tokens left and when they were last refilled
+    Bucket { tokens: f64, refilled: Instant },
+}
+
+/// Usage of one limit
+#[derive(Debug)]
+struct Counter {
+    limit: RateLimit,
+    meter: Meter,
+}
+
+impl Counter {
+    /// Create a counter with the whole limit available
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        let meter = match limit.algorithm {
+            Algorithm::SlidingWindow => Meter::Window { log: VecDeque::new(), used: 0 },
+            Algorithm::TokenBucket => Meter::Bucket { tokens: limit.max_weight as f64, refilled: now },
+        };
+        Self { limit, meter }
+    }
+
+    /// Tokens refilled per second
+    fn rate(&self) -> f64 {
+        self.limit.max_weight as f64 / self.limit.window.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Drop requests that have left the window, or refill tokens
+    fn update(&mut self, now: Instant) {
+        let rate = self.rate();
+        let max = self.limit.max_weight as f64;
+
+        match &mut self.meter {
+            Meter::Window { log, used } => {
+                while let Some(&(at, weight)) = log.front() {
+                    if now.duration_since(at) < self.limit.window {
+                        break;
+                    }
+                    log.pop_front();
+                    *used -= weight;
+                }
+            }
+            Meter::Bucket { tokens, refilled } => {
+                let elapsed = now.duration_since(*refilled).as_secs_f64();
+                *tokens = (*tokens + elapsed * rate).min(max);
+                *refilled = now;
+            }
+        }
+    }
+
+    /// Time until `extra` more weight fits, after `ahead` weight queued before it
+    fn wait_for(&self, now: Instant, ahead: u64, extra: u32) -> Duration {
+        match &self.meter {
+            Meter::Window { log, used } => {
+                let max = self.limit.max_weight as u64;
+                let needed = *used as u64 + ahead + extra as u64;
+                if needed <= max {
+                    return Duration::ZERO;
+                }
+
+                // Weight that has to leave the window first
+                let mut excess = needed - max;
+                for &(at, weight) in log {
+                    if excess <= weight as u64 {
+                        return (at + self.limit.window).saturating_duration_since(now);
+                    }
+                    excess -= weight as u64;
+                }
+
+                // Queued requests will fill further windows as well
+                let last = log.back()
+                    .map(|&(at, _)| (at + self.limit.window).saturating_duration_since(now))
+                    .unwrap_or_default();
+                let windows = excess.div_ceil(max.max(1));
+                last + self.limit.window * windows as u32
+            }
+            Meter::Bucket { tokens, .. } => {
+                let deficit = (ahead + extra as u64) as f64 - tokens;
+                if deficit <= 0.0 {
+                    Duration::ZERO
+                } else {
+                    Duration::from_secs_f64(deficit / self.rate())
+                }
+            }
+        }
+    }
+
+    /// Count a request of `weight` sent now
+    fn record(&mut self, now: Instant, weight: u32) {
+        match &mut self.meter {
+            Meter::Window { log, used } => {
+                log.push_back((now, weight));
+                *used += weight;
+            }
+            Meter::Bucket { tokens, .. } => *tokens -= weight as f64,
+        }
+    }
+
+    /// Weight that could be sent right away
+    fn remaining(&self) -> u32 {
+        match &self.meter {
+            Meter::Window { used, .. } => self.limit.max_weight.saturating_sub(*used),
+            Meter::Bucket { tokens, .. } => tokens.max(0.0) as u32,
+        }
+    }
+}
+
+/// Limit state last reported by the server
+#[derive(Debug)]
+struct Reported {
+    /// Weight left until the reset
+    remaining: u32,
+
+    /// When the server's window resets
+    reset_at: Instant,
+}
+
+/// Limiter state of one key
+#[derive(Debug)]
+struct State {
+    /// One counter per limit
+    counters: Vec<Counter>,
+
+    /// Limit reported in response headers
+    reported: Option<Reported>,
+
+    /// Weight of requests waiting for their turn
+    queued: u64,
+
+    /// Requests are held until this time, e.g. after a 429
+    paused_until: Option<Instant>,
+}
+
+impl State {
+    /// Bring counters and the reported limit up to date
+    fn update(&mut self, now: Instant) {
+        for counter in &mut self.counters {
+            counter.update(now);
+        }
+        if self.reported.as_ref().is_some_and(|reported| now >= reported.reset_at) {
+            self.reported = None;
+        }
+    }
+
+    /// Time until a request of `weight` may be sent
+    fn wait_for(&mut self, now: Instant, ahead: u64, weight: u32) -> Duration {
+        self.update(now);
+
+        let paused = self.paused_until
+            .map(|until| until.saturating_duration_since(now))
+            .unwrap_or_default();
+        let reported = match &self.reported {
+            Some(reported) if (reported.remaining as u64) < ahead + weight as u64 => {
+                reported.reset_at.saturating_duration_since(now)
+            }
+            _ => Duration::ZERO,
+        };
+
+        self.counters.iter()
+            .map(|counter| counter.wait_for(now, ahead, weight))
+            .fold(paused.max(reported), Duration::max)
+    }
+
+    /// Count a request of `weight` sent now
+    fn record(&mut self, now: Instant, weight: u32) {
+        for counter in &mut self.counters {
+            counter.record(now, weight);
+        }
+        if let Some(reported) = &mut self.reported {
+            reported.remaining = reported.remaining.saturating_sub(weight);
+        }
+    }
+
+    /// Hold requests until `until`, keeping a later pause
+    fn pause_until(&mut self, until: Instant) {
+        self.paused_until = Some(self.paused_until.map_or(until, |current| current.max(until)));
+    }
+}
+
+/// Removes a request's weight from the queue when it is sent or cancelled
+struct Queued<'a> {
+    state: &'a Mutex<State>,
+    weight: u32,
+}
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.queued -= self.weight as u64;
+        }
+    }
+}
+
+/// Rate limiter of one key
+#[derive(Debug)]
+struct Limiter {
+    /// Usage of the key's limits
+    state: Mutex<State>,
+
+    /// Serializes waiting requests in arrival order
+    turn: tokio::sync::Mutex<()>,
+}
+
+impl Limiter {
+    /// Create a limiter with the given limits
+    fn new(limits: &[RateLimit]) -> Self {
+        let now = Instant::now();
+        Self {
+            state: Mutex::new(State {
+                counters: limits.iter().map(|&limit| Counter::new(limit, now)).collect(),
+                reported: None,
+                queued: 0,
+                paused_until: None,
+            }),
+            turn: tokio::sync::Mutex::new(()),
+        }
+    }
+}
+
+/// Paces requests per key according to their rate limits
+///
+/// Share one limiter (e.g. in an `Arc`) between all tasks that talk to the
+/// same service, so they draw from the same limits. Keys without limits of
+/// their own use the default limits; if there are none, they are only paced
+/// by what the server reports through [`update_from_headers`](Self::update_from_headers)
+/// and [`pause`](Self::pause).
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    /// Limiters by key
+    limiters: Mutex<HashMap<String, Arc<Limiter>>>,
+
+    /// Limits of keys that were not registered
+    default_limits: Vec<RateLimit>,
+}
+
+impl RateLimiter {
+    /// Create a limiter without any limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the limits of keys that are not registered with their own
+    pub fn with_default_limits(mut self, limits: Vec<RateLimit>) -> Self {
+        self.default_limits = limits;
+        self
+    }
+
+    /// Set the limits of a key, replacing earlier limits
+    ///
+    /// An empty list makes the key fall back to the default limits.
+    pub fn register(&self, key: &str, limits: Vec<RateLimit>) {
+        let mut limiters = self.limiters.lock().unwrap();
+        if limits.is_empty() {
+            limiters.remove(key);
+            return;
+        }
+
+        limiters.insert(key.to_string(), Arc::new(Limiter::new(&limits)));
+    }
+
+    /// Get the limits of a key
+    pub fn limits(&self, key: &str) -> Vec<RateLimit> {
+        match self.limiters.lock().unwrap().get(key) {
+            Some(limiter) => limiter.state.lock().unwrap().counters.iter().map(|c| c.limit).collect(),
+            None => self.default_limits.clone(),
+        }
+    }
+
+    /// Get the limiter of a key, creating it if the key has default limits
+    /// or `create` is set
+    fn limiter(&self, key: &str, create: bool) -> Option<Arc<Limiter>> {
+        let mut limiters = self.limiters.lock().unwrap();
+        if let Some(limiter) = limiters.get(key) {
+            return Some(limiter.clone());
+        }
+        if !create && self.default_limits.is_empty() {
+            return None;
+        }
+
+        let limiter = Arc::new(Limiter::new(&self.default_limits));
+        limiters.insert(key.to_string(), limiter.clone());
+        Some(limiter)
+    }
+
+    /// Wait until a request of `weight` may be sent for a key
+    ///
+    /// The request's weight counts against the key's limits from the moment
+    /// this returns.
+    pub async fn acquire(&self, key: &str, weight: u32) -> Result<()> {
+        self.acquire_until(key, weight, None).await
+    }
+
+    /// Wait until a request of `weight` may be sent for a key, giving up if
+    /// that is not possible before `deadline`
+    ///
+    /// Fails right away, without waiting, when the limits already show that
+    /// the deadline cannot be met.
+    pub async fn acquire_with_deadline(&self, key: &str, weight: u32, deadline: Instant) -> Result<()> {
+        self.acquire_until(key, weight, Some(deadline)).await
+    }
+
+    /// Wait for a request's turn, up to an optional deadline
+    async fn acquire_until(&self, key: &str, weight: u32, deadline: Option<Instant>) -> Result<()> {
+        let limiter = match self.limiter(key, false) {
+            Some(limiter) => limiter,
+            None => return Ok(()),
+        };
+
+        {
+            let state = limiter.state.lock().unwrap();
+            if let Some(limit) = state.counters.iter().map(|c| c.limit).find(|l| weight > l.max_weight) {
+                return Err(Error::WeightExceedsLimit {
+                    key: key.to_string(),
+                    weight,
+                    max_weight: limit.max_weight,
+                    window: limit.window,
+                });
+            }
+        }
+
+        let deadline_exceeded = || Error::DeadlineExceeded { key: key.to_string() };
+
+        limiter.state.lock().unwrap().queued += weight as u64;
+        let _queued = Queued { state: &limiter.state, weight };
+        let _turn = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline, limiter.turn.lock())
+                .await
+                .map_err(|_| deadline_exceeded())?,
+            None => limiter.turn.lock().await,
+        };
+
+        loop {
+            let now = Instant::now();
+            let wait = {
+                let mut state = limiter.state.lock().unwrap();
+                let wait = state.wait_for(now, 0, weight);
+
+                if wait.is_zero() {
+                    state.record(now, weight);
+                    return Ok(());
+                }
+                wait
+            };
+
+            if deadline.is_some_and(|deadline| now + wait > deadline) {
+                return Err(deadline_exceeded());
+            }
+
+            debug!("Waiting {:?} for the {} rate limit", wait, key);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Estimate how long a request of `weight` would wait if made now
+    ///
+    /// Accounts for requests already queued for the key.
+    pub fn estimated_wait(&self, key: &str, weight: u32) -> Duration {
+        match self.limiter(key, false) {
+            Some(limiter) => {
+                let mut state = limiter.state.lock().unwrap();
+                let queued = state.queued;
+                state.wait_for(Instant::now(), queued, weight)
+            }
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Weight that could be sent for a key right away, if it is limited
+    pub fn remaining(&self, key: &str) -> Option<u32> {
+        let limiter = self.limiter(key, false)?;
+        let mut state = limiter.state.lock().unwrap();
+        state.update(Instant::now());
+
+        state.counters.iter()
+            .map(Counter::remaining)
+            .chain(state.reported.as_ref().map(|reported| reported.remaining))
+            .min()
+    }
+
+    /// Hold all requests for a key for a while
+    ///
+    /// Use this when a server reports that a limit was hit anyway, e.g.
+    /// with the `Retry-After` of a 429 response.
+    pub fn pause(&self, key: &str, duration: Duration) {
+        if let Some(limiter) = self.limiter(key, true) {
+            limiter.state.lock().unwrap().pause_until(Instant::now() + duration);
+        }
+    }
+
+    /// Apply the rate limit state reported in a response for a key
+    ///
+    /// The remaining weight is only used together with the time of the
+    /// reset, so that a server reporting an empty limit cannot stall the key
+    /// indefinitely. A `Retry-After` pauses the key.
+    pub fn update_from_headers(&self, key: &str, headers: &RateLimitHeaders) {
+        if headers.is_empty() {
+            return;
+        }
+        let Some(limiter) = self.limiter(key, true) else { return };
+
+        let now = Instant::now();
+        let mut state = limiter.state.lock().unwrap();
+        if let (Some(remaining), Some(reset)) = (headers.remaining(), headers.reset) {
+            debug!("{} has {} left in its rate limit, resetting in {:?}", key, remaining, reset);
+            state.reported = Some(Reported { remaining, reset_at: now + reset });
+        }
+        if let Some(retry_after) = headers.retry_after {
+            state.pause_until(now + retry_after);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_acquire_paces_requests() {
+        let limiter = Arc::new(RateLimiter::new());
+        limiter.register("test", vec![RateLimit::new(2, Duration::from_millis(200))]);
+
+        let start = Instant::now();
+        let tasks: Vec<_> = (0..5)
+            .map(|_| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move { limiter.acquire("test", 1).await })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        // 5 requests at 2 per 200ms need two full windows
+        assert_eq!(start.elapsed(), Duration::from_millis(400));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_weights_and_estimates() {
+        let limiter = RateLimiter::new();
+        limiter.register("binance", vec![RateLimit::new(10, Duration::from_secs(60))]);
+
+        limiter.acquire("binance", 6).await.unwrap();
+        assert_eq!(limiter.estimated_wait("binance", 4), Duration::ZERO);
+        assert_eq!(limiter.estimated_wait("binance", 5), Duration::from_secs(60));
+        assert_eq!(limiter.remaining("binance"), Some(4));
+
+        assert!(matches!(limiter.acquire("binance", 11).await, Err(Error::WeightExceedsLimit { .. })));
+        assert_eq!(limiter.estimated_wait("unlimited", 100), Duration::ZERO);
+        assert_eq!(limiter.remaining("unlimited"), None);
+        limiter.acquire("unlimited", 100).await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_token_bucket() {
+        let limiter = RateLimiter::new()
+            .with_default_limits(vec![RateLimit::per_second(10).token_bucket()]);
+
+        // A full bucket allows a burst, then refills at 10 per second
+        limiter.acquire("host", 10).await.unwrap();
+        assert_eq!(limiter.estimated_wait("host", 2), Duration::from_millis(200));
+
+        let start = Instant::now();
+        limiter.acquire("host", 2).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_millis(200));
+        assert_eq!(limiter.remaining("host"), Some(0));
+        assert_eq!(limiter.limits("other"), vec![RateLimit::per_second(10).token_bucket()]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_deadline() {
+        let limiter = RateLimiter::new();
+        limiter.register("test", vec![RateLimit::per_minute(1)]);
+        limiter.acquire("test", 1).await.unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let result = limiter.acquire_with_deadline("test", 1, deadline).await;
+        assert!(matches!(result, Err(Error::DeadlineExceeded { .. })));
+        assert!(Instant::now() < deadline);
+
+        let deadline = Instant::now() + Duration::from_secs(90);
+        limiter.acquire_with_deadline("test", 1, deadline).await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_update_from_headers() {
+        let limiter = RateLimiter::new();
+        limiter.update_from_headers("reddit", &RateLimitHeaders {
+            remaining: Some(1),
+            reset: Some(Duration::from_secs(30)),
+            ..Default::default()
+        });
+        assert_eq!(limiter.remaining("reddit"), Some(1));
+
+        limiter.acquire("reddit", 1).await.unwrap();
+        assert_eq!(limiter.estimated_wait("reddit", 1), Duration::from_secs(30));
+
+        let start = Instant::now();
+        limiter.acquire("reddit", 1).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_secs(30));
+        assert_eq!(limiter.remaining("reddit"), None);
+
+        limiter.update_from_headers("reddit", &RateLimitHeaders {
+            retry_after: Some(Duration::from_secs(5)),
+            ..Default::default()
+        });
+        assert_eq!(limiter.estimated_wait("reddit", 1), Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,270 @@
+//! Conditional requests and response caching
+//!
+//! Monitoring bots poll the same listings over and over, and most of those
+//! responses are unchanged. With a [`CacheConfig`] set on the
+//! [`ClientConfig`](crate::ClientConfig), `GET` responses carrying an `ETag`
+//! or `Last-Modified` header are kept, and the next request for the same URL
+//! sends `If-None-Match` or `If-Modified-Since`. A `304 Not Modified` answer
+//! is then served from the cache. Responses younger than
+//! [`CacheConfig::fresh_for`] are served without any request, so they cost
+//! nothing against the rate limit.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use reqwest::header::{self, HeaderMap, HeaderName};
+
+/// Configuration of the response cache
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// Maximum number of responses kept, the oldest being evicted first
+    pub max_entries: usize,
+    
+    /// How long a response is served without revalidating it
+    pub fresh_for: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 256,
+            fresh_for: Duration::ZERO,
+        }
+    }
+}
+
+impl CacheConfig {
+    /// Set the maximum number of responses kept
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+    
+    /// Set how long a response is served without revalidating it
+    pub fn with_fresh_for(mut self, fresh_for: Duration) -> Self {
+        self.fresh_for = fresh_for;
+        self
+    }
+}
+
+/// Counts of the requests answered with the cache's help
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Responses served from the cache without a request
+    pub hits: u64,
+    
+    /// Requests answered with `304 Not Modified`
+    pub revalidated: u64,
+    
+    /// Requests answered with a full response
+    pub misses: u64,
+}
+
+/// A cached response body and its validators
+#[derive(Debug, Clone)]
+struct CachedResponse {
+    /// `ETag` header of the response
+    etag: Option<String>,
+    
+    /// `Last-Modified` header of the response
+    last_modified: Option<String>,
+    
+    /// Body of the response
+    body: Vec<u8>,
+    
+    /// When the response was received or last revalidated
+    stored_at: Instant,
+}
+
+/// Cache of `GET` responses keyed by URL and query parameters
+#[derive(Debug)]
+pub struct ResponseCache {
+    /// Cache configuration
+    config: CacheConfig,
+    
+    /// Cached responses by key
+    entries: Mutex<HashMap<String, CachedResponse>>,
+    
+    /// Counts of cache outcomes
+    stats: Mutex<CacheStats>,
+}
+
+impl ResponseCache {
+    /// Create an empty cache
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(HashMap::new()),
+            stats: Mutex::new(CacheStats::default()),
+        }
+    }
+    
+    /// Get the key of a request, independent of the order of its parameters
+    pub(crate) fn key(url: &str, params: Option<&HashMap<String, String>>) -> String {
+        let mut params: Vec<_> = params.into_iter().flatten().collect();
+        params.sort();
+        
+        let mut key = url.to_string();
+        for (i, (name, value)) in params.into_iter().enumerate() {
+            key.push(if i == 0 { '?' } else { '&' });
+            key.push_str(name);
+            key.push('=');
+            key.push_str(value);
+        }
+        key
+    }
+    
+    /// Get the body of a response still fresh enough to serve without a request
+    pub(crate) fn fresh(&self, key: &str) -> Option<Vec<u8>> {
+        if self.config.fresh_for.is_zero() {
+            return None;
+        }
+        
+        let body = self.entries.lock().unwrap().get(key)
+            .filter(|entry| entry.stored_at.elapsed() < self.config.fresh_for)
+            .map(|entry| entry.body.clone())?;
+        self.stats.lock().unwrap().hits += 1;
+        Some(body)
+    }
+    
+    /// Get the headers making a request conditional on the cached response
+    pub(crate) fn conditional_headers(&self, key: &str) -> Vec<(HeaderName, String)> {
+        let entries = self.entries.lock().unwrap();
+        let Some(entry) = entries.get(key) else {
+            return Vec::new();
+        };
+        
+        let mut headers = Vec::new();
+        if let Some(etag) = &entry.etag {
+            headers.push((header::IF_NONE_MATCH, etag.clone()));
+        }
+        if let Some(last_modified) = &entry.last_modified {
+            headers.push((header::IF_MODIFIED_SINCE, last_modified.clone()));
+        }
+        headers
+    }
+    
+    /// Get the cached body of a response answered with `304 Not Modified`
+    pub(crate) fn not_modified(&self, key: &str) -> Option<Vec<u8>> {
+        let body = self.entries.lock().unwrap().get_mut(key).map(|entry| {
+            entry.stored_at = Instant::now();
+            entry.body.clone()
+        })?;
+        self.stats.lock().unwrap().revalidated += 1;
+        Some(body)
+    }
+    
+    /// Keep a full response if it can be revalidated or served while fresh
+    pub(crate) fn store(&self, key: &str, headers: &HeaderMap, body: &[u8]) {
+        self.stats.lock().unwrap().misses += 1;
+        
+        let header_value = |name: HeaderName| headers.get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let etag = header_value(header::ETAG);
+        let last_modified = header_value(header::LAST_MODIFIED);
+        
+        let mut entries = self.entries.lock().unwrap();
+        if (etag.is_none() && last_modified.is_none() && self.config.fresh_for.is_zero()) || self.config.max_entries == 0 {
+            entries.remove(key);
+            return;
+        }
+        
+        if entries.len() >= self.config.max_entries && !entries.contains_key(key) {
+            let oldest = entries.iter()
+                .min_by_key(|(_, entry)| entry.stored_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        
+        entries.insert(key.to_string(), CachedResponse {
+            etag,
+            last_modified,
+            body: body.to_vec(),
+            stored_at: Instant::now(),
+        });
+    }
+    
+    /// Get the number of cached responses
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+    
+    /// Check whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// Drop all cached responses
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+    
+    /// Get the counts of cache outcomes
+    pub fn stats(&self) -> CacheStats {
+        *self.stats.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    
+    #[test]
+    fn test_key_ignores_parameter_order() {
+        let params: HashMap<String, String> = [("limit", "25"), ("after", "t3_abc")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        
+        assert_eq!(ResponseCache::key("https://oauth.reddit.com/r/rust/new", Some(&params)), "https://oauth.reddit.com/r/rust/new?after=t3_abc&limit=25");
+        assert_eq!(ResponseCache::key("https://oauth.reddit.com/r/rust/new", None), "https://oauth.reddit.com/r/rust/new");
+    }
+    
+    #[test]
+    fn test_revalidation() {
+        let cache = ResponseCache::new(CacheConfig::default().with_max_entries(1));
+        
+        // Responses without validators are not kept
+        cache.store("a", &HeaderMap::new(), b"{}");
+        assert!(cache.is_empty());
+        assert!(cache.conditional_headers("a").is_empty());
+        
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ETAG, HeaderValue::from_static("\"v1\""));
+        cache.store("a", &headers, b"[1]");
+        assert_eq!(cache.conditional_headers("a"), vec![(header::IF_NONE_MATCH, "\"v1\"".to_string())]);
+        assert_eq!(cache.fresh("a"), None);
+        assert_eq!(cache.not_modified("a"), Some(b"[1]".to_vec()));
+        
+        // The oldest response makes room for a new one
+        headers.insert(header::LAST_MODIFIED, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        cache.store("b", &headers, b"[2]");
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.not_modified("a"), None);
+        assert_eq!(cache.conditional_headers("b").len(), 2);
+        
+        assert_eq!(cache.stats(), CacheStats {
+            hits: 0,
+            revalidated: 1,
+            misses: 3,
+        });
+    }
+    
+    #[test]
+    fn test_fresh_responses() {
+        let cache = ResponseCache::new(CacheConfig::default().with_fresh_for(Duration::from_secs(60)));
+        
+        // Fresh responses are kept even without validators
+        cache.store("a", &HeaderMap::new(), b"[1]");
+        assert_eq!(cache.fresh("a"), Some(b"[1]".to_vec()));
+        assert_eq!(cache.fresh("b"), None);
+        assert_eq!(cache.stats().hits, 1);
+        
+        cache.clear();
+        assert_eq!(cache.fresh("a"), None);
+    }
+}
@@ -14,9 +14,10 @@ use serde_json::json;
 use log::{debug, info, warn, error};
 
 use crate::{Result, Error, API_BASE, DEFAULT_USER_AGENT, Scope, Sort, TimeRange, VoteDirection};
+use crate::cache::{CacheConfig, CacheStats, ResponseCache};
 use crate::auth::{Authenticator, TokenResponse, Credentials, TokenStore, MemoryTokenStore};
 use crate::models::{Thing, Listing, ListingData, Post, Comment, Subreddit, User, Message};
-use crate::throttle::RateLimiter;
+use crate::throttle::{AimdConfig, RateLimiter};
 use crate::subreddit::SubredditClient;
 use crate::user::UserClient;
 use crate::post::PostClient;
@@ -30,6 +31,9 @@ use crate::stealth::StealthConfig;
 #[cfg(feature = "tor")]
 use crate::tor::TorConfig;
 
+#[cfg(feature = "mock")]
+use crate::mock::MockTransport;
+
 /// Configuration for the Reddit client
 #[derive(Debug, Clone)]
 pub struct ClientConfig {
@@ -51,6 +55,9 @@ pub struct ClientConfig {
     /// Whether to use the rate limiter
     pub use_rate_limiter: bool,
 
+    /// Parameters of the rate limiter's adaptive concurrency limit
+    pub aimd: AimdConfig,
+
     /// Whether to log requests
     pub log_requests: bool,
 
@@ -64,6 +71,9 @@ pub struct ClientConfig {
     
     /// Request headers to include with every request
     pub custom_headers: HashMap<String, String>,
+    
+    /// Cache of `GET` responses revalidated with conditional requests
+    pub response_cache: Option<CacheConfig>,
 }
 
 impl Default for ClientConfig {
@@ -75,12 +85,14 @@ impl Default for ClientConfig {
             auto_retry: true,
             max_retries: 3,
             use_rate_limiter: true,
+            aimd: AimdConfig::default(),
             log_requests: true,
             #[cfg(feature = "stealth")]
             stealth_config: None,
             #[cfg(feature = "tor")]
             tor_config: None,
             custom_headers: HashMap::new(),
+            response_cache: None,
         }
     }
 }
@@ -122,6 +134,12 @@ impl ClientConfig {
         self
     }
 
+    /// Set the parameters of the rate limiter's adaptive concurrency limit
+    pub fn with_aimd(mut self, aimd: AimdConfig) -> Self {
+        self.aimd = aimd;
+        self
+    }
+
     /// Enable or disable request logging
     pub fn with_log_requests(mut self, log_requests: bool) -> Self {
         self.log_requests = log_requests;
@@ -134,6 +152,12 @@ impl ClientConfig {
         self
     }
 
+    /// Cache `GET` responses and revalidate them with `ETag` and `Last-Modified`
+    pub fn with_response_cache(mut self, cache: CacheConfig) -> Self {
+        self.response_cache = Some(cache);
+        self
+    }
+
     /// Configure stealth mode (requires stealth feature)
     #[cfg(feature = "stealth")]
     pub fn with_stealth(mut self, stealth_config: StealthConfig) -> Self {
@@ -180,13 +204,20 @@ pub struct RedditClient {
     token_store: Arc<dyn TokenStore>,
     
     /// Rate limiter for managing request rates
-    rate_limiter: Arc<RwLock<RateLimiter>>,
+    rate_limiter: Arc<RateLimiter>,
     
     /// Client state
     state: Arc<RwLock<ClientState>>,
     
     /// Request counter
     request_count: Arc<Mutex<u64>>,
+    
+    /// Cache of `GET` responses
+    cache: Option<Arc<ResponseCache>>,
+    
+    /// Mock transport answering requests instead of Reddit
+    #[cfg(feature = "mock")]
+    mock: Option<MockTransport>,
 }
 
 impl RedditClient {
@@ -216,7 +247,7 @@ impl RedditClient {
         let token_store: Arc<dyn TokenStore> = Arc::new(MemoryTokenStore::new());
         
         // Create the rate limiter
-        let rate_limiter = Arc::new(RwLock::new(RateLimiter::new(600, Duration::from_secs(600))));
+        let rate_limiter = Arc::new(RateLimiter::with_aimd(600, Duration::from_secs(600), config.aimd.clone()));
         
         // Create the client state
         let state = Arc::new(RwLock::new(ClientState {
@@ -229,6 +260,10 @@ impl RedditClient {
         // Create the request counter
         let request_count = Arc::new(Mutex::new(0));
         
+        // Create the response cache if enabled
+        let cache = config.response_cache.clone()
+            .map(|cache_config| Arc::new(ResponseCache::new(cache_config)));
+        
         Ok(Self {
             client,
             config,
@@ -237,6 +272,9 @@ impl RedditClient {
             rate_limiter,
             state,
             request_count,
+            cache,
+            #[cfg(feature = "mock")]
+            mock: None,
         })
     }
     
@@ -246,6 +284,22 @@ impl RedditClient {
         self
     }
     
+    /// Answer every request from a mock transport instead of Reddit (requires mock feature)
+    ///
+    /// The client is treated as authenticated as `mock_user`, so no
+    /// credentials are needed.
+    #[cfg(feature = "mock")]
+    pub fn with_mock_transport(mut self, transport: MockTransport) -> Self {
+        self.mock = Some(transport);
+        self.state = Arc::new(RwLock::new(ClientState {
+            username: Some("mock_user".to_string()),
+            authenticated: true,
+            scopes: vec![Scope::All],
+            token_expires_at: None,
+        }));
+        self
+    }
+    
     /// Authenticate with a username and password (Resource Owner Password Credentials flow)
     pub async fn authenticate_username_password(
         &self,
@@ -319,6 +373,8 @@ impl RedditClient {
                     "submit" => Scope::Submit,
                     "subscribe" => Scope::Vote,
                     "vote" => Scope::Vote,
+                    "wikiread" => Scope::WikiRead,
+                    "wikiedit" => Scope::WikiEdit,
                     "*" => Scope::All,
                     _ => Scope::Read, // Default to Read for unknown scopes
                 })
@@ -366,7 +422,15 @@ impl RedditClient {
         &self.config
     }
     
+    /// Get the response cache, if enabled
+    pub fn response_cache(&self) -> Option<&Arc<ResponseCache>> {
+        self.cache.as_ref()
+    }
+    
     /// Make a GET request to the Reddit API
+    ///
+    /// With a response cache configured, the request is made conditional on
+    /// the cached response and a `304 Not Modified` answer is served from it.
     pub async fn get<T: for<'de> Deserialize<'de>>(
         &self,
         endpoint: &str,
@@ -412,6 +476,17 @@ impl RedditClient {
         params: Option<HashMap<String, String>>,
         body: Option<B>,
     ) -> Result<T> {
+        // Answer from the mock transport if one is configured
+        #[cfg(feature = "mock")]
+        if let Some(mock) = &self.mock {
+            let body = body.map(|b| serde_json::to_value(&b)).transpose()?;
+            {
+                let mut count = self.request_count.lock().await;
+                *count += 1;
+            }
+            return mock.respond(&method, endpoint, params.as_ref(), body);
+        }
+        
         // Check if the client is authenticated when needed
         if !endpoint.contains("/api/v1/access_token") {
             let is_authenticated = self.is_authenticated().await;
@@ -464,12 +539,6 @@ impl RedditClient {
             }
         }
         
-        // Apply rate limiting if enabled
-        if self.config.use_rate_limiter {
-            let mut rate_limiter = self.rate_limiter.write().await;
-            rate_limiter.acquire().await?;
-        }
-        
         // Build the URL
         let url = if endpoint.starts_with("https://") || endpoint.starts_with("http://") {
             endpoint.to_string()
@@ -477,6 +546,23 @@ impl RedditClient {
             format!("{}{}", self.config.api_base, endpoint)
         };
         
+        // Serve fresh cached responses without a request
+        let cache = self.cache.as_ref().filter(|_| method == Method::GET);
+        let cache_key = cache.map(|_| ResponseCache::key(&url, params.as_ref()));
+        if let (Some(cache), Some(key)) = (cache, &cache_key) {
+            if let Some(body) = cache.fresh(key) {
+                debug!("Serving {} from the response cache", url);
+                return parse_body(&body);
+            }
+        }
+        
+        // Apply rate limiting if enabled, holding a concurrency slot until the response arrives
+        let _permit = if self.config.use_rate_limiter {
+            Some(self.rate_limiter.acquire().await?)
+        } else {
+            None
+        };
+        
         // Build the request
         let mut request_builder = self.client.request(method.clone(), &url);
         
@@ -506,6 +592,13 @@ impl RedditClient {
             request_builder = request_builder.header(name, value);
         }
         
+        // Make the request conditional on the cached response
+        if let (Some(cache), Some(key)) = (cache, &cache_key) {
+            for (name, value) in cache.conditional_headers(key) {
+                request_builder = request_builder.header(name, value);
+            }
+        }
+        
         // Log the request if enabled
         if self.config.log_requests {
             debug!("{} {}", method, url);
@@ -522,31 +615,31 @@ impl RedditClient {
         
         // Update rate limits from response headers
         if self.config.use_rate_limiter {
-            let mut rate_limiter = self.rate_limiter.write().await;
-            
-            if let Some(remaining) = response.headers().get("x-ratelimit-remaining") {
-                if let Ok(remaining) = remaining.to_str() {
-                    if let Ok(remaining) = remaining.parse::<u32>() {
-                        rate_limiter.set_remaining(remaining);
-                    }
-                }
-            }
-            
-            if let Some(reset) = response.headers().get("x-ratelimit-reset") {
-                if let Ok(reset) = reset.to_str() {
-                    if let Ok(reset) = reset.parse::<u64>() {
-                        rate_limiter.set_reset(Duration::from_secs(reset));
-                    }
-                }
-            }
+            self.rate_limiter.update_from_headers(response.headers());
         }
         
         // Check the response status
         let status = response.status();
+        if let (Some(cache), Some(key)) = (cache, &cache_key) {
+            if status == StatusCode::NOT_MODIFIED {
+                if let Some(body) = cache.not_modified(key) {
+                    debug!("{} not modified, serving it from the response cache", url);
+                    return parse_body(&body);
+                }
+            }
+        }
         if !status.is_success() {
             return Err(handle_error_response(response).await);
         }
         
+        // Keep the response for later revalidation
+        if let (Some(cache), Some(key)) = (cache, &cache_key) {
+            let headers = response.headers().clone();
+            let body = response.bytes().await?;
+            cache.store(key, &headers, &body);
+            return parse_body(&body);
+        }
+        
         // Parse the response
         let response_data = response.json::<T>().await
             .map_err(|e| Error::ParseError(format!("Failed to parse response: {}", e)))?;
@@ -723,15 +816,14 @@ impl RedditClient {
             *count
         };
         
-        let rate_limits = {
-            let rate_limiter = self.rate_limiter.read().await;
-            (rate_limiter.remaining(), rate_limiter.reset_time())
-        };
+        let rate_limits = (self.rate_limiter.remaining(), self.rate_limiter.reset_time());
         
         ClientStats {
             requests,
             remaining_requests: rate_limits.0,
             reset_time: rate_limits.1,
+            concurrency_limit: self.rate_limiter.concurrency_limit(),
+            cache: self.cache.as_ref().map(|cache| cache.stats()).unwrap_or_default(),
         }
     }
 }
@@ -746,6 +838,9 @@ impl Clone for RedditClient {
             rate_limiter: self.rate_limiter.clone(),
             state: self.state.clone(),
             request_count: self.request_count.clone(),
+            cache: self.cache.clone(),
+            #[cfg(feature = "mock")]
+            mock: self.mock.clone(),
         }
     }
 }
@@ -761,6 +856,12 @@ pub struct ClientStats {
     
     /// Time until rate limit reset
     pub reset_time: Duration,
+    
+    /// Requests currently allowed in flight
+    pub concurrency_limit: usize,
+    
+    /// Requests answered with the response cache's help
+    pub cache: CacheStats,
 }
 
 /// Type of post to submit
@@ -782,10 +883,15 @@ pub enum PostKind {
     Poll,
 }
 
+/// Parse a response body
+fn parse_body<T: for<'de> Deserialize<'de>>(body: &[u8]) -> Result<T> {
+    serde_json::from_slice(body)
+        .map_err(|e| Error::ParseError(format!("Failed to parse response: {}", e)))
+}
+
 /// Handle an error response from the Reddit API
 async fn handle_error_response(response: Response) -> Error {
-    let status = response.status();
-    let status_code = status.as_u16();
+    let status_code = response.status().as_u16();
     
     // Try to parse the error message from the response
     let body = match response.text().await {
@@ -793,6 +899,11 @@ async fn handle_error_response(response: Response) -> Error {
         Err(_) => String::from("Unknown error"),
     };
     
+    error_for_status(status_code, body)
+}
+
+/// Map an error status code and response body to an error
+pub(crate) fn error_for_status(status_code: u16, body: String) -> Error {
     match status_code {
         401 => Error::AuthError("Unauthorized: Invalid or expired token".to_string()),
         403 => Error::AuthError("Forbidden: Insufficient permissions".to_string()),
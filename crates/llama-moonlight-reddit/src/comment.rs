@@ -0,0 +1,316 @@
+//! Comment trees
+//!
+//! This module provides typed comment trees built from Reddit's comment
+//! listings. Reddit only returns part of a large thread and leaves `more`
+//! placeholders where comments were cut off; [`CommentForest`] collects the
+//! comments it has seen so far and the placeholders that can still be
+//! expanded, and turns them into a [`CommentTree`] once fetching is done.
+
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::{Result, Error};
+use crate::models::{Comment, Replies};
+
+/// A `more` placeholder for comments that were not returned
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MoreComments {
+    /// ID of the placeholder
+    pub id: String,
+    
+    /// Fullname of the parent comment or post
+    pub parent_id: String,
+    
+    /// Number of comments behind the placeholder
+    #[serde(default)]
+    pub count: i32,
+    
+    /// Depth of the hidden comments
+    #[serde(default)]
+    pub depth: i32,
+    
+    /// IDs of the hidden comments (without prefix)
+    #[serde(default)]
+    pub children: Vec<String>,
+}
+
+impl MoreComments {
+    /// Check whether this is a "continue this thread" link
+    ///
+    /// These placeholders list no children; the thread has to be loaded
+    /// separately from the parent comment's permalink.
+    pub fn is_continue_thread(&self) -> bool {
+        self.children.is_empty()
+    }
+}
+
+/// A comment and its replies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommentNode {
+    /// The comment
+    pub comment: Comment,
+    
+    /// Direct replies, in the order Reddit returned them
+    pub replies: Vec<CommentNode>,
+}
+
+impl CommentNode {
+    /// Count this comment and all of its replies
+    pub fn count(&self) -> usize {
+        1 + self.replies.iter().map(CommentNode::count).sum::<usize>()
+    }
+}
+
+/// The comment forest of a post
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommentTree {
+    /// Fullname of the post
+    pub link_id: String,
+    
+    /// Top-level comments
+    pub comments: Vec<CommentNode>,
+    
+    /// Placeholders that were not expanded because of depth or limit controls
+    pub more: Vec<MoreComments>,
+}
+
+impl CommentTree {
+    /// Count all comments in the tree
+    pub fn len(&self) -> usize {
+        self.comments.iter().map(CommentNode::count).sum()
+    }
+    
+    /// Check whether the tree has no comments
+    pub fn is_empty(&self) -> bool {
+        self.comments.is_empty()
+    }
+    
+    /// Iterate over all comments depth-first, with their depth (0 for top-level)
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Comment)> {
+        let mut stack: Vec<(usize, &CommentNode)> = self.comments.iter().rev().map(|node| (0, node)).collect();
+        
+        std::iter::from_fn(move || {
+            let (depth, node) = stack.pop()?;
+            stack.extend(node.replies.iter().rev().map(|reply| (depth + 1, reply)));
+            Some((depth, &node.comment))
+        })
+    }
+    
+    /// Find a comment by ID (with or without the t1_ prefix)
+    pub fn find(&self, id: &str) -> Option<&Comment> {
+        let id = id.strip_prefix("t1_").unwrap_or(id);
+        self.iter().map(|(_, comment)| comment).find(|comment| comment.id == id)
+    }
+}
+
+/// Comments collected while fetching a thread
+///
+/// Comments are kept flat and linked to their parents by fullname, so
+/// comments returned by `/api/morechildren` can be attached wherever they
+/// belong before the tree is assembled.
+#[derive(Debug, Default)]
+pub(crate) struct CommentForest {
+    /// Comments in the order they were received
+    comments: Vec<Comment>,
+    
+    /// Index of each comment by fullname
+    index: HashMap<String, usize>,
+}
+
+impl CommentForest {
+    /// Create an empty forest
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Number of comments collected
+    pub(crate) fn len(&self) -> usize {
+        self.comments.len()
+    }
+    
+    /// Add the children of a comment listing, returning the placeholders found
+    pub(crate) fn add_listing(&mut self, listing: &Value) -> Result<Vec<MoreComments>> {
+        let children = listing["data"]["children"].as_array()
+            .ok_or_else(|| Error::ParseError("Comment listing has no children".to_string()))?;
+        
+        self.add_things(children)
+    }
+    
+    /// Add comment and `more` things, returning the placeholders found
+    ///
+    /// Replies nested in a comment's `replies` listing are added as well.
+    pub(crate) fn add_things(&mut self, things: &[Value]) -> Result<Vec<MoreComments>> {
+        let mut more = Vec::new();
+        
+        for thing in things {
+            match thing["kind"].as_str() {
+                Some("t1") => {
+                    let mut data = thing["data"].clone();
+                    let replies = data["replies"].take();
+                    data["replies"] = Value::String(String::new());
+                    
+                    let comment: Comment = serde_json::from_value(data)
+                        .map_err(|e| Error::ParseError(format!("Failed to parse comment: {}", e)))?;
+                    self.insert(comment);
+                    
+                    if replies.is_object() {
+                        more.extend(self.add_listing(&replies)?);
+                    }
+                }
+                Some("more") => {
+                    let placeholder: MoreComments = serde_json::from_value(thing["data"].clone())
+                        .map_err(|e| Error::ParseError(format!("Failed to parse more comments: {}", e)))?;
+                    more.push(placeholder);
+                }
+                _ => {}
+            }
+        }
+        
+        Ok(more)
+    }
+    
+    /// Add a comment, replacing an earlier copy with the same fullname
+    fn insert(&mut self, comment: Comment) {
+        match self.index.get(&comment.name) {
+            Some(&i) => self.comments[i] = comment,
+            None => {
+                self.index.insert(comment.name.clone(), self.comments.len());
+                self.comments.push(comment);
+            }
+        }
+    }
+    
+    /// Assemble the tree
+    ///
+    /// Comments whose parent is not in the forest become top-level comments.
+    pub(crate) fn into_tree(self, link_id: &str, more: Vec<MoreComments>) -> CommentTree {
+        let mut children: HashMap<Option<usize>, Vec<usize>> = HashMap::new();
+        for (i, comment) in self.comments.iter().enumerate() {
+            let parent = self.index.get(&comment.parent_id).copied();
+            children.entry(parent).or_default().push(i);
+        }
+        
+        let mut slots: Vec<Option<Comment>> = self.comments.into_iter().map(Some).collect();
+        
+        fn build(
+            i: usize,
+            slots: &mut Vec<Option<Comment>>,
+            children: &HashMap<Option<usize>, Vec<usize>>,
+        ) -> CommentNode {
+            let comment = slots[i].take().expect("comment is placed once");
+            let replies = children.get(&Some(i))
+                .map(|ids| ids.iter().map(|&c| build(c, slots, children)).collect())
+                .unwrap_or_default();
+            
+            CommentNode {
+                comment: Comment { replies: Replies::Empty(String::new()), ..comment },
+                replies,
+            }
+        }
+        
+        let comments = children.get(&None)
+            .map(|ids| ids.iter().map(|&i| build(i, &mut slots, &children)).collect())
+            .unwrap_or_default();
+        
+        CommentTree {
+            link_id: link_id.to_string(),
+            comments,
+            more,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    fn comment(id: &str, parent_id: &str, replies: Value) -> Value {
+        json!({
+            "kind": "t1",
+            "data": {
+                "id": id,
+                "name": format!("t1_{}", id),
+                "parent_id": parent_id,
+                "link_id": "t3_post",
+                "subreddit": "rust",
+                "subreddit_name_prefixed": "r/rust",
+                "author": "ferris",
+                "author_fullname": null,
+                "distinguished": null,
+                "body": format!("comment {}", id),
+                "body_html": null,
+                "edited": false,
+                "permalink": format!("/r/rust/comments/post/_/{}/", id),
+                "ups": 1,
+                "downs": 0,
+                "score": 1,
+                "score_hidden": false,
+                "created_utc": 1700000000.0,
+                "stickied": false,
+                "locked": false,
+                "archived": false,
+                "saved": false,
+                "author_flair_text": null,
+                "replies": replies,
+                "depth": null,
+                "count": null,
+                "children": null,
+                "removed": null,
+                "spam": null,
+                "approved": null,
+                "all_awardings": [],
+            }
+        })
+    }
+    
+    fn listing(children: Vec<Value>) -> Value {
+        json!({ "kind": "Listing", "data": { "children": children } })
+    }
+    
+    #[test]
+    fn test_forest_with_more_children() {
+        let mut forest = CommentForest::new();
+        let more = forest.add_listing(&listing(vec![
+            comment("a", "t3_post", listing(vec![
+                comment("b", "t1_a", json!("")),
+                json!({ "kind": "more", "data": { "id": "m1", "parent_id": "t1_a", "count": 2, "depth": 1, "children": ["c", "d"] } }),
+            ])),
+            comment("e", "t3_post", json!("")),
+        ])).unwrap();
+        
+        assert_eq!(forest.len(), 3);
+        assert_eq!(more.len(), 1);
+        assert_eq!(more[0].children, vec!["c", "d"]);
+        
+        // Expanded comments arrive flat, linked by parent_id
+        let more = forest.add_things(&[
+            comment("c", "t1_a", json!("")),
+            comment("d", "t1_c", json!("")),
+        ]).unwrap();
+        assert!(more.is_empty());
+        
+        let tree = forest.into_tree("t3_post", Vec::new());
+        assert_eq!(tree.len(), 5);
+        assert_eq!(tree.comments.len(), 2);
+        assert_eq!(tree.comments[0].replies.len(), 2);
+        assert_eq!(tree.comments[0].replies[1].replies[0].comment.id, "d");
+        
+        let order: Vec<(usize, &str)> = tree.iter().map(|(depth, c)| (depth, c.id.as_str())).collect();
+        assert_eq!(order, vec![(0, "a"), (1, "b"), (1, "c"), (2, "d"), (0, "e")]);
+        assert!(tree.find("t1_d").is_some());
+    }
+    
+    #[test]
+    fn test_continue_thread() {
+        let more = MoreComments {
+            id: "_".to_string(),
+            parent_id: "t1_a".to_string(),
+            count: 0,
+            depth: 10,
+            children: Vec::new(),
+        };
+        assert!(more.is_continue_thread());
+    }
+}
@@ -9,12 +9,15 @@
 //!
 //! - OAuth2 authentication with Reddit API
 //! - Rate limiting and automatic throttling
+//! - Conditional requests and response caching for polled listings
 //! - Content interaction (posting, commenting, voting)
 //! - Subreddit management and discovery
 //! - Stealth mode to avoid detection
 //! - Proxy support, including Tor integration
 //! - Browser automation integration
 //! - Stateful session management
+//! - Mock transport for offline testing
+//! - Rules engine for moderation bots
 //!
 //! ## Example
 //!
@@ -62,6 +65,7 @@ use serde::{Serialize, Deserialize};
 
 // Public modules
 pub mod auth;
+pub mod cache;
 pub mod client;
 pub mod models;
 pub mod api;
@@ -80,6 +84,7 @@ pub mod stream;
 pub mod throttle;
 pub mod parsing;
 pub mod utils;
+pub mod wiki;
 
 // Feature-gated modules
 #[cfg(feature = "browser")]
@@ -94,11 +99,15 @@ pub mod tor;
 #[cfg(feature = "mock")]
 pub mod mock;
 
+#[cfg(feature = "moderation")]
+pub mod rules;
+
 // Re-exports for common types
 pub use client::{RedditClient, ClientConfig};
+pub use cache::{CacheConfig, CacheStats, ResponseCache};
 pub use auth::{Authenticator, Credentials, TokenStore};
 pub use models::{Thing, Listing, ThingKind};
-pub use throttle::RateLimiter;
+pub use throttle::{AimdConfig, RateLimiter, RequestPermit};
 
 /// Custom result type for Reddit operations
 pub type Result<T> = std::result::Result<T, Error>;
@@ -166,6 +175,16 @@ pub enum Error {
     #[error("Tor error: {0}")]
     TorError(String),
     
+    /// Mock transport errors
+    #[cfg(feature = "mock")]
+    #[error("Mock error: {0}")]
+    MockError(String),
+    
+    /// Moderation rule errors
+    #[cfg(feature = "moderation")]
+    #[error("Rule error: {0}")]
+    RuleError(String),
+    
     /// Other/unexpected errors
     #[error("Other error: {0}")]
     Other(String),
@@ -227,6 +246,10 @@ pub enum Scope {
     ModerateInvites,
     /// Manage collections and events
     Collections,
+    /// Read wiki pages through your account
+    WikiRead,
+    /// Edit wiki pages
+    WikiEdit,
     /// All scopes
     All,
 }
@@ -300,6 +323,8 @@ impl std::fmt::Display for Scope {
             Scope::Submit => "submit",
             Scope::ModerateInvites => "moderateinvites",
             Scope::Collections => "collections",
+            Scope::WikiRead => "wikiread",
+            Scope::WikiEdit => "wikiedit",
             Scope::All => "*",
         };
         write!(f, "{}", scope_str)
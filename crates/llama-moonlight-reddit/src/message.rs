@@ -0,0 +1,399 @@
+//! Private messages and the inbox
+//!
+//! This module provides functionality for reading and sending private
+//! messages, and for monitoring the inbox: an [`InboxMonitor`] streams unread
+//! inbox items and calls a handler for each new mention or private message,
+//! marking it as read once handled. This is the core of most reply bots:
+//!
+//! ```rust,no_run
+//! use llama_moonlight_reddit::RedditClient;
+//!
+//! # async fn example(client: RedditClient) {
+//! client.messages()
+//!     .on_mention(|item| async move {
+//!         item.reply(&format!("Hi u/{}!", item.message.author)).await?;
+//!         Ok(())
+//!     })
+//!     .run()
+//!     .await;
+//! # }
+//! ```
+//!
+//! Reading the inbox needs the `privatemessages` scope, and replying needs
+//! `submit` as well.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use futures::future::{BoxFuture, FutureExt};
+use futures::stream::{BoxStream, StreamExt};
+use log::{debug, warn};
+use tokio::task::JoinHandle;
+
+use crate::Result;
+use crate::client::RedditClient;
+use crate::models::{Listing, Message, Thing};
+use crate::stream::{poll_listing, StreamOptions};
+
+/// A client for the authenticated user's messages
+#[derive(Debug, Clone)]
+pub struct MessageClient {
+    /// Reddit client
+    client: RedditClient,
+}
+
+impl MessageClient {
+    /// Create a new message client
+    pub fn new(client: RedditClient) -> Self {
+        Self { client }
+    }
+    
+    /// Get the newest items of the inbox: messages, replies and mentions
+    pub async fn inbox(&self, limit: Option<u32>) -> Result<Vec<Message>> {
+        self.list("inbox", limit).await
+    }
+    
+    /// Get the unread items of the inbox
+    pub async fn unread(&self, limit: Option<u32>) -> Result<Vec<Message>> {
+        self.list("unread", limit).await
+    }
+    
+    /// Get the newest mentions of the user
+    pub async fn mentions(&self, limit: Option<u32>) -> Result<Vec<Message>> {
+        self.list("mentions", limit).await
+    }
+    
+    /// Get the newest messages sent by the user
+    pub async fn sent(&self, limit: Option<u32>) -> Result<Vec<Message>> {
+        self.list("sent", limit).await
+    }
+    
+    /// Get a folder of the inbox
+    async fn list(&self, folder: &str, limit: Option<u32>) -> Result<Vec<Message>> {
+        let endpoint = format!("/message/{}", folder);
+        
+        let mut params = HashMap::new();
+        params.insert("limit".to_string(), limit.unwrap_or(25).to_string());
+        params.insert("raw_json".to_string(), "1".to_string());
+        
+        let response: Listing<Thing<Message>> = self.client.get(&endpoint, Some(params)).await?;
+        
+        Ok(response.data.children.into_iter().map(|thing| thing.data).collect())
+    }
+    
+    /// Mark inbox items as read, by fullname
+    pub async fn mark_read(&self, names: &[&str]) -> Result<()> {
+        self.set_read("/api/read_message", names).await
+    }
+    
+    /// Mark inbox items as unread, by fullname
+    pub async fn mark_unread(&self, names: &[&str]) -> Result<()> {
+        self.set_read("/api/unread_message", names).await
+    }
+    
+    /// Mark every inbox item as read
+    pub async fn mark_all_read(&self) -> Result<()> {
+        let _: serde_json::Value = self.client.post("/api/read_all_messages", None, None).await?;
+        
+        Ok(())
+    }
+    
+    async fn set_read(&self, endpoint: &str, names: &[&str]) -> Result<()> {
+        if names.is_empty() {
+            return Ok(());
+        }
+        
+        let mut params = HashMap::new();
+        params.insert("id".to_string(), names.join(","));
+        
+        let _: serde_json::Value = self.client.post(endpoint, Some(params), None).await?;
+        
+        Ok(())
+    }
+    
+    /// Send a private message
+    pub async fn compose(&self, to: &str, subject: &str, text: &str) -> Result<()> {
+        debug!("Sending a message to {}", to);
+        
+        let mut params = HashMap::new();
+        params.insert("api_type".to_string(), "json".to_string());
+        params.insert("to".to_string(), to.strip_prefix("u/").unwrap_or(to).to_string());
+        params.insert("subject".to_string(), subject.to_string());
+        params.insert("text".to_string(), text.to_string());
+        
+        let _: serde_json::Value = self.client.post("/api/compose", Some(params), None).await?;
+        
+        Ok(())
+    }
+    
+    /// Reply to an inbox item, by fullname, returning the fullname of the reply
+    ///
+    /// Replies to messages are messages; replies to mentions and comment
+    /// replies are comments.
+    pub async fn reply(&self, name: &str, text: &str) -> Result<String> {
+        self.client.submit_comment(name, text).await
+    }
+    
+    /// Stream the unread items of the inbox as they arrive
+    pub fn stream_unread(&self, options: StreamOptions) -> BoxStream<'static, Result<Message>> {
+        let messages = self.clone();
+        poll_listing(
+            move || {
+                let messages = messages.clone();
+                async move { messages.unread(Some(100)).await }
+            },
+            |message: &Message| message.name.as_str(),
+            options,
+        )
+    }
+    
+    /// Start monitoring the inbox, calling `handler` for each new mention
+    pub fn on_mention<F, Fut>(&self, handler: F) -> InboxMonitor
+    where
+        F: Fn(InboxItem) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        InboxMonitor::new(self.clone()).on_mention(handler)
+    }
+    
+    /// Start monitoring the inbox, calling `handler` for each new private message
+    pub fn on_private_message<F, Fut>(&self, handler: F) -> InboxMonitor
+    where
+        F: Fn(InboxItem) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        InboxMonitor::new(self.clone()).on_private_message(handler)
+    }
+}
+
+/// Kind of an inbox item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InboxKind {
+    /// A private message
+    PrivateMessage,
+    /// A comment mentioning the user
+    Mention,
+    /// A reply to a post or comment of the user
+    Reply,
+}
+
+impl InboxKind {
+    /// Get the kind of an inbox item
+    pub fn of(message: &Message) -> Self {
+        if !message.was_comment {
+            return Self::PrivateMessage;
+        }
+        
+        let item_type = message.additional_fields.get("type").and_then(|value| value.as_str());
+        if item_type == Some("username_mention") || message.subject == "username mention" {
+            Self::Mention
+        } else {
+            Self::Reply
+        }
+    }
+}
+
+/// An inbox item passed to a handler, with helpers to act on it
+#[derive(Debug, Clone)]
+pub struct InboxItem {
+    /// The item
+    pub message: Message,
+    
+    /// Kind of the item
+    pub kind: InboxKind,
+    
+    /// Client for the user's messages
+    messages: MessageClient,
+}
+
+impl InboxItem {
+    /// Reply to the item, returning the fullname of the reply
+    pub async fn reply(&self, text: &str) -> Result<String> {
+        self.messages.reply(&self.message.name, text).await
+    }
+    
+    /// Mark the item as read
+    pub async fn mark_read(&self) -> Result<()> {
+        self.messages.mark_read(&[&self.message.name]).await
+    }
+    
+    /// Mark the item as unread, e.g. to handle it again later
+    pub async fn mark_unread(&self) -> Result<()> {
+        self.messages.mark_unread(&[&self.message.name]).await
+    }
+    
+    /// Get the client for the user's messages
+    pub fn messages(&self) -> &MessageClient {
+        &self.messages
+    }
+}
+
+/// Handler of inbox items
+type Handler = Arc<dyn Fn(InboxItem) -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+/// Calls handlers for new inbox items
+///
+/// Items are marked as read once their handler succeeds, unless automatic
+/// marking is turned off. An item whose handler fails stays unread, so it is
+/// handled again when the monitor restarts. Items without a handler are left
+/// untouched.
+#[derive(Clone)]
+pub struct InboxMonitor {
+    /// Client for the user's messages
+    messages: MessageClient,
+    
+    /// Handlers by item kind
+    handlers: HashMap<InboxKind, Handler>,
+    
+    /// Polling options
+    options: StreamOptions,
+    
+    /// Whether to mark handled items as read
+    auto_mark_read: bool,
+}
+
+impl std::fmt::Debug for InboxMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InboxMonitor")
+            .field("handlers", &self.handlers.keys().collect::<Vec<_>>())
+            .field("options", &self.options)
+            .field("auto_mark_read", &self.auto_mark_read)
+            .finish()
+    }
+}
+
+impl InboxMonitor {
+    /// Create a monitor without handlers
+    pub fn new(messages: MessageClient) -> Self {
+        Self {
+            messages,
+            handlers: HashMap::new(),
+            options: StreamOptions::default(),
+            auto_mark_read: true,
+        }
+    }
+    
+    /// Call `handler` for each new mention
+    pub fn on_mention<F, Fut>(self, handler: F) -> Self
+    where
+        F: Fn(InboxItem) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.on(InboxKind::Mention, handler)
+    }
+    
+    /// Call `handler` for each new private message
+    pub fn on_private_message<F, Fut>(self, handler: F) -> Self
+    where
+        F: Fn(InboxItem) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.on(InboxKind::PrivateMessage, handler)
+    }
+    
+    /// Call `handler` for each new reply to a post or comment of the user
+    pub fn on_reply<F, Fut>(self, handler: F) -> Self
+    where
+        F: Fn(InboxItem) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.on(InboxKind::Reply, handler)
+    }
+    
+    /// Call `handler` for each new item of a kind, replacing its previous handler
+    pub fn on<F, Fut>(mut self, kind: InboxKind, handler: F) -> Self
+    where
+        F: Fn(InboxItem) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.handlers.insert(kind, Arc::new(move |item| handler(item).boxed()));
+        self
+    }
+    
+    /// Set the polling options
+    pub fn with_options(mut self, options: StreamOptions) -> Self {
+        self.options = options;
+        self
+    }
+    
+    /// Set whether to mark handled items as read
+    pub fn with_auto_mark_read(mut self, auto_mark_read: bool) -> Self {
+        self.auto_mark_read = auto_mark_read;
+        self
+    }
+    
+    /// Monitor the inbox until the task is cancelled
+    pub async fn run(self) {
+        let mut unread = self.messages.stream_unread(self.options.clone());
+        
+        while let Some(message) = unread.next().await {
+            match message {
+                Ok(message) => self.handle(message).await,
+                Err(e) => warn!("Failed to poll the inbox: {}", e),
+            }
+        }
+    }
+    
+    /// Monitor the inbox in a background task
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+    
+    /// Call the handler of an item and mark it as read
+    async fn handle(&self, message: Message) {
+        let kind = InboxKind::of(&message);
+        let handler = match self.handlers.get(&kind) {
+            Some(handler) => handler.clone(),
+            None => return,
+        };
+        
+        let item = InboxItem {
+            message,
+            kind,
+            messages: self.messages.clone(),
+        };
+        let name = item.message.name.clone();
+        
+        debug!("Handling {:?} {}", kind, name);
+        if let Err(e) = handler(item).await {
+            warn!("Handler for {:?} {} failed: {}", kind, name, e);
+            return;
+        }
+        
+        if self.auto_mark_read {
+            if let Err(e) = self.messages.mark_read(&[&name]).await {
+                warn!("Failed to mark {} as read: {}", name, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    fn message(was_comment: bool, subject: &str, item_type: Option<&str>) -> Message {
+        serde_json::from_value(json!({
+            "id": "abc",
+            "name": "t1_abc",
+            "subject": subject,
+            "body": "hello",
+            "author": "someone",
+            "dest": "bot",
+            "created_utc": 1700000000.0,
+            "new": true,
+            "was_comment": was_comment,
+            "type": item_type,
+        }))
+        .unwrap()
+    }
+    
+    #[test]
+    fn test_inbox_kind() {
+        assert_eq!(InboxKind::of(&message(false, "hi", None)), InboxKind::PrivateMessage);
+        assert_eq!(InboxKind::of(&message(true, "username mention", None)), InboxKind::Mention);
+        assert_eq!(InboxKind::of(&message(true, "post reply", Some("username_mention"))), InboxKind::Mention);
+        assert_eq!(InboxKind::of(&message(true, "comment reply", Some("comment_reply"))), InboxKind::Reply);
+    }
+}
@@ -0,0 +1,289 @@
+//! Mock transport for offline testing
+//!
+//! This module lets bot logic run against canned responses instead of the
+//! Reddit API. Fixtures are matched on method, endpoint, and query
+//! parameters, and every request is recorded so tests can assert on what was
+//! sent. A client with a mock transport needs no credentials.
+//!
+//! ```rust,no_run
+//! use llama_moonlight_reddit::{RedditClient, ClientConfig, Result};
+//! use llama_moonlight_reddit::mock::{Fixture, MockTransport};
+//! use serde_json::json;
+//!
+//! # async fn example() -> Result<()> {
+//! let mock = MockTransport::new();
+//! mock.register(
+//!     Fixture::get("/api/v1/me").respond(json!({ "kind": "t2", "data": { "name": "bot" } })),
+//! );
+//!
+//! let client = RedditClient::new(ClientConfig::default())
+//!     .await?
+//!     .with_mock_transport(mock.clone());
+//!
+//! let me = client.me().await?;
+//! assert_eq!(mock.requests().len(), 1);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use reqwest::Method;
+use serde::Deserialize;
+use log::debug;
+
+use crate::{Result, Error};
+use crate::client::error_for_status;
+
+/// A canned response for matching requests
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    /// HTTP method to match
+    pub method: Method,
+
+    /// Endpoint path to match, e.g. `/r/rust/hot`
+    pub endpoint: String,
+
+    /// Query parameters that must be present with these values
+    pub params: HashMap<String, String>,
+
+    /// HTTP status code of the response
+    pub status: u16,
+
+    /// JSON body of the response
+    pub body: serde_json::Value,
+
+    /// How many times the fixture may be used (`None` for unlimited)
+    pub times: Option<usize>,
+}
+
+impl Fixture {
+    /// Create a fixture for the specified method and endpoint
+    pub fn new(method: Method, endpoint: &str) -> Self {
+        Self {
+            method,
+            endpoint: normalize_endpoint(endpoint),
+            params: HashMap::new(),
+            status: 200,
+            body: serde_json::Value::Null,
+            times: None,
+        }
+    }
+
+    /// Create a fixture for a GET request
+    pub fn get(endpoint: &str) -> Self {
+        Self::new(Method::GET, endpoint)
+    }
+
+    /// Create a fixture for a POST request
+    pub fn post(endpoint: &str) -> Self {
+        Self::new(Method::POST, endpoint)
+    }
+
+    /// Only match requests that have this query parameter value
+    pub fn with_param(mut self, name: &str, value: &str) -> Self {
+        self.params.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Respond with the specified JSON body
+    pub fn respond(mut self, body: serde_json::Value) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// Respond with the specified status code
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Only use the fixture for the first `times` matching requests
+    pub fn times(mut self, times: usize) -> Self {
+        self.times = Some(times);
+        self
+    }
+
+    /// Check whether the fixture matches a request
+    fn matches(&self, method: &Method, endpoint: &str, params: &HashMap<String, String>) -> bool {
+        self.method == *method
+            && self.endpoint == endpoint
+            && self.times != Some(0)
+            && self.params.iter().all(|(name, value)| params.get(name) == Some(value))
+    }
+}
+
+/// A request received by the mock transport
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedRequest {
+    /// HTTP method
+    pub method: Method,
+
+    /// Endpoint path
+    pub endpoint: String,
+
+    /// Query parameters
+    pub params: HashMap<String, String>,
+
+    /// JSON body, if any
+    pub body: Option<serde_json::Value>,
+}
+
+/// Transport answering requests from registered fixtures
+///
+/// Clones share fixtures and recorded requests, so a test can keep a handle
+/// after passing one to the client.
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport {
+    /// Registered fixtures
+    fixtures: Arc<Mutex<Vec<Fixture>>>,
+
+    /// Requests received so far
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl MockTransport {
+    /// Create an empty mock transport
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a fixture
+    ///
+    /// When several fixtures match a request, the most recently registered one
+    /// is used, so tests can override defaults.
+    pub fn register(&self, fixture: Fixture) -> &Self {
+        self.fixtures.lock().unwrap().push(fixture);
+        self
+    }
+
+    /// Get all requests received so far
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Get the requests received for an endpoint
+    pub fn requests_to(&self, endpoint: &str) -> Vec<RecordedRequest> {
+        let endpoint = normalize_endpoint(endpoint);
+        self.requests().into_iter().filter(|r| r.endpoint == endpoint).collect()
+    }
+
+    /// Remove all fixtures and recorded requests
+    pub fn reset(&self) {
+        self.fixtures.lock().unwrap().clear();
+        self.requests.lock().unwrap().clear();
+    }
+
+    /// Answer a request from the registered fixtures
+    pub(crate) fn respond<T: for<'de> Deserialize<'de>>(
+        &self,
+        method: &Method,
+        endpoint: &str,
+        params: Option<&HashMap<String, String>>,
+        body: Option<serde_json::Value>,
+    ) -> Result<T> {
+        let endpoint = normalize_endpoint(endpoint);
+        let params = params.cloned().unwrap_or_default();
+
+        debug!("mock {} {}", method, endpoint);
+
+        self.requests.lock().unwrap().push(RecordedRequest {
+            method: method.clone(),
+            endpoint: endpoint.clone(),
+            params: params.clone(),
+            body,
+        });
+
+        let (status, response) = {
+            let mut fixtures = self.fixtures.lock().unwrap();
+            let fixture = fixtures.iter_mut()
+                .rev()
+                .find(|f| f.matches(method, &endpoint, &params))
+                .ok_or_else(|| Error::MockError(format!("No fixture for {} {}", method, endpoint)))?;
+
+            if let Some(times) = fixture.times.as_mut() {
+                *times -= 1;
+            }
+
+            (fixture.status, fixture.body.clone())
+        };
+
+        if !(200..300).contains(&status) {
+            return Err(error_for_status(status, response.to_string()));
+        }
+
+        serde_json::from_value(response)
+            .map_err(|e| Error::ParseError(format!("Failed to parse response: {}", e)))
+    }
+}
+
+/// Strip the API base, query string, and trailing slash from an endpoint
+fn normalize_endpoint(endpoint: &str) -> String {
+    let path = match endpoint.find("://") {
+        Some(scheme_end) => {
+            let rest = &endpoint[scheme_end + 3..];
+            rest.find('/').map(|i| &rest[i..]).unwrap_or("/")
+        }
+        None => endpoint,
+    };
+
+    let path = path.split('?').next().unwrap_or(path);
+    let path = if path.len() > 1 { path.trim_end_matches('/') } else { path };
+
+    if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/{}", path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_normalize_endpoint() {
+        assert_eq!(normalize_endpoint("/r/rust/hot/"), "/r/rust/hot");
+        assert_eq!(normalize_endpoint("r/rust/hot?limit=5"), "/r/rust/hot");
+        assert_eq!(normalize_endpoint("https://oauth.reddit.com/api/v1/me"), "/api/v1/me");
+        assert_eq!(normalize_endpoint("/"), "/");
+    }
+
+    #[test]
+    fn test_matching_and_recording() {
+        let mock = MockTransport::new();
+        mock.register(Fixture::get("/r/rust/hot").respond(json!({ "page": 1 })));
+        mock.register(Fixture::get("/r/rust/hot").with_param("after", "t3_x").respond(json!({ "page": 2 })));
+
+        let first: serde_json::Value = mock.respond(&Method::GET, "/r/rust/hot", Some(&params(&[("limit", "5")])), None).unwrap();
+        assert_eq!(first["page"], 1);
+
+        let second: serde_json::Value = mock
+            .respond(&Method::GET, "/r/rust/hot", Some(&params(&[("limit", "5"), ("after", "t3_x")])), None)
+            .unwrap();
+        assert_eq!(second["page"], 2);
+
+        let missing: Result<serde_json::Value> = mock.respond(&Method::POST, "/r/rust/hot", None, None);
+        assert!(matches!(missing, Err(Error::MockError(_))));
+
+        assert_eq!(mock.requests_to("/r/rust/hot").len(), 3);
+    }
+
+    #[test]
+    fn test_times_and_status() {
+        let mock = MockTransport::new();
+        mock.register(Fixture::post("/api/vote").with_status(429).respond(json!({ "message": "slow down" })).times(1));
+        mock.register(Fixture::post("/api/vote").respond(json!({})).times(0));
+
+        let limited: Result<serde_json::Value> = mock.respond(&Method::POST, "/api/vote", None, None);
+        assert!(matches!(limited, Err(Error::RateLimitError(_))));
+
+        let exhausted: Result<serde_json::Value> = mock.respond(&Method::POST, "/api/vote", None, None);
+        assert!(matches!(exhausted, Err(Error::MockError(_))));
+    }
+}
@@ -728,6 +728,118 @@ pub struct ModAction {
     pub created_utc: DateTime<Utc>,
 }
 
+/// A subreddit wiki page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WikiPage {
+    /// Markdown content
+    pub content_md: String,
+    
+    /// HTML formatted content
+    pub content_html: Option<String>,
+    
+    /// ID of the current revision
+    pub revision_id: Option<String>,
+    
+    /// Username of the author of the current revision
+    #[serde(default, with = "wiki_author")]
+    pub revision_by: Option<String>,
+    
+    /// Time of the current revision (UTC)
+    #[serde(with = "timestamp_seconds")]
+    pub revision_date: DateTime<Utc>,
+    
+    /// Whether the current user may edit the page
+    #[serde(default)]
+    pub may_revise: bool,
+    
+    /// Reason given for the current revision
+    pub reason: Option<String>,
+}
+
+/// A revision of a wiki page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WikiRevision {
+    /// Revision ID
+    pub id: String,
+    
+    /// Name of the page
+    pub page: String,
+    
+    /// Username of the author of the revision
+    #[serde(default, with = "wiki_author")]
+    pub author: Option<String>,
+    
+    /// Reason given for the revision
+    pub reason: Option<String>,
+    
+    /// Time of the revision (UTC)
+    #[serde(with = "timestamp_seconds")]
+    pub timestamp: DateTime<Utc>,
+    
+    /// Whether the revision is hidden from the page history
+    #[serde(default)]
+    pub revision_hidden: bool,
+}
+
+/// Who may edit a wiki page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WikiPermissionLevel {
+    /// Use the subreddit's wiki settings
+    Inherit,
+    /// Only approved wiki contributors
+    Contributors,
+    /// Only moderators
+    Moderators,
+}
+
+impl WikiPermissionLevel {
+    /// Get the numeric level used by the API
+    pub fn level(&self) -> u8 {
+        match self {
+            WikiPermissionLevel::Inherit => 0,
+            WikiPermissionLevel::Contributors => 1,
+            WikiPermissionLevel::Moderators => 2,
+        }
+    }
+}
+
+impl Serialize for WikiPermissionLevel {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_u8(self.level())
+    }
+}
+
+impl<'de> Deserialize<'de> for WikiPermissionLevel {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match u8::deserialize(deserializer)? {
+            0 => Ok(WikiPermissionLevel::Inherit),
+            1 => Ok(WikiPermissionLevel::Contributors),
+            2 => Ok(WikiPermissionLevel::Moderators),
+            level => Err(de::Error::custom(format!("Invalid wiki permission level: {}", level))),
+        }
+    }
+}
+
+/// Settings of a wiki page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WikiPageSettings {
+    /// Who may edit the page
+    pub permlevel: WikiPermissionLevel,
+    
+    /// Whether the page is shown in the page list
+    pub listed: bool,
+    
+    /// Users allowed to edit the page regardless of the permission level
+    #[serde(default, with = "wiki_editors")]
+    pub editors: Vec<String>,
+}
+
 /// A private message
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
@@ -902,6 +1014,69 @@ mod timestamp_seconds {
     }
 }
 
+/// Wiki authors are returned as account things; keep only the username
+mod wiki_author {
+    use serde::{Deserialize, Serializer, Deserializer};
+    
+    #[derive(Deserialize)]
+    struct Account {
+        data: AccountData,
+    }
+    
+    #[derive(Deserialize)]
+    struct AccountData {
+        name: String,
+    }
+    
+    pub fn serialize<S>(author: &Option<String>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match author {
+            Some(name) => serializer.serialize_some(name),
+            None => serializer.serialize_none(),
+        }
+    }
+    
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let account = Option::<Account>::deserialize(deserializer)?;
+        Ok(account.map(|a| a.data.name))
+    }
+}
+
+/// Wiki page editors are returned as account things; keep only the usernames
+mod wiki_editors {
+    use serde::{Deserialize, Serialize, Serializer, Deserializer};
+    
+    #[derive(Deserialize)]
+    struct Account {
+        data: AccountData,
+    }
+    
+    #[derive(Deserialize)]
+    struct AccountData {
+        name: String,
+    }
+    
+    pub fn serialize<S>(editors: &[String], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        editors.serialize(serializer)
+    }
+    
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let accounts = Vec::<Account>::deserialize(deserializer)?;
+        Ok(accounts.into_iter().map(|a| a.data.name).collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -952,4 +1127,27 @@ mod tests {
         assert!(edited_bool.timestamp().is_none());
         assert!(edited_time.timestamp().is_some());
     }
-} 
\ No newline at end of file
+    
+    #[test]
+    fn test_wiki_models() {
+        let page: WikiPage = serde_json::from_value(serde_json::json!({
+            "content_md": "# Index",
+            "content_html": null,
+            "revision_id": "1c4e8f2a",
+            "revision_by": { "kind": "t2", "data": { "name": "automoderator" } },
+            "revision_date": 1700000000,
+            "may_revise": true,
+            "reason": null,
+        })).unwrap();
+        assert_eq!(page.revision_by.as_deref(), Some("automoderator"));
+        assert_eq!(page.revision_date.timestamp(), 1700000000);
+        
+        let settings: WikiPageSettings = serde_json::from_value(serde_json::json!({
+            "permlevel": 2,
+            "listed": false,
+            "editors": [{ "kind": "t2", "data": { "name": "ferris" } }],
+        })).unwrap();
+        assert_eq!(settings.permlevel, WikiPermissionLevel::Moderators);
+        assert_eq!(settings.editors, vec!["ferris"]);
+    }
+}
@@ -0,0 +1,225 @@
+//! Post interactions
+//!
+//! This module provides functionality for interacting with a single Reddit post.
+
+use std::collections::{HashMap, VecDeque};
+use serde_json::Value;
+use log::debug;
+
+use crate::{Result, Error, Sort};
+use crate::client::RedditClient;
+use crate::comment::{CommentForest, CommentTree, MoreComments};
+
+/// Maximum number of comment IDs per `/api/morechildren` request
+const MORECHILDREN_BATCH: usize = 100;
+
+/// A client for interacting with a specific post
+#[derive(Debug, Clone)]
+pub struct PostClient {
+    /// Reddit client
+    client: RedditClient,
+    
+    /// Post ID (without prefix)
+    id: String,
+}
+
+impl PostClient {
+    /// Create a new post client
+    pub fn new(client: RedditClient, id: &str) -> Self {
+        // Remove the t3_ prefix if present
+        let id = id.strip_prefix("t3_").unwrap_or(id).to_string();
+        
+        Self {
+            client,
+            id,
+        }
+    }
+    
+    /// Get the post ID (without prefix)
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    
+    /// Get the post fullname (with t3_ prefix)
+    pub fn fullname(&self) -> String {
+        format!("t3_{}", self.id)
+    }
+    
+    /// Fetch the post's comments as a tree
+    ///
+    /// `more` placeholders are expanded through `/api/morechildren` until the
+    /// thread is complete or one of the builder's limits is reached.
+    pub fn comments(&self) -> CommentsBuilder {
+        CommentsBuilder::new(self.clone())
+    }
+}
+
+/// A builder for comment tree requests
+#[derive(Debug, Clone)]
+pub struct CommentsBuilder {
+    /// Post client
+    post: PostClient,
+    
+    /// Comment sort order
+    sort: Option<Sort>,
+    
+    /// Maximum depth of the tree
+    depth: Option<u32>,
+    
+    /// Maximum number of comments
+    limit: Option<usize>,
+    
+    /// Maximum number of `/api/morechildren` requests
+    max_expansions: usize,
+}
+
+impl CommentsBuilder {
+    /// Create a new comments builder
+    pub fn new(post: PostClient) -> Self {
+        Self {
+            post,
+            sort: None,
+            depth: None,
+            limit: None,
+            max_expansions: 32,
+        }
+    }
+    
+    /// Set the comment sort order
+    pub fn sort(mut self, sort: Sort) -> Self {
+        self.sort = Some(sort);
+        self
+    }
+    
+    /// Set the maximum depth of the tree
+    pub fn depth(mut self, depth: u32) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+    
+    /// Stop expanding once this many comments have been fetched
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+    
+    /// Set the maximum number of `/api/morechildren` requests (0 to disable expansion)
+    pub fn max_expansions(mut self, max_expansions: usize) -> Self {
+        self.max_expansions = max_expansions;
+        self
+    }
+    
+    /// Build the shared sort and depth parameters
+    fn base_params(&self) -> HashMap<String, String> {
+        let mut params = HashMap::new();
+        params.insert("raw_json".to_string(), "1".to_string());
+        
+        if let Some(sort) = self.sort {
+            params.insert("sort".to_string(), sort_param(sort).to_string());
+        }
+        
+        if let Some(depth) = self.depth {
+            params.insert("depth".to_string(), depth.to_string());
+        }
+        
+        params
+    }
+    
+    /// Fetch the comment tree
+    pub async fn fetch(self) -> Result<CommentTree> {
+        let link_id = self.post.fullname();
+        
+        let mut params = self.base_params();
+        if let Some(limit) = self.limit {
+            params.insert("limit".to_string(), limit.to_string());
+        }
+        
+        // The response is [post listing, comment listing]
+        let endpoint = format!("/comments/{}", self.post.id);
+        let response: Value = self.post.client.get(&endpoint, Some(params)).await?;
+        let comments = response.get(1)
+            .ok_or_else(|| Error::ParseError("Comments response has no comment listing".to_string()))?;
+        
+        let mut forest = CommentForest::new();
+        let mut pending: VecDeque<MoreComments> = forest.add_listing(comments)?.into();
+        let mut unexpanded = Vec::new();
+        let mut requests = 0;
+        
+        while let Some(more) = pending.pop_front() {
+            let too_deep = matches!(self.depth, Some(depth) if more.depth >= depth as i32);
+            let over_limit = matches!(self.limit, Some(limit) if forest.len() >= limit);
+            
+            if more.is_continue_thread() || too_deep || over_limit || requests >= self.max_expansions {
+                unexpanded.push(more);
+                continue;
+            }
+            
+            let mut children = more.children.clone();
+            while !children.is_empty() {
+                if requests >= self.max_expansions {
+                    unexpanded.push(MoreComments {
+                        count: children.len() as i32,
+                        children,
+                        ..more.clone()
+                    });
+                    break;
+                }
+                
+                let rest = children.split_off(children.len().min(MORECHILDREN_BATCH));
+                let batch = std::mem::replace(&mut children, rest);
+                
+                debug!("Expanding {} more comments under {}", batch.len(), more.parent_id);
+                let things = self.more_children(&link_id, &batch).await?;
+                requests += 1;
+                
+                pending.extend(forest.add_things(&things)?);
+            }
+        }
+        
+        Ok(forest.into_tree(&link_id, unexpanded))
+    }
+    
+    /// Fetch hidden comments through `/api/morechildren`
+    async fn more_children(&self, link_id: &str, children: &[String]) -> Result<Vec<Value>> {
+        let mut params = self.base_params();
+        params.insert("api_type".to_string(), "json".to_string());
+        params.insert("link_id".to_string(), link_id.to_string());
+        params.insert("children".to_string(), children.join(","));
+        params.insert("limit_children".to_string(), "false".to_string());
+        
+        let response: Value = self.post.client.get("/api/morechildren", Some(params)).await?;
+        
+        if let Some(errors) = response["json"]["errors"].as_array().filter(|errors| !errors.is_empty()) {
+            return Err(Error::ApiError {
+                status_code: 200,
+                message: format!("morechildren failed: {}", Value::Array(errors.clone())),
+            });
+        }
+        
+        Ok(response["json"]["data"]["things"].as_array().cloned().unwrap_or_default())
+    }
+}
+
+/// Get the API name of a comment sort order
+fn sort_param(sort: Sort) -> &'static str {
+    match sort {
+        Sort::Hot => "hot",
+        Sort::New => "new",
+        Sort::Top => "top",
+        Sort::Rising => "rising",
+        Sort::Controversial => "controversial",
+        Sort::Best => "confidence",
+        Sort::Relevance => "relevance",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_sort_param() {
+        assert_eq!(sort_param(Sort::Best), "confidence");
+        assert_eq!(sort_param(Sort::New), "new");
+    }
+}
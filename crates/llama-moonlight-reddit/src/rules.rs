@@ -0,0 +1,765 @@
+//! Rules engine for moderation bots
+//!
+//! This module evaluates posts and comments, typically as they arrive from a
+//! stream, against configurable rules, much like AutoModerator. A rule matches
+//! when all of its conditions hold, and then runs its actions: removing,
+//! reporting, replying with a template, or setting flair.
+//!
+//! Rules are plain data and can be deserialized from JSON:
+//!
+//! ```json
+//! {
+//!     "name": "link shorteners",
+//!     "kind": "post",
+//!     "conditions": [
+//!         { "type": "domain", "domains": ["bit.ly", "tinyurl.com"] },
+//!         { "type": "author_karma", "max": 100 }
+//!     ],
+//!     "actions": [
+//!         { "type": "remove" },
+//!         { "type": "reply", "template": "Hi u/{{author}}, link shorteners are not allowed in r/{{subreddit}}.", "distinguish": true }
+//!     ]
+//! }
+//! ```
+//!
+//! Author conditions fetch the author's profile once and cache it. The
+//! toxicity condition needs a [`ToxicityScorer`] to be set on the engine.
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::future::{BoxFuture, FutureExt};
+use log::{debug, info};
+use regex::{Regex, RegexBuilder};
+use serde::{Serialize, Deserialize};
+use tokio::sync::{Mutex, OnceCell};
+use url::Url;
+
+use crate::{Result, Error};
+use crate::client::RedditClient;
+use crate::models::{Thing, Post, Comment, User};
+
+/// A post or comment to evaluate
+#[derive(Debug, Clone, Copy)]
+pub enum Content<'a> {
+    /// A post
+    Post(&'a Post),
+    
+    /// A comment
+    Comment(&'a Comment),
+}
+
+impl<'a> Content<'a> {
+    /// Get the kind of content
+    pub fn kind(&self) -> ContentKind {
+        match self {
+            Content::Post(_) => ContentKind::Post,
+            Content::Comment(_) => ContentKind::Comment,
+        }
+    }
+    
+    /// Get the fullname of the content
+    pub fn fullname(&self) -> &'a str {
+        match self {
+            Content::Post(post) => &post.name,
+            Content::Comment(comment) => &comment.name,
+        }
+    }
+    
+    /// Get the author's username
+    pub fn author(&self) -> &'a str {
+        match self {
+            Content::Post(post) => &post.author,
+            Content::Comment(comment) => &comment.author,
+        }
+    }
+    
+    /// Get the subreddit name
+    pub fn subreddit(&self) -> &'a str {
+        match self {
+            Content::Post(post) => &post.subreddit,
+            Content::Comment(comment) => &comment.subreddit,
+        }
+    }
+    
+    /// Get the permalink
+    pub fn permalink(&self) -> &'a str {
+        match self {
+            Content::Post(post) => &post.permalink,
+            Content::Comment(comment) => &comment.permalink,
+        }
+    }
+    
+    /// Get the title of a post, or an empty string for comments
+    pub fn title(&self) -> &'a str {
+        match self {
+            Content::Post(post) => &post.title,
+            Content::Comment(_) => "",
+        }
+    }
+    
+    /// Get the text of a self post or comment
+    pub fn body(&self) -> &'a str {
+        match self {
+            Content::Post(post) => &post.selftext,
+            Content::Comment(comment) => &comment.body,
+        }
+    }
+    
+    /// Get the title and text together
+    pub fn text(&self) -> String {
+        match self {
+            Content::Post(post) if !post.selftext.is_empty() => format!("{}\n\n{}", post.title, post.selftext),
+            Content::Post(post) => post.title.clone(),
+            Content::Comment(comment) => comment.body.clone(),
+        }
+    }
+    
+    /// Get the domains linked by the content
+    ///
+    /// For link posts this is the post's domain; links in the text of self
+    /// posts and comments are included too.
+    pub fn domains(&self) -> Vec<String> {
+        let mut domains = Vec::new();
+        
+        if let Content::Post(post) = self {
+            if !post.is_self && !post.domain.is_empty() {
+                domains.push(post.domain.to_lowercase());
+            }
+        }
+        
+        let finder = linkify::LinkFinder::new();
+        for link in finder.links(self.body()) {
+            if let Some(host) = Url::parse(link.as_str()).ok().and_then(|url| url.host_str().map(str::to_lowercase)) {
+                if !domains.contains(&host) {
+                    domains.push(host);
+                }
+            }
+        }
+        
+        domains
+    }
+}
+
+/// Kind of content a rule applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentKind {
+    /// Posts
+    Post,
+    
+    /// Comments
+    Comment,
+}
+
+/// Field of the content a pattern is matched against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    /// Post title
+    Title,
+    
+    /// Text of a self post or comment
+    Body,
+    
+    /// Title and text
+    Text,
+    
+    /// URL of a link post
+    Url,
+    
+    /// Author's username
+    Author,
+    
+    /// Author's flair text
+    AuthorFlair,
+}
+
+/// A condition on a post or comment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    /// A field matches a regular expression
+    Regex {
+        /// Field to match
+        field: Field,
+        /// Regular expression
+        pattern: String,
+        /// Whether matching is case sensitive
+        #[serde(default)]
+        case_sensitive: bool,
+    },
+    
+    /// The author's total karma is in a range
+    AuthorKarma {
+        /// Minimum karma, inclusive
+        #[serde(default)]
+        min: Option<i32>,
+        /// Maximum karma, inclusive
+        #[serde(default)]
+        max: Option<i32>,
+    },
+    
+    /// The author's account age in days is in a range
+    AuthorAge {
+        /// Minimum age in days, inclusive
+        #[serde(default)]
+        min_days: Option<i64>,
+        /// Maximum age in days, inclusive
+        #[serde(default)]
+        max_days: Option<i64>,
+    },
+    
+    /// The content links to one of the domains or their subdomains
+    Domain {
+        /// Domains to match
+        domains: Vec<String>,
+    },
+    
+    /// The toxicity score of the text is at least a threshold
+    Toxicity {
+        /// Threshold between 0 and 1
+        threshold: f32,
+    },
+    
+    /// At least one of the conditions holds
+    Any {
+        /// Conditions to check
+        conditions: Vec<Condition>,
+    },
+    
+    /// The condition does not hold
+    Not {
+        /// Condition to negate
+        condition: Box<Condition>,
+    },
+}
+
+/// An action taken on matching content
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    /// Remove the content
+    Remove {
+        /// Whether to remove it as spam
+        #[serde(default)]
+        spam: bool,
+    },
+    
+    /// Report the content to the moderators
+    Report {
+        /// Report reason, which may use template placeholders
+        reason: String,
+    },
+    
+    /// Reply to the content
+    ///
+    /// The template may use the placeholders `{{author}}`, `{{subreddit}}`,
+    /// `{{title}}`, `{{permalink}}`, `{{kind}}` and `{{rule}}`.
+    Reply {
+        /// Reply template
+        template: String,
+        /// Whether to distinguish the reply as a moderator
+        #[serde(default)]
+        distinguish: bool,
+        /// Whether to sticky the reply (top-level replies to posts only)
+        #[serde(default)]
+        sticky: bool,
+    },
+    
+    /// Set the flair of a post, or the author's flair for comments
+    Flair {
+        /// Flair text
+        #[serde(default)]
+        text: Option<String>,
+        /// Flair CSS class
+        #[serde(default)]
+        css_class: Option<String>,
+        /// Flair template ID
+        #[serde(default)]
+        template_id: Option<String>,
+    },
+}
+
+/// A moderation rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    /// Name of the rule, used in logs and templates
+    pub name: String,
+    
+    /// Kind of content the rule applies to, or both if not set
+    #[serde(default)]
+    pub kind: Option<ContentKind>,
+    
+    /// Conditions that must all hold
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    
+    /// Actions taken when the rule matches
+    #[serde(default)]
+    pub actions: Vec<Action>,
+}
+
+impl Rule {
+    /// Create a new rule
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: None,
+            conditions: Vec::new(),
+            actions: Vec::new(),
+        }
+    }
+    
+    /// Apply the rule to one kind of content only
+    pub fn for_kind(mut self, kind: ContentKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+    
+    /// Add a condition
+    pub fn when(mut self, condition: Condition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+    
+    /// Add an action
+    pub fn then(mut self, action: Action) -> Self {
+        self.actions.push(action);
+        self
+    }
+}
+
+/// A rule that matched some content
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleMatch {
+    /// Name of the rule
+    pub rule: String,
+    
+    /// Actions of the rule
+    pub actions: Vec<Action>,
+}
+
+/// Hook scoring the toxicity of text
+#[async_trait]
+pub trait ToxicityScorer: Send + Sync {
+    /// Score the text between 0 (harmless) and 1 (toxic)
+    async fn score(&self, text: &str) -> Result<f32>;
+}
+
+/// Author details used by author conditions
+#[derive(Debug, Clone, Copy)]
+struct AuthorInfo {
+    /// Total karma
+    karma: i32,
+    
+    /// Account creation time
+    created_utc: DateTime<Utc>,
+}
+
+/// Evaluates content against rules and runs their actions
+#[derive(Clone)]
+pub struct RulesEngine {
+    /// Reddit client
+    client: RedditClient,
+    
+    /// Rules, in evaluation order
+    rules: Vec<Rule>,
+    
+    /// Compiled patterns by cache key
+    patterns: HashMap<String, Regex>,
+    
+    /// Toxicity scorer
+    scorer: Option<Arc<dyn ToxicityScorer>>,
+    
+    /// Whether actions are only logged
+    dry_run: bool,
+    
+    /// Author details by username, `None` for deleted or suspended accounts
+    authors: Arc<Mutex<HashMap<String, Option<AuthorInfo>>>>,
+}
+
+impl RulesEngine {
+    /// Create a new rules engine
+    pub fn new(client: RedditClient) -> Self {
+        Self {
+            client,
+            rules: Vec::new(),
+            patterns: HashMap::new(),
+            scorer: None,
+            dry_run: false,
+            authors: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+    
+    /// Add a rule, compiling its patterns
+    pub fn with_rule(mut self, rule: Rule) -> Result<Self> {
+        self.add_rule(rule)?;
+        Ok(self)
+    }
+    
+    /// Add rules from a JSON array
+    pub fn with_rules_json(mut self, json: &str) -> Result<Self> {
+        let rules: Vec<Rule> = serde_json::from_str(json)?;
+        for rule in rules {
+            self.add_rule(rule)?;
+        }
+        Ok(self)
+    }
+    
+    /// Set the toxicity scorer
+    pub fn with_toxicity_scorer<S: ToxicityScorer + 'static>(mut self, scorer: S) -> Self {
+        self.scorer = Some(Arc::new(scorer));
+        self
+    }
+    
+    /// Only log actions instead of running them
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+    
+    /// Add a rule, compiling its patterns
+    pub fn add_rule(&mut self, rule: Rule) -> Result<()> {
+        for condition in &rule.conditions {
+            self.compile(&rule.name, condition)?;
+        }
+        self.rules.push(rule);
+        Ok(())
+    }
+    
+    /// Get the rules
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+    
+    /// Find the rules matching the content, without running their actions
+    pub async fn evaluate(&self, content: Content<'_>) -> Result<Vec<RuleMatch>> {
+        let toxicity = OnceCell::new();
+        let mut matches = Vec::new();
+        
+        for rule in &self.rules {
+            if rule.kind.is_some_and(|kind| kind != content.kind()) {
+                continue;
+            }
+            
+            let mut matched = true;
+            for condition in &rule.conditions {
+                if !self.check(content, condition, &toxicity).await? {
+                    matched = false;
+                    break;
+                }
+            }
+            
+            if matched {
+                debug!("Rule '{}' matched {}", rule.name, content.fullname());
+                matches.push(RuleMatch {
+                    rule: rule.name.clone(),
+                    actions: rule.actions.clone(),
+                });
+            }
+        }
+        
+        Ok(matches)
+    }
+    
+    /// Evaluate the content and run the actions of the matching rules
+    pub async fn apply(&self, content: Content<'_>) -> Result<Vec<RuleMatch>> {
+        let matches = self.evaluate(content).await?;
+        let mut removed = false;
+        
+        for rule_match in &matches {
+            for action in &rule_match.actions {
+                if matches!(action, Action::Remove { .. }) {
+                    if removed {
+                        continue;
+                    }
+                    removed = true;
+                }
+                
+                self.execute(content, &rule_match.rule, action).await?;
+            }
+        }
+        
+        Ok(matches)
+    }
+    
+    /// Run an action on the content
+    pub async fn execute(&self, content: Content<'_>, rule: &str, action: &Action) -> Result<()> {
+        if self.dry_run {
+            info!("Dry run: rule '{}' would {:?} {}", rule, action, content.fullname());
+            return Ok(());
+        }
+        
+        info!("Rule '{}': {:?} {}", rule, action, content.fullname());
+        
+        match action {
+            Action::Remove { spam } => {
+                let mut params = HashMap::new();
+                params.insert("id".to_string(), content.fullname().to_string());
+                params.insert("spam".to_string(), spam.to_string());
+                
+                self.client.post::<serde_json::Value>("/api/remove", Some(params), None).await?;
+            }
+            Action::Report { reason } => {
+                let mut params = HashMap::new();
+                params.insert("thing_id".to_string(), content.fullname().to_string());
+                params.insert("reason".to_string(), render_template(reason, content, rule));
+                
+                self.client.post::<serde_json::Value>("/api/report", Some(params), None).await?;
+            }
+            Action::Reply { template, distinguish, sticky } => {
+                let text = render_template(template, content, rule);
+                let reply = self.client.submit_comment(content.fullname(), &text).await?;
+                
+                if *distinguish {
+                    let mut params = HashMap::new();
+                    params.insert("id".to_string(), reply);
+                    params.insert("how".to_string(), "yes".to_string());
+                    params.insert("sticky".to_string(), (*sticky && content.kind() == ContentKind::Post).to_string());
+                    
+                    self.client.post::<serde_json::Value>("/api/distinguish", Some(params), None).await?;
+                }
+            }
+            Action::Flair { text, css_class, template_id } => {
+                let endpoint = format!("/r/{}/api/selectflair", content.subreddit());
+                
+                let mut params = HashMap::new();
+                match content {
+                    Content::Post(post) => params.insert("link".to_string(), post.name.clone()),
+                    Content::Comment(comment) => params.insert("name".to_string(), comment.author.clone()),
+                };
+                if let Some(text) = text {
+                    params.insert("text".to_string(), render_template(text, content, rule));
+                }
+                if let Some(css_class) = css_class {
+                    params.insert("css_class".to_string(), css_class.clone());
+                }
+                if let Some(template_id) = template_id {
+                    params.insert("flair_template_id".to_string(), template_id.clone());
+                }
+                
+                self.client.post::<serde_json::Value>(&endpoint, Some(params), None).await?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Compile the patterns of a condition
+    fn compile(&mut self, rule: &str, condition: &Condition) -> Result<()> {
+        match condition {
+            Condition::Regex { pattern, case_sensitive, .. } => {
+                let key = pattern_key(pattern, *case_sensitive);
+                if let Entry::Vacant(entry) = self.patterns.entry(key) {
+                    let regex = RegexBuilder::new(pattern)
+                        .case_insensitive(!case_sensitive)
+                        .build()
+                        .map_err(|e| Error::RuleError(format!("Invalid pattern in rule '{}': {}", rule, e)))?;
+                    entry.insert(regex);
+                }
+            }
+            Condition::Toxicity { threshold } if !(0.0..=1.0).contains(threshold) => {
+                return Err(Error::RuleError(format!("Toxicity threshold of rule '{}' must be between 0 and 1", rule)));
+            }
+            Condition::Any { conditions } => {
+                for condition in conditions {
+                    self.compile(rule, condition)?;
+                }
+            }
+            Condition::Not { condition } => self.compile(rule, condition)?,
+            _ => {}
+        }
+        
+        Ok(())
+    }
+    
+    /// Check a condition against the content
+    fn check<'a>(
+        &'a self,
+        content: Content<'a>,
+        condition: &'a Condition,
+        toxicity: &'a OnceCell<f32>,
+    ) -> BoxFuture<'a, Result<bool>> {
+        async move {
+            match condition {
+                Condition::Regex { field, pattern, case_sensitive } => {
+                    let regex = &self.patterns[&pattern_key(pattern, *case_sensitive)];
+                    let value = match field {
+                        Field::Title => content.title().to_string(),
+                        Field::Body => content.body().to_string(),
+                        Field::Text => content.text(),
+                        Field::Url => match content {
+                            Content::Post(post) if !post.is_self => post.url.clone(),
+                            _ => String::new(),
+                        },
+                        Field::Author => content.author().to_string(),
+                        Field::AuthorFlair => match content {
+                            Content::Post(post) => post.author_flair_text.clone().unwrap_or_default(),
+                            Content::Comment(comment) => comment.author_flair_text.clone().unwrap_or_default(),
+                        },
+                    };
+                    Ok(regex.is_match(&value))
+                }
+                Condition::AuthorKarma { min, max } => {
+                    Ok(self.author(content.author()).await?.is_some_and(|author| {
+                        min.is_none_or(|min| author.karma >= min) && max.is_none_or(|max| author.karma <= max)
+                    }))
+                }
+                Condition::AuthorAge { min_days, max_days } => {
+                    Ok(self.author(content.author()).await?.is_some_and(|author| {
+                        let days = (Utc::now() - author.created_utc).num_days();
+                        min_days.is_none_or(|min| days >= min) && max_days.is_none_or(|max| days <= max)
+                    }))
+                }
+                Condition::Domain { domains } => {
+                    let linked = content.domains();
+                    Ok(domains.iter().any(|domain| linked.iter().any(|host| domain_matches(domain, host))))
+                }
+                Condition::Toxicity { threshold } => {
+                    let scorer = self.scorer.as_ref()
+                        .ok_or_else(|| Error::RuleError("No toxicity scorer configured".to_string()))?;
+                    let text = content.text();
+                    let score = toxicity.get_or_try_init(|| scorer.score(&text)).await?;
+                    Ok(*score >= *threshold)
+                }
+                Condition::Any { conditions } => {
+                    for condition in conditions {
+                        if self.check(content, condition, toxicity).await? {
+                            return Ok(true);
+                        }
+                    }
+                    Ok(false)
+                }
+                Condition::Not { condition } => Ok(!self.check(content, condition, toxicity).await?),
+            }
+        }
+        .boxed()
+    }
+    
+    /// Get the details of an author, fetching them on first use
+    async fn author(&self, username: &str) -> Result<Option<AuthorInfo>> {
+        if username == "[deleted]" {
+            return Ok(None);
+        }
+        
+        let mut authors = self.authors.lock().await;
+        if let Some(author) = authors.get(username) {
+            return Ok(*author);
+        }
+        
+        let endpoint = format!("/user/{}/about", username);
+        let author = match self.client.get::<Thing<User>>(&endpoint, None).await {
+            Ok(user) => Some(AuthorInfo {
+                karma: user.data.total_karma.unwrap_or(user.data.link_karma + user.data.comment_karma),
+                created_utc: user.data.created_utc,
+            }),
+            Err(Error::ApiError { status_code: 403 | 404, .. }) => None,
+            Err(e) => return Err(e),
+        };
+        
+        authors.insert(username.to_string(), author);
+        Ok(author)
+    }
+}
+
+impl std::fmt::Debug for RulesEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RulesEngine")
+            .field("rules", &self.rules)
+            .field("scorer", &self.scorer.is_some())
+            .field("dry_run", &self.dry_run)
+            .finish()
+    }
+}
+
+/// Cache key of a compiled pattern
+fn pattern_key(pattern: &str, case_sensitive: bool) -> String {
+    format!("{}:{}", if case_sensitive { "s" } else { "i" }, pattern)
+}
+
+/// Check whether a host is a domain or one of its subdomains
+fn domain_matches(domain: &str, host: &str) -> bool {
+    let domain = domain.trim_start_matches('.').to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// Fill in the placeholders of a template
+pub fn render_template(template: &str, content: Content<'_>, rule: &str) -> String {
+    let kind = match content.kind() {
+        ContentKind::Post => "post",
+        ContentKind::Comment => "comment",
+    };
+    
+    template
+        .replace("{{author}}", content.author())
+        .replace("{{subreddit}}", content.subreddit())
+        .replace("{{title}}", content.title())
+        .replace("{{permalink}}", content.permalink())
+        .replace("{{kind}}", kind)
+        .replace("{{rule}}", rule)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ClientConfig;
+    use serde_json::json;
+    
+    fn post(title: &str, selftext: &str, domain: &str) -> Post {
+        serde_json::from_value(json!({
+            "id": "abc", "name": "t3_abc", "title": title,
+            "subreddit": "rust", "subreddit_id": "t5_2s7lj", "subreddit_name_prefixed": "r/rust",
+            "author": "ferris", "selftext": selftext, "url": format!("https://{}/x", domain),
+            "permalink": "/r/rust/comments/abc/", "domain": domain, "is_self": domain == "self.rust",
+            "over_18": false, "spoiler": false, "locked": false, "stickied": false, "archived": false,
+            "saved": false, "hidden": false, "visited": false, "ups": 1, "downs": 0, "score": 1,
+            "upvote_ratio": 1.0, "num_comments": 0, "created_utc": 1700000000, "edited": false,
+            "all_awardings": [],
+        })).unwrap()
+    }
+    
+    struct FixedScorer(f32);
+    
+    #[async_trait]
+    impl ToxicityScorer for FixedScorer {
+        async fn score(&self, _text: &str) -> Result<f32> {
+            Ok(self.0)
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_evaluate() {
+        let client = RedditClient::new(ClientConfig::default()).await.unwrap();
+        let engine = RulesEngine::new(client)
+            .with_rules_json(r#"[
+                {"name": "shorteners", "conditions": [{"type": "domain", "domains": ["bit.ly"]}], "actions": [{"type": "remove", "spam": true}]},
+                {"name": "help", "kind": "post", "conditions": [{"type": "regex", "field": "title", "pattern": "^help\\b"}], "actions": [{"type": "flair", "text": "Question"}]},
+                {"name": "toxic", "conditions": [{"type": "not", "condition": {"type": "toxicity", "threshold": 0.5}}]}
+            ]"#)
+            .unwrap()
+            .with_toxicity_scorer(FixedScorer(0.2));
+        
+        let matches = engine.evaluate(Content::Post(&post("HELP with lifetimes", "see https://www.bit.ly/abc", "self.rust"))).await.unwrap();
+        let names: Vec<_> = matches.iter().map(|m| m.rule.as_str()).collect();
+        assert_eq!(names, vec!["shorteners", "help", "toxic"]);
+        assert_eq!(matches[0].actions, vec![Action::Remove { spam: true }]);
+        
+        let matches = engine.evaluate(Content::Post(&post("Helpful crates", "", "github.com"))).await.unwrap();
+        assert_eq!(matches.len(), 1);
+        
+        let invalid = Rule::new("invalid").when(Condition::Regex { field: Field::Body, pattern: "(".to_string(), case_sensitive: false });
+        assert!(matches!(engine.clone().with_rule(invalid), Err(Error::RuleError(_))));
+    }
+    
+    #[test]
+    fn test_render_template() {
+        let post = post("Title", "", "self.rust");
+        let text = render_template("Hi u/{{author}}, your {{kind}} in r/{{subreddit}} broke '{{rule}}'", Content::Post(&post), "no memes");
+        assert_eq!(text, "Hi u/ferris, your post in r/rust broke 'no memes'");
+    }
+}
@@ -1,13 +1,19 @@
 //! Reddit search functionality
 //!
-//! This module provides functionality for searching Reddit content.
+//! This module provides functionality for searching Reddit content, and for
+//! discovering subreddits related to a set of seed subreddits.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
+use lazy_static::lazy_static;
+use log::{debug, warn};
+use regex::Regex;
+
 use crate::{Result, Error, Sort, TimeRange};
 use crate::client::RedditClient;
 use crate::models::{Thing, Listing, Post, Comment, Subreddit};
+use crate::subreddit::ListingFilter;
 
 /// The type of content to search for
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -91,6 +97,11 @@ impl SearchClient {
             .await
     }
     
+    /// Discover subreddits related to some seed subreddits
+    pub fn discover<S: AsRef<str>>(&self, seeds: &[S]) -> SubredditDiscovery {
+        SubredditDiscovery::new(self.client.clone(), seeds)
+    }
+    
     /// Search for comments
     pub async fn search_comments(&self, query: &str, limit: Option<u32>) -> Result<Vec<Comment>> {
         self.query(query)
@@ -351,6 +362,293 @@ impl SearchBuilder {
     }
 }
 
+/// Search for subreddits matching a query
+pub async fn subreddits(client: &RedditClient, query: &str) -> Result<Vec<Subreddit>> {
+    SearchClient::new(client.clone()).search_subreddits(query, None).await
+}
+
+lazy_static! {
+    /// Subreddit links in sidebar markdown, e.g. `r/rust` or `/r/learnrust`
+    static ref SUBREDDIT_LINK: Regex = Regex::new(r"(?:^|[^\w/]|reddit\.com)/?r/([A-Za-z0-9][A-Za-z0-9_]{1,20})\b").unwrap();
+}
+
+/// Accounts whose posts say nothing about where a community's members hang out
+const IGNORED_POSTERS: &[&str] = &["[deleted]", "AutoModerator"];
+
+/// A subreddit found by [`SubredditDiscovery`], with the evidence it was ranked by
+#[derive(Debug, Clone)]
+pub struct RelatedSubreddit {
+    /// The related subreddit
+    pub subreddit: Subreddit,
+    
+    /// Relevance score; higher is more related
+    pub score: f64,
+    
+    /// Number of top posters of the seeds who also post in the subreddit
+    pub shared_posters: usize,
+    
+    /// Number of seeds whose sidebar links to the subreddit
+    pub sidebar_links: usize,
+}
+
+/// Evidence collected for a candidate subreddit before it is looked up
+#[derive(Debug, Clone, Default)]
+struct Candidate {
+    /// Top posters of the seeds who post in the candidate
+    posters: HashSet<String>,
+    
+    /// Seeds whose sidebar links to the candidate
+    linked_from: HashSet<String>,
+}
+
+/// A builder for discovering subreddits related to seed subreddits
+///
+/// Candidates are found two ways: the communities the top posters of the
+/// seeds also post in, and the subreddits the seeds link to in their
+/// sidebars. Each shared poster counts once and each sidebar link counts
+/// `sidebar_weight`, so a candidate that many seeds point at outranks one
+/// that a single prolific poster happens to frequent.
+#[derive(Debug, Clone)]
+pub struct SubredditDiscovery {
+    /// Reddit client
+    client: RedditClient,
+    
+    /// Seed subreddits, without the r/ prefix
+    seeds: Vec<String>,
+    
+    /// Time range of the top posts the posters are taken from
+    time: TimeRange,
+    
+    /// Number of top posts to fetch per seed
+    posts_per_seed: u32,
+    
+    /// Number of top posters to follow per seed
+    posters_per_seed: usize,
+    
+    /// Number of submissions to fetch per poster
+    submissions_per_poster: u32,
+    
+    /// Score of a sidebar link, relative to a shared poster
+    sidebar_weight: f64,
+    
+    /// Maximum number of results to return
+    limit: usize,
+    
+    /// Whether to include NSFW subreddits
+    include_nsfw: bool,
+}
+
+impl SubredditDiscovery {
+    /// Create a new discovery builder
+    pub fn new<S: AsRef<str>>(client: RedditClient, seeds: &[S]) -> Self {
+        let seeds = seeds.iter()
+            .map(|s| normalize_name(s.as_ref()))
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        
+        Self {
+            client,
+            seeds,
+            time: TimeRange::Month,
+            posts_per_seed: 50,
+            posters_per_seed: 10,
+            submissions_per_poster: 50,
+            sidebar_weight: 3.0,
+            limit: 25,
+            include_nsfw: false,
+        }
+    }
+    
+    /// Set the time range of the top posts the posters are taken from
+    pub fn time(mut self, time: TimeRange) -> Self {
+        self.time = time;
+        self
+    }
+    
+    /// Set the number of top posts to fetch per seed
+    pub fn posts_per_seed(mut self, posts: u32) -> Self {
+        self.posts_per_seed = posts;
+        self
+    }
+    
+    /// Set the number of top posters to follow per seed
+    pub fn posters_per_seed(mut self, posters: usize) -> Self {
+        self.posters_per_seed = posters;
+        self
+    }
+    
+    /// Set the number of submissions to fetch per poster
+    pub fn submissions_per_poster(mut self, submissions: u32) -> Self {
+        self.submissions_per_poster = submissions;
+        self
+    }
+    
+    /// Set the score of a sidebar link, relative to a shared poster
+    pub fn sidebar_weight(mut self, weight: f64) -> Self {
+        self.sidebar_weight = weight;
+        self
+    }
+    
+    /// Set the maximum number of results to return
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+    
+    /// Set whether to include NSFW subreddits
+    pub fn include_nsfw(mut self, include_nsfw: bool) -> Self {
+        self.include_nsfw = include_nsfw;
+        self
+    }
+    
+    /// Discover related subreddits, most related first
+    ///
+    /// Posters whose submissions cannot be fetched (suspended or deleted
+    /// accounts) and candidates that cannot be looked up (private or banned
+    /// subreddits) are skipped.
+    pub async fn discover(self) -> Result<Vec<RelatedSubreddit>> {
+        if self.seeds.is_empty() {
+            return Err(Error::Other("At least one seed subreddit is required".to_string()));
+        }
+        
+        let seeds: HashSet<&str> = self.seeds.iter().map(String::as_str).collect();
+        let mut candidates: HashMap<String, Candidate> = HashMap::new();
+        let mut posters = Vec::new();
+        
+        for seed in &self.seeds {
+            let subreddit = self.client.subreddit(seed);
+            
+            let about = subreddit.about().await?;
+            for linked in sidebar_links(&about.description) {
+                if !seeds.contains(linked.as_str()) {
+                    candidates.entry(linked).or_default().linked_from.insert(seed.clone());
+                }
+            }
+            
+            let posts = subreddit
+                .posts(ListingFilter::Top(self.time), Some(self.posts_per_seed), None, None)
+                .await?;
+            for poster in top_posters(&posts, self.posters_per_seed) {
+                if !posters.contains(&poster) {
+                    posters.push(poster);
+                }
+            }
+        }
+        
+        for poster in &posters {
+            let submissions = match self.submissions(poster).await {
+                Ok(submissions) => submissions,
+                Err(e) => {
+                    debug!("Skipping poster u/{}: {}", poster, e);
+                    continue;
+                }
+            };
+            
+            for post in submissions {
+                let name = post.subreddit.to_lowercase();
+                if !seeds.contains(name.as_str()) && !name.starts_with("u_") {
+                    candidates.entry(name).or_default().posters.insert(poster.clone());
+                }
+            }
+        }
+        
+        let mut related = Vec::new();
+        for (name, score) in rank_candidates(&candidates, self.sidebar_weight) {
+            if related.len() >= self.limit {
+                break;
+            }
+            
+            let subreddit = match self.client.subreddit(&name).about().await {
+                Ok(subreddit) => subreddit,
+                Err(e) => {
+                    warn!("Skipping related subreddit r/{}: {}", name, e);
+                    continue;
+                }
+            };
+            
+            if subreddit.over18 && !self.include_nsfw {
+                continue;
+            }
+            
+            let candidate = &candidates[&name];
+            related.push(RelatedSubreddit {
+                subreddit,
+                score,
+                shared_posters: candidate.posters.len(),
+                sidebar_links: candidate.linked_from.len(),
+            });
+        }
+        
+        Ok(related)
+    }
+    
+    /// Fetch the latest submissions of a poster
+    async fn submissions(&self, poster: &str) -> Result<Vec<Post>> {
+        let endpoint = format!("/user/{}/submitted", poster);
+        
+        let mut params = HashMap::new();
+        params.insert("limit".to_string(), self.submissions_per_poster.to_string());
+        
+        let response: Listing<Thing<Post>> = self.client.get(&endpoint, Some(params)).await?;
+        
+        Ok(response.data.children.into_iter().map(|p| p.data).collect())
+    }
+}
+
+/// Lowercase a subreddit name and remove the r/ prefix, if present
+fn normalize_name(name: &str) -> String {
+    let name = name.trim().trim_start_matches('/');
+    let name = name.strip_prefix("r/").unwrap_or(name);
+    name.trim_end_matches('/').to_lowercase()
+}
+
+/// Extract the subreddits a sidebar links to, lowercased and deduplicated
+fn sidebar_links(description: &str) -> Vec<String> {
+    let mut links = Vec::new();
+    
+    for capture in SUBREDDIT_LINK.captures_iter(description) {
+        let name = capture[1].to_lowercase();
+        if !links.contains(&name) {
+            links.push(name);
+        }
+    }
+    
+    links
+}
+
+/// Pick the posters with the most posts in a listing
+fn top_posters(posts: &[Post], count: usize) -> Vec<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for post in posts {
+        if !IGNORED_POSTERS.contains(&post.author.as_str()) {
+            *counts.entry(post.author.as_str()).or_default() += 1;
+        }
+    }
+    
+    let mut posters = counts.into_iter().collect::<Vec<_>>();
+    posters.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    
+    posters.into_iter()
+        .take(count)
+        .map(|(author, _)| author.to_string())
+        .collect()
+}
+
+/// Score candidates and sort them, most related first
+fn rank_candidates(candidates: &HashMap<String, Candidate>, sidebar_weight: f64) -> Vec<(String, f64)> {
+    let mut ranked = candidates.iter()
+        .map(|(name, candidate)| {
+            let score = candidate.posters.len() as f64
+                + candidate.linked_from.len() as f64 * sidebar_weight;
+            (name.clone(), score)
+        })
+        .filter(|(_, score)| *score > 0.0)
+        .collect::<Vec<_>>();
+    
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -396,4 +694,42 @@ mod tests {
         assert_eq!(params.get("include_facets"), Some(&"true".to_string()));
         assert_eq!(params.get("after"), Some(&"t3_123456".to_string()));
     }
-} 
\ No newline at end of file
+    
+    #[test]
+    fn test_sidebar_links() {
+        let description = "Related: r/learnrust, /r/rust_gamedev and [Cargo](https://reddit.com/r/cargo).\n\
+                           See also r/LearnRust and https://example.com/r/ignored";
+        
+        assert_eq!(
+            sidebar_links(description),
+            vec!["learnrust", "rust_gamedev", "cargo"],
+        );
+        assert_eq!(normalize_name("/r/Rust/"), "rust");
+        assert_eq!(normalize_name("golang"), "golang");
+    }
+    
+    #[test]
+    fn test_rank_candidates() {
+        let mut candidates: HashMap<String, Candidate> = HashMap::new();
+        
+        let popular = candidates.entry("golang".to_string()).or_default();
+        popular.posters.extend(["alice", "bob", "carol"].map(String::from));
+        
+        let linked = candidates.entry("learnrust".to_string()).or_default();
+        linked.posters.insert("alice".to_string());
+        linked.linked_from.insert("rust".to_string());
+        
+        candidates.entry("cpp".to_string()).or_default().posters.insert("bob".to_string());
+        candidates.insert("empty".to_string(), Candidate::default());
+        
+        let ranked = rank_candidates(&candidates, 3.0);
+        assert_eq!(
+            ranked,
+            vec![
+                ("learnrust".to_string(), 4.0),
+                ("golang".to_string(), 3.0),
+                ("cpp".to_string(), 1.0),
+            ],
+        );
+    }
+}
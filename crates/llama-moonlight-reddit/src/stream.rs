@@ -0,0 +1,214 @@
+//! Streams of new content
+//!
+//! Reddit has no push API, so streams poll a listing and yield the items that
+//! were not seen before, oldest first. Polling slows down while nothing new
+//! arrives and speeds back up as soon as something does. Errors are yielded
+//! without ending the stream, so a bot survives a failed poll.
+
+use std::collections::{HashSet, VecDeque};
+use std::future::Future;
+use std::time::Duration;
+use futures::stream::{self, BoxStream, StreamExt};
+use log::debug;
+
+use crate::Result;
+
+/// Options of a polling stream
+#[derive(Debug, Clone)]
+pub struct StreamOptions {
+    /// Delay between polls while new items arrive
+    pub interval: Duration,
+    
+    /// Longest delay between polls while nothing new arrives
+    pub max_interval: Duration,
+    
+    /// Whether to skip the items present at the first poll
+    pub skip_existing: bool,
+    
+    /// Number of item names remembered to recognize items already yielded
+    pub seen_capacity: usize,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            max_interval: Duration::from_secs(120),
+            skip_existing: false,
+            seen_capacity: 1000,
+        }
+    }
+}
+
+impl StreamOptions {
+    /// Set the delay between polls while new items arrive
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+    
+    /// Set the longest delay between polls while nothing new arrives
+    pub fn with_max_interval(mut self, max_interval: Duration) -> Self {
+        self.max_interval = max_interval;
+        self
+    }
+    
+    /// Set whether to skip the items present at the first poll
+    pub fn with_skip_existing(mut self, skip_existing: bool) -> Self {
+        self.skip_existing = skip_existing;
+        self
+    }
+}
+
+/// Names of the items seen recently, forgetting the oldest beyond a capacity
+#[derive(Debug)]
+struct SeenNames {
+    names: HashSet<String>,
+    order: VecDeque<String>,
+    capacity: usize,
+}
+
+impl SeenNames {
+    fn new(capacity: usize) -> Self {
+        Self {
+            names: HashSet::new(),
+            order: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+    
+    /// Remember a name, returning whether it is new
+    fn insert(&mut self, name: &str) -> bool {
+        if self.names.contains(name) {
+            return false;
+        }
+        
+        self.names.insert(name.to_string());
+        self.order.push_back(name.to_string());
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.names.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// State of a polling stream between items
+struct PollState<T, F> {
+    fetch: F,
+    name_of: fn(&T) -> &str,
+    options: StreamOptions,
+    seen: SeenNames,
+    queue: VecDeque<T>,
+    delay: Duration,
+    polls: u64,
+}
+
+/// Stream the new items of a listing
+///
+/// `fetch` returns the newest items of the listing, newest first, as Reddit
+/// lists them; `name_of` gives the fullname identifying an item.
+pub fn poll_listing<T, F, Fut>(
+    fetch: F,
+    name_of: fn(&T) -> &str,
+    options: StreamOptions,
+) -> BoxStream<'static, Result<T>>
+where
+    T: Send + 'static,
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<Vec<T>>> + Send,
+{
+    let state = PollState {
+        fetch,
+        name_of,
+        seen: SeenNames::new(options.seen_capacity),
+        delay: options.interval,
+        options,
+        queue: VecDeque::new(),
+        polls: 0,
+    };
+    
+    stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(item) = state.queue.pop_front() {
+                return Some((Ok(item), state));
+            }
+            
+            if state.polls > 0 {
+                tokio::time::sleep(state.delay).await;
+            }
+            state.polls += 1;
+            
+            match (state.fetch)().await {
+                Ok(items) => {
+                    let name_of = state.name_of;
+                    let mut new_items: Vec<T> = items
+                        .into_iter()
+                        .filter(|item| state.seen.insert(name_of(item)))
+                        .collect();
+                    new_items.reverse();
+                    
+                    if state.polls == 1 && state.options.skip_existing {
+                        debug!("Skipping {} existing items", new_items.len());
+                        new_items.clear();
+                    }
+                    
+                    state.delay = if new_items.is_empty() {
+                        (state.delay * 2).min(state.options.max_interval)
+                    } else {
+                        state.options.interval
+                    };
+                    state.queue.extend(new_items);
+                }
+                Err(e) => {
+                    state.delay = (state.delay * 2).min(state.options.max_interval);
+                    return Some((Err(e), state));
+                }
+            }
+        }
+    })
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    
+    #[tokio::test]
+    async fn test_poll_listing() {
+        // Each poll returns the newest items first
+        let polls = Arc::new(Mutex::new(VecDeque::from(vec![
+            vec!["b", "a"],
+            vec!["c", "b", "a"],
+            vec!["e", "d", "c"],
+        ])));
+        let fetch = move || {
+            let polls = polls.clone();
+            async move {
+                let items = polls.lock().unwrap().pop_front().unwrap_or_default();
+                Ok(items.into_iter().map(String::from).collect::<Vec<_>>())
+            }
+        };
+        
+        let options = StreamOptions::default().with_interval(Duration::from_millis(1));
+        let names: Vec<String> = poll_listing(fetch, |name: &String| name.as_str(), options)
+            .take(5)
+            .map(|item| item.unwrap())
+            .collect()
+            .await;
+        assert_eq!(names, vec!["a", "b", "c", "d", "e"]);
+    }
+    
+    #[test]
+    fn test_seen_names() {
+        let mut seen = SeenNames::new(2);
+        assert!(seen.insert("a"));
+        assert!(!seen.insert("a"));
+        assert!(seen.insert("b"));
+        assert!(seen.insert("c"));
+        // The oldest name is forgotten
+        assert!(seen.insert("a"));
+    }
+}
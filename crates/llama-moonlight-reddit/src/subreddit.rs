@@ -8,6 +8,7 @@ use serde::{Serialize, Deserialize};
 
 use crate::{Result, Error, Sort, TimeRange};
 use crate::client::RedditClient;
+use crate::wiki::WikiClient;
 use crate::models::{Thing, Listing, Post, Comment, Subreddit, SubredditRule};
 
 /// Listing filter for subreddit listings
@@ -372,6 +373,11 @@ impl SubredditClient {
         Ok(response.json.data.name)
     }
     
+    /// Get a client for the subreddit's wiki
+    pub fn wiki(&self) -> WikiClient {
+        WikiClient::new(self.client.clone(), &self.name)
+    }
+    
     /// Get the subreddit's wiki index
     pub async fn wiki_index(&self) -> Result<String> {
         let endpoint = format!("/r/{}/wiki/index", self.name);
@@ -2,15 +2,43 @@
 //!
 //! This module provides functionality to respect Reddit's rate limits.
 //! Reddit limits API usage to a specific number of requests per minute,
-//! and this module helps ensure those limits are not exceeded.
+//! and this module helps ensure those limits are not exceeded. The pacing
+//! is done by the shared `llama-moonlight-ratelimit` crate.
+//!
+//! Pacing alone does not stop a bot with many tasks from spending its budget
+//! in bursts: every task may be paced correctly while all of them together
+//! drain `x-ratelimit-remaining` long before the window resets. The limiter
+//! therefore also caps how many requests are in flight, and adapts that cap
+//! with additive increase, multiplicative decrease (AIMD): it is cut when the
+//! remaining budget drops faster than it can last until the reset, and raised
+//! again each time the window resets. A reset is recognised by the reset time
+//! moving forward, not by the remaining budget growing, since responses to
+//! concurrent requests arrive out of order.
 
-use std::time::{Duration, Instant};
-use tokio::time::sleep;
-use log::{debug, warn};
+use std::sync::Mutex;
+use std::time::Duration;
+use llama_moonlight_ratelimit::{RateLimit, RateLimitHeaders};
+use log::debug;
+use reqwest::header::HeaderMap;
+use tokio::sync::Notify;
+use tokio::time::Instant;
 
 use crate::{Result, Error};
 
+/// Key of the single limit in a [`RateLimiter`]
+const KEY: &str = "reddit";
+
+/// How far the reported end of a window may drift between responses
+///
+/// `x-ratelimit-reset` is rounded to seconds and responses take varying
+/// time to arrive, so responses in the same window disagree slightly on when
+/// it ends.
+const RESET_SLACK: Duration = Duration::from_secs(2);
+
 /// A rate limiter for Reddit API requests
+///
+/// Paces requests with the shared sliding window limiter, which also follows
+/// the limit Reddit reports in its `x-ratelimit-*` headers.
 #[derive(Debug)]
 pub struct RateLimiter {
     /// Maximum number of requests allowed in the time window
@@ -19,96 +47,76 @@ pub struct RateLimiter {
     /// Duration of the time window
     time_window: Duration,
     
-    /// Number of requests remaining in the current window
-    remaining: u32,
-    
-    /// Time when the rate limit resets
-    reset_time: Duration,
+    /// Shared limiter doing the pacing
+    limiter: llama_moonlight_ratelimit::RateLimiter,
     
-    /// Last time the rate limiter was reset
-    last_reset: Instant,
+    /// Adaptive cap on requests in flight
+    concurrency: AdaptiveConcurrency,
 }
 
 impl RateLimiter {
-    /// Create a new rate limiter
+    /// Create a new rate limiter with the default AIMD parameters
     pub fn new(max_requests: u32, time_window: Duration) -> Self {
+        Self::with_aimd(max_requests, time_window, AimdConfig::default())
+    }
+    
+    /// Create a new rate limiter with custom AIMD parameters
+    pub fn with_aimd(max_requests: u32, time_window: Duration, aimd: AimdConfig) -> Self {
+        let limiter = llama_moonlight_ratelimit::RateLimiter::new();
+        limiter.register(KEY, vec![RateLimit::new(max_requests, time_window)]);
+        
         Self {
             max_requests,
             time_window,
-            remaining: max_requests,
-            reset_time: time_window,
-            last_reset: Instant::now(),
+            limiter,
+            concurrency: AdaptiveConcurrency::new(aimd),
         }
     }
     
     /// Acquire permission to make a request, waiting if necessary
-    pub async fn acquire(&mut self) -> Result<()> {
-        // Check if the time window has elapsed
-        let now = Instant::now();
-        let elapsed = now.duration_since(self.last_reset);
-        
-        if elapsed >= self.time_window {
-            // Reset the rate limiter
-            self.remaining = self.max_requests;
-            self.last_reset = now;
-            self.reset_time = self.time_window;
-        } else {
-            // Update the reset time
-            self.reset_time = self.time_window.saturating_sub(elapsed);
-        }
-        
-        // Check if we have any requests remaining
-        if self.remaining == 0 {
-            // Calculate how long to wait
-            let wait_time = self.reset_time;
-            
-            debug!("Rate limit exceeded, waiting for {} seconds", wait_time.as_secs());
-            
-            // Wait until the rate limit resets
-            sleep(wait_time).await;
-            
-            // Reset the rate limiter
-            self.remaining = self.max_requests;
-            self.last_reset = Instant::now();
-            self.reset_time = self.time_window;
-        }
-        
-        // Decrement the remaining requests
-        self.remaining -= 1;
-        
-        Ok(())
+    ///
+    /// The returned permit holds one of the concurrency slots until it is
+    /// dropped, which should be once the response headers have been passed to
+    /// [`update_from_headers`](Self::update_from_headers).
+    pub async fn acquire(&self) -> Result<RequestPermit<'_>> {
+        let permit = self.concurrency.acquire().await;
+        self.limiter.acquire(KEY, 1).await
+            .map_err(|e| Error::RateLimitError(e.to_string()))?;
+        Ok(permit)
     }
     
     /// Get the number of requests remaining
     pub fn remaining(&self) -> u32 {
-        self.remaining
+        self.limiter.remaining(KEY).unwrap_or(self.max_requests)
     }
     
-    /// Get the time until the rate limit resets
+    /// Get the time until the full rate limit is available again
     pub fn reset_time(&self) -> Duration {
-        let now = Instant::now();
-        let elapsed = now.duration_since(self.last_reset);
-        
-        if elapsed >= self.time_window {
-            Duration::from_secs(0)
-        } else {
-            self.time_window.saturating_sub(elapsed)
-        }
+        self.limiter.estimated_wait(KEY, self.max_requests)
+    }
+    
+    /// Update the limit from the `x-ratelimit-*` headers of a response
+    ///
+    /// Also adapts the concurrency limit to how fast the remaining budget drops.
+    pub fn update_from_headers(&self, headers: &HeaderMap) {
+        let headers = RateLimitHeaders::from_header_map(headers);
+        self.limiter.update_from_headers(KEY, &headers);
+        self.concurrency.observe(&headers, Instant::now());
     }
     
-    /// Set the remaining requests based on response headers
-    pub fn set_remaining(&mut self, remaining: u32) {
-        self.remaining = remaining;
+    /// Get the current limit on requests in flight
+    pub fn concurrency_limit(&self) -> usize {
+        self.concurrency.limit()
     }
     
-    /// Set the reset time based on response headers
-    pub fn set_reset(&mut self, reset: Duration) {
-        self.reset_time = reset;
+    /// Get the number of requests in flight
+    pub fn in_flight(&self) -> usize {
+        self.concurrency.in_flight()
     }
     
     /// Check if a request can be made immediately
     pub fn can_request(&self) -> bool {
-        self.remaining > 0
+        self.limiter.estimated_wait(KEY, 1).is_zero()
     }
     
     /// Get the maximum number of requests
@@ -117,8 +125,11 @@ impl RateLimiter {
     }
     
     /// Set the maximum number of requests
+    ///
+    /// Requests made so far no longer count against the new limit.
     pub fn set_max_requests(&mut self, max_requests: u32) {
         self.max_requests = max_requests;
+        self.limiter.register(KEY, vec![RateLimit::new(self.max_requests, self.time_window)]);
     }
     
     /// Get the time window
@@ -127,8 +138,229 @@ impl RateLimiter {
     }
     
     /// Set the time window
+    ///
+    /// Requests made so far no longer count against the new limit.
     pub fn set_time_window(&mut self, time_window: Duration) {
         self.time_window = time_window;
+        self.limiter.register(KEY, vec![RateLimit::new(self.max_requests, self.time_window)]);
+    }
+}
+
+/// Parameters of the adaptive concurrency limit
+#[derive(Debug, Clone)]
+pub struct AimdConfig {
+    /// Requests allowed in flight at first
+    pub initial_concurrency: usize,
+    
+    /// Lowest the limit is cut to
+    pub min_concurrency: usize,
+    
+    /// Highest the limit is raised to
+    pub max_concurrency: usize,
+    
+    /// Amount the limit is raised by each time the window resets
+    pub additive_increase: usize,
+    
+    /// Factor the limit is multiplied by when the budget drops too fast
+    pub multiplicative_decrease: f64,
+    
+    /// How much faster than sustainable the budget may drop before the limit is cut
+    ///
+    /// The sustainable rate spends the remaining budget evenly until the reset.
+    pub tolerance: f64,
+    
+    /// Requests the budget must drop by before its rate is judged
+    pub sample_size: u32,
+}
+
+impl Default for AimdConfig {
+    fn default() -> Self {
+        Self {
+            initial_concurrency: 8,
+            min_concurrency: 1,
+            max_concurrency: 16,
+            additive_increase: 1,
+            multiplicative_decrease: 0.5,
+            tolerance: 1.5,
+            sample_size: 5,
+        }
+    }
+}
+
+/// Permission to make one request, holding a concurrency slot until dropped
+#[derive(Debug)]
+pub struct RequestPermit<'a> {
+    /// Limit the slot is returned to
+    concurrency: &'a AdaptiveConcurrency,
+}
+
+impl Drop for RequestPermit<'_> {
+    fn drop(&mut self) {
+        self.concurrency.release();
+    }
+}
+
+/// Remaining budget reported at some point
+#[derive(Debug, Clone, Copy)]
+struct BudgetSample {
+    /// Requests remaining in the window
+    remaining: u32,
+    
+    /// Time until the window resets, if reported
+    reset: Option<Duration>,
+    
+    /// When the sample was taken
+    at: Instant,
+}
+
+impl BudgetSample {
+    /// Get when the window of the sample ends, if the reset was reported
+    fn window_end(&self) -> Option<Instant> {
+        self.reset.map(|reset| self.at + reset)
+    }
+    
+    /// Check whether the sample belongs to a later window than `baseline`
+    ///
+    /// Without reset times, a budget that grew is taken as a reset.
+    fn starts_window_after(&self, baseline: &BudgetSample) -> bool {
+        match (self.window_end(), baseline.window_end()) {
+            (Some(end), Some(baseline_end)) => end > baseline_end + RESET_SLACK,
+            _ => self.remaining > baseline.remaining,
+        }
+    }
+}
+
+/// State of an adaptive concurrency limit
+#[derive(Debug)]
+struct ConcurrencyState {
+    /// Requests allowed in flight
+    limit: usize,
+    
+    /// Requests in flight
+    in_flight: usize,
+    
+    /// Budget the next rate is measured from
+    baseline: Option<BudgetSample>,
+}
+
+/// A limit on requests in flight, adapted with AIMD
+#[derive(Debug)]
+struct AdaptiveConcurrency {
+    config: AimdConfig,
+    state: Mutex<ConcurrencyState>,
+    released: Notify,
+}
+
+impl AdaptiveConcurrency {
+    fn new(config: AimdConfig) -> Self {
+        let limit = config.initial_concurrency
+            .clamp(config.min_concurrency.max(1), config.max_concurrency.max(1));
+        
+        Self {
+            config,
+            state: Mutex::new(ConcurrencyState {
+                limit,
+                in_flight: 0,
+                baseline: None,
+            }),
+            released: Notify::new(),
+        }
+    }
+    
+    fn limit(&self) -> usize {
+        self.state.lock().unwrap().limit
+    }
+    
+    fn in_flight(&self) -> usize {
+        self.state.lock().unwrap().in_flight
+    }
+    
+    /// Wait for a free slot
+    async fn acquire(&self) -> RequestPermit<'_> {
+        loop {
+            // Created before checking so a release in between is not missed
+            let released = self.released.notified();
+            if let Some(permit) = self.try_acquire() {
+                return permit;
+            }
+            released.await;
+        }
+    }
+    
+    /// Take a slot if one is free
+    fn try_acquire(&self) -> Option<RequestPermit<'_>> {
+        let mut state = self.state.lock().unwrap();
+        if state.in_flight >= state.limit {
+            return None;
+        }
+        state.in_flight += 1;
+        Some(RequestPermit { concurrency: self })
+    }
+    
+    fn release(&self) {
+        self.state.lock().unwrap().in_flight -= 1;
+        self.released.notify_waiters();
+    }
+    
+    /// Adapt the limit to the budget reported at `now`
+    fn observe(&self, headers: &RateLimitHeaders, now: Instant) {
+        let remaining = match headers.remaining() {
+            Some(remaining) => remaining,
+            None => return,
+        };
+        let sample = BudgetSample { remaining, reset: headers.reset, at: now };
+        
+        let mut state = self.state.lock().unwrap();
+        let baseline = match state.baseline {
+            Some(baseline) => baseline,
+            None => {
+                state.baseline = Some(sample);
+                return;
+            },
+        };
+        
+        if sample.starts_window_after(&baseline) {
+            let limit = (state.limit + self.config.additive_increase).min(self.config.max_concurrency);
+            if limit != state.limit {
+                debug!("Reddit rate limit window reset, raising concurrency to {}", limit);
+                state.limit = limit;
+                self.released.notify_waiters();
+            }
+            state.baseline = Some(sample);
+            return;
+        }
+        
+        // A response to an earlier request may arrive late with a larger
+        // budget; the baseline keeps the lowest one of the window
+        if remaining > baseline.remaining {
+            return;
+        }
+        
+        let spent = baseline.remaining - remaining;
+        if spent < self.config.sample_size.max(1) {
+            return;
+        }
+        state.baseline = Some(sample);
+        
+        let elapsed = now.duration_since(baseline.at).as_secs_f64();
+        let reset = match baseline.reset {
+            Some(reset) if !reset.is_zero() => reset.as_secs_f64(),
+            _ => return,
+        };
+        let sustainable = baseline.remaining as f64 / reset;
+        let observed = spent as f64 / elapsed.max(f64::EPSILON);
+        
+        if observed > sustainable * self.config.tolerance {
+            let limit = ((state.limit as f64 * self.config.multiplicative_decrease) as usize)
+                .max(self.config.min_concurrency.max(1));
+            if limit != state.limit {
+                debug!(
+                    "Reddit rate limit spent at {:.2}/s, sustainable {:.2}/s, lowering concurrency to {}",
+                    observed, sustainable, limit
+                );
+                state.limit = limit;
+            }
+        }
     }
 }
 
@@ -252,15 +484,14 @@ impl ThrottleManager {
     }
     
     /// Acquire permission to make a request to the specified endpoint
-    pub async fn acquire(&mut self, endpoint: &str) -> Result<()> {
-        let limiter = self.get_limiter(endpoint);
-        limiter.acquire().await
+    ///
+    /// The permit holds a concurrency slot of the endpoint's limiter until dropped.
+    pub async fn acquire(&mut self, endpoint: &str) -> Result<RequestPermit<'_>> {
+        self.get_limiter(endpoint).acquire().await
     }
     
     /// Update throttle settings
     pub fn update_settings(&mut self, settings: ThrottleSettings) {
-        self.settings = settings;
-        
         self.api_limiter.set_max_requests(settings.api.0);
         self.api_limiter.set_time_window(settings.api.1);
         
@@ -281,6 +512,8 @@ impl ThrottleManager {
         
         self.vote_limiter.set_max_requests(settings.vote.0);
         self.vote_limiter.set_time_window(settings.vote.1);
+        
+        self.settings = settings;
     }
 }
 
@@ -310,12 +543,92 @@ mod tests {
     
     #[test]
     fn test_rate_limiter() {
-        let mut limiter = RateLimiter::new(5, Duration::from_secs(1));
+        let limiter = RateLimiter::new(5, Duration::from_secs(1));
         
         assert_eq!(limiter.remaining(), 5);
         assert_eq!(limiter.max_requests(), 5);
         assert_eq!(limiter.time_window(), Duration::from_secs(1));
         assert!(limiter.can_request());
+        assert_eq!(limiter.concurrency_limit(), AimdConfig::default().initial_concurrency);
+    }
+    
+    fn budget(remaining: u32, reset_secs: u64) -> RateLimitHeaders {
+        RateLimitHeaders {
+            remaining: Some(remaining),
+            reset: Some(Duration::from_secs(reset_secs)),
+            ..RateLimitHeaders::default()
+        }
+    }
+    
+    #[test]
+    fn test_aimd() {
+        let concurrency = AdaptiveConcurrency::new(AimdConfig {
+            initial_concurrency: 8,
+            max_concurrency: 9,
+            ..AimdConfig::default()
+        });
+        let start = Instant::now();
+        
+        // 100 requests over 100 seconds is sustainable, 10 in one second is not
+        concurrency.observe(&budget(100, 100), start);
+        concurrency.observe(&budget(90, 99), start + Duration::from_secs(1));
+        assert_eq!(concurrency.limit(), 4);
+        
+        // Spending at the sustainable rate keeps the limit
+        concurrency.observe(&budget(80, 89), start + Duration::from_secs(11));
+        assert_eq!(concurrency.limit(), 4);
+        
+        // Too few requests to judge
+        concurrency.observe(&budget(78, 88), start + Duration::from_secs(12));
+        assert_eq!(concurrency.limit(), 4);
+        
+        // Each reset raises the limit, up to the maximum
+        for window in 0..10 {
+            let window_start = start + Duration::from_secs(100 + 600 * window);
+            concurrency.observe(&budget(100, 600), window_start);
+            concurrency.observe(&budget(99, 599), window_start + Duration::from_secs(1));
+        }
+        assert_eq!(concurrency.limit(), 9);
+    }
+    
+    #[test]
+    fn test_late_responses_are_not_resets() {
+        let concurrency = AdaptiveConcurrency::new(AimdConfig {
+            initial_concurrency: 4,
+            ..AimdConfig::default()
+        });
+        let start = Instant::now();
+        
+        concurrency.observe(&budget(50, 300), start);
+        
+        // Sent before the last response, so it reports a larger budget for the same window
+        concurrency.observe(&budget(53, 299), start + Duration::from_secs(1));
+        concurrency.observe(&budget(52, 300), start + Duration::from_millis(1500));
+        assert_eq!(concurrency.limit(), 4);
+        
+        // The budget is still measured from the lowest one seen
+        concurrency.observe(&budget(40, 298), start + Duration::from_secs(2));
+        assert_eq!(concurrency.limit(), 2);
+        
+        // The reset time jumping ahead is a new window
+        concurrency.observe(&budget(100, 600), start + Duration::from_secs(300));
+        assert_eq!(concurrency.limit(), 3);
+    }
+    
+    #[test]
+    fn test_concurrency_permits() {
+        let concurrency = AdaptiveConcurrency::new(AimdConfig {
+            initial_concurrency: 2,
+            ..AimdConfig::default()
+        });
+        
+        let first = concurrency.try_acquire().unwrap();
+        let _second = concurrency.try_acquire().unwrap();
+        assert!(concurrency.try_acquire().is_none());
+        assert_eq!(concurrency.in_flight(), 2);
+        
+        drop(first);
+        assert!(concurrency.try_acquire().is_some());
     }
     
     #[test]
@@ -0,0 +1,222 @@
+//! Subreddit wiki
+//!
+//! This module provides functionality for reading and maintaining a subreddit's
+//! wiki. Reading public pages needs the `wikiread` scope; editing pages needs
+//! `wikiedit`, and changing page settings or editors needs `modwiki`.
+
+use std::collections::HashMap;
+use serde::Deserialize;
+use log::debug;
+
+use crate::Result;
+use crate::client::RedditClient;
+use crate::models::{WikiPage, WikiPageSettings, WikiPermissionLevel, WikiRevision};
+
+/// A client for a subreddit's wiki
+#[derive(Debug, Clone)]
+pub struct WikiClient {
+    /// Reddit client
+    client: RedditClient,
+    
+    /// Subreddit name
+    subreddit: String,
+}
+
+impl WikiClient {
+    /// Create a new wiki client
+    pub fn new(client: RedditClient, subreddit: &str) -> Self {
+        // Remove the r/ prefix if present
+        let subreddit = subreddit.strip_prefix("r/").unwrap_or(subreddit).to_string();
+        
+        Self {
+            client,
+            subreddit,
+        }
+    }
+    
+    /// Get the subreddit name
+    pub fn subreddit(&self) -> &str {
+        &self.subreddit
+    }
+    
+    /// List the names of all wiki pages
+    pub async fn pages(&self) -> Result<Vec<String>> {
+        let endpoint = format!("/r/{}/wiki/pages", self.subreddit);
+        
+        #[derive(Deserialize)]
+        struct PagesResponse {
+            data: Vec<String>,
+        }
+        
+        let response: PagesResponse = self.client.get(&endpoint, None).await?;
+        
+        Ok(response.data)
+    }
+    
+    /// Get the current version of a wiki page
+    pub async fn page(&self, page: &str) -> Result<WikiPage> {
+        self.fetch_page(page, None).await
+    }
+    
+    /// Get a wiki page as of a specific revision
+    pub async fn page_at(&self, page: &str, revision_id: &str) -> Result<WikiPage> {
+        self.fetch_page(page, Some(revision_id)).await
+    }
+    
+    /// Get a wiki page, optionally at a revision
+    async fn fetch_page(&self, page: &str, revision_id: Option<&str>) -> Result<WikiPage> {
+        let endpoint = format!("/r/{}/wiki/{}", self.subreddit, page);
+        
+        let mut params = HashMap::new();
+        params.insert("raw_json".to_string(), "1".to_string());
+        
+        if let Some(revision_id) = revision_id {
+            params.insert("v".to_string(), revision_id.to_string());
+        }
+        
+        #[derive(Deserialize)]
+        struct PageResponse {
+            data: WikiPage,
+        }
+        
+        let response: PageResponse = self.client.get(&endpoint, Some(params)).await?;
+        
+        Ok(response.data)
+    }
+    
+    /// Replace the content of a wiki page, creating it if needed
+    ///
+    /// `previous` is the revision the edit is based on. If it is set and the
+    /// page has changed since, Reddit rejects the edit with a conflict error
+    /// instead of overwriting the newer revision.
+    pub async fn edit(
+        &self,
+        page: &str,
+        content: &str,
+        reason: Option<&str>,
+        previous: Option<&str>,
+    ) -> Result<()> {
+        debug!("Editing wiki page {} in r/{}", page, self.subreddit);
+        
+        let endpoint = format!("/r/{}/api/wiki/edit", self.subreddit);
+        
+        let mut params = HashMap::new();
+        params.insert("page".to_string(), page.to_string());
+        params.insert("content".to_string(), content.to_string());
+        
+        if let Some(reason) = reason {
+            params.insert("reason".to_string(), reason.to_string());
+        }
+        
+        if let Some(previous) = previous {
+            params.insert("previous".to_string(), previous.to_string());
+        }
+        
+        let _: serde_json::Value = self.client.post(&endpoint, Some(params), None).await?;
+        
+        Ok(())
+    }
+    
+    /// Revert a wiki page to a previous revision
+    pub async fn revert(&self, page: &str, revision_id: &str) -> Result<()> {
+        let endpoint = format!("/r/{}/api/wiki/revert", self.subreddit);
+        
+        let mut params = HashMap::new();
+        params.insert("page".to_string(), page.to_string());
+        params.insert("revision".to_string(), revision_id.to_string());
+        
+        let _: serde_json::Value = self.client.post(&endpoint, Some(params), None).await?;
+        
+        Ok(())
+    }
+    
+    /// Get the revision history of a wiki page, newest first
+    pub async fn revisions(&self, page: &str, limit: Option<u32>, after: Option<&str>) -> Result<Vec<WikiRevision>> {
+        let endpoint = format!("/r/{}/wiki/revisions/{}", self.subreddit, page);
+        
+        let mut params = HashMap::new();
+        
+        if let Some(limit) = limit {
+            params.insert("limit".to_string(), limit.to_string());
+        }
+        
+        if let Some(after) = after {
+            params.insert("after".to_string(), after.to_string());
+        }
+        
+        // Revisions are listed directly rather than wrapped in things
+        #[derive(Deserialize)]
+        struct RevisionsResponse {
+            data: RevisionsData,
+        }
+        
+        #[derive(Deserialize)]
+        struct RevisionsData {
+            children: Vec<WikiRevision>,
+        }
+        
+        let response: RevisionsResponse = self.client.get(&endpoint, Some(params)).await?;
+        
+        Ok(response.data.children)
+    }
+    
+    /// Get the settings of a wiki page
+    pub async fn settings(&self, page: &str) -> Result<WikiPageSettings> {
+        let endpoint = format!("/r/{}/wiki/settings/{}", self.subreddit, page);
+        
+        #[derive(Deserialize)]
+        struct SettingsResponse {
+            data: WikiPageSettings,
+        }
+        
+        let response: SettingsResponse = self.client.get(&endpoint, None).await?;
+        
+        Ok(response.data)
+    }
+    
+    /// Update the settings of a wiki page
+    pub async fn update_settings(
+        &self,
+        page: &str,
+        permlevel: WikiPermissionLevel,
+        listed: bool,
+    ) -> Result<WikiPageSettings> {
+        let endpoint = format!("/r/{}/wiki/settings/{}", self.subreddit, page);
+        
+        let mut params = HashMap::new();
+        params.insert("permlevel".to_string(), permlevel.level().to_string());
+        params.insert("listed".to_string(), listed.to_string());
+        
+        #[derive(Deserialize)]
+        struct SettingsResponse {
+            data: WikiPageSettings,
+        }
+        
+        let response: SettingsResponse = self.client.post(&endpoint, Some(params), None).await?;
+        
+        Ok(response.data)
+    }
+    
+    /// Allow a user to edit a wiki page
+    pub async fn add_editor(&self, page: &str, username: &str) -> Result<()> {
+        self.set_editor(page, username, "add").await
+    }
+    
+    /// Revoke a user's permission to edit a wiki page
+    pub async fn remove_editor(&self, page: &str, username: &str) -> Result<()> {
+        self.set_editor(page, username, "del").await
+    }
+    
+    /// Add or remove a page editor
+    async fn set_editor(&self, page: &str, username: &str, act: &str) -> Result<()> {
+        let endpoint = format!("/r/{}/api/wiki/alloweditor/{}", self.subreddit, act);
+        
+        let mut params = HashMap::new();
+        params.insert("page".to_string(), page.to_string());
+        params.insert("username".to_string(), username.to_string());
+        
+        let _: serde_json::Value = self.client.post(&endpoint, Some(params), None).await?;
+        
+        Ok(())
+    }
+}
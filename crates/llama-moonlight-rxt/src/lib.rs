@@ -18,6 +18,10 @@ use std::{
 use thiserror::Error;
 use tokio::sync::mpsc;
 
+pub mod ops;
+
+pub use ops::BrowserEventOps;
+
 #[derive(Error, Debug)]
 pub enum RxtError {
     #[error("Rxrust error: {0}")]
@@ -72,6 +76,51 @@ pub enum BrowserEvent {
     Custom { event_type: String, data: serde_json::Value },
 }
 
+/// Kind of a [`BrowserEvent`], without its data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BrowserEventKind {
+    Launched,
+    Closed,
+    ContextCreated,
+    ContextClosed,
+    PageCreated,
+    PageClosed,
+    NavigationStarted,
+    NavigationCompleted,
+    ConsoleMessage,
+    Dialog,
+    RequestStarted,
+    RequestCompleted,
+    RequestFailed,
+    DownloadStarted,
+    DownloadCompleted,
+    Custom,
+}
+
+impl BrowserEvent {
+    /// Get the kind of the event
+    pub fn kind(&self) -> BrowserEventKind {
+        match self {
+            BrowserEvent::Launched { .. } => BrowserEventKind::Launched,
+            BrowserEvent::Closed { .. } => BrowserEventKind::Closed,
+            BrowserEvent::ContextCreated { .. } => BrowserEventKind::ContextCreated,
+            BrowserEvent::ContextClosed { .. } => BrowserEventKind::ContextClosed,
+            BrowserEvent::PageCreated { .. } => BrowserEventKind::PageCreated,
+            BrowserEvent::PageClosed { .. } => BrowserEventKind::PageClosed,
+            BrowserEvent::NavigationStarted { .. } => BrowserEventKind::NavigationStarted,
+            BrowserEvent::NavigationCompleted { .. } => BrowserEventKind::NavigationCompleted,
+            BrowserEvent::ConsoleMessage { .. } => BrowserEventKind::ConsoleMessage,
+            BrowserEvent::Dialog { .. } => BrowserEventKind::Dialog,
+            BrowserEvent::RequestStarted { .. } => BrowserEventKind::RequestStarted,
+            BrowserEvent::RequestCompleted { .. } => BrowserEventKind::RequestCompleted,
+            BrowserEvent::RequestFailed { .. } => BrowserEventKind::RequestFailed,
+            BrowserEvent::DownloadStarted { .. } => BrowserEventKind::DownloadStarted,
+            BrowserEvent::DownloadCompleted { .. } => BrowserEventKind::DownloadCompleted,
+            BrowserEvent::Custom { .. } => BrowserEventKind::Custom,
+        }
+    }
+}
+
 /// A browser with reactive extension functionality
 pub struct RxBrowser {
     browser: Arc<Browser>,
@@ -0,0 +1,297 @@
+//! Time-window and sampling operators for browser event streams.
+//!
+//! Pages emit request and console events far faster than a dashboard or a
+//! log can usefully show them. [`BrowserEventOps`] adds operators to
+//! `Observable<BrowserEvent>` that group or downsample such streams:
+//!
+//! - [`window`](BrowserEventOps::window) groups events into consecutive time windows
+//! - [`sample`](BrowserEventOps::sample) keeps the latest event of each interval
+//! - [`buffer_until`](BrowserEventOps::buffer_until) groups events up to one matching a predicate
+//! - [`throttle_events`](BrowserEventOps::throttle_events) limits the rate of one kind of event
+
+use crate::{BrowserEvent, BrowserEventKind};
+use rxrust::prelude::*;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::mpsc,
+    time::{interval_at, Instant},
+};
+
+/// Shortest window or sampling interval, as timers cannot tick every 0ms
+const MIN_PERIOD: Duration = Duration::from_millis(1);
+
+/// Operators on streams of browser events
+pub trait BrowserEventOps {
+    /// Group events into consecutive windows of `duration`
+    ///
+    /// Every window is emitted when it ends, empty ones included, so
+    /// counting the events of each gives a rate over time. Durations below
+    /// 1ms are raised to 1ms.
+    fn window(&self, duration: Duration) -> Observable<Vec<BrowserEvent>>;
+
+    /// Emit the latest event of each `interval`
+    ///
+    /// Intervals without a new event emit nothing. Intervals below 1ms are
+    /// raised to 1ms.
+    fn sample(&self, interval: Duration) -> Observable<BrowserEvent>;
+
+    /// Group events up to and including the next one matching `predicate`
+    ///
+    /// Events after the last match are emitted as a final group when the
+    /// stream completes.
+    fn buffer_until<P>(&self, predicate: P) -> Observable<Vec<BrowserEvent>>
+    where
+        P: Fn(&BrowserEvent) -> bool + Send + Sync + 'static;
+
+    /// Let at most `rate` events of `kind` through per second, dropping the rest
+    ///
+    /// Events of other kinds pass through unchanged. A rate of 0 drops every
+    /// event of the kind.
+    fn throttle_events(&self, kind: BrowserEventKind, rate: u32) -> Observable<BrowserEvent>;
+}
+
+impl BrowserEventOps for Observable<BrowserEvent> {
+    fn window(&self, duration: Duration) -> Observable<Vec<BrowserEvent>> {
+        let source = self.clone();
+        let duration = tick_period(duration);
+
+        Observable::create(move |s| {
+            let mut events = forward(&source);
+            tokio::spawn(async move {
+                let mut ticks = interval_at(Instant::now() + duration, duration);
+                let mut window = Vec::new();
+
+                loop {
+                    tokio::select! {
+                        event = events.recv() => match event {
+                            Some(event) => window.push(event),
+                            None => break,
+                        },
+                        _ = ticks.tick() => {
+                            if !s.is_subscribed() {
+                                return;
+                            }
+                            s.next(std::mem::take(&mut window));
+                        }
+                    }
+                }
+
+                if !window.is_empty() {
+                    s.next(window);
+                }
+                s.complete();
+            });
+        })
+    }
+
+    fn sample(&self, interval: Duration) -> Observable<BrowserEvent> {
+        let source = self.clone();
+        let interval = tick_period(interval);
+
+        Observable::create(move |s| {
+            let mut events = forward(&source);
+            tokio::spawn(async move {
+                let mut ticks = interval_at(Instant::now() + interval, interval);
+                let mut latest = None;
+
+                loop {
+                    tokio::select! {
+                        event = events.recv() => match event {
+                            Some(event) => latest = Some(event),
+                            None => break,
+                        },
+                        _ = ticks.tick() => {
+                            if !s.is_subscribed() {
+                                return;
+                            }
+                            if let Some(event) = latest.take() {
+                                s.next(event);
+                            }
+                        }
+                    }
+                }
+
+                s.complete();
+            });
+        })
+    }
+
+    fn buffer_until<P>(&self, predicate: P) -> Observable<Vec<BrowserEvent>>
+    where
+        P: Fn(&BrowserEvent) -> bool + Send + Sync + 'static,
+    {
+        let source = self.clone();
+        let predicate = Arc::new(predicate);
+
+        Observable::create(move |s| {
+            let mut events = forward(&source);
+            let mut buffer = BufferUntil::new(predicate.clone());
+            tokio::spawn(async move {
+                while let Some(event) = events.recv().await {
+                    if !s.is_subscribed() {
+                        return;
+                    }
+                    if let Some(group) = buffer.push(event) {
+                        s.next(group);
+                    }
+                }
+
+                if let Some(group) = buffer.finish() {
+                    s.next(group);
+                }
+                s.complete();
+            });
+        })
+    }
+
+    fn throttle_events(&self, kind: BrowserEventKind, rate: u32) -> Observable<BrowserEvent> {
+        let throttle = Mutex::new(Throttle::new(kind, rate));
+        self.filter(move |event| throttle.lock().unwrap().admit(event, Instant::now()))
+    }
+}
+
+/// Subscribe to a stream, receiving its events on a channel
+///
+/// The channel is unbounded so a slow consumer never holds up the page's
+/// event stream.
+fn forward(source: &Observable<BrowserEvent>) -> mpsc::UnboundedReceiver<BrowserEvent> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    source.clone().subscribe(move |event| {
+        let _ = tx.send(event);
+    });
+    rx
+}
+
+/// Groups events up to one matching a predicate
+struct BufferUntil<P> {
+    predicate: Arc<P>,
+    buffer: Vec<BrowserEvent>,
+}
+
+impl<P: Fn(&BrowserEvent) -> bool> BufferUntil<P> {
+    fn new(predicate: Arc<P>) -> Self {
+        Self { predicate, buffer: Vec::new() }
+    }
+
+    /// Add an event, returning the group it completes, if any
+    fn push(&mut self, event: BrowserEvent) -> Option<Vec<BrowserEvent>> {
+        let done = (self.predicate)(&event);
+        self.buffer.push(event);
+        done.then(|| std::mem::take(&mut self.buffer))
+    }
+
+    /// Take the events left after the last match
+    fn finish(&mut self) -> Option<Vec<BrowserEvent>> {
+        (!self.buffer.is_empty()).then(|| std::mem::take(&mut self.buffer))
+    }
+}
+
+/// Limits the rate of one kind of event
+///
+/// At most `rate` events of the kind are admitted in any second, counted
+/// from the first event admitted in that second.
+struct Throttle {
+    kind: BrowserEventKind,
+    rate: u32,
+    window_start: Option<Instant>,
+    admitted: u32,
+}
+
+impl Throttle {
+    fn new(kind: BrowserEventKind, rate: u32) -> Self {
+        Self { kind, rate, window_start: None, admitted: 0 }
+    }
+
+    /// Check whether an event arriving at `now` goes through
+    fn admit(&mut self, event: &BrowserEvent, now: Instant) -> bool {
+        if event.kind() != self.kind {
+            return true;
+        }
+
+        let expired = self
+            .window_start
+            .map_or(true, |start| now.duration_since(start) >= Duration::from_secs(1));
+        if expired {
+            self.window_start = Some(now);
+            self.admitted = 0;
+        }
+
+        if self.admitted < self.rate {
+            self.admitted += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Get the timer period for a window or sampling interval
+fn tick_period(duration: Duration) -> Duration {
+    duration.max(MIN_PERIOD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(id: &str) -> BrowserEvent {
+        BrowserEvent::RequestStarted {
+            page_id: "page".to_string(),
+            request_id: id.to_string(),
+            url: "https://example.com/".to_string(),
+            method: "GET".to_string(),
+        }
+    }
+
+    fn navigation() -> BrowserEvent {
+        BrowserEvent::NavigationCompleted {
+            page_id: "page".to_string(),
+            url: "https://example.com/".to_string(),
+            status: 200,
+        }
+    }
+
+    #[test]
+    fn test_throttle() {
+        let start = Instant::now();
+        let mut throttle = Throttle::new(BrowserEventKind::RequestStarted, 2);
+
+        assert!(throttle.admit(&request("1"), start));
+        assert!(throttle.admit(&request("2"), start + Duration::from_millis(100)));
+        assert!(!throttle.admit(&request("3"), start + Duration::from_millis(200)));
+
+        // Other kinds are never held back
+        assert!(throttle.admit(&navigation(), start + Duration::from_millis(300)));
+
+        // The next second admits again
+        assert!(throttle.admit(&request("4"), start + Duration::from_millis(1000)));
+
+        let mut closed = Throttle::new(BrowserEventKind::RequestStarted, 0);
+        assert!(!closed.admit(&request("1"), start));
+    }
+
+    #[test]
+    fn test_tick_period() {
+        assert_eq!(tick_period(Duration::ZERO), MIN_PERIOD);
+        assert_eq!(tick_period(Duration::from_secs(1)), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_buffer_until() {
+        let predicate = Arc::new(|event: &BrowserEvent| event.kind() == BrowserEventKind::NavigationCompleted);
+        let mut buffer = BufferUntil::new(predicate);
+
+        assert!(buffer.push(request("1")).is_none());
+        assert!(buffer.push(request("2")).is_none());
+        let group = buffer.push(navigation()).unwrap();
+        assert_eq!(group.len(), 3);
+        assert_eq!(group[2].kind(), BrowserEventKind::NavigationCompleted);
+
+        assert!(buffer.finish().is_none());
+        buffer.push(request("3"));
+        assert_eq!(buffer.finish().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,358 @@
+//! Backpressure-aware buffering in front of a sink.
+//!
+//! [`BufferedSink`] owns a [`Sink`] in a background task and feeds it through a
+//! bounded channel. Records are written in batches of `batch_size`, or after
+//! `flush_interval` if fewer arrive. When the channel is full, [`BufferedSink::send`]
+//! waits, slowing producers down to the speed of the destination instead of
+//! growing memory without bound.
+
+use crate::sink::Sink;
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, warn};
+
+/// Configuration for a buffered sink.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BufferConfig {
+    /// Records buffered before producers are made to wait
+    pub capacity: usize,
+
+    /// Records written per batch
+    pub batch_size: usize,
+
+    /// Maximum time a record waits before its batch is written
+    pub flush_interval: Duration,
+
+    /// Retries of a failed batch before it is dropped
+    pub max_retries: u32,
+
+    /// Delay before the first retry (doubled for each further retry)
+    pub retry_backoff: Duration,
+}
+
+impl Default for BufferConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            batch_size: 500,
+            flush_interval: Duration::from_secs(5),
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Counters of a buffered sink.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SinkStats {
+    /// Records written successfully
+    pub written: u64,
+    /// Records dropped after exhausting retries
+    pub dropped: u64,
+    /// Batches written
+    pub batches: u64,
+    /// Failed write attempts
+    pub errors: u64,
+}
+
+enum Command {
+    Record(Value),
+    Flush(oneshot::Sender<Result<()>>),
+    Shutdown(oneshot::Sender<Result<SinkStats>>),
+}
+
+/// A cloneable handle feeding a sink running in a background task.
+#[derive(Clone)]
+pub struct BufferedSink {
+    tx: mpsc::Sender<Command>,
+    task: Arc<Mutex<Option<JoinHandle<()>>>>,
+    name: Arc<str>,
+}
+
+impl BufferedSink {
+    /// Starts a background task writing to `sink`.
+    pub fn spawn(sink: impl Sink + 'static, config: BufferConfig) -> Self {
+        let (tx, rx) = mpsc::channel(config.capacity.max(1));
+        let name: Arc<str> = Arc::from(sink.name());
+        let task = tokio::spawn(run(Box::new(sink), config, rx));
+
+        Self {
+            tx,
+            task: Arc::new(Mutex::new(Some(task))),
+            name,
+        }
+    }
+
+    /// Name of the underlying sink.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Queues a record, waiting while the buffer is full.
+    pub async fn send(&self, record: Value) -> Result<()> {
+        self.tx.send(Command::Record(record)).await.map_err(|_| Error::Closed)
+    }
+
+    /// Serializes and queues a record, waiting while the buffer is full.
+    pub async fn send_serialized<T: Serialize>(&self, record: &T) -> Result<()> {
+        self.send(serde_json::to_value(record)?).await
+    }
+
+    /// Queues a record without waiting; fails with [`Error::BufferFull`] if the buffer is full.
+    pub fn try_send(&self, record: Value) -> Result<()> {
+        self.tx.try_send(Command::Record(record)).map_err(|e| match e {
+            mpsc::error::TrySendError::Full(_) => Error::BufferFull,
+            mpsc::error::TrySendError::Closed(_) => Error::Closed,
+        })
+    }
+
+    /// Writes everything queued so far and flushes the sink.
+    pub async fn flush(&self) -> Result<()> {
+        let (reply, done) = oneshot::channel();
+        self.tx.send(Command::Flush(reply)).await.map_err(|_| Error::Closed)?;
+        done.await.map_err(|_| Error::Closed)?
+    }
+
+    /// Writes everything queued so far, closes the sink, and stops the task.
+    ///
+    /// Records sent through other handles after this call fail with [`Error::Closed`].
+    pub async fn shutdown(&self) -> Result<SinkStats> {
+        let (reply, done) = oneshot::channel();
+        self.tx.send(Command::Shutdown(reply)).await.map_err(|_| Error::Closed)?;
+        let stats = done.await.map_err(|_| Error::Closed)?;
+
+        if let Some(task) = self.task.lock().await.take() {
+            let _ = task.await;
+        }
+        stats
+    }
+}
+
+/// The background task.
+async fn run(mut sink: Box<dyn Sink>, config: BufferConfig, mut rx: mpsc::Receiver<Command>) {
+    let batch_size = config.batch_size.max(1);
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut stats = SinkStats::default();
+    let mut ticker = tokio::time::interval(config.flush_interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        let command = tokio::select! {
+            command = rx.recv() => command,
+            _ = ticker.tick() => {
+                if !batch.is_empty() {
+                    let _ = write(sink.as_mut(), &mut batch, &config, &mut stats).await;
+                }
+                continue;
+            }
+        };
+
+        match command {
+            Some(Command::Record(record)) => {
+                batch.push(record);
+                if batch.len() >= batch_size {
+                    let _ = write(sink.as_mut(), &mut batch, &config, &mut stats).await;
+                }
+            }
+            Some(Command::Flush(reply)) => {
+                let written = write(sink.as_mut(), &mut batch, &config, &mut stats).await;
+                let flushed = match written {
+                    Ok(()) => sink.flush().await,
+                    Err(e) => Err(e),
+                };
+                let _ = reply.send(flushed);
+            }
+            Some(Command::Shutdown(reply)) => {
+                let _ = reply.send(finish(sink.as_mut(), &mut batch, &mut rx, &config, &mut stats).await);
+                return;
+            }
+            None => {
+                // Every handle was dropped without a shutdown: still flush
+                if let Err(e) = finish(sink.as_mut(), &mut batch, &mut rx, &config, &mut stats).await {
+                    error!("Failed to close sink {}: {}", sink.name(), e);
+                }
+                return;
+            }
+        }
+    }
+}
+
+/// Drains queued records, writes them, and closes the sink.
+async fn finish(
+    sink: &mut dyn Sink,
+    batch: &mut Vec<Value>,
+    rx: &mut mpsc::Receiver<Command>,
+    config: &BufferConfig,
+    stats: &mut SinkStats,
+) -> Result<SinkStats> {
+    rx.close();
+    while let Ok(command) = rx.try_recv() {
+        match command {
+            Command::Record(record) => batch.push(record),
+            Command::Flush(reply) => {
+                let _ = reply.send(Err(Error::Closed));
+            }
+            Command::Shutdown(reply) => {
+                let _ = reply.send(Err(Error::Closed));
+            }
+        }
+    }
+
+    let written = write(sink, batch, config, stats).await;
+    let closed = sink.close().await;
+    debug!(sink = sink.name(), ?stats, "Sink closed");
+    written.and(closed).map(|_| stats.clone())
+}
+
+/// Writes the batch with retries, clearing it either way.
+async fn write(sink: &mut dyn Sink, batch: &mut Vec<Value>, config: &BufferConfig, stats: &mut SinkStats) -> Result<()> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+
+    let mut backoff = config.retry_backoff;
+    let mut attempt = 0;
+    loop {
+        match sink.write_batch(batch).await {
+            Ok(()) => {
+                stats.written += batch.len() as u64;
+                stats.batches += 1;
+                batch.clear();
+                return Ok(());
+            }
+            Err(e) if attempt < config.max_retries => {
+                stats.errors += 1;
+                attempt += 1;
+                warn!("Sink {} write failed (attempt {}): {}", sink.name(), attempt, e);
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+            Err(e) => {
+                stats.errors += 1;
+                stats.dropped += batch.len() as u64;
+                error!("Sink {} dropped {} records: {}", sink.name(), batch.len(), e);
+                batch.clear();
+                return Err(e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Records batches in memory; fails the first `fail_first` writes.
+    #[derive(Default)]
+    struct TestSink {
+        batches: Arc<std::sync::Mutex<Vec<Vec<Value>>>>,
+        closed: Arc<AtomicUsize>,
+        fail_first: usize,
+        calls: usize,
+    }
+
+    #[async_trait]
+    impl Sink for TestSink {
+        async fn write_batch(&mut self, records: &[Value]) -> Result<()> {
+            self.calls += 1;
+            if self.calls <= self.fail_first {
+                return Err(Error::SinkError("unavailable".to_string()));
+            }
+            self.batches.lock().unwrap().push(records.to_vec());
+            Ok(())
+        }
+
+        async fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn close(&mut self) -> Result<()> {
+            self.closed.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            "test"
+        }
+    }
+
+    fn config() -> BufferConfig {
+        BufferConfig {
+            capacity: 4,
+            batch_size: 2,
+            flush_interval: Duration::from_secs(3600),
+            max_retries: 2,
+            retry_backoff: Duration::from_millis(1),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batches_and_flushes_on_shutdown() {
+        let sink = TestSink::default();
+        let (batches, closed) = (sink.batches.clone(), sink.closed.clone());
+        let buffered = BufferedSink::spawn(sink, config());
+
+        for i in 0..5 {
+            buffered.send(json!({ "i": i })).await.unwrap();
+        }
+        let stats = buffered.shutdown().await.unwrap();
+
+        assert_eq!(stats.written, 5);
+        let sizes: Vec<usize> = batches.lock().unwrap().iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![2, 2, 1]);
+        assert_eq!(closed.load(Ordering::SeqCst), 1);
+        assert!(matches!(buffered.send(json!({})).await, Err(Error::Closed)));
+    }
+
+    #[tokio::test]
+    async fn test_retries_failed_batches() {
+        let sink = TestSink {
+            fail_first: 2,
+            ..TestSink::default()
+        };
+        let batches = sink.batches.clone();
+        let buffered = BufferedSink::spawn(sink, config());
+
+        buffered.send(json!(1)).await.unwrap();
+        buffered.flush().await.unwrap();
+
+        assert_eq!(batches.lock().unwrap().len(), 1);
+        assert_eq!(buffered.shutdown().await.unwrap().errors, 2);
+    }
+
+    #[tokio::test]
+    async fn test_drops_after_retries() {
+        let sink = TestSink {
+            fail_first: 10,
+            ..TestSink::default()
+        };
+        let buffered = BufferedSink::spawn(sink, config());
+
+        buffered.send(json!(1)).await.unwrap();
+        assert!(buffered.flush().await.is_err());
+        assert_eq!(buffered.shutdown().await.unwrap().dropped, 1);
+    }
+
+    #[tokio::test]
+    async fn test_flushes_when_all_handles_drop() {
+        let sink = TestSink::default();
+        let (batches, closed) = (sink.batches.clone(), sink.closed.clone());
+        let buffered = BufferedSink::spawn(sink, config());
+        let task = buffered.task.clone();
+
+        buffered.send(json!(1)).await.unwrap();
+        drop(buffered);
+        task.lock().await.take().unwrap().await.unwrap();
+
+        assert_eq!(batches.lock().unwrap().len(), 1);
+        assert_eq!(closed.load(Ordering::SeqCst), 1);
+    }
+}
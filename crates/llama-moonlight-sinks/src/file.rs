@@ -0,0 +1,182 @@
+//! Local file sinks.
+
+use crate::sink::Sink;
+use crate::{Error, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::path::Path;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+/// Writes one JSON object per line.
+pub struct JsonlSink {
+    writer: BufWriter<File>,
+}
+
+impl JsonlSink {
+    /// Creates (or truncates) the file.
+    pub async fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::create(path).await?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    /// Opens the file for appending, creating it if needed.
+    pub async fn append(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path).await?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+        })
+    }
+}
+
+#[async_trait]
+impl Sink for JsonlSink {
+    async fn write_batch(&mut self, records: &[Value]) -> Result<()> {
+        // Serialize the whole batch first so a bad record writes nothing
+        let mut buf = Vec::new();
+        for record in records {
+            serde_json::to_writer(&mut buf, record)?;
+            buf.push(b'\n');
+        }
+        self.writer.write_all(&buf).await?;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.writer.flush().await?;
+        self.writer.get_ref().sync_data().await?;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "jsonl"
+    }
+}
+
+/// Writes records as CSV rows.
+///
+/// Columns are fixed up front or taken from the keys of the first record.
+/// Keys missing from a record produce empty cells, extra keys are ignored, and
+/// nested values are written as JSON.
+pub struct CsvSink {
+    writer: BufWriter<File>,
+    columns: Option<Vec<String>>,
+}
+
+impl CsvSink {
+    /// Creates (or truncates) the file; columns come from the first record.
+    pub async fn create(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path).await?),
+            columns: None,
+        })
+    }
+
+    /// Creates (or truncates) the file with the given columns.
+    pub async fn with_columns(path: impl AsRef<Path>, columns: Vec<String>) -> Result<Self> {
+        if columns.is_empty() {
+            return Err(Error::ConfigError("CSV sink needs at least one column".to_string()));
+        }
+        Ok(Self {
+            writer: BufWriter::new(File::create(path).await?),
+            columns: Some(columns),
+        })
+    }
+
+    /// Returns the columns, if already known.
+    pub fn columns(&self) -> Option<&[String]> {
+        self.columns.as_deref()
+    }
+}
+
+/// Renders a value as a CSV cell.
+fn cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+#[async_trait]
+impl Sink for CsvSink {
+    async fn write_batch(&mut self, records: &[Value]) -> Result<()> {
+        let mut csv = csv::Writer::from_writer(Vec::new());
+
+        if self.columns.is_none() {
+            let Some(first) = records.first() else {
+                return Ok(());
+            };
+            let columns: Vec<String> = match first {
+                Value::Object(map) => map.keys().cloned().collect(),
+                _ => vec!["value".to_string()],
+            };
+            csv.write_record(&columns)?;
+            self.columns = Some(columns);
+        }
+        let columns = self.columns.as_deref().unwrap_or_default();
+
+        for record in records {
+            let row: Vec<String> = match record {
+                Value::Object(map) => columns.iter().map(|c| cell(map.get(c))).collect(),
+                other => vec![cell(Some(other))],
+            };
+            csv.write_record(&row)?;
+        }
+
+        let bytes = csv
+            .into_inner()
+            .map_err(|e| Error::SinkError(format!("Failed to finish CSV batch: {}", e)))?;
+        self.writer.write_all(&bytes).await?;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.writer.flush().await?;
+        self.writer.get_ref().sync_data().await?;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "csv"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_jsonl_sink() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.jsonl");
+
+        let mut sink = JsonlSink::create(&path).await.unwrap();
+        sink.write_batch(&[json!({"a": 1}), json!({"a": 2})]).await.unwrap();
+        sink.close().await.unwrap();
+
+        let mut sink = JsonlSink::append(&path).await.unwrap();
+        sink.write_batch(&[json!({"a": 3})]).await.unwrap();
+        sink.close().await.unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "{\"a\":1}\n{\"a\":2}\n{\"a\":3}\n");
+    }
+
+    #[tokio::test]
+    async fn test_csv_sink_infers_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.csv");
+
+        let mut sink = CsvSink::create(&path).await.unwrap();
+        sink.write_batch(&[json!({"title": "A, B", "tags": ["x"]})]).await.unwrap();
+        sink.write_batch(&[json!({"title": "C", "extra": true})]).await.unwrap();
+        sink.close().await.unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "tags,title\n\"[\"\"x\"\"]\",\"A, B\"\n,C\n");
+    }
+}
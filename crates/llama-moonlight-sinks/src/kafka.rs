@@ -0,0 +1,129 @@
+//! Kafka sink.
+//!
+//! Every record becomes one message on the configured topic, optionally keyed
+//! by one of its fields so related records land on the same partition. A
+//! batch is only reported written once the broker acknowledged every message;
+//! if any message fails the whole batch is retried, so consumers may see
+//! duplicates (at-least-once).
+
+use crate::sink::Sink;
+use crate::{Error, Result};
+use async_trait::async_trait;
+use rdkafka::config::ClientConfig;
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use rdkafka::util::Timeout;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Configuration for the Kafka sink.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KafkaConfig {
+    /// Comma-separated bootstrap servers
+    pub brokers: String,
+
+    /// Topic to produce to
+    pub topic: String,
+
+    /// Record field used as the message key
+    pub key_field: Option<String>,
+
+    /// How long to wait for delivery of one message
+    pub delivery_timeout: Duration,
+
+    /// Extra librdkafka settings (e.g. `security.protocol`)
+    pub settings: HashMap<String, String>,
+}
+
+impl KafkaConfig {
+    /// Creates a configuration for the given brokers and topic.
+    pub fn new(brokers: &str, topic: &str) -> Self {
+        Self {
+            brokers: brokers.to_string(),
+            topic: topic.to_string(),
+            key_field: None,
+            delivery_timeout: Duration::from_secs(30),
+            settings: HashMap::new(),
+        }
+    }
+}
+
+/// Produces records to a Kafka topic.
+pub struct KafkaSink {
+    producer: FutureProducer,
+    config: KafkaConfig,
+}
+
+impl KafkaSink {
+    /// Creates an idempotent producer for the topic.
+    pub fn new(config: KafkaConfig) -> Result<Self> {
+        let mut client = ClientConfig::new();
+        client
+            .set("bootstrap.servers", &config.brokers)
+            .set("message.timeout.ms", config.delivery_timeout.as_millis().to_string())
+            .set("enable.idempotence", "true");
+        for (key, value) in &config.settings {
+            client.set(key, value);
+        }
+
+        let producer = client
+            .create()
+            .map_err(|e| Error::SinkError(format!("Failed to create Kafka producer: {}", e)))?;
+        Ok(Self { producer, config })
+    }
+
+    fn key_of(&self, record: &Value) -> Option<String> {
+        let field = self.config.key_field.as_ref()?;
+        match record.get(field)? {
+            Value::String(s) => Some(s.clone()),
+            Value::Null => None,
+            other => Some(other.to_string()),
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for KafkaSink {
+    async fn write_batch(&mut self, records: &[Value]) -> Result<()> {
+        let messages = records
+            .iter()
+            .map(|record| Ok((self.key_of(record), serde_json::to_string(record)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let deliveries = messages.iter().map(|(key, payload)| {
+            let mut message = FutureRecord::to(&self.config.topic).payload(payload);
+            if let Some(key) = key {
+                message = message.key(key);
+            }
+            self.producer.send(message, Timeout::After(self.config.delivery_timeout))
+        });
+
+        let failures: Vec<String> = futures::future::join_all(deliveries)
+            .await
+            .into_iter()
+            .filter_map(|delivery| delivery.err().map(|(e, _)| e.to_string()))
+            .collect();
+
+        match failures.first() {
+            None => Ok(()),
+            Some(first) => Err(Error::SinkError(format!(
+                "{} of {} Kafka messages failed: {}",
+                failures.len(),
+                records.len(),
+                first
+            ))),
+        }
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        // Deliveries are awaited in write_batch, so this returns promptly
+        self.producer
+            .flush(Timeout::After(self.config.delivery_timeout))
+            .map_err(|e| Error::SinkError(format!("Kafka flush failed: {}", e)))
+    }
+
+    fn name(&self) -> &str {
+        "kafka"
+    }
+}
@@ -0,0 +1,97 @@
+//! # llama-moonlight-sinks
+//!
+//! Result sinks for the Llama Moonlight ecosystem.
+//!
+//! A [`Sink`] writes batches of JSON records somewhere durable. Producers
+//! don't talk to sinks directly; they send records through a [`BufferedSink`],
+//! which batches them in a background task, applies backpressure when the
+//! destination falls behind, and flushes everything on shutdown.
+//!
+//! ## Features
+//!
+//! - Local JSON Lines and CSV files
+//! - S3 multipart uploads (`s3` feature)
+//! - Batched Postgres inserts into a JSONB table (`postgres` feature)
+//! - Kafka producer (`kafka` feature)
+//! - Bounded buffering with backpressure, retries, and flush-on-shutdown
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use llama_moonlight_sinks::{BufferConfig, BufferedSink, JsonlSink, Result};
+//! use serde_json::json;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<()> {
+//!     let sink = BufferedSink::spawn(JsonlSink::create("results.jsonl").await?, BufferConfig::default());
+//!
+//!     sink.send(json!({ "url": "https://example.com", "title": "Example" })).await?;
+//!
+//!     // Flushes the buffer and closes the file
+//!     let stats = sink.shutdown().await?;
+//!     println!("Wrote {} records", stats.written);
+//!     Ok(())
+//! }
+//! ```
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs)]
+
+use thiserror::Error;
+
+pub mod buffer;
+pub mod file;
+pub mod sink;
+
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "s3")]
+pub mod s3;
+
+pub use buffer::{BufferConfig, BufferedSink, SinkStats};
+pub use file::{CsvSink, JsonlSink};
+pub use sink::Sink;
+
+#[cfg(feature = "kafka")]
+pub use kafka::{KafkaConfig, KafkaSink};
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresSink;
+#[cfg(feature = "s3")]
+pub use s3::{S3Config, S3Sink};
+
+/// Custom result type for sink operations
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Error type for sink operations
+#[derive(Debug, Error)]
+pub enum Error {
+    /// IO errors
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// Serialization errors
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+
+    /// CSV errors
+    #[error("CSV error: {0}")]
+    CsvError(#[from] csv::Error),
+
+    /// Errors from a remote destination (S3, Postgres, Kafka)
+    #[error("Sink error: {0}")]
+    SinkError(String),
+
+    /// The buffer is full and the record was not accepted
+    #[error("Sink buffer is full")]
+    BufferFull,
+
+    /// The sink was shut down
+    #[error("Sink is closed")]
+    Closed,
+
+    /// Invalid configuration
+    #[error("Configuration error: {0}")]
+    ConfigError(String),
+}
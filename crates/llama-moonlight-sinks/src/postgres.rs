@@ -0,0 +1,128 @@
+//! Postgres sink.
+//!
+//! Records are stored as JSONB rows. Each batch is inserted with a single
+//! `INSERT ... SELECT FROM UNNEST` statement, so a batch is either stored
+//! completely or not at all and can be retried safely.
+
+use crate::sink::Sink;
+use crate::{Error, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use std::time::Duration;
+use tracing::info;
+
+/// Inserts records into a Postgres table.
+pub struct PostgresSink {
+    pool: PgPool,
+    table: String,
+    insert: String,
+}
+
+/// Checks that a (possibly schema-qualified) table name is a plain identifier.
+fn validate_table(table: &str) -> Result<()> {
+    let valid = !table.is_empty()
+        && table.split('.').all(|part| {
+            let mut chars = part.chars();
+            matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::ConfigError(format!("Invalid table name: {}", table)))
+    }
+}
+
+impl PostgresSink {
+    /// Connects and creates the table if it doesn't exist.
+    pub async fn connect(database_url: &str, table: &str) -> Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .acquire_timeout(Duration::from_secs(30))
+            .connect(database_url)
+            .await
+            .map_err(pg_error)?;
+        Self::with_pool(pool, table).await
+    }
+
+    /// Uses an existing pool, creating the table if it doesn't exist.
+    pub async fn with_pool(pool: PgPool, table: &str) -> Result<Self> {
+        validate_table(table)?;
+
+        sqlx::query(&format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                id BIGSERIAL PRIMARY KEY,
+                data JSONB NOT NULL,
+                inserted_at TIMESTAMPTZ NOT NULL DEFAULT now()
+            )
+            "#,
+            table
+        ))
+        .execute(&pool)
+        .await
+        .map_err(pg_error)?;
+
+        info!("Writing results to Postgres table {}", table);
+        Ok(Self {
+            pool,
+            table: table.to_string(),
+            insert: format!("INSERT INTO {} (data) SELECT * FROM UNNEST($1::jsonb[])", table),
+        })
+    }
+
+    /// Returns the table name.
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+}
+
+/// Converts a sqlx error into a sink error.
+fn pg_error(e: sqlx::Error) -> Error {
+    Error::SinkError(format!("Postgres error: {}", e))
+}
+
+#[async_trait]
+impl Sink for PostgresSink {
+    async fn write_batch(&mut self, records: &[Value]) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        sqlx::query(&self.insert)
+            .bind(records.to_vec())
+            .execute(&self.pool)
+            .await
+            .map_err(pg_error)?;
+        Ok(())
+    }
+
+    /// Every batch is committed as it is written, so there is nothing to flush.
+    async fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.pool.close().await;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "postgres"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_table() {
+        assert!(validate_table("results").is_ok());
+        assert!(validate_table("scrape.results_2024").is_ok());
+        assert!(validate_table("results; DROP TABLE x").is_err());
+        assert!(validate_table("1results").is_err());
+        assert!(validate_table("").is_err());
+    }
+}
@@ -0,0 +1,287 @@
+//! S3 sink.
+//!
+//! Records are written as JSON Lines into a single object using a multipart
+//! upload. Parts are uploaded as soon as `part_size` bytes are buffered; the
+//! object only becomes visible once the sink is closed and the upload is
+//! completed. Small outputs that never fill a part are written with a single
+//! `PutObject` instead. If completing fails, the upload is aborted so no
+//! orphaned parts keep accruing storage costs.
+//!
+//! A part that fails to upload stays buffered and is retried with the next
+//! batch, until `max_buffered` bytes are pending; from then on batches fail
+//! with the upload error instead of growing the buffer.
+
+use crate::sink::Sink;
+use crate::{Error, Result};
+use async_trait::async_trait;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use aws_sdk_s3::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, info, warn};
+
+/// S3 requires every part but the last to be at least 5 MiB.
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// Configuration for the S3 sink.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3Config {
+    /// Bucket name
+    pub bucket: String,
+
+    /// Object key
+    pub key: String,
+
+    /// Region (taken from the environment if not set)
+    pub region: Option<String>,
+
+    /// Custom endpoint, e.g. for MinIO (enables path-style addressing)
+    pub endpoint: Option<String>,
+
+    /// Bytes buffered before a part is uploaded (at least 5 MiB)
+    pub part_size: usize,
+
+    /// Bytes kept buffered while parts fail to upload (at least two parts)
+    #[serde(default)]
+    pub max_buffered: usize,
+}
+
+impl S3Config {
+    /// Creates a configuration for the given object.
+    pub fn new(bucket: &str, key: &str) -> Self {
+        Self {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            region: None,
+            endpoint: None,
+            part_size: 8 * 1024 * 1024,
+            max_buffered: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// Streams records into an S3 object.
+pub struct S3Sink {
+    client: Client,
+    config: S3Config,
+    buffer: Vec<u8>,
+    upload_id: Option<String>,
+    parts: Vec<CompletedPart>,
+    closed: bool,
+}
+
+/// Converts an SDK error into a sink error.
+fn s3_error(context: &str, e: impl std::fmt::Display) -> Error {
+    Error::SinkError(format!("S3 {} failed: {}", context, e))
+}
+
+impl S3Sink {
+    /// Creates a sink using an existing client.
+    pub fn new(client: Client, config: S3Config) -> Self {
+        Self {
+            client,
+            config,
+            buffer: Vec::new(),
+            upload_id: None,
+            parts: Vec::new(),
+            closed: false,
+        }
+    }
+
+    /// Creates a sink with credentials and region from the environment.
+    pub async fn from_env(config: S3Config) -> Result<Self> {
+        let mut loader = aws_config::from_env();
+        if let Some(region) = &config.region {
+            loader = loader.region(aws_sdk_s3::config::Region::new(region.clone()));
+        }
+        let shared = loader.load().await;
+
+        let mut builder = aws_sdk_s3::config::Builder::from(&shared);
+        if let Some(endpoint) = &config.endpoint {
+            builder = builder.endpoint_url(endpoint).force_path_style(true);
+        }
+
+        Ok(Self::new(Client::from_conf(builder.build()), config))
+    }
+
+    fn part_size(&self) -> usize {
+        self.config.part_size.max(MIN_PART_SIZE)
+    }
+
+    fn max_buffered(&self) -> usize {
+        self.config.max_buffered.max(2 * self.part_size())
+    }
+
+    /// Starts the multipart upload if it hasn't been started yet.
+    async fn upload_id(&mut self) -> Result<String> {
+        if let Some(id) = &self.upload_id {
+            return Ok(id.clone());
+        }
+
+        let output = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.config.bucket)
+            .key(&self.config.key)
+            .content_type("application/x-ndjson")
+            .send()
+            .await
+            .map_err(|e| s3_error("CreateMultipartUpload", e))?;
+        let id = output
+            .upload_id()
+            .ok_or_else(|| Error::SinkError("S3 returned no upload ID".to_string()))?
+            .to_string();
+
+        info!("Started multipart upload to s3://{}/{}", self.config.bucket, self.config.key);
+        self.upload_id = Some(id.clone());
+        Ok(id)
+    }
+
+    /// Uploads the first `len` buffered bytes as the next part.
+    async fn upload_part(&mut self, len: usize) -> Result<()> {
+        let upload_id = self.upload_id().await?;
+        let part_number = self.parts.len() as i32 + 1;
+        let body: Vec<u8> = self.buffer[..len].to_vec();
+
+        let output = self
+            .client
+            .upload_part()
+            .bucket(&self.config.bucket)
+            .key(&self.config.key)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .body(ByteStream::from(body))
+            .send()
+            .await
+            .map_err(|e| s3_error("UploadPart", e))?;
+
+        // Only drop the bytes once the part is stored, so a retry resends them
+        self.buffer.drain(..len);
+        self.parts.push(
+            CompletedPart::builder()
+                .set_e_tag(output.e_tag().map(str::to_string))
+                .part_number(part_number)
+                .build(),
+        );
+        debug!(part_number, bytes = len, "Uploaded part");
+        Ok(())
+    }
+
+    /// Appends records to the buffer as JSON Lines.
+    fn append(&mut self, records: &[Value]) -> Result<()> {
+        for record in records {
+            serde_json::to_writer(&mut self.buffer, record)?;
+            self.buffer.push(b'\n');
+        }
+        Ok(())
+    }
+
+    /// Uploads as many full parts as are buffered.
+    async fn upload_full_parts(&mut self) -> Result<()> {
+        let part_size = self.part_size();
+        while self.buffer.len() >= part_size {
+            self.upload_part(part_size).await?;
+        }
+        Ok(())
+    }
+
+    async fn complete(&mut self) -> Result<()> {
+        let Some(upload_id) = self.upload_id.clone() else {
+            // Never reached a full part: a single PutObject is enough
+            self.client
+                .put_object()
+                .bucket(&self.config.bucket)
+                .key(&self.config.key)
+                .content_type("application/x-ndjson")
+                .body(ByteStream::from(std::mem::take(&mut self.buffer)))
+                .send()
+                .await
+                .map_err(|e| s3_error("PutObject", e))?;
+            return Ok(());
+        };
+
+        if !self.buffer.is_empty() {
+            self.upload_part(self.buffer.len()).await?;
+        }
+
+        self.client
+            .complete_multipart_upload()
+            .bucket(&self.config.bucket)
+            .key(&self.config.key)
+            .upload_id(&upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(self.parts.clone()))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|e| s3_error("CompleteMultipartUpload", e))?;
+
+        info!(
+            "Completed upload of {} parts to s3://{}/{}",
+            self.parts.len(),
+            self.config.bucket,
+            self.config.key
+        );
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sink for S3Sink {
+    async fn write_batch(&mut self, records: &[Value]) -> Result<()> {
+        if self.closed {
+            return Err(Error::Closed);
+        }
+
+        // Retry parts that failed before; once too much is pending, refuse the
+        // batch (without buffering it, so a retry doesn't duplicate records)
+        if let Err(e) = self.upload_full_parts().await {
+            if self.buffer.len() >= self.max_buffered() {
+                return Err(e);
+            }
+            warn!("Deferring part upload: {}", e);
+            return self.append(records);
+        }
+
+        self.append(records)?;
+        // A failure here keeps the bytes buffered; the next write or close retries
+        if let Err(e) = self.upload_full_parts().await {
+            warn!("Deferring part upload: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Uploads full parts. Objects are only visible after [`Sink::close`].
+    async fn flush(&mut self) -> Result<()> {
+        self.upload_full_parts().await
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+
+        let completed = self.complete().await;
+        if completed.is_err() {
+            if let Some(upload_id) = &self.upload_id {
+                let _ = self
+                    .client
+                    .abort_multipart_upload()
+                    .bucket(&self.config.bucket)
+                    .key(&self.config.key)
+                    .upload_id(upload_id)
+                    .send()
+                    .await;
+            }
+        }
+        self.closed = true;
+        completed
+    }
+
+    fn name(&self) -> &str {
+        "s3"
+    }
+}
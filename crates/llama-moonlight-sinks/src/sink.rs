@@ -0,0 +1,29 @@
+//! The sink trait.
+
+use crate::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+
+/// A destination for result records.
+///
+/// Sinks are driven by a single task (see [`crate::BufferedSink`]), so they take
+/// `&mut self` and need not be internally synchronized.
+#[async_trait]
+pub trait Sink: Send {
+    /// Writes a batch of records.
+    ///
+    /// A failed call may be retried with the same batch, so implementations
+    /// should not leave a partially written batch behind where avoidable.
+    async fn write_batch(&mut self, records: &[Value]) -> Result<()>;
+
+    /// Makes everything written so far durable.
+    async fn flush(&mut self) -> Result<()>;
+
+    /// Flushes and releases the destination. No writes follow.
+    async fn close(&mut self) -> Result<()> {
+        self.flush().await
+    }
+
+    /// Short name used in logs.
+    fn name(&self) -> &str;
+}
@@ -0,0 +1,182 @@
+//! Runs a corpus of detector snippets against Chromium with the standard evasions.
+//!
+//! Meant for CI: the process exits with status 1 if a snippet detected the
+//! browser, or with `--history` only if a snippet regressed since the last run.
+//!
+//! ```sh
+//! cargo run --example evasion_regression -- detectors/ \
+//!     --url https://example.com --report report.json --history history.jsonl
+//! ```
+
+use llama_moonlight_core::{Browser, Moonlight, Page};
+use llama_moonlight_headers::{BrowserType, DeviceType, PlatformType};
+use llama_moonlight_stealth::{
+    evasion::{InterceptHandler, StealthTarget},
+    DetectorCorpus, RegressionReport, RegressionRunner,
+};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::PathBuf;
+use tokio::runtime::Handle;
+
+/// Page of a fresh browser context, driven synchronously as the runner expects
+#[derive(Debug)]
+struct PageTarget {
+    page: Page,
+    headers: HashMap<String, String>,
+}
+
+impl PageTarget {
+    /// Open a page in a new context of the browser
+    fn open(browser: &Browser, url: &str) -> llama_moonlight_stealth::Result<Self> {
+        block_on(async {
+            let context = browser.new_context().await?;
+            let page = context.new_page().await?;
+            page.goto(url).await?;
+            Ok::<_, llama_moonlight_core::Error>(page)
+        })
+        .map(|page| Self { page, headers: HashMap::new() })
+        .map_err(|e| llama_moonlight_stealth::Error::InitError(e.to_string()))
+    }
+}
+
+impl StealthTarget for PageTarget {
+    fn execute_script(&mut self, script: &str) -> llama_moonlight_stealth::Result<String> {
+        // Promises, such as the verdicts of snippets, are awaited
+        let value: serde_json::Value = block_on(self.page.evaluate(script))
+            .map_err(|e| llama_moonlight_stealth::Error::InjectionError(e.to_string()))?;
+
+        Ok(match value {
+            serde_json::Value::String(text) => text,
+            serde_json::Value::Null => String::new(),
+            other => other.to_string(),
+        })
+    }
+
+    fn browser_type(&self) -> BrowserType {
+        BrowserType::Chrome
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Desktop
+    }
+
+    fn platform_type(&self) -> PlatformType {
+        PlatformType::Linux
+    }
+
+    fn set_header(&mut self, name: &str, value: &str) -> llama_moonlight_stealth::Result<()> {
+        self.headers.insert(name.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn get_header(&self, name: &str) -> Option<String> {
+        self.headers.get(name).cloned()
+    }
+
+    fn remove_header(&mut self, name: &str) -> llama_moonlight_stealth::Result<()> {
+        self.headers.remove(name);
+        Ok(())
+    }
+
+    fn intercept_requests(&mut self, _pattern: &str, _handler: InterceptHandler) -> llama_moonlight_stealth::Result<()> {
+        // Detector snippets run in the page, so requests need not be rewritten
+        Ok(())
+    }
+
+    fn set_cookie(&mut self, name: &str, value: &str, domain: &str) -> llama_moonlight_stealth::Result<()> {
+        let script = format!(
+            "document.cookie = {}",
+            serde_json::to_string(&format!("{}={}; domain={}; path=/", name, value, domain))?
+        );
+        self.execute_script(&script).map(|_| ())
+    }
+}
+
+/// Wait for a future from the synchronous target methods
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::task::block_in_place(|| Handle::current().block_on(future))
+}
+
+/// Command line arguments
+struct Args {
+    corpus: PathBuf,
+    url: String,
+    report: Option<PathBuf>,
+    history: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse() -> Result<Self, Box<dyn Error>> {
+        let usage = "usage: evasion_regression <corpus-dir> [--url URL] [--report FILE] [--history FILE]";
+        let mut args = std::env::args().skip(1);
+        let mut corpus = None;
+        let mut url = "about:blank".to_string();
+        let mut report = None;
+        let mut history = None;
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value\n{}", arg, usage));
+            match arg.as_str() {
+                "--url" => url = value()?,
+                "--report" => report = Some(PathBuf::from(value()?)),
+                "--history" => history = Some(PathBuf::from(value()?)),
+                _ if corpus.is_none() && !arg.starts_with("--") => corpus = Some(PathBuf::from(&arg)),
+                _ => return Err(format!("unexpected argument {}\n{}", arg, usage).into()),
+            }
+        }
+
+        Ok(Self {
+            corpus: corpus.ok_or(usage)?,
+            url,
+            report,
+            history,
+        })
+    }
+}
+
+#[tokio::main(flavor = "multi_thread")]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse()?;
+
+    let corpus = DetectorCorpus::load(&args.corpus)?;
+    println!("Loaded {} detector snippets from {}", corpus.len(), args.corpus.display());
+
+    let moonlight = Moonlight::new().await?;
+    let browser = moonlight.browser_type("chromium").ok_or("Chromium is not available")?.launch().await?;
+
+    let runner = RegressionRunner::new(corpus);
+    let report = runner.run(|| PageTarget::open(&browser, &args.url));
+    browser.close().await?;
+    let report = report?;
+
+    for result in &report.results {
+        let detail = result.error.as_deref().map(|e| format!(" ({})", e)).unwrap_or_default();
+        println!("{:?}\t{}{}", result.status, result.id, detail);
+    }
+    println!("{} passed, {} failed", report.passed_count(), report.failed_count());
+
+    if let Some(path) = &args.report {
+        std::fs::write(path, report.to_json()?)?;
+    }
+
+    // With a history, only regressions since the previous run fail the build
+    let failed = match &args.history {
+        Some(path) => {
+            let previous = RegressionReport::load_history(path)?.pop();
+            report.append_to_history(path)?;
+
+            let regressions = previous.map(|previous| report.regressions_since(&previous)).unwrap_or_default();
+            for id in &regressions {
+                println!("Regressed: {}", id);
+            }
+            !regressions.is_empty()
+        },
+        None => !report.all_passed(),
+    };
+
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -7,7 +7,10 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use lazy_static::lazy_static;
-use log::{debug, info, warn, error};
+use rand::distributions::{Distribution, Standard};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tracing::{debug, info, instrument, warn, error};
 
 use crate::Result;
 use crate::Error;
@@ -15,10 +18,17 @@ use crate::StealthConfig;
 use crate::StealthCapabilities;
 use crate::evasion::{EvasionManager, StealthTarget, InterceptHandler, InterceptedRequest};
 use crate::fingerprint::{FingerprintManager, BrowserFingerprint};
-use crate::proxy::{ProxyManager, ProxyConfig};
+use crate::proxy::{ProxyManager, ProxyConfig, ProxyFailure};
 use crate::humanize::HumanizationManager;
+use crate::navigation::NavigationHistory;
+use crate::display::HeadfulBrowser;
+use llama_moonlight_core::telemetry::FIELD_DOMAIN;
+use llama_moonlight_core::BrowserOptions;
 use llama_moonlight_headers::{BrowserType, DeviceType, PlatformType, HeaderGenerator};
 
+/// Identity used when none is set
+pub const DEFAULT_IDENTITY: &str = "default";
+
 /// Client for stealth browser automation
 #[derive(Debug)]
 pub struct StealthClient {
@@ -54,6 +64,12 @@ pub struct StealthClient {
     
     /// Domains that have been visited
     visited_domains: HashMap<String, u32>,
+    
+    /// Identity whose navigation history is used for headers
+    identity: String,
+    
+    /// Pages visited by each identity
+    navigation: NavigationHistory,
 }
 
 impl StealthClient {
@@ -87,6 +103,8 @@ impl StealthClient {
             humanization_manager: HumanizationManager::new(),
             stealth_applied: false,
             visited_domains: HashMap::new(),
+            identity: DEFAULT_IDENTITY.to_string(),
+            navigation: NavigationHistory::new(),
         }
     }
     
@@ -117,6 +135,12 @@ impl StealthClient {
         self
     }
     
+    /// Set the identity whose navigation history is used for headers
+    pub fn with_identity(mut self, identity: &str) -> Self {
+        self.identity = identity.to_string();
+        self
+    }
+    
     /// Enable proxy support with the provided proxy configuration
     pub fn with_proxy(mut self, proxy: ProxyConfig) -> Self {
         let mut proxy_manager = ProxyManager::new();
@@ -132,6 +156,7 @@ impl StealthClient {
     }
     
     /// Apply stealth techniques to the target
+    #[instrument(skip_all)]
     pub fn apply_stealth<T: StealthTarget + StealthCapabilities>(&mut self, target: &mut T) -> Result<()> {
         if self.stealth_applied {
             debug!("Stealth already applied, skipping");
@@ -158,8 +183,9 @@ impl StealthClient {
             }
         }
         
-        // Apply evasion techniques
+        // Apply evasion techniques, with canvas noise seeded by the fingerprint
         debug!("Applying evasion techniques");
+        self.evasion_manager.set_noise_seed(self.fingerprint_manager.noise_seed());
         self.evasion_manager.apply_all(target)?;
         
         // Apply interception if needed
@@ -187,9 +213,22 @@ impl StealthClient {
     }
     
     /// Generate stealth headers for a URL
+    ///
+    /// Once the current identity has visited a page, `Referer` and
+    /// `Sec-Fetch-Site` follow from that page rather than being randomized.
+    #[instrument(skip(self, url), fields({ FIELD_DOMAIN } = %url::Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_string)).unwrap_or_default()))]
     pub fn generate_headers(&self, url: &str) -> HashMap<String, String> {
         debug!("Generating stealth headers for URL: {}", url);
-        self.header_generator.generate(url)
+        let mut headers = self.header_generator.generate(url);
+        self.navigation.apply(&self.identity, url, &mut headers);
+        headers
+    }
+    
+    /// Generate stealth headers for navigating to a URL and record the visit
+    pub fn navigate(&mut self, url: &str) -> Result<HashMap<String, String>> {
+        let headers = self.generate_headers(url);
+        self.record_visit(url)?;
+        Ok(headers)
     }
     
     /// Setup fingerprinting interception
@@ -248,11 +287,15 @@ impl StealthClient {
         Ok(())
     }
     
-    /// Record a visit to a domain
+    /// Record a visit to a page
     pub fn record_visit(&mut self, url: &str) -> Result<()> {
         let domain = extract_domain(url)?;
+        if let Some(proxy_manager) = &mut self.proxy_manager {
+            proxy_manager.set_domain(Some(&domain));
+        }
         let count = self.visited_domains.entry(domain.clone()).or_insert(0);
         *count += 1;
+        self.navigation.record(&self.identity, url);
         
         debug!("Recorded visit to domain: {} (count: {})", domain, count);
         
@@ -265,6 +308,26 @@ impl StealthClient {
         Ok(*self.visited_domains.get(&domain).unwrap_or(&0))
     }
     
+    /// Switch to another identity, keeping the navigation history of each
+    pub fn set_identity(&mut self, identity: &str) {
+        self.identity = identity.to_string();
+    }
+    
+    /// Get the current identity
+    pub fn identity(&self) -> &str {
+        &self.identity
+    }
+    
+    /// Get the navigation history
+    pub fn navigation(&self) -> &NavigationHistory {
+        &self.navigation
+    }
+    
+    /// Get a mutable reference to the navigation history
+    pub fn navigation_mut(&mut self) -> &mut NavigationHistory {
+        &mut self.navigation
+    }
+    
     /// Rotate the proxy if proxy support is enabled
     pub fn rotate_proxy(&mut self) -> Option<&ProxyConfig> {
         if let Some(proxy_manager) = &mut self.proxy_manager {
@@ -282,10 +345,15 @@ impl StealthClient {
         }
     }
     
-    /// Record a failed request with the current proxy
+    /// Record a failed request with the current proxy, for an unknown reason
     pub fn record_proxy_failure(&mut self) {
+        self.record_proxy_failure_kind(ProxyFailure::Other);
+    }
+    
+    /// Record a failed request with the current proxy and why it failed
+    pub fn record_proxy_failure_kind(&mut self, failure: ProxyFailure) {
         if let Some(proxy_manager) = &mut self.proxy_manager {
-            proxy_manager.record_failure();
+            proxy_manager.record_failure_kind(failure);
         }
     }
     
@@ -314,6 +382,20 @@ impl StealthClient {
         &mut self.fingerprint_manager
     }
     
+    /// Launch a browser as configured for stealth
+    ///
+    /// With [`StealthConfig::virtual_display`] enabled, the browser runs
+    /// headful in a new virtual display, stopped along with the browser.
+    /// The browser's stealth option defaults to the client's.
+    pub async fn launch(
+        &self,
+        browser_type: &llama_moonlight_core::BrowserType,
+        mut options: BrowserOptions,
+    ) -> Result<HeadfulBrowser> {
+        options.stealth.get_or_insert(self.config.stealth_enabled);
+        HeadfulBrowser::launch(browser_type, options, &self.config.virtual_display).await
+    }
+    
     /// Get the browser type
     pub fn browser_type(&self) -> &BrowserType {
         &self.browser_type
@@ -344,10 +426,13 @@ fn extract_domain(url: &str) -> Result<String> {
 }
 
 /// Humanization manager for emulating human behavior
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct HumanizationManager {
     /// Configuration for humanization
     config: HumanizationConfig,
+    
+    /// Random number generator behind delays, mouse paths and typos
+    rng: std::sync::Mutex<StdRng>,
 }
 
 /// Configuration for humanization
@@ -389,25 +474,44 @@ impl Default for HumanizationConfig {
     }
 }
 
+impl Default for HumanizationManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HumanizationManager {
     /// Create a new humanization manager
     pub fn new() -> Self {
-        Self {
-            config: HumanizationConfig::default(),
-        }
+        Self::with_config(HumanizationConfig::default())
     }
     
     /// Create a new humanization manager with the specified configuration
     pub fn with_config(config: HumanizationConfig) -> Self {
         Self {
             config,
+            rng: std::sync::Mutex::new(StdRng::from_entropy()),
         }
     }
     
+    /// Seed the random number generator, making the generated behavior reproducible
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = std::sync::Mutex::new(StdRng::seed_from_u64(seed));
+        self
+    }
+    
+    /// Draw a random value
+    fn random<T>(&self) -> T
+    where
+        Standard: Distribution<T>,
+    {
+        self.rng.lock().unwrap().gen()
+    }
+    
     /// Get a random delay between actions
     pub fn random_delay(&self) -> Duration {
         let range = self.config.max_delay_ms - self.config.min_delay_ms;
-        let random_ms = rand::random::<u64>() % range;
+        let random_ms = self.random::<u64>() % range;
         Duration::from_millis(self.config.min_delay_ms + random_ms)
     }
     
@@ -416,11 +520,11 @@ impl HumanizationManager {
         let mut path = Vec::with_capacity(steps);
         
         // Control points for Bezier curve
-        let cp1_x = start_x + (end_x - start_x) / 3.0 + (rand::random::<f64>() - 0.5) * self.config.mouse_jitter * 100.0;
-        let cp1_y = start_y + (end_y - start_y) / 3.0 + (rand::random::<f64>() - 0.5) * self.config.mouse_jitter * 100.0;
+        let cp1_x = start_x + (end_x - start_x) / 3.0 + (self.random::<f64>() - 0.5) * self.config.mouse_jitter * 100.0;
+        let cp1_y = start_y + (end_y - start_y) / 3.0 + (self.random::<f64>() - 0.5) * self.config.mouse_jitter * 100.0;
         
-        let cp2_x = start_x + 2.0 * (end_x - start_x) / 3.0 + (rand::random::<f64>() - 0.5) * self.config.mouse_jitter * 100.0;
-        let cp2_y = start_y + 2.0 * (end_y - start_y) / 3.0 + (rand::random::<f64>() - 0.5) * self.config.mouse_jitter * 100.0;
+        let cp2_x = start_x + 2.0 * (end_x - start_x) / 3.0 + (self.random::<f64>() - 0.5) * self.config.mouse_jitter * 100.0;
+        let cp2_y = start_y + 2.0 * (end_y - start_y) / 3.0 + (self.random::<f64>() - 0.5) * self.config.mouse_jitter * 100.0;
         
         for i in 0..steps {
             let t = i as f64 / (steps - 1) as f64;
@@ -437,8 +541,8 @@ impl HumanizationManager {
                    t.powi(3) * end_y;
             
             // Add jitter
-            let jitter_x = (rand::random::<f64>() - 0.5) * self.config.mouse_jitter * 5.0;
-            let jitter_y = (rand::random::<f64>() - 0.5) * self.config.mouse_jitter * 5.0;
+            let jitter_x = (self.random::<f64>() - 0.5) * self.config.mouse_jitter * 5.0;
+            let jitter_y = (self.random::<f64>() - 0.5) * self.config.mouse_jitter * 5.0;
             
             path.push((x + jitter_x, y + jitter_y));
         }
@@ -458,24 +562,24 @@ impl HumanizationManager {
             // Add variance based on character
             let mut delay = match c {
                 // Space and punctuation usually take longer
-                ' ' | '.' | ',' | '!' | '?' | ';' | ':' => base_delay_ms + rand::random::<u64>() % 300,
+                ' ' | '.' | ',' | '!' | '?' | ';' | ':' => base_delay_ms + self.random::<u64>() % 300,
                 
                 // Shift characters might take longer
                 'A'..='Z' | '@' | '#' | '$' | '%' | '^' | '&' | '*' | '(' | ')' | '_' | '+' | '{' | '}' | '|' | '"' | '<' | '>' | '?' => 
-                    base_delay_ms + rand::random::<u64>() % 200,
+                    base_delay_ms + self.random::<u64>() % 200,
                 
                 // Numbers and letters are faster
                 'a'..='z' | '0'..='9' => 
-                    base_delay_ms + rand::random::<u64>() % 100,
+                    base_delay_ms + self.random::<u64>() % 100,
                 
                 // Default
-                _ => base_delay_ms + rand::random::<u64>() % 150,
+                _ => base_delay_ms + self.random::<u64>() % 150,
             };
             
             // If the previous character is on the opposite side of the keyboard, add a bit more delay
             if i > 0 {
                 if is_opposite_side_of_keyboard(chars[i-1], *c) {
-                    delay += rand::random::<u64>() % 50 + 50;
+                    delay += self.random::<u64>() % 50 + 50;
                 }
             }
             
@@ -492,14 +596,14 @@ impl HumanizationManager {
         
         for c in text.chars() {
             // Chance of making a typo
-            if rand::random::<f64>() < self.config.typing_mistake_prob {
+            if self.random::<f64>() < self.config.typing_mistake_prob {
                 // Add a typo
-                let typo = generate_typo(c);
+                let typo = generate_typo(c, self.random::<usize>());
                 result.push(typo);
                 delays.push(self.random_delay());
                 
                 // Chance of correcting the typo
-                if rand::random::<f64>() < self.config.correction_prob {
+                if self.random::<f64>() < self.config.correction_prob {
                     // Add a backspace
                     result.push('\u{0008}'); // Backspace character
                     delays.push(Duration::from_millis(300));
@@ -547,7 +651,7 @@ fn is_opposite_side_of_keyboard(a: char, b: char) -> bool {
 }
 
 /// Generate a typo for a character based on QWERTY keyboard layout
-fn generate_typo(c: char) -> char {
+fn generate_typo(c: char, choice: usize) -> char {
     lazy_static! {
         static ref ADJACENT_KEYS: HashMap<char, Vec<char>> = {
             let mut m = HashMap::new();
@@ -585,7 +689,7 @@ fn generate_typo(c: char) -> char {
     let was_upper = c.is_uppercase();
     
     if let Some(adjacent) = ADJACENT_KEYS.get(&lower_c) {
-        let typo_index = rand::random::<usize>() % adjacent.len();
+        let typo_index = choice % adjacent.len();
         let typo_char = adjacent[typo_index];
         
         if was_upper {
@@ -738,6 +842,23 @@ mod tests {
         assert!(target.capabilities.interceptors.contains(&"**/fonts/**".to_string()));
     }
     
+    #[test]
+    fn test_navigation_headers() {
+        let mut client = StealthClient::new().with_identity("alice");
+        
+        client.navigate("https://www.example.com/").unwrap();
+        let headers = client.navigate("https://www.example.com/about").unwrap();
+        assert_eq!(headers.get("Referer").unwrap(), "https://www.example.com/");
+        assert_eq!(headers.get("Sec-Fetch-Site").unwrap(), "same-origin");
+        assert_eq!(client.visit_count("https://www.example.com/").unwrap(), 2);
+        
+        // A new identity starts from an external entry point
+        client.set_identity("bob");
+        let headers = client.generate_headers("https://www.example.com/about");
+        assert_ne!(headers.get("Referer").map(String::as_str), Some("https://www.example.com/about"));
+        assert_eq!(client.navigation().pages("alice").len(), 2);
+    }
+    
     #[test]
     fn test_extract_domain() {
         assert_eq!(extract_domain("https://www.example.com/path").unwrap(), "www.example.com");
@@ -761,4 +882,14 @@ mod tests {
         assert!(text.len() >= 4); // May be longer if it includes typos and corrections
         assert_eq!(delays.len(), text.len());
     }
-} 
\ No newline at end of file
+    
+    #[test]
+    fn test_seeded_humanization() {
+        let a = HumanizationManager::new().with_seed(7);
+        let b = HumanizationManager::new().with_seed(7);
+        
+        assert_eq!(a.typing_delays("Hello, world!"), b.typing_delays("Hello, world!"));
+        assert_eq!(a.mouse_path(0.0, 0.0, 100.0, 100.0, 10), b.mouse_path(0.0, 0.0, 100.0, 100.0, 10));
+        assert_eq!(a.humanize_text("reproducible"), b.humanize_text("reproducible"));
+    }
+}
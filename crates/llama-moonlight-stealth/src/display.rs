@@ -0,0 +1,593 @@
+//! Headful browsers in a virtual display
+//!
+//! Several bot detectors treat headless browsers, including Chrome's new
+//! headless mode, differently from real headful sessions. This module runs
+//! the browser headful inside a virtual display server instead: Xvfb for X11
+//! or a headless Weston compositor for Wayland. A [`VirtualDisplay`] owns the
+//! server process and tears it down when dropped, so a failed launch or a
+//! panic does not leak it. Lock files and sockets left behind by a server
+//! that was killed with its parent are cleared before a display number is
+//! reused.
+//!
+//! Unless a display number is configured, Xvfb picks a free one itself and
+//! reports it over `-displayfd`, and Weston displays claim their number by
+//! creating its runtime directory, so concurrent launches never race for
+//! the same display.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+use llama_moonlight_core::{Browser, BrowserOptions, BrowserType};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::Error;
+use crate::Result;
+
+/// Directory holding the X server lock files
+const X_LOCK_DIR: &str = "/tmp";
+
+/// Directory holding the X server sockets
+const X_SOCKET_DIR: &str = "/tmp/.X11-unix";
+
+/// Display server run as a virtual display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayServer {
+    /// The X virtual framebuffer
+    Xvfb,
+    
+    /// The Weston compositor with its headless backend
+    Weston,
+}
+
+impl DisplayServer {
+    /// Get the executable run when none is configured
+    fn default_executable(&self) -> &'static str {
+        match self {
+            DisplayServer::Xvfb => "Xvfb",
+            DisplayServer::Weston => "weston",
+        }
+    }
+}
+
+/// Configuration of the virtual display browsers are launched in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VirtualDisplayConfig {
+    /// Whether to launch browsers headful in a virtual display
+    pub enabled: bool,
+    
+    /// Display server to run
+    pub server: DisplayServer,
+    
+    /// Screen width in pixels
+    pub width: u32,
+    
+    /// Screen height in pixels
+    pub height: u32,
+    
+    /// Color depth in bits, for Xvfb
+    pub depth: u32,
+    
+    /// Display number to use, a free one picked at startup if unset
+    pub display_number: Option<u32>,
+    
+    /// Path to the server executable, found on the `PATH` if unset
+    pub executable: Option<String>,
+    
+    /// Additional arguments passed to the server
+    pub extra_args: Vec<String>,
+    
+    /// How long to wait for the server to accept connections (milliseconds)
+    pub startup_timeout_ms: u64,
+}
+
+impl Default for VirtualDisplayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            server: DisplayServer::Xvfb,
+            width: 1920,
+            height: 1080,
+            depth: 24,
+            display_number: None,
+            executable: None,
+            extra_args: Vec::new(),
+            startup_timeout_ms: 10000,
+        }
+    }
+}
+
+impl VirtualDisplayConfig {
+    /// Get the command line of the server for a display number
+    ///
+    /// Without a number, Xvfb is asked to pick a free one and write it to
+    /// its standard output.
+    fn command(&self, display_number: Option<u32>) -> (String, Vec<String>) {
+        let executable = self.executable.clone().unwrap_or_else(|| self.server.default_executable().to_string());
+        
+        let mut args: Vec<String> = match self.server {
+            DisplayServer::Xvfb => {
+                let mut args = match display_number {
+                    Some(number) => vec![format!(":{}", number)],
+                    None => vec!["-displayfd".to_string(), "1".to_string()],
+                };
+                args.extend([
+                    "-screen".to_string(),
+                    "0".to_string(),
+                    format!("{}x{}x{}", self.width, self.height, self.depth),
+                    "-nolisten".to_string(),
+                    "tcp".to_string(),
+                ]);
+                args
+            },
+            DisplayServer::Weston => vec![
+                "--backend=headless-backend.so".to_string(),
+                format!("--socket={}", wayland_socket(display_number.unwrap_or(0))),
+                format!("--width={}", self.width),
+                format!("--height={}", self.height),
+                "--idle-time=0".to_string(),
+            ],
+        };
+        args.extend(self.extra_args.iter().cloned());
+        
+        (executable, args)
+    }
+}
+
+/// A running virtual display server
+///
+/// The server is stopped when the display is dropped.
+#[derive(Debug)]
+pub struct VirtualDisplay {
+    /// Server process, taken when stopped
+    child: Option<Child>,
+    
+    /// Server that was started
+    server: DisplayServer,
+    
+    /// Display number of the server
+    display_number: u32,
+    
+    /// Runtime directory holding the Wayland socket, removed when stopped
+    runtime_dir: Option<PathBuf>,
+}
+
+impl VirtualDisplay {
+    /// Start a display server and wait until it accepts connections
+    pub async fn start(config: &VirtualDisplayConfig) -> Result<Self> {
+        let timeout = Duration::from_millis(config.startup_timeout_ms);
+        
+        let (display_number, runtime_dir) = match (config.server, config.display_number) {
+            (DisplayServer::Xvfb, Some(number)) => {
+                if !clear_stale_x_lock(Path::new(X_LOCK_DIR), Path::new(X_SOCKET_DIR), number) {
+                    return Err(Error::InitError(format!("Display :{} is already in use", number)));
+                }
+                (Some(number), None)
+            },
+            // Xvfb reports the number it bound once it is listening
+            (DisplayServer::Xvfb, None) => (None, None),
+            (DisplayServer::Weston, Some(number)) => (Some(number), Some(create_runtime_dir(number)?)),
+            (DisplayServer::Weston, None) => {
+                let (number, runtime_dir) = claim_runtime_dir(99)?;
+                (Some(number), Some(runtime_dir))
+            },
+        };
+        
+        let (executable, args) = config.command(display_number);
+        match display_number {
+            Some(number) => info!("Starting {} on display {}", executable, number),
+            None => info!("Starting {} on a free display", executable),
+        }
+        
+        let mut command = Command::new(&executable);
+        command.args(&args).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+        if display_number.is_none() {
+            command.stdout(Stdio::piped());
+        }
+        if let Some(runtime_dir) = &runtime_dir {
+            command.env("XDG_RUNTIME_DIR", runtime_dir);
+        }
+        
+        let mut child = command.spawn().map_err(|e| {
+            if let Some(runtime_dir) = &runtime_dir {
+                let _ = std::fs::remove_dir_all(runtime_dir);
+            }
+            Error::InitError(format!("Failed to start {}: {}", executable, e))
+        })?;
+        
+        let display_number = match display_number {
+            Some(number) => number,
+            None => match read_display_number(&mut child, timeout).await {
+                Ok(number) => number,
+                Err(e) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(e);
+                },
+            },
+        };
+        
+        // From here on, dropping the display cleans up
+        let mut display = Self {
+            child: Some(child),
+            server: config.server,
+            display_number,
+            runtime_dir,
+        };
+        display.wait_ready(timeout).await?;
+        
+        info!("Virtual display {} is ready", display.name());
+        Ok(display)
+    }
+    
+    /// Wait for the server's socket to appear
+    async fn wait_ready(&mut self, timeout: Duration) -> Result<()> {
+        let socket = self.socket_path();
+        let deadline = Instant::now() + timeout;
+        
+        loop {
+            if socket.exists() {
+                return Ok(());
+            }
+            if !self.is_running() {
+                return Err(Error::InitError(format!("Display server for {} exited during startup", self.name())));
+            }
+            if Instant::now() >= deadline {
+                return Err(Error::InitError(format!(
+                    "Display server for {} did not start within {}ms",
+                    self.name(),
+                    timeout.as_millis()
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+    
+    /// Get the path of the socket clients connect to
+    fn socket_path(&self) -> PathBuf {
+        match (&self.server, &self.runtime_dir) {
+            (DisplayServer::Weston, Some(runtime_dir)) => runtime_dir.join(wayland_socket(self.display_number)),
+            _ => Path::new(X_SOCKET_DIR).join(format!("X{}", self.display_number)),
+        }
+    }
+    
+    /// Get the display name clients use, `:99` or `wayland-99`
+    pub fn name(&self) -> String {
+        match self.server {
+            DisplayServer::Xvfb => format!(":{}", self.display_number),
+            DisplayServer::Weston => wayland_socket(self.display_number),
+        }
+    }
+    
+    /// Get the environment variables pointing clients to the display
+    pub fn env(&self) -> HashMap<String, String> {
+        let mut env = HashMap::new();
+        match self.server {
+            DisplayServer::Xvfb => {
+                env.insert("DISPLAY".to_string(), self.name());
+            },
+            DisplayServer::Weston => {
+                env.insert("WAYLAND_DISPLAY".to_string(), self.name());
+                env.insert("MOZ_ENABLE_WAYLAND".to_string(), "1".to_string());
+                if let Some(runtime_dir) = &self.runtime_dir {
+                    env.insert("XDG_RUNTIME_DIR".to_string(), runtime_dir.display().to_string());
+                }
+            },
+        }
+        env
+    }
+    
+    /// Make browser options launch a headful browser on this display
+    pub fn apply(&self, options: &mut BrowserOptions) {
+        options.headless = Some(false);
+        options.env.get_or_insert_with(HashMap::new).extend(self.env());
+        
+        if self.server == DisplayServer::Weston {
+            let args = options.args.get_or_insert_with(Vec::new);
+            if !args.iter().any(|arg| arg.starts_with("--ozone-platform")) {
+                args.push("--ozone-platform=wayland".to_string());
+            }
+        }
+    }
+    
+    /// Check whether the server is still running
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.as_mut().map(|child| child.try_wait()), Some(Ok(None)))
+    }
+    
+    /// Stop the server and remove what it left behind
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+    
+    /// Kill the server and remove its runtime directory or stale lock
+    fn shutdown(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            if let Err(e) = child.kill() {
+                debug!("Display server for {} already exited: {}", self.name(), e);
+            }
+            let _ = child.wait();
+            info!("Stopped virtual display {}", self.name());
+        }
+        
+        match self.server {
+            DisplayServer::Xvfb => {
+                // A killed server may not remove its lock and socket
+                clear_stale_x_lock(Path::new(X_LOCK_DIR), Path::new(X_SOCKET_DIR), self.display_number);
+            },
+            DisplayServer::Weston => {
+                if let Some(runtime_dir) = self.runtime_dir.take() {
+                    if let Err(e) = std::fs::remove_dir_all(&runtime_dir) {
+                        warn!("Failed to remove runtime directory {}: {}", runtime_dir.display(), e);
+                    }
+                }
+            },
+        }
+    }
+}
+
+impl Drop for VirtualDisplay {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// A browser and the virtual display it runs in, if any
+///
+/// Closing the browser stops the display. If the browser is dropped without
+/// being closed, e.g. after it crashed, the display is still stopped.
+#[derive(Debug)]
+pub struct HeadfulBrowser {
+    /// The browser
+    pub browser: Browser,
+    
+    /// The display the browser runs in
+    pub display: Option<VirtualDisplay>,
+}
+
+impl HeadfulBrowser {
+    /// Launch a browser headful in a new virtual display
+    ///
+    /// If the configuration is not enabled, as in the default
+    /// [`StealthConfig::virtual_display`](crate::StealthConfig::virtual_display),
+    /// the browser is launched with the options unchanged.
+    pub async fn launch(browser_type: &BrowserType, mut options: BrowserOptions, config: &VirtualDisplayConfig) -> Result<Self> {
+        let display = if config.enabled {
+            let display = VirtualDisplay::start(config).await?;
+            display.apply(&mut options);
+            Some(display)
+        } else {
+            None
+        };
+        
+        // The display is dropped, and so stopped, if the browser fails to launch
+        let browser = browser_type
+            .launch_with_options(options)
+            .await
+            .map_err(|e| Error::InitError(format!("Failed to launch browser: {}", e)))?;
+        
+        Ok(Self { browser, display })
+    }
+    
+    /// Close the browser, then stop its display
+    pub async fn close(self) -> Result<()> {
+        let result = self.browser.close().await.map_err(|e| Error::Other(e.to_string()));
+        if let Some(display) = self.display {
+            display.stop();
+        }
+        result
+    }
+}
+
+impl std::ops::Deref for HeadfulBrowser {
+    type Target = Browser;
+    
+    fn deref(&self) -> &Browser {
+        &self.browser
+    }
+}
+
+/// Get the Wayland socket name of a display number
+fn wayland_socket(display_number: u32) -> String {
+    format!("wayland-{}", display_number)
+}
+
+/// Read the display number Xvfb writes to its standard output once it listens
+///
+/// The output stays attached to the child, so the server never writes to a
+/// closed pipe.
+async fn read_display_number(child: &mut Child, timeout: Duration) -> Result<u32> {
+    let stdout = child.stdout.take()
+        .ok_or_else(|| Error::InitError("Display server output is not captured".to_string()))?;
+    
+    let read = tokio::task::spawn_blocking(move || {
+        let mut reader = BufReader::new(stdout);
+        let mut line = String::new();
+        reader.read_line(&mut line).map(|_| (reader.into_inner(), line))
+    });
+    
+    // Killing the server on timeout ends the blocked read
+    let (stdout, line) = match tokio::time::timeout(timeout, read).await {
+        Ok(Ok(result)) => result?,
+        Ok(Err(e)) => return Err(Error::InitError(format!("Failed to read the display number: {}", e))),
+        Err(_) => return Err(Error::InitError(format!(
+            "Display server did not report a display within {}ms",
+            timeout.as_millis()
+        ))),
+    };
+    child.stdout = Some(stdout);
+    
+    line.trim().parse().map_err(|_| {
+        Error::InitError("Display server exited before reporting a display".to_string())
+    })
+}
+
+/// Claim the first Weston display number from `start` whose runtime
+/// directory another launch has not created
+///
+/// Creating the directory is atomic, so concurrent launches claim
+/// different numbers.
+fn claim_runtime_dir(start: u32) -> Result<(u32, PathBuf)> {
+    for number in start..start + 100 {
+        let runtime_dir = runtime_dir_path(number);
+        match std::fs::create_dir(&runtime_dir) {
+            Ok(()) => {
+                set_private(&runtime_dir)?;
+                return Ok((number, runtime_dir));
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(Error::InitError(format!("No free display number from {}", start)))
+}
+
+/// Check whether an X display number is free, clearing the lock of a server that died
+///
+/// A lock file holds the process ID of its server. When that process no longer
+/// exists, the lock and the server's socket are removed.
+fn clear_stale_x_lock(lock_dir: &Path, socket_dir: &Path, display_number: u32) -> bool {
+    let lock = lock_dir.join(format!(".X{}-lock", display_number));
+    let socket = socket_dir.join(format!("X{}", display_number));
+    
+    let pid = match std::fs::read_to_string(&lock) {
+        Ok(contents) => contents.trim().parse::<u32>().ok(),
+        Err(_) => return !socket.exists(),
+    };
+    
+    if pid.is_some_and(process_exists) {
+        return false;
+    }
+    
+    debug!("Removing stale lock of display :{}", display_number);
+    let _ = std::fs::remove_file(&lock);
+    let _ = std::fs::remove_file(&socket);
+    true
+}
+
+/// Check whether a process exists
+fn process_exists(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+/// Get the runtime directory of a Weston display number
+fn runtime_dir_path(display_number: u32) -> PathBuf {
+    std::env::temp_dir().join(format!("llama-moonlight-weston-{}", display_number))
+}
+
+/// Create a private runtime directory for Weston, which refuses shared ones
+fn create_runtime_dir(display_number: u32) -> Result<PathBuf> {
+    let runtime_dir = runtime_dir_path(display_number);
+    std::fs::create_dir_all(&runtime_dir)?;
+    set_private(&runtime_dir)?;
+    Ok(runtime_dir)
+}
+
+/// Make a runtime directory accessible to its owner only
+fn set_private(runtime_dir: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(runtime_dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_command() {
+        let config = VirtualDisplayConfig {
+            width: 1366,
+            height: 768,
+            extra_args: vec!["-ac".to_string()],
+            ..VirtualDisplayConfig::default()
+        };
+        let (executable, args) = config.command(Some(99));
+        assert_eq!(executable, "Xvfb");
+        assert_eq!(args, vec![":99", "-screen", "0", "1366x768x24", "-nolisten", "tcp", "-ac"]);
+        
+        // Without a number, Xvfb picks one and reports it on its output
+        let (_, args) = config.command(None);
+        assert_eq!(args[..2], ["-displayfd", "1"]);
+        
+        let config = VirtualDisplayConfig {
+            server: DisplayServer::Weston,
+            executable: Some("/usr/bin/weston".to_string()),
+            ..VirtualDisplayConfig::default()
+        };
+        let (executable, args) = config.command(Some(3));
+        assert_eq!(executable, "/usr/bin/weston");
+        assert!(args.contains(&"--socket=wayland-3".to_string()));
+        assert!(args.contains(&"--width=1920".to_string()));
+    }
+    
+    #[test]
+    fn test_apply_to_browser_options() {
+        let display = VirtualDisplay {
+            child: None,
+            server: DisplayServer::Weston,
+            display_number: 7,
+            runtime_dir: None,
+        };
+        let mut options = BrowserOptions {
+            headless: Some(true),
+            ..BrowserOptions::default()
+        };
+        display.apply(&mut options);
+        display.apply(&mut options);
+        
+        assert_eq!(options.headless, Some(false));
+        assert_eq!(options.env.as_ref().unwrap()["WAYLAND_DISPLAY"], "wayland-7");
+        assert_eq!(options.args.as_ref().unwrap().iter().filter(|arg| arg.starts_with("--ozone-platform")).count(), 1);
+        assert_eq!(display.name(), "wayland-7");
+    }
+    
+    #[test]
+    fn test_clear_stale_x_lock() {
+        let dir = std::env::temp_dir().join(format!("llama-moonlight-display-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        
+        // No lock and no socket
+        assert!(clear_stale_x_lock(&dir, &dir, 42));
+        
+        // A lock held by a live process
+        std::fs::write(dir.join(".X42-lock"), format!("{:>10}\n", std::process::id())).unwrap();
+        assert!(!clear_stale_x_lock(&dir, &dir, 42));
+        
+        // A lock left behind by a dead server, with its socket
+        std::fs::write(dir.join(".X42-lock"), format!("{:>10}\n", u32::MAX)).unwrap();
+        std::fs::write(dir.join("X42"), "").unwrap();
+        assert!(clear_stale_x_lock(&dir, &dir, 42));
+        assert!(!dir.join(".X42-lock").exists());
+        assert!(!dir.join("X42").exists());
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_read_display_number() {
+        // Stands in for Xvfb reporting the display it bound
+        let mut child = Command::new("sh")
+            .args(["-c", "echo 104; sleep 5"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        assert_eq!(read_display_number(&mut child, Duration::from_secs(5)).await.unwrap(), 104);
+        assert!(child.stdout.is_some());
+        let _ = child.kill();
+        let _ = child.wait();
+        
+        // A server exiting without a number fails the start
+        let mut child = Command::new("sh").args(["-c", "exit 1"]).stdout(Stdio::piped()).spawn().unwrap();
+        assert!(read_display_number(&mut child, Duration::from_secs(5)).await.is_err());
+        let _ = child.wait();
+    }
+}
@@ -9,6 +9,7 @@ use std::sync::Arc;
 
 use crate::Result;
 use crate::Error;
+use crate::fingerprint::NoiseSeed;
 use llama_moonlight_headers::{BrowserType, DeviceType, PlatformType};
 
 /// Priority level for evasion techniques
@@ -259,6 +260,19 @@ impl EvasionManager {
             .collect()
     }
     
+    /// Seed the canvas and WebGL noise with the seeds of an identity
+    ///
+    /// Replaces the noise script of `canvas_protection`, keeping whether it
+    /// is enabled, so canvas hashes stay stable across page loads of the
+    /// identity.
+    pub fn set_noise_seed(&mut self, seed: NoiseSeed) {
+        if let Some(evasion) = self.get_mut("canvas_protection") {
+            let enabled = evasion.is_enabled();
+            *evasion = canvas_protection(seed);
+            evasion.enabled = enabled;
+        }
+    }
+    
     /// Create a standard set of evasion techniques
     pub fn standard_evasions() -> Self {
         let mut manager = Self::new();
@@ -366,86 +380,7 @@ impl EvasionManager {
         ));
         
         // Canvas fingerprinting protection
-        manager.register(EvasionTechnique::with_js(
-            "canvas_protection",
-            "Protect against canvas fingerprinting",
-            30,
-            |target| {
-                target.execute_script(r#"
-                    (() => {
-                        const originalGetImageData = CanvasRenderingContext2D.prototype.getImageData;
-                        CanvasRenderingContext2D.prototype.getImageData = function(x, y, width, height) {
-                            const imageData = originalGetImageData.call(this, x, y, width, height);
-                            
-                            // Add subtle noise to the canvas data
-                            const data = imageData.data;
-                            for (let i = 0; i < data.length; i += 4) {
-                                // Modify only a small percentage of pixels
-                                if (Math.random() < 0.01) {
-                                    data[i] = data[i] ^ 1;     // Red
-                                    data[i + 1] = data[i + 1] ^ 1; // Green
-                                    data[i + 2] = data[i + 2] ^ 1; // Blue
-                                    // Don't modify alpha
-                                }
-                            }
-                            
-                            return imageData;
-                        };
-                        
-                        const originalToDataURL = HTMLCanvasElement.prototype.toDataURL;
-                        HTMLCanvasElement.prototype.toDataURL = function(type, quality) {
-                            // For tiny canvases (used for fingerprinting), add some noise
-                            if (this.width <= 16 && this.height <= 16) {
-                                const ctx = this.getContext('2d');
-                                if (ctx) {
-                                    ctx.fillStyle = `rgba(${Math.floor(Math.random() * 2)}, ${Math.floor(Math.random() * 2)}, ${Math.floor(Math.random() * 2)}, 0.01)`;
-                                    ctx.fillRect(0, 0, 1, 1);
-                                }
-                            }
-                            
-                            return originalToDataURL.call(this, type, quality);
-                        };
-                    })();
-                "#)?;
-                Ok(())
-            },
-            r#"
-                (() => {
-                    const originalGetImageData = CanvasRenderingContext2D.prototype.getImageData;
-                    CanvasRenderingContext2D.prototype.getImageData = function(x, y, width, height) {
-                        const imageData = originalGetImageData.call(this, x, y, width, height);
-                        
-                        // Add subtle noise to the canvas data
-                        const data = imageData.data;
-                        for (let i = 0; i < data.length; i += 4) {
-                            // Modify only a small percentage of pixels
-                            if (Math.random() < 0.01) {
-                                data[i] = data[i] ^ 1;     // Red
-                                data[i + 1] = data[i + 1] ^ 1; // Green
-                                data[i + 2] = data[i + 2] ^ 1; // Blue
-                                // Don't modify alpha
-                            }
-                        }
-                        
-                        return imageData;
-                    };
-                    
-                    const originalToDataURL = HTMLCanvasElement.prototype.toDataURL;
-                    HTMLCanvasElement.prototype.toDataURL = function(type, quality) {
-                        // For tiny canvases (used for fingerprinting), add some noise
-                        if (this.width <= 16 && this.height <= 16) {
-                            const ctx = this.getContext('2d');
-                            if (ctx) {
-                                ctx.fillStyle = `rgba(${Math.floor(Math.random() * 2)}, ${Math.floor(Math.random() * 2)}, ${Math.floor(Math.random() * 2)}, 0.01)`;
-                                ctx.fillRect(0, 0, 1, 1);
-                            }
-                        }
-                        
-                        return originalToDataURL.call(this, type, quality);
-                    };
-                })();
-            "#
-        ));
+        manager.register(canvas_protection(NoiseSeed::random()));
         
         // WebGL fingerprinting protection
         manager.register(EvasionTechnique::with_js(
@@ -709,6 +644,24 @@ impl EvasionManager {
     }
 }
 
+/// Create the canvas protection evasion adding `seed`'s noise to canvas and
+/// WebGL readback
+fn canvas_protection(seed: NoiseSeed) -> EvasionTechnique {
+    let js = seed.to_js();
+    let script = js.clone();
+    
+    EvasionTechnique::with_js(
+        "canvas_protection",
+        "Protect against canvas fingerprinting",
+        30,
+        move |target| {
+            target.execute_script(&script)?;
+            Ok(())
+        },
+        &js,
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -858,4 +811,21 @@ mod tests {
         assert!(target.executed_scripts.iter().any(|s| s.contains("CanvasRenderingContext2D")));
         assert!(target.executed_scripts.iter().any(|s| s.contains("WebGLRenderingContext")));
     }
+    
+    #[test]
+    fn test_set_noise_seed() {
+        let mut manager = EvasionManager::standard_evasions();
+        manager.disable("canvas_protection").unwrap();
+        manager.set_noise_seed(NoiseSeed::new(11, 22));
+        
+        let evasion = manager.get("canvas_protection").unwrap();
+        assert!(!evasion.is_enabled());
+        assert_eq!(evasion.priority(), 30);
+        assert_eq!(evasion.js_code().unwrap(), NoiseSeed::new(11, 22).to_js());
+        
+        manager.enable("canvas_protection").unwrap();
+        let mut target = MockStealthTarget::new();
+        manager.apply_all(&mut target).unwrap();
+        assert!(target.executed_scripts.contains(&NoiseSeed::new(11, 22).to_js()));
+    }
 } 
\ No newline at end of file
@@ -16,6 +16,50 @@ use llama_moonlight_headers::{BrowserType, DeviceType, PlatformType};
 
 // Re-export the headers fingerprint for convenience
 pub use llama_moonlight_headers::fingerprint::BrowserFingerprint;
+use llama_moonlight_headers::fingerprint::noise_script;
+
+/// Seeds of the canvas and WebGL noise presented by one identity
+///
+/// Fresh noise on every read makes canvas hashes change across reloads,
+/// which is itself a bot signal. Seeded noise keeps the hashes stable for
+/// an identity while still differing between identities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NoiseSeed {
+    /// Seed of the 2D canvas noise
+    pub canvas: u32,
+    
+    /// Seed of the WebGL noise
+    pub webgl: u32,
+}
+
+impl NoiseSeed {
+    /// Create noise seeds from their canvas and WebGL parts
+    pub fn new(canvas: u32, webgl: u32) -> Self {
+        Self { canvas, webgl }
+    }
+    
+    /// Derive the noise seeds of the identity presenting `fingerprint`
+    pub fn from_fingerprint(fingerprint: &BrowserFingerprint) -> Self {
+        Self::new(fingerprint.canvas_noise_seed(), fingerprint.webgl_noise_seed())
+    }
+    
+    /// Derive noise seeds from a 64-bit seed
+    pub fn from_u64(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        Self::new(rng.gen(), rng.gen())
+    }
+    
+    /// Pick random noise seeds for a new identity
+    pub fn random() -> Self {
+        let mut rng = rand::thread_rng();
+        Self::new(rng.gen(), rng.gen())
+    }
+    
+    /// Get the script adding this identity's noise to canvas and WebGL readback
+    pub fn to_js(&self) -> String {
+        noise_script(self.canvas, self.webgl)
+    }
+}
 
 /// Manager for browser fingerprinting
 #[derive(Debug)]
@@ -29,6 +73,9 @@ pub struct FingerprintManager {
     /// Seed for consistent fingerprints
     seed: Option<u64>,
     
+    /// Noise seeds used while no fingerprint is active
+    noise_seed: NoiseSeed,
+    
     /// JavaScript code cache
     js_cache: HashMap<String, String>,
 }
@@ -40,6 +87,7 @@ impl FingerprintManager {
             active_fingerprint: None,
             consistent: false,
             seed: None,
+            noise_seed: NoiseSeed::random(),
             js_cache: HashMap::new(),
         }
     }
@@ -50,6 +98,7 @@ impl FingerprintManager {
             active_fingerprint: None,
             consistent: true,
             seed: Some(seed),
+            noise_seed: NoiseSeed::from_u64(seed),
             js_cache: HashMap::new(),
         }
     }
@@ -75,6 +124,16 @@ impl FingerprintManager {
         Ok(self.active_fingerprint.as_ref().unwrap())
     }
     
+    /// Get the canvas and WebGL noise seeds of the active fingerprint
+    ///
+    /// Before a fingerprint is active, the manager's own seeds are used.
+    pub fn noise_seed(&self) -> NoiseSeed {
+        self.active_fingerprint
+            .as_ref()
+            .map(NoiseSeed::from_fingerprint)
+            .unwrap_or(self.noise_seed)
+    }
+    
     /// Set a specific fingerprint
     pub fn set_fingerprint(&mut self, fingerprint: BrowserFingerprint) {
         self.active_fingerprint = Some(fingerprint);
@@ -113,34 +172,18 @@ impl FingerprintManager {
     }
     
     /// Get or create fingerprint protection JavaScript code
+    ///
+    /// Canvas and WebGL noise is seeded by [`Self::noise_seed`], so it stays
+    /// the same across page loads of one fingerprint.
     pub fn get_protection_js(&mut self) -> String {
-        let cache_key = "protection";
-        if let Some(js) = self.js_cache.get(cache_key) {
+        let noise_seed = self.noise_seed();
+        let cache_key = format!("protection:{}:{}", noise_seed.canvas, noise_seed.webgl);
+        if let Some(js) = self.js_cache.get(&cache_key) {
             return js.clone();
         }
         
-        let js = r#"
+        let js = noise_seed.to_js() + r#"
         (() => {
-            // Protect against canvas fingerprinting
-            const originalGetImageData = CanvasRenderingContext2D.prototype.getImageData;
-            CanvasRenderingContext2D.prototype.getImageData = function(x, y, width, height) {
-                const imageData = originalGetImageData.call(this, x, y, width, height);
-                
-                // Add subtle noise to the canvas data
-                const data = imageData.data;
-                for (let i = 0; i < data.length; i += 4) {
-                    // Modify only a small percentage of pixels
-                    if (Math.random() < 0.01) {
-                        data[i] = data[i] ^ 1;     // Red
-                        data[i + 1] = data[i + 1] ^ 1; // Green
-                        data[i + 2] = data[i + 2] ^ 1; // Blue
-                        // Don't modify alpha
-                    }
-                }
-                
-                return imageData;
-            };
-            
             // Protect against WebGL fingerprinting
             const getParameterProxies = {
                 WebGLRenderingContext: WebGLRenderingContext.prototype.getParameter,
@@ -223,9 +266,9 @@ impl FingerprintManager {
                 };
             }
         })();
-        "#.to_string();
+        "#;
         
-        self.js_cache.insert(cache_key.to_string(), js.clone());
+        self.js_cache.insert(cache_key, js.clone());
         js
     }
     
@@ -353,6 +396,37 @@ mod tests {
         assert!(target.executed_scripts[1].contains("CanvasRenderingContext2D"));
     }
     
+    #[test]
+    fn test_noise_seeded_per_identity() {
+        let consistent = |seed| BrowserFingerprint::consistent(
+            &BrowserType::Chrome,
+            &DeviceType::Desktop,
+            &PlatformType::Windows,
+            seed,
+        );
+        
+        // The same identity gets the same noise on every page load
+        let mut manager = FingerprintManager::new();
+        manager.set_fingerprint(consistent(7));
+        let js = manager.get_protection_js();
+        assert_eq!(js, manager.get_protection_js());
+        assert_eq!(manager.noise_seed(), NoiseSeed::from_fingerprint(&consistent(7)));
+        assert!(js.contains(&manager.noise_seed().to_js()));
+        
+        // Another identity gets different noise
+        let mut other = FingerprintManager::new();
+        other.set_fingerprint(consistent(8));
+        assert_ne!(other.noise_seed(), manager.noise_seed());
+        assert_ne!(other.get_protection_js(), js);
+        
+        // Without a fingerprint, seeded managers are stable too
+        assert_eq!(FingerprintManager::with_seed(3).noise_seed(), FingerprintManager::with_seed(3).noise_seed());
+        
+        let script = NoiseSeed::new(1, 2).to_js();
+        assert!(script.ends_with("(1, 2);"));
+        assert!(!script.contains("Math.random"));
+    }
+    
     #[test]
     fn test_domain_consistent_fingerprint() {
         // Test that fingerprints are consistent for the same domain
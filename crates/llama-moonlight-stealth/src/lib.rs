@@ -7,6 +7,8 @@
 #![forbid(unsafe_code)]
 #![warn(missing_docs)]
 
+use llama_moonlight_config::ConfigSection;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 pub mod evasion;
@@ -16,8 +18,14 @@ pub mod injection;
 pub mod intercept;
 pub mod proxy;
 pub mod detection;
+pub mod display;
 pub mod humanize;
+pub mod navigation;
+pub mod regression;
+#[cfg(feature = "pool")]
+pub mod rotation;
 pub mod timing;
+pub mod warmup;
 
 /// Result type used throughout the library
 pub type Result<T> = std::result::Result<T, Error>;
@@ -76,7 +84,8 @@ pub fn version() -> &'static str {
 }
 
 /// Configuration for stealth operations
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct StealthConfig {
     /// Whether to enable stealth mode
     pub stealth_enabled: bool,
@@ -107,6 +116,9 @@ pub struct StealthConfig {
     
     /// Whether to hide automation markers
     pub hide_automation: bool,
+    
+    /// Virtual display [`StealthClient::launch`](client::StealthClient::launch) runs browsers headful in
+    pub virtual_display: display::VirtualDisplayConfig,
 }
 
 impl Default for StealthConfig {
@@ -122,10 +134,15 @@ impl Default for StealthConfig {
             intercept_canvas: true,
             intercept_fonts: true,
             hide_automation: true,
+            virtual_display: display::VirtualDisplayConfig::default(),
         }
     }
 }
 
+impl ConfigSection for StealthConfig {
+    const SECTION: &'static str = "stealth";
+}
+
 /// Stealth capabilities for a browser session
 pub trait StealthCapabilities {
     /// Apply stealth techniques to the browser
@@ -150,6 +167,13 @@ pub trait StealthCapabilities {
 // Re-export key types for convenience
 pub use evasion::{EvasionManager, EvasionTechnique};
 pub use client::StealthClient;
-pub use fingerprint::BrowserFingerprint;
+pub use navigation::NavigationHistory;
+pub use timing::{DiurnalPattern, DomainBudget, SessionPacer};
+pub use warmup::{Warmup, WarmupConfig, WarmupReport};
+#[cfg(feature = "pool")]
+pub use rotation::{FingerprintRotation, StealthIdentity};
+pub use fingerprint::{BrowserFingerprint, NoiseSeed};
 pub use detection::DetectionTest;
-pub use proxy::ProxyConfig; 
\ No newline at end of file
+pub use regression::{DetectorCorpus, RegressionReport, RegressionRunner};
+pub use display::{DisplayServer, HeadfulBrowser, VirtualDisplay, VirtualDisplayConfig};
+pub use proxy::{ProxyConfig, ProxyFailure, ProxyFeedback, ProxyManager}; 
\ No newline at end of file
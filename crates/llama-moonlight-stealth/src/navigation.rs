@@ -0,0 +1,247 @@
+//! Navigation history for consistent referers
+//!
+//! This module tracks the pages each identity has visited so that the
+//! `Referer` and `Sec-Fetch-Site` headers of a request match the page a real
+//! browser would have navigated from, instead of a random referer.
+
+use std::collections::{HashMap, VecDeque};
+use url::Url;
+
+/// Default number of pages remembered per identity
+pub const DEFAULT_HISTORY_LIMIT: usize = 50;
+
+/// Second-level labels under which country-code domains are registered
+const SECOND_LEVEL_LABELS: &[&str] = &["co", "com", "net", "org", "gov", "edu", "ac"];
+
+/// How a request relates to the page it was made from, as in `Sec-Fetch-Site`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchSite {
+    /// Typed or bookmarked navigation without a prior page
+    None,
+    
+    /// Navigation within the same origin
+    SameOrigin,
+    
+    /// Navigation to another origin of the same registrable domain
+    SameSite,
+    
+    /// Navigation from another site
+    CrossSite,
+}
+
+impl FetchSite {
+    /// Get the `Sec-Fetch-Site` header value
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FetchSite::None => "none",
+            FetchSite::SameOrigin => "same-origin",
+            FetchSite::SameSite => "same-site",
+            FetchSite::CrossSite => "cross-site",
+        }
+    }
+    
+    /// Classify a navigation from one URL to another
+    pub fn between(from: &Url, to: &Url) -> Self {
+        if from.origin() == to.origin() {
+            return FetchSite::SameOrigin;
+        }
+        
+        match (from.host_str().map(registrable_domain), to.host_str().map(registrable_domain)) {
+            (Some(a), Some(b)) if a == b && from.scheme() == to.scheme() => FetchSite::SameSite,
+            _ => FetchSite::CrossSite,
+        }
+    }
+}
+
+/// Pages visited by each identity
+#[derive(Debug, Clone)]
+pub struct NavigationHistory {
+    /// Visited URLs per identity, oldest first
+    pages: HashMap<String, VecDeque<Url>>,
+    
+    /// Maximum number of pages remembered per identity
+    limit: usize,
+}
+
+impl NavigationHistory {
+    /// Create an empty navigation history
+    pub fn new() -> Self {
+        Self::with_limit(DEFAULT_HISTORY_LIMIT)
+    }
+    
+    /// Create an empty navigation history remembering up to `limit` pages per identity
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            pages: HashMap::new(),
+            limit: limit.max(1),
+        }
+    }
+    
+    /// Record a page visited by an identity
+    ///
+    /// URLs that cannot be parsed are ignored.
+    pub fn record(&mut self, identity: &str, url: &str) {
+        let url = match Url::parse(url) {
+            Ok(url) => url,
+            Err(_) => return,
+        };
+        
+        let pages = self.pages.entry(identity.to_string()).or_default();
+        pages.push_back(url);
+        while pages.len() > self.limit {
+            pages.pop_front();
+        }
+    }
+    
+    /// Get the last page visited by an identity
+    pub fn current_page(&self, identity: &str) -> Option<&Url> {
+        self.pages.get(identity).and_then(|pages| pages.back())
+    }
+    
+    /// Get the pages visited by an identity, oldest first
+    pub fn pages(&self, identity: &str) -> Vec<&Url> {
+        self.pages.get(identity).map(|pages| pages.iter().collect()).unwrap_or_default()
+    }
+    
+    /// Forget the pages visited by an identity
+    pub fn clear(&mut self, identity: &str) {
+        self.pages.remove(identity);
+    }
+    
+    /// Get the referer of a navigation to `url` by an identity
+    ///
+    /// Applies the browsers' default `strict-origin-when-cross-origin` policy:
+    /// the full URL within an origin, only the origin across origins, and no
+    /// referer from HTTPS to HTTP. Returns `None` without a prior page.
+    pub fn referer(&self, identity: &str, url: &str) -> Option<String> {
+        let from = self.current_page(identity)?;
+        let to = Url::parse(url).ok()?;
+        
+        if from.scheme() == "https" && to.scheme() != "https" {
+            return None;
+        }
+        
+        if from.origin() == to.origin() {
+            let mut referer = from.clone();
+            referer.set_fragment(None);
+            let _ = referer.set_username("");
+            let _ = referer.set_password(None);
+            Some(referer.to_string())
+        } else {
+            Some(format!("{}/", from.origin().ascii_serialization()))
+        }
+    }
+    
+    /// Get the `Sec-Fetch-Site` of a navigation to `url` by an identity
+    pub fn fetch_site(&self, identity: &str, url: &str) -> FetchSite {
+        match (self.current_page(identity), Url::parse(url)) {
+            (Some(from), Ok(to)) => FetchSite::between(from, &to),
+            _ => FetchSite::None,
+        }
+    }
+    
+    /// Make the navigation headers of a request consistent with the history
+    ///
+    /// With a prior page, `Referer` and `Sec-Fetch-Site` are derived from it.
+    /// On the first request of an identity the generated referer is kept as
+    /// the external entry point, and `Sec-Fetch-Site` is made to agree with it.
+    pub fn apply(&self, identity: &str, url: &str, headers: &mut HashMap<String, String>) {
+        let fetch_site = if self.current_page(identity).is_some() {
+            match self.referer(identity, url) {
+                Some(referer) => headers.insert("Referer".to_string(), referer),
+                None => headers.remove("Referer"),
+            };
+            self.fetch_site(identity, url)
+        } else {
+            match (headers.get("Referer").and_then(|r| Url::parse(r).ok()), Url::parse(url)) {
+                (Some(from), Ok(to)) => FetchSite::between(&from, &to),
+                _ => FetchSite::None,
+            }
+        };
+        
+        // Only browsers that send Sec-Fetch-* headers get the updated value
+        if let Some(site) = headers.get_mut("Sec-Fetch-Site") {
+            *site = fetch_site.as_str().to_string();
+        }
+    }
+}
+
+impl Default for NavigationHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Get the registrable domain of a host, e.g. `example.co.uk` for `www.example.co.uk`
+pub(crate) fn registrable_domain(host: &str) -> String {
+    let labels: Vec<&str> = host.trim_end_matches('.').split('.').collect();
+    if labels.len() <= 2 || host.parse::<std::net::IpAddr>().is_ok() {
+        return host.to_string();
+    }
+    
+    let tld = labels[labels.len() - 1];
+    let sld = labels[labels.len() - 2];
+    let keep = if tld.len() == 2 && SECOND_LEVEL_LABELS.contains(&sld) { 3 } else { 2 };
+    
+    labels[labels.len().saturating_sub(keep)..].join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_referer_chain() {
+        let mut history = NavigationHistory::new();
+        
+        // First request keeps the generated search referer
+        let mut headers = HashMap::new();
+        headers.insert("Referer".to_string(), "https://www.google.com/search?q=example".to_string());
+        headers.insert("Sec-Fetch-Site".to_string(), "none".to_string());
+        history.apply("a", "https://www.example.com/", &mut headers);
+        assert_eq!(headers["Referer"], "https://www.google.com/search?q=example");
+        assert_eq!(headers["Sec-Fetch-Site"], "cross-site");
+        history.record("a", "https://www.example.com/");
+        
+        // Internal navigation refers to the prior page
+        history.apply("a", "https://www.example.com/products?page=2", &mut headers);
+        assert_eq!(headers["Referer"], "https://www.example.com/");
+        assert_eq!(headers["Sec-Fetch-Site"], "same-origin");
+        history.record("a", "https://www.example.com/products?page=2#top");
+        
+        // Other subdomains get the origin only
+        history.apply("a", "https://shop.example.com/cart", &mut headers);
+        assert_eq!(headers["Referer"], "https://www.example.com/");
+        assert_eq!(headers["Sec-Fetch-Site"], "same-site");
+        
+        assert_eq!(history.referer("a", "https://www.example.com/about").unwrap(), "https://www.example.com/products?page=2");
+        assert_eq!(history.fetch_site("a", "https://other.org/"), FetchSite::CrossSite);
+        assert_eq!(history.referer("a", "http://www.example.com/"), None);
+        
+        // Identities have separate histories
+        assert_eq!(history.fetch_site("b", "https://www.example.com/"), FetchSite::None);
+    }
+    
+    #[test]
+    fn test_history_limit() {
+        let mut history = NavigationHistory::with_limit(2);
+        for page in 1..=3 {
+            history.record("a", &format!("https://example.com/{}", page));
+        }
+        history.record("a", "not a url");
+        
+        let pages: Vec<String> = history.pages("a").iter().map(|u| u.to_string()).collect();
+        assert_eq!(pages, vec!["https://example.com/2", "https://example.com/3"]);
+        
+        history.clear("a");
+        assert!(history.current_page("a").is_none());
+    }
+    
+    #[test]
+    fn test_registrable_domain() {
+        assert_eq!(registrable_domain("www.example.com"), "example.com");
+        assert_eq!(registrable_domain("a.b.example.co.uk"), "example.co.uk");
+        assert_eq!(registrable_domain("example.com"), "example.com");
+        assert_eq!(registrable_domain("127.0.0.1"), "127.0.0.1");
+    }
+}
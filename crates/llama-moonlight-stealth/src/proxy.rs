@@ -6,6 +6,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use url::Url;
 use rand::seq::SliceRandom;
@@ -259,6 +260,34 @@ impl std::fmt::Display for ProxyConfig {
     }
 }
 
+/// Why a request through a proxy failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyFailure {
+    /// The target blocked the request, e.g. with a 403 or a ban page
+    Blocked,
+    
+    /// The target answered with a CAPTCHA challenge
+    Captcha,
+    
+    /// The proxy could not be reached or dropped the connection
+    Connection,
+    
+    /// The request failed for another or an unknown reason
+    Other,
+}
+
+/// Receiver of the outcomes recorded by a [`ProxyManager`]
+///
+/// Lets the source the proxies came from, such as a proxy database, learn
+/// how they fare without copying statistics back by hand.
+pub trait ProxyFeedback: Send + Sync + std::fmt::Debug {
+    /// Called when a request through a proxy succeeded
+    fn on_success(&self, proxy: &ProxyConfig, domain: Option<&str>, response_time_ms: Option<u64>);
+    
+    /// Called when a request through a proxy failed
+    fn on_failure(&self, proxy: &ProxyConfig, domain: Option<&str>, failure: ProxyFailure);
+}
+
 /// Manager for proxy rotation
 #[derive(Debug)]
 pub struct ProxyManager {
@@ -276,6 +305,12 @@ pub struct ProxyManager {
     
     /// Minimum success rate to keep a proxy
     min_success_rate: f64,
+    
+    /// Domain requests currently go to
+    domain: Option<String>,
+    
+    /// Receiver of the recorded outcomes
+    feedback: Option<Arc<dyn ProxyFeedback>>,
 }
 
 /// Strategy for rotating proxies
@@ -306,6 +341,8 @@ impl ProxyManager {
             rotation_strategy: RotationStrategy::RoundRobin,
             max_failures: 5,
             min_success_rate: 0.7,
+            domain: None,
+            feedback: None,
         }
     }
     
@@ -317,6 +354,8 @@ impl ProxyManager {
             rotation_strategy: strategy,
             max_failures: 5,
             min_success_rate: 0.7,
+            domain: None,
+            feedback: None,
         }
     }
     
@@ -361,6 +400,27 @@ impl ProxyManager {
         self
     }
     
+    /// Set the receiver of the outcomes recorded with the proxies
+    pub fn with_feedback(mut self, feedback: Arc<dyn ProxyFeedback>) -> Self {
+        self.feedback = Some(feedback);
+        self
+    }
+    
+    /// Set the domain requests currently go to, passed on with the outcomes
+    pub fn set_domain(&mut self, domain: Option<&str>) {
+        self.domain = domain.map(str::to_string);
+    }
+    
+    /// Get the domain requests currently go to
+    pub fn domain(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
+    
+    /// Get the proxies of the manager
+    pub fn proxies(&self) -> &[ProxyConfig] {
+        &self.proxies
+    }
+    
     /// Get the current active proxy
     pub fn active_proxy(&self) -> Option<&ProxyConfig> {
         self.active_proxy.and_then(|idx| self.proxies.get(idx))
@@ -435,15 +495,26 @@ impl ProxyManager {
         if let Some(idx) = self.active_proxy {
             if let Some(proxy) = self.proxies.get_mut(idx) {
                 proxy.record_success(response_time_ms);
+                if let Some(feedback) = &self.feedback {
+                    feedback.on_success(proxy, self.domain.as_deref(), response_time_ms);
+                }
             }
         }
     }
     
-    /// Record a failed use of the current proxy
+    /// Record a failed use of the current proxy, for an unknown reason
     pub fn record_failure(&mut self) {
+        self.record_failure_kind(ProxyFailure::Other);
+    }
+    
+    /// Record a failed use of the current proxy and why it failed
+    pub fn record_failure_kind(&mut self, failure: ProxyFailure) {
         if let Some(idx) = self.active_proxy {
             if let Some(proxy) = self.proxies.get_mut(idx) {
                 proxy.record_failure();
+                if let Some(feedback) = &self.feedback {
+                    feedback.on_failure(proxy, self.domain.as_deref(), failure);
+                }
             }
         }
     }
@@ -594,4 +665,40 @@ mod tests {
         assert_eq!(manager.proxies[0].host, "proxy1.example.com");
         assert_eq!(manager.proxies[1].host, "proxy3.example.com");
     }
+    
+    #[derive(Debug, Default)]
+    struct RecordingFeedback {
+        outcomes: std::sync::Mutex<Vec<(String, Option<String>, Option<ProxyFailure>)>>,
+    }
+    
+    impl ProxyFeedback for RecordingFeedback {
+        fn on_success(&self, proxy: &ProxyConfig, domain: Option<&str>, _response_time_ms: Option<u64>) {
+            self.outcomes.lock().unwrap().push((proxy.host.clone(), domain.map(String::from), None));
+        }
+        
+        fn on_failure(&self, proxy: &ProxyConfig, domain: Option<&str>, failure: ProxyFailure) {
+            self.outcomes.lock().unwrap().push((proxy.host.clone(), domain.map(String::from), Some(failure)));
+        }
+    }
+    
+    #[test]
+    fn test_proxy_manager_feedback() {
+        let feedback = Arc::new(RecordingFeedback::default());
+        let mut manager = ProxyManager::new().with_feedback(feedback.clone());
+        manager.add_proxy(ProxyConfig::new(ProxyProtocol::Http, "proxy1.example.com", 8080));
+        
+        manager.record_success(Some(100));
+        manager.set_domain(Some("example.com"));
+        manager.record_failure();
+        manager.record_failure_kind(ProxyFailure::Captcha);
+        
+        assert_eq!(
+            *feedback.outcomes.lock().unwrap(),
+            vec![
+                ("proxy1.example.com".to_string(), None, None),
+                ("proxy1.example.com".to_string(), Some("example.com".to_string()), Some(ProxyFailure::Other)),
+                ("proxy1.example.com".to_string(), Some("example.com".to_string()), Some(ProxyFailure::Captcha)),
+            ]
+        );
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,532 @@
+//! Evasion regression runner
+//!
+//! Runs a corpus of detector snippets against a stealth browser and reports
+//! which evasions hold up, so that changes to the evasions can be checked
+//! against every detector known to the user before they ship.
+//!
+//! The corpus is a directory of `.js` files, one detector per file, named
+//! after the file. Each snippet is the body of a function returning its
+//! verdict: `true` or `{ "detected": true, ... }` when it spotted
+//! automation, `false` or `{ "detected": false, ... }` otherwise. Leading
+//! comments may name the evasions the detector targets and describe it:
+//!
+//! ```js
+//! // @evasions: webdriver_disable
+//! // @description: navigator.webdriver must not be true
+//! return { detected: navigator.webdriver === true, value: navigator.webdriver };
+//! ```
+//!
+//! Snippets may `await`, e.g. to query `navigator.permissions`.
+//! The `evasion_regression` example runs a corpus in Chromium from CI.
+//!
+//! Reports serialize to JSON for CI, and can be appended to a history file
+//! to find the detectors that started to catch the browser since an earlier
+//! run.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::evasion::{EvasionManager, StealthTarget};
+use crate::{Error, Result};
+
+/// A detector snippet of the corpus
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectorSnippet {
+    /// Identifier of the snippet, the file name without extension
+    pub id: String,
+    
+    /// Evasions the detector targets, from the `@evasions` header
+    pub evasions: Vec<String>,
+    
+    /// Description of the detector, from the `@description` header
+    pub description: Option<String>,
+    
+    /// JavaScript function body returning the verdict
+    pub source: String,
+}
+
+impl DetectorSnippet {
+    /// Parse a snippet and its header comments
+    pub fn parse(id: &str, source: &str) -> Self {
+        let mut evasions = Vec::new();
+        let mut description = None;
+        
+        let headers = source.lines()
+            .map(str::trim)
+            .take_while(|line| line.is_empty() || line.starts_with("//"))
+            .filter_map(|line| line.strip_prefix("//"))
+            .filter_map(|comment| comment.trim().strip_prefix('@'));
+        for header in headers {
+            let (key, value) = header.split_once(':').unwrap_or((header, ""));
+            match key.trim() {
+                "evasions" => evasions.extend(
+                    value.split(',').map(str::trim).filter(|e| !e.is_empty()).map(String::from),
+                ),
+                "description" => description = Some(value.trim().to_string()),
+                _ => {},
+            }
+        }
+        
+        Self {
+            id: id.to_string(),
+            evasions,
+            description,
+            source: source.to_string(),
+        }
+    }
+    
+    /// Get the script executed in the browser, returning the verdict as JSON
+    ///
+    /// The snippet runs as an async function, so it may `await` or return a
+    /// Promise; the script then evaluates to a Promise of the verdict, which
+    /// targets must wait for (e.g. CDP's `awaitPromise`).
+    pub fn script(&self) -> String {
+        format!("(async () => {{ const verdict = await (async () => {{\n{}\n}})(); return JSON.stringify(verdict); }})()", self.source)
+    }
+}
+
+/// Collection of detector snippets
+#[derive(Debug, Clone, Default)]
+pub struct DetectorCorpus {
+    /// Snippets, sorted by identifier
+    snippets: Vec<DetectorSnippet>,
+}
+
+impl DetectorCorpus {
+    /// Load the `.js` files of a directory
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir.as_ref())?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "js"))
+            .collect();
+        paths.sort();
+        
+        let mut snippets = Vec::with_capacity(paths.len());
+        for path in paths {
+            let id = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+            snippets.push(DetectorSnippet::parse(&id, &fs::read_to_string(&path)?));
+        }
+        
+        Ok(Self::from_snippets(snippets))
+    }
+    
+    /// Create a corpus from snippets
+    pub fn from_snippets(mut snippets: Vec<DetectorSnippet>) -> Self {
+        snippets.sort_by(|a, b| a.id.cmp(&b.id));
+        Self { snippets }
+    }
+    
+    /// Get the snippets
+    pub fn snippets(&self) -> &[DetectorSnippet] {
+        &self.snippets
+    }
+    
+    /// Get the number of snippets
+    pub fn len(&self) -> usize {
+        self.snippets.len()
+    }
+    
+    /// Check if the corpus has no snippets
+    pub fn is_empty(&self) -> bool {
+        self.snippets.is_empty()
+    }
+}
+
+/// Outcome of a detector snippet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnippetStatus {
+    /// The detector did not spot automation
+    Passed,
+    
+    /// The detector spotted automation
+    Detected,
+    
+    /// The evasions or the snippet failed to run, or the verdict was invalid
+    Error,
+}
+
+/// Result of a detector snippet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnippetResult {
+    /// Identifier of the snippet
+    pub id: String,
+    
+    /// Outcome of the snippet
+    pub status: SnippetStatus,
+    
+    /// Evasions the detector targets
+    pub evasions: Vec<String>,
+    
+    /// Verdict returned by the snippet, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verdict: Option<serde_json::Value>,
+    
+    /// Error message, if the snippet could not be evaluated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    
+    /// Time taken by the snippet in milliseconds
+    pub duration_ms: u64,
+}
+
+/// Results of the detectors targeting an evasion
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvasionSummary {
+    /// Whether the evasion was enabled during the run
+    pub enabled: bool,
+    
+    /// Identifiers of the detectors the evasion passed
+    pub passed: Vec<String>,
+    
+    /// Identifiers of the detectors that spotted automation or failed
+    pub failed: Vec<String>,
+}
+
+/// Report of a regression run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegressionReport {
+    /// Version of the crate the evasions come from
+    pub version: String,
+    
+    /// Time of the run, in RFC 3339 format
+    pub timestamp: String,
+    
+    /// Evasions enabled during the run
+    pub enabled_evasions: Vec<String>,
+    
+    /// Results by snippet
+    pub results: Vec<SnippetResult>,
+    
+    /// Results by evasion named in the snippet headers
+    pub evasions: BTreeMap<String, EvasionSummary>,
+}
+
+impl RegressionReport {
+    /// Get the result of a snippet
+    pub fn result(&self, id: &str) -> Option<&SnippetResult> {
+        self.results.iter().find(|r| r.id == id)
+    }
+    
+    /// Get the number of snippets that passed
+    pub fn passed_count(&self) -> usize {
+        self.results.iter().filter(|r| r.status == SnippetStatus::Passed).count()
+    }
+    
+    /// Get the number of snippets that spotted automation or failed
+    pub fn failed_count(&self) -> usize {
+        self.results.len() - self.passed_count()
+    }
+    
+    /// Check if every snippet passed
+    pub fn all_passed(&self) -> bool {
+        self.failed_count() == 0
+    }
+    
+    /// Get the snippets that passed in an earlier report but not in this one
+    pub fn regressions_since(&self, previous: &RegressionReport) -> Vec<String> {
+        self.results.iter()
+            .filter(|r| r.status != SnippetStatus::Passed)
+            .filter(|r| previous.result(&r.id).is_some_and(|p| p.status == SnippetStatus::Passed))
+            .map(|r| r.id.clone())
+            .collect()
+    }
+    
+    /// Get the snippets that failed in an earlier report and pass in this one
+    pub fn fixed_since(&self, previous: &RegressionReport) -> Vec<String> {
+        self.results.iter()
+            .filter(|r| r.status == SnippetStatus::Passed)
+            .filter(|r| previous.result(&r.id).is_some_and(|p| p.status != SnippetStatus::Passed))
+            .map(|r| r.id.clone())
+            .collect()
+    }
+    
+    /// Serialize the report to pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+    
+    /// Append the report to a history file of one JSON report per line
+    pub fn append_to_history(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", serde_json::to_string(self)?)?;
+        Ok(())
+    }
+    
+    /// Load the reports of a history file, oldest first
+    ///
+    /// A missing file is an empty history.
+    pub fn load_history(path: impl AsRef<Path>) -> Result<Vec<RegressionReport>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(Error::from))
+            .collect()
+    }
+}
+
+/// Runner of a detector corpus against stealth browsers
+#[derive(Debug)]
+pub struct RegressionRunner {
+    /// Detector snippets to run
+    corpus: DetectorCorpus,
+    
+    /// Evasions applied before each snippet
+    evasions: EvasionManager,
+}
+
+impl RegressionRunner {
+    /// Create a runner applying the standard evasions
+    pub fn new(corpus: DetectorCorpus) -> Self {
+        Self::with_evasions(corpus, EvasionManager::standard_evasions())
+    }
+    
+    /// Create a runner applying the given evasions
+    pub fn with_evasions(corpus: DetectorCorpus, evasions: EvasionManager) -> Self {
+        Self { corpus, evasions }
+    }
+    
+    /// Get the corpus
+    pub fn corpus(&self) -> &DetectorCorpus {
+        &self.corpus
+    }
+    
+    /// Run every snippet in a fresh browser with the evasions applied
+    ///
+    /// `new_target` opens the browser for each snippet, so that snippets do
+    /// not see the side effects of earlier ones. Snippets that cannot be
+    /// evaluated are reported as errors instead of aborting the run.
+    pub fn run<T, F>(&self, mut new_target: F) -> Result<RegressionReport>
+    where
+        T: StealthTarget,
+        F: FnMut() -> Result<T>,
+    {
+        let enabled = self.evasions.list_enabled();
+        let mut results = Vec::with_capacity(self.corpus.len());
+        
+        for snippet in self.corpus.snippets() {
+            let mut target = new_target()?;
+            let start = Instant::now();
+            let outcome = self.evasions.apply_all(&mut target)
+                .and_then(|()| target.execute_script(&snippet.script()));
+            let duration_ms = start.elapsed().as_millis() as u64;
+            
+            let (status, verdict, error) = match outcome {
+                Ok(raw) => match parse_verdict(&raw) {
+                    Ok((detected, verdict)) => {
+                        let status = if detected { SnippetStatus::Detected } else { SnippetStatus::Passed };
+                        (status, Some(verdict), None)
+                    },
+                    Err(e) => (SnippetStatus::Error, None, Some(e)),
+                },
+                Err(e) => (SnippetStatus::Error, None, Some(e.to_string())),
+            };
+            
+            results.push(SnippetResult {
+                id: snippet.id.clone(),
+                status,
+                evasions: snippet.evasions.clone(),
+                verdict,
+                error,
+                duration_ms,
+            });
+        }
+        
+        let mut evasions: BTreeMap<String, EvasionSummary> = BTreeMap::new();
+        for result in &results {
+            for name in &result.evasions {
+                let summary = evasions.entry(name.clone()).or_insert_with(|| EvasionSummary {
+                    enabled: enabled.contains(name),
+                    ..EvasionSummary::default()
+                });
+                match result.status {
+                    SnippetStatus::Passed => summary.passed.push(result.id.clone()),
+                    _ => summary.failed.push(result.id.clone()),
+                }
+            }
+        }
+        
+        Ok(RegressionReport {
+            version: crate::version().to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            enabled_evasions: enabled,
+            results,
+            evasions,
+        })
+    }
+}
+
+/// Parse the verdict of a snippet into whether it spotted automation
+fn parse_verdict(raw: &str) -> std::result::Result<(bool, serde_json::Value), String> {
+    let verdict: serde_json::Value = serde_json::from_str(raw)
+        .map_err(|e| format!("Invalid verdict {:?}: {}", raw, e))?;
+    
+    let detected = match &verdict {
+        serde_json::Value::Bool(detected) => Some(*detected),
+        serde_json::Value::Object(fields) => fields.get("detected").and_then(|d| d.as_bool()),
+        _ => None,
+    };
+    
+    match detected {
+        Some(detected) => Ok((detected, verdict)),
+        None => Err(format!("Verdict has no boolean `detected`: {}", raw)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evasion::{EvasionTechnique, InterceptHandler};
+    use llama_moonlight_headers::{BrowserType, DeviceType, PlatformType};
+    
+    /// Browser answering snippets by the globals the evasions set
+    #[derive(Debug, Default)]
+    struct MockBrowser {
+        webdriver_hidden: bool,
+    }
+    
+    impl StealthTarget for MockBrowser {
+        fn execute_script(&mut self, script: &str) -> Result<String> {
+            if script.contains("hide-webdriver") {
+                self.webdriver_hidden = true;
+                return Ok(String::new());
+            }
+            if script.contains("throw") {
+                return Err(Error::InjectionError("ReferenceError".to_string()));
+            }
+            if script.contains("navigator.webdriver") {
+                return Ok(format!(r#"{{"detected":{},"value":true}}"#, !self.webdriver_hidden));
+            }
+            if script.contains("navigator.plugins") {
+                return Ok("true".to_string());
+            }
+            Ok("\"maybe\"".to_string())
+        }
+        
+        fn browser_type(&self) -> BrowserType {
+            BrowserType::Chrome
+        }
+        
+        fn device_type(&self) -> DeviceType {
+            DeviceType::Desktop
+        }
+        
+        fn platform_type(&self) -> PlatformType {
+            PlatformType::Windows
+        }
+        
+        fn set_header(&mut self, _name: &str, _value: &str) -> Result<()> {
+            Ok(())
+        }
+        
+        fn get_header(&self, _name: &str) -> Option<String> {
+            None
+        }
+        
+        fn remove_header(&mut self, _name: &str) -> Result<()> {
+            Ok(())
+        }
+        
+        fn intercept_requests(&mut self, _pattern: &str, _handler: InterceptHandler) -> Result<()> {
+            Ok(())
+        }
+        
+        fn set_cookie(&mut self, _name: &str, _value: &str, _domain: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+    
+    fn corpus() -> DetectorCorpus {
+        DetectorCorpus::from_snippets(vec![
+            DetectorSnippet::parse("webdriver", "// @evasions: webdriver_disable\n// @description: webdriver flag\nreturn { detected: navigator.webdriver === true };"),
+            DetectorSnippet::parse("plugins", "// @evasions: plugins_spoof, webdriver_disable\nreturn navigator.plugins.length === 0;"),
+            DetectorSnippet::parse("broken", "throw new Error('boom');"),
+            DetectorSnippet::parse("unclear", "return 'maybe';"),
+        ])
+    }
+    
+    fn evasions() -> EvasionManager {
+        let mut manager = EvasionManager::new();
+        manager.register(EvasionTechnique::new("webdriver_disable", "Hide webdriver", 10, |target| {
+            target.execute_script("hide-webdriver")?;
+            Ok(())
+        }));
+        manager
+    }
+    
+    #[test]
+    fn test_parse_snippet_headers() {
+        let snippet = &corpus().snippets()[3];
+        assert_eq!(snippet.id, "webdriver");
+        assert_eq!(snippet.evasions, vec!["webdriver_disable"]);
+        assert_eq!(snippet.description.as_deref(), Some("webdriver flag"));
+        assert!(snippet.script().contains("JSON.stringify(verdict)"));
+        assert!(snippet.script().contains("await (async () =>"));
+        
+        let snippet = DetectorSnippet::parse("x", "return false;\n// @evasions: late");
+        assert!(snippet.evasions.is_empty());
+    }
+    
+    #[test]
+    fn test_run_reports_evasions_and_regressions() {
+        let runner = RegressionRunner::with_evasions(corpus(), evasions());
+        let report = runner.run(|| Ok(MockBrowser::default())).unwrap();
+        
+        let status = |id: &str| report.result(id).unwrap().status;
+        assert_eq!(status("webdriver"), SnippetStatus::Passed);
+        assert_eq!(status("plugins"), SnippetStatus::Detected);
+        assert_eq!(status("broken"), SnippetStatus::Error);
+        assert_eq!(status("unclear"), SnippetStatus::Error);
+        assert_eq!((report.passed_count(), report.failed_count()), (1, 3));
+        
+        let webdriver = &report.evasions["webdriver_disable"];
+        assert!(webdriver.enabled);
+        assert_eq!(webdriver.passed, vec!["webdriver"]);
+        assert_eq!(webdriver.failed, vec!["plugins"]);
+        assert!(!report.evasions["plugins_spoof"].enabled);
+        
+        // Disabling the evasion makes its detector regress
+        let unprotected = RegressionRunner::with_evasions(corpus(), EvasionManager::new())
+            .run(|| Ok(MockBrowser::default()))
+            .unwrap();
+        assert_eq!(unprotected.regressions_since(&report), vec!["webdriver"]);
+        assert_eq!(report.fixed_since(&unprotected), vec!["webdriver"]);
+        
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["results"][3]["status"], "passed");
+    }
+    
+    #[test]
+    fn test_corpus_and_history_files() {
+        let dir = std::env::temp_dir().join(format!("stealth-corpus-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("b_webdriver.js"), "// @evasions: webdriver_disable\nreturn navigator.webdriver;").unwrap();
+        fs::write(dir.join("a_plugins.js"), "return navigator.plugins.length === 0;").unwrap();
+        fs::write(dir.join("notes.txt"), "not a snippet").unwrap();
+        
+        let corpus = DetectorCorpus::load(&dir).unwrap();
+        let ids: Vec<&str> = corpus.snippets().iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["a_plugins", "b_webdriver"]);
+        
+        let history = dir.join("history.jsonl");
+        assert!(RegressionReport::load_history(&history).unwrap().is_empty());
+        
+        let report = RegressionRunner::new(corpus).run(|| Ok(MockBrowser::default())).unwrap();
+        report.append_to_history(&history).unwrap();
+        report.append_to_history(&history).unwrap();
+        let loaded = RegressionReport::load_history(&history).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[1], report);
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
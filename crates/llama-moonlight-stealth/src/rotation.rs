@@ -0,0 +1,144 @@
+//! Fingerprint rotation for pooled browsers
+//!
+//! Browsers in a [`BrowserPool`] are reused across claims. Installing a
+//! [`FingerprintRotation`] on the pool gives every claim a freshly generated
+//! [`BrowserFingerprint`], applied to the contexts and pages opened during
+//! the claim, so logically distinct sessions sharing a browser do not share
+//! a stable fingerprint.
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use llama_moonlight_core::options::{ContextOptions, Viewport};
+use llama_moonlight_core::Page;
+use llama_moonlight_headers::{BrowserType, DeviceType, PlatformType};
+use llama_moonlight_pool::{BrowserPool, ContextIdentity, IdentityProvider};
+use tracing::debug;
+
+use crate::fingerprint::{BrowserFingerprint, NoiseSeed};
+
+/// Provides a fresh fingerprint and header identity for each claim of a
+/// pooled browser
+#[derive(Debug, Clone, Default)]
+pub struct FingerprintRotation {
+    /// Browser the fingerprints imitate
+    browser_type: BrowserType,
+    
+    /// Device the fingerprints imitate
+    device_type: DeviceType,
+    
+    /// Platform the fingerprints imitate
+    platform_type: PlatformType,
+}
+
+impl FingerprintRotation {
+    /// Create a rotation of fingerprints of the default browser, device and platform
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Set the browser the fingerprints imitate
+    pub fn with_browser_type(mut self, browser_type: BrowserType) -> Self {
+        self.browser_type = browser_type;
+        self
+    }
+    
+    /// Set the device the fingerprints imitate
+    pub fn with_device_type(mut self, device_type: DeviceType) -> Self {
+        self.device_type = device_type;
+        self
+    }
+    
+    /// Set the platform the fingerprints imitate
+    pub fn with_platform_type(mut self, platform_type: PlatformType) -> Self {
+        self.platform_type = platform_type;
+        self
+    }
+    
+    /// Give every later claim of a browser from `pool` a fresh identity
+    pub fn install(self, pool: &BrowserPool) {
+        pool.set_identity_provider(self);
+    }
+}
+
+impl IdentityProvider for FingerprintRotation {
+    fn new_identity(&self) -> Arc<dyn ContextIdentity> {
+        let fingerprint = BrowserFingerprint::new(&self.browser_type, &self.device_type, &self.platform_type);
+        debug!("Rotated to fingerprint with user agent {}", fingerprint.user_agent);
+        Arc::new(StealthIdentity::new(fingerprint))
+    }
+}
+
+/// Fingerprint and header identity of one claim of a pooled browser
+#[derive(Debug, Clone)]
+pub struct StealthIdentity {
+    /// Fingerprint presented by the claim's pages
+    fingerprint: BrowserFingerprint,
+}
+
+impl StealthIdentity {
+    /// Create an identity presenting `fingerprint`
+    pub fn new(fingerprint: BrowserFingerprint) -> Self {
+        Self { fingerprint }
+    }
+    
+    /// Get the fingerprint presented by the identity
+    pub fn fingerprint(&self) -> &BrowserFingerprint {
+        &self.fingerprint
+    }
+}
+
+#[async_trait]
+impl ContextIdentity for StealthIdentity {
+    fn apply_to_options(&self, options: &mut ContextOptions) {
+        let fingerprint = &self.fingerprint;
+        
+        // The user agent and locale also set the User-Agent and Accept-Language headers
+        options.user_agent = Some(fingerprint.user_agent.clone());
+        options.locale = Some(fingerprint.language.clone());
+        options.timezone_id = Some(fingerprint.timezone.clone());
+        options.viewport = Some(Viewport {
+            width: fingerprint.available_width as i32,
+            height: fingerprint.available_height as i32,
+        });
+        options.device_scale_factor = Some(fingerprint.pixel_ratio as f64);
+        options.is_mobile = Some(fingerprint.user_agent.contains("Mobi"));
+    }
+    
+    async fn prepare_page(&self, page: &Page) -> std::result::Result<(), llama_moonlight_core::Error> {
+        page.add_init_script(&self.fingerprint.to_js()).await?;
+        
+        // Fingerprints blocking canvas reads already bring the same seeded noise
+        if self.fingerprint.canvas_blocked {
+            return Ok(());
+        }
+        
+        // Canvas noise keyed by the fingerprint stays stable across the claim's page loads
+        page.add_init_script(&NoiseSeed::from_fingerprint(&self.fingerprint).to_js()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_identity_per_claim() {
+        let rotation = FingerprintRotation::new().with_device_type(DeviceType::Desktop);
+        
+        let mut first = ContextOptions::default();
+        rotation.new_identity().apply_to_options(&mut first);
+        assert!(first.user_agent.is_some());
+        assert!(first.locale.is_some());
+        assert_eq!(first.is_mobile, Some(false));
+        
+        // Identities are generated independently, so over a few claims they differ
+        let identities: std::collections::HashSet<_> = (0..20)
+            .map(|_| {
+                let mut options = ContextOptions::default();
+                rotation.new_identity().apply_to_options(&mut options);
+                (options.user_agent, options.viewport.map(|v| (v.width, v.height)))
+            })
+            .collect();
+        assert!(identities.len() > 1);
+    }
+}
@@ -3,9 +3,15 @@
 //! This module provides capabilities for timing-based stealth operations,
 //! such as realistic delays, throttling, and timing attack prevention.
 
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
+use chrono::{FixedOffset, Timelike, Utc};
 use rand::{Rng, thread_rng};
 
+use crate::fingerprint::BrowserFingerprint;
+use crate::navigation::registrable_domain;
+
 use crate::Result;
 use crate::Error;
 
@@ -193,6 +199,298 @@ pub enum ActionType {
     Custom(u64),
 }
 
+/// Relative activity of a person over the hours of a day
+///
+/// Gaps between paced actions are divided by the activity of the current
+/// hour, so an hour at 0.25 spaces actions four times as far apart as an hour
+/// at 1.0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiurnalPattern {
+    /// Activity of each hour from midnight, in (0, 1]
+    hourly_activity: [f64; 24],
+}
+
+impl DiurnalPattern {
+    /// Lowest activity of an hour, so no gap grows without bound
+    pub const MIN_ACTIVITY: f64 = 0.05;
+    
+    /// Create a pattern from the activity of each hour from midnight
+    ///
+    /// Activities are clamped to [`MIN_ACTIVITY`](Self::MIN_ACTIVITY)..=1.
+    pub fn from_hourly(hourly_activity: [f64; 24]) -> Self {
+        Self {
+            hourly_activity: hourly_activity.map(|activity| activity.clamp(Self::MIN_ACTIVITY, 1.0)),
+        }
+    }
+    
+    /// Create a pattern with the same activity at every hour
+    pub fn flat() -> Self {
+        Self::from_hourly([1.0; 24])
+    }
+    
+    /// Create the pattern of someone browsing mostly in the day and evening,
+    /// and barely at night
+    pub fn daytime() -> Self {
+        Self::from_hourly([
+            0.15, 0.08, 0.05, 0.05, 0.05, 0.08, // 00-05
+            0.2, 0.4, 0.6, 0.75, 0.8, 0.85, // 06-11
+            0.8, 0.75, 0.8, 0.8, 0.85, 0.9, // 12-17
+            0.95, 1.0, 1.0, 0.9, 0.6, 0.3, // 18-23
+        ])
+    }
+    
+    /// Get the activity at an hour of the day (0-23)
+    pub fn activity_at(&self, hour: u32) -> f64 {
+        self.hourly_activity[(hour % 24) as usize]
+    }
+}
+
+impl Default for DiurnalPattern {
+    fn default() -> Self {
+        Self::daytime()
+    }
+}
+
+/// Maximum number of requests to a domain within a time window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DomainBudget {
+    /// Requests allowed per window
+    pub max_requests: u32,
+    
+    /// Length of the window
+    pub window: Duration,
+}
+
+impl DomainBudget {
+    /// Create a budget of `max_requests` per `window`
+    pub fn new(max_requests: u32, window: Duration) -> Self {
+        Self {
+            max_requests: max_requests.max(1),
+            window,
+        }
+    }
+    
+    /// Create a budget of `max_requests` per minute
+    pub fn per_minute(max_requests: u32) -> Self {
+        Self::new(max_requests, Duration::from_secs(60))
+    }
+    
+    /// Create a budget of `max_requests` per hour
+    pub fn per_hour(max_requests: u32) -> Self {
+        Self::new(max_requests, Duration::from_secs(3600))
+    }
+}
+
+/// State of a pacer, shared by its callers
+#[derive(Debug, Default)]
+struct PacerState {
+    /// When the latest action was scheduled
+    last_action: Option<Instant>,
+    
+    /// When the latest request to each domain was scheduled
+    last_request: HashMap<String, Instant>,
+    
+    /// When the requests within the budget window of each budget key were scheduled
+    request_times: HashMap<String, VecDeque<Instant>>,
+}
+
+/// Spaces the actions and requests of a session like a person would
+///
+/// Gaps follow the session's [`DiurnalPattern`] in the identity's time zone,
+/// growing longer at night, and requests to a domain never exceed its
+/// [`DomainBudget`]. Budgets are counted per registrable domain, so
+/// `www.example.com` and `cdn.example.com` share one, unless a subdomain
+/// has a budget of its own. The pacer only decides how long to wait, so it works for
+/// browser flows and HTTP-only clients alike; share it behind an `Arc` to pace
+/// concurrent tasks of one session together.
+#[derive(Debug)]
+pub struct SessionPacer {
+    /// Gap between actions at full activity
+    action_delay: DelayConfig,
+    
+    /// Gap between requests to a domain at full activity
+    request_delay: DelayConfig,
+    
+    /// Activity over the day
+    pattern: DiurnalPattern,
+    
+    /// Offset of the identity's time zone from UTC, in minutes
+    utc_offset_minutes: i32,
+    
+    /// Budget of domains without their own
+    default_budget: Option<DomainBudget>,
+    
+    /// Budgets by domain, also covering its subdomains
+    domain_budgets: HashMap<String, DomainBudget>,
+    
+    /// Scheduled actions and requests
+    state: Mutex<PacerState>,
+}
+
+impl SessionPacer {
+    /// Create a pacer with the daytime pattern in UTC and no budgets
+    pub fn new() -> Self {
+        Self {
+            action_delay: DelayConfig::normal(1500, 700, 400, 6000),
+            request_delay: DelayConfig::normal(8000, 4000, 2000, 30000),
+            pattern: DiurnalPattern::default(),
+            utc_offset_minutes: 0,
+            default_budget: None,
+            domain_budgets: HashMap::new(),
+            state: Mutex::new(PacerState::default()),
+        }
+    }
+    
+    /// Set the gap between actions at full activity
+    pub fn with_action_delay(mut self, delay: DelayConfig) -> Self {
+        self.action_delay = delay;
+        self
+    }
+    
+    /// Set the gap between requests to a domain at full activity
+    pub fn with_request_delay(mut self, delay: DelayConfig) -> Self {
+        self.request_delay = delay;
+        self
+    }
+    
+    /// Set the activity over the day
+    pub fn with_pattern(mut self, pattern: DiurnalPattern) -> Self {
+        self.pattern = pattern;
+        self
+    }
+    
+    /// Set the offset of the identity's time zone from UTC, in minutes
+    pub fn with_utc_offset(mut self, minutes: i32) -> Self {
+        self.utc_offset_minutes = minutes;
+        self
+    }
+    
+    /// Follow the day in the time zone of a fingerprint
+    pub fn with_timezone_of(self, fingerprint: &BrowserFingerprint) -> Self {
+        self.with_utc_offset(fingerprint.timezone_offset)
+    }
+    
+    /// Set the budget of domains without their own
+    pub fn with_default_budget(mut self, budget: DomainBudget) -> Self {
+        self.default_budget = Some(budget);
+        self
+    }
+    
+    /// Set the budget of a domain and its subdomains
+    pub fn with_domain_budget(mut self, domain: &str, budget: DomainBudget) -> Self {
+        self.domain_budgets.insert(domain.to_lowercase(), budget);
+        self
+    }
+    
+    /// Get the hour of the day in the identity's time zone
+    pub fn local_hour(&self) -> u32 {
+        let offset = FixedOffset::east_opt(self.utc_offset_minutes * 60)
+            .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+        Utc::now().with_timezone(&offset).hour()
+    }
+    
+    /// Get the budget applying to a domain
+    pub fn budget_for(&self, domain: &str) -> Option<DomainBudget> {
+        self.budget_key(&domain.to_lowercase()).1
+    }
+    
+    /// Get the domain whose requests count against the budget of a domain,
+    /// with that budget
+    ///
+    /// This is the registrable domain, or the domain of a more specific
+    /// budget covering it.
+    fn budget_key(&self, domain: &str) -> (String, Option<DomainBudget>) {
+        let site = registrable_domain(domain);
+        let own = self.domain_budgets
+            .iter()
+            .filter(|(budget_domain, _)| {
+                domain == budget_domain.as_str() || domain.ends_with(&format!(".{}", budget_domain))
+            })
+            // The most specific domain wins
+            .max_by_key(|(budget_domain, _)| budget_domain.len());
+        
+        match own {
+            Some((budget_domain, budget)) if budget_domain.len() > site.len() => (budget_domain.clone(), Some(*budget)),
+            Some((_, budget)) => (site, Some(*budget)),
+            None => (site, self.default_budget),
+        }
+    }
+    
+    /// Schedule the next action, returning how long to wait before it
+    pub fn schedule_action(&self) -> Duration {
+        self.schedule_action_at(Instant::now(), self.local_hour())
+    }
+    
+    /// Schedule the next request to a URL, returning how long to wait before it
+    ///
+    /// `url` may also be a bare domain.
+    pub fn schedule_request(&self, url: &str) -> Duration {
+        self.schedule_request_at(&domain_of(url), Instant::now(), self.local_hour())
+    }
+    
+    /// Wait until the next action is due
+    pub async fn pace_action(&self) {
+        tokio::time::sleep(self.schedule_action()).await;
+    }
+    
+    /// Wait until the next request to a URL is due
+    pub async fn pace_request(&self, url: &str) {
+        tokio::time::sleep(self.schedule_request(url)).await;
+    }
+    
+    fn schedule_action_at(&self, now: Instant, hour: u32) -> Duration {
+        let gap = self.scaled(&self.action_delay, hour);
+        let mut state = self.state.lock().unwrap();
+        
+        let due = state.last_action.map_or(now, |last| (last + gap).max(now));
+        state.last_action = Some(due);
+        due - now
+    }
+    
+    fn schedule_request_at(&self, domain: &str, now: Instant, hour: u32) -> Duration {
+        let gap = self.scaled(&self.request_delay, hour);
+        let (key, budget) = self.budget_key(domain);
+        let mut state = self.state.lock().unwrap();
+        
+        let mut due = state.last_request.get(domain).map_or(now, |last| (*last + gap).max(now));
+        
+        if let Some(budget) = budget {
+            let times = state.request_times.entry(key).or_default();
+            while times.front().is_some_and(|time| *time + budget.window <= due) {
+                times.pop_front();
+            }
+            // Wait for the oldest request in the window to leave it
+            if times.len() >= budget.max_requests as usize {
+                let oldest = times[times.len() - budget.max_requests as usize];
+                due = due.max(oldest + budget.window);
+            }
+            times.push_back(due);
+        }
+        
+        state.last_request.insert(domain.to_string(), due);
+        due - now
+    }
+    
+    /// Draw a gap from `delay`, stretched by the inactivity of the hour
+    fn scaled(&self, delay: &DelayConfig, hour: u32) -> Duration {
+        delay.random_delay().div_f64(self.pattern.activity_at(hour))
+    }
+}
+
+impl Default for SessionPacer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Get the host of a URL, or the input itself if it is not a URL
+fn domain_of(url: &str) -> String {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_lowercase))
+        .unwrap_or_else(|| url.to_lowercase())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -243,4 +541,65 @@ mod tests {
         let ms = delay.as_millis() as u64;
         assert!(ms >= 200 && ms <= 400);
     }
+    
+    #[test]
+    fn test_diurnal_pattern() {
+        let pattern = DiurnalPattern::from_hourly([2.0; 24]);
+        assert_eq!(pattern.activity_at(12), 1.0);
+        
+        let pattern = DiurnalPattern::daytime();
+        assert!(pattern.activity_at(3) < pattern.activity_at(20));
+        assert_eq!(pattern.activity_at(27), pattern.activity_at(3));
+        
+        // Night gaps are longer than evening gaps
+        let pacer = SessionPacer::new().with_action_delay(DelayConfig::uniform(1000, 1000));
+        let now = Instant::now();
+        assert_eq!(pacer.schedule_action_at(now, 20), Duration::ZERO);
+        assert_eq!(pacer.schedule_action_at(now, 20), Duration::from_millis(1000));
+        assert_eq!(pacer.schedule_action_at(now, 3), Duration::from_millis(21000));
+    }
+    
+    #[test]
+    fn test_domain_budget() {
+        let pacer = SessionPacer::new()
+            .with_pattern(DiurnalPattern::flat())
+            .with_request_delay(DelayConfig::uniform(0, 0))
+            .with_default_budget(DomainBudget::per_hour(120))
+            .with_domain_budget("example.com", DomainBudget::per_minute(2));
+        
+        assert_eq!(pacer.budget_for("www.example.com"), Some(DomainBudget::per_minute(2)));
+        assert_eq!(pacer.budget_for("notexample.com"), Some(DomainBudget::per_hour(120)));
+        assert_eq!(domain_of("https://WWW.Example.com/path"), "www.example.com");
+        
+        let now = Instant::now();
+        assert_eq!(pacer.schedule_request_at("www.example.com", now, 12), Duration::ZERO);
+        assert_eq!(pacer.schedule_request_at("www.example.com", now, 12), Duration::ZERO);
+        // The third request waits for the first to leave the window
+        assert_eq!(pacer.schedule_request_at("www.example.com", now, 12), Duration::from_secs(60));
+        assert_eq!(pacer.schedule_request_at("www.example.com", now, 12), Duration::from_secs(60));
+        assert_eq!(pacer.schedule_request_at("www.example.com", now, 12), Duration::from_secs(120));
+        
+        // Other domains have their own budget
+        assert_eq!(pacer.schedule_request_at("other.org", now, 12), Duration::ZERO);
+    }
+    
+    #[test]
+    fn test_budget_covers_registrable_domain() {
+        let pacer = SessionPacer::new()
+            .with_pattern(DiurnalPattern::flat())
+            .with_request_delay(DelayConfig::uniform(0, 0))
+            .with_default_budget(DomainBudget::per_minute(2))
+            .with_domain_budget("api.shop.co.uk", DomainBudget::per_minute(1));
+        
+        // Subdomains of a site draw from one budget
+        let now = Instant::now();
+        assert_eq!(pacer.schedule_request_at("www.example.com", now, 12), Duration::ZERO);
+        assert_eq!(pacer.schedule_request_at("cdn.example.com", now, 12), Duration::ZERO);
+        assert_eq!(pacer.schedule_request_at("img.example.com", now, 12), Duration::from_secs(60));
+        
+        // A subdomain with its own budget is counted apart from its site
+        assert_eq!(pacer.schedule_request_at("api.shop.co.uk", now, 12), Duration::ZERO);
+        assert_eq!(pacer.schedule_request_at("www.shop.co.uk", now, 12), Duration::ZERO);
+        assert_eq!(pacer.schedule_request_at("v2.api.shop.co.uk", now, 12), Duration::from_secs(60));
+    }
 } 
\ No newline at end of file
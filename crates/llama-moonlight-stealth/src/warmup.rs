@@ -0,0 +1,481 @@
+//! Warm-up routines for new identities
+//!
+//! This module ages a fresh browser profile before it touches real targets.
+//! A [`Warmup`] runs a sequence of benign browsing actions (visiting popular
+//! sites, dwelling and scrolling like a reader) and collects the cookies set
+//! along the way, so that later sessions of the identity can start from them.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use llama_moonlight_core::options::Cookie;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::Error;
+use crate::Result;
+
+/// Popular sites visited by default
+pub const DEFAULT_WARMUP_SITES: &[&str] = &[
+    "https://www.wikipedia.org/",
+    "https://www.youtube.com/",
+    "https://www.reddit.com/",
+    "https://www.amazon.com/",
+    "https://www.bbc.com/news",
+    "https://www.nytimes.com/",
+    "https://weather.com/",
+    "https://www.imdb.com/",
+    "https://www.espn.com/",
+    "https://www.allrecipes.com/",
+];
+
+/// A browsing action of a warm-up routine
+#[derive(Debug, Clone, PartialEq)]
+pub enum WarmupAction {
+    /// Navigate to a URL
+    Visit(String),
+    
+    /// Stay on the current page
+    Dwell(Duration),
+    
+    /// Scroll the current page by a number of pixels
+    Scroll(i64),
+}
+
+/// Configuration of generated warm-up routines
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WarmupConfig {
+    /// Sites to pick pages from
+    pub sites: Vec<String>,
+    
+    /// Number of pages to visit
+    pub pages: usize,
+    
+    /// Minimum dwell time on a page (milliseconds)
+    pub min_dwell_ms: u64,
+    
+    /// Maximum dwell time on a page (milliseconds)
+    pub max_dwell_ms: u64,
+    
+    /// Maximum number of scrolls per page
+    pub max_scrolls: u32,
+    
+    /// Whether to abort the routine on the first failed action
+    pub stop_on_error: bool,
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        Self {
+            sites: DEFAULT_WARMUP_SITES.iter().map(|s| s.to_string()).collect(),
+            pages: 5,
+            min_dwell_ms: 2000,
+            max_dwell_ms: 8000,
+            max_scrolls: 4,
+            stop_on_error: false,
+        }
+    }
+}
+
+impl WarmupConfig {
+    /// Generate a randomized sequence of actions
+    ///
+    /// Each page is visited once per pass over the sites, in random order, and
+    /// followed by a dwell and a few scrolls.
+    pub fn plan(&self) -> Vec<WarmupAction> {
+        let mut rng = thread_rng();
+        let mut actions = Vec::new();
+        
+        if self.sites.is_empty() {
+            return actions;
+        }
+        
+        let mut sites = Vec::new();
+        while sites.len() < self.pages {
+            let mut pass = self.sites.clone();
+            pass.shuffle(&mut rng);
+            sites.extend(pass);
+        }
+        
+        for site in sites.into_iter().take(self.pages) {
+            actions.push(WarmupAction::Visit(site));
+            actions.push(WarmupAction::Dwell(self.random_dwell(&mut rng)));
+            
+            for _ in 0..rng.gen_range(0..=self.max_scrolls) {
+                actions.push(WarmupAction::Scroll(rng.gen_range(200..900)));
+                actions.push(WarmupAction::Dwell(self.random_dwell(&mut rng) / 2));
+            }
+        }
+        
+        actions
+    }
+    
+    /// Get a random dwell time within the configured range
+    fn random_dwell(&self, rng: &mut impl Rng) -> Duration {
+        let max_dwell_ms = self.max_dwell_ms.max(self.min_dwell_ms);
+        Duration::from_millis(rng.gen_range(self.min_dwell_ms..=max_dwell_ms))
+    }
+}
+
+/// A browser page that a warm-up routine can drive
+#[async_trait]
+pub trait WarmupBrowser: Send {
+    /// Navigate to a URL
+    async fn visit(&mut self, url: &str) -> Result<()>;
+    
+    /// Scroll the current page by a number of pixels
+    async fn scroll(&mut self, pixels: i64) -> Result<()>;
+    
+    /// Get the cookies of the current page, including HTTP-only ones
+    async fn cookies(&mut self) -> Result<Vec<Cookie>>;
+}
+
+#[async_trait]
+impl WarmupBrowser for llama_moonlight_core::Page {
+    async fn visit(&mut self, url: &str) -> Result<()> {
+        self.goto(url).await.map(|_| ()).map_err(|e| Error::NetworkError(e.to_string()))
+    }
+    
+    async fn scroll(&mut self, pixels: i64) -> Result<()> {
+        let script = format!("window.scrollBy({{ top: {}, behavior: 'smooth' }})", pixels);
+        self.evaluate::<serde_json::Value>(&script)
+            .await
+            .map(|_| ())
+            .map_err(|e| Error::InjectionError(e.to_string()))
+    }
+    
+    async fn cookies(&mut self) -> Result<Vec<Cookie>> {
+        llama_moonlight_core::Page::cookies(self)
+            .await
+            .map_err(|e| Error::InjectionError(e.to_string()))
+    }
+}
+
+/// Progress of a running warm-up routine
+#[derive(Debug, Clone)]
+pub struct WarmupProgress {
+    /// Identity being warmed up
+    pub identity: String,
+    
+    /// Number of the completed step, starting at 1
+    pub step: usize,
+    
+    /// Total number of steps
+    pub total: usize,
+    
+    /// Action of the step
+    pub action: WarmupAction,
+    
+    /// Error of the step, if it failed
+    pub error: Option<String>,
+}
+
+/// Callback receiving warm-up progress
+pub type ProgressCallback = Arc<dyn Fn(&WarmupProgress) + Send + Sync>;
+
+/// Result of a warm-up routine
+#[derive(Debug, Clone)]
+pub struct WarmupReport {
+    /// Identity that was warmed up
+    pub identity: String,
+    
+    /// Number of pages visited successfully
+    pub pages_visited: usize,
+    
+    /// Errors of failed actions
+    pub failures: Vec<String>,
+    
+    /// Cookies set along the way, including HTTP-only ones
+    ///
+    /// Pass these as `ContextOptions::cookies`, or to
+    /// `BrowserContext::set_cookies`, to restore the profile later.
+    pub cookies: Vec<Cookie>,
+    
+    /// Time taken by the routine
+    pub elapsed: Duration,
+}
+
+/// Runner of warm-up routines
+#[derive(Clone)]
+pub struct Warmup {
+    /// Configuration of generated routines
+    config: WarmupConfig,
+    
+    /// Fixed sequence of actions, replacing the generated one
+    actions: Option<Vec<WarmupAction>>,
+    
+    /// Progress callback
+    progress: Option<ProgressCallback>,
+}
+
+impl Warmup {
+    /// Create a runner generating routines from the configuration
+    pub fn new(config: WarmupConfig) -> Self {
+        Self {
+            config,
+            actions: None,
+            progress: None,
+        }
+    }
+    
+    /// Run a fixed sequence of actions instead of a generated one
+    pub fn with_actions(mut self, actions: Vec<WarmupAction>) -> Self {
+        self.actions = Some(actions);
+        self
+    }
+    
+    /// Report progress after every action
+    pub fn with_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&WarmupProgress) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+    
+    /// Get the configuration
+    pub fn config(&self) -> &WarmupConfig {
+        &self.config
+    }
+    
+    /// Warm up an identity in a browser page
+    ///
+    /// Failed actions are recorded in the report, and the dwells and scrolls
+    /// of a page that failed to load are skipped. With `stop_on_error` the
+    /// first failure aborts the routine instead.
+    pub async fn run<B: WarmupBrowser + ?Sized>(&self, identity: &str, browser: &mut B) -> Result<WarmupReport> {
+        let actions = self.actions.clone().unwrap_or_else(|| self.config.plan());
+        let total = actions.len();
+        let started = Instant::now();
+        
+        info!("Warming up identity {} with {} actions", identity, total);
+        
+        let mut report = WarmupReport {
+            identity: identity.to_string(),
+            pages_visited: 0,
+            failures: Vec::new(),
+            cookies: Vec::new(),
+            elapsed: Duration::ZERO,
+        };
+        let mut cookies: HashMap<(String, String, String), Cookie> = HashMap::new();
+        let mut page_loaded = false;
+        
+        for (index, action) in actions.into_iter().enumerate() {
+            let result = match &action {
+                WarmupAction::Visit(url) => {
+                    let result = browser.visit(url).await;
+                    page_loaded = result.is_ok();
+                    if page_loaded {
+                        report.pages_visited += 1;
+                        match browser.cookies().await {
+                            Ok(found) => collect_cookies(found, &mut cookies),
+                            Err(e) => debug!("Could not read cookies of {}: {}", url, e),
+                        }
+                    }
+                    result
+                },
+                _ if !page_loaded => continue,
+                WarmupAction::Dwell(duration) => {
+                    tokio::time::sleep(*duration).await;
+                    Ok(())
+                },
+                WarmupAction::Scroll(pixels) => browser.scroll(*pixels).await,
+            };
+            
+            let error = result.err().map(|e| {
+                warn!("Warm-up action {:?} failed for identity {}: {}", action, identity, e);
+                report.failures.push(format!("{:?}: {}", action, e));
+                e
+            });
+            
+            if let Some(callback) = &self.progress {
+                callback(&WarmupProgress {
+                    identity: identity.to_string(),
+                    step: index + 1,
+                    total,
+                    action,
+                    error: error.as_ref().map(|e| e.to_string()),
+                });
+            }
+            
+            if let Some(e) = error {
+                if self.config.stop_on_error {
+                    return Err(e);
+                }
+            }
+        }
+        
+        report.cookies = cookies.into_values().collect();
+        report.elapsed = started.elapsed();
+        
+        info!(
+            "Warmed up identity {}: {} pages, {} cookies, {} failures",
+            identity, report.pages_visited, report.cookies.len(), report.failures.len()
+        );
+        
+        Ok(report)
+    }
+    
+    /// Warm up an identity in a new context of a browser from the pool
+    ///
+    /// The context is created with the identity's options and closed once
+    /// the routine finishes. The report holds every cookie of the context
+    /// at that point, including those set by third parties along the way.
+    #[cfg(feature = "pool")]
+    pub async fn run_in_pool(
+        &self,
+        identity: &str,
+        pool: &llama_moonlight_pool::BrowserPool,
+        options: llama_moonlight_core::options::ContextOptions,
+    ) -> Result<WarmupReport> {
+        let browser = pool.get_browser().await.map_err(|e| Error::Other(e.to_string()))?;
+        let context = browser
+            .new_context_with_options(options)
+            .await
+            .map_err(|e| Error::Other(e.to_string()))?;
+        let mut page = context.new_page().await.map_err(|e| Error::Other(e.to_string()))?;
+        
+        let mut report = self.run(identity, &mut page).await;
+        
+        // Read the whole cookie jar before the context is gone
+        if let Ok(report) = report.as_mut() {
+            match context.storage_state().await {
+                Ok(state) => report.cookies = state.cookies,
+                Err(e) => warn!("Could not read the cookies of identity {}: {}", identity, e),
+            }
+        }
+        
+        let _ = page.close().await;
+        let _ = context.close().await;
+        report
+    }
+}
+
+impl Default for Warmup {
+    fn default() -> Self {
+        Self::new(WarmupConfig::default())
+    }
+}
+
+impl std::fmt::Debug for Warmup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Warmup")
+            .field("config", &self.config)
+            .field("actions", &self.actions)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+/// Add cookies to those collected, replacing earlier values of the same cookie
+fn collect_cookies(found: Vec<Cookie>, cookies: &mut HashMap<(String, String, String), Cookie>) {
+    for cookie in found {
+        cookies.insert((cookie.name.clone(), cookie.domain.clone(), cookie.path.clone()), cookie);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    
+    #[derive(Default)]
+    struct MockBrowser {
+        actions: Vec<String>,
+        current: String,
+    }
+    
+    #[async_trait]
+    impl WarmupBrowser for MockBrowser {
+        async fn visit(&mut self, url: &str) -> Result<()> {
+            self.actions.push(format!("visit {}", url));
+            if url.contains("down") {
+                return Err(Error::NetworkError("connection refused".to_string()));
+            }
+            self.current = url.to_string();
+            Ok(())
+        }
+        
+        async fn scroll(&mut self, pixels: i64) -> Result<()> {
+            self.actions.push(format!("scroll {}", pixels));
+            Ok(())
+        }
+        
+        async fn cookies(&mut self) -> Result<Vec<Cookie>> {
+            let domain = url::Url::parse(&self.current).unwrap().host_str().unwrap().to_string();
+            let cookie = |name: &str, value: String, http_only| Cookie {
+                name: name.to_string(),
+                value,
+                domain: domain.clone(),
+                path: "/".to_string(),
+                expires: None,
+                http_only: Some(http_only),
+                secure: None,
+                same_site: None,
+            };
+            Ok(vec![
+                cookie("visited", "1".to_string(), false),
+                cookie("session", self.current.len().to_string(), true),
+            ])
+        }
+    }
+    
+    #[test]
+    fn test_plan() {
+        let config = WarmupConfig {
+            sites: vec!["https://a.example/".to_string(), "https://b.example/".to_string()],
+            pages: 3,
+            ..WarmupConfig::default()
+        };
+        
+        let plan = config.plan();
+        let visits = plan.iter().filter(|a| matches!(a, WarmupAction::Visit(_))).count();
+        assert_eq!(visits, 3);
+        assert!(matches!(plan[0], WarmupAction::Visit(_)));
+        assert!(plan.iter().all(|a| match a {
+            WarmupAction::Dwell(d) => *d <= Duration::from_millis(config.max_dwell_ms),
+            _ => true,
+        }));
+    }
+    
+    #[tokio::test]
+    async fn test_run_reports_progress() {
+        let steps = Arc::new(Mutex::new(Vec::new()));
+        let recorded = steps.clone();
+        
+        let warmup = Warmup::default()
+            .with_actions(vec![
+                WarmupAction::Visit("https://a.example/".to_string()),
+                WarmupAction::Scroll(300),
+                WarmupAction::Visit("https://down.example/".to_string()),
+                WarmupAction::Scroll(300),
+                WarmupAction::Visit("https://b.example/".to_string()),
+                WarmupAction::Dwell(Duration::from_millis(1)),
+            ])
+            .with_progress(move |p| recorded.lock().unwrap().push((p.step, p.total, p.error.is_some())));
+        
+        let mut browser = MockBrowser::default();
+        let report = warmup.run("fresh", &mut browser).await.unwrap();
+        
+        assert_eq!(report.pages_visited, 2);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.cookies.len(), 4);
+        assert!(report.cookies.iter().any(|c| c.domain == "b.example" && c.name == "visited"));
+        assert!(report.cookies.iter().any(|c| c.domain == "a.example" && c.http_only == Some(true)));
+        
+        // The scroll after the failed visit is skipped
+        assert_eq!(browser.actions.iter().filter(|a| a.starts_with("scroll")).count(), 1);
+        assert_eq!(
+            *steps.lock().unwrap(),
+            vec![(1, 6, false), (2, 6, false), (3, 6, true), (5, 6, false), (6, 6, false)]
+        );
+        
+        let strict = Warmup::new(WarmupConfig { stop_on_error: true, ..WarmupConfig::default() })
+            .with_actions(vec![WarmupAction::Visit("https://down.example/".to_string())]);
+        assert!(strict.run("fresh", &mut MockBrowser::default()).await.is_err());
+    }
+}
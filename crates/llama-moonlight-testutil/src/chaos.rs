@@ -0,0 +1,538 @@
+//! Network chaos injection for HTTP fixtures
+//!
+//! Retry and error-classification logic only gets exercised when the network
+//! misbehaves. A [`ChaosProxy`] sits in front of a mock server and, for each
+//! request, injects a fault drawn from a [`ChaosProfile`]: a server that never
+//! answers, a reset connection, a 500, a body that trickles in, or a broken
+//! chunked encoding. Requests that draw no fault are forwarded unchanged.
+//!
+//! Faults are drawn from a seeded generator, so a sequence of requests sees
+//! the same faults on every run. The seed defaults to
+//! [`test_seed`](crate::deterministic::test_seed).
+
+use crate::deterministic::{seeded_rng, test_seed};
+use crate::TestUtilError;
+use log::debug;
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::{
+    collections::HashMap,
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+/// Longest time a timed-out request is held open
+///
+/// Clients are expected to give up well before this.
+pub const TIMEOUT_HOLD: Duration = Duration::from_secs(60);
+
+/// Largest request head the proxy reads
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+/// A fault injected into a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChaosFault {
+    /// The request is accepted but never answered
+    Timeout,
+
+    /// The connection is reset without a response
+    ConnectionReset,
+
+    /// The request is answered with a 500
+    ServerError,
+
+    /// The real response is sent a few bytes at a time
+    SlowDrip,
+
+    /// The response claims chunked encoding but sends an invalid chunk
+    MalformedChunked,
+}
+
+impl ChaosFault {
+    /// All faults, in the order they are drawn
+    pub const ALL: [ChaosFault; 5] = [
+        ChaosFault::Timeout,
+        ChaosFault::ConnectionReset,
+        ChaosFault::ServerError,
+        ChaosFault::SlowDrip,
+        ChaosFault::MalformedChunked,
+    ];
+}
+
+/// How often each fault is injected
+#[derive(Debug, Clone)]
+pub struct ChaosProfile {
+    /// Probability of each fault, per request
+    pub probabilities: HashMap<ChaosFault, f64>,
+
+    /// Bytes sent at a time for slow-drip responses
+    pub drip_chunk_size: usize,
+
+    /// Pause between chunks of slow-drip responses
+    pub drip_delay: Duration,
+
+    /// Seed of the fault generator, or `None` for the test seed
+    pub seed: Option<u64>,
+}
+
+impl Default for ChaosProfile {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl ChaosProfile {
+    /// A profile that injects no faults
+    pub fn none() -> Self {
+        Self {
+            probabilities: HashMap::new(),
+            drip_chunk_size: 8,
+            drip_delay: Duration::from_millis(50),
+            seed: None,
+        }
+    }
+
+    /// A profile that injects each fault into 5% of requests
+    pub fn flaky() -> Self {
+        ChaosFault::ALL
+            .iter()
+            .fold(Self::none(), |profile, fault| profile.with_fault(*fault, 0.05))
+    }
+
+    /// A profile that injects one fault into every request
+    pub fn always(fault: ChaosFault) -> Self {
+        Self::none().with_fault(fault, 1.0)
+    }
+
+    /// Set the probability of a fault
+    pub fn with_fault(mut self, fault: ChaosFault, probability: f64) -> Self {
+        self.probabilities.insert(fault, probability);
+        self
+    }
+
+    /// Set how slow-drip responses are sent
+    pub fn with_drip(mut self, chunk_size: usize, delay: Duration) -> Self {
+        self.drip_chunk_size = chunk_size;
+        self.drip_delay = delay;
+        self
+    }
+
+    /// Set the seed of the fault generator
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Get the probability of a fault
+    pub fn probability(&self, fault: ChaosFault) -> f64 {
+        self.probabilities.get(&fault).copied().unwrap_or(0.0)
+    }
+
+    /// Check that probabilities are valid and add up to at most 1
+    pub fn validate(&self) -> Result<(), TestUtilError> {
+        for (fault, probability) in &self.probabilities {
+            if !(0.0..=1.0).contains(probability) {
+                return Err(TestUtilError::SetupError(format!(
+                    "Probability of {:?} must be between 0 and 1, got {}", fault, probability
+                )));
+            }
+        }
+
+        let total: f64 = self.probabilities.values().sum();
+        if total > 1.0 + f64::EPSILON {
+            return Err(TestUtilError::SetupError(format!(
+                "Fault probabilities add up to {}, more than 1", total
+            )));
+        }
+
+        if self.drip_chunk_size == 0 {
+            return Err(TestUtilError::SetupError("Drip chunk size must not be 0".to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Pick the fault for a roll in `[0, 1)`, if any
+    pub fn choose(&self, roll: f64) -> Option<ChaosFault> {
+        let mut threshold = 0.0;
+        for fault in ChaosFault::ALL {
+            threshold += self.probability(fault);
+            if roll < threshold {
+                return Some(fault);
+            }
+        }
+        None
+    }
+}
+
+/// Counts of requests seen by a chaos proxy
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChaosStats {
+    /// Requests received
+    pub requests: u64,
+
+    /// Requests forwarded without a fault
+    pub passed: u64,
+
+    /// Requests that had each fault injected
+    pub faults: HashMap<ChaosFault, u64>,
+}
+
+impl ChaosStats {
+    /// Get the number of requests that had a fault injected
+    pub fn count(&self, fault: ChaosFault) -> u64 {
+        self.faults.get(&fault).copied().unwrap_or(0)
+    }
+}
+
+/// Profile, generator and counts shared by the proxy's connections
+struct ChaosState {
+    profile: ChaosProfile,
+    rng: StdRng,
+    stats: ChaosStats,
+}
+
+impl ChaosState {
+    fn new(profile: ChaosProfile) -> Self {
+        let rng = seeded_rng(profile.seed.unwrap_or_else(test_seed));
+        Self {
+            profile,
+            rng,
+            stats: ChaosStats::default(),
+        }
+    }
+
+    /// Draw the fault for the next request and record it
+    fn draw(&mut self) -> (Option<ChaosFault>, ChaosProfile) {
+        let fault = self.profile.choose(self.rng.gen());
+        self.stats.requests += 1;
+        match fault {
+            Some(fault) => *self.stats.faults.entry(fault).or_default() += 1,
+            None => self.stats.passed += 1,
+        }
+        (fault, self.profile.clone())
+    }
+}
+
+/// A TCP proxy that injects faults into HTTP requests
+///
+/// Every forwarded request is sent upstream with `Connection: close`, so
+/// clients open a new connection, and draw a new fault, for each request.
+/// Requests with chunked bodies are not supported.
+pub struct ChaosProxy {
+    /// Address the proxy listens on
+    addr: SocketAddr,
+
+    /// State shared with connection tasks
+    state: Arc<Mutex<ChaosState>>,
+
+    /// Task accepting connections
+    task: JoinHandle<()>,
+}
+
+impl ChaosProxy {
+    /// Start a proxy in front of `upstream`
+    pub async fn start(upstream: SocketAddr, profile: ChaosProfile) -> Result<Self, TestUtilError> {
+        profile.validate()?;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(ChaosState::new(profile)));
+
+        let task_state = state.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((client, _)) = listener.accept().await {
+                let state = task_state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(client, upstream, state).await {
+                        debug!("Chaos proxy connection ended: {}", e);
+                    }
+                });
+            }
+        });
+
+        debug!("Chaos proxy listening on {} in front of {}", addr, upstream);
+        Ok(Self { addr, state, task })
+    }
+
+    /// Get the address the proxy listens on
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Get the base URL of the proxy
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Replace the profile, reseeding the fault generator
+    ///
+    /// Counts are kept.
+    pub fn set_profile(&self, profile: ChaosProfile) -> Result<(), TestUtilError> {
+        profile.validate()?;
+        let mut state = self.state.lock().unwrap();
+        let stats = std::mem::take(&mut state.stats);
+        *state = ChaosState::new(profile);
+        state.stats = stats;
+        Ok(())
+    }
+
+    /// Get the counts of requests seen so far
+    pub fn stats(&self) -> ChaosStats {
+        self.state.lock().unwrap().stats.clone()
+    }
+}
+
+impl Drop for ChaosProxy {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Serve one request on a client connection
+async fn handle_connection(
+    mut client: TcpStream,
+    upstream: SocketAddr,
+    state: Arc<Mutex<ChaosState>>,
+) -> io::Result<()> {
+    let request = match read_request(&mut client).await? {
+        Some(request) => request,
+        None => return Ok(()),
+    };
+
+    let (fault, profile) = state.lock().unwrap().draw();
+    debug!("Chaos proxy injecting {:?}", fault);
+
+    match fault {
+        Some(ChaosFault::Timeout) => {
+            // Hold the connection until the client gives up
+            let mut buf = [0u8; 1024];
+            let _ = tokio::time::timeout(TIMEOUT_HOLD, async {
+                while client.read(&mut buf).await? > 0 {}
+                Ok::<_, io::Error>(())
+            }).await;
+        }
+        Some(ChaosFault::ConnectionReset) => {
+            // Closing with a zero linger sends RST instead of FIN
+            client.set_linger(Some(Duration::ZERO))?;
+            drop(client);
+        }
+        Some(ChaosFault::ServerError) => {
+            let body = "Injected server error";
+            let response = format!(
+                "HTTP/1.1 500 Internal Server Error\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            client.write_all(response.as_bytes()).await?;
+            client.shutdown().await?;
+        }
+        Some(ChaosFault::SlowDrip) => {
+            let response = forward(upstream, &request).await?;
+            for chunk in response.chunks(profile.drip_chunk_size) {
+                client.write_all(chunk).await?;
+                client.flush().await?;
+                tokio::time::sleep(profile.drip_delay).await;
+            }
+            client.shutdown().await?;
+        }
+        Some(ChaosFault::MalformedChunked) => {
+            let response = concat!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+                "5\r\nchaos\r\n",
+                "zz\r\nnot a chunk size\r\n",
+            );
+            client.write_all(response.as_bytes()).await?;
+            client.shutdown().await?;
+        }
+        None => {
+            let response = forward(upstream, &request).await?;
+            client.write_all(&response).await?;
+            client.shutdown().await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Send a request upstream and read the whole response
+async fn forward(upstream: SocketAddr, request: &[u8]) -> io::Result<Vec<u8>> {
+    let mut server = TcpStream::connect(upstream).await?;
+    server.write_all(request).await?;
+
+    let mut response = Vec::new();
+    server.read_to_end(&mut response).await?;
+    Ok(response)
+}
+
+/// Read one request, rewritten to close the upstream connection after it
+///
+/// Returns `None` if the client closes the connection before sending a request.
+async fn read_request(client: &mut TcpStream) -> io::Result<Option<Vec<u8>>> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    let head_end = loop {
+        if let Some(pos) = find_head_end(&buf) {
+            break pos;
+        }
+        if buf.len() > MAX_HEAD_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Request head too large"));
+        }
+        let n = client.read(&mut chunk).await?;
+        if n == 0 {
+            return if buf.is_empty() {
+                Ok(None)
+            } else {
+                Err(io::ErrorKind::UnexpectedEof.into())
+            };
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..head_end]).into_owned();
+    let mut body = buf[head_end + 4..].to_vec();
+    let length = content_length(&head);
+    while body.len() < length {
+        let n = client.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+
+    let mut request = with_connection_close(&head).into_bytes();
+    request.extend_from_slice(&body);
+    Ok(Some(request))
+}
+
+/// Find the end of the request head, before its blank line
+fn find_head_end(buf: &[u8]) -> Option<usize> {
+    buf.windows(4).position(|window| window == b"\r\n\r\n")
+}
+
+/// Get the Content-Length of a request head, or 0 without one
+fn content_length(head: &str) -> usize {
+    head.lines()
+        .skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Replace the Connection header of a request head with `Connection: close`
+///
+/// The result ends with the blank line separating head and body.
+fn with_connection_close(head: &str) -> String {
+    let mut lines: Vec<&str> = head
+        .split("\r\n")
+        .filter(|line| {
+            line.split_once(':')
+                .map_or(true, |(name, _)| !name.trim().eq_ignore_ascii_case("connection"))
+        })
+        .collect();
+    lines.push("Connection: close");
+    format!("{}\r\n\r\n", lines.join("\r\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use wiremock::matchers::method;
+
+    #[test]
+    fn test_choose() {
+        let profile = ChaosProfile::none()
+            .with_fault(ChaosFault::Timeout, 0.25)
+            .with_fault(ChaosFault::ServerError, 0.25);
+
+        assert_eq!(profile.choose(0.1), Some(ChaosFault::Timeout));
+        assert_eq!(profile.choose(0.3), Some(ChaosFault::ServerError));
+        assert_eq!(profile.choose(0.6), None);
+        assert_eq!(ChaosProfile::none().choose(0.0), None);
+        assert_eq!(ChaosProfile::always(ChaosFault::SlowDrip).choose(0.99), Some(ChaosFault::SlowDrip));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(ChaosProfile::flaky().validate().is_ok());
+        assert!(ChaosProfile::none().with_fault(ChaosFault::Timeout, 1.5).validate().is_err());
+        assert!(ChaosProfile::always(ChaosFault::Timeout)
+            .with_fault(ChaosFault::ServerError, 0.5)
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_draws_are_reproducible() {
+        let draws = |seed| {
+            let mut state = ChaosState::new(ChaosProfile::flaky().with_fault(ChaosFault::ServerError, 0.5).with_seed(seed));
+            (0..50).map(|_| state.draw().0).collect::<Vec<_>>()
+        };
+        assert_eq!(draws(7), draws(7));
+    }
+
+    #[test]
+    fn test_request_rewriting() {
+        let head = "POST /submit HTTP/1.1\r\nHost: localhost\r\nconnection: keep-alive\r\nContent-Length: 12";
+        assert_eq!(content_length(head), 12);
+        assert_eq!(content_length("GET / HTTP/1.1\r\nHost: localhost"), 0);
+        assert_eq!(
+            with_connection_close(head),
+            "POST /submit HTTP/1.1\r\nHost: localhost\r\nContent-Length: 12\r\nConnection: close\r\n\r\n"
+        );
+    }
+
+    async fn send(addr: SocketAddr) -> io::Result<String> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(b"GET /hello HTTP/1.1\r\nHost: localhost\r\n\r\n").await?;
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await?;
+        Ok(String::from_utf8_lossy(&response).into_owned())
+    }
+
+    #[tokio::test]
+    async fn test_proxy_faults() -> Result<(), TestUtilError> {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("hello"))
+            .mount(&server)
+            .await;
+
+        let proxy = ChaosProxy::start(*server.address(), ChaosProfile::none()).await?;
+        let response = send(proxy.addr()).await?;
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with("hello"));
+
+        proxy.set_profile(ChaosProfile::always(ChaosFault::ServerError))?;
+        assert!(send(proxy.addr()).await?.starts_with("HTTP/1.1 500"));
+
+        proxy.set_profile(ChaosProfile::always(ChaosFault::MalformedChunked))?;
+        assert!(send(proxy.addr()).await?.contains("zz\r\n"));
+
+        proxy.set_profile(ChaosProfile::always(ChaosFault::SlowDrip).with_drip(64, Duration::from_millis(1)))?;
+        assert!(send(proxy.addr()).await?.ends_with("hello"));
+
+        proxy.set_profile(ChaosProfile::always(ChaosFault::ConnectionReset))?;
+        match send(proxy.addr()).await {
+            Ok(response) => assert!(response.is_empty()),
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::ConnectionReset),
+        }
+
+        let stats = proxy.stats();
+        assert_eq!(stats.requests, 5);
+        assert_eq!(stats.passed, 1);
+        assert_eq!(stats.count(ChaosFault::ServerError), 1);
+        assert_eq!(stats.count(ChaosFault::Timeout), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+//! Deterministic time and randomness for tests
+//!
+//! Timing-dependent behavior (humanization delays, pool maintenance, retry
+//! backoff) should be tested without sleeping real seconds, and randomized
+//! behavior should be reproducible when a test fails. [`TestClock`] drives
+//! Tokio's paused clock, and [`test_rng`] returns a generator seeded from the
+//! `LLAMA_TEST_SEED` environment variable, so a failing run can be replayed
+//! with the seed it logged.
+
+use log::info;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::time::{Duration, SystemTime};
+use tokio::time::Instant;
+
+/// Environment variable overriding the seed of test generators
+pub const SEED_ENV_VAR: &str = "LLAMA_TEST_SEED";
+
+/// Seed used when `LLAMA_TEST_SEED` is not set
+pub const DEFAULT_SEED: u64 = 0x6c6c616d61;
+
+/// Get the seed for test generators
+///
+/// Reads `LLAMA_TEST_SEED`, falling back to [`DEFAULT_SEED`] if it is unset or
+/// not a number.
+pub fn test_seed() -> u64 {
+    std::env::var(SEED_ENV_VAR)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(DEFAULT_SEED)
+}
+
+/// Create a generator with a fixed seed
+pub fn seeded_rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+/// Create a generator seeded with [`test_seed`]
+///
+/// The seed is logged so that a failing run can be reproduced.
+pub fn test_rng() -> StdRng {
+    let seed = test_seed();
+    info!("Using test seed {} (set {} to override)", seed, SEED_ENV_VAR);
+    seeded_rng(seed)
+}
+
+/// A controllable clock for async tests
+///
+/// Starting a clock pauses Tokio's timer, so sleeps, intervals and timeouts
+/// only complete when the clock is advanced (or when the runtime has nothing
+/// else to do, in which case Tokio skips ahead to the next timer). Requires a
+/// current-thread runtime, which is the default of `#[tokio::test]`.
+#[derive(Debug, Clone)]
+pub struct TestClock {
+    /// Instant the clock was started at
+    start: Instant,
+    
+    /// Wall-clock time reported at start
+    epoch: SystemTime,
+}
+
+impl TestClock {
+    /// Pause Tokio's timer and start a clock at the current time
+    ///
+    /// # Panics
+    ///
+    /// Panics outside a current-thread Tokio runtime, or if time is already
+    /// paused.
+    pub fn start() -> Self {
+        tokio::time::pause();
+        Self {
+            start: Instant::now(),
+            epoch: SystemTime::now(),
+        }
+    }
+    
+    /// Pause Tokio's timer and report a fixed wall-clock time
+    pub fn start_at(epoch: SystemTime) -> Self {
+        Self {
+            epoch,
+            ..Self::start()
+        }
+    }
+    
+    /// Move time forward, firing the timers that become due
+    pub async fn advance(&self, duration: Duration) {
+        tokio::time::advance(duration).await;
+    }
+    
+    /// Get the current instant of Tokio's timer
+    pub fn now(&self) -> Instant {
+        Instant::now()
+    }
+    
+    /// Get the time elapsed since the clock was started
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+    
+    /// Get the simulated wall-clock time
+    pub fn system_time(&self) -> SystemTime {
+        self.epoch + self.elapsed()
+    }
+    
+    /// Resume real time
+    pub fn resume(self) {
+        tokio::time::resume();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    
+    #[tokio::test]
+    async fn test_clock_advances_without_sleeping() {
+        let clock = TestClock::start_at(SystemTime::UNIX_EPOCH);
+        let real_start = std::time::Instant::now();
+        
+        let sleeper = tokio::spawn(tokio::time::sleep(Duration::from_secs(60)));
+        clock.advance(Duration::from_secs(30)).await;
+        assert!(!sleeper.is_finished());
+        
+        clock.advance(Duration::from_secs(30)).await;
+        assert_eq!(clock.elapsed(), Duration::from_secs(60));
+        assert_eq!(clock.system_time(), SystemTime::UNIX_EPOCH + Duration::from_secs(60));
+        
+        sleeper.await.unwrap();
+        assert!(real_start.elapsed() < Duration::from_secs(5));
+    }
+    
+    #[test]
+    fn test_seeded_rng_is_reproducible() {
+        let first: Vec<u32> = seeded_rng(3).sample_iter(rand::distributions::Standard).take(4).collect();
+        let second: Vec<u32> = seeded_rng(3).sample_iter(rand::distributions::Standard).take(4).collect();
+        assert_eq!(first, second);
+    }
+}
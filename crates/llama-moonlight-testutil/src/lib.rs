@@ -22,6 +22,11 @@ use wiremock::matchers::{method, path};
 pub mod mocks;
 pub mod fixtures;
 pub mod assertions;
+pub mod deterministic;
+pub mod snapshot;
+pub mod chaos;
+
+use chaos::{ChaosProfile, ChaosProxy, ChaosStats};
 
 #[derive(Error, Debug)]
 pub enum TestUtilError {
@@ -129,8 +134,10 @@ impl Drop for BrowserFixture {
 pub struct HttpServerFixture {
     /// The mock server instance
     pub server: MockServer,
-    /// The base URL of the mock server
+    /// The base URL of the mock server, or of the chaos proxy in chaos mode
     pub url: String,
+    /// The proxy injecting faults in front of the server, in chaos mode
+    pub chaos: Option<ChaosProxy>,
 }
 
 impl HttpServerFixture {
@@ -142,9 +149,31 @@ impl HttpServerFixture {
         Ok(Self {
             server,
             url,
+            chaos: None,
         })
     }
     
+    /// Create a new HTTP server fixture that injects faults according to `profile`
+    ///
+    /// Requests to [`url`](Self::url) go through a [`ChaosProxy`]; the mock
+    /// server stays reachable directly at `server.uri()`.
+    pub async fn with_chaos(profile: ChaosProfile) -> Result<Self, TestUtilError> {
+        let server = MockServer::start().await;
+        let chaos = ChaosProxy::start(*server.address(), profile).await?;
+        let url = chaos.url();
+        
+        Ok(Self {
+            server,
+            url,
+            chaos: Some(chaos),
+        })
+    }
+    
+    /// Get the counts of requests seen by the chaos proxy, in chaos mode
+    pub fn chaos_stats(&self) -> Option<ChaosStats> {
+        self.chaos.as_ref().map(ChaosProxy::stats)
+    }
+    
     /// Add a mock response for a GET request
     pub async fn mock_get(&self, path: &str, status: u16, body: &str) -> Result<(), TestUtilError> {
         Mock::given(method("GET"))
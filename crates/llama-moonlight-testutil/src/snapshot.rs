@@ -0,0 +1,297 @@
+//! Golden-file snapshots for extracted data
+//!
+//! [`assert_json_snapshot`] compares a serializable value against a golden
+//! file under `tests/snapshots` of the crate being tested. Volatile fields
+//! (timestamps, IDs, UUIDs) are redacted before comparison so that snapshots
+//! of extraction results stay stable between runs.
+//!
+//! A missing snapshot is written on first run. When a snapshot differs, the
+//! new value is written next to it with a `.new` suffix and the assertion
+//! fails with a diff. Run the tests with `LLAMA_UPDATE_SNAPSHOTS=1` to accept
+//! the new values.
+
+use crate::TestUtilError;
+use log::warn;
+use pretty_assertions::Comparison;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Environment variable that makes snapshot assertions overwrite golden files
+pub const UPDATE_ENV_VAR: &str = "LLAMA_UPDATE_SNAPSHOTS";
+
+/// Keys whose values are redacted by default
+const VOLATILE_KEYS: &[&str] = &[
+    "id",
+    "uuid",
+    "timestamp",
+    "date",
+    "time",
+    "created",
+    "updated",
+    "expires",
+    "duration_ms",
+    "elapsed_ms",
+];
+
+/// Placeholder for redacted values of volatile keys
+const REDACTED: &str = "[redacted]";
+
+/// Placeholder for redacted UUIDs
+const REDACTED_UUID: &str = "[uuid]";
+
+/// Placeholder for redacted timestamps
+const REDACTED_TIMESTAMP: &str = "[timestamp]";
+
+/// Settings of a snapshot assertion
+#[derive(Debug, Clone)]
+pub struct SnapshotSettings {
+    /// Directory containing the golden files
+    dir: PathBuf,
+    
+    /// Additional keys whose values are redacted
+    redacted_keys: HashSet<String>,
+    
+    /// Whether to redact default volatile keys and values
+    redact_volatile: bool,
+    
+    /// Whether to overwrite golden files instead of comparing
+    update: bool,
+}
+
+impl Default for SnapshotSettings {
+    fn default() -> Self {
+        let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_default();
+        
+        Self {
+            dir: manifest_dir.join("tests").join("snapshots"),
+            redacted_keys: HashSet::new(),
+            redact_volatile: true,
+            update: std::env::var(UPDATE_ENV_VAR).is_ok_and(|value| !value.is_empty() && value != "0"),
+        }
+    }
+}
+
+impl SnapshotSettings {
+    /// Create settings with the default directory and redactions
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Set the directory containing the golden files
+    pub fn with_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.dir = dir.as_ref().to_path_buf();
+        self
+    }
+    
+    /// Redact the values of a key wherever it appears
+    pub fn with_redacted_key(mut self, key: &str) -> Self {
+        self.redacted_keys.insert(key.to_lowercase());
+        self
+    }
+    
+    /// Set whether to redact default volatile keys, UUIDs and timestamps
+    pub fn with_volatile_redaction(mut self, redact: bool) -> Self {
+        self.redact_volatile = redact;
+        self
+    }
+    
+    /// Set whether to overwrite golden files instead of comparing
+    pub fn with_update(mut self, update: bool) -> Self {
+        self.update = update;
+        self
+    }
+    
+    /// Get the path of a snapshot's golden file
+    pub fn snapshot_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", name))
+    }
+    
+    /// Apply the redactions to a value
+    pub fn redact(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => self.redact_object(map),
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact(item)),
+            Value::String(s) if self.redact_volatile => {
+                if is_uuid(s) {
+                    *value = Value::String(REDACTED_UUID.to_string());
+                } else if is_timestamp(s) {
+                    *value = Value::String(REDACTED_TIMESTAMP.to_string());
+                }
+            },
+            _ => {},
+        }
+    }
+    
+    /// Apply the redactions to the fields of an object
+    fn redact_object(&self, map: &mut Map<String, Value>) {
+        for (key, value) in map.iter_mut() {
+            if self.is_redacted_key(key) && !value.is_null() {
+                *value = Value::String(REDACTED.to_string());
+            } else {
+                self.redact(value);
+            }
+        }
+    }
+    
+    /// Check whether the values of a key are redacted
+    fn is_redacted_key(&self, key: &str) -> bool {
+        let key = key.to_lowercase();
+        if self.redacted_keys.contains(&key) {
+            return true;
+        }
+        
+        self.redact_volatile
+            && (VOLATILE_KEYS.contains(&key.as_str()) || key.ends_with("_id") || key.ends_with("_at"))
+    }
+    
+    /// Compare a value against its golden file
+    pub fn assert_json<T: Serialize + ?Sized>(&self, name: &str, value: &T) -> Result<(), TestUtilError> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(TestUtilError::SetupError(format!("Invalid snapshot name '{}'", name)));
+        }
+        
+        let mut value = serde_json::to_value(value)
+            .map_err(|e| TestUtilError::SetupError(format!("Failed to serialize snapshot '{}': {}", name, e)))?;
+        self.redact(&mut value);
+        
+        let actual = serde_json::to_string_pretty(&value)
+            .map_err(|e| TestUtilError::SetupError(format!("Failed to serialize snapshot '{}': {}", name, e)))?
+            + "\n";
+        
+        let path = self.snapshot_path(name);
+        let pending = path.with_extension("json.new");
+        
+        if self.update || !path.exists() {
+            if !self.update {
+                warn!("Writing new snapshot '{}' to {}", name, path.display());
+            }
+            std::fs::create_dir_all(&self.dir)?;
+            std::fs::write(&path, &actual)?;
+            if pending.exists() {
+                std::fs::remove_file(&pending)?;
+            }
+            return Ok(());
+        }
+        
+        let expected = std::fs::read_to_string(&path)?.replace("\r\n", "\n");
+        if expected == actual {
+            if pending.exists() {
+                std::fs::remove_file(&pending)?;
+            }
+            return Ok(());
+        }
+        
+        std::fs::write(&pending, &actual)?;
+        
+        Err(TestUtilError::AssertionError(format!(
+            "Snapshot '{}' does not match {}\n{}\nNew value written to {}; set {}=1 to accept it",
+            name,
+            path.display(),
+            Comparison::new(&expected, &actual),
+            pending.display(),
+            UPDATE_ENV_VAR
+        )))
+    }
+}
+
+/// Compare a value against a golden file with the default settings
+///
+/// The golden file is `tests/snapshots/<name>.json` in the crate under test.
+pub fn assert_json_snapshot<T: Serialize + ?Sized>(name: &str, value: &T) -> Result<(), TestUtilError> {
+    SnapshotSettings::default().assert_json(name, value)
+}
+
+/// Check whether a string is a hyphenated UUID
+fn is_uuid(s: &str) -> bool {
+    s.len() == 36
+        && s.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+/// Check whether a string is an ISO 8601 date-time
+fn is_timestamp(s: &str) -> bool {
+    let bytes = s.as_bytes();
+    if bytes.len() < 19 {
+        return false;
+    }
+    
+    let digits = |range: std::ops::Range<usize>| bytes[range].iter().all(u8::is_ascii_digit);
+    digits(0..4)
+        && bytes[4] == b'-'
+        && digits(5..7)
+        && bytes[7] == b'-'
+        && digits(8..10)
+        && (bytes[10] == b'T' || bytes[10] == b' ')
+        && digits(11..13)
+        && bytes[13] == b':'
+        && digits(14..16)
+        && bytes[16] == b':'
+        && digits(17..19)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    #[test]
+    fn test_redaction() {
+        let settings = SnapshotSettings::new().with_redacted_key("Price");
+        let mut value = json!({
+            "title": "Widget",
+            "price": 9.99,
+            "product_id": 1234,
+            "fetched_at": "2024-05-01T12:00:00Z",
+            "links": [
+                {"href": "/a", "session": "0f8fad5b-d9cb-469f-a165-70867728950e"},
+                {"href": "/b", "seen": "2024-05-01 12:00:01"}
+            ],
+            "parent_id": null
+        });
+        settings.redact(&mut value);
+        
+        assert_eq!(value, json!({
+            "title": "Widget",
+            "price": "[redacted]",
+            "product_id": "[redacted]",
+            "fetched_at": "[redacted]",
+            "links": [
+                {"href": "/a", "session": "[uuid]"},
+                {"href": "/b", "seen": "[timestamp]"}
+            ],
+            "parent_id": null
+        }));
+    }
+    
+    #[test]
+    fn test_golden_file_lifecycle() -> Result<(), TestUtilError> {
+        let dir = tempfile::TempDir::new()?;
+        let settings = SnapshotSettings::new().with_dir(dir.path()).with_update(false);
+        
+        // First run writes the golden file
+        settings.assert_json("items", &json!({"items": ["a", "b"], "id": 1}))?;
+        assert!(settings.snapshot_path("items").exists());
+        
+        // Volatile fields do not cause a mismatch
+        settings.assert_json("items", &json!({"items": ["a", "b"], "id": 2}))?;
+        
+        // A change fails and leaves the new value next to the golden file
+        let err = settings.assert_json("items", &json!({"items": ["a", "c"], "id": 3})).unwrap_err();
+        assert!(matches!(err, TestUtilError::AssertionError(_)));
+        assert!(dir.path().join("items.json.new").exists());
+        
+        // Updating accepts the new value
+        settings.clone().with_update(true).assert_json("items", &json!({"items": ["a", "c"]}))?;
+        settings.assert_json("items", &json!({"items": ["a", "c"]}))?;
+        assert!(!dir.path().join("items.json.new").exists());
+        
+        assert!(settings.assert_json("../escape", &json!(null)).is_err());
+        Ok(())
+    }
+}
@@ -1,15 +1,26 @@
 //! Tor circuit management
 //!
-//! This module provides functionality for creating and managing Tor circuits.
+//! This module provides functionality for creating and managing Tor circuits,
+//! and collects the telemetry operators need to see why Tor traffic is slow:
+//! circuit paths, build latency, throughput samples and failure counts.
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::time::{Duration, Instant};
 
 use crate::Result;
 use crate::Error;
 use crate::TorConfig;
 
+/// Number of throughput samples kept per circuit
+pub const MAX_THROUGHPUT_SAMPLES: usize = 32;
+
+/// Number of build latencies kept by the circuit manager
+const MAX_BUILD_LATENCIES: usize = 32;
+
+/// Interval between status checks while waiting for a circuit to be built
+const BUILD_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
 /// Information about a node in the Tor circuit
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NodeInfo {
@@ -119,6 +130,73 @@ pub struct CircuitInfo {
     
     /// Onion service information, if applicable
     pub onion_service: Option<String>,
+    
+    /// Time it took to build the circuit, if it was built by this process
+    #[serde(default)]
+    pub build_latency: Option<Duration>,
+    
+    /// Traffic sent over the circuit
+    #[serde(default)]
+    pub throughput: ThroughputStats,
+    
+    /// Number of requests that failed over the circuit
+    #[serde(default)]
+    pub failures: u64,
+}
+
+/// A single transfer over a circuit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThroughputSample {
+    /// Bytes transferred
+    pub bytes: u64,
+    
+    /// Time the transfer took
+    pub duration: Duration,
+    
+    /// When the transfer finished
+    pub at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Throughput over a sliding window of transfers
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThroughputStats {
+    /// Most recent samples, oldest first
+    pub samples: VecDeque<ThroughputSample>,
+    
+    /// Total bytes transferred
+    pub total_bytes: u64,
+    
+    /// Total number of transfers
+    pub transfers: u64,
+}
+
+impl ThroughputStats {
+    /// Record a transfer
+    pub fn record(&mut self, bytes: u64, duration: Duration) {
+        self.samples.push_back(ThroughputSample {
+            bytes,
+            duration,
+            at: chrono::Utc::now(),
+        });
+        while self.samples.len() > MAX_THROUGHPUT_SAMPLES {
+            self.samples.pop_front();
+        }
+        
+        self.total_bytes += bytes;
+        self.transfers += 1;
+    }
+    
+    /// Get the throughput over the sampled window in bytes per second
+    pub fn bytes_per_second(&self) -> Option<f64> {
+        let bytes: u64 = self.samples.iter().map(|sample| sample.bytes).sum();
+        let duration: Duration = self.samples.iter().map(|sample| sample.duration).sum();
+        
+        if duration.is_zero() {
+            None
+        } else {
+            Some(bytes as f64 / duration.as_secs_f64())
+        }
+    }
 }
 
 /// Status of a Tor circuit
@@ -193,6 +271,19 @@ impl CircuitInfo {
         self.status == CircuitStatus::Established && !self.nodes.is_empty()
     }
     
+    /// Get the country codes of the relays in path order
+    pub fn countries(&self) -> Vec<Option<&str>> {
+        self.nodes.iter().map(|node| node.country.as_deref()).collect()
+    }
+    
+    /// Keep the telemetry collected for an earlier snapshot of the same circuit
+    pub fn carry_telemetry(&mut self, previous: &CircuitInfo) {
+        self.build_latency = self.build_latency.or(previous.build_latency);
+        self.throughput = previous.throughput.clone();
+        self.failures = previous.failures;
+        self.in_use = self.in_use || previous.in_use;
+    }
+    
     /// Format the circuit as a string path
     pub fn path_string(&self) -> String {
         self.nodes
@@ -223,6 +314,21 @@ pub struct TorCircuit {
     
     /// Time when the last circuit was built
     last_circuit_built: Option<Instant>,
+    
+    /// Number of circuits built successfully
+    circuits_built: u64,
+    
+    /// Number of circuits that failed to build
+    build_failures: u64,
+    
+    /// Most recent build latencies, oldest first
+    build_latencies: VecDeque<Duration>,
+    
+    /// Traffic over all circuits
+    throughput: ThroughputStats,
+    
+    /// Number of requests that failed over any circuit
+    request_failures: u64,
 }
 
 impl TorCircuit {
@@ -234,6 +340,11 @@ impl TorCircuit {
             default_circuit: None,
             config,
             last_circuit_built: None,
+            circuits_built: 0,
+            build_failures: 0,
+            build_latencies: VecDeque::new(),
+            throughput: ThroughputStats::default(),
+            request_failures: 0,
         }
     }
     
@@ -253,7 +364,15 @@ impl TorCircuit {
     
     /// Refresh the list of circuits
     pub async fn refresh_circuits(&mut self) -> Result<&HashMap<String, CircuitInfo>> {
-        let circuits = self.controller.get_circuits().await?;
+        let mut circuits = self.controller.get_circuits().await?;
+        for (id, circuit) in circuits.iter_mut() {
+            if let Some(previous) = self.circuits.get(id) {
+                circuit.carry_telemetry(previous);
+            }
+            if circuit.status == CircuitStatus::Established {
+                circuit.established_for = (chrono::Utc::now() - circuit.created_at).to_std().ok();
+            }
+        }
         self.circuits = circuits;
         
         if let Some(default_id) = &self.default_circuit {
@@ -287,6 +406,7 @@ impl TorCircuit {
         };
         
         // Create the circuit
+        let started = Instant::now();
         let circuit_id = if exit_spec.is_empty() {
             self.controller.create_circuit(None).await?
         } else {
@@ -296,12 +416,50 @@ impl TorCircuit {
         // Update the last build time
         self.last_circuit_built = Some(Instant::now());
         
-        // Refresh circuit list to get the new circuit
-        self.refresh_circuits().await?;
+        // Wait for the circuit to be built
+        self.wait_until_built(&circuit_id, started).await?;
         
         Ok(circuit_id)
     }
     
+    /// Wait for a circuit to leave the building state and record its build latency
+    async fn wait_until_built(&mut self, circuit_id: &str, started: Instant) -> Result<()> {
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        
+        loop {
+            self.refresh_circuits().await?;
+            
+            let status = self.circuits.get(circuit_id).map(|circuit| circuit.status);
+            match status {
+                Some(CircuitStatus::Established) => {
+                    let latency = started.elapsed();
+                    if let Some(circuit) = self.circuits.get_mut(circuit_id) {
+                        circuit.build_latency = Some(latency);
+                    }
+                    
+                    self.circuits_built += 1;
+                    self.build_latencies.push_back(latency);
+                    while self.build_latencies.len() > MAX_BUILD_LATENCIES {
+                        self.build_latencies.pop_front();
+                    }
+                    
+                    return Ok(());
+                },
+                Some(CircuitStatus::Failed) | Some(CircuitStatus::Closed) => {
+                    self.build_failures += 1;
+                    return Err(Error::CircuitError(format!("Circuit {} failed to build", circuit_id)));
+                },
+                _ if started.elapsed() >= timeout => {
+                    self.build_failures += 1;
+                    return Err(Error::CircuitError(format!(
+                        "Circuit {} not built after {:?}", circuit_id, timeout
+                    )));
+                },
+                _ => tokio::time::sleep(BUILD_POLL_INTERVAL).await,
+            }
+        }
+    }
+    
     /// Close a specific circuit
     pub async fn close_circuit(&mut self, circuit_id: &str) -> Result<()> {
         if self.default_circuit.as_deref() == Some(circuit_id) {
@@ -355,6 +513,55 @@ impl TorCircuit {
         self.circuits.values().find(|&circuit| predicate(circuit))
     }
     
+    /// Record a transfer over the default circuit
+    pub fn record_transfer(&mut self, bytes: u64, duration: Duration) {
+        self.throughput.record(bytes, duration);
+        
+        if let Some(circuit) = self.default_circuit.as_ref().and_then(|id| self.circuits.get_mut(id)) {
+            circuit.throughput.record(bytes, duration);
+            circuit.in_use = true;
+        }
+    }
+    
+    /// Record a request that failed over the default circuit
+    pub fn record_request_failure(&mut self) {
+        self.request_failures += 1;
+        
+        if let Some(circuit) = self.default_circuit.as_ref().and_then(|id| self.circuits.get_mut(id)) {
+            circuit.failures += 1;
+        }
+    }
+    
+    /// Get the number of circuits built successfully
+    pub fn circuits_built(&self) -> u64 {
+        self.circuits_built
+    }
+    
+    /// Get the number of circuits that failed to build
+    pub fn build_failures(&self) -> u64 {
+        self.build_failures
+    }
+    
+    /// Get the average latency of recent circuit builds
+    pub fn average_build_latency(&self) -> Option<Duration> {
+        if self.build_latencies.is_empty() {
+            return None;
+        }
+        
+        let total: Duration = self.build_latencies.iter().sum();
+        Some(total / self.build_latencies.len() as u32)
+    }
+    
+    /// Get the traffic over all circuits
+    pub fn throughput(&self) -> &ThroughputStats {
+        &self.throughput
+    }
+    
+    /// Get the number of requests that failed over any circuit
+    pub fn request_failures(&self) -> u64 {
+        self.request_failures
+    }
+    
     /// Set a specific circuit as the default
     pub fn set_default_circuit(&mut self, circuit_id: &str) -> Result<()> {
         if !self.circuits.contains_key(circuit_id) {
@@ -399,6 +606,9 @@ mod tests {
             in_use: true,
             is_hidden_service: false,
             onion_service: None,
+            build_latency: Some(Duration::from_millis(1500)),
+            throughput: ThroughputStats::default(),
+            failures: 0,
         }
     }
     
@@ -444,5 +654,32 @@ mod tests {
         // Test path formatting
         let path = circuit.path_string();
         assert_eq!(path, "guard1(US) -> middle(FR) -> exit1(DE)");
+        assert_eq!(circuit.countries(), vec![Some("US"), Some("FR"), Some("DE")]);
+    }
+    
+    #[test]
+    fn test_throughput_and_telemetry() {
+        let mut previous = create_test_circuit_info();
+        assert_eq!(previous.throughput.bytes_per_second(), None);
+        
+        previous.throughput.record(1000, Duration::from_millis(500));
+        previous.throughput.record(3000, Duration::from_millis(1500));
+        previous.failures = 2;
+        assert_eq!(previous.throughput.bytes_per_second(), Some(2000.0));
+        assert_eq!(previous.throughput.total_bytes, 4000);
+        
+        for _ in 0..MAX_THROUGHPUT_SAMPLES {
+            previous.throughput.record(10, Duration::from_millis(10));
+        }
+        assert_eq!(previous.throughput.samples.len(), MAX_THROUGHPUT_SAMPLES);
+        assert_eq!(previous.throughput.transfers, MAX_THROUGHPUT_SAMPLES as u64 + 2);
+        
+        // A refreshed snapshot keeps the telemetry
+        let mut refreshed = create_test_circuit_info();
+        refreshed.build_latency = None;
+        refreshed.carry_telemetry(&previous);
+        assert_eq!(refreshed.build_latency, Some(Duration::from_millis(1500)));
+        assert_eq!(refreshed.failures, 2);
+        assert_eq!(refreshed.throughput.total_bytes, previous.throughput.total_bytes);
     }
 } 
\ No newline at end of file
@@ -4,9 +4,10 @@
 //! the Tor network, with capabilities for circuit management and stealth options.
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
-use reqwest::{Client, ClientBuilder, Proxy, Method, RequestBuilder, Response, StatusCode};
+use std::time::{Duration, Instant};
+use reqwest::{Client, ClientBuilder, Proxy, Method, RequestBuilder, Response, ResponseBuilderExt, StatusCode};
 use serde::{Deserialize, Serialize};
 use url::Url;
 use tokio::sync::{Mutex, RwLock};
@@ -15,10 +16,45 @@ use crate::Result;
 use crate::Error;
 use crate::TorConfig;
 use crate::TorCapable;
-use crate::circuit::{CircuitInfo, TorCircuit};
+use crate::circuit::{CircuitInfo, CircuitStatus, TorCircuit};
 use crate::controller::TorController;
 use crate::proxy::TorProxy;
 use crate::onion::OnionService;
+use crate::rotation::{BlockDetector, BlockReport, NewIdentityPolicy, SignatureDetector, UnblockedResponse};
+
+/// Snapshot of Tor telemetry, suitable for dashboards
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TorStats {
+    /// When the snapshot was taken
+    pub collected_at: chrono::DateTime<chrono::Utc>,
+    
+    /// Circuit currently used for requests, with its path and telemetry
+    pub current_circuit: Option<CircuitInfo>,
+    
+    /// Number of established circuits
+    pub open_circuits: usize,
+    
+    /// Number of circuits built successfully
+    pub circuits_built: u64,
+    
+    /// Number of circuits that failed to build
+    pub circuit_build_failures: u64,
+    
+    /// Average latency of recent circuit builds
+    pub average_build_latency: Option<Duration>,
+    
+    /// Number of requests completed
+    pub requests: u64,
+    
+    /// Number of requests that failed
+    pub request_failures: u64,
+    
+    /// Response body bytes received
+    pub bytes_received: u64,
+    
+    /// Recent throughput in bytes per second
+    pub throughput_bps: Option<f64>,
+}
 
 /// HTTP client that routes traffic through Tor
 #[derive(Debug)]
@@ -61,6 +97,21 @@ pub struct TorClient {
     
     /// Whether the client is initialized
     initialized: Arc<RwLock<bool>>,
+    
+    /// Policy for replacing the identity when a request is blocked
+    identity_policy: Option<NewIdentityPolicy>,
+    
+    /// Detector classifying responses as blocks
+    block_detector: Arc<dyn BlockDetector>,
+    
+    /// Number of identity changes made in response to blocks
+    identity_generation: Arc<AtomicU64>,
+    
+    /// Time of the last identity change, locked while an identity change is in progress
+    last_identity_change: Arc<Mutex<Option<Instant>>>,
+    
+    /// Exit IP observed after the last identity change
+    exit_ip: Arc<RwLock<Option<String>>>,
 }
 
 impl TorClient {
@@ -84,6 +135,11 @@ impl TorClient {
             request_count: Arc::new(Mutex::new(0)),
             stealth_mode: false,
             initialized: Arc::new(RwLock::new(false)),
+            identity_policy: None,
+            block_detector: Arc::new(SignatureDetector),
+            identity_generation: Arc::new(AtomicU64::new(0)),
+            last_identity_change: Arc::new(Mutex::new(None)),
+            exit_ip: Arc::new(RwLock::new(None)),
         }
     }
     
@@ -158,6 +214,18 @@ impl TorClient {
         self
     }
     
+    /// Replace the identity automatically when requests are blocked
+    pub fn with_new_identity_policy(mut self, policy: NewIdentityPolicy) -> Self {
+        self.identity_policy = Some(policy);
+        self
+    }
+    
+    /// Set the detector classifying responses as blocks
+    pub fn with_block_detector(mut self, detector: impl BlockDetector + 'static) -> Self {
+        self.block_detector = Arc::new(detector);
+        self
+    }
+    
     /// Add a default header to all requests
     pub async fn add_default_header(&self, name: &str, value: &str) -> Result<()> {
         let mut headers = self.default_headers.write().await;
@@ -184,6 +252,29 @@ impl TorClient {
         Ok(circuit.default_circuit_info().cloned())
     }
     
+    /// Get a snapshot of circuit and traffic telemetry
+    pub async fn stats(&self) -> TorStats {
+        let circuit = self.circuit.read().await;
+        let throughput = circuit.throughput();
+        
+        TorStats {
+            collected_at: chrono::Utc::now(),
+            current_circuit: circuit.default_circuit_info().cloned(),
+            open_circuits: circuit
+                .all_circuits()
+                .values()
+                .filter(|info| info.status == CircuitStatus::Established)
+                .count(),
+            circuits_built: circuit.circuits_built(),
+            circuit_build_failures: circuit.build_failures(),
+            average_build_latency: circuit.average_build_latency(),
+            requests: throughput.transfers,
+            request_failures: circuit.request_failures(),
+            bytes_received: throughput.total_bytes,
+            throughput_bps: throughput.bytes_per_second(),
+        }
+    }
+    
     /// Request a new circuit
     pub async fn new_circuit(&self) -> Result<String> {
         let mut circuit = self.circuit.write().await;
@@ -196,6 +287,117 @@ impl TorClient {
         Ok(circuit_id)
     }
     
+    /// Get the number of identity changes made in response to blocks
+    ///
+    /// Attach it to a [`BlockReport`] so that blocks seen by requests sent
+    /// before an identity change do not trigger another one.
+    pub fn identity_generation(&self) -> u64 {
+        self.identity_generation.load(Ordering::SeqCst)
+    }
+    
+    /// Report a block on the current identity
+    ///
+    /// If the identity policy covers the block, requests a new identity,
+    /// waiting for the policy's minimum interval since the last change, and
+    /// checks that the exit IP changed. Returns whether the blocked identity
+    /// has been replaced, either now or by a concurrent report.
+    pub async fn report_block(&self, report: &BlockReport) -> Result<bool> {
+        let policy = match &self.identity_policy {
+            Some(policy) if policy.applies_to(report.kind) => policy,
+            _ => return Ok(false),
+        };
+        
+        // Hold the lock for the whole change so concurrent reports share it
+        let mut last_change = self.last_identity_change.lock().await;
+        if report.generation.is_some_and(|generation| generation != self.identity_generation()) {
+            log::debug!("Identity already replaced since the request to {}", report.url);
+            return Ok(true);
+        }
+        
+        let wait = policy.wait_time(last_change.map(|changed| changed.elapsed()));
+        if !wait.is_zero() {
+            log::debug!("Waiting {:?} before requesting a new identity", wait);
+            tokio::time::sleep(wait).await;
+        }
+        
+        let previous_ip = if policy.verify_exit_ip {
+            match self.exit_ip.read().await.clone() {
+                Some(ip) => Some(ip),
+                None => self.get_ip().await.ok(),
+            }
+        } else {
+            None
+        };
+        
+        let attempts = policy.ip_change_attempts.max(1);
+        for attempt in 1..=attempts {
+            self.new_circuit().await?;
+            *last_change = Some(Instant::now());
+            
+            if !policy.verify_exit_ip {
+                self.identity_generation.fetch_add(1, Ordering::SeqCst);
+                return Ok(true);
+            }
+            
+            match self.get_ip().await {
+                Ok(ip) if previous_ip.as_deref() != Some(ip.as_str()) => {
+                    log::info!("New Tor identity after {:?} on {}: exit IP {}", report.kind, report.url, ip);
+                    *self.exit_ip.write().await = Some(ip);
+                    self.identity_generation.fetch_add(1, Ordering::SeqCst);
+                    return Ok(true);
+                },
+                Ok(ip) => log::warn!("Exit IP {} unchanged after new identity (attempt {}/{})", ip, attempt, attempts),
+                Err(e) => log::warn!("Failed to check exit IP after new identity: {}", e),
+            }
+            
+            if attempt < attempts {
+                tokio::time::sleep(policy.min_interval).await;
+            }
+        }
+        
+        Err(Error::CircuitError(format!(
+            "Exit IP did not change after {} identity changes", attempts
+        )))
+    }
+    
+    /// Make a GET request, replacing the identity and retrying while it is blocked
+    ///
+    /// Without an identity policy the request is sent once. The response
+    /// body is read to detect blocks, so it is returned as text.
+    pub async fn get_unblocked(&self, url: &str) -> Result<UnblockedResponse> {
+        let max_retries = self.identity_policy.as_ref().map_or(0, |policy| policy.max_retries);
+        let mut attempts = 0;
+        
+        loop {
+            let generation = self.identity_generation();
+            let response = self.get(url).await?;
+            attempts += 1;
+            
+            let status = response.status();
+            let headers = response.headers().clone();
+            let body = response.text().await.map_err(Error::HttpError)?;
+            let block = self.block_detector.detect(status, &headers, &body);
+            
+            let retry = match block {
+                Some(kind) if attempts <= max_retries => {
+                    log::warn!("{:?} on {} (attempt {})", kind, url, attempts);
+                    self.report_block(&BlockReport::new(url, kind).with_generation(generation)).await?
+                },
+                _ => false,
+            };
+            
+            if !retry {
+                return Ok(UnblockedResponse {
+                    status,
+                    headers,
+                    body,
+                    attempts,
+                    block,
+                });
+            }
+        }
+    }
+    
     /// Get the current IP address as seen from the Internet
     pub async fn get_ip(&self) -> Result<String> {
         // Ensure client is initialized
@@ -328,11 +530,45 @@ impl TorClient {
     }
     
     /// Send a prepared request
+    ///
+    /// The body is read here, so the circuit's throughput is sampled from
+    /// the bytes actually received and the time taken to receive them.
     async fn send_request(&self, request: RequestBuilder) -> Result<Response> {
-        // Send the request
-        let response = request.send().await.map_err(|e| Error::HttpError(e))?;
+        let started = Instant::now();
+        match Self::read_response(request).await {
+            Ok((response, bytes)) => {
+                self.circuit.write().await.record_transfer(bytes, started.elapsed());
+                Ok(response)
+            },
+            Err(e) => {
+                self.circuit.write().await.record_request_failure();
+                Err(Error::HttpError(e))
+            },
+        }
+    }
+    
+    /// Send a request and read its body, returning the response with the
+    /// body buffered and the number of body bytes received
+    async fn read_response(request: RequestBuilder) -> std::result::Result<(Response, u64), reqwest::Error> {
+        let mut response = request.send().await?;
+        
+        let capacity = response.content_length().unwrap_or(0).min(1 << 20) as usize;
+        let mut body = Vec::with_capacity(capacity);
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+        }
+        let bytes = body.len() as u64;
+        
+        let mut builder = http::Response::builder()
+            .status(response.status())
+            .version(response.version())
+            .url(response.url().clone());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = response.headers().clone();
+        }
+        let buffered = builder.body(body).expect("parts copied from a valid response");
         
-        Ok(response)
+        Ok((Response::from(buffered), bytes))
     }
     
     /// Ensure the client is initialized
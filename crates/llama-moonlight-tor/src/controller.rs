@@ -93,7 +93,7 @@ impl TorController {
     pub async fn authenticate(&self) -> Result<()> {
         if let Some(password) = &self.config.control_password {
             // Hash the password
-            let hashed_password = format!("\"{}\"", password); // In a real implementation, this should be hashed properly
+            let hashed_password = format!("\"{}\"", password.expose()); // In a real implementation, this should be hashed properly
             
             // Send authentication command
             let response = self.send_command(&format!("AUTHENTICATE {}", hashed_password)).await?;
@@ -372,13 +372,18 @@ fn parse_circuit_line(line: &str) -> Option<CircuitInfo> {
         CircuitPurpose::General
     };
     
-    // Extract build flags if provided
+    // Extract build flags and creation time if provided
     let mut build_flags = Vec::new();
-    if parts.len() > 4 {
-        for part in &parts[4..] {
+    let mut created_at = chrono::Utc::now();
+    if parts.len() > 3 {
+        for part in &parts[3..] {
             if part.starts_with("BUILD_FLAGS=") {
                 let flags = part.trim_start_matches("BUILD_FLAGS=");
                 build_flags = flags.split(',').map(|s| s.to_string()).collect();
+            } else if let Some(time) = part.strip_prefix("TIME_CREATED=") {
+                if let Ok(time) = chrono::NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S%.f") {
+                    created_at = time.and_utc();
+                }
             }
         }
     }
@@ -388,7 +393,7 @@ fn parse_circuit_line(line: &str) -> Option<CircuitInfo> {
         id,
         status,
         nodes,
-        created_at: chrono::Utc::now(),
+        created_at,
         established_for: None,
         purpose,
         build_reason: None,
@@ -396,6 +401,9 @@ fn parse_circuit_line(line: &str) -> Option<CircuitInfo> {
         in_use: false,
         is_hidden_service: matches!(purpose, CircuitPurpose::HiddenService),
         onion_service: None,
+        build_latency: None,
+        throughput: Default::default(),
+        failures: 0,
     })
 }
 
@@ -475,4 +483,12 @@ mod tests {
         assert_eq!(circuit.build_flags[0], "NEED_CAPACITY");
         assert_eq!(circuit.build_flags[1], "NEED_UPTIME");
     }
+    
+    #[test]
+    fn test_parse_circuit_line_with_time_created() {
+        let line = "4 BUILT A~AAAA,B~BBBB PURPOSE=GENERAL TIME_CREATED=2024-03-01T12:30:45.123456";
+        let circuit = parse_circuit_line(line).unwrap();
+        
+        assert_eq!(circuit.created_at.to_rfc3339(), "2024-03-01T12:30:45.123456+00:00");
+    }
 } 
\ No newline at end of file
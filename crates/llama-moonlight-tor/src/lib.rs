@@ -7,6 +7,8 @@
 #![forbid(unsafe_code)]
 #![warn(missing_docs)]
 
+use llama_moonlight_config::{ConfigSection, Secret};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 pub mod client;
@@ -17,6 +19,7 @@ pub mod engines;
 pub mod guard;
 pub mod onion;
 pub mod proxy;
+pub mod rotation;
 pub mod search;
 pub mod socks;
 pub mod utils;
@@ -98,7 +101,8 @@ pub fn version() -> &'static str {
 }
 
 /// Configuration for Tor operations
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TorConfig {
     /// Path to the Tor data directory
     pub data_dir: std::path::PathBuf,
@@ -119,7 +123,7 @@ pub struct TorConfig {
     pub control_port: u16,
     
     /// Authentication password for the control port
-    pub control_password: Option<String>,
+    pub control_password: Option<Secret>,
     
     /// Country codes to use for exit nodes (e.g. "US,DE,FR")
     pub exit_nodes: Option<String>,
@@ -156,6 +160,10 @@ impl Default for TorConfig {
     }
 }
 
+impl ConfigSection for TorConfig {
+    const SECTION: &'static str = "tor";
+}
+
 /// Capabilities for a browser or client that can use Tor
 pub trait TorCapable {
     /// Configure the browser or client to use Tor
@@ -175,9 +183,11 @@ pub trait TorCapable {
 }
 
 // Re-export key types for convenience
-pub use client::TorClient;
+pub use client::{TorClient, TorStats};
 pub use circuit::TorCircuit;
 pub use controller::TorController;
 pub use onion::OnionService;
 pub use proxy::TorProxy;
-pub use search::TorSearchEngine; 
\ No newline at end of file
+pub use rotation::{BlockKind, BlockReport, NewIdentityPolicy};
+pub use search::TorSearchEngine;
+pub use socks::{SocksMultiplexer, SocksMultiplexerHandle}; 
\ No newline at end of file
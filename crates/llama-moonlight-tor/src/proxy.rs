@@ -176,7 +176,7 @@ impl TorProxy {
         
         // If we have a control password, add it
         if let Some(pass) = &self.config.control_password {
-            cmd.arg("--HashedControlPassword").arg(pass);
+            cmd.arg("--HashedControlPassword").arg(pass.expose());
         } else {
             // Otherwise, allow connections without authentication
             cmd.arg("--CookieAuthentication").arg("1");
@@ -0,0 +1,236 @@
+//! Automatic identity rotation on blocks
+//!
+//! Exit relays are shared by many Tor users, so a site blocking or
+//! challenging one exit is common. This module lets the layers that detect
+//! blocks (Cloudflare challenge detection, stealth checks, or the default
+//! [`SignatureDetector`]) report them, and defines the policy a [`TorClient`]
+//! follows in response: request a new identity (NEWNYM), verify that the exit
+//! IP changed, and retry the request.
+//!
+//! [`TorClient`]: crate::client::TorClient
+
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Minimum interval between NEWNYM signals accepted by Tor
+pub const TOR_NEWNYM_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Kind of block reported for a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockKind {
+    /// Access denied
+    Blocked,
+    
+    /// CAPTCHA or interactive challenge
+    Captcha,
+    
+    /// Too many requests
+    RateLimited,
+}
+
+/// A block detected on a Tor-backed identity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockReport {
+    /// URL of the blocked request
+    pub url: String,
+    
+    /// Kind of block
+    pub kind: BlockKind,
+    
+    /// Identity generation the request was sent with, if known
+    ///
+    /// Reports for an identity that has already been replaced do not trigger
+    /// another rotation.
+    pub generation: Option<u64>,
+}
+
+impl BlockReport {
+    /// Create a report for a blocked URL
+    pub fn new(url: &str, kind: BlockKind) -> Self {
+        Self {
+            url: url.to_string(),
+            kind,
+            generation: None,
+        }
+    }
+    
+    /// Set the identity generation the request was sent with
+    pub fn with_generation(mut self, generation: u64) -> Self {
+        self.generation = Some(generation);
+        self
+    }
+}
+
+/// Detects blocks in responses
+pub trait BlockDetector: Send + Sync + std::fmt::Debug {
+    /// Classify a response, returning `None` if it is not a block
+    fn detect(&self, status: StatusCode, headers: &HeaderMap, body: &str) -> Option<BlockKind>;
+}
+
+/// Detector matching the signatures of common block and challenge pages
+///
+/// Recognizes Cloudflare challenges (a `cf-ray` header with a 403, 429 or 503
+/// status), CAPTCHA widgets, and plain 429 responses.
+#[derive(Debug, Clone, Default)]
+pub struct SignatureDetector;
+
+/// Markers of CAPTCHA widgets in page bodies
+const CAPTCHA_MARKERS: &[&str] = &["g-recaptcha", "h-captcha", "cf-turnstile", "captcha-delivery", "/captcha/"];
+
+/// Markers of Cloudflare interstitial pages in page bodies
+const CHALLENGE_MARKERS: &[&str] = &["Checking your browser", "cf-browser-verification", "challenge-platform", "Attention Required!"];
+
+impl BlockDetector for SignatureDetector {
+    fn detect(&self, status: StatusCode, headers: &HeaderMap, body: &str) -> Option<BlockKind> {
+        let cloudflare = headers.contains_key("cf-ray") || headers.contains_key("cf-mitigated");
+        
+        if CAPTCHA_MARKERS.iter().any(|marker| body.contains(marker)) && (cloudflare || status.is_client_error()) {
+            return Some(BlockKind::Captcha);
+        }
+        
+        match status {
+            StatusCode::TOO_MANY_REQUESTS => Some(BlockKind::RateLimited),
+            StatusCode::FORBIDDEN | StatusCode::SERVICE_UNAVAILABLE if cloudflare => {
+                if CHALLENGE_MARKERS.iter().any(|marker| body.contains(marker)) {
+                    Some(BlockKind::Captcha)
+                } else {
+                    Some(BlockKind::Blocked)
+                }
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Policy for replacing blocked Tor identities
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NewIdentityPolicy {
+    /// Number of times a blocked request is retried with a new identity
+    pub max_retries: u32,
+    
+    /// Minimum interval between two identity changes
+    pub min_interval: Duration,
+    
+    /// Whether to check that the exit IP changed after NEWNYM
+    pub verify_exit_ip: bool,
+    
+    /// Number of NEWNYM attempts to get a different exit IP
+    pub ip_change_attempts: u32,
+    
+    /// Kinds of blocks that trigger a new identity
+    pub rotate_on: Vec<BlockKind>,
+}
+
+impl Default for NewIdentityPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            min_interval: TOR_NEWNYM_INTERVAL,
+            verify_exit_ip: true,
+            ip_change_attempts: 3,
+            rotate_on: vec![BlockKind::Blocked, BlockKind::Captcha, BlockKind::RateLimited],
+        }
+    }
+}
+
+impl NewIdentityPolicy {
+    /// Set the number of retries with a new identity
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+    
+    /// Set the minimum interval between identity changes
+    ///
+    /// Intervals below Tor's own limit of 10 seconds are raised to it.
+    pub fn with_min_interval(mut self, min_interval: Duration) -> Self {
+        self.min_interval = min_interval.max(TOR_NEWNYM_INTERVAL);
+        self
+    }
+    
+    /// Set whether to check that the exit IP changed
+    pub fn with_verify_exit_ip(mut self, verify: bool) -> Self {
+        self.verify_exit_ip = verify;
+        self
+    }
+    
+    /// Check whether a kind of block triggers a new identity
+    pub fn applies_to(&self, kind: BlockKind) -> bool {
+        self.rotate_on.contains(&kind)
+    }
+    
+    /// Get how long to wait before the next identity change
+    pub fn wait_time(&self, since_last_change: Option<Duration>) -> Duration {
+        match since_last_change {
+            Some(elapsed) => self.min_interval.saturating_sub(elapsed),
+            None => Duration::ZERO,
+        }
+    }
+}
+
+/// Response to a request made under a [`NewIdentityPolicy`]
+#[derive(Debug, Clone)]
+pub struct UnblockedResponse {
+    /// Final status code
+    pub status: StatusCode,
+    
+    /// Final response headers
+    pub headers: HeaderMap,
+    
+    /// Final response body
+    pub body: String,
+    
+    /// Number of requests sent
+    pub attempts: u32,
+    
+    /// Block detected on the final response, if retries ran out
+    pub block: Option<BlockKind>,
+}
+
+impl UnblockedResponse {
+    /// Check whether the final response was not blocked
+    pub fn is_unblocked(&self) -> bool {
+        self.block.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    
+    #[test]
+    fn test_signature_detector() {
+        let detector = SignatureDetector;
+        let mut cloudflare = HeaderMap::new();
+        cloudflare.insert("cf-ray", HeaderValue::from_static("8a1b2c3d4e5f-AMS"));
+        let plain = HeaderMap::new();
+        
+        assert_eq!(detector.detect(StatusCode::OK, &plain, "<html>ok</html>"), None);
+        assert_eq!(detector.detect(StatusCode::FORBIDDEN, &plain, "Forbidden"), None);
+        assert_eq!(detector.detect(StatusCode::FORBIDDEN, &cloudflare, "Sorry, you have been blocked"), Some(BlockKind::Blocked));
+        assert_eq!(detector.detect(StatusCode::SERVICE_UNAVAILABLE, &cloudflare, "Checking your browser"), Some(BlockKind::Captcha));
+        assert_eq!(detector.detect(StatusCode::FORBIDDEN, &cloudflare, "<div class=\"cf-turnstile\">"), Some(BlockKind::Captcha));
+        assert_eq!(detector.detect(StatusCode::TOO_MANY_REQUESTS, &plain, ""), Some(BlockKind::RateLimited));
+    }
+    
+    #[test]
+    fn test_policy_rate_limit() {
+        let policy = NewIdentityPolicy::default().with_min_interval(Duration::from_secs(1));
+        assert_eq!(policy.min_interval, TOR_NEWNYM_INTERVAL);
+        
+        assert_eq!(policy.wait_time(None), Duration::ZERO);
+        assert_eq!(policy.wait_time(Some(Duration::from_secs(4))), Duration::from_secs(6));
+        assert_eq!(policy.wait_time(Some(Duration::from_secs(30))), Duration::ZERO);
+        
+        let policy = NewIdentityPolicy {
+            rotate_on: vec![BlockKind::Captcha],
+            ..Default::default()
+        };
+        assert!(policy.applies_to(BlockKind::Captcha));
+        assert!(!policy.applies_to(BlockKind::RateLimited));
+    }
+}
@@ -226,15 +226,14 @@ impl DarkWebSearch {
             let future = async move {
                 let start_time = Instant::now();
                 
-                // Make the request
-                let result = client_clone.get(&search_url).await;
+                // Make the request, replacing the identity if the engine blocks it
+                let result = client_clone.get_unblocked(&search_url).await;
                 
                 // Return engine name and results
                 match result {
                     Ok(response) => {
-                        if response.status().is_success() {
-                            let html = response.text().await.unwrap_or_default();
-                            let results = engine_clone.parse_results(&html);
+                        if response.status.is_success() && response.is_unblocked() {
+                            let results = engine_clone.parse_results(&response.body);
                             (engine_clone.name, results, true)
                         } else {
                             (engine_clone.name, Vec::new(), false)
@@ -0,0 +1,565 @@
+//! SOCKS multiplexer with per-session circuit isolation
+//!
+//! Tor puts streams whose SOCKS credentials differ on different circuits, but
+//! few tools know to vary their credentials. This module provides a local
+//! SOCKS5 listener that any SOCKS-capable client can use as an ordinary
+//! proxy: the username of each connection names a session, and every session
+//! is mapped to its own isolated Tor circuit. A session can be given a new
+//! identity without touching the others, so a pool of Tor exits can be
+//! consumed as if they were independent per-session proxies.
+
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use log::{debug, info, warn};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio_socks::tcp::Socks5Stream;
+use tokio_socks::TargetAddr;
+
+use crate::{Error, Result, TorConfig};
+
+/// Session of clients connecting without credentials
+pub const ANONYMOUS_SESSION: &str = "anonymous";
+
+/// Prefix of the sessions of isolated connections without credentials
+const ISOLATED_ANONYMOUS_PREFIX: &str = "anonymous#";
+
+/// Prefix of the credentials sent to Tor, keeping ours apart from other clients'
+const ISOLATION_PREFIX: &str = "moonlight";
+
+/// Longest time a client may take to negotiate
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// SOCKS protocol constants
+const SOCKS_VERSION: u8 = 0x05;
+const AUTH_VERSION: u8 = 0x01;
+const METHOD_NONE: u8 = 0x00;
+const METHOD_PASSWORD: u8 = 0x02;
+const METHOD_UNACCEPTABLE: u8 = 0xff;
+const COMMAND_CONNECT: u8 = 0x01;
+const ADDRESS_IPV4: u8 = 0x01;
+const ADDRESS_DOMAIN: u8 = 0x03;
+const ADDRESS_IPV6: u8 = 0x04;
+
+/// SOCKS5 reply codes
+const REPLY_SUCCEEDED: u8 = 0x00;
+const REPLY_GENERAL_FAILURE: u8 = 0x01;
+const REPLY_NOT_ALLOWED: u8 = 0x02;
+const REPLY_NETWORK_UNREACHABLE: u8 = 0x03;
+const REPLY_HOST_UNREACHABLE: u8 = 0x04;
+const REPLY_CONNECTION_REFUSED: u8 = 0x05;
+const REPLY_TTL_EXPIRED: u8 = 0x06;
+const REPLY_COMMAND_NOT_SUPPORTED: u8 = 0x07;
+const REPLY_ADDRESS_NOT_SUPPORTED: u8 = 0x08;
+
+/// Configuration of a SOCKS multiplexer
+#[derive(Debug, Clone)]
+pub struct SocksMultiplexerConfig {
+    /// Address the multiplexer listens on
+    pub listen_addr: SocketAddr,
+    
+    /// Address of the Tor SOCKS port
+    pub upstream_addr: String,
+    
+    /// Whether every connection without credentials gets its own circuit,
+    /// instead of all of them sharing the anonymous session
+    pub isolate_anonymous: bool,
+    
+    /// Longest time to wait for Tor to open a stream
+    pub connect_timeout: Duration,
+}
+
+impl SocksMultiplexerConfig {
+    /// Create a configuration forwarding to the SOCKS port of a Tor configuration
+    ///
+    /// The multiplexer listens on an ephemeral localhost port.
+    pub fn new(config: &TorConfig) -> Self {
+        Self {
+            listen_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+            upstream_addr: format!("{}:{}", config.socks_host, config.socks_port),
+            isolate_anonymous: false,
+            connect_timeout: Duration::from_secs(config.timeout_secs.max(1)),
+        }
+    }
+}
+
+/// Usage of a session
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionStats {
+    /// Session name, the SOCKS username
+    pub name: String,
+    
+    /// Identity generation, bumped on every new identity
+    pub generation: u64,
+    
+    /// Connections opened
+    pub connections: u64,
+    
+    /// Connections open now
+    pub active: u64,
+    
+    /// Connections Tor failed to open
+    pub failures: u64,
+    
+    /// Bytes sent by the clients
+    pub bytes_sent: u64,
+    
+    /// Bytes received by the clients
+    pub bytes_received: u64,
+    
+    /// When the session was last used
+    pub last_used: Instant,
+}
+
+impl SessionStats {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            generation: 0,
+            connections: 0,
+            active: 0,
+            failures: 0,
+            bytes_sent: 0,
+            bytes_received: 0,
+            last_used: Instant::now(),
+        }
+    }
+}
+
+/// Sessions of a multiplexer, by name
+#[derive(Debug, Default)]
+struct Sessions {
+    sessions: Mutex<HashMap<String, SessionStats>>,
+    anonymous: AtomicU64,
+}
+
+impl Sessions {
+    /// Open a connection of a session, returning the credentials isolating it
+    fn open(&self, name: &str) -> (String, String) {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.entry(name.to_string()).or_insert_with(|| SessionStats::new(name));
+        session.connections += 1;
+        session.active += 1;
+        session.last_used = Instant::now();
+        
+        isolation_credentials(name, session.generation)
+    }
+    
+    /// Close a connection of a session
+    fn close(&self, name: &str, connected: bool, bytes_sent: u64, bytes_received: u64) {
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(session) = sessions.get_mut(name) {
+            session.active = session.active.saturating_sub(1);
+            session.bytes_sent += bytes_sent;
+            session.bytes_received += bytes_received;
+            session.last_used = Instant::now();
+            if !connected {
+                session.failures += 1;
+            }
+        }
+        
+        // Sessions of isolated anonymous connections are never reused
+        if name.starts_with(ISOLATED_ANONYMOUS_PREFIX) {
+            sessions.remove(name);
+        }
+    }
+    
+    /// Move a session to a new identity generation, returning it
+    fn new_identity(&self, name: &str) -> u64 {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.entry(name.to_string()).or_insert_with(|| SessionStats::new(name));
+        session.generation += 1;
+        session.generation
+    }
+    
+    /// Name a session for a connection without credentials
+    fn anonymous(&self, isolate: bool) -> String {
+        if isolate {
+            format!("{}{}", ISOLATED_ANONYMOUS_PREFIX, self.anonymous.fetch_add(1, Ordering::Relaxed))
+        } else {
+            ANONYMOUS_SESSION.to_string()
+        }
+    }
+}
+
+/// Get the credentials sent to Tor for a session generation
+fn isolation_credentials(session: &str, generation: u64) -> (String, String) {
+    (format!("{}:{}", ISOLATION_PREFIX, session), generation.to_string())
+}
+
+/// Local SOCKS5 proxy mapping sessions to isolated Tor circuits
+#[derive(Debug)]
+pub struct SocksMultiplexer {
+    config: SocksMultiplexerConfig,
+}
+
+impl SocksMultiplexer {
+    /// Create a multiplexer forwarding to the SOCKS port of a Tor configuration
+    pub fn new(config: &TorConfig) -> Self {
+        Self::with_config(SocksMultiplexerConfig::new(config))
+    }
+    
+    /// Create a multiplexer from its configuration
+    pub fn with_config(config: SocksMultiplexerConfig) -> Self {
+        Self { config }
+    }
+    
+    /// Set the address to listen on
+    pub fn with_listen_addr(mut self, addr: SocketAddr) -> Self {
+        self.config.listen_addr = addr;
+        self
+    }
+    
+    /// Set whether every connection without credentials gets its own circuit
+    pub fn with_isolate_anonymous(mut self, isolate: bool) -> Self {
+        self.config.isolate_anonymous = isolate;
+        self
+    }
+    
+    /// Start listening, serving clients in the background
+    pub async fn start(self) -> Result<SocksMultiplexerHandle> {
+        let listener = TcpListener::bind(self.config.listen_addr).await.map_err(|e| {
+            Error::SocksError(format!("Failed to listen on {}: {}", self.config.listen_addr, e))
+        })?;
+        let local_addr = listener.local_addr()?;
+        let sessions = Arc::new(Sessions::default());
+        let config = Arc::new(self.config);
+        
+        info!("SOCKS multiplexer listening on {}, forwarding to Tor at {}", local_addr, config.upstream_addr);
+        let task = tokio::spawn(accept_loop(listener, config, sessions.clone()));
+        
+        Ok(SocksMultiplexerHandle {
+            local_addr,
+            sessions,
+            task,
+        })
+    }
+}
+
+/// Running SOCKS multiplexer
+///
+/// Dropping the handle stops accepting connections; open connections are
+/// served until they close.
+#[derive(Debug)]
+pub struct SocksMultiplexerHandle {
+    local_addr: SocketAddr,
+    sessions: Arc<Sessions>,
+    task: JoinHandle<()>,
+}
+
+impl SocksMultiplexerHandle {
+    /// Get the address the multiplexer listens on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+    
+    /// Get the proxy URL of a session, e.g. for a proxy pool
+    ///
+    /// Host names are resolved by Tor, so the URL uses the `socks5h` scheme.
+    pub fn proxy_url(&self, session: &str) -> String {
+        let mut url = url::Url::parse(&format!("socks5h://{}", self.local_addr)).expect("socket address is a valid host");
+        // The password is ignored but many clients need one
+        let _ = url.set_username(session);
+        let _ = url.set_password(Some("x"));
+        url.to_string()
+    }
+    
+    /// Give a session a new identity
+    ///
+    /// Connections opened afterwards use a new circuit, and usually a new
+    /// exit; open connections keep their circuit. Returns the new generation.
+    pub fn new_identity(&self, session: &str) -> u64 {
+        let generation = self.sessions.new_identity(session);
+        debug!("Session '{}' moved to identity generation {}", session, generation);
+        generation
+    }
+    
+    /// Get the usage of a session
+    pub fn session(&self, session: &str) -> Option<SessionStats> {
+        self.sessions.sessions.lock().unwrap().get(session).cloned()
+    }
+    
+    /// Get the usage of every session, by name
+    pub fn sessions(&self) -> Vec<SessionStats> {
+        let mut sessions: Vec<SessionStats> = self.sessions.sessions.lock().unwrap().values().cloned().collect();
+        sessions.sort_by(|a, b| a.name.cmp(&b.name));
+        sessions
+    }
+    
+    /// Stop accepting connections
+    pub fn shutdown(self) {}
+}
+
+impl Drop for SocksMultiplexerHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Accept clients until the listener fails
+async fn accept_loop(listener: TcpListener, config: Arc<SocksMultiplexerConfig>, sessions: Arc<Sessions>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                let config = config.clone();
+                let sessions = sessions.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, &config, &sessions).await {
+                        debug!("SOCKS connection from {} ended: {}", peer, e);
+                    }
+                });
+            }
+            Err(e) => {
+                warn!("Failed to accept SOCKS connection: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+    }
+}
+
+/// Serve a client: negotiate, open the stream through Tor and relay
+async fn serve(mut client: TcpStream, config: &SocksMultiplexerConfig, sessions: &Sessions) -> Result<()> {
+    let request = tokio::time::timeout(HANDSHAKE_TIMEOUT, negotiate(&mut client))
+        .await
+        .map_err(|_| Error::SocksError("Client handshake timed out".to_string()))??;
+    
+    let session = match request.username {
+        Some(username) if !username.is_empty() => username,
+        _ => sessions.anonymous(config.isolate_anonymous),
+    };
+    let (username, password) = sessions.open(&session);
+    
+    let upstream = tokio::time::timeout(
+        config.connect_timeout,
+        Socks5Stream::connect_with_password(config.upstream_addr.as_str(), request.target.clone(), &username, &password),
+    )
+    .await;
+    let mut upstream = match upstream {
+        Ok(Ok(upstream)) => upstream,
+        Ok(Err(e)) => {
+            sessions.close(&session, false, 0, 0);
+            write_reply(&mut client, reply_code(&e)).await?;
+            return Err(Error::SocksError(format!("Tor failed to connect to {}: {}", describe(&request.target), e)));
+        }
+        Err(_) => {
+            sessions.close(&session, false, 0, 0);
+            write_reply(&mut client, REPLY_TTL_EXPIRED).await?;
+            return Err(Error::SocksError(format!("Timed out connecting to {}", describe(&request.target))));
+        }
+    };
+    
+    write_reply(&mut client, REPLY_SUCCEEDED).await?;
+    debug!("Session '{}' connected to {}", session, describe(&request.target));
+    
+    let relayed = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+    let (sent, received) = relayed.as_ref().map(|(sent, received)| (*sent, *received)).unwrap_or_default();
+    sessions.close(&session, true, sent, received);
+    
+    relayed.map(|_| ()).map_err(Error::from)
+}
+
+/// Connection request of a client
+#[derive(Debug, Clone, PartialEq)]
+struct ConnectRequest {
+    /// Username given by the client, if it authenticated
+    username: Option<String>,
+    
+    /// Destination of the connection
+    target: TargetAddr<'static>,
+}
+
+/// Negotiate with a client up to its CONNECT request
+///
+/// Answers unsupported methods, commands and address types as the protocol
+/// requires before failing.
+async fn negotiate<S: AsyncRead + AsyncWrite + Unpin>(client: &mut S) -> Result<ConnectRequest> {
+    // Greeting: version, methods
+    let version = client.read_u8().await?;
+    if version != SOCKS_VERSION {
+        return Err(Error::SocksError(format!("Unsupported SOCKS version {}", version)));
+    }
+    let count = client.read_u8().await? as usize;
+    let mut methods = vec![0; count];
+    client.read_exact(&mut methods).await?;
+    
+    let method = if methods.contains(&METHOD_PASSWORD) {
+        METHOD_PASSWORD
+    } else if methods.contains(&METHOD_NONE) {
+        METHOD_NONE
+    } else {
+        METHOD_UNACCEPTABLE
+    };
+    client.write_all(&[SOCKS_VERSION, method]).await?;
+    if method == METHOD_UNACCEPTABLE {
+        return Err(Error::SocksError("No acceptable authentication method".to_string()));
+    }
+    
+    // Username and password; any pair is accepted, the username names the session
+    let mut username = None;
+    if method == METHOD_PASSWORD {
+        let version = client.read_u8().await?;
+        if version != AUTH_VERSION {
+            return Err(Error::SocksError(format!("Unsupported authentication version {}", version)));
+        }
+        let name = read_string(client).await?;
+        let _password = read_string(client).await?;
+        client.write_all(&[AUTH_VERSION, 0x00]).await?;
+        username = Some(name);
+    }
+    
+    // Request: version, command, reserved, address
+    let mut header = [0; 4];
+    client.read_exact(&mut header).await?;
+    let [version, command, _, address_type] = header;
+    if version != SOCKS_VERSION {
+        return Err(Error::SocksError(format!("Unsupported SOCKS version {}", version)));
+    }
+    if command != COMMAND_CONNECT {
+        write_reply(client, REPLY_COMMAND_NOT_SUPPORTED).await?;
+        return Err(Error::SocksError(format!("Unsupported SOCKS command {}", command)));
+    }
+    
+    let target = match address_type {
+        ADDRESS_IPV4 => {
+            let mut ip = [0; 4];
+            client.read_exact(&mut ip).await?;
+            let port = client.read_u16().await?;
+            TargetAddr::Ip(SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::from(ip), port)))
+        }
+        ADDRESS_IPV6 => {
+            let mut ip = [0; 16];
+            client.read_exact(&mut ip).await?;
+            let port = client.read_u16().await?;
+            TargetAddr::Ip(SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::from(ip), port, 0, 0)))
+        }
+        ADDRESS_DOMAIN => {
+            let domain = read_string(client).await?;
+            let port = client.read_u16().await?;
+            TargetAddr::Domain(domain.into(), port)
+        }
+        other => {
+            write_reply(client, REPLY_ADDRESS_NOT_SUPPORTED).await?;
+            return Err(Error::SocksError(format!("Unsupported address type {}", other)));
+        }
+    };
+    
+    Ok(ConnectRequest { username, target })
+}
+
+/// Read a string prefixed by its length in one byte
+async fn read_string<S: AsyncRead + Unpin>(client: &mut S) -> Result<String> {
+    let length = client.read_u8().await? as usize;
+    let mut bytes = vec![0; length];
+    client.read_exact(&mut bytes).await?;
+    
+    String::from_utf8(bytes).map_err(|_| Error::SocksError("Invalid UTF-8 in SOCKS request".to_string()))
+}
+
+/// Answer a CONNECT request
+///
+/// The bound address is not meaningful through Tor and is always reported
+/// as 0.0.0.0:0.
+async fn write_reply<S: AsyncWrite + Unpin>(client: &mut S, code: u8) -> Result<()> {
+    client
+        .write_all(&[SOCKS_VERSION, code, 0x00, ADDRESS_IPV4, 0, 0, 0, 0, 0, 0])
+        .await?;
+    Ok(())
+}
+
+/// Get the reply code passing on a failure of Tor to the client
+fn reply_code(error: &tokio_socks::Error) -> u8 {
+    match error {
+        tokio_socks::Error::ConnectionNotAllowedByRuleset => REPLY_NOT_ALLOWED,
+        tokio_socks::Error::NetworkUnreachable => REPLY_NETWORK_UNREACHABLE,
+        tokio_socks::Error::HostUnreachable => REPLY_HOST_UNREACHABLE,
+        tokio_socks::Error::ConnectionRefused => REPLY_CONNECTION_REFUSED,
+        tokio_socks::Error::TtlExpired => REPLY_TTL_EXPIRED,
+        tokio_socks::Error::AddressTypeNotSupported => REPLY_ADDRESS_NOT_SUPPORTED,
+        _ => REPLY_GENERAL_FAILURE,
+    }
+}
+
+/// Describe the destination of a connection for logs
+fn describe(target: &TargetAddr<'_>) -> String {
+    match target {
+        TargetAddr::Ip(addr) => addr.to_string(),
+        TargetAddr::Domain(domain, port) => format!("{}:{}", domain, port),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_negotiate_with_password() {
+        let (mut client, mut server) = tokio::io::duplex(256);
+        let negotiation = tokio::spawn(async move { negotiate(&mut server).await });
+        
+        client.write_all(&[5, 2, 0, 2]).await.unwrap();
+        let mut choice = [0; 2];
+        client.read_exact(&mut choice).await.unwrap();
+        assert_eq!(choice, [5, 2]);
+        
+        client.write_all(&[1, 5]).await.unwrap();
+        client.write_all(b"alice").await.unwrap();
+        client.write_all(&[1, b'x']).await.unwrap();
+        let mut status = [0; 2];
+        client.read_exact(&mut status).await.unwrap();
+        assert_eq!(status, [1, 0]);
+        
+        client.write_all(&[5, 1, 0, 3, 11]).await.unwrap();
+        client.write_all(b"example.com").await.unwrap();
+        client.write_all(&443u16.to_be_bytes()).await.unwrap();
+        
+        let request = negotiation.await.unwrap().unwrap();
+        assert_eq!(request.username.as_deref(), Some("alice"));
+        assert_eq!(request.target, TargetAddr::Domain("example.com".into(), 443));
+    }
+    
+    #[tokio::test]
+    async fn test_negotiate_rejects_bind() {
+        let (mut client, mut server) = tokio::io::duplex(256);
+        let negotiation = tokio::spawn(async move { negotiate(&mut server).await });
+        
+        client.write_all(&[5, 1, 0]).await.unwrap();
+        let mut choice = [0; 2];
+        client.read_exact(&mut choice).await.unwrap();
+        assert_eq!(choice, [5, 0]);
+        
+        client.write_all(&[5, 2, 0, 1, 127, 0, 0, 1, 0, 80]).await.unwrap();
+        let mut reply = [0; 10];
+        client.read_exact(&mut reply).await.unwrap();
+        assert_eq!(reply[1], REPLY_COMMAND_NOT_SUPPORTED);
+        assert!(negotiation.await.unwrap().is_err());
+    }
+    
+    #[test]
+    fn test_sessions() {
+        let sessions = Sessions::default();
+        assert_eq!(sessions.open("alice"), isolation_credentials("alice", 0));
+        assert_ne!(sessions.open("alice"), sessions.open("bob"));
+        sessions.close("alice", true, 10, 20);
+        
+        let stats = sessions.sessions.lock().unwrap().get("alice").cloned().unwrap();
+        assert_eq!(stats.connections, 2);
+        assert_eq!(stats.active, 1);
+        assert_eq!(stats.bytes_sent, 10);
+        
+        // A new identity changes the credentials of later connections
+        assert_eq!(sessions.new_identity("alice"), 1);
+        assert_eq!(sessions.open("alice"), isolation_credentials("alice", 1));
+        
+        // Isolated anonymous sessions are distinct and forgotten once closed
+        let first = sessions.anonymous(true);
+        assert_ne!(first, sessions.anonymous(true));
+        assert_eq!(sessions.anonymous(false), ANONYMOUS_SESSION);
+        sessions.open(&first);
+        sessions.close(&first, true, 0, 0);
+        assert!(sessions.sessions.lock().unwrap().get(&first).is_none());
+    }
+}
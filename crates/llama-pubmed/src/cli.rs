@@ -1,5 +1,8 @@
 use crate::config_manager;
 use crate::error_handling;
+use crate::export;
+use crate::graph;
+use crate::mesh;
 use crate::metadata_manager;
 use crate::pdf_downloader;
 use crate::pubmed_api;
@@ -12,7 +15,7 @@ use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 // Import the structs from the main module to handle args.
-use crate::{ConfigArgs, DownloadArgs, SearchArgs};
+use crate::{ConfigArgs, DownloadArgs, ExportArgs, GraphArgs, MeshArgs, SearchArgs};
 
 // Define types for error handling throughout the CLI module.
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
@@ -65,6 +68,7 @@ pub async fn handle_download(args: DownloadArgs) -> Result<()> {
             }
         }
     } else if let Some(search_query) = args.search {
+        let search_query = expand_query(&client, &search_query, &args.mesh).await?;
         println!("Searching PubMed for: {}", search_query.bright_cyan());
         let pb = ProgressBar::new_spinner();
         pb.set_style(
@@ -157,8 +161,9 @@ pub async fn handle_download(args: DownloadArgs) -> Result<()> {
 
 pub async fn handle_search(args: SearchArgs) -> Result<()> {
     let client = reqwest::Client::new();
+    let query = expand_query(&client, &args.query, &args.mesh).await?;
     
-    println!("Searching PubMed for: {}", args.query.bright_cyan());
+    println!("Searching PubMed for: {}", query.bright_cyan());
     
     let pb = ProgressBar::new_spinner();
     pb.set_style(
@@ -171,7 +176,7 @@ pub async fn handle_search(args: SearchArgs) -> Result<()> {
     
     let search_results = pubmed_api::search_pubmed(
         &client,
-        &args.query,
+        &query,
         args.date_range,
         args.journal,
     )
@@ -223,6 +228,162 @@ pub async fn handle_search(args: SearchArgs) -> Result<()> {
     Ok(())
 }
 
+pub async fn handle_export(args: ExportArgs) -> Result<()> {
+    // Check the format before anything runs so a bad one leaves --output untouched
+    if !matches!(args.format.as_str(), "csv" | "jsonl") {
+        return Err(error_handling::LlamaError::InvalidArgument(format!(
+            "Unsupported export format: {}", args.format
+        )).into());
+    }
+
+    let mut ids: Vec<String> = Vec::new();
+
+    if let Some(ids_str) = args.ids {
+        ids.extend(ids_str.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()));
+    }
+    if let Some(ids_file_path) = args.ids_file {
+        let reader = BufReader::new(File::open(ids_file_path)?);
+        for line in reader.lines() {
+            if let Some(id) = line?.split_whitespace().next() {
+                ids.push(id.to_string());
+            }
+        }
+    }
+
+    if ids.is_empty() {
+        return Err(error_handling::LlamaError::InvalidArgument(
+            "No identifiers provided. Use --ids or --ids-file.".to_string()
+        ).into());
+    }
+
+    let mut pipeline = export::ExportPipeline::new(reqwest::Client::new());
+    if let Some(pdf_dir) = &args.pdf_dir {
+        pipeline = pipeline.with_pdf_dir(pdf_dir);
+    }
+
+    let records = pipeline.run(&ids).await?;
+    let writer: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(std::io::stdout()),
+    };
+
+    if args.format == "csv" {
+        export::write_csv(&records, writer)?;
+    } else {
+        export::write_jsonl(&records, writer)?;
+    }
+
+    if let Some(path) = &args.output {
+        println!("Exported {} records from {} identifiers to: {}", 
+            records.len().to_string().bright_green(), 
+            ids.len().to_string().bright_cyan(),
+            path.display().to_string().bright_cyan()
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn handle_graph(args: GraphArgs) -> Result<()> {
+    // Check the format before anything runs so a bad one leaves --output untouched
+    if !matches!(args.format.as_str(), "json" | "dot") {
+        return Err(error_handling::LlamaError::InvalidArgument(format!(
+            "Unsupported graph format: {}", args.format
+        )).into());
+    }
+
+    let config = match &args.config {
+        Some(config_path) => config_manager::load_config_from_file(config_path)?,
+        None => config_manager::load_default_config()?,
+    };
+
+    let mut pmids: Vec<String> = Vec::new();
+
+    if let Some(pmids_str) = args.pmids {
+        pmids.extend(pmids_str.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()));
+    }
+    if let Some(pmids_file_path) = args.pmids_file {
+        let reader = BufReader::new(File::open(pmids_file_path)?);
+        for line in reader.lines() {
+            if let Some(pmid) = line?.split_whitespace().next() {
+                pmids.push(pmid.to_string());
+            }
+        }
+    }
+
+    if pmids.is_empty() {
+        return Err(error_handling::LlamaError::InvalidArgument(
+            "No PMIDs provided. Use --pmids or --pmids-file.".to_string()
+        ).into());
+    }
+
+    let kinds = args
+        .links
+        .split(',')
+        .map(graph::LinkKind::parse)
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let limits = graph::ExpansionLimits {
+        hops: args.hops,
+        max_per_hop: args.max_per_hop,
+        max_per_node: args.max_per_node,
+        min_score: args.min_score,
+        ..graph::ExpansionLimits::default()
+    };
+
+    let mut client = reqwest::Client::builder();
+    if let Some(user_agent) = &config.user_agent {
+        client = client.user_agent(user_agent);
+    }
+
+    let mut expander = graph::GraphExpander::new(client.build()?)
+        .with_link_kinds(kinds)
+        .with_limits(limits);
+    if let Some(api_key) = &config.api_key {
+        expander = expander.with_api_key(api_key);
+    }
+    let citation_graph = expander.expand(&pmids).await?;
+
+    let writer: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(std::io::stdout()),
+    };
+
+    if args.format == "json" {
+        graph::write_json(&citation_graph, writer)?;
+    } else {
+        graph::write_dot(&citation_graph, writer)?;
+    }
+
+    if let Some(path) = &args.candidates {
+        graph::write_candidates(&citation_graph, File::create(path)?)?;
+        println!("Found {} candidate papers from {} seeds, saved to: {}",
+            citation_graph.candidates().len().to_string().bright_green(),
+            pmids.len().to_string().bright_cyan(),
+            path.display().to_string().bright_cyan()
+        );
+    }
+
+    Ok(())
+}
+
+/// Expand a query with MeSH terms if requested, printing how each term was expanded.
+async fn expand_query(client: &reqwest::Client, query: &str, args: &MeshArgs) -> Result<String> {
+    if !args.mesh {
+        return Ok(query.to_string());
+    }
+
+    let expander = args.no_expand.iter().fold(
+        mesh::MeshExpander::new(client.clone()).with_explode(!args.no_explode),
+        |expander, term| expander.skip_term(term),
+    );
+
+    let expanded = expander.expand_query(query).await?;
+    println!("{}", "MeSH expansion:".bright_green());
+    println!("{}", expanded);
+
+    Ok(expanded.expanded)
+}
+
 pub fn handle_config(args: ConfigArgs) -> Result<()> {
     match args {
         ConfigArgs::Show => {
@@ -27,6 +27,9 @@ pub struct Config {
     pub max_retries: u32,
     pub user_agent: Option<String>,
     pub finders: Vec<String>,
+    /// NCBI API key, which raises the E-utilities rate limit
+    #[serde(default)]
+    pub api_key: Option<String>,
 }
 
 // Define the default configuration values.
@@ -48,6 +51,7 @@ impl Default for Config {
                 "science_direct".to_string(),
                 "direct_pdf_link".to_string(),
             ],
+            api_key: None,
         }
     }
 }
@@ -0,0 +1,556 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use log::{debug, warn};
+
+const IDCONV_URL: &str = "https://www.ncbi.nlm.nih.gov/pmc/utils/idconv/v1.0/";
+const EUTILS_BASE_URL: &str = "https://eutils.ncbi.nlm.nih.gov/entrez/eutils";
+const BATCH_SIZE: usize = 200;
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("Network error: {0}")]
+    Network(#[from] reqwest::Error),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("API response error: {0}")]
+    ApiResponse(String),
+
+    #[error("Unrecognized identifier: {0}")]
+    InvalidIdentifier(String),
+}
+
+/// An article identifier in one of the supported databases
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Identifier {
+    Pmid(String),
+    Pmcid(String),
+    Doi(String),
+}
+
+impl Identifier {
+    /// Recognize a PMID, PMCID, or DOI, including URL and prefixed forms
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        let lower = input.to_lowercase();
+
+        for prefix in ["https://doi.org/", "http://doi.org/", "https://dx.doi.org/", "doi:"] {
+            if lower.starts_with(prefix) {
+                return normalize_doi(&input[prefix.len()..]).map(Identifier::Doi);
+            }
+        }
+        if lower.starts_with("10.") {
+            return normalize_doi(input).map(Identifier::Doi);
+        }
+        if lower.starts_with("pmc") {
+            return normalize_pmcid(input).map(Identifier::Pmcid);
+        }
+
+        let pmid = lower.strip_prefix("pmid:").unwrap_or(&lower).trim();
+        if !pmid.is_empty() && pmid.chars().all(|c| c.is_ascii_digit()) {
+            return Some(Identifier::Pmid(pmid.to_string()));
+        }
+
+        None
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Identifier::Pmid(_) => "pmid",
+            Identifier::Pmcid(_) => "pmcid",
+            Identifier::Doi(_) => "doi",
+        }
+    }
+
+    fn value(&self) -> &str {
+        match self {
+            Identifier::Pmid(value) | Identifier::Pmcid(value) | Identifier::Doi(value) => value,
+        }
+    }
+}
+
+/// A normalized article record, reconciled across PubMed, PMC, and DOI
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ExportRecord {
+    pub pmid: Option<String>,
+    pub pmcid: Option<String>,
+    pub doi: Option<String>,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub journal: String,
+    pub year: Option<u32>,
+    pub pubmed_url: Option<String>,
+    pub pmc_url: Option<String>,
+    pub doi_url: Option<String>,
+    pub pdf_path: Option<PathBuf>,
+    /// Input identifiers that resolved to this record
+    pub input_ids: Vec<String>,
+}
+
+impl ExportRecord {
+    /// Keys under which two records are considered the same article
+    fn keys(&self) -> Vec<String> {
+        let mut keys = Vec::new();
+        if let Some(pmid) = &self.pmid {
+            keys.push(format!("pmid:{}", pmid));
+        }
+        if let Some(pmcid) = &self.pmcid {
+            keys.push(format!("pmcid:{}", pmcid));
+        }
+        if let Some(doi) = &self.doi {
+            keys.push(format!("doi:{}", doi));
+        }
+
+        let title: String = self
+            .title
+            .to_lowercase()
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect();
+        if !title.is_empty() {
+            keys.push(format!("title:{}:{}", title, self.year.unwrap_or(0)));
+        }
+
+        keys
+    }
+
+    /// Fill missing fields from another record of the same article
+    fn merge(&mut self, other: ExportRecord) {
+        self.pmid = self.pmid.take().or(other.pmid);
+        self.pmcid = self.pmcid.take().or(other.pmcid);
+        self.doi = self.doi.take().or(other.doi);
+        if self.title.is_empty() {
+            self.title = other.title;
+        }
+        if self.authors.is_empty() {
+            self.authors = other.authors;
+        }
+        if self.journal.is_empty() {
+            self.journal = other.journal;
+        }
+        self.year = self.year.or(other.year);
+        self.pdf_path = self.pdf_path.take().or(other.pdf_path);
+        for id in other.input_ids {
+            if !self.input_ids.contains(&id) {
+                self.input_ids.push(id);
+            }
+        }
+    }
+
+    fn fill_links(&mut self) {
+        self.pubmed_url = self.pmid.as_ref().map(|pmid| format!("https://pubmed.ncbi.nlm.nih.gov/{}/", pmid));
+        self.pmc_url = self.pmcid.as_ref().map(|pmcid| format!("https://www.ncbi.nlm.nih.gov/pmc/articles/{}/", pmcid));
+        self.doi_url = self.doi.as_ref().map(|doi| format!("https://doi.org/{}", doi));
+    }
+}
+
+/// Merge records that share a PMID, PMCID, DOI, or title and year
+pub fn deduplicate(records: Vec<ExportRecord>) -> Vec<ExportRecord> {
+    // Union-find over record indices, joined through shared keys
+    let mut parent: Vec<usize> = (0..records.len()).collect();
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut owners: HashMap<String, usize> = HashMap::new();
+    for (i, record) in records.iter().enumerate() {
+        for key in record.keys() {
+            match owners.get(&key) {
+                Some(&owner) => {
+                    let (a, b) = (find(&mut parent, owner), find(&mut parent, i));
+                    if a != b {
+                        parent[b.max(a)] = a.min(b);
+                    }
+                }
+                None => {
+                    owners.insert(key, i);
+                }
+            }
+        }
+    }
+
+    let mut merged: Vec<Option<ExportRecord>> = vec![None; records.len()];
+    for (i, record) in records.into_iter().enumerate() {
+        let root = find(&mut parent, i);
+        match &mut merged[root] {
+            Some(existing) => existing.merge(record),
+            slot => *slot = Some(record),
+        }
+    }
+
+    merged
+        .into_iter()
+        .flatten()
+        .map(|mut record| {
+            record.fill_links();
+            record
+        })
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct IdConvResponse {
+    #[serde(default)]
+    records: Vec<IdConvRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdConvRecord {
+    #[serde(rename = "requested-id")]
+    requested_id: Option<String>,
+    pmid: Option<String>,
+    pmcid: Option<String>,
+    doi: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ESummaryResponse {
+    result: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PubMedSummary {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    authors: Vec<PubMedAuthor>,
+    #[serde(default)]
+    fulljournalname: String,
+    #[serde(default)]
+    source: String,
+    #[serde(default)]
+    pubdate: String,
+    #[serde(default)]
+    articleids: Vec<PubMedArticleId>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PubMedAuthor {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PubMedArticleId {
+    idtype: String,
+    value: String,
+}
+
+/// Resolves identifiers across databases and builds deduplicated export records
+pub struct ExportPipeline {
+    client: Client,
+    idconv_url: String,
+    eutils_url: String,
+    pdf_dir: Option<PathBuf>,
+}
+
+impl ExportPipeline {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            idconv_url: IDCONV_URL.to_string(),
+            eutils_url: EUTILS_BASE_URL.to_string(),
+            pdf_dir: None,
+        }
+    }
+
+    /// Use different NCBI endpoints
+    pub fn with_endpoints(mut self, idconv_url: &str, eutils_url: &str) -> Self {
+        self.idconv_url = idconv_url.to_string();
+        self.eutils_url = eutils_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Link records to PDFs downloaded into this directory
+    pub fn with_pdf_dir(mut self, pdf_dir: &Path) -> Self {
+        self.pdf_dir = Some(pdf_dir.to_path_buf());
+        self
+    }
+
+    /// Resolve the identifiers and return one record per distinct article
+    pub async fn run(&self, inputs: &[String]) -> Result<Vec<ExportRecord>, ExportError> {
+        let mut identifiers = Vec::new();
+        for input in inputs.iter().filter(|input| !input.trim().is_empty()) {
+            match Identifier::parse(input) {
+                Some(identifier) => identifiers.push((input.trim().to_string(), identifier)),
+                None => return Err(ExportError::InvalidIdentifier(input.clone())),
+            }
+        }
+
+        let mut records = self.convert_ids(&identifiers).await?;
+
+        let pmids: Vec<String> = records.iter().filter_map(|record| record.pmid.clone()).collect();
+        let summaries = self.fetch_summaries(&pmids).await?;
+        for record in &mut records {
+            if let Some(summary) = record.pmid.as_ref().and_then(|pmid| summaries.get(pmid)) {
+                record.merge(summary.clone());
+            }
+            if let Some(pdf_dir) = &self.pdf_dir {
+                record.pdf_path = find_pdf(pdf_dir, record);
+            }
+        }
+
+        let records = deduplicate(records);
+        debug!("Reconciled {} identifiers into {} records", identifiers.len(), records.len());
+
+        Ok(records)
+    }
+
+    /// Map every identifier to its PMID, PMCID, and DOI
+    async fn convert_ids(&self, identifiers: &[(String, Identifier)]) -> Result<Vec<ExportRecord>, ExportError> {
+        let mut records = Vec::with_capacity(identifiers.len());
+
+        // The converter requires all identifiers of a request to be of the same type
+        for kind in ["pmid", "pmcid", "doi"] {
+            let batch: Vec<&(String, Identifier)> =
+                identifiers.iter().filter(|(_, identifier)| identifier.kind() == kind).collect();
+
+            for chunk in batch.chunks(BATCH_SIZE) {
+                let ids: Vec<&str> = chunk.iter().map(|(_, identifier)| identifier.value()).collect();
+                let response = self
+                    .client
+                    .get(&self.idconv_url)
+                    .query(&[("ids", ids.join(",").as_str()), ("idtype", kind), ("format", "json")])
+                    .send()
+                    .await?;
+                if !response.status().is_success() {
+                    return Err(ExportError::ApiResponse(format!(
+                        "ID converter returned status code: {}",
+                        response.status()
+                    )));
+                }
+                let converted: IdConvResponse = response.json().await?;
+
+                for (input, identifier) in chunk {
+                    let found = converted.records.iter().find(|record| {
+                        record
+                            .requested_id
+                            .as_deref()
+                            .is_some_and(|id| id.eq_ignore_ascii_case(identifier.value()))
+                    });
+
+                    let mut record = ExportRecord {
+                        input_ids: vec![input.clone()],
+                        ..ExportRecord::default()
+                    };
+                    if let Some(found) = found {
+                        record.pmid = found.pmid.clone();
+                        record.pmcid = found.pmcid.as_deref().and_then(normalize_pmcid);
+                        record.doi = found.doi.as_deref().and_then(normalize_doi);
+                    } else {
+                        warn!("No cross-references found for {}", input);
+                    }
+                    match identifier {
+                        Identifier::Pmid(pmid) => record.pmid = Some(pmid.clone()),
+                        Identifier::Pmcid(pmcid) => record.pmcid = Some(pmcid.clone()),
+                        Identifier::Doi(doi) => record.doi = Some(doi.clone()),
+                    }
+                    records.push(record);
+                }
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Fetch bibliographic metadata for PMIDs
+    async fn fetch_summaries(&self, pmids: &[String]) -> Result<HashMap<String, ExportRecord>, ExportError> {
+        let mut summaries = HashMap::new();
+
+        for chunk in pmids.chunks(BATCH_SIZE) {
+            let url = format!("{}/esummary.fcgi", self.eutils_url);
+            let response = self
+                .client
+                .get(&url)
+                .query(&[("db", "pubmed"), ("retmode", "json"), ("id", chunk.join(",").as_str())])
+                .send()
+                .await?;
+            if !response.status().is_success() {
+                return Err(ExportError::ApiResponse(format!(
+                    "esummary returned status code: {}",
+                    response.status()
+                )));
+            }
+            let summary: ESummaryResponse = response.json().await?;
+
+            for pmid in chunk {
+                let Some(value) = summary.result.get(pmid) else { continue };
+                match serde_json::from_value::<PubMedSummary>(value.clone()) {
+                    Ok(article) => {
+                        summaries.insert(pmid.clone(), summary_record(pmid, article));
+                    }
+                    Err(e) => warn!("Skipping unparseable summary for PMID {}: {}", pmid, e),
+                }
+            }
+        }
+
+        Ok(summaries)
+    }
+}
+
+fn summary_record(pmid: &str, article: PubMedSummary) -> ExportRecord {
+    let article_id = |kind: &str| {
+        article
+            .articleids
+            .iter()
+            .find(|id| id.idtype == kind)
+            .map(|id| id.value.clone())
+    };
+
+    ExportRecord {
+        pmid: Some(pmid.to_string()),
+        pmcid: article_id("pmc").as_deref().and_then(normalize_pmcid),
+        doi: article_id("doi").as_deref().and_then(normalize_doi),
+        title: article.title.trim_end_matches('.').to_string(),
+        authors: article.authors.into_iter().map(|author| author.name).collect(),
+        journal: if article.fulljournalname.is_empty() {
+            article.source
+        } else {
+            article.fulljournalname
+        },
+        year: article.pubdate.get(..4).and_then(|year| year.parse().ok()),
+        ..ExportRecord::default()
+    }
+}
+
+/// Find a downloaded PDF named after one of the record's identifiers
+fn find_pdf(pdf_dir: &Path, record: &ExportRecord) -> Option<PathBuf> {
+    let names = [
+        record.pmid.clone(),
+        record.pmcid.clone(),
+        record.doi.as_ref().map(|doi| doi.replace('/', "_")),
+    ];
+
+    names
+        .into_iter()
+        .flatten()
+        .map(|name| pdf_dir.join(format!("{}.pdf", name)))
+        .find(|path| path.exists())
+}
+
+fn normalize_doi(doi: &str) -> Option<String> {
+    let doi = doi.trim().to_lowercase();
+    doi.starts_with("10.").then_some(doi)
+}
+
+fn normalize_pmcid(pmcid: &str) -> Option<String> {
+    let digits = pmcid.trim().to_uppercase();
+    let digits = digits.strip_prefix("PMC").unwrap_or(&digits);
+    (!digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())).then(|| format!("PMC{}", digits))
+}
+
+const CSV_HEADER: [&str; 12] = [
+    "pmid", "pmcid", "doi", "title", "authors", "journal", "year",
+    "pubmed_url", "pmc_url", "doi_url", "pdf_path", "input_ids",
+];
+
+/// Write records as CSV with a header row
+pub fn write_csv<W: Write>(records: &[ExportRecord], mut writer: W) -> Result<(), ExportError> {
+    writeln!(writer, "{}", CSV_HEADER.join(","))?;
+
+    for record in records {
+        let fields = [
+            record.pmid.clone().unwrap_or_default(),
+            record.pmcid.clone().unwrap_or_default(),
+            record.doi.clone().unwrap_or_default(),
+            record.title.clone(),
+            record.authors.join("; "),
+            record.journal.clone(),
+            record.year.map(|year| year.to_string()).unwrap_or_default(),
+            record.pubmed_url.clone().unwrap_or_default(),
+            record.pmc_url.clone().unwrap_or_default(),
+            record.doi_url.clone().unwrap_or_default(),
+            record.pdf_path.as_ref().map(|path| path.display().to_string()).unwrap_or_default(),
+            record.input_ids.join("; "),
+        ];
+        let row: Vec<String> = fields.iter().map(|field| csv_escape(field)).collect();
+        writeln!(writer, "{}", row.join(","))?;
+    }
+
+    Ok(())
+}
+
+/// Write records as JSON Lines, one record per line
+pub fn write_jsonl<W: Write>(records: &[ExportRecord], mut writer: W) -> Result<(), ExportError> {
+    for record in records {
+        serde_json::to_writer(&mut writer, record)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_identifier() {
+        assert_eq!(Identifier::parse("12345678"), Some(Identifier::Pmid("12345678".to_string())));
+        assert_eq!(Identifier::parse("pmc123"), Some(Identifier::Pmcid("PMC123".to_string())));
+        assert_eq!(
+            Identifier::parse("https://doi.org/10.1000/ABC"),
+            Some(Identifier::Doi("10.1000/abc".to_string()))
+        );
+        assert_eq!(Identifier::parse("not an id"), None);
+    }
+
+    #[test]
+    fn test_deduplicate() {
+        let records = vec![
+            ExportRecord {
+                pmid: Some("1".to_string()),
+                title: "Aspirin and Stroke".to_string(),
+                input_ids: vec!["1".to_string()],
+                ..ExportRecord::default()
+            },
+            ExportRecord {
+                pmcid: Some("PMC9".to_string()),
+                doi: Some("10.1/x".to_string()),
+                input_ids: vec!["PMC9".to_string()],
+                ..ExportRecord::default()
+            },
+            ExportRecord {
+                pmid: Some("1".to_string()),
+                doi: Some("10.1/x".to_string()),
+                input_ids: vec!["10.1/x".to_string()],
+                ..ExportRecord::default()
+            },
+            ExportRecord {
+                pmid: Some("2".to_string()),
+                title: "Something Else".to_string(),
+                ..ExportRecord::default()
+            },
+        ];
+
+        let records = deduplicate(records);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].pmcid.as_deref(), Some("PMC9"));
+        assert_eq!(records[0].input_ids, vec!["1", "PMC9", "10.1/x"]);
+        assert_eq!(records[0].doi_url.as_deref(), Some("https://doi.org/10.1/x"));
+
+        let mut csv = Vec::new();
+        write_csv(&records, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 3);
+        assert!(csv.lines().nth(1).unwrap().ends_with(",1; PMC9; 10.1/x"));
+    }
+}
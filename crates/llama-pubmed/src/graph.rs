@@ -0,0 +1,567 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::time::Duration;
+use thiserror::Error;
+use log::debug;
+
+const EUTILS_BASE_URL: &str = "https://eutils.ncbi.nlm.nih.gov/entrez/eutils";
+const BATCH_SIZE: usize = 100;
+
+/// E-utilities allow three requests per second without an API key
+const REQUEST_INTERVAL: Duration = Duration::from_millis(350);
+
+/// E-utilities allow ten requests per second with an API key
+const API_KEY_REQUEST_INTERVAL: Duration = Duration::from_millis(110);
+
+#[derive(Error, Debug)]
+pub enum GraphError {
+    #[error("Network error: {0}")]
+    Network(#[from] reqwest::Error),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("API response error: {0}")]
+    ApiResponse(String),
+
+    #[error("Unknown link type: {0}")]
+    InvalidLinkKind(String),
+}
+
+/// A kind of NCBI link followed from an article
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LinkKind {
+    /// Articles PubMed computes as similar, with a similarity score
+    Similar,
+    /// Articles cited by the article
+    References,
+    /// Articles citing the article
+    CitedBy,
+}
+
+impl LinkKind {
+    pub const ALL: [LinkKind; 3] = [LinkKind::Similar, LinkKind::References, LinkKind::CitedBy];
+
+    /// Parse a link kind as given on the command line
+    pub fn parse(input: &str) -> Result<Self, GraphError> {
+        match input.trim().to_lowercase().as_str() {
+            "similar" => Ok(LinkKind::Similar),
+            "references" | "refs" => Ok(LinkKind::References),
+            "cited-by" | "citedby" => Ok(LinkKind::CitedBy),
+            _ => Err(GraphError::InvalidLinkKind(input.to_string())),
+        }
+    }
+
+    fn linkname(&self) -> &'static str {
+        match self {
+            LinkKind::Similar => "pubmed_pubmed",
+            LinkKind::References => "pubmed_pubmed_refs",
+            LinkKind::CitedBy => "pubmed_pubmed_citedin",
+        }
+    }
+
+    fn command(&self) -> &'static str {
+        match self {
+            LinkKind::Similar => "neighbor_score",
+            LinkKind::References | LinkKind::CitedBy => "neighbor",
+        }
+    }
+}
+
+/// The relation an edge of the graph stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// `from` and `to` are similar articles
+    Similar,
+    /// `from` cites `to`
+    Cites,
+}
+
+/// An article of the graph
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct GraphNode {
+    pub pmid: String,
+    /// Number of hops from the nearest seed, 0 for seeds
+    pub hop: u32,
+    /// Relevance to the seeds, accumulated over the links that reached the article
+    pub score: f64,
+    pub seed: bool,
+}
+
+/// A citation or similarity link between two articles
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+    pub kind: EdgeKind,
+    /// PubMed similarity score, for similarity edges
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<u64>,
+}
+
+/// A link found from an article of the frontier
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub source: String,
+    pub target: String,
+    pub kind: LinkKind,
+    /// Weight of the link in (0, 1], similarity scores being relative to the source's best match
+    pub weight: f64,
+    pub score: Option<u64>,
+}
+
+impl Link {
+    fn edge(&self) -> GraphEdge {
+        let (from, to, kind) = match self.kind {
+            LinkKind::Similar => (&self.source, &self.target, EdgeKind::Similar),
+            LinkKind::References => (&self.source, &self.target, EdgeKind::Cites),
+            LinkKind::CitedBy => (&self.target, &self.source, EdgeKind::Cites),
+        };
+        GraphEdge {
+            from: from.clone(),
+            to: to.clone(),
+            kind,
+            score: self.score,
+        }
+    }
+}
+
+/// Limits and scoring of a graph expansion
+#[derive(Debug, Clone)]
+pub struct ExpansionLimits {
+    /// Number of hops walked from the seeds
+    pub hops: u32,
+    /// Maximum number of new articles kept per hop, the best scored first
+    pub max_per_hop: usize,
+    /// Maximum number of links followed per article and link kind
+    pub max_per_node: usize,
+    /// Factor applied to scores at every hop past the first
+    pub decay: f64,
+    /// Minimum score of a new article
+    pub min_score: f64,
+}
+
+impl Default for ExpansionLimits {
+    fn default() -> Self {
+        Self {
+            hops: 2,
+            max_per_hop: 50,
+            max_per_node: 20,
+            decay: 0.5,
+            min_score: 0.0,
+        }
+    }
+}
+
+/// A citation and similarity graph grown from a set of seed articles
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CitationGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    #[serde(skip)]
+    index: HashMap<String, usize>,
+    #[serde(skip)]
+    edge_keys: HashSet<(String, String, EdgeKind)>,
+}
+
+impl CitationGraph {
+    /// Start a graph from seed PMIDs
+    pub fn new(seeds: &[String]) -> Self {
+        let mut graph = Self::default();
+        for pmid in seeds {
+            if !graph.index.contains_key(pmid) {
+                graph.index.insert(pmid.clone(), graph.nodes.len());
+                graph.nodes.push(GraphNode {
+                    pmid: pmid.clone(),
+                    hop: 0,
+                    score: 0.0,
+                    seed: true,
+                });
+            }
+        }
+        graph
+    }
+
+    /// Add the links found from the previous hop's articles and return the new frontier
+    ///
+    /// Articles not yet in the graph are scored by the weights of the links
+    /// reaching them, and only the best `max_per_hop` of them scoring at
+    /// least `min_score` are added. Links to articles left out are dropped.
+    pub fn add_hop(&mut self, hop: u32, links: &[Link], limits: &ExpansionLimits) -> Vec<String> {
+        let decay = limits.decay.powi(hop.saturating_sub(1) as i32);
+
+        let mut scores: HashMap<&str, f64> = HashMap::new();
+        let mut order: Vec<&str> = Vec::new();
+        for link in links {
+            if self.index.contains_key(&link.target) {
+                continue;
+            }
+            let score = scores.entry(&link.target).or_insert_with(|| {
+                order.push(&link.target);
+                0.0
+            });
+            *score += link.weight * decay;
+        }
+
+        let mut candidates: Vec<(&str, f64)> = order
+            .into_iter()
+            .map(|pmid| (pmid, scores[pmid]))
+            .filter(|(_, score)| *score >= limits.min_score)
+            .collect();
+        // Stable sort keeps discovery order among equal scores
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+        candidates.truncate(limits.max_per_hop);
+
+        let mut frontier = Vec::with_capacity(candidates.len());
+        for (pmid, score) in candidates {
+            self.index.insert(pmid.to_string(), self.nodes.len());
+            self.nodes.push(GraphNode {
+                pmid: pmid.to_string(),
+                hop,
+                score,
+                seed: false,
+            });
+            frontier.push(pmid.to_string());
+        }
+
+        for link in links {
+            if !self.index.contains_key(&link.target) {
+                continue;
+            }
+            let edge = link.edge();
+            let key = match edge.kind {
+                // Similarity is symmetric, so both directions are the same edge
+                EdgeKind::Similar if edge.to < edge.from => (edge.to.clone(), edge.from.clone(), edge.kind),
+                _ => (edge.from.clone(), edge.to.clone(), edge.kind),
+            };
+            if self.edge_keys.insert(key) {
+                self.edges.push(edge);
+            }
+        }
+
+        frontier
+    }
+
+    /// Get the node of an article
+    pub fn node(&self, pmid: &str) -> Option<&GraphNode> {
+        self.index.get(pmid).map(|&i| &self.nodes[i])
+    }
+
+    /// Get the articles found from the seeds, best scored first
+    pub fn candidates(&self) -> Vec<&GraphNode> {
+        let mut candidates: Vec<&GraphNode> = self.nodes.iter().filter(|node| !node.seed).collect();
+        candidates.sort_by(|a, b| a.hop.cmp(&b.hop).then(b.score.total_cmp(&a.score)));
+        candidates
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ELinkResponse {
+    #[serde(default)]
+    linksets: Vec<ELinkSet>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ELinkSet {
+    #[serde(default)]
+    ids: Vec<String>,
+    #[serde(default)]
+    linksetdbs: Vec<ELinkSetDb>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ELinkSetDb {
+    linkname: String,
+    #[serde(default)]
+    links: Vec<ELinkId>,
+}
+
+/// A linked ID, scored when requested with `cmd=neighbor_score`
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ELinkId {
+    Plain(String),
+    Scored {
+        id: String,
+        #[serde(default)]
+        score: serde_json::Value,
+    },
+}
+
+impl ELinkId {
+    fn id(&self) -> &str {
+        match self {
+            ELinkId::Plain(id) | ELinkId::Scored { id, .. } => id,
+        }
+    }
+
+    fn score(&self) -> Option<u64> {
+        match self {
+            ELinkId::Plain(_) => None,
+            ELinkId::Scored { score, .. } => score.as_u64().or_else(|| score.as_str()?.parse().ok()),
+        }
+    }
+}
+
+/// Extract the links of one kind from an ELink response, at most `max_per_node` per source
+fn parse_links(response: ELinkResponse, kind: LinkKind, max_per_node: usize) -> Vec<Link> {
+    let mut links = Vec::new();
+
+    for linkset in response.linksets {
+        let Some(source) = linkset.ids.first() else { continue };
+        for db in linkset.linksetdbs.iter().filter(|db| db.linkname == kind.linkname()) {
+            // Similar articles include the article itself, with the best score
+            let ids: Vec<&ELinkId> = db.links.iter().filter(|id| id.id() != source).take(max_per_node).collect();
+            let best = ids.iter().filter_map(|id| id.score()).max().unwrap_or(0);
+
+            for id in ids {
+                let score = id.score();
+                let weight = match (kind, score) {
+                    (LinkKind::Similar, Some(score)) if best > 0 => score as f64 / best as f64,
+                    _ => 1.0,
+                };
+                links.push(Link {
+                    source: source.clone(),
+                    target: id.id().to_string(),
+                    kind,
+                    weight,
+                    score,
+                });
+            }
+        }
+    }
+
+    links
+}
+
+/// Walks PubMed's related-articles and citation links out from seed articles
+pub struct GraphExpander {
+    client: Client,
+    eutils_url: String,
+    api_key: Option<String>,
+    kinds: Vec<LinkKind>,
+    limits: ExpansionLimits,
+}
+
+impl GraphExpander {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            eutils_url: EUTILS_BASE_URL.to_string(),
+            api_key: None,
+            kinds: LinkKind::ALL.to_vec(),
+            limits: ExpansionLimits::default(),
+        }
+    }
+
+    /// Use a different E-utilities endpoint
+    pub fn with_endpoint(mut self, eutils_url: &str) -> Self {
+        self.eutils_url = eutils_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Send an NCBI API key with each request, which allows a faster request rate
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    /// Follow only these kinds of links (default all)
+    pub fn with_link_kinds(mut self, kinds: Vec<LinkKind>) -> Self {
+        self.kinds = kinds;
+        self
+    }
+
+    /// Set the hops, per-hop limits, and scoring of the expansion
+    pub fn with_limits(mut self, limits: ExpansionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Expand the seeds into a graph
+    pub async fn expand(&self, seeds: &[String]) -> Result<CitationGraph, GraphError> {
+        let mut graph = CitationGraph::new(seeds);
+        let mut frontier: Vec<String> = graph.nodes.iter().map(|node| node.pmid.clone()).collect();
+
+        for hop in 1..=self.limits.hops {
+            if frontier.is_empty() {
+                break;
+            }
+
+            let mut links = Vec::new();
+            for kind in &self.kinds {
+                links.extend(self.fetch_links(&frontier, *kind).await?);
+            }
+
+            frontier = graph.add_hop(hop, &links, &self.limits);
+            debug!("Hop {}: {} links, {} new articles", hop, links.len(), frontier.len());
+        }
+
+        Ok(graph)
+    }
+
+    /// Fetch the links of one kind from each of the articles
+    async fn fetch_links(&self, pmids: &[String], kind: LinkKind) -> Result<Vec<Link>, GraphError> {
+        let mut links = Vec::new();
+
+        for chunk in pmids.chunks(BATCH_SIZE) {
+            // Repeating `id` gets one linkset per article instead of a merged one
+            let mut query = vec![
+                ("dbfrom", "pubmed"),
+                ("db", "pubmed"),
+                ("retmode", "json"),
+                ("cmd", kind.command()),
+                ("linkname", kind.linkname()),
+            ];
+            query.extend(chunk.iter().map(|pmid| ("id", pmid.as_str())));
+            if let Some(api_key) = &self.api_key {
+                query.push(("api_key", api_key.as_str()));
+            }
+
+            let url = format!("{}/elink.fcgi", self.eutils_url);
+            let response = self.client.get(&url).query(&query).send().await?;
+            if !response.status().is_success() {
+                return Err(GraphError::ApiResponse(format!(
+                    "elink returned status code: {}",
+                    response.status()
+                )));
+            }
+            let elink: ELinkResponse = response.json().await?;
+            links.extend(parse_links(elink, kind, self.limits.max_per_node));
+
+            let interval = if self.api_key.is_some() { API_KEY_REQUEST_INTERVAL } else { REQUEST_INTERVAL };
+            tokio::time::sleep(interval).await;
+        }
+
+        Ok(links)
+    }
+}
+
+/// Write the graph as JSON with its nodes and edges
+pub fn write_json<W: Write>(graph: &CitationGraph, mut writer: W) -> Result<(), GraphError> {
+    serde_json::to_writer_pretty(&mut writer, graph)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Write the graph in Graphviz DOT format
+pub fn write_dot<W: Write>(graph: &CitationGraph, mut writer: W) -> Result<(), GraphError> {
+    writeln!(writer, "digraph pubmed {{")?;
+    for node in &graph.nodes {
+        let shape = if node.seed { "doublecircle" } else { "ellipse" };
+        writeln!(
+            writer,
+            "  \"{}\" [shape={}, hop={}, score={:.3}];",
+            node.pmid, shape, node.hop, node.score
+        )?;
+    }
+    for edge in &graph.edges {
+        match edge.kind {
+            EdgeKind::Cites => writeln!(writer, "  \"{}\" -> \"{}\";", edge.from, edge.to)?,
+            EdgeKind::Similar => writeln!(
+                writer,
+                "  \"{}\" -> \"{}\" [dir=none, style=dashed, score={}];",
+                edge.from,
+                edge.to,
+                edge.score.unwrap_or(0)
+            )?,
+        }
+    }
+    writeln!(writer, "}}")?;
+    Ok(())
+}
+
+/// Write the candidate articles, one per line with the PMID first, best scored first
+///
+/// The file can be passed to `download --pmids-file`, which reads the first column.
+pub fn write_candidates<W: Write>(graph: &CitationGraph, mut writer: W) -> Result<(), GraphError> {
+    for node in graph.candidates() {
+        writeln!(writer, "{}\thop={}\tscore={:.3}", node.pmid, node.hop, node.score)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(source: &str, target: &str, kind: LinkKind, weight: f64) -> Link {
+        Link {
+            source: source.to_string(),
+            target: target.to_string(),
+            kind,
+            weight,
+            score: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_links() {
+        let json = r#"{"linksets":[{"dbfrom":"pubmed","ids":["1"],"linksetdbs":[
+            {"dbto":"pubmed","linkname":"pubmed_pubmed","links":[
+                {"id":"1","score":"900"},{"id":"2","score":"600"},{"id":"3","score":300},{"id":"4","score":"100"}]},
+            {"dbto":"pubmed","linkname":"pubmed_pubmed_refs","links":["9"]}]}]}"#;
+        let response: ELinkResponse = serde_json::from_str(json).unwrap();
+
+        let links = parse_links(response, LinkKind::Similar, 2);
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].target, "2");
+        assert_eq!(links[0].weight, 1.0);
+        assert_eq!(links[1].score, Some(300));
+        assert_eq!(links[1].weight, 0.5);
+
+        let response: ELinkResponse = serde_json::from_str(json).unwrap();
+        let links = parse_links(response, LinkKind::References, 20);
+        assert_eq!(links, vec![link("1", "9", LinkKind::References, 1.0)]);
+    }
+
+    #[test]
+    fn test_add_hop() {
+        let seeds = vec!["1".to_string(), "2".to_string()];
+        let mut graph = CitationGraph::new(&seeds);
+        let limits = ExpansionLimits {
+            max_per_hop: 2,
+            ..ExpansionLimits::default()
+        };
+
+        let links = vec![
+            link("1", "3", LinkKind::Similar, 0.4),
+            link("2", "3", LinkKind::Similar, 0.4),
+            link("1", "4", LinkKind::CitedBy, 1.0),
+            link("1", "5", LinkKind::Similar, 0.2),
+            link("1", "2", LinkKind::References, 1.0),
+        ];
+        let frontier = graph.add_hop(1, &links, &limits);
+
+        // Article 5 falls past the per-hop limit, with its edge
+        assert_eq!(frontier, vec!["4".to_string(), "3".to_string()]);
+        assert!(graph.node("5").is_none());
+        assert_eq!(graph.edges.len(), 4);
+        assert!(graph.edges.contains(&GraphEdge {
+            from: "4".to_string(),
+            to: "1".to_string(),
+            kind: EdgeKind::Cites,
+            score: None,
+        }));
+
+        // Later hops are scored lower and known articles only add edges
+        let links = vec![
+            link("3", "6", LinkKind::Similar, 1.0),
+            link("3", "1", LinkKind::Similar, 1.0),
+            link("1", "3", LinkKind::Similar, 0.4),
+        ];
+        let frontier = graph.add_hop(2, &links, &limits);
+        assert_eq!(frontier, vec!["6".to_string()]);
+        assert_eq!(graph.node("6").unwrap().score, 0.5);
+        assert_eq!(graph.edges.len(), 5);
+
+        let candidates: Vec<&str> = graph.candidates().iter().map(|node| node.pmid.as_str()).collect();
+        assert_eq!(candidates, vec!["4", "3", "6"]);
+    }
+}
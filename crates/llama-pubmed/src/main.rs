@@ -1,6 +1,9 @@
 mod cli;
 mod config_manager;
 mod error_handling;
+mod export;
+mod graph;
+mod mesh;
 mod metadata_manager;
 mod pdf_downloader;
 mod pubmed_api;
@@ -23,6 +26,12 @@ enum Command {
     /// Search PubMed and display results
     Search(SearchArgs),
 
+    /// Reconcile PMIDs, PMCIDs, and DOIs into a deduplicated CSV or JSONL export
+    Export(ExportArgs),
+
+    /// Expand PMIDs through related-articles and citation links into a graph
+    Graph(GraphArgs),
+
     /// Manage the LlamaPubMed configuration
     Config(ConfigArgs),
 }
@@ -72,6 +81,9 @@ struct DownloadArgs {
     /// Format for metadata output (json, yaml, bibtex)
     #[arg(long, value_name = "FORMAT")]
     metadata_format: Option<String>,
+
+    #[command(flatten)]
+    mesh: MeshArgs,
 }
 
 #[derive(Parser, Debug)]
@@ -95,6 +107,94 @@ struct SearchArgs {
     /// Download articles from a specified journal (e.g., "Nature")
     #[arg(long, value_name = "JOURNAL")]
     journal: Option<String>,
+
+    #[command(flatten)]
+    mesh: MeshArgs,
+}
+
+#[derive(Parser, Debug)]
+struct ExportArgs {
+    /// Comma-separated list of PMIDs, PMCIDs, or DOIs (e.g., 1234567,PMC765432,10.1000/xyz)
+    #[arg(short, long, value_name = "IDS")]
+    ids: Option<String>,
+
+    /// File containing PMIDs, PMCIDs, or DOIs, one per line
+    #[arg(short = 'f', long, value_name = "FILE")]
+    ids_file: Option<PathBuf>,
+
+    /// Directory of downloaded PDFs to link records to
+    #[arg(short, long, value_name = "DIR")]
+    pdf_dir: Option<PathBuf>,
+
+    /// Output format (csv, jsonl)
+    #[arg(long, value_name = "FORMAT", default_value = "csv")]
+    format: String,
+
+    /// Output file (defaults to standard output)
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+struct GraphArgs {
+    /// Comma-separated list of seed PMIDs (e.g., 1234567,7654321)
+    #[arg(short, long, value_name = "PMIDS")]
+    pmids: Option<String>,
+
+    /// File containing seed PMIDs, one per line
+    #[arg(short = 'f', long, value_name = "FILE")]
+    pmids_file: Option<PathBuf>,
+
+    /// Number of hops walked from the seeds
+    #[arg(long, value_name = "NUM", default_value_t = 2)]
+    hops: u32,
+
+    /// Maximum number of new articles kept per hop
+    #[arg(long, value_name = "NUM", default_value_t = 50)]
+    max_per_hop: usize,
+
+    /// Maximum number of links followed per article and link type
+    #[arg(long, value_name = "NUM", default_value_t = 20)]
+    max_per_node: usize,
+
+    /// Minimum score of a new article
+    #[arg(long, value_name = "SCORE", default_value_t = 0.0)]
+    min_score: f64,
+
+    /// Comma-separated link types to follow (similar, references, cited-by)
+    #[arg(long, value_name = "TYPES", default_value = "similar,references,cited-by")]
+    links: String,
+
+    /// Output format (json, dot)
+    #[arg(long, value_name = "FORMAT", default_value = "json")]
+    format: String,
+
+    /// Output file for the graph (defaults to standard output)
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Output file for the new candidate PMIDs, best scored first
+    #[arg(short, long, value_name = "FILE")]
+    candidates: Option<PathBuf>,
+
+    /// Load configuration from a file
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+struct MeshArgs {
+    /// Expand search terms with MeSH descriptors and their synonyms
+    #[arg(long)]
+    mesh: bool,
+
+    /// Do not include narrower MeSH descriptors when expanding
+    #[arg(long, requires = "mesh")]
+    no_explode: bool,
+
+    /// Leave a search term unexpanded (can be repeated)
+    #[arg(long, value_name = "TERM", requires = "mesh")]
+    no_expand: Vec<String>,
 }
 
 #[derive(clap::Subcommand, Debug)]
@@ -122,6 +222,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Command::Search(search_args) => {
             cli::handle_search(search_args).await?;
         }
+        Command::Export(export_args) => {
+            cli::handle_export(export_args).await?;
+        }
+        Command::Graph(graph_args) => {
+            cli::handle_graph(graph_args).await?;
+        }
         Command::Config(config_args) => {
             cli::handle_config(config_args)?;
         }
@@ -0,0 +1,409 @@
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use thiserror::Error;
+use log::{debug, warn};
+
+const EUTILS_BASE_URL: &str = "https://eutils.ncbi.nlm.nih.gov/entrez/eutils";
+
+#[derive(Error, Debug)]
+pub enum MeshError {
+    #[error("Network error: {0}")]
+    Network(#[from] reqwest::Error),
+
+    #[error("MeSH API response error: {0}")]
+    ApiResponse(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct ESearchResponse {
+    esearchresult: ESearchResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct ESearchResult {
+    #[serde(default)]
+    idlist: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ESummaryResponse {
+    result: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MeshSummary {
+    #[serde(default)]
+    ds_meshui: String,
+    #[serde(default)]
+    ds_meshterms: Vec<String>,
+}
+
+/// A MeSH descriptor with its entry terms (synonyms)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshDescriptor {
+    pub ui: String,
+    pub name: String,
+    pub entry_terms: Vec<String>,
+}
+
+/// How a single query term was expanded
+#[derive(Debug, Clone)]
+pub struct TermExpansion {
+    pub term: String,
+    pub descriptor: Option<MeshDescriptor>,
+    pub skipped: bool,
+}
+
+/// A query expanded with MeSH descriptors and synonyms
+#[derive(Debug, Clone)]
+pub struct ExpandedQuery {
+    pub original: String,
+    pub expanded: String,
+    pub terms: Vec<TermExpansion>,
+}
+
+impl fmt::Display for ExpandedQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for expansion in &self.terms {
+            match (&expansion.descriptor, expansion.skipped) {
+                (_, true) => writeln!(f, "  {} -> not expanded (opted out)", expansion.term)?,
+                (Some(descriptor), false) => writeln!(
+                    f,
+                    "  {} -> {} [{}] + {} synonyms",
+                    expansion.term,
+                    descriptor.name,
+                    descriptor.ui,
+                    descriptor.entry_terms.len()
+                )?,
+                (None, false) => writeln!(f, "  {} -> no MeSH descriptor found", expansion.term)?,
+            }
+        }
+        write!(f, "Expanded query: {}", self.expanded)
+    }
+}
+
+/// Part of a PubMed query
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// A free-text term that may be expanded
+    Term(String),
+    /// Anything passed through unchanged: operators, parentheses, field-tagged terms
+    Verbatim(String),
+}
+
+/// Expands PubMed queries with MeSH descriptors and entry terms
+///
+/// Every free-text term of the query is looked up in the NCBI MeSH database;
+/// multi-word concepts need quotes, e.g. `"heart attack"`, to be looked up as one.
+/// When a descriptor is found, the term is replaced by the descriptor as a
+/// `[MeSH Terms]` search, which PubMed explodes to narrower descriptors, OR'd
+/// with the original term and the descriptor's entry terms searched in titles
+/// and abstracts. Terms that already carry a field tag are left unchanged.
+pub struct MeshExpander {
+    client: Client,
+    base_url: String,
+    explode: bool,
+    max_synonyms: usize,
+    skip_terms: HashSet<String>,
+}
+
+impl MeshExpander {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            base_url: EUTILS_BASE_URL.to_string(),
+            explode: true,
+            max_synonyms: 10,
+            skip_terms: HashSet::new(),
+        }
+    }
+
+    /// Use a different E-utilities endpoint
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Whether descriptor searches include narrower descriptors (default true)
+    pub fn with_explode(mut self, explode: bool) -> Self {
+        self.explode = explode;
+        self
+    }
+
+    /// Maximum number of entry terms added per descriptor (default 10)
+    pub fn with_max_synonyms(mut self, max_synonyms: usize) -> Self {
+        self.max_synonyms = max_synonyms;
+        self
+    }
+
+    /// Leave a term of the query unexpanded
+    pub fn skip_term(mut self, term: &str) -> Self {
+        self.skip_terms.insert(normalize(term));
+        self
+    }
+
+    /// Expand every free-text term of a query
+    pub async fn expand_query(&self, query: &str) -> Result<ExpandedQuery, MeshError> {
+        let tokens = tokenize(query);
+        let mut terms = Vec::new();
+
+        for token in &tokens {
+            let Token::Term(term) = token else { continue };
+            if terms.iter().any(|expansion: &TermExpansion| expansion.term == *term) {
+                continue;
+            }
+
+            let skipped = self.skip_terms.contains(&normalize(term));
+            let descriptor = if skipped {
+                None
+            } else {
+                self.lookup(term).await?
+            };
+
+            terms.push(TermExpansion {
+                term: term.clone(),
+                descriptor,
+                skipped,
+            });
+        }
+
+        let expanded = render(&tokens, &terms, self.explode, self.max_synonyms);
+        debug!("Expanded query '{}' to '{}'", query, expanded);
+
+        Ok(ExpandedQuery {
+            original: query.to_string(),
+            expanded,
+            terms,
+        })
+    }
+
+    /// Find the MeSH descriptor best matching a term
+    pub async fn lookup(&self, term: &str) -> Result<Option<MeshDescriptor>, MeshError> {
+        let url = format!(
+            "{}/esearch.fcgi?db=mesh&retmode=json&retmax=5&term={}",
+            self.base_url,
+            urlencoding::encode(term)
+        );
+        let response = self.client.get(&url).send().await?;
+        if !response.status().is_success() {
+            return Err(MeshError::ApiResponse(format!(
+                "esearch returned status code: {}",
+                response.status()
+            )));
+        }
+        let search: ESearchResponse = response.json().await?;
+        if search.esearchresult.idlist.is_empty() {
+            return Ok(None);
+        }
+
+        let url = format!(
+            "{}/esummary.fcgi?db=mesh&retmode=json&id={}",
+            self.base_url,
+            search.esearchresult.idlist.join(",")
+        );
+        let response = self.client.get(&url).send().await?;
+        if !response.status().is_success() {
+            return Err(MeshError::ApiResponse(format!(
+                "esummary returned status code: {}",
+                response.status()
+            )));
+        }
+        let summary: ESummaryResponse = response.json().await?;
+
+        // Keep the ranking of esearch and take the first descriptor; supplementary
+        // concepts and qualifiers cannot be searched as [MeSH Terms]
+        for uid in &search.esearchresult.idlist {
+            let Some(value) = summary.result.get(uid) else { continue };
+            let record: MeshSummary = match serde_json::from_value(value.clone()) {
+                Ok(record) => record,
+                Err(e) => {
+                    warn!("Skipping unparseable MeSH record {}: {}", uid, e);
+                    continue;
+                }
+            };
+
+            if !record.ds_meshui.starts_with('D') {
+                continue;
+            }
+            let mut names = record.ds_meshterms.into_iter();
+            if let Some(name) = names.next() {
+                return Ok(Some(MeshDescriptor {
+                    ui: record.ds_meshui,
+                    name,
+                    entry_terms: names.collect(),
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Split a query into free-text terms and verbatim parts
+///
+/// Every unquoted word is a term of its own, as PubMed ANDs adjacent words;
+/// a quoted phrase is a single term.
+fn tokenize(query: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' || c == ')' {
+            tokens.push(Token::Verbatim(c.to_string()));
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let phrase: String = chars.by_ref().take_while(|&c| c != '"').collect();
+            // A quoted phrase followed by a field tag stays verbatim
+            if chars.peek() == Some(&'[') {
+                let tag: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                tokens.push(Token::Verbatim(format!("\"{}\"{}]", phrase, tag)));
+            } else if !phrase.trim().is_empty() {
+                tokens.push(Token::Term(phrase.trim().to_string()));
+            }
+        } else {
+            let mut word = String::new();
+            let mut in_tag = false;
+            while let Some(&c) = chars.peek() {
+                if !in_tag && (c.is_whitespace() || c == '(' || c == ')' || c == '"') {
+                    break;
+                }
+                in_tag = (in_tag || c == '[') && c != ']';
+                word.push(c);
+                chars.next();
+            }
+            if matches!(word.as_str(), "AND" | "OR" | "NOT") || word.contains('[') {
+                tokens.push(Token::Verbatim(word));
+            } else {
+                tokens.push(Token::Term(word));
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Build the expanded query from its tokens
+fn render(tokens: &[Token], terms: &[TermExpansion], explode: bool, max_synonyms: usize) -> String {
+    let mesh_tag = if explode { "[MeSH Terms]" } else { "[MeSH Terms:noexp]" };
+    let mut parts = Vec::with_capacity(tokens.len());
+
+    for token in tokens {
+        let part = match token {
+            Token::Verbatim(text) => text.clone(),
+            Token::Term(term) => {
+                let descriptor = terms
+                    .iter()
+                    .find(|expansion| expansion.term == *term)
+                    .and_then(|expansion| expansion.descriptor.as_ref());
+
+                match descriptor {
+                    Some(descriptor) => {
+                        let mut alternatives = vec![
+                            format!("\"{}\"{}", descriptor.name, mesh_tag),
+                            format!("\"{}\"[tiab]", term),
+                        ];
+                        let mut seen: HashSet<String> = HashSet::from([normalize(term)]);
+                        for synonym in &descriptor.entry_terms {
+                            if alternatives.len() >= max_synonyms + 2 {
+                                break;
+                            }
+                            if seen.insert(normalize(synonym)) {
+                                alternatives.push(format!("\"{}\"[tiab]", synonym));
+                            }
+                        }
+                        format!("({})", alternatives.join(" OR "))
+                    }
+                    // Phrases keep their quotes so they are not split into words
+                    None if term.contains(char::is_whitespace) => format!("\"{}\"", term),
+                    None => term.clone(),
+                }
+            }
+        };
+        parts.push(part);
+    }
+
+    parts.join(" ").replace("( ", "(").replace(" )", ")")
+}
+
+fn normalize(term: &str) -> String {
+    term.trim().trim_matches('"').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("\"heart attack\" AND (aspirin OR \"low dose\") NOT smoking[mh]");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Term("heart attack".to_string()),
+                Token::Verbatim("AND".to_string()),
+                Token::Verbatim("(".to_string()),
+                Token::Term("aspirin".to_string()),
+                Token::Verbatim("OR".to_string()),
+                Token::Term("low dose".to_string()),
+                Token::Verbatim(")".to_string()),
+                Token::Verbatim("NOT".to_string()),
+                Token::Verbatim("smoking[mh]".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_splits_adjacent_words() {
+        let tokens = tokenize("aspirin stroke prevention[tiab] \"low dose\"");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Term("aspirin".to_string()),
+                Token::Term("stroke".to_string()),
+                Token::Verbatim("prevention[tiab]".to_string()),
+                Token::Term("low dose".to_string()),
+            ]
+        );
+
+        // Unexpanded words stay separate, unexpanded phrases stay quoted
+        let terms: Vec<TermExpansion> = ["aspirin", "stroke", "low dose"]
+            .iter()
+            .map(|term| TermExpansion { term: term.to_string(), descriptor: None, skipped: false })
+            .collect();
+        assert_eq!(
+            render(&tokens, &terms, true, 10),
+            "aspirin stroke prevention[tiab] \"low dose\""
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let tokens = tokenize("\"heart attack\" AND (aspirin OR smoking)");
+        let terms = vec![
+            TermExpansion {
+                term: "heart attack".to_string(),
+                descriptor: Some(MeshDescriptor {
+                    ui: "D009203".to_string(),
+                    name: "Myocardial Infarction".to_string(),
+                    entry_terms: vec!["Heart Attack".to_string(), "Cardiovascular Stroke".to_string()],
+                }),
+                skipped: false,
+            },
+            TermExpansion {
+                term: "aspirin".to_string(),
+                descriptor: None,
+                skipped: true,
+            },
+        ];
+
+        assert_eq!(
+            render(&tokens, &terms, false, 10),
+            "(\"Myocardial Infarction\"[MeSH Terms:noexp] OR \"heart attack\"[tiab] OR \"Cardiovascular Stroke\"[tiab]) AND (aspirin OR smoking)"
+        );
+    }
+}